@@ -0,0 +1,444 @@
+//! Audit log of tool calls.
+//!
+//! Every tool call is recorded with its tool name, query (full text or a
+//! SHA-256 hash, depending on configuration), duration, rows read or
+//! affected, outcome and client identity. The most recent entries are kept
+//! in a ring buffer that `get_query_history` reads; entries can additionally
+//! be appended to a JSON Lines file or inserted into an audit table.
+
+use crate::config::AuditConfig;
+use crate::database::QueryExecutor;
+use crate::error::ServerError;
+use crate::security::{parse_qualified_name, safe_identifier};
+use crate::telemetry::RequestContext;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// One recorded tool call.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// When the call started.
+    pub timestamp: DateTime<Utc>,
+
+    /// Request ID of the call.
+    pub request_id: String,
+
+    /// Name of the tool.
+    pub tool: String,
+
+    /// Identity of the MCP client, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+
+    /// Full query text (only recorded when `MSSQL_AUDIT_QUERY_TEXT` is enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+
+    /// SHA-256 hash of the query text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_hash: Option<String>,
+
+    /// Duration of the call in milliseconds.
+    pub duration_ms: u64,
+
+    /// Rows read or affected by statements executed for the call.
+    pub rows: u64,
+
+    /// Whether the call succeeded.
+    pub success: bool,
+
+    /// Error message of a failed call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Criteria for selecting audit entries.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// Only calls of this tool.
+    pub tool: Option<String>,
+
+    /// Only calls from clients whose identity contains this text.
+    pub client: Option<String>,
+
+    /// Only successful (`true`) or failed (`false`) calls.
+    pub success: Option<bool>,
+
+    /// Only calls started at or after this time.
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only calls whose query text or hash contains this text (case-insensitive).
+    pub contains: Option<String>,
+
+    /// Only calls that took at least this long.
+    pub min_duration_ms: Option<u64>,
+}
+
+impl AuditFilter {
+    /// Check whether an entry satisfies every criterion.
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        if let Some(tool) = &self.tool {
+            if !entry.tool.eq_ignore_ascii_case(tool) {
+                return false;
+            }
+        }
+        if let Some(client) = &self.client {
+            let client = client.to_lowercase();
+            if !entry
+                .client
+                .as_ref()
+                .is_some_and(|c| c.to_lowercase().contains(&client))
+            {
+                return false;
+            }
+        }
+        if self.success.is_some_and(|success| success != entry.success) {
+            return false;
+        }
+        if self.since.is_some_and(|since| entry.timestamp < since) {
+            return false;
+        }
+        if self
+            .min_duration_ms
+            .is_some_and(|min| entry.duration_ms < min)
+        {
+            return false;
+        }
+        if let Some(text) = &self.contains {
+            let text = text.to_lowercase();
+            let in_query = entry
+                .query
+                .as_ref()
+                .is_some_and(|q| q.to_lowercase().contains(&text));
+            let in_hash = entry
+                .query_hash
+                .as_ref()
+                .is_some_and(|h| h.starts_with(&text));
+            if !in_query && !in_hash {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Audit table that entries are inserted into.
+struct AuditTable {
+    executor: Arc<QueryExecutor>,
+    /// Escaped `[schema].[table]` name.
+    name: String,
+    /// Whether the table is known to exist.
+    ready: AtomicBool,
+}
+
+/// Recorder of tool calls with an in-memory ring buffer and optional sinks.
+pub struct AuditLog {
+    enabled: bool,
+    log_query_text: bool,
+    max_entries: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+    file: Option<Mutex<File>>,
+    table: Option<Arc<AuditTable>>,
+}
+
+impl AuditLog {
+    /// Create an audit log from configuration.
+    ///
+    /// The audit file is opened (and created if missing) immediately so that
+    /// an unwritable path is reported at startup.
+    pub fn new(config: &AuditConfig, executor: Arc<QueryExecutor>) -> Result<Self, ServerError> {
+        let file = match &config.file {
+            Some(path) if config.enabled => Some(Mutex::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| {
+                        ServerError::config(format!(
+                            "Cannot open audit file {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?,
+            )),
+            _ => None,
+        };
+
+        let table = match &config.table {
+            Some(table) if config.enabled => {
+                let (schema, name) = parse_qualified_name(table)?;
+                let name = format!(
+                    "{}.{}",
+                    safe_identifier(schema.as_deref().unwrap_or("dbo"))?,
+                    safe_identifier(&name)?
+                );
+                Some(Arc::new(AuditTable {
+                    executor,
+                    name,
+                    ready: AtomicBool::new(false),
+                }))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            enabled: config.enabled,
+            log_query_text: config.log_query_text,
+            max_entries: config.max_entries.max(1),
+            entries: Mutex::new(VecDeque::new()),
+            file,
+            table,
+        })
+    }
+
+    /// Create an in-memory audit log without file or table sinks.
+    pub fn in_memory(max_entries: usize, log_query_text: bool) -> Self {
+        Self {
+            enabled: true,
+            log_query_text,
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(VecDeque::new()),
+            file: None,
+            table: None,
+        }
+    }
+
+    /// Whether tool calls are recorded.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record a finished tool call.
+    ///
+    /// `error` is `None` for successful calls. Sink failures are logged and
+    /// never affect the call itself.
+    pub fn record(
+        &self,
+        request: &RequestContext,
+        tool: &str,
+        query: Option<&str>,
+        error: Option<String>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let duration_ms = request.elapsed().as_millis() as u64;
+        let entry = AuditEntry {
+            timestamp: Utc::now() - chrono::Duration::milliseconds(duration_ms as i64),
+            request_id: request.correlation_id.clone(),
+            tool: tool.to_string(),
+            client: request.client_id.clone(),
+            query: query.filter(|_| self.log_query_text).map(str::to_string),
+            query_hash: query.map(hash_query),
+            duration_ms,
+            rows: request.rows(),
+            success: error.is_none(),
+            error,
+        };
+
+        if let Some(file) = &self.file {
+            let written = serde_json::to_string(&entry)
+                .map_err(|e| e.to_string())
+                .and_then(|line| writeln!(file.lock(), "{}", line).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                warn!("Failed to write audit entry: {}", e);
+            }
+        }
+
+        if let Some(table) = &self.table {
+            let table = Arc::clone(table);
+            let entry = entry.clone();
+            tokio::spawn(async move {
+                if let Err(e) = table.insert(&entry).await {
+                    warn!("Failed to insert audit entry into {}: {}", table.name, e);
+                }
+            });
+        }
+
+        let mut entries = self.entries.lock();
+        while entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Get the most recent entries matching a filter, newest first.
+    pub fn history(&self, filter: &AuditFilter, limit: usize) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of entries held in memory.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Check whether no entries are held in memory.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+}
+
+impl AuditTable {
+    /// Insert an entry, creating the table on first use.
+    async fn insert(&self, entry: &AuditEntry) -> Result<(), ServerError> {
+        let mut batch = String::new();
+        if !self.ready.load(Ordering::Relaxed) {
+            batch.push_str(&format!(
+                "IF OBJECT_ID(N'{object}', N'U') IS NULL\n\
+                 CREATE TABLE {table} (\n    \
+                     id BIGINT IDENTITY(1,1) PRIMARY KEY,\n    \
+                     logged_at DATETIME2 NOT NULL,\n    \
+                     request_id NVARCHAR(64) NOT NULL,\n    \
+                     tool NVARCHAR(128) NOT NULL,\n    \
+                     client NVARCHAR(256) NULL,\n    \
+                     query_text NVARCHAR(MAX) NULL,\n    \
+                     query_hash CHAR(64) NULL,\n    \
+                     duration_ms BIGINT NOT NULL,\n    \
+                     row_count BIGINT NOT NULL,\n    \
+                     success BIT NOT NULL,\n    \
+                     error NVARCHAR(MAX) NULL\n\
+                 );\n",
+                object = self.name.replace('\'', "''"),
+                table = self.name
+            ));
+        }
+        batch.push_str(&format!(
+            "INSERT INTO {} (logged_at, request_id, tool, client, query_text, query_hash, \
+             duration_ms, row_count, success, error) \
+             VALUES ('{}', {}, {}, {}, {}, {}, {}, {}, {}, {});",
+            self.name,
+            entry.timestamp.format("%Y-%m-%dT%H:%M:%S%.3f"),
+            sql_string(Some(&entry.request_id)),
+            sql_string(Some(&entry.tool)),
+            sql_string(entry.client.as_deref()),
+            sql_string(entry.query.as_deref()),
+            sql_string(entry.query_hash.as_deref()),
+            entry.duration_ms,
+            entry.rows,
+            u8::from(entry.success),
+            sql_string(entry.error.as_deref())
+        ));
+
+        self.executor.execute_non_query(&batch).await?;
+        self.ready.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Extract the SQL a tool call executes from its arguments.
+///
+/// Uses the `query` argument, `EXEC schema.procedure` for procedure calls, or
+/// `KILL n` with the confirmed login and host for `kill_session` calls, which
+/// carry a numeric `session_id` (async and pinned session IDs are strings).
+pub fn query_text(args: &Value) -> Option<String> {
+    if let Some(query) = args.get("query").and_then(Value::as_str) {
+        return Some(query.to_string());
+    }
+    if let (Some(session_id), Some(login)) = (
+        args.get("session_id").and_then(Value::as_i64),
+        args.get("login").and_then(Value::as_str),
+    ) {
+        let host = args.get("host").and_then(Value::as_str).unwrap_or("");
+        return Some(format!(
+            "KILL {} /* login={} host={} */",
+            session_id,
+            login.replace("*/", ""),
+            host.replace("*/", "")
+        ));
+    }
+    let procedure = args.get("procedure").and_then(Value::as_str)?;
+    let schema = args.get("schema").and_then(Value::as_str).unwrap_or("dbo");
+    Some(format!("EXEC {}.{}", schema, procedure))
+}
+
+/// Hex-encoded SHA-256 hash of query text.
+pub fn hash_query(query: &str) -> String {
+    format!("{:x}", Sha256::digest(query.as_bytes()))
+}
+
+/// Render an optional string as an N'...' literal or NULL.
+fn sql_string(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("N'{}'", value.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ring_buffer_and_filters() {
+        let log = AuditLog::in_memory(2, false);
+        let request = RequestContext::new().with_client("client/1.0");
+
+        log.record(&request, "execute_query", Some("SELECT 1"), None);
+        log.record(&request, "execute_query", Some("SELECT 2"), None);
+        log.record(&request, "list_tables", None, Some("boom".to_string()));
+        assert_eq!(log.len(), 2);
+
+        let all = log.history(&AuditFilter::default(), 10);
+        assert_eq!(all[0].tool, "list_tables");
+        assert!(!all[0].success);
+        assert_eq!(all[1].query, None);
+        assert_eq!(
+            all[1].query_hash.as_deref(),
+            Some(hash_query("SELECT 2").as_str())
+        );
+
+        let filter = AuditFilter {
+            success: Some(true),
+            client: Some("CLIENT".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.history(&filter, 10).len(), 1);
+
+        let filter = AuditFilter {
+            tool: Some("get_query_history".to_string()),
+            ..Default::default()
+        };
+        assert!(log.history(&filter, 10).is_empty());
+    }
+
+    #[test]
+    fn test_query_text() {
+        assert_eq!(
+            query_text(&json!({"query": "SELECT 1"})).as_deref(),
+            Some("SELECT 1")
+        );
+        assert_eq!(
+            query_text(&json!({"schema": "sales", "procedure": "GetOrders"})).as_deref(),
+            Some("EXEC sales.GetOrders")
+        );
+        assert_eq!(
+            query_text(&json!({"session_id": 73, "login": "app", "host": "web01"})).as_deref(),
+            Some("KILL 73 /* login=app host=web01 */")
+        );
+        assert_eq!(query_text(&json!({"session_id": 73})), None);
+        assert_eq!(
+            query_text(&json!({"session_id": "a1b2", "login": "app"})),
+            None
+        );
+        assert_eq!(query_text(&json!({"table": "Orders"})), None);
+        assert_eq!(hash_query("SELECT 1").len(), 64);
+    }
+}
@@ -2,14 +2,58 @@
 //!
 //! Provides in-memory caching for query results to reduce database load
 //! and improve response times for repeated queries.
-
-use crate::database::QueryResult;
+//!
+//! Results are keyed on the query, row limit, database and security mode.
+//! Queries touching temp tables or non-deterministic functions are never
+//! cached, and statements that modify a table invalidate cached results
+//! referencing it (see [`modified_tables`]).
+
+use crate::config::QueryConfig;
+use crate::database::{QueryResult, ResultRow};
+use crate::security::{is_read_only, ValidationMode};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// An object name with up to three qualifying parts, bracketed or not.
+const OBJECT_NAME: &str = r"((?:\[[^\]]+\]|[\w@#$]+)(?:\s*\.\s*(?:\[[^\]]+\]|[\w@#$]+)){0,3})";
+
+/// Tables read by a query.
+static READ_TABLES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"(?i)\b(?:FROM|JOIN|APPLY)\s+{}", OBJECT_NAME))
+        .unwrap_or_else(|e| panic!("Internal error: invalid read table pattern: {}", e))
+});
+
+/// Tables modified by a statement.
+static WRITTEN_TABLES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        concat!(
+            r"(?i)\b(?:INSERT\s+(?:INTO\s+)?|UPDATE\s+|DELETE\s+(?:FROM\s+)?|MERGE\s+(?:INTO\s+)?",
+            r"|TRUNCATE\s+TABLE\s+|(?:ALTER|DROP)\s+TABLE\s+(?:IF\s+EXISTS\s+)?){}",
+        ),
+        OBJECT_NAME
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid written table pattern: {}", e))
+});
+
+/// Temp tables, which belong to one connection, and functions whose results
+/// change between calls.
+static UNCACHEABLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(?i)#|@@(?:SPID|IDENTITY)|\b(?:GETDATE|GETUTCDATE|SYSDATETIME|SYSUTCDATETIME",
+        r"|SYSDATETIMEOFFSET|CURRENT_TIMESTAMP|NEWID|NEWSEQUENTIALID|RAND|CRYPT_GEN_RANDOM",
+        r"|SCOPE_IDENTITY)\b",
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid uncacheable pattern: {}", e))
+});
+
 /// Cache entry containing a query result and metadata.
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
@@ -27,6 +71,9 @@ pub struct CacheEntry {
 
     /// Size estimate in bytes.
     pub size_bytes: usize,
+
+    /// Tables the query reads (lowercase, unqualified).
+    pub tables: Vec<String>,
 }
 
 impl CacheEntry {
@@ -39,6 +86,7 @@ impl CacheEntry {
             ttl,
             hit_count: 0,
             size_bytes,
+            tables: Vec::new(),
         }
     }
 
@@ -69,6 +117,9 @@ pub struct CacheKey {
 
     /// Current database context (if any).
     database: Option<String>,
+
+    /// Security mode the query was validated under.
+    mode: ValidationMode,
 }
 
 impl Hash for CacheKey {
@@ -76,18 +127,66 @@ impl Hash for CacheKey {
         self.query.hash(state);
         self.max_rows.hash(state);
         self.database.hash(state);
+        self.mode.hash(state);
     }
 }
 
 impl CacheKey {
     /// Create a new cache key.
-    pub fn new(query: &str, max_rows: usize, database: Option<String>) -> Self {
+    pub fn new(
+        query: &str,
+        max_rows: usize,
+        database: Option<String>,
+        mode: ValidationMode,
+    ) -> Self {
         Self {
             query: normalize_query(query),
             max_rows,
             database,
+            mode,
         }
     }
+
+    /// Stable identifier of the key that does not reveal the query text.
+    pub fn id(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Description of a cached entry for inspection.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntryInfo {
+    /// Hashed cache key (see [`CacheKey::id`]).
+    pub id: String,
+
+    /// Database the query ran in, if one was selected.
+    pub database: Option<String>,
+
+    /// Security mode the query was validated under.
+    pub mode: ValidationMode,
+
+    /// Row limit the query ran with.
+    pub max_rows: usize,
+
+    /// Tables the query reads.
+    pub tables: Vec<String>,
+
+    /// Rows in the cached result.
+    pub row_count: usize,
+
+    /// Estimated size in bytes.
+    pub size_bytes: usize,
+
+    /// Number of times the entry was served.
+    pub hit_count: u64,
+
+    /// Seconds since the entry was cached.
+    pub age_secs: u64,
+
+    /// Seconds until the entry expires.
+    pub ttl_remaining_secs: u64,
 }
 
 /// Cache statistics.
@@ -127,7 +226,7 @@ pub struct QueryCache {
     entries: RwLock<HashMap<CacheKey, CacheEntry>>,
 
     /// Default TTL for entries.
-    default_ttl: Duration,
+    default_ttl: parking_lot::RwLock<Duration>,
 
     /// Maximum cache size in bytes.
     max_size_bytes: usize,
@@ -136,7 +235,7 @@ pub struct QueryCache {
     max_entries: usize,
 
     /// Whether caching is enabled.
-    enabled: bool,
+    enabled: AtomicBool,
 
     /// Cache statistics.
     stats: RwLock<CacheStats>,
@@ -152,22 +251,33 @@ impl QueryCache {
     ) -> Self {
         Self {
             entries: RwLock::new(HashMap::new()),
-            default_ttl,
+            default_ttl: parking_lot::RwLock::new(default_ttl),
             max_size_bytes: max_size_mb * 1024 * 1024,
             max_entries,
-            enabled,
+            enabled: AtomicBool::new(enabled),
             stats: RwLock::new(CacheStats::default()),
         }
     }
 
     /// Check if caching is enabled.
     pub fn is_enabled(&self) -> bool {
-        self.enabled
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Apply the caching settings of a (reloaded) configuration.
+    ///
+    /// Size limits keep their startup values. Disabling the cache drops all entries.
+    pub async fn configure(&self, config: &QueryConfig) {
+        *self.default_ttl.write() = config.cache_ttl;
+        let was_enabled = self.enabled.swap(config.enable_caching, Ordering::Relaxed);
+        if was_enabled && !config.enable_caching {
+            self.clear().await;
+        }
     }
 
     /// Get a cached result.
     pub async fn get(&self, key: &CacheKey) -> Option<QueryResult> {
-        if !self.enabled {
+        if !self.is_enabled() {
             return None;
         }
 
@@ -198,16 +308,19 @@ impl QueryCache {
 
     /// Insert a result into the cache.
     pub async fn insert(&self, key: CacheKey, result: QueryResult) {
-        self.insert_with_ttl(key, result, self.default_ttl).await;
+        let ttl = *self.default_ttl.read();
+        self.insert_with_ttl(key, result, ttl).await;
     }
 
     /// Insert a result with a specific TTL.
     pub async fn insert_with_ttl(&self, key: CacheKey, result: QueryResult, ttl: Duration) {
-        if !self.enabled {
+        if !self.is_enabled() {
             return;
         }
 
-        let entry = CacheEntry::new(result, ttl);
+        let tables = referenced_tables(&key.query);
+        let mut entry = CacheEntry::new(result, ttl);
+        entry.tables = tables;
         let entry_size = entry.size_bytes;
 
         let mut entries = self.entries.write().await;
@@ -298,14 +411,86 @@ impl QueryCache {
         stats.total_size_bytes = 0;
     }
 
-    /// Invalidate entries matching a pattern.
-    pub async fn invalidate(&self, pattern: &str) {
+    /// Invalidate entries whose query contains `pattern` (case-insensitive) or
+    /// that read a table named `pattern`.
+    ///
+    /// Returns the number of entries removed.
+    pub async fn invalidate(&self, pattern: &str) -> usize {
+        let query_pattern = normalize_query(pattern);
+        let table = table_name(pattern);
+        self.remove_where(|key, entry| {
+            key.query.contains(&query_pattern) || entry.tables.contains(&table)
+        })
+        .await
+    }
+
+    /// Invalidate entries that read any of the given tables.
+    ///
+    /// Table names may be qualified and bracketed. Returns the number of
+    /// entries removed.
+    pub async fn invalidate_tables(&self, tables: &[String]) -> usize {
+        if tables.is_empty() {
+            return 0;
+        }
+        let tables: Vec<String> = tables.iter().map(|t| table_name(t)).collect();
+        self.remove_where(|_, entry| entry.tables.iter().any(|t| tables.contains(t)))
+            .await
+    }
+
+    /// Invalidate entries reading tables that a statement modifies.
+    pub async fn invalidate_modified(&self, query: &str) -> usize {
+        self.invalidate_tables(&modified_tables(query)).await
+    }
+
+    /// Describe the live entries, most frequently hit first.
+    pub async fn entries(&self) -> Vec<CacheEntryInfo> {
+        let entries = self.entries.read().await;
+        let mut infos: Vec<CacheEntryInfo> = entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired())
+            .map(|(key, entry)| CacheEntryInfo {
+                id: key.id(),
+                database: key.database.clone(),
+                mode: key.mode,
+                max_rows: key.max_rows,
+                tables: entry.tables.clone(),
+                row_count: entry.result.rows.len(),
+                size_bytes: entry.size_bytes,
+                hit_count: entry.hit_count,
+                age_secs: entry.age().as_secs(),
+                ttl_remaining_secs: entry.ttl.saturating_sub(entry.age()).as_secs(),
+            })
+            .collect();
+        infos.sort_by(|a, b| {
+            b.hit_count
+                .cmp(&a.hit_count)
+                .then(b.size_bytes.cmp(&a.size_bytes))
+        });
+        infos
+    }
+
+    /// Evict the entry with the given [`CacheKey::id`].
+    ///
+    /// Returns whether an entry was removed.
+    pub async fn evict(&self, id: &str) -> bool {
+        let removed = self.remove_where(|key, _| key.id() == id).await;
+        if removed > 0 {
+            self.stats.write().await.evictions += removed as u64;
+        }
+        removed > 0
+    }
+
+    /// Remove entries matching a predicate and return how many were removed.
+    async fn remove_where(&self, matches: impl Fn(&CacheKey, &CacheEntry) -> bool) -> usize {
         let mut entries = self.entries.write().await;
-        entries.retain(|key, _| !key.query.contains(pattern));
+        let before = entries.len();
+        entries.retain(|key, entry| !matches(key, entry));
+        let removed = before - entries.len();
 
         let mut stats = self.stats.write().await;
         stats.entry_count = entries.len();
         stats.total_size_bytes = entries.values().map(|e| e.size_bytes).sum();
+        removed
     }
 
     /// Get cache statistics.
@@ -363,8 +548,52 @@ fn normalize_query(query: &str) -> String {
         .to_uppercase()
 }
 
+/// Check whether a query's result may be cached.
+///
+/// Only read-only queries qualify, and not those touching temp tables (which
+/// belong to a single connection) or non-deterministic functions.
+pub fn is_cacheable(query: &str) -> bool {
+    is_read_only(query) && !UNCACHEABLE.is_match(query)
+}
+
+/// Tables a query reads from (lowercase, unqualified).
+pub fn referenced_tables(query: &str) -> Vec<String> {
+    extract_tables(&READ_TABLES, query)
+}
+
+/// Tables a statement inserts into, updates, deletes from, truncates,
+/// alters or drops (lowercase, unqualified).
+pub fn modified_tables(query: &str) -> Vec<String> {
+    extract_tables(&WRITTEN_TABLES, query)
+}
+
+fn extract_tables(pattern: &Regex, query: &str) -> Vec<String> {
+    let mut tables: Vec<String> = pattern
+        .captures_iter(query)
+        .map(|caps| table_name(&caps[1]))
+        .filter(|t| !t.is_empty())
+        .collect();
+    tables.sort();
+    tables.dedup();
+    tables
+}
+
+/// The unqualified, unbracketed, lowercase name of a table.
+///
+/// Only the last part is kept, so `[dbo].[Orders]` and `sales.orders` match;
+/// invalidating too much is harmless.
+fn table_name(name: &str) -> String {
+    name.rsplit('.')
+        .next()
+        .unwrap_or(name)
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase()
+}
+
 /// Estimate the size of a query result in bytes.
-fn estimate_result_size(result: &QueryResult) -> usize {
+pub(crate) fn estimate_result_size(result: &QueryResult) -> usize {
     let mut size = 0;
 
     // Column metadata
@@ -375,12 +604,7 @@ fn estimate_result_size(result: &QueryResult) -> usize {
     }
 
     // Row data (rough estimate)
-    for row in &result.rows {
-        for (key, value) in &row.columns {
-            size += key.len();
-            size += estimate_value_size(value);
-        }
-    }
+    size += result.rows.iter().map(estimate_row_size).sum::<usize>();
 
     // Base overhead
     size += 64;
@@ -388,6 +612,14 @@ fn estimate_result_size(result: &QueryResult) -> usize {
     size
 }
 
+/// Estimate the size of a result row in bytes.
+pub(crate) fn estimate_row_size(row: &ResultRow) -> usize {
+    row.columns
+        .iter()
+        .map(|(key, value)| key.len() + estimate_value_size(value))
+        .sum()
+}
+
 /// Estimate the size of a SQL value in bytes.
 fn estimate_value_size(value: &crate::database::types::SqlValue) -> usize {
     use crate::database::types::SqlValue;
@@ -403,6 +635,7 @@ fn estimate_value_size(value: &crate::database::types::SqlValue) -> usize {
         SqlValue::F64(_) => 8,
         SqlValue::String(s) => s.len(),
         SqlValue::Bytes(b) => b.len(),
+        SqlValue::Udt(v) => v.bytes().len(),
         SqlValue::Decimal(d) => d.to_string().len(),
         SqlValue::Uuid(_) => 16,
         SqlValue::DateTime(_) => 32,
@@ -430,19 +663,100 @@ mod tests {
 
     #[test]
     fn test_cache_key() {
-        let key1 = CacheKey::new("SELECT * FROM Users", 100, None);
-        let key2 = CacheKey::new("SELECT  *  FROM  Users", 100, None);
+        let key1 = CacheKey::new("SELECT * FROM Users", 100, None, ValidationMode::ReadOnly);
+        let key2 = CacheKey::new(
+            "SELECT  *  FROM  Users",
+            100,
+            None,
+            ValidationMode::ReadOnly,
+        );
         assert_eq!(key1, key2);
 
-        let key3 = CacheKey::new("SELECT * FROM Users", 200, None);
+        let key3 = CacheKey::new("SELECT * FROM Users", 200, None, ValidationMode::ReadOnly);
         assert_ne!(key1, key3);
+
+        let key4 = CacheKey::new(
+            "SELECT * FROM Users",
+            100,
+            Some("Sales".into()),
+            ValidationMode::ReadOnly,
+        );
+        assert_ne!(key1, key4);
+
+        let key5 = CacheKey::new("SELECT * FROM Users", 100, None, ValidationMode::Standard);
+        assert_ne!(key1, key5);
+    }
+
+    #[test]
+    fn test_cacheable_and_tables() {
+        assert!(is_cacheable("SELECT * FROM Orders"));
+        assert!(!is_cacheable("SELECT * FROM #staging"));
+        assert!(!is_cacheable("SELECT NEWID(), name FROM Users"));
+        assert!(!is_cacheable("UPDATE Orders SET total = 0"));
+
+        assert_eq!(
+            referenced_tables("SELECT * FROM [dbo].[Orders] o JOIN sales.Customers c ON 1 = 1"),
+            vec!["customers", "orders"]
+        );
+        assert_eq!(
+            modified_tables("INSERT INTO dbo.Orders VALUES (1); DELETE FROM [Items] WHERE id = 2"),
+            vec!["items", "orders"]
+        );
+        assert_eq!(modified_tables("TRUNCATE TABLE Logs"), vec!["logs"]);
+        assert!(modified_tables("SELECT * FROM Orders").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidation() {
+        let cache = new_shared_cache(Duration::from_secs(60), 10, 100, true);
+        let orders = CacheKey::new(
+            "SELECT * FROM dbo.Orders",
+            100,
+            None,
+            ValidationMode::ReadOnly,
+        );
+        let users = CacheKey::new("SELECT * FROM Users", 100, None, ValidationMode::ReadOnly);
+        cache.insert(orders.clone(), QueryResult::empty()).await;
+        cache.insert(users.clone(), QueryResult::empty()).await;
+
+        assert_eq!(
+            cache
+                .invalidate_modified("UPDATE [dbo].[Orders] SET x = 1")
+                .await,
+            1
+        );
+        assert!(cache.get(&orders).await.is_none());
+        assert!(cache.get(&users).await.is_some());
+
+        assert_eq!(cache.invalidate("from users").await, 1);
+        assert_eq!(cache.stats().await.entry_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_entries_and_evict() {
+        let cache = new_shared_cache(Duration::from_secs(60), 10, 100, true);
+        let key = CacheKey::new("SELECT * FROM Orders", 100, None, ValidationMode::ReadOnly);
+        cache.insert(key.clone(), QueryResult::empty()).await;
+        cache.get(&key).await;
+
+        let entries = cache.entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, key.id());
+        assert_eq!(entries[0].hit_count, 1);
+        assert_eq!(entries[0].tables, vec!["orders"]);
+        assert!(entries[0].ttl_remaining_secs <= 60);
+
+        assert!(!cache.evict("0000000000000000").await);
+        assert!(cache.evict(&key.id()).await);
+        assert!(cache.entries().await.is_empty());
+        assert_eq!(cache.stats().await.evictions, 1);
     }
 
     #[tokio::test]
     async fn test_cache_operations() {
         let cache = new_shared_cache(Duration::from_secs(60), 10, 100, true);
 
-        let key = CacheKey::new("SELECT 1", 100, None);
+        let key = CacheKey::new("SELECT 1", 100, None, ValidationMode::ReadOnly);
         let result = QueryResult::empty();
 
         // Insert
@@ -463,7 +777,7 @@ mod tests {
     async fn test_cache_expiration() {
         let cache = new_shared_cache(Duration::from_millis(10), 10, 100, true);
 
-        let key = CacheKey::new("SELECT 1", 100, None);
+        let key = CacheKey::new("SELECT 1", 100, None, ValidationMode::ReadOnly);
         let result = QueryResult::empty();
 
         cache.insert(key.clone(), result).await;
@@ -482,7 +796,7 @@ mod tests {
     async fn test_cache_disabled() {
         let cache = new_shared_cache(Duration::from_secs(60), 10, 100, false);
 
-        let key = CacheKey::new("SELECT 1", 100, None);
+        let key = CacheKey::new("SELECT 1", 100, None, ValidationMode::ReadOnly);
         let result = QueryResult::empty();
 
         cache.insert(key.clone(), result).await;
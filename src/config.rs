@@ -1,18 +1,46 @@
 //! Configuration management for the MSSQL MCP Server.
 //!
 //! Configuration is loaded from environment variables following the 12-factor app pattern.
+//! Settings may also come from a TOML or YAML file named by `MSSQL_MCP_CONFIG`, with
+//! environment variables taking precedence.
 
 use crate::constants::{
-    DEFAULT_CACHE_MAX_ENTRIES, DEFAULT_CACHE_MAX_SIZE_MB, DEFAULT_CACHE_TTL,
-    DEFAULT_CACHE_TTL_SECS, DEFAULT_CLEANUP_INTERVAL, DEFAULT_CONNECTION_TIMEOUT,
-    DEFAULT_CONNECTION_TIMEOUT_SECS, DEFAULT_MAX_CONNECTIONS, DEFAULT_MAX_RESULT_ROWS,
-    DEFAULT_MIN_CONNECTIONS, DEFAULT_QUERY_TIMEOUT, DEFAULT_QUERY_TIMEOUT_SECS,
+    DEFAULT_AUDIT_MAX_ENTRIES, DEFAULT_BINARY_PREVIEW_BYTES, DEFAULT_CACHE_MAX_ENTRIES,
+    DEFAULT_CACHE_MAX_SIZE_MB, DEFAULT_CACHE_TTL, DEFAULT_CACHE_TTL_SECS, DEFAULT_CLEANUP_INTERVAL,
+    DEFAULT_CLEANUP_INTERVAL_SECS, DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_SECS,
+    DEFAULT_CONFIRM_BULK_ROWS, DEFAULT_CONNECTION_TIMEOUT, DEFAULT_CONNECTION_TIMEOUT_SECS,
+    DEFAULT_MAX_CONNECTIONS, DEFAULT_MAX_FINISHED_SESSIONS, DEFAULT_MAX_RESULT_ROWS,
+    DEFAULT_MIN_CONNECTIONS, DEFAULT_PINNED_SESSION_IDLE_TIMEOUT_SECS,
+    DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS, DEFAULT_POOL_MAX_LIFETIME_SECS,
+    DEFAULT_QUERY_MEMORY_MB, DEFAULT_QUERY_TIMEOUT, DEFAULT_QUERY_TIMEOUT_SECS,
+    DEFAULT_RESULT_MEMORY_MB, DEFAULT_RESULT_REF_THRESHOLD_KB, DEFAULT_RESULT_RETENTION_SECS,
+    DEFAULT_SCHEMA_POLL_INTERVAL_SECS, DEFAULT_SECRETS_REFRESH_SECS, DEFAULT_SESSION_MEMORY_MB,
+    DEFAULT_SESSION_STORE_MAX_MB, DEFAULT_SLOW_QUERY_LOG_SIZE, MIN_WATCH_POLL_INTERVAL_SECS,
 };
+use crate::database::blob::{BinaryFormat, BinaryOptions};
 use crate::error::ServerError;
+use crate::security::procedures;
 use crate::security::ValidationMode;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+mod connection_string;
+mod secrets;
+
+pub use connection_string::parse_connection_string;
+pub use secrets::{
+    secrets_provider, AwsSecretsManager, AzureKeyVault, EnvFileProvider, HashiCorpVault,
+    SecretsProvider,
+};
+
+/// Environment variable naming the optional configuration file.
+pub const CONFIG_FILE_ENV: &str = "MSSQL_MCP_CONFIG";
+
+/// Environment variable holding an ADO.NET, ODBC or JDBC connection string.
+pub const CONNECTION_STRING_ENV: &str = "MSSQL_CONNECTION_STRING";
+
 /// Server configuration loaded from environment variables.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -27,6 +55,9 @@ pub struct Config {
 
     /// Session management configuration
     pub session: SessionConfig,
+
+    /// Audit log configuration
+    pub audit: AuditConfig,
 }
 
 /// Database connection configuration.
@@ -42,6 +73,11 @@ pub struct DatabaseConfig {
     /// When specified, connects to host\instance
     pub instance: Option<String>,
 
+    /// Look up the instance's TCP port through SQL Browser (UDP 1434) before connecting.
+    /// Set when a named instance is configured without an explicit port.
+    #[serde(default)]
+    pub resolve_instance_port: bool,
+
     /// Database name (optional, enables database mode vs server mode)
     pub database: Option<String>,
 
@@ -73,6 +109,29 @@ pub struct DatabaseConfig {
     /// TDS protocol version to request.
     /// The server may negotiate a lower version if it doesn't support the requested version.
     pub tds_version: TdsVersionConfig,
+
+    /// Workload declared when connecting to an Availability Group listener.
+    /// Read-only connections are routed to a readable secondary.
+    #[serde(default)]
+    pub application_intent: ApplicationIntent,
+
+    /// Try every address of the host in parallel and use the first that answers,
+    /// for listeners spanning several subnets.
+    #[serde(default)]
+    pub multi_subnet_failover: bool,
+
+    /// Servers tried in order when the host is unreachable, as `host[:port]`.
+    #[serde(default)]
+    pub failover_partners: Vec<String>,
+
+    /// Open a second pool with read-only intent for read-only tools and metadata.
+    #[serde(default)]
+    pub read_pool: bool,
+
+    /// Server the read-only pool connects to, as `host[:port]`, instead of
+    /// the host's read-only routing target.
+    #[serde(default)]
+    pub read_pool_host: Option<String>,
 }
 
 /// Retry policy configuration for transient error handling.
@@ -123,6 +182,7 @@ pub enum TdsVersionConfig {
 
 impl TdsVersionConfig {
     /// Parse TDS version from string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "7.3" | "7.3a" => Some(Self::V7_3A),
@@ -155,8 +215,40 @@ impl std::fmt::Display for TdsVersionConfig {
     }
 }
 
+/// Application workload type declared for Availability Group routing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplicationIntent {
+    /// Connect to the primary replica (default)
+    #[default]
+    ReadWrite,
+
+    /// Route to a readable secondary replica when one is available
+    ReadOnly,
+}
+
+impl ApplicationIntent {
+    /// Parse an application intent from string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace(['_', '-'], "").as_str() {
+            "readwrite" => Some(Self::ReadWrite),
+            "readonly" => Some(Self::ReadOnly),
+            _ => None,
+        }
+    }
+
+    /// Get the intent name as used in connection strings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadWrite => "ReadWrite",
+            Self::ReadOnly => "ReadOnly",
+        }
+    }
+}
+
 /// Authentication configuration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AuthConfig {
     /// SQL Server authentication (username/password)
     SqlServer { username: String, password: String },
@@ -190,6 +282,16 @@ pub struct PoolConfig {
 
     /// Idle connection timeout
     pub idle_timeout: Duration,
+
+    /// Connections older than this are closed and replaced
+    pub max_lifetime: Duration,
+
+    /// Interval between idle connection probes (zero disables probing)
+    pub health_check_interval: Duration,
+
+    /// Interval between checks for rotated credentials (zero disables checking)
+    #[serde(default)]
+    pub credential_check_interval: Duration,
 }
 
 /// Granular timeout configuration for different connection phases.
@@ -231,6 +333,109 @@ pub struct SecurityConfig {
 
     /// Maximum result rows per query
     pub max_result_rows: usize,
+
+    /// Directories that `export_data` may write files to (empty disables file export)
+    pub export_directories: Vec<PathBuf>,
+
+    /// Directories that `import_data` may read files from (empty disables file import)
+    pub import_directories: Vec<PathBuf>,
+
+    /// Per-client rate limits and quotas
+    pub rate_limit: RateLimitConfig,
+
+    /// Limits on concurrent calls per tool and per database
+    pub concurrency: ConcurrencyConfig,
+
+    /// Masking of sensitive values in results
+    pub masking: MaskingConfig,
+
+    /// Require a confirmation step for unfiltered UPDATE/DELETE, TRUNCATE and DROP
+    pub require_confirmation: bool,
+
+    /// Ask for confirmation through MCP elicitation when the client supports it
+    pub elicit_confirmation: bool,
+
+    /// Rows above which `bulk_insert` and `generate_test_data` need confirmation (0 never asks)
+    pub confirm_bulk_rows: usize,
+
+    /// Per-query resource guardrails
+    pub governor: GovernorConfig,
+
+    /// TOML or YAML file of named queries for `execute_registered`
+    pub query_registry: Option<PathBuf>,
+
+    /// Refuse SQL text; only registered queries can be executed
+    pub registered_queries_only: bool,
+
+    /// Allow four-part names and OPENQUERY outside unrestricted mode
+    pub allow_linked_servers: bool,
+
+    /// Procedures `execute_procedure` may run (`schema.name`, `schema.*` or `name`; empty allows all)
+    pub allowed_procedures: Vec<String>,
+
+    /// Disable the tools that run ad-hoc SQL; only allowed procedures can be executed
+    pub procedures_only: bool,
+
+    /// Tools to advertise and accept calls for (empty enables all)
+    pub enabled_tools: Vec<String>,
+
+    /// Tools to hide and refuse calls for, applied after `enabled_tools`
+    pub disabled_tools: Vec<String>,
+}
+
+/// Per-query resource guardrails applied before execution.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GovernorConfig {
+    /// Reject statements whose estimated plan cost exceeds this
+    pub max_estimated_cost: Option<f64>,
+
+    /// Maximum degree of parallelism, added as an `OPTION (MAXDOP n)` hint
+    pub max_dop: Option<u32>,
+
+    /// Inject `TOP` into SELECTs without a row limit (constrained mode)
+    pub enforce_row_limit: bool,
+
+    /// Reject SELECTs whose estimated result exceeds this many megabytes
+    pub max_result_estimate_mb: Option<f64>,
+
+    /// Only warn about SELECTs over `max_result_estimate_mb` instead of rejecting them
+    pub result_estimate_warn_only: bool,
+}
+
+/// Per-client rate limits; `None` means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Maximum tool calls per client per minute
+    pub queries_per_minute: Option<u32>,
+
+    /// Maximum concurrent async sessions, pinned sessions and transactions per client
+    pub concurrent_sessions: Option<usize>,
+
+    /// Maximum rows read or affected per client per hour
+    pub rows_per_hour: Option<u64>,
+}
+
+/// Limits on concurrent tool calls; calls over a limit queue for a slot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConcurrencyConfig {
+    /// Maximum concurrent calls by tool name
+    pub tools: HashMap<String, usize>,
+
+    /// Maximum concurrent calls by database name
+    pub databases: HashMap<String, usize>,
+
+    /// How long a call waits for a slot before it is rejected
+    pub queue_timeout: Duration,
+}
+
+/// Masking of sensitive values in query results.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaskingConfig {
+    /// Column rules of the form `[schema.][table.]column[=redact|partial|hash]`
+    pub column_rules: Vec<String>,
+
+    /// Content detectors applied to all text values (email, ssn, credit_card, phone, all)
+    pub detectors: Vec<String>,
 }
 
 /// Query execution configuration.
@@ -253,6 +458,51 @@ pub struct QueryConfig {
 
     /// Maximum number of cached entries
     pub cache_max_entries: usize,
+
+    /// Language for localized error messages (e.g. "de", "fr"); `None` for English
+    pub error_language: Option<String>,
+
+    /// Automatically retry read-only statements that fail with transient errors
+    pub retry_read_only: bool,
+
+    /// Also retry statements that may modify data on transient errors
+    pub retry_statements: bool,
+
+    /// Times a transaction chosen as deadlock victim is replayed; 0 disables
+    pub deadlock_replays: u32,
+
+    /// Results larger than this (in KB) are returned by reference; 0 disables
+    pub result_ref_threshold_kb: usize,
+
+    /// Prefix executed SQL with a comment carrying the request ID
+    pub tag_queries: bool,
+
+    /// Set `CONTEXT_INFO` and session context naming the client, tool and request
+    pub session_context: bool,
+
+    /// Statements running longer than this (in ms) are logged as slow; 0 disables
+    pub slow_query_threshold_ms: u64,
+
+    /// Capture execution plans of slow queries
+    pub slow_query_plans: bool,
+
+    /// Number of slow queries kept in memory
+    pub slow_query_log_size: usize,
+
+    /// Return DECIMAL, NUMERIC and MONEY values as exact strings instead of `f64`
+    pub exact_decimals: bool,
+
+    /// Representation of binary values in query results
+    pub binary_format: BinaryFormat,
+
+    /// Bytes shown in the hex preview of a binary value; 0 shows the full value
+    pub binary_preview_bytes: usize,
+
+    /// Memory in MB the rows of a single query may take; 0 is unlimited
+    pub query_memory_mb: usize,
+
+    /// Memory in MB the rows of all queries in flight may take together; 0 is unlimited
+    pub result_memory_mb: usize,
 }
 
 /// Session management configuration.
@@ -264,8 +514,54 @@ pub struct SessionConfig {
     /// Session cleanup interval
     pub cleanup_interval: Duration,
 
+    /// End pinned sessions idle for longer than this; zero disables
+    pub pinned_idle_timeout: Duration,
+
+    /// End pinned sessions older than this; zero disables
+    pub pinned_max_lifetime: Duration,
+
+    /// Maximum concurrent pinned sessions
+    pub max_pinned_sessions: usize,
+
     /// Session result retention time
     pub result_retention: Duration,
+
+    /// Maximum finished async sessions kept in memory; 0 is unlimited
+    pub max_finished_sessions: usize,
+
+    /// Maximum memory held by finished async session results in MB; 0 is unlimited
+    pub session_memory_mb: usize,
+
+    /// Interval between schema change polls for subscribed resources
+    pub schema_poll_interval: Duration,
+
+    /// File that persists table size snapshots between restarts (`None` = memory only)
+    pub table_size_history_path: Option<PathBuf>,
+
+    /// Directory that persists finished async sessions between restarts (`None` = memory only)
+    pub session_store_dir: Option<PathBuf>,
+
+    /// Maximum total size of persisted async sessions in MB
+    pub session_store_max_mb: usize,
+}
+
+/// Audit log configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Record tool calls in the audit log
+    pub enabled: bool,
+
+    /// Record full query text instead of only its SHA-256 hash
+    pub log_query_text: bool,
+
+    /// Number of entries kept in memory for `get_query_history`
+    pub max_entries: usize,
+
+    /// JSON Lines file that entries are appended to
+    pub file: Option<PathBuf>,
+
+    /// Table (`schema.table`) that entries are inserted into; created if missing
+    pub table: Option<String>,
 }
 
 impl Config {
@@ -274,45 +570,179 @@ impl Config {
     /// # Environment Variables
     ///
     /// ## Required
-    /// - `MSSQL_HOST`: SQL Server hostname
+    /// - `MSSQL_HOST`: SQL Server hostname, or `HOST\INSTANCE` for a named instance
     /// - `MSSQL_USER`: SQL Server username (for SQL auth)
     /// - `MSSQL_PASSWORD`: SQL Server password (for SQL auth)
     ///
     /// ## Optional
-    /// - `MSSQL_PORT`: Port number (default: 1433)
+    /// - `MSSQL_PASSWORD_FILE`: File holding the password, read when `MSSQL_PASSWORD` is unset
+    /// - `MSSQL_CONNECTION_STRING`: ADO.NET, ODBC or JDBC connection string supplying the
+    ///   connection settings; individual variables override its values
+    /// - `MSSQL_PORT`: Port number (default: 1433, or resolved through SQL Browser for named instances)
+    /// - `MSSQL_INSTANCE`: Named instance whose port is resolved through SQL Browser (UDP 1434)
+    /// - `MSSQL_APPLICATION_INTENT`: `ReadOnly` routes to a readable AG secondary (default: ReadWrite)
+    /// - `MSSQL_MULTI_SUBNET_FAILOVER`: Connect to the first answering listener address (default: false)
+    /// - `MSSQL_FAILOVER_PARTNERS`: Comma-separated `host[:port]` servers tried when the host is unreachable
+    /// - `MSSQL_READ_POOL`: Run read-only tools on a second pool with read-only intent (default: false)
+    /// - `MSSQL_READ_POOL_HOST`: `host[:port]` of a read replica for the read-only pool; implies `MSSQL_READ_POOL`
     /// - `MSSQL_DATABASE`: Database name (omit for server mode)
+    /// - `MSSQL_APPLICATION_NAME`: Application name reported at login (default: mssql-mcp-server)
     /// - `MSSQL_ENCRYPT`: Enable TLS (default: true)
     /// - `MSSQL_TRUST_CERT`: Trust server certificate (default: false)
     /// - `MSSQL_POOL_MIN`: Minimum pool connections (default: 1)
     /// - `MSSQL_POOL_MAX`: Maximum pool connections (default: 10)
     /// - `MSSQL_CONNECT_TIMEOUT`: Connection timeout in seconds (default: 30)
+    /// - `MSSQL_POOL_MAX_LIFETIME`: Seconds before a pooled connection is recycled (default: 1800)
+    /// - `MSSQL_POOL_HEALTH_CHECK_INTERVAL`: Seconds between idle connection probes (default: 30, 0 disables)
+    /// - `MSSQL_CREDENTIAL_CHECK_INTERVAL`: Seconds between checks for rotated credentials
+    ///   (default: 300 with a secrets provider, else 0, disabled)
+    /// - `MSSQL_SECRETS_PROVIDER`: `env-file`, `aws`, `azure` or `vault` supplying settings such as
+    ///   `MSSQL_PASSWORD` that are not set directly (default: none)
+    /// - `MSSQL_SECRETS_FILE`: `KEY=value` file read by the `env-file` provider
+    /// - `MSSQL_SECRETS_AWS_SECRET_ID`: AWS Secrets Manager secret, read with the `AWS_*` access keys
+    /// - `MSSQL_SECRETS_AWS_REGION`: Region of the secret (default: `AWS_REGION`)
+    /// - `MSSQL_SECRETS_AZURE_VAULT_URL`: Key Vault URL, read with the `MSSQL_AZURE_*` service principal
+    /// - `MSSQL_SECRETS_AZURE_SECRET`: Name of the Key Vault secret
+    /// - `MSSQL_SECRETS_VAULT_PATH`: HashiCorp Vault secret path, read from `VAULT_ADDR` with `VAULT_TOKEN`
     /// - `MSSQL_QUERY_TIMEOUT`: Default query timeout in seconds (default: 30)
     /// - `MSSQL_VALIDATION_MODE`: Query validation mode (readonly, standard, unrestricted)
     /// - `MSSQL_MAX_ROWS`: Maximum result rows (default: 10000)
+    /// - `MSSQL_EXPORT_DIRS`: Comma-separated directories `export_data` may write to (default: none)
+    /// - `MSSQL_IMPORT_DIRS`: Comma-separated directories `import_data` may read from (default: none)
+    /// - `MSSQL_RATE_LIMIT_QPM`: Maximum tool calls per client per minute (default: unlimited)
+    /// - `MSSQL_RATE_LIMIT_SESSIONS`: Maximum concurrent sessions and transactions per client (default: unlimited)
+    /// - `MSSQL_RATE_LIMIT_ROWS_PER_HOUR`: Maximum rows read or affected per client per hour (default: unlimited)
+    /// - `MSSQL_TOOL_CONCURRENCY`: Comma-separated `tool=n` limits on concurrent calls (default: none)
+    /// - `MSSQL_DATABASE_CONCURRENCY`: Comma-separated `database=n` limits on concurrent calls (default: none)
+    /// - `MSSQL_CONCURRENCY_QUEUE_TIMEOUT`: Seconds a call waits for a concurrency slot (default: 30)
+    /// - `MSSQL_MASK_COLUMNS`: Comma-separated column masking rules (default: none)
+    /// - `MSSQL_MASK_PATTERNS`: Comma-separated content detectors to mask (default: none)
+    /// - `MSSQL_REQUIRE_CONFIRMATION`: Confirm destructive statements before running them (default: false)
+    /// - `MSSQL_ELICIT_CONFIRMATION`: Confirm through the client's elicitation prompt when supported (default: true)
+    /// - `MSSQL_CONFIRM_BULK_ROWS`: Rows above which `bulk_insert` and `generate_test_data` need confirmation, 0 to never ask (default: 10000)
+    /// - `MSSQL_MAX_QUERY_COST`: Reject statements with a higher estimated plan cost (default: unlimited)
+    /// - `MSSQL_MAX_DOP`: Limit the degree of parallelism with a MAXDOP hint (default: unlimited)
+    /// - `MSSQL_ENFORCE_ROW_LIMIT`: Inject TOP into SELECTs without a row limit (default: false)
+    /// - `MSSQL_MAX_RESULT_ESTIMATE_MB`: Reject SELECTs with a larger estimated result (default: unlimited)
+    /// - `MSSQL_RESULT_ESTIMATE_WARN_ONLY`: Warn about large estimated results instead of rejecting them (default: false)
+    /// - `MSSQL_QUERY_REGISTRY`: TOML or YAML file of named queries for `execute_registered` (default: none)
+    /// - `MSSQL_REGISTERED_ONLY`: Only allow registered queries, refusing SQL text (default: false)
+    /// - `MSSQL_ALLOW_LINKED_SERVERS`: Allow four-part names and OPENQUERY in read-only and standard mode (default: false)
+    /// - `MSSQL_ALLOWED_PROCEDURES`: Comma-separated procedures `execute_procedure` may run (default: all)
+    /// - `MSSQL_PROCEDURES_ONLY`: Disable ad-hoc SQL tools; requires `MSSQL_ALLOWED_PROCEDURES` (default: false)
+    /// - `MSSQL_ENABLED_TOOLS`: Comma-separated tools to expose; others are hidden (default: all)
+    /// - `MSSQL_DISABLED_TOOLS`: Comma-separated tools to hide and refuse (default: none)
+    /// - `MSSQL_ERROR_LANGUAGE`: Language for localized error messages (default: English)
+    /// - `MSSQL_RETRY_READ_ONLY`: Retry read-only statements on transient errors (default: true)
+    /// - `MSSQL_RETRY_STATEMENTS`: Also retry statements that may modify data (default: false)
+    /// - `MSSQL_DEADLOCK_REPLAYS`: Replay deadlocked transactions up to this many times (default: 0)
+    /// - `MSSQL_RESULT_REF_THRESHOLD_KB`: Return larger results by reference (default: 256, 0 disables)
+    /// - `MSSQL_TAG_QUERIES`: Tag executed SQL with the request ID (default: false)
+    /// - `MSSQL_SLOW_QUERY_MS`: Log statements running longer than this many milliseconds (default: 0, disabled)
+    /// - `MSSQL_SLOW_QUERY_PLANS`: Capture execution plans of slow queries (default: false)
+    /// - `MSSQL_SLOW_QUERY_LOG_SIZE`: Slow queries kept in memory (default: 100)
+    /// - `MSSQL_QUERY_MEMORY_MB`: Memory the rows of a single query may take before it fails (default: 256, 0 unlimited)
+    /// - `MSSQL_RESULT_MEMORY_MB`: Memory the rows of all queries in flight may take together (default: 1024, 0 unlimited)
+    /// - `MSSQL_SESSION_CONTEXT`: Set CONTEXT_INFO and session context naming the client, tool and request (default: false)
+    /// - `MSSQL_MAX_PINNED_SESSIONS`: Maximum concurrent pinned sessions (default: `MSSQL_MAX_SESSIONS`)
+    /// - `MSSQL_SESSION_TIMEOUT`: End pinned sessions idle for this many seconds (default: 3600, 0 disables)
+    /// - `MSSQL_SESSION_MAX_LIFETIME`: End pinned sessions older than this many seconds (default: 0, disabled)
+    /// - `MSSQL_CLEANUP_INTERVAL`: Seconds between expired session checks (default: 60)
+    /// - `MSSQL_RESULT_RETENTION`: Seconds finished async sessions and result references are kept (default: 3600)
+    /// - `MSSQL_MAX_FINISHED_SESSIONS`: Finished async sessions kept in memory, oldest removed first (default: 1000, 0 unlimited)
+    /// - `MSSQL_SESSION_MEMORY_MB`: Memory held by finished async session results, oldest removed first (default: 512, 0 unlimited)
+    /// - `MSSQL_SCHEMA_POLL_INTERVAL`: Schema change poll interval in seconds for resource subscriptions (default: 30, minimum: 5)
+    /// - `MSSQL_TABLE_SIZE_HISTORY_PATH`: JSON file persisting table size snapshots for growth tracking (default: memory only)
+    /// - `MSSQL_SESSION_STORE_DIR`: Directory persisting finished async session results across restarts (default: memory only)
+    /// - `MSSQL_SESSION_STORE_MAX_MB`: Maximum size of persisted session results; oldest are dropped first (default: 256)
+    /// - `MSSQL_AUDIT_ENABLED`: Record tool calls in the audit log (default: true)
+    /// - `MSSQL_AUDIT_QUERY_TEXT`: Record full query text instead of a SHA-256 hash (default: false)
+    /// - `MSSQL_AUDIT_MAX_ENTRIES`: Audit entries kept in memory (default: 1000)
+    /// - `MSSQL_AUDIT_FILE`: JSON Lines file audit entries are appended to (default: none)
+    /// - `MSSQL_AUDIT_TABLE`: Table audit entries are inserted into, e.g. `dbo.McpAuditLog` (default: none)
     pub fn from_env() -> Result<Self, ServerError> {
-        // Required: Host
-        let host = std::env::var("MSSQL_HOST")
-            .map_err(|_| ServerError::config("MSSQL_HOST environment variable is required"))?;
+        Self::from_lookup(|name| std::env::var(name))
+    }
+
+    /// Load configuration from a file layered under environment variables.
+    ///
+    /// When `MSSQL_MCP_CONFIG` names a `.toml`, `.yaml` or `.yml` file, its
+    /// settings are read first and any environment variable that is also set
+    /// overrides the file value. Without it this is the same as [`Config::from_env`].
+    ///
+    /// File keys use the environment variable names without the `MSSQL_`
+    /// prefix, in any case. Tables only group settings, so `[security]
+    /// validation_mode = "readonly"` sets `MSSQL_VALIDATION_MODE`. Arrays are
+    /// joined into comma-separated lists.
+    pub fn load() -> Result<Self, ServerError> {
+        match std::env::var(CONFIG_FILE_ENV) {
+            Ok(path) if !path.trim().is_empty() => {
+                let settings = read_config_file(Path::new(path.trim()))?;
+                Self::from_lookup(|name| {
+                    std::env::var(name).or_else(|_| {
+                        settings
+                            .get(name)
+                            .cloned()
+                            .ok_or(std::env::VarError::NotPresent)
+                    })
+                })
+            }
+            _ => Self::from_env(),
+        }
+    }
+
+    /// Build the configuration from a variable lookup function.
+    fn from_lookup<F>(lookup: F) -> Result<Self, ServerError>
+    where
+        F: Fn(&str) -> Result<String, std::env::VarError>,
+    {
+        // Optional: Connection string, overridden by individual settings
+        let connection = match lookup(CONNECTION_STRING_ENV) {
+            Ok(s) if !s.trim().is_empty() => parse_connection_string(&s)?,
+            _ => HashMap::new(),
+        };
+        // Optional: Secrets provider, supplying settings not set directly
+        let provider = secrets_provider(|name| lookup(name).ok())?;
+        let secrets = match &provider {
+            Some(provider) => provider.fetch()?,
+            None => HashMap::new(),
+        };
+        let var = |name: &str| {
+            lookup(name).or_else(|_| {
+                secrets
+                    .get(name)
+                    .or_else(|| connection.get(name))
+                    .cloned()
+                    .ok_or(std::env::VarError::NotPresent)
+            })
+        };
+
+        // Required: Host, optionally written as HOST\INSTANCE
+        let host = var("MSSQL_HOST").map_err(|_| {
+            ServerError::config("MSSQL_HOST or MSSQL_CONNECTION_STRING is required")
+        })?;
+        let (host, host_instance) = match host.split_once('\\') {
+            Some((h, i)) => (h.to_string(), Some(i.to_string()).filter(|i| !i.is_empty())),
+            None => (host, None),
+        };
 
         // Determine authentication type
-        let auth_type = std::env::var("MSSQL_AUTH_TYPE")
-            .ok()
-            .map(|s| s.to_lowercase());
+        let auth_type = var("MSSQL_AUTH_TYPE").ok().map(|s| s.to_lowercase());
 
         let auth = match auth_type.as_deref() {
             Some("azuread") | Some("azure") | Some("aad") => {
                 // Azure AD Authentication
-                let client_id = std::env::var("MSSQL_AZURE_CLIENT_ID").map_err(|_| {
+                let client_id = var("MSSQL_AZURE_CLIENT_ID").map_err(|_| {
                     ServerError::config(
                         "MSSQL_AZURE_CLIENT_ID is required for Azure AD authentication",
                     )
                 })?;
-                let client_secret = std::env::var("MSSQL_AZURE_CLIENT_SECRET").map_err(|_| {
+                let client_secret = var("MSSQL_AZURE_CLIENT_SECRET").map_err(|_| {
                     ServerError::config(
                         "MSSQL_AZURE_CLIENT_SECRET is required for Azure AD authentication",
                     )
                 })?;
-                let tenant_id = std::env::var("MSSQL_AZURE_TENANT_ID").map_err(|_| {
+                let tenant_id = var("MSSQL_AZURE_TENANT_ID").map_err(|_| {
                     ServerError::config(
                         "MSSQL_AZURE_TENANT_ID is required for Azure AD authentication",
                     )
@@ -326,8 +756,14 @@ impl Config {
             }
             _ => {
                 // SQL Server Authentication (default)
-                let username = std::env::var("MSSQL_USER").ok();
-                let password = std::env::var("MSSQL_PASSWORD").ok();
+                let username = var("MSSQL_USER").ok();
+                let password = match var("MSSQL_PASSWORD") {
+                    Ok(password) => Some(password),
+                    Err(_) => var("MSSQL_PASSWORD_FILE")
+                        .ok()
+                        .map(|path| read_secret_file(Path::new(&path)))
+                        .transpose()?,
+                };
 
                 match (username, password) {
                     (Some(u), Some(p)) => AuthConfig::SqlServer {
@@ -354,105 +790,195 @@ impl Config {
         };
 
         // Optional: Port
-        let port = std::env::var("MSSQL_PORT")
+        let port = var("MSSQL_PORT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(1433);
 
         // Optional: Database (None = server mode)
-        let database = std::env::var("MSSQL_DATABASE").ok();
+        let database = var("MSSQL_DATABASE").ok();
 
         // Optional: Encryption settings
-        let encrypt = std::env::var("MSSQL_ENCRYPT")
+        let encrypt = var("MSSQL_ENCRYPT")
             .map(|v| v.to_lowercase() == "true" || v == "1")
             .unwrap_or(true);
 
-        let trust_server_certificate = std::env::var("MSSQL_TRUST_CERT")
+        let trust_server_certificate = var("MSSQL_TRUST_CERT")
             .map(|v| v.to_lowercase() == "true" || v == "1")
             .unwrap_or(false);
 
-        // Optional: Named instance
-        let instance = std::env::var("MSSQL_INSTANCE").ok();
+        // mssql-client 0.5 builds its TLS settings from MSSQL_TRUST_CERT alone, so
+        // custom roots, host names and client certificates would be ignored
+        for name in [
+            "MSSQL_TLS_CA_FILE",
+            "MSSQL_TLS_HOSTNAME",
+            "MSSQL_TLS_CLIENT_CERT",
+            "MSSQL_TLS_CLIENT_KEY",
+        ] {
+            if var(name).is_ok() {
+                return Err(ServerError::config(format!(
+                    "{} is not supported: the SQL Server driver does not apply custom TLS \
+                     certificates or host names yet",
+                    name
+                )));
+            }
+        }
+
+        // mssql-client 0.5 does not implement Always Encrypted, so encrypted
+        // columns could not be decrypted nor parameters for them encrypted
+        for name in [
+            "MSSQL_COLUMN_ENCRYPTION",
+            "MSSQL_AE_CERTIFICATE",
+            "MSSQL_AE_AZURE_KEY_VAULT",
+        ] {
+            if var(name).is_ok() {
+                return Err(ServerError::config(format!(
+                    "{} is not supported: the SQL Server driver does not implement \
+                     Always Encrypted yet",
+                    name
+                )));
+            }
+        }
+
+        // Optional: Named instance, whose port SQL Browser resolves unless one is set
+        let instance = var("MSSQL_INSTANCE").ok().or(host_instance);
+        let resolve_instance_port = instance.is_some() && var("MSSQL_PORT").is_err();
 
         // Optional: MARS (Multiple Active Result Sets)
-        let mars = std::env::var("MSSQL_MARS")
+        let mars = var("MSSQL_MARS")
             .map(|v| v.to_lowercase() == "true" || v == "1")
             .unwrap_or(false);
 
+        // Optional: Application name reported at login (APP_NAME())
+        let application_name = var("MSSQL_APPLICATION_NAME")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "mssql-mcp-server".to_string());
+
         // Optional: Retry configuration
-        let retry_max_retries = std::env::var("MSSQL_RETRY_MAX")
+        let retry_max_retries = var("MSSQL_RETRY_MAX")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(3);
 
-        let retry_initial_backoff_ms = std::env::var("MSSQL_RETRY_INITIAL_BACKOFF_MS")
+        let retry_initial_backoff_ms = var("MSSQL_RETRY_INITIAL_BACKOFF_MS")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(100);
 
-        let retry_max_backoff_ms = std::env::var("MSSQL_RETRY_MAX_BACKOFF_MS")
+        let retry_max_backoff_ms = var("MSSQL_RETRY_MAX_BACKOFF_MS")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(10_000);
 
-        let retry_backoff_multiplier = std::env::var("MSSQL_RETRY_BACKOFF_MULTIPLIER")
+        let retry_backoff_multiplier = var("MSSQL_RETRY_BACKOFF_MULTIPLIER")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(2.0);
 
-        let retry_jitter = std::env::var("MSSQL_RETRY_JITTER")
+        let retry_jitter = var("MSSQL_RETRY_JITTER")
             .map(|v| v.to_lowercase() != "false" && v != "0")
             .unwrap_or(true);
 
         // Optional: TDS version (7.3, 7.3a, 7.3b, 7.4, 8.0)
-        let tds_version = std::env::var("MSSQL_TDS_VERSION")
+        let tds_version = var("MSSQL_TDS_VERSION")
             .ok()
             .and_then(|v| TdsVersionConfig::from_str(&v))
             .unwrap_or_default();
 
+        // Optional: Availability Group routing
+        let application_intent = match var("MSSQL_APPLICATION_INTENT") {
+            Ok(v) => ApplicationIntent::from_str(&v).ok_or_else(|| {
+                ServerError::config(format!(
+                    "Invalid MSSQL_APPLICATION_INTENT '{v}': expected ReadWrite or ReadOnly"
+                ))
+            })?,
+            Err(_) => ApplicationIntent::default(),
+        };
+        let multi_subnet_failover = var("MSSQL_MULTI_SUBNET_FAILOVER")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        let failover_partners: Vec<String> = var("MSSQL_FAILOVER_PARTNERS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let read_pool_host = var("MSSQL_READ_POOL_HOST")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let read_pool = read_pool_host.is_some()
+            || var("MSSQL_READ_POOL")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false);
+
         // Optional: Pool settings
-        let min_connections = std::env::var("MSSQL_POOL_MIN")
+        let min_connections = var("MSSQL_POOL_MIN")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_MIN_CONNECTIONS);
 
-        let max_connections = std::env::var("MSSQL_POOL_MAX")
+        let max_connections = var("MSSQL_POOL_MAX")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_MAX_CONNECTIONS);
 
-        let connection_timeout_secs = std::env::var("MSSQL_CONNECT_TIMEOUT")
+        let connection_timeout_secs = var("MSSQL_CONNECT_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_CONNECTION_TIMEOUT_SECS);
 
-        let idle_timeout_secs: u64 = std::env::var("MSSQL_IDLE_TIMEOUT")
+        let idle_timeout_secs: u64 = var("MSSQL_IDLE_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(300);
 
+        let max_lifetime_secs: u64 = var("MSSQL_POOL_MAX_LIFETIME")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_POOL_MAX_LIFETIME_SECS);
+
+        let health_check_interval_secs: u64 = var("MSSQL_POOL_HEALTH_CHECK_INTERVAL")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS);
+
+        // Secrets from a provider are refreshed periodically unless disabled
+        let credential_check_interval_secs: u64 = var("MSSQL_CREDENTIAL_CHECK_INTERVAL")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(if provider.is_some() {
+                DEFAULT_SECRETS_REFRESH_SECS
+            } else {
+                0
+            });
+
         // Optional: Granular timeout settings
-        let tcp_connect_timeout_secs: u64 = std::env::var("MSSQL_TCP_CONNECT_TIMEOUT")
+        let tcp_connect_timeout_secs: u64 = var("MSSQL_TCP_CONNECT_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(15);
 
-        let tls_timeout_secs: u64 = std::env::var("MSSQL_TLS_TIMEOUT")
+        let tls_timeout_secs: u64 = var("MSSQL_TLS_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(10);
 
-        let login_timeout_secs: u64 = std::env::var("MSSQL_LOGIN_TIMEOUT")
+        let login_timeout_secs: u64 = var("MSSQL_LOGIN_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(30);
 
-        let command_timeout_secs: u64 = std::env::var("MSSQL_COMMAND_TIMEOUT")
+        let command_timeout_secs: u64 = var("MSSQL_COMMAND_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(30);
 
-        let keepalive_interval_secs: Option<u64> = std::env::var("MSSQL_KEEPALIVE_INTERVAL")
+        let keepalive_interval_secs: Option<u64> = var("MSSQL_KEEPALIVE_INTERVAL")
             .ok()
             .and_then(|p| {
                 if p.to_lowercase() == "none" || p == "0" {
@@ -464,18 +990,18 @@ impl Config {
             .or(Some(30));
 
         // Optional: Query settings
-        let default_timeout_secs = std::env::var("MSSQL_QUERY_TIMEOUT")
+        let default_timeout_secs = var("MSSQL_QUERY_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_QUERY_TIMEOUT_SECS);
 
-        let max_timeout_secs = std::env::var("MSSQL_MAX_QUERY_TIMEOUT")
+        let max_timeout_secs = var("MSSQL_MAX_QUERY_TIMEOUT")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(300);
 
         // Optional: Security settings
-        let validation_mode = std::env::var("MSSQL_VALIDATION_MODE")
+        let validation_mode = var("MSSQL_VALIDATION_MODE")
             .ok()
             .and_then(|m| match m.to_lowercase().as_str() {
                 "readonly" | "read_only" | "read-only" => Some(ValidationMode::ReadOnly),
@@ -485,51 +1011,360 @@ impl Config {
             })
             .unwrap_or(ValidationMode::Standard);
 
-        let max_query_length = std::env::var("MSSQL_MAX_QUERY_LENGTH")
+        let max_query_length = var("MSSQL_MAX_QUERY_LENGTH")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(1_000_000); // 1MB default
 
-        let max_result_rows = std::env::var("MSSQL_MAX_ROWS")
+        let max_result_rows = var("MSSQL_MAX_ROWS")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_MAX_RESULT_ROWS);
 
-        let injection_detection = std::env::var("MSSQL_INJECTION_DETECTION")
+        let injection_detection = var("MSSQL_INJECTION_DETECTION")
             .map(|v| v.to_lowercase() != "false" && v != "0")
             .unwrap_or(true);
 
+        // Optional: Directories allowed as export destinations
+        let export_directories = var("MSSQL_EXPORT_DIRS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Optional: Directories allowed as import sources
+        let import_directories = var("MSSQL_IMPORT_DIRS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Optional: Per-client rate limits (0 or unset = unlimited)
+        let rate_limit = RateLimitConfig {
+            queries_per_minute: var("MSSQL_RATE_LIMIT_QPM")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n| n > 0),
+            concurrent_sessions: var("MSSQL_RATE_LIMIT_SESSIONS")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n| n > 0),
+            rows_per_hour: var("MSSQL_RATE_LIMIT_ROWS_PER_HOUR")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n| n > 0),
+        };
+
+        // Optional: Concurrency limits per tool and per database
+        let concurrency = ConcurrencyConfig {
+            tools: parse_limits("MSSQL_TOOL_CONCURRENCY", var("MSSQL_TOOL_CONCURRENCY").ok())?,
+            databases: parse_limits(
+                "MSSQL_DATABASE_CONCURRENCY",
+                var("MSSQL_DATABASE_CONCURRENCY").ok(),
+            )?,
+            queue_timeout: Duration::from_secs(
+                var("MSSQL_CONCURRENCY_QUEUE_TIMEOUT")
+                    .ok()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_SECS),
+            ),
+        };
+
+        // Optional: Masking of sensitive values
+        let split_list = |name: &str| -> Vec<String> {
+            var(name)
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|r| !r.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let masking = MaskingConfig {
+            column_rules: split_list("MSSQL_MASK_COLUMNS"),
+            detectors: split_list("MSSQL_MASK_PATTERNS"),
+        };
+
+        let require_confirmation = var("MSSQL_REQUIRE_CONFIRMATION")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        let elicit_confirmation = var("MSSQL_ELICIT_CONFIRMATION")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(true);
+        let confirm_bulk_rows = var("MSSQL_CONFIRM_BULK_ROWS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CONFIRM_BULK_ROWS);
+
+        // Optional: Registry of named queries
+        let query_registry = var("MSSQL_QUERY_REGISTRY").ok().map(PathBuf::from);
+        let registered_queries_only = var("MSSQL_REGISTERED_ONLY")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Optional: Linked server queries
+        let allow_linked_servers = var("MSSQL_ALLOW_LINKED_SERVERS")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Optional: Stored procedure allow-list
+        let allowed_procedures = split_list("MSSQL_ALLOWED_PROCEDURES");
+        let procedures_only = var("MSSQL_PROCEDURES_ONLY")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        if procedures_only && allowed_procedures.is_empty() {
+            return Err(ServerError::config(
+                "MSSQL_PROCEDURES_ONLY requires MSSQL_ALLOWED_PROCEDURES",
+            ));
+        }
+
+        // Optional: Tool-level capability filtering
+        let enabled_tools = split_list("MSSQL_ENABLED_TOOLS");
+        let disabled_tools = split_list("MSSQL_DISABLED_TOOLS");
+        for (list, names) in [
+            ("MSSQL_ENABLED_TOOLS", &enabled_tools),
+            ("MSSQL_DISABLED_TOOLS", &disabled_tools),
+        ] {
+            if let Some(name) = names
+                .iter()
+                .find(|n| !crate::tools::names::is_known_tool(n))
+            {
+                return Err(ServerError::config(format!(
+                    "Unknown tool '{}' in {}",
+                    name, list
+                )));
+            }
+        }
+
+        // Optional: Query governor (0 or unset = unlimited)
+        let governor = GovernorConfig {
+            max_estimated_cost: var("MSSQL_MAX_QUERY_COST")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n: &f64| n > 0.0),
+            max_dop: var("MSSQL_MAX_DOP")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n| n > 0),
+            enforce_row_limit: var("MSSQL_ENFORCE_ROW_LIMIT")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false),
+            max_result_estimate_mb: var("MSSQL_MAX_RESULT_ESTIMATE_MB")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .filter(|&n: &f64| n > 0.0),
+            result_estimate_warn_only: var("MSSQL_RESULT_ESTIMATE_WARN_ONLY")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false),
+        };
+
         // Optional: Session settings
-        let max_sessions = std::env::var("MSSQL_MAX_SESSIONS")
+        let max_sessions = var("MSSQL_MAX_SESSIONS")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(10);
 
+        let cleanup_interval_secs = var("MSSQL_CLEANUP_INTERVAL")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_CLEANUP_INTERVAL_SECS);
+
+        // Optional: Pinned session limits
+        let pinned_idle_timeout_secs = var("MSSQL_SESSION_TIMEOUT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_PINNED_SESSION_IDLE_TIMEOUT_SECS);
+
+        let pinned_max_lifetime_secs = var("MSSQL_SESSION_MAX_LIFETIME")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(0);
+
+        let max_pinned_sessions = var("MSSQL_MAX_PINNED_SESSIONS")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(max_sessions);
+
         // Optional: Cache settings
-        let enable_caching = std::env::var("MSSQL_ENABLE_CACHE")
+        let enable_caching = var("MSSQL_ENABLE_CACHE")
             .map(|v| v.to_lowercase() == "true" || v == "1")
             .unwrap_or(false);
 
-        let cache_ttl_secs = std::env::var("MSSQL_CACHE_TTL")
+        let cache_ttl_secs = var("MSSQL_CACHE_TTL")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_CACHE_TTL_SECS);
 
-        let cache_max_size_mb = std::env::var("MSSQL_CACHE_SIZE_MB")
+        let cache_max_size_mb = var("MSSQL_CACHE_SIZE_MB")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_CACHE_MAX_SIZE_MB);
 
-        let cache_max_entries = std::env::var("MSSQL_CACHE_MAX_ENTRIES")
+        let cache_max_entries = var("MSSQL_CACHE_MAX_ENTRIES")
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_CACHE_MAX_ENTRIES);
 
+        // Optional: Error message localization
+        let error_language = var("MSSQL_ERROR_LANGUAGE")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+
+        // Optional: Statement-level retry (policy from MSSQL_RETRY_MAX_ATTEMPTS etc.)
+        let retry_read_only = var("MSSQL_RETRY_READ_ONLY")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(true);
+        let retry_statements = var("MSSQL_RETRY_STATEMENTS")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Optional: Replay of transactions chosen as deadlock victim
+        let deadlock_replays = var("MSSQL_DEADLOCK_REPLAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
+        // Optional: Result references for large results
+        let result_ref_threshold_kb = var("MSSQL_RESULT_REF_THRESHOLD_KB")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_RESULT_REF_THRESHOLD_KB);
+
+        // Optional: Request ID comment tags on executed SQL
+        let tag_queries = var("MSSQL_TAG_QUERIES")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Optional: CONTEXT_INFO and session context attribution of executed SQL
+        let session_context = var("MSSQL_SESSION_CONTEXT")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        // Optional: Slow query log
+        let slow_query_threshold_ms = var("MSSQL_SLOW_QUERY_MS")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(0);
+
+        let slow_query_plans = var("MSSQL_SLOW_QUERY_PLANS")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        let slow_query_log_size = var("MSSQL_SLOW_QUERY_LOG_SIZE")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_LOG_SIZE);
+
+        // Optional: f64 numbers for DECIMAL/NUMERIC/MONEY (previous behavior)
+        let exact_decimals = var("MSSQL_EXACT_DECIMALS")
+            .map(|v| v.to_lowercase() != "false" && v != "0")
+            .unwrap_or(true);
+
+        // Optional: Binary values in query results
+        let binary_format = match var("MSSQL_BINARY_FORMAT") {
+            Ok(v) => BinaryFormat::from_str(&v).ok_or_else(|| {
+                ServerError::config(format!(
+                    "Invalid MSSQL_BINARY_FORMAT '{v}': expected hex, base64 or omit"
+                ))
+            })?,
+            Err(_) => BinaryFormat::default(),
+        };
+        let binary_preview_bytes = var("MSSQL_BINARY_PREVIEW_BYTES")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_BINARY_PREVIEW_BYTES);
+
+        // Optional: Result memory budgets
+        let query_memory_mb = var("MSSQL_QUERY_MEMORY_MB")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_QUERY_MEMORY_MB);
+
+        let result_memory_mb = var("MSSQL_RESULT_MEMORY_MB")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_RESULT_MEMORY_MB);
+
+        // Optional: Schema change polling for resource subscriptions
+        let schema_poll_interval_secs = var("MSSQL_SCHEMA_POLL_INTERVAL")
+            .ok()
+            .and_then(|p| p.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCHEMA_POLL_INTERVAL_SECS)
+            .max(MIN_WATCH_POLL_INTERVAL_SECS);
+
+        // Optional: Persisted table size snapshots
+        let table_size_history_path = var("MSSQL_TABLE_SIZE_HISTORY_PATH")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| PathBuf::from(p.trim()));
+
+        // Optional: Retention of finished async sessions
+        let result_retention_secs = var("MSSQL_RESULT_RETENTION")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_RESULT_RETENTION_SECS);
+
+        let max_finished_sessions = var("MSSQL_MAX_FINISHED_SESSIONS")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_MAX_FINISHED_SESSIONS);
+
+        let session_memory_mb = var("MSSQL_SESSION_MEMORY_MB")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_SESSION_MEMORY_MB);
+
+        // Optional: Persisted async session results
+        let session_store_dir = var("MSSQL_SESSION_STORE_DIR")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| PathBuf::from(p.trim()));
+
+        let session_store_max_mb = var("MSSQL_SESSION_STORE_MAX_MB")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_SESSION_STORE_MAX_MB);
+
+        // Optional: Audit log
+        let audit_enabled = var("MSSQL_AUDIT_ENABLED")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(true);
+
+        let audit_log_query_text = var("MSSQL_AUDIT_QUERY_TEXT")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        let audit_max_entries = var("MSSQL_AUDIT_MAX_ENTRIES")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_AUDIT_MAX_ENTRIES);
+
+        let audit_file = var("MSSQL_AUDIT_FILE")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| PathBuf::from(p.trim()));
+
+        let audit_table = var("MSSQL_AUDIT_TABLE")
+            .ok()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+
         Ok(Config {
             database: DatabaseConfig {
                 host,
                 port,
                 instance,
+                resolve_instance_port,
                 database,
                 auth,
                 pool: PoolConfig {
@@ -537,6 +1372,9 @@ impl Config {
                     max_connections,
                     connection_timeout: Duration::from_secs(connection_timeout_secs),
                     idle_timeout: Duration::from_secs(idle_timeout_secs),
+                    max_lifetime: Duration::from_secs(max_lifetime_secs),
+                    health_check_interval: Duration::from_secs(health_check_interval_secs),
+                    credential_check_interval: Duration::from_secs(credential_check_interval_secs),
                 },
                 timeouts: TimeoutsConfig {
                     connect_timeout: Duration::from_secs(tcp_connect_timeout_secs),
@@ -548,7 +1386,7 @@ impl Config {
                 },
                 encrypt,
                 trust_server_certificate,
-                application_name: "mssql-mcp-server".to_string(),
+                application_name,
                 mars,
                 retry: RetryConfig {
                     max_retries: retry_max_retries,
@@ -558,12 +1396,33 @@ impl Config {
                     jitter: retry_jitter,
                 },
                 tds_version,
+                application_intent,
+                multi_subnet_failover,
+                failover_partners,
+                read_pool,
+                read_pool_host,
             },
             security: SecurityConfig {
                 validation_mode,
                 injection_detection,
                 max_query_length,
                 max_result_rows,
+                export_directories,
+                import_directories,
+                rate_limit,
+                concurrency,
+                masking,
+                require_confirmation,
+                elicit_confirmation,
+                confirm_bulk_rows,
+                governor,
+                query_registry,
+                registered_queries_only,
+                allow_linked_servers,
+                allowed_procedures,
+                procedures_only,
+                enabled_tools,
+                disabled_tools,
             },
             query: QueryConfig {
                 default_timeout: Duration::from_secs(default_timeout_secs),
@@ -572,15 +1431,100 @@ impl Config {
                 cache_ttl: Duration::from_secs(cache_ttl_secs),
                 cache_max_size_mb,
                 cache_max_entries,
+                error_language,
+                retry_read_only,
+                retry_statements,
+                deadlock_replays,
+                result_ref_threshold_kb,
+                tag_queries,
+                session_context,
+                slow_query_threshold_ms,
+                slow_query_plans,
+                slow_query_log_size,
+                exact_decimals,
+                binary_format,
+                binary_preview_bytes,
+                query_memory_mb,
+                result_memory_mb,
             },
             session: SessionConfig {
                 max_sessions,
-                cleanup_interval: DEFAULT_CLEANUP_INTERVAL,
-                result_retention: Duration::from_secs(3600),
+                cleanup_interval: Duration::from_secs(cleanup_interval_secs),
+                pinned_idle_timeout: Duration::from_secs(pinned_idle_timeout_secs),
+                pinned_max_lifetime: Duration::from_secs(pinned_max_lifetime_secs),
+                max_pinned_sessions,
+                result_retention: Duration::from_secs(result_retention_secs),
+                max_finished_sessions,
+                session_memory_mb,
+                schema_poll_interval: Duration::from_secs(schema_poll_interval_secs),
+                table_size_history_path,
+                session_store_dir,
+                session_store_max_mb,
+            },
+            audit: AuditConfig {
+                enabled: audit_enabled,
+                log_query_text: audit_log_query_text,
+                max_entries: audit_max_entries,
+                file: audit_file,
+                table: audit_table,
             },
         })
     }
 
+    /// Merge the settings that can change at runtime from a reloaded configuration.
+    ///
+    /// Security settings other than rate limits, query timeouts, caching and
+    /// tagging, and the session limits are taken from `reloaded`. Connection,
+    /// rate limit, concurrency, audit and retention settings keep their current
+    /// values.
+    pub fn with_runtime_settings(&self, reloaded: Config) -> Config {
+        let mut merged = self.clone();
+        merged.security = SecurityConfig {
+            rate_limit: self.security.rate_limit.clone(),
+            concurrency: self.security.concurrency.clone(),
+            ..reloaded.security
+        };
+        merged.query = QueryConfig {
+            error_language: self.query.error_language.clone(),
+            retry_read_only: self.query.retry_read_only,
+            retry_statements: self.query.retry_statements,
+            result_ref_threshold_kb: self.query.result_ref_threshold_kb,
+            ..reloaded.query
+        };
+        merged.session.max_sessions = reloaded.session.max_sessions;
+        merged.session.pinned_idle_timeout = reloaded.session.pinned_idle_timeout;
+        merged.session.pinned_max_lifetime = reloaded.session.pinned_max_lifetime;
+        merged.session.max_pinned_sessions = reloaded.session.max_pinned_sessions;
+        merged
+    }
+
+    /// List the settings that differ from another configuration as `section.field` names.
+    pub fn changed_settings(&self, other: &Config) -> Vec<String> {
+        use serde_json::Value;
+
+        let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return Vec::new();
+        };
+
+        let mut changed = Vec::new();
+        for (section, old_value) in &old {
+            match (old_value, new.get(section)) {
+                (Value::Object(old_fields), Some(Value::Object(fields))) => {
+                    for (field, value) in old_fields {
+                        if fields.get(field) != Some(value) {
+                            changed.push(format!("{section}.{field}"));
+                        }
+                    }
+                }
+                (value, other) if other != Some(value) => changed.push(section.clone()),
+                _ => {}
+            }
+        }
+        changed
+    }
+
     /// Check if running in database mode (specific database) vs server mode (instance-wide).
     pub fn is_database_mode(&self) -> bool {
         self.database.database.is_some()
@@ -592,6 +1536,122 @@ impl Config {
     }
 }
 
+/// Read a secret such as a password from a file, without its trailing line break.
+///
+/// Re-read on every credential check, so replacing the file rotates the secret.
+pub fn read_secret_file(path: &Path) -> Result<String, ServerError> {
+    let secret = std::fs::read_to_string(path).map_err(|e| {
+        ServerError::config(format!(
+            "Failed to read secret file {}: {e}",
+            path.display()
+        ))
+    })?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Read a configuration file into environment-style settings.
+///
+/// The format is chosen by extension: `.toml`, `.yaml` or `.yml`.
+pub fn read_config_file(path: &Path) -> Result<HashMap<String, String>, ServerError> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        ServerError::config(format!(
+            "Failed to read config file {}: {e}",
+            path.display()
+        ))
+    })?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let value: serde_json::Value = match extension.as_str() {
+        "toml" => toml::from_str(&text)
+            .map_err(|e| ServerError::config(format!("Invalid TOML in {}: {e}", path.display())))?,
+        "yaml" | "yml" => serde_yaml_ng::from_str(&text)
+            .map_err(|e| ServerError::config(format!("Invalid YAML in {}: {e}", path.display())))?,
+        _ => {
+            return Err(ServerError::config(format!(
+                "Unsupported config file format '{}': expected .toml, .yaml or .yml",
+                path.display()
+            )))
+        }
+    };
+
+    let mut settings = HashMap::new();
+    flatten_settings(&value, &mut settings)?;
+    Ok(settings)
+}
+
+/// Collect the leaf values of a parsed config file under their variable names.
+fn flatten_settings(
+    value: &serde_json::Value,
+    settings: &mut HashMap<String, String>,
+) -> Result<(), ServerError> {
+    let serde_json::Value::Object(map) = value else {
+        return Err(ServerError::config(
+            "Config file must contain a table of settings",
+        ));
+    };
+
+    for (key, value) in map {
+        let text = match value {
+            serde_json::Value::Object(_) => {
+                flatten_settings(value, settings)?;
+                continue;
+            }
+            serde_json::Value::Null => continue,
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            other => other.to_string(),
+        };
+
+        let upper = key.to_ascii_uppercase().replace(['-', '.'], "_");
+        let name = if upper.starts_with("MSSQL_") {
+            upper
+        } else {
+            format!("MSSQL_{upper}")
+        };
+        if settings.insert(name.clone(), text).is_some() {
+            return Err(ServerError::config(format!(
+                "Config file sets {name} more than once"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Parse comma-separated `name=limit` pairs, e.g. `bulk_insert=2,execute_query=4`.
+fn parse_limits(
+    variable: &str,
+    value: Option<String>,
+) -> Result<HashMap<String, usize>, ServerError> {
+    let mut limits = HashMap::new();
+    for entry in value.iter().flat_map(|v| v.split(',')) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let limit = entry
+            .split_once('=')
+            .and_then(|(name, limit)| Some((name.trim(), limit.trim().parse().ok()?)))
+            .filter(|(name, limit)| !name.is_empty() && *limit > 0);
+        let Some((name, limit)) = limit else {
+            return Err(ServerError::config(format!(
+                "Invalid {variable} entry '{entry}': expected name=limit with a positive limit"
+            )));
+        };
+        limits.insert(name.to_string(), limit);
+    }
+    Ok(limits)
+}
+
 impl Default for PoolConfig {
     fn default() -> Self {
         Self {
@@ -599,6 +1659,9 @@ impl Default for PoolConfig {
             max_connections: DEFAULT_MAX_CONNECTIONS,
             connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
             idle_timeout: Duration::from_secs(300),
+            max_lifetime: Duration::from_secs(DEFAULT_POOL_MAX_LIFETIME_SECS),
+            health_check_interval: Duration::from_secs(DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS),
+            credential_check_interval: Duration::ZERO,
         }
     }
 }
@@ -610,10 +1673,49 @@ impl Default for SecurityConfig {
             injection_detection: true,
             max_query_length: 1_000_000,
             max_result_rows: DEFAULT_MAX_RESULT_ROWS,
+            export_directories: Vec::new(),
+            import_directories: Vec::new(),
+            rate_limit: RateLimitConfig::default(),
+            concurrency: ConcurrencyConfig::default(),
+            masking: MaskingConfig::default(),
+            require_confirmation: false,
+            elicit_confirmation: true,
+            confirm_bulk_rows: DEFAULT_CONFIRM_BULK_ROWS,
+            governor: GovernorConfig::default(),
+            query_registry: None,
+            registered_queries_only: false,
+            allow_linked_servers: false,
+            allowed_procedures: Vec::new(),
+            procedures_only: false,
+            enabled_tools: Vec::new(),
+            disabled_tools: Vec::new(),
         }
     }
 }
 
+impl SecurityConfig {
+    /// Why a tool is unavailable under these settings, if it is.
+    pub fn tool_disabled_reason(&self, name: &str) -> Option<String> {
+        let listed = |tools: &[String]| tools.iter().any(|t| t.eq_ignore_ascii_case(name));
+        if !self.enabled_tools.is_empty() && !listed(&self.enabled_tools) {
+            return Some(format!(
+                "Tool '{name}' is disabled: it is not in MSSQL_ENABLED_TOOLS"
+            ));
+        }
+        if listed(&self.disabled_tools) {
+            return Some(format!(
+                "Tool '{name}' is disabled by the server configuration (MSSQL_DISABLED_TOOLS)"
+            ));
+        }
+        if self.procedures_only && procedures::is_ad_hoc_tool(name) {
+            return Some(format!(
+                "Tool '{name}' is disabled: only allowed stored procedures can be executed (MSSQL_PROCEDURES_ONLY)"
+            ));
+        }
+        None
+    }
+}
+
 impl Default for QueryConfig {
     fn default() -> Self {
         Self {
@@ -623,6 +1725,31 @@ impl Default for QueryConfig {
             cache_ttl: DEFAULT_CACHE_TTL,
             cache_max_size_mb: DEFAULT_CACHE_MAX_SIZE_MB,
             cache_max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            error_language: None,
+            retry_read_only: true,
+            retry_statements: false,
+            deadlock_replays: 0,
+            result_ref_threshold_kb: DEFAULT_RESULT_REF_THRESHOLD_KB,
+            tag_queries: false,
+            session_context: false,
+            slow_query_threshold_ms: 0,
+            slow_query_plans: false,
+            slow_query_log_size: DEFAULT_SLOW_QUERY_LOG_SIZE,
+            exact_decimals: true,
+            binary_format: BinaryFormat::default(),
+            binary_preview_bytes: DEFAULT_BINARY_PREVIEW_BYTES,
+            query_memory_mb: DEFAULT_QUERY_MEMORY_MB,
+            result_memory_mb: DEFAULT_RESULT_MEMORY_MB,
+        }
+    }
+}
+
+impl QueryConfig {
+    /// Binary value rendering, optionally overriding the configured format.
+    pub fn binary_options(&self, format: Option<BinaryFormat>) -> BinaryOptions {
+        BinaryOptions {
+            format: format.unwrap_or(self.binary_format),
+            preview_bytes: self.binary_preview_bytes,
         }
     }
 }
@@ -632,7 +1759,28 @@ impl Default for SessionConfig {
         Self {
             max_sessions: 10,
             cleanup_interval: DEFAULT_CLEANUP_INTERVAL,
-            result_retention: Duration::from_secs(3600),
+            pinned_idle_timeout: Duration::from_secs(DEFAULT_PINNED_SESSION_IDLE_TIMEOUT_SECS),
+            pinned_max_lifetime: Duration::ZERO,
+            max_pinned_sessions: 10,
+            result_retention: Duration::from_secs(DEFAULT_RESULT_RETENTION_SECS),
+            max_finished_sessions: DEFAULT_MAX_FINISHED_SESSIONS,
+            session_memory_mb: DEFAULT_SESSION_MEMORY_MB,
+            schema_poll_interval: Duration::from_secs(DEFAULT_SCHEMA_POLL_INTERVAL_SECS),
+            table_size_history_path: None,
+            session_store_dir: None,
+            session_store_max_mb: DEFAULT_SESSION_STORE_MAX_MB,
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            log_query_text: false,
+            max_entries: DEFAULT_AUDIT_MAX_ENTRIES,
+            file: None,
+            table: None,
         }
     }
 }
@@ -666,10 +1814,269 @@ impl Default for TimeoutsConfig {
 mod tests {
     use super::*;
 
+    /// A settings lookup with SQL Server credentials plus `extra`.
+    fn lookup_with(
+        extra: &'static [(&'static str, &'static str)],
+    ) -> impl Fn(&str) -> Result<String, std::env::VarError> {
+        move |name| {
+            [
+                ("MSSQL_HOST", "db"),
+                ("MSSQL_USER", "sa"),
+                ("MSSQL_PASSWORD", "x"),
+            ]
+            .iter()
+            .chain(extra)
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+            .ok_or(std::env::VarError::NotPresent)
+        }
+    }
+
     #[test]
     fn test_pool_config_defaults() {
         let config = PoolConfig::default();
         assert_eq!(config.min_connections, 1);
         assert_eq!(config.max_connections, 10);
+        assert_eq!(config.max_lifetime, Duration::from_secs(1800));
+        assert_eq!(config.health_check_interval, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_tool_filtering() {
+        let mut security = SecurityConfig {
+            disabled_tools: vec!["bulk_insert".to_string(), "Switch_Database".to_string()],
+            ..SecurityConfig::default()
+        };
+        assert!(security.tool_disabled_reason("bulk_insert").is_some());
+        assert!(security.tool_disabled_reason("switch_database").is_some());
+        assert!(security.tool_disabled_reason("execute_query").is_none());
+
+        security.enabled_tools = vec!["execute_query".to_string(), "bulk_insert".to_string()];
+        assert!(security.tool_disabled_reason("execute_query").is_none());
+        assert!(security.tool_disabled_reason("bulk_insert").is_some());
+        assert!(security.tool_disabled_reason("health_check").is_some());
+    }
+
+    #[test]
+    fn test_unknown_tool_names() {
+        let lookup = |disabled: &'static str| {
+            move |name: &str| match name {
+                "MSSQL_HOST" => Ok("db".to_string()),
+                "MSSQL_USER" => Ok("sa".to_string()),
+                "MSSQL_PASSWORD" => Ok("x".to_string()),
+                "MSSQL_DISABLED_TOOLS" => Ok(disabled.to_string()),
+                _ => Err(std::env::VarError::NotPresent),
+            }
+        };
+
+        let config = Config::from_lookup(lookup("bulk_insert, Switch_Database")).unwrap();
+        assert_eq!(config.security.disabled_tools.len(), 2);
+        let err = Config::from_lookup(lookup("bulk_insert,bulk_insrt")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'bulk_insrt' in MSSQL_DISABLED_TOOLS"));
+    }
+
+    #[test]
+    fn test_config_file_settings() {
+        let toml_value: serde_json::Value = toml::from_str(
+            r#"
+            host = "db.example.com"
+
+            [security]
+            validation_mode = "readonly"
+            max_rows = 500
+            export_dirs = ["/tmp/a", "/tmp/b"]
+            "#,
+        )
+        .unwrap();
+        let yaml_value: serde_json::Value = serde_yaml_ng::from_str(concat!(
+            "host: db.example.com\n",
+            "security:\n",
+            "  validation_mode: readonly\n",
+            "  max_rows: 500\n",
+            "  export_dirs: [/tmp/a, /tmp/b]\n",
+        ))
+        .unwrap();
+
+        for value in [toml_value, yaml_value] {
+            let mut settings = HashMap::new();
+            flatten_settings(&value, &mut settings).unwrap();
+            assert_eq!(settings["MSSQL_HOST"], "db.example.com");
+            assert_eq!(settings["MSSQL_VALIDATION_MODE"], "readonly");
+            assert_eq!(settings["MSSQL_MAX_ROWS"], "500");
+            assert_eq!(settings["MSSQL_EXPORT_DIRS"], "/tmp/a,/tmp/b");
+        }
+
+        let duplicate = serde_json::json!({ "max_rows": 1, "security": { "MAX_ROWS": 2 } });
+        assert!(flatten_settings(&duplicate, &mut HashMap::new()).is_err());
+
+        let settings: HashMap<String, String> = [
+            ("MSSQL_HOST", "db"),
+            ("MSSQL_USER", "sa"),
+            ("MSSQL_PASSWORD", "secret"),
+            ("MSSQL_MAX_ROWS", "500"),
+            ("MSSQL_MAX_SESSIONS", "4"),
+            ("MSSQL_SESSION_MAX_LIFETIME", "7200"),
+            ("MSSQL_READ_POOL_HOST", "replica:1450"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let config = Config::from_lookup(|name| {
+            settings
+                .get(name)
+                .cloned()
+                .ok_or(std::env::VarError::NotPresent)
+        })
+        .unwrap();
+        assert_eq!(config.database.host, "db");
+        assert_eq!(config.security.max_result_rows, 500);
+        assert_eq!(config.session.max_pinned_sessions, 4);
+        assert_eq!(
+            config.session.pinned_max_lifetime,
+            Duration::from_secs(7200)
+        );
+        assert_eq!(
+            config.session.pinned_idle_timeout,
+            Duration::from_secs(3600)
+        );
+        assert!(config.database.read_pool);
+        assert_eq!(
+            config.database.read_pool_host.as_deref(),
+            Some("replica:1450")
+        );
+
+        let mut reloaded = config.clone();
+        reloaded.database.host = "other".to_string();
+        reloaded.security.max_result_rows = 50;
+        reloaded.security.rate_limit.queries_per_minute = Some(10);
+        let merged = config.with_runtime_settings(reloaded);
+        assert_eq!(merged.database.host, "db");
+        assert_eq!(merged.security.rate_limit.queries_per_minute, None);
+        assert_eq!(
+            config.changed_settings(&merged),
+            vec!["security.max_result_rows"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_tls_settings() {
+        const CA_FILE: &[(&str, &str)] = &[("MSSQL_TLS_CA_FILE", "/etc/ssl/corp-ca.pem")];
+        let err = Config::from_lookup(lookup_with(CA_FILE)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("MSSQL_TLS_CA_FILE is not supported"));
+        const HOSTNAME: &[(&str, &str)] = &[("MSSQL_TLS_HOSTNAME", "db.corp.example.com")];
+        assert!(Config::from_lookup(lookup_with(HOSTNAME)).is_err());
+        const CLIENT_KEY: &[(&str, &str)] = &[("MSSQL_TLS_CLIENT_KEY", "/etc/mcp/client.key")];
+        assert!(Config::from_lookup(lookup_with(CLIENT_KEY)).is_err());
+    }
+
+    #[test]
+    fn test_password_file() {
+        let path = std::env::temp_dir().join(format!("password_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "s3cret\n").unwrap();
+        let file = path.to_string_lossy().to_string();
+        let lookup = |password: Option<&'static str>| {
+            let file = file.clone();
+            move |name: &str| match name {
+                "MSSQL_HOST" => Ok("10.0.0.5".to_string()),
+                "MSSQL_USER" => Ok("app".to_string()),
+                "MSSQL_PASSWORD_FILE" => Ok(file.clone()),
+                "MSSQL_CREDENTIAL_CHECK_INTERVAL" => Ok("60".to_string()),
+                "MSSQL_PASSWORD" => password
+                    .map(str::to_string)
+                    .ok_or(std::env::VarError::NotPresent),
+                _ => Err(std::env::VarError::NotPresent),
+            }
+        };
+
+        let config = Config::from_lookup(lookup(None)).unwrap();
+        assert!(matches!(
+            &config.database.auth,
+            AuthConfig::SqlServer { password, .. } if password == "s3cret"
+        ));
+        assert_eq!(
+            config.database.pool.credential_check_interval,
+            Duration::from_secs(60)
+        );
+        let config = Config::from_lookup(lookup(Some("override"))).unwrap();
+        assert!(matches!(
+            &config.database.auth,
+            AuthConfig::SqlServer { password, .. } if password == "override"
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(Config::from_lookup(lookup(None)).is_err());
+    }
+
+    #[test]
+    fn test_secrets_provider() {
+        let path = std::env::temp_dir().join(format!("secrets_{}.env", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "username=app\npassword=from-file\n").unwrap();
+        let file = path.to_string_lossy().to_string();
+        let lookup = |password: Option<&'static str>| {
+            let file = file.clone();
+            move |name: &str| match name {
+                "MSSQL_HOST" => Ok("10.0.0.5".to_string()),
+                "MSSQL_SECRETS_PROVIDER" => Ok("env-file".to_string()),
+                "MSSQL_SECRETS_FILE" => Ok(file.clone()),
+                "MSSQL_PASSWORD" => password
+                    .map(str::to_string)
+                    .ok_or(std::env::VarError::NotPresent),
+                _ => Err(std::env::VarError::NotPresent),
+            }
+        };
+
+        let config = Config::from_lookup(lookup(None)).unwrap();
+        assert_eq!(
+            config.database.auth,
+            AuthConfig::SqlServer {
+                username: "app".to_string(),
+                password: "from-file".to_string(),
+            }
+        );
+        assert_eq!(
+            config.database.pool.credential_check_interval,
+            Duration::from_secs(DEFAULT_SECRETS_REFRESH_SECS)
+        );
+        // Settings made directly take precedence over the provider
+        let config = Config::from_lookup(lookup(Some("direct"))).unwrap();
+        assert!(matches!(
+            &config.database.auth,
+            AuthConfig::SqlServer { password, .. } if password == "direct"
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_column_encryption() {
+        const ENABLED: &[(&str, &str)] = &[("MSSQL_COLUMN_ENCRYPTION", "true")];
+        let err = Config::from_lookup(lookup_with(ENABLED)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("MSSQL_COLUMN_ENCRYPTION is not supported"));
+        const CERTIFICATE: &[(&str, &str)] = &[("MSSQL_AE_CERTIFICATE", "/etc/mcp/cmk.pem")];
+        assert!(Config::from_lookup(lookup_with(CERTIFICATE)).is_err());
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = parse_limits(
+            "MSSQL_TOOL_CONCURRENCY",
+            Some(" bulk_insert=2, execute_query = 4,".to_string()),
+        )
+        .unwrap();
+        assert_eq!(limits["bulk_insert"], 2);
+        assert_eq!(limits["execute_query"], 4);
+        assert!(parse_limits("MSSQL_TOOL_CONCURRENCY", None)
+            .unwrap()
+            .is_empty());
+
+        for invalid in ["bulk_insert", "bulk_insert=0", "=2", "bulk_insert=x"] {
+            assert!(parse_limits("MSSQL_TOOL_CONCURRENCY", Some(invalid.to_string())).is_err());
+        }
     }
 }
@@ -0,0 +1,432 @@
+//! Connection string parsing.
+//!
+//! Accepts ADO.NET (`Server=tcp:host,1433;Database=db;User Id=sa;...`), ODBC
+//! (`Driver={...};Server=host;Uid=sa;Pwd={...}`) and JDBC
+//! (`jdbc:sqlserver://host\instance:1433;databaseName=db;...`) connection strings
+//! and translates them into the environment-style settings read by [`Config`].
+//!
+//! [`Config`]: super::Config
+
+use super::ApplicationIntent;
+use crate::error::ServerError;
+use std::collections::HashMap;
+
+/// Keywords that are accepted but have no effect on this server.
+const IGNORED_KEYWORDS: &[&str] = &[
+    "driver",
+    "packetsize",
+    "persistsecurityinfo",
+    "pooling",
+    "provider",
+    "workstationid",
+    "wsid",
+];
+
+/// Parse a connection string into settings keyed by environment variable name.
+pub fn parse_connection_string(input: &str) -> Result<HashMap<String, String>, ServerError> {
+    let input = input.trim();
+    let mut settings = HashMap::new();
+
+    let pairs = match strip_prefix_ignore_case(input, "jdbc:sqlserver://") {
+        Some(rest) => {
+            let (server, properties) = rest.split_once(';').unwrap_or((rest, ""));
+            if !server.is_empty() {
+                apply_jdbc_server(server, &mut settings)?;
+            }
+            split_pairs(properties)?
+        }
+        None => split_pairs(input)?,
+    };
+
+    let mut azure_principal = false;
+    for (key, value) in pairs {
+        let keyword = normalize_keyword(&key);
+        match keyword.as_str() {
+            "server" | "datasource" | "address" | "addr" | "networkaddress" => {
+                apply_server(&value, &mut settings)?
+            }
+            "servername" => apply_jdbc_server(&value, &mut settings)?,
+            "instancename" => set(&mut settings, "MSSQL_INSTANCE", value),
+            "port" | "portnumber" => set(
+                &mut settings,
+                "MSSQL_PORT",
+                number(&key, &value)?.to_string(),
+            ),
+            "app" | "applicationname" => set(&mut settings, "MSSQL_APPLICATION_NAME", value),
+            "database" | "initialcatalog" | "databasename" => {
+                set(&mut settings, "MSSQL_DATABASE", value)
+            }
+            "userid" | "uid" | "user" | "username" => set(&mut settings, "MSSQL_USER", value),
+            "password" | "pwd" => set(&mut settings, "MSSQL_PASSWORD", value),
+            "encrypt" => {
+                let encrypt = match value.trim().to_ascii_lowercase().as_str() {
+                    "mandatory" | "strict" => true,
+                    "optional" => false,
+                    _ => boolean(&key, &value)?,
+                };
+                set(&mut settings, "MSSQL_ENCRYPT", encrypt.to_string())
+            }
+            "trustservercertificate" => set(
+                &mut settings,
+                "MSSQL_TRUST_CERT",
+                boolean(&key, &value)?.to_string(),
+            ),
+            "connecttimeout" | "connectiontimeout" | "timeout" | "logintimeout" => set(
+                &mut settings,
+                "MSSQL_CONNECT_TIMEOUT",
+                number(&key, &value)?.to_string(),
+            ),
+            "multipleactiveresultsets" | "marsconnection" => set(
+                &mut settings,
+                "MSSQL_MARS",
+                boolean(&key, &value)?.to_string(),
+            ),
+            "minpoolsize" => set(
+                &mut settings,
+                "MSSQL_POOL_MIN",
+                number(&key, &value)?.to_string(),
+            ),
+            "maxpoolsize" => set(
+                &mut settings,
+                "MSSQL_POOL_MAX",
+                number(&key, &value)?.to_string(),
+            ),
+            "applicationintent" => {
+                let intent = ApplicationIntent::from_str(&value).ok_or_else(|| {
+                    ServerError::config(format!(
+                        "Invalid value '{}' for connection string keyword '{}': \
+                         expected ReadWrite or ReadOnly",
+                        value.trim(),
+                        key.trim()
+                    ))
+                })?;
+                set(
+                    &mut settings,
+                    "MSSQL_APPLICATION_INTENT",
+                    intent.as_str().to_string(),
+                )
+            }
+            "columnencryptionsetting" | "columnencryption" => {
+                let enabled = match value.trim().to_ascii_lowercase().as_str() {
+                    "enabled" => true,
+                    "disabled" => false,
+                    _ => boolean(&key, &value)?,
+                };
+                if enabled {
+                    return Err(ServerError::config(format!(
+                        "Unsupported connection string keyword '{}': \
+                         Always Encrypted is not supported",
+                        key.trim()
+                    )));
+                }
+            }
+            "multisubnetfailover" => set(
+                &mut settings,
+                "MSSQL_MULTI_SUBNET_FAILOVER",
+                boolean(&key, &value)?.to_string(),
+            ),
+            "failoverpartner" => {
+                // ADO.NET writes the partner port after a comma, like Server
+                let partner = value.trim();
+                let partner = strip_prefix_ignore_case(partner, "tcp:").unwrap_or(partner);
+                let partner = match partner.rsplit_once(',') {
+                    Some((host, port)) => format!("{}:{}", host, number(&key, port)?),
+                    None => partner.to_string(),
+                };
+                set(&mut settings, "MSSQL_FAILOVER_PARTNERS", partner)
+            }
+            "authentication" => match normalize_keyword(&value).as_str() {
+                "sqlpassword" => {}
+                "activedirectoryserviceprincipal" => azure_principal = true,
+                _ => {
+                    return Err(ServerError::config(format!(
+                        "Unsupported connection string authentication '{}': \
+                         use SqlPassword or ActiveDirectoryServicePrincipal",
+                        value.trim()
+                    )))
+                }
+            },
+            "integratedsecurity" | "trustedconnection" => {
+                let integrated =
+                    value.trim().eq_ignore_ascii_case("sspi") || boolean(&key, &value)?;
+                if integrated {
+                    return Err(ServerError::config(format!(
+                        "Unsupported connection string keyword '{}': \
+                         Windows integrated authentication is not supported",
+                        key.trim()
+                    )));
+                }
+            }
+            k if IGNORED_KEYWORDS.contains(&k) => {}
+            _ => {
+                return Err(ServerError::config(format!(
+                    "Unsupported connection string keyword '{}'",
+                    key.trim()
+                )))
+            }
+        }
+    }
+
+    // A service principal signs in with its client ID and secret
+    if azure_principal {
+        settings.insert("MSSQL_AUTH_TYPE".to_string(), "azuread".to_string());
+        if let Some(user) = settings.remove("MSSQL_USER") {
+            settings.insert("MSSQL_AZURE_CLIENT_ID".to_string(), user);
+        }
+        if let Some(password) = settings.remove("MSSQL_PASSWORD") {
+            settings.insert("MSSQL_AZURE_CLIENT_SECRET".to_string(), password);
+        }
+    }
+
+    Ok(settings)
+}
+
+fn set(settings: &mut HashMap<String, String>, name: &str, value: String) {
+    settings.insert(name.to_string(), value);
+}
+
+/// Lowercase a keyword and drop spaces and underscores (`User ID` -> `userid`).
+fn normalize_keyword(key: &str) -> String {
+    key.chars()
+        .filter(|c| !c.is_whitespace() && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|head| head.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+fn boolean(key: &str, value: &str) -> Result<bool, ServerError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(ServerError::config(format!(
+            "Invalid value '{}' for connection string keyword '{}': expected true or false",
+            value.trim(),
+            key.trim()
+        ))),
+    }
+}
+
+fn number(key: &str, value: &str) -> Result<u64, ServerError> {
+    value.trim().parse().map_err(|_| {
+        ServerError::config(format!(
+            "Invalid value '{}' for connection string keyword '{}': expected a number",
+            value.trim(),
+            key.trim()
+        ))
+    })
+}
+
+/// Apply an ADO.NET/ODBC server value: `[tcp:]host[\instance][,port]`.
+fn apply_server(value: &str, settings: &mut HashMap<String, String>) -> Result<(), ServerError> {
+    let value = value.trim();
+    let value = match value.split_once(':') {
+        Some((protocol, rest)) if protocol.eq_ignore_ascii_case("tcp") => rest,
+        Some((protocol, _))
+            if ["np", "lpc", "admin"].contains(&protocol.to_lowercase().as_str()) =>
+        {
+            return Err(ServerError::config(format!(
+                "Unsupported connection string protocol '{protocol}': only TCP is supported"
+            )))
+        }
+        _ => value,
+    };
+
+    let (address, port) = match value.rsplit_once(',') {
+        Some((address, port)) => (address, Some(number("Server", port)?)),
+        None => (value, None),
+    };
+    apply_host(address, settings);
+    if let Some(port) = port {
+        set(settings, "MSSQL_PORT", port.to_string());
+    }
+    Ok(())
+}
+
+/// Apply a JDBC server value: `host[\instance][:port]`.
+fn apply_jdbc_server(
+    value: &str,
+    settings: &mut HashMap<String, String>,
+) -> Result<(), ServerError> {
+    let value = value.trim();
+    // Bracketed IPv6 addresses contain colons of their own
+    let port_separator = match value.rfind(']') {
+        Some(end) => value[end..].rfind(':').map(|i| end + i),
+        None => value.rfind(':'),
+    };
+    let (address, port) = match port_separator {
+        Some(i) => (&value[..i], Some(number("portNumber", &value[i + 1..])?)),
+        None => (value, None),
+    };
+    apply_host(address, settings);
+    if let Some(port) = port {
+        set(settings, "MSSQL_PORT", port.to_string());
+    }
+    Ok(())
+}
+
+/// Apply `host[\instance]`, mapping local aliases to `localhost`.
+fn apply_host(address: &str, settings: &mut HashMap<String, String>) {
+    let (host, instance) = match address.split_once('\\') {
+        Some((host, instance)) => (host.trim(), Some(instance.trim())),
+        None => (address.trim(), None),
+    };
+    let host = match host {
+        "." | "(local)" | "(localdb)" => "localhost",
+        h => h.trim_start_matches('[').trim_end_matches(']'),
+    };
+    set(settings, "MSSQL_HOST", host.to_string());
+    if let Some(instance) = instance.filter(|i| !i.is_empty()) {
+        set(settings, "MSSQL_INSTANCE", instance.to_string());
+    }
+}
+
+/// Split `key=value;key=value` pairs, honoring `{...}` and quoted values.
+fn split_pairs(input: &str) -> Result<Vec<(String, String)>, ServerError> {
+    let mut pairs = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        // Key: up to '=', skipping empty segments
+        let mut key = String::new();
+        for c in chars.by_ref() {
+            if c == '=' {
+                break;
+            }
+            if c == ';' {
+                if !key.trim().is_empty() {
+                    return Err(ServerError::config(format!(
+                        "Connection string keyword '{}' has no value",
+                        key.trim()
+                    )));
+                }
+                key.clear();
+                continue;
+            }
+            key.push(c);
+        }
+        if key.trim().is_empty() {
+            break;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut value = String::new();
+        match chars.peek().copied() {
+            Some(open @ ('{' | '"' | '\'')) => {
+                let close = if open == '{' { '}' } else { open };
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    if c == close {
+                        // A doubled closing character is an escaped literal
+                        if chars.next_if_eq(&close).is_some() {
+                            value.push(close);
+                            continue;
+                        }
+                        closed = true;
+                        break;
+                    }
+                    value.push(c);
+                }
+                if !closed {
+                    return Err(ServerError::config(format!(
+                        "Unterminated value for connection string keyword '{}'",
+                        key.trim()
+                    )));
+                }
+                // Skip to the end of the pair
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                    value.push(c);
+                }
+                value = value.trim().to_string();
+            }
+        }
+
+        pairs.push((key.trim().to_string(), value));
+    }
+
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ado_odbc_and_jdbc() {
+        let ado = parse_connection_string(
+            "Server=tcp:db.example.com\\SQLEXPRESS,1444;Initial Catalog=Sales;\
+             User ID=app;Password='it''s;secret';Encrypt=Mandatory;\
+             TrustServerCertificate=False;Connect Timeout=15;Application Name=x",
+        )
+        .unwrap();
+        assert_eq!(ado["MSSQL_HOST"], "db.example.com");
+        assert_eq!(ado["MSSQL_INSTANCE"], "SQLEXPRESS");
+        assert_eq!(ado["MSSQL_PORT"], "1444");
+        assert_eq!(ado["MSSQL_DATABASE"], "Sales");
+        assert_eq!(ado["MSSQL_PASSWORD"], "it's;secret");
+        assert_eq!(ado["MSSQL_ENCRYPT"], "true");
+        assert_eq!(ado["MSSQL_TRUST_CERT"], "false");
+        assert_eq!(ado["MSSQL_CONNECT_TIMEOUT"], "15");
+        assert_eq!(ado["MSSQL_APPLICATION_NAME"], "x");
+
+        let odbc = parse_connection_string(
+            "Driver={ODBC Driver 18 for SQL Server};Server=(local);Database=master;\
+             Uid=sa;Pwd={p@ss}};word};Encrypt=no;ColumnEncryption=Disabled;",
+        )
+        .unwrap();
+        assert_eq!(odbc["MSSQL_HOST"], "localhost");
+        assert_eq!(odbc["MSSQL_PASSWORD"], "p@ss};word");
+        assert_eq!(odbc["MSSQL_ENCRYPT"], "false");
+        assert!(!odbc.contains_key("MSSQL_COLUMN_ENCRYPTION"));
+
+        let ag = parse_connection_string(
+            "Server=tcp:aglistener,1433;ApplicationIntent=ReadOnly;\
+             MultiSubnetFailover=True;Failover Partner=sql02,1450",
+        )
+        .unwrap();
+        assert_eq!(ag["MSSQL_APPLICATION_INTENT"], "ReadOnly");
+        assert_eq!(ag["MSSQL_MULTI_SUBNET_FAILOVER"], "true");
+        assert_eq!(ag["MSSQL_FAILOVER_PARTNERS"], "sql02:1450");
+
+        let jdbc = parse_connection_string(
+            "jdbc:sqlserver://sql01:1500;databaseName=Hr;user=svc;password=pw;\
+             authentication=ActiveDirectoryServicePrincipal",
+        )
+        .unwrap();
+        assert_eq!(jdbc["MSSQL_HOST"], "sql01");
+        assert_eq!(jdbc["MSSQL_PORT"], "1500");
+        assert_eq!(jdbc["MSSQL_AUTH_TYPE"], "azuread");
+        assert_eq!(jdbc["MSSQL_AZURE_CLIENT_ID"], "svc");
+        assert!(!jdbc.contains_key("MSSQL_USER"));
+    }
+
+    #[test]
+    fn test_unsupported_keywords() {
+        let err = parse_connection_string("Server=db;Frobnicate=1").unwrap_err();
+        assert!(err.to_string().contains("Frobnicate"));
+        assert!(parse_connection_string("Server=db;HostNameInCertificate=db.corp").is_err());
+        assert!(parse_connection_string("Server=db;Integrated Security=SSPI").is_err());
+        assert!(parse_connection_string("Server=db;Column Encryption Setting=Enabled").is_err());
+        assert!(parse_connection_string("Server=np:\\\\db\\pipe\\sql\\query").is_err());
+        assert!(parse_connection_string("Server=db;Encrypt=maybe").is_err());
+        assert!(parse_connection_string("Server=db;Password={open").is_err());
+        assert!(parse_connection_string("Server=db;Integrated Security=false").is_ok());
+    }
+}
@@ -0,0 +1,514 @@
+//! External secrets providers.
+//!
+//! A [`SecretsProvider`] supplies settings such as `MSSQL_PASSWORD` from outside
+//! the environment: a dotenv-style file, AWS Secrets Manager, Azure Key Vault
+//! or HashiCorp Vault, chosen with `MSSQL_SECRETS_PROVIDER`. Secrets are fetched
+//! every time the configuration is loaded, so the periodic credential check
+//! refreshes them and a rotated password takes effect without a restart.
+//!
+//! A secret holding a JSON object provides one setting per key: `password`,
+//! `username`, `host`, `port` and `dbname` map to the matching `MSSQL_*`
+//! settings (the layout of RDS-style secrets), other keys are upper-cased and
+//! given the `MSSQL_` prefix unless they have it. Any other secret is the
+//! password.
+
+use crate::constants::SECRETS_HTTP_TIMEOUT_SECS;
+use crate::error::ServerError;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Source of secret settings, keyed by environment variable name.
+pub trait SecretsProvider: Send + Sync {
+    /// Provider name used in logs and errors.
+    fn name(&self) -> &'static str;
+
+    /// Fetch the current secret settings.
+    ///
+    /// Blocks until the provider answers; call it off the async runtime.
+    fn fetch(&self) -> Result<HashMap<String, String>, ServerError>;
+}
+
+/// Create the provider named by `MSSQL_SECRETS_PROVIDER`, if any.
+///
+/// `var` looks up the provider's settings, which never come from the
+/// provider itself.
+pub fn secrets_provider<F>(var: F) -> Result<Option<Box<dyn SecretsProvider>>, ServerError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let required = |name: &str, provider: &str| {
+        var(name).filter(|v| !v.trim().is_empty()).ok_or_else(|| {
+            ServerError::config(format!(
+                "{name} is required for the {provider} secrets provider"
+            ))
+        })
+    };
+
+    let Some(kind) = var("MSSQL_SECRETS_PROVIDER").filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let provider: Box<dyn SecretsProvider> = match kind.trim().to_lowercase().as_str() {
+        "env-file" | "envfile" | "file" | "dotenv" => Box::new(EnvFileProvider {
+            path: PathBuf::from(required("MSSQL_SECRETS_FILE", "env-file")?),
+        }),
+        "aws" | "aws-secrets-manager" | "secretsmanager" => {
+            let region = var("MSSQL_SECRETS_AWS_REGION")
+                .or_else(|| var("AWS_REGION"))
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .ok_or_else(|| {
+                    ServerError::config(
+                        "MSSQL_SECRETS_AWS_REGION or AWS_REGION is required for the aws secrets provider",
+                    )
+                })?;
+            Box::new(AwsSecretsManager {
+                secret_id: required("MSSQL_SECRETS_AWS_SECRET_ID", "aws")?,
+                region,
+                access_key_id: required("AWS_ACCESS_KEY_ID", "aws")?,
+                secret_access_key: required("AWS_SECRET_ACCESS_KEY", "aws")?,
+                session_token: var("AWS_SESSION_TOKEN").filter(|v| !v.is_empty()),
+            })
+        }
+        "azure" | "azure-key-vault" | "akv" | "keyvault" => Box::new(AzureKeyVault {
+            vault_url: required("MSSQL_SECRETS_AZURE_VAULT_URL", "azure")?
+                .trim_end_matches('/')
+                .to_string(),
+            secret_name: required("MSSQL_SECRETS_AZURE_SECRET", "azure")?,
+            tenant_id: required("MSSQL_AZURE_TENANT_ID", "azure")?,
+            client_id: required("MSSQL_AZURE_CLIENT_ID", "azure")?,
+            client_secret: required("MSSQL_AZURE_CLIENT_SECRET", "azure")?,
+        }),
+        "vault" | "hashicorp" | "hashicorp-vault" => Box::new(HashiCorpVault {
+            address: required("VAULT_ADDR", "vault")?
+                .trim_end_matches('/')
+                .to_string(),
+            token: required("VAULT_TOKEN", "vault")?,
+            path: required("MSSQL_SECRETS_VAULT_PATH", "vault")?
+                .trim_matches('/')
+                .to_string(),
+            namespace: var("VAULT_NAMESPACE").filter(|v| !v.is_empty()),
+        }),
+        other => {
+            return Err(ServerError::config(format!(
+                "Unknown MSSQL_SECRETS_PROVIDER '{other}': expected env-file, aws, azure or vault"
+            )))
+        }
+    };
+    Ok(Some(provider))
+}
+
+/// Settings from a dotenv-style file of `KEY=value` lines.
+pub struct EnvFileProvider {
+    path: PathBuf,
+}
+
+impl SecretsProvider for EnvFileProvider {
+    fn name(&self) -> &'static str {
+        "env-file"
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, String>, ServerError> {
+        let text = std::fs::read_to_string(&self.path).map_err(|e| {
+            ServerError::config(format!(
+                "Failed to read secrets file {}: {e}",
+                self.path.display()
+            ))
+        })?;
+        Ok(parse_env_file(&text))
+    }
+}
+
+/// A secret of AWS Secrets Manager, read with the `AWS_*` access keys.
+pub struct AwsSecretsManager {
+    secret_id: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl SecretsProvider for AwsSecretsManager {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, String>, ServerError> {
+        let host = format!("secretsmanager.{}.amazonaws.com", self.region);
+        let body = serde_json::json!({ "SecretId": self.secret_id }).to_string();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", "secretsmanager.GetSecretValue".to_string()));
+        let authorization = sigv4_authorization(
+            &SigningKey {
+                access_key_id: &self.access_key_id,
+                secret_access_key: &self.secret_access_key,
+                region: &self.region,
+                service: "secretsmanager",
+            },
+            &amz_date,
+            &headers,
+            &body,
+        );
+
+        let mut request = http_agent()
+            .post(format!("https://{host}/"))
+            .header("Authorization", authorization);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+        let response = read_json(self.name(), request.send(body.as_str()))?;
+        match response.get("SecretString").and_then(|v| v.as_str()) {
+            Some(secret) => Ok(parse_secret(secret)),
+            None => Err(ServerError::config(format!(
+                "AWS secret {} has no SecretString; binary secrets are not supported",
+                self.secret_id
+            ))),
+        }
+    }
+}
+
+/// A secret of Azure Key Vault, read with the `MSSQL_AZURE_*` service principal.
+pub struct AzureKeyVault {
+    vault_url: String,
+    secret_name: String,
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+}
+
+impl SecretsProvider for AzureKeyVault {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, String>, ServerError> {
+        let agent = http_agent();
+        let token = read_json(
+            self.name(),
+            agent
+                .post(format!(
+                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                    self.tenant_id
+                ))
+                .send_form([
+                    ("grant_type", "client_credentials"),
+                    ("client_id", self.client_id.as_str()),
+                    ("client_secret", self.client_secret.as_str()),
+                    ("scope", "https://vault.azure.net/.default"),
+                ]),
+        )?;
+        let token = token
+            .get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ServerError::config("Azure AD token response has no access_token"))?;
+
+        let secret = read_json(
+            self.name(),
+            agent
+                .get(format!(
+                    "{}/secrets/{}?api-version=7.4",
+                    self.vault_url, self.secret_name
+                ))
+                .header("Authorization", format!("Bearer {token}"))
+                .call(),
+        )?;
+        match secret.get("value").and_then(|v| v.as_str()) {
+            Some(value) => Ok(parse_secret(value)),
+            None => Err(ServerError::config(format!(
+                "Key Vault secret {} has no value",
+                self.secret_name
+            ))),
+        }
+    }
+}
+
+/// A secret of HashiCorp Vault (KV version 1 or 2), read with `VAULT_TOKEN`.
+pub struct HashiCorpVault {
+    address: String,
+    token: String,
+    path: String,
+    namespace: Option<String>,
+}
+
+impl SecretsProvider for HashiCorpVault {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, String>, ServerError> {
+        let mut request = http_agent()
+            .get(format!("{}/v1/{}", self.address, self.path))
+            .header("X-Vault-Token", &self.token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = read_json(self.name(), request.call())?;
+
+        // KV version 2 nests the secret under data.data
+        let data = response.get("data");
+        let data = data
+            .and_then(|d| d.get("data"))
+            .filter(|d| d.is_object())
+            .or(data);
+        match data.and_then(|d| d.as_object()) {
+            Some(object) => Ok(object_settings(object)),
+            None => Err(ServerError::config(format!(
+                "Vault path {} holds no secret data",
+                self.path
+            ))),
+        }
+    }
+}
+
+/// HTTP client for provider requests.
+fn http_agent() -> ureq::Agent {
+    ureq::Agent::new_with_config(
+        ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(SECRETS_HTTP_TIMEOUT_SECS)))
+            .build(),
+    )
+}
+
+/// Read the JSON body of a provider response.
+fn read_json(
+    provider: &str,
+    response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<serde_json::Value, ServerError> {
+    let error = |e: String| ServerError::config(format!("{provider} secrets provider: {e}"));
+    let body = response
+        .and_then(|mut r| r.body_mut().read_to_string())
+        .map_err(|e| error(e.to_string()))?;
+    serde_json::from_str(&body).map_err(|e| error(format!("invalid response: {e}")))
+}
+
+/// Parse a secret value: a JSON object of settings, or else the password.
+fn parse_secret(secret: &str) -> HashMap<String, String> {
+    match serde_json::from_str::<serde_json::Value>(secret) {
+        Ok(serde_json::Value::Object(object)) => object_settings(&object),
+        _ => HashMap::from([(
+            "MSSQL_PASSWORD".to_string(),
+            secret.trim_end_matches(['\r', '\n']).to_string(),
+        )]),
+    }
+}
+
+/// Settings from the string, number and boolean members of a JSON object.
+fn object_settings(object: &serde_json::Map<String, serde_json::Value>) -> HashMap<String, String> {
+    object
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            Some((setting_name(key), value))
+        })
+        .collect()
+}
+
+/// Parse `KEY=value` lines, skipping blank lines and `#` comments.
+fn parse_env_file(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = [('"', '"'), ('\'', '\'')]
+                .iter()
+                .find_map(|(open, close)| {
+                    value
+                        .strip_prefix(*open)
+                        .and_then(|v| v.strip_suffix(*close))
+                })
+                .unwrap_or(value);
+            Some((setting_name(key), value.to_string()))
+        })
+        .collect()
+}
+
+/// Environment variable name for a secret key.
+fn setting_name(key: &str) -> String {
+    let key = key.trim().to_ascii_uppercase().replace(['-', '.'], "_");
+    match key.as_str() {
+        "USERNAME" | "USER" => "MSSQL_USER".to_string(),
+        "PASSWORD" => "MSSQL_PASSWORD".to_string(),
+        "HOST" => "MSSQL_HOST".to_string(),
+        "PORT" => "MSSQL_PORT".to_string(),
+        "DBNAME" | "DATABASE" => "MSSQL_DATABASE".to_string(),
+        _ if key.starts_with("MSSQL_") => key,
+        _ => format!("MSSQL_{key}"),
+    }
+}
+
+/// Credentials and scope of an AWS Signature Version 4 signature.
+struct SigningKey<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+}
+
+/// `Authorization` header of a `POST /` request signed with AWS Signature Version 4.
+///
+/// `headers` are the signed headers, lowercase and sorted by name.
+fn sigv4_authorization(
+    key: &SigningKey<'_>,
+    amz_date: &str,
+    headers: &[(&str, String)],
+    body: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{:x}",
+        Sha256::digest(body.as_bytes())
+    );
+    let scope = format!("{date}/{}/{}/aws4_request", key.region, key.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{:x}",
+        Sha256::digest(canonical_request.as_bytes())
+    );
+
+    let signing_key = signing_key(key, date);
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        key.access_key_id
+    )
+}
+
+/// Derive the Signature Version 4 signing key for `date` (`YYYYMMDD`).
+fn signing_key(key: &SigningKey<'_>, date: &str) -> [u8; 32] {
+    let secret = format!("AWS4{}", key.secret_access_key);
+    let k_date = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, key.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, key.service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_file() {
+        let settings = parse_env_file(
+            "# rotated nightly\nexport MSSQL_PASSWORD=\"p@ss=word\"\n\nusername = app\nport=1450\n",
+        );
+        assert_eq!(settings["MSSQL_PASSWORD"], "p@ss=word");
+        assert_eq!(settings["MSSQL_USER"], "app");
+        assert_eq!(settings["MSSQL_PORT"], "1450");
+        assert_eq!(settings.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_secret() {
+        let settings = parse_secret(
+            r#"{"username":"app","password":"s3cret","port":1433,"engine":"sqlserver"}"#,
+        );
+        assert_eq!(settings["MSSQL_USER"], "app");
+        assert_eq!(settings["MSSQL_PASSWORD"], "s3cret");
+        assert_eq!(settings["MSSQL_PORT"], "1433");
+        assert_eq!(settings["MSSQL_ENGINE"], "sqlserver");
+
+        assert_eq!(
+            parse_secret("plain-password\n")["MSSQL_PASSWORD"],
+            "plain-password"
+        );
+    }
+
+    #[test]
+    fn test_provider_selection() {
+        let lookup = |settings: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                settings
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert!(secrets_provider(lookup(&[])).unwrap().is_none());
+        let provider = secrets_provider(lookup(&[
+            ("MSSQL_SECRETS_PROVIDER", "vault"),
+            ("VAULT_ADDR", "https://vault.corp:8200/"),
+            ("VAULT_TOKEN", "hvs.token"),
+            ("MSSQL_SECRETS_VAULT_PATH", "secret/data/mssql"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(provider.name(), "vault");
+
+        let missing = secrets_provider(lookup(&[
+            ("MSSQL_SECRETS_PROVIDER", "aws"),
+            ("AWS_REGION", "eu-west-1"),
+        ]));
+        assert!(missing.is_err());
+        assert!(secrets_provider(lookup(&[("MSSQL_SECRETS_PROVIDER", "keepass")])).is_err());
+    }
+
+    #[test]
+    fn test_sigv4_signing() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // Signing key example of the AWS Signature Version 4 documentation
+        let key = SigningKey {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            region: "us-east-1",
+            service: "iam",
+        };
+        assert_eq!(
+            hex(&signing_key(&key, "20120215")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+
+        let authorization = sigv4_authorization(
+            &key,
+            "20120215T000000Z",
+            &[("host", "iam.amazonaws.com".to_string())],
+            "",
+        );
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20120215/us-east-1/iam/aws4_request, SignedHeaders=host, Signature="
+        ));
+    }
+}
@@ -38,6 +38,29 @@ pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
 /// Default connection idle timeout in seconds.
 pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 600;
 
+/// Default maximum lifetime of a pooled connection in seconds.
+pub const DEFAULT_POOL_MAX_LIFETIME_SECS: u64 = 1800;
+
+/// Default interval between idle connection probes in seconds.
+pub const DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Default interval between secrets provider refreshes in seconds, used as
+/// the credential check interval when a provider is configured.
+pub const DEFAULT_SECRETS_REFRESH_SECS: u64 = 300;
+
+/// Timeout of a request to an external secrets provider in seconds.
+pub const SECRETS_HTTP_TIMEOUT_SECS: u64 = 30;
+
+// =============================================================================
+// SQL Server Browser Constants
+// =============================================================================
+
+/// UDP port of the SQL Server Browser service.
+pub const SQL_BROWSER_PORT: u16 = 1434;
+
+/// Time to wait for a SQL Server Browser response.
+pub const SQL_BROWSER_TIMEOUT: Duration = Duration::from_secs(2);
+
 // =============================================================================
 // Result Size Constants
 // =============================================================================
@@ -60,13 +83,39 @@ pub const DEFAULT_SAMPLE_SIZE: usize = 100;
 /// Maximum sample size for data sampling.
 pub const MAX_SAMPLE_SIZE: usize = 10_000;
 
+/// Default number of affected rows previewed by `estimate_impact`.
+pub const DEFAULT_IMPACT_SAMPLE_SIZE: usize = 10;
+
 /// Default batch size for bulk inserts.
 pub const DEFAULT_BATCH_SIZE: usize = 1000;
 
+/// Default number of rows per INSERT statement for `import_data`.
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 500;
+
+/// Maximum rows per INSERT statement (SQL Server's VALUES row constructor limit).
+pub const MAX_IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Maximum number of invalid rows reported in an import result.
+pub const MAX_IMPORT_BAD_ROWS_REPORTED: usize = 50;
+
+/// Maximum rows `generate_test_data` inserts per call.
+pub const MAX_TEST_DATA_ROWS: usize = 100_000;
+
+/// Parent key values sampled per foreign key by `generate_test_data`.
+pub const TEST_DATA_PARENT_SAMPLE: usize = 1000;
+
+/// Default rows per INSERT ... SELECT batch for `copy_table`.
+pub const DEFAULT_COPY_BATCH_SIZE: usize = 10_000;
+
+/// Maximum rows per INSERT ... SELECT batch for `copy_table`.
+pub const MAX_COPY_BATCH_SIZE: usize = 100_000;
+
 // Compile-time assertions to ensure constant relationships are valid
 const _: () = assert!(DEFAULT_PAGE_SIZE >= MIN_PAGE_SIZE);
 const _: () = assert!(DEFAULT_PAGE_SIZE <= MAX_PAGE_SIZE);
 const _: () = assert!(DEFAULT_SAMPLE_SIZE <= MAX_SAMPLE_SIZE);
+const _: () = assert!(DEFAULT_IMPORT_BATCH_SIZE <= MAX_IMPORT_BATCH_SIZE);
+const _: () = assert!(DEFAULT_COPY_BATCH_SIZE <= MAX_COPY_BATCH_SIZE);
 
 // =============================================================================
 // Cache Constants
@@ -81,9 +130,75 @@ pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(DEFAULT_CACHE_TTL_SE
 /// Default maximum cache size in MB.
 pub const DEFAULT_CACHE_MAX_SIZE_MB: usize = 100;
 
+/// Default memory the rows of a single query may take, in MB.
+pub const DEFAULT_QUERY_MEMORY_MB: usize = 256;
+
+/// Default memory the rows of all queries in flight may take together, in MB.
+pub const DEFAULT_RESULT_MEMORY_MB: usize = 1024;
+
+/// Default time finished async sessions and result references are kept, in seconds.
+pub const DEFAULT_RESULT_RETENTION_SECS: u64 = 3600;
+
+/// Default maximum number of finished async sessions kept in memory.
+pub const DEFAULT_MAX_FINISHED_SESSIONS: usize = 1000;
+
+/// Default maximum memory held by finished async session results in MB.
+pub const DEFAULT_SESSION_MEMORY_MB: usize = 512;
+
+/// Default maximum size of persisted async session results in MB.
+pub const DEFAULT_SESSION_STORE_MAX_MB: usize = 256;
+
 /// Default maximum cache entries.
 pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 1000;
 
+// =============================================================================
+// Result Reference Constants
+// =============================================================================
+
+/// Default size above which results are returned by reference, in KB.
+pub const DEFAULT_RESULT_REF_THRESHOLD_KB: usize = 256;
+
+/// Maximum number of stored results kept for `fetch_result_chunk`.
+pub const RESULT_REF_MAX_ENTRIES: usize = 100;
+
+/// Number of rows included as a preview in a result reference.
+pub const RESULT_REF_PREVIEW_ROWS: usize = 5;
+
+/// Default number of rows returned by `fetch_result_chunk`.
+pub const DEFAULT_RESULT_CHUNK_SIZE: usize = 100;
+
+/// Default number of bytes shown in the hex preview of a binary value.
+pub const DEFAULT_BINARY_PREVIEW_BYTES: usize = 64;
+
+/// Bytes read per round trip by `download_blob`.
+pub const BLOB_CHUNK_SIZE: usize = 1024 * 1024;
+
+// =============================================================================
+// Script Constants
+// =============================================================================
+
+/// Largest repeat count accepted by a `GO n` batch separator.
+pub const MAX_GO_REPEAT: usize = 1000;
+
+// =============================================================================
+// Slow Query Log Constants
+// =============================================================================
+
+/// Default number of slow queries kept in memory.
+pub const DEFAULT_SLOW_QUERY_LOG_SIZE: usize = 100;
+
+/// Default number of entries returned by `get_slow_queries`.
+pub const DEFAULT_SLOW_QUERY_LIMIT: usize = 20;
+
+/// Maximum number of distinct query shapes tracked by the metrics collector.
+pub const MAX_QUERY_SHAPES: usize = 500;
+
+/// Default number of query shapes reported by `get_internal_metrics`.
+pub const DEFAULT_TOP_QUERY_SHAPES: usize = 10;
+
+/// Number of query shapes, most executed first, exported on `/metrics`.
+pub const PROMETHEUS_QUERY_SHAPES: usize = 50;
+
 // =============================================================================
 // Shutdown Constants
 // =============================================================================
@@ -113,15 +228,133 @@ pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 60;
 /// Default cleanup interval as Duration.
 pub const DEFAULT_CLEANUP_INTERVAL: Duration = Duration::from_secs(DEFAULT_CLEANUP_INTERVAL_SECS);
 
+/// Default idle timeout of pinned sessions in seconds.
+pub const DEFAULT_PINNED_SESSION_IDLE_TIMEOUT_SECS: u64 = 3600;
+
 /// Maximum session limit.
 pub const DEFAULT_SESSION_LIMIT: usize = 100;
 
 /// Maximum transaction limit.
 pub const DEFAULT_TRANSACTION_LIMIT: usize = 50;
 
+/// Statements recorded per transaction for deadlock replay; transactions with
+/// more statements are not replayed.
+pub const MAX_REPLAY_STATEMENTS: usize = 1000;
+
+/// Longest savepoint name SQL Server accepts.
+pub const MAX_SAVEPOINT_NAME_LENGTH: usize = 32;
+
+/// Clients whose current database and timeout are remembered; the least
+/// recently updated client is forgotten beyond this.
+pub const MAX_CLIENT_CONTEXTS: usize = 1000;
+
+/// Plan baselines kept per client for `compare_plans`; the oldest is dropped
+/// beyond this.
+pub const MAX_PLAN_BASELINES: usize = 20;
+
 /// Session progress when complete.
 pub const SESSION_PROGRESS_COMPLETE: u8 = 100;
 
+/// Rows an async query reads between progress updates.
+pub const ASYNC_PROGRESS_ROWS: usize = 1000;
+
+// =============================================================================
+// Table Watch Constants
+// =============================================================================
+
+/// Maximum number of concurrent table watches.
+pub const MAX_TABLE_WATCHES: usize = 10;
+
+/// Default interval between watch polls in seconds.
+pub const DEFAULT_WATCH_POLL_INTERVAL_SECS: u64 = 30;
+
+/// Minimum interval between watch polls in seconds (per-watch throttle).
+pub const MIN_WATCH_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Default maximum rows fetched per watch poll.
+pub const DEFAULT_WATCH_ROWS_PER_POLL: usize = 100;
+
+/// Maximum rows queued per watch before the oldest are dropped.
+pub const MAX_WATCH_PENDING_ROWS: usize = 1000;
+
+/// Maximum number of concurrent query watches.
+pub const MAX_QUERY_WATCHES: usize = 10;
+
+/// Maximum change records queued per query watch before the oldest are dropped.
+pub const MAX_QUERY_WATCH_PENDING_CHANGES: usize = 50;
+
+/// Maximum rows listed per change category in a query watch change record.
+pub const MAX_QUERY_WATCH_CHANGE_ROWS: usize = 100;
+
+/// Default interval between schema change polls for resource subscriptions in seconds.
+pub const DEFAULT_SCHEMA_POLL_INTERVAL_SECS: u64 = 30;
+
+// =============================================================================
+// Index Maintenance Constants
+// =============================================================================
+
+/// Default minimum index size in pages for fragmentation reports and maintenance.
+pub const DEFAULT_INDEX_MIN_PAGE_COUNT: u64 = 1000;
+
+/// Default fragmentation percentage at which indexes are reorganized.
+pub const DEFAULT_REORGANIZE_THRESHOLD_PERCENT: f64 = 5.0;
+
+/// Default fragmentation percentage at which indexes are rebuilt.
+pub const DEFAULT_REBUILD_THRESHOLD_PERCENT: f64 = 30.0;
+
+/// Default estimated savings percentage at which `compression_advisor` recommends compression.
+pub const DEFAULT_COMPRESSION_MIN_SAVINGS_PERCENT: f64 = 20.0;
+
+/// Maximum number of tables estimated per `compression_advisor` call.
+pub const MAX_COMPRESSION_ADVISOR_TABLES: usize = 25;
+
+/// Seconds allowed for one `sp_estimate_data_compression_savings` call.
+pub const COMPRESSION_ESTIMATE_TIMEOUT_SECS: u64 = 300;
+
+// =============================================================================
+// Dependency Analysis Constants
+// =============================================================================
+
+/// Default number of levels followed by `get_dependencies`.
+pub const DEFAULT_DEPENDENCY_DEPTH: usize = 3;
+
+/// Maximum number of levels followed by `get_dependencies`.
+pub const MAX_DEPENDENCY_DEPTH: usize = 10;
+
+// =============================================================================
+// Audit Log Constants
+// =============================================================================
+
+/// Default number of tool calls kept in the in-memory audit log.
+pub const DEFAULT_AUDIT_MAX_ENTRIES: usize = 1000;
+
+/// Default number of entries returned by `get_query_history`.
+pub const DEFAULT_QUERY_HISTORY_LIMIT: usize = 50;
+
+/// Seconds a destructive-statement confirmation token stays valid.
+pub const CONFIRMATION_TOKEN_TTL_SECS: u64 = 300;
+
+/// Seconds to wait for the user to answer an elicited confirmation.
+pub const ELICITATION_TIMEOUT_SECS: u64 = 300;
+
+/// Default row count above which `bulk_insert` needs confirmation.
+pub const DEFAULT_CONFIRM_BULK_ROWS: usize = 10_000;
+
+/// Seconds to wait for the client's model to answer a sampling request.
+pub const SAMPLING_TIMEOUT_SECS: u64 = 120;
+
+/// Default number of rows profiled by `data_quality`.
+pub const DEFAULT_PROFILE_SAMPLE_ROWS: usize = 10_000;
+
+/// Rows of character columns sampled for value patterns.
+pub const PROFILE_PATTERN_SAMPLE_ROWS: usize = 1_000;
+
+/// Maximum number of rows `profile_table` reads.
+pub const MAX_PROFILE_SAMPLE_ROWS: usize = 1_000_000;
+
+/// Maximum number of top values or histogram buckets per column.
+pub const MAX_PROFILE_BUCKETS: usize = 50;
+
 // =============================================================================
 // Rate Limiting Constants
 // =============================================================================
@@ -129,6 +362,9 @@ pub const SESSION_PROGRESS_COMPLETE: u8 = 100;
 /// Default rate limit in requests per minute.
 pub const DEFAULT_RATE_LIMIT_RPM: u32 = 100;
 
+/// Default time a call waits for a concurrency slot before it is rejected, in seconds.
+pub const DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_SECS: u64 = 30;
+
 // =============================================================================
 // Telemetry Constants
 // =============================================================================
@@ -153,6 +389,18 @@ pub const LOG_QUERY_EXTENDED_TRUNCATE_LENGTH: usize = 500;
 /// Default time range for metrics queries in minutes.
 pub const DEFAULT_METRICS_TIME_RANGE_MINUTES: u64 = 60;
 
+/// Default number of sessions listed by `get_tempdb_usage`.
+pub const DEFAULT_TEMPDB_SESSION_LIMIT: usize = 20;
+
+/// Maximum number of sessions listed by `get_tempdb_usage`.
+pub const MAX_TEMPDB_SESSION_LIMIT: usize = 500;
+
+/// Default number of requests listed by `get_active_requests`.
+pub const DEFAULT_ACTIVE_REQUESTS_LIMIT: usize = 100;
+
+/// Maximum number of requests listed by `get_active_requests`.
+pub const MAX_ACTIVE_REQUESTS_LIMIT: usize = 1000;
+
 // =============================================================================
 // Percentage Constants (for calculations)
 // =============================================================================
@@ -1,25 +1,88 @@
 //! Database connectivity and query execution.
 
+pub mod activity;
+pub mod anonymize;
 mod auth;
+pub mod blob;
+mod browser;
 mod bulk;
+pub mod compression;
 mod connection;
+pub mod copy;
+pub mod crud;
+pub mod data_dictionary;
+pub mod dependencies;
+pub mod describe;
+mod failover;
+pub mod graph;
+pub mod hadr;
+pub mod integrity;
+pub mod json_column;
+pub mod maintenance;
+mod messages;
 pub mod metadata;
+pub mod migration;
+pub mod pagination;
+pub mod plan;
+mod pool_health;
+pub mod profile;
 mod query;
+mod query_watch;
+pub mod relationships;
+mod result_memory;
+mod rotation;
+pub mod schema_search;
+mod schema_watch;
+pub mod script;
+pub mod scripting;
 mod session;
+mod slow_query;
+pub mod sql_generation;
+pub mod statement_results;
+pub mod storage;
+pub mod table_sizes;
+pub mod temporal;
+pub mod test_data;
 mod transaction;
+pub mod tvp;
 pub mod types;
+pub mod udt;
+mod watch;
+pub mod xtp;
 
 pub use auth::{create_connection, truncate_for_log, RawConnection};
+pub use browser::{resolve_instance, resolve_instance_port};
 pub use bulk::{BulkInsertManager, BulkInsertMethod, NativeBulkOptions, NativeBulkResult};
-pub use connection::{create_pool, pool_status, ConnectionPool, PoolStatus, PooledConn};
+pub use connection::{
+    connect_pool, connect_read_pool, create_pool, pool_status, ConnectionPool, PoolStatus,
+    PooledConn,
+};
+pub use failover::{FailoverRecovery, FailoverStats};
+pub use messages::{capture_messages, ServerMessageLayer};
 pub use metadata::{
-    ColumnInfo, DatabaseInfo, FunctionInfo, FunctionParameter, MetadataQueries, ProcedureInfo,
-    ProcedureParameter, ServerInfo, TableInfo, TriggerInfo, ViewInfo,
+    ColumnInfo, ConnectionSecurity, DatabaseInfo, EncryptedColumn, ForeignKeyRelationship,
+    FunctionInfo, FunctionParameter, LoginInfo, MetadataQueries, ObjectPermission, ProcedureInfo,
+    ProcedureParameter, RoleInfo, ServerInfo, TableInfo, TableVersioning, TriggerInfo, ViewInfo,
 };
+pub use migration::{MigrationAction, MigrationPlan, MigrationStep};
+pub use pool_health::{ConnectionStats, PoolHealth, PoolHealthSnapshot};
+pub(crate) use query::csv_field;
 pub use query::{
-    ColumnInfo as QueryColumnInfo, MultiQueryResult, QueryExecutor, QueryResult, ResultRow,
-    TransactionBatchResult, ValidationResult,
+    with_read_intent, ColumnInfo as QueryColumnInfo, MultiQueryResult, ProcedureResult,
+    QueryExecutor, QueryResult, ResultRow, StreamSummary, TransactionBatchResult, ValidationResult,
+};
+pub use query_watch::{
+    query_watch_uri, QueryChange, QueryWatchInfo, QueryWatchManager, QueryWatchSpec,
+    QueryWatchStatus, QUERY_WATCH_URI_PREFIX,
+};
+pub use result_memory::{
+    result_bytes_in_use, set_result_memory_budgets, with_partial_results, ResultMemory,
 };
-pub use session::{SessionInfo, SessionManager};
-pub use transaction::TransactionManager;
-pub use types::{SqlValue, TypeMapper};
+pub use rotation::{CredentialRotation, RotationOutcome, RotationStats};
+pub use schema_watch::{subscription_key, SchemaWatcher};
+pub use session::{ExpiryReason, SessionInfo, SessionLimits, SessionManager};
+pub use slow_query::{SlowQuery, SlowQueryLog, WaitStat};
+pub use table_sizes::{TableGrowth, TableSize, TableSizeHistory, TableSizeSnapshot};
+pub use transaction::{DeadlockReplay, TransactionExecution, TransactionManager};
+pub use types::{set_exact_decimals, SqlValue, TypeMapper};
+pub use watch::{WatchChanges, WatchInfo, WatchManager, WatchSpec};
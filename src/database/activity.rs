@@ -0,0 +1,452 @@
+//! Currently running requests.
+//!
+//! Joins `sys.dm_exec_requests` with the session, connection, tempdb task
+//! usage and memory grant DMVs into one row per request, in the spirit of
+//! `sp_WhoIsActive`. Sessions that block others but have no running request
+//! (an idle session holding an open transaction) are listed too, with the
+//! last statement they ran, since they are usually the cause of a blocking
+//! chain.
+//!
+//! [`KillTarget`] describes a session before it is killed, so that system
+//! sessions and the server's own connections can be refused and the caller
+//! can confirm the login and host of the session they mean.
+
+use crate::database::query::ResultRow;
+use serde::Serialize;
+
+/// Filters for [`active_requests_query`].
+#[derive(Debug, Clone, Default)]
+pub struct ActivityFilter<'a> {
+    /// Only requests running in this database.
+    pub database: Option<&'a str>,
+    /// Only requests of this login.
+    pub login: Option<&'a str>,
+    /// Include background and system sessions.
+    pub include_system_sessions: bool,
+    /// Include the statement's plan XML.
+    pub include_plans: bool,
+}
+
+/// One running request, or an idle session blocking others.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveRequest {
+    pub session_id: i64,
+    /// Request status (`running`, `runnable`, `suspended`), or the session
+    /// status (`sleeping`) for an idle blocker.
+    pub status: Option<String>,
+    pub command: Option<String>,
+    pub database: Option<String>,
+    pub login: Option<String>,
+    pub host: Option<String>,
+    pub program: Option<String>,
+    pub start_time: Option<String>,
+    /// Milliseconds since the request started (since the last request
+    /// ended for an idle blocker).
+    pub elapsed_ms: Option<i64>,
+    pub cpu_ms: Option<i64>,
+    pub logical_reads: Option<i64>,
+    pub reads: Option<i64>,
+    pub writes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_time_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_resource: Option<String>,
+    /// Session blocking this request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_by: Option<i64>,
+    /// Number of requests this session blocks.
+    pub blocking_count: i64,
+    /// Blocks others without being blocked itself.
+    pub is_head_blocker: bool,
+    pub open_transactions: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent_complete: Option<f64>,
+    pub tempdb_mb: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granted_memory_mb: Option<f64>,
+    /// Module the statement belongs to (`schema.name`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    /// Statement being executed (the last batch for an idle blocker).
+    pub statement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_xml: Option<String>,
+}
+
+impl ActiveRequest {
+    /// Build from a row of [`active_requests_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+        let mb = |column: &str| {
+            number(column).map(|kb| (kb.max(0) as f64 / 1024.0 * 100.0).round() / 100.0)
+        };
+
+        let blocked_by = number("blocking_session_id").filter(|&id| id > 0);
+        let blocking_count = number("blocking_count").unwrap_or(0);
+        Some(Self {
+            session_id: number("session_id")?,
+            status: text("status"),
+            command: text("command"),
+            database: text("database_name"),
+            login: text("login_name"),
+            host: text("host_name"),
+            program: text("program_name"),
+            start_time: text("start_time"),
+            elapsed_ms: number("elapsed_ms"),
+            cpu_ms: number("cpu_ms"),
+            logical_reads: number("logical_reads"),
+            reads: number("reads"),
+            writes: number("writes"),
+            wait_type: text("wait_type"),
+            wait_time_ms: number("wait_time_ms").filter(|&ms| ms > 0),
+            wait_resource: text("wait_resource").filter(|r| !r.is_empty()),
+            blocked_by,
+            blocking_count,
+            is_head_blocker: blocking_count > 0 && blocked_by.is_none(),
+            open_transactions: number("open_transaction_count").unwrap_or(0),
+            percent_complete: text("percent_complete")
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|&p| p > 0.0),
+            tempdb_mb: mb("tempdb_kb").unwrap_or(0.0),
+            granted_memory_mb: mb("granted_memory_kb"),
+            object: text("object_name"),
+            statement: text("statement_text").map(|s| s.trim().to_string()),
+            plan_xml: text("query_plan"),
+        })
+    }
+}
+
+/// Query returning running requests and idle blockers, longest running first.
+pub fn active_requests_query(filter: &ActivityFilter<'_>, limit: usize) -> String {
+    let mut conditions = vec!["s.session_id <> @@SPID".to_string()];
+    if !filter.include_system_sessions {
+        conditions.push("s.is_user_process = 1".to_string());
+    }
+    if let Some(database) = filter.database {
+        conditions.push(format!(
+            "DB_NAME(COALESCE(r.database_id, s.database_id)) = N'{}'",
+            database.replace('\'', "''")
+        ));
+    }
+    if let Some(login) = filter.login {
+        conditions.push(format!("s.login_name = N'{}'", login.replace('\'', "''")));
+    }
+    let (plan_column, plan_apply) = if filter.include_plans {
+        (
+            "qp.query_plan",
+            "\n        OUTER APPLY sys.dm_exec_text_query_plan(\n            r.plan_handle, r.statement_start_offset, r.statement_end_offset) qp",
+        )
+    } else {
+        ("CAST(NULL AS nvarchar(max)) AS query_plan", "")
+    };
+
+    format!(
+        r#"
+        SELECT TOP ({limit})
+            s.session_id,
+            COALESCE(r.status, s.status) AS status,
+            r.command,
+            DB_NAME(COALESCE(r.database_id, s.database_id)) AS database_name,
+            s.login_name,
+            s.host_name,
+            s.program_name,
+            CONVERT(varchar(23), COALESCE(r.start_time, s.last_request_start_time), 121)
+                AS start_time,
+            CAST(COALESCE(r.total_elapsed_time,
+                DATEDIFF(second, s.last_request_end_time, GETDATE()) * 1000.0) AS bigint)
+                AS elapsed_ms,
+            COALESCE(r.cpu_time, s.cpu_time) AS cpu_ms,
+            COALESCE(r.logical_reads, s.logical_reads) AS logical_reads,
+            COALESCE(r.reads, s.reads) AS reads,
+            COALESCE(r.writes, s.writes) AS writes,
+            r.wait_type,
+            r.wait_time AS wait_time_ms,
+            r.wait_resource,
+            r.blocking_session_id,
+            (SELECT COUNT(*) FROM sys.dm_exec_requests b
+             WHERE b.blocking_session_id = s.session_id) AS blocking_count,
+            COALESCE(r.open_transaction_count, s.open_transaction_count)
+                AS open_transaction_count,
+            CAST(r.percent_complete AS decimal(5, 2)) AS percent_complete,
+            ISNULL(t.tempdb_pages, 0) * 8 AS tempdb_kb,
+            mg.granted_memory_kb,
+            OBJECT_SCHEMA_NAME(qt.objectid, qt.dbid) + N'.' + OBJECT_NAME(qt.objectid, qt.dbid)
+                AS object_name,
+            CASE
+                WHEN r.sql_handle IS NULL THEN qt.text
+                ELSE SUBSTRING(qt.text, r.statement_start_offset / 2 + 1,
+                    (CASE r.statement_end_offset
+                        WHEN -1 THEN DATALENGTH(qt.text)
+                        ELSE r.statement_end_offset
+                     END - r.statement_start_offset) / 2 + 1)
+            END AS statement_text,
+            {plan_column}
+        FROM sys.dm_exec_sessions s
+        LEFT JOIN sys.dm_exec_requests r ON r.session_id = s.session_id
+        LEFT JOIN sys.dm_exec_connections c
+            ON c.session_id = s.session_id AND c.parent_connection_id IS NULL
+        LEFT JOIN (
+            SELECT
+                session_id,
+                request_id,
+                SUM(user_objects_alloc_page_count - user_objects_dealloc_page_count
+                    + internal_objects_alloc_page_count - internal_objects_dealloc_page_count)
+                    AS tempdb_pages
+            FROM sys.dm_db_task_space_usage
+            GROUP BY session_id, request_id
+        ) t ON t.session_id = r.session_id AND t.request_id = r.request_id
+        LEFT JOIN sys.dm_exec_query_memory_grants mg
+            ON mg.session_id = r.session_id AND mg.request_id = r.request_id
+        OUTER APPLY sys.dm_exec_sql_text(COALESCE(r.sql_handle, c.most_recent_sql_handle)) qt{plan_apply}
+        WHERE (r.session_id IS NOT NULL OR EXISTS (
+            SELECT 1 FROM sys.dm_exec_requests b WHERE b.blocking_session_id = s.session_id))
+        AND {conditions}
+        ORDER BY elapsed_ms DESC
+    "#,
+        limit = limit,
+        plan_column = plan_column,
+        plan_apply = plan_apply,
+        conditions = conditions.join("\n        AND "),
+    )
+}
+
+/// A session about to be killed.
+#[derive(Debug, Clone, Serialize)]
+pub struct KillTarget {
+    pub session_id: i64,
+    pub login: Option<String>,
+    pub host: Option<String>,
+    pub program: Option<String>,
+    pub database: Option<String>,
+    pub status: Option<String>,
+    pub command: Option<String>,
+    pub open_transactions: i64,
+    #[serde(skip)]
+    pub is_user_process: bool,
+    /// The connection running the query, or another connection of this server.
+    #[serde(skip)]
+    pub is_own_connection: bool,
+}
+
+impl KillTarget {
+    /// Build from the row of [`kill_target_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+        let flag = |column: &str| matches!(text(column).as_deref(), Some("1" | "true"));
+
+        Some(Self {
+            session_id: number("session_id")?,
+            login: text("login_name"),
+            host: text("host_name").filter(|h| !h.is_empty()),
+            program: text("program_name"),
+            database: text("database_name"),
+            status: text("status"),
+            command: text("command"),
+            open_transactions: number("open_transaction_count").unwrap_or(0),
+            is_user_process: flag("is_user_process"),
+            is_own_connection: flag("is_own_connection"),
+        })
+    }
+
+    /// Why the session must not be killed, if it must not.
+    pub fn refusal(&self) -> Option<String> {
+        if !self.is_user_process {
+            Some(format!(
+                "Session {} is a system session and cannot be killed",
+                self.session_id
+            ))
+        } else if self.is_own_connection {
+            Some(format!(
+                "Session {} is one of this server's own connections and cannot be killed",
+                self.session_id
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the echoed login and host match the session (case-insensitive).
+    ///
+    /// A session without a host name is confirmed by omitting the host.
+    pub fn confirmed_by(&self, login: Option<&str>, host: Option<&str>) -> bool {
+        let same = |expected: Option<&str>, given: Option<&str>| {
+            let given = given.map(str::trim).filter(|v| !v.is_empty());
+            match (expected, given) {
+                (Some(expected), Some(given)) => expected.eq_ignore_ascii_case(given),
+                (None, None) => true,
+                _ => false,
+            }
+        };
+        login.is_some_and(|l| !l.trim().is_empty())
+            && same(self.login.as_deref(), login)
+            && same(self.host.as_deref(), host)
+    }
+}
+
+/// Query describing a session, including whether it belongs to this server.
+///
+/// The server's connections share its host name and application name (pinned
+/// sessions and transactions add a suffix such as `-session`).
+pub fn kill_target_query(session_id: i64) -> String {
+    format!(
+        r#"
+        SELECT
+            s.session_id,
+            s.login_name,
+            s.host_name,
+            s.program_name,
+            DB_NAME(s.database_id) AS database_name,
+            s.status,
+            r.command,
+            s.open_transaction_count,
+            CAST(s.is_user_process AS int) AS is_user_process,
+            CASE WHEN s.session_id = @@SPID
+                OR (ISNULL(s.host_name, N'') = ISNULL(HOST_NAME(), N'')
+                    AND (s.program_name = APP_NAME() OR s.program_name LIKE APP_NAME() + N'-%'))
+                THEN 1 ELSE 0 END AS is_own_connection
+        FROM sys.dm_exec_sessions s
+        LEFT JOIN sys.dm_exec_requests r ON r.session_id = s.session_id
+        WHERE s.session_id = {}
+    "#,
+        session_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    #[test]
+    fn test_query_filters() {
+        let query = active_requests_query(
+            &ActivityFilter {
+                database: Some("Sales"),
+                login: Some("o'brien"),
+                ..Default::default()
+            },
+            50,
+        );
+        assert!(query.contains("TOP (50)"));
+        assert!(query.contains("= N'Sales'"));
+        assert!(query.contains("s.login_name = N'o''brien'"));
+        assert!(query.contains("s.is_user_process = 1"));
+        assert!(!query.contains("dm_exec_text_query_plan"));
+    }
+
+    #[test]
+    fn test_query_with_plans_and_system_sessions() {
+        let query = active_requests_query(
+            &ActivityFilter {
+                include_system_sessions: true,
+                include_plans: true,
+                ..Default::default()
+            },
+            10,
+        );
+        assert!(query.contains("dm_exec_text_query_plan"));
+        assert!(!query.contains("is_user_process"));
+    }
+
+    #[test]
+    fn test_head_blocker() {
+        let blocker = ActiveRequest::from_row(&ResultRow::from_values(&[
+            ("session_id", SqlValue::I16(61)),
+            ("status", SqlValue::String("sleeping".into())),
+            ("blocking_session_id", SqlValue::Null),
+            ("blocking_count", SqlValue::I32(2)),
+            ("open_transaction_count", SqlValue::I32(1)),
+        ]))
+        .unwrap();
+        assert!(blocker.is_head_blocker);
+        assert_eq!(blocker.blocked_by, None);
+
+        let blocked = ActiveRequest::from_row(&ResultRow::from_values(&[
+            ("session_id", SqlValue::I16(62)),
+            ("blocking_session_id", SqlValue::I16(61)),
+            ("blocking_count", SqlValue::I32(1)),
+            ("wait_type", SqlValue::String("LCK_M_X".into())),
+            ("wait_time_ms", SqlValue::I32(4_500)),
+        ]))
+        .unwrap();
+        assert!(!blocked.is_head_blocker);
+        assert_eq!(blocked.blocked_by, Some(61));
+        assert_eq!(blocked.wait_time_ms, Some(4_500));
+    }
+
+    #[test]
+    fn test_usage_in_mb() {
+        let request = ActiveRequest::from_row(&ResultRow::from_values(&[
+            ("session_id", SqlValue::I16(70)),
+            ("blocking_session_id", SqlValue::I16(0)),
+            ("tempdb_kb", SqlValue::I64(5_120)),
+            ("granted_memory_kb", SqlValue::I64(1_536)),
+            ("percent_complete", SqlValue::String("0.00".into())),
+            ("statement_text", SqlValue::String("\n  SELECT 1\n".into())),
+        ]))
+        .unwrap();
+        assert_eq!(request.blocked_by, None);
+        assert_eq!(request.tempdb_mb, 5.0);
+        assert_eq!(request.granted_memory_mb, Some(1.5));
+        assert_eq!(request.percent_complete, None);
+        assert_eq!(request.statement.as_deref(), Some("SELECT 1"));
+    }
+
+    fn target(login: Option<&str>, host: Option<&str>) -> KillTarget {
+        KillTarget {
+            session_id: 73,
+            login: login.map(str::to_string),
+            host: host.map(str::to_string),
+            program: None,
+            database: None,
+            status: Some("sleeping".into()),
+            command: None,
+            open_transactions: 1,
+            is_user_process: true,
+            is_own_connection: false,
+        }
+    }
+
+    #[test]
+    fn test_kill_confirmation() {
+        let t = target(Some("CORP\\etl"), Some("BATCH01"));
+        assert!(t.confirmed_by(Some("corp\\ETL"), Some(" batch01 ")));
+        assert!(!t.confirmed_by(Some("CORP\\etl"), None));
+        assert!(!t.confirmed_by(Some("CORP\\etl"), Some("BATCH02")));
+        assert!(!t.confirmed_by(None, Some("BATCH01")));
+
+        let no_host = target(Some("app"), None);
+        assert!(no_host.confirmed_by(Some("app"), None));
+        assert!(no_host.confirmed_by(Some("app"), Some("")));
+        assert!(!no_host.confirmed_by(Some("app"), Some("web01")));
+    }
+
+    #[test]
+    fn test_kill_refusal() {
+        assert!(target(Some("app"), None).refusal().is_none());
+
+        let mut system = target(None, None);
+        system.is_user_process = false;
+        assert!(system.refusal().unwrap().contains("system session"));
+
+        let own = KillTarget::from_row(&ResultRow::from_values(&[
+            ("session_id", SqlValue::I16(55)),
+            ("is_user_process", SqlValue::I32(1)),
+            ("is_own_connection", SqlValue::I32(1)),
+        ]))
+        .unwrap();
+        assert!(own.refusal().unwrap().contains("own connections"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Column scrubbing for sanitized copies of production data.
+//!
+//! Rules name a strategy per column: `hash` replaces values with a salted
+//! SHA-256 digest computed on the server (equal inputs stay equal, so joins
+//! across scrubbed tables still line up), `shuffle` permutes the column's
+//! values among the selected rows, `fake` writes generated values of the same
+//! kind (see [`crate::database::test_data`]) and `nullify` sets NULL.
+//!
+//! Rows are updated in key order, one batch per statement, with keyset
+//! pagination so a filter on a scrubbed column doesn't make batches skip rows.
+
+use crate::database::metadata::ColumnInfo;
+use crate::database::test_data::{generator_for, GeneratedColumn};
+use crate::error::ServerError;
+use crate::security::safe_identifier;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Character types a digest can be written to.
+const TEXT_TYPES: &[&str] = &["char", "varchar", "nchar", "nvarchar"];
+
+/// Length of a hex-encoded SHA-256 digest.
+const DIGEST_LENGTH: i32 = 64;
+
+/// Shortest column a digest is truncated to.
+const MIN_DIGEST_LENGTH: i32 = 8;
+
+/// Parameter the salt is bound to, so it never appears in statement text
+/// (and with it in the logs or slow query history).
+pub const SALT_PARAMETER: &str = "@p1";
+
+/// How a column is scrubbed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubStrategy {
+    Hash,
+    Shuffle,
+    Fake,
+    Nullify,
+}
+
+impl ScrubStrategy {
+    /// Parse a rule value (`hash`, `shuffle`, `fake` or `nullify`).
+    pub fn parse(value: &str) -> Result<Self, ServerError> {
+        match value.trim().to_lowercase().as_str() {
+            "hash" => Ok(Self::Hash),
+            "shuffle" => Ok(Self::Shuffle),
+            "fake" | "faker" | "replace" => Ok(Self::Fake),
+            "nullify" | "null" => Ok(Self::Nullify),
+            other => Err(ServerError::invalid_input(format!(
+                "Unknown scrubbing strategy '{}' (expected hash, shuffle, fake or nullify)",
+                other
+            ))),
+        }
+    }
+}
+
+/// A validated rule for one column.
+#[derive(Debug, Clone)]
+pub struct ScrubRule {
+    pub column: String,
+    pub strategy: ScrubStrategy,
+    /// Character length of the column (-1 for `max`).
+    pub max_length: Option<i32>,
+    /// Value generator for `fake` rules.
+    pub generator: Option<GeneratedColumn>,
+}
+
+/// Validate rules against the table's columns.
+///
+/// Key columns, identity and computed columns can't be scrubbed; `nullify`
+/// needs a nullable column and `hash` a character column long enough for a
+/// useful digest.
+pub fn plan_rules(
+    columns: &[ColumnInfo],
+    key_columns: &[String],
+    rules: &HashMap<String, String>,
+) -> Result<Vec<ScrubRule>, ServerError> {
+    if rules.is_empty() {
+        return Err(ServerError::invalid_input("No scrubbing rules given"));
+    }
+    let mut planned = Vec::with_capacity(rules.len());
+    for info in columns {
+        let Some(strategy) = rules
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&info.column_name))
+            .map(|(_, strategy)| strategy)
+        else {
+            continue;
+        };
+        let strategy = ScrubStrategy::parse(strategy)?;
+        let name = &info.column_name;
+        let data_type = info.data_type.to_lowercase();
+
+        if key_columns.iter().any(|k| k.eq_ignore_ascii_case(name)) {
+            return Err(ServerError::invalid_input(format!(
+                "Column {} is a key column and can't be scrubbed",
+                name
+            )));
+        }
+        if info.is_identity || info.is_computed {
+            return Err(ServerError::invalid_input(format!(
+                "Column {} is an identity or computed column and can't be scrubbed",
+                name
+            )));
+        }
+        let generator = match strategy {
+            ScrubStrategy::Nullify if !info.is_nullable => {
+                return Err(ServerError::invalid_input(format!(
+                    "Column {} is NOT NULL; use hash, shuffle or fake instead of nullify",
+                    name
+                )));
+            }
+            ScrubStrategy::Hash
+                if !TEXT_TYPES.contains(&data_type.as_str())
+                    || matches!(info.max_length, Some(n) if n > 0 && n < MIN_DIGEST_LENGTH) =>
+            {
+                return Err(ServerError::invalid_input(format!(
+                    "Column {} ({}) can't hold a digest; hash needs a character column of at least {} characters",
+                    name, info.data_type, MIN_DIGEST_LENGTH
+                )));
+            }
+            ScrubStrategy::Fake => {
+                let Some(generator) = generator_for(info) else {
+                    return Err(ServerError::invalid_input(format!(
+                        "Column {} is {}, which has no fake values; use nullify instead",
+                        name, info.data_type
+                    )));
+                };
+                Some(GeneratedColumn {
+                    name: name.clone(),
+                    data_type,
+                    max_length: info.max_length,
+                    nullable: false,
+                    generator,
+                })
+            }
+            _ => None,
+        };
+        planned.push(ScrubRule {
+            column: name.clone(),
+            strategy,
+            max_length: info.max_length,
+            generator,
+        });
+    }
+
+    if let Some(missing) = rules.keys().find(|name| {
+        !columns
+            .iter()
+            .any(|c| c.column_name.eq_ignore_ascii_case(name))
+    }) {
+        return Err(ServerError::invalid_input(format!(
+            "Column not found: {}",
+            missing
+        )));
+    }
+    Ok(planned)
+}
+
+/// Server-side expression computing the digest of a column of `t`, salted
+/// with [`SALT_PARAMETER`].
+pub fn hash_expression(rule: &ScrubRule) -> Result<String, ServerError> {
+    let length = match rule.max_length {
+        Some(n) if n > 0 => n.min(DIGEST_LENGTH),
+        _ => DIGEST_LENGTH,
+    };
+    Ok(format!(
+        "LEFT(CONVERT(nvarchar(64), HASHBYTES('SHA2_256', CONCAT({}, CAST(t.{} AS nvarchar(4000)))), 2), {})",
+        SALT_PARAMETER,
+        safe_identifier(&rule.column)?,
+        length
+    ))
+}
+
+/// Statement permuting a column's values among the rows matching `filter`.
+pub fn shuffle_statement(
+    table: &str,
+    key_columns: &[String],
+    column: &str,
+    filter: Option<&str>,
+) -> Result<String, ServerError> {
+    let column = safe_identifier(column)?;
+    let order = escaped_list(key_columns, "")?;
+    let filter = filter
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| format!(" WHERE {}", f))
+        .unwrap_or_default();
+    Ok(format!(
+        "WITH source AS (SELECT {column} AS v, ROW_NUMBER() OVER (ORDER BY NEWID()) AS rn FROM {table}{filter}),\n\
+         target AS (SELECT {column}, ROW_NUMBER() OVER (ORDER BY {order}) AS rn FROM {table}{filter})\n\
+         UPDATE target SET {column} = source.v FROM target INNER JOIN source ON source.rn = target.rn"
+    ))
+}
+
+/// Query reading the next `batch_size` keys after `after` (a previous key as
+/// SQL literals), in key order.
+pub fn key_batch_query(
+    table: &str,
+    key_columns: &[String],
+    filter: Option<&str>,
+    after: Option<&[String]>,
+    batch_size: usize,
+) -> Result<String, ServerError> {
+    let keys = escaped_list(key_columns, "t.")?;
+    let mut conditions = Vec::new();
+    if let Some(filter) = filter.filter(|f| !f.trim().is_empty()) {
+        conditions.push(format!("({})", filter));
+    }
+    if let Some(after) = after {
+        conditions.push(keyset_condition(key_columns, after)?);
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    Ok(format!(
+        "SELECT TOP ({}) {} FROM {} AS t{} ORDER BY {}",
+        batch_size.max(1),
+        keys,
+        table,
+        where_clause,
+        keys
+    ))
+}
+
+/// `(k1, k2) > (a, b)` written out for SQL Server.
+fn keyset_condition(key_columns: &[String], after: &[String]) -> Result<String, ServerError> {
+    let mut alternatives = Vec::new();
+    for i in 0..key_columns.len() {
+        let mut parts = Vec::new();
+        for j in 0..i {
+            parts.push(format!(
+                "t.{} = {}",
+                safe_identifier(&key_columns[j])?,
+                after[j]
+            ));
+        }
+        parts.push(format!(
+            "t.{} > {}",
+            safe_identifier(&key_columns[i])?,
+            after[i]
+        ));
+        alternatives.push(parts.join(" AND "));
+    }
+    Ok(format!("(({}))", alternatives.join(") OR (")))
+}
+
+/// Statement scrubbing the rows with the given keys.
+///
+/// `fake_values` holds one row of literals per key, one per `fake` rule in
+/// rule order. Shuffle rules are not part of the batch update.
+pub fn update_statement(
+    table: &str,
+    key_columns: &[String],
+    rules: &[ScrubRule],
+    keys: &[Vec<String>],
+    fake_values: &[Vec<String>],
+) -> Result<String, ServerError> {
+    let mut assignments = Vec::new();
+    let mut value_columns: Vec<String> = (0..key_columns.len()).map(|i| format!("k{i}")).collect();
+    let mut fake = 0;
+    for rule in rules {
+        let column = safe_identifier(&rule.column)?;
+        match rule.strategy {
+            ScrubStrategy::Hash => {
+                assignments.push(format!("{} = {}", column, hash_expression(rule)?))
+            }
+            ScrubStrategy::Nullify => assignments.push(format!("{} = NULL", column)),
+            ScrubStrategy::Fake => {
+                assignments.push(format!("{} = v.f{}", column, fake));
+                value_columns.push(format!("f{fake}"));
+                fake += 1;
+            }
+            ScrubStrategy::Shuffle => {}
+        }
+    }
+    if assignments.is_empty() {
+        return Err(ServerError::invalid_input(
+            "No hash, fake or nullify rules to apply",
+        ));
+    }
+
+    let rows = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let mut values = key.clone();
+            if let Some(fakes) = fake_values.get(i) {
+                values.extend(fakes.iter().cloned());
+            }
+            format!("({})", values.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let join = key_columns
+        .iter()
+        .enumerate()
+        .map(|(i, k)| safe_identifier(k).map(|k| format!("t.{} = v.k{}", k, i)))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(" AND ");
+
+    Ok(format!(
+        "UPDATE t SET {} FROM {} AS t INNER JOIN (VALUES {}) AS v ({}) ON {}",
+        assignments.join(", "),
+        table,
+        rows,
+        value_columns.join(", "),
+        join
+    ))
+}
+
+fn escaped_list(columns: &[String], prefix: &str) -> Result<String, ServerError> {
+    Ok(columns
+        .iter()
+        .map(|c| safe_identifier(c).map(|c| format!("{}{}", prefix, c)))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, nullable: bool, max_length: Option<i32>) -> ColumnInfo {
+        ColumnInfo {
+            column_name: name.to_string(),
+            ordinal_position: 0,
+            data_type: data_type.to_string(),
+            max_length,
+            precision: None,
+            scale: None,
+            is_nullable: nullable,
+            default_value: None,
+            is_identity: false,
+            is_computed: false,
+        }
+    }
+
+    fn columns() -> Vec<ColumnInfo> {
+        vec![
+            column("Id", "int", false, None),
+            column("Email", "nvarchar", false, Some(100)),
+            column("Phone", "varchar", true, Some(20)),
+            column("Salary", "int", false, None),
+            column("Notes", "nvarchar", true, Some(-1)),
+        ]
+    }
+
+    fn rules(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(c, s)| (c.to_string(), s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_rules() {
+        let key = vec!["Id".to_string()];
+        let planned = plan_rules(
+            &columns(),
+            &key,
+            &rules(&[("email", "fake"), ("Phone", "hash"), ("Notes", "nullify")]),
+        )
+        .unwrap();
+        assert_eq!(planned.len(), 3);
+        assert_eq!(planned[0].column, "Email");
+        assert!(planned[0].generator.is_some());
+
+        for bad in [
+            rules(&[("Id", "hash")]),
+            rules(&[("Salary", "hash")]),
+            rules(&[("Email", "nullify")]),
+            rules(&[("Missing", "fake")]),
+            rules(&[("Email", "encrypt")]),
+        ] {
+            assert!(plan_rules(&columns(), &key, &bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_statements() {
+        let key = vec!["Id".to_string()];
+        let planned = plan_rules(
+            &columns(),
+            &key,
+            &rules(&[("Email", "fake"), ("Phone", "hash"), ("Salary", "shuffle")]),
+        )
+        .unwrap();
+
+        let hash = hash_expression(&planned[1]).unwrap();
+        assert_eq!(
+            hash,
+            "LEFT(CONVERT(nvarchar(64), HASHBYTES('SHA2_256', CONCAT(@p1, CAST(t.[Phone] AS nvarchar(4000)))), 2), 20)"
+        );
+
+        let update = update_statement(
+            "[dbo].[People]",
+            &key,
+            &planned,
+            &[vec!["1".to_string()], vec!["2".to_string()]],
+            &[
+                vec!["N'a@example.com'".to_string()],
+                vec!["N'b@example.com'".to_string()],
+            ],
+        )
+        .unwrap();
+        assert!(update.starts_with("UPDATE t SET [Email] = v.f0, [Phone] = LEFT("));
+        assert!(update.ends_with(
+            "FROM [dbo].[People] AS t INNER JOIN (VALUES (1, N'a@example.com'), (2, N'b@example.com')) AS v (k0, f0) ON t.[Id] = v.k0"
+        ));
+
+        let shuffle = shuffle_statement("[dbo].[People]", &key, "Salary", Some("Id > 10")).unwrap();
+        assert!(shuffle.contains("ORDER BY NEWID()) AS rn FROM [dbo].[People] WHERE Id > 10"));
+        assert!(shuffle.ends_with("UPDATE target SET [Salary] = source.v FROM target INNER JOIN source ON source.rn = target.rn"));
+
+        let keys = vec!["A".to_string(), "B".to_string()];
+        let batch = key_batch_query(
+            "[dbo].[T]",
+            &keys,
+            Some("x = 1"),
+            Some(&["1".to_string(), "2".to_string()]),
+            100,
+        )
+        .unwrap();
+        assert_eq!(
+            batch,
+            "SELECT TOP (100) t.[A], t.[B] FROM [dbo].[T] AS t WHERE (x = 1) AND \
+             ((t.[A] > 1) OR (t.[A] = 1 AND t.[B] > 2)) ORDER BY t.[A], t.[B]"
+        );
+    }
+}
@@ -242,6 +242,17 @@ pub async fn create_connection(
     Ok(client)
 }
 
+/// Switch a dedicated connection to another database.
+///
+/// Only this connection is affected; pooled connections keep their database.
+pub async fn use_database(conn: &mut RawConnection, database: &str) -> Result<(), ServerError> {
+    let sql = format!("USE [{}]", database.replace(']', "]]"));
+    conn.execute(&sql, &[])
+        .await
+        .map_err(|e| ServerError::query_failed("Failed to switch database", e))?;
+    Ok(())
+}
+
 /// Truncate a string for logging purposes.
 ///
 /// This is a shared utility for safe logging of potentially long strings.
@@ -263,6 +274,7 @@ mod tests {
             host: "localhost".to_string(),
             port: 1433,
             instance: None,
+            resolve_instance_port: false,
             database: Some("master".to_string()),
             auth: AuthConfig::SqlServer {
                 username: "sa".to_string(),
@@ -276,6 +288,11 @@ mod tests {
             mars: false,
             retry: RetryConfig::default(),
             tds_version: TdsVersionConfig::default(),
+            application_intent: Default::default(),
+            multi_subnet_failover: false,
+            failover_partners: Vec::new(),
+            read_pool: false,
+            read_pool_host: None,
         }
     }
 
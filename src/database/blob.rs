@@ -0,0 +1,199 @@
+//! Handling of binary (`varbinary`, `binary`, `image`) column values.
+//!
+//! Query results show binary cells as a truncated hex preview by default so
+//! that a single document or image column cannot flood the response. Full
+//! contents are available as base64, or written to a file by the
+//! `download_blob` tool, which reads the cell in chunks with `SUBSTRING`
+//! instead of holding the whole value in memory.
+
+use crate::constants::DEFAULT_BINARY_PREVIEW_BYTES;
+use crate::database::types::{hex, SqlValue};
+use serde::{Deserialize, Serialize};
+
+// =============================================================================
+// Representation
+// =============================================================================
+
+/// How binary values are returned in query results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryFormat {
+    /// `0x`-prefixed hex, truncated to the preview length (default).
+    #[default]
+    Hex,
+    /// The full value as base64.
+    Base64,
+    /// Only the size of the value, e.g. `<binary 20480 bytes>`.
+    Omit,
+}
+
+impl BinaryFormat {
+    /// Parse a binary format from string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "hex" => Some(Self::Hex),
+            "base64" => Some(Self::Base64),
+            "omit" | "none" => Some(Self::Omit),
+            _ => None,
+        }
+    }
+
+    /// Get the format name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+            Self::Omit => "omit",
+        }
+    }
+
+    /// Generate JSON Schema for this type.
+    pub fn tool_input_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "string",
+            "enum": ["hex", "base64", "omit"],
+            "description": "Representation of binary values: 'hex' (preview truncated to MSSQL_BINARY_PREVIEW_BYTES), 'base64' (full content), or 'omit' (size only)"
+        })
+    }
+}
+
+/// Binary value representation and preview length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryOptions {
+    /// Representation of binary values.
+    pub format: BinaryFormat,
+    /// Bytes shown in a hex preview; 0 shows the full value.
+    pub preview_bytes: usize,
+}
+
+impl Default for BinaryOptions {
+    fn default() -> Self {
+        Self {
+            format: BinaryFormat::Hex,
+            preview_bytes: DEFAULT_BINARY_PREVIEW_BYTES,
+        }
+    }
+}
+
+impl BinaryOptions {
+    /// Render a binary value for a query result.
+    pub fn render(&self, bytes: &[u8]) -> SqlValue {
+        let text = match self.format {
+            BinaryFormat::Hex if self.preview_bytes > 0 && bytes.len() > self.preview_bytes => {
+                format!(
+                    "0x{}... ({} bytes)",
+                    hex::encode(&bytes[..self.preview_bytes]),
+                    bytes.len()
+                )
+            }
+            BinaryFormat::Hex => format!("0x{}", hex::encode(bytes)),
+            BinaryFormat::Base64 => base64::encode(bytes),
+            BinaryFormat::Omit => format!("<binary {} bytes>", bytes.len()),
+        };
+        SqlValue::String(text)
+    }
+}
+
+/// Base64 encoding helper (minimal implementation to avoid extra dependency).
+pub(crate) mod base64 {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn encode(bytes: &[u8]) -> String {
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+}
+
+// =============================================================================
+// Download
+// =============================================================================
+
+/// Query returning the number of rows matched and the size of the cell.
+///
+/// `table` and `column` must already be quoted.
+pub fn blob_info_query(table: &str, column: &str, filter: &str) -> String {
+    format!(
+        "SELECT COUNT_BIG(*) AS row_count, MAX(DATALENGTH({})) AS byte_count\nFROM {}\nWHERE {}",
+        column, table, filter
+    )
+}
+
+/// Query returning `length` bytes of the cell starting at zero-based `offset`.
+pub fn blob_chunk_query(
+    table: &str,
+    column: &str,
+    filter: &str,
+    offset: usize,
+    length: usize,
+) -> String {
+    format!(
+        "SELECT SUBSTRING(CAST({} AS varbinary(max)), {}, {}) AS chunk\nFROM {}\nWHERE {}",
+        column,
+        offset + 1,
+        length,
+        table,
+        filter
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: BinaryFormat, preview_bytes: usize, bytes: &[u8]) -> String {
+        BinaryOptions {
+            format,
+            preview_bytes,
+        }
+        .render(bytes)
+        .to_display_string()
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let bytes = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A];
+        assert_eq!(
+            render(BinaryFormat::Hex, 4, &bytes),
+            "0x89504E47... (6 bytes)"
+        );
+        assert_eq!(render(BinaryFormat::Hex, 0, &bytes), "0x89504E470D0A");
+        assert_eq!(render(BinaryFormat::Hex, 6, &bytes), "0x89504E470D0A");
+        assert_eq!(render(BinaryFormat::Base64, 4, &bytes), "iVBORw0K");
+        assert_eq!(render(BinaryFormat::Omit, 4, &bytes), "<binary 6 bytes>");
+    }
+
+    #[test]
+    fn test_base64_padding() {
+        assert_eq!(base64::encode(b""), "");
+        assert_eq!(base64::encode(b"f"), "Zg==");
+        assert_eq!(base64::encode(b"fo"), "Zm8=");
+        assert_eq!(base64::encode(b"foo"), "Zm9v");
+        assert_eq!(base64::encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_blob_queries() {
+        assert_eq!(
+            blob_chunk_query("[dbo].[Documents]", "[Content]", "[Id] = 7", 1048576, 1024),
+            "SELECT SUBSTRING(CAST([Content] AS varbinary(max)), 1048577, 1024) AS chunk\nFROM [dbo].[Documents]\nWHERE [Id] = 7"
+        );
+        assert!(
+            blob_info_query("[dbo].[Documents]", "[Content]", "[Id] = 7")
+                .starts_with("SELECT COUNT_BIG(*) AS row_count, MAX(DATALENGTH([Content]))")
+        );
+    }
+}
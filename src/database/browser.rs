@@ -0,0 +1,183 @@
+//! SQL Server Browser resolution of named instance ports.
+//!
+//! Named instances usually listen on a dynamic TCP port. The SQL Server Browser
+//! service answers unicast instance requests on UDP 1434 with the connection
+//! details of the instance, including its TCP port (SSRP, `[MC-SQLR]`).
+
+use crate::config::DatabaseConfig;
+use crate::constants::{SQL_BROWSER_PORT, SQL_BROWSER_TIMEOUT};
+use crate::error::ServerError;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+
+/// `CLNT_UCAST_INST`: request the details of a single instance.
+const CLNT_UCAST_INST: u8 = 0x04;
+
+/// `SVR_RESP`: response carrying instance details.
+const SVR_RESP: u8 = 0x05;
+
+/// Largest SSRP response datagram.
+const MAX_RESPONSE_SIZE: usize = 65_535;
+
+/// Resolve the TCP port of the configured named instance, if needed.
+///
+/// Queries SQL Browser when `resolve_instance_port` is set and updates `port`
+/// with the answer. If the lookup fails, the configured port is kept and a
+/// warning is logged, so an explicit `MSSQL_PORT` remains a working fallback.
+pub async fn resolve_instance(config: &mut DatabaseConfig) {
+    let Some(instance) = config
+        .instance
+        .clone()
+        .filter(|_| config.resolve_instance_port)
+    else {
+        return;
+    };
+
+    match resolve_instance_port(&config.host, &instance, SQL_BROWSER_TIMEOUT).await {
+        Ok(port) => {
+            info!(
+                "SQL Browser resolved instance {}\\{} to port {}",
+                config.host, instance, port
+            );
+            config.port = port;
+        }
+        Err(e) => warn!(
+            "SQL Browser lookup for {}\\{} failed, using port {}: {}",
+            config.host, instance, config.port, e
+        ),
+    }
+}
+
+/// Ask the SQL Browser service on `host` for the TCP port of `instance`.
+pub async fn resolve_instance_port(
+    host: &str,
+    instance: &str,
+    timeout: Duration,
+) -> Result<u16, ServerError> {
+    if instance.is_empty() || instance.len() > 32 || !instance.is_ascii() {
+        return Err(ServerError::config(format!(
+            "Invalid instance name '{instance}': expected at most 32 ASCII characters"
+        )));
+    }
+
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, SQL_BROWSER_PORT))
+        .await
+        .map_err(|e| ServerError::connection(format!("Failed to resolve {host}: {e}")))?
+        .collect();
+
+    let mut request = Vec::with_capacity(instance.len() + 2);
+    request.push(CLNT_UCAST_INST);
+    request.extend_from_slice(instance.as_bytes());
+    request.push(0);
+
+    let mut last_error = ServerError::connection(format!("No addresses found for {host}"));
+    for address in addresses {
+        match query_browser(address, &request, timeout).await {
+            Ok(response) => {
+                return parse_browser_response(&response, instance).ok_or_else(|| {
+                    ServerError::connection(format!(
+                        "SQL Browser on {host} did not report a TCP port for instance {instance}"
+                    ))
+                })
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Send one request datagram to `address` and wait for the reply.
+async fn query_browser(
+    address: SocketAddr,
+    request: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>, ServerError> {
+    let local: SocketAddr = if address.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local)
+        .await
+        .map_err(|e| ServerError::connection(format!("Failed to open UDP socket: {e}")))?;
+    socket
+        .connect(address)
+        .await
+        .map_err(|e| ServerError::connection(format!("Failed to reach {address}: {e}")))?;
+    socket
+        .send(request)
+        .await
+        .map_err(|e| ServerError::connection(format!("Failed to query SQL Browser: {e}")))?;
+
+    let mut buffer = vec![0u8; MAX_RESPONSE_SIZE];
+    let received = tokio::time::timeout(timeout, socket.recv(&mut buffer))
+        .await
+        .map_err(|_| {
+            ServerError::connection(format!(
+                "SQL Browser on {address} did not respond within {}ms",
+                timeout.as_millis()
+            ))
+        })?
+        .map_err(|e| ServerError::connection(format!("SQL Browser query failed: {e}")))?;
+    buffer.truncate(received);
+    Ok(buffer)
+}
+
+/// Extract the TCP port of `instance` from an `SVR_RESP` datagram.
+///
+/// The payload is a list of `key;value;...` records separated by `;;`, e.g.
+/// `ServerName;SQL01;InstanceName;SQLEXPRESS;IsClustered;No;Version;16.0.1000.6;tcp;49721;;`.
+pub fn parse_browser_response(response: &[u8], instance: &str) -> Option<u16> {
+    let (&kind, rest) = response.split_first()?;
+    if kind != SVR_RESP || rest.len() < 2 {
+        return None;
+    }
+    let size = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+    let payload = rest.get(2..2 + size).unwrap_or(&rest[2..]);
+    let text = String::from_utf8_lossy(payload);
+
+    text.split(";;").find_map(|record| {
+        let fields: Vec<&str> = record.split(';').collect();
+        let value = |key: &str| {
+            fields
+                .chunks(2)
+                .find(|pair| pair[0].eq_ignore_ascii_case(key))
+                .and_then(|pair| pair.get(1).copied())
+        };
+        value("InstanceName")
+            .filter(|name| name.eq_ignore_ascii_case(instance))
+            .and_then(|_| value("tcp"))
+            .and_then(|port| port.parse().ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(text: &str) -> Vec<u8> {
+        let mut bytes = vec![SVR_RESP];
+        bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(text.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_parse_browser_response() {
+        let data = response(
+            "ServerName;SQL01;InstanceName;SQLEXPRESS;IsClustered;No;Version;16.0.1000.6;\
+             tcp;49721;np;\\\\SQL01\\pipe\\MSSQL$SQLEXPRESS\\sql\\query;;\
+             ServerName;SQL01;InstanceName;OTHER;IsClustered;No;Version;15.0.2000.5;tcp;50000;;",
+        );
+        assert_eq!(parse_browser_response(&data, "sqlexpress"), Some(49721));
+        assert_eq!(parse_browser_response(&data, "OTHER"), Some(50000));
+        assert_eq!(parse_browser_response(&data, "MISSING"), None);
+
+        // Instances with TCP disabled report no port
+        let no_tcp = response("ServerName;SQL01;InstanceName;PIPES;IsClustered;No;;");
+        assert_eq!(parse_browser_response(&no_tcp, "PIPES"), None);
+        assert_eq!(parse_browser_response(&[0x04, 0, 0], "PIPES"), None);
+    }
+}
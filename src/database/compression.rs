@@ -0,0 +1,408 @@
+//! Data compression estimates.
+//!
+//! Wraps `sp_estimate_data_compression_savings`, which copies a sample of each
+//! index into tempdb under the requested setting and scales the result up.
+//! Estimates for every option are grouped per index, the option saving the
+//! most space above a threshold is recommended, and the matching
+//! `ALTER ... REBUILD` or `CREATE CLUSTERED COLUMNSTORE INDEX` statement is
+//! generated. COLUMNSTORE estimates need SQL Server 2019 or later and are only
+//! made for heaps and clustered rowstore indexes.
+
+use crate::database::query::ResultRow;
+use crate::error::ServerError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A compression setting to estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionOption {
+    Row,
+    Page,
+    Columnstore,
+}
+
+impl CompressionOption {
+    /// Parse a tool option (`row`, `page` or `columnstore`).
+    pub fn parse(value: &str) -> Result<Self, ServerError> {
+        match value.trim().to_lowercase().as_str() {
+            "row" => Ok(Self::Row),
+            "page" => Ok(Self::Page),
+            "columnstore" => Ok(Self::Columnstore),
+            other => Err(ServerError::invalid_input(format!(
+                "Unknown compression option '{}' (expected row, page or columnstore)",
+                other
+            ))),
+        }
+    }
+
+    /// `@data_compression` value of the option.
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            Self::Row => "ROW",
+            Self::Page => "PAGE",
+            Self::Columnstore => "COLUMNSTORE",
+        }
+    }
+
+    /// Whether the option can be estimated for an index of this type.
+    pub fn applies_to(self, index_type: &str) -> bool {
+        match self {
+            _ if index_type.contains("COLUMNSTORE") => false,
+            Self::Columnstore => matches!(index_type, "HEAP" | "CLUSTERED"),
+            Self::Row | Self::Page => true,
+        }
+    }
+}
+
+/// Estimated size of an index under one option, summed over its partitions.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompressionEstimate {
+    pub option: CompressionOption,
+    pub current_kb: i64,
+    pub estimated_kb: i64,
+    pub savings_percent: f64,
+}
+
+/// Compression state, estimates and recommendation for one index (or heap).
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexCompression {
+    pub schema: String,
+    pub table: String,
+    pub index_id: i64,
+    /// Index name (`None` for a heap).
+    pub index: Option<String>,
+    /// `sys.indexes.type_desc`, e.g. `CLUSTERED`.
+    pub index_type: String,
+    /// Backs a primary key or unique constraint.
+    #[serde(skip)]
+    pub is_constraint: bool,
+    /// Current `data_compression_desc`, or `MIXED` across partitions.
+    pub current_compression: String,
+    pub partition_count: i64,
+    pub row_count: i64,
+    pub estimates: Vec<CompressionEstimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommendation: Option<CompressionOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl IndexCompression {
+    /// Build from a row of [`indexes_query`].
+    pub fn from_row(schema: &str, table: &str, row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+        let flag = |column: &str| matches!(text(column).as_deref(), Some("1" | "true"));
+
+        Some(Self {
+            schema: schema.to_string(),
+            table: table.to_string(),
+            index_id: number("index_id")?,
+            index: text("index_name"),
+            index_type: text("index_type").unwrap_or_default(),
+            is_constraint: flag("is_primary_key") || flag("is_unique_constraint"),
+            current_compression: text("current_compression").unwrap_or_else(|| "NONE".into()),
+            partition_count: number("partition_count").unwrap_or(1),
+            row_count: number("row_count").unwrap_or(0),
+            estimates: Vec::new(),
+            recommendation: None,
+            sql: None,
+            note: None,
+        })
+    }
+
+    /// Pick the option with the largest savings of at least
+    /// `min_savings_percent` and generate its statement.
+    pub fn recommend(&mut self, min_savings_percent: f64, online: bool) {
+        let best = self
+            .estimates
+            .iter()
+            .filter(|e| e.savings_percent >= min_savings_percent)
+            .filter(|e| {
+                !e.option
+                    .as_sql()
+                    .eq_ignore_ascii_case(&self.current_compression)
+            })
+            .max_by(|a, b| a.savings_percent.total_cmp(&b.savings_percent))
+            .map(|e| e.option);
+        let Some(option) = best else {
+            return;
+        };
+        self.recommendation = Some(option);
+        self.sql = compression_sql(self, option, online);
+        if option == CompressionOption::Columnstore {
+            self.note = Some(if self.sql.is_none() {
+                "The clustered index backs a constraint; drop the constraint (and recreate it \
+                 as nonclustered) before creating a clustered columnstore index."
+                    .to_string()
+            } else {
+                "Columnstore suits large, scan-heavy tables; singleton lookups and frequent \
+                 updates get slower."
+                    .to_string()
+            });
+        }
+    }
+}
+
+/// Query returning the indexes of a table with their current compression.
+pub fn indexes_query(schema: &str, table: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            i.index_id,
+            i.name AS index_name,
+            i.type_desc AS index_type,
+            i.is_primary_key,
+            i.is_unique_constraint,
+            CASE WHEN MIN(p.data_compression_desc) = MAX(p.data_compression_desc)
+                 THEN MIN(p.data_compression_desc) ELSE 'MIXED' END AS current_compression,
+            COUNT(*) AS partition_count,
+            SUM(p.rows) AS row_count
+        FROM sys.indexes i
+        INNER JOIN sys.partitions p ON p.object_id = i.object_id AND p.index_id = i.index_id
+        WHERE i.object_id = OBJECT_ID(N'[{}].[{}]')
+        AND i.is_hypothetical = 0
+        AND i.is_disabled = 0
+        GROUP BY i.index_id, i.name, i.type_desc, i.is_primary_key, i.is_unique_constraint
+        ORDER BY i.index_id
+    "#,
+        schema.replace(']', "]]").replace('\'', "''"),
+        table.replace(']', "]]").replace('\'', "''")
+    )
+}
+
+/// Call of `sp_estimate_data_compression_savings` for all indexes and
+/// partitions of a table.
+pub fn estimate_query(schema: &str, table: &str, option: CompressionOption) -> String {
+    format!(
+        "EXEC sys.sp_estimate_data_compression_savings @schema_name = N'{}', \
+         @object_name = N'{}', @index_id = NULL, @partition_number = NULL, \
+         @data_compression = '{}'",
+        schema.replace('\'', "''"),
+        table.replace('\'', "''"),
+        option.as_sql()
+    )
+}
+
+/// Add the estimates of one option, summing partitions, to the indexes it
+/// applies to.
+pub fn add_estimates(
+    indexes: &mut [IndexCompression],
+    option: CompressionOption,
+    rows: &[ResultRow],
+) {
+    let kb = |row: &ResultRow, column: &str| {
+        row.get(column)
+            .and_then(|v| v.to_display_string().parse::<i64>().ok())
+            .unwrap_or(0)
+    };
+    let mut sizes: BTreeMap<i64, (i64, i64)> = BTreeMap::new();
+    for row in rows {
+        let Some(index_id) = row
+            .get("index_id")
+            .and_then(|v| v.to_display_string().parse::<i64>().ok())
+        else {
+            continue;
+        };
+        let size = sizes.entry(index_id).or_default();
+        size.0 += kb(row, "size_with_current_compression_setting(KB)");
+        size.1 += kb(row, "size_with_requested_compression_setting(KB)");
+    }
+
+    for index in indexes
+        .iter_mut()
+        .filter(|i| option.applies_to(&i.index_type))
+    {
+        if let Some(&(current_kb, estimated_kb)) = sizes.get(&index.index_id) {
+            let savings_percent = if current_kb > 0 {
+                ((current_kb - estimated_kb) as f64 * 1000.0 / current_kb as f64).round() / 10.0
+            } else {
+                0.0
+            };
+            index.estimates.push(CompressionEstimate {
+                option,
+                current_kb,
+                estimated_kb,
+                savings_percent,
+            });
+        }
+    }
+}
+
+/// Statement applying an option to an index, or `None` when it can't be
+/// applied in one statement (a clustered index backing a constraint can't be
+/// replaced by a columnstore index).
+pub fn compression_sql(
+    index: &IndexCompression,
+    option: CompressionOption,
+    online: bool,
+) -> Option<String> {
+    let quote = |name: &str| format!("[{}]", name.replace(']', "]]"));
+    let table = format!("{}.{}", quote(&index.schema), quote(&index.table));
+    match option {
+        CompressionOption::Row | CompressionOption::Page => {
+            let target = match &index.index {
+                Some(name) if index.index_type != "HEAP" => {
+                    format!("ALTER INDEX {} ON {}", quote(name), table)
+                }
+                _ => format!("ALTER TABLE {}", table),
+            };
+            let partitions = if index.partition_count > 1 {
+                " PARTITION = ALL"
+            } else {
+                ""
+            };
+            Some(format!(
+                "{} REBUILD{} WITH (DATA_COMPRESSION = {}{})",
+                target,
+                partitions,
+                option.as_sql(),
+                if online { ", ONLINE = ON" } else { "" }
+            ))
+        }
+        CompressionOption::Columnstore => {
+            let online = if online { "ONLINE = ON" } else { "" };
+            match &index.index {
+                Some(_) if index.is_constraint => None,
+                Some(name) if index.index_type == "CLUSTERED" => Some(format!(
+                    "CREATE CLUSTERED COLUMNSTORE INDEX {} ON {} WITH (DROP_EXISTING = ON{})",
+                    quote(name),
+                    table,
+                    if online.is_empty() {
+                        String::new()
+                    } else {
+                        format!(", {}", online)
+                    }
+                )),
+                _ => Some(format!(
+                    "CREATE CLUSTERED COLUMNSTORE INDEX {} ON {}{}",
+                    quote(&format!("CCI_{}", index.table)),
+                    table,
+                    if online.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" WITH ({})", online)
+                    }
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn index(index_id: i64, name: Option<&str>, index_type: &str) -> IndexCompression {
+        IndexCompression {
+            schema: "sales".to_string(),
+            table: "Orders".to_string(),
+            index_id,
+            index: name.map(str::to_string),
+            index_type: index_type.to_string(),
+            is_constraint: false,
+            current_compression: "NONE".to_string(),
+            partition_count: 1,
+            row_count: 1_000_000,
+            estimates: Vec::new(),
+            recommendation: None,
+            sql: None,
+            note: None,
+        }
+    }
+
+    fn estimate_row(index_id: i64, current: i64, requested: i64) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert("index_id".to_string(), SqlValue::I32(index_id as i32));
+        row.insert(
+            "size_with_current_compression_setting(KB)".to_string(),
+            SqlValue::I64(current),
+        );
+        row.insert(
+            "size_with_requested_compression_setting(KB)".to_string(),
+            SqlValue::I64(requested),
+        );
+        row
+    }
+
+    #[test]
+    fn test_add_estimates_and_recommend() {
+        let mut indexes = vec![
+            index(1, Some("CIX_Orders"), "CLUSTERED"),
+            index(2, Some("IX_Date"), "NONCLUSTERED"),
+        ];
+        // Two partitions of the clustered index are summed
+        let page = [
+            estimate_row(1, 600, 200),
+            estimate_row(1, 400, 200),
+            estimate_row(2, 100, 90),
+        ];
+        add_estimates(&mut indexes, CompressionOption::Page, &page);
+        add_estimates(
+            &mut indexes,
+            CompressionOption::Columnstore,
+            &[estimate_row(1, 1000, 100), estimate_row(2, 100, 10)],
+        );
+        assert_eq!(indexes[0].estimates.len(), 2);
+        assert_eq!(indexes[0].estimates[0].savings_percent, 60.0);
+        assert_eq!(indexes[1].estimates.len(), 1);
+
+        for index in &mut indexes {
+            index.recommend(20.0, false);
+        }
+        assert_eq!(
+            indexes[0].recommendation,
+            Some(CompressionOption::Columnstore)
+        );
+        assert_eq!(
+            indexes[0].sql.as_deref(),
+            Some(
+                "CREATE CLUSTERED COLUMNSTORE INDEX [CIX_Orders] ON [sales].[Orders] WITH (DROP_EXISTING = ON)"
+            )
+        );
+        // 10% savings stay below the threshold
+        assert!(indexes[1].recommendation.is_none());
+    }
+
+    #[test]
+    fn test_compression_sql() {
+        let mut heap = index(0, None, "HEAP");
+        heap.partition_count = 4;
+        assert_eq!(
+            compression_sql(&heap, CompressionOption::Page, true).as_deref(),
+            Some("ALTER TABLE [sales].[Orders] REBUILD PARTITION = ALL WITH (DATA_COMPRESSION = PAGE, ONLINE = ON)")
+        );
+        assert_eq!(
+            compression_sql(&heap, CompressionOption::Columnstore, false).as_deref(),
+            Some("CREATE CLUSTERED COLUMNSTORE INDEX [CCI_Orders] ON [sales].[Orders]")
+        );
+
+        let ix = index(2, Some("IX_Date"), "NONCLUSTERED");
+        assert_eq!(
+            compression_sql(&ix, CompressionOption::Row, false).as_deref(),
+            Some("ALTER INDEX [IX_Date] ON [sales].[Orders] REBUILD WITH (DATA_COMPRESSION = ROW)")
+        );
+        assert!(!CompressionOption::Columnstore.applies_to("NONCLUSTERED"));
+        assert!(!CompressionOption::Page.applies_to("CLUSTERED COLUMNSTORE"));
+
+        let mut pk = index(1, Some("PK_Orders"), "CLUSTERED");
+        pk.is_constraint = true;
+        pk.estimates.push(CompressionEstimate {
+            option: CompressionOption::Columnstore,
+            current_kb: 1000,
+            estimated_kb: 100,
+            savings_percent: 90.0,
+        });
+        pk.recommend(20.0, false);
+        assert!(pk.sql.is_none());
+        assert!(pk.note.is_some());
+    }
+}
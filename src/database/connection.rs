@@ -1,13 +1,138 @@
 //! Connection pool management for SQL Server.
 
 use super::auth::create_config;
-use crate::config::DatabaseConfig;
+use crate::config::{ApplicationIntent, DatabaseConfig};
+use crate::constants::DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS;
 use crate::error::ServerError;
-use mssql_driver_pool::{Pool, PoolBuilder, PooledConnection};
-use tracing::{debug, info};
+use futures_util::TryStreamExt;
+use mssql_driver_pool::{Pool, PoolBuilder, PoolError, PooledConnection};
+use parking_lot::RwLock;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
 
-/// Type alias for the connection pool.
-pub type ConnectionPool = Pool;
+/// Connection pool whose connections can be re-established with new credentials.
+///
+/// The driver pool keeps the login it was built with for its whole lifetime,
+/// so rotating credentials swaps in a new pool. Connections checked out from
+/// the old pool finish their work and are closed when returned; every
+/// checkout after the swap gets a connection from the new pool.
+pub struct ConnectionPool {
+    current: RwLock<Arc<Pool>>,
+    pool_config: mssql_driver_pool::PoolConfig,
+}
+
+impl ConnectionPool {
+    /// Get a connection, waiting until one is available.
+    pub async fn get(&self) -> Result<PooledConn, PoolError> {
+        let pool = self.current();
+        pool.get().await
+    }
+
+    /// Get an idle connection without waiting.
+    pub fn try_get(&self) -> Result<Option<PooledConn>, PoolError> {
+        self.current.read().try_get()
+    }
+
+    /// Status of the current pool.
+    pub fn status(&self) -> mssql_driver_pool::PoolStatus {
+        self.current.read().status()
+    }
+
+    /// Metrics of the current pool, since it was created.
+    pub fn metrics(&self) -> mssql_driver_pool::PoolMetrics {
+        self.current.read().metrics()
+    }
+
+    /// Close the pool.
+    pub async fn close(&self) {
+        let pool = self.current();
+        pool.close().await;
+    }
+
+    /// Whether the pool is closed.
+    pub fn is_closed(&self) -> bool {
+        self.current.read().is_closed()
+    }
+
+    /// Build a pool like this one that connects with `client_config`.
+    ///
+    /// The new pool is warmed up and checked to log in, so it can be swapped
+    /// in with [`swap`](Self::swap) knowing the credentials work.
+    pub async fn connect_replacement(
+        &self,
+        client_config: mssql_client::Config,
+    ) -> Result<Pool, ServerError> {
+        let pool = PoolBuilder::new()
+            .client_config(client_config)
+            .pool_config(self.pool_config.clone())
+            .build()
+            .await
+            .map_err(|e| {
+                ServerError::connection_with_source("Failed to create connection pool", e)
+            })?;
+        if let Err(e) = pool.get().await {
+            pool.close().await;
+            return Err(ServerError::connection(format!(
+                "Failed to establish initial connection: {}",
+                e
+            )));
+        }
+        Ok(pool)
+    }
+
+    /// Swap in `pool`, returning the pool it replaces.
+    ///
+    /// Every checkout after the swap gets a connection from `pool`; pass the
+    /// old pool to [`retire`](Self::retire) to close it.
+    pub fn swap(&self, pool: Pool) -> Arc<Pool> {
+        std::mem::replace(&mut *self.current.write(), Arc::new(pool))
+    }
+
+    /// Close a pool that was swapped out.
+    ///
+    /// Its idle connections are closed at once; returns the number still
+    /// checked out, which are closed when their work finishes.
+    pub async fn retire(old: Arc<Pool>) -> u32 {
+        for _ in 0..old.status().available {
+            match old.try_get() {
+                Ok(Some(conn)) => drop(conn.detach()),
+                _ => break,
+            }
+        }
+        // Connections returned to a closed pool are never handed out again
+        old.close().await;
+        old.status().in_use
+    }
+
+    pub(crate) fn current(&self) -> Arc<Pool> {
+        Arc::clone(&self.current.read())
+    }
+}
+
+#[cfg(test)]
+impl ConnectionPool {
+    /// Wrap `pool`, building replacements with the default pool settings.
+    pub(crate) fn from_pool(pool: Pool) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(pool)),
+            pool_config: mssql_driver_pool::PoolConfig::new(),
+        }
+    }
+
+    /// A pool that opens no connection until one is requested, for tests
+    /// without a server.
+    pub(crate) async fn unconnected() -> Pool {
+        PoolBuilder::new()
+            .client_config(mssql_client::Config::new())
+            .pool_config(mssql_driver_pool::PoolConfig::new().min_connections(0))
+            .build()
+            .await
+            .unwrap()
+    }
+}
 
 /// Type alias for a pooled connection.
 pub type PooledConn = PooledConnection;
@@ -22,14 +147,27 @@ pub async fn create_pool(config: &DatabaseConfig) -> Result<ConnectionPool, Serv
     // Create base configuration
     let client_config = create_config(config).await?;
 
-    // Build the pool with settings from our config
-    let pool = PoolBuilder::new()
-        .client_config(client_config)
+    // The pool's reaper runs at the health check interval, which must be non-zero
+    // even when background probing is disabled
+    let reaper_interval = if config.pool.health_check_interval.is_zero() {
+        Duration::from_secs(DEFAULT_POOL_HEALTH_CHECK_INTERVAL_SECS)
+    } else {
+        config.pool.health_check_interval
+    };
+
+    // Build the pool with settings from our config; creating it warms up
+    // min_connections connections
+    let pool_config = mssql_driver_pool::PoolConfig::new()
         .min_connections(config.pool.min_connections)
         .max_connections(config.pool.max_connections)
         .idle_timeout(config.pool.idle_timeout)
         .connection_timeout(config.pool.connection_timeout)
-        .sp_reset_connection(true) // Enable connection state cleanup
+        .max_lifetime(config.pool.max_lifetime)
+        .health_check_interval(reaper_interval)
+        .sp_reset_connection(true); // Enable connection state cleanup
+    let pool = PoolBuilder::new()
+        .client_config(client_config)
+        .pool_config(pool_config.clone())
         .build()
         .await
         .map_err(|e| ServerError::connection_with_source("Failed to create connection pool", e))?;
@@ -43,8 +181,287 @@ pub async fn create_pool(config: &DatabaseConfig) -> Result<ConnectionPool, Serv
         // Connection dropped here, releasing borrow and returning to pool
     }
 
-    info!("Connection pool created successfully");
-    Ok(pool)
+    info!(
+        "Connection pool created successfully ({} connections established)",
+        pool.status().total
+    );
+    Ok(ConnectionPool {
+        current: RwLock::new(Arc::new(pool)),
+        pool_config,
+    })
+}
+
+/// Read-only routing target of the primary replica for the current database.
+///
+/// Mirrors the listener's routing for `ApplicationIntent=ReadOnly`: the first
+/// connected replica of the primary's read-only routing list that accepts reads.
+const READ_ONLY_ROUTING_QUERY: &str = r#"
+    SELECT TOP (1) sec.read_only_routing_url
+    FROM sys.dm_hadr_availability_replica_states AS local_state
+    JOIN sys.availability_read_only_routing_lists AS rl
+        ON rl.replica_id = local_state.replica_id
+    JOIN sys.availability_replicas AS sec
+        ON sec.replica_id = rl.read_only_replica_id
+    JOIN sys.dm_hadr_availability_replica_states AS sec_state
+        ON sec_state.replica_id = sec.replica_id
+    WHERE local_state.is_local = 1
+        AND local_state.role = 1
+        AND sec_state.connected_state = 1
+        AND sec.secondary_role_allow_connections IN (1, 2)
+        AND sec.read_only_routing_url IS NOT NULL
+        AND (
+            NOT EXISTS (
+                SELECT 1 FROM sys.dm_hadr_database_replica_states
+                WHERE is_local = 1 AND database_id = DB_ID()
+            )
+            OR local_state.group_id IN (
+                SELECT group_id FROM sys.dm_hadr_database_replica_states
+                WHERE is_local = 1 AND database_id = DB_ID()
+            )
+        )
+    ORDER BY rl.routing_priority
+"#;
+
+/// Create a connection pool, choosing the endpoint to connect to first.
+///
+/// Tries the configured host and then each failover partner. With multi-subnet
+/// failover the first address of a host that accepts a TCP connection is used,
+/// and with read-only intent the pool moves to the primary's read-only routing
+/// target when one is available. `config` is updated to the endpoint in use, so
+/// connections created from it later reach the same server.
+pub async fn connect_pool(config: &mut DatabaseConfig) -> Result<ConnectionPool, ServerError> {
+    let mut candidates = vec![(config.host.clone(), config.port)];
+    for partner in &config.failover_partners {
+        candidates.push(parse_endpoint(partner, config.port)?);
+    }
+
+    let mut last_error = None;
+    for (index, (host, port)) in candidates.into_iter().enumerate() {
+        let mut candidate = config.clone();
+        candidate.host = host;
+        candidate.port = port;
+        if index > 0 {
+            // A partner is a different server, not another port of the instance
+            candidate.instance = None;
+            info!(
+                "Trying failover partner {}:{}",
+                candidate.host, candidate.port
+            );
+        }
+
+        if config.multi_subnet_failover {
+            match first_reachable_address(&candidate).await {
+                Ok(address) => candidate.host = address.ip().to_string(),
+                Err(e) => {
+                    warn!("{}", e);
+                    last_error = Some(e);
+                    continue;
+                }
+            }
+        }
+
+        let pool = match create_pool(&candidate).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                warn!(
+                    "Connection to {}:{} failed: {}",
+                    candidate.host, candidate.port, e
+                );
+                last_error = Some(e);
+                continue;
+            }
+        };
+
+        let pool = if config.application_intent == ApplicationIntent::ReadOnly {
+            route_read_only(pool, &mut candidate).await
+        } else {
+            pool
+        };
+        *config = candidate;
+        return Ok(pool);
+    }
+
+    Err(last_error.unwrap_or_else(|| ServerError::connection("No server to connect to")))
+}
+
+/// Create the pool for read-only work, if configured.
+///
+/// Connects with read-only intent to `read_pool_host` when given, or else to
+/// the read-only routing target of the server `config` is connected to. The
+/// read pool is optional: when it cannot be created or there is no routing
+/// target, read-only work stays on the primary pool. Returns the pool and the
+/// configuration of the endpoint it connected to.
+pub async fn connect_read_pool(
+    config: &DatabaseConfig,
+) -> Option<(ConnectionPool, DatabaseConfig)> {
+    if !config.read_pool {
+        return None;
+    }
+    let mut read_config = config.clone();
+    read_config.application_intent = ApplicationIntent::ReadOnly;
+    if let Some(endpoint) = &config.read_pool_host {
+        let (host, port) = match parse_endpoint(endpoint, config.port) {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                warn!("Read pool disabled: {}", e);
+                return None;
+            }
+        };
+        read_config.host = host;
+        read_config.port = port;
+        read_config.instance = None;
+        read_config.failover_partners.clear();
+    }
+
+    match connect_pool(&mut read_config).await {
+        Ok(_) if read_config.host == config.host && read_config.port == config.port => {
+            info!("No read-only routing target; read-only tools use the primary pool");
+            None
+        }
+        Ok(pool) => {
+            info!(
+                "Read-only tools use {}:{}",
+                read_config.host, read_config.port
+            );
+            Some((pool, read_config))
+        }
+        Err(e) => {
+            warn!(
+                "Read pool unavailable, read-only tools use the primary pool: {}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Move a pool connected to a primary replica to its read-only routing target.
+///
+/// Keeps the original pool when the server is not a primary, has no routing
+/// target, or the target cannot be reached.
+async fn route_read_only(pool: ConnectionPool, config: &mut DatabaseConfig) -> ConnectionPool {
+    let url = match query_routing_url(&pool).await {
+        Ok(Some(url)) => url,
+        Ok(None) => {
+            debug!("No read-only routing target; staying on {}", config.host);
+            return pool;
+        }
+        Err(e) => {
+            warn!(
+                "Read-only routing lookup failed, staying on {}: {}",
+                config.host, e
+            );
+            return pool;
+        }
+    };
+
+    let Some((host, port)) = parse_routing_url(&url) else {
+        warn!("Ignoring unrecognized read-only routing URL '{}'", url);
+        return pool;
+    };
+    let mut routed = config.clone();
+    routed.host = host;
+    routed.port = port;
+    routed.instance = None;
+
+    match create_pool(&routed).await {
+        Ok(routed_pool) => {
+            info!("Read-only intent routed to {}:{}", routed.host, routed.port);
+            *config = routed;
+            routed_pool
+        }
+        Err(e) => {
+            warn!(
+                "Read-only routing target {}:{} unreachable, staying on {}: {}",
+                routed.host, routed.port, config.host, e
+            );
+            pool
+        }
+    }
+}
+
+async fn query_routing_url(pool: &ConnectionPool) -> Result<Option<String>, ServerError> {
+    let mut conn = pool.get().await.map_err(|e| {
+        ServerError::connection(format!("Failed to get connection from pool: {}", e))
+    })?;
+    let rows: Vec<mssql_client::Row> = conn
+        .query(READ_ONLY_ROUTING_QUERY, &[])
+        .await
+        .map_err(|e| ServerError::query_error(format!("Routing query failed: {}", e)))?
+        .try_collect()
+        .await
+        .map_err(|e| ServerError::query_error(format!("Routing query failed: {}", e)))?;
+    Ok(rows.first().and_then(|row| row.try_get::<String>(0)))
+}
+
+/// Connect to every address of the host at once and return the first that answers.
+async fn first_reachable_address(config: &DatabaseConfig) -> Result<SocketAddr, ServerError> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((config.host.as_str(), config.port))
+        .await
+        .map_err(|e| ServerError::connection(format!("Failed to resolve {}: {e}", config.host)))?
+        .collect();
+
+    let timeout = config.timeouts.connect_timeout;
+    let attempts: Vec<_> = addresses
+        .into_iter()
+        .map(|address| {
+            Box::pin(async move {
+                match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+                    Ok(Ok(_)) => Ok(address),
+                    Ok(Err(e)) => Err(format!("{address}: {e}")),
+                    Err(_) => Err(format!("{address}: timed out")),
+                }
+            })
+        })
+        .collect();
+    if attempts.is_empty() {
+        return Err(ServerError::connection(format!(
+            "No addresses found for {}",
+            config.host
+        )));
+    }
+
+    match futures_util::future::select_ok(attempts).await {
+        Ok((address, _)) => {
+            debug!(
+                "Multi-subnet failover selected {} for {}",
+                address, config.host
+            );
+            Ok(address)
+        }
+        Err(e) => Err(ServerError::connection(format!(
+            "No address of {} accepted a connection (last error {})",
+            config.host, e
+        ))),
+    }
+}
+
+/// Parse a `host[:port]` endpoint, with IPv6 addresses in brackets.
+fn parse_endpoint(endpoint: &str, default_port: u16) -> Result<(String, u16), ServerError> {
+    let endpoint = endpoint.trim();
+    let port_separator = match endpoint.rfind(']') {
+        Some(end) => endpoint[end..].rfind(':').map(|i| end + i),
+        None => endpoint.rfind(':'),
+    };
+    match port_separator {
+        Some(i) => {
+            let port = endpoint[i + 1..].parse().map_err(|_| {
+                ServerError::config(format!("Invalid port in failover partner '{endpoint}'"))
+            })?;
+            Ok((endpoint[..i].trim_matches(['[', ']']).to_string(), port))
+        }
+        None => Ok((endpoint.trim_matches(['[', ']']).to_string(), default_port)),
+    }
+}
+
+/// Parse a replica routing URL such as `TCP://sql02.corp.local:1433`.
+fn parse_routing_url(url: &str) -> Option<(String, u16)> {
+    let endpoint = url.trim();
+    let endpoint = endpoint
+        .get(..6)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("tcp://"))
+        .map(|_| &endpoint[6..])?;
+    parse_endpoint(endpoint.trim_end_matches('/'), 1433).ok()
 }
 
 /// Get pool health status.
@@ -81,6 +498,7 @@ mod tests {
             host: "localhost".to_string(),
             port: 1433,
             instance: None,
+            resolve_instance_port: false,
             database: Some("master".to_string()),
             auth: AuthConfig::SqlServer {
                 username: "sa".to_string(),
@@ -94,9 +512,32 @@ mod tests {
             mars: false,
             retry: RetryConfig::default(),
             tds_version: TdsVersionConfig::default(),
+            application_intent: Default::default(),
+            multi_subnet_failover: false,
+            failover_partners: Vec::new(),
+            read_pool: false,
+            read_pool_host: None,
         }
     }
 
+    #[test]
+    fn test_parse_endpoints() {
+        assert_eq!(
+            parse_endpoint("sql02:1450", 1433).unwrap(),
+            ("sql02".to_string(), 1450)
+        );
+        assert_eq!(
+            parse_endpoint("[fe80::1]", 1433).unwrap(),
+            ("fe80::1".to_string(), 1433)
+        );
+        assert!(parse_endpoint("sql02:port", 1433).is_err());
+        assert_eq!(
+            parse_routing_url("TCP://sql03.corp.local:1433"),
+            Some(("sql03.corp.local".to_string(), 1433))
+        );
+        assert_eq!(parse_routing_url("sql03:1433"), None);
+    }
+
     #[test]
     fn test_pool_config() {
         let config = test_config();
@@ -0,0 +1,480 @@
+//! Table copies and schema clones.
+//!
+//! Copies are scripted from the source's [`TableDefinition`]: the definition
+//! is renamed, stripped of what wasn't asked for, and rendered by the
+//! scripting module. Constraint names are unique per schema, so a copy in the
+//! source's own schema gets its key, check, default and foreign key names
+//! rewritten. Rows are copied with `INSERT ... SELECT`, paged over a unique
+//! key when there is one.
+//!
+//! Module definitions (views, functions, procedures, triggers) are cloned as
+//! text: references qualified with the source schema are rewritten and the
+//! created object is named in the target schema. Unqualified references
+//! inside a definition still resolve through the caller's default schema.
+
+use crate::database::dependencies::DependencyEdge;
+use crate::database::scripting::{ScriptIndex, TableDefinition};
+use crate::error::ServerError;
+use crate::security::safe_identifier;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Longest identifier SQL Server accepts.
+const MAX_NAME_LENGTH: usize = 128;
+
+/// Matches the name of the object a module creates, and the table of a
+/// trigger's `ON` clause.
+static MODULE_NAME: Lazy<Regex> = Lazy::new(|| {
+    let part = r#"(?:\[(?:[^\]]|\]\])+\]|"[^"]+"|[\w@#$]+)"#;
+    let name = format!(r"{part}(?:\s*\.\s*{part})?");
+    Regex::new(&format!(
+        concat!(
+            r"(?is)^((?:\s|--[^\n]*\n|/\*.*?\*/)*CREATE\s+(?:OR\s+ALTER\s+)?",
+            r"(?:PROC|PROCEDURE|VIEW|FUNCTION|TRIGGER)\s+)({name})(\s+ON\s+{name})?"
+        ),
+        name = name
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid module name pattern: {}", e))
+});
+
+/// What a copy keeps besides columns.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CopyOptions {
+    /// Primary key, unique, check, default and foreign key constraints.
+    pub include_constraints: bool,
+    /// Indexes that don't back a constraint.
+    pub include_indexes: bool,
+    /// Triggers, with references to the source schema rewritten. Only
+    /// meaningful when copying into another schema.
+    pub include_triggers: bool,
+}
+
+/// Definition of a copy of `source` named `schema.name`.
+pub fn copy_definition(
+    source: &TableDefinition,
+    schema: &str,
+    name: &str,
+    options: &CopyOptions,
+) -> TableDefinition {
+    let mut copy = source.clone();
+    copy.schema = schema.to_string();
+    copy.name = name.to_string();
+
+    if !options.include_constraints {
+        copy.indexes.retain(|i| !i.is_constraint());
+        copy.checks.clear();
+        copy.foreign_keys.clear();
+        for column in &mut copy.columns {
+            column.default = None;
+        }
+    }
+    if !options.include_indexes {
+        copy.indexes.retain(ScriptIndex::is_constraint);
+    }
+    if options.include_triggers {
+        for trigger in &mut copy.triggers {
+            let definition = rewrite_schema(&trigger.definition, &source.schema, schema);
+            trigger.definition = qualify_module(&definition, schema, &trigger.name, Some(name));
+        }
+    } else {
+        copy.triggers.clear();
+    }
+
+    if source.schema.eq_ignore_ascii_case(schema) {
+        let rename = |constraint: &str| constraint_name(constraint, &source.name, name);
+        for index in copy.indexes.iter_mut().filter(|i| i.is_constraint()) {
+            index.name = rename(&index.name);
+        }
+        for check in &mut copy.checks {
+            check.name = rename(&check.name);
+        }
+        for key in &mut copy.foreign_keys {
+            key.name = rename(&key.name);
+        }
+        for column in &mut copy.columns {
+            if let Some((constraint, _)) = &mut column.default {
+                *constraint = rename(constraint);
+            }
+        }
+    }
+    copy
+}
+
+/// Name of a constraint on a copy: the source table's name inside it is
+/// replaced by the copy's (`PK_Orders` becomes `PK_Orders_2024`), otherwise
+/// the copy's name is appended.
+pub fn constraint_name(constraint: &str, source: &str, target: &str) -> String {
+    let pattern = Regex::new(&format!("(?i){}", regex::escape(source)));
+    let renamed = match pattern {
+        Ok(re) if re.is_match(constraint) => {
+            re.replace(constraint, regex::NoExpand(target)).into_owned()
+        }
+        _ => format!("{}_{}", constraint, target),
+    };
+    renamed.chars().take(MAX_NAME_LENGTH).collect()
+}
+
+/// Point foreign keys that reference tables in `from` at the same tables in `to`.
+pub fn retarget_foreign_keys(definition: &mut TableDefinition, from: &str, to: &str) {
+    for key in &mut definition.foreign_keys {
+        if key.referenced_schema.eq_ignore_ascii_case(from) {
+            key.referenced_schema = to.to_string();
+        }
+    }
+}
+
+/// Rewrite references qualified with schema `from` (`from.x`, `[from].x`,
+/// `"from".x`) to `[to].x`. Unqualified and three-part names are left alone.
+pub fn rewrite_schema(definition: &str, from: &str, to: &str) -> String {
+    let pattern = format!(
+        r#"(?i)(^|[^\w\]@#$.])(\[{}\]|"{}"|{})(\s*\.)"#,
+        regex::escape(&from.replace(']', "]]")),
+        regex::escape(from),
+        regex::escape(from)
+    );
+    let Ok(re) = Regex::new(&pattern) else {
+        return definition.to_string();
+    };
+    let replacement = format!("[{}]", to.replace(']', "]]"));
+    re.replace_all(definition, |caps: &regex::Captures| {
+        format!("{}{}{}", &caps[1], replacement, &caps[3])
+    })
+    .into_owned()
+}
+
+/// Name the object a module definition creates `[schema].[name]`, and the
+/// table of a trigger `[schema].[parent]`, so unqualified definitions don't
+/// land in the caller's default schema.
+pub fn qualify_module(definition: &str, schema: &str, name: &str, parent: Option<&str>) -> String {
+    let quote = |part: &str| {
+        format!(
+            "[{}].[{}]",
+            schema.replace(']', "]]"),
+            part.replace(']', "]]")
+        )
+    };
+    MODULE_NAME
+        .replace(definition, |caps: &regex::Captures| {
+            let on = match (caps.get(3), parent) {
+                (Some(_), Some(parent)) => format!(" ON {}", quote(parent)),
+                (Some(on), None) => on.as_str().to_string(),
+                (None, _) => String::new(),
+            };
+            format!("{}{}{}", &caps[1], quote(name), on)
+        })
+        .into_owned()
+}
+
+/// Columns a copy can insert: everything but computed and rowversion columns.
+pub fn insert_columns(definition: &TableDefinition) -> Vec<String> {
+    definition
+        .columns
+        .iter()
+        .filter(|c| {
+            c.computed.is_none()
+                && !matches!(
+                    c.type_name.to_lowercase().as_str(),
+                    "timestamp" | "rowversion"
+                )
+        })
+        .map(|c| c.name.clone())
+        .collect()
+}
+
+/// Columns that order rows uniquely: the primary key, or else the first
+/// unfiltered unique index.
+pub fn key_columns(definition: &TableDefinition) -> Option<Vec<String>> {
+    definition
+        .indexes
+        .iter()
+        .find(|i| i.primary_key)
+        .or_else(|| {
+            definition
+                .indexes
+                .iter()
+                .find(|i| i.unique && i.filter.is_none() && !i.key_columns.is_empty())
+        })
+        .map(|i| i.key_columns.iter().map(|(c, _)| c.clone()).collect())
+}
+
+/// `INSERT ... SELECT` copying rows of `source` into `target`.
+///
+/// `page` is an offset and row count over `order`; identity values are kept
+/// with `IDENTITY_INSERT` when the target has an identity column.
+pub fn copy_data_statement(
+    source: &str,
+    target: &TableDefinition,
+    filter: Option<&str>,
+    order: &[String],
+    page: Option<(usize, usize)>,
+) -> Result<String, ServerError> {
+    let target_table = format!(
+        "{}.{}",
+        safe_identifier(&target.schema)?,
+        safe_identifier(&target.name)?
+    );
+    let columns = escaped_list(&insert_columns(target))?;
+    let mut sql = format!(
+        "INSERT INTO {} ({}) SELECT {} FROM {}",
+        target_table, columns, columns, source
+    );
+    if let Some(filter) = filter.filter(|f| !f.trim().is_empty()) {
+        sql.push_str(&format!(" WHERE {}", filter));
+    }
+    if let Some((offset, rows)) = page {
+        if order.is_empty() {
+            return Err(ServerError::invalid_input(
+                "Copying in batches needs a primary key or unique index",
+            ));
+        }
+        sql.push_str(&format!(
+            " ORDER BY {} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+            escaped_list(order)?,
+            offset,
+            rows.max(1)
+        ));
+    }
+    if target.columns.iter().any(|c| c.identity.is_some()) {
+        sql = format!(
+            "SET IDENTITY_INSERT {table} ON;\n{};\nSET IDENTITY_INSERT {table} OFF;",
+            sql,
+            table = target_table
+        );
+    }
+    Ok(sql)
+}
+
+/// Order in which to create modules of `schema` so that each comes after the
+/// modules it references. `objects` are names; objects in a reference cycle
+/// come last, in their original order.
+pub fn creation_order(objects: &[String], edges: &[DependencyEdge], schema: &str) -> Vec<usize> {
+    let index_of = |name: &str| objects.iter().position(|o| o.eq_ignore_ascii_case(name));
+    let in_schema = |s: &Option<String>| s.as_deref().unwrap_or("dbo").eq_ignore_ascii_case(schema);
+    let mut depends_on = vec![Vec::new(); objects.len()];
+    for edge in edges {
+        if edge.referenced.database.is_some()
+            || !in_schema(&edge.referencing.schema)
+            || !in_schema(&edge.referenced.schema)
+        {
+            continue;
+        }
+        if let (Some(from), Some(to)) = (
+            index_of(&edge.referencing.name),
+            index_of(&edge.referenced.name),
+        ) {
+            if from != to {
+                depends_on[from].push(to);
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(objects.len());
+    let mut placed = vec![false; objects.len()];
+    while order.len() < objects.len() {
+        let next = (0..objects.len())
+            .find(|&i| !placed[i] && depends_on[i].iter().all(|&d| placed[d]))
+            .or_else(|| (0..objects.len()).find(|&i| !placed[i]));
+        let Some(next) = next else { break };
+        placed[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+fn escaped_list(columns: &[String]) -> Result<String, ServerError> {
+    Ok(columns
+        .iter()
+        .map(|c| safe_identifier(c))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dependencies::DependencyObject;
+    use crate::database::scripting::{ScriptColumn, ScriptDefinition, ScriptForeignKey};
+
+    fn orders() -> TableDefinition {
+        TableDefinition {
+            schema: "sales".to_string(),
+            name: "Orders".to_string(),
+            columns: vec![
+                ScriptColumn {
+                    name: "Id".to_string(),
+                    type_name: "int".to_string(),
+                    identity: Some((1, 1)),
+                    ..Default::default()
+                },
+                ScriptColumn {
+                    name: "Status".to_string(),
+                    type_name: "int".to_string(),
+                    default: Some(("DF_Orders_Status".to_string(), "((0))".to_string())),
+                    ..Default::default()
+                },
+                ScriptColumn {
+                    name: "Version".to_string(),
+                    type_name: "timestamp".to_string(),
+                    ..Default::default()
+                },
+            ],
+            indexes: vec![
+                ScriptIndex {
+                    name: "PK_Orders".to_string(),
+                    index_type: "CLUSTERED".to_string(),
+                    unique: true,
+                    primary_key: true,
+                    key_columns: vec![("Id".to_string(), false)],
+                    ..Default::default()
+                },
+                ScriptIndex {
+                    name: "IX_Status".to_string(),
+                    index_type: "NONCLUSTERED".to_string(),
+                    key_columns: vec![("Status".to_string(), false)],
+                    ..Default::default()
+                },
+            ],
+            foreign_keys: vec![ScriptForeignKey {
+                name: "FK_Orders_Customers".to_string(),
+                columns: vec!["CustomerId".to_string()],
+                referenced_schema: "sales".to_string(),
+                referenced_table: "Customers".to_string(),
+                referenced_columns: vec!["Id".to_string()],
+                ..Default::default()
+            }],
+            checks: vec![ScriptDefinition {
+                name: "CK_Status".to_string(),
+                definition: "([Status]>=(0))".to_string(),
+                disabled: false,
+            }],
+            triggers: vec![ScriptDefinition {
+                name: "TR_Orders".to_string(),
+                definition:
+                    "CREATE TRIGGER sales.TR_Orders ON [sales].[Orders] AFTER INSERT AS SELECT 1"
+                        .to_string(),
+                disabled: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_copy_definition() {
+        let source = orders();
+        let bare = copy_definition(&source, "sales", "Orders_Copy", &CopyOptions::default());
+        assert!(bare.indexes.is_empty());
+        assert!(bare.checks.is_empty() && bare.foreign_keys.is_empty() && bare.triggers.is_empty());
+        assert!(bare.columns.iter().all(|c| c.default.is_none()));
+
+        let options = CopyOptions {
+            include_constraints: true,
+            include_indexes: true,
+            include_triggers: false,
+        };
+        let full = copy_definition(&source, "sales", "Orders_Copy", &options);
+        assert_eq!(full.indexes[0].name, "PK_Orders_Copy");
+        assert_eq!(full.indexes[1].name, "IX_Status");
+        assert_eq!(full.checks[0].name, "CK_Status_Orders_Copy");
+        assert_eq!(full.foreign_keys[0].name, "FK_Orders_Copy_Customers");
+        assert_eq!(
+            full.columns[1].default.as_ref().map(|d| d.0.as_str()),
+            Some("DF_Orders_Copy_Status")
+        );
+
+        let options = CopyOptions {
+            include_triggers: true,
+            ..options
+        };
+        let mut clone = copy_definition(&source, "sales_test", "Orders", &options);
+        retarget_foreign_keys(&mut clone, "sales", "sales_test");
+        assert_eq!(clone.indexes[0].name, "PK_Orders");
+        assert_eq!(clone.foreign_keys[0].referenced_schema, "sales_test");
+        assert_eq!(
+            clone.triggers[0].definition,
+            "CREATE TRIGGER [sales_test].[TR_Orders] ON [sales_test].[Orders] AFTER INSERT AS SELECT 1"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_schema() {
+        assert_eq!(
+            rewrite_schema(
+                "SELECT * FROM Sales.Orders o JOIN \"sales\".x ON 1=1 JOIN db.sales.y JOIN wholesales.z",
+                "sales",
+                "copy"
+            ),
+            "SELECT * FROM [copy].Orders o JOIN [copy].x ON 1=1 JOIN db.sales.y JOIN wholesales.z"
+        );
+    }
+
+    #[test]
+    fn test_qualify_module() {
+        assert_eq!(
+            qualify_module(
+                "-- report\nCREATE   VIEW vTotals AS SELECT 1 AS n",
+                "copy",
+                "vTotals",
+                None
+            ),
+            "-- report\nCREATE   VIEW [copy].[vTotals] AS SELECT 1 AS n"
+        );
+        assert_eq!(
+            qualify_module(
+                "create or alter trigger [app].[TR] on Orders after update as select 1",
+                "copy",
+                "TR",
+                Some("Orders")
+            ),
+            "create or alter trigger [copy].[TR] ON [copy].[Orders] after update as select 1"
+        );
+    }
+
+    #[test]
+    fn test_copy_data_statement() {
+        let copy = copy_definition(&orders(), "sales", "Orders_Copy", &CopyOptions::default());
+        assert_eq!(insert_columns(&copy), vec!["Id", "Status"]);
+        let order = key_columns(&orders()).unwrap();
+
+        let sql = copy_data_statement(
+            "[sales].[Orders]",
+            &copy,
+            Some("Status = 1"),
+            &order,
+            Some((2000, 1000)),
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SET IDENTITY_INSERT [sales].[Orders_Copy] ON;\n\
+             INSERT INTO [sales].[Orders_Copy] ([Id], [Status]) SELECT [Id], [Status] FROM [sales].[Orders] \
+             WHERE Status = 1 ORDER BY [Id] OFFSET 2000 ROWS FETCH NEXT 1000 ROWS ONLY;\n\
+             SET IDENTITY_INSERT [sales].[Orders_Copy] OFF;"
+        );
+        assert!(copy_data_statement("[sales].[Orders]", &copy, None, &[], Some((0, 10))).is_err());
+    }
+
+    #[test]
+    fn test_creation_order() {
+        let object = |name: &str| DependencyObject {
+            schema: Some("app".to_string()),
+            name: name.to_string(),
+            object_type: None,
+            database: None,
+            server: None,
+        };
+        let edge = |from: &str, to: &str| DependencyEdge {
+            referencing: object(from),
+            referenced: object(to),
+            is_unresolved: false,
+        };
+        let objects = vec![
+            "vTotals".to_string(),
+            "vBase".to_string(),
+            "pReport".to_string(),
+        ];
+        let edges = vec![edge("pReport", "vTotals"), edge("vTotals", "vBase")];
+        assert_eq!(creation_order(&objects, &edges, "app"), vec![1, 0, 2]);
+
+        let cycle = vec![edge("vTotals", "vBase"), edge("vBase", "vTotals")];
+        assert_eq!(creation_order(&objects, &cycle, "app"), vec![2, 0, 1]);
+    }
+}
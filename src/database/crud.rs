@@ -0,0 +1,515 @@
+//! CRUD stored procedure scaffolding.
+//!
+//! Generates Get/List/Insert/Update/Delete procedures for a table from its
+//! column metadata. Identity, computed and rowversion columns are never
+//! written; a rowversion column, when present, is used for optimistic
+//! concurrency in Update and Delete.
+
+use crate::database::metadata::ColumnInfo;
+use crate::database::migration::SchemaColumn;
+use serde::Serialize;
+
+/// Default procedure naming pattern.
+pub const DEFAULT_CRUD_NAMING: &str = "usp_{table}_{action}";
+
+/// A CRUD operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CrudAction {
+    Get,
+    List,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl CrudAction {
+    /// All operations, in the order they are generated.
+    pub const ALL: [CrudAction; 5] = [
+        CrudAction::Get,
+        CrudAction::List,
+        CrudAction::Insert,
+        CrudAction::Update,
+        CrudAction::Delete,
+    ];
+
+    /// Parse an operation name (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// Operation name as used in procedure names.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Get => "Get",
+            Self::List => "List",
+            Self::Insert => "Insert",
+            Self::Update => "Update",
+            Self::Delete => "Delete",
+        }
+    }
+
+    /// Whether the operation addresses a single row by primary key.
+    fn needs_key(&self) -> bool {
+        matches!(self, Self::Get | Self::Update | Self::Delete)
+    }
+}
+
+/// A generated stored procedure.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrudProcedure {
+    /// Operation the procedure implements.
+    pub action: CrudAction,
+
+    /// Schema-qualified procedure name.
+    pub name: String,
+
+    /// `CREATE OR ALTER PROCEDURE` statement.
+    pub sql: String,
+}
+
+/// Procedures generated for a table.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrudScript {
+    pub procedures: Vec<CrudProcedure>,
+    pub warnings: Vec<String>,
+}
+
+impl CrudScript {
+    /// All procedures as one script with `GO` separators.
+    pub fn to_script(&self) -> String {
+        self.procedures
+            .iter()
+            .map(|p| format!("{}\nGO\n", p.sql))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Generate CRUD procedures for a table.
+///
+/// `naming` is a pattern with `{schema}`, `{table}` and `{action}`
+/// placeholders. Operations that need a primary key are skipped with a
+/// warning when the table has none.
+pub fn generate_crud(
+    schema: &str,
+    table: &str,
+    columns: &[ColumnInfo],
+    primary_key: &[String],
+    naming: &str,
+    actions: &[CrudAction],
+) -> CrudScript {
+    let mut procedures = Vec::new();
+    let mut warnings = Vec::new();
+
+    let key: Vec<&ColumnInfo> = primary_key
+        .iter()
+        .filter_map(|k| {
+            columns
+                .iter()
+                .find(|c| c.column_name.eq_ignore_ascii_case(k))
+        })
+        .collect();
+    let rowversion = columns.iter().find(|c| is_rowversion(c));
+    let skipped: Vec<&str> = columns
+        .iter()
+        .filter(|c| c.is_computed || is_rowversion(c))
+        .map(|c| c.column_name.as_str())
+        .collect();
+    if !skipped.is_empty() {
+        warnings.push(format!(
+            "Computed and rowversion columns are read-only: {}",
+            skipped.join(", ")
+        ));
+    }
+
+    for &action in actions {
+        if action.needs_key() && key.is_empty() {
+            warnings.push(format!(
+                "{} skipped: {}.{} has no primary key",
+                action.name(),
+                schema,
+                table
+            ));
+            continue;
+        }
+
+        let name = naming
+            .replace("{schema}", schema)
+            .replace("{table}", table)
+            .replace("{action}", action.name());
+        let qualified = format!("[{}].[{}]", schema, name);
+        let body = match action {
+            CrudAction::Get => get_body(schema, table, columns, &key),
+            CrudAction::List => list_body(schema, table, columns, &key),
+            CrudAction::Insert => insert_body(schema, table, columns, &key),
+            CrudAction::Update => update_body(schema, table, columns, &key, rowversion),
+            CrudAction::Delete => delete_body(schema, table, &key, rowversion),
+        };
+        procedures.push(CrudProcedure {
+            action,
+            name: format!("{}.{}", schema, name),
+            sql: format!("CREATE OR ALTER PROCEDURE {}\n{}", qualified, body),
+        });
+    }
+
+    CrudScript {
+        procedures,
+        warnings,
+    }
+}
+
+fn get_body(schema: &str, table: &str, columns: &[ColumnInfo], key: &[&ColumnInfo]) -> String {
+    format!(
+        "{}\nAS\nBEGIN\n    SET NOCOUNT ON;\n\n    SELECT {}\n    FROM [{}].[{}]\n    WHERE {};\nEND",
+        parameters(key.iter().map(|c| param_declaration(c, false))),
+        select_list(columns),
+        schema,
+        table,
+        key_predicate(key)
+    )
+}
+
+fn list_body(schema: &str, table: &str, columns: &[ColumnInfo], key: &[&ColumnInfo]) -> String {
+    let order_by = if key.is_empty() {
+        "(SELECT NULL)".to_string()
+    } else {
+        key.iter()
+            .map(|c| format!("[{}]", c.column_name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "    @Offset INT = 0,\n    @PageSize INT = 100\nAS\nBEGIN\n    SET NOCOUNT ON;\n\n    \
+         SELECT {}\n    FROM [{}].[{}]\n    ORDER BY {}\n    \
+         OFFSET @Offset ROWS FETCH NEXT @PageSize ROWS ONLY;\nEND",
+        select_list(columns),
+        schema,
+        table,
+        order_by
+    )
+}
+
+fn insert_body(schema: &str, table: &str, columns: &[ColumnInfo], key: &[&ColumnInfo]) -> String {
+    let insertable: Vec<&ColumnInfo> = columns
+        .iter()
+        .filter(|c| !c.is_identity && !c.is_computed && !is_rowversion(c))
+        .collect();
+    let identity = columns.iter().find(|c| c.is_identity);
+
+    let mut sql = format!(
+        "{}\nAS\nBEGIN\n    SET NOCOUNT ON;\n\n    INSERT INTO [{}].[{}] ({})\n    VALUES ({});\n",
+        parameters(
+            insertable
+                .iter()
+                .map(|c| param_declaration(c, c.is_nullable || c.default_value.is_some()))
+        ),
+        schema,
+        table,
+        insertable
+            .iter()
+            .map(|c| format!("[{}]", c.column_name))
+            .collect::<Vec<_>>()
+            .join(", "),
+        insertable
+            .iter()
+            .map(|c| match &c.default_value {
+                // An omitted argument falls back to the column default
+                Some(default) => format!("COALESCE({}, {})", param_name(&c.column_name), default),
+                None => param_name(&c.column_name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // Return the new row, including identity and computed values
+    if !key.is_empty() {
+        let predicate = key
+            .iter()
+            .map(|c| match identity {
+                Some(id) if id.column_name == c.column_name => {
+                    format!("[{}] = SCOPE_IDENTITY()", c.column_name)
+                }
+                _ => format!("[{}] = {}", c.column_name, param_name(&c.column_name)),
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        sql.push_str(&format!(
+            "\n    SELECT {}\n    FROM [{}].[{}]\n    WHERE {};\n",
+            select_list(columns),
+            schema,
+            table,
+            predicate
+        ));
+    } else if let Some(id) = identity {
+        sql.push_str(&format!(
+            "\n    SELECT CAST(SCOPE_IDENTITY() AS {}) AS [{}];\n",
+            type_definition(id),
+            id.column_name
+        ));
+    }
+    sql.push_str("END");
+    sql
+}
+
+fn update_body(
+    schema: &str,
+    table: &str,
+    columns: &[ColumnInfo],
+    key: &[&ColumnInfo],
+    rowversion: Option<&ColumnInfo>,
+) -> String {
+    let updatable: Vec<&ColumnInfo> = columns
+        .iter()
+        .filter(|c| {
+            !c.is_identity
+                && !c.is_computed
+                && !is_rowversion(c)
+                && !key.iter().any(|k| k.column_name == c.column_name)
+        })
+        .collect();
+    if updatable.is_empty() {
+        return format!(
+            "{}\nAS\nBEGIN\n    SET NOCOUNT ON;\n    -- [{}].[{}] has no updatable columns\n    \
+             SELECT 0 AS rows_affected;\nEND",
+            parameters(key.iter().map(|c| param_declaration(c, false))),
+            schema,
+            table
+        );
+    }
+
+    let params = key
+        .iter()
+        .chain(updatable.iter())
+        .map(|c| param_declaration(c, false))
+        .chain(rowversion.map(|rv| format!("{} BINARY(8) = NULL", param_name(&rv.column_name))));
+    format!(
+        "{}\nAS\nBEGIN\n    SET NOCOUNT ON;\n\n    UPDATE [{}].[{}]\n    SET {}\n    WHERE {}{};\n\n    \
+         SELECT @@ROWCOUNT AS rows_affected;\nEND",
+        parameters(params),
+        schema,
+        table,
+        updatable
+            .iter()
+            .map(|c| format!("[{}] = {}", c.column_name, param_name(&c.column_name)))
+            .collect::<Vec<_>>()
+            .join(",\n        "),
+        key_predicate(key),
+        concurrency_predicate(rowversion)
+    )
+}
+
+fn delete_body(
+    schema: &str,
+    table: &str,
+    key: &[&ColumnInfo],
+    rowversion: Option<&ColumnInfo>,
+) -> String {
+    let params = key
+        .iter()
+        .map(|c| param_declaration(c, false))
+        .chain(rowversion.map(|rv| format!("{} BINARY(8) = NULL", param_name(&rv.column_name))));
+    format!(
+        "{}\nAS\nBEGIN\n    SET NOCOUNT ON;\n\n    DELETE FROM [{}].[{}]\n    WHERE {}{};\n\n    \
+         SELECT @@ROWCOUNT AS rows_affected;\nEND",
+        parameters(params),
+        schema,
+        table,
+        key_predicate(key),
+        concurrency_predicate(rowversion)
+    )
+}
+
+/// Optional rowversion check; a NULL argument skips it.
+fn concurrency_predicate(rowversion: Option<&ColumnInfo>) -> String {
+    rowversion
+        .map(|rv| {
+            let param = param_name(&rv.column_name);
+            format!(
+                "\n      AND ({} IS NULL OR [{}] = {})",
+                param, rv.column_name, param
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn parameters(declarations: impl Iterator<Item = String>) -> String {
+    declarations
+        .map(|d| format!("    {}", d))
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+fn param_declaration(column: &ColumnInfo, optional: bool) -> String {
+    format!(
+        "{} {}{}",
+        param_name(&column.column_name),
+        type_definition(column).to_uppercase(),
+        if optional { " = NULL" } else { "" }
+    )
+}
+
+fn select_list(columns: &[ColumnInfo]) -> String {
+    columns
+        .iter()
+        .map(|c| format!("[{}]", c.column_name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn key_predicate(key: &[&ColumnInfo]) -> String {
+    key.iter()
+        .map(|c| format!("[{}] = {}", c.column_name, param_name(&c.column_name)))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Parameter name for a column: `@` followed by the name with characters
+/// that are not valid in a variable name replaced by `_`.
+fn param_name(column: &str) -> String {
+    let name: String = column
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("@{}", name)
+}
+
+fn type_definition(column: &ColumnInfo) -> String {
+    SchemaColumn {
+        name: column.column_name.clone(),
+        data_type: column.data_type.clone(),
+        max_length: column.max_length,
+        precision: column.precision,
+        scale: column.scale,
+        is_nullable: column.is_nullable,
+    }
+    .type_definition()
+}
+
+fn is_rowversion(column: &ColumnInfo) -> bool {
+    matches!(
+        column.data_type.to_lowercase().as_str(),
+        "timestamp" | "rowversion"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, max_length: Option<i32>) -> ColumnInfo {
+        ColumnInfo {
+            column_name: name.to_string(),
+            ordinal_position: 0,
+            data_type: data_type.to_string(),
+            max_length,
+            precision: None,
+            scale: None,
+            is_nullable: false,
+            default_value: None,
+            is_identity: false,
+            is_computed: false,
+        }
+    }
+
+    fn columns() -> Vec<ColumnInfo> {
+        vec![
+            ColumnInfo {
+                is_identity: true,
+                ..column("OrderId", "int", None)
+            },
+            column("Customer Name", "nvarchar", Some(100)),
+            ColumnInfo {
+                default_value: Some("(getdate())".to_string()),
+                ..column("CreatedAt", "datetime", None)
+            },
+            ColumnInfo {
+                is_nullable: true,
+                ..column("Notes", "nvarchar", Some(-1))
+            },
+            ColumnInfo {
+                is_computed: true,
+                ..column("Total", "decimal", None)
+            },
+            column("RowVer", "timestamp", None),
+        ]
+    }
+
+    #[test]
+    fn test_generate_all_actions() {
+        let script = generate_crud(
+            "dbo",
+            "Orders",
+            &columns(),
+            &["OrderId".to_string()],
+            DEFAULT_CRUD_NAMING,
+            &CrudAction::ALL,
+        );
+        let names: Vec<&str> = script.procedures.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "dbo.usp_Orders_Get",
+                "dbo.usp_Orders_List",
+                "dbo.usp_Orders_Insert",
+                "dbo.usp_Orders_Update",
+                "dbo.usp_Orders_Delete",
+            ]
+        );
+
+        let insert = &script.procedures[2].sql;
+        assert!(insert.starts_with("CREATE OR ALTER PROCEDURE [dbo].[usp_Orders_Insert]"));
+        assert!(insert.contains("@Customer_Name NVARCHAR(100),\n    @CreatedAt DATETIME = NULL,"));
+        assert!(insert.contains("@Notes NVARCHAR(MAX) = NULL"));
+        assert!(
+            insert.contains("INSERT INTO [dbo].[Orders] ([Customer Name], [CreatedAt], [Notes])")
+        );
+        assert!(
+            insert.contains("VALUES (@Customer_Name, COALESCE(@CreatedAt, (getdate())), @Notes)")
+        );
+        assert!(insert.contains("WHERE [OrderId] = SCOPE_IDENTITY()"));
+        assert!(!insert.contains("@OrderId") && !insert.contains("@Total"));
+
+        let update = &script.procedures[3].sql;
+        assert!(update.contains("@OrderId INT,"));
+        assert!(update
+            .contains("SET [Customer Name] = @Customer_Name,\n        [CreatedAt] = @CreatedAt"));
+        assert!(update.contains("AND (@RowVer IS NULL OR [RowVer] = @RowVer)"));
+        assert!(!update.contains("[Total] ="));
+
+        assert!(script.to_script().matches("\nGO\n").count() == 5);
+        assert_eq!(script.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_naming_and_missing_key() {
+        let script = generate_crud(
+            "sales",
+            "Orders",
+            &columns(),
+            &[],
+            "{schema}_{table}{action}",
+            &[CrudAction::Get, CrudAction::List, CrudAction::Insert],
+        );
+        let names: Vec<&str> = script.procedures.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["sales.sales_OrdersList", "sales.sales_OrdersInsert"]
+        );
+        assert!(script.procedures[0].sql.contains("ORDER BY (SELECT NULL)"));
+        assert!(script.procedures[1]
+            .sql
+            .contains("SELECT CAST(SCOPE_IDENTITY() AS int) AS [OrderId]"));
+        assert!(script.warnings.iter().any(|w| w.starts_with("Get skipped")));
+
+        assert_eq!(CrudAction::parse(" delete"), Some(CrudAction::Delete));
+        assert_eq!(CrudAction::parse("upsert"), None);
+    }
+}
@@ -0,0 +1,489 @@
+//! Data dictionary generation.
+//!
+//! Collects tables, columns, keys, relationships, `MS_Description` extended
+//! properties and row counts in two queries and renders them as a single
+//! Markdown, HTML or JSON document.
+
+use crate::database::metadata::ForeignKeyRelationship;
+use crate::database::migration::SchemaColumn;
+use crate::database::types::SqlValue;
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use serde::Serialize;
+
+/// Output format of a data dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictionaryFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl DictionaryFormat {
+    /// Parse a format name: 'markdown', 'html' or 'json'.
+    pub fn parse(value: &str) -> Result<Self, ServerError> {
+        match value.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            other => Err(ServerError::invalid_input(format!(
+                "Invalid format '{}' (expected markdown, html, or json)",
+                other
+            ))),
+        }
+    }
+}
+
+/// A documented column.
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryColumn {
+    pub name: String,
+    /// Type as written in DDL (e.g. `nvarchar(50)`).
+    pub data_type: String,
+    pub is_nullable: bool,
+    pub is_primary_key: bool,
+    pub is_identity: bool,
+    pub is_computed: bool,
+    pub default_value: Option<String>,
+    pub description: Option<String>,
+    /// Referenced `schema.table.column` if the column is part of a foreign key.
+    pub references: Option<String>,
+}
+
+/// A documented table.
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryTable {
+    pub schema: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub row_count: Option<i64>,
+    pub columns: Vec<DictionaryColumn>,
+    pub foreign_keys: Vec<ForeignKeyRelationship>,
+    /// Foreign keys of other tables referencing this one.
+    pub referenced_by: Vec<ForeignKeyRelationship>,
+}
+
+fn schema_filter(schema: Option<&str>) -> String {
+    schema
+        .map(|s| format!("AND s.name = N'{}'", s.replace('\'', "''")))
+        .unwrap_or_default()
+}
+
+/// Query listing tables with their descriptions and (optionally) row counts.
+pub fn tables_query(schema: Option<&str>, include_row_counts: bool) -> String {
+    let row_count = if include_row_counts {
+        "(SELECT SUM(p.rows) FROM sys.partitions p \
+          WHERE p.object_id = t.object_id AND p.index_id IN (0, 1))"
+    } else {
+        "CAST(NULL AS BIGINT)"
+    };
+    format!(
+        r#"
+        SELECT
+            s.name AS schema_name,
+            t.name AS table_name,
+            CAST(ep.value AS NVARCHAR(4000)) AS description,
+            {row_count} AS row_count
+        FROM sys.tables t
+        INNER JOIN sys.schemas s ON s.schema_id = t.schema_id
+        LEFT JOIN sys.extended_properties ep
+            ON ep.class = 1 AND ep.major_id = t.object_id AND ep.minor_id = 0
+            AND ep.name = 'MS_Description'
+        WHERE t.is_ms_shipped = 0 {filter}
+        ORDER BY s.name, t.name
+        "#,
+        filter = schema_filter(schema)
+    )
+}
+
+/// Query listing the columns of all tables with descriptions and key flags.
+pub fn columns_query(schema: Option<&str>) -> String {
+    format!(
+        r#"
+        SELECT
+            s.name AS schema_name,
+            t.name AS table_name,
+            c.name AS column_name,
+            TYPE_NAME(c.user_type_id) AS data_type,
+            CASE WHEN c.max_length = -1 THEN -1
+                 WHEN TYPE_NAME(c.system_type_id) IN ('nchar', 'nvarchar') THEN c.max_length / 2
+                 ELSE c.max_length END AS max_length,
+            CAST(c.precision AS INT) AS precision,
+            CAST(c.scale AS INT) AS scale,
+            c.is_nullable,
+            c.is_identity,
+            c.is_computed,
+            dc.definition AS default_value,
+            CAST(ep.value AS NVARCHAR(4000)) AS description,
+            CAST(CASE WHEN EXISTS (
+                SELECT 1 FROM sys.indexes i
+                INNER JOIN sys.index_columns ic
+                    ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+                WHERE i.object_id = c.object_id AND i.is_primary_key = 1
+                  AND ic.column_id = c.column_id
+            ) THEN 1 ELSE 0 END AS BIT) AS is_primary_key
+        FROM sys.columns c
+        INNER JOIN sys.tables t ON t.object_id = c.object_id
+        INNER JOIN sys.schemas s ON s.schema_id = t.schema_id
+        LEFT JOIN sys.default_constraints dc ON dc.object_id = c.default_object_id
+        LEFT JOIN sys.extended_properties ep
+            ON ep.class = 1 AND ep.major_id = c.object_id AND ep.minor_id = c.column_id
+            AND ep.name = 'MS_Description'
+        WHERE t.is_ms_shipped = 0 {filter}
+        ORDER BY s.name, t.name, c.column_id
+        "#,
+        filter = schema_filter(schema)
+    )
+}
+
+/// Assemble tables from the rows of [`tables_query`] and [`columns_query`].
+pub fn build_dictionary(
+    table_rows: &[ResultRow],
+    column_rows: &[ResultRow],
+    relationships: &[ForeignKeyRelationship],
+) -> Vec<DictionaryTable> {
+    let mut tables: Vec<DictionaryTable> = table_rows
+        .iter()
+        .filter_map(|row| {
+            Some(DictionaryTable {
+                schema: text(row, "schema_name")?,
+                name: text(row, "table_name")?,
+                description: text(row, "description"),
+                row_count: int(row, "row_count"),
+                columns: Vec::new(),
+                foreign_keys: Vec::new(),
+                referenced_by: Vec::new(),
+            })
+        })
+        .collect();
+
+    for table in &mut tables {
+        let is_table = |schema: &str, name: &str| {
+            schema.eq_ignore_ascii_case(&table.schema) && name.eq_ignore_ascii_case(&table.name)
+        };
+        table.foreign_keys = relationships
+            .iter()
+            .filter(|fk| is_table(&fk.parent_schema, &fk.parent_table))
+            .cloned()
+            .collect();
+        table.referenced_by = relationships
+            .iter()
+            .filter(|fk| is_table(&fk.referenced_schema, &fk.referenced_table))
+            .cloned()
+            .collect();
+
+        table.columns = column_rows
+            .iter()
+            .filter(|row| {
+                is_table(
+                    &text(row, "schema_name").unwrap_or_default(),
+                    &text(row, "table_name").unwrap_or_default(),
+                )
+            })
+            .map(|row| {
+                let name = text(row, "column_name").unwrap_or_default();
+                let column_type = SchemaColumn {
+                    name: name.clone(),
+                    data_type: text(row, "data_type").unwrap_or_default(),
+                    max_length: int(row, "max_length").map(|v| v as i32),
+                    precision: int(row, "precision").map(|v| v as i32),
+                    scale: int(row, "scale").map(|v| v as i32),
+                    is_nullable: flag(row, "is_nullable"),
+                };
+                let references = table.foreign_keys.iter().find_map(|fk| {
+                    let position = fk
+                        .parent_columns
+                        .iter()
+                        .position(|c| c.eq_ignore_ascii_case(&name))?;
+                    Some(format!(
+                        "{}.{}.{}",
+                        fk.referenced_schema, fk.referenced_table, fk.referenced_columns[position]
+                    ))
+                });
+                DictionaryColumn {
+                    data_type: column_type.type_definition(),
+                    is_nullable: column_type.is_nullable,
+                    is_primary_key: flag(row, "is_primary_key"),
+                    is_identity: flag(row, "is_identity"),
+                    is_computed: flag(row, "is_computed"),
+                    default_value: text(row, "default_value"),
+                    description: text(row, "description"),
+                    references,
+                    name,
+                }
+            })
+            .collect();
+    }
+    tables
+}
+
+fn text(row: &ResultRow, column: &str) -> Option<String> {
+    row.get(column)
+        .filter(|v| !v.is_null())
+        .map(|v| v.to_display_string())
+}
+
+fn int(row: &ResultRow, column: &str) -> Option<i64> {
+    match row.get(column)? {
+        SqlValue::I64(v) => Some(*v),
+        SqlValue::I32(v) => Some(*v as i64),
+        SqlValue::I16(v) => Some(*v as i64),
+        SqlValue::I8(v) => Some(*v as i64),
+        SqlValue::Decimal(v) => v.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+fn flag(row: &ResultRow, column: &str) -> bool {
+    match row.get(column) {
+        Some(SqlValue::Bool(v)) => *v,
+        Some(SqlValue::I32(v)) => *v != 0,
+        _ => false,
+    }
+}
+
+/// Key column text (`PK`, `FK → target`) for a column.
+fn key_label(column: &DictionaryColumn) -> String {
+    let mut keys = Vec::new();
+    if column.is_primary_key {
+        keys.push("PK".to_string());
+    }
+    if let Some(target) = &column.references {
+        keys.push(format!("FK → {}", target));
+    }
+    keys.join(", ")
+}
+
+/// One-line summary of a foreign key.
+fn fk_summary(fk: &ForeignKeyRelationship) -> String {
+    let mut summary = format!(
+        "{}: {}.{} ({}) → {}.{} ({})",
+        fk.constraint_name,
+        fk.parent_schema,
+        fk.parent_table,
+        fk.parent_columns.join(", "),
+        fk.referenced_schema,
+        fk.referenced_table,
+        fk.referenced_columns.join(", ")
+    );
+    if fk.on_delete != "NO_ACTION" {
+        summary.push_str(&format!(" ON DELETE {}", fk.on_delete));
+    }
+    if fk.on_update != "NO_ACTION" {
+        summary.push_str(&format!(" ON UPDATE {}", fk.on_update));
+    }
+    summary
+}
+
+/// Render a data dictionary as Markdown.
+pub fn to_markdown(title: &str, tables: &[DictionaryTable]) -> String {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    let mut out = format!(
+        "# Data Dictionary: {}\n\n{} tables\n\n",
+        title,
+        tables.len()
+    );
+
+    out.push_str("| Table | Rows | Description |\n|-------|------|-------------|\n");
+    for table in tables {
+        out.push_str(&format!(
+            "| {}.{} | {} | {} |\n",
+            table.schema,
+            table.name,
+            table.row_count.map(|r| r.to_string()).unwrap_or_default(),
+            cell(table.description.as_deref().unwrap_or(""))
+        ));
+    }
+
+    for table in tables {
+        out.push_str(&format!("\n## {}.{}\n\n", table.schema, table.name));
+        if let Some(description) = &table.description {
+            out.push_str(&format!("{}\n\n", description));
+        }
+        if let Some(rows) = table.row_count {
+            out.push_str(&format!("Rows: {}\n\n", rows));
+        }
+        out.push_str(
+            "| Column | Type | Nullable | Key | Default | Description |\n\
+             |--------|------|----------|-----|---------|-------------|\n",
+        );
+        for column in &table.columns {
+            let mut data_type = column.data_type.clone();
+            if column.is_identity {
+                data_type.push_str(" IDENTITY");
+            }
+            if column.is_computed {
+                data_type.push_str(" (computed)");
+            }
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                column.name,
+                data_type,
+                if column.is_nullable { "YES" } else { "NO" },
+                key_label(column),
+                cell(column.default_value.as_deref().unwrap_or("")),
+                cell(column.description.as_deref().unwrap_or(""))
+            ));
+        }
+        if !table.foreign_keys.is_empty() {
+            out.push_str("\n**Foreign keys**\n\n");
+            for fk in &table.foreign_keys {
+                out.push_str(&format!("- {}\n", fk_summary(fk)));
+            }
+        }
+        if !table.referenced_by.is_empty() {
+            out.push_str("\n**Referenced by**\n\n");
+            for fk in &table.referenced_by {
+                out.push_str(&format!("- {}\n", fk_summary(fk)));
+            }
+        }
+    }
+    out
+}
+
+/// Render a data dictionary as a standalone HTML page.
+pub fn to_html(title: &str, tables: &[DictionaryTable]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let anchor = |t: &DictionaryTable| escape(&format!("{}.{}", t.schema, t.name));
+
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Data Dictionary: {0}</title>\n\
+         <style>body{{font-family:sans-serif}}table{{border-collapse:collapse}}\
+         th,td{{border:1px solid #ccc;padding:4px 8px;text-align:left}}</style>\n\
+         </head>\n<body>\n<h1>Data Dictionary: {0}</h1>\n<p>{1} tables</p>\n",
+        escape(title),
+        tables.len()
+    );
+
+    out.push_str("<table>\n<tr><th>Table</th><th>Rows</th><th>Description</th></tr>\n");
+    for table in tables {
+        out.push_str(&format!(
+            "<tr><td><a href=\"#{0}\">{0}</a></td><td>{1}</td><td>{2}</td></tr>\n",
+            anchor(table),
+            table.row_count.map(|r| r.to_string()).unwrap_or_default(),
+            escape(table.description.as_deref().unwrap_or(""))
+        ));
+    }
+    out.push_str("</table>\n");
+
+    for table in tables {
+        out.push_str(&format!("<h2 id=\"{0}\">{0}</h2>\n", anchor(table)));
+        if let Some(description) = &table.description {
+            out.push_str(&format!("<p>{}</p>\n", escape(description)));
+        }
+        if let Some(rows) = table.row_count {
+            out.push_str(&format!("<p>Rows: {}</p>\n", rows));
+        }
+        out.push_str(
+            "<table>\n<tr><th>Column</th><th>Type</th><th>Nullable</th><th>Key</th>\
+             <th>Default</th><th>Description</th></tr>\n",
+        );
+        for column in &table.columns {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(&column.name),
+                escape(&column.data_type),
+                if column.is_identity { " IDENTITY" } else { "" },
+                if column.is_nullable { "YES" } else { "NO" },
+                escape(&key_label(column)),
+                escape(column.default_value.as_deref().unwrap_or("")),
+                escape(column.description.as_deref().unwrap_or(""))
+            ));
+        }
+        out.push_str("</table>\n");
+        for (heading, fks) in [
+            ("Foreign keys", &table.foreign_keys),
+            ("Referenced by", &table.referenced_by),
+        ] {
+            if fks.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("<h3>{}</h3>\n<ul>\n", heading));
+            for fk in fks {
+                out.push_str(&format!("<li>{}</li>\n", escape(&fk_summary(fk))));
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(value: &str) -> SqlValue {
+        SqlValue::String(value.to_string())
+    }
+
+    fn column(table: &str, name: &str, data_type: &str, max_length: i32, pk: bool) -> ResultRow {
+        ResultRow::from_values(&[
+            ("schema_name", s("dbo")),
+            ("table_name", s(table)),
+            ("column_name", s(name)),
+            ("data_type", s(data_type)),
+            ("max_length", SqlValue::I32(max_length)),
+            ("is_nullable", SqlValue::Bool(!pk)),
+            ("is_primary_key", SqlValue::Bool(pk)),
+        ])
+    }
+
+    #[test]
+    fn test_build_and_render_dictionary() {
+        let tables = vec![
+            ResultRow::from_values(&[
+                ("schema_name", s("dbo")),
+                ("table_name", s("Customers")),
+                ("description", s("People who | buy things")),
+                ("row_count", SqlValue::I64(42)),
+            ]),
+            ResultRow::from_values(&[("schema_name", s("dbo")), ("table_name", s("Orders"))]),
+        ];
+        let columns = vec![
+            column("Customers", "Id", "int", 4, true),
+            column("Customers", "Name", "nvarchar", 100, false),
+            column("Orders", "Id", "int", 4, true),
+            column("Orders", "CustomerId", "int", 4, false),
+        ];
+        let fk = ForeignKeyRelationship {
+            constraint_name: "FK_Orders_Customers".to_string(),
+            parent_schema: "dbo".to_string(),
+            parent_table: "Orders".to_string(),
+            parent_columns: vec!["CustomerId".to_string()],
+            referenced_schema: "dbo".to_string(),
+            referenced_table: "Customers".to_string(),
+            referenced_columns: vec!["Id".to_string()],
+            on_delete: "CASCADE".to_string(),
+            on_update: "NO_ACTION".to_string(),
+            is_disabled: false,
+            is_not_trusted: false,
+        };
+
+        let dictionary = build_dictionary(&tables, &columns, &[fk]);
+        assert_eq!(dictionary.len(), 2);
+        assert_eq!(dictionary[0].columns[1].data_type, "nvarchar(100)");
+        assert_eq!(dictionary[0].referenced_by.len(), 1);
+        assert_eq!(
+            dictionary[1].columns[1].references.as_deref(),
+            Some("dbo.Customers.Id")
+        );
+
+        let markdown = to_markdown("Shop.dbo", &dictionary);
+        assert!(markdown.contains("| dbo.Customers | 42 | People who \\| buy things |"));
+        assert!(markdown.contains("| CustomerId | int | YES | FK → dbo.Customers.Id |  |  |"));
+        assert!(markdown.contains(
+            "- FK_Orders_Customers: dbo.Orders (CustomerId) → dbo.Customers (Id) ON DELETE CASCADE"
+        ));
+
+        let html = to_html("Shop.dbo", &dictionary);
+        assert!(html.contains("<h2 id=\"dbo.Orders\">dbo.Orders</h2>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
@@ -0,0 +1,373 @@
+//! Object dependency analysis.
+//!
+//! Builds dependency trees from `sys.sql_expression_dependencies`: the objects
+//! a module references (`uses`) and the modules referencing an object
+//! (`used_by`). References to other databases or servers are reported as
+//! leaves since their dependencies cannot be followed from here.
+
+use crate::database::types::SqlValue;
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Query returning all expression dependencies between objects of the current database.
+pub const DEPENDENCY_EDGES_QUERY: &str = r#"
+    SELECT DISTINCT
+        OBJECT_SCHEMA_NAME(d.referencing_id) AS referencing_schema,
+        OBJECT_NAME(d.referencing_id) AS referencing_name,
+        src.type_desc AS referencing_type,
+        COALESCE(d.referenced_schema_name, OBJECT_SCHEMA_NAME(d.referenced_id)) AS referenced_schema,
+        d.referenced_entity_name AS referenced_name,
+        COALESCE(tgt.type_desc, d.referenced_class_desc) AS referenced_type,
+        d.referenced_database_name AS referenced_database,
+        d.referenced_server_name AS referenced_server,
+        CAST(CASE WHEN d.referenced_id IS NULL
+                   AND d.referenced_database_name IS NULL THEN 1 ELSE 0 END AS BIT) AS is_unresolved
+    FROM sys.sql_expression_dependencies d
+    INNER JOIN sys.objects src ON src.object_id = d.referencing_id
+    LEFT JOIN sys.objects tgt ON tgt.object_id = d.referenced_id
+    WHERE d.referencing_class = 1
+"#;
+
+/// Build a query returning the type of an object (no rows if it does not exist).
+pub fn object_type_query(schema: &str, name: &str) -> String {
+    format!(
+        "SELECT o.type_desc AS object_type FROM sys.objects o \
+         WHERE o.object_id = OBJECT_ID(N'[{}].[{}]')",
+        schema.replace(']', "]]").replace('\'', "''"),
+        name.replace(']', "]]").replace('\'', "''")
+    )
+}
+
+/// Direction in which dependencies are followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyDirection {
+    /// Objects the root references.
+    Uses,
+    /// Objects referencing the root.
+    UsedBy,
+    /// Both directions.
+    Both,
+}
+
+impl DependencyDirection {
+    /// Parse a direction name: 'uses', 'used_by' or 'both'.
+    pub fn parse(value: &str) -> Result<Self, ServerError> {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "uses" => Ok(Self::Uses),
+            "used_by" => Ok(Self::UsedBy),
+            "both" => Ok(Self::Both),
+            other => Err(ServerError::invalid_input(format!(
+                "Invalid direction '{}' (expected uses, used_by, or both)",
+                other
+            ))),
+        }
+    }
+
+    fn includes_uses(&self) -> bool {
+        matches!(self, Self::Uses | Self::Both)
+    }
+
+    fn includes_used_by(&self) -> bool {
+        matches!(self, Self::UsedBy | Self::Both)
+    }
+}
+
+/// An object taking part in a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyObject {
+    pub schema: Option<String>,
+    pub name: String,
+    /// Object type (e.g. USER_TABLE, SQL_STORED_PROCEDURE), if known.
+    pub object_type: Option<String>,
+    /// Database of a cross-database reference.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    /// Server of a linked-server reference.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+}
+
+impl DependencyObject {
+    /// Case-insensitive key of a local object.
+    fn key(&self) -> Option<String> {
+        if self.database.is_some() || self.server.is_some() {
+            return None;
+        }
+        Some(object_key(
+            self.schema.as_deref().unwrap_or("dbo"),
+            &self.name,
+        ))
+    }
+
+    /// Qualified display name.
+    pub fn qualified_name(&self) -> String {
+        [
+            self.server.as_deref(),
+            self.database.as_deref(),
+            self.schema.as_deref(),
+            Some(self.name.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(".")
+    }
+}
+
+fn object_key(schema: &str, name: &str) -> String {
+    format!("{}.{}", schema.to_lowercase(), name.to_lowercase())
+}
+
+/// A reference from one object to another.
+#[derive(Debug, Clone)]
+pub struct DependencyEdge {
+    pub referencing: DependencyObject,
+    pub referenced: DependencyObject,
+    /// The referenced object could not be resolved (e.g. it was dropped).
+    pub is_unresolved: bool,
+}
+
+impl DependencyEdge {
+    /// Parse a row of [`DEPENDENCY_EDGES_QUERY`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        Some(Self {
+            referencing: DependencyObject {
+                schema: text("referencing_schema"),
+                name: text("referencing_name")?,
+                object_type: text("referencing_type"),
+                database: None,
+                server: None,
+            },
+            referenced: DependencyObject {
+                schema: text("referenced_schema"),
+                name: text("referenced_name")?,
+                object_type: text("referenced_type"),
+                database: text("referenced_database"),
+                server: text("referenced_server"),
+            },
+            is_unresolved: matches!(row.get("is_unresolved"), Some(SqlValue::Bool(true))),
+        })
+    }
+}
+
+/// A node of a dependency tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyNode {
+    #[serde(flatten)]
+    pub object: DependencyObject,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_unresolved: bool,
+    /// The object already appears earlier in the tree; its dependencies are listed there.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub already_listed: bool,
+    /// Dependencies were not followed because the depth limit was reached.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<DependencyNode>,
+}
+
+/// Dependency edges of a database, indexed in both directions.
+pub struct DependencyGraph {
+    uses: HashMap<String, Vec<DependencyEdge>>,
+    used_by: HashMap<String, Vec<DependencyEdge>>,
+}
+
+impl DependencyGraph {
+    /// Index dependency edges.
+    pub fn new(edges: Vec<DependencyEdge>) -> Self {
+        let mut uses: HashMap<String, Vec<DependencyEdge>> = HashMap::new();
+        let mut used_by: HashMap<String, Vec<DependencyEdge>> = HashMap::new();
+        for edge in edges {
+            if let Some(key) = edge.referenced.key() {
+                used_by.entry(key).or_default().push(edge.clone());
+            }
+            if let Some(key) = edge.referencing.key() {
+                uses.entry(key).or_default().push(edge);
+            }
+        }
+        Self { uses, used_by }
+    }
+
+    /// Objects the root references, followed up to `max_depth` levels.
+    pub fn uses_tree(&self, schema: &str, name: &str, max_depth: usize) -> Vec<DependencyNode> {
+        let mut seen = HashSet::from([object_key(schema, name)]);
+        self.children(&object_key(schema, name), true, 1, max_depth, &mut seen)
+    }
+
+    /// Objects referencing the root, followed up to `max_depth` levels.
+    pub fn used_by_tree(&self, schema: &str, name: &str, max_depth: usize) -> Vec<DependencyNode> {
+        let mut seen = HashSet::from([object_key(schema, name)]);
+        self.children(&object_key(schema, name), false, 1, max_depth, &mut seen)
+    }
+
+    fn children(
+        &self,
+        key: &str,
+        uses: bool,
+        depth: usize,
+        max_depth: usize,
+        seen: &mut HashSet<String>,
+    ) -> Vec<DependencyNode> {
+        let index = if uses { &self.uses } else { &self.used_by };
+        let Some(edges) = index.get(key) else {
+            return Vec::new();
+        };
+
+        let mut nodes = Vec::new();
+        for edge in edges {
+            let object = if uses {
+                &edge.referenced
+            } else {
+                &edge.referencing
+            };
+            let mut node = DependencyNode {
+                object: object.clone(),
+                is_unresolved: uses && edge.is_unresolved,
+                already_listed: false,
+                truncated: false,
+                dependencies: Vec::new(),
+            };
+
+            if let Some(child_key) = object.key() {
+                if !seen.insert(child_key.clone()) {
+                    node.already_listed = true;
+                } else if depth >= max_depth {
+                    node.truncated = index.contains_key(&child_key);
+                } else {
+                    node.dependencies = self.children(&child_key, uses, depth + 1, max_depth, seen);
+                }
+            }
+            nodes.push(node);
+        }
+        nodes.sort_by_key(|n| n.object.qualified_name().to_lowercase());
+        nodes
+    }
+}
+
+/// Build the dependency trees of an object in the requested directions.
+///
+/// Returns `(uses, used_by)`; a direction that was not requested is `None`.
+pub fn dependency_trees(
+    graph: &DependencyGraph,
+    schema: &str,
+    name: &str,
+    direction: DependencyDirection,
+    max_depth: usize,
+) -> (Option<Vec<DependencyNode>>, Option<Vec<DependencyNode>>) {
+    (
+        direction
+            .includes_uses()
+            .then(|| graph.uses_tree(schema, name, max_depth)),
+        direction
+            .includes_used_by()
+            .then(|| graph.used_by_tree(schema, name, max_depth)),
+    )
+}
+
+/// Flatten a tree into the distinct objects it contains (excluding the root).
+pub fn flatten(nodes: &[DependencyNode]) -> Vec<String> {
+    fn walk(nodes: &[DependencyNode], out: &mut Vec<String>) {
+        for node in nodes.iter().filter(|n| !n.already_listed) {
+            let name = node.object.qualified_name();
+            if !out.contains(&name) {
+                out.push(name);
+            }
+            walk(&node.dependencies, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(nodes, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str, object_type: &str) -> DependencyObject {
+        DependencyObject {
+            schema: Some("dbo".to_string()),
+            name: name.to_string(),
+            object_type: Some(object_type.to_string()),
+            database: None,
+            server: None,
+        }
+    }
+
+    fn edge(from: DependencyObject, to: DependencyObject) -> DependencyEdge {
+        DependencyEdge {
+            referencing: from,
+            referenced: to,
+            is_unresolved: false,
+        }
+    }
+
+    fn graph() -> DependencyGraph {
+        let orders = object("Orders", "USER_TABLE");
+        let view = object("OrderSummary", "VIEW");
+        let proc_a = object("ReportOrders", "SQL_STORED_PROCEDURE");
+        let proc_b = object("RunReports", "SQL_STORED_PROCEDURE");
+        let remote = DependencyObject {
+            database: Some("Archive".to_string()),
+            ..object("OldOrders", "")
+        };
+        DependencyGraph::new(vec![
+            edge(view.clone(), orders.clone()),
+            edge(proc_a.clone(), view.clone()),
+            edge(proc_a.clone(), remote),
+            edge(proc_b.clone(), proc_a.clone()),
+            // Mutual recursion must not loop
+            edge(proc_a, proc_b),
+        ])
+    }
+
+    #[test]
+    fn test_used_by_tree() {
+        let graph = graph();
+        let tree = graph.used_by_tree("DBO", "orders", 10);
+        assert_eq!(
+            flatten(&tree),
+            vec!["dbo.OrderSummary", "dbo.ReportOrders", "dbo.RunReports"]
+        );
+
+        // RunReports calls ReportOrders back, which is already in the tree
+        let run_reports = &tree[0].dependencies[0].dependencies[0];
+        assert_eq!(run_reports.object.name, "RunReports");
+        assert!(run_reports.dependencies[0].already_listed);
+
+        let shallow = graph.used_by_tree("dbo", "Orders", 1);
+        assert_eq!(shallow.len(), 1);
+        assert!(shallow[0].truncated);
+        assert!(shallow[0].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_uses_tree() {
+        let graph = graph();
+        let (uses, used_by) =
+            dependency_trees(&graph, "dbo", "ReportOrders", DependencyDirection::Uses, 3);
+        assert!(used_by.is_none());
+        assert_eq!(
+            flatten(&uses.unwrap()),
+            vec![
+                "Archive.dbo.OldOrders",
+                "dbo.OrderSummary",
+                "dbo.Orders",
+                "dbo.RunReports"
+            ]
+        );
+        assert_eq!(
+            DependencyDirection::parse("Used-By").unwrap(),
+            DependencyDirection::UsedBy
+        );
+        assert!(DependencyDirection::parse("sideways").is_err());
+    }
+}
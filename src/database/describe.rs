@@ -0,0 +1,184 @@
+//! Result set shape of a query without executing it.
+//!
+//! Uses `sp_describe_first_result_set` in browse mode, which also reports the
+//! base table and column each result column is read from. The server only
+//! compiles the batch, so this is cheap even for expensive queries; it fails
+//! for queries whose first result set depends on control flow or temp tables
+//! created in the same batch.
+
+use crate::database::types::SqlValue;
+use crate::database::{csv_field, ResultRow};
+use serde::Serialize;
+
+/// Build the `sp_describe_first_result_set` call for a query.
+pub fn describe_query_sql(query: &str) -> String {
+    format!(
+        "EXEC sp_describe_first_result_set @tsql = N'{}', @params = NULL, @browse_information_mode = 1",
+        query.replace('\'', "''")
+    )
+}
+
+/// A column of a described result set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DescribedColumn {
+    pub ordinal: usize,
+    /// Column name; `None` for unnamed expressions.
+    pub name: Option<String>,
+    /// Type including length, precision and scale, e.g. `nvarchar(50)`.
+    pub type_name: String,
+    /// Storage size in bytes, -1 for MAX types.
+    #[serde(skip)]
+    pub max_length: i32,
+    pub nullable: bool,
+    /// `schema.table` the column is read from, if it maps to a base table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_column: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_identity: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_computed: bool,
+}
+
+impl DescribedColumn {
+    /// Parse a row of `sp_describe_first_result_set`.
+    ///
+    /// Returns `None` for the hidden key columns that browse mode appends.
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let flag = |column: &str| matches!(row.get(column), Some(SqlValue::Bool(true)));
+        if flag("is_hidden") {
+            return None;
+        }
+
+        let source_table = text("source_table").map(|table| {
+            [text("source_database"), text("source_schema"), Some(table)]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(".")
+        });
+        Some(Self {
+            ordinal: text("column_ordinal")?.parse().ok()?,
+            name: text("name"),
+            type_name: text("system_type_name")?,
+            max_length: text("max_length")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(-1),
+            nullable: flag("is_nullable"),
+            source_table,
+            source_column: text("source_column"),
+            is_identity: flag("is_identity_column"),
+            is_computed: flag("is_computed_column"),
+        })
+    }
+}
+
+/// Render described columns as a markdown table.
+pub fn columns_to_markdown(columns: &[DescribedColumn]) -> String {
+    let mut output = String::from(
+        "| # | Column | Type | Nullable | Source |\n|---|--------|------|----------|--------|\n",
+    );
+    for column in columns {
+        let source = match (&column.source_table, &column.source_column) {
+            (Some(table), Some(col)) => format!("{}.{}", table, col),
+            (Some(table), None) => table.clone(),
+            _ if column.is_computed => "(computed)".to_string(),
+            _ => "(expression)".to_string(),
+        };
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            column.ordinal,
+            column.name.as_deref().unwrap_or("(no name)"),
+            column.type_name,
+            if column.nullable { "YES" } else { "NO" },
+            source
+        ));
+    }
+    output
+}
+
+/// Render described columns as CSV.
+pub fn columns_to_csv(columns: &[DescribedColumn]) -> String {
+    let mut output = String::from("ordinal,name,type,nullable,source_table,source_column\n");
+    for column in columns {
+        let fields = [
+            column.ordinal.to_string(),
+            column.name.clone().unwrap_or_default(),
+            column.type_name.clone(),
+            column.nullable.to_string(),
+            column.source_table.clone().unwrap_or_default(),
+            column.source_column.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.into_iter().map(csv_field).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_query_sql_escapes_quotes() {
+        assert_eq!(
+            describe_query_sql("SELECT 'a' AS x"),
+            "EXEC sp_describe_first_result_set @tsql = N'SELECT ''a'' AS x', @params = NULL, @browse_information_mode = 1"
+        );
+    }
+
+    #[test]
+    fn test_column_from_row() {
+        let column = DescribedColumn::from_row(&ResultRow::from_values(&[
+            ("column_ordinal", SqlValue::I32(1)),
+            ("name", SqlValue::String("OrderId".into())),
+            ("system_type_name", SqlValue::String("int".into())),
+            ("is_nullable", SqlValue::Bool(false)),
+            ("is_hidden", SqlValue::Bool(false)),
+            ("source_schema", SqlValue::String("dbo".into())),
+            ("source_table", SqlValue::String("Orders".into())),
+            ("source_column", SqlValue::String("Id".into())),
+            ("is_identity_column", SqlValue::Bool(true)),
+        ]))
+        .unwrap();
+        assert_eq!(column.source_table.as_deref(), Some("dbo.Orders"));
+        assert!(column.is_identity && !column.nullable);
+
+        let expression = DescribedColumn::from_row(&ResultRow::from_values(&[
+            ("column_ordinal", SqlValue::I32(2)),
+            ("name", SqlValue::Null),
+            ("system_type_name", SqlValue::String("decimal(38,2)".into())),
+            ("is_nullable", SqlValue::Bool(true)),
+        ]))
+        .unwrap();
+        assert_eq!(expression.name, None);
+        assert_eq!(
+            columns_to_csv(std::slice::from_ref(&expression)),
+            "ordinal,name,type,nullable,source_table,source_column\n2,,\"decimal(38,2)\",true,,\n"
+        );
+        assert_eq!(
+            columns_to_markdown(&[column, expression])
+                .lines()
+                .skip(2)
+                .collect::<Vec<_>>(),
+            vec![
+                "| 1 | OrderId | int | NO | dbo.Orders.Id |",
+                "| 2 | (no name) | decimal(38,2) | YES | (expression) |"
+            ]
+        );
+
+        let hidden = ResultRow::from_values(&[
+            ("column_ordinal", SqlValue::I32(3)),
+            ("system_type_name", SqlValue::String("int".into())),
+            ("is_hidden", SqlValue::Bool(true)),
+        ]);
+        assert!(DescribedColumn::from_row(&hidden).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+//! Recovery from server failovers and restarts.
+//!
+//! When SQL Server fails over to another replica or restarts, every pooled
+//! connection to the old instance is dead, and each one would fail a tool call
+//! before the pool noticed. An error with a failover signature (see
+//! [`ServerError::is_failover`]) instead drops the idle connections of every
+//! pool established before the error and re-establishes `min_connections`, so
+//! the next calls run on the new primary. Read-only statements are retried
+//! once on a fresh connection, making the failover transparent to them.
+
+use crate::database::PoolHealth;
+use crate::error::ServerError;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::warn;
+
+/// Failovers detected so far, for `get_pool_metrics`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FailoverStats {
+    /// Errors with a failover signature.
+    pub detected: u64,
+    /// Stale connections dropped from the pools.
+    pub connections_flushed: u64,
+    /// Read-only statements retried after a failover.
+    pub reads_retried: u64,
+    /// When the last failover was detected.
+    pub last_detected: Option<DateTime<Utc>>,
+    /// Error of the last failover.
+    pub last_error: Option<String>,
+}
+
+/// Flushes and rebuilds the pools when an error shows the server failed over.
+pub struct FailoverRecovery {
+    pools: Vec<Arc<PoolHealth>>,
+    /// Serializes rebuilds, so a burst of errors from one failover waits for
+    /// the first rebuild instead of racing it.
+    rebuilding: tokio::sync::Mutex<()>,
+    stats: Mutex<FailoverStats>,
+}
+
+impl FailoverRecovery {
+    /// Create recovery for the pools maintained by `pools`.
+    pub fn new(pools: Vec<Arc<PoolHealth>>) -> Self {
+        Self {
+            pools,
+            rebuilding: tokio::sync::Mutex::new(()),
+            stats: Mutex::new(FailoverStats::default()),
+        }
+    }
+
+    /// Rebuild the pools if `error` has a failover signature.
+    ///
+    /// Returns whether it had one; the pools then hold only connections
+    /// established after the error.
+    pub async fn recover(&self, error: &ServerError) -> bool {
+        if !error.is_failover() {
+            return false;
+        }
+        let detected = Instant::now();
+        warn!("Connection failover detected: {}", error);
+        {
+            let mut stats = self.stats.lock();
+            stats.detected += 1;
+            stats.last_detected = Some(Utc::now());
+            stats.last_error = Some(error.to_string());
+        }
+
+        let _rebuilding = self.rebuilding.lock().await;
+        let mut flushed = 0;
+        for pool in &self.pools {
+            flushed += pool.rebuild(detected).await;
+        }
+        self.stats.lock().connections_flushed += flushed;
+        true
+    }
+
+    /// Count a read-only statement retried after a failover.
+    pub fn record_retry(&self) {
+        self.stats.lock().reads_retried += 1;
+    }
+
+    /// Failovers detected so far.
+    pub fn stats(&self) -> FailoverStats {
+        self.stats.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recover_ignores_other_errors() {
+        let recovery = FailoverRecovery::new(Vec::new());
+        assert!(!recovery.recover(&ServerError::timeout(30)).await);
+        assert_eq!(recovery.stats().detected, 0);
+
+        let error = ServerError::query_error_with_code("Cannot open database", 4060, None);
+        assert!(recovery.recover(&error).await);
+        recovery.record_retry();
+        let stats = recovery.stats();
+        assert_eq!((stats.detected, stats.reads_retried), (1, 1));
+        assert!(stats.last_error.unwrap().contains("Cannot open database"));
+    }
+}
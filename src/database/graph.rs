@@ -0,0 +1,365 @@
+//! SQL Server graph tables (node and edge tables).
+//!
+//! Graph tables are ordinary tables with implicit columns: a node table has
+//! a `$node_id` pseudo-column, an edge table `$edge_id`, `$from_id` and
+//! `$to_id`. The physical columns behind them carry a hex suffix
+//! (`$node_id_7D3B...`, `graph_id_...`) and must not be queried by name;
+//! tables are traversed with `MATCH` rather than joins on those columns.
+//!
+//! Detection uses the implicit column names, so the queries also run on
+//! servers older than SQL Server 2017, where they find nothing.
+
+use crate::database::query::ResultRow;
+use serde::Serialize;
+
+/// Kind of graph table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum GraphKind {
+    Node,
+    Edge,
+}
+
+/// A pseudo-column of a graph table.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PseudoColumn {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+const NODE_PSEUDO_COLUMNS: &[PseudoColumn] = &[PseudoColumn {
+    name: "$node_id",
+    description:
+        "Identifier of the node (JSON text); use it as $from_id or $to_id when inserting edges",
+}];
+
+const EDGE_PSEUDO_COLUMNS: &[PseudoColumn] = &[
+    PseudoColumn {
+        name: "$edge_id",
+        description: "Identifier of the edge (JSON text)",
+    },
+    PseudoColumn {
+        name: "$from_id",
+        description: "$node_id of the node the edge starts from",
+    },
+    PseudoColumn {
+        name: "$to_id",
+        description: "$node_id of the node the edge points to",
+    },
+];
+
+impl GraphKind {
+    /// Detect the kind of a table from its physical column names.
+    pub fn from_column_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut kind = None;
+        for name in names {
+            let name = name.to_lowercase();
+            if name.starts_with("$node_id_") {
+                return Some(Self::Node);
+            }
+            if name.starts_with("$edge_id_") {
+                kind = Some(Self::Edge);
+            }
+        }
+        kind
+    }
+
+    /// Parse `NODE` or `EDGE`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "NODE" => Some(Self::Node),
+            "EDGE" => Some(Self::Edge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Node => "NODE",
+            Self::Edge => "EDGE",
+        }
+    }
+
+    /// Pseudo-columns to use instead of the implicit physical columns.
+    pub fn pseudo_columns(&self) -> &'static [PseudoColumn] {
+        match self {
+            Self::Node => NODE_PSEUDO_COLUMNS,
+            Self::Edge => EDGE_PSEUDO_COLUMNS,
+        }
+    }
+}
+
+/// Whether a physical column is one of the implicit graph columns.
+pub fn is_implicit_column(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.starts_with('$')
+        || [
+            "graph_id_",
+            "from_obj_id_",
+            "from_id_",
+            "to_obj_id_",
+            "to_id_",
+        ]
+        .iter()
+        .any(|prefix| {
+            name.strip_prefix(prefix).is_some_and(|suffix| {
+                !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_hexdigit())
+            })
+        })
+}
+
+/// A node or edge table with its user columns.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphTable {
+    pub schema_name: String,
+    pub table_name: String,
+    pub kind: GraphKind,
+    /// User-defined columns as `name type`.
+    pub columns: Vec<String>,
+    /// Node tables an edge may connect, as `(from, to)` pairs from its edge
+    /// constraints (SQL Server 2019 and later).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<(String, String)>,
+}
+
+impl GraphTable {
+    /// `[schema].[table]`.
+    pub fn quoted_name(&self) -> String {
+        format!(
+            "[{}].[{}]",
+            self.schema_name.replace(']', "]]"),
+            self.table_name.replace(']', "]]")
+        )
+    }
+
+    /// Group rows of [`GRAPH_TABLES_QUERY`] into tables and attach the
+    /// connections from rows of [`EDGE_CONSTRAINTS_QUERY`].
+    pub fn from_rows(table_rows: &[ResultRow], constraint_rows: &[ResultRow]) -> Vec<Self> {
+        let text = |row: &ResultRow, column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+
+        let mut tables: Vec<Self> = Vec::new();
+        for row in table_rows {
+            let (Some(schema), Some(name), Some(kind)) = (
+                text(row, "schema_name"),
+                text(row, "table_name"),
+                text(row, "graph_type")
+                    .as_deref()
+                    .and_then(GraphKind::parse),
+            ) else {
+                continue;
+            };
+            let column = text(row, "column_name").map(|c| match text(row, "data_type") {
+                Some(t) => format!("{} {}", c, t),
+                None => c,
+            });
+            match tables.last_mut() {
+                Some(t) if t.schema_name == schema && t.table_name == name => {
+                    t.columns.extend(column);
+                }
+                _ => tables.push(Self {
+                    schema_name: schema,
+                    table_name: name,
+                    kind,
+                    columns: column.into_iter().collect(),
+                    connections: Vec::new(),
+                }),
+            }
+        }
+
+        for row in constraint_rows {
+            let (Some(schema), Some(name), Some(from), Some(to)) = (
+                text(row, "edge_schema"),
+                text(row, "edge_table"),
+                text(row, "from_table"),
+                text(row, "to_table"),
+            ) else {
+                continue;
+            };
+            if let Some(edge) = tables
+                .iter_mut()
+                .find(|t| t.schema_name == schema && t.table_name == name)
+            {
+                edge.connections.push((from, to));
+            }
+        }
+        tables
+    }
+}
+
+/// Example `MATCH` query over the given graph tables.
+///
+/// Uses an edge constraint when there is one, so the node tables fit the
+/// edge; otherwise pairs the first edge table with the first node table.
+pub fn match_example(tables: &[GraphTable]) -> Option<String> {
+    let quote = |name: &str| {
+        name.split('.')
+            .map(|part| format!("[{}]", part.replace(']', "]]")))
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+    let edges: Vec<&GraphTable> = tables
+        .iter()
+        .filter(|t| t.kind == GraphKind::Edge)
+        .collect();
+
+    let (from, edge, to) = match edges
+        .iter()
+        .find_map(|e| e.connections.first().map(|c| (e, c)))
+    {
+        Some((edge, (from, to))) => (quote(from), edge.quoted_name(), quote(to)),
+        None => {
+            let node = tables.iter().find(|t| t.kind == GraphKind::Node)?;
+            let edge = edges.first()?;
+            (node.quoted_name(), edge.quoted_name(), node.quoted_name())
+        }
+    };
+    Some(format!(
+        "SELECT TOP (100) a.*, b.*\nFROM {} AS a, {} AS e, {} AS b\nWHERE MATCH(a-(e)->b)",
+        from, edge, to
+    ))
+}
+
+/// Node and edge tables of the current database with their user columns,
+/// node tables first.
+pub const GRAPH_TABLES_QUERY: &str = r#"
+    SELECT
+        s.name AS schema_name,
+        t.name AS table_name,
+        CASE WHEN EXISTS (
+            SELECT 1 FROM sys.columns nc
+            WHERE nc.object_id = t.object_id AND nc.name LIKE '$node[_]id[_]%'
+        ) THEN 'NODE' ELSE 'EDGE' END AS graph_type,
+        c.name AS column_name,
+        TYPE_NAME(c.user_type_id) AS data_type
+    FROM sys.tables t
+    INNER JOIN sys.schemas s ON s.schema_id = t.schema_id
+    LEFT JOIN sys.columns c ON c.object_id = t.object_id
+        AND c.is_hidden = 0
+        AND c.name NOT LIKE '$%'
+    WHERE EXISTS (
+        SELECT 1 FROM sys.columns gc
+        WHERE gc.object_id = t.object_id
+        AND (gc.name LIKE '$node[_]id[_]%' OR gc.name LIKE '$edge[_]id[_]%')
+    )
+    ORDER BY graph_type DESC, s.name, t.name, c.column_id
+"#;
+
+/// Node tables each edge table may connect (SQL Server 2019 and later).
+pub const EDGE_CONSTRAINTS_QUERY: &str = r#"
+    SELECT
+        OBJECT_SCHEMA_NAME(ec.parent_object_id) AS edge_schema,
+        OBJECT_NAME(ec.parent_object_id) AS edge_table,
+        OBJECT_SCHEMA_NAME(cl.from_object_id) + '.' + OBJECT_NAME(cl.from_object_id) AS from_table,
+        OBJECT_SCHEMA_NAME(cl.to_object_id) + '.' + OBJECT_NAME(cl.to_object_id) AS to_table
+    FROM sys.edge_constraints ec
+    INNER JOIN sys.edge_constraint_clauses cl ON cl.object_id = ec.object_id
+    ORDER BY edge_schema, edge_table, from_table, to_table
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn row(values: &[(&str, &str)]) -> ResultRow {
+        let mut row = ResultRow::new();
+        for (name, value) in values {
+            row.insert(name.to_string(), SqlValue::String(value.to_string()));
+        }
+        row
+    }
+
+    #[test]
+    fn test_kind_from_column_names() {
+        assert_eq!(
+            GraphKind::from_column_names(["graph_id_3A2B", "$node_id_3A2B", "name"]),
+            Some(GraphKind::Node)
+        );
+        assert_eq!(
+            GraphKind::from_column_names(["$edge_id_1F", "$from_id_1F", "$to_id_1F"]),
+            Some(GraphKind::Edge)
+        );
+        assert_eq!(GraphKind::from_column_names(["id", "name"]), None);
+    }
+
+    #[test]
+    fn test_is_implicit_column() {
+        assert!(is_implicit_column("$node_id_7D3B0C"));
+        assert!(is_implicit_column("graph_id_7D3B0C"));
+        assert!(is_implicit_column("from_obj_id_AB12"));
+        assert!(!is_implicit_column("from_id"));
+        assert!(!is_implicit_column("to_id_customer"));
+        assert!(!is_implicit_column("name"));
+    }
+
+    #[test]
+    fn test_from_rows_groups_columns_and_connections() {
+        let tables = GraphTable::from_rows(
+            &[
+                row(&[
+                    ("schema_name", "dbo"),
+                    ("table_name", "Person"),
+                    ("graph_type", "NODE"),
+                    ("column_name", "id"),
+                    ("data_type", "int"),
+                ]),
+                row(&[
+                    ("schema_name", "dbo"),
+                    ("table_name", "Person"),
+                    ("graph_type", "NODE"),
+                    ("column_name", "name"),
+                    ("data_type", "nvarchar"),
+                ]),
+                row(&[
+                    ("schema_name", "dbo"),
+                    ("table_name", "Likes"),
+                    ("graph_type", "EDGE"),
+                ]),
+            ],
+            &[row(&[
+                ("edge_schema", "dbo"),
+                ("edge_table", "Likes"),
+                ("from_table", "dbo.Person"),
+                ("to_table", "dbo.Restaurant"),
+            ])],
+        );
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].columns, ["id int", "name nvarchar"]);
+        assert!(tables[1].columns.is_empty());
+        assert_eq!(
+            tables[1].connections,
+            [("dbo.Person".to_string(), "dbo.Restaurant".to_string())]
+        );
+
+        let example = match_example(&tables).unwrap();
+        assert!(example
+            .contains("FROM [dbo].[Person] AS a, [dbo].[Likes] AS e, [dbo].[Restaurant] AS b"));
+        assert!(example.contains("MATCH(a-(e)->b)"));
+    }
+
+    #[test]
+    fn test_match_example_without_constraints() {
+        let tables = GraphTable::from_rows(
+            &[
+                row(&[
+                    ("schema_name", "dbo"),
+                    ("table_name", "Person"),
+                    ("graph_type", "NODE"),
+                ]),
+                row(&[
+                    ("schema_name", "dbo"),
+                    ("table_name", "FriendOf"),
+                    ("graph_type", "EDGE"),
+                ]),
+            ],
+            &[],
+        );
+        let example = match_example(&tables).unwrap();
+        assert!(example.contains("[dbo].[Person] AS a, [dbo].[FriendOf] AS e, [dbo].[Person] AS b"));
+        assert!(match_example(&tables[..1]).is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Availability Group and replication health.
+//!
+//! Availability Group state comes from `sys.dm_hadr_database_replica_states`,
+//! one row per database and replica. A secondary only sees its own rows, so
+//! the full picture, including each secondary's commit lag behind the
+//! primary, is only available when connected to the primary.
+//!
+//! Replication agent status is read from the distribution database: the
+//! latest history row of each log reader and distribution agent gives its
+//! run status, last message and delivery latency.
+
+use crate::database::query::ResultRow;
+use serde::Serialize;
+
+/// State of one availability database on one replica.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaDatabaseState {
+    pub availability_group: String,
+    pub replica: String,
+    pub database: String,
+    pub role: Option<String>,
+    pub is_local: bool,
+    pub availability_mode: Option<String>,
+    pub synchronization_state: Option<String>,
+    pub synchronization_health: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suspend_reason: Option<String>,
+    pub log_send_queue_kb: Option<i64>,
+    pub log_send_rate_kb_per_sec: Option<i64>,
+    pub redo_queue_kb: Option<i64>,
+    pub redo_rate_kb_per_sec: Option<i64>,
+    pub last_commit_time: Option<String>,
+    pub last_hardened_time: Option<String>,
+    pub last_redone_time: Option<String>,
+    /// Seconds the replica's last commit is behind the primary's.
+    pub commit_lag_seconds: Option<i64>,
+    /// Seconds to redo the queued log at the current redo rate.
+    pub estimated_redo_seconds: Option<f64>,
+    /// Whether the secondary has received and redone all log sent so far.
+    pub caught_up: bool,
+    pub warnings: Vec<String>,
+}
+
+impl ReplicaDatabaseState {
+    /// Build from a row of [`AG_DATABASE_STATES_QUERY`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+        let flag = |column: &str| matches!(text(column).as_deref(), Some("1" | "true"));
+
+        let mut state = Self {
+            availability_group: text("availability_group")?,
+            replica: text("replica_server_name")?,
+            database: text("database_name").unwrap_or_default(),
+            role: text("role"),
+            is_local: flag("is_local"),
+            availability_mode: text("availability_mode"),
+            synchronization_state: text("synchronization_state"),
+            synchronization_health: text("synchronization_health"),
+            suspend_reason: flag("is_suspended")
+                .then(|| text("suspend_reason").unwrap_or_else(|| "SUSPENDED".to_string())),
+            log_send_queue_kb: number("log_send_queue_kb"),
+            log_send_rate_kb_per_sec: number("log_send_rate_kb"),
+            redo_queue_kb: number("redo_queue_kb"),
+            redo_rate_kb_per_sec: number("redo_rate_kb"),
+            last_commit_time: text("last_commit_time"),
+            last_hardened_time: text("last_hardened_time"),
+            last_redone_time: text("last_redone_time"),
+            commit_lag_seconds: number("commit_lag_seconds").map(|s| s.max(0)),
+            estimated_redo_seconds: None,
+            caught_up: false,
+            warnings: Vec::new(),
+        };
+        state.estimated_redo_seconds = match (state.redo_queue_kb, state.redo_rate_kb_per_sec) {
+            (Some(0), _) => Some(0.0),
+            (Some(queue), Some(rate)) if rate > 0 => {
+                Some((queue as f64 / rate as f64 * 10.0).round() / 10.0)
+            }
+            _ => None,
+        };
+        state.caught_up = state.is_primary()
+            || (state.log_send_queue_kb == Some(0) && state.redo_queue_kb == Some(0));
+        state.warnings = state.health_warnings();
+        Some(state)
+    }
+
+    /// Whether this row is the primary replica's copy of the database.
+    pub fn is_primary(&self) -> bool {
+        self.role.as_deref() == Some("PRIMARY")
+    }
+
+    /// Problems worth pointing out for this replica's copy of the database.
+    fn health_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(reason) = &self.suspend_reason {
+            warnings.push(format!("Data movement is suspended ({})", reason));
+        }
+        if let Some(health) = &self.synchronization_health {
+            if health != "HEALTHY" {
+                warnings.push(format!("Synchronization health is {}", health));
+            }
+        }
+        if !self.is_primary() {
+            let synchronous = self.availability_mode.as_deref() == Some("SYNCHRONOUS_COMMIT");
+            if synchronous && self.synchronization_state.as_deref() != Some("SYNCHRONIZED") {
+                warnings.push(
+                    "Synchronous-commit replica is not SYNCHRONIZED; failover would lose data"
+                        .to_string(),
+                );
+            }
+            if self.redo_rate_kb_per_sec == Some(0) && self.redo_queue_kb.is_some_and(|q| q > 0) {
+                warnings.push("Redo queue is not draining (redo rate is 0)".to_string());
+            }
+        }
+        warnings
+    }
+}
+
+/// Every availability database on every replica visible from this server,
+/// with the commit lag of each copy behind the primary's.
+pub const AG_DATABASE_STATES_QUERY: &str = r#"
+    SELECT
+        ag.name AS availability_group,
+        ar.replica_server_name,
+        DB_NAME(drs.database_id) AS database_name,
+        rs.role_desc AS role,
+        CAST(drs.is_local AS int) AS is_local,
+        ar.availability_mode_desc AS availability_mode,
+        drs.synchronization_state_desc AS synchronization_state,
+        drs.synchronization_health_desc AS synchronization_health,
+        CAST(drs.is_suspended AS int) AS is_suspended,
+        drs.suspend_reason_desc AS suspend_reason,
+        drs.log_send_queue_size AS log_send_queue_kb,
+        drs.log_send_rate AS log_send_rate_kb,
+        drs.redo_queue_size AS redo_queue_kb,
+        drs.redo_rate AS redo_rate_kb,
+        CONVERT(varchar(23), drs.last_commit_time, 121) AS last_commit_time,
+        CONVERT(varchar(23), drs.last_hardened_time, 121) AS last_hardened_time,
+        CONVERT(varchar(23), drs.last_redone_time, 121) AS last_redone_time,
+        DATEDIFF(second, drs.last_commit_time, p.last_commit_time) AS commit_lag_seconds
+    FROM sys.dm_hadr_database_replica_states drs
+    INNER JOIN sys.availability_replicas ar ON ar.replica_id = drs.replica_id
+    INNER JOIN sys.availability_groups ag ON ag.group_id = drs.group_id
+    LEFT JOIN sys.dm_hadr_availability_replica_states rs ON rs.replica_id = drs.replica_id
+    OUTER APPLY (
+        SELECT TOP (1) pd.last_commit_time
+        FROM sys.dm_hadr_database_replica_states pd
+        INNER JOIN sys.dm_hadr_availability_replica_states pr ON pr.replica_id = pd.replica_id
+        WHERE pd.group_id = drs.group_id
+        AND pd.group_database_id = drs.group_database_id
+        AND pr.role_desc = 'PRIMARY'
+    ) p
+    ORDER BY ag.name, database_name, ar.replica_server_name
+"#;
+
+/// Run status of a replication agent history row.
+pub fn agent_run_status(code: i64) -> &'static str {
+    match code {
+        1 => "started",
+        2 => "succeeded",
+        3 => "in_progress",
+        4 => "idle",
+        5 => "retrying",
+        6 => "failed",
+        _ => "unknown",
+    }
+}
+
+/// Latest status of a log reader or distribution agent.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicationAgent {
+    /// `log_reader` or `distribution`.
+    pub agent_type: String,
+    pub name: String,
+    pub publisher_db: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscriber: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscriber_db: Option<String>,
+    pub status: &'static str,
+    pub last_message: Option<String>,
+    pub last_action_time: Option<String>,
+    /// Delivery latency of the last reported batch in milliseconds.
+    pub latency_ms: Option<i64>,
+}
+
+impl ReplicationAgent {
+    /// Build from a row of [`replication_agents_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+
+        Some(Self {
+            agent_type: text("agent_type")?,
+            name: text("agent_name")?,
+            publisher_db: text("publisher_db"),
+            publication: text("publication"),
+            subscriber: text("subscriber"),
+            subscriber_db: text("subscriber_db"),
+            status: number("runstatus").map_or("never_run", agent_run_status),
+            last_message: text("comments"),
+            last_action_time: text("last_action_time"),
+            latency_ms: number("delivery_latency"),
+        })
+    }
+}
+
+/// Distribution databases on this server.
+pub const DISTRIBUTION_DATABASES_QUERY: &str =
+    "SELECT name FROM sys.databases WHERE is_distributor = 1 ORDER BY name";
+
+/// Log reader and distribution agents of a distribution database with the
+/// latest history row of each.
+pub fn replication_agents_query(distribution_db: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            'log_reader' AS agent_type,
+            a.name AS agent_name,
+            a.publisher_db,
+            CAST(NULL AS sysname) AS publication,
+            CAST(NULL AS sysname) AS subscriber,
+            CAST(NULL AS sysname) AS subscriber_db,
+            h.runstatus,
+            h.comments,
+            CONVERT(varchar(23), h.time, 121) AS last_action_time,
+            h.delivery_latency
+        FROM {db}.dbo.MSlogreader_agents a
+        OUTER APPLY (
+            SELECT TOP (1) runstatus, comments, time, delivery_latency
+            FROM {db}.dbo.MSlogreader_history
+            WHERE agent_id = a.id
+            ORDER BY time DESC
+        ) h
+        UNION ALL
+        SELECT
+            'distribution',
+            a.name,
+            a.publisher_db,
+            a.publication,
+            s.name,
+            a.subscriber_db,
+            h.runstatus,
+            h.comments,
+            CONVERT(varchar(23), h.time, 121),
+            h.delivery_latency
+        FROM {db}.dbo.MSdistribution_agents a
+        LEFT JOIN sys.servers s ON s.server_id = a.subscriber_id
+        OUTER APPLY (
+            SELECT TOP (1) runstatus, comments, time, delivery_latency
+            FROM {db}.dbo.MSdistribution_history
+            WHERE agent_id = a.id
+            ORDER BY time DESC
+        ) h
+        ORDER BY agent_type DESC, agent_name
+    "#,
+        db = format!("[{}]", distribution_db.replace(']', "]]"))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn secondary(state: &str, mode: &str, send_queue: i64, redo_queue: i64) -> ResultRow {
+        ResultRow::from_values(&[
+            ("availability_group", SqlValue::String("ag1".into())),
+            ("replica_server_name", SqlValue::String("SQL02".into())),
+            ("database_name", SqlValue::String("Sales".into())),
+            ("role", SqlValue::String("SECONDARY".into())),
+            ("availability_mode", SqlValue::String(mode.into())),
+            ("synchronization_state", SqlValue::String(state.into())),
+            ("synchronization_health", SqlValue::String("HEALTHY".into())),
+            ("is_suspended", SqlValue::Bool(false)),
+            ("log_send_queue_kb", SqlValue::I64(send_queue)),
+            ("redo_queue_kb", SqlValue::I64(redo_queue)),
+            ("redo_rate_kb", SqlValue::I64(2_048)),
+            ("commit_lag_seconds", SqlValue::I32(12)),
+        ])
+    }
+
+    #[test]
+    fn test_caught_up_secondary() {
+        let state =
+            ReplicaDatabaseState::from_row(&secondary("SYNCHRONIZED", "SYNCHRONOUS_COMMIT", 0, 0))
+                .unwrap();
+        assert!(state.caught_up);
+        assert_eq!(state.estimated_redo_seconds, Some(0.0));
+        assert!(state.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_lagging_secondary() {
+        let state = ReplicaDatabaseState::from_row(&secondary(
+            "SYNCHRONIZING",
+            "SYNCHRONOUS_COMMIT",
+            512,
+            10_240,
+        ))
+        .unwrap();
+        assert!(!state.caught_up);
+        assert_eq!(state.estimated_redo_seconds, Some(5.0));
+        assert_eq!(state.commit_lag_seconds, Some(12));
+        assert!(state.warnings[0].contains("not SYNCHRONIZED"));
+
+        let async_state = ReplicaDatabaseState::from_row(&secondary(
+            "SYNCHRONIZING",
+            "ASYNCHRONOUS_COMMIT",
+            512,
+            10_240,
+        ))
+        .unwrap();
+        assert!(async_state.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_suspended_database() {
+        let mut r = secondary("NOT SYNCHRONIZING", "ASYNCHRONOUS_COMMIT", 0, 0);
+        r.insert("is_suspended".into(), SqlValue::Bool(true));
+        r.insert(
+            "suspend_reason".into(),
+            SqlValue::String("SUSPEND_FROM_USER".into()),
+        );
+        let state = ReplicaDatabaseState::from_row(&r).unwrap();
+        assert_eq!(state.suspend_reason.as_deref(), Some("SUSPEND_FROM_USER"));
+        assert!(state.warnings[0].contains("suspended"));
+    }
+
+    #[test]
+    fn test_replication_agent_status() {
+        let agent = ReplicationAgent::from_row(&ResultRow::from_values(&[
+            ("agent_type", SqlValue::String("distribution".into())),
+            (
+                "agent_name",
+                SqlValue::String("SQL01-Sales-Pub-SQL03-1".into()),
+            ),
+            ("runstatus", SqlValue::I32(6)),
+            ("delivery_latency", SqlValue::I32(1_500)),
+        ]))
+        .unwrap();
+        assert_eq!(agent.status, "failed");
+        assert_eq!(agent.latency_ms, Some(1_500));
+
+        let idle = ReplicationAgent::from_row(&ResultRow::from_values(&[
+            ("agent_type", SqlValue::String("log_reader".into())),
+            ("agent_name", SqlValue::String("SQL01-Sales-1".into())),
+        ]))
+        .unwrap();
+        assert_eq!(idle.status, "never_run");
+    }
+
+    #[test]
+    fn test_replication_agents_query_escapes_database() {
+        let query = replication_agents_query("dist]x");
+        assert!(query.contains("[dist]]x].dbo.MSlogreader_agents"));
+        assert!(query.contains("[dist]]x].dbo.MSdistribution_history"));
+    }
+}
@@ -0,0 +1,271 @@
+//! Referential integrity checks.
+//!
+//! Foreign keys that were disabled, or re-enabled `WITH NOCHECK` after a bulk
+//! load, can hide child rows whose parent does not exist. Each key is checked
+//! with a `NOT EXISTS` probe against the parent table, following SQL Server's
+//! rule that rows with a NULL in any key column are not checked. Repair
+//! scripts delete or detach the orphans and re-validate the constraint with
+//! `WITH CHECK CHECK CONSTRAINT`, which also makes it trusted again. Nothing
+//! here executes SQL.
+
+use crate::database::metadata::ForeignKeyRelationship;
+use crate::error::ServerError;
+use crate::security::safe_identifier;
+use serde::Serialize;
+use serde_json::Value;
+
+/// State of a foreign key constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintStatus {
+    Enabled,
+    Disabled,
+    /// Enabled but not validated against existing rows.
+    NotTrusted,
+}
+
+impl ConstraintStatus {
+    /// Status of a foreign key from its metadata.
+    pub fn of(fk: &ForeignKeyRelationship) -> Self {
+        if fk.is_disabled {
+            Self::Disabled
+        } else if fk.is_not_trusted {
+            Self::NotTrusted
+        } else {
+            Self::Enabled
+        }
+    }
+}
+
+/// How a repair script treats orphaned rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairStrategy {
+    /// Delete the orphaned child rows.
+    Delete,
+    /// Set the foreign key columns of orphaned rows to NULL.
+    SetNull,
+}
+
+impl RepairStrategy {
+    /// Parse a tool option (`delete` or `set_null`).
+    pub fn parse(value: &str) -> Result<Self, ServerError> {
+        match value.to_lowercase().as_str() {
+            "delete" => Ok(Self::Delete),
+            "set_null" | "null" => Ok(Self::SetNull),
+            other => Err(ServerError::invalid_input(format!(
+                "Unknown repair strategy '{}' (expected delete or set_null)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Result of checking one foreign key.
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeyCheck {
+    pub constraint: String,
+    /// Child table as `schema.table`.
+    pub table: String,
+    pub columns: Vec<String>,
+    /// Parent table as `schema.table`.
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    pub status: ConstraintStatus,
+    /// Child rows without a parent (`None` when the check failed).
+    pub orphan_count: Option<i64>,
+    /// Most frequent missing parent keys with their child row counts.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_keys: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repair_sql: Option<String>,
+}
+
+impl ForeignKeyCheck {
+    /// An unchecked result for a foreign key.
+    pub fn new(fk: &ForeignKeyRelationship) -> Self {
+        Self {
+            constraint: fk.constraint_name.clone(),
+            table: format!("{}.{}", fk.parent_schema, fk.parent_table),
+            columns: fk.parent_columns.clone(),
+            referenced_table: format!("{}.{}", fk.referenced_schema, fk.referenced_table),
+            referenced_columns: fk.referenced_columns.clone(),
+            status: ConstraintStatus::of(fk),
+            orphan_count: None,
+            missing_keys: Vec::new(),
+            error: None,
+            repair_sql: None,
+        }
+    }
+
+    /// Whether the key has orphans or is not enforced on existing rows.
+    pub fn needs_attention(&self) -> bool {
+        self.orphan_count.unwrap_or(0) > 0 || self.status != ConstraintStatus::Enabled
+    }
+}
+
+/// `FROM` and `WHERE` clauses selecting the orphaned rows of a foreign key,
+/// with the child table aliased as `c`.
+pub fn orphan_clause(fk: &ForeignKeyRelationship) -> Result<String, ServerError> {
+    if fk.parent_columns.is_empty() || fk.parent_columns.len() != fk.referenced_columns.len() {
+        return Err(ServerError::invalid_input(format!(
+            "Foreign key {} has mismatched columns",
+            fk.constraint_name
+        )));
+    }
+    let mut not_null = Vec::new();
+    let mut join = Vec::new();
+    for (child, parent) in fk.parent_columns.iter().zip(&fk.referenced_columns) {
+        let child = safe_identifier(child)?;
+        not_null.push(format!("c.{} IS NOT NULL", child));
+        join.push(format!("p.{} = c.{}", safe_identifier(parent)?, child));
+    }
+    Ok(format!(
+        "FROM {}.{} AS c WHERE {} AND NOT EXISTS (SELECT 1 FROM {}.{} AS p WHERE {})",
+        safe_identifier(&fk.parent_schema)?,
+        safe_identifier(&fk.parent_table)?,
+        not_null.join(" AND "),
+        safe_identifier(&fk.referenced_schema)?,
+        safe_identifier(&fk.referenced_table)?,
+        join.join(" AND ")
+    ))
+}
+
+/// Query counting the orphaned rows of a foreign key as `orphan_count`.
+pub fn orphan_count_query(fk: &ForeignKeyRelationship) -> Result<String, ServerError> {
+    Ok(format!(
+        "SELECT COUNT_BIG(*) AS orphan_count {}",
+        orphan_clause(fk)?
+    ))
+}
+
+/// Query listing the `limit` missing parent keys referenced by the most
+/// child rows, with the child row count as `row_count`.
+pub fn missing_keys_query(
+    fk: &ForeignKeyRelationship,
+    limit: usize,
+) -> Result<String, ServerError> {
+    let columns = fk
+        .parent_columns
+        .iter()
+        .map(|c| safe_identifier(c).map(|c| format!("c.{}", c)))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", ");
+    Ok(format!(
+        "SELECT TOP ({}) {}, COUNT_BIG(*) AS row_count {} GROUP BY {} ORDER BY row_count DESC",
+        limit.max(1),
+        columns,
+        orphan_clause(fk)?,
+        columns
+    ))
+}
+
+/// Script that repairs a foreign key and re-validates it.
+///
+/// Orphans are only touched when `orphan_count` is non-zero; the constraint
+/// is always re-checked so it ends up enabled and trusted.
+pub fn repair_script(
+    fk: &ForeignKeyRelationship,
+    strategy: RepairStrategy,
+    orphan_count: i64,
+) -> Result<String, ServerError> {
+    let table = format!(
+        "{}.{}",
+        safe_identifier(&fk.parent_schema)?,
+        safe_identifier(&fk.parent_table)?
+    );
+    let mut statements = vec![format!(
+        "-- {}: {} orphaned row(s) in {}.{}",
+        fk.constraint_name, orphan_count, fk.parent_schema, fk.parent_table
+    )];
+    if orphan_count > 0 {
+        let clause = orphan_clause(fk)?;
+        statements.push(match strategy {
+            RepairStrategy::Delete => format!("DELETE c {};", clause),
+            RepairStrategy::SetNull => {
+                let assignments = fk
+                    .parent_columns
+                    .iter()
+                    .map(|c| safe_identifier(c).map(|c| format!("{} = NULL", c)))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(", ");
+                format!("UPDATE c SET {} {};", assignments, clause)
+            }
+        });
+    }
+    statements.push(format!(
+        "ALTER TABLE {} WITH CHECK CHECK CONSTRAINT {};",
+        table,
+        safe_identifier(&fk.constraint_name)?
+    ));
+    Ok(statements.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_lines_fk() -> ForeignKeyRelationship {
+        ForeignKeyRelationship {
+            constraint_name: "FK_OrderLines_Orders".to_string(),
+            parent_schema: "sales".to_string(),
+            parent_table: "OrderLines".to_string(),
+            parent_columns: vec!["OrderId".to_string(), "Region".to_string()],
+            referenced_schema: "sales".to_string(),
+            referenced_table: "Orders".to_string(),
+            referenced_columns: vec!["Id".to_string(), "Region".to_string()],
+            on_delete: "NO_ACTION".to_string(),
+            on_update: "NO_ACTION".to_string(),
+            is_disabled: false,
+            is_not_trusted: true,
+        }
+    }
+
+    #[test]
+    fn test_orphan_queries() {
+        let fk = order_lines_fk();
+        assert_eq!(ConstraintStatus::of(&fk), ConstraintStatus::NotTrusted);
+
+        let query = orphan_count_query(&fk).unwrap();
+        assert_eq!(
+            query,
+            "SELECT COUNT_BIG(*) AS orphan_count FROM [sales].[OrderLines] AS c \
+             WHERE c.[OrderId] IS NOT NULL AND c.[Region] IS NOT NULL AND NOT EXISTS \
+             (SELECT 1 FROM [sales].[Orders] AS p WHERE p.[Id] = c.[OrderId] AND p.[Region] = c.[Region])"
+        );
+
+        let query = missing_keys_query(&fk, 5).unwrap();
+        assert!(
+            query.starts_with("SELECT TOP (5) c.[OrderId], c.[Region], COUNT_BIG(*) AS row_count")
+        );
+        assert!(query.ends_with("GROUP BY c.[OrderId], c.[Region] ORDER BY row_count DESC"));
+
+        let mut broken = order_lines_fk();
+        broken.referenced_columns.pop();
+        assert!(orphan_clause(&broken).is_err());
+    }
+
+    #[test]
+    fn test_repair_script() {
+        let fk = order_lines_fk();
+        let script = repair_script(&fk, RepairStrategy::Delete, 3).unwrap();
+        assert!(script.contains("DELETE c FROM [sales].[OrderLines] AS c WHERE"));
+        assert!(script.ends_with(
+            "ALTER TABLE [sales].[OrderLines] WITH CHECK CHECK CONSTRAINT [FK_OrderLines_Orders];"
+        ));
+
+        let script = repair_script(&fk, RepairStrategy::SetNull, 3).unwrap();
+        assert!(script.contains("UPDATE c SET [OrderId] = NULL, [Region] = NULL FROM"));
+
+        let script = repair_script(&fk, RepairStrategy::Delete, 0).unwrap();
+        assert_eq!(script.lines().count(), 2);
+
+        assert_eq!(
+            RepairStrategy::parse("SET_NULL").unwrap(),
+            RepairStrategy::SetNull
+        );
+        assert!(RepairStrategy::parse("cascade").is_err());
+    }
+}
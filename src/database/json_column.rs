@@ -0,0 +1,582 @@
+//! JSON column schema inference and query generation.
+//!
+//! Sampled values of a JSON text column are parsed and merged into the list
+//! of paths they contain, with the JSON types seen at each path. Queries are
+//! then generated from that list with `JSON_VALUE`, `JSON_QUERY` and
+//! `OPENJSON ... WITH`, quoting path keys the way SQL Server expects
+//! (`$."order id"`), typing scalar values, and expanding arrays into rows.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Deepest object nesting that is walked.
+const MAX_DEPTH: usize = 8;
+
+/// Maximum number of distinct paths collected from a sample.
+pub const MAX_PATHS: usize = 200;
+
+/// Maximum number of paths projected when none are requested.
+const MAX_DEFAULT_PROJECTIONS: usize = 50;
+
+/// A JSON value type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Object,
+    Array,
+}
+
+impl JsonType {
+    /// Lowercase name, as serialized.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Object => "object",
+            Self::Array => "array",
+        }
+    }
+
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Boolean,
+            Value::Number(n) if n.is_i64() || n.is_u64() => Self::Integer,
+            Value::Number(_) => Self::Number,
+            Value::String(_) => Self::String,
+            Value::Object(_) => Self::Object,
+            Value::Array(_) => Self::Array,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    Key(String),
+    Each,
+}
+
+/// A path observed in the sampled documents.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonPathInfo {
+    /// Path in SQL Server syntax; `[*]` marks the elements of an array.
+    pub path: String,
+    /// Types seen at this path.
+    pub types: BTreeSet<JsonType>,
+    /// Number of sampled rows containing the path.
+    pub occurrences: usize,
+    /// SQL type used when projecting the path.
+    pub sql_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
+    #[serde(skip)]
+    segments: Vec<Segment>,
+    #[serde(skip)]
+    last_row: usize,
+}
+
+impl JsonPathInfo {
+    /// Whether the path lies inside an array and needs `OPENJSON` to reach.
+    pub fn in_array(&self) -> bool {
+        self.segments.contains(&Segment::Each)
+    }
+
+    fn is_scalar(&self) -> bool {
+        !self.types.contains(&JsonType::Object) && !self.types.contains(&JsonType::Array)
+    }
+}
+
+/// Paths and types inferred from a sample of a JSON column.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JsonColumnSchema {
+    /// Non-null values sampled.
+    pub sampled_rows: usize,
+    /// Sampled values that are not valid JSON.
+    pub invalid_rows: usize,
+    /// More than [`MAX_PATHS`] paths were found; the rest are not listed.
+    pub truncated: bool,
+    pub paths: Vec<JsonPathInfo>,
+}
+
+impl JsonColumnSchema {
+    /// Infer the schema from sampled column values.
+    pub fn infer<'a>(documents: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut schema = Self::default();
+        let mut index = HashMap::new();
+        for document in documents {
+            schema.sampled_rows += 1;
+            match serde_json::from_str::<Value>(document) {
+                Ok(value) => {
+                    let row = schema.sampled_rows;
+                    schema.walk(&mut index, &value, &mut Vec::new(), row, 0);
+                }
+                Err(_) => schema.invalid_rows += 1,
+            }
+        }
+        for info in &mut schema.paths {
+            info.sql_type = sql_type(&info.types);
+        }
+        schema
+    }
+
+    fn walk(
+        &mut self,
+        index: &mut HashMap<Vec<Segment>, usize>,
+        value: &Value,
+        segments: &mut Vec<Segment>,
+        row: usize,
+        depth: usize,
+    ) {
+        if !segments.is_empty() {
+            self.record(index, value, segments, row);
+        }
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    segments.push(Segment::Key(key.clone()));
+                    self.walk(index, child, segments, row, depth + 1);
+                    segments.pop();
+                }
+            }
+            Value::Array(items) => {
+                segments.push(Segment::Each);
+                for item in items {
+                    self.walk(index, item, segments, row, depth + 1);
+                }
+                segments.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn record(
+        &mut self,
+        index: &mut HashMap<Vec<Segment>, usize>,
+        value: &Value,
+        segments: &[Segment],
+        row: usize,
+    ) {
+        let position = match index.get(segments) {
+            Some(&position) => position,
+            None if self.paths.len() >= MAX_PATHS => {
+                self.truncated = true;
+                return;
+            }
+            None => {
+                index.insert(segments.to_vec(), self.paths.len());
+                self.paths.push(JsonPathInfo {
+                    path: json_path(segments),
+                    types: BTreeSet::new(),
+                    occurrences: 0,
+                    sql_type: "",
+                    example: None,
+                    segments: segments.to_vec(),
+                    last_row: 0,
+                });
+                self.paths.len() - 1
+            }
+        };
+        let info = &mut self.paths[position];
+        info.types.insert(JsonType::of(value));
+        if info.last_row != row {
+            info.last_row = row;
+            info.occurrences += 1;
+        }
+        if info.example.is_none()
+            && !matches!(value, Value::Null | Value::Object(_) | Value::Array(_))
+        {
+            let text = value.to_string();
+            info.example = Some(if text.chars().count() > 60 {
+                format!("{}...", text.chars().take(60).collect::<String>())
+            } else {
+                text
+            });
+        }
+    }
+
+    fn find(&self, path: &str) -> Option<&JsonPathInfo> {
+        let path = path.trim();
+        self.paths.iter().find(|p| p.path == path)
+    }
+}
+
+/// SQL type for values with the given JSON types.
+fn sql_type(types: &BTreeSet<JsonType>) -> &'static str {
+    let types: Vec<_> = types.iter().filter(|t| **t != JsonType::Null).collect();
+    match types.as_slice() {
+        [JsonType::Boolean] => "bit",
+        [JsonType::Integer] => "bigint",
+        [JsonType::Number] | [JsonType::Integer, JsonType::Number] => "float",
+        [JsonType::Object] | [JsonType::Array] | [JsonType::Object, JsonType::Array] => {
+            "nvarchar(max)"
+        }
+        _ => "nvarchar(4000)",
+    }
+}
+
+/// Format path segments in SQL Server JSON path syntax.
+fn json_path(segments: &[Segment]) -> String {
+    let mut path = String::from("$");
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                let plain = key
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if plain {
+                    path.push('.');
+                    path.push_str(key);
+                } else {
+                    path.push_str(&format!(
+                        ".\"{}\"",
+                        key.replace('\\', "\\\\").replace('"', "\\\"")
+                    ));
+                }
+            }
+            Segment::Each => path.push_str("[*]"),
+        }
+    }
+    path
+}
+
+/// Unicode string literal for a JSON path.
+fn path_literal(path: &str) -> String {
+    format!("N'{}'", path.replace('\'', "''"))
+}
+
+/// Bracket-quote a column alias.
+fn alias(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Alias derived from the keys of a path, e.g. `customer_address_city`.
+fn path_alias(segments: &[Segment], used: &mut HashSet<String>) -> String {
+    let keys: Vec<&str> = segments
+        .iter()
+        .filter_map(|s| match s {
+            Segment::Key(key) => Some(key.as_str()),
+            Segment::Each => None,
+        })
+        .collect();
+    let base = if keys.is_empty() {
+        "value".to_string()
+    } else {
+        keys.join("_")
+    };
+    let mut name = base.clone();
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{}_{}", base, n);
+        n += 1;
+    }
+    name
+}
+
+/// What to project from a JSON column.
+#[derive(Debug, Default)]
+pub struct JsonProjection<'a> {
+    /// Quoted table name.
+    pub table: &'a str,
+    /// Quoted JSON column name.
+    pub column: &'a str,
+    /// Quoted regular columns to return alongside the JSON values.
+    pub include_columns: &'a [String],
+    /// Paths to project; by default every scalar path outside arrays (or
+    /// every field of the expanded array).
+    pub paths: &'a [String],
+    /// Array path whose elements become rows through `OPENJSON`.
+    pub expand_array: Option<&'a str>,
+    /// Condition added to the WHERE clause.
+    pub filter: Option<&'a str>,
+    pub max_rows: usize,
+}
+
+/// Generate a query projecting JSON paths as typed columns.
+pub fn projection_query(
+    schema: &JsonColumnSchema,
+    request: &JsonProjection<'_>,
+) -> Result<String, String> {
+    let column = format!("t.{}", request.column);
+    let mut used: HashSet<String> = request
+        .include_columns
+        .iter()
+        .map(|c| c.trim_matches(|ch| ch == '[' || ch == ']').to_lowercase())
+        .collect();
+
+    let array = match request.expand_array {
+        Some(path) => {
+            let path = path.trim().trim_end_matches("[*]");
+            match schema.find(path) {
+                Some(info) if info.types.contains(&JsonType::Array) && !info.in_array() => {
+                    Some(info)
+                }
+                Some(_) => {
+                    return Err(format!(
+                        "'{}' is not an array at the top level of the document",
+                        path
+                    ));
+                }
+                None => return Err(format!("Array path '{}' was not found in the sample", path)),
+            }
+        }
+        None => None,
+    };
+    let element_prefix = array.map(|a| {
+        let mut prefix = a.segments.clone();
+        prefix.push(Segment::Each);
+        prefix
+    });
+    let in_element = |info: &JsonPathInfo| {
+        element_prefix.as_ref().is_some_and(|prefix| {
+            info.segments.starts_with(prefix)
+                && !info.segments[prefix.len()..].contains(&Segment::Each)
+        })
+    };
+
+    let mut top_level = Vec::new();
+    let mut element_fields = Vec::new();
+    if request.paths.is_empty() {
+        if let Some(prefix) = &element_prefix {
+            element_fields.extend(schema.paths.iter().filter(|info| {
+                in_element(info) && (info.segments.len() == prefix.len() || info.is_scalar())
+            }));
+            // Objects whose fields are listed separately need no column of their own
+            let has_fields = element_fields
+                .iter()
+                .any(|f| f.segments.len() > prefix.len());
+            element_fields.retain(|f| f.segments.len() > prefix.len() || !has_fields);
+        } else {
+            top_level.extend(
+                schema
+                    .paths
+                    .iter()
+                    .filter(|info| !info.in_array() && info.is_scalar())
+                    .take(MAX_DEFAULT_PROJECTIONS),
+            );
+        }
+    } else {
+        for path in request.paths {
+            match schema.find(path) {
+                Some(info) if in_element(info) => element_fields.push(info),
+                Some(info) if info.in_array() => {
+                    return Err(format!(
+                        "'{}' is inside an array; set expand_array to the array path to project it",
+                        path
+                    ));
+                }
+                Some(info) => top_level.push(info),
+                None => {
+                    return Err(format!(
+                        "Path '{}' was not found in the sample; use one of the inferred paths",
+                        path
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut select = Vec::new();
+    select.extend(request.include_columns.iter().map(|c| format!("t.{}", c)));
+    for info in &top_level {
+        let name = alias(&path_alias(&info.segments, &mut used));
+        let literal = path_literal(&info.path);
+        select.push(match info.sql_type {
+            "nvarchar(max)" => format!("JSON_QUERY({}, {}) AS {}", column, literal, name),
+            "nvarchar(4000)" => format!("JSON_VALUE({}, {}) AS {}", column, literal, name),
+            sql_type => format!(
+                "TRY_CAST(JSON_VALUE({}, {}) AS {}) AS {}",
+                column, literal, sql_type, name
+            ),
+        });
+    }
+
+    let mut from = format!("FROM {} AS t", request.table);
+    if let (Some(array), Some(prefix)) = (array, &element_prefix) {
+        let mut with = Vec::new();
+        for info in &element_fields {
+            let relative = &info.segments[prefix.len()..];
+            let name = alias(&path_alias(relative, &mut used));
+            select.push(format!("j.{}", name));
+            with.push(format!(
+                "{} {} {}{}",
+                name,
+                info.sql_type,
+                path_literal(&json_path(relative)),
+                if info.sql_type == "nvarchar(max)" {
+                    " AS JSON"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if with.is_empty() {
+            return Err(format!(
+                "No fields were found in the elements of '{}'",
+                array.path
+            ));
+        }
+        from.push_str(&format!(
+            "\nCROSS APPLY OPENJSON({}, {}) WITH (\n    {}\n) AS j",
+            column,
+            path_literal(&array.path),
+            with.join(",\n    ")
+        ));
+    }
+
+    if select.is_empty() {
+        return Err("Nothing to project: no scalar paths were found".to_string());
+    }
+
+    let mut query = format!(
+        "SELECT TOP ({})\n    {}\n{}\nWHERE ISJSON({}) = 1",
+        request.max_rows,
+        select.join(",\n    "),
+        from,
+        column
+    );
+    if let Some(filter) = request.filter.map(str::trim).filter(|f| !f.is_empty()) {
+        query.push_str(&format!("\nAND ({})", filter));
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENTS: &[&str] = &[
+        r#"{"id": 1, "customer": {"name": "Ada", "first order": "2024-01-02"}, "total": 10.5, "paid": true, "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}], "tags": ["new"]}"#,
+        r#"{"id": 2, "customer": {"name": "Bob"}, "total": 7, "paid": false, "items": [{"sku": "C3", "qty": 5, "note": null}]}"#,
+        "not json",
+    ];
+
+    fn schema() -> JsonColumnSchema {
+        JsonColumnSchema::infer(DOCUMENTS.iter().copied())
+    }
+
+    #[test]
+    fn test_infer_paths_and_types() {
+        let schema = schema();
+        assert_eq!(schema.sampled_rows, 3);
+        assert_eq!(schema.invalid_rows, 1);
+
+        let id = schema.find("$.id").unwrap();
+        assert_eq!(id.occurrences, 2);
+        assert_eq!(id.sql_type, "bigint");
+        assert_eq!(schema.find("$.total").unwrap().sql_type, "float");
+        assert_eq!(schema.find("$.paid").unwrap().sql_type, "bit");
+        assert_eq!(schema.find("$.customer").unwrap().sql_type, "nvarchar(max)");
+
+        let first_order = schema.find("$.customer.\"first order\"").unwrap();
+        assert_eq!(first_order.occurrences, 1);
+        assert_eq!(first_order.example.as_deref(), Some("\"2024-01-02\""));
+
+        let sku = schema.find("$.items[*].sku").unwrap();
+        assert!(sku.in_array());
+        assert_eq!(sku.occurrences, 2);
+        assert_eq!(schema.find("$.tags[*]").unwrap().sql_type, "nvarchar(4000)");
+    }
+
+    #[test]
+    fn test_projection_query() {
+        let schema = schema();
+        let include = vec!["[OrderId]".to_string()];
+        let paths = vec!["$.id".to_string(), "$.customer.\"first order\"".to_string()];
+        let query = projection_query(
+            &schema,
+            &JsonProjection {
+                table: "[dbo].[Orders]",
+                column: "[Payload]",
+                include_columns: &include,
+                paths: &paths,
+                filter: Some("t.[OrderId] > 5"),
+                max_rows: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "SELECT TOP (10)\n    t.[OrderId],\n    TRY_CAST(JSON_VALUE(t.[Payload], N'$.id') AS bigint) AS [id],\n    JSON_VALUE(t.[Payload], N'$.customer.\"first order\"') AS [customer_first order]\nFROM [dbo].[Orders] AS t\nWHERE ISJSON(t.[Payload]) = 1\nAND (t.[OrderId] > 5)"
+        );
+
+        let all = projection_query(
+            &schema,
+            &JsonProjection {
+                table: "[dbo].[Orders]",
+                column: "[Payload]",
+                max_rows: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(all.contains("AS [customer_name]") && all.contains("AS bit) AS [paid]"));
+        assert!(!all.contains("sku") && !all.contains("AS [customer]"));
+    }
+
+    #[test]
+    fn test_projection_expands_arrays() {
+        let schema = schema();
+        let query = projection_query(
+            &schema,
+            &JsonProjection {
+                table: "[dbo].[Orders]",
+                column: "[Payload]",
+                paths: &["$.id".to_string(), "$.items[*].sku".to_string()],
+                expand_array: Some("$.items[*]"),
+                max_rows: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(query.contains("j.[sku]"));
+        assert!(query.contains(
+            "CROSS APPLY OPENJSON(t.[Payload], N'$.items') WITH (\n    [sku] nvarchar(4000) N'$.sku'\n) AS j"
+        ));
+
+        let tags = projection_query(
+            &schema,
+            &JsonProjection {
+                table: "[dbo].[Orders]",
+                column: "[Payload]",
+                expand_array: Some("$.tags"),
+                max_rows: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(tags.contains("[value] nvarchar(4000) N'$'"));
+
+        let request = |paths: &'static [&'static str], expand| {
+            let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            projection_query(
+                &schema,
+                &JsonProjection {
+                    table: "[dbo].[Orders]",
+                    column: "[Payload]",
+                    paths: &paths,
+                    expand_array: expand,
+                    max_rows: 10,
+                    ..Default::default()
+                },
+            )
+        };
+        assert!(request(&["$.items[*].sku"], None).is_err());
+        assert!(request(&["$.missing"], None).is_err());
+        assert!(request(&[], Some("$.id")).is_err());
+    }
+}
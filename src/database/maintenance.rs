@@ -0,0 +1,483 @@
+//! Index and statistics maintenance planning.
+//!
+//! Reads fragmentation and page density from `sys.dm_db_index_physical_stats`
+//! and turns them into `ALTER INDEX ... REORGANIZE` or `REBUILD` statements
+//! based on configurable thresholds. Statistics freshness comes from
+//! `sys.dm_db_stats_properties` and produces `UPDATE STATISTICS` statements.
+//! Planning is pure so the statements can be previewed before anything is
+//! executed.
+
+use crate::database::query::ResultRow;
+use crate::error::ServerError;
+use serde::Serialize;
+
+/// Scan modes accepted by `sys.dm_db_index_physical_stats`.
+pub const SCAN_MODES: &[&str] = &["LIMITED", "SAMPLED", "DETAILED"];
+
+/// Fragmentation of one index partition.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexFragmentation {
+    pub schema: String,
+    pub table: String,
+    pub index: String,
+    pub index_type: String,
+    pub partition_number: i64,
+    /// Number of partitions of the index (statements target a partition when > 1).
+    pub partition_count: i64,
+    pub fragmentation_percent: f64,
+    /// Average page fullness (`None` in LIMITED mode).
+    pub page_density_percent: Option<f64>,
+    pub page_count: i64,
+}
+
+impl IndexFragmentation {
+    /// Build from a row of [`fragmentation_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<f64>().ok());
+
+        Some(Self {
+            schema: text("schema_name")?,
+            table: text("table_name")?,
+            index: text("index_name")?,
+            index_type: text("index_type").unwrap_or_default(),
+            partition_number: number("partition_number").unwrap_or(1.0) as i64,
+            partition_count: number("partition_count").unwrap_or(1.0) as i64,
+            fragmentation_percent: number("fragmentation_percent").unwrap_or(0.0),
+            page_density_percent: number("page_density_percent"),
+            page_count: number("page_count").unwrap_or(0.0) as i64,
+        })
+    }
+}
+
+/// Build the fragmentation query for the current database.
+///
+/// Identifiers must already be validated. Only rowstore indexes are reported
+/// (heaps, columnstore and disabled indexes are skipped).
+pub fn fragmentation_query(
+    schema: Option<&str>,
+    table: Option<&str>,
+    mode: &str,
+    min_page_count: u64,
+) -> String {
+    let object_id = match (schema, table) {
+        (schema, Some(table)) => format!(
+            "OBJECT_ID(N'[{}].[{}]')",
+            schema.unwrap_or("dbo").replace('\'', "''"),
+            table.replace('\'', "''")
+        ),
+        (_, None) => "NULL".to_string(),
+    };
+    let schema_filter = schema
+        .map(|s| format!("\n  AND s.name = N'{}'", s.replace('\'', "''")))
+        .unwrap_or_default();
+
+    format!(
+        "SELECT s.name AS schema_name, t.name AS table_name, i.name AS index_name, \
+i.type_desc AS index_type, ps.partition_number, \
+(SELECT COUNT(*) FROM sys.partitions p WHERE p.object_id = i.object_id AND p.index_id = i.index_id) AS partition_count, \
+CAST(ps.avg_fragmentation_in_percent AS decimal(5, 2)) AS fragmentation_percent, \
+CAST(ps.avg_page_space_used_in_percent AS decimal(5, 2)) AS page_density_percent, \
+ps.page_count
+FROM sys.dm_db_index_physical_stats(DB_ID(), {}, NULL, NULL, '{}') ps
+JOIN sys.indexes i ON i.object_id = ps.object_id AND i.index_id = ps.index_id
+JOIN sys.tables t ON t.object_id = i.object_id
+JOIN sys.schemas s ON s.schema_id = t.schema_id
+WHERE i.type IN (1, 2)
+  AND i.is_disabled = 0
+  AND i.is_hypothetical = 0
+  AND t.is_ms_shipped = 0
+  AND ps.index_level = 0
+  AND ps.alloc_unit_type_desc = 'IN_ROW_DATA'
+  AND ps.page_count >= {}{}
+ORDER BY ps.avg_fragmentation_in_percent DESC, ps.page_count DESC",
+        object_id, mode, min_page_count, schema_filter
+    )
+}
+
+/// Index maintenance operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceAction {
+    /// Defragment leaf pages in place (always online).
+    Reorganize,
+    /// Recreate the index.
+    Rebuild,
+}
+
+/// Thresholds that decide which indexes need maintenance.
+#[derive(Debug, Clone)]
+pub struct MaintenanceThresholds {
+    /// Reorganize at or above this fragmentation percentage.
+    pub reorganize_percent: f64,
+    /// Rebuild at or above this fragmentation percentage.
+    pub rebuild_percent: f64,
+    /// Rebuild when page density falls below this percentage (`None` = ignore density).
+    pub min_page_density_percent: Option<f64>,
+    /// Rebuild online (Enterprise edition and Azure SQL).
+    pub online: bool,
+}
+
+/// A planned maintenance statement for one index partition.
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStep {
+    pub schema: String,
+    pub table: String,
+    pub index: String,
+    pub partition_number: Option<i64>,
+    pub action: MaintenanceAction,
+    pub reason: String,
+    pub sql: String,
+}
+
+/// Plan maintenance for the indexes that exceed the thresholds.
+pub fn plan_index_maintenance(
+    indexes: &[IndexFragmentation],
+    thresholds: &MaintenanceThresholds,
+) -> Vec<MaintenanceStep> {
+    indexes
+        .iter()
+        .filter_map(|index| {
+            let low_density = thresholds
+                .min_page_density_percent
+                .zip(index.page_density_percent)
+                .filter(|(min, density)| density < min);
+
+            let (action, reason) = if index.fragmentation_percent >= thresholds.rebuild_percent {
+                (
+                    MaintenanceAction::Rebuild,
+                    format!(
+                        "fragmentation {:.1}% >= {:.1}%",
+                        index.fragmentation_percent, thresholds.rebuild_percent
+                    ),
+                )
+            } else if let Some((min, density)) = low_density {
+                (
+                    MaintenanceAction::Rebuild,
+                    format!("page density {:.1}% < {:.1}%", density, min),
+                )
+            } else if index.fragmentation_percent >= thresholds.reorganize_percent {
+                (
+                    MaintenanceAction::Reorganize,
+                    format!(
+                        "fragmentation {:.1}% >= {:.1}%",
+                        index.fragmentation_percent, thresholds.reorganize_percent
+                    ),
+                )
+            } else {
+                return None;
+            };
+
+            let partition = (index.partition_count > 1).then_some(index.partition_number);
+            Some(MaintenanceStep {
+                sql: maintenance_sql(index, action, partition, thresholds.online),
+                schema: index.schema.clone(),
+                table: index.table.clone(),
+                index: index.index.clone(),
+                partition_number: partition,
+                action,
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// Render the `ALTER INDEX` statement for a step.
+fn maintenance_sql(
+    index: &IndexFragmentation,
+    action: MaintenanceAction,
+    partition: Option<i64>,
+    online: bool,
+) -> String {
+    let mut sql = format!(
+        "ALTER INDEX [{}] ON [{}].[{}] {}",
+        index.index.replace(']', "]]"),
+        index.schema.replace(']', "]]"),
+        index.table.replace(']', "]]"),
+        match action {
+            MaintenanceAction::Reorganize => "REORGANIZE",
+            MaintenanceAction::Rebuild => "REBUILD",
+        }
+    );
+    if let Some(partition) = partition {
+        sql.push_str(&format!(" PARTITION = {}", partition));
+    }
+    if online && action == MaintenanceAction::Rebuild {
+        sql.push_str(" WITH (ONLINE = ON)");
+    }
+    sql
+}
+
+// =============================================================================
+// Statistics
+// =============================================================================
+
+/// Freshness of one statistics object.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatisticsInfo {
+    pub schema: String,
+    pub table: String,
+    pub statistics: String,
+    /// Whether the statistics were created automatically for a column.
+    pub auto_created: bool,
+    pub has_filter: bool,
+    pub last_updated: Option<String>,
+    pub rows: i64,
+    pub rows_sampled: i64,
+    pub modification_counter: i64,
+    /// Modifications as a percentage of rows.
+    pub modification_percent: f64,
+    /// Modifications after which SQL Server updates the statistics automatically.
+    pub auto_update_threshold: i64,
+    /// Whether the statistics exceed the requested staleness threshold.
+    pub stale: bool,
+}
+
+impl StatisticsInfo {
+    /// Build from a row of [`statistics_query`].
+    ///
+    /// With `threshold_percent` set, statistics are stale once modifications
+    /// reach that percentage of rows; otherwise once they reach the automatic
+    /// update threshold (`MIN(500 + 20% of rows, SQRT(1000 * rows))`).
+    pub fn from_row(row: &ResultRow, threshold_percent: Option<f64>) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| {
+            text(column)
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+        let flag = |column: &str| text(column).is_some_and(|v| v == "1" || v == "true");
+
+        let rows = number("rows") as i64;
+        let modification_counter = number("modification_counter") as i64;
+        let modification_percent = if rows > 0 {
+            (modification_counter as f64 * 10_000.0 / rows as f64).round() / 100.0
+        } else {
+            0.0
+        };
+        let auto_update_threshold = auto_update_threshold(rows);
+        let stale = match threshold_percent {
+            Some(percent) => modification_percent >= percent,
+            None => modification_counter >= auto_update_threshold,
+        };
+
+        Some(Self {
+            schema: text("schema_name")?,
+            table: text("table_name")?,
+            statistics: text("stats_name")?,
+            auto_created: flag("auto_created"),
+            has_filter: flag("has_filter"),
+            last_updated: text("last_updated"),
+            rows,
+            rows_sampled: number("rows_sampled") as i64,
+            modification_counter,
+            modification_percent,
+            auto_update_threshold,
+            stale,
+        })
+    }
+}
+
+/// Modification count that triggers an automatic statistics update.
+fn auto_update_threshold(rows: i64) -> i64 {
+    let classic = 500.0 + 0.2 * rows as f64;
+    let dynamic = (1000.0 * rows as f64).sqrt();
+    classic.min(dynamic).ceil() as i64
+}
+
+/// Build the statistics freshness query for the current database.
+///
+/// Identifiers must already be validated. Statistics without modifications
+/// since their last update are skipped.
+pub fn statistics_query(schema: Option<&str>, table: Option<&str>) -> String {
+    let mut filters = String::new();
+    if let Some(schema) = schema {
+        filters.push_str(&format!(
+            "\n  AND s.name = N'{}'",
+            schema.replace('\'', "''")
+        ));
+    }
+    if let Some(table) = table {
+        filters.push_str(&format!(
+            "\n  AND t.name = N'{}'",
+            table.replace('\'', "''")
+        ));
+    }
+
+    format!(
+        "SELECT s.name AS schema_name, t.name AS table_name, st.name AS stats_name, \
+st.auto_created, st.has_filter, \
+CONVERT(varchar(19), sp.last_updated, 126) AS last_updated, \
+sp.rows, sp.rows_sampled, sp.modification_counter
+FROM sys.stats st
+JOIN sys.tables t ON t.object_id = st.object_id
+JOIN sys.schemas s ON s.schema_id = t.schema_id
+CROSS APPLY sys.dm_db_stats_properties(st.object_id, st.stats_id) sp
+WHERE t.is_ms_shipped = 0
+  AND sp.modification_counter > 0{}
+ORDER BY sp.modification_counter DESC",
+        filters
+    )
+}
+
+/// How `UPDATE STATISTICS` samples the table.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatisticsScan {
+    /// Let SQL Server choose the sample size.
+    Default,
+    /// Read every row.
+    FullScan,
+    /// Read the given percentage of rows.
+    SamplePercent(f64),
+}
+
+impl StatisticsScan {
+    /// Choose the scan from tool options.
+    pub fn from_options(fullscan: bool, sample_percent: Option<f64>) -> Result<Self, ServerError> {
+        match (fullscan, sample_percent) {
+            (true, Some(_)) => Err(ServerError::invalid_input(
+                "Specify either fullscan or sample_percent, not both",
+            )),
+            (true, None) => Ok(Self::FullScan),
+            (false, Some(percent)) if percent > 0.0 && percent <= 100.0 => {
+                Ok(Self::SamplePercent(percent))
+            }
+            (false, Some(percent)) => Err(ServerError::invalid_input(format!(
+                "sample_percent must be greater than 0 and at most 100 (got {})",
+                percent
+            ))),
+            (false, None) => Ok(Self::Default),
+        }
+    }
+
+    /// Render the `WITH` clause.
+    fn with_clause(&self) -> String {
+        match self {
+            Self::Default => String::new(),
+            Self::FullScan => " WITH FULLSCAN".to_string(),
+            Self::SamplePercent(percent) => format!(" WITH SAMPLE {} PERCENT", percent),
+        }
+    }
+}
+
+/// Render the `UPDATE STATISTICS` statement for one statistics object.
+pub fn update_statistics_sql(stats: &StatisticsInfo, scan: StatisticsScan) -> String {
+    format!(
+        "UPDATE STATISTICS [{}].[{}] ([{}]){}",
+        stats.schema.replace(']', "]]"),
+        stats.table.replace(']', "]]"),
+        stats.statistics.replace(']', "]]"),
+        scan.with_clause()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(name: &str, fragmentation: f64, density: Option<f64>) -> IndexFragmentation {
+        IndexFragmentation {
+            schema: "dbo".to_string(),
+            table: "Orders".to_string(),
+            index: name.to_string(),
+            index_type: "NONCLUSTERED INDEX".to_string(),
+            partition_number: 1,
+            partition_count: 1,
+            fragmentation_percent: fragmentation,
+            page_density_percent: density,
+            page_count: 5000,
+        }
+    }
+
+    #[test]
+    fn test_plan_index_maintenance() {
+        let thresholds = MaintenanceThresholds {
+            reorganize_percent: 5.0,
+            rebuild_percent: 30.0,
+            min_page_density_percent: Some(70.0),
+            online: true,
+        };
+        let mut partitioned = index("IX_Date", 12.0, Some(90.0));
+        partitioned.partition_number = 3;
+        partitioned.partition_count = 4;
+
+        let steps = plan_index_maintenance(
+            &[
+                index("IX_Customer", 45.0, Some(80.0)),
+                index("IX_Status", 2.0, Some(55.0)),
+                index("IX_Total", 1.0, Some(95.0)),
+                partitioned,
+            ],
+            &thresholds,
+        );
+
+        let sql: Vec<&str> = steps.iter().map(|s| s.sql.as_str()).collect();
+        assert_eq!(
+            sql,
+            vec![
+                "ALTER INDEX [IX_Customer] ON [dbo].[Orders] REBUILD WITH (ONLINE = ON)",
+                "ALTER INDEX [IX_Status] ON [dbo].[Orders] REBUILD WITH (ONLINE = ON)",
+                "ALTER INDEX [IX_Date] ON [dbo].[Orders] REORGANIZE PARTITION = 3",
+            ]
+        );
+        assert!(steps[1].reason.contains("page density"));
+    }
+
+    #[test]
+    fn test_fragmentation_query_filters() {
+        let query = fragmentation_query(Some("sales"), Some("Orders"), "SAMPLED", 1000);
+        assert!(query.contains("DB_ID(), OBJECT_ID(N'[sales].[Orders]'), NULL, NULL, 'SAMPLED'"));
+        assert!(query.contains("ps.page_count >= 1000"));
+        assert!(query.contains("AND s.name = N'sales'"));
+
+        let query = fragmentation_query(None, None, "LIMITED", 0);
+        assert!(query.contains("DB_ID(), NULL, NULL, NULL, 'LIMITED'"));
+    }
+
+    #[test]
+    fn test_statistics_staleness_and_sql() {
+        use crate::database::types::SqlValue;
+
+        let mut row = ResultRow::new();
+        for (column, value) in [
+            ("schema_name", SqlValue::String("dbo".to_string())),
+            ("table_name", SqlValue::String("Orders".to_string())),
+            ("stats_name", SqlValue::String("IX_Customer".to_string())),
+            ("auto_created", SqlValue::Bool(false)),
+            ("has_filter", SqlValue::Bool(false)),
+            ("rows", SqlValue::I64(1_000_000)),
+            ("rows_sampled", SqlValue::I64(50_000)),
+            ("modification_counter", SqlValue::I64(40_000)),
+        ] {
+            row.insert(column.to_string(), value);
+        }
+
+        // SQRT(1000 * 1,000,000) = 31,623 modifications
+        let stats = StatisticsInfo::from_row(&row, None).unwrap();
+        assert_eq!(stats.auto_update_threshold, 31_623);
+        assert_eq!(stats.modification_percent, 4.0);
+        assert!(stats.stale);
+        assert!(!StatisticsInfo::from_row(&row, Some(10.0)).unwrap().stale);
+
+        let scan = StatisticsScan::from_options(false, Some(25.0)).unwrap();
+        assert_eq!(
+            update_statistics_sql(&stats, scan),
+            "UPDATE STATISTICS [dbo].[Orders] ([IX_Customer]) WITH SAMPLE 25 PERCENT"
+        );
+        assert_eq!(
+            update_statistics_sql(&stats, StatisticsScan::FullScan),
+            "UPDATE STATISTICS [dbo].[Orders] ([IX_Customer]) WITH FULLSCAN"
+        );
+        assert!(StatisticsScan::from_options(true, Some(10.0)).is_err());
+        assert!(StatisticsScan::from_options(false, Some(0.0)).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Capture of server informational messages (PRINT, low-severity RAISERROR).
+//!
+//! The SQL Server driver reports TDS INFO tokens as tracing events rather than
+//! returning them to the caller. [`ServerMessageLayer`] picks those events up
+//! and routes them to the [`capture_messages`] scope that issued the query, so
+//! PRINT output can be returned alongside query results.
+//!
+//! The layer must be installed in the tracing subscriber (the server binary
+//! does this at startup); without it, captured message lists are empty.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Instrument, Metadata, Subscriber};
+use tracing_subscriber::filter::{filter_fn, FilterFn, Filtered};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Name of the span that scopes a message capture.
+const CAPTURE_SPAN: &str = "capture_server_messages";
+
+/// Tracing target prefix of the SQL Server driver.
+const DRIVER_TARGET: &str = "mssql_client";
+
+/// Event message the driver uses when reporting INFO tokens.
+const INFO_EVENT: &str = "server info message";
+
+/// Next capture scope identifier.
+static NEXT_CAPTURE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Messages collected per active capture scope.
+static CAPTURED: Lazy<Mutex<HashMap<u64, Vec<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Run a future, collecting informational messages the server sends meanwhile.
+pub async fn capture_messages<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    let capture_id = NEXT_CAPTURE_ID.fetch_add(1, Ordering::Relaxed);
+    CAPTURED.lock().insert(capture_id, Vec::new());
+
+    let span = tracing::info_span!("capture_server_messages", capture_id);
+    let output = future.instrument(span).await;
+
+    let messages = CAPTURED.lock().remove(&capture_id).unwrap_or_default();
+    (output, messages)
+}
+
+/// Capture scope identifier stored in span extensions.
+struct CaptureId(u64);
+
+/// Tracing layer that routes driver INFO messages to capture scopes.
+pub struct ServerMessageLayer;
+
+/// [`ServerMessageLayer`] with its per-layer callsite filter applied.
+pub type FilteredServerMessageLayer<S> =
+    Filtered<ServerMessageLayer, FilterFn<fn(&Metadata<'_>) -> bool>, S>;
+
+impl ServerMessageLayer {
+    /// Create the layer with a filter that only sees driver events and capture spans.
+    ///
+    /// The filter is independent of the log level filter, so messages are
+    /// captured even when driver logging is disabled.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<S>() -> FilteredServerMessageLayer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let filter: fn(&Metadata<'_>) -> bool = is_relevant;
+        ServerMessageLayer.with_filter(filter_fn(filter))
+    }
+}
+
+/// Check whether a callsite is relevant to message capture.
+fn is_relevant(metadata: &Metadata<'_>) -> bool {
+    metadata.target().starts_with(DRIVER_TARGET) || metadata.name() == CAPTURE_SPAN
+}
+
+impl<S> Layer<S> for ServerMessageLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != CAPTURE_SPAN {
+            return;
+        }
+        let mut visitor = CaptureIdVisitor(None);
+        attrs.record(&mut visitor);
+        if let (Some(capture_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(CaptureId(capture_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !event.metadata().target().starts_with(DRIVER_TARGET) {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let Some(text) = visitor.server_message() else {
+            return;
+        };
+
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        for span in scope {
+            if let Some(CaptureId(capture_id)) = span.extensions().get::<CaptureId>() {
+                if let Some(messages) = CAPTURED.lock().get_mut(capture_id) {
+                    messages.push(text);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Extracts the capture ID from a capture span.
+struct CaptureIdVisitor(Option<u64>);
+
+impl Visit for CaptureIdVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "capture_id" {
+            self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+/// Collects the `message` fields of a driver event.
+///
+/// INFO events carry both the fixed event text and the server's message in
+/// fields named `message`.
+#[derive(Default)]
+struct MessageVisitor {
+    messages: Vec<String>,
+}
+
+impl MessageVisitor {
+    /// Get the server message if this is an INFO event.
+    fn server_message(self) -> Option<String> {
+        if !self.messages.iter().any(|m| m == INFO_EVENT) {
+            return None;
+        }
+        self.messages.into_iter().find(|m| m != INFO_EVENT)
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.messages.push(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.messages.push(format!("{:?}", value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[tokio::test]
+    async fn test_capture_driver_info_messages() {
+        let subscriber = tracing_subscriber::registry().with(ServerMessageLayer::new());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let ((), messages) = capture_messages(async {
+            tracing::info!(
+                target: "mssql_client::client",
+                number = 0,
+                message = %"Rebuilding index IX_Orders",
+                "server info message"
+            );
+            tracing::info!(target: "mssql_client::client", "unrelated event");
+            tracing::info!(target: "other_crate", message = %"ignored", "server info message");
+        })
+        .await;
+
+        assert_eq!(messages, vec!["Rebuilding index IX_Orders".to_string()]);
+    }
+}
@@ -1,9 +1,14 @@
 //! SQL Server metadata queries for schema introspection.
 
+use crate::database::migration::{SchemaColumn, SchemaForeignKey, SchemaTable};
+use crate::database::table_sizes::TableSize;
+use crate::database::tvp::TableTypeColumn;
 use crate::database::types::SqlValue;
-use crate::database::{ConnectionPool, QueryExecutor, QueryResult, ResultRow};
+use crate::database::{ConnectionPool, FailoverRecovery, QueryExecutor, QueryResult, ResultRow};
 use crate::error::ServerError;
+use crate::resilience::CircuitBreaker;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 /// Database metadata.
@@ -27,6 +32,67 @@ pub struct TableInfo {
     pub row_count: Option<i64>,
     pub data_size_kb: Option<i64>,
     pub index_size_kb: Option<i64>,
+    /// `SYSTEM_VERSIONED_TEMPORAL_TABLE` or `HISTORY_TABLE`; `None` for regular tables.
+    pub temporal_type: Option<String>,
+    /// History table (schema.name) of a system-versioned temporal table.
+    pub history_table: Option<String>,
+    pub cdc_enabled: bool,
+    pub change_tracking_enabled: bool,
+    /// Memory-optimized (In-Memory OLTP) table.
+    pub is_memory_optimized: bool,
+    /// `SCHEMA_AND_DATA` or `SCHEMA_ONLY` for memory-optimized tables.
+    pub durability: Option<String>,
+    /// `NODE` or `EDGE` for graph tables.
+    pub graph_type: Option<String>,
+}
+
+/// System versioning and change capture settings of a table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableVersioning {
+    /// `SYSTEM_VERSIONED_TEMPORAL_TABLE` or `HISTORY_TABLE`; `None` for regular tables.
+    pub temporal_type: Option<String>,
+    /// History table (schema.name) of a system-versioned temporal table.
+    pub history_table: Option<String>,
+    /// Temporal table (schema.name) whose history this table holds.
+    pub temporal_table: Option<String>,
+    pub period_start_column: Option<String>,
+    pub period_end_column: Option<String>,
+    /// Period columns are declared HIDDEN and not returned by `SELECT *`.
+    pub period_columns_hidden: bool,
+    pub cdc_enabled: bool,
+    pub change_tracking_enabled: bool,
+    /// Change tracking records which columns changed.
+    pub change_tracking_columns_updated: bool,
+}
+
+impl TableVersioning {
+    /// Whether the table can be queried with `FOR SYSTEM_TIME`.
+    pub fn is_system_versioned(&self) -> bool {
+        self.temporal_type.as_deref() == Some("SYSTEM_VERSIONED_TEMPORAL_TABLE")
+    }
+}
+
+/// A column protected by Always Encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedColumn {
+    pub column_name: String,
+    /// `DETERMINISTIC` (supports equality lookups and joins) or `RANDOMIZED`.
+    pub encryption_type: String,
+    pub algorithm: String,
+    pub column_encryption_key: String,
+    /// Column master key wrapping the column encryption key; the most recent one
+    /// while the key is being rotated.
+    pub column_master_key: String,
+    /// Key store holding the master key, e.g. `AZURE_KEY_VAULT` or `MSSQL_CERTIFICATE_STORE`.
+    pub key_store_provider: String,
+    pub key_path: String,
+}
+
+impl EncryptedColumn {
+    /// Whether equal plaintexts encrypt to equal ciphertexts.
+    pub fn is_deterministic(&self) -> bool {
+        self.encryption_type.eq_ignore_ascii_case("DETERMINISTIC")
+    }
 }
 
 /// Column metadata.
@@ -60,6 +126,8 @@ pub struct ProcedureInfo {
     pub procedure_name: String,
     pub create_date: String,
     pub modify_date: String,
+    /// Natively compiled (In-Memory OLTP) procedure.
+    pub is_natively_compiled: bool,
 }
 
 /// Stored procedure parameter metadata.
@@ -124,6 +192,94 @@ pub struct ServerInfo {
     pub collation: String,
 }
 
+/// Transport security negotiated by the current connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionSecurity {
+    /// Whether the connection is encrypted with TLS after login.
+    pub encrypted: bool,
+    /// Authentication scheme: `SQL`, `NTLM`, `KERBEROS`.
+    pub auth_scheme: String,
+    /// Transport protocol, e.g. `TCP` or `Shared memory`.
+    pub net_transport: String,
+}
+
+/// Server login metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginInfo {
+    pub login_name: String,
+    pub login_type: String,
+    pub is_disabled: bool,
+    pub default_database: Option<String>,
+    pub create_date: String,
+    pub server_roles: Vec<String>,
+}
+
+/// Linked server metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedServerInfo {
+    pub name: String,
+    pub product: String,
+    pub provider: String,
+    pub data_source: Option<String>,
+    pub catalog: Option<String>,
+    /// Queries (four-part names, OPENQUERY) may read through the server.
+    pub data_access: bool,
+    /// Remote procedure calls may be made to the server.
+    pub rpc_out: bool,
+    /// Seconds, 0 for the server-wide default.
+    pub connect_timeout: i64,
+    /// Seconds, 0 for the server-wide default.
+    pub query_timeout: i64,
+    pub modify_date: String,
+    /// Login mappings as `local -> remote`; `(all logins)` is the default mapping
+    /// and `(self)` passes the caller's own credentials.
+    pub login_mappings: Vec<String>,
+}
+
+/// Database role metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleInfo {
+    pub role_name: String,
+    pub role_type: String,
+    pub is_fixed_role: bool,
+    pub owner: Option<String>,
+    pub members: Vec<String>,
+}
+
+/// A permission that applies to a database object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectPermission {
+    /// Principal that holds the permission.
+    pub principal_name: String,
+    pub principal_type: String,
+    pub permission_name: String,
+    /// GRANT, DENY or GRANT_WITH_GRANT_OPTION.
+    pub state: String,
+    /// Where the permission is granted: OBJECT, SCHEMA, DATABASE or FIXED_ROLE.
+    pub scope: String,
+    /// Role the permission is inherited from (`None` if held directly).
+    pub granted_via: Option<String>,
+}
+
+/// A foreign key relationship between two tables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignKeyRelationship {
+    pub constraint_name: String,
+    /// Table holding the foreign key (child).
+    pub parent_schema: String,
+    pub parent_table: String,
+    pub parent_columns: Vec<String>,
+    /// Table referenced by the foreign key.
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    /// NO_ACTION, CASCADE, SET_NULL or SET_DEFAULT.
+    pub on_delete: String,
+    pub on_update: String,
+    pub is_disabled: bool,
+    pub is_not_trusted: bool,
+}
+
 /// Metadata query builder.
 pub struct MetadataQueries {
     executor: QueryExecutor,
@@ -137,6 +293,32 @@ impl MetadataQueries {
         }
     }
 
+    /// Share the circuit breaker guarding connection acquisition.
+    pub fn with_circuit_breaker(self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        Self {
+            executor: self.executor.with_circuit_breaker(circuit_breaker),
+        }
+    }
+
+    /// Run metadata queries of tasks with read intent on the read-only pool.
+    pub fn with_read_pool(self, read_pool: Option<Arc<ConnectionPool>>) -> Self {
+        Self {
+            executor: self.executor.with_read_pool(read_pool),
+        }
+    }
+
+    /// Rebuild the pools and retry metadata queries after failovers.
+    pub fn with_failover_recovery(self, failover: Arc<FailoverRecovery>) -> Self {
+        Self {
+            executor: self.executor.with_failover_recovery(failover),
+        }
+    }
+
+    /// Update the row limit for metadata queries.
+    pub fn set_max_rows(&self, max_rows: usize) {
+        self.executor.set_max_rows(max_rows);
+    }
+
     /// Get server information.
     pub async fn get_server_info(&self) -> Result<ServerInfo, ServerError> {
         // Note: SERVERPROPERTY() returns sql_variant which needs explicit casting.
@@ -171,6 +353,31 @@ impl MetadataQueries {
         })
     }
 
+    /// Get the transport security negotiated by the current connection.
+    ///
+    /// Uses `CONNECTIONPROPERTY`, which needs no `VIEW SERVER STATE` permission.
+    pub async fn get_connection_security(&self) -> Result<ConnectionSecurity, ServerError> {
+        let query = r#"
+            SELECT
+                CAST(CONNECTIONPROPERTY('encrypt_option') AS NVARCHAR(10)) AS encrypt_option,
+                CAST(CONNECTIONPROPERTY('auth_scheme') AS NVARCHAR(40)) AS auth_scheme,
+                CAST(CONNECTIONPROPERTY('net_transport') AS NVARCHAR(40)) AS net_transport
+        "#;
+
+        let result = self.executor.execute(query).await?;
+        let row = result
+            .rows
+            .first()
+            .ok_or_else(|| ServerError::internal("Failed to get connection properties"))?;
+
+        Ok(ConnectionSecurity {
+            encrypted: extract_string(row, "encrypt_option")
+                .is_some_and(|v| v.eq_ignore_ascii_case("TRUE")),
+            auth_scheme: extract_string(row, "auth_scheme").unwrap_or_default(),
+            net_transport: extract_string(row, "net_transport").unwrap_or_default(),
+        })
+    }
+
     /// List all databases on the server.
     pub async fn list_databases(&self) -> Result<Vec<DatabaseInfo>, ServerError> {
         let query = r#"
@@ -232,15 +439,34 @@ impl MetadataQueries {
                 'TABLE' AS table_type,
                 SUM(p.rows) AS row_count,
                 SUM(a.data_pages) * 8 AS data_size_kb,
-                SUM(a.used_pages - a.data_pages) * 8 AS index_size_kb
+                SUM(a.used_pages - a.data_pages) * 8 AS index_size_kb,
+                NULLIF(t.temporal_type_desc, 'NON_TEMPORAL_TABLE') AS temporal_type,
+                OBJECT_SCHEMA_NAME(t.history_table_id) + '.' + OBJECT_NAME(t.history_table_id)
+                    AS history_table,
+                t.is_tracked_by_cdc AS cdc_enabled,
+                CASE WHEN ct.object_id IS NULL THEN 0 ELSE 1 END AS change_tracking_enabled,
+                t.is_memory_optimized,
+                CASE WHEN t.is_memory_optimized = 1 THEN t.durability_desc END AS durability,
+                CASE
+                    WHEN EXISTS (
+                        SELECT 1 FROM sys.columns gc
+                        WHERE gc.object_id = t.object_id AND gc.name LIKE '$node[_]id[_]%'
+                    ) THEN 'NODE'
+                    WHEN EXISTS (
+                        SELECT 1 FROM sys.columns gc
+                        WHERE gc.object_id = t.object_id AND gc.name LIKE '$edge[_]id[_]%'
+                    ) THEN 'EDGE'
+                END AS graph_type
             FROM sys.tables t
             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
             INNER JOIN sys.indexes i ON t.object_id = i.object_id
             INNER JOIN sys.partitions p ON i.object_id = p.object_id AND i.index_id = p.index_id
-            INNER JOIN sys.allocation_units a ON p.partition_id = a.container_id
+            LEFT JOIN sys.allocation_units a ON p.partition_id = a.container_id
+            LEFT JOIN sys.change_tracking_tables ct ON ct.object_id = t.object_id
             WHERE t.is_ms_shipped = 0
             {}
-            GROUP BY s.name, t.name
+            GROUP BY s.name, t.name, t.temporal_type_desc, t.history_table_id, t.is_tracked_by_cdc,
+                ct.object_id, t.is_memory_optimized, t.durability_desc, t.object_id
             ORDER BY s.name, t.name
         "#,
             schema
@@ -260,6 +486,120 @@ impl MetadataQueries {
                 row_count: extract_i64(row, "row_count"),
                 data_size_kb: extract_i64(row, "data_size_kb"),
                 index_size_kb: extract_i64(row, "index_size_kb"),
+                temporal_type: extract_string(row, "temporal_type"),
+                history_table: extract_string(row, "history_table"),
+                cdc_enabled: extract_bool(row, "cdc_enabled").unwrap_or(false),
+                change_tracking_enabled: extract_bool(row, "change_tracking_enabled")
+                    .unwrap_or(false),
+                is_memory_optimized: extract_bool(row, "is_memory_optimized").unwrap_or(false),
+                durability: extract_string(row, "durability"),
+                graph_type: extract_string(row, "graph_type"),
+            })
+            .collect())
+    }
+
+    /// Get the temporal, CDC and change tracking settings of a table.
+    ///
+    /// Returns `None` if the table does not exist.
+    pub async fn get_table_versioning(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Option<TableVersioning>, ServerError> {
+        let query = format!(
+            r#"
+            SELECT
+                NULLIF(t.temporal_type_desc, 'NON_TEMPORAL_TABLE') AS temporal_type,
+                OBJECT_SCHEMA_NAME(t.history_table_id) + '.' + OBJECT_NAME(t.history_table_id)
+                    AS history_table,
+                OBJECT_SCHEMA_NAME(parent.object_id) + '.' + parent.name AS temporal_table,
+                COL_NAME(pr.object_id, pr.start_column_id) AS period_start_column,
+                COL_NAME(pr.object_id, pr.end_column_id) AS period_end_column,
+                ISNULL(sc.is_hidden, 0) AS period_columns_hidden,
+                t.is_tracked_by_cdc AS cdc_enabled,
+                CASE WHEN ct.object_id IS NULL THEN 0 ELSE 1 END AS change_tracking_enabled,
+                ISNULL(ct.is_track_columns_updated_on, 0) AS change_tracking_columns_updated
+            FROM sys.tables t
+            INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+            LEFT JOIN sys.periods pr ON pr.object_id = t.object_id
+            LEFT JOIN sys.columns sc
+                ON sc.object_id = pr.object_id AND sc.column_id = pr.start_column_id
+            LEFT JOIN sys.tables parent ON parent.history_table_id = t.object_id
+            LEFT JOIN sys.change_tracking_tables ct ON ct.object_id = t.object_id
+            WHERE s.name = '{}'
+            AND t.name = '{}'
+        "#,
+            schema.replace('\'', "''"),
+            table.replace('\'', "''")
+        );
+
+        let result = self.executor.execute(&query).await?;
+
+        Ok(result.rows.first().map(|row| TableVersioning {
+            temporal_type: extract_string(row, "temporal_type"),
+            history_table: extract_string(row, "history_table"),
+            temporal_table: extract_string(row, "temporal_table"),
+            period_start_column: extract_string(row, "period_start_column"),
+            period_end_column: extract_string(row, "period_end_column"),
+            period_columns_hidden: extract_bool(row, "period_columns_hidden").unwrap_or(false),
+            cdc_enabled: extract_bool(row, "cdc_enabled").unwrap_or(false),
+            change_tracking_enabled: extract_bool(row, "change_tracking_enabled").unwrap_or(false),
+            change_tracking_columns_updated: extract_bool(row, "change_tracking_columns_updated")
+                .unwrap_or(false),
+        }))
+    }
+
+    /// Get the Always Encrypted columns of a table and their keys.
+    pub async fn get_encrypted_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<EncryptedColumn>, ServerError> {
+        let query = format!(
+            r#"
+            SELECT
+                c.name AS column_name,
+                c.encryption_type_desc AS encryption_type,
+                c.encryption_algorithm_name AS algorithm,
+                cek.name AS column_encryption_key,
+                cmk.name AS column_master_key,
+                cmk.key_store_provider_name AS key_store_provider,
+                cmk.key_path
+            FROM sys.columns c
+            INNER JOIN sys.tables t ON t.object_id = c.object_id
+            INNER JOIN sys.schemas s ON s.schema_id = t.schema_id
+            INNER JOIN sys.column_encryption_keys cek
+                ON cek.column_encryption_key_id = c.column_encryption_key_id
+            OUTER APPLY (
+                SELECT TOP (1) k.name, k.key_store_provider_name, k.key_path
+                FROM sys.column_encryption_key_values v
+                INNER JOIN sys.column_master_keys k
+                    ON k.column_master_key_id = v.column_master_key_id
+                WHERE v.column_encryption_key_id = cek.column_encryption_key_id
+                ORDER BY k.create_date DESC
+            ) cmk
+            WHERE s.name = '{}'
+            AND t.name = '{}'
+            ORDER BY c.column_id
+        "#,
+            schema.replace('\'', "''"),
+            table.replace('\'', "''")
+        );
+
+        let result = self.executor.execute(&query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| EncryptedColumn {
+                column_name: extract_string(row, "column_name").unwrap_or_default(),
+                encryption_type: extract_string(row, "encryption_type").unwrap_or_default(),
+                algorithm: extract_string(row, "algorithm").unwrap_or_default(),
+                column_encryption_key: extract_string(row, "column_encryption_key")
+                    .unwrap_or_default(),
+                column_master_key: extract_string(row, "column_master_key").unwrap_or_default(),
+                key_store_provider: extract_string(row, "key_store_provider").unwrap_or_default(),
+                key_path: extract_string(row, "key_path").unwrap_or_default(),
             })
             .collect())
     }
@@ -312,6 +652,82 @@ impl MetadataQueries {
             .collect())
     }
 
+    /// Get the primary key columns of a table, in key order.
+    pub async fn get_primary_key_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<String>, ServerError> {
+        let query = format!(
+            r#"
+            SELECT kcu.COLUMN_NAME AS column_name
+            FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+            INNER JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu
+                ON tc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME AND tc.TABLE_SCHEMA = kcu.TABLE_SCHEMA
+            WHERE tc.TABLE_SCHEMA = '{}'
+            AND tc.TABLE_NAME = '{}'
+            AND tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+            ORDER BY kcu.ORDINAL_POSITION
+        "#,
+            schema.replace('\'', "''"),
+            table.replace('\'', "''")
+        );
+
+        let result = self.executor.execute(&query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .filter_map(|row| extract_string(row, "column_name"))
+            .collect())
+    }
+
+    /// Get the columns of a user-defined table type, in order.
+    ///
+    /// Returns an empty list if the table type does not exist.
+    pub async fn get_table_type_columns(
+        &self,
+        schema: &str,
+        type_name: &str,
+    ) -> Result<Vec<TableTypeColumn>, ServerError> {
+        let query = format!(
+            r#"
+            SELECT
+                c.name AS column_name,
+                TYPE_NAME(c.system_type_id) AS data_type,
+                c.max_length AS max_length,
+                c.precision AS precision,
+                c.scale AS scale,
+                c.is_nullable AS is_nullable
+            FROM sys.table_types tt
+            INNER JOIN sys.schemas s ON tt.schema_id = s.schema_id
+            INNER JOIN sys.columns c ON c.object_id = tt.type_table_object_id
+            WHERE s.name = '{}'
+            AND tt.name = '{}'
+            ORDER BY c.column_id
+        "#,
+            schema.replace('\'', "''"),
+            type_name.replace('\'', "''")
+        );
+
+        let result = self.executor.execute(&query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| TableTypeColumn {
+                name: extract_string(row, "column_name").unwrap_or_default(),
+                data_type: extract_string(row, "data_type")
+                    .unwrap_or_default()
+                    .to_lowercase(),
+                max_length: extract_i32(row, "max_length").unwrap_or(0),
+                precision: extract_i32(row, "precision").unwrap_or(0),
+                scale: extract_i32(row, "scale").unwrap_or(0),
+                nullable: extract_bool(row, "is_nullable").unwrap_or(true),
+            })
+            .collect())
+    }
+
     /// List views in a schema.
     pub async fn list_views(&self, schema: Option<&str>) -> Result<Vec<ViewInfo>, ServerError> {
         let query = format!(
@@ -358,9 +774,11 @@ impl MetadataQueries {
                 s.name AS schema_name,
                 p.name AS procedure_name,
                 CONVERT(VARCHAR(23), p.create_date, 121) AS create_date,
-                CONVERT(VARCHAR(23), p.modify_date, 121) AS modify_date
+                CONVERT(VARCHAR(23), p.modify_date, 121) AS modify_date,
+                ISNULL(m.uses_native_compilation, 0) AS is_natively_compiled
             FROM sys.procedures p
             INNER JOIN sys.schemas s ON p.schema_id = s.schema_id
+            LEFT JOIN sys.sql_modules m ON m.object_id = p.object_id
             WHERE p.is_ms_shipped = 0
             {}
             ORDER BY s.name, p.name
@@ -380,6 +798,7 @@ impl MetadataQueries {
                 procedure_name: extract_string(row, "procedure_name").unwrap_or_default(),
                 create_date: extract_string(row, "create_date").unwrap_or_default(),
                 modify_date: extract_string(row, "modify_date").unwrap_or_default(),
+                is_natively_compiled: extract_bool(row, "is_natively_compiled").unwrap_or(false),
             })
             .collect())
     }
@@ -607,6 +1026,474 @@ impl MetadataQueries {
             .collect())
     }
 
+    /// List server logins with their server role memberships.
+    ///
+    /// Without VIEW ANY DEFINITION, SQL Server only returns the caller's own
+    /// login and the fixed server roles.
+    pub async fn list_logins(&self) -> Result<Vec<LoginInfo>, ServerError> {
+        let query = r#"
+            SELECT
+                sp.name AS login_name,
+                sp.type_desc AS login_type,
+                sp.is_disabled,
+                sp.default_database_name AS default_database,
+                CONVERT(VARCHAR(23), sp.create_date, 121) AS create_date,
+                STUFF((
+                    SELECT ', ' + r.name
+                    FROM sys.server_role_members rm
+                    INNER JOIN sys.server_principals r ON r.principal_id = rm.role_principal_id
+                    WHERE rm.member_principal_id = sp.principal_id
+                    ORDER BY r.name
+                    FOR XML PATH(''), TYPE
+                ).value('.', 'NVARCHAR(MAX)'), 1, 2, '') AS server_roles
+            FROM sys.server_principals sp
+            WHERE sp.type IN ('S', 'U', 'G', 'E', 'X', 'C', 'K')
+            AND sp.name NOT LIKE '##%'
+            ORDER BY sp.name
+        "#;
+
+        let result = self.executor.execute(query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| LoginInfo {
+                login_name: extract_string(row, "login_name").unwrap_or_default(),
+                login_type: extract_string(row, "login_type").unwrap_or_default(),
+                is_disabled: extract_bool(row, "is_disabled").unwrap_or(false),
+                default_database: extract_string(row, "default_database"),
+                create_date: extract_string(row, "create_date").unwrap_or_default(),
+                server_roles: split_list(extract_string(row, "server_roles")),
+            })
+            .collect())
+    }
+
+    /// List linked servers with their login mappings.
+    pub async fn list_linked_servers(&self) -> Result<Vec<LinkedServerInfo>, ServerError> {
+        let query = r#"
+            SELECT
+                s.name,
+                s.product,
+                s.provider,
+                s.data_source,
+                s.catalog,
+                s.is_data_access_enabled,
+                s.is_rpc_out_enabled,
+                s.connect_timeout,
+                s.query_timeout,
+                CONVERT(VARCHAR(23), s.modify_date, 121) AS modify_date,
+                STUFF((
+                    SELECT ', ' + ISNULL(p.name, '(all logins)') + ' -> '
+                        + CASE WHEN ll.uses_self_credential = 1 THEN '(self)'
+                               ELSE ISNULL(ll.remote_name, '(none)') END
+                    FROM sys.linked_logins ll
+                    LEFT JOIN sys.server_principals p ON p.principal_id = ll.local_principal_id
+                    WHERE ll.server_id = s.server_id
+                    ORDER BY p.name
+                    FOR XML PATH(''), TYPE
+                ).value('.', 'NVARCHAR(MAX)'), 1, 2, '') AS login_mappings
+            FROM sys.servers s
+            WHERE s.is_linked = 1
+            ORDER BY s.name
+        "#;
+
+        let result = self.executor.execute(query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| LinkedServerInfo {
+                name: extract_string(row, "name").unwrap_or_default(),
+                product: extract_string(row, "product").unwrap_or_default(),
+                provider: extract_string(row, "provider").unwrap_or_default(),
+                data_source: extract_string(row, "data_source").filter(|s| !s.is_empty()),
+                catalog: extract_string(row, "catalog").filter(|s| !s.is_empty()),
+                data_access: extract_bool(row, "is_data_access_enabled").unwrap_or(false),
+                rpc_out: extract_bool(row, "is_rpc_out_enabled").unwrap_or(false),
+                connect_timeout: extract_i64(row, "connect_timeout").unwrap_or(0),
+                query_timeout: extract_i64(row, "query_timeout").unwrap_or(0),
+                modify_date: extract_string(row, "modify_date").unwrap_or_default(),
+                login_mappings: split_list(extract_string(row, "login_mappings")),
+            })
+            .collect())
+    }
+
+    /// List database roles with their members.
+    pub async fn list_database_roles(&self) -> Result<Vec<RoleInfo>, ServerError> {
+        let query = r#"
+            SELECT
+                r.name AS role_name,
+                r.type_desc AS role_type,
+                r.is_fixed_role,
+                o.name AS owner,
+                STUFF((
+                    SELECT ', ' + m.name
+                    FROM sys.database_role_members rm
+                    INNER JOIN sys.database_principals m ON m.principal_id = rm.member_principal_id
+                    WHERE rm.role_principal_id = r.principal_id
+                    ORDER BY m.name
+                    FOR XML PATH(''), TYPE
+                ).value('.', 'NVARCHAR(MAX)'), 1, 2, '') AS members
+            FROM sys.database_principals r
+            LEFT JOIN sys.database_principals o ON o.principal_id = r.owning_principal_id
+            WHERE r.type IN ('R', 'A')
+            ORDER BY r.is_fixed_role, r.name
+        "#;
+
+        let result = self.executor.execute(query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| RoleInfo {
+                role_name: extract_string(row, "role_name").unwrap_or_default(),
+                role_type: extract_string(row, "role_type").unwrap_or_default(),
+                is_fixed_role: extract_bool(row, "is_fixed_role").unwrap_or(false),
+                owner: extract_string(row, "owner"),
+                members: split_list(extract_string(row, "members")),
+            })
+            .collect())
+    }
+
+    /// Get the permissions that apply to an object, including inherited ones.
+    ///
+    /// Combines permissions granted on the object, its schema and the database
+    /// with the implied permissions of the fixed data roles, then expands role
+    /// memberships so each row names the principal that ends up holding the
+    /// permission. Members of `sysadmin` and the database owner are not listed.
+    pub async fn get_object_permissions(
+        &self,
+        schema: &str,
+        object: &str,
+    ) -> Result<Vec<ObjectPermission>, ServerError> {
+        let query = format!(
+            r#"
+            WITH perms AS (
+                SELECT dp.grantee_principal_id AS principal_id, dp.permission_name,
+                       dp.state_desc, 'OBJECT' AS scope
+                FROM sys.database_permissions dp
+                WHERE dp.class = 1 AND dp.major_id = OBJECT_ID(N'[{0}].[{1}]')
+                UNION ALL
+                SELECT dp.grantee_principal_id, dp.permission_name, dp.state_desc, 'SCHEMA'
+                FROM sys.database_permissions dp
+                WHERE dp.class = 3 AND dp.major_id = SCHEMA_ID(N'{0}')
+                UNION ALL
+                SELECT dp.grantee_principal_id, dp.permission_name, dp.state_desc, 'DATABASE'
+                FROM sys.database_permissions dp
+                WHERE dp.class = 0
+                AND dp.permission_name IN ('SELECT', 'INSERT', 'UPDATE', 'DELETE', 'EXECUTE',
+                    'REFERENCES', 'ALTER', 'CONTROL', 'TAKE OWNERSHIP', 'VIEW DEFINITION')
+                UNION ALL
+                SELECT DATABASE_PRINCIPAL_ID(f.role_name), f.permission_name, f.state_desc, 'FIXED_ROLE'
+                FROM (VALUES
+                    ('db_owner', 'CONTROL', 'GRANT'),
+                    ('db_datareader', 'SELECT', 'GRANT'),
+                    ('db_datawriter', 'INSERT', 'GRANT'),
+                    ('db_datawriter', 'UPDATE', 'GRANT'),
+                    ('db_datawriter', 'DELETE', 'GRANT'),
+                    ('db_ddladmin', 'ALTER', 'GRANT'),
+                    ('db_denydatareader', 'SELECT', 'DENY'),
+                    ('db_denydatawriter', 'INSERT', 'DENY'),
+                    ('db_denydatawriter', 'UPDATE', 'DENY'),
+                    ('db_denydatawriter', 'DELETE', 'DENY')
+                ) f(role_name, permission_name, state_desc)
+            ),
+            memberships AS (
+                SELECT principal_id AS member_id, principal_id AS via_id, 0 AS depth
+                FROM sys.database_principals
+                UNION ALL
+                SELECT rm.member_principal_id, m.via_id, m.depth + 1
+                FROM sys.database_role_members rm
+                INNER JOIN memberships m ON rm.role_principal_id = m.member_id
+                WHERE m.depth < 10
+            )
+            SELECT DISTINCT
+                p.name AS principal_name,
+                p.type_desc AS principal_type,
+                perms.permission_name,
+                perms.state_desc AS state,
+                perms.scope,
+                CASE WHEN m.depth = 0 THEN NULL ELSE via.name END AS granted_via
+            FROM perms
+            INNER JOIN memberships m ON m.via_id = perms.principal_id
+            INNER JOIN sys.database_principals p ON p.principal_id = m.member_id
+            INNER JOIN sys.database_principals via ON via.principal_id = m.via_id
+            WHERE p.type <> 'R' OR m.depth = 0
+            ORDER BY principal_name, permission_name, scope
+        "#,
+            schema.replace('\'', "''"),
+            object.replace('\'', "''")
+        );
+
+        let result = self.executor.execute(&query).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| ObjectPermission {
+                principal_name: extract_string(row, "principal_name").unwrap_or_default(),
+                principal_type: extract_string(row, "principal_type").unwrap_or_default(),
+                permission_name: extract_string(row, "permission_name").unwrap_or_default(),
+                state: extract_string(row, "state").unwrap_or_default(),
+                scope: extract_string(row, "scope").unwrap_or_default(),
+                granted_via: extract_string(row, "granted_via"),
+            })
+            .collect())
+    }
+
+    /// List user tables with row counts and space usage, largest first.
+    ///
+    /// Sizes are in KB and mirror `sp_spaceused`: data covers the heap or
+    /// clustered index pages, index is the remaining used space and unused is
+    /// space reserved but not yet used.
+    pub async fn list_table_sizes(&self) -> Result<Vec<TableSize>, ServerError> {
+        let query = r#"
+            SELECT
+                s.name AS schema_name,
+                t.name AS table_name,
+                SUM(CASE WHEN ps.index_id IN (0, 1) THEN ps.row_count ELSE 0 END) AS row_count,
+                SUM(ps.reserved_page_count) * 8 AS reserved_kb,
+                SUM(ps.used_page_count) * 8 AS used_kb,
+                SUM(CASE WHEN ps.index_id IN (0, 1)
+                    THEN ps.in_row_data_page_count + ps.lob_used_page_count
+                         + ps.row_overflow_used_page_count
+                    ELSE 0 END) * 8 AS data_kb
+            FROM sys.dm_db_partition_stats ps
+            INNER JOIN sys.tables t ON t.object_id = ps.object_id
+            INNER JOIN sys.schemas s ON s.schema_id = t.schema_id
+            WHERE t.is_ms_shipped = 0
+            GROUP BY s.name, t.name
+            ORDER BY reserved_kb DESC, s.name, t.name
+        "#;
+
+        let result = self.executor.execute_with_limit(query, usize::MAX).await?;
+
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| {
+                let reserved_kb = extract_i64(row, "reserved_kb").unwrap_or(0);
+                let used_kb = extract_i64(row, "used_kb").unwrap_or(0);
+                let data_kb = extract_i64(row, "data_kb").unwrap_or(0);
+                TableSize {
+                    schema_name: extract_string(row, "schema_name").unwrap_or_default(),
+                    table_name: extract_string(row, "table_name").unwrap_or_default(),
+                    row_count: extract_i64(row, "row_count").unwrap_or(0),
+                    reserved_kb,
+                    used_kb,
+                    data_kb,
+                    index_kb: (used_kb - data_kb).max(0),
+                    unused_kb: (reserved_kb - used_kb).max(0),
+                }
+            })
+            .collect())
+    }
+
+    /// List foreign key relationships, optionally only those involving one table.
+    ///
+    /// With a table filter, both the table's own foreign keys and the foreign
+    /// keys of other tables referencing it are returned.
+    pub async fn list_foreign_keys(
+        &self,
+        table: Option<(&str, &str)>,
+    ) -> Result<Vec<ForeignKeyRelationship>, ServerError> {
+        let table_filter = table
+            .map(|(schema, name)| {
+                let object = format!(
+                    "OBJECT_ID(N'[{}].[{}]')",
+                    schema.replace(']', "]]").replace('\'', "''"),
+                    name.replace(']', "]]").replace('\'', "''")
+                );
+                format!(
+                    "WHERE fk.parent_object_id = {0} OR fk.referenced_object_id = {0}",
+                    object
+                )
+            })
+            .unwrap_or_default();
+
+        let query = format!(
+            r#"
+            SELECT
+                fk.name AS constraint_name,
+                SCHEMA_NAME(pt.schema_id) AS parent_schema,
+                pt.name AS parent_table,
+                pc.name AS parent_column,
+                SCHEMA_NAME(rt.schema_id) AS referenced_schema,
+                rt.name AS referenced_table,
+                rc.name AS referenced_column,
+                fk.delete_referential_action_desc AS on_delete,
+                fk.update_referential_action_desc AS on_update,
+                fk.is_disabled,
+                fk.is_not_trusted
+            FROM sys.foreign_keys fk
+            INNER JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+            INNER JOIN sys.tables pt ON pt.object_id = fk.parent_object_id
+            INNER JOIN sys.columns pc
+                ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+            INNER JOIN sys.tables rt ON rt.object_id = fk.referenced_object_id
+            INNER JOIN sys.columns rc
+                ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+            {}
+            ORDER BY parent_schema, parent_table, fk.name, fkc.constraint_column_id
+        "#,
+            table_filter
+        );
+
+        let result = self.executor.execute_with_limit(&query, usize::MAX).await?;
+
+        let mut relationships: Vec<ForeignKeyRelationship> = Vec::new();
+        for row in &result.rows {
+            let constraint_name = extract_string(row, "constraint_name").unwrap_or_default();
+            let parent_schema = extract_string(row, "parent_schema").unwrap_or_default();
+            let parent_column = extract_string(row, "parent_column").unwrap_or_default();
+            let referenced_column = extract_string(row, "referenced_column").unwrap_or_default();
+
+            // Rows are ordered by constraint, so columns of one key are adjacent
+            match relationships.last_mut() {
+                Some(fk)
+                    if fk.constraint_name == constraint_name
+                        && fk.parent_schema == parent_schema =>
+                {
+                    fk.parent_columns.push(parent_column);
+                    fk.referenced_columns.push(referenced_column);
+                }
+                _ => relationships.push(ForeignKeyRelationship {
+                    constraint_name,
+                    parent_schema,
+                    parent_table: extract_string(row, "parent_table").unwrap_or_default(),
+                    parent_columns: vec![parent_column],
+                    referenced_schema: extract_string(row, "referenced_schema").unwrap_or_default(),
+                    referenced_table: extract_string(row, "referenced_table").unwrap_or_default(),
+                    referenced_columns: vec![referenced_column],
+                    on_delete: extract_string(row, "on_delete").unwrap_or_default(),
+                    on_update: extract_string(row, "on_update").unwrap_or_default(),
+                    is_disabled: extract_bool(row, "is_disabled").unwrap_or(false),
+                    is_not_trusted: extract_bool(row, "is_not_trusted").unwrap_or(false),
+                }),
+            }
+        }
+
+        Ok(relationships)
+    }
+
+    /// Load table, column, primary key and foreign key definitions for a schema.
+    ///
+    /// Used for schema diffing and migration script generation.
+    pub async fn load_schema_tables(&self, schema: &str) -> Result<Vec<SchemaTable>, ServerError> {
+        let schema_escaped = schema.replace('\'', "''");
+
+        let columns_query = format!(
+            r#"
+            SELECT
+                c.TABLE_NAME AS table_name,
+                c.COLUMN_NAME AS column_name,
+                c.DATA_TYPE AS data_type,
+                c.CHARACTER_MAXIMUM_LENGTH AS max_length,
+                CAST(c.NUMERIC_PRECISION AS INT) AS precision,
+                CAST(COALESCE(c.NUMERIC_SCALE, c.DATETIME_PRECISION) AS INT) AS scale,
+                CASE WHEN c.IS_NULLABLE = 'YES' THEN 1 ELSE 0 END AS is_nullable
+            FROM INFORMATION_SCHEMA.COLUMNS c
+            INNER JOIN INFORMATION_SCHEMA.TABLES t
+                ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+            WHERE c.TABLE_SCHEMA = '{0}' AND t.TABLE_TYPE = 'BASE TABLE'
+            ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION
+        "#,
+            schema_escaped
+        );
+
+        let pk_query = format!(
+            r#"
+            SELECT
+                tc.TABLE_NAME AS table_name,
+                kcu.COLUMN_NAME AS column_name
+            FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+            INNER JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu
+                ON tc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME AND tc.TABLE_SCHEMA = kcu.TABLE_SCHEMA
+            WHERE tc.TABLE_SCHEMA = '{0}' AND tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+            ORDER BY tc.TABLE_NAME, kcu.ORDINAL_POSITION
+        "#,
+            schema_escaped
+        );
+
+        let fk_query = format!(
+            r#"
+            SELECT
+                fk.name AS fk_name,
+                OBJECT_NAME(fk.parent_object_id) AS table_name,
+                pc.name AS column_name,
+                SCHEMA_NAME(rt.schema_id) AS referenced_schema,
+                rt.name AS referenced_table,
+                rc.name AS referenced_column
+            FROM sys.foreign_keys fk
+            INNER JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+            INNER JOIN sys.columns pc
+                ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+            INNER JOIN sys.tables rt ON rt.object_id = fkc.referenced_object_id
+            INNER JOIN sys.columns rc
+                ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+            WHERE SCHEMA_NAME(fk.schema_id) = '{0}'
+            ORDER BY fk.name, fkc.constraint_column_id
+        "#,
+            schema_escaped
+        );
+
+        let mut tables: BTreeMap<String, SchemaTable> = BTreeMap::new();
+
+        for row in &self.executor.execute(&columns_query).await?.rows {
+            let table_name = extract_string(row, "table_name").unwrap_or_default();
+            tables
+                .entry(table_name.clone())
+                .or_insert_with(|| SchemaTable {
+                    name: table_name,
+                    ..Default::default()
+                })
+                .columns
+                .push(SchemaColumn {
+                    name: extract_string(row, "column_name").unwrap_or_default(),
+                    data_type: extract_string(row, "data_type").unwrap_or_default(),
+                    max_length: extract_i32(row, "max_length"),
+                    precision: extract_i32(row, "precision"),
+                    scale: extract_i32(row, "scale"),
+                    is_nullable: extract_bool(row, "is_nullable").unwrap_or(true),
+                });
+        }
+
+        for row in &self.executor.execute(&pk_query).await?.rows {
+            let table_name = extract_string(row, "table_name").unwrap_or_default();
+            if let Some(table) = tables.get_mut(&table_name) {
+                table
+                    .primary_key
+                    .push(extract_string(row, "column_name").unwrap_or_default());
+            }
+        }
+
+        for row in &self.executor.execute(&fk_query).await?.rows {
+            let table_name = extract_string(row, "table_name").unwrap_or_default();
+            let Some(table) = tables.get_mut(&table_name) else {
+                continue;
+            };
+            let fk_name = extract_string(row, "fk_name").unwrap_or_default();
+            let column = extract_string(row, "column_name").unwrap_or_default();
+            let referenced_column = extract_string(row, "referenced_column").unwrap_or_default();
+
+            match table.foreign_keys.iter_mut().find(|fk| fk.name == fk_name) {
+                Some(fk) => {
+                    fk.columns.push(column);
+                    fk.referenced_columns.push(referenced_column);
+                }
+                None => table.foreign_keys.push(SchemaForeignKey {
+                    name: fk_name,
+                    columns: vec![column],
+                    referenced_schema: extract_string(row, "referenced_schema").unwrap_or_default(),
+                    referenced_table: extract_string(row, "referenced_table").unwrap_or_default(),
+                    referenced_columns: vec![referenced_column],
+                }),
+            }
+        }
+
+        Ok(tables.into_values().collect())
+    }
+
     /// Execute a raw query (passthrough for QueryExecutor).
     pub async fn execute_query(&self, query: &str) -> Result<QueryResult, ServerError> {
         self.executor.execute(query).await
@@ -614,6 +1501,17 @@ impl MetadataQueries {
 }
 
 // Helper functions to extract values from result rows
+fn split_list(value: Option<String>) -> Vec<String> {
+    value
+        .map(|v| {
+            v.split(", ")
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 fn extract_string(row: &ResultRow, column: &str) -> Option<String> {
     match row.get(column)? {
         SqlValue::String(s) => Some(s.clone()),
@@ -0,0 +1,609 @@
+//! Schema migration script generation.
+//!
+//! Builds an ordered list of DDL statements that bring a target schema in
+//! line with a source schema. The plan is computed purely from metadata so
+//! it can be previewed before anything is executed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Column definition used for schema diffing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaColumn {
+    pub name: String,
+    pub data_type: String,
+    pub max_length: Option<i32>,
+    pub precision: Option<i32>,
+    pub scale: Option<i32>,
+    pub is_nullable: bool,
+}
+
+impl SchemaColumn {
+    /// Render the column type as it appears in DDL (e.g. `nvarchar(50)`).
+    pub fn type_definition(&self) -> String {
+        let data_type = self.data_type.to_lowercase();
+        match data_type.as_str() {
+            "char" | "varchar" | "nchar" | "nvarchar" | "binary" | "varbinary" => {
+                match self.max_length {
+                    Some(-1) => format!("{}(max)", data_type),
+                    Some(len) => format!("{}({})", data_type, len),
+                    None => data_type,
+                }
+            }
+            "decimal" | "numeric" => match (self.precision, self.scale) {
+                (Some(p), Some(s)) => format!("{}({}, {})", data_type, p, s),
+                (Some(p), None) => format!("{}({})", data_type, p),
+                _ => data_type,
+            },
+            "datetime2" | "datetimeoffset" | "time" => match self.scale {
+                Some(s) => format!("{}({})", data_type, s),
+                None => data_type,
+            },
+            _ => data_type,
+        }
+    }
+
+    /// Render the full column definition (`[name] type NULL|NOT NULL`).
+    pub fn definition(&self) -> String {
+        format!(
+            "[{}] {} {}",
+            self.name,
+            self.type_definition(),
+            if self.is_nullable { "NULL" } else { "NOT NULL" }
+        )
+    }
+}
+
+/// Foreign key definition used for schema diffing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+/// Table definition used for schema diffing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaTable {
+    pub name: String,
+    pub columns: Vec<SchemaColumn>,
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<SchemaForeignKey>,
+}
+
+impl SchemaTable {
+    fn column(&self, name: &str) -> Option<&SchemaColumn> {
+        self.columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Kind of change performed by a migration step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationAction {
+    CreateTable,
+    AddColumn,
+    AlterColumn,
+    AddForeignKey,
+    DropForeignKey,
+    DropColumn,
+    DropTable,
+}
+
+impl MigrationAction {
+    /// Whether this action can lose data when applied.
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            MigrationAction::AlterColumn | MigrationAction::DropColumn | MigrationAction::DropTable
+        )
+    }
+}
+
+/// A single statement in a migration plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationStep {
+    pub action: MigrationAction,
+    pub object: String,
+    pub sql: String,
+    pub destructive: bool,
+}
+
+/// An ordered set of migration statements with any warnings raised while planning.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    pub steps: Vec<MigrationStep>,
+    pub warnings: Vec<String>,
+}
+
+impl MigrationPlan {
+    /// Render the plan as a single T-SQL script.
+    pub fn to_script(&self) -> String {
+        self.steps
+            .iter()
+            .map(|s| format!("{};", s.sql))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Number of steps that may lose data.
+    pub fn destructive_count(&self) -> usize {
+        self.steps.iter().filter(|s| s.destructive).count()
+    }
+
+    fn push(&mut self, action: MigrationAction, object: String, sql: String) {
+        self.steps.push(MigrationStep {
+            destructive: action.is_destructive(),
+            action,
+            object,
+            sql,
+        });
+    }
+}
+
+/// Build a plan that brings `target_schema` in line with `source_schema`.
+///
+/// Statements are ordered so that tables are created before the tables that
+/// reference them, foreign keys are added once every referenced table exists,
+/// and drops (only emitted when `include_drops` is set) remove referencing
+/// constraints before the tables they point at.
+pub fn plan_migration(
+    source_schema: &str,
+    source: &[SchemaTable],
+    target_schema: &str,
+    target: &[SchemaTable],
+    include_drops: bool,
+) -> MigrationPlan {
+    let mut plan = MigrationPlan::default();
+
+    let source_by_name: BTreeMap<String, &SchemaTable> =
+        source.iter().map(|t| (t.name.to_lowercase(), t)).collect();
+    let target_by_name: BTreeMap<String, &SchemaTable> =
+        target.iter().map(|t| (t.name.to_lowercase(), t)).collect();
+
+    let qualify = |table: &str| format!("[{}].[{}]", target_schema, table);
+
+    // 1. Create tables missing from the target, referenced tables first
+    let new_tables: Vec<&SchemaTable> = source
+        .iter()
+        .filter(|t| !target_by_name.contains_key(&t.name.to_lowercase()))
+        .collect();
+    let (ordered_new, cyclic) = order_by_dependencies(&new_tables, source_schema);
+    if !cyclic.is_empty() {
+        plan.warnings.push(format!(
+            "Circular foreign key dependencies between: {}",
+            cyclic.join(", ")
+        ));
+    }
+
+    for table in &ordered_new {
+        let mut lines: Vec<String> = table.columns.iter().map(|c| c.definition()).collect();
+        if !table.primary_key.is_empty() {
+            lines.push(format!(
+                "CONSTRAINT [PK_{}] PRIMARY KEY ({})",
+                table.name,
+                bracket_list(&table.primary_key)
+            ));
+        }
+        plan.push(
+            MigrationAction::CreateTable,
+            qualify(&table.name),
+            format!(
+                "CREATE TABLE {} (\n    {}\n)",
+                qualify(&table.name),
+                lines.join(",\n    ")
+            ),
+        );
+    }
+
+    // 2. Add and alter columns on tables present in both schemas
+    for (key, source_table) in &source_by_name {
+        let Some(target_table) = target_by_name.get(key) else {
+            continue;
+        };
+        let object = qualify(&target_table.name);
+
+        for column in &source_table.columns {
+            match target_table.column(&column.name) {
+                None => {
+                    if !column.is_nullable {
+                        plan.warnings.push(format!(
+                            "Adding NOT NULL column {}.[{}] will fail if the table has rows; add a default or backfill first",
+                            object, column.name
+                        ));
+                    }
+                    plan.push(
+                        MigrationAction::AddColumn,
+                        format!("{}.[{}]", object, column.name),
+                        format!("ALTER TABLE {} ADD {}", object, column.definition()),
+                    );
+                }
+                Some(existing)
+                    if existing.type_definition() != column.type_definition()
+                        || existing.is_nullable != column.is_nullable =>
+                {
+                    plan.push(
+                        MigrationAction::AlterColumn,
+                        format!("{}.[{}]", object, column.name),
+                        format!(
+                            "ALTER TABLE {} ALTER COLUMN {}",
+                            object,
+                            column.definition()
+                        ),
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    // 3. Add foreign keys once every referenced table exists
+    for table in &ordered_new {
+        for fk in &table.foreign_keys {
+            plan.push(
+                MigrationAction::AddForeignKey,
+                format!("{}.[{}]", qualify(&table.name), fk.name),
+                add_foreign_key_sql(&qualify(&table.name), fk, source_schema, target_schema),
+            );
+        }
+    }
+    for (key, source_table) in &source_by_name {
+        let Some(target_table) = target_by_name.get(key) else {
+            continue;
+        };
+        for fk in &source_table.foreign_keys {
+            let mapped = remap_schema(&fk.referenced_schema, source_schema, target_schema);
+            let exists = target_table.foreign_keys.iter().any(|t| {
+                t.columns == fk.columns
+                    && t.referenced_schema.eq_ignore_ascii_case(&mapped)
+                    && t.referenced_table
+                        .eq_ignore_ascii_case(&fk.referenced_table)
+                    && t.referenced_columns == fk.referenced_columns
+            });
+            if !exists {
+                plan.push(
+                    MigrationAction::AddForeignKey,
+                    format!("{}.[{}]", qualify(&target_table.name), fk.name),
+                    add_foreign_key_sql(
+                        &qualify(&target_table.name),
+                        fk,
+                        source_schema,
+                        target_schema,
+                    ),
+                );
+            }
+        }
+    }
+
+    // 4. Drops (opt-in): columns, then constraints, then tables in reverse dependency order
+    let dropped_tables: Vec<&SchemaTable> = target
+        .iter()
+        .filter(|t| !source_by_name.contains_key(&t.name.to_lowercase()))
+        .collect();
+
+    if include_drops {
+        for (key, target_table) in &target_by_name {
+            let Some(source_table) = source_by_name.get(key) else {
+                continue;
+            };
+            for column in &target_table.columns {
+                if source_table.column(&column.name).is_none() {
+                    plan.push(
+                        MigrationAction::DropColumn,
+                        format!("{}.[{}]", qualify(&target_table.name), column.name),
+                        format!(
+                            "ALTER TABLE {} DROP COLUMN [{}]",
+                            qualify(&target_table.name),
+                            column.name
+                        ),
+                    );
+                }
+            }
+        }
+
+        for table in &dropped_tables {
+            for fk in &table.foreign_keys {
+                plan.push(
+                    MigrationAction::DropForeignKey,
+                    format!("{}.[{}]", qualify(&table.name), fk.name),
+                    format!(
+                        "ALTER TABLE {} DROP CONSTRAINT [{}]",
+                        qualify(&table.name),
+                        fk.name
+                    ),
+                );
+            }
+        }
+
+        let (ordered_drops, _) = order_by_dependencies(&dropped_tables, target_schema);
+        for table in ordered_drops.iter().rev() {
+            plan.push(
+                MigrationAction::DropTable,
+                qualify(&table.name),
+                format!("DROP TABLE {}", qualify(&table.name)),
+            );
+        }
+
+        let dropped: BTreeSet<String> = dropped_tables
+            .iter()
+            .map(|t| t.name.to_lowercase())
+            .collect();
+        for table in target
+            .iter()
+            .filter(|t| !dropped.contains(&t.name.to_lowercase()))
+        {
+            for fk in &table.foreign_keys {
+                if fk.referenced_schema.eq_ignore_ascii_case(target_schema)
+                    && dropped.contains(&fk.referenced_table.to_lowercase())
+                {
+                    plan.warnings.push(format!(
+                        "Foreign key [{}] on {} references dropped table [{}]; drop it before applying",
+                        fk.name,
+                        qualify(&table.name),
+                        fk.referenced_table
+                    ));
+                }
+            }
+        }
+    } else if !dropped_tables.is_empty() {
+        plan.warnings.push(format!(
+            "{} table(s) exist only in the target schema and were left in place (set include_drops to remove them)",
+            dropped_tables.len()
+        ));
+    }
+
+    plan
+}
+
+/// Order tables so that referenced tables come before referencing ones.
+///
+/// Only foreign keys pointing at other tables in `tables` (within `schema`)
+/// are considered. Tables involved in a cycle are appended in name order and
+/// their names are returned as the second element.
+fn order_by_dependencies<'a>(
+    tables: &[&'a SchemaTable],
+    schema: &str,
+) -> (Vec<&'a SchemaTable>, Vec<String>) {
+    let by_name: HashMap<String, &'a SchemaTable> =
+        tables.iter().map(|t| (t.name.to_lowercase(), *t)).collect();
+
+    let mut pending: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for table in tables {
+        let deps = table
+            .foreign_keys
+            .iter()
+            .filter(|fk| fk.referenced_schema.eq_ignore_ascii_case(schema))
+            .map(|fk| fk.referenced_table.to_lowercase())
+            .filter(|dep| *dep != table.name.to_lowercase() && by_name.contains_key(dep))
+            .collect();
+        pending.insert(table.name.to_lowercase(), deps);
+    }
+
+    let mut ordered = Vec::with_capacity(tables.len());
+    loop {
+        let ready: Vec<String> = pending
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for name in ready {
+            pending.remove(&name);
+            for deps in pending.values_mut() {
+                deps.remove(&name);
+            }
+            ordered.push(by_name[&name]);
+        }
+    }
+
+    let cyclic: Vec<String> = pending.keys().map(|k| by_name[k].name.clone()).collect();
+    ordered.extend(pending.keys().map(|k| by_name[k]));
+    (ordered, cyclic)
+}
+
+fn add_foreign_key_sql(
+    table: &str,
+    fk: &SchemaForeignKey,
+    source_schema: &str,
+    target_schema: &str,
+) -> String {
+    format!(
+        "ALTER TABLE {} ADD CONSTRAINT [{}] FOREIGN KEY ({}) REFERENCES [{}].[{}] ({})",
+        table,
+        fk.name,
+        bracket_list(&fk.columns),
+        remap_schema(&fk.referenced_schema, source_schema, target_schema),
+        fk.referenced_table,
+        bracket_list(&fk.referenced_columns)
+    )
+}
+
+/// References into the source schema are redirected to the target schema.
+fn remap_schema(schema: &str, source_schema: &str, target_schema: &str) -> String {
+    if schema.eq_ignore_ascii_case(source_schema) {
+        target_schema.to_string()
+    } else {
+        schema.to_string()
+    }
+}
+
+fn bracket_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|n| format!("[{}]", n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(
+        name: &str,
+        data_type: &str,
+        max_length: Option<i32>,
+        nullable: bool,
+    ) -> SchemaColumn {
+        SchemaColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            max_length,
+            precision: None,
+            scale: None,
+            is_nullable: nullable,
+        }
+    }
+
+    fn table(name: &str, columns: Vec<SchemaColumn>) -> SchemaTable {
+        SchemaTable {
+            name: name.to_string(),
+            columns,
+            primary_key: vec!["Id".to_string()],
+            foreign_keys: Vec::new(),
+        }
+    }
+
+    fn fk(name: &str, column: &str, schema: &str, referenced: &str) -> SchemaForeignKey {
+        SchemaForeignKey {
+            name: name.to_string(),
+            columns: vec![column.to_string()],
+            referenced_schema: schema.to_string(),
+            referenced_table: referenced.to_string(),
+            referenced_columns: vec!["Id".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_type_definition() {
+        assert_eq!(
+            column("a", "nvarchar", Some(50), true).type_definition(),
+            "nvarchar(50)"
+        );
+        assert_eq!(
+            column("a", "varbinary", Some(-1), true).type_definition(),
+            "varbinary(max)"
+        );
+        let mut dec = column("a", "decimal", None, false);
+        dec.precision = Some(18);
+        dec.scale = Some(2);
+        assert_eq!(dec.definition(), "[a] decimal(18, 2) NOT NULL");
+    }
+
+    #[test]
+    fn test_create_tables_in_dependency_order() {
+        let mut orders = table("Orders", vec![column("Id", "int", None, false)]);
+        orders
+            .foreign_keys
+            .push(fk("FK_Orders_Customers", "CustomerId", "dev", "Customers"));
+        let customers = table("Customers", vec![column("Id", "int", None, false)]);
+
+        let plan = plan_migration("dev", &[orders, customers], "prod", &[], false);
+        let creates: Vec<&str> = plan
+            .steps
+            .iter()
+            .filter(|s| s.action == MigrationAction::CreateTable)
+            .map(|s| s.object.as_str())
+            .collect();
+        assert_eq!(creates, vec!["[prod].[Customers]", "[prod].[Orders]"]);
+
+        let fk_step = plan
+            .steps
+            .iter()
+            .find(|s| s.action == MigrationAction::AddForeignKey)
+            .unwrap();
+        assert!(fk_step.sql.contains("REFERENCES [prod].[Customers]"));
+        assert_eq!(
+            plan.steps.last().unwrap().action,
+            MigrationAction::AddForeignKey
+        );
+    }
+
+    #[test]
+    fn test_column_changes() {
+        let source = table(
+            "Users",
+            vec![
+                column("Id", "int", None, false),
+                column("Name", "nvarchar", Some(200), false),
+                column("Email", "nvarchar", Some(100), true),
+            ],
+        );
+        let target = table(
+            "Users",
+            vec![
+                column("Id", "int", None, false),
+                column("Name", "nvarchar", Some(100), false),
+                column("Legacy", "int", None, true),
+            ],
+        );
+
+        let plan = plan_migration(
+            "dev",
+            std::slice::from_ref(&source),
+            "prod",
+            std::slice::from_ref(&target),
+            false,
+        );
+        let actions: Vec<MigrationAction> = plan.steps.iter().map(|s| s.action).collect();
+        assert_eq!(
+            actions,
+            vec![MigrationAction::AlterColumn, MigrationAction::AddColumn]
+        );
+        assert_eq!(plan.destructive_count(), 1);
+
+        let plan = plan_migration("dev", &[source], "prod", &[target], true);
+        assert!(plan
+            .steps
+            .iter()
+            .any(|s| s.sql == "ALTER TABLE [prod].[Users] DROP COLUMN [Legacy]"));
+    }
+
+    #[test]
+    fn test_drops_are_opt_in_and_ordered() {
+        let parent = table("Parent", vec![column("Id", "int", None, false)]);
+        let mut child = table("Child", vec![column("Id", "int", None, false)]);
+        child
+            .foreign_keys
+            .push(fk("FK_Child_Parent", "ParentId", "prod", "Parent"));
+
+        let plan = plan_migration("dev", &[], "prod", &[parent.clone(), child.clone()], false);
+        assert!(plan.steps.is_empty());
+        assert_eq!(plan.warnings.len(), 1);
+
+        let plan = plan_migration("dev", &[], "prod", &[parent, child], true);
+        let order: Vec<&str> = plan.steps.iter().map(|s| s.sql.as_str()).collect();
+        assert_eq!(
+            order,
+            vec![
+                "ALTER TABLE [prod].[Child] DROP CONSTRAINT [FK_Child_Parent]",
+                "DROP TABLE [prod].[Child]",
+                "DROP TABLE [prod].[Parent]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_circular_dependencies_reported() {
+        let mut a = table("A", vec![column("Id", "int", None, false)]);
+        a.foreign_keys.push(fk("FK_A_B", "BId", "dev", "B"));
+        let mut b = table("B", vec![column("Id", "int", None, false)]);
+        b.foreign_keys.push(fk("FK_B_A", "AId", "dev", "A"));
+
+        let plan = plan_migration("dev", &[a, b], "prod", &[], false);
+        assert_eq!(
+            plan.steps
+                .iter()
+                .filter(|s| s.action == MigrationAction::CreateTable)
+                .count(),
+            2
+        );
+        assert!(plan.warnings[0].contains("Circular"));
+    }
+}
@@ -0,0 +1,447 @@
+//! Cursors and query rewriting for `execute_paginated`.
+//!
+//! Offset pagination appends `OFFSET ... FETCH` to the query. Keyset
+//! pagination instead seeks past the ORDER BY values of the last row of the
+//! previous page, so deep pages cost the same as the first and rows inserted
+//! before the current position do not shift later pages. It needs an ORDER BY
+//! on plain columns that appear in the select list and together identify a
+//! row; before a keyset cursor is issued, [`tie_query`] checks that no other
+//! row shares the last row's values, since the seek would skip such rows.
+//!
+//! Cursors are opaque tokens signed with a key generated at startup and bound
+//! to a hash of the query: a modified cursor, a cursor of another query or one
+//! issued before a restart is rejected.
+
+use crate::database::types::{hex, SqlValue};
+use crate::security::governor::tokens;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Key signing cursors, random per process.
+static CURSOR_KEY: Lazy<[u8; 32]> = Lazy::new(|| {
+    let mut key = [0u8; 32];
+    key[..16].copy_from_slice(Uuid::new_v4().as_bytes());
+    key[16..].copy_from_slice(Uuid::new_v4().as_bytes());
+    key
+});
+
+/// Length of the truncated HMAC appended to a cursor.
+const MAC_LEN: usize = 16;
+
+// =============================================================================
+// Cursors
+// =============================================================================
+
+/// Pagination strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaginationMode {
+    /// `OFFSET ... FETCH` (default); supports jumping to a page number.
+    #[default]
+    Offset,
+    /// Seek past the ORDER BY values of the previous page; forward only.
+    Keyset,
+}
+
+impl PaginationMode {
+    /// Get the mode name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Offset => "offset",
+            Self::Keyset => "keyset",
+        }
+    }
+
+    /// Generate JSON Schema for this type.
+    pub fn tool_input_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "string",
+            "enum": ["offset", "keyset"],
+            "description": "Pagination strategy: 'offset' (OFFSET/FETCH, supports page numbers) or 'keyset' (seeks past the ORDER BY values of the previous page; stable and fast for deep pages, requires ORDER BY on plain columns in the select list that identify a row, e.g. ending with the primary key)"
+        })
+    }
+}
+
+/// Position of a page, carried in a cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// 1-based page number.
+    #[serde(rename = "p")]
+    pub page: usize,
+    /// Rows before the page.
+    #[serde(rename = "o")]
+    pub offset: usize,
+    /// SQL literals of the ORDER BY values of the previous page's last row.
+    #[serde(rename = "k", default, skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    #[serde(rename = "q")]
+    query_hash: String,
+    #[serde(flatten)]
+    cursor: PageCursor,
+}
+
+/// Encode a signed cursor for a page of `query`.
+pub fn encode_cursor(query: &str, cursor: &PageCursor) -> String {
+    let payload = CursorPayload {
+        query_hash: query_hash(query),
+        cursor: cursor.clone(),
+    };
+    let json = serde_json::to_vec(&payload).unwrap_or_default();
+    format!("{}{}", hex::encode(&json), hex::encode(&sign(&json)))
+}
+
+/// Decode a cursor, checking its signature and that it belongs to `query`.
+pub fn decode_cursor(token: &str, query: &str) -> Result<PageCursor, String> {
+    let bytes = decode_hex(token).ok_or("Invalid cursor format")?;
+    if bytes.len() <= MAC_LEN {
+        return Err("Invalid cursor format".to_string());
+    }
+    let (json, mac) = bytes.split_at(bytes.len() - MAC_LEN);
+    if cursor_mac(json).verify_truncated_left(mac).is_err() {
+        return Err("Cursor signature is invalid (cursors expire when the server restarts)".into());
+    }
+
+    let payload: CursorPayload =
+        serde_json::from_slice(json).map_err(|_| "Invalid cursor contents")?;
+    if payload.query_hash != query_hash(query) {
+        return Err("Cursor belongs to a different query".to_string());
+    }
+    Ok(payload.cursor)
+}
+
+/// Short hash identifying a query.
+fn query_hash(query: &str) -> String {
+    hex::encode(&Sha256::digest(query.trim().as_bytes())[..8])
+}
+
+/// HMAC-SHA256 of a payload under the cursor key.
+fn cursor_mac(payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&*CURSOR_KEY).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac
+}
+
+/// HMAC-SHA256 of a payload, truncated to [`MAC_LEN`] bytes.
+fn sign(payload: &[u8]) -> [u8; MAC_LEN] {
+    let mut mac = [0u8; MAC_LEN];
+    mac.copy_from_slice(&cursor_mac(payload).finalize().into_bytes()[..MAC_LEN]);
+    mac
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+// =============================================================================
+// Queries
+// =============================================================================
+
+/// A column of a keyset ORDER BY.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyColumn {
+    /// Unquoted column name.
+    pub name: String,
+    pub descending: bool,
+}
+
+/// Split a query at its final top-level ORDER BY.
+///
+/// Returns the query without the clause and the ORDER BY list; `None` if the
+/// query has no top-level ORDER BY.
+pub fn split_order_by(query: &str) -> Option<(&str, &str)> {
+    let tokens = tokens(query);
+    let order = tokens.windows(2).rposition(|pair| {
+        pair[0].depth == 0 && pair[0].is_word("ORDER") && pair[1].is_word("BY")
+    })?;
+    let end = match tokens.last() {
+        Some(last) if last.text == ";" && last.depth == 0 => last.start,
+        _ => query.len(),
+    };
+    let list = query[tokens[order + 1].end()..end].trim();
+    if list.is_empty() {
+        return None;
+    }
+    Some((query[..tokens[order].start].trim_end(), list))
+}
+
+/// Parse an ORDER BY list of plain columns for keyset pagination.
+pub fn key_columns(order_by: &str) -> Result<Vec<KeyColumn>, String> {
+    let tokens = tokens(order_by);
+    let mut columns = Vec::new();
+    for item in tokens.split(|t| t.depth == 0 && t.text == ",") {
+        let (path, descending) = match item.split_last() {
+            Some((last, rest)) if last.is_word("DESC") => (rest, true),
+            Some((last, rest)) if last.is_word("ASC") => (rest, false),
+            _ => (item, false),
+        };
+        let plain = !path.is_empty()
+            && path.len() % 2 == 1
+            && path.iter().enumerate().all(|(i, t)| {
+                let starts = t.text.chars().next().unwrap_or('.');
+                if i % 2 == 1 {
+                    t.text == "."
+                } else {
+                    starts == '[' || starts == '"' || starts.is_alphabetic() || starts == '_'
+                }
+            });
+        if !plain {
+            let text = match (item.first(), item.last()) {
+                (Some(first), Some(last)) => &order_by[first.start..last.end()],
+                _ => "",
+            };
+            return Err(format!(
+                "Keyset pagination needs ORDER BY on plain columns; '{}' is an expression (use mode=offset)",
+                text
+            ));
+        }
+        columns.push(KeyColumn {
+            name: unquote(path[path.len() - 1].text),
+            descending,
+        });
+    }
+    Ok(columns)
+}
+
+fn unquote(identifier: &str) -> String {
+    if let Some(inner) = identifier
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+    {
+        return inner.replace("]]", "]");
+    }
+    if let Some(inner) = identifier
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+    {
+        return inner.replace("\"\"", "\"");
+    }
+    identifier.to_string()
+}
+
+fn quote(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Query for a page by offset.
+pub fn offset_query(body: &str, order_by: &str, offset: usize, fetch: usize) -> String {
+    format!(
+        "{}\nORDER BY {}\nOFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+        body, order_by, offset, fetch
+    )
+}
+
+/// Query for the page after the row with the given key values.
+pub fn keyset_query(body: &str, columns: &[KeyColumn], keys: &[String], fetch: usize) -> String {
+    let mut seek = Vec::with_capacity(columns.len());
+    for (i, column) in columns.iter().enumerate() {
+        let mut terms: Vec<String> = columns[..i]
+            .iter()
+            .zip(keys)
+            .map(|(c, key)| format!("{} = {}", quote(&c.name), key))
+            .collect();
+        let op = if column.descending { "<" } else { ">" };
+        terms.push(format!("{} {} {}", quote(&column.name), op, keys[i]));
+        seek.push(format!("({})", terms.join(" AND ")));
+    }
+    let order: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            format!(
+                "{} {}",
+                quote(&c.name),
+                if c.descending { "DESC" } else { "ASC" }
+            )
+        })
+        .collect();
+    format!(
+        "SELECT * FROM (\n{}\n) AS [page]\nWHERE {}\nORDER BY {}\nOFFSET 0 ROWS FETCH NEXT {} ROWS ONLY",
+        body,
+        seek.join(" OR "),
+        order.join(", "),
+        fetch
+    )
+}
+
+/// Query counting up to two rows of `body` with the given key values, to tell
+/// whether the key columns identify the row.
+pub fn tie_query(body: &str, columns: &[KeyColumn], keys: &[String]) -> String {
+    let terms: Vec<String> = columns
+        .iter()
+        .zip(keys)
+        .map(|(c, key)| format!("{} = {}", quote(&c.name), key))
+        .collect();
+    format!(
+        "SELECT COUNT_BIG(*) AS tied_rows FROM (SELECT TOP (2) 1 AS [row] FROM (\n{}\n) AS [page]\nWHERE {}) AS [tied]",
+        body,
+        terms.join(" AND ")
+    )
+}
+
+/// Query counting the rows of a query without its ORDER BY.
+pub fn count_query(body: &str) -> String {
+    format!(
+        "SELECT COUNT_BIG(*) AS total_rows FROM (\n{}\n) AS [counted]",
+        body
+    )
+}
+
+/// SQL literal of a key value, or `None` if it cannot be used as a seek key.
+pub fn key_literal(value: &SqlValue) -> Option<String> {
+    let literal = match value {
+        SqlValue::Null | SqlValue::Udt(_) => return None,
+        SqlValue::Bool(v) => (if *v { "1" } else { "0" }).to_string(),
+        SqlValue::I8(v) => v.to_string(),
+        SqlValue::I16(v) => v.to_string(),
+        SqlValue::I32(v) => v.to_string(),
+        SqlValue::I64(v) => v.to_string(),
+        SqlValue::F32(v) if v.is_finite() => v.to_string(),
+        SqlValue::F64(v) if v.is_finite() => v.to_string(),
+        SqlValue::F32(_) | SqlValue::F64(_) => return None,
+        SqlValue::Decimal(v) => v.to_string(),
+        SqlValue::String(v) => format!("N'{}'", v.replace('\'', "''")),
+        SqlValue::Bytes(v) => format!("0x{}", hex::encode(v)),
+        SqlValue::Uuid(v) => format!("'{}'", v),
+        SqlValue::Date(v) => format!("CAST('{}' AS date)", v.format("%Y-%m-%d")),
+        SqlValue::Time(v) => format!("CAST('{}' AS time(7))", v.format("%H:%M:%S%.f")),
+        SqlValue::DateTime(v) => format!(
+            "CAST('{}.{:07}' AS datetime2(7))",
+            v.format("%Y-%m-%dT%H:%M:%S"),
+            v.and_utc().timestamp_subsec_nanos() / 100
+        ),
+        SqlValue::DateTimeUtc(v) => format!(
+            "CAST('{}.{:07}+00:00' AS datetimeoffset(7))",
+            v.format("%Y-%m-%dT%H:%M:%S"),
+            v.timestamp_subsec_nanos() / 100
+        ),
+    };
+    Some(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip_and_tampering() {
+        let query = "SELECT * FROM Orders ORDER BY Id";
+        let cursor = PageCursor {
+            page: 3,
+            offset: 200,
+            keys: Some(vec!["42".to_string()]),
+        };
+        let token = encode_cursor(query, &cursor);
+        assert!(!token.contains("cursor"));
+        assert_eq!(decode_cursor(&token, query).unwrap(), cursor);
+        // Surrounding whitespace does not change the query
+        assert!(decode_cursor(&token, &format!("  {}\n", query)).is_ok());
+
+        assert_eq!(
+            decode_cursor(&token, "SELECT * FROM Customers ORDER BY Id").unwrap_err(),
+            "Cursor belongs to a different query"
+        );
+        let mut tampered = token.clone().into_bytes();
+        tampered[10] = if tampered[10] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(decode_cursor(&tampered, query)
+            .unwrap_err()
+            .starts_with("Cursor signature is invalid"));
+        assert!(decode_cursor("xyz", query).is_err());
+        assert!(decode_cursor("", query).is_err());
+    }
+
+    #[test]
+    fn test_split_order_by() {
+        assert_eq!(
+            split_order_by("SELECT * FROM t ORDER BY a DESC, [b];"),
+            Some(("SELECT * FROM t", "a DESC, [b]"))
+        );
+        // ORDER BY inside a subquery or window function is not the query's
+        assert_eq!(
+            split_order_by("SELECT ROW_NUMBER() OVER (ORDER BY a) AS n FROM t"),
+            None
+        );
+        assert_eq!(split_order_by("SELECT 'ORDER BY x' AS s"), None);
+    }
+
+    #[test]
+    fn test_key_columns() {
+        assert_eq!(
+            key_columns("o.[Order Date] DESC, \"Id\"").unwrap(),
+            vec![
+                KeyColumn {
+                    name: "Order Date".to_string(),
+                    descending: true
+                },
+                KeyColumn {
+                    name: "Id".to_string(),
+                    descending: false
+                },
+            ]
+        );
+        assert!(key_columns("LEN(Name)")
+            .unwrap_err()
+            .contains("'LEN(Name)'"));
+        assert!(key_columns("1 DESC").is_err());
+        assert!(key_columns("a + b").is_err());
+    }
+
+    #[test]
+    fn test_keyset_query() {
+        let columns = key_columns("CreatedAt DESC, Id").unwrap();
+        let keys = vec!["N'2024'".to_string(), "7".to_string()];
+        assert_eq!(
+            keyset_query("SELECT Id, CreatedAt FROM t", &columns, &keys, 11),
+            "SELECT * FROM (\nSELECT Id, CreatedAt FROM t\n) AS [page]\n\
+             WHERE ([CreatedAt] < N'2024') OR ([CreatedAt] = N'2024' AND [Id] > 7)\n\
+             ORDER BY [CreatedAt] DESC, [Id] ASC\nOFFSET 0 ROWS FETCH NEXT 11 ROWS ONLY"
+        );
+        assert_eq!(
+            tie_query("SELECT Id, CreatedAt FROM t", &columns, &keys),
+            "SELECT COUNT_BIG(*) AS tied_rows FROM (SELECT TOP (2) 1 AS [row] FROM (\n\
+             SELECT Id, CreatedAt FROM t\n) AS [page]\n\
+             WHERE [CreatedAt] = N'2024' AND [Id] = 7) AS [tied]"
+        );
+        assert_eq!(
+            offset_query("SELECT * FROM t", "Id", 20, 11),
+            "SELECT * FROM t\nORDER BY Id\nOFFSET 20 ROWS FETCH NEXT 11 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn test_key_literal() {
+        use chrono::NaiveDate;
+
+        assert_eq!(
+            key_literal(&SqlValue::String("O'Brien".into())).as_deref(),
+            Some("N'O''Brien'")
+        );
+        assert_eq!(
+            key_literal(&SqlValue::Bytes(vec![0xAB, 0x01])).as_deref(),
+            Some("0xAB01")
+        );
+        let timestamp = NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_nano_opt(10, 30, 0, 123_456_789)
+            .unwrap();
+        assert_eq!(
+            key_literal(&SqlValue::DateTime(timestamp)).as_deref(),
+            Some("CAST('2024-05-01T10:30:00.1234567' AS datetime2(7))")
+        );
+        assert_eq!(key_literal(&SqlValue::Null), None);
+        assert_eq!(key_literal(&SqlValue::F64(f64::NAN)), None);
+    }
+}
@@ -0,0 +1,1121 @@
+//! Execution plan parsing.
+//!
+//! Converts showplan XML (`SET SHOWPLAN_XML` for estimated plans,
+//! `SET STATISTICS XML` for actual plans) into an operator tree with row
+//! estimates, costs and warnings, plus a flat list of likely problems ordered
+//! by severity. Only the parts of the showplan schema needed for that are
+//! read, using a small XML reader that understands elements and attributes
+//! and skips everything else.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+/// Share of the statement cost above which a scan is reported.
+const SCAN_COST_PERCENT: f64 = 25.0;
+
+/// Share of the statement cost above which a lookup is reported.
+const LOOKUP_COST_PERCENT: f64 = 10.0;
+
+/// Share of the statement cost above which any operator is reported.
+const EXPENSIVE_COST_PERCENT: f64 = 50.0;
+
+/// Factor between estimated and actual rows that counts as a misestimate.
+const MISESTIMATE_FACTOR: f64 = 10.0;
+
+/// Rows below which misestimates are not worth reporting.
+const MISESTIMATE_MIN_ROWS: f64 = 100.0;
+
+/// A parsed execution plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionPlan {
+    /// Statements of the batch, in order.
+    pub statements: Vec<PlanStatement>,
+
+    /// Likely problems across all statements, most severe first.
+    pub problems: Vec<PlanProblem>,
+}
+
+/// Plan of a single statement.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanStatement {
+    /// Statement text.
+    pub text: Option<String>,
+
+    /// Statement type, e.g. `SELECT`.
+    pub statement_type: Option<String>,
+
+    /// Estimated cost of the whole statement.
+    pub estimated_cost: Option<f64>,
+
+    /// Estimated rows returned by the statement.
+    pub estimated_rows: Option<f64>,
+
+    /// Statement-level warnings (e.g. implicit conversions affecting the plan).
+    pub warnings: Vec<String>,
+
+    /// Indexes the optimizer reported as missing.
+    pub missing_indexes: Vec<MissingIndex>,
+
+    /// Root operator, absent for statements without a plan (e.g. `SET`).
+    pub root: Option<PlanOperator>,
+}
+
+/// An operator in the plan tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanOperator {
+    /// Node ID, unique within the statement.
+    pub node_id: u32,
+
+    /// Physical operator, e.g. `Clustered Index Scan`.
+    pub physical_op: String,
+
+    /// Logical operator, e.g. `Inner Join`.
+    pub logical_op: String,
+
+    /// Object the operator reads or writes (`schema.table`, with the index).
+    pub object: Option<String>,
+
+    /// Estimated rows per execution.
+    pub estimated_rows: Option<f64>,
+
+    /// Actual rows over all executions (actual plans only).
+    pub actual_rows: Option<f64>,
+
+    /// Estimated cost of the operator and its inputs.
+    pub estimated_subtree_cost: Option<f64>,
+
+    /// Estimated cost of the operator alone.
+    pub estimated_operator_cost: Option<f64>,
+
+    /// Operator cost as a percentage of the statement cost.
+    pub cost_percent: Option<f64>,
+
+    /// Warnings attached to the operator.
+    pub warnings: Vec<String>,
+
+    /// Input operators.
+    pub children: Vec<PlanOperator>,
+}
+
+/// An index the optimizer reported as missing.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingIndex {
+    /// Estimated improvement in percent.
+    pub impact: Option<f64>,
+
+    /// Table the index belongs on (`schema.table`).
+    pub table: String,
+
+    /// Columns used in equality predicates.
+    pub equality_columns: Vec<String>,
+
+    /// Columns used in inequality predicates.
+    pub inequality_columns: Vec<String>,
+
+    /// Columns to include.
+    pub include_columns: Vec<String>,
+}
+
+/// Severity of a plan problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    High,
+    Medium,
+    Low,
+}
+
+/// A likely problem found in the plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanProblem {
+    /// How much the problem is likely to matter.
+    pub severity: Severity,
+
+    /// Kind of problem, e.g. `implicit_conversion` or `scan`.
+    pub kind: &'static str,
+
+    /// Index of the statement in the batch.
+    pub statement: usize,
+
+    /// Operator node ID, for operator-level problems.
+    pub node_id: Option<u32>,
+
+    /// Physical operator, for operator-level problems.
+    pub operator: Option<String>,
+
+    /// Human-readable description.
+    pub detail: String,
+
+    /// Operator cost as a percentage of the statement cost, if known.
+    #[serde(skip)]
+    cost_percent: f64,
+
+    /// What the problem is about, stable across plans of the same query.
+    #[serde(skip)]
+    subject: String,
+}
+
+impl MissingIndex {
+    /// Table and key columns, e.g. `dbo.Orders (code) INCLUDE (id)`.
+    pub fn describe(&self) -> String {
+        let mut columns = self.equality_columns.clone();
+        columns.extend(self.inequality_columns.iter().cloned());
+        let mut description = format!("{} ({})", self.table, columns.join(", "));
+        if !self.include_columns.is_empty() {
+            description.push_str(&format!(" INCLUDE ({})", self.include_columns.join(", ")));
+        }
+        description
+    }
+}
+
+impl ExecutionPlan {
+    /// Parse one or more showplan XML documents (one per result set).
+    pub fn parse(documents: &[String]) -> Result<Self, String> {
+        let mut statements = Vec::new();
+        for document in documents {
+            let root = parse_xml(document)?;
+            let mut stmts = Vec::new();
+            root.descendants("StmtSimple", &mut stmts);
+            statements.extend(stmts.into_iter().map(parse_statement));
+        }
+        if statements.is_empty() {
+            return Err("no statements found in the showplan".to_string());
+        }
+
+        let mut problems = Vec::new();
+        for (index, statement) in statements.iter().enumerate() {
+            find_problems(index, statement, &mut problems);
+        }
+        problems.sort_by(|a, b| {
+            a.severity
+                .cmp(&b.severity)
+                .then(b.cost_percent.total_cmp(&a.cost_percent))
+        });
+
+        Ok(Self {
+            statements,
+            problems,
+        })
+    }
+}
+
+// =============================================================================
+// Plan Extraction
+// =============================================================================
+
+fn parse_statement(stmt: &Element) -> PlanStatement {
+    let query_plan = stmt.child("QueryPlan");
+    let estimated_cost = stmt.number("StatementSubTreeCost");
+
+    let warnings = query_plan
+        .and_then(|plan| plan.child("Warnings"))
+        .map(describe_warnings)
+        .unwrap_or_default();
+
+    let mut groups = Vec::new();
+    if let Some(plan) = query_plan {
+        plan.descendants("MissingIndexGroup", &mut groups);
+    }
+    let missing_indexes = groups.into_iter().flat_map(missing_indexes).collect();
+
+    let root = query_plan
+        .and_then(|plan| plan.child("RelOp"))
+        .map(|relop| parse_operator(relop, estimated_cost));
+
+    PlanStatement {
+        text: stmt.attr("StatementText").map(|t| t.trim().to_string()),
+        statement_type: stmt.attr("StatementType").map(str::to_string),
+        estimated_cost,
+        estimated_rows: stmt.number("StatementEstRows"),
+        warnings,
+        missing_indexes,
+        root,
+    }
+}
+
+fn parse_operator(relop: &Element, statement_cost: Option<f64>) -> PlanOperator {
+    let mut inputs = Vec::new();
+    for child in &relop.children {
+        child.operators(&mut inputs);
+    }
+    let children: Vec<PlanOperator> = inputs
+        .into_iter()
+        .map(|input| parse_operator(input, statement_cost))
+        .collect();
+
+    let subtree_cost = relop.number("EstimatedTotalSubtreeCost");
+    let operator_cost = subtree_cost.map(|cost| {
+        let inputs: f64 = children
+            .iter()
+            .filter_map(|c| c.estimated_subtree_cost)
+            .sum();
+        (cost - inputs).max(0.0)
+    });
+    let cost_percent = match (operator_cost, statement_cost) {
+        (Some(cost), Some(total)) if total > 0.0 => Some(round(cost / total * 100.0)),
+        _ => None,
+    };
+
+    let actual_rows = relop.child("RunTimeInformation").map(|runtime| {
+        runtime
+            .children
+            .iter()
+            .filter(|c| c.name == "RunTimeCountersPerThread")
+            .filter_map(|c| c.number("ActualRows"))
+            .sum()
+    });
+
+    PlanOperator {
+        node_id: relop.number("NodeId").unwrap_or_default() as u32,
+        physical_op: relop.attr("PhysicalOp").unwrap_or_default().to_string(),
+        logical_op: relop.attr("LogicalOp").unwrap_or_default().to_string(),
+        object: operator_object(relop),
+        estimated_rows: relop.number("EstimateRows"),
+        actual_rows,
+        estimated_subtree_cost: subtree_cost,
+        estimated_operator_cost: operator_cost,
+        cost_percent,
+        warnings: relop
+            .child("Warnings")
+            .map(describe_warnings)
+            .unwrap_or_default(),
+        children,
+    }
+}
+
+/// The first object referenced by the operator itself (not by its inputs).
+fn operator_object(relop: &Element) -> Option<String> {
+    fn find(element: &Element) -> Option<&Element> {
+        for child in &element.children {
+            match child.name.as_str() {
+                "RelOp" => continue,
+                "Object" => return Some(child),
+                _ => {
+                    if let Some(found) = find(child) {
+                        return Some(found);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    let object = find(relop)?;
+    let table = object.attr("Table")?;
+    let mut name = match object.attr("Schema") {
+        Some(schema) => format!("{}.{}", unbracket(schema), unbracket(table)),
+        None => unbracket(table).to_string(),
+    };
+    if let Some(index) = object.attr("Index") {
+        name.push_str(&format!(" ({})", unbracket(index)));
+    }
+    Some(name)
+}
+
+fn missing_indexes(group: &Element) -> Vec<MissingIndex> {
+    let impact = group.number("Impact");
+    group
+        .children
+        .iter()
+        .filter(|c| c.name == "MissingIndex")
+        .map(|index| {
+            let columns = |usage: &str| -> Vec<String> {
+                index
+                    .children
+                    .iter()
+                    .filter(|g| g.name == "ColumnGroup" && g.attr("Usage") == Some(usage))
+                    .flat_map(|g| g.children.iter())
+                    .filter_map(|c| c.attr("Name").map(|n| unbracket(n).to_string()))
+                    .collect()
+            };
+            MissingIndex {
+                impact,
+                table: format!(
+                    "{}.{}",
+                    unbracket(index.attr("Schema").unwrap_or_default()),
+                    unbracket(index.attr("Table").unwrap_or_default())
+                ),
+                equality_columns: columns("EQUALITY"),
+                inequality_columns: columns("INEQUALITY"),
+                include_columns: columns("INCLUDE"),
+            }
+        })
+        .collect()
+}
+
+/// Describe the entries of a `Warnings` element.
+fn describe_warnings(warnings: &Element) -> Vec<String> {
+    let mut described = Vec::new();
+    if warnings.attr("NoJoinPredicate") == Some("true") {
+        described.push("No join predicate".to_string());
+    }
+    if warnings.attr("UnmatchedIndexes") == Some("true") {
+        described.push("Filtered index not used because of parameterization".to_string());
+    }
+    for warning in &warnings.children {
+        let text = match warning.name.as_str() {
+            "PlanAffectingConvert" => format!(
+                "Implicit conversion affects {}: {}",
+                warning.attr("ConvertIssue").unwrap_or("the plan"),
+                warning.attr("Expression").unwrap_or_default()
+            ),
+            "SpillToTempDb" => format!(
+                "Spill to tempdb (level {})",
+                warning.attr("SpillLevel").unwrap_or("?")
+            ),
+            "SortSpillDetails" | "HashSpillDetails" | "ExchangeSpillDetails" => format!(
+                "{} spilled {} pages to tempdb",
+                warning.name.trim_end_matches("SpillDetails"),
+                warning.attr("WritesToTempDb").unwrap_or("?")
+            ),
+            "ColumnsWithNoStatistics" => {
+                let mut columns = Vec::new();
+                warning.descendants("ColumnReference", &mut columns);
+                let names: Vec<&str> = columns
+                    .iter()
+                    .filter_map(|c| c.attr("Column"))
+                    .map(unbracket)
+                    .collect();
+                format!("Columns with no statistics: {}", names.join(", "))
+            }
+            "MemoryGrantWarning" => format!(
+                "Memory grant warning ({}): requested {} KB, granted {} KB, used {} KB",
+                warning.attr("GrantWarningKind").unwrap_or("unknown"),
+                warning.attr("RequestedMemory").unwrap_or("?"),
+                warning.attr("GrantedMemory").unwrap_or("?"),
+                warning.attr("MaxUsedMemory").unwrap_or("?")
+            ),
+            "Wait" => format!(
+                "Waited {} ms on {}",
+                warning.attr("WaitTime").unwrap_or("?"),
+                warning.attr("WaitType").unwrap_or("unknown")
+            ),
+            other => other.to_string(),
+        };
+        described.push(text);
+    }
+    described
+}
+
+// =============================================================================
+// Problem Detection
+// =============================================================================
+
+fn find_problems(index: usize, statement: &PlanStatement, problems: &mut Vec<PlanProblem>) {
+    for warning in &statement.warnings {
+        problems.push(PlanProblem {
+            severity: warning_severity(warning),
+            kind: warning_kind(warning),
+            statement: index,
+            node_id: None,
+            operator: None,
+            detail: warning.clone(),
+            cost_percent: 0.0,
+            subject: warning.clone(),
+        });
+    }
+
+    for missing in &statement.missing_indexes {
+        let mut detail = format!("Missing index on {}", missing.describe());
+        if let Some(impact) = missing.impact {
+            detail.push_str(&format!(", estimated improvement {:.0}%", impact));
+        }
+        let impact = missing.impact.unwrap_or_default();
+        problems.push(PlanProblem {
+            severity: if impact >= 50.0 {
+                Severity::High
+            } else {
+                Severity::Medium
+            },
+            kind: "missing_index",
+            statement: index,
+            node_id: None,
+            operator: None,
+            detail,
+            cost_percent: impact,
+            subject: missing.describe(),
+        });
+    }
+
+    if let Some(root) = &statement.root {
+        operator_problems(index, root, problems);
+    }
+}
+
+fn operator_problems(index: usize, op: &PlanOperator, problems: &mut Vec<PlanProblem>) {
+    let cost_percent = op.cost_percent.unwrap_or_default();
+    let target = op
+        .object
+        .as_deref()
+        .map(|o| format!(" of {}", o))
+        .unwrap_or_default();
+    let subject = format!("{}{}", op.physical_op, target);
+    let mut push = |severity, kind, detail: String| {
+        problems.push(PlanProblem {
+            severity,
+            kind,
+            statement: index,
+            node_id: Some(op.node_id),
+            operator: Some(op.physical_op.clone()),
+            detail,
+            cost_percent,
+            subject: subject.clone(),
+        });
+    };
+
+    for warning in &op.warnings {
+        push(
+            warning_severity(warning),
+            warning_kind(warning),
+            warning.clone(),
+        );
+    }
+
+    let mut flagged = !op.warnings.is_empty();
+    match op.physical_op.as_str() {
+        "Table Scan" | "Clustered Index Scan" | "Index Scan"
+            if cost_percent >= SCAN_COST_PERCENT =>
+        {
+            push(
+                Severity::Medium,
+                "scan",
+                format!(
+                    "{}{} accounts for {:.0}% of the cost",
+                    op.physical_op, target, cost_percent
+                ),
+            );
+            flagged = true;
+        }
+        "Key Lookup" | "RID Lookup" if cost_percent >= LOOKUP_COST_PERCENT => {
+            push(
+                Severity::Medium,
+                "lookup",
+                format!(
+                    "{}{} accounts for {:.0}% of the cost; a covering index may avoid it",
+                    op.physical_op, target, cost_percent
+                ),
+            );
+            flagged = true;
+        }
+        _ => {}
+    }
+
+    if let (Some(estimated), Some(actual)) = (op.estimated_rows, op.actual_rows) {
+        let (low, high) = if estimated < actual {
+            (estimated, actual)
+        } else {
+            (actual, estimated)
+        };
+        if high >= MISESTIMATE_MIN_ROWS && high >= low.max(1.0) * MISESTIMATE_FACTOR {
+            push(
+                Severity::Medium,
+                "row_misestimate",
+                format!(
+                    "{}{} estimated {} rows but returned {}",
+                    op.physical_op,
+                    target,
+                    round(estimated),
+                    round(actual)
+                ),
+            );
+            flagged = true;
+        }
+    }
+
+    if !flagged && cost_percent >= EXPENSIVE_COST_PERCENT {
+        push(
+            Severity::Low,
+            "expensive_operator",
+            format!(
+                "{}{} accounts for {:.0}% of the cost",
+                op.physical_op, target, cost_percent
+            ),
+        );
+    }
+
+    for child in &op.children {
+        operator_problems(index, child, problems);
+    }
+}
+
+fn warning_kind(warning: &str) -> &'static str {
+    if warning.starts_with("Implicit conversion") {
+        "implicit_conversion"
+    } else if warning.contains("tempdb") {
+        "spill"
+    } else if warning.starts_with("No join predicate") {
+        "no_join_predicate"
+    } else if warning.starts_with("Columns with no statistics") {
+        "missing_statistics"
+    } else if warning.starts_with("Memory grant") {
+        "memory_grant"
+    } else {
+        "warning"
+    }
+}
+
+fn warning_severity(warning: &str) -> Severity {
+    match warning_kind(warning) {
+        "spill" | "no_join_predicate" => Severity::High,
+        "implicit_conversion" if warning.contains("Seek Plan") => Severity::High,
+        "warning" => Severity::Low,
+        _ => Severity::Medium,
+    }
+}
+
+fn unbracket(name: &str) -> &str {
+    name.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// =============================================================================
+// Plan Comparison
+// =============================================================================
+
+/// Relative cost change below which two plans count as equivalent.
+const UNCHANGED_COST_PERCENT: f64 = 1.0;
+
+/// Differences between a baseline plan and a candidate plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanComparison {
+    /// `improved`, `regressed` or `unchanged`, judged by estimated cost.
+    pub verdict: &'static str,
+
+    /// Estimated cost of the baseline (sum over statements).
+    pub before_cost: f64,
+
+    /// Estimated cost of the candidate (sum over statements).
+    pub after_cost: f64,
+
+    /// `after_cost - before_cost`.
+    pub cost_delta: f64,
+
+    /// Cost change relative to the baseline, in percent.
+    pub cost_change_percent: Option<f64>,
+
+    /// Operators (with the object they access) whose count differs.
+    pub operator_changes: Vec<OperatorChange>,
+
+    /// Joins whose strategy differs, by position in the plan.
+    pub join_changes: Vec<JoinChange>,
+
+    /// Missing-index suggestions only in the baseline.
+    pub missing_indexes_resolved: Vec<String>,
+
+    /// Missing-index suggestions only in the candidate.
+    pub missing_indexes_introduced: Vec<String>,
+
+    /// Problems only in the baseline.
+    pub problems_resolved: Vec<PlanProblem>,
+
+    /// Problems only in the candidate.
+    pub problems_introduced: Vec<PlanProblem>,
+}
+
+/// An operator that appears a different number of times in two plans.
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorChange {
+    /// Physical operator and object, e.g. `Index Seek of dbo.Orders (IX_code)`.
+    pub operator: String,
+
+    /// Occurrences in the baseline.
+    pub before: usize,
+
+    /// Occurrences in the candidate.
+    pub after: usize,
+}
+
+/// A join whose physical strategy differs between two plans.
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinChange {
+    /// Position of the join in plan order (1-based).
+    pub position: usize,
+
+    /// Join in the baseline, e.g. `Nested Loops (Inner Join)`.
+    pub before: Option<String>,
+
+    /// Join in the candidate.
+    pub after: Option<String>,
+}
+
+impl ExecutionPlan {
+    /// Estimated cost of all statements.
+    pub fn total_cost(&self) -> f64 {
+        self.statements
+            .iter()
+            .filter_map(|s| s.estimated_cost)
+            .sum()
+    }
+
+    /// Compare this plan (the baseline) with another plan.
+    pub fn compare(&self, after: &ExecutionPlan) -> PlanComparison {
+        let before_cost = self.total_cost();
+        let after_cost = after.total_cost();
+        let cost_delta = after_cost - before_cost;
+        let cost_change_percent =
+            (before_cost > 0.0).then(|| round(cost_delta / before_cost * 100.0));
+        let verdict = match cost_change_percent {
+            Some(p) if p.abs() < UNCHANGED_COST_PERCENT => "unchanged",
+            None if cost_delta == 0.0 => "unchanged",
+            _ if cost_delta < 0.0 => "improved",
+            _ => "regressed",
+        };
+
+        let before_ops = self.operator_counts();
+        let after_ops = after.operator_counts();
+        let operator_changes = before_ops
+            .keys()
+            .chain(after_ops.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|operator| {
+                let before = before_ops.get(operator).copied().unwrap_or_default();
+                let after = after_ops.get(operator).copied().unwrap_or_default();
+                (before != after).then(|| OperatorChange {
+                    operator: operator.clone(),
+                    before,
+                    after,
+                })
+            })
+            .collect();
+
+        let before_joins = self.joins();
+        let after_joins = after.joins();
+        let join_changes = (0..before_joins.len().max(after_joins.len()))
+            .filter_map(|i| {
+                let before = before_joins.get(i).cloned();
+                let after = after_joins.get(i).cloned();
+                (before != after).then_some(JoinChange {
+                    position: i + 1,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+
+        let before_indexes = self.missing_indexes();
+        let after_indexes = after.missing_indexes();
+
+        let problem_key = |p: &PlanProblem| (p.kind, p.subject.clone());
+        let before_problems: BTreeSet<_> = self.problems.iter().map(problem_key).collect();
+        let after_problems: BTreeSet<_> = after.problems.iter().map(problem_key).collect();
+
+        PlanComparison {
+            verdict,
+            before_cost: round(before_cost),
+            after_cost: round(after_cost),
+            cost_delta: round(cost_delta),
+            cost_change_percent,
+            operator_changes,
+            join_changes,
+            missing_indexes_resolved: before_indexes.difference(&after_indexes).cloned().collect(),
+            missing_indexes_introduced: after_indexes
+                .difference(&before_indexes)
+                .cloned()
+                .collect(),
+            problems_resolved: self
+                .problems
+                .iter()
+                .filter(|p| !after_problems.contains(&problem_key(p)))
+                .cloned()
+                .collect(),
+            problems_introduced: after
+                .problems
+                .iter()
+                .filter(|p| !before_problems.contains(&problem_key(p)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn operators(&self) -> Vec<&PlanOperator> {
+        fn walk<'a>(op: &'a PlanOperator, out: &mut Vec<&'a PlanOperator>) {
+            out.push(op);
+            for child in &op.children {
+                walk(child, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        for root in self.statements.iter().filter_map(|s| s.root.as_ref()) {
+            walk(root, &mut out);
+        }
+        out
+    }
+
+    fn operator_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for op in self.operators() {
+            let key = match &op.object {
+                Some(object) => format!("{} of {}", op.physical_op, object),
+                None => op.physical_op.clone(),
+            };
+            *counts.entry(key).or_default() += 1;
+        }
+        counts
+    }
+
+    fn joins(&self) -> Vec<String> {
+        self.operators()
+            .into_iter()
+            .filter(|op| op.logical_op.contains("Join") || op.logical_op.contains("Apply"))
+            .map(|op| format!("{} ({})", op.physical_op, op.logical_op))
+            .collect()
+    }
+
+    fn missing_indexes(&self) -> BTreeSet<String> {
+        self.statements
+            .iter()
+            .flat_map(|s| s.missing_indexes.iter().map(MissingIndex::describe))
+            .collect()
+    }
+}
+
+// =============================================================================
+// XML Reader
+// =============================================================================
+
+/// An XML element with its attributes and child elements (text is dropped).
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        self.attr(name).and_then(|v| v.parse().ok())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Collect all descendants with the given name, in document order.
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            child.descendants(name, found);
+        }
+    }
+
+    /// Collect the nearest `RelOp` elements at or below this element.
+    fn operators<'a>(&'a self, found: &mut Vec<&'a Element>) {
+        if self.name == "RelOp" {
+            found.push(self);
+            return;
+        }
+        for child in &self.children {
+            child.operators(found);
+        }
+    }
+}
+
+/// Parse an XML document into its root element.
+fn parse_xml(xml: &str) -> Result<Element, String> {
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if let Some(body) = rest.strip_prefix("<?") {
+            rest = skip_past(body, "?>")?;
+        } else if let Some(body) = rest.strip_prefix("<!--") {
+            rest = skip_past(body, "-->")?;
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            rest = skip_past(body, "]]>")?;
+        } else if let Some(body) = rest.strip_prefix("<!") {
+            rest = skip_past(body, ">")?;
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let end = body.find('>').ok_or("unterminated closing tag")?;
+            let name = local_name(body[..end].trim());
+            let element = stack.pop().filter(|_| !stack.is_empty());
+            match element {
+                Some(element) if element.name == name => {
+                    stack
+                        .last_mut()
+                        .ok_or("unbalanced closing tag")?
+                        .children
+                        .push(element);
+                }
+                _ => return Err(format!("unexpected closing tag </{}>", name)),
+            }
+            rest = &body[end + 1..];
+        } else {
+            let (element, self_closing, remaining) = parse_start_tag(&rest[1..])?;
+            if self_closing {
+                stack
+                    .last_mut()
+                    .ok_or("unbalanced tag")?
+                    .children
+                    .push(element);
+            } else {
+                stack.push(element);
+            }
+            rest = remaining;
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err("unterminated element".to_string());
+    }
+    stack
+        .pop()
+        .and_then(|document| document.children.into_iter().next())
+        .ok_or_else(|| "empty document".to_string())
+}
+
+/// Parse the inside of a start tag; returns the element, whether it closed
+/// itself, and the input after the tag.
+fn parse_start_tag(input: &str) -> Result<(Element, bool, &str), String> {
+    let name_end = input
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .ok_or("unterminated start tag")?;
+    let mut element = Element {
+        name: local_name(&input[..name_end]).to_string(),
+        ..Element::default()
+    };
+    let mut rest = &input[name_end..];
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return Ok((element, true, after));
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return Ok((element, false, after));
+        }
+
+        let eq = rest.find('=').ok_or("malformed attribute")?;
+        let key = rest[..eq].trim();
+        let value_part = rest[eq + 1..].trim_start();
+        let quote = value_part.chars().next().ok_or("missing attribute value")?;
+        if quote != '"' && quote != '\'' {
+            return Err(format!("unquoted value for attribute {}", key));
+        }
+        let value_end = value_part[1..]
+            .find(quote)
+            .ok_or("unterminated attribute value")?;
+        // Namespace declarations are not needed to read the plan
+        if key != "xmlns" && !key.starts_with("xmlns:") {
+            element.attrs.push((
+                local_name(key).to_string(),
+                unescape(&value_part[1..1 + value_end]),
+            ));
+        }
+        rest = &value_part[value_end + 2..];
+    }
+}
+
+fn skip_past<'a>(input: &'a str, terminator: &str) -> Result<&'a str, String> {
+    input
+        .find(terminator)
+        .map(|end| &input[end + terminator.len()..])
+        .ok_or_else(|| format!("missing '{}'", terminator))
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Replace predefined and numeric character references.
+fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match replacement {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = r#"<?xml version="1.0" encoding="utf-16"?>
+<ShowPlanXML xmlns="http://schemas.microsoft.com/sqlserver/2004/07/showplan" Version="1.564">
+  <BatchSequence><Batch><Statements>
+    <StmtSimple StatementText="SELECT o.id FROM dbo.Orders o JOIN dbo.Customers c ON c.code = o.code WHERE o.code = N'A&amp;B'" StatementType="SELECT" StatementSubTreeCost="10" StatementEstRows="500">
+      <QueryPlan>
+        <Warnings>
+          <PlanAffectingConvert ConvertIssue="Seek Plan" Expression="CONVERT_IMPLICIT(nvarchar(20),[o].[code],0)=N'A&amp;B'" />
+        </Warnings>
+        <MissingIndexes>
+          <MissingIndexGroup Impact="87.5">
+            <MissingIndex Database="[Shop]" Schema="[dbo]" Table="[Orders]">
+              <ColumnGroup Usage="EQUALITY"><Column Name="[code]" ColumnId="2" /></ColumnGroup>
+              <ColumnGroup Usage="INCLUDE"><Column Name="[id]" ColumnId="1" /></ColumnGroup>
+            </MissingIndex>
+          </MissingIndexGroup>
+        </MissingIndexes>
+        <RelOp NodeId="0" PhysicalOp="Hash Match" LogicalOp="Inner Join" EstimateRows="500" EstimatedTotalSubtreeCost="10">
+          <Warnings><SpillToTempDb SpillLevel="1" SpilledThreadCount="1" /></Warnings>
+          <Hash>
+            <RelOp NodeId="1" PhysicalOp="Clustered Index Scan" LogicalOp="Clustered Index Scan" EstimateRows="1000" EstimatedTotalSubtreeCost="6">
+              <RunTimeInformation>
+                <RunTimeCountersPerThread Thread="0" ActualRows="40000" />
+              </RunTimeInformation>
+              <IndexScan><Object Database="[Shop]" Schema="[dbo]" Table="[Orders]" Index="[PK_Orders]" /></IndexScan>
+            </RelOp>
+            <RelOp NodeId="2" PhysicalOp="Index Seek" LogicalOp="Index Seek" EstimateRows="10" EstimatedTotalSubtreeCost="1">
+              <IndexScan><Object Schema="[dbo]" Table="[Customers]" Index="[IX_code]" /></IndexScan>
+            </RelOp>
+          </Hash>
+        </RelOp>
+      </QueryPlan>
+    </StmtSimple>
+  </Statements></Batch></BatchSequence>
+</ShowPlanXML>"#;
+
+    #[test]
+    fn test_parse_plan() {
+        let plan = ExecutionPlan::parse(&[PLAN.to_string()]).unwrap();
+        assert_eq!(plan.statements.len(), 1);
+
+        let statement = &plan.statements[0];
+        assert!(statement.text.as_deref().unwrap().ends_with("N'A&B'"));
+        assert_eq!(statement.estimated_cost, Some(10.0));
+        assert_eq!(statement.warnings.len(), 1);
+        assert_eq!(statement.missing_indexes[0].table, "dbo.Orders");
+        assert_eq!(statement.missing_indexes[0].equality_columns, vec!["code"]);
+        assert_eq!(statement.missing_indexes[0].include_columns, vec!["id"]);
+
+        let root = statement.root.as_ref().unwrap();
+        assert_eq!(root.physical_op, "Hash Match");
+        assert_eq!(root.estimated_operator_cost, Some(3.0));
+        assert_eq!(root.warnings, vec!["Spill to tempdb (level 1)"]);
+        assert_eq!(root.children.len(), 2);
+
+        let scan = &root.children[0];
+        assert_eq!(scan.object.as_deref(), Some("dbo.Orders (PK_Orders)"));
+        assert_eq!(scan.cost_percent, Some(60.0));
+        assert_eq!(scan.actual_rows, Some(40000.0));
+    }
+
+    #[test]
+    fn test_plan_problems() {
+        let plan = ExecutionPlan::parse(&[PLAN.to_string()]).unwrap();
+        let kinds: Vec<&str> = plan.problems.iter().map(|p| p.kind).collect();
+
+        // High severity first: conversion on a seek, missing index, spill
+        assert_eq!(plan.problems[0].severity, Severity::High);
+        for kind in [
+            "implicit_conversion",
+            "missing_index",
+            "spill",
+            "scan",
+            "row_misestimate",
+        ] {
+            assert!(kinds.contains(&kind), "missing {}", kind);
+        }
+        assert!(plan.problems.iter().all(|p| p.node_id != Some(2)));
+    }
+
+    #[test]
+    fn test_compare_plans() {
+        let before = ExecutionPlan::parse(&[PLAN.to_string()]).unwrap();
+        let rewritten = PLAN
+            .replace(
+                r#"StatementSubTreeCost="10""#,
+                r#"StatementSubTreeCost="2""#,
+            )
+            .replace(
+                r#"EstimatedTotalSubtreeCost="10""#,
+                r#"EstimatedTotalSubtreeCost="2""#,
+            )
+            .replace(
+                r#"EstimatedTotalSubtreeCost="6""#,
+                r#"EstimatedTotalSubtreeCost="0.5""#,
+            )
+            .replace("Hash Match", "Nested Loops")
+            .replace(
+                r#"PhysicalOp="Clustered Index Scan" LogicalOp="Clustered Index Scan""#,
+                r#"PhysicalOp="Index Seek" LogicalOp="Index Seek""#,
+            )
+            .replace("[PK_Orders]", "[IX_Orders_code]")
+            .replace("MissingIndexGroup", "ResolvedIndexGroup");
+        let after = ExecutionPlan::parse(&[rewritten]).unwrap();
+
+        let comparison = before.compare(&after);
+        assert_eq!(comparison.verdict, "improved");
+        assert_eq!(comparison.cost_delta, -8.0);
+        assert_eq!(comparison.cost_change_percent, Some(-80.0));
+        assert_eq!(comparison.join_changes.len(), 1);
+        assert_eq!(
+            comparison.join_changes[0].after.as_deref(),
+            Some("Nested Loops (Inner Join)")
+        );
+        assert!(comparison
+            .operator_changes
+            .iter()
+            .any(|c| c.operator == "Index Seek of dbo.Orders (IX_Orders_code)" && c.after == 1));
+        assert_eq!(
+            comparison.missing_indexes_resolved,
+            vec!["dbo.Orders (code) INCLUDE (id)"]
+        );
+        assert!(comparison
+            .problems_resolved
+            .iter()
+            .any(|p| p.kind == "scan"));
+
+        let same = before.compare(&before);
+        assert_eq!(same.verdict, "unchanged");
+        assert!(same.operator_changes.is_empty() && same.problems_introduced.is_empty());
+    }
+
+    #[test]
+    fn test_parse_xml_errors() {
+        assert!(ExecutionPlan::parse(&["<ShowPlanXML>".to_string()]).is_err());
+        assert!(ExecutionPlan::parse(&["<a></b>".to_string()]).is_err());
+        assert!(ExecutionPlan::parse(&["<ShowPlanXML/>".to_string()]).is_err());
+        assert_eq!(
+            unescape("a &lt;&#65;&#x42;&gt; &unknown; b"),
+            "a <AB> &unknown; b"
+        );
+    }
+}
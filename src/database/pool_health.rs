@@ -0,0 +1,283 @@
+//! Background maintenance of the connection pool.
+//!
+//! The pool itself warms up `min_connections` on creation and validates
+//! connections when they are checked out. Between checkouts an idle connection
+//! can silently die (failover, network timeout, `KILL`), so this task
+//! periodically:
+//!
+//! - probes each idle connection with a lightweight query and drops the ones
+//!   that fail,
+//! - recycles connections older than the configured maximum lifetime,
+//! - re-establishes connections until the pool holds `min_connections` again,
+//!
+//! and records per-connection age and usage for `get_pool_metrics`. After a
+//! failover, [`PoolHealth::rebuild`] drops the idle connections to the old
+//! server at once instead of waiting for the next run.
+
+use crate::config::PoolConfig;
+use crate::database::connection::{ConnectionPool, PooledConn};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Query used to validate idle connections.
+const PROBE_QUERY: &str = "SELECT 1";
+
+/// Longest time a probe may take before the connection is considered dead.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Age and usage of a pooled connection, as seen by the last probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStats {
+    /// Pool-assigned connection identifier.
+    pub id: u64,
+    /// Seconds since the connection was established.
+    pub age_seconds: u64,
+    /// Times the connection has been checked out, including probes.
+    pub checkouts: u64,
+    /// Round trip of the probe query in milliseconds.
+    pub probe_ms: u64,
+}
+
+/// Outcome of the maintenance runs so far.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PoolHealthSnapshot {
+    /// Number of completed maintenance runs.
+    pub runs: u64,
+    /// When the last run finished.
+    pub last_run: Option<DateTime<Utc>>,
+    /// Idle connections probed.
+    pub probes: u64,
+    /// Probes that failed, dropping the connection.
+    pub probe_failures: u64,
+    /// Connections closed for exceeding the maximum lifetime.
+    pub recycled: u64,
+    /// Connections established to get back to `min_connections`.
+    pub replenished: u64,
+    /// Idle connections that passed the last probe.
+    pub connections: Vec<ConnectionStats>,
+}
+
+/// Periodic validation, recycling and replenishment of pool connections.
+pub struct PoolHealth {
+    pool: Arc<ConnectionPool>,
+    min_connections: u32,
+    max_lifetime: Duration,
+    interval: Duration,
+    snapshot: Mutex<PoolHealthSnapshot>,
+}
+
+impl PoolHealth {
+    /// Create pool maintenance for `pool` using the pool settings.
+    pub fn new(pool: Arc<ConnectionPool>, config: &PoolConfig) -> Self {
+        Self {
+            pool,
+            min_connections: config.min_connections,
+            max_lifetime: config.max_lifetime,
+            interval: config.health_check_interval,
+            snapshot: Mutex::new(PoolHealthSnapshot::default()),
+        }
+    }
+
+    /// Interval between maintenance runs; zero when disabled.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Statistics of the maintenance runs so far.
+    pub fn snapshot(&self) -> PoolHealthSnapshot {
+        self.snapshot.lock().clone()
+    }
+
+    /// Run maintenance in the background until the pool is closed.
+    ///
+    /// Does nothing when the interval is zero. Must be called within a Tokio
+    /// runtime.
+    pub fn spawn(self: &Arc<Self>) {
+        if self.interval.is_zero() {
+            debug!("Pool health probing disabled");
+            return;
+        }
+
+        let health = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(health.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            // The first tick completes immediately; the pool was just warmed up
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if health.pool.is_closed() {
+                    debug!("Pool health task stopping: pool closed");
+                    break;
+                }
+                health.run_once().await;
+            }
+        });
+    }
+
+    /// Probe idle connections, recycle old ones and top the pool back up.
+    pub async fn run_once(&self) {
+        let mut probes = 0u64;
+        let mut failures = 0u64;
+        let mut recycled = 0u64;
+        let mut connections = Vec::new();
+
+        // Each checkout takes the oldest idle connection and returns it to the
+        // back of the queue, so one pass visits every idle connection once.
+        let mut seen = HashSet::new();
+        for _ in 0..self.pool.status().available {
+            let mut conn = match self.pool.try_get() {
+                Ok(Some(conn)) => conn,
+                _ => break,
+            };
+            let metadata = conn.metadata().clone();
+            if !seen.insert(metadata.id) {
+                break;
+            }
+
+            if metadata.is_expired(self.max_lifetime) {
+                debug!(
+                    "Recycling connection {} after {}s",
+                    metadata.id,
+                    metadata.created_at.elapsed().as_secs()
+                );
+                drop(conn.detach());
+                recycled += 1;
+                continue;
+            }
+
+            probes += 1;
+            let started = Instant::now();
+            match tokio::time::timeout(PROBE_TIMEOUT, probe(&mut conn)).await {
+                Ok(Ok(())) => connections.push(ConnectionStats {
+                    id: metadata.id,
+                    age_seconds: metadata.created_at.elapsed().as_secs(),
+                    checkouts: metadata.checkout_count,
+                    probe_ms: started.elapsed().as_millis() as u64,
+                }),
+                Ok(Err(e)) => {
+                    warn!(
+                        "Dropping pooled connection {}: probe failed: {}",
+                        metadata.id, e
+                    );
+                    drop(conn.detach());
+                    failures += 1;
+                }
+                Err(_) => {
+                    warn!(
+                        "Dropping pooled connection {}: probe timed out",
+                        metadata.id
+                    );
+                    drop(conn.detach());
+                    failures += 1;
+                }
+            }
+        }
+
+        let replenished = self.replenish().await;
+        if failures > 0 || recycled > 0 || replenished > 0 {
+            info!(
+                "Pool maintenance: {} probe failures, {} recycled, {} replenished",
+                failures, recycled, replenished
+            );
+        }
+
+        connections.sort_by_key(|c| c.id);
+        let mut snapshot = self.snapshot.lock();
+        snapshot.runs += 1;
+        snapshot.last_run = Some(Utc::now());
+        snapshot.probes += probes;
+        snapshot.probe_failures += failures;
+        snapshot.recycled += recycled;
+        snapshot.replenished += replenished;
+        snapshot.connections = connections;
+    }
+
+    /// Drop the idle connections established before `before` and top the pool
+    /// back up with fresh ones.
+    ///
+    /// Used after a failover, when every connection to the old server is
+    /// dead. Connections checked out at the time are not touched. Returns the
+    /// number of connections dropped.
+    pub async fn rebuild(&self, before: Instant) -> u64 {
+        let mut flushed = 0u64;
+        let mut seen = HashSet::new();
+        for _ in 0..self.pool.status().available {
+            let conn = match self.pool.try_get() {
+                Ok(Some(conn)) => conn,
+                _ => break,
+            };
+            let (id, created_at) = (conn.metadata().id, conn.metadata().created_at);
+            if !seen.insert(id) {
+                break;
+            }
+            if created_at < before {
+                drop(conn.detach());
+                flushed += 1;
+            }
+        }
+
+        let replenished = self.replenish().await;
+        info!(
+            "Pool rebuilt after failover: {} stale connections dropped, {} established",
+            flushed, replenished
+        );
+        let mut snapshot = self.snapshot.lock();
+        snapshot.replenished += replenished;
+        flushed
+    }
+
+    /// Establish connections until the pool holds `min_connections`.
+    ///
+    /// Returns the number of connections created.
+    async fn replenish(&self) -> u64 {
+        let missing = self
+            .min_connections
+            .saturating_sub(self.pool.status().total);
+        if missing == 0 {
+            return 0;
+        }
+
+        // Hold every checkout until done so each `get` creates a new connection
+        let mut held: Vec<PooledConn> = Vec::with_capacity(missing as usize);
+        let mut created = 0u64;
+        while self.pool.status().total < self.min_connections {
+            match self.pool.get().await {
+                Ok(conn) => {
+                    if conn.metadata().checkout_count == 1 {
+                        created += 1;
+                    }
+                    held.push(conn);
+                }
+                Err(e) => {
+                    warn!("Failed to replenish connection pool: {}", e);
+                    break;
+                }
+            }
+            if held.len() >= self.min_connections as usize {
+                break;
+            }
+        }
+        created
+    }
+}
+
+/// Run the probe query on a pooled connection.
+async fn probe(conn: &mut PooledConn) -> Result<(), String> {
+    let stream = conn
+        .query(PROBE_QUERY, &[])
+        .await
+        .map_err(|e| e.to_string())?;
+    stream
+        .try_collect::<Vec<_>>()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,621 @@
+//! Server-side column profiling.
+//!
+//! One aggregate query over a sample of the table computes, for each column,
+//! the null and distinct counts, the minimum and maximum and, for character
+//! columns, value lengths. Character columns are also sampled to derive value
+//! shapes such as `A+-9999`, so a model can reason about formats without being
+//! fed raw rows. Per-column queries add the most frequent values and numeric
+//! histograms.
+
+use crate::database::metadata::ColumnInfo;
+use crate::database::types::SqlValue;
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use crate::security::safe_identifier;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Longest min/max value or pattern reported.
+const MAX_VALUE_CHARS: usize = 100;
+
+/// Number of patterns reported per column.
+pub const TOP_PATTERNS: usize = 5;
+
+/// How a column can be profiled, from its SQL type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnKind {
+    Numeric,
+    Text,
+    Temporal,
+    Boolean,
+    /// Binary and uniqueidentifier columns: nulls and distinct values only.
+    Opaque,
+    /// LOB, XML, spatial and variant columns: nulls only.
+    Unsupported,
+}
+
+impl ColumnKind {
+    /// Classify a SQL Server type name.
+    pub fn from_type(data_type: &str) -> Self {
+        match data_type.to_lowercase().as_str() {
+            "tinyint" | "smallint" | "int" | "bigint" | "decimal" | "numeric" | "money"
+            | "smallmoney" | "float" | "real" => Self::Numeric,
+            "char" | "varchar" | "nchar" | "nvarchar" => Self::Text,
+            "date" | "time" | "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" => {
+                Self::Temporal
+            }
+            "bit" => Self::Boolean,
+            "binary" | "varbinary" | "uniqueidentifier" | "timestamp" | "rowversion" => {
+                Self::Opaque
+            }
+            _ => Self::Unsupported,
+        }
+    }
+
+    /// Whether `COUNT(DISTINCT ...)` works on the type.
+    fn has_distinct(&self) -> bool {
+        !matches!(self, Self::Unsupported)
+    }
+
+    /// Whether `MIN`/`MAX` are meaningful for the type.
+    fn has_range(&self) -> bool {
+        matches!(
+            self,
+            Self::Numeric | Self::Text | Self::Temporal | Self::Boolean
+        )
+    }
+
+    /// Whether values are readable as text, so frequent values can be listed.
+    pub fn has_top_values(&self) -> bool {
+        self.has_range()
+    }
+}
+
+/// A column to profile.
+#[derive(Debug, Clone)]
+pub struct ProfileColumn {
+    pub name: String,
+    pub data_type: String,
+    pub kind: ColumnKind,
+}
+
+impl From<&ColumnInfo> for ProfileColumn {
+    fn from(info: &ColumnInfo) -> Self {
+        Self {
+            name: info.column_name.clone(),
+            data_type: info.data_type.clone(),
+            kind: ColumnKind::from_type(&info.data_type),
+        }
+    }
+}
+
+/// Share of sampled non-null values with a given shape.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatternCount {
+    pub pattern: String,
+    pub count: usize,
+    pub percent: f64,
+}
+
+/// Occurrences of one of a column's most frequent values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: i64,
+    pub percent: f64,
+}
+
+/// Character lengths of a column's non-null values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LengthStats {
+    pub min: i64,
+    pub avg: f64,
+    pub max: i64,
+}
+
+/// Non-null values of a numeric column within `[lower, upper)`; the last
+/// bucket includes its upper bound.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: i64,
+}
+
+/// Statistics of one column.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: String,
+    pub kind: ColumnKind,
+    pub null_count: i64,
+    pub null_percent: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distinct_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<LengthStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<PatternCount>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_values: Vec<ValueCount>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub histogram: Vec<HistogramBucket>,
+}
+
+impl ColumnProfile {
+    /// Number of non-null values profiled, given the rows profiled.
+    pub fn non_null(&self, rows: i64) -> i64 {
+        (rows - self.null_count).max(0)
+    }
+}
+
+/// Statistics of a table sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableProfile {
+    /// Table name as `schema.table`.
+    pub table: String,
+    /// Rows the statistics were computed over.
+    pub rows_profiled: i64,
+    /// Maximum rows read; statistics cover the whole table when
+    /// `rows_profiled` is below it.
+    pub sample_limit: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+impl TableProfile {
+    /// Build a profile from the row of [`profile_query`] and the rows of
+    /// [`pattern_query`].
+    pub fn from_rows(
+        table: String,
+        columns: &[ProfileColumn],
+        aggregate: &ResultRow,
+        pattern_rows: &[ResultRow],
+        sample_limit: usize,
+    ) -> Self {
+        let rows = number(aggregate.get("row_count")).unwrap_or(0);
+        let percent = |count: i64| {
+            if rows == 0 {
+                0.0
+            } else {
+                (count as f64 * 1000.0 / rows as f64).round() / 10.0
+            }
+        };
+
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let null_count = number(aggregate.get(&format!("c{i}_nulls"))).unwrap_or(0);
+                let patterns = if column.kind == ColumnKind::Text {
+                    let alias = format!("c{i}");
+                    top_patterns(
+                        pattern_rows
+                            .iter()
+                            .filter_map(|row| text(row.get(&alias)))
+                            .collect::<Vec<_>>()
+                            .iter()
+                            .map(String::as_str),
+                        TOP_PATTERNS,
+                    )
+                } else {
+                    Vec::new()
+                };
+                let length = match (
+                    number(aggregate.get(&format!("c{i}_min_len"))),
+                    float(aggregate.get(&format!("c{i}_avg_len"))),
+                    number(aggregate.get(&format!("c{i}_max_len"))),
+                ) {
+                    (Some(min), Some(avg), Some(max)) => Some(LengthStats {
+                        min,
+                        avg: (avg * 10.0).round() / 10.0,
+                        max,
+                    }),
+                    _ => None,
+                };
+                ColumnProfile {
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    kind: column.kind,
+                    null_count,
+                    null_percent: percent(null_count),
+                    distinct_count: number(aggregate.get(&format!("c{i}_distinct"))),
+                    min: text(aggregate.get(&format!("c{i}_min"))).map(|v| truncate(&v)),
+                    max: text(aggregate.get(&format!("c{i}_max"))).map(|v| truncate(&v)),
+                    length,
+                    patterns,
+                    top_values: Vec::new(),
+                    histogram: Vec::new(),
+                }
+            })
+            .collect();
+
+        Self {
+            table,
+            rows_profiled: rows,
+            sample_limit,
+            columns,
+        }
+    }
+}
+
+/// Build the aggregate query profiling the first `sample_rows` rows.
+///
+/// Columns are aliased by position (`c0_nulls`, `c0_distinct`, ...) so any
+/// column name is safe.
+pub fn profile_query(
+    schema: &str,
+    table: &str,
+    columns: &[ProfileColumn],
+    sample_rows: usize,
+) -> Result<String, ServerError> {
+    let mut selects = vec!["COUNT_BIG(*) AS row_count".to_string()];
+    for (i, column) in columns.iter().enumerate() {
+        let name = format!("t.{}", safe_identifier(&column.name)?);
+        selects.push(format!(
+            "SUM(CASE WHEN {name} IS NULL THEN CAST(1 AS bigint) ELSE 0 END) AS c{i}_nulls"
+        ));
+        if column.kind.has_distinct() {
+            selects.push(format!("COUNT_BIG(DISTINCT {name}) AS c{i}_distinct"));
+        }
+        if column.kind.has_range() {
+            // MIN/MAX are not defined for bit
+            let value = if column.kind == ColumnKind::Boolean {
+                format!("CAST({name} AS tinyint)")
+            } else {
+                name.clone()
+            };
+            selects.push(format!(
+                "CAST(MIN({value}) AS nvarchar(4000)) AS c{i}_min, CAST(MAX({value}) AS nvarchar(4000)) AS c{i}_max"
+            ));
+        }
+        if column.kind == ColumnKind::Text {
+            selects.push(format!(
+                "MIN(LEN({name})) AS c{i}_min_len, AVG(CAST(LEN({name}) AS float)) AS c{i}_avg_len, MAX(LEN({name})) AS c{i}_max_len"
+            ));
+        }
+    }
+
+    Ok(format!(
+        "SELECT {} FROM (SELECT TOP ({}) * FROM {}.{}) AS t",
+        selects.join(",\n    "),
+        sample_rows.max(1),
+        safe_identifier(schema)?,
+        safe_identifier(table)?
+    ))
+}
+
+/// Build the query sampling character columns for pattern detection, if
+/// the table has any.
+pub fn pattern_query(
+    schema: &str,
+    table: &str,
+    columns: &[ProfileColumn],
+    sample_rows: usize,
+) -> Result<Option<String>, ServerError> {
+    let mut selects = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        if column.kind == ColumnKind::Text {
+            selects.push(format!(
+                "CAST(LEFT({}, {}) AS nvarchar({})) AS c{i}",
+                safe_identifier(&column.name)?,
+                MAX_VALUE_CHARS,
+                MAX_VALUE_CHARS
+            ));
+        }
+    }
+    if selects.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "SELECT TOP ({}) {} FROM {}.{}",
+        sample_rows.max(1),
+        selects.join(", "),
+        safe_identifier(schema)?,
+        safe_identifier(table)?
+    )))
+}
+
+/// Build the query counting the `limit` most frequent values of a column
+/// among the first `sample_rows` rows.
+pub fn top_values_query(
+    schema: &str,
+    table: &str,
+    column: &str,
+    sample_rows: usize,
+    limit: usize,
+) -> Result<String, ServerError> {
+    Ok(format!(
+        "SELECT TOP ({}) v AS value, COUNT_BIG(*) AS value_count \
+         FROM (SELECT TOP ({}) CAST({} AS nvarchar({})) AS v FROM {}.{}) AS t \
+         WHERE v IS NOT NULL GROUP BY v ORDER BY value_count DESC, v",
+        limit.max(1),
+        sample_rows.max(1),
+        safe_identifier(column)?,
+        MAX_VALUE_CHARS,
+        safe_identifier(schema)?,
+        safe_identifier(table)?
+    ))
+}
+
+/// Parse the rows of [`top_values_query`]; percentages are of the `non_null`
+/// values profiled.
+pub fn top_values(rows: &[ResultRow], non_null: i64) -> Vec<ValueCount> {
+    rows.iter()
+        .filter_map(|row| {
+            let value = text(row.get("value"))?;
+            let count = number(row.get("value_count"))?;
+            Some(ValueCount {
+                value: truncate(&value),
+                count,
+                percent: if non_null == 0 {
+                    0.0
+                } else {
+                    (count as f64 * 1000.0 / non_null as f64).round() / 10.0
+                },
+            })
+        })
+        .collect()
+}
+
+/// Build the query counting the values of a numeric column in `buckets`
+/// equal-width buckets between `min` and `max`, or `None` when the range is
+/// empty.
+pub fn histogram_query(
+    schema: &str,
+    table: &str,
+    column: &str,
+    sample_rows: usize,
+    min: f64,
+    max: f64,
+    buckets: usize,
+) -> Result<Option<String>, ServerError> {
+    if !(min.is_finite() && max.is_finite()) || max <= min || buckets == 0 {
+        return Ok(None);
+    }
+    let width = (max - min) / buckets as f64;
+    Ok(Some(format!(
+        "SELECT bucket, COUNT_BIG(*) AS value_count FROM (\
+         SELECT CASE WHEN v >= ({max}) THEN {last} ELSE CAST(FLOOR((v - ({min})) / ({width})) AS int) END AS bucket \
+         FROM (SELECT TOP ({rows}) CAST({column} AS float) AS v FROM {schema}.{table}) AS t \
+         WHERE v IS NOT NULL) AS b GROUP BY bucket ORDER BY bucket",
+        last = buckets - 1,
+        rows = sample_rows.max(1),
+        column = safe_identifier(column)?,
+        schema = safe_identifier(schema)?,
+        table = safe_identifier(table)?,
+    )))
+}
+
+/// Parse the rows of [`histogram_query`] into `buckets` buckets, including
+/// empty ones.
+pub fn histogram(rows: &[ResultRow], min: f64, max: f64, buckets: usize) -> Vec<HistogramBucket> {
+    if buckets == 0 || max <= min {
+        return Vec::new();
+    }
+    let width = (max - min) / buckets as f64;
+    let mut histogram: Vec<HistogramBucket> = (0..buckets)
+        .map(|i| HistogramBucket {
+            lower: min + width * i as f64,
+            upper: if i + 1 == buckets {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for row in rows {
+        let (Some(bucket), Some(count)) =
+            (number(row.get("bucket")), number(row.get("value_count")))
+        else {
+            continue;
+        };
+        // Float rounding may put the largest values one bucket too far
+        let index = bucket.clamp(0, buckets as i64 - 1) as usize;
+        histogram[index].count += count;
+    }
+    histogram
+}
+
+/// Shape of a value: uppercase letters become `A`, lowercase `a` and digits
+/// `9`; runs of two or more letters of the same case collapse to `A+`/`a+`.
+///
+/// Digits keep their count since fixed-width numbers (postal codes, phone
+/// numbers) are usually what a format check is about.
+pub fn value_pattern(value: &str) -> String {
+    let mut pattern = String::new();
+    let mut previous: Option<char> = None;
+    let mut run = 0;
+    for c in value.chars().take(MAX_VALUE_CHARS) {
+        let class = if c.is_uppercase() {
+            'A'
+        } else if c.is_alphabetic() {
+            'a'
+        } else if c.is_ascii_digit() {
+            '9'
+        } else {
+            c
+        };
+        if class == 'A' || class == 'a' {
+            if previous == Some(class) {
+                run += 1;
+                if run == 2 {
+                    pattern.push('+');
+                }
+                continue;
+            }
+            run = 1;
+        } else {
+            run = 0;
+        }
+        previous = Some(class);
+        pattern.push(class);
+    }
+    pattern
+}
+
+/// Most common value shapes, most frequent first.
+pub fn top_patterns<'a>(values: impl Iterator<Item = &'a str>, limit: usize) -> Vec<PatternCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut total = 0;
+    for value in values {
+        *counts.entry(value_pattern(value)).or_default() += 1;
+        total += 1;
+    }
+    let mut patterns: Vec<PatternCount> = counts
+        .into_iter()
+        .map(|(pattern, count)| PatternCount {
+            pattern,
+            count,
+            percent: (count as f64 * 1000.0 / total as f64).round() / 10.0,
+        })
+        .collect();
+    patterns.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    patterns.truncate(limit);
+    patterns
+}
+
+fn number(value: Option<&SqlValue>) -> Option<i64> {
+    match value? {
+        SqlValue::I64(v) => Some(*v),
+        SqlValue::I32(v) => Some(*v as i64),
+        SqlValue::I16(v) => Some(*v as i64),
+        SqlValue::I8(v) => Some(*v as i64),
+        SqlValue::Null => None,
+        other => other.to_display_string().parse().ok(),
+    }
+}
+
+fn float(value: Option<&SqlValue>) -> Option<f64> {
+    match value? {
+        SqlValue::F64(v) => Some(*v),
+        SqlValue::F32(v) => Some(*v as f64),
+        SqlValue::Null => None,
+        other => other.to_display_string().parse().ok(),
+    }
+}
+
+fn text(value: Option<&SqlValue>) -> Option<String> {
+    match value? {
+        SqlValue::Null => None,
+        SqlValue::String(s) => Some(s.clone()),
+        other => Some(other.to_display_string()),
+    }
+}
+
+fn truncate(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_CHARS {
+        value.to_string()
+    } else {
+        let mut truncated: String = value.chars().take(MAX_VALUE_CHARS).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str) -> ProfileColumn {
+        ProfileColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            kind: ColumnKind::from_type(data_type),
+        }
+    }
+
+    #[test]
+    fn test_value_pattern() {
+        assert_eq!(value_pattern("ABC-1234"), "A+-9999");
+        assert_eq!(value_pattern("John Smith"), "Aa+ Aa+");
+        assert_eq!(value_pattern("j@x.com"), "a@a.a+");
+        assert_eq!(value_pattern("98052"), "99999");
+
+        let patterns = top_patterns(["12345", "98052", "N/A"].into_iter(), 5);
+        assert_eq!(patterns[0].pattern, "99999");
+        assert_eq!(patterns[0].count, 2);
+        assert_eq!(patterns[0].percent, 66.7);
+    }
+
+    #[test]
+    fn test_profile_queries() {
+        let columns = vec![
+            column("Id", "int"),
+            column("Active", "bit"),
+            column("Notes", "ntext"),
+            column("Code", "varchar"),
+        ];
+        let query = profile_query("dbo", "Orders", &columns, 1000).unwrap();
+        assert!(query.contains("COUNT_BIG(DISTINCT t.[Id]) AS c0_distinct"));
+        assert!(query.contains("MIN(CAST(t.[Active] AS tinyint))"));
+        assert!(query.contains("c2_nulls"));
+        assert!(!query.contains("c2_distinct"));
+        assert!(query.ends_with("FROM (SELECT TOP (1000) * FROM [dbo].[Orders]) AS t"));
+
+        let patterns = pattern_query("dbo", "Orders", &columns, 500)
+            .unwrap()
+            .unwrap();
+        assert!(patterns.contains("AS c3"));
+        assert!(!patterns.contains("[Notes]"));
+
+        let mut aggregate = ResultRow::new();
+        aggregate.insert("row_count".to_string(), SqlValue::I64(4));
+        aggregate.insert("c0_nulls".to_string(), SqlValue::I64(1));
+        aggregate.insert("c0_distinct".to_string(), SqlValue::I64(3));
+        aggregate.insert("c0_min".to_string(), SqlValue::String("1".to_string()));
+        let profile =
+            TableProfile::from_rows("dbo.Orders".to_string(), &columns, &aggregate, &[], 1000);
+        assert_eq!(profile.rows_profiled, 4);
+        assert_eq!(profile.columns[0].null_percent, 25.0);
+        assert_eq!(profile.columns[0].distinct_count, Some(3));
+        assert_eq!(profile.columns[0].min.as_deref(), Some("1"));
+        assert_eq!(profile.columns[2].distinct_count, None);
+        assert_eq!(profile.columns[3].length, None);
+    }
+
+    #[test]
+    fn test_top_values_and_histogram() {
+        let columns = vec![column("Code", "varchar")];
+        let query = profile_query("dbo", "Orders", &columns, 100).unwrap();
+        assert!(query.contains("AVG(CAST(LEN(t.[Code]) AS float)) AS c0_avg_len"));
+
+        let query = top_values_query("dbo", "Orders", "Code", 100, 3).unwrap();
+        assert!(query.starts_with("SELECT TOP (3) v AS value"));
+        assert!(query.contains("FROM (SELECT TOP (100) CAST([Code] AS nvarchar(100)) AS v"));
+
+        let mut row = ResultRow::new();
+        row.insert("value".to_string(), SqlValue::String("A".to_string()));
+        row.insert("value_count".to_string(), SqlValue::I64(3));
+        let values = top_values(&[row], 4);
+        assert_eq!(values[0].value, "A");
+        assert_eq!(values[0].percent, 75.0);
+
+        assert!(histogram_query("dbo", "Orders", "Total", 100, 5.0, 5.0, 10)
+            .unwrap()
+            .is_none());
+        let query = histogram_query("dbo", "Orders", "Total", 100, -10.0, 10.0, 4)
+            .unwrap()
+            .unwrap();
+        assert!(query.contains("WHEN v >= (10) THEN 3"));
+        assert!(query.contains("FLOOR((v - (-10)) / (5))"));
+
+        let mut row = ResultRow::new();
+        row.insert("bucket".to_string(), SqlValue::I32(4));
+        row.insert("value_count".to_string(), SqlValue::I64(2));
+        let buckets = histogram(&[row], -10.0, 10.0, 4);
+        assert_eq!(buckets.len(), 4);
+        assert_eq!(buckets[1].lower, -5.0);
+        assert_eq!(buckets[3].upper, 10.0);
+        assert_eq!(buckets[3].count, 2);
+    }
+}
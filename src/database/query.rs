@@ -1,13 +1,28 @@
 //! Query execution and result handling.
 
+use crate::database::blob::BinaryOptions;
+use crate::database::tvp::{coerce_rows, TableTypeColumn};
 use crate::database::types::{SqlValue, TypeMapper};
-use crate::database::ConnectionPool;
+use crate::database::udt::SpatialFormat;
+use crate::database::{
+    capture_messages, ConnectionPool, FailoverRecovery, PooledConn, ResultMemory, SlowQueryLog,
+};
 use crate::error::ServerError;
-use crate::resilience::{RetryConfig, with_retry};
+use crate::resilience::{
+    retry_async, CircuitBreaker, CircuitBreakerConfig, RetryConfig, RetryInfo, RetryOutcome,
+};
+use crate::security::is_read_only;
+use crate::telemetry::{
+    new_shared_metrics, record_request_rows, report_progress, tag_query, ServerMetrics,
+    SharedMetrics,
+};
 use futures_util::TryStreamExt;
-use mssql_client::{TvpColumn, TvpRow, TvpValue};
+use mssql_client::{ToSql, TvpColumn, TvpRow, TvpValue};
+use mssql_driver_pool::PoolError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
@@ -46,6 +61,18 @@ impl Default for ResultRow {
     }
 }
 
+#[cfg(test)]
+impl ResultRow {
+    /// Build a row from column names and values.
+    pub fn from_values(values: &[(&str, SqlValue)]) -> Self {
+        let mut row = Self::new();
+        for (column, value) in values {
+            row.insert(column.to_string(), value.clone());
+        }
+        row
+    }
+}
+
 /// Result of executing multiple statements in a transaction.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionBatchResult {
@@ -82,6 +109,14 @@ pub struct QueryResult {
 
     /// Whether results were truncated due to row limit.
     pub truncated: bool,
+
+    /// Retry metadata, present when the statement was retried.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryInfo>,
+
+    /// Informational messages from PRINT and low-severity RAISERROR.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<String>,
 }
 
 /// Information about a result column.
@@ -106,11 +141,49 @@ impl QueryResult {
             rows_affected: 0,
             execution_time_ms: 0,
             truncated: false,
+            retry: None,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Render binary values as a hex preview, base64 or their size.
+    pub fn set_binary_options(&mut self, options: BinaryOptions) {
+        for row in &mut self.rows {
+            for value in row.columns.values_mut() {
+                if let SqlValue::Bytes(bytes) = value {
+                    *value = options.render(bytes);
+                }
+            }
+        }
+    }
+
+    /// Set how geometry and geography values are rendered.
+    pub fn set_spatial_format(&mut self, format: SpatialFormat) {
+        for row in &mut self.rows {
+            for value in row.columns.values_mut() {
+                if let SqlValue::Udt(v) = value {
+                    v.set_format(format);
+                }
+            }
         }
     }
 
     /// Format the result as a markdown table.
+    ///
+    /// Informational messages, if any, are listed after the table.
     pub fn to_markdown_table(&self) -> String {
+        let mut output = self.format_table();
+        if !self.messages.is_empty() {
+            output.push_str("\n\n**Messages:**\n");
+            for message in &self.messages {
+                output.push_str(&format!("\n    {}", message));
+            }
+        }
+        output
+    }
+
+    /// Format the rows as a markdown table without messages.
+    fn format_table(&self) -> String {
         if self.columns.is_empty() {
             if self.rows_affected > 0 {
                 return format!(
@@ -162,6 +235,12 @@ impl QueryResult {
             output.push_str(" _(truncated)_");
         }
         output.push_str(&format!(" _({} ms)_", self.execution_time_ms));
+        if let Some(retry) = &self.retry {
+            output.push_str(&format!(
+                " _(retried: {} attempts, {} ms waiting)_",
+                retry.attempts, retry.total_wait_ms
+            ));
+        }
 
         output
     }
@@ -185,16 +264,11 @@ impl QueryResult {
                 .columns
                 .iter()
                 .map(|col| {
-                    let value = row
-                        .get(&col.name)
-                        .map(|v| v.to_display_string())
-                        .unwrap_or_default();
-                    // Escape CSV values
-                    if value.contains(',') || value.contains('"') || value.contains('\n') {
-                        format!("\"{}\"", value.replace('"', "\"\""))
-                    } else {
-                        value
-                    }
+                    csv_field(
+                        row.get(&col.name)
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                    )
                 })
                 .collect();
             output.push_str(&values.join(","));
@@ -205,6 +279,31 @@ impl QueryResult {
     }
 }
 
+/// Escape a value for use as a CSV field.
+pub(crate) fn csv_field(value: String) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Summary of a query whose rows were streamed to a callback.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamSummary {
+    /// Column information.
+    pub columns: Vec<ColumnInfo>,
+
+    /// Number of rows passed to the callback.
+    pub row_count: usize,
+
+    /// Whether reading stopped at the row limit.
+    pub truncated: bool,
+
+    /// Execution time in milliseconds.
+    pub execution_time_ms: u64,
+}
+
 /// Result containing multiple result sets from a single query.
 ///
 /// This is returned when a query contains multiple SELECT statements
@@ -342,6 +441,57 @@ impl MultiQueryResult {
     }
 }
 
+/// Result of executing a stored procedure.
+///
+/// Procedures may return any number of result sets; all of them are captured
+/// in the order they were produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcedureResult {
+    /// Result sets returned by the procedure, in order.
+    pub result_sets: Vec<QueryResult>,
+
+    /// Rows affected by the procedure's last statement (from `@@ROWCOUNT`).
+    pub rows_affected: u64,
+
+    /// Value passed to `RETURN` by the procedure.
+    pub return_value: Option<i32>,
+
+    /// Total execution time in milliseconds.
+    pub execution_time_ms: u64,
+}
+
+impl ProcedureResult {
+    /// Format all result sets as markdown tables, followed by a status line.
+    pub fn to_markdown_table(&self) -> String {
+        let mut output = if self.result_sets.is_empty() {
+            format!(
+                "Procedure executed successfully. {} row(s) affected.",
+                self.rows_affected
+            )
+        } else {
+            MultiQueryResult {
+                result_sets: self.result_sets.clone(),
+                execution_time_ms: self.execution_time_ms,
+            }
+            .to_markdown_table()
+        };
+
+        if let Some(value) = self.return_value {
+            output.push_str(&format!("\n\n_Return value: {}_", value));
+        }
+        output
+    }
+
+    /// Format all result sets as CSV (concatenated with blank line separators).
+    pub fn to_csv(&self) -> String {
+        self.result_sets
+            .iter()
+            .map(|r| r.to_csv())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
 /// Result of SQL syntax validation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
@@ -420,11 +570,46 @@ fn extract_line_number(error_message: &str) -> Option<u32> {
     })
 }
 
+/// Get the driver client of a pooled connection.
+///
+/// `PooledConnection::query` flattens driver errors into strings; querying the
+/// client directly keeps the SQL Server error number.
+fn client_mut(
+    conn: &mut PooledConn,
+) -> Result<&mut mssql_client::Client<mssql_client::Ready>, ServerError> {
+    conn.client_mut()
+        .ok_or_else(|| ServerError::connection("Connection not available".to_string()))
+}
+
+tokio::task_local! {
+    /// Whether the current task only reads, and may use the read-only pool.
+    static READ_INTENT: bool;
+}
+
+/// Run a future whose queries use the read-only pool, if one is configured, when `read_only` is set.
+pub async fn with_read_intent<F: Future>(read_only: bool, future: F) -> F::Output {
+    READ_INTENT.scope(read_only, future).await
+}
+
+/// Whether the current task runs with read intent.
+fn read_intent() -> bool {
+    READ_INTENT.try_with(|intent| *intent).unwrap_or(false)
+}
+
 /// Query executor for running SQL queries.
 pub struct QueryExecutor {
     pool: Arc<ConnectionPool>,
-    max_rows: usize,
+    /// Pool used by tasks running with read intent.
+    read_pool: Option<Arc<ConnectionPool>>,
+    max_rows: AtomicUsize,
     retry_config: Option<RetryConfig>,
+    retry_writes: bool,
+    slow_queries: Arc<SlowQueryLog>,
+    circuit_breaker: Arc<CircuitBreaker>,
+    /// Pool rebuild and read retry after failovers.
+    failover: Option<Arc<FailoverRecovery>>,
+    /// Per-shape statement statistics.
+    metrics: SharedMetrics,
 }
 
 impl QueryExecutor {
@@ -432,25 +617,141 @@ impl QueryExecutor {
     pub fn new(pool: Arc<ConnectionPool>, max_rows: usize) -> Self {
         Self {
             pool,
-            max_rows,
+            read_pool: None,
+            max_rows: AtomicUsize::new(max_rows),
             retry_config: None,
+            retry_writes: false,
+            slow_queries: Arc::new(SlowQueryLog::new()),
+            circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::database())),
+            failover: None,
+            metrics: new_shared_metrics(),
         }
     }
 
     /// Create a new query executor with retry support for transient failures.
+    ///
+    /// Only read-only statements are retried unless `with_write_retry` is set.
     pub fn with_retry(pool: Arc<ConnectionPool>, max_rows: usize, retry_config: RetryConfig) -> Self {
         Self {
             pool,
-            max_rows,
+            read_pool: None,
+            max_rows: AtomicUsize::new(max_rows),
             retry_config: Some(retry_config),
+            retry_writes: false,
+            slow_queries: Arc::new(SlowQueryLog::new()),
+            circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::database())),
+            failover: None,
+            metrics: new_shared_metrics(),
         }
     }
 
+    /// Get the default row limit.
+    pub fn max_rows(&self) -> usize {
+        self.max_rows.load(Ordering::Relaxed)
+    }
+
+    /// Share a circuit breaker guarding connection acquisition.
+    ///
+    /// Executors sharing a breaker fail fast together once the server
+    /// becomes unreachable.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self
+    }
+
+    /// Get the circuit breaker guarding connection acquisition.
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+
+    /// Run queries of tasks with read intent (see [`with_read_intent`]) on a
+    /// separate pool, e.g. one connected to a readable secondary.
+    pub fn with_read_pool(mut self, read_pool: Option<Arc<ConnectionPool>>) -> Self {
+        self.read_pool = read_pool;
+        self
+    }
+
+    /// Rebuild the pools when a query fails with a failover signature, and
+    /// retry read-only statements once on a fresh connection.
+    pub fn with_failover_recovery(mut self, failover: Arc<FailoverRecovery>) -> Self {
+        self.failover = Some(failover);
+        self
+    }
+
+    /// Record executed statements in a shared metrics collector.
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// The pool for the current task.
+    fn pool(&self) -> &Arc<ConnectionPool> {
+        match &self.read_pool {
+            Some(read_pool) if read_intent() => read_pool,
+            _ => &self.pool,
+        }
+    }
+
+    /// Get a pooled connection through the circuit breaker.
+    ///
+    /// Failures to connect to the server count towards opening the circuit;
+    /// while it is open, this fails immediately with `ServerError::CircuitOpen`.
+    /// Waiting for a busy pool does not count as a failure.
+    pub async fn acquire(&self) -> Result<PooledConn, ServerError> {
+        Self::acquire_from(self.pool(), &self.circuit_breaker).await
+    }
+
+    async fn acquire_from(
+        pool: &ConnectionPool,
+        circuit_breaker: &CircuitBreaker,
+    ) -> Result<PooledConn, ServerError> {
+        let acquired = circuit_breaker
+            .call(|| async {
+                match pool.get().await {
+                    Err(e @ (PoolError::Connection(_) | PoolError::ConnectionCreation(_))) => {
+                        Err(ServerError::connection(format!(
+                            "Failed to get connection from pool: {}",
+                            e
+                        )))
+                    }
+                    other => Ok(other),
+                }
+            })
+            .await?;
+        acquired.map_err(|e| {
+            ServerError::connection(format!("Failed to get connection from pool: {}", e))
+        })
+    }
+
+    /// Get the log of statements exceeding the slow query threshold.
+    pub fn slow_queries(&self) -> &SlowQueryLog {
+        &self.slow_queries
+    }
+
+    /// Update the default row limit, e.g. after a configuration reload.
+    pub fn set_max_rows(&self, max_rows: usize) {
+        self.max_rows.store(max_rows, Ordering::Relaxed);
+    }
+
     /// Enable or update retry configuration.
     pub fn set_retry_config(&mut self, config: RetryConfig) {
         self.retry_config = Some(config);
     }
 
+    /// Also retry statements that may modify data.
+    ///
+    /// A statement that failed after reaching the server may have taken
+    /// effect, so retrying writes can duplicate their side effects.
+    pub fn with_write_retry(mut self, retry_writes: bool) -> Self {
+        self.retry_writes = retry_writes;
+        self
+    }
+
+    /// Check if statements that may modify data are retried.
+    pub fn retries_writes(&self) -> bool {
+        self.retry_writes
+    }
+
     /// Disable retry (execute queries once without retry).
     pub fn disable_retry(&mut self) {
         self.retry_config = None;
@@ -463,7 +764,7 @@ impl QueryExecutor {
 
     /// Execute a query and return results.
     pub async fn execute(&self, query: &str) -> Result<QueryResult, ServerError> {
-        self.execute_with_limit(query, self.max_rows).await
+        self.execute_with_limit(query, self.max_rows()).await
     }
 
     /// Execute a query with a specific row limit.
@@ -481,7 +782,7 @@ impl QueryExecutor {
         query: &str,
         timeout_seconds: u64,
     ) -> Result<QueryResult, ServerError> {
-        self.execute_with_options(query, self.max_rows, Some(timeout_seconds))
+        self.execute_with_options(query, self.max_rows(), Some(timeout_seconds))
             .await
     }
 
@@ -496,64 +797,185 @@ impl QueryExecutor {
         max_rows: usize,
         timeout_seconds: Option<u64>,
     ) -> Result<QueryResult, ServerError> {
+        self.execute_with_retry_policy(query, max_rows, timeout_seconds, true)
+            .await
+    }
+
+    /// Execute a query, optionally bypassing the configured retry policy.
+    ///
+    /// Only read-only batches are retried unless write retry is enabled; pass
+    /// `allow_retry = false` to opt out entirely. When a statement is retried,
+    /// the result carries the retry metadata; when retries are exhausted, the
+    /// error is wrapped in `ServerError::RetriesExhausted`. With failover
+    /// recovery, a read-only batch failing with a failover signature is run
+    /// once more after the pools are rebuilt.
+    pub async fn execute_with_retry_policy(
+        &self,
+        query: &str,
+        max_rows: usize,
+        timeout_seconds: Option<u64>,
+        allow_retry: bool,
+    ) -> Result<QueryResult, ServerError> {
+        let result = self
+            .execute_with_configured_retry(query, max_rows, timeout_seconds, allow_retry)
+            .await;
+        let (Err(error), Some(failover)) = (&result, &self.failover) else {
+            return result;
+        };
+        if !failover.recover(error).await || !allow_retry || !is_read_only(query) {
+            return result;
+        }
+
+        info!("Retrying read-only query after failover");
+        failover.record_retry();
+        let attempts = match error {
+            ServerError::RetriesExhausted { attempts, .. } => attempts + 1,
+            _ => 2,
+        };
+        let mut result = Self::execute_query_inner(
+            self.pool(),
+            &self.circuit_breaker,
+            &self.slow_queries,
+            &self.metrics,
+            query,
+            max_rows,
+            timeout_seconds,
+        )
+        .await?;
+        result.retry = Some(RetryInfo {
+            attempts,
+            total_wait_ms: result.retry.map_or(0, |info| info.total_wait_ms),
+            outcome: RetryOutcome::Succeeded,
+        });
+        Ok(result)
+    }
+
+    /// Execute a query under the configured retry policy.
+    async fn execute_with_configured_retry(
+        &self,
+        query: &str,
+        max_rows: usize,
+        timeout_seconds: Option<u64>,
+        allow_retry: bool,
+    ) -> Result<QueryResult, ServerError> {
+        let retry_config = self
+            .retry_config
+            .as_ref()
+            .filter(|_| allow_retry && (self.retry_writes || is_read_only(query)));
+
         debug!(
             "Executing query (max_rows={}, timeout={:?}s, retry={}): {}",
             max_rows,
             timeout_seconds,
-            self.retry_enabled(),
+            retry_config.is_some(),
             truncate_for_log(query, 200)
         );
 
         // Use retry if enabled
-        if let Some(ref retry_config) = self.retry_config {
-            let pool = self.pool.clone();
+        if let Some(retry_config) = retry_config {
+            let pool = self.pool().clone();
+            let slow_queries = self.slow_queries.clone();
+            let circuit_breaker = self.circuit_breaker.clone();
+            let metrics = self.metrics.clone();
             let query_owned = query.to_string();
 
-            with_retry(retry_config, || {
+            let outcome = retry_async(retry_config, || {
                 let pool = pool.clone();
+                let slow_queries = slow_queries.clone();
+                let circuit_breaker = circuit_breaker.clone();
+                let metrics = metrics.clone();
                 let query = query_owned.clone();
                 async move {
-                    Self::execute_query_inner(&pool, &query, max_rows, timeout_seconds).await
+                    Self::execute_query_inner(
+                        &pool,
+                        &circuit_breaker,
+                        &slow_queries,
+                        &metrics,
+                        &query,
+                        max_rows,
+                        timeout_seconds,
+                    )
+                    .await
                 }
             })
-            .await
+            .await;
+
+            let info = outcome.info();
+            match outcome.into_result() {
+                Ok(mut result) => {
+                    if info.attempts > 1 {
+                        result.retry = Some(info);
+                    }
+                    Ok(result)
+                }
+                Err(e) => Err(e.with_retry_info(&info)),
+            }
         } else {
-            Self::execute_query_inner(&self.pool, query, max_rows, timeout_seconds).await
+            Self::execute_query_inner(
+                self.pool(),
+                &self.circuit_breaker,
+                &self.slow_queries,
+                &self.metrics,
+                query,
+                max_rows,
+                timeout_seconds,
+            )
+            .await
         }
     }
 
     /// Inner query execution (without retry logic).
     async fn execute_query_inner(
         pool: &Arc<ConnectionPool>,
+        circuit_breaker: &CircuitBreaker,
+        slow_queries: &SlowQueryLog,
+        metrics: &ServerMetrics,
         query: &str,
         max_rows: usize,
         timeout_seconds: Option<u64>,
     ) -> Result<QueryResult, ServerError> {
         let start = Instant::now();
+        let sql = tag_query(query);
 
         // Wrap execution in timeout if specified
         let execution_future = async {
-            let mut conn = pool.get().await.map_err(|e| {
-                ServerError::connection(format!("Failed to get connection from pool: {}", e))
-            })?;
+            let mut conn = Self::acquire_from(pool, circuit_breaker).await?;
 
-            let stream = conn
-                .query(query, &[])
+            let stream = client_mut(&mut conn)?
+                .query(&sql, &[])
                 .await
-                .map_err(|e| ServerError::query_error(format!("Query execution failed: {}", e)))?;
+                .map_err(|e| ServerError::query_failed("Query execution failed", e))?;
 
             // Use streaming to process rows - stops at max_rows without loading all into memory
-            Self::process_stream_static(stream, max_rows, start).await
+            let result = Self::process_stream_static(stream, max_rows, start).await?;
+            Ok::<_, ServerError>((result, conn))
         };
 
-        let result = if let Some(secs) = timeout_seconds {
-            let duration = Duration::from_secs(secs);
-            timeout(duration, execution_future)
-                .await
-                .map_err(|_| ServerError::timeout(secs))?
-        } else {
-            execution_future.await
-        }?;
+        let (result, messages) = capture_messages(async {
+            if let Some(secs) = timeout_seconds {
+                let duration = Duration::from_secs(secs);
+                timeout(duration, execution_future)
+                    .await
+                    .map_err(|_| ServerError::timeout(secs))?
+            } else {
+                execution_future.await
+            }
+        })
+        .await;
+        let (mut result, mut conn) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                metrics.record_statement(query, false, start.elapsed(), 0);
+                return Err(e);
+            }
+        };
+        // Server messages come before the note on a partial result
+        result.messages = [messages, std::mem::take(&mut result.messages)].concat();
+        record_request_rows(result.rows.len() as u64);
+        metrics.record_statement(query, true, start.elapsed(), result.rows.len() as u64);
+        slow_queries
+            .observe(&mut conn, &sql, start.elapsed(), result.rows.len() as u64)
+            .await;
 
         debug!(
             "Query completed: {} rows in {} ms",
@@ -570,17 +992,28 @@ impl QueryExecutor {
 
         debug!("Executing non-query: {}", truncate_for_log(query, 200));
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let sql = tag_query(query);
+        let result = async {
+            let mut conn = self.acquire().await?;
 
-        // Execute query - returns rows affected directly as u64
-        let rows_affected = conn
-            .execute(query, &[])
-            .await
-            .map_err(|e| ServerError::query_error(format!("Non-query execution failed: {}", e)))?;
+            // Execute query - returns rows affected directly as u64
+            let rows_affected = client_mut(&mut conn)?
+                .execute(&sql, &[])
+                .await
+                .map_err(|e| ServerError::query_failed("Non-query execution failed", e))?;
+            Ok::<_, ServerError>((rows_affected, conn))
+        }
+        .await;
+        let rows_affected = result.as_ref().map_or(0, |(rows, _)| *rows);
+        self.metrics
+            .record_statement(query, result.is_ok(), start.elapsed(), rows_affected);
+        let (rows_affected, mut conn) = result?;
+        self.slow_queries
+            .observe(&mut conn, &sql, start.elapsed(), rows_affected)
+            .await;
 
         debug!("Non-query completed: {} rows affected", rows_affected);
+        record_request_rows(rows_affected);
 
         Ok(QueryResult {
             columns: Vec::new(),
@@ -588,6 +1021,8 @@ impl QueryExecutor {
             rows_affected,
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated: false,
+            retry: None,
+            messages: Vec::new(),
         })
     }
 
@@ -601,18 +1036,30 @@ impl QueryExecutor {
 
         debug!("Executing raw query: {}", truncate_for_log(query, 200));
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let (result, messages) = capture_messages(async {
+            let mut conn = self.acquire().await?;
 
-        // Execute raw SQL
-        let stream = conn
-            .query(query, &[])
-            .await
-            .map_err(|e| ServerError::query_error(format!("Raw query failed: {}", e)))?;
+            // Execute raw SQL
+            let stream = client_mut(&mut conn)?
+                .query(query, &[])
+                .await
+                .map_err(|e| ServerError::query_failed("Raw query failed", e))?;
 
-        // Use streaming to process rows - stops at max_rows without loading all into memory
-        let result = self.process_stream(stream, self.max_rows, start).await?;
+            // Use streaming to process rows - stops at max_rows without loading all into memory
+            let result = self.process_stream(stream, self.max_rows(), start).await?;
+            self.slow_queries
+                .observe(&mut conn, query, start.elapsed(), result.rows.len() as u64)
+                .await;
+            Ok::<_, ServerError>(result)
+        })
+        .await;
+        let rows = result.as_ref().map_or(0, |result| result.rows.len() as u64);
+        self.metrics
+            .record_statement(query, result.is_ok(), start.elapsed(), rows);
+        let mut result = result?;
+        // Server messages come before the note on a partial result
+        result.messages = [messages, std::mem::take(&mut result.messages)].concat();
+        record_request_rows(result.rows.len() as u64);
 
         debug!(
             "Raw query completed: {} rows in {} ms",
@@ -623,6 +1070,57 @@ impl QueryExecutor {
         Ok(result)
     }
 
+    /// Execute a query and pass each row to a callback as it is read.
+    ///
+    /// Rows are not accumulated, so arbitrarily large results can be processed
+    /// (e.g. written to disk) with constant memory. Reading stops after
+    /// `max_rows` rows or when the callback returns an error.
+    pub async fn stream_rows<F>(
+        &self,
+        query: &str,
+        max_rows: usize,
+        mut on_row: F,
+    ) -> Result<StreamSummary, ServerError>
+    where
+        F: FnMut(&[ColumnInfo], &ResultRow) -> Result<(), ServerError>,
+    {
+        let start = Instant::now();
+
+        debug!("Streaming query: {}", truncate_for_log(query, 200));
+
+        let mut conn = self.acquire().await?;
+
+        let mut stream = client_mut(&mut conn)?
+            .query(&tag_query(query), &[])
+            .await
+            .map_err(|e| ServerError::query_failed("Query execution failed", e))?;
+
+        let mut summary = StreamSummary::default();
+        while let Some(row) = TryStreamExt::try_next(&mut stream)
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to read row from stream", e))?
+        {
+            if summary.columns.is_empty() {
+                summary.columns = Self::row_columns(&row);
+            }
+            if summary.row_count >= max_rows {
+                summary.truncated = true;
+                break;
+            }
+            on_row(&summary.columns, &Self::convert_row(&row, &summary.columns))?;
+            summary.row_count += 1;
+        }
+        summary.execution_time_ms = start.elapsed().as_millis() as u64;
+        record_request_rows(summary.row_count as u64);
+
+        debug!(
+            "Streamed query completed: {} rows in {} ms",
+            summary.row_count, summary.execution_time_ms
+        );
+
+        Ok(summary)
+    }
+
     /// Execute a query that may return multiple result sets.
     ///
     /// This uses `query_multiple` to properly capture all result sets from queries
@@ -633,29 +1131,44 @@ impl QueryExecutor {
         max_rows_per_result: usize,
     ) -> Result<MultiQueryResult, ServerError> {
         let start = Instant::now();
+        let result = self
+            .read_result_sets(query, max_rows_per_result, start)
+            .await;
+        let rows = result.as_ref().map_or(0, |result| {
+            result.result_sets.iter().map(|r| r.rows.len() as u64).sum()
+        });
+        self.metrics
+            .record_statement(query, result.is_ok(), start.elapsed(), rows);
+        result
+    }
 
+    /// Read every result set of a multi-result query.
+    async fn read_result_sets(
+        &self,
+        query: &str,
+        max_rows_per_result: usize,
+        start: Instant,
+    ) -> Result<MultiQueryResult, ServerError> {
         debug!(
             "Executing multi-result query: {}",
             truncate_for_log(query, 200)
         );
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         // Get the underlying client to access query_multiple
-        let client = conn.client_mut().ok_or_else(|| {
-            ServerError::connection("Connection not available".to_string())
-        })?;
+        let client = client_mut(&mut conn)?;
 
         // Use query_multiple to get all result sets
+        let sql = tag_query(query);
         let mut multi_stream = client
-            .query_multiple(query, &[])
+            .query_multiple(&sql, &[])
             .await
-            .map_err(|e| ServerError::query_error(format!("Multi-result query failed: {}", e)))?;
+            .map_err(|e| ServerError::query_failed("Multi-result query failed", e))?;
 
         let mut result_sets = Vec::new();
         let result_count = multi_stream.result_count();
+        let mut memory = ResultMemory::new();
 
         debug!("Query returned {} result set(s)", result_count);
 
@@ -685,7 +1198,7 @@ impl QueryExecutor {
             while let Some(row) = multi_stream
                 .next_row()
                 .await
-                .map_err(|e| ServerError::query_error(format!("Failed to read row: {}", e)))?
+                .map_err(|e| ServerError::query_failed("Failed to read row", e))?
             {
                 if rows.len() >= max_rows_per_result {
                     truncated = true;
@@ -697,17 +1210,24 @@ impl QueryExecutor {
                     let value = TypeMapper::extract_column(&row, idx);
                     result_row.insert(col.name.clone(), value);
                 }
+                if !memory.admit(&result_row)? {
+                    truncated = true;
+                    continue;
+                }
                 rows.push(result_row);
             }
 
             // Only add result set if it has columns (skip empty result sets from non-SELECT statements)
             if !columns.is_empty() || !rows.is_empty() {
+                let note = memory.partial_note().filter(|_| truncated);
                 result_sets.push(QueryResult {
                     columns,
                     rows,
                     rows_affected: 0,
                     execution_time_ms: result_set_start.elapsed().as_millis() as u64,
                     truncated,
+                    retry: None,
+                    messages: note.into_iter().collect(),
                 });
             }
 
@@ -715,13 +1235,20 @@ impl QueryExecutor {
             if !multi_stream
                 .next_result()
                 .await
-                .map_err(|e| ServerError::query_error(format!("Failed to advance to next result: {}", e)))?
+                .map_err(|e| ServerError::query_failed("Failed to advance to next result", e))?
             {
                 break;
             }
         }
 
+        drop(multi_stream);
+        let rows: u64 = result_sets.iter().map(|r| r.rows.len() as u64).sum();
+        self.slow_queries
+            .observe(&mut conn, &sql, start.elapsed(), rows)
+            .await;
+
         let execution_time_ms = start.elapsed().as_millis() as u64;
+        record_request_rows(rows);
 
         debug!(
             "Multi-result query completed: {} result set(s) in {} ms",
@@ -735,6 +1262,62 @@ impl QueryExecutor {
         })
     }
 
+    /// Execute a stored procedure, capturing every result set it returns.
+    ///
+    /// `exec_args` is the escaped procedure name followed by its argument list
+    /// (e.g. `[dbo].[GetOrders] @CustomerId = 42`). The call is wrapped so the
+    /// procedure's return value and final `@@ROWCOUNT` are reported alongside
+    /// its result sets.
+    pub async fn execute_procedure(
+        &self,
+        exec_args: &str,
+        max_rows_per_result: usize,
+    ) -> Result<ProcedureResult, ServerError> {
+        let batch = format!(
+            "DECLARE @__mcp_return_value INT;\n\
+             EXEC @__mcp_return_value = {};\n\
+             SELECT CAST(@@ROWCOUNT AS BIGINT) AS [__mcp_rows_affected], \
+             @__mcp_return_value AS [__mcp_return_value];",
+            exec_args
+        );
+
+        let mut multi = self
+            .execute_multi_result(&batch, max_rows_per_result)
+            .await?;
+
+        // The trailing status result set is ours, not the procedure's
+        let mut rows_affected = 0;
+        let mut return_value = None;
+        let is_status = multi.result_sets.last().is_some_and(|r| {
+            r.columns
+                .first()
+                .is_some_and(|c| c.name == "__mcp_rows_affected")
+        });
+        if is_status {
+            if let Some(row) = multi
+                .result_sets
+                .pop()
+                .and_then(|r| r.rows.into_iter().next())
+            {
+                rows_affected = row
+                    .get("__mcp_rows_affected")
+                    .and_then(|v| v.to_display_string().parse().ok())
+                    .unwrap_or(0);
+                return_value = row
+                    .get("__mcp_return_value")
+                    .filter(|v| !v.is_null())
+                    .and_then(|v| v.to_display_string().parse().ok());
+            }
+        }
+
+        Ok(ProcedureResult {
+            result_sets: multi.result_sets,
+            rows_affected,
+            return_value,
+            execution_time_ms: multi.execution_time_ms,
+        })
+    }
+
     /// Check if a query likely contains multiple SELECT statements.
     ///
     /// This is a heuristic check to determine if `execute_multi_result` should be used.
@@ -833,9 +1416,7 @@ impl QueryExecutor {
             truncate_for_log(query, 200)
         );
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         // Determine which SET statements to use based on plan type
         let (set_on, set_off) = match plan_type.to_lowercase().as_str() {
@@ -865,7 +1446,7 @@ impl QueryExecutor {
             // Turn off SHOWPLAN (best effort)
             let _ = conn.execute(set_off, &[]).await;
 
-            let result = self.process_rows(rows, self.max_rows, start)?;
+            let result = self.process_rows(rows, self.max_rows(), start)?;
 
             debug!(
                 "Showplan query completed: {} rows in {} ms",
@@ -886,7 +1467,7 @@ impl QueryExecutor {
                 ServerError::query_error(format!("Failed to collect statistics results: {}", e))
             })?;
 
-            let result = self.process_rows(rows, self.max_rows, start)?;
+            let result = self.process_rows(rows, self.max_rows(), start)?;
 
             debug!(
                 "Statistics query completed: {} rows in {} ms",
@@ -898,6 +1479,91 @@ impl QueryExecutor {
         }
     }
 
+    /// Get the execution plan of a query as showplan XML documents.
+    ///
+    /// Estimated plans use `SET SHOWPLAN_XML` (the query is not executed);
+    /// actual plans use `SET STATISTICS XML`, which executes the query and
+    /// discards its results. Returns one document per showplan result set.
+    pub async fn execute_with_showplan_xml(
+        &self,
+        query: &str,
+        plan_type: &str,
+    ) -> Result<Vec<String>, ServerError> {
+        debug!(
+            "Executing query with showplan XML ({}): {}",
+            plan_type,
+            truncate_for_log(query, 200)
+        );
+
+        let (set_on, set_off) = if plan_type.eq_ignore_ascii_case("actual") {
+            ("SET STATISTICS XML ON", "SET STATISTICS XML OFF")
+        } else {
+            ("SET SHOWPLAN_XML ON", "SET SHOWPLAN_XML OFF")
+        };
+
+        let mut conn = self.acquire().await?;
+        let client = client_mut(&mut conn)?;
+
+        // SET SHOWPLAN_XML must be the only statement in its batch
+        client
+            .execute(set_on, &[])
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to enable showplan XML", e))?;
+
+        let documents = Self::collect_showplans(client, query).await;
+
+        // Turn the plan output off again (best effort)
+        let _ = client.execute(set_off, &[]).await;
+
+        let documents = documents?;
+        if documents.is_empty() {
+            return Err(ServerError::query_error(
+                "The server did not return an execution plan",
+            ));
+        }
+        Ok(documents)
+    }
+
+    /// Run a query and keep the first column of every showplan result set.
+    async fn collect_showplans(
+        client: &mut mssql_client::Client<mssql_client::Ready>,
+        query: &str,
+    ) -> Result<Vec<String>, ServerError> {
+        let mut stream = client
+            .query_multiple(query, &[])
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to get execution plan", e))?;
+
+        let mut documents = Vec::new();
+        loop {
+            let is_plan = stream
+                .columns()
+                .and_then(|cols| cols.first())
+                .is_some_and(|col| col.name.contains("Showplan"));
+
+            while let Some(row) = stream
+                .next_row()
+                .await
+                .map_err(|e| ServerError::query_failed("Failed to read execution plan", e))?
+            {
+                if is_plan {
+                    if let Some(xml) = row.try_get::<String>(0) {
+                        documents.push(xml);
+                    }
+                }
+            }
+
+            if !stream
+                .next_result()
+                .await
+                .map_err(|e| ServerError::query_failed("Failed to advance to next result", e))?
+            {
+                break;
+            }
+        }
+        Ok(documents)
+    }
+
     /// Process query result rows into a QueryResult.
     fn process_rows(
         &self,
@@ -954,6 +1620,8 @@ impl QueryExecutor {
             rows_affected: 0,
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated,
+            retry: None,
+            messages: Vec::new(),
         })
     }
 
@@ -993,29 +1661,17 @@ impl QueryExecutor {
         let mut result_rows: Vec<ResultRow> = Vec::new();
         let mut truncated = false;
         let mut row_count = 0;
+        let mut memory = ResultMemory::new();
 
         // Process rows one at a time from the stream
-        while let Some(row) = stream.try_next().await.map_err(|e| {
-            ServerError::query_error(format!("Failed to read row from stream: {}", e))
-        })? {
+        while let Some(row) = stream
+            .try_next()
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to read row from stream", e))?
+        {
             // Extract column info from the first row
             if columns.is_empty() {
-                let row_columns = row.columns();
-                for (i, col) in row_columns.iter().enumerate() {
-                    let name = col.name.clone();
-                    let sql_type = if !col.type_name.is_empty() {
-                        col.type_name.clone()
-                    } else {
-                        let sample_value = TypeMapper::extract_column(&row, i);
-                        TypeMapper::sql_type_name_from_value(&sample_value).to_string()
-                    };
-
-                    columns.push(ColumnInfo {
-                        name,
-                        sql_type,
-                        nullable: col.nullable,
-                    });
-                }
+                columns = Self::row_columns(&row);
             }
 
             // Check if we've reached the limit
@@ -1027,10 +1683,10 @@ impl QueryExecutor {
             }
 
             // Extract row data
-            let mut result_row = ResultRow::new();
-            for (col_idx, col) in columns.iter().enumerate() {
-                let value = TypeMapper::extract_column(&row, col_idx);
-                result_row.insert(col.name.clone(), value);
+            let result_row = Self::convert_row(&row, &columns);
+            if !memory.admit(&result_row)? {
+                truncated = true;
+                break;
             }
             result_rows.push(result_row);
             row_count += 1;
@@ -1042,9 +1698,42 @@ impl QueryExecutor {
             rows_affected: 0,
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated,
+            retry: None,
+            messages: memory.partial_note().into_iter().collect(),
         })
     }
 
+    /// Extract column metadata from a row.
+    fn row_columns(row: &mssql_client::Row) -> Vec<ColumnInfo> {
+        row.columns()
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                let sql_type = if !col.type_name.is_empty() {
+                    col.type_name.clone()
+                } else {
+                    let sample_value = TypeMapper::extract_column(row, i);
+                    TypeMapper::sql_type_name_from_value(&sample_value).to_string()
+                };
+                ColumnInfo {
+                    name: col.name.clone(),
+                    sql_type,
+                    nullable: col.nullable,
+                }
+            })
+            .collect()
+    }
+
+    /// Convert a driver row into a result row.
+    fn convert_row(row: &mssql_client::Row, columns: &[ColumnInfo]) -> ResultRow {
+        let mut result_row = ResultRow::new();
+        for (col_idx, col) in columns.iter().enumerate() {
+            let value = TypeMapper::extract_column(row, col_idx);
+            result_row.insert(col.name.clone(), value);
+        }
+        result_row
+    }
+
     /// Validate SQL syntax without executing the query.
     ///
     /// Uses SET PARSEONLY ON to check if the SQL syntax is valid without
@@ -1056,9 +1745,7 @@ impl QueryExecutor {
 
         debug!("Validating query syntax: {}", truncate_for_log(query, 200));
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         // Enable PARSEONLY mode - this parses but doesn't execute
         conn.query("SET PARSEONLY ON", &[])
@@ -1131,10 +1818,9 @@ impl QueryExecutor {
         let mut combined_columns: Vec<ColumnInfo> = Vec::new();
         let mut combined_rows: Vec<ResultRow> = Vec::new();
         let mut batch_num = 0;
+        let mut memory = ResultMemory::new();
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         for batch in batches {
             let trimmed = batch.trim();
@@ -1207,7 +1893,7 @@ impl QueryExecutor {
                 }
 
                 // Check row limit
-                if combined_rows.len() >= self.max_rows {
+                if combined_rows.len() >= self.max_rows() {
                     continue;
                 }
 
@@ -1217,11 +1903,14 @@ impl QueryExecutor {
                     let value = TypeMapper::extract_column(&row, idx);
                     result_row.insert(col.name.clone(), value);
                 }
-                combined_rows.push(result_row);
+                if memory.admit(&result_row)? {
+                    combined_rows.push(result_row);
+                }
             }
         }
 
-        let truncated = combined_rows.len() >= self.max_rows;
+        let truncated = combined_rows.len() >= self.max_rows() || memory.partial_note().is_some();
+        record_request_rows(combined_rows.len() as u64);
 
         // Summary at INFO level
         info!(
@@ -1237,6 +1926,8 @@ impl QueryExecutor {
             rows_affected: 0, // Multi-batch doesn't track rows affected
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated,
+            retry: None,
+            messages: memory.partial_note().into_iter().collect(),
         })
     }
 
@@ -1262,10 +1953,9 @@ impl QueryExecutor {
         let mut combined_columns: Vec<ColumnInfo> = Vec::new();
         let mut combined_rows: Vec<ResultRow> = Vec::new();
         let mut batch_num = 0;
+        let mut memory = ResultMemory::new();
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         for batch in batches {
             let trimmed = batch.trim();
@@ -1277,7 +1967,7 @@ impl QueryExecutor {
 
             // Prepend USE [database] to each batch if a database is specified
             let effective_batch = match database {
-                Some(db) => format!("USE [{}];\n{}", db, trimmed),
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), trimmed),
                 None => trimmed.to_string(),
             };
 
@@ -1346,7 +2036,7 @@ impl QueryExecutor {
                 }
 
                 // Check row limit
-                if combined_rows.len() >= self.max_rows {
+                if combined_rows.len() >= self.max_rows() {
                     continue;
                 }
 
@@ -1356,11 +2046,14 @@ impl QueryExecutor {
                     let value = TypeMapper::extract_column(&row, idx);
                     result_row.insert(col.name.clone(), value);
                 }
-                combined_rows.push(result_row);
+                if memory.admit(&result_row)? {
+                    combined_rows.push(result_row);
+                }
             }
         }
 
-        let truncated = combined_rows.len() >= self.max_rows;
+        let truncated = combined_rows.len() >= self.max_rows() || memory.partial_note().is_some();
+        record_request_rows(combined_rows.len() as u64);
 
         // Summary at INFO level
         info!(
@@ -1377,6 +2070,8 @@ impl QueryExecutor {
             rows_affected: 0,
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated,
+            retry: None,
+            messages: memory.partial_note().into_iter().collect(),
         })
     }
 
@@ -1391,6 +2086,21 @@ impl QueryExecutor {
         &self,
         statements: &[String],
         continue_on_error: bool,
+    ) -> Result<TransactionBatchResult, ServerError> {
+        self.execute_in_transaction_with_params(statements, &[], continue_on_error)
+            .await
+    }
+
+    /// Execute multiple statements in a single transaction, binding `params`
+    /// as `@p1`, `@p2`, ... in every statement.
+    ///
+    /// Parameter values never appear in the statement text, so they stay out
+    /// of the logs and statement metrics.
+    pub async fn execute_in_transaction_with_params(
+        &self,
+        statements: &[String],
+        params: &[&(dyn ToSql + Sync)],
+        continue_on_error: bool,
     ) -> Result<TransactionBatchResult, ServerError> {
         let start = Instant::now();
         let total_statements = statements.len();
@@ -1400,9 +2110,7 @@ impl QueryExecutor {
             total_statements, continue_on_error
         );
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         // Begin transaction
         conn.execute("BEGIN TRANSACTION", &[])
@@ -1422,7 +2130,7 @@ impl QueryExecutor {
                 stmt_preview
             );
 
-            match conn.execute(stmt, &[]).await {
+            match conn.execute(stmt, params).await {
                 Ok(rows_affected) => {
                     total_rows_affected += rows_affected;
                     successful_statements += 1;
@@ -1447,6 +2155,11 @@ impl QueryExecutor {
                     }
                 }
             }
+            report_progress(
+                (idx + 1) as u64,
+                Some(total_statements as u64),
+                &format!("Statement {}/{}", idx + 1, total_statements),
+            );
         }
 
         // Commit transaction
@@ -1519,9 +2232,7 @@ impl QueryExecutor {
             truncate_for_log(query, 200)
         );
 
-        let mut conn = self.pool.get().await.map_err(|e| {
-            ServerError::connection(format!("Failed to get connection from pool: {}", e))
-        })?;
+        let mut conn = self.acquire().await?;
 
         // Execute with TVP as parameter
         let stream = conn
@@ -1541,6 +2252,33 @@ impl QueryExecutor {
         Ok(result)
     }
 
+    /// Execute a query binding `params` as `@p1`, `@p2`, ...
+    ///
+    /// Like [`Self::execute_in_transaction_with_params`], the values are kept
+    /// out of the statement text.
+    pub async fn execute_with_params(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+        max_rows: usize,
+    ) -> Result<QueryResult, ServerError> {
+        let start = Instant::now();
+
+        debug!(
+            "Executing query with {} parameters: {}",
+            params.len(),
+            truncate_for_log(query, 200)
+        );
+
+        let mut conn = self.acquire().await?;
+        let stream = conn
+            .query(query, params)
+            .await
+            .map_err(|e| ServerError::query_error(format!("Query execution failed: {}", e)))?;
+
+        self.process_stream(stream, max_rows, start).await
+    }
+
     /// Build a TvpValue from column definitions and row data.
     ///
     /// # Arguments
@@ -1601,6 +2339,35 @@ impl QueryExecutor {
             rows: tvp_rows,
         })
     }
+
+    /// Build a TvpValue from a table type's definition and JSON rows.
+    ///
+    /// Rows may be arrays in column order or objects keyed by column name;
+    /// values are coerced to the column types, and errors name the offending
+    /// row and column.
+    pub fn build_typed_tvp(
+        type_name: &str,
+        columns: &[TableTypeColumn],
+        rows: &[serde_json::Value],
+    ) -> Result<TvpValue, ServerError> {
+        let tvp_columns = columns
+            .iter()
+            .enumerate()
+            .map(|(ordinal, c)| TvpColumn::new(c.name.clone(), c.sql_type(), ordinal))
+            .collect();
+
+        let tvp_rows = coerce_rows(columns, rows)
+            .map_err(ServerError::validation)?
+            .into_iter()
+            .map(TvpRow::new)
+            .collect();
+
+        Ok(TvpValue {
+            type_name: type_name.to_string(),
+            columns: tvp_columns,
+            rows: tvp_rows,
+        })
+    }
 }
 
 /// Convert a JSON value to a SQL value for TVP parameters.
@@ -1753,6 +2520,13 @@ mod tests {
         assert!(row.get("missing").is_none());
     }
 
+    #[tokio::test]
+    async fn test_read_intent_scope() {
+        assert!(!read_intent());
+        assert!(with_read_intent(true, async { read_intent() }).await);
+        assert!(!with_read_intent(false, async { read_intent() }).await);
+    }
+
     #[test]
     fn test_query_result_empty() {
         let result = QueryResult::empty();
@@ -1926,6 +2700,8 @@ mod tests {
             rows_affected: 0,
             execution_time_ms: 10,
             truncated: false,
+            retry: None,
+            messages: Vec::new(),
         };
 
         let multi = MultiQueryResult::single(result);
@@ -1950,6 +2726,8 @@ mod tests {
             rows_affected: 0,
             execution_time_ms: 5,
             truncated: false,
+            retry: None,
+            messages: Vec::new(),
         };
 
         let result2 = QueryResult {
@@ -1966,6 +2744,8 @@ mod tests {
             rows_affected: 0,
             execution_time_ms: 5,
             truncated: false,
+            retry: None,
+            messages: Vec::new(),
         };
 
         let multi = MultiQueryResult {
@@ -1983,6 +2763,46 @@ mod tests {
         assert!(markdown.contains("| b |"));
     }
 
+    #[test]
+    fn test_procedure_result_formatting() {
+        let non_query = ProcedureResult {
+            result_sets: vec![],
+            rows_affected: 3,
+            return_value: Some(0),
+            execution_time_ms: 4,
+        };
+        let markdown = non_query.to_markdown_table();
+        assert!(markdown.contains("3 row(s) affected"));
+        assert!(markdown.contains("Return value: 0"));
+
+        let result_set = |name: &str| QueryResult {
+            columns: vec![ColumnInfo {
+                name: name.to_string(),
+                sql_type: "INT".to_string(),
+                nullable: false,
+            }],
+            rows: vec![],
+            rows_affected: 0,
+            execution_time_ms: 1,
+            truncated: false,
+            retry: None,
+            messages: Vec::new(),
+        };
+        let multi = ProcedureResult {
+            result_sets: vec![result_set("a"), result_set("b")],
+            rows_affected: 0,
+            return_value: None,
+            execution_time_ms: 2,
+        };
+        let markdown = multi.to_markdown_table();
+        assert!(markdown.contains("Result Set 2 of 2"));
+        assert!(!markdown.contains("Return value"));
+
+        let json = serde_json::to_value(&multi).unwrap();
+        assert_eq!(json["result_sets"].as_array().unwrap().len(), 2);
+        assert_eq!(json["result_sets"][1]["columns"][0]["name"], "b");
+    }
+
     #[test]
     fn test_extract_line_number() {
         // Standard SQL Server error format
@@ -0,0 +1,581 @@
+//! Query result watches that re-execute a query and report changes.
+//!
+//! A query watch registers a read-only query and a poll interval. A background
+//! poller re-executes the query, hashes the result, and when the hash changes
+//! queues a change record listing the rows that were added, changed or removed
+//! since the previous poll. Rows are matched by key columns when given, and by
+//! their full contents otherwise (in which case updates show up as a removed
+//! row plus an added row).
+//!
+//! Watches created with notifications enabled also publish their resource URI
+//! on a broadcast channel so the transport can send `resources/updated`.
+
+use crate::constants::{
+    MAX_QUERY_WATCHES, MAX_QUERY_WATCH_CHANGE_ROWS, MAX_QUERY_WATCH_PENDING_CHANGES,
+    MIN_WATCH_POLL_INTERVAL_SECS,
+};
+use crate::database::query::{QueryExecutor, QueryResult, ResultRow};
+use crate::database::watch::use_prefix;
+use crate::error::ServerError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// URI prefix of query watch resources.
+pub const QUERY_WATCH_URI_PREFIX: &str = "mssql://query-watches/";
+
+/// Build the resource URI of a query watch.
+pub fn query_watch_uri(watch_id: &str) -> String {
+    format!("{}{}", QUERY_WATCH_URI_PREFIX, watch_id)
+}
+
+/// Parameters for registering a query watch.
+#[derive(Debug, Clone)]
+pub struct QueryWatchSpec {
+    /// Read-only query to re-execute.
+    pub query: String,
+    /// Columns identifying a row (empty = compare whole rows).
+    pub key_columns: Vec<String>,
+    /// Database to run the query in (`None` = connection default).
+    pub database: Option<String>,
+    /// Interval between polls.
+    pub poll_interval: Duration,
+    /// Publish a notification whenever the result changes.
+    pub notify: bool,
+}
+
+/// Public status of a query watch.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryWatchInfo {
+    /// Watch ID.
+    pub id: String,
+    /// Watched query.
+    pub query: String,
+    /// Columns identifying a row.
+    pub key_columns: Vec<String>,
+    /// Database the query runs in.
+    pub database: Option<String>,
+    /// Resource URI of the watch.
+    pub uri: String,
+    /// Interval between polls in seconds.
+    pub poll_interval_secs: u64,
+    /// Whether changes are published as notifications.
+    pub notify: bool,
+    /// Hash of the most recent result.
+    pub result_hash: Option<String>,
+    /// Row count of the most recent result.
+    pub row_count: usize,
+    /// When the watch was created.
+    pub created_at: DateTime<Utc>,
+    /// When the query was last executed.
+    pub last_polled_at: Option<DateTime<Utc>>,
+    /// When the result last changed.
+    pub last_changed_at: Option<DateTime<Utc>>,
+    /// Number of polls performed.
+    pub poll_count: u64,
+    /// Number of result changes detected.
+    pub change_count: u64,
+    /// Change records dropped because the queue was full.
+    pub changes_dropped: u64,
+    /// Change records waiting to be retrieved.
+    pub pending_changes: usize,
+    /// Error from the most recent poll, if it failed.
+    pub last_error: Option<String>,
+}
+
+/// A detected change in a watched query's result.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryChange {
+    /// When the change was detected.
+    pub detected_at: DateTime<Utc>,
+    /// Hash of the previous result.
+    pub previous_hash: String,
+    /// Hash of the new result.
+    pub result_hash: String,
+    /// Row count of the new result.
+    pub row_count: usize,
+    /// Number of added rows.
+    pub added_count: usize,
+    /// Number of changed rows (keyed watches only).
+    pub changed_count: usize,
+    /// Number of removed rows.
+    pub removed_count: usize,
+    /// Added rows.
+    pub added: Vec<ResultRow>,
+    /// Changed rows, with their new values.
+    pub changed: Vec<ResultRow>,
+    /// Removed rows, with their last known values.
+    pub removed: Vec<ResultRow>,
+    /// Whether the row lists were truncated.
+    pub truncated: bool,
+}
+
+/// Status and queued changes of a query watch.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryWatchStatus {
+    /// Watch status after retrieval.
+    pub watch: QueryWatchInfo,
+    /// Changes detected since the last retrieval, oldest first.
+    pub changes: Vec<QueryChange>,
+}
+
+/// Internal watch state.
+struct QueryWatch {
+    info: QueryWatchInfo,
+    /// Rows of the most recent result.
+    rows: Vec<ResultRow>,
+    pending: VecDeque<QueryChange>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Manager for query watches and their background pollers.
+pub struct QueryWatchManager {
+    /// Executor used for polling queries.
+    executor: Arc<QueryExecutor>,
+
+    /// Active watches keyed by watch ID.
+    watches: Arc<Mutex<HashMap<String, QueryWatch>>>,
+
+    /// Resource URIs of watches whose result changed.
+    updates: broadcast::Sender<String>,
+}
+
+impl QueryWatchManager {
+    /// Create a new query watch manager.
+    pub fn new(executor: Arc<QueryExecutor>) -> Self {
+        let (updates, _) = broadcast::channel(MAX_QUERY_WATCH_PENDING_CHANGES);
+        Self {
+            executor,
+            watches: Arc::new(Mutex::new(HashMap::new())),
+            updates,
+        }
+    }
+
+    /// Subscribe to the resource URIs of watches whose result changed.
+    pub fn updates(&self) -> broadcast::Receiver<String> {
+        self.updates.subscribe()
+    }
+
+    /// Register a watch, run the query once as a baseline, and start its poller.
+    pub async fn start(&self, spec: QueryWatchSpec) -> Result<QueryWatchInfo, ServerError> {
+        if self.watches.lock().await.len() >= MAX_QUERY_WATCHES {
+            return Err(ServerError::invalid_input(format!(
+                "Maximum number of query watches ({}) reached",
+                MAX_QUERY_WATCHES
+            )));
+        }
+
+        let result = self
+            .executor
+            .execute(&watch_query(&spec.query, spec.database.as_deref()))
+            .await?;
+        let columns = column_names(&result);
+        let key_columns = resolve_key_columns(&spec.key_columns, &columns)?;
+
+        let poll_interval = spec
+            .poll_interval
+            .max(Duration::from_secs(MIN_WATCH_POLL_INTERVAL_SECS));
+        let id = uuid::Uuid::new_v4().to_string();
+        let info = QueryWatchInfo {
+            uri: query_watch_uri(&id),
+            id: id.clone(),
+            query: spec.query,
+            key_columns,
+            database: spec.database,
+            poll_interval_secs: poll_interval.as_secs(),
+            notify: spec.notify,
+            result_hash: Some(result_hash(&columns, &result.rows)),
+            row_count: result.rows.len(),
+            created_at: Utc::now(),
+            last_polled_at: Some(Utc::now()),
+            last_changed_at: None,
+            poll_count: 0,
+            change_count: 0,
+            changes_dropped: 0,
+            pending_changes: 0,
+            last_error: None,
+        };
+
+        let handle = tokio::spawn(poll_loop(
+            id.clone(),
+            poll_interval,
+            Arc::clone(&self.executor),
+            Arc::clone(&self.watches),
+            self.updates.clone(),
+        ));
+
+        self.watches.lock().await.insert(
+            id,
+            QueryWatch {
+                info: info.clone(),
+                rows: result.rows,
+                pending: VecDeque::new(),
+                handle: Some(handle),
+            },
+        );
+
+        Ok(info)
+    }
+
+    /// Get a watch's status, optionally taking its queued changes.
+    pub async fn status(
+        &self,
+        watch_id: &str,
+        take_changes: bool,
+    ) -> Result<QueryWatchStatus, ServerError> {
+        let mut watches = self.watches.lock().await;
+        let watch = watches
+            .get_mut(watch_id)
+            .ok_or_else(|| ServerError::object_not_found("query watch", watch_id))?;
+
+        let changes = if take_changes {
+            watch.pending.drain(..).collect()
+        } else {
+            watch.pending.iter().cloned().collect()
+        };
+        watch.info.pending_changes = watch.pending.len();
+
+        Ok(QueryWatchStatus {
+            watch: watch.info.clone(),
+            changes,
+        })
+    }
+
+    /// List all active query watches.
+    pub async fn list(&self) -> Vec<QueryWatchInfo> {
+        let watches = self.watches.lock().await;
+        let mut list: Vec<QueryWatchInfo> = watches.values().map(|w| w.info.clone()).collect();
+        list.sort_by_key(|w| w.created_at);
+        list
+    }
+
+    /// Stop a watch and discard its queued changes.
+    pub async fn stop(&self, watch_id: &str) -> Result<QueryWatchInfo, ServerError> {
+        let watch = self
+            .watches
+            .lock()
+            .await
+            .remove(watch_id)
+            .ok_or_else(|| ServerError::object_not_found("query watch", watch_id))?;
+
+        if let Some(handle) = watch.handle {
+            handle.abort();
+        }
+        Ok(watch.info)
+    }
+
+    /// Stop all watches (used during shutdown).
+    pub async fn stop_all(&self) -> usize {
+        let mut watches = self.watches.lock().await;
+        let count = watches.len();
+        for (_, watch) in watches.drain() {
+            if let Some(handle) = watch.handle {
+                handle.abort();
+            }
+        }
+        count
+    }
+}
+
+/// Background poller for a single query watch. Exits when the watch is removed.
+async fn poll_loop(
+    id: String,
+    interval: Duration,
+    executor: Arc<QueryExecutor>,
+    watches: Arc<Mutex<HashMap<String, QueryWatch>>>,
+    updates: broadcast::Sender<String>,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let query = {
+            let watches = watches.lock().await;
+            let Some(watch) = watches.get(&id) else {
+                break;
+            };
+            watch_query(&watch.info.query, watch.info.database.as_deref())
+        };
+
+        let outcome = executor.execute(&query).await;
+
+        let mut watches = watches.lock().await;
+        let Some(watch) = watches.get_mut(&id) else {
+            break;
+        };
+        watch.info.poll_count += 1;
+        watch.info.last_polled_at = Some(Utc::now());
+
+        let result = match outcome {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Query watch {} poll failed: {}", id, e);
+                watch.info.last_error = Some(e.to_string());
+                continue;
+            }
+        };
+        watch.info.last_error = None;
+
+        let columns = column_names(&result);
+        let hash = result_hash(&columns, &result.rows);
+        let previous_hash = watch.info.result_hash.replace(hash.clone());
+        watch.info.row_count = result.rows.len();
+        if previous_hash.as_deref() == Some(hash.as_str()) {
+            continue;
+        }
+
+        let diff = RowDiff::compute(&columns, &watch.info.key_columns, &watch.rows, &result.rows);
+        let change = diff.into_change(previous_hash.unwrap_or_default(), hash, result.rows.len());
+        debug!(
+            "Query watch {} changed: {} added, {} changed, {} removed",
+            id, change.added_count, change.changed_count, change.removed_count
+        );
+
+        watch.rows = result.rows;
+        watch.info.change_count += 1;
+        watch.info.last_changed_at = Some(change.detected_at);
+        watch.pending.push_back(change);
+        while watch.pending.len() > MAX_QUERY_WATCH_PENDING_CHANGES {
+            watch.pending.pop_front();
+            watch.info.changes_dropped += 1;
+        }
+        watch.info.pending_changes = watch.pending.len();
+
+        if watch.info.notify {
+            // No receivers just means no transport is listening
+            let _ = updates.send(watch.info.uri.clone());
+        }
+    }
+}
+
+/// Build the query executed for a watch.
+fn watch_query(query: &str, database: Option<&str>) -> String {
+    format!("{}{}", use_prefix(database), query)
+}
+
+/// Column names of a result, in order.
+fn column_names(result: &QueryResult) -> Vec<String> {
+    result.columns.iter().map(|c| c.name.clone()).collect()
+}
+
+/// Match requested key columns against the result columns (case-insensitive).
+fn resolve_key_columns(
+    requested: &[String],
+    columns: &[String],
+) -> Result<Vec<String>, ServerError> {
+    requested
+        .iter()
+        .map(|key| {
+            columns
+                .iter()
+                .find(|c| c.eq_ignore_ascii_case(key))
+                .cloned()
+                .ok_or_else(|| {
+                    ServerError::invalid_input(format!(
+                        "Key column '{}' is not in the query result (columns: {})",
+                        key,
+                        columns.join(", ")
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Hash the given columns of a row.
+fn row_hash(columns: &[String], row: &ResultRow) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for column in columns {
+        row.get(column)
+            .filter(|v| !v.is_null())
+            .map(|v| v.to_display_string())
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Hash a whole result, including its column names and row order.
+fn result_hash(columns: &[String], rows: &[ResultRow]) -> String {
+    let mut hasher = DefaultHasher::new();
+    columns.hash(&mut hasher);
+    for row in rows {
+        row_hash(columns, row).hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Rows added, changed and removed between two results.
+#[derive(Debug, Default)]
+struct RowDiff {
+    added: Vec<ResultRow>,
+    changed: Vec<ResultRow>,
+    removed: Vec<ResultRow>,
+}
+
+impl RowDiff {
+    /// Compare two results, matching rows by key columns when given.
+    fn compute(
+        columns: &[String],
+        key_columns: &[String],
+        previous: &[ResultRow],
+        current: &[ResultRow],
+    ) -> Self {
+        if key_columns.is_empty() {
+            return Self::compute_unkeyed(columns, previous, current);
+        }
+
+        let mut previous_by_key: HashMap<u64, &ResultRow> = previous
+            .iter()
+            .map(|row| (row_hash(key_columns, row), row))
+            .collect();
+
+        let mut diff = Self::default();
+        for row in current {
+            match previous_by_key.remove(&row_hash(key_columns, row)) {
+                None => diff.added.push(row.clone()),
+                Some(old) if row_hash(columns, old) != row_hash(columns, row) => {
+                    diff.changed.push(row.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        // Keep removed rows in their original order
+        diff.removed = previous
+            .iter()
+            .filter(|row| previous_by_key.contains_key(&row_hash(key_columns, row)))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Compare two results as multisets of whole rows.
+    fn compute_unkeyed(columns: &[String], previous: &[ResultRow], current: &[ResultRow]) -> Self {
+        fn unmatched(
+            columns: &[String],
+            rows: &[ResultRow],
+            others: &[ResultRow],
+        ) -> Vec<ResultRow> {
+            let mut counts: HashMap<u64, usize> = HashMap::new();
+            for row in others {
+                *counts.entry(row_hash(columns, row)).or_default() += 1;
+            }
+            rows.iter()
+                .filter(|row| match counts.get_mut(&row_hash(columns, row)) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                })
+                .cloned()
+                .collect()
+        }
+
+        Self {
+            added: unmatched(columns, current, previous),
+            changed: Vec::new(),
+            removed: unmatched(columns, previous, current),
+        }
+    }
+
+    /// Turn the diff into a change record, truncating long row lists.
+    fn into_change(
+        self,
+        previous_hash: String,
+        result_hash: String,
+        row_count: usize,
+    ) -> QueryChange {
+        let (added_count, changed_count, removed_count) =
+            (self.added.len(), self.changed.len(), self.removed.len());
+        let truncated = [added_count, changed_count, removed_count]
+            .iter()
+            .any(|&n| n > MAX_QUERY_WATCH_CHANGE_ROWS);
+        let cap = |mut rows: Vec<ResultRow>| {
+            rows.truncate(MAX_QUERY_WATCH_CHANGE_ROWS);
+            rows
+        };
+
+        QueryChange {
+            detected_at: Utc::now(),
+            previous_hash,
+            result_hash,
+            row_count,
+            added_count,
+            changed_count,
+            removed_count,
+            added: cap(self.added),
+            changed: cap(self.changed),
+            removed: cap(self.removed),
+            truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn row(id: i32, status: &str) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert("Id".to_string(), SqlValue::I32(id));
+        row.insert("Status".to_string(), SqlValue::String(status.to_string()));
+        row
+    }
+
+    fn describe(rows: &[ResultRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| {
+                format!(
+                    "{}:{}",
+                    row.get("Id").unwrap().to_display_string(),
+                    row.get("Status").unwrap().to_display_string()
+                )
+            })
+            .collect()
+    }
+
+    fn columns() -> Vec<String> {
+        vec!["Id".to_string(), "Status".to_string()]
+    }
+
+    #[test]
+    fn test_keyed_diff() {
+        let previous = vec![row(1, "queued"), row(2, "queued"), row(3, "queued")];
+        let current = vec![row(1, "queued"), row(2, "running"), row(4, "queued")];
+
+        let diff = RowDiff::compute(&columns(), &["Id".to_string()], &previous, &current);
+        assert_eq!(describe(&diff.added), vec!["4:queued"]);
+        assert_eq!(describe(&diff.changed), vec!["2:running"]);
+        assert_eq!(describe(&diff.removed), vec!["3:queued"]);
+    }
+
+    #[test]
+    fn test_unkeyed_diff_and_hash() {
+        let previous = vec![row(1, "queued"), row(1, "queued"), row(2, "queued")];
+        let current = vec![row(1, "queued"), row(2, "running")];
+
+        let diff = RowDiff::compute(&columns(), &[], &previous, &current);
+        assert_eq!(describe(&diff.added), vec!["2:running"]);
+        assert!(diff.changed.is_empty());
+        assert_eq!(describe(&diff.removed), vec!["1:queued", "2:queued"]);
+
+        assert_eq!(
+            result_hash(&columns(), &previous),
+            result_hash(&columns(), &previous.clone())
+        );
+        assert_ne!(
+            result_hash(&columns(), &previous),
+            result_hash(&columns(), &current)
+        );
+
+        let keys = resolve_key_columns(&["id".to_string()], &columns()).unwrap();
+        assert_eq!(keys, vec!["Id".to_string()]);
+        assert!(resolve_key_columns(&["missing".to_string()], &columns()).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! Rendering of foreign key relationship graphs.
+//!
+//! The relationship resources return JSON by default; `?format=dot` renders
+//! a Graphviz digraph and `?format=mermaid` a Mermaid ER diagram.
+
+use crate::database::metadata::ForeignKeyRelationship;
+use crate::error::ServerError;
+use std::collections::BTreeSet;
+
+/// Output format of a relationship graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationshipFormat {
+    Json,
+    Dot,
+    Mermaid,
+}
+
+impl RelationshipFormat {
+    /// Read the `format` query parameter of a resource URI (default: JSON).
+    pub fn from_uri(uri: &str) -> Result<Self, ServerError> {
+        let query = uri.split_once('?').map(|(_, q)| q).unwrap_or("");
+        let format = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "format")
+            .map(|(_, value)| value.to_lowercase());
+
+        match format.as_deref() {
+            None | Some("json") => Ok(Self::Json),
+            Some("dot") | Some("graphviz") => Ok(Self::Dot),
+            Some("mermaid") => Ok(Self::Mermaid),
+            Some(other) => Err(ServerError::invalid_input(format!(
+                "Unknown relationship format '{}' (expected json, dot or mermaid)",
+                other
+            ))),
+        }
+    }
+
+    /// MIME type of the rendered graph.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Dot => "text/vnd.graphviz",
+            Self::Mermaid => "text/vnd.mermaid",
+        }
+    }
+}
+
+/// Strip the query string from a resource URI.
+pub fn strip_query(uri: &str) -> &str {
+    uri.split_once('?').map(|(path, _)| path).unwrap_or(uri)
+}
+
+/// Build the join condition between the referencing and referenced table.
+pub fn join_condition(fk: &ForeignKeyRelationship) -> String {
+    fk.parent_columns
+        .iter()
+        .zip(&fk.referenced_columns)
+        .map(|(parent, referenced)| {
+            format!(
+                "[{}].[{}].[{}] = [{}].[{}].[{}]",
+                fk.parent_schema,
+                fk.parent_table,
+                parent,
+                fk.referenced_schema,
+                fk.referenced_table,
+                referenced
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Render relationships as a Graphviz digraph (edges point from child to parent).
+pub fn to_dot(relationships: &[ForeignKeyRelationship]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+
+    let mut lines = vec![
+        "digraph relationships {".to_string(),
+        "  rankdir=LR;".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+    for table in tables(relationships) {
+        lines.push(format!("  \"{}\";", escape(&table)));
+    }
+    for fk in relationships {
+        let mut label = format!(
+            "{}\\n{} -> {}",
+            escape(&fk.constraint_name),
+            escape(&fk.parent_columns.join(", ")),
+            escape(&fk.referenced_columns.join(", "))
+        );
+        if fk.on_delete != "NO_ACTION" {
+            label.push_str(&format!("\\nON DELETE {}", fk.on_delete));
+        }
+        if fk.on_update != "NO_ACTION" {
+            label.push_str(&format!("\\nON UPDATE {}", fk.on_update));
+        }
+        let style = if fk.is_disabled { ", style=dashed" } else { "" };
+        lines.push(format!(
+            "  \"{}.{}\" -> \"{}.{}\" [label=\"{}\"{}];",
+            escape(&fk.parent_schema),
+            escape(&fk.parent_table),
+            escape(&fk.referenced_schema),
+            escape(&fk.referenced_table),
+            label,
+            style
+        ));
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+/// Render relationships as a Mermaid ER diagram.
+pub fn to_mermaid(relationships: &[ForeignKeyRelationship]) -> String {
+    let mut lines = vec!["erDiagram".to_string()];
+    for fk in relationships {
+        lines.push(format!(
+            "    {} ||--o{{ {} : \"{}\"",
+            mermaid_entity(&fk.referenced_schema, &fk.referenced_table),
+            mermaid_entity(&fk.parent_schema, &fk.parent_table),
+            fk.parent_columns.join(", ").replace('"', "'")
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Qualified names of all tables taking part in the relationships.
+fn tables(relationships: &[ForeignKeyRelationship]) -> BTreeSet<String> {
+    relationships
+        .iter()
+        .flat_map(|fk| {
+            [
+                format!("{}.{}", fk.parent_schema, fk.parent_table),
+                format!("{}.{}", fk.referenced_schema, fk.referenced_table),
+            ]
+        })
+        .collect()
+}
+
+/// Mermaid entity names allow only alphanumerics, `_` and `-`.
+fn mermaid_entity(schema: &str, table: &str) -> String {
+    format!("{}_{}", schema, table)
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orders_fk() -> ForeignKeyRelationship {
+        ForeignKeyRelationship {
+            constraint_name: "FK_Orders_Customers".to_string(),
+            parent_schema: "dbo".to_string(),
+            parent_table: "Orders".to_string(),
+            parent_columns: vec!["CustomerId".to_string()],
+            referenced_schema: "dbo".to_string(),
+            referenced_table: "Customers".to_string(),
+            referenced_columns: vec!["Id".to_string()],
+            on_delete: "CASCADE".to_string(),
+            on_update: "NO_ACTION".to_string(),
+            is_disabled: false,
+            is_not_trusted: false,
+        }
+    }
+
+    #[test]
+    fn test_format_from_uri() {
+        assert_eq!(
+            RelationshipFormat::from_uri("mssql://relationships").unwrap(),
+            RelationshipFormat::Json
+        );
+        assert_eq!(
+            RelationshipFormat::from_uri("mssql://relationships?format=Mermaid").unwrap(),
+            RelationshipFormat::Mermaid
+        );
+        assert_eq!(
+            RelationshipFormat::from_uri("mssql://tables/dbo/Orders/relationships?format=dot")
+                .unwrap(),
+            RelationshipFormat::Dot
+        );
+        assert!(RelationshipFormat::from_uri("mssql://relationships?format=svg").is_err());
+        assert_eq!(
+            strip_query("mssql://relationships?format=dot"),
+            "mssql://relationships"
+        );
+    }
+
+    #[test]
+    fn test_render_graph() {
+        let fks = vec![orders_fk()];
+
+        assert_eq!(
+            join_condition(&fks[0]),
+            "[dbo].[Orders].[CustomerId] = [dbo].[Customers].[Id]"
+        );
+
+        let dot = to_dot(&fks);
+        assert!(dot.contains(
+            "\"dbo.Orders\" -> \"dbo.Customers\" [label=\"FK_Orders_Customers\\nCustomerId -> Id\\nON DELETE CASCADE\"];"
+        ));
+
+        assert_eq!(
+            to_mermaid(&fks),
+            "erDiagram\n    dbo_Customers ||--o{ dbo_Orders : \"CustomerId\""
+        );
+    }
+}
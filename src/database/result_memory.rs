@@ -0,0 +1,184 @@
+//! Memory budgets for result sets.
+//!
+//! Rows read from SQL Server are held in memory until the result is returned.
+//! Every query charges the approximate size of its rows to a per-query budget
+//! and to a server-wide budget shared by all queries in flight, so a single
+//! large SELECT fails with a clear error instead of exhausting process memory.
+//! Tools that accept partial results run their queries inside
+//! [`with_partial_results`]; over-budget queries then stop reading and return
+//! the rows read so far, marked as truncated.
+
+use crate::cache::estimate_row_size;
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::warn;
+
+/// Bytes a single query may accumulate (0 = unlimited).
+static QUERY_BUDGET_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes all queries in flight may accumulate together (0 = unlimited).
+static SERVER_BUDGET_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes currently held by queries in flight.
+static BYTES_IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+tokio::task_local! {
+    /// Whether over-budget queries return the rows read so far.
+    static PARTIAL_RESULTS: bool;
+}
+
+/// Set the per-query and server-wide result memory budgets in bytes; 0 disables a budget.
+pub fn set_result_memory_budgets(query_bytes: usize, server_bytes: usize) {
+    QUERY_BUDGET_BYTES.store(query_bytes, Ordering::Relaxed);
+    SERVER_BUDGET_BYTES.store(server_bytes, Ordering::Relaxed);
+}
+
+/// Bytes of rows currently held by queries in flight.
+pub fn result_bytes_in_use() -> usize {
+    BYTES_IN_USE.load(Ordering::Relaxed)
+}
+
+/// Run a future in which over-budget queries return partial results if `allow` is set.
+pub async fn with_partial_results<F: Future>(allow: bool, future: F) -> F::Output {
+    PARTIAL_RESULTS.scope(allow, future).await
+}
+
+/// Whether the current task accepts partial results.
+fn partial_results_allowed() -> bool {
+    PARTIAL_RESULTS.try_with(|allow| *allow).unwrap_or(false)
+}
+
+/// Rows accumulated by one query, charged against the memory budgets.
+///
+/// The charge is released when the value is dropped.
+#[derive(Debug, Default)]
+pub struct ResultMemory {
+    /// Bytes charged so far.
+    bytes: usize,
+    /// Rows admitted so far.
+    rows: usize,
+    /// Why reading stopped early, once a budget was exceeded with partial results allowed.
+    stopped: Option<String>,
+}
+
+impl ResultMemory {
+    /// Start tracking a query's rows.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charge a row against the budgets.
+    ///
+    /// Returns `Ok(true)` if the row fits. Over budget, this fails unless
+    /// partial results are allowed, in which case it returns `Ok(false)` for
+    /// this and every later row.
+    pub fn admit(&mut self, row: &ResultRow) -> Result<bool, ServerError> {
+        if self.stopped.is_some() {
+            return Ok(false);
+        }
+        match self.charge(estimate_row_size(row)) {
+            Ok(()) => {
+                self.rows += 1;
+                Ok(true)
+            }
+            Err(reason) if partial_results_allowed() => {
+                warn!("Returning partial result: {}", reason);
+                self.stopped = Some(reason);
+                Ok(false)
+            }
+            Err(reason) => Err(ServerError::memory_budget(format!(
+                "{}. Select fewer rows or columns, or pass allow_partial to return the rows read so far",
+                reason
+            ))),
+        }
+    }
+
+    /// Note to return with a partial result, if reading stopped early.
+    pub fn partial_note(&self) -> Option<String> {
+        self.stopped
+            .as_ref()
+            .map(|reason| format!("Partial result: {}", reason))
+    }
+
+    /// Charge `bytes` against both budgets.
+    fn charge(&mut self, bytes: usize) -> Result<(), String> {
+        let query_budget = QUERY_BUDGET_BYTES.load(Ordering::Relaxed);
+        if query_budget > 0 && self.bytes + bytes > query_budget {
+            return Err(format!(
+                "result exceeded the {} per-query memory budget after {} rows",
+                format_budget(query_budget),
+                self.rows
+            ));
+        }
+
+        let in_use = BYTES_IN_USE.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let server_budget = SERVER_BUDGET_BYTES.load(Ordering::Relaxed);
+        if server_budget > 0 && in_use > server_budget {
+            BYTES_IN_USE.fetch_sub(bytes, Ordering::Relaxed);
+            return Err(format!(
+                "results of queries in flight exceeded the {} server memory budget after {} rows",
+                format_budget(server_budget),
+                self.rows
+            ));
+        }
+        self.bytes += bytes;
+        Ok(())
+    }
+}
+
+impl Drop for ResultMemory {
+    fn drop(&mut self) {
+        BYTES_IN_USE.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+/// Format a budget in MB, or bytes if below 1 MB.
+fn format_budget(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{} MB", bytes / (1024 * 1024))
+    } else {
+        format!("{} byte", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn row(text: &str) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert("name".to_string(), SqlValue::String(text.to_string()));
+        row
+    }
+
+    #[tokio::test]
+    async fn test_query_budget() {
+        // The budgets are global; only the per-query one is set so other tests are unaffected
+        set_result_memory_budgets(100, 0);
+
+        let mut memory = ResultMemory::new();
+        assert!(memory.admit(&row(&"x".repeat(40))).unwrap());
+        let error = memory.admit(&row(&"x".repeat(80))).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("per-query memory budget after 1 rows"));
+
+        let (admitted, note) = with_partial_results(true, async {
+            let mut memory = ResultMemory::new();
+            let admitted = [
+                memory.admit(&row(&"x".repeat(40))).unwrap(),
+                memory.admit(&row(&"x".repeat(80))).unwrap(),
+                memory.admit(&row("x")).unwrap(),
+            ];
+            (admitted, memory.partial_note())
+        })
+        .await;
+        assert_eq!(admitted, [true, false, false]);
+        assert!(note.unwrap().starts_with("Partial result:"));
+
+        set_result_memory_budgets(0, 0);
+    }
+}
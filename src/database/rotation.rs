@@ -0,0 +1,275 @@
+//! Credential rotation without restarting the server.
+//!
+//! The driver pool keeps the login it was created with, so new credentials
+//! take effect by building a new pool for every endpoint and swapping it in
+//! (see [`ConnectionPool::swap`]). The pools are only swapped once every new
+//! one has logged in, so all endpoints use the same credentials. Requests that
+//! hold a connection of an old pool finish on it; the connection is closed
+//! when returned.
+
+use crate::config::{AuthConfig, DatabaseConfig};
+use crate::database::auth::create_config;
+use crate::database::ConnectionPool;
+use crate::error::ServerError;
+use chrono::{DateTime, Utc};
+use mssql_driver_pool::Pool;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Rotations performed so far, for `get_pool_metrics`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RotationStats {
+    /// Successful rotations.
+    pub rotations: u64,
+    /// Rotations that failed, leaving the previous credentials in use.
+    pub failures: u64,
+    /// When the credentials were last rotated.
+    pub last_rotated: Option<DateTime<Utc>>,
+    /// Error of the last failed rotation.
+    pub last_error: Option<String>,
+}
+
+/// Result of a rotation.
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationOutcome {
+    /// Pools re-established with the new credentials.
+    pub pools_replaced: usize,
+    /// Connections of the old pools still in use; closed when their work finishes.
+    pub connections_draining: u32,
+}
+
+/// Re-establishes the pools when the database credentials change.
+pub struct CredentialRotation {
+    /// Each pool with the configuration of the endpoint it connects to.
+    endpoints: Mutex<Vec<(Arc<ConnectionPool>, DatabaseConfig)>>,
+    /// Serializes rotations, so two callers never build pools at once.
+    rotating: tokio::sync::Mutex<()>,
+    stats: Mutex<RotationStats>,
+}
+
+impl CredentialRotation {
+    /// Create rotation for `pools`, each with the configuration it was created from.
+    pub fn new(endpoints: Vec<(Arc<ConnectionPool>, DatabaseConfig)>) -> Self {
+        Self {
+            endpoints: Mutex::new(endpoints),
+            rotating: tokio::sync::Mutex::new(()),
+            stats: Mutex::new(RotationStats::default()),
+        }
+    }
+
+    /// Whether the pools already connect with `auth`.
+    pub fn is_current(&self, auth: &AuthConfig) -> bool {
+        self.endpoints
+            .lock()
+            .iter()
+            .all(|(_, config)| config.auth == *auth)
+    }
+
+    /// Re-establish every pool with `auth`.
+    ///
+    /// A new pool is built and logged in for every endpoint before any pool is
+    /// replaced, so when a login fails the current credentials stay in use
+    /// everywhere. `on_swap` runs right after the pools are swapped, before any
+    /// await, so state that depends on the credentials changes along with them.
+    pub async fn rotate(
+        &self,
+        auth: &AuthConfig,
+        on_swap: impl FnOnce(),
+    ) -> Result<RotationOutcome, ServerError> {
+        self.rotate_with(
+            auth,
+            |pool, config| async move {
+                let client_config = create_config(&config).await?;
+                pool.connect_replacement(client_config).await
+            },
+            on_swap,
+        )
+        .await
+    }
+
+    /// [`rotate`](Self::rotate), building each new pool with `connect`.
+    async fn rotate_with<F, Fut>(
+        &self,
+        auth: &AuthConfig,
+        connect: F,
+        on_swap: impl FnOnce(),
+    ) -> Result<RotationOutcome, ServerError>
+    where
+        F: Fn(Arc<ConnectionPool>, DatabaseConfig) -> Fut,
+        Fut: Future<Output = Result<Pool, ServerError>>,
+    {
+        let _rotating = self.rotating.lock().await;
+        let endpoints = self.endpoints.lock().clone();
+
+        let mut connected = Vec::with_capacity(endpoints.len());
+        for (pool, config) in &endpoints {
+            let mut rotated = config.clone();
+            rotated.auth = auth.clone();
+            match connect(Arc::clone(pool), rotated.clone()).await {
+                Ok(new_pool) => connected.push((new_pool, rotated)),
+                Err(e) => {
+                    warn!(
+                        "Credential rotation failed for {}:{}: {}",
+                        config.host, config.port, e
+                    );
+                    for (new_pool, _) in connected {
+                        new_pool.close().await;
+                    }
+                    let mut stats = self.stats.lock();
+                    stats.failures += 1;
+                    stats.last_error = Some(e.to_string());
+                    return Err(ServerError::auth(format!(
+                        "Failed to connect to {}:{} with the new credentials, no pool was replaced: {}",
+                        config.host, config.port, e
+                    )));
+                }
+            }
+        }
+
+        // Rotations are serialized, so the endpoints are still those cloned above
+        let retired: Vec<_> = self
+            .endpoints
+            .lock()
+            .iter_mut()
+            .zip(connected)
+            .map(|((pool, config), (new_pool, rotated))| {
+                *config = rotated;
+                pool.swap(new_pool)
+            })
+            .collect();
+        on_swap();
+
+        let mut outcome = RotationOutcome {
+            pools_replaced: retired.len(),
+            connections_draining: 0,
+        };
+        for old in retired {
+            outcome.connections_draining += ConnectionPool::retire(old).await;
+        }
+
+        info!(
+            "Credentials rotated: {} pools replaced, {} connections draining",
+            outcome.pools_replaced, outcome.connections_draining
+        );
+        let mut stats = self.stats.lock();
+        stats.rotations += 1;
+        stats.last_rotated = Some(Utc::now());
+        Ok(outcome)
+    }
+
+    /// Rotations performed so far.
+    pub fn stats(&self) -> RotationStats {
+        self.stats.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PoolConfig, RetryConfig, TdsVersionConfig, TimeoutsConfig};
+
+    #[tokio::test]
+    async fn test_rotate_without_pools() {
+        let rotation = CredentialRotation::new(Vec::new());
+        let auth = AuthConfig::SqlServer {
+            username: "app".to_string(),
+            password: "rotated".to_string(),
+        };
+        assert!(rotation.is_current(&auth));
+
+        let outcome = rotation.rotate(&auth, || {}).await.unwrap();
+        assert_eq!(
+            (outcome.pools_replaced, outcome.connections_draining),
+            (0, 0)
+        );
+        let stats = rotation.stats();
+        assert_eq!((stats.rotations, stats.failures), (1, 0));
+        assert!(stats.last_rotated.is_some());
+    }
+
+    fn endpoint(host: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            host: host.to_string(),
+            port: 1433,
+            instance: None,
+            resolve_instance_port: false,
+            database: Some("master".to_string()),
+            auth: AuthConfig::SqlServer {
+                username: "app".to_string(),
+                password: "old".to_string(),
+            },
+            pool: PoolConfig::default(),
+            timeouts: TimeoutsConfig::default(),
+            encrypt: false,
+            trust_server_certificate: true,
+            application_name: "test".to_string(),
+            mars: false,
+            retry: RetryConfig::default(),
+            tds_version: TdsVersionConfig::default(),
+            application_intent: Default::default(),
+            multi_subnet_failover: false,
+            failover_partners: Vec::new(),
+            read_pool: false,
+            read_pool_host: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_login_replaces_no_pool() {
+        let primary = Arc::new(ConnectionPool::from_pool(
+            ConnectionPool::unconnected().await,
+        ));
+        let replica = Arc::new(ConnectionPool::from_pool(
+            ConnectionPool::unconnected().await,
+        ));
+        let before = (primary.current(), replica.current());
+        let old_auth = endpoint("primary").auth;
+        let rotation = CredentialRotation::new(vec![
+            (Arc::clone(&primary), endpoint("primary")),
+            (Arc::clone(&replica), endpoint("replica")),
+        ]);
+        let auth = AuthConfig::SqlServer {
+            username: "app".to_string(),
+            password: "rotated".to_string(),
+        };
+
+        // The primary logs in with the new credentials, the replica does not
+        let mut swapped = false;
+        let err = rotation
+            .rotate_with(
+                &auth,
+                |_, config| async move {
+                    match config.host.as_str() {
+                        "primary" => Ok(ConnectionPool::unconnected().await),
+                        _ => Err(ServerError::connection("Login failed")),
+                    }
+                },
+                || swapped = true,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("replica:1433"));
+        assert!(!swapped);
+        assert!(Arc::ptr_eq(&primary.current(), &before.0));
+        assert!(Arc::ptr_eq(&replica.current(), &before.1));
+        assert!(rotation.is_current(&old_auth));
+        assert_eq!(rotation.stats().failures, 1);
+
+        let outcome = rotation
+            .rotate_with(
+                &auth,
+                |_, _| async { Ok(ConnectionPool::unconnected().await) },
+                || swapped = true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.pools_replaced, 2);
+        assert!(swapped);
+        assert!(!Arc::ptr_eq(&primary.current(), &before.0));
+        assert!(before.0.is_closed() && before.1.is_closed());
+        assert!(rotation.is_current(&auth));
+    }
+}
@@ -0,0 +1,373 @@
+//! Keyword and regex search over schema metadata.
+//!
+//! Searches object names, column names, module definitions (views,
+//! procedures, functions, triggers) and extended properties, and ranks the
+//! matching objects so the best candidates come first. Keyword searches are
+//! filtered on the server with `LIKE`; regex searches fetch the metadata and
+//! filter locally.
+
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Places that can be searched.
+pub const SEARCH_SOURCES: &[&str] = &["names", "columns", "definitions", "extended_properties"];
+
+/// Characters of context shown on each side of a definition match.
+const SNIPPET_CONTEXT: usize = 60;
+
+/// What to look for: a case-insensitive keyword or regular expression.
+#[derive(Debug, Clone)]
+pub struct SearchPattern {
+    regex: Regex,
+    /// The keyword for plain searches, used to filter on the server.
+    keyword: Option<String>,
+}
+
+impl SearchPattern {
+    /// Build a pattern from user input.
+    pub fn new(pattern: &str, regex: bool) -> Result<Self, ServerError> {
+        if pattern.trim().is_empty() {
+            return Err(ServerError::invalid_input(
+                "Search pattern must not be empty",
+            ));
+        }
+        let source = if regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        let compiled = RegexBuilder::new(&source)
+            .case_insensitive(true)
+            .size_limit(1 << 20)
+            .build()
+            .map_err(|e| ServerError::invalid_input(format!("Invalid regex: {}", e)))?;
+        Ok(Self {
+            regex: compiled,
+            keyword: (!regex).then(|| pattern.to_string()),
+        })
+    }
+
+    /// The keyword of a plain (non-regex) search.
+    pub fn keyword(&self) -> Option<&str> {
+        self.keyword.as_deref()
+    }
+
+    /// Byte range of the first match in `text`.
+    fn find(&self, text: &str) -> Option<(usize, usize)> {
+        self.regex.find(text).map(|m| (m.start(), m.end()))
+    }
+
+    /// Number of non-overlapping matches in `text`.
+    fn count(&self, text: &str) -> usize {
+        self.regex.find_iter(text).count()
+    }
+
+    /// Whether the pattern matches all of `text`.
+    fn matches_whole(&self, text: &str) -> bool {
+        self.regex
+            .find(text)
+            .is_some_and(|m| m.start() == 0 && m.end() == text.len())
+    }
+}
+
+/// Build the metadata search query for the selected sources.
+///
+/// With a keyword, rows are filtered with `LIKE` on the server; regex
+/// searches pass `None` and filter the returned rows.
+pub fn search_query(sources: &[String], schema: Option<&str>, keyword: Option<&str>) -> String {
+    let object_types = "('U', 'V', 'P', 'PC', 'FN', 'IF', 'TF', 'FS', 'FT', 'TR', 'SN')";
+    let mut parts = Vec::new();
+
+    if sources.iter().any(|s| s == "names") {
+        parts.push(format!(
+            "SELECT s.name AS schema_name, o.name AS object_name, o.type_desc AS object_type, \
+             NULL AS column_name, 'name' AS source, o.name AS match_text \
+             FROM sys.objects o INNER JOIN sys.schemas s ON s.schema_id = o.schema_id \
+             WHERE o.is_ms_shipped = 0 AND o.type IN {}",
+            object_types
+        ));
+    }
+    if sources.iter().any(|s| s == "columns") {
+        parts.push(format!(
+            "SELECT s.name, o.name, o.type_desc, c.name, 'column', c.name \
+             FROM sys.columns c \
+             INNER JOIN sys.objects o ON o.object_id = c.object_id \
+             INNER JOIN sys.schemas s ON s.schema_id = o.schema_id \
+             WHERE o.is_ms_shipped = 0 AND o.type IN {}",
+            object_types
+        ));
+    }
+    if sources.iter().any(|s| s == "definitions") {
+        parts.push(
+            "SELECT s.name, o.name, o.type_desc, NULL, 'definition', m.definition \
+             FROM sys.sql_modules m \
+             INNER JOIN sys.objects o ON o.object_id = m.object_id \
+             INNER JOIN sys.schemas s ON s.schema_id = o.schema_id \
+             WHERE o.is_ms_shipped = 0"
+                .to_string(),
+        );
+    }
+    if sources.iter().any(|s| s == "extended_properties") {
+        parts.push(
+            "SELECT s.name, o.name, o.type_desc, c.name, 'extended_property', \
+             ep.name + N': ' + CAST(ep.value AS NVARCHAR(4000)) \
+             FROM sys.extended_properties ep \
+             INNER JOIN sys.objects o ON o.object_id = ep.major_id \
+             INNER JOIN sys.schemas s ON s.schema_id = o.schema_id \
+             LEFT JOIN sys.columns c ON c.object_id = ep.major_id AND c.column_id = ep.minor_id \
+             WHERE ep.class = 1 AND o.is_ms_shipped = 0"
+                .to_string(),
+        );
+    }
+
+    let mut filters = Vec::new();
+    if let Some(schema) = schema {
+        filters.push(format!("x.schema_name = N'{}'", schema.replace('\'', "''")));
+    }
+    if let Some(keyword) = keyword {
+        let escaped = keyword
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+            .replace('[', "\\[")
+            .replace('\'', "''");
+        filters.push(format!("x.match_text LIKE N'%{}%' ESCAPE '\\'", escaped));
+    }
+
+    format!(
+        "SELECT x.schema_name, x.object_name, x.object_type, x.column_name, x.source, x.match_text \
+         FROM ({}) x{}",
+        parts.join(" UNION ALL "),
+        if filters.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", filters.join(" AND "))
+        }
+    )
+}
+
+/// One place where the pattern was found.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// name, column, definition or extended_property.
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    /// Matching text, or an excerpt around the first match in a definition.
+    pub snippet: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurrences: Option<usize>,
+    #[serde(skip)]
+    score: u32,
+}
+
+/// An object with all its matches, ranked by score.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaMatch {
+    pub schema: String,
+    pub object: String,
+    pub object_type: String,
+    pub score: u32,
+    pub matches: Vec<SearchHit>,
+}
+
+/// Match the rows of [`search_query`] and rank the matching objects.
+pub fn rank_matches(rows: &[ResultRow], pattern: &SearchPattern) -> Vec<SchemaMatch> {
+    let mut objects: HashMap<(String, String), SchemaMatch> = HashMap::new();
+
+    for row in rows {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let (Some(schema), Some(object), Some(source), Some(match_text)) = (
+            text("schema_name"),
+            text("object_name"),
+            text("source"),
+            text("match_text"),
+        ) else {
+            continue;
+        };
+        let Some(hit) = score_hit(&source, text("column_name"), &match_text, pattern) else {
+            continue;
+        };
+
+        let entry = objects
+            .entry((schema.clone(), object.clone()))
+            .or_insert_with(|| SchemaMatch {
+                schema,
+                object,
+                object_type: text("object_type").unwrap_or_default(),
+                score: 0,
+                matches: Vec::new(),
+            });
+        entry.score += hit.score;
+        entry.matches.push(hit);
+    }
+
+    let mut matches: Vec<SchemaMatch> = objects.into_values().collect();
+    for m in &mut matches {
+        m.matches.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    }
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.schema.cmp(&b.schema))
+            .then_with(|| a.object.cmp(&b.object))
+    });
+    matches
+}
+
+/// Score a single match; exact name matches rank highest, definitions lowest.
+fn score_hit(
+    source: &str,
+    column: Option<String>,
+    text: &str,
+    pattern: &SearchPattern,
+) -> Option<SearchHit> {
+    let (start, end) = pattern.find(text)?;
+    let whole = pattern.matches_whole(text);
+
+    let (score, snippet, line, occurrences) = match source {
+        "name" => {
+            let score = if whole {
+                100
+            } else if start == 0 && end > 0 {
+                75
+            } else {
+                50
+            };
+            (score, text.to_string(), None, None)
+        }
+        "column" => (if whole { 60 } else { 40 }, text.to_string(), None, None),
+        "definition" => {
+            let occurrences = pattern.count(text).max(1);
+            let line = text[..start].matches('\n').count() + 1;
+            (
+                10 + occurrences.min(10) as u32,
+                excerpt(text, start, end),
+                Some(line),
+                Some(occurrences),
+            )
+        }
+        _ => (30, excerpt(text, start, end), None, None),
+    };
+
+    Some(SearchHit {
+        source: source.to_string(),
+        column,
+        snippet,
+        line,
+        occurrences,
+        score,
+    })
+}
+
+/// Excerpt of `text` around a match, on one line.
+fn excerpt(text: &str, start: usize, end: usize) -> String {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(to) {
+        to += 1;
+    }
+
+    let body = text[from..to]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{}{}{}",
+        if from > 0 { "..." } else { "" },
+        body,
+        if to < text.len() { "..." } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn row(
+        schema: &str,
+        object: &str,
+        column: Option<&str>,
+        source: &str,
+        text: &str,
+    ) -> ResultRow {
+        let mut row = ResultRow::new();
+        let value = |s: &str| SqlValue::String(s.to_string());
+        row.insert("schema_name".to_string(), value(schema));
+        row.insert("object_name".to_string(), value(object));
+        row.insert("object_type".to_string(), value("USER_TABLE"));
+        row.insert(
+            "column_name".to_string(),
+            column.map(value).unwrap_or(SqlValue::Null),
+        );
+        row.insert("source".to_string(), value(source));
+        row.insert("match_text".to_string(), value(text));
+        row
+    }
+
+    #[test]
+    fn test_rank_matches() {
+        let rows = vec![
+            row("dbo", "GetInvoices", None, "name", "GetInvoices"),
+            row(
+                "dbo",
+                "GetInvoices",
+                None,
+                "definition",
+                "CREATE PROCEDURE GetInvoices AS\nSELECT * FROM dbo.Invoice",
+            ),
+            row("dbo", "Invoice", None, "name", "Invoice"),
+            row("dbo", "Orders", Some("InvoiceId"), "column", "InvoiceId"),
+            row("dbo", "Customers", None, "name", "Customers"),
+        ];
+
+        let ranked = rank_matches(&rows, &SearchPattern::new("INVOICE", false).unwrap());
+        let order: Vec<&str> = ranked.iter().map(|m| m.object.as_str()).collect();
+        assert_eq!(order, vec!["Invoice", "GetInvoices", "Orders"]);
+
+        let definition = &ranked[1].matches[1];
+        assert_eq!(definition.source, "definition");
+        assert_eq!(definition.line, Some(1));
+        assert_eq!(definition.occurrences, Some(2));
+
+        let regex = SearchPattern::new(r"^Get\w+s$", true).unwrap();
+        let ranked = rank_matches(&rows, &regex);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].score, 100);
+
+        assert!(SearchPattern::new("(", true).is_err());
+        assert!(SearchPattern::new("  ", false).is_err());
+    }
+
+    #[test]
+    fn test_search_query() {
+        let sources = vec!["names".to_string(), "columns".to_string()];
+        let query = search_query(&sources, Some("dbo"), Some("50%_off"));
+        assert!(query.contains("UNION ALL"));
+        assert!(!query.contains("sys.sql_modules"));
+        assert!(query.contains(r"LIKE N'%50\%\_off%' ESCAPE '\'"));
+        assert!(query.contains("x.schema_name = N'dbo'"));
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let text = format!("{}needle{}", "a ".repeat(50), " b".repeat(50));
+        let start = text.find("needle").unwrap();
+        let snippet = excerpt(&text, start, start + 6);
+        assert!(snippet.starts_with("...") && snippet.ends_with("..."));
+        assert!(snippet.contains("needle"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Schema change detection for resource subscriptions.
+//!
+//! Clients subscribe to schema resources such as `mssql://tables` or
+//! `mssql://tables/{schema}/{table}` (and the view, procedure, function,
+//! trigger and schema equivalents). While at least one subscription is
+//! active, the watcher periodically snapshots object modification dates from
+//! `sys.objects` and reports the subscribed URIs affected by objects that were
+//! created, altered or dropped since the previous snapshot.
+
+use crate::database::query::{QueryExecutor, ResultRow};
+use crate::error::ServerError;
+use crate::security::parse_qualified_name;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Subscribable resource kinds and the `sys.objects` types they cover.
+const RESOURCE_KINDS: &[(&str, &[&str])] = &[
+    ("tables", &["U"]),
+    ("views", &["V"]),
+    ("procedures", &["P", "PC"]),
+    ("functions", &["FN", "IF", "TF", "FS", "FT"]),
+    ("triggers", &["TR"]),
+];
+
+/// Query listing user objects with their modification dates, plus all schemas.
+const SNAPSHOT_QUERY: &str = "\
+SELECT s.name AS schema_name, o.name AS object_name, RTRIM(o.type) AS object_type, \
+       CONVERT(varchar(27), o.modify_date, 126) AS modify_date \
+FROM sys.objects o \
+JOIN sys.schemas s ON s.schema_id = o.schema_id \
+WHERE o.is_ms_shipped = 0 \
+  AND o.type IN ('U', 'V', 'P', 'PC', 'FN', 'IF', 'TF', 'FS', 'FT', 'TR') \
+UNION ALL \
+SELECT name, NULL, 'SCHEMA', NULL FROM sys.schemas";
+
+/// Normalize a subscribable resource URI to a case-insensitive key.
+///
+/// Returns `None` for URIs that are not schema resources. Object URIs accept
+/// both `mssql://tables/dbo/Orders` and `mssql://tables/dbo.Orders`.
+pub fn subscription_key(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("mssql://")?;
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let kind = segments.next()?.to_lowercase();
+    let rest: Vec<&str> = segments.collect();
+
+    if kind == "schemas" {
+        return rest.is_empty().then_some(kind);
+    }
+    if !RESOURCE_KINDS.iter().any(|(k, _)| *k == kind) {
+        return None;
+    }
+
+    let (schema, name) = match rest.as_slice() {
+        [] => return Some(kind),
+        [schema, name] => ((*schema).to_string(), (*name).to_string()),
+        [qualified] => {
+            let (schema, name) = parse_qualified_name(qualified).ok()?;
+            (schema.unwrap_or_else(|| "dbo".to_string()), name)
+        }
+        _ => return None,
+    };
+    Some(object_key(&kind, &schema, &name))
+}
+
+/// Build the key of a schema object.
+fn object_key(kind: &str, schema: &str, name: &str) -> String {
+    format!("{}/{}/{}", kind, schema.to_lowercase(), name.to_lowercase())
+}
+
+/// Point-in-time view of the schema used to detect changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SchemaSnapshot {
+    /// Modification date of each object, keyed like subscriptions.
+    objects: HashMap<String, String>,
+    /// Schema names (lowercase).
+    schemas: BTreeSet<String>,
+}
+
+impl SchemaSnapshot {
+    /// Build a snapshot from the rows of [`SNAPSHOT_QUERY`].
+    fn from_rows(rows: &[ResultRow]) -> Self {
+        let mut snapshot = Self::default();
+        for row in rows {
+            let text = |column: &str| {
+                row.get(column)
+                    .filter(|v| !v.is_null())
+                    .map(|v| v.to_display_string())
+            };
+            let (Some(schema), Some(object_type)) = (text("schema_name"), text("object_type"))
+            else {
+                continue;
+            };
+            if object_type == "SCHEMA" {
+                snapshot.schemas.insert(schema.to_lowercase());
+                continue;
+            }
+
+            let kind = RESOURCE_KINDS
+                .iter()
+                .find(|(_, types)| types.contains(&object_type.as_str()))
+                .map(|(kind, _)| *kind);
+            if let (Some(kind), Some(name)) = (kind, text("object_name")) {
+                snapshot.objects.insert(
+                    object_key(kind, &schema, &name),
+                    text("modify_date").unwrap_or_default(),
+                );
+            }
+        }
+        snapshot
+    }
+
+    /// Keys of the resources affected by differences from an older snapshot.
+    ///
+    /// A created, altered or dropped object changes both its own resource and
+    /// the list resource of its kind.
+    fn changed_keys(&self, older: &Self) -> BTreeSet<String> {
+        let mut changed: BTreeSet<String> = self
+            .objects
+            .iter()
+            .filter(|(key, modified)| older.objects.get(*key) != Some(*modified))
+            .map(|(key, _)| key.clone())
+            .chain(
+                older
+                    .objects
+                    .keys()
+                    .filter(|key| !self.objects.contains_key(*key))
+                    .cloned(),
+            )
+            .collect();
+
+        let lists: Vec<String> = changed
+            .iter()
+            .filter_map(|key| key.split('/').next())
+            .map(str::to_string)
+            .collect();
+        changed.extend(lists);
+
+        if self.schemas != older.schemas {
+            changed.insert("schemas".to_string());
+        }
+        changed
+    }
+}
+
+/// Tracks schema resource subscriptions and detects schema changes.
+pub struct SchemaWatcher {
+    executor: Arc<QueryExecutor>,
+    poll_interval: Duration,
+    /// Subscribed URIs as sent by the client, keyed by normalized key.
+    subscriptions: Mutex<HashMap<String, String>>,
+    /// Snapshot from the most recent poll (`None` while nothing is subscribed).
+    snapshot: Mutex<Option<SchemaSnapshot>>,
+}
+
+impl SchemaWatcher {
+    /// Create a schema watcher.
+    pub fn new(executor: Arc<QueryExecutor>, poll_interval: Duration) -> Self {
+        Self {
+            executor,
+            poll_interval,
+            subscriptions: Mutex::new(HashMap::new()),
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    /// Get the interval between schema polls.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Subscribe to change notifications for a schema resource.
+    pub async fn subscribe(&self, uri: &str) -> Result<(), ServerError> {
+        let key = subscription_key(uri).ok_or_else(|| {
+            ServerError::invalid_input(format!("Resource '{}' does not support subscriptions", uri))
+        })?;
+        self.subscriptions.lock().await.insert(key, uri.to_string());
+
+        // Take the baseline now so changes made right after subscribing are reported
+        let mut snapshot = self.snapshot.lock().await;
+        if snapshot.is_none() {
+            match self.take_snapshot().await {
+                Ok(current) => *snapshot = Some(current),
+                Err(e) => warn!("Failed to take schema snapshot: {}", e),
+            }
+        }
+        debug!("Subscribed to schema resource {}", uri);
+        Ok(())
+    }
+
+    /// Remove a subscription. Returns `false` if the URI was not subscribed.
+    pub async fn unsubscribe(&self, uri: &str) -> bool {
+        let Some(key) = subscription_key(uri) else {
+            return false;
+        };
+        let mut subscriptions = self.subscriptions.lock().await;
+        let removed = subscriptions.remove(&key).is_some();
+        if subscriptions.is_empty() {
+            *self.snapshot.lock().await = None;
+        }
+        removed
+    }
+
+    /// List subscribed URIs.
+    pub async fn subscriptions(&self) -> Vec<String> {
+        let mut uris: Vec<String> = self.subscriptions.lock().await.values().cloned().collect();
+        uris.sort();
+        uris
+    }
+
+    /// Take a new snapshot and return the subscribed URIs whose resources changed.
+    pub async fn poll(&self) -> Result<Vec<String>, ServerError> {
+        if self.subscriptions.lock().await.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let current = self.take_snapshot().await?;
+        let Some(previous) = self.snapshot.lock().await.replace(current.clone()) else {
+            return Ok(Vec::new());
+        };
+
+        let changed = current.changed_keys(&previous);
+        let subscriptions = self.subscriptions.lock().await;
+        Ok(changed
+            .iter()
+            .filter_map(|key| subscriptions.get(key).cloned())
+            .collect())
+    }
+
+    /// Query the current schema snapshot.
+    async fn take_snapshot(&self) -> Result<SchemaSnapshot, ServerError> {
+        let result = self
+            .executor
+            .execute_with_limit(SNAPSHOT_QUERY, usize::MAX)
+            .await?;
+        Ok(SchemaSnapshot::from_rows(&result.rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn object(schema: &str, name: &str, object_type: &str, modified: &str) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert(
+            "schema_name".to_string(),
+            SqlValue::String(schema.to_string()),
+        );
+        row.insert(
+            "object_name".to_string(),
+            SqlValue::String(name.to_string()),
+        );
+        row.insert(
+            "object_type".to_string(),
+            SqlValue::String(object_type.to_string()),
+        );
+        row.insert(
+            "modify_date".to_string(),
+            SqlValue::String(modified.to_string()),
+        );
+        row
+    }
+
+    fn schema(name: &str) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert(
+            "schema_name".to_string(),
+            SqlValue::String(name.to_string()),
+        );
+        row.insert("object_name".to_string(), SqlValue::Null);
+        row.insert(
+            "object_type".to_string(),
+            SqlValue::String("SCHEMA".to_string()),
+        );
+        row.insert("modify_date".to_string(), SqlValue::Null);
+        row
+    }
+
+    #[test]
+    fn test_subscription_key() {
+        assert_eq!(
+            subscription_key("mssql://tables").as_deref(),
+            Some("tables")
+        );
+        assert_eq!(
+            subscription_key("mssql://tables/dbo/Orders").as_deref(),
+            Some("tables/dbo/orders")
+        );
+        assert_eq!(
+            subscription_key("mssql://views/Sales.Summary").as_deref(),
+            Some("views/sales/summary")
+        );
+        assert_eq!(
+            subscription_key("mssql://schemas").as_deref(),
+            Some("schemas")
+        );
+        assert!(subscription_key("mssql://server/info").is_none());
+        assert!(subscription_key("file:///etc/hosts").is_none());
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let before = SchemaSnapshot::from_rows(&[
+            object("dbo", "Orders", "U", "2024-01-01T00:00:00"),
+            object("dbo", "Customers", "U", "2024-01-01T00:00:00"),
+            object("dbo", "GetOrders", "P", "2024-01-01T00:00:00"),
+            schema("dbo"),
+        ]);
+        let after = SchemaSnapshot::from_rows(&[
+            object("dbo", "Orders", "U", "2024-02-01T00:00:00"),
+            object("dbo", "GetOrders", "P", "2024-01-01T00:00:00"),
+            object("sales", "Summary", "V", "2024-02-01T00:00:00"),
+            schema("dbo"),
+            schema("sales"),
+        ]);
+
+        let changed: Vec<String> = after.changed_keys(&before).into_iter().collect();
+        assert_eq!(
+            changed,
+            vec![
+                "schemas",
+                "tables",
+                "tables/dbo/customers",
+                "tables/dbo/orders",
+                "views",
+                "views/sales/summary",
+            ]
+        );
+        assert!(after.changed_keys(&after).is_empty());
+    }
+}
@@ -0,0 +1,463 @@
+//! Parsing of multi-batch SQLCMD-style scripts for `run_script`.
+//!
+//! Supports the subset of SQLCMD syntax found in deployment scripts:
+//! `GO [count]` batch separators, `:setvar NAME value` with `$(NAME)`
+//! substitution, and `:on error exit|ignore`. Commands that reach outside
+//! the connection (`:r`, `:connect`, `:out`, `!!`) are rejected.
+
+use crate::constants::MAX_GO_REPEAT;
+use crate::database::types::SqlValue;
+use crate::database::{QueryColumnInfo, QueryResult, ResultRow};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A `$(NAME)` variable reference.
+static VARIABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\(([A-Za-z_][\w-]*)\)").unwrap());
+
+/// A batch of a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptBatch {
+    /// 1-based batch number, counting each repetition of `GO n`.
+    pub number: usize,
+    /// 1-based line of the script the batch starts on.
+    pub line: usize,
+    /// Batch text with variables substituted.
+    pub sql: String,
+}
+
+/// A parsed script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub batches: Vec<ScriptBatch>,
+    /// Set by `:on error exit` (true) or `:on error ignore` (false).
+    pub stop_on_error: Option<bool>,
+}
+
+/// Outcome of running one batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchStatus {
+    Succeeded {
+        rows_affected: u64,
+    },
+    Failed {
+        error: String,
+    },
+    /// Not run because an earlier batch failed or the run was cancelled.
+    Skipped,
+}
+
+/// A batch together with its outcome and duration.
+#[derive(Debug, Clone)]
+pub struct BatchOutcome {
+    pub batch: ScriptBatch,
+    pub status: BatchStatus,
+    pub duration_ms: u64,
+}
+
+/// Build the per-batch summary returned as the session result.
+pub fn summary_result(outcomes: &[BatchOutcome], execution_time_ms: u64) -> QueryResult {
+    let column = |name: &str, sql_type: &str, nullable: bool| QueryColumnInfo {
+        name: name.to_string(),
+        sql_type: sql_type.to_string(),
+        nullable,
+    };
+    let columns = vec![
+        column("batch", "int", false),
+        column("line", "int", false),
+        column("status", "nvarchar", false),
+        column("rows_affected", "bigint", true),
+        column("duration_ms", "bigint", false),
+        column("error", "nvarchar", true),
+        column("sql", "nvarchar", false),
+    ];
+
+    let mut rows_affected = 0;
+    let rows = outcomes
+        .iter()
+        .map(|outcome| {
+            let (status, rows, error) = match &outcome.status {
+                BatchStatus::Succeeded { rows_affected: n } => {
+                    rows_affected += n;
+                    ("succeeded", SqlValue::I64(*n as i64), SqlValue::Null)
+                }
+                BatchStatus::Failed { error } => {
+                    ("failed", SqlValue::Null, SqlValue::String(error.clone()))
+                }
+                BatchStatus::Skipped => ("skipped", SqlValue::Null, SqlValue::Null),
+            };
+            let mut row = ResultRow::new();
+            row.insert("batch".into(), SqlValue::I32(outcome.batch.number as i32));
+            row.insert("line".into(), SqlValue::I32(outcome.batch.line as i32));
+            row.insert("status".into(), SqlValue::String(status.into()));
+            row.insert("rows_affected".into(), rows);
+            row.insert(
+                "duration_ms".into(),
+                SqlValue::I64(outcome.duration_ms as i64),
+            );
+            row.insert("error".into(), error);
+            row.insert("sql".into(), SqlValue::String(preview(&outcome.batch.sql)));
+            row
+        })
+        .collect();
+
+    QueryResult {
+        columns,
+        rows,
+        rows_affected,
+        execution_time_ms,
+        truncated: false,
+        retry: None,
+        messages: Vec::new(),
+    }
+}
+
+/// First line of a batch, shortened for the summary.
+fn preview(sql: &str) -> String {
+    let first = sql.lines().next().unwrap_or("");
+    let mut text: String = first.chars().take(80).collect();
+    if text.len() < first.len() || sql.contains('\n') {
+        text.push_str("...");
+    }
+    text
+}
+
+/// Split a script into batches, applying SQLCMD variables.
+///
+/// `variables` provides values for `$(NAME)` references; as in sqlcmd,
+/// `:setvar` in the script takes precedence. Variable names are
+/// case-insensitive. An undefined variable is an error.
+pub fn parse_script(script: &str, variables: &HashMap<String, String>) -> Result<Script, String> {
+    let mut values: HashMap<String, String> = variables
+        .iter()
+        .map(|(name, value)| (name.to_uppercase(), value.clone()))
+        .collect();
+    let mut parsed = Script::default();
+    let mut current = String::new();
+    let mut start_line = 1;
+    let mut lexer = LineLexer::default();
+
+    for (index, raw_line) in script.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = raw_line.trim();
+
+        // Separators and commands inside a block comment or string are text
+        if lexer.is_open() {
+            lexer.scan(raw_line);
+            current.push('\n');
+            current.push_str(&substitute(raw_line, &values, line_number)?);
+            continue;
+        }
+
+        if let Some(command) = trimmed.strip_prefix(':') {
+            let mut parts = command.splitn(2, char::is_whitespace);
+            let name = parts.next().unwrap_or("").to_lowercase();
+            let args = parts.next().unwrap_or("").trim();
+            match name.as_str() {
+                "setvar" => {
+                    let (var, value) = parse_setvar(args)
+                        .ok_or_else(|| format!("Line {}: invalid :setvar", line_number))?;
+                    values.insert(var.to_uppercase(), value);
+                }
+                "on" => {
+                    parsed.stop_on_error =
+                        match args.to_lowercase().split_whitespace().collect::<Vec<_>>()[..] {
+                            ["error", "exit"] => Some(true),
+                            ["error", "ignore"] => Some(false),
+                            _ => {
+                                return Err(format!(
+                                    "Line {}: expected ':on error exit|ignore'",
+                                    line_number
+                                ))
+                            }
+                        };
+                }
+                _ => {
+                    return Err(format!(
+                        "Line {}: SQLCMD command ':{}' is not supported",
+                        line_number, name
+                    ));
+                }
+            }
+            if current.trim().is_empty() {
+                start_line = line_number + 1;
+            }
+            continue;
+        }
+        if trimmed.starts_with("!!") {
+            return Err(format!(
+                "Line {}: shell commands (!!) are not supported",
+                line_number
+            ));
+        }
+
+        if let Some(count) = go_count(trimmed) {
+            if count > MAX_GO_REPEAT {
+                return Err(format!(
+                    "Line {}: GO repeat count {} exceeds the maximum of {}",
+                    line_number, count, MAX_GO_REPEAT
+                ));
+            }
+            push_batch(&mut parsed.batches, &current, start_line, count);
+            current.clear();
+            start_line = line_number + 1;
+            continue;
+        }
+
+        if current.trim().is_empty() && trimmed.is_empty() {
+            start_line = line_number + 1;
+            continue;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        lexer.scan(raw_line);
+        current.push_str(&substitute(raw_line, &values, line_number)?);
+    }
+    push_batch(&mut parsed.batches, &current, start_line, 1);
+
+    Ok(parsed)
+}
+
+/// Tracks block comments and string literals spanning lines.
+#[derive(Debug, Default)]
+struct LineLexer {
+    /// Nesting depth of `/* */` comments.
+    comment_depth: usize,
+    /// Inside a `'...'` literal.
+    in_string: bool,
+}
+
+impl LineLexer {
+    /// Whether the next line starts inside a comment or string.
+    fn is_open(&self) -> bool {
+        self.comment_depth > 0 || self.in_string
+    }
+
+    /// Advance the state past one line.
+    fn scan(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let next = chars.get(i + 1).copied();
+            if self.in_string {
+                // A doubled quote toggles twice, so it stays inside the string
+                self.in_string = chars[i] != '\'';
+            } else if self.comment_depth > 0 {
+                if chars[i] == '*' && next == Some('/') {
+                    self.comment_depth -= 1;
+                    i += 1;
+                } else if chars[i] == '/' && next == Some('*') {
+                    self.comment_depth += 1;
+                    i += 1;
+                }
+            } else {
+                match (chars[i], next) {
+                    ('-', Some('-')) => return,
+                    ('/', Some('*')) => {
+                        self.comment_depth += 1;
+                        i += 1;
+                    }
+                    ('\'', _) => self.in_string = true,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Repeat count of a `GO` / `GO n` line (optionally followed by a comment).
+fn go_count(line: &str) -> Option<usize> {
+    let line = line.split("--").next().unwrap_or("").trim();
+    let mut words = line.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("GO") {
+        return None;
+    }
+    match (words.next(), words.next()) {
+        (None, _) => Some(1),
+        (Some(n), None) => n.parse::<usize>().ok().map(|n| n.max(1)),
+        _ => None,
+    }
+}
+
+fn push_batch(batches: &mut Vec<ScriptBatch>, sql: &str, line: usize, count: usize) {
+    let sql = sql.trim();
+    if sql.is_empty() {
+        return;
+    }
+    for _ in 0..count {
+        batches.push(ScriptBatch {
+            number: batches.len() + 1,
+            line,
+            sql: sql.to_string(),
+        });
+    }
+}
+
+/// Parse `NAME value` or `NAME "quoted value"` of a `:setvar` command.
+fn parse_setvar(args: &str) -> Option<(String, String)> {
+    let (name, value) = match args.split_once(char::is_whitespace) {
+        Some((name, value)) => (name, value.trim()),
+        None => (args, ""),
+    };
+    if !VARIABLE.is_match(&format!("$({})", name)) {
+        return None;
+    }
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => quoted.strip_suffix('"')?.replace("\"\"", "\""),
+        None => value.to_string(),
+    };
+    Some((name.to_string(), value))
+}
+
+/// Replace `$(NAME)` references in a line.
+fn substitute(
+    line: &str,
+    values: &HashMap<String, String>,
+    line_number: usize,
+) -> Result<String, String> {
+    let mut missing = None;
+    let replaced = VARIABLE.replace_all(line, |caps: &regex::Captures| {
+        match values.get(&caps[1].to_uppercase()) {
+            Some(value) => value.clone(),
+            None => {
+                missing.get_or_insert_with(|| caps[1].to_string());
+                String::new()
+            }
+        }
+    });
+    match missing {
+        Some(name) => Err(format!(
+            "Line {}: variable '{}' is not defined (use :setvar or the variables parameter)",
+            line_number, name
+        )),
+        None => Ok(replaced.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_and_line_numbers() {
+        let script = "CREATE TABLE t (id INT)\nGO\n\nINSERT t VALUES (1)\ngo 3 -- three rows\nSELECT * FROM t";
+        let parsed = parse_script(script, &HashMap::new()).unwrap();
+        let batches: Vec<(usize, usize, &str)> = parsed
+            .batches
+            .iter()
+            .map(|b| (b.number, b.line, b.sql.as_str()))
+            .collect();
+        assert_eq!(
+            batches,
+            vec![
+                (1, 1, "CREATE TABLE t (id INT)"),
+                (2, 4, "INSERT t VALUES (1)"),
+                (3, 4, "INSERT t VALUES (1)"),
+                (4, 4, "INSERT t VALUES (1)"),
+                (5, 6, "SELECT * FROM t"),
+            ]
+        );
+        assert_eq!(parsed.stop_on_error, None);
+    }
+
+    #[test]
+    fn test_variables() {
+        let script = ":setvar Schema \"sales\"\n:on error ignore\nSELECT * FROM $(schema).$(Table)";
+        let variables = HashMap::from([
+            ("TABLE".to_string(), "Orders".to_string()),
+            ("Schema".to_string(), "dbo".to_string()),
+        ]);
+        let parsed = parse_script(script, &variables).unwrap();
+        assert_eq!(parsed.batches[0].sql, "SELECT * FROM sales.Orders");
+        assert_eq!(parsed.batches[0].line, 3);
+        assert_eq!(parsed.stop_on_error, Some(false));
+
+        let err = parse_script("GO\nSELECT $(Missing)", &HashMap::new()).unwrap_err();
+        assert!(err.starts_with("Line 2: variable 'Missing'"));
+    }
+
+    #[test]
+    fn test_summary_result() {
+        let batch = |number, sql: &str| ScriptBatch {
+            number,
+            line: number,
+            sql: sql.to_string(),
+        };
+        let outcomes = [
+            BatchOutcome {
+                batch: batch(1, "UPDATE t SET x = 1\nWHERE id > 10"),
+                status: BatchStatus::Succeeded { rows_affected: 5 },
+                duration_ms: 3,
+            },
+            BatchOutcome {
+                batch: batch(2, "DROP TABLE missing"),
+                status: BatchStatus::Failed {
+                    error: "Cannot drop the table".into(),
+                },
+                duration_ms: 1,
+            },
+            BatchOutcome {
+                batch: batch(3, "SELECT 1"),
+                status: BatchStatus::Skipped,
+                duration_ms: 0,
+            },
+        ];
+        let result = summary_result(&outcomes, 10);
+        assert_eq!(result.rows_affected, 5);
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(
+            result.rows[0].get("sql").unwrap().to_display_string(),
+            "UPDATE t SET x = 1..."
+        );
+        assert_eq!(
+            result.rows[1].get("error").unwrap().to_display_string(),
+            "Cannot drop the table"
+        );
+        assert_eq!(
+            result.rows[2].get("status").unwrap().to_display_string(),
+            "skipped"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_commands() {
+        assert!(parse_script(":r other.sql", &HashMap::new()).is_err());
+        assert!(parse_script("!! dir", &HashMap::new()).is_err());
+        assert!(parse_script(":on error maybe", &HashMap::new()).is_err());
+        assert!(parse_script(":setvar 1bad x", &HashMap::new()).is_err());
+        // A column named "go" is not a separator
+        let parsed = parse_script("SELECT 1 AS x\nGO fast", &HashMap::new()).unwrap();
+        assert_eq!(parsed.batches.len(), 1);
+
+        let err = parse_script("SELECT 1\nGO 4000000000", &HashMap::new()).unwrap_err();
+        assert!(err.contains("exceeds the maximum"));
+        let parsed = parse_script("SELECT 1\nGO 1000", &HashMap::new()).unwrap();
+        assert_eq!(parsed.batches.len(), MAX_GO_REPEAT);
+    }
+
+    #[test]
+    fn test_go_inside_comments_and_strings() {
+        let script =
+            "/* setup\nGO\n:r nothing.sql\n*/ SELECT 1\nGO\nINSERT t VALUES ('a\nGO\nb''c')\nGO";
+        let parsed = parse_script(script, &HashMap::new()).unwrap();
+        let batches: Vec<(usize, &str)> = parsed
+            .batches
+            .iter()
+            .map(|b| (b.line, b.sql.as_str()))
+            .collect();
+        assert_eq!(
+            batches,
+            vec![
+                (1, "/* setup\nGO\n:r nothing.sql\n*/ SELECT 1"),
+                (6, "INSERT t VALUES ('a\nGO\nb''c')"),
+            ]
+        );
+
+        let script =
+            "SELECT 1 -- it's a comment /*\nGO\nSELECT '/*' /* a /* nested */ comment */\nGO";
+        let parsed = parse_script(script, &HashMap::new()).unwrap();
+        assert_eq!(parsed.batches.len(), 2);
+    }
+}
@@ -0,0 +1,994 @@
+//! Object DDL scripting.
+//!
+//! Produces re-runnable CREATE scripts for database objects, like "Script As"
+//! in SSMS. Tables are rebuilt from catalog views (columns with identity,
+//! computed and default definitions, key, unique and check constraints,
+//! foreign keys, indexes and triggers); views, procedures, functions and
+//! triggers come from their module definitions.
+
+use crate::database::types::SqlValue;
+use crate::database::ResultRow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Matches the CREATE keyword that starts a module definition.
+static CREATE_MODULE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(?is)^((?:\s|--[^\n]*\n|/\*.*?\*/)*)CREATE\s+",
+        r"(PROC|PROCEDURE|VIEW|FUNCTION|TRIGGER)\b"
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid module definition pattern: {}", e))
+});
+
+/// Options controlling the generated script.
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptOptions {
+    /// Start with `DROP ... IF EXISTS`; otherwise modules use `CREATE OR ALTER`.
+    pub include_drop: bool,
+    /// Script nonclustered indexes (key constraints are always scripted).
+    pub include_indexes: bool,
+    /// Script triggers defined on a table.
+    pub include_triggers: bool,
+    /// Script a table's foreign keys with the table; when scripting several
+    /// tables into one file they go at the end instead (see
+    /// [`TableDefinition::foreign_keys_script`]).
+    pub include_foreign_keys: bool,
+}
+
+// =============================================================================
+// Catalog Queries
+// =============================================================================
+
+fn object_id(schema: &str, name: &str) -> String {
+    format!(
+        "OBJECT_ID(N'[{}].[{}]')",
+        schema.replace(']', "]]").replace('\'', "''"),
+        name.replace(']', "]]").replace('\'', "''")
+    )
+}
+
+/// Query returning the columns of a table.
+pub fn columns_query(schema: &str, name: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            c.name AS column_name,
+            t.name AS type_name,
+            SCHEMA_NAME(t.schema_id) AS type_schema,
+            t.is_user_defined AS is_user_defined,
+            c.max_length AS max_length,
+            c.precision AS precision,
+            c.scale AS scale,
+            c.is_nullable AS is_nullable,
+            CASE WHEN c.collation_name COLLATE DATABASE_DEFAULT
+                      <> CAST(DATABASEPROPERTYEX(DB_NAME(), 'Collation') AS sysname) COLLATE DATABASE_DEFAULT
+                 THEN c.collation_name END AS collation_name,
+            CAST(ic.seed_value AS bigint) AS identity_seed,
+            CAST(ic.increment_value AS bigint) AS identity_increment,
+            cc.definition AS computed_definition,
+            cc.is_persisted AS is_persisted,
+            dc.name AS default_name,
+            dc.definition AS default_definition,
+            c.is_rowguidcol AS is_rowguidcol,
+            c.is_sparse AS is_sparse
+        FROM sys.columns c
+        INNER JOIN sys.types t ON t.user_type_id = c.user_type_id
+        LEFT JOIN sys.identity_columns ic
+            ON ic.object_id = c.object_id AND ic.column_id = c.column_id
+        LEFT JOIN sys.computed_columns cc
+            ON cc.object_id = c.object_id AND cc.column_id = c.column_id
+        LEFT JOIN sys.default_constraints dc
+            ON dc.parent_object_id = c.object_id AND dc.parent_column_id = c.column_id
+        WHERE c.object_id = {}
+        ORDER BY c.column_id
+    "#,
+        object_id(schema, name)
+    )
+}
+
+/// Query returning the indexes (including key constraints) of a table or view,
+/// one row per index column.
+pub fn indexes_query(schema: &str, name: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            i.name AS index_name,
+            i.type_desc AS index_type,
+            i.is_unique AS is_unique,
+            i.is_primary_key AS is_primary_key,
+            i.is_unique_constraint AS is_unique_constraint,
+            i.is_disabled AS is_disabled,
+            i.filter_definition AS filter_definition,
+            c.name AS column_name,
+            ic.is_descending_key AS is_descending,
+            ic.is_included_column AS is_included
+        FROM sys.indexes i
+        INNER JOIN sys.index_columns ic
+            ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+        INNER JOIN sys.columns c
+            ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+        WHERE i.object_id = {}
+        AND i.type > 0
+        AND i.is_hypothetical = 0
+        ORDER BY i.index_id, ic.is_included_column, ic.key_ordinal, ic.index_column_id
+    "#,
+        object_id(schema, name)
+    )
+}
+
+/// Query returning the foreign keys of a table, one row per column.
+pub fn foreign_keys_query(schema: &str, name: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            fk.name AS fk_name,
+            pc.name AS column_name,
+            OBJECT_SCHEMA_NAME(fk.referenced_object_id) AS referenced_schema,
+            OBJECT_NAME(fk.referenced_object_id) AS referenced_table,
+            rc.name AS referenced_column,
+            fk.delete_referential_action_desc AS on_delete,
+            fk.update_referential_action_desc AS on_update,
+            fk.is_disabled AS is_disabled
+        FROM sys.foreign_keys fk
+        INNER JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+        INNER JOIN sys.columns pc
+            ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+        INNER JOIN sys.columns rc
+            ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+        WHERE fk.parent_object_id = {}
+        ORDER BY fk.name, fkc.constraint_column_id
+    "#,
+        object_id(schema, name)
+    )
+}
+
+/// Query returning the check constraints of a table.
+pub fn check_constraints_query(schema: &str, name: &str) -> String {
+    format!(
+        "SELECT name AS constraint_name, definition, is_disabled \
+         FROM sys.check_constraints WHERE parent_object_id = {} ORDER BY name",
+        object_id(schema, name)
+    )
+}
+
+/// Query returning the triggers of a table or view.
+pub fn triggers_query(schema: &str, name: &str) -> String {
+    format!(
+        "SELECT t.name AS trigger_name, OBJECT_DEFINITION(t.object_id) AS definition, \
+         t.is_disabled FROM sys.triggers t WHERE t.parent_id = {} ORDER BY t.name",
+        object_id(schema, name)
+    )
+}
+
+/// Query returning the definition of a module (NULL if encrypted).
+pub fn module_definition_query(schema: &str, name: &str) -> String {
+    format!(
+        "SELECT OBJECT_DEFINITION({}) AS definition",
+        object_id(schema, name)
+    )
+}
+
+// =============================================================================
+// Table Definitions
+// =============================================================================
+
+/// A column as scripted in CREATE TABLE.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptColumn {
+    pub name: String,
+    pub type_name: String,
+    /// Schema of a user-defined type.
+    pub type_schema: Option<String>,
+    pub max_length: i32,
+    pub precision: i32,
+    pub scale: i32,
+    pub nullable: bool,
+    /// Collation, when it differs from the database default.
+    pub collation: Option<String>,
+    /// Identity seed and increment.
+    pub identity: Option<(i64, i64)>,
+    /// Computed column expression.
+    pub computed: Option<String>,
+    pub persisted: bool,
+    /// Default constraint name and expression.
+    pub default: Option<(String, String)>,
+    pub rowguidcol: bool,
+    pub sparse: bool,
+}
+
+/// An index or key constraint.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptIndex {
+    pub name: String,
+    /// `sys.indexes.type_desc`, e.g. `NONCLUSTERED`.
+    pub index_type: String,
+    pub unique: bool,
+    pub primary_key: bool,
+    pub unique_constraint: bool,
+    pub disabled: bool,
+    pub filter: Option<String>,
+    /// Key columns with their descending flag.
+    pub key_columns: Vec<(String, bool)>,
+    pub included_columns: Vec<String>,
+}
+
+/// A foreign key constraint.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    /// Referential actions as in `sys.foreign_keys`, e.g. `CASCADE`, `SET_NULL`.
+    pub on_delete: String,
+    pub on_update: String,
+    pub disabled: bool,
+}
+
+/// A check constraint, or a trigger (name, definition, disabled).
+#[derive(Debug, Clone, Default)]
+pub struct ScriptDefinition {
+    pub name: String,
+    pub definition: String,
+    pub disabled: bool,
+}
+
+/// Everything needed to script a table.
+#[derive(Debug, Clone, Default)]
+pub struct TableDefinition {
+    pub schema: String,
+    pub name: String,
+    pub columns: Vec<ScriptColumn>,
+    pub indexes: Vec<ScriptIndex>,
+    pub foreign_keys: Vec<ScriptForeignKey>,
+    pub checks: Vec<ScriptDefinition>,
+    pub triggers: Vec<ScriptDefinition>,
+}
+
+fn text(row: &ResultRow, column: &str) -> Option<String> {
+    row.get(column)
+        .filter(|v| !v.is_null())
+        .map(|v| v.to_display_string())
+}
+
+fn int(row: &ResultRow, column: &str) -> Option<i64> {
+    text(row, column).and_then(|v| v.parse().ok())
+}
+
+fn flag(row: &ResultRow, column: &str) -> bool {
+    match row.get(column) {
+        Some(SqlValue::Bool(b)) => *b,
+        Some(other) => matches!(other.to_display_string().as_str(), "1" | "true"),
+        None => false,
+    }
+}
+
+/// Parse rows of [`columns_query`].
+pub fn parse_columns(rows: &[ResultRow]) -> Vec<ScriptColumn> {
+    rows.iter()
+        .map(|row| ScriptColumn {
+            name: text(row, "column_name").unwrap_or_default(),
+            type_name: text(row, "type_name").unwrap_or_default(),
+            type_schema: flag(row, "is_user_defined")
+                .then(|| text(row, "type_schema"))
+                .flatten(),
+            max_length: int(row, "max_length").unwrap_or_default() as i32,
+            precision: int(row, "precision").unwrap_or_default() as i32,
+            scale: int(row, "scale").unwrap_or_default() as i32,
+            nullable: flag(row, "is_nullable"),
+            collation: text(row, "collation_name"),
+            identity: int(row, "identity_seed").zip(int(row, "identity_increment")),
+            computed: text(row, "computed_definition"),
+            persisted: flag(row, "is_persisted"),
+            default: text(row, "default_name").zip(text(row, "default_definition")),
+            rowguidcol: flag(row, "is_rowguidcol"),
+            sparse: flag(row, "is_sparse"),
+        })
+        .collect()
+}
+
+/// Parse rows of [`indexes_query`].
+pub fn parse_indexes(rows: &[ResultRow]) -> Vec<ScriptIndex> {
+    let mut indexes: Vec<ScriptIndex> = Vec::new();
+    for row in rows {
+        let name = text(row, "index_name").unwrap_or_default();
+        if indexes.last().is_none_or(|i| i.name != name) {
+            indexes.push(ScriptIndex {
+                name,
+                index_type: text(row, "index_type").unwrap_or_default(),
+                unique: flag(row, "is_unique"),
+                primary_key: flag(row, "is_primary_key"),
+                unique_constraint: flag(row, "is_unique_constraint"),
+                disabled: flag(row, "is_disabled"),
+                filter: text(row, "filter_definition"),
+                ..Default::default()
+            });
+        }
+        if let (Some(index), Some(column)) = (indexes.last_mut(), text(row, "column_name")) {
+            if flag(row, "is_included") {
+                index.included_columns.push(column);
+            } else {
+                index.key_columns.push((column, flag(row, "is_descending")));
+            }
+        }
+    }
+    indexes
+}
+
+/// Parse rows of [`foreign_keys_query`].
+pub fn parse_foreign_keys(rows: &[ResultRow]) -> Vec<ScriptForeignKey> {
+    let mut keys: BTreeMap<String, ScriptForeignKey> = BTreeMap::new();
+    for row in rows {
+        let name = text(row, "fk_name").unwrap_or_default();
+        let key = keys
+            .entry(name.clone())
+            .or_insert_with(|| ScriptForeignKey {
+                name,
+                referenced_schema: text(row, "referenced_schema").unwrap_or_default(),
+                referenced_table: text(row, "referenced_table").unwrap_or_default(),
+                on_delete: text(row, "on_delete").unwrap_or_default(),
+                on_update: text(row, "on_update").unwrap_or_default(),
+                disabled: flag(row, "is_disabled"),
+                ..Default::default()
+            });
+        key.columns.extend(text(row, "column_name"));
+        key.referenced_columns
+            .extend(text(row, "referenced_column"));
+    }
+    keys.into_values().collect()
+}
+
+/// Parse rows of [`check_constraints_query`] or [`triggers_query`].
+pub fn parse_definitions(rows: &[ResultRow], name_column: &str) -> Vec<ScriptDefinition> {
+    rows.iter()
+        .map(|row| ScriptDefinition {
+            name: text(row, name_column).unwrap_or_default(),
+            definition: text(row, "definition").unwrap_or_default(),
+            disabled: flag(row, "is_disabled"),
+        })
+        .collect()
+}
+
+// =============================================================================
+// Script Rendering
+// =============================================================================
+
+fn quote(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Type of a `sys.columns` column as written in DDL, e.g. `NVARCHAR(50)`.
+///
+/// `max_length` is in bytes as stored in `sys.columns` (-1 for MAX).
+pub fn sys_type_definition(type_name: &str, max_length: i32, precision: i32, scale: i32) -> String {
+    let base = type_name.to_uppercase();
+    match type_name.to_lowercase().as_str() {
+        "varchar" | "char" | "varbinary" | "binary" => match max_length {
+            -1 => format!("{}(MAX)", base),
+            len => format!("{}({})", base, len),
+        },
+        "nvarchar" | "nchar" => match max_length {
+            -1 => format!("{}(MAX)", base),
+            len => format!("{}({})", base, len / 2),
+        },
+        "decimal" | "numeric" => format!("{}({},{})", base, precision, scale),
+        "datetime2" | "time" | "datetimeoffset" => format!("{}({})", base, scale),
+        _ => base,
+    }
+}
+
+impl ScriptColumn {
+    fn definition(&self) -> String {
+        if let Some(expression) = &self.computed {
+            let persisted = if self.persisted { " PERSISTED" } else { "" };
+            return format!("{} AS {}{}", quote(&self.name), expression, persisted);
+        }
+
+        let mut sql = format!("{} ", quote(&self.name));
+        match &self.type_schema {
+            Some(schema) => sql.push_str(&format!("{}.{}", quote(schema), quote(&self.type_name))),
+            None => sql.push_str(&sys_type_definition(
+                &self.type_name,
+                self.max_length,
+                self.precision,
+                self.scale,
+            )),
+        }
+        if self.sparse {
+            sql.push_str(" SPARSE");
+        }
+        if let Some(collation) = &self.collation {
+            sql.push_str(&format!(" COLLATE {}", collation));
+        }
+        if let Some((seed, increment)) = self.identity {
+            sql.push_str(&format!(" IDENTITY({},{})", seed, increment));
+        }
+        if self.rowguidcol {
+            sql.push_str(" ROWGUIDCOL");
+        }
+        sql.push_str(if self.nullable { " NULL" } else { " NOT NULL" });
+        if let Some((name, expression)) = &self.default {
+            sql.push_str(&format!(
+                " CONSTRAINT {} DEFAULT {}",
+                quote(name),
+                expression
+            ));
+        }
+        sql
+    }
+}
+
+impl ScriptIndex {
+    /// Whether the index backs a primary key or unique constraint.
+    pub fn is_constraint(&self) -> bool {
+        self.primary_key || self.unique_constraint
+    }
+
+    fn is_columnstore(&self) -> bool {
+        self.index_type.contains("COLUMNSTORE")
+    }
+
+    fn key_list(&self) -> String {
+        self.key_columns
+            .iter()
+            .map(|(column, descending)| {
+                if self.is_columnstore() {
+                    quote(column)
+                } else {
+                    format!(
+                        "{} {}",
+                        quote(column),
+                        if *descending { "DESC" } else { "ASC" }
+                    )
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Inline constraint definition for CREATE TABLE.
+    fn constraint_definition(&self) -> String {
+        format!(
+            "CONSTRAINT {} {} {} ({})",
+            quote(&self.name),
+            if self.primary_key {
+                "PRIMARY KEY"
+            } else {
+                "UNIQUE"
+            },
+            self.index_type,
+            self.key_list()
+        )
+    }
+
+    /// Standalone CREATE INDEX statement.
+    fn create_statement(&self, table: &str) -> String {
+        if self.index_type == "CLUSTERED COLUMNSTORE" {
+            return format!(
+                "CREATE CLUSTERED COLUMNSTORE INDEX {} ON {};",
+                quote(&self.name),
+                table
+            );
+        }
+
+        let mut sql = format!(
+            "CREATE {}{} INDEX {} ON {} ({})",
+            if self.unique { "UNIQUE " } else { "" },
+            self.index_type,
+            quote(&self.name),
+            table,
+            self.key_list()
+        );
+        if !self.included_columns.is_empty() {
+            let included: Vec<String> = self.included_columns.iter().map(|c| quote(c)).collect();
+            sql.push_str(&format!(" INCLUDE ({})", included.join(", ")));
+        }
+        if let Some(filter) = &self.filter {
+            sql.push_str(&format!(" WHERE {}", filter));
+        }
+        sql.push(';');
+        sql
+    }
+}
+
+impl ScriptForeignKey {
+    fn add_statement(&self, table: &str) -> String {
+        let list = |columns: &[String]| {
+            columns
+                .iter()
+                .map(|c| quote(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut sql = format!(
+            "ALTER TABLE {} WITH CHECK ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}.{} ({})",
+            table,
+            quote(&self.name),
+            list(&self.columns),
+            quote(&self.referenced_schema),
+            quote(&self.referenced_table),
+            list(&self.referenced_columns)
+        );
+        for (event, action) in [("DELETE", &self.on_delete), ("UPDATE", &self.on_update)] {
+            if !action.is_empty() && action != "NO_ACTION" {
+                sql.push_str(&format!(" ON {} {}", event, action.replace('_', " ")));
+            }
+        }
+        sql.push(';');
+        sql
+    }
+}
+
+impl TableDefinition {
+    /// Render the table as a script of `GO`-separated batches.
+    pub fn script(&self, options: &ScriptOptions) -> String {
+        let table = format!("{}.{}", quote(&self.schema), quote(&self.name));
+        let mut batches = Vec::new();
+
+        if options.include_drop {
+            batches.push(format!("DROP TABLE IF EXISTS {};", table));
+        }
+
+        let mut lines: Vec<String> = self.columns.iter().map(ScriptColumn::definition).collect();
+        lines.extend(
+            self.indexes
+                .iter()
+                .filter(|i| i.is_constraint())
+                .map(ScriptIndex::constraint_definition),
+        );
+        lines.extend(
+            self.checks
+                .iter()
+                .map(|c| format!("CONSTRAINT {} CHECK {}", quote(&c.name), c.definition)),
+        );
+        batches.push(format!(
+            "CREATE TABLE {} (\n    {}\n);",
+            table,
+            lines.join(",\n    ")
+        ));
+
+        let mut after = Vec::new();
+        for check in self.checks.iter().filter(|c| c.disabled) {
+            after.push(format!(
+                "ALTER TABLE {} NOCHECK CONSTRAINT {};",
+                table,
+                quote(&check.name)
+            ));
+        }
+        if options.include_foreign_keys {
+            after.extend(self.foreign_key_statements(&table));
+        }
+        if !after.is_empty() {
+            batches.push(after.join("\n"));
+        }
+
+        let indexes: Vec<&ScriptIndex> = self
+            .indexes
+            .iter()
+            .filter(|i| {
+                !i.is_constraint() && (options.include_indexes || i.index_type == "CLUSTERED")
+            })
+            .collect();
+        if !indexes.is_empty() {
+            let mut sql: Vec<String> = indexes.iter().map(|i| i.create_statement(&table)).collect();
+            for index in indexes.iter().filter(|i| i.disabled) {
+                sql.push(format!(
+                    "ALTER INDEX {} ON {} DISABLE;",
+                    quote(&index.name),
+                    table
+                ));
+            }
+            batches.push(sql.join("\n"));
+        }
+
+        if options.include_triggers {
+            for trigger in &self.triggers {
+                batches.extend(trigger_batches(trigger, &table));
+            }
+        }
+
+        batches
+            .into_iter()
+            .map(|b| format!("{}\nGO\n", b))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The table's foreign keys as one batch, if it has any.
+    pub fn foreign_keys_script(&self) -> Option<String> {
+        let table = format!("{}.{}", quote(&self.schema), quote(&self.name));
+        let statements = self.foreign_key_statements(&table);
+        (!statements.is_empty()).then(|| format!("{}\nGO\n", statements.join("\n")))
+    }
+
+    /// The table's triggers as batches, if it has any.
+    pub fn triggers_script(&self) -> Option<String> {
+        let table = format!("{}.{}", quote(&self.schema), quote(&self.name));
+        let batches: Vec<String> = self
+            .triggers
+            .iter()
+            .flat_map(|t| trigger_batches(t, &table))
+            .map(|b| format!("{}\nGO\n", b))
+            .collect();
+        (!batches.is_empty()).then(|| batches.join("\n"))
+    }
+
+    fn foreign_key_statements(&self, table: &str) -> Vec<String> {
+        let mut statements = Vec::new();
+        for key in &self.foreign_keys {
+            statements.push(key.add_statement(table));
+            if key.disabled {
+                statements.push(format!(
+                    "ALTER TABLE {} NOCHECK CONSTRAINT {};",
+                    table,
+                    quote(&key.name)
+                ));
+            }
+        }
+        statements
+    }
+}
+
+fn trigger_batches(trigger: &ScriptDefinition, table: &str) -> Vec<String> {
+    let mut batches = vec![create_or_alter(trigger.definition.trim())];
+    if trigger.disabled {
+        batches.push(format!(
+            "DISABLE TRIGGER {} ON {};",
+            quote(&trigger.name),
+            table
+        ));
+    }
+    batches
+}
+
+/// Turn a module's leading `CREATE` into `CREATE OR ALTER`.
+pub fn create_or_alter(definition: &str) -> String {
+    CREATE_MODULE
+        .replace(definition, "${1}CREATE OR ALTER ${2}")
+        .into_owned()
+}
+
+/// Keyword used to drop a module of the given `sys.objects.type_desc`.
+pub fn module_kind(object_type: &str) -> Option<&'static str> {
+    match object_type {
+        "VIEW" => Some("VIEW"),
+        "SQL_STORED_PROCEDURE" => Some("PROCEDURE"),
+        "SQL_SCALAR_FUNCTION"
+        | "SQL_INLINE_TABLE_VALUED_FUNCTION"
+        | "SQL_TABLE_VALUED_FUNCTION" => Some("FUNCTION"),
+        "SQL_TRIGGER" => Some("TRIGGER"),
+        _ => None,
+    }
+}
+
+/// Script a view, procedure, function or trigger from its definition.
+///
+/// `indexes` and `triggers` apply to views (indexed views and INSTEAD OF triggers).
+pub fn module_script(
+    schema: &str,
+    name: &str,
+    kind: &str,
+    definition: &str,
+    indexes: &[ScriptIndex],
+    triggers: &[ScriptDefinition],
+    options: &ScriptOptions,
+) -> String {
+    let object = format!("{}.{}", quote(schema), quote(name));
+    let mut batches = Vec::new();
+    if options.include_drop {
+        batches.push(format!("DROP {} IF EXISTS {};", kind, object));
+        batches.push(definition.trim().to_string());
+    } else {
+        batches.push(create_or_alter(definition.trim()));
+    }
+
+    if options.include_indexes && !indexes.is_empty() {
+        let sql: Vec<String> = indexes
+            .iter()
+            .map(|i| i.create_statement(&object))
+            .collect();
+        batches.push(sql.join("\n"));
+    }
+    if options.include_triggers {
+        for trigger in triggers {
+            batches.extend(trigger_batches(trigger, &object));
+        }
+    }
+
+    batches
+        .into_iter()
+        .map(|b| format!("{}\nGO\n", b))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// =============================================================================
+// Database Scripting
+// =============================================================================
+
+/// Query listing the user objects that can be scripted: tables, views,
+/// functions and procedures, in that order.
+pub const SCRIPTABLE_OBJECTS_QUERY: &str = r#"
+    SELECT
+        SCHEMA_NAME(o.schema_id) AS schema_name,
+        o.name AS object_name,
+        o.type_desc AS object_type
+    FROM sys.objects o
+    WHERE o.is_ms_shipped = 0
+    AND o.type IN ('U', 'V', 'FN', 'IF', 'TF', 'P')
+    AND NOT (o.type = 'U' AND o.name = 'sysdiagrams')
+    ORDER BY
+        CASE o.type WHEN 'U' THEN 0 WHEN 'V' THEN 1 WHEN 'P' THEN 3 ELSE 2 END,
+        SCHEMA_NAME(o.schema_id),
+        o.name
+"#;
+
+/// Folder an object type is scripted into.
+pub fn object_folder(object_type: &str) -> Option<&'static str> {
+    match object_type {
+        "USER_TABLE" => Some("Tables"),
+        "VIEW" => Some("Views"),
+        "SQL_STORED_PROCEDURE" => Some("StoredProcedures"),
+        "SQL_SCALAR_FUNCTION"
+        | "SQL_INLINE_TABLE_VALUED_FUNCTION"
+        | "SQL_TABLE_VALUED_FUNCTION" => Some("Functions"),
+        _ => None,
+    }
+}
+
+/// File name for an object's script, e.g. `dbo.Orders.sql`.
+///
+/// Characters that are not safe in file names are replaced by `_`.
+pub fn script_file_name(schema: &str, name: &str) -> String {
+    let safe = |part: &str| -> String {
+        part.chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '_' | '-' | ' ' | '$' | '#' | '@') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!("{}.{}.sql", safe(schema), safe(name))
+}
+
+/// Name filters for database scripting.
+///
+/// Patterns match `schema.name` case-insensitively, with `*` as a wildcard
+/// (e.g. `dbo.*` or `*.Audit*`).
+#[derive(Debug, Default)]
+pub struct ObjectFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl ObjectFilter {
+    /// Build a filter; an empty include list includes everything.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, String> {
+            patterns
+                .iter()
+                .map(|p| {
+                    let pattern = format!("(?i)^{}$", regex::escape(p.trim()).replace(r"\*", ".*"));
+                    Regex::new(&pattern).map_err(|e| format!("Invalid pattern '{}': {}", p, e))
+                })
+                .collect()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether `schema.name` passes the filter.
+    pub fn matches(&self, schema: &str, name: &str) -> bool {
+        let qualified = format!("{}.{}", schema, name);
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(&qualified)))
+            && !self.exclude.iter().any(|r| r.is_match(&qualified))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: ScriptOptions = ScriptOptions {
+        include_drop: false,
+        include_indexes: true,
+        include_triggers: true,
+        include_foreign_keys: true,
+    };
+
+    fn table() -> TableDefinition {
+        TableDefinition {
+            schema: "dbo".to_string(),
+            name: "Orders".to_string(),
+            columns: vec![
+                ScriptColumn {
+                    name: "Id".to_string(),
+                    type_name: "int".to_string(),
+                    identity: Some((1, 1)),
+                    ..Default::default()
+                },
+                ScriptColumn {
+                    name: "Code".to_string(),
+                    type_name: "nvarchar".to_string(),
+                    max_length: 40,
+                    collation: Some("Latin1_General_BIN2".to_string()),
+                    default: Some(("DF_Orders_Code".to_string(), "(N'')".to_string())),
+                    ..Default::default()
+                },
+                ScriptColumn {
+                    name: "Amount".to_string(),
+                    type_name: "decimal".to_string(),
+                    precision: 18,
+                    scale: 2,
+                    nullable: true,
+                    ..Default::default()
+                },
+                ScriptColumn {
+                    name: "Total".to_string(),
+                    computed: Some("([Amount]*(2))".to_string()),
+                    persisted: true,
+                    ..Default::default()
+                },
+            ],
+            indexes: vec![
+                ScriptIndex {
+                    name: "PK_Orders".to_string(),
+                    index_type: "CLUSTERED".to_string(),
+                    unique: true,
+                    primary_key: true,
+                    key_columns: vec![("Id".to_string(), false)],
+                    ..Default::default()
+                },
+                ScriptIndex {
+                    name: "IX_Orders_Code".to_string(),
+                    index_type: "NONCLUSTERED".to_string(),
+                    key_columns: vec![("Code".to_string(), true)],
+                    included_columns: vec!["Amount".to_string()],
+                    filter: Some("([Amount] IS NOT NULL)".to_string()),
+                    ..Default::default()
+                },
+            ],
+            foreign_keys: vec![ScriptForeignKey {
+                name: "FK_Orders_Customers".to_string(),
+                columns: vec!["Code".to_string()],
+                referenced_schema: "sales".to_string(),
+                referenced_table: "Customers".to_string(),
+                referenced_columns: vec!["Code".to_string()],
+                on_delete: "CASCADE".to_string(),
+                on_update: "NO_ACTION".to_string(),
+                disabled: true,
+            }],
+            checks: vec![ScriptDefinition {
+                name: "CK_Orders_Amount".to_string(),
+                definition: "([Amount]>=(0))".to_string(),
+                disabled: false,
+            }],
+            triggers: vec![ScriptDefinition {
+                name: "TR_Orders".to_string(),
+                definition: "/* audit */\nCREATE TRIGGER dbo.TR_Orders ON dbo.Orders AFTER INSERT AS SELECT 1".to_string(),
+                disabled: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_table_script() {
+        let script = table().script(&OPTIONS);
+
+        assert!(script.contains(
+            "CREATE TABLE [dbo].[Orders] (\n    [Id] INT IDENTITY(1,1) NOT NULL,\n    \
+             [Code] NVARCHAR(20) COLLATE Latin1_General_BIN2 NOT NULL CONSTRAINT [DF_Orders_Code] DEFAULT (N''),\n    \
+             [Amount] DECIMAL(18,2) NULL,\n    [Total] AS ([Amount]*(2)) PERSISTED,\n    \
+             CONSTRAINT [PK_Orders] PRIMARY KEY CLUSTERED ([Id] ASC),\n    \
+             CONSTRAINT [CK_Orders_Amount] CHECK ([Amount]>=(0))\n);\nGO\n"
+        ));
+        assert!(script.contains(
+            "ALTER TABLE [dbo].[Orders] WITH CHECK ADD CONSTRAINT [FK_Orders_Customers] FOREIGN KEY ([Code]) \
+             REFERENCES [sales].[Customers] ([Code]) ON DELETE CASCADE;\n\
+             ALTER TABLE [dbo].[Orders] NOCHECK CONSTRAINT [FK_Orders_Customers];"
+        ));
+        assert!(script.contains(
+            "CREATE NONCLUSTERED INDEX [IX_Orders_Code] ON [dbo].[Orders] ([Code] DESC) \
+             INCLUDE ([Amount]) WHERE ([Amount] IS NOT NULL);"
+        ));
+        assert!(script.contains("/* audit */\nCREATE OR ALTER TRIGGER dbo.TR_Orders"));
+        assert!(script.contains("DISABLE TRIGGER [TR_Orders] ON [dbo].[Orders];"));
+        assert!(!script.contains("DROP TABLE"));
+
+        let minimal = table().script(&ScriptOptions {
+            include_drop: true,
+            include_indexes: false,
+            include_triggers: false,
+            include_foreign_keys: false,
+        });
+        assert!(minimal.starts_with("DROP TABLE IF EXISTS [dbo].[Orders];\nGO\n"));
+        assert!(!minimal.contains("IX_Orders_Code") && !minimal.contains("TRIGGER"));
+        assert!(!minimal.contains("FOREIGN KEY"));
+        assert!(table()
+            .foreign_keys_script()
+            .is_some_and(|fk| fk.contains("FOREIGN KEY") && fk.ends_with("\nGO\n")));
+    }
+
+    #[test]
+    fn test_module_script() {
+        let definition = "-- header\nCREATE   PROC dbo.GetOrders AS SELECT 1";
+        let script = module_script(
+            "dbo",
+            "GetOrders",
+            "PROCEDURE",
+            definition,
+            &[],
+            &[],
+            &OPTIONS,
+        );
+        assert_eq!(
+            script,
+            "-- header\nCREATE OR ALTER PROC dbo.GetOrders AS SELECT 1\nGO\n"
+        );
+
+        let dropped = module_script(
+            "dbo",
+            "GetOrders",
+            "PROCEDURE",
+            definition,
+            &[],
+            &[],
+            &ScriptOptions {
+                include_drop: true,
+                ..OPTIONS
+            },
+        );
+        assert!(dropped.starts_with(
+            "DROP PROCEDURE IF EXISTS [dbo].[GetOrders];\nGO\n\n-- header\nCREATE   PROC"
+        ));
+
+        assert_eq!(
+            create_or_alter("CREATE OR ALTER VIEW v AS SELECT 1"),
+            "CREATE OR ALTER VIEW v AS SELECT 1"
+        );
+        assert_eq!(
+            module_kind("SQL_INLINE_TABLE_VALUED_FUNCTION"),
+            Some("FUNCTION")
+        );
+        assert_eq!(module_kind("USER_TABLE"), None);
+    }
+
+    #[test]
+    fn test_object_filter_and_files() {
+        let filter = ObjectFilter::new(
+            &["dbo.*".to_string(), "sales.Orders".to_string()],
+            &["*.tmp*".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches("dbo", "Customers"));
+        assert!(filter.matches("SALES", "orders"));
+        assert!(!filter.matches("sales", "OrderLines"));
+        assert!(!filter.matches("dbo", "TmpImport"));
+        assert!(ObjectFilter::new(&[], &[]).unwrap().matches("any", "thing"));
+
+        assert_eq!(
+            script_file_name("dbo", "Order/Lines"),
+            "dbo.Order_Lines.sql"
+        );
+        assert_eq!(
+            object_folder("SQL_STORED_PROCEDURE"),
+            Some("StoredProcedures")
+        );
+        assert_eq!(object_folder("SQL_TRIGGER"), None);
+    }
+
+    #[test]
+    fn test_sys_type_definition() {
+        assert_eq!(sys_type_definition("nvarchar", -1, 0, 0), "NVARCHAR(MAX)");
+        assert_eq!(sys_type_definition("varbinary", 16, 0, 0), "VARBINARY(16)");
+        assert_eq!(sys_type_definition("datetime2", 8, 27, 3), "DATETIME2(3)");
+        assert_eq!(sys_type_definition("int", 4, 10, 0), "INT");
+    }
+}
@@ -4,18 +4,82 @@
 //! Unlike pooled connections, session connections are held for the entire
 //! lifetime of a session, allowing temp tables, session variables, and
 //! SET options to persist across queries.
+//!
+//! Sessions are limited in number and ended by a background task once idle
+//! for too long or past their maximum lifetime, so sessions leaked by crashed
+//! clients do not hold connections indefinitely.
 
-use super::auth::{create_connection, truncate_for_log, RawConnection};
-use crate::config::DatabaseConfig;
+use super::auth::{create_connection, truncate_for_log, use_database, RawConnection};
+use crate::config::{AuthConfig, DatabaseConfig, SessionConfig};
 use crate::database::query::{ColumnInfo, QueryResult, ResultRow};
 use crate::database::types::TypeMapper;
 use crate::error::ServerError;
+use crate::telemetry::{record_request_rows, tag_query};
 use futures_util::TryStreamExt;
-use std::collections::HashMap;
-use std::sync::Arc;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
+
+/// Number of ended-by-cleanup session IDs remembered for error messages.
+const EXPIRED_SESSION_MEMORY: usize = 100;
+
+/// Why the cleanup task ended a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryReason {
+    /// No query ran for longer than the idle timeout.
+    IdleTimeout,
+    /// The session existed for longer than the maximum lifetime.
+    MaxLifetime,
+}
+
+impl std::fmt::Display for ExpiryReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpiryReason::IdleTimeout => write!(f, "idle timeout"),
+            ExpiryReason::MaxLifetime => write!(f, "maximum lifetime"),
+        }
+    }
+}
+
+/// Limits applied to pinned sessions; zero durations disable the check.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionLimits {
+    /// End sessions without activity for this long.
+    pub idle_timeout: Duration,
+    /// End sessions older than this.
+    pub max_lifetime: Duration,
+    /// Maximum concurrent sessions.
+    pub max_sessions: usize,
+}
+
+impl SessionLimits {
+    /// Take the pinned session limits from the session configuration.
+    pub fn from_config(config: &SessionConfig) -> Self {
+        Self {
+            idle_timeout: config.pinned_idle_timeout,
+            max_lifetime: config.pinned_max_lifetime,
+            max_sessions: config.max_pinned_sessions,
+        }
+    }
+
+    /// Check whether a session has expired, and why.
+    fn expiry(&self, info: &SessionInfo, now: Instant) -> Option<ExpiryReason> {
+        if !self.max_lifetime.is_zero() && now.duration_since(info.created_at) > self.max_lifetime {
+            Some(ExpiryReason::MaxLifetime)
+        } else if !self.idle_timeout.is_zero()
+            && now.duration_since(info.last_activity) > self.idle_timeout
+        {
+            Some(ExpiryReason::IdleTimeout)
+        } else {
+            None
+        }
+    }
+}
 
 /// Metadata about a pinned session.
 #[derive(Debug, Clone)]
@@ -28,6 +92,8 @@ pub struct SessionInfo {
     pub last_activity: Instant,
     /// Number of queries executed in this session.
     pub query_count: u64,
+    /// Database selected with `begin_session` or `use_database`, if any.
+    pub database: Option<String>,
 }
 
 /// Manager for session-pinned connections.
@@ -42,38 +108,117 @@ pub struct SessionInfo {
 /// - SET options that should persist for multiple queries
 pub struct SessionManager {
     /// Database configuration for creating new connections.
-    db_config: Arc<DatabaseConfig>,
+    db_config: parking_lot::RwLock<Arc<DatabaseConfig>>,
 
     /// Active session connections keyed by session ID.
     connections: Mutex<HashMap<String, (RawConnection, SessionInfo)>>,
 
     /// Maximum rows to return from queries.
-    max_rows: usize,
+    max_rows: AtomicUsize,
 
-    /// Session timeout duration.
-    session_timeout: Duration,
+    /// Idle timeout, maximum lifetime and session limit.
+    limits: parking_lot::RwLock<SessionLimits>,
+
+    /// Sessions recently ended by cleanup, so later calls can say why.
+    expired: parking_lot::Mutex<VecDeque<(String, ExpiryReason)>>,
 }
 
 impl SessionManager {
     /// Create a new session manager.
-    pub fn new(db_config: Arc<DatabaseConfig>, max_rows: usize, session_timeout: Duration) -> Self {
+    pub fn new(db_config: Arc<DatabaseConfig>, max_rows: usize, limits: SessionLimits) -> Self {
         Self {
-            db_config,
+            db_config: parking_lot::RwLock::new(db_config),
             connections: Mutex::new(HashMap::new()),
-            max_rows,
-            session_timeout,
+            max_rows: AtomicUsize::new(max_rows),
+            limits: parking_lot::RwLock::new(limits),
+            expired: parking_lot::Mutex::new(VecDeque::new()),
         }
     }
 
+    /// Update the session limits, e.g. after a configuration reload.
+    ///
+    /// Existing sessions beyond a lowered limit are kept until they end.
+    pub fn set_limits(&self, limits: SessionLimits) {
+        *self.limits.write() = limits;
+    }
+
+    /// Current session limits.
+    pub fn limits(&self) -> SessionLimits {
+        *self.limits.read()
+    }
+
+    /// Run `cleanup_expired` every `interval` until the manager is dropped.
+    ///
+    /// `on_expired` is called with the ID of every session ended. Must be
+    /// called within a Tokio runtime.
+    pub fn spawn_cleanup<F>(self: &Arc<Self>, interval: Duration, on_expired: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        if interval.is_zero() {
+            debug!("Pinned session cleanup disabled");
+            return;
+        }
+
+        let manager: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                for (id, _) in manager.cleanup_expired().await {
+                    on_expired(&id);
+                }
+            }
+        });
+    }
+
+    /// Error for a session ID that is not active.
+    fn not_found(&self, session_id: &str) -> ServerError {
+        let expired = self.expired.lock();
+        match expired.iter().find(|(id, _)| id == session_id) {
+            Some((_, reason)) => ServerError::Session(format!(
+                "Session {} was ended after reaching its {}; start a new session",
+                session_id, reason
+            )),
+            None => ServerError::Session(format!("Session not found: {}", session_id)),
+        }
+    }
+
+    /// Update the default row limit, e.g. after a configuration reload.
+    pub fn set_max_rows(&self, max_rows: usize) {
+        self.max_rows.store(max_rows, Ordering::Relaxed);
+    }
+
+    /// Connect new sessions with `auth`, e.g. after a credential rotation.
+    ///
+    /// Open sessions keep the connection they were started on.
+    pub fn set_auth(&self, auth: AuthConfig) {
+        let mut db_config = self.db_config.write();
+        let mut updated = DatabaseConfig::clone(&db_config);
+        updated.auth = auth;
+        *db_config = Arc::new(updated);
+    }
+
     /// Create a new raw connection using the database configuration.
     async fn create_session_connection(&self) -> Result<RawConnection, ServerError> {
-        create_connection(&self.db_config, Some("session")).await
+        let db_config = Arc::clone(&self.db_config.read());
+        create_connection(&db_config, Some("session")).await
     }
 
-    /// Begin a new pinned session.
+    /// Begin a new pinned session, optionally in a specific database.
     ///
     /// Returns the session ID if successful.
-    pub async fn begin_session(&self, session_id: &str) -> Result<SessionInfo, ServerError> {
+    pub async fn begin_session(
+        &self,
+        session_id: &str,
+        database: Option<&str>,
+    ) -> Result<SessionInfo, ServerError> {
         // Check if session already exists
         {
             let connections = self.connections.lock().await;
@@ -83,26 +228,45 @@ impl SessionManager {
                     session_id
                 )));
             }
+            self.check_capacity(connections.len())?;
         }
 
         // Create a dedicated connection for this session
-        let conn = self.create_session_connection().await?;
+        let mut conn = self.create_session_connection().await?;
+        if let Some(db) = database {
+            use_database(&mut conn, db).await?;
+        }
 
         let info = SessionInfo {
             id: session_id.to_string(),
             created_at: Instant::now(),
             last_activity: Instant::now(),
             query_count: 0,
+            database: database.map(str::to_string),
         };
 
-        // Store the connection
+        // Store the connection, unless other sessions took the remaining slots
         let mut connections = self.connections.lock().await;
+        self.check_capacity(connections.len())?;
         connections.insert(session_id.to_string(), (conn, info.clone()));
 
         debug!("Session {} started with dedicated connection", session_id);
         Ok(info)
     }
 
+    /// Fail if another session would exceed the session limit.
+    fn check_capacity(&self, active: usize) -> Result<(), ServerError> {
+        let max_sessions = self.limits.read().max_sessions;
+        if active >= max_sessions {
+            return Err(ServerError::Session(format!(
+                "Maximum number of pinned sessions ({}) reached; end an unused session \
+                 with end_pinned_session",
+                max_sessions
+            )));
+        }
+        Ok(())
+    }
+
     /// Execute a query within an existing session.
     pub async fn execute_in_session(
         &self,
@@ -114,7 +278,7 @@ impl SessionManager {
         let mut connections = self.connections.lock().await;
         let (conn, info) = connections
             .get_mut(session_id)
-            .ok_or_else(|| ServerError::Session(format!("Session not found: {}", session_id)))?;
+            .ok_or_else(|| self.not_found(session_id))?;
 
         // Update last activity and query count
         info.last_activity = Instant::now();
@@ -128,7 +292,7 @@ impl SessionManager {
 
         // Execute query and collect stream
         let stream = conn
-            .query(query, &[])
+            .query(&tag_query(query), &[])
             .await
             .map_err(|e| ServerError::query_error(format!("Query execution failed: {}", e)))?;
 
@@ -138,6 +302,7 @@ impl SessionManager {
 
         // Convert results
         let result = self.process_rows(rows, start);
+        record_request_rows(result.rows.len() as u64);
 
         debug!(
             "Session query completed: {} rows in {} ms",
@@ -148,12 +313,31 @@ impl SessionManager {
         Ok(result)
     }
 
+    /// Switch the database of a session without affecting other connections.
+    pub async fn use_database(
+        &self,
+        session_id: &str,
+        database: &str,
+    ) -> Result<SessionInfo, ServerError> {
+        let mut connections = self.connections.lock().await;
+        let (conn, info) = connections
+            .get_mut(session_id)
+            .ok_or_else(|| self.not_found(session_id))?;
+
+        use_database(conn, database).await?;
+        info.last_activity = Instant::now();
+        info.database = Some(database.to_string());
+
+        debug!("Session {} now using database {}", session_id, database);
+        Ok(info.clone())
+    }
+
     /// End a session and release its connection.
     pub async fn end_session(&self, session_id: &str) -> Result<SessionInfo, ServerError> {
         let mut connections = self.connections.lock().await;
         let (mut conn, info) = connections
             .remove(session_id)
-            .ok_or_else(|| ServerError::Session(format!("Session not found: {}", session_id)))?;
+            .ok_or_else(|| self.not_found(session_id))?;
 
         // Clean up any temp tables or transactions before closing
         // This is best-effort - we don't fail if cleanup fails
@@ -193,35 +377,48 @@ impl SessionManager {
         connections.len()
     }
 
-    /// Clean up expired sessions.
+    /// End sessions past their idle timeout or maximum lifetime.
     ///
-    /// This should be called periodically to release idle sessions.
-    pub async fn cleanup_expired(&self) -> Vec<String> {
+    /// Any open transaction is rolled back and the connection closed. Returns
+    /// the IDs of the ended sessions with the reason.
+    pub async fn cleanup_expired(&self) -> Vec<(String, ExpiryReason)> {
+        let limits = self.limits();
         let mut connections = self.connections.lock().await;
         let now = Instant::now();
 
-        let expired: Vec<String> = connections
+        let expired: Vec<(String, ExpiryReason)> = connections
             .iter()
-            .filter(|(_, (_, info))| now.duration_since(info.last_activity) > self.session_timeout)
-            .map(|(id, _)| id.clone())
+            .filter_map(|(id, (_, info))| limits.expiry(info, now).map(|r| (id.clone(), r)))
             .collect();
 
         let mut cleaned = Vec::new();
-        for id in expired {
+        for (id, reason) in expired {
             if let Some((mut conn, info)) = connections.remove(&id) {
                 warn!(
-                    "Cleaning up expired session {} (idle for {:?})",
+                    "Ending pinned session {}: {} reached (age {:?}, idle {:?}, {} queries)",
                     id,
-                    now.duration_since(info.last_activity)
+                    reason,
+                    now.duration_since(info.created_at),
+                    now.duration_since(info.last_activity),
+                    info.query_count
                 );
                 // Try to clean up before dropping
                 let _ = conn
                     .execute("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION", &[])
                     .await;
-                cleaned.push(id);
+                cleaned.push((id, reason));
+            }
+        }
+        drop(connections);
+
+        if !cleaned.is_empty() {
+            info!("Ended {} expired pinned session(s)", cleaned.len());
+            let mut remembered = self.expired.lock();
+            remembered.extend(cleaned.iter().cloned());
+            while remembered.len() > EXPIRED_SESSION_MEMORY {
+                remembered.pop_front();
             }
         }
-
         cleaned
     }
 
@@ -253,7 +450,7 @@ impl SessionManager {
             }
 
             // Check row limit
-            if result_rows.len() >= self.max_rows {
+            if result_rows.len() >= self.max_rows.load(Ordering::Relaxed) {
                 truncated = true;
                 continue;
             }
@@ -273,6 +470,8 @@ impl SessionManager {
             rows_affected: 0,
             execution_time_ms: start.elapsed().as_millis() as u64,
             truncated,
+            retry: None,
+            messages: Vec::new(),
         }
     }
 }
@@ -0,0 +1,318 @@
+//! Slow query log.
+//!
+//! Statements that run longer than the configured threshold are logged and
+//! kept in a bounded in-memory ring buffer together with their duration, row
+//! count and the waits the session accumulated while running them. Pooled
+//! connections are reset when returned, so `sys.dm_exec_session_wait_stats`
+//! for the session reflects just the slow statement. Optionally the plan is
+//! captured from the plan cache: the last actual plan where
+//! `LAST_QUERY_PLAN_STATS` is enabled (SQL Server 2019+), otherwise the cached
+//! estimated plan.
+
+use crate::config::QueryConfig;
+use crate::database::auth::truncate_for_log;
+use crate::database::connection::PooledConn;
+use crate::telemetry::current_request;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Longest query text kept per entry.
+const MAX_QUERY_TEXT: usize = 4000;
+
+/// Waits of the current session, largest first.
+const WAIT_STATS_QUERY: &str = "\
+SELECT TOP (5) wait_type, wait_time_ms, waiting_tasks_count \
+FROM sys.dm_exec_session_wait_stats \
+WHERE session_id = @@SPID AND wait_time_ms > 0 \
+ORDER BY wait_time_ms DESC";
+
+/// A wait type the session spent time on while running a slow query.
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitStat {
+    /// Wait type, e.g. `PAGEIOLATCH_SH` or `LCK_M_S`.
+    pub wait_type: String,
+    /// Total time spent waiting in milliseconds.
+    pub wait_time_ms: i64,
+    /// Number of waits of this type.
+    pub waiting_tasks: i64,
+}
+
+/// A statement that exceeded the slow query threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowQuery {
+    /// Sequence number of the entry.
+    pub id: u64,
+    /// When the statement finished.
+    pub timestamp: DateTime<Utc>,
+    /// Request ID of the tool call that ran the statement.
+    pub request_id: Option<String>,
+    /// Tool that ran the statement.
+    pub tool: Option<String>,
+    /// Statement text (truncated).
+    pub query: String,
+    /// Elapsed time in milliseconds.
+    pub duration_ms: u64,
+    /// Rows returned or affected.
+    pub rows: u64,
+    /// Largest session waits while the statement ran.
+    pub waits: Vec<WaitStat>,
+    /// `actual` or `cached`, when a plan was captured.
+    pub plan_kind: Option<String>,
+    /// Execution plan XML, when captured.
+    pub plan_xml: Option<String>,
+}
+
+/// Slow query log settings.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    threshold: Duration,
+    capture_plans: bool,
+    capacity: usize,
+}
+
+/// Bounded log of statements exceeding the slow query threshold.
+pub struct SlowQueryLog {
+    settings: RwLock<Settings>,
+    entries: Mutex<VecDeque<SlowQuery>>,
+    next_id: AtomicU64,
+}
+
+impl SlowQueryLog {
+    /// Create a disabled slow query log.
+    pub fn new() -> Self {
+        Self {
+            settings: RwLock::new(Settings {
+                threshold: Duration::ZERO,
+                capture_plans: false,
+                capacity: 0,
+            }),
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Apply the slow query settings, e.g. on startup or configuration reload.
+    pub fn configure(&self, config: &QueryConfig) {
+        let settings = Settings {
+            threshold: Duration::from_millis(config.slow_query_threshold_ms),
+            capture_plans: config.slow_query_plans,
+            capacity: config.slow_query_log_size,
+        };
+        *self.settings.write() = settings;
+
+        let mut entries = self.entries.lock();
+        while entries.len() > settings.capacity {
+            entries.pop_front();
+        }
+    }
+
+    /// Whether slow queries are being recorded.
+    pub fn is_enabled(&self) -> bool {
+        let settings = *self.settings.read();
+        !settings.threshold.is_zero() && settings.capacity > 0
+    }
+
+    /// Threshold above which statements are recorded.
+    pub fn threshold(&self) -> Duration {
+        self.settings.read().threshold
+    }
+
+    /// Whether execution plans are captured.
+    pub fn captures_plans(&self) -> bool {
+        self.settings.read().capture_plans
+    }
+
+    /// Record a statement if it exceeded the threshold.
+    ///
+    /// `sql` must be the exact batch text sent to the server; it is used to
+    /// find the plan. Must be called on the connection that ran the statement,
+    /// before it is returned to the pool.
+    pub async fn observe(&self, conn: &mut PooledConn, sql: &str, elapsed: Duration, rows: u64) {
+        let settings = *self.settings.read();
+        if settings.threshold.is_zero() || settings.capacity == 0 || elapsed < settings.threshold {
+            return;
+        }
+
+        let waits = session_waits(conn).await.unwrap_or_else(|e| {
+            debug!("Failed to read session waits for slow query: {}", e);
+            Vec::new()
+        });
+        let (plan_kind, plan_xml) = if settings.capture_plans {
+            match cached_plan(conn, sql).await {
+                Ok(Some((kind, xml))) => (Some(kind), Some(xml)),
+                Ok(None) => (None, None),
+                Err(e) => {
+                    debug!("Failed to capture plan for slow query: {}", e);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        let request = current_request();
+        let entry = SlowQuery {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: Utc::now(),
+            request_id: request.as_ref().map(|r| r.correlation_id.clone()),
+            tool: request.and_then(|r| r.operation),
+            query: truncate_for_log(sql, MAX_QUERY_TEXT),
+            duration_ms: elapsed.as_millis() as u64,
+            rows,
+            waits,
+            plan_kind,
+            plan_xml,
+        };
+
+        warn!(
+            "Slow query ({} ms, {} rows, top wait {}): {}",
+            entry.duration_ms,
+            entry.rows,
+            entry.waits.first().map_or("none", |w| w.wait_type.as_str()),
+            truncate_for_log(sql, 200)
+        );
+
+        let mut entries = self.entries.lock();
+        entries.push_back(entry);
+        while entries.len() > settings.capacity {
+            entries.pop_front();
+        }
+    }
+
+    /// Recorded slow queries, most recent first.
+    pub fn entries(&self, limit: usize) -> Vec<SlowQuery> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of entries currently held.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Whether no slow queries are held.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+impl Default for SlowQueryLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read the largest waits of the connection's session.
+async fn session_waits(conn: &mut PooledConn) -> Result<Vec<WaitStat>, String> {
+    let rows: Vec<mssql_client::Row> = conn
+        .query(WAIT_STATS_QUERY, &[])
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .map(|row| WaitStat {
+            wait_type: row.try_get::<String>(0).unwrap_or_default(),
+            wait_time_ms: row.try_get::<i64>(1).unwrap_or(0),
+            waiting_tasks: row.try_get::<i64>(2).unwrap_or(0),
+        })
+        .collect())
+}
+
+/// Find the plan of the batch with text `sql` in the plan cache.
+///
+/// Prefers the last actual plan (`sys.dm_exec_query_plan_stats`, SQL Server
+/// 2019+) and falls back to the cached estimated plan on older servers.
+async fn cached_plan(conn: &mut PooledConn, sql: &str) -> Result<Option<(String, String)>, String> {
+    let text = sql.replace('\'', "''");
+    let actual = plan_query(&text, true);
+    match fetch_plan(conn, &actual).await {
+        Ok(plan) => Ok(plan),
+        Err(e) => {
+            debug!("Actual plan unavailable, using cached plan: {}", e);
+            fetch_plan(conn, &plan_query(&text, false)).await
+        }
+    }
+}
+
+/// Build the plan lookup for an escaped batch text.
+fn plan_query(escaped_text: &str, with_actual: bool) -> String {
+    let (actual_apply, plan, kind) = if with_actual {
+        (
+            "OUTER APPLY sys.dm_exec_query_plan_stats(qs.plan_handle) ps ",
+            "COALESCE(ps.query_plan, qp.query_plan)",
+            "CASE WHEN ps.query_plan IS NOT NULL THEN 'actual' ELSE 'cached' END",
+        )
+    } else {
+        ("", "qp.query_plan", "'cached'")
+    };
+    format!(
+        "SELECT TOP (1) {kind} AS plan_kind, CAST({plan} AS nvarchar(max)) AS plan_xml \
+         FROM sys.dm_exec_query_stats qs \
+         CROSS APPLY sys.dm_exec_sql_text(qs.sql_handle) st \
+         OUTER APPLY sys.dm_exec_query_plan(qs.plan_handle) qp \
+         {actual_apply}\
+         WHERE st.text = N'{escaped_text}' AND qp.query_plan IS NOT NULL \
+         ORDER BY qs.last_execution_time DESC"
+    )
+}
+
+/// Run a plan lookup and return `(plan_kind, plan_xml)`.
+async fn fetch_plan(
+    conn: &mut PooledConn,
+    query: &str,
+) -> Result<Option<(String, String)>, String> {
+    let rows: Vec<mssql_client::Row> = conn
+        .query(query, &[])
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .first()
+        .and_then(|row| Some((row.try_get::<String>(0)?, row.try_get::<String>(1)?))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_and_plan_lookup() {
+        let log = SlowQueryLog::new();
+        assert!(!log.is_enabled());
+
+        log.configure(&QueryConfig {
+            slow_query_threshold_ms: 500,
+            slow_query_log_size: 2,
+            ..QueryConfig::default()
+        });
+        assert!(log.is_enabled());
+        assert_eq!(log.threshold(), Duration::from_millis(500));
+        assert!(!log.captures_plans());
+
+        let query = plan_query("SELECT ''x''", true);
+        assert!(query.contains("st.text = N'SELECT ''x'''"));
+        assert!(query.contains("dm_exec_query_plan_stats"));
+        assert!(!plan_query("SELECT 1", false).contains("dm_exec_query_plan_stats"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Schema grounding for natural-language-to-SQL generation.
+//!
+//! `generate_sql` asks the client's model (MCP sampling) to write a query for
+//! a question. The tables most relevant to the question are picked by matching
+//! its words against table and column names, then widened with the tables they
+//! reference through foreign keys, and rendered as compact definitions for the
+//! prompt. Nothing here executes SQL.
+
+use crate::database::migration::SchemaTable;
+use serde::Serialize;
+
+/// Instructions sent as the system prompt of the sampling request.
+pub const SYSTEM_PROMPT: &str = "You write Microsoft SQL Server (T-SQL) queries. \
+    Use only the tables and columns listed in the schema excerpts, with \
+    schema-qualified names. Prefer a single read-only SELECT statement. \
+    Reply with the query in one ```sql code block and nothing else.";
+
+/// Words that never identify a table or column.
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "that", "this", "what", "which", "who", "whose", "how",
+    "many", "much", "are", "was", "were", "have", "has", "had", "all", "each", "per", "show",
+    "list", "give", "find", "get", "top", "last", "first", "most", "least", "than", "more", "less",
+    "their", "there", "where", "when", "did", "does",
+];
+
+/// A table definition included in the prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaExcerpt {
+    /// Table name as `schema.table`.
+    pub table: String,
+    /// Why the table was included: requested, matched words, or a foreign key.
+    pub reason: String,
+    /// Columns, primary key and foreign keys.
+    pub definition: String,
+}
+
+/// Pick the tables relevant to a question, up to `max_tables`.
+///
+/// Tables named in `required` come first, then tables whose name (3 points
+/// per word) or columns (1 point per word) match the question, then tables
+/// they reference. A schema no larger than `max_tables` is included whole
+/// when no table matches.
+pub fn select_tables(
+    schema: &str,
+    tables: &[SchemaTable],
+    question: &str,
+    required: &[String],
+    max_tables: usize,
+) -> Vec<SchemaExcerpt> {
+    let max_tables = max_tables.max(1);
+    let terms = question_terms(question);
+    let mut picked: Vec<(usize, String)> = Vec::new();
+
+    for name in required {
+        let name = name.rsplit('.').next().unwrap_or(name);
+        if let Some(index) = tables
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(name))
+        {
+            if !picked.iter().any(|(i, _)| *i == index) {
+                picked.push((index, "requested".to_string()));
+            }
+        }
+    }
+
+    let mut scored: Vec<(usize, usize, Vec<String>)> = tables
+        .iter()
+        .enumerate()
+        .filter_map(|(index, table)| {
+            let mut score = 0;
+            let mut matched = Vec::new();
+            for term in &terms {
+                if name_words(&table.name).iter().any(|w| same_word(w, term)) {
+                    score += 3;
+                    matched.push(term.clone());
+                } else if table
+                    .columns
+                    .iter()
+                    .any(|c| name_words(&c.name).iter().any(|w| same_word(w, term)))
+                {
+                    score += 1;
+                    matched.push(term.clone());
+                }
+            }
+            (score > 0).then_some((index, score, matched))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    for (index, _, matched) in scored {
+        if picked.len() >= max_tables {
+            break;
+        }
+        if !picked.iter().any(|(i, _)| *i == index) {
+            picked.push((index, format!("matches: {}", matched.join(", "))));
+        }
+    }
+
+    if picked.is_empty() && tables.len() <= max_tables {
+        picked = (0..tables.len())
+            .map(|index| (index, "small schema".to_string()))
+            .collect();
+    }
+
+    // Add referenced tables so joins can be written
+    let mut next = 0;
+    while next < picked.len() && picked.len() < max_tables {
+        let (index, _) = picked[next];
+        let source = &tables[index];
+        for fk in &source.foreign_keys {
+            if picked.len() >= max_tables || !fk.referenced_schema.eq_ignore_ascii_case(schema) {
+                continue;
+            }
+            let Some(referenced) = tables
+                .iter()
+                .position(|t| t.name.eq_ignore_ascii_case(&fk.referenced_table))
+            else {
+                continue;
+            };
+            if !picked.iter().any(|(i, _)| *i == referenced) {
+                picked.push((
+                    referenced,
+                    format!("referenced by {}.{}", schema, source.name),
+                ));
+            }
+        }
+        next += 1;
+    }
+
+    picked
+        .into_iter()
+        .map(|(index, reason)| SchemaExcerpt {
+            table: format!("{}.{}", schema, tables[index].name),
+            reason,
+            definition: table_definition(schema, &tables[index]),
+        })
+        .collect()
+}
+
+/// Render a table as a compact, DDL-like definition.
+pub fn table_definition(schema: &str, table: &SchemaTable) -> String {
+    let mut lines = vec![format!("TABLE {}.{}", schema, table.name)];
+    for column in &table.columns {
+        lines.push(format!(
+            "  {} {}{}",
+            column.name,
+            column.type_definition(),
+            if column.is_nullable {
+                " NULL"
+            } else {
+                " NOT NULL"
+            }
+        ));
+    }
+    if !table.primary_key.is_empty() {
+        lines.push(format!("  PRIMARY KEY ({})", table.primary_key.join(", ")));
+    }
+    for fk in &table.foreign_keys {
+        lines.push(format!(
+            "  FOREIGN KEY ({}) REFERENCES {}.{} ({})",
+            fk.columns.join(", "),
+            fk.referenced_schema,
+            fk.referenced_table,
+            fk.referenced_columns.join(", ")
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Build the user message of the sampling request.
+pub fn sampling_prompt(
+    question: &str,
+    excerpts: &[SchemaExcerpt],
+    server_version: Option<u32>,
+) -> String {
+    let definitions = excerpts
+        .iter()
+        .map(|e| e.definition.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let version = server_version
+        .map(|v| format!(" The server is SQL Server major version {}.", v))
+        .unwrap_or_default();
+    format!(
+        "Schema excerpts:\n\n{}\n\nWrite a T-SQL query that answers this question.{}\n\nQuestion: {}",
+        definitions, version, question
+    )
+}
+
+/// Take the SQL out of a model reply, preferring a fenced code block.
+pub fn extract_sql(reply: &str) -> String {
+    if let Some(start) = reply.find("```") {
+        let block = &reply[start + 3..];
+        // Skip the language tag on the opening fence
+        let block = block.split_once('\n').map_or(block, |(_, rest)| rest);
+        let block = block.split("```").next().unwrap_or(block);
+        return block.trim().to_string();
+    }
+    reply.trim().to_string()
+}
+
+/// Lowercase words of a question that may name tables or columns.
+fn question_terms(question: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in question
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(name_words)
+    {
+        if word.len() >= 3 && !STOP_WORDS.contains(&word.as_str()) && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Split a name into lowercase words at underscores and case changes.
+fn name_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c == '_' || c == ' ' {
+            previous_lower = false;
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Whether two lowercase words are the same, ignoring plural endings.
+fn same_word(a: &str, b: &str) -> bool {
+    singular(a) == singular(b)
+}
+
+fn singular(word: &str) -> &str {
+    if word.len() > 3 && word.ends_with("ies") {
+        // "categories" and "category" share "categor"
+        &word[..word.len() - 3]
+    } else if word.len() > 3
+        && (word.ends_with('y') || (word.ends_with('s') && !word.ends_with("ss")))
+    {
+        &word[..word.len() - 1]
+    } else {
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migration::{SchemaColumn, SchemaForeignKey};
+
+    fn column(name: &str, data_type: &str) -> SchemaColumn {
+        SchemaColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            max_length: None,
+            precision: None,
+            scale: None,
+            is_nullable: false,
+        }
+    }
+
+    fn tables() -> Vec<SchemaTable> {
+        vec![
+            SchemaTable {
+                name: "Customers".to_string(),
+                columns: vec![column("CustomerId", "int"), column("Country", "int")],
+                primary_key: vec!["CustomerId".to_string()],
+                foreign_keys: Vec::new(),
+            },
+            SchemaTable {
+                name: "Orders".to_string(),
+                columns: vec![column("OrderId", "int"), column("CustomerId", "int")],
+                primary_key: vec!["OrderId".to_string()],
+                foreign_keys: vec![SchemaForeignKey {
+                    name: "FK_Orders_Customers".to_string(),
+                    columns: vec!["CustomerId".to_string()],
+                    referenced_schema: "dbo".to_string(),
+                    referenced_table: "Customers".to_string(),
+                    referenced_columns: vec!["CustomerId".to_string()],
+                }],
+            },
+            SchemaTable {
+                name: "ProductCategories".to_string(),
+                columns: vec![column("CategoryId", "int")],
+                primary_key: Vec::new(),
+                foreign_keys: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_select_tables() {
+        let tables = tables();
+        let excerpts = select_tables("dbo", &tables, "How many orders were placed?", &[], 5);
+        let names: Vec<&str> = excerpts.iter().map(|e| e.table.as_str()).collect();
+        assert_eq!(names, ["dbo.Orders", "dbo.Customers"]);
+        assert_eq!(excerpts[1].reason, "referenced by dbo.Orders");
+        assert!(excerpts[0]
+            .definition
+            .contains("FOREIGN KEY (CustomerId) REFERENCES dbo.Customers (CustomerId)"));
+
+        let excerpts = select_tables("dbo", &tables, "Products per category", &[], 5);
+        assert_eq!(excerpts[0].table, "dbo.ProductCategories");
+
+        let excerpts = select_tables(
+            "dbo",
+            &tables,
+            "orders by month",
+            &["dbo.ProductCategories".to_string()],
+            1,
+        );
+        assert_eq!(excerpts.len(), 1);
+        assert_eq!(excerpts[0].reason, "requested");
+
+        assert_eq!(select_tables("dbo", &tables, "revenue", &[], 5).len(), 3);
+        assert!(select_tables("dbo", &tables, "revenue", &[], 2).is_empty());
+    }
+
+    #[test]
+    fn test_extract_sql() {
+        assert_eq!(
+            extract_sql("Here you go:\n```sql\nSELECT 1;\n```\nDone."),
+            "SELECT 1;"
+        );
+        assert_eq!(extract_sql("  SELECT 2  "), "SELECT 2");
+    }
+}
@@ -0,0 +1,447 @@
+//! Per-statement results of a batch.
+//!
+//! The driver returns the result sets of a batch but not the row count of
+//! each statement. To report both, the batch is split at top-level
+//! semicolons and every statement that counts rows is followed by a marker
+//! `SELECT @@ROWCOUNT`; the marker result sets are then used to assign the
+//! other result sets to their statements.
+//!
+//! Batches with control flow, batches reading `@@ROWCOUNT` themselves and
+//! batch-first DDL are not split, since a marker would change what they do;
+//! they are reported as a single statement.
+
+use crate::database::query::{QueryExecutor, QueryResult};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+/// Column name of the marker result set.
+const ROWS_AFFECTED_MARKER: &str = "__mcp_rows_affected";
+
+/// Keywords whose statements span semicolons or depend on what follows.
+static CONTROL_FLOW: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(IF|ELSE|WHILE|BEGIN|GOTO|RETURN|WAITFOR|BREAK|CONTINUE)\b|@@ROWCOUNT")
+        .unwrap()
+});
+
+/// Statements after which `@@ROWCOUNT` is their own row count.
+static COUNTS_ROWS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^[\s(]*(SELECT|INSERT|UPDATE|DELETE|MERGE|WITH|EXEC|EXECUTE)\b").unwrap()
+});
+
+/// A batch rewritten with row count markers.
+#[derive(Debug, Clone)]
+pub struct InstrumentedBatch {
+    /// SQL to execute.
+    pub sql: String,
+    /// Statements in order, with whether a marker follows them.
+    pub statements: Vec<(String, bool)>,
+}
+
+/// Split a batch at top-level semicolons.
+///
+/// Semicolons in string literals, quoted or bracketed identifiers and
+/// comments do not split. Also returns the batch without its literals and
+/// comments, for keyword checks.
+fn split_statements(query: &str) -> (Vec<String>, String) {
+    let mut statements = Vec::new();
+    let mut code = String::with_capacity(query.len());
+    let mut current = String::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                current.push(c);
+                while let Some(n) = chars.next() {
+                    current.push(n);
+                    if n == close {
+                        if chars.peek() == Some(&close) {
+                            current.push(chars.next().unwrap_or(close));
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                code.push(' ');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                current.push(c);
+                for n in chars.by_ref() {
+                    current.push(n);
+                    if n == '\n' {
+                        break;
+                    }
+                }
+                code.push('\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                current.push(c);
+                current.push(chars.next().unwrap_or('*'));
+                let mut previous = ' ';
+                for n in chars.by_ref() {
+                    current.push(n);
+                    if previous == '*' && n == '/' {
+                        break;
+                    }
+                    previous = n;
+                }
+                code.push(' ');
+            }
+            ';' => {
+                statements.push(std::mem::take(&mut current));
+                code.push(';');
+            }
+            _ => {
+                current.push(c);
+                code.push(c);
+            }
+        }
+    }
+    statements.push(current);
+
+    let statements = statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    (statements, code)
+}
+
+/// A statement without the comments in front of it.
+fn without_leading_comments(statement: &str) -> &str {
+    let mut rest = statement.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, r)| r).trim_start();
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, r)| r).trim_start();
+        } else {
+            return rest;
+        }
+    }
+}
+
+/// Rewrite a batch so the row count of each statement is reported.
+pub fn instrument_batch(query: &str) -> InstrumentedBatch {
+    let marker = format!(
+        "SELECT CAST(@@ROWCOUNT AS BIGINT) AS [{}]",
+        ROWS_AFFECTED_MARKER
+    );
+    if QueryExecutor::requires_raw_execution(query) {
+        return InstrumentedBatch {
+            sql: query.to_string(),
+            statements: vec![(query.trim().to_string(), false)],
+        };
+    }
+
+    let (split, code) = split_statements(query);
+    if CONTROL_FLOW.is_match(&code) || split.len() < 2 {
+        let statement = query.trim().trim_end_matches(';').trim_end().to_string();
+        let counts = COUNTS_ROWS.is_match(without_leading_comments(&statement));
+        return InstrumentedBatch {
+            sql: if counts {
+                format!("{}\n;\n{}", statement, marker)
+            } else {
+                query.to_string()
+            },
+            statements: vec![(statement, counts)],
+        };
+    }
+
+    let mut parts = Vec::new();
+    let statements: Vec<(String, bool)> = split
+        .into_iter()
+        .map(|statement| {
+            let counts = COUNTS_ROWS.is_match(without_leading_comments(&statement));
+            parts.push(statement.clone());
+            if counts {
+                parts.push(marker.clone());
+            }
+            (statement, counts)
+        })
+        .collect();
+
+    InstrumentedBatch {
+        // Separators on their own line so a trailing line comment cannot swallow them
+        sql: parts.join("\n;\n"),
+        statements,
+    }
+}
+
+/// Result of one statement of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementResult {
+    /// Statement text.
+    pub statement: String,
+    /// Rows affected (or returned, for a SELECT).
+    pub rows_affected: u64,
+    /// Result sets the statement returned.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub result_sets: Vec<QueryResult>,
+}
+
+/// Results of every statement of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    pub statements: Vec<StatementResult>,
+    /// Rows affected by all statements.
+    pub rows_affected: u64,
+    /// Informational messages from PRINT and low-severity RAISERROR.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<String>,
+    /// Execution time in milliseconds.
+    pub execution_time_ms: u64,
+}
+
+impl BatchResult {
+    /// Assign the result sets of an instrumented batch to its statements.
+    ///
+    /// Result sets left over after the last marker belong to the last
+    /// statement.
+    pub fn from_result_sets(
+        batch: &InstrumentedBatch,
+        result_sets: Vec<QueryResult>,
+        messages: Vec<String>,
+        execution_time_ms: u64,
+    ) -> Self {
+        let mut sets = result_sets.into_iter();
+        let mut statements: Vec<StatementResult> = batch
+            .statements
+            .iter()
+            .map(|(statement, counted)| {
+                let mut result = StatementResult {
+                    statement: statement.clone(),
+                    rows_affected: 0,
+                    result_sets: Vec::new(),
+                };
+                if *counted {
+                    for set in sets.by_ref() {
+                        if let Some(count) = marker_count(&set) {
+                            result.rows_affected = count;
+                            break;
+                        }
+                        result.result_sets.push(set);
+                    }
+                }
+                result
+            })
+            .collect();
+        if let Some(last) = statements.last_mut() {
+            last.result_sets
+                .extend(sets.filter(|set| marker_count(set).is_none()));
+        }
+
+        Self {
+            rows_affected: statements.iter().map(|s| s.rows_affected).sum(),
+            statements,
+            messages,
+            execution_time_ms,
+        }
+    }
+
+    /// All result sets in order.
+    pub fn result_sets(&self) -> impl Iterator<Item = &QueryResult> {
+        self.statements.iter().flat_map(|s| s.result_sets.iter())
+    }
+
+    /// All result sets in order, for masking.
+    pub fn result_sets_mut(&mut self) -> impl Iterator<Item = &mut QueryResult> {
+        self.statements
+            .iter_mut()
+            .flat_map(|s| s.result_sets.iter_mut())
+    }
+
+    /// Format as markdown: one section per statement with its row count and
+    /// result sets, followed by any messages.
+    ///
+    /// A single statement is shown like a plain query result.
+    pub fn to_markdown_table(&self) -> String {
+        let mut output = match self.statements.as_slice() {
+            [] => "Query executed successfully. No results returned.".to_string(),
+            [only] => match only.result_sets.as_slice() {
+                [] => format!(
+                    "Query executed successfully. {} row(s) affected.",
+                    only.rows_affected
+                ),
+                sets => sets
+                    .iter()
+                    .map(QueryResult::to_markdown_table)
+                    .collect::<Vec<_>>()
+                    .join("\n\n---\n\n"),
+            },
+            statements => {
+                let mut output = String::new();
+                for (i, statement) in statements.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str("\n\n---\n\n");
+                    }
+                    output.push_str(&format!(
+                        "**Statement {} of {}:** `{}` ({} row(s) affected)",
+                        i + 1,
+                        statements.len(),
+                        crate::database::truncate_for_log(&statement.statement, 100)
+                            .replace('`', "'")
+                            .replace('\n', " "),
+                        statement.rows_affected
+                    ));
+                    for set in &statement.result_sets {
+                        output.push_str("\n\n");
+                        output.push_str(&set.to_markdown_table());
+                    }
+                }
+                output.push_str(&format!(
+                    "\n\n_Total: {} row(s) affected, {} ms_",
+                    self.rows_affected, self.execution_time_ms
+                ));
+                output
+            }
+        };
+        if !self.messages.is_empty() {
+            output.push_str("\n\n**Messages:**\n");
+            for message in &self.messages {
+                output.push_str(&format!("\n    {}", message));
+            }
+        }
+        output
+    }
+
+    /// Format the result sets as CSV, separated by blank lines.
+    pub fn to_csv(&self) -> String {
+        self.result_sets()
+            .map(QueryResult::to_csv)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Row count reported by a marker result set.
+fn marker_count(set: &QueryResult) -> Option<u64> {
+    if set.columns.len() != 1 || set.columns[0].name != ROWS_AFFECTED_MARKER {
+        return None;
+    }
+    Some(
+        set.rows
+            .first()
+            .and_then(|row| row.get(ROWS_AFFECTED_MARKER))
+            .and_then(|v| v.to_display_string().parse().ok())
+            .unwrap_or(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::query::{ColumnInfo, ResultRow};
+    use crate::database::types::SqlValue;
+
+    fn result_set(column: &str, value: i64) -> QueryResult {
+        let mut row = ResultRow::new();
+        row.insert(column.to_string(), SqlValue::I64(value));
+        QueryResult {
+            columns: vec![ColumnInfo {
+                name: column.to_string(),
+                sql_type: "bigint".to_string(),
+                nullable: false,
+            }],
+            rows: vec![row],
+            rows_affected: 0,
+            execution_time_ms: 0,
+            truncated: false,
+            retry: None,
+            messages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_ignores_quoted_semicolons() {
+        let batch = instrument_batch("-- note\nDELETE FROM t; SELECT 1");
+        assert!(batch.statements[0].1);
+
+        let (statements, _) = split_statements(
+            "UPDATE t SET a = 'x;y'; -- note; here\nDELETE FROM [odd;name]; /* ; */ SELECT 1;",
+        );
+        assert_eq!(
+            statements,
+            [
+                "UPDATE t SET a = 'x;y'",
+                "-- note; here\nDELETE FROM [odd;name]",
+                "/* ; */ SELECT 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instrument_adds_markers_after_counting_statements() {
+        let batch = instrument_batch("DECLARE @n int = 1; UPDATE t SET a = @n; SELECT * FROM t;");
+        assert_eq!(
+            batch.statements,
+            [
+                ("DECLARE @n int = 1".to_string(), false),
+                ("UPDATE t SET a = @n".to_string(), true),
+                ("SELECT * FROM t".to_string(), true),
+            ]
+        );
+        assert_eq!(batch.sql.matches(ROWS_AFFECTED_MARKER).count(), 2);
+        assert!(batch
+            .sql
+            .starts_with("DECLARE @n int = 1\n;\nUPDATE t SET a = @n\n;\nSELECT CAST"));
+    }
+
+    #[test]
+    fn test_instrument_keeps_control_flow_whole() {
+        let batch = instrument_batch("IF 1 = 1 UPDATE t SET a = 1; ELSE DELETE FROM t;");
+        assert_eq!(batch.statements.len(), 1);
+        assert!(!batch.statements[0].1);
+
+        let batch = instrument_batch("UPDATE t SET a = 1; SELECT @@ROWCOUNT;");
+        assert_eq!(batch.statements.len(), 1);
+        assert!(batch.statements[0].1);
+
+        let batch = instrument_batch("CREATE VIEW v AS SELECT 1 AS x");
+        assert_eq!(batch.sql, "CREATE VIEW v AS SELECT 1 AS x");
+    }
+
+    #[test]
+    fn test_result_sets_assigned_to_statements() {
+        let batch = instrument_batch("UPDATE t SET a = 1; SELECT a FROM t; DELETE FROM t;");
+        let result = BatchResult::from_result_sets(
+            &batch,
+            vec![
+                result_set(ROWS_AFFECTED_MARKER, 3),
+                result_set("a", 1),
+                result_set(ROWS_AFFECTED_MARKER, 1),
+                result_set(ROWS_AFFECTED_MARKER, 2),
+            ],
+            vec!["done".to_string()],
+            5,
+        );
+        assert_eq!(result.statements.len(), 3);
+        assert_eq!(result.statements[0].rows_affected, 3);
+        assert!(result.statements[0].result_sets.is_empty());
+        assert_eq!(result.statements[1].result_sets.len(), 1);
+        assert_eq!(result.statements[2].rows_affected, 2);
+        assert_eq!(result.rows_affected, 6);
+
+        let markdown = result.to_markdown_table();
+        assert!(markdown.contains("**Statement 1 of 3:** `UPDATE t SET a = 1` (3 row(s) affected)"));
+        assert!(markdown.contains("**Messages:**"));
+        assert_eq!(result.to_csv().lines().next(), Some("a"));
+    }
+
+    #[test]
+    fn test_single_statement_formats_like_query_result() {
+        let batch = instrument_batch("DELETE FROM t WHERE id = 1");
+        let result = BatchResult::from_result_sets(
+            &batch,
+            vec![result_set(ROWS_AFFECTED_MARKER, 1)],
+            Vec::new(),
+            2,
+        );
+        assert_eq!(
+            result.to_markdown_table(),
+            "Query executed successfully. 1 row(s) affected."
+        );
+    }
+}
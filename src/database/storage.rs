@@ -0,0 +1,417 @@
+//! Database file and tempdb space usage.
+//!
+//! File sizes come from `sys.database_files` and `FILEPROPERTY(name,
+//! 'SpaceUsed')`, which only see the current database, so the file query runs
+//! after a `USE`. tempdb consumers add the pages of a session's finished tasks
+//! (`sys.dm_db_session_space_usage`) to those of its running tasks
+//! (`sys.dm_db_task_space_usage`), which are only charged to the session when
+//! the task ends.
+
+use crate::database::query::ResultRow;
+use serde::Serialize;
+
+/// Data files with less free space than this percentage are flagged.
+pub const LOW_FREE_SPACE_PERCENT: f64 = 10.0;
+
+/// One data or log file of a database.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseFile {
+    pub file_id: i64,
+    pub name: String,
+    /// `ROWS`, `LOG`, `FILESTREAM` or `FULLTEXT`.
+    pub file_type: String,
+    pub physical_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filegroup: Option<String>,
+    pub state: String,
+    pub size_mb: f64,
+    /// `None` if the file is offline.
+    pub used_mb: Option<f64>,
+    pub free_mb: Option<f64>,
+    pub free_percent: Option<f64>,
+    /// `None` if the file may grow until the disk is full.
+    pub max_size_mb: Option<f64>,
+    /// Growth increment, e.g. `64 MB` or `10%` (`disabled` for no growth).
+    pub autogrowth: String,
+    pub warnings: Vec<String>,
+}
+
+impl DatabaseFile {
+    /// Build from a row of [`FILES_QUERY`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| text(column).and_then(|v| v.parse::<i64>().ok());
+
+        let size_pages = number("size_pages").unwrap_or(0);
+        let used_pages = number("used_pages");
+        let max_size_pages = number("max_size_pages").unwrap_or(-1);
+        let growth = number("growth").unwrap_or(0);
+        let is_percent_growth = text("is_percent_growth").as_deref() == Some("1");
+
+        let size_mb = pages_to_mb(size_pages);
+        let used_mb = used_pages.map(pages_to_mb);
+        let free_mb = used_mb.map(|used| round2(size_mb - used));
+        let free_percent = used_pages
+            .filter(|_| size_pages > 0)
+            .map(|used| round2((size_pages - used) as f64 * 100.0 / size_pages as f64));
+        let max_size_mb = (max_size_pages > 0).then(|| pages_to_mb(max_size_pages));
+        let autogrowth = match (growth, is_percent_growth) {
+            (0, _) => "disabled".to_string(),
+            (percent, true) => format!("{}%", percent),
+            (pages, false) => format!("{} MB", pages_to_mb(pages)),
+        };
+
+        let mut file = Self {
+            file_id: number("file_id")?,
+            name: text("name")?,
+            file_type: text("type_desc").unwrap_or_default(),
+            physical_name: text("physical_name").unwrap_or_default(),
+            filegroup: text("filegroup"),
+            state: text("state_desc").unwrap_or_default(),
+            size_mb,
+            used_mb,
+            free_mb,
+            free_percent,
+            max_size_mb,
+            autogrowth,
+            warnings: Vec::new(),
+        };
+        file.warnings = file_warnings(&file, growth, is_percent_growth, max_size_pages, size_pages);
+        Some(file)
+    }
+}
+
+/// Storage problems worth pointing out for a file.
+fn file_warnings(
+    file: &DatabaseFile,
+    growth: i64,
+    is_percent_growth: bool,
+    max_size_pages: i64,
+    size_pages: i64,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let low_free = file
+        .free_percent
+        .is_some_and(|free| free < LOW_FREE_SPACE_PERCENT);
+    let at_max = max_size_pages > 0 && size_pages >= max_size_pages;
+
+    if growth == 0 && low_free {
+        warnings.push(format!(
+            "Less than {}% free and autogrowth is disabled",
+            LOW_FREE_SPACE_PERCENT
+        ));
+    } else if at_max && low_free {
+        warnings.push(format!(
+            "Less than {}% free and the file is at its maximum size",
+            LOW_FREE_SPACE_PERCENT
+        ));
+    }
+    if is_percent_growth {
+        warnings.push(
+            "Percentage autogrowth makes each growth larger; use a fixed size in MB".to_string(),
+        );
+    }
+    if file.file_type == "LOG" && !is_percent_growth && growth > 0 && pages_to_mb(growth) < 64.0 {
+        warnings.push("Log growth below 64 MB creates many virtual log files".to_string());
+    }
+    warnings
+}
+
+/// Files of the current database with their space usage and growth settings.
+pub const FILES_QUERY: &str = r#"
+    SELECT
+        f.file_id,
+        f.name,
+        f.type_desc,
+        f.physical_name,
+        fg.name AS filegroup,
+        f.state_desc,
+        CAST(f.size AS bigint) AS size_pages,
+        CAST(FILEPROPERTY(f.name, 'SpaceUsed') AS bigint) AS used_pages,
+        CAST(f.max_size AS bigint) AS max_size_pages,
+        CAST(f.growth AS bigint) AS growth,
+        CAST(f.is_percent_growth AS int) AS is_percent_growth
+    FROM sys.database_files f
+    LEFT JOIN sys.filegroups fg ON fg.data_space_id = f.data_space_id
+    ORDER BY f.type, f.file_id
+"#;
+
+/// Recovery model, log reuse wait and log space of the current database.
+pub const LOG_QUERY: &str = r#"
+    SELECT
+        d.name AS database_name,
+        d.recovery_model_desc,
+        d.log_reuse_wait_desc,
+        CAST(ls.total_log_size_in_bytes / 1048576.0 AS decimal(18, 2)) AS log_size_mb,
+        CAST(ls.used_log_space_in_percent AS decimal(5, 2)) AS log_used_percent
+    FROM sys.databases d
+    OUTER APPLY sys.dm_db_log_space_usage ls
+    WHERE d.database_id = DB_ID()
+"#;
+
+/// What to do about a `log_reuse_wait_desc` that keeps the log from being truncated.
+pub fn log_reuse_wait_hint(wait: &str) -> Option<&'static str> {
+    Some(match wait {
+        "LOG_BACKUP" => "Take a transaction log backup, or switch to SIMPLE recovery if point-in-time restore is not needed",
+        "ACTIVE_TRANSACTION" => "A long-running open transaction holds the log; find it with DBCC OPENTRAN",
+        "ACTIVE_BACKUP_OR_RESTORE" => "A backup or restore is running; the log is reused once it finishes",
+        "REPLICATION" => "Transactions are waiting for the replication log reader or CDC capture job",
+        "AVAILABILITY_REPLICA" => "A secondary replica has not yet received or redone the log; check synchronization health",
+        "DATABASE_MIRRORING" => "The mirror has not yet received the log; check the mirroring session",
+        "CHECKPOINT" => "No checkpoint has run since the last truncation; run CHECKPOINT",
+        "DATABASE_SNAPSHOT_CREATION" => "A database snapshot is being created",
+        "OLDEST_PAGE" => "Indirect checkpoints have not yet flushed the oldest dirty page",
+        "XTP_CHECKPOINT" => "An In-Memory OLTP checkpoint is pending",
+        _ => return None,
+    })
+}
+
+/// tempdb allocation by category, in MB.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TempdbSummary {
+    pub size_mb: f64,
+    pub free_mb: f64,
+    pub user_objects_mb: f64,
+    pub internal_objects_mb: f64,
+    pub version_store_mb: f64,
+    pub mixed_extents_mb: f64,
+}
+
+impl TempdbSummary {
+    /// Build from the row of [`TEMPDB_SUMMARY_QUERY`].
+    pub fn from_row(row: &ResultRow) -> Self {
+        let pages = |column: &str| {
+            pages_to_mb(
+                row.get(column)
+                    .filter(|v| !v.is_null())
+                    .and_then(|v| v.to_display_string().parse::<i64>().ok())
+                    .unwrap_or(0),
+            )
+        };
+        Self {
+            size_mb: pages("total_pages"),
+            free_mb: pages("unallocated_pages"),
+            user_objects_mb: pages("user_object_pages"),
+            internal_objects_mb: pages("internal_object_pages"),
+            version_store_mb: pages("version_store_pages"),
+            mixed_extents_mb: pages("mixed_extent_pages"),
+        }
+    }
+}
+
+/// Space of the tempdb data files by allocation category.
+pub const TEMPDB_SUMMARY_QUERY: &str = r#"
+    SELECT
+        SUM(CAST(f.size AS bigint)) AS total_pages,
+        SUM(u.unallocated_extent_page_count) AS unallocated_pages,
+        SUM(u.user_object_reserved_page_count) AS user_object_pages,
+        SUM(u.internal_object_reserved_page_count) AS internal_object_pages,
+        SUM(u.version_store_reserved_page_count) AS version_store_pages,
+        SUM(u.mixed_extent_page_count) AS mixed_extent_pages
+    FROM tempdb.sys.dm_db_file_space_usage u
+    INNER JOIN tempdb.sys.database_files f ON f.file_id = u.file_id
+"#;
+
+/// A session holding tempdb space.
+#[derive(Debug, Clone, Serialize)]
+pub struct TempdbConsumer {
+    pub session_id: i64,
+    pub login: Option<String>,
+    pub host: Option<String>,
+    pub program: Option<String>,
+    pub database: Option<String>,
+    pub status: Option<String>,
+    /// Temporary tables and table variables.
+    pub user_objects_mb: f64,
+    /// Sorts, hashes, spools and cursors.
+    pub internal_objects_mb: f64,
+    pub total_mb: f64,
+    /// Statement the session is running, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running_query: Option<String>,
+}
+
+impl TempdbConsumer {
+    /// Build from a row of [`tempdb_consumers_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let pages = |column: &str| {
+            pages_to_mb(
+                text(column)
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .unwrap_or(0)
+                    .max(0),
+            )
+        };
+        let user_objects_mb = pages("user_object_pages");
+        let internal_objects_mb = pages("internal_object_pages");
+
+        Some(Self {
+            session_id: text("session_id")?.parse().ok()?,
+            login: text("login_name"),
+            host: text("host_name"),
+            program: text("program_name"),
+            database: text("database_name"),
+            status: text("status"),
+            user_objects_mb,
+            internal_objects_mb,
+            total_mb: round2(user_objects_mb + internal_objects_mb),
+            running_query: text("running_query"),
+        })
+    }
+}
+
+/// Sessions with tempdb space allocated, largest first.
+pub fn tempdb_consumers_query(limit: usize, include_system_sessions: bool) -> String {
+    format!(
+        r#"
+        SELECT TOP ({}) *
+        FROM (
+            SELECT
+                s.session_id,
+                s.login_name,
+                s.host_name,
+                s.program_name,
+                DB_NAME(s.database_id) AS database_name,
+                s.status,
+                su.user_objects_alloc_page_count - su.user_objects_dealloc_page_count
+                    + ISNULL(t.user_alloc, 0) - ISNULL(t.user_dealloc, 0) AS user_object_pages,
+                su.internal_objects_alloc_page_count - su.internal_objects_dealloc_page_count
+                    + ISNULL(t.internal_alloc, 0) - ISNULL(t.internal_dealloc, 0)
+                    AS internal_object_pages,
+                SUBSTRING(qt.text, 1, 500) AS running_query
+            FROM sys.dm_db_session_space_usage su
+            INNER JOIN sys.dm_exec_sessions s ON s.session_id = su.session_id
+            LEFT JOIN (
+                SELECT
+                    session_id,
+                    SUM(user_objects_alloc_page_count) AS user_alloc,
+                    SUM(user_objects_dealloc_page_count) AS user_dealloc,
+                    SUM(internal_objects_alloc_page_count) AS internal_alloc,
+                    SUM(internal_objects_dealloc_page_count) AS internal_dealloc
+                FROM sys.dm_db_task_space_usage
+                GROUP BY session_id
+            ) t ON t.session_id = su.session_id
+            LEFT JOIN sys.dm_exec_requests r ON r.session_id = s.session_id
+            OUTER APPLY sys.dm_exec_sql_text(r.sql_handle) qt
+            WHERE su.database_id = 2{}
+        ) usage
+        WHERE user_object_pages + internal_object_pages > 0
+        ORDER BY user_object_pages + internal_object_pages DESC
+    "#,
+        limit,
+        if include_system_sessions {
+            ""
+        } else {
+            "\n            AND s.is_user_process = 1"
+        }
+    )
+}
+
+/// Convert 8 KB pages to MB, rounded to two decimals.
+pub fn pages_to_mb(pages: i64) -> f64 {
+    round2(pages as f64 / 128.0)
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn file_row(file_type: &str, size: i64, used: i64, growth: i64, percent: bool) -> ResultRow {
+        ResultRow::from_values(&[
+            ("file_id", SqlValue::I64(1)),
+            ("name", SqlValue::String("app_data".into())),
+            ("type_desc", SqlValue::String(file_type.into())),
+            (
+                "physical_name",
+                SqlValue::String("D:\\data\\app.mdf".into()),
+            ),
+            ("state_desc", SqlValue::String("ONLINE".into())),
+            ("size_pages", SqlValue::I64(size)),
+            ("used_pages", SqlValue::I64(used)),
+            ("max_size_pages", SqlValue::I64(-1)),
+            ("growth", SqlValue::I64(growth)),
+            ("is_percent_growth", SqlValue::I32(percent as i32)),
+        ])
+    }
+
+    #[test]
+    fn test_file_sizes_from_pages() {
+        let file = DatabaseFile::from_row(&file_row("ROWS", 12_800, 3_200, 8_192, false)).unwrap();
+        assert_eq!(file.size_mb, 100.0);
+        assert_eq!(file.used_mb, Some(25.0));
+        assert_eq!(file.free_mb, Some(75.0));
+        assert_eq!(file.free_percent, Some(75.0));
+        assert_eq!(file.max_size_mb, None);
+        assert_eq!(file.autogrowth, "64 MB");
+        assert!(file.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_file_autogrowth_warnings() {
+        let full = DatabaseFile::from_row(&file_row("ROWS", 1_000, 950, 0, false)).unwrap();
+        assert_eq!(full.autogrowth, "disabled");
+        assert!(full.warnings[0].contains("autogrowth is disabled"));
+
+        let percent = DatabaseFile::from_row(&file_row("ROWS", 1_000, 100, 10, true)).unwrap();
+        assert_eq!(percent.autogrowth, "10%");
+        assert!(percent.warnings[0].contains("Percentage autogrowth"));
+
+        let log = DatabaseFile::from_row(&file_row("LOG", 1_000, 100, 128, false)).unwrap();
+        assert_eq!(log.autogrowth, "1 MB");
+        assert!(log.warnings[0].contains("virtual log files"));
+    }
+
+    #[test]
+    fn test_offline_file_has_no_usage() {
+        let mut r = file_row("ROWS", 1_000, 0, 0, false);
+        r.insert("used_pages".into(), SqlValue::Null);
+        let file = DatabaseFile::from_row(&r).unwrap();
+        assert_eq!(file.used_mb, None);
+        assert_eq!(file.free_percent, None);
+        assert!(file.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_log_reuse_wait_hint() {
+        assert!(log_reuse_wait_hint("LOG_BACKUP")
+            .unwrap()
+            .contains("log backup"));
+        assert!(log_reuse_wait_hint("NOTHING").is_none());
+    }
+
+    #[test]
+    fn test_tempdb_consumer_totals() {
+        let consumer = TempdbConsumer::from_row(&ResultRow::from_values(&[
+            ("session_id", SqlValue::I32(57)),
+            ("login_name", SqlValue::String("app".into())),
+            ("user_object_pages", SqlValue::I64(256)),
+            ("internal_object_pages", SqlValue::I64(-64)),
+        ]))
+        .unwrap();
+        assert_eq!(consumer.session_id, 57);
+        assert_eq!(consumer.user_objects_mb, 2.0);
+        assert_eq!(consumer.internal_objects_mb, 0.0);
+        assert_eq!(consumer.total_mb, 2.0);
+    }
+
+    #[test]
+    fn test_tempdb_consumers_query_filters_system_sessions() {
+        assert!(tempdb_consumers_query(20, false).contains("is_user_process = 1"));
+        assert!(!tempdb_consumers_query(20, true).contains("is_user_process"));
+        assert!(tempdb_consumers_query(5, false).contains("TOP (5)"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Table size snapshots and growth tracking.
+//!
+//! Each read of the table size dashboard records a snapshot per database.
+//! Growth is reported relative to the previous snapshot, which is kept in
+//! memory and optionally persisted to a JSON file so it survives restarts.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Row count and space usage of one table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSize {
+    pub schema_name: String,
+    pub table_name: String,
+    pub row_count: i64,
+    pub reserved_kb: i64,
+    pub used_kb: i64,
+    pub data_kb: i64,
+    pub index_kb: i64,
+    pub unused_kb: i64,
+}
+
+/// Point-in-time sizes of all tables in a database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSizeSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub tables: Vec<TableSize>,
+}
+
+/// Change of a table's size since the previous snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableGrowth {
+    pub row_count_delta: i64,
+    pub reserved_kb_delta: i64,
+    /// `true` if the table did not exist in the previous snapshot.
+    pub is_new: bool,
+}
+
+impl TableSizeSnapshot {
+    /// Growth of each table relative to an older snapshot, keyed by `schema.table`.
+    pub fn growth_since(&self, older: &TableSizeSnapshot) -> HashMap<String, TableGrowth> {
+        let previous: HashMap<String, &TableSize> =
+            older.tables.iter().map(|t| (table_key(t), t)).collect();
+
+        self.tables
+            .iter()
+            .map(|table| {
+                let growth = match previous.get(&table_key(table)) {
+                    Some(old) => TableGrowth {
+                        row_count_delta: table.row_count - old.row_count,
+                        reserved_kb_delta: table.reserved_kb - old.reserved_kb,
+                        is_new: false,
+                    },
+                    None => TableGrowth {
+                        row_count_delta: table.row_count,
+                        reserved_kb_delta: table.reserved_kb,
+                        is_new: true,
+                    },
+                };
+                (table_key(table), growth)
+            })
+            .collect()
+    }
+
+    /// Tables present in an older snapshot but not in this one.
+    pub fn dropped_since(&self, older: &TableSizeSnapshot) -> Vec<String> {
+        let current: std::collections::HashSet<String> =
+            self.tables.iter().map(table_key).collect();
+        older
+            .tables
+            .iter()
+            .map(table_key)
+            .filter(|key| !current.contains(key))
+            .collect()
+    }
+}
+
+/// Key of a table within a snapshot.
+pub fn table_key(table: &TableSize) -> String {
+    format!("{}.{}", table.schema_name, table.table_name)
+}
+
+/// Most recent table size snapshot per database.
+pub struct TableSizeHistory {
+    /// File the snapshots are persisted to (`None` = memory only).
+    path: Option<PathBuf>,
+    /// Snapshots keyed by database name (loaded from `path` on first use).
+    snapshots: Mutex<Option<HashMap<String, TableSizeSnapshot>>>,
+}
+
+impl TableSizeHistory {
+    /// Create a history, persisted to `path` if given.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            snapshots: Mutex::new(None),
+        }
+    }
+
+    /// Store a new snapshot for a database and return the previous one.
+    pub async fn record(
+        &self,
+        database: &str,
+        snapshot: TableSizeSnapshot,
+    ) -> Option<TableSizeSnapshot> {
+        let mut guard = self.snapshots.lock().await;
+        let snapshots = guard.get_or_insert_with(|| self.load());
+        let previous = snapshots.insert(database.to_string(), snapshot);
+        self.save(snapshots);
+        previous
+    }
+
+    /// Read persisted snapshots, starting empty if the file is missing or invalid.
+    fn load(&self) -> HashMap<String, TableSizeSnapshot> {
+        let Some(path) = &self.path else {
+            return HashMap::new();
+        };
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!(
+                    "Ignoring invalid table size history {}: {}",
+                    path.display(),
+                    e
+                );
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// Persist snapshots, logging failures (growth tracking is best effort).
+    fn save(&self, snapshots: &HashMap<String, TableSizeSnapshot>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(snapshots)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!(
+                "Failed to save table size history {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, rows: i64, reserved_kb: i64) -> TableSize {
+        TableSize {
+            schema_name: "dbo".to_string(),
+            table_name: name.to_string(),
+            row_count: rows,
+            reserved_kb,
+            used_kb: reserved_kb,
+            data_kb: reserved_kb,
+            index_kb: 0,
+            unused_kb: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_growth_since_previous_snapshot() {
+        let path = std::env::temp_dir().join(format!("table_sizes_{}.json", uuid::Uuid::new_v4()));
+        let history = TableSizeHistory::new(Some(path.clone()));
+
+        let first = TableSizeSnapshot {
+            taken_at: Utc::now(),
+            tables: vec![table("Orders", 100, 800), table("Logs", 10, 80)],
+        };
+        assert!(history.record("Shop", first).await.is_none());
+
+        let second = TableSizeSnapshot {
+            taken_at: Utc::now(),
+            tables: vec![table("Orders", 150, 1000), table("Audit", 5, 16)],
+        };
+        // A new history reads the persisted snapshot
+        let reloaded = TableSizeHistory::new(Some(path.clone()));
+        let previous = reloaded.record("Shop", second.clone()).await.unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let growth = second.growth_since(&previous);
+        assert_eq!(growth["dbo.Orders"].row_count_delta, 50);
+        assert_eq!(growth["dbo.Orders"].reserved_kb_delta, 200);
+        assert!(growth["dbo.Audit"].is_new);
+        assert_eq!(
+            second.dropped_since(&previous),
+            vec!["dbo.Logs".to_string()]
+        );
+    }
+}
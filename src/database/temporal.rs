@@ -0,0 +1,209 @@
+//! Queries against system-versioned temporal tables.
+//!
+//! Builds the `FOR SYSTEM_TIME` clause used by the `query_history_table`
+//! tool. Timestamps are accepted only as ISO 8601 literals so they can be
+//! embedded in the query safely; the period columns of a temporal table
+//! are stored in UTC.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// ISO 8601 date or date-time, as accepted by `datetime2`.
+static TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}([ T]\d{2}:\d{2}(:\d{2}(\.\d{1,7})?)?)?$").unwrap()
+});
+
+/// Range forms of `FOR SYSTEM_TIME`.
+pub const RANGE_TYPES: &[&str] = &["between", "from_to", "contained_in"];
+
+/// A `FOR SYSTEM_TIME` sub-clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemTime {
+    /// Rows as they were at a point in time.
+    AsOf(String),
+    /// Row versions active at any time in `[start, end)`.
+    FromTo(String, String),
+    /// Row versions active at any time in `[start, end]`.
+    Between(String, String),
+    /// Row versions that both started and ended within `[start, end]`.
+    ContainedIn(String, String),
+    /// Every row version, current and historical.
+    All,
+}
+
+impl SystemTime {
+    /// Build the clause from tool parameters.
+    ///
+    /// Exactly one of `as_of`, a `start`/`end` range, or `all` must be given;
+    /// `range_type` selects the range form (default: between).
+    pub fn from_parts(
+        as_of: Option<&str>,
+        start: Option<&str>,
+        end: Option<&str>,
+        range_type: Option<&str>,
+        all: bool,
+    ) -> Result<Self, String> {
+        let has_range = start.is_some() || end.is_some();
+        if [as_of.is_some(), has_range, all]
+            .iter()
+            .filter(|b| **b)
+            .count()
+            != 1
+        {
+            return Err(
+                "Specify exactly one of as_of, a start/end range, or all_versions".to_string(),
+            );
+        }
+        if range_type.is_some() && !has_range {
+            return Err("range_type applies only to a start/end range".to_string());
+        }
+
+        if let Some(as_of) = as_of {
+            return Ok(Self::AsOf(timestamp(as_of)?));
+        }
+        if all {
+            return Ok(Self::All);
+        }
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err("A range needs both start and end".to_string());
+        };
+        let (start, end) = (timestamp(start)?, timestamp(end)?);
+        if start > end {
+            return Err(format!("start ({}) is after end ({})", start, end));
+        }
+        match range_type.unwrap_or("between").to_lowercase().as_str() {
+            "between" => Ok(Self::Between(start, end)),
+            "from_to" => Ok(Self::FromTo(start, end)),
+            "contained_in" => Ok(Self::ContainedIn(start, end)),
+            other => Err(format!(
+                "Invalid range_type '{}' (expected {})",
+                other,
+                RANGE_TYPES.join(", ")
+            )),
+        }
+    }
+
+    /// The clause following `FOR SYSTEM_TIME`.
+    pub fn to_sql(&self) -> String {
+        match self {
+            Self::AsOf(at) => format!("AS OF '{}'", at),
+            Self::FromTo(start, end) => format!("FROM '{}' TO '{}'", start, end),
+            Self::Between(start, end) => format!("BETWEEN '{}' AND '{}'", start, end),
+            Self::ContainedIn(start, end) => format!("CONTAINED IN ('{}', '{}')", start, end),
+            Self::All => "ALL".to_string(),
+        }
+    }
+}
+
+/// Normalize a timestamp parameter to `YYYY-MM-DDTHH:MM[:SS[.fffffff]]`.
+fn timestamp(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if !TIMESTAMP.is_match(value) {
+        return Err(format!(
+            "Invalid timestamp '{}' (expected ISO 8601, e.g. 2024-01-31T12:00:00)",
+            value
+        ));
+    }
+    Ok(value.replacen(' ', "T", 1))
+}
+
+/// Build a query against a temporal table.
+///
+/// `table` and `select_list` must already be quoted; rows are ordered by the
+/// period start column so the versions of a row read oldest first.
+pub fn history_query(
+    table: &str,
+    select_list: &str,
+    system_time: &SystemTime,
+    filter: Option<&str>,
+    period_start: &str,
+    max_rows: usize,
+) -> String {
+    let filter = filter
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| format!("\nWHERE {}", f))
+        .unwrap_or_default();
+    format!(
+        "SELECT TOP ({}) {}\nFROM {} FOR SYSTEM_TIME {}{}\nORDER BY {}",
+        max_rows,
+        select_list,
+        table,
+        system_time.to_sql(),
+        filter,
+        period_start
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time_clauses() {
+        let as_of = SystemTime::from_parts(Some("2024-01-31 12:00"), None, None, None, false);
+        assert_eq!(as_of.unwrap().to_sql(), "AS OF '2024-01-31T12:00'");
+
+        let range = |kind| {
+            SystemTime::from_parts(None, Some("2024-01-01"), Some("2024-02-01"), kind, false)
+                .unwrap()
+                .to_sql()
+        };
+        assert_eq!(range(None), "BETWEEN '2024-01-01' AND '2024-02-01'");
+        assert_eq!(range(Some("from_to")), "FROM '2024-01-01' TO '2024-02-01'");
+        assert_eq!(
+            range(Some("CONTAINED_IN")),
+            "CONTAINED IN ('2024-01-01', '2024-02-01')"
+        );
+        assert_eq!(
+            SystemTime::from_parts(None, None, None, None, true).unwrap(),
+            SystemTime::All
+        );
+    }
+
+    #[test]
+    fn test_system_time_rejects_bad_parameters() {
+        assert!(SystemTime::from_parts(None, None, None, None, false).is_err());
+        assert!(SystemTime::from_parts(Some("2024-01-01"), None, None, None, true).is_err());
+        assert!(SystemTime::from_parts(None, Some("2024-01-01"), None, None, false).is_err());
+        assert!(
+            SystemTime::from_parts(Some("2024-01-01"), None, None, Some("between"), false).is_err()
+        );
+        assert!(
+            SystemTime::from_parts(None, Some("2024-03-01"), Some("2024-02-01"), None, false)
+                .is_err()
+        );
+        assert!(SystemTime::from_parts(
+            Some("2024-01-01'; DROP TABLE x--"),
+            None,
+            None,
+            None,
+            false
+        )
+        .is_err());
+        assert!(SystemTime::from_parts(
+            None,
+            Some("2024-01-01"),
+            Some("2024-02-01"),
+            Some("overlaps"),
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_history_query() {
+        let query = history_query(
+            "[dbo].[Orders]",
+            "*, [ValidFrom], [ValidTo]",
+            &SystemTime::All,
+            Some(" [OrderId] = 42 "),
+            "[ValidFrom]",
+            100,
+        );
+        assert_eq!(
+            query,
+            "SELECT TOP (100) *, [ValidFrom], [ValidTo]\nFROM [dbo].[Orders] FOR SYSTEM_TIME ALL\nWHERE [OrderId] = 42\nORDER BY [ValidFrom]"
+        );
+    }
+}
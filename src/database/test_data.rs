@@ -0,0 +1,654 @@
+//! Synthetic test data generation.
+//!
+//! Each column of the target table gets a value generator chosen from its
+//! type and name (`Email` columns get addresses, `City` columns city names,
+//! `decimal(10,2)` columns amounts that fit). Identity, computed and
+//! rowversion columns are left to the server. Foreign key columns draw from
+//! key values sampled from the parent tables, with composite keys drawn as a
+//! whole. A seeded generator makes every run reproducible.
+
+use crate::database::metadata::ColumnInfo;
+use crate::database::types::SqlValue;
+use crate::error::ServerError;
+use chrono::{Duration, NaiveDate};
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bob", "Carla", "David", "Elena", "Farid", "Grace", "Hiro", "Ines", "Jonas", "Kara",
+    "Luis", "Maya", "Noah", "Olga", "Pedro", "Quinn", "Rosa", "Sam", "Tara", "Umar", "Vera", "Wei",
+    "Yara",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Anders", "Brown", "Chen", "Diaz", "Evans", "Fischer", "Garcia", "Hughes", "Ito", "Jensen",
+    "Kim", "Lopez", "Martin", "Novak", "Okafor", "Patel", "Rossi", "Silva", "Tanaka", "Walsh",
+];
+
+const CITIES: &[&str] = &[
+    "Amsterdam",
+    "Austin",
+    "Berlin",
+    "Chicago",
+    "Dublin",
+    "Lisbon",
+    "Madrid",
+    "Melbourne",
+    "Osaka",
+    "Oslo",
+    "Paris",
+    "Seattle",
+    "Toronto",
+    "Vienna",
+];
+
+const COUNTRIES: &[&str] = &[
+    "Australia",
+    "Austria",
+    "Canada",
+    "France",
+    "Germany",
+    "Ireland",
+    "Japan",
+    "Netherlands",
+    "Norway",
+    "Portugal",
+    "Spain",
+    "United States",
+];
+
+const WORDS: &[&str] = &[
+    "alpha", "amber", "basic", "bright", "cedar", "delta", "ember", "forest", "granite", "harbor",
+    "island", "juniper", "lunar", "maple", "north", "ocean", "prime", "quartz", "river", "summit",
+    "timber", "urban", "valley", "willow",
+];
+
+/// Deterministic pseudo-random numbers (SplitMix64).
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[min, max]`.
+    pub fn between(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i128 - min as i128 + 1) as u128;
+        (min as i128 + (self.next_u64() as u128 % span) as i128) as i64
+    }
+
+    /// True with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        p > 0.0 && (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= p
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.between(0, items.len() as i64 - 1) as usize]
+    }
+}
+
+/// How the values of a column are produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueGenerator {
+    FirstName,
+    LastName,
+    FullName,
+    Email,
+    Phone,
+    City,
+    Country,
+    PostalCode,
+    Url,
+    /// Uppercase letters and digits, for codes, SKUs and reference numbers.
+    Code,
+    /// Capitalized words, for product or company names.
+    Title,
+    Text,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    /// Consecutive integers, for primary keys without an identity.
+    Sequence {
+        start: i64,
+    },
+    Decimal {
+        max: i64,
+        scale: u32,
+    },
+    Float,
+    Bit,
+    Date,
+    DateTime,
+    DateTimeOffset,
+    Time,
+    Guid,
+    Binary,
+    /// Column `position` of a key drawn from parent key set `set`.
+    ForeignKey {
+        set: usize,
+        position: usize,
+    },
+}
+
+/// A column that receives generated values.
+#[derive(Debug, Clone)]
+pub struct GeneratedColumn {
+    pub name: String,
+    pub data_type: String,
+    pub max_length: Option<i32>,
+    pub nullable: bool,
+    pub generator: ValueGenerator,
+}
+
+/// A column left out of the INSERT.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkippedColumn {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Key values sampled from a parent table for a foreign key.
+#[derive(Debug, Clone)]
+pub struct ParentKeys {
+    /// Child columns of the foreign key.
+    pub columns: Vec<String>,
+    /// Parent table as `schema.table`, for messages.
+    pub parent: String,
+    /// Sampled keys as SQL literals, one per child column.
+    pub keys: Vec<Vec<String>>,
+}
+
+/// Settings shared by all generated rows.
+#[derive(Debug, Clone)]
+pub struct GenerationOptions {
+    /// Share of nullable, non-key columns set to NULL.
+    pub null_rate: f64,
+    pub date_from: NaiveDate,
+    pub date_to: NaiveDate,
+}
+
+/// Choose a generator for each column of a table.
+///
+/// `sequence_start` is the first value for a single-column integer primary
+/// key without an identity. Fails when a required column can't be generated.
+pub fn plan_columns(
+    columns: &[ColumnInfo],
+    primary_key: &[String],
+    parents: &[ParentKeys],
+    sequence_start: i64,
+) -> Result<(Vec<GeneratedColumn>, Vec<SkippedColumn>), ServerError> {
+    let mut generated = Vec::new();
+    let mut skipped = Vec::new();
+    let data_type = |c: &ColumnInfo| c.data_type.to_lowercase();
+
+    for column in columns {
+        let skip = |reason: &str| SkippedColumn {
+            name: column.column_name.clone(),
+            reason: reason.to_string(),
+        };
+        if column.is_identity {
+            skipped.push(skip("identity"));
+            continue;
+        }
+        if column.is_computed {
+            skipped.push(skip("computed"));
+            continue;
+        }
+        if matches!(data_type(column).as_str(), "timestamp" | "rowversion") {
+            skipped.push(skip("rowversion"));
+            continue;
+        }
+
+        let foreign_key = parents.iter().enumerate().find_map(|(set, parent)| {
+            parent
+                .columns
+                .iter()
+                .position(|c| c.eq_ignore_ascii_case(&column.column_name))
+                .map(|position| (set, position))
+        });
+        let generator = if let Some((set, position)) = foreign_key {
+            if parents[set].keys.is_empty() && !column.is_nullable {
+                return Err(ServerError::invalid_input(format!(
+                    "Column {} references {}, which has no rows to sample",
+                    column.column_name, parents[set].parent
+                )));
+            }
+            Some(ValueGenerator::ForeignKey { set, position })
+        } else if primary_key.len() == 1
+            && primary_key[0].eq_ignore_ascii_case(&column.column_name)
+            && matches!(
+                data_type(column).as_str(),
+                "tinyint" | "smallint" | "int" | "bigint"
+            )
+        {
+            Some(ValueGenerator::Sequence {
+                start: sequence_start,
+            })
+        } else {
+            generator_for(column)
+        };
+
+        match generator {
+            Some(generator) => generated.push(GeneratedColumn {
+                name: column.column_name.clone(),
+                data_type: data_type(column),
+                max_length: column.max_length,
+                nullable: column.is_nullable,
+                generator,
+            }),
+            None if column.is_nullable || column.default_value.is_some() => {
+                skipped.push(skip(&format!("{} is not generated", column.data_type)));
+            }
+            None => {
+                return Err(ServerError::invalid_input(format!(
+                    "Column {} is a required {} column, which can't be generated",
+                    column.column_name, column.data_type
+                )));
+            }
+        }
+    }
+
+    if generated.is_empty() {
+        return Err(ServerError::invalid_input(
+            "The table has no columns that can be generated",
+        ));
+    }
+    Ok((generated, skipped))
+}
+
+/// Generator for a column from its type and name, or `None` for types that
+/// aren't generated (XML, spatial, `sql_variant`, ...).
+pub fn generator_for(column: &ColumnInfo) -> Option<ValueGenerator> {
+    let name = column.column_name.to_lowercase().replace('_', "");
+    let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+
+    let generator = match column.data_type.to_lowercase().as_str() {
+        "char" | "varchar" | "nchar" | "nvarchar" | "text" | "ntext" => {
+            if has(&["email"]) {
+                ValueGenerator::Email
+            } else if has(&["firstname", "givenname", "forename"]) {
+                ValueGenerator::FirstName
+            } else if has(&["lastname", "surname", "familyname"]) {
+                ValueGenerator::LastName
+            } else if matches!(
+                name.as_str(),
+                "name" | "fullname" | "contactname" | "customername" | "employeename"
+            ) {
+                ValueGenerator::FullName
+            } else if has(&["phone", "mobile", "fax"]) {
+                ValueGenerator::Phone
+            } else if has(&["city", "town"]) {
+                ValueGenerator::City
+            } else if has(&["country"]) {
+                ValueGenerator::Country
+            } else if has(&["zip", "postal", "postcode"]) {
+                ValueGenerator::PostalCode
+            } else if has(&["url", "website", "homepage"]) {
+                ValueGenerator::Url
+            } else if has(&["code", "sku", "number", "ref"]) {
+                ValueGenerator::Code
+            } else if name.ends_with("name") || has(&["title"]) {
+                ValueGenerator::Title
+            } else {
+                ValueGenerator::Text
+            }
+        }
+        "tinyint" | "smallint" | "int" | "bigint" => {
+            let (min, max) = if has(&["quantity", "qty", "count"]) {
+                (1, 100)
+            } else if has(&["age"]) {
+                (18, 90)
+            } else if has(&["year"]) {
+                (1990, 2030)
+            } else {
+                match column.data_type.to_lowercase().as_str() {
+                    "tinyint" => (0, 255),
+                    "smallint" => (0, 1_000),
+                    "int" => (1, 100_000),
+                    _ => (1, 1_000_000),
+                }
+            };
+            ValueGenerator::Integer { min, max }
+        }
+        "decimal" | "numeric" => {
+            let precision = column.precision.unwrap_or(18).clamp(1, 38) as u32;
+            let scale = column.scale.unwrap_or(0).clamp(0, precision as i32) as u32;
+            let digits = (precision - scale).min(5);
+            ValueGenerator::Decimal {
+                max: 10i64.pow(digits) - 1,
+                scale: scale.min(4),
+            }
+        }
+        "money" | "smallmoney" => ValueGenerator::Decimal {
+            max: 10_000,
+            scale: 2,
+        },
+        "float" | "real" => ValueGenerator::Float,
+        "bit" => ValueGenerator::Bit,
+        "date" => ValueGenerator::Date,
+        "datetime" | "datetime2" | "smalldatetime" => ValueGenerator::DateTime,
+        "datetimeoffset" => ValueGenerator::DateTimeOffset,
+        "time" => ValueGenerator::Time,
+        "uniqueidentifier" => ValueGenerator::Guid,
+        "binary" | "varbinary" | "image" => ValueGenerator::Binary,
+        _ => return None,
+    };
+    Some(generator)
+}
+
+/// Generate the SQL literals of one row, in column order.
+///
+/// `row` is the zero-based row number, used by sequences.
+pub fn generate_row(
+    rng: &mut SeededRng,
+    columns: &[GeneratedColumn],
+    parents: &[ParentKeys],
+    options: &GenerationOptions,
+    row: usize,
+) -> Vec<String> {
+    // One key per foreign key, so composite keys stay consistent
+    let keys: Vec<Option<&Vec<String>>> = parents
+        .iter()
+        .map(|p| (!p.keys.is_empty()).then(|| rng.pick(&p.keys)))
+        .collect();
+
+    columns
+        .iter()
+        .map(|column| {
+            if let ValueGenerator::ForeignKey { set, position } = column.generator {
+                return match keys[set] {
+                    Some(key) if !(column.nullable && rng.chance(options.null_rate)) => {
+                        key[position].clone()
+                    }
+                    _ => "NULL".to_string(),
+                };
+            }
+            let keyed = matches!(column.generator, ValueGenerator::Sequence { .. });
+            if column.nullable && !keyed && rng.chance(options.null_rate) {
+                return "NULL".to_string();
+            }
+            generate_value(rng, column, options, row)
+        })
+        .collect()
+}
+
+/// Generate the SQL literal of one non-key value.
+pub fn generate_value(
+    rng: &mut SeededRng,
+    column: &GeneratedColumn,
+    options: &GenerationOptions,
+    row: usize,
+) -> String {
+    let text = |value: String| {
+        let limit = match column.max_length {
+            Some(n) if n > 0 => n as usize,
+            _ => 200,
+        };
+        let value: String = value.chars().take(limit).collect();
+        format!("N'{}'", value.replace('\'', "''"))
+    };
+
+    match &column.generator {
+        ValueGenerator::FirstName => text(rng.pick(FIRST_NAMES).to_string()),
+        ValueGenerator::LastName => text(rng.pick(LAST_NAMES).to_string()),
+        ValueGenerator::FullName => text(format!(
+            "{} {}",
+            rng.pick(FIRST_NAMES),
+            rng.pick(LAST_NAMES)
+        )),
+        ValueGenerator::Email => text(format!(
+            "{}.{}{}@example.com",
+            rng.pick(FIRST_NAMES).to_lowercase(),
+            rng.pick(LAST_NAMES).to_lowercase(),
+            rng.between(1, 999_999)
+        )),
+        ValueGenerator::Phone => text(format!(
+            "+1-555-{:03}-{:04}",
+            rng.between(100, 999),
+            rng.between(0, 9999)
+        )),
+        ValueGenerator::City => text(rng.pick(CITIES).to_string()),
+        ValueGenerator::Country => text(rng.pick(COUNTRIES).to_string()),
+        ValueGenerator::PostalCode => text(format!("{:05}", rng.between(1000, 99999))),
+        ValueGenerator::Url => text(format!(
+            "https://{}-{}.example.com",
+            rng.pick(WORDS),
+            rng.between(1, 9999)
+        )),
+        ValueGenerator::Code => {
+            let letters: String = (0..3)
+                .map(|_| (b'A' + rng.between(0, 25) as u8) as char)
+                .collect();
+            text(format!("{}-{:06}", letters, rng.between(0, 999_999)))
+        }
+        ValueGenerator::Title => {
+            let mut word = || {
+                let w = rng.pick(WORDS);
+                let mut chars = w.chars();
+                chars
+                    .next()
+                    .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                    .unwrap_or_default()
+            };
+            let title = format!("{} {}", word(), word());
+            text(title)
+        }
+        ValueGenerator::Text => {
+            let count = rng.between(3, 8);
+            let words: Vec<&str> = (0..count).map(|_| *rng.pick(WORDS)).collect();
+            text(words.join(" "))
+        }
+        ValueGenerator::Integer { min, max } => rng.between(*min, *max).to_string(),
+        ValueGenerator::Sequence { start } => (start + row as i64).to_string(),
+        ValueGenerator::Decimal { max, scale } => {
+            let whole = rng.between(0, *max);
+            if *scale == 0 {
+                whole.to_string()
+            } else {
+                let fraction = rng.between(0, 10i64.pow(*scale) - 1);
+                format!("{}.{:0width$}", whole, fraction, width = *scale as usize)
+            }
+        }
+        ValueGenerator::Float => format!("{}.{:04}", rng.between(0, 999), rng.between(0, 9999)),
+        ValueGenerator::Bit => rng.between(0, 1).to_string(),
+        ValueGenerator::Date => format!("'{}'", random_date(rng, options).format("%Y-%m-%d")),
+        ValueGenerator::DateTime => format!(
+            "'{}T{}'",
+            random_date(rng, options).format("%Y-%m-%d"),
+            random_time(rng)
+        ),
+        ValueGenerator::DateTimeOffset => format!(
+            "'{}T{}+00:00'",
+            random_date(rng, options).format("%Y-%m-%d"),
+            random_time(rng)
+        ),
+        ValueGenerator::Time => format!("'{}'", random_time(rng)),
+        ValueGenerator::Guid => {
+            let high = rng.next_u64();
+            let low = rng.next_u64();
+            // Version 4 layout so the values look like NEWID() output
+            let uuid = uuid::Builder::from_random_bytes(
+                ((high as u128) << 64 | low as u128).to_be_bytes(),
+            )
+            .into_uuid();
+            format!("'{}'", uuid)
+        }
+        ValueGenerator::Binary => {
+            let length = match column.max_length {
+                Some(n) if n > 0 => (n as usize).min(16),
+                _ => 16,
+            };
+            let bytes: String = (0..length)
+                .map(|_| format!("{:02X}", rng.between(0, 255)))
+                .collect();
+            format!("0x{}", bytes)
+        }
+        // Drawn in generate_row
+        ValueGenerator::ForeignKey { .. } => "NULL".to_string(),
+    }
+}
+
+fn random_date(rng: &mut SeededRng, options: &GenerationOptions) -> NaiveDate {
+    let days = (options.date_to - options.date_from).num_days().max(0);
+    options.date_from + Duration::days(rng.between(0, days))
+}
+
+fn random_time(rng: &mut SeededRng) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        rng.between(0, 23),
+        rng.between(0, 59),
+        rng.between(0, 59)
+    )
+}
+
+/// SQL literal of a sampled key value, or `None` for NULL and values that
+/// can't be written back as literals.
+pub fn key_literal(value: &SqlValue) -> Option<String> {
+    match value {
+        SqlValue::Null | SqlValue::Udt(_) => None,
+        SqlValue::Bool(v) => Some(if *v { "1" } else { "0" }.to_string()),
+        SqlValue::I8(_)
+        | SqlValue::I16(_)
+        | SqlValue::I32(_)
+        | SqlValue::I64(_)
+        | SqlValue::F32(_)
+        | SqlValue::F64(_)
+        | SqlValue::Decimal(_)
+        | SqlValue::Bytes(_) => Some(value.to_display_string()),
+        SqlValue::String(s) => Some(format!("N'{}'", s.replace('\'', "''"))),
+        other => Some(format!("'{}'", other.to_display_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> ColumnInfo {
+        ColumnInfo {
+            column_name: name.to_string(),
+            ordinal_position: 0,
+            data_type: data_type.to_string(),
+            max_length: None,
+            precision: Some(10),
+            scale: Some(2),
+            is_nullable: nullable,
+            default_value: None,
+            is_identity: false,
+            is_computed: false,
+        }
+    }
+
+    fn options() -> GenerationOptions {
+        GenerationOptions {
+            null_rate: 0.0,
+            date_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            date_to: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_plan_columns() {
+        let mut id = column("Id", "int", false);
+        id.is_identity = true;
+        let columns = vec![
+            id,
+            column("CustomerId", "int", false),
+            column("Email", "nvarchar", false),
+            column("Total", "decimal", false),
+            column("Location", "geography", true),
+            column("CreatedAt", "datetime2", false),
+        ];
+        let parents = vec![ParentKeys {
+            columns: vec!["CustomerId".to_string()],
+            parent: "dbo.Customers".to_string(),
+            keys: vec![vec!["7".to_string()], vec!["9".to_string()]],
+        }];
+        let (generated, skipped) =
+            plan_columns(&columns, &["Id".to_string()], &parents, 1).unwrap();
+        let generators: Vec<_> = generated.iter().map(|c| c.generator.clone()).collect();
+        assert_eq!(
+            generators,
+            [
+                ValueGenerator::ForeignKey {
+                    set: 0,
+                    position: 0
+                },
+                ValueGenerator::Email,
+                ValueGenerator::Decimal {
+                    max: 99_999,
+                    scale: 2
+                },
+                ValueGenerator::DateTime,
+            ]
+        );
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].reason, "identity");
+
+        let empty = vec![ParentKeys {
+            keys: Vec::new(),
+            ..parents[0].clone()
+        }];
+        assert!(plan_columns(&columns, &[], &empty, 1).is_err());
+        assert!(plan_columns(&[column("Shape", "geometry", false)], &[], &[], 1).is_err());
+    }
+
+    #[test]
+    fn test_generate_rows() {
+        let columns = vec![
+            column("OrderId", "int", false),
+            column("CustomerId", "int", false),
+            column("Email", "nvarchar", false),
+            column("OrderDate", "date", false),
+        ];
+        let parents = vec![ParentKeys {
+            columns: vec!["CustomerId".to_string()],
+            parent: "dbo.Customers".to_string(),
+            keys: vec![vec!["7".to_string()], vec!["9".to_string()]],
+        }];
+        let (generated, _) =
+            plan_columns(&columns, &["OrderId".to_string()], &parents, 100).unwrap();
+
+        let rows = |seed| {
+            let mut rng = SeededRng::new(seed);
+            (0..20)
+                .map(|row| generate_row(&mut rng, &generated, &parents, &options(), row))
+                .collect::<Vec<_>>()
+        };
+        let first = rows(42);
+        assert_eq!(first, rows(42));
+        assert_ne!(first, rows(43));
+
+        assert_eq!(first[0][0], "100");
+        assert_eq!(first[19][0], "119");
+        assert!(first.iter().all(|r| r[1] == "7" || r[1] == "9"));
+        assert!(first[0][2].starts_with("N'") && first[0][2].ends_with("@example.com'"));
+        assert!(first
+            .iter()
+            .all(|r| r[3].as_str() >= "'2024-01-01'" && r[3].as_str() <= "'2024-12-31'"));
+    }
+
+    #[test]
+    fn test_key_literal() {
+        assert_eq!(key_literal(&SqlValue::I32(5)).as_deref(), Some("5"));
+        assert_eq!(
+            key_literal(&SqlValue::String("O'Neil".to_string())).as_deref(),
+            Some("N'O''Neil'")
+        );
+        assert_eq!(key_literal(&SqlValue::Null), None);
+    }
+}
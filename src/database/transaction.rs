@@ -3,19 +3,69 @@
 //! This module provides dedicated connections for database transactions.
 //! Unlike pooled connections, transaction connections are held for the
 //! entire lifetime of a transaction to maintain transaction state.
+//!
+//! Statements executed in a transaction are recorded so that, when the server
+//! chooses the transaction as a deadlock victim and rolls it back, the whole
+//! sequence can optionally be replayed in a new transaction.
 
-use super::auth::{create_connection, truncate_for_log, RawConnection};
-use crate::config::DatabaseConfig;
+use super::auth::{create_connection, truncate_for_log, use_database, RawConnection};
+use crate::config::{AuthConfig, DatabaseConfig};
+use crate::constants::MAX_REPLAY_STATEMENTS;
 use crate::database::query::{ColumnInfo, QueryResult, ResultRow};
+use crate::database::statement_results::{instrument_batch, BatchResult};
 use crate::database::types::TypeMapper;
+use crate::database::{capture_messages, ResultMemory};
 use crate::error::ServerError;
 use crate::state::IsolationLevel;
-use futures_util::TryStreamExt;
+use crate::telemetry::{record_request_rows, tag_query};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
+
+/// SQL Server error number for a transaction chosen as deadlock victim.
+const DEADLOCK_VICTIM: i32 = 1205;
+
+/// Base delay before replaying a deadlocked transaction, multiplied by the attempt.
+const REPLAY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A dedicated transaction connection and what is needed to replay it.
+struct TransactionConnection {
+    conn: RawConnection,
+    isolation_level: IsolationLevel,
+    name: Option<String>,
+    /// Database the transaction started in.
+    database: Option<String>,
+    /// Statements that completed, in order.
+    statements: Vec<String>,
+    /// False once more than `MAX_REPLAY_STATEMENTS` statements ran.
+    replayable: bool,
+}
+
+/// A replay of a transaction after the server rolled it back as a deadlock victim.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlockReplay {
+    /// Replay attempt, starting at 1.
+    pub attempt: u32,
+    /// Earlier statements re-executed in the new transaction.
+    pub statements_replayed: usize,
+    /// Time waited before replaying, in milliseconds.
+    pub waited_ms: u64,
+}
+
+/// Result of a statement run with deadlock replay.
+#[derive(Debug)]
+pub struct TransactionExecution {
+    /// Result of each statement of the query.
+    pub result: BatchResult,
+    /// Replays needed before the statement succeeded, if any.
+    pub replays: Vec<DeadlockReplay>,
+    /// The earlier statements that were replayed.
+    pub replayed_statements: Vec<String>,
+}
 
 /// Manager for transaction-dedicated connections.
 ///
@@ -24,60 +74,92 @@ use tracing::{debug, warn};
 /// pool until the transaction is committed or rolled back.
 pub struct TransactionManager {
     /// Database configuration for creating new connections.
-    db_config: Arc<DatabaseConfig>,
+    db_config: parking_lot::RwLock<Arc<DatabaseConfig>>,
 
     /// Active transaction connections keyed by transaction ID.
-    connections: Mutex<HashMap<String, RawConnection>>,
+    connections: Mutex<HashMap<String, TransactionConnection>>,
 
     /// Maximum rows to return from queries.
-    max_rows: usize,
+    max_rows: AtomicUsize,
+
+    /// Default number of deadlock replays; 0 disables replay.
+    deadlock_replays: AtomicU32,
 }
 
 impl TransactionManager {
     /// Create a new transaction manager.
     pub fn new(db_config: Arc<DatabaseConfig>, max_rows: usize) -> Self {
         Self {
-            db_config,
+            db_config: parking_lot::RwLock::new(db_config),
             connections: Mutex::new(HashMap::new()),
-            max_rows,
+            max_rows: AtomicUsize::new(max_rows),
+            deadlock_replays: AtomicU32::new(0),
         }
     }
 
+    /// Update the default row limit, e.g. after a configuration reload.
+    pub fn set_max_rows(&self, max_rows: usize) {
+        self.max_rows.store(max_rows, Ordering::Relaxed);
+    }
+
+    /// Connect new transactions with `auth`, e.g. after a credential rotation.
+    ///
+    /// Open transactions keep the connection they were started on.
+    pub fn set_auth(&self, auth: AuthConfig) {
+        let mut db_config = self.db_config.write();
+        let mut updated = DatabaseConfig::clone(&db_config);
+        updated.auth = auth;
+        *db_config = Arc::new(updated);
+    }
+
+    /// Set how often a deadlocked transaction is replayed by default.
+    pub fn set_deadlock_replays(&self, attempts: u32) {
+        self.deadlock_replays.store(attempts, Ordering::Relaxed);
+    }
+
+    /// Default number of deadlock replays; 0 when disabled.
+    pub fn deadlock_replays(&self) -> u32 {
+        self.deadlock_replays.load(Ordering::Relaxed)
+    }
+
     /// Create a new raw connection using the database configuration.
     async fn create_txn_connection(&self) -> Result<RawConnection, ServerError> {
-        create_connection(&self.db_config, Some("txn")).await
+        let db_config = Arc::clone(&self.db_config.read());
+        create_connection(&db_config, Some("txn")).await
     }
 
     /// Begin a new transaction and store its dedicated connection.
     ///
     /// Uses the native IsolationLevel enum for type safety, consistent with mssql-client's API.
+    /// With `database`, the connection switches to that database before the
+    /// transaction begins; other connections are not affected.
     pub async fn begin_transaction(
         &self,
         transaction_id: &str,
         isolation_level: IsolationLevel,
         name: Option<&str>,
+        database: Option<&str>,
     ) -> Result<(), ServerError> {
         // Create a dedicated connection for this transaction
         let mut conn = self.create_txn_connection().await?;
-
-        // Set isolation level using the as_sql() method (matches mssql-client API)
-        let begin_tx = match name {
-            Some(n) => format!("BEGIN TRANSACTION [{}]", n.replace(']', "]]")),
-            None => "BEGIN TRANSACTION".to_string(),
-        };
-
-        // Execute as separate statements on the same connection
-        conn.execute(isolation_level.as_sql(), &[])
-            .await
-            .map_err(|e| ServerError::query_error(format!("Failed to set isolation level: {}", e)))?;
-
-        conn.execute(&begin_tx, &[])
-            .await
-            .map_err(|e| ServerError::query_error(format!("Failed to begin transaction: {}", e)))?;
+        if let Some(db) = database {
+            use_database(&mut conn, db).await?;
+        }
+        start_transaction(&mut conn, isolation_level, name).await?;
 
         // Store the connection
         let mut connections = self.connections.lock().await;
-        connections.insert(transaction_id.to_string(), conn);
+        connections.insert(
+            transaction_id.to_string(),
+            TransactionConnection {
+                conn,
+                isolation_level,
+                name: name.map(str::to_string),
+                database: database.map(str::to_string),
+                statements: Vec::new(),
+                replayable: true,
+            },
+        );
 
         debug!(
             transaction_id = transaction_id,
@@ -92,11 +174,31 @@ impl TransactionManager {
         &self,
         transaction_id: &str,
         query: &str,
-    ) -> Result<QueryResult, ServerError> {
-        let start = Instant::now();
+    ) -> Result<BatchResult, ServerError> {
+        self.execute_with_replay(transaction_id, query, Some(0))
+            .await
+            .map(|execution| execution.result)
+    }
+
+    /// Execute a query within an existing transaction, replaying the
+    /// transaction if the server rolls it back as a deadlock victim.
+    ///
+    /// On a deadlock the transaction is restarted on the same connection with
+    /// its isolation level, every earlier statement is executed again and the
+    /// query is retried, up to `max_replays` times (`None` uses the configured
+    /// default). If a replay fails for another reason the transaction is
+    /// rolled back and the error returned.
+    pub async fn execute_with_replay(
+        &self,
+        transaction_id: &str,
+        query: &str,
+        max_replays: Option<u32>,
+    ) -> Result<TransactionExecution, ServerError> {
+        let max_replays = max_replays.unwrap_or_else(|| self.deadlock_replays());
+        let max_rows = self.max_rows.load(Ordering::Relaxed);
 
         let mut connections = self.connections.lock().await;
-        let conn = connections.get_mut(transaction_id).ok_or_else(|| {
+        let txn = connections.get_mut(transaction_id).ok_or_else(|| {
             ServerError::Session(format!(
                 "Transaction connection not found: {}",
                 transaction_id
@@ -109,42 +211,129 @@ impl TransactionManager {
             truncate_for_log(query, 100)
         );
 
-        // Execute query and collect stream
-        let stream = conn
-            .query(query, &[])
-            .await
-            .map_err(|e| ServerError::query_error(format!("Query execution failed: {}", e)))?;
+        let mut replays = Vec::new();
+        let result = loop {
+            let error = match run_statement(&mut txn.conn, query, max_rows).await {
+                Ok(result) => break result,
+                Err(e) => e,
+            };
+            if !is_deadlock(&error) || replays.len() as u32 >= max_replays {
+                return Err(error);
+            }
+            if !txn.replayable {
+                return Err(ServerError::query_error(format!(
+                    "{} (not replayed: more than {} statements in the transaction)",
+                    error, MAX_REPLAY_STATEMENTS
+                )));
+            }
 
-        let rows: Vec<mssql_client::Row> = stream.try_collect().await.map_err(|e| {
-            ServerError::query_error(format!("Failed to collect query results: {}", e))
-        })?;
+            let attempt = replays.len() as u32 + 1;
+            let waited = REPLAY_BACKOFF * attempt;
+            warn!(
+                "Transaction {} was a deadlock victim, replaying {} statement(s) (attempt {}/{})",
+                transaction_id,
+                txn.statements.len(),
+                attempt,
+                max_replays
+            );
+            tokio::time::sleep(waited).await;
+            replays.push(DeadlockReplay {
+                attempt,
+                statements_replayed: txn.statements.len(),
+                waited_ms: waited.as_millis() as u64,
+            });
+
+            if let Err(e) = replay(txn, max_rows).await {
+                if is_deadlock(&e) && (replays.len() as u32) < max_replays {
+                    // Deadlocked again while replaying; the next attempt starts over
+                    continue;
+                }
+                let _ = txn
+                    .conn
+                    .execute("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION", &[])
+                    .await;
+                return Err(ServerError::query_error(format!(
+                    "Transaction was rolled back after a deadlock and could not be replayed \
+                     (attempt {}): {}",
+                    attempt, e
+                )));
+            }
+        };
 
-        // Process results
-        let result = self.process_rows(rows, self.max_rows, start)?;
+        if !replays.is_empty() {
+            info!(
+                "Transaction {} replayed {} time(s) after deadlock",
+                transaction_id,
+                replays.len()
+            );
+        }
+        let replayed_statements = if replays.is_empty() {
+            Vec::new()
+        } else {
+            txn.statements.clone()
+        };
+        record_statement(txn, query);
 
         debug!(
-            "Transaction query completed: {} rows in {} ms",
-            result.rows.len(),
+            "Transaction query completed: {} statement(s), {} rows affected in {} ms",
+            result.statements.len(),
+            result.rows_affected,
             result.execution_time_ms
         );
 
-        Ok(result)
+        Ok(TransactionExecution {
+            result,
+            replays,
+            replayed_statements,
+        })
     }
 
-    /// Commit a transaction and release its connection.
-    pub async fn commit_transaction(
+    /// Create a savepoint in an existing transaction.
+    ///
+    /// The `SAVE TRANSACTION` is recorded like any statement, so a deadlock
+    /// replay recreates the savepoint.
+    pub async fn create_savepoint(
         &self,
         transaction_id: &str,
-        name: Option<&str>,
+        savepoint: &str,
     ) -> Result<(), ServerError> {
         let mut connections = self.connections.lock().await;
-        let mut conn = connections.remove(transaction_id).ok_or_else(|| {
+        let txn = connections.get_mut(transaction_id).ok_or_else(|| {
             ServerError::Session(format!(
                 "Transaction connection not found: {}",
                 transaction_id
             ))
         })?;
 
+        let save_sql = save_savepoint_sql(savepoint);
+        txn.conn
+            .execute(&save_sql, &[])
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to create savepoint", e))?;
+        record_statement(txn, &save_sql);
+
+        debug!(
+            "Transaction {} created savepoint {}",
+            transaction_id, savepoint
+        );
+        Ok(())
+    }
+
+    /// Commit a transaction and release its connection.
+    pub async fn commit_transaction(
+        &self,
+        transaction_id: &str,
+        name: Option<&str>,
+    ) -> Result<(), ServerError> {
+        let mut connections = self.connections.lock().await;
+        let TransactionConnection { mut conn, .. } =
+            connections.remove(transaction_id).ok_or_else(|| {
+                ServerError::Session(format!(
+                    "Transaction connection not found: {}",
+                    transaction_id
+                ))
+            })?;
+
         let commit_sql = match name {
             Some(n) => format!("COMMIT TRANSACTION [{}]", n.replace(']', "]]")),
             None => "COMMIT TRANSACTION".to_string(),
@@ -173,7 +362,7 @@ impl TransactionManager {
 
         // For savepoint rollback, we don't remove the connection
         if let Some(sp) = savepoint {
-            let conn = connections.get_mut(transaction_id).ok_or_else(|| {
+            let txn = connections.get_mut(transaction_id).ok_or_else(|| {
                 ServerError::Session(format!(
                     "Transaction connection not found: {}",
                     transaction_id
@@ -181,10 +370,16 @@ impl TransactionManager {
             })?;
 
             let rollback_sql = format!("ROLLBACK TRANSACTION [{}]", sp.replace(']', "]]"));
-            conn.execute(&rollback_sql, &[]).await.map_err(|e| {
+            txn.conn.execute(&rollback_sql, &[]).await.map_err(|e| {
                 ServerError::query_error(format!("Failed to rollback to savepoint: {}", e))
             })?;
 
+            // Statements after the savepoint were undone and must not be replayed
+            let save_sql = save_savepoint_sql(sp);
+            if let Some(pos) = txn.statements.iter().rposition(|s| *s == save_sql) {
+                txn.statements.truncate(pos + 1);
+            }
+
             debug!(
                 "Transaction {} rolled back to savepoint {}",
                 transaction_id, sp
@@ -193,12 +388,13 @@ impl TransactionManager {
         }
 
         // Full rollback - remove and close connection
-        let mut conn = connections.remove(transaction_id).ok_or_else(|| {
-            ServerError::Session(format!(
-                "Transaction connection not found: {}",
-                transaction_id
-            ))
-        })?;
+        let TransactionConnection { mut conn, .. } =
+            connections.remove(transaction_id).ok_or_else(|| {
+                ServerError::Session(format!(
+                    "Transaction connection not found: {}",
+                    transaction_id
+                ))
+            })?;
 
         let rollback_sql = match name {
             Some(n) => format!("ROLLBACK TRANSACTION [{}]", n.replace(']', "]]")),
@@ -229,62 +425,6 @@ impl TransactionManager {
         connections.len()
     }
 
-    /// Process query result rows into a QueryResult.
-    fn process_rows(
-        &self,
-        rows: Vec<mssql_client::Row>,
-        max_rows: usize,
-        start: Instant,
-    ) -> Result<QueryResult, ServerError> {
-        let mut columns: Vec<ColumnInfo> = Vec::new();
-        let mut result_rows: Vec<ResultRow> = Vec::new();
-        let mut truncated = false;
-
-        for (idx, row) in rows.into_iter().enumerate() {
-            // Extract column info from the first row
-            if columns.is_empty() {
-                let row_columns = row.columns();
-                for (i, col) in row_columns.iter().enumerate() {
-                    let name = col.name.clone();
-                    let sql_type = if !col.type_name.is_empty() {
-                        col.type_name.clone()
-                    } else {
-                        let sample_value = TypeMapper::extract_column(&row, i);
-                        TypeMapper::sql_type_name_from_value(&sample_value).to_string()
-                    };
-
-                    columns.push(ColumnInfo {
-                        name,
-                        sql_type,
-                        nullable: col.nullable,
-                    });
-                }
-            }
-
-            // Check row limit
-            if idx >= max_rows {
-                truncated = true;
-                continue;
-            }
-
-            // Extract row data
-            let mut result_row = ResultRow::new();
-            for (col_idx, col) in columns.iter().enumerate() {
-                let value = TypeMapper::extract_column(&row, col_idx);
-                result_row.insert(col.name.clone(), value);
-            }
-            result_rows.push(result_row);
-        }
-
-        Ok(QueryResult {
-            columns,
-            rows: result_rows,
-            rows_affected: 0,
-            execution_time_ms: start.elapsed().as_millis() as u64,
-            truncated,
-        })
-    }
-
     /// Clean up orphaned transaction connections.
     ///
     /// This should be called periodically or when transactions are cleaned up.
@@ -298,12 +438,196 @@ impl TransactionManager {
 
         for id in orphaned {
             warn!("Cleaning up orphaned transaction connection: {}", id);
-            if let Some(mut conn) = connections.remove(&id) {
+            if let Some(mut txn) = connections.remove(&id) {
                 // Try to rollback before dropping
-                let _ = conn
+                let _ = txn
+                    .conn
                     .execute("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION", &[])
                     .await;
             }
         }
     }
 }
+
+/// Set the isolation level and begin a transaction on a dedicated connection.
+async fn start_transaction(
+    conn: &mut RawConnection,
+    isolation_level: IsolationLevel,
+    name: Option<&str>,
+) -> Result<(), ServerError> {
+    // Set isolation level using the as_sql() method (matches mssql-client API)
+    let begin_tx = match name {
+        Some(n) => format!("BEGIN TRANSACTION [{}]", n.replace(']', "]]")),
+        None => "BEGIN TRANSACTION".to_string(),
+    };
+
+    // Execute as separate statements on the same connection
+    conn.execute(isolation_level.as_sql(), &[])
+        .await
+        .map_err(|e| ServerError::query_error(format!("Failed to set isolation level: {}", e)))?;
+
+    conn.execute(&begin_tx, &[])
+        .await
+        .map_err(|e| ServerError::query_error(format!("Failed to begin transaction: {}", e)))?;
+    Ok(())
+}
+
+/// Run a query on a transaction connection and collect the result sets and
+/// row count of each of its statements.
+async fn run_statement(
+    conn: &mut RawConnection,
+    query: &str,
+    max_rows: usize,
+) -> Result<BatchResult, ServerError> {
+    let start = Instant::now();
+    let batch = instrument_batch(query);
+
+    let (result_sets, messages) =
+        capture_messages(read_result_sets(conn, &tag_query(&batch.sql), max_rows)).await;
+
+    let result = BatchResult::from_result_sets(
+        &batch,
+        result_sets?,
+        messages,
+        start.elapsed().as_millis() as u64,
+    );
+    record_request_rows(result.result_sets().map(|r| r.rows.len() as u64).sum());
+    Ok(result)
+}
+
+/// Read every result set of a query, keeping at most `max_rows` rows of each.
+async fn read_result_sets(
+    conn: &mut RawConnection,
+    sql: &str,
+    max_rows: usize,
+) -> Result<Vec<QueryResult>, ServerError> {
+    let mut stream = conn
+        .query_multiple(sql, &[])
+        .await
+        .map_err(|e| ServerError::query_failed("Query execution failed", e))?;
+
+    let mut result_sets = Vec::new();
+    let mut memory = ResultMemory::new();
+    loop {
+        let start = Instant::now();
+        let columns: Vec<ColumnInfo> = stream
+            .columns()
+            .unwrap_or_default()
+            .iter()
+            .map(|col| ColumnInfo {
+                name: col.name.clone(),
+                sql_type: if col.type_name.is_empty() {
+                    "unknown".to_string()
+                } else {
+                    col.type_name.clone()
+                },
+                nullable: col.nullable,
+            })
+            .collect();
+
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        while let Some(row) = stream
+            .next_row()
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to read row", e))?
+        {
+            if rows.len() >= max_rows {
+                truncated = true;
+                continue;
+            }
+            let mut result_row = ResultRow::new();
+            for (idx, col) in columns.iter().enumerate() {
+                result_row.insert(col.name.clone(), TypeMapper::extract_column(&row, idx));
+            }
+            if !memory.admit(&result_row)? {
+                truncated = true;
+                continue;
+            }
+            rows.push(result_row);
+        }
+
+        if !columns.is_empty() {
+            let note = memory.partial_note().filter(|_| truncated);
+            result_sets.push(QueryResult {
+                columns,
+                rows,
+                rows_affected: 0,
+                execution_time_ms: start.elapsed().as_millis() as u64,
+                truncated,
+                retry: None,
+                messages: note.into_iter().collect(),
+            });
+        }
+
+        if !stream
+            .next_result()
+            .await
+            .map_err(|e| ServerError::query_failed("Failed to advance to next result", e))?
+        {
+            break;
+        }
+    }
+    Ok(result_sets)
+}
+
+/// Restart a transaction the server rolled back and re-execute its statements.
+async fn replay(txn: &mut TransactionConnection, max_rows: usize) -> Result<(), ServerError> {
+    // The server has already rolled back a deadlock victim; this is a safeguard
+    txn.conn
+        .execute("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION", &[])
+        .await
+        .map_err(|e| ServerError::query_failed("Failed to clear transaction", e))?;
+    // USE is not transactional; go back to where the transaction started
+    if let Some(db) = &txn.database {
+        use_database(&mut txn.conn, db).await?;
+    }
+    start_transaction(&mut txn.conn, txn.isolation_level, txn.name.as_deref()).await?;
+
+    for (i, statement) in txn.statements.iter().enumerate() {
+        run_statement(&mut txn.conn, statement, max_rows)
+            .await
+            .map_err(|e| {
+                if is_deadlock(&e) {
+                    e
+                } else {
+                    ServerError::query_error(format!(
+                        "statement {} of {} failed: {}",
+                        i + 1,
+                        txn.statements.len(),
+                        e
+                    ))
+                }
+            })?;
+    }
+    Ok(())
+}
+
+/// Statement creating a savepoint.
+fn save_savepoint_sql(savepoint: &str) -> String {
+    format!("SAVE TRANSACTION [{}]", savepoint.replace(']', "]]"))
+}
+
+/// Remember a completed statement for replay.
+fn record_statement(txn: &mut TransactionConnection, query: &str) {
+    if !txn.replayable {
+        return;
+    }
+    if txn.statements.len() >= MAX_REPLAY_STATEMENTS {
+        txn.replayable = false;
+        txn.statements = Vec::new();
+        return;
+    }
+    txn.statements.push(query.to_string());
+}
+
+/// Check whether an error means the transaction was chosen as deadlock victim.
+fn is_deadlock(error: &ServerError) -> bool {
+    matches!(
+        error.root(),
+        ServerError::QueryExecution {
+            sql_error_code: Some(DEADLOCK_VICTIM),
+            ..
+        }
+    )
+}
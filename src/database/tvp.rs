@@ -0,0 +1,473 @@
+//! Table-valued parameter row validation.
+//!
+//! Rows for a TVP arrive as JSON, either as arrays in column order or as
+//! objects keyed by column name. They are checked against the table type's
+//! definition (read from `sys.table_types` / `sys.columns`) and each value is
+//! coerced to the column's SQL type, so that mistakes are reported with the
+//! offending row and column instead of as a generic conversion error from the
+//! server.
+
+use crate::database::scripting::sys_type_definition;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use mssql_client::SqlValue;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// A column of a user-defined table type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableTypeColumn {
+    /// Column name.
+    pub name: String,
+
+    /// Base type name, e.g. `nvarchar`.
+    pub data_type: String,
+
+    /// Maximum length in bytes as reported by `sys.columns` (-1 for MAX).
+    pub max_length: i32,
+
+    /// Numeric precision.
+    pub precision: i32,
+
+    /// Numeric or fractional-seconds scale.
+    pub scale: i32,
+
+    /// Whether the column allows NULL.
+    pub nullable: bool,
+}
+
+impl TableTypeColumn {
+    /// Full SQL type, e.g. `NVARCHAR(100)` or `DECIMAL(18,2)`.
+    pub fn sql_type(&self) -> String {
+        sys_type_definition(&self.data_type, self.max_length, self.precision, self.scale)
+    }
+
+    /// Maximum length in characters (or bytes for binary types), if limited.
+    fn max_chars(&self) -> Option<usize> {
+        match (self.data_type.as_str(), self.max_length) {
+            (_, -1) => None,
+            ("nvarchar" | "nchar", len) => Some(len as usize / 2),
+            ("varchar" | "char" | "varbinary" | "binary", len) => Some(len as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Convert JSON rows into SQL values matching the table type's columns.
+///
+/// Errors name the 0-based row index and the column.
+pub fn coerce_rows(
+    columns: &[TableTypeColumn],
+    rows: &[Value],
+) -> Result<Vec<Vec<SqlValue>>, String> {
+    rows.iter()
+        .enumerate()
+        .map(|(row_idx, row)| {
+            let values = row_values(columns, row).map_err(|e| format!("Row {}: {}", row_idx, e))?;
+            columns
+                .iter()
+                .zip(values)
+                .map(|(column, value)| {
+                    coerce_value(column, value).map_err(|e| {
+                        format!(
+                            "Row {}, column '{}' ({}): {}",
+                            row_idx,
+                            column.name,
+                            column.sql_type(),
+                            e
+                        )
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Values of a row in column order; missing object keys become NULL.
+fn row_values<'a>(columns: &[TableTypeColumn], row: &'a Value) -> Result<Vec<&'a Value>, String> {
+    match row {
+        Value::Array(values) => {
+            if values.len() != columns.len() {
+                return Err(format!(
+                    "has {} values but the table type has {} columns ({})",
+                    values.len(),
+                    columns.len(),
+                    column_names(columns)
+                ));
+            }
+            Ok(values.iter().collect())
+        }
+        Value::Object(fields) => {
+            if let Some(unknown) = fields
+                .keys()
+                .find(|key| !columns.iter().any(|c| c.name.eq_ignore_ascii_case(key)))
+            {
+                return Err(format!(
+                    "unknown column '{}' (columns: {})",
+                    unknown,
+                    column_names(columns)
+                ));
+            }
+            Ok(columns
+                .iter()
+                .map(|column| {
+                    fields
+                        .iter()
+                        .find(|(key, _)| column.name.eq_ignore_ascii_case(key))
+                        .map(|(_, value)| value)
+                        .unwrap_or(&Value::Null)
+                })
+                .collect())
+        }
+        _ => Err("must be an array of values or an object keyed by column name".to_string()),
+    }
+}
+
+fn column_names(columns: &[TableTypeColumn]) -> String {
+    columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Coerce a JSON value to the SQL type of a column.
+pub fn coerce_value(column: &TableTypeColumn, value: &Value) -> Result<SqlValue, String> {
+    if value.is_null() {
+        return if column.nullable {
+            Ok(SqlValue::Null)
+        } else {
+            Err("NULL is not allowed".to_string())
+        };
+    }
+
+    let value = match column.data_type.as_str() {
+        "bit" => SqlValue::Bool(to_bool(value)?),
+        "tinyint" => SqlValue::TinyInt(to_integer(value)?),
+        "smallint" => SqlValue::SmallInt(to_integer(value)?),
+        "int" => SqlValue::Int(to_integer(value)?),
+        "bigint" => SqlValue::BigInt(to_integer(value)?),
+        "real" => SqlValue::Float(to_float(value)? as f32),
+        "float" => SqlValue::Double(to_float(value)?),
+        "decimal" | "numeric" | "money" | "smallmoney" => {
+            SqlValue::Decimal(to_decimal(value, column)?)
+        }
+        "char" | "varchar" | "nchar" | "nvarchar" | "text" | "ntext" | "sysname" => {
+            let text = to_text(value)?;
+            if let Some(max) = column.max_chars() {
+                let len = text.chars().count();
+                if len > max {
+                    return Err(format!("{} characters exceed the maximum of {}", len, max));
+                }
+            }
+            SqlValue::String(text)
+        }
+        "binary" | "varbinary" | "image" => {
+            let bytes = to_binary(value)?;
+            if let Some(max) = column.max_chars() {
+                if bytes.len() > max {
+                    return Err(format!(
+                        "{} bytes exceed the maximum of {}",
+                        bytes.len(),
+                        max
+                    ));
+                }
+            }
+            SqlValue::Binary(bytes.into())
+        }
+        "uniqueidentifier" => SqlValue::Uuid(
+            uuid::Uuid::parse_str(expect_str(value)?)
+                .map_err(|_| format!("'{}' is not a valid GUID", value_text(value)))?,
+        ),
+        "date" => SqlValue::Date(
+            NaiveDate::parse_from_str(expect_str(value)?, "%Y-%m-%d")
+                .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD)", value_text(value)))?,
+        ),
+        "time" => SqlValue::Time(
+            NaiveTime::parse_from_str(expect_str(value)?, "%H:%M:%S%.f")
+                .map_err(|_| format!("'{}' is not a time (HH:MM:SS)", value_text(value)))?,
+        ),
+        "datetime" | "datetime2" | "smalldatetime" => {
+            SqlValue::DateTime(to_datetime(expect_str(value)?)?)
+        }
+        "datetimeoffset" => SqlValue::DateTimeOffset(
+            DateTime::parse_from_rfc3339(expect_str(value)?).map_err(|_| {
+                format!(
+                    "'{}' is not an ISO 8601 timestamp with offset",
+                    value_text(value)
+                )
+            })?,
+        ),
+        "xml" => SqlValue::Xml(to_text(value)?),
+        other => return Err(format!("type '{}' is not supported in TVPs", other)),
+    };
+    Ok(value)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn expect_str(value: &Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string, got {}", value))
+}
+
+fn to_bool(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        Value::Number(n) if n.as_i64() == Some(0) => Ok(false),
+        Value::Number(n) if n.as_i64() == Some(1) => Ok(true),
+        Value::String(s) => match s.to_ascii_lowercase().as_str() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err(format!("'{}' is not a boolean", s)),
+        },
+        other => Err(format!("{} is not a boolean", other)),
+    }
+}
+
+fn to_integer<T: TryFrom<i64>>(value: &Value) -> Result<T, String> {
+    let n = match value {
+        Value::Number(n) => n
+            .as_i64()
+            .ok_or_else(|| format!("{} is not an integer", n))?,
+        Value::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("'{}' is not an integer", s))?,
+        Value::Bool(b) => i64::from(*b),
+        other => return Err(format!("{} is not an integer", other)),
+    };
+    T::try_from(n).map_err(|_| format!("{} is out of range", n))
+}
+
+fn to_float(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(n) => n.as_f64().ok_or_else(|| format!("{} is not a number", n)),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", s)),
+        other => Err(format!("{} is not a number", other)),
+    }
+}
+
+fn to_decimal(value: &Value, column: &TableTypeColumn) -> Result<Decimal, String> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        other => return Err(format!("{} is not a number", other)),
+    };
+    let decimal = Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .map_err(|_| format!("'{}' is not a number", text))?;
+
+    if column.precision <= 0 {
+        return Ok(decimal);
+    }
+    // SQL Server rounds extra fractional digits but rejects integer overflow
+    let rounded = decimal.round_dp_with_strategy(
+        column.scale.max(0) as u32,
+        RoundingStrategy::MidpointAwayFromZero,
+    );
+    let integer_digits = rounded
+        .trunc()
+        .abs()
+        .to_string()
+        .trim_start_matches('0')
+        .len() as i32;
+    if integer_digits > column.precision - column.scale {
+        return Err(format!(
+            "{} does not fit (at most {} digits before the decimal point)",
+            text,
+            column.precision - column.scale
+        ));
+    }
+    Ok(rounded)
+}
+
+fn to_text(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        other => Err(format!("{} is not a string", other)),
+    }
+}
+
+/// Parse binary data given as a `0x`-prefixed hex string.
+fn to_binary(value: &Value) -> Result<Vec<u8>, String> {
+    let text = expect_str(value)?;
+    let hex = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .ok_or_else(|| format!("'{}' is not a 0x-prefixed hex string", text))?;
+    if hex.len() % 2 != 0 {
+        return Err(format!("'{}' has an odd number of hex digits", text));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| format!("'{}' is not a valid hex string", text))
+        })
+        .collect()
+}
+
+/// Parse an ISO 8601 date-time (a `T` or a space separator; date only means midnight).
+fn to_datetime(text: &str) -> Result<NaiveDateTime, String> {
+    let text = text.trim();
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| format!("'{}' is not an ISO 8601 date-time", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(
+        name: &str,
+        data_type: &str,
+        max_length: i32,
+        precision: i32,
+        scale: i32,
+    ) -> TableTypeColumn {
+        TableTypeColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            max_length,
+            precision,
+            scale,
+            nullable: true,
+        }
+    }
+
+    fn columns() -> Vec<TableTypeColumn> {
+        vec![
+            TableTypeColumn {
+                nullable: false,
+                ..column("Id", "int", 4, 10, 0)
+            },
+            column("Name", "nvarchar", 20, 0, 0),
+            column("Price", "decimal", 9, 5, 2),
+            column("CreatedAt", "datetime2", 8, 27, 7),
+        ]
+    }
+
+    #[test]
+    fn test_sql_type() {
+        let cols = columns();
+        assert_eq!(cols[0].sql_type(), "INT");
+        assert_eq!(cols[1].sql_type(), "NVARCHAR(10)");
+        assert_eq!(cols[2].sql_type(), "DECIMAL(5,2)");
+        assert_eq!(cols[3].sql_type(), "DATETIME2(7)");
+        assert_eq!(
+            column("Data", "varbinary", -1, 0, 0).sql_type(),
+            "VARBINARY(MAX)"
+        );
+    }
+
+    #[test]
+    fn test_coerce_array_and_object_rows() {
+        let rows = vec![
+            json!(["1", "Widget", 12.345, "2024-05-01T10:30:00"]),
+            json!({"id": 2, "PRICE": "7"}),
+        ];
+        let values = coerce_rows(&columns(), &rows).unwrap();
+
+        assert!(matches!(values[0][0], SqlValue::Int(1)));
+        assert!(matches!(&values[0][1], SqlValue::String(s) if s == "Widget"));
+        assert!(matches!(values[0][2], SqlValue::Decimal(d) if d.to_string() == "12.35"));
+        assert!(matches!(values[0][3], SqlValue::DateTime(_)));
+        assert!(matches!(values[1][1], SqlValue::Null));
+        assert!(matches!(values[1][3], SqlValue::Null));
+    }
+
+    #[test]
+    fn test_coerce_errors_name_row_and_column() {
+        let cols = columns();
+        let cases = [
+            (json!([1, "x"]), "Row 0: has 2 values"),
+            (
+                json!({"Id": 1, "Colour": "red"}),
+                "Row 0: unknown column 'Colour'",
+            ),
+            (
+                json!({"Name": "x"}),
+                "Row 0, column 'Id' (INT): NULL is not allowed",
+            ),
+            (
+                json!([1, "a very long name indeed", null, null]),
+                "column 'Name' (NVARCHAR(10))",
+            ),
+            (
+                json!([1, null, 1234.5, null]),
+                "column 'Price' (DECIMAL(5,2))",
+            ),
+            (json!([1.5, null, null, null]), "1.5 is not an integer"),
+            (
+                json!([1, null, null, "yesterday"]),
+                "not an ISO 8601 date-time",
+            ),
+            (json!("1"), "must be an array"),
+        ];
+        for (row, expected) in cases {
+            let err = coerce_rows(&cols, &[row]).unwrap_err();
+            assert!(
+                err.contains(expected),
+                "{} does not contain {}",
+                err,
+                expected
+            );
+        }
+
+        let tiny = column("Flag", "tinyint", 1, 3, 0);
+        assert!(coerce_value(&tiny, &json!(300))
+            .unwrap_err()
+            .contains("out of range"));
+    }
+
+    #[test]
+    fn test_coerce_other_types() {
+        let guid = column("Key", "uniqueidentifier", 16, 0, 0);
+        assert!(coerce_value(&guid, &json!("6F9619FF-8B86-D011-B42D-00C04FC964FF")).is_ok());
+        assert!(coerce_value(&guid, &json!("nope")).is_err());
+
+        let bin = column("Data", "varbinary", 2, 0, 0);
+        assert!(
+            matches!(coerce_value(&bin, &json!("0x0aFF")), Ok(SqlValue::Binary(b)) if b.len() == 2)
+        );
+        assert!(coerce_value(&bin, &json!("0x010203")).is_err());
+
+        let bit = column("Active", "bit", 1, 1, 0);
+        assert!(matches!(
+            coerce_value(&bit, &json!("TRUE")),
+            Ok(SqlValue::Bool(true))
+        ));
+        assert!(matches!(
+            coerce_value(&bit, &json!(0)),
+            Ok(SqlValue::Bool(false))
+        ));
+
+        let offset = column("At", "datetimeoffset", 10, 34, 7);
+        assert!(coerce_value(&offset, &json!("2024-05-01T10:30:00+02:00")).is_ok());
+    }
+}
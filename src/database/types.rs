@@ -1,11 +1,22 @@
 //! SQL Server type mapping to Rust types.
 
+use crate::database::udt::UdtValue;
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use mssql_client::Row;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
 
+/// Whether DECIMAL, NUMERIC and MONEY values keep their exact value.
+static EXACT_DECIMALS: AtomicBool = AtomicBool::new(true);
+
+/// Return DECIMAL, NUMERIC and MONEY values as exact decimals (serialized as
+/// strings such as `"123.4500"`), or as `f64` numbers when disabled.
+pub fn set_exact_decimals(enabled: bool) {
+    EXACT_DECIMALS.store(enabled, Ordering::Relaxed);
+}
+
 /// A SQL value that can be serialized to JSON.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -26,6 +37,9 @@ pub enum SqlValue {
     Time(NaiveTime),
     DateTime(NaiveDateTime),
     DateTimeUtc(DateTime<Utc>),
+    /// geometry, geography or hierarchyid (see [`UdtValue`]).
+    #[serde(skip_deserializing)]
+    Udt(UdtValue),
 }
 
 impl SqlValue {
@@ -53,6 +67,7 @@ impl SqlValue {
             SqlValue::Time(v) => v.to_string(),
             SqlValue::DateTime(v) => v.to_string(),
             SqlValue::DateTimeUtc(v) => v.to_rfc3339(),
+            SqlValue::Udt(v) => v.to_display_string(),
         }
     }
 }
@@ -73,6 +88,13 @@ impl TypeMapper {
             return SqlValue::Null;
         }
 
+        // Exact numerics would otherwise be read as f64 below
+        if EXACT_DECIMALS.load(Ordering::Relaxed) {
+            if let Some(value) = Self::extract_exact_numeric(row, idx) {
+                return value;
+            }
+        }
+
         // Try each type in order of likelihood
         // Strings (most common)
         if let Some(v) = row.try_get::<String>(idx) {
@@ -128,15 +150,37 @@ impl TypeMapper {
             return SqlValue::Time(v);
         }
 
-        // Binary
+        // Binary, including CLR types (geometry, geography, hierarchyid)
         if let Some(v) = row.try_get::<Vec<u8>>(idx) {
-            return SqlValue::Bytes(v);
+            let is_udt = row.columns().get(idx).is_some_and(|c| c.type_name == "Udt");
+            return if is_udt {
+                SqlValue::Udt(UdtValue::decode(v))
+            } else {
+                SqlValue::Bytes(v)
+            };
         }
 
         // Fall back to NULL for unsupported types
         SqlValue::Null
     }
 
+    /// Extract a DECIMAL, NUMERIC or MONEY column as a [`Decimal`].
+    ///
+    /// Returns `None` for other column types.
+    fn extract_exact_numeric(row: &Row, idx: usize) -> Option<SqlValue> {
+        let column = row.columns().get(idx)?;
+        match column.type_name.as_str() {
+            "Decimal" | "Numeric" | "DecimalN" | "NumericN" => {
+                row.try_get::<Decimal>(idx).map(SqlValue::Decimal)
+            }
+            // The driver decodes MONEY into an f64 of the scaled integer
+            "Money" | "Money4" | "MoneyN" => row
+                .try_get::<f64>(idx)
+                .map(|v| SqlValue::Decimal(money_to_decimal(v))),
+            _ => None,
+        }
+    }
+
     /// Get the SQL type name for a column based on the value.
     ///
     /// Note: This is a best-effort type detection based on the extracted value.
@@ -159,12 +203,21 @@ impl TypeMapper {
             SqlValue::Time(_) => "TIME",
             SqlValue::DateTime(_) => "DATETIME2",
             SqlValue::DateTimeUtc(_) => "DATETIMEOFFSET",
+            SqlValue::Udt(v) => v.type_name(),
         }
     }
 }
 
+/// Recover the exact MONEY value (four decimal places) from its f64 form.
+///
+/// MONEY is an integer count of ten-thousandths, so rounding restores it
+/// exactly for any value within f64's 53-bit integer range.
+fn money_to_decimal(value: f64) -> Decimal {
+    Decimal::new((value * 10_000.0).round() as i64, 4)
+}
+
 /// Hex encoding helper (minimal implementation to avoid extra dependency).
-mod hex {
+pub(crate) mod hex {
     pub fn encode(bytes: &[u8]) -> String {
         bytes.iter().map(|b| format!("{:02X}", b)).collect()
     }
@@ -185,6 +238,25 @@ mod tests {
         assert_eq!(SqlValue::Bool(true).to_display_string(), "true");
     }
 
+    #[test]
+    fn test_money_to_decimal() {
+        assert_eq!(money_to_decimal(123.45).to_string(), "123.4500");
+        assert_eq!(money_to_decimal(-0.0001).to_string(), "-0.0001");
+        assert_eq!(
+            money_to_decimal(92233720368.5477).to_string(),
+            "92233720368.5477"
+        );
+    }
+
+    #[test]
+    fn test_decimal_serializes_as_string() {
+        let value = SqlValue::Decimal("12345678901234567890.1234".parse().unwrap());
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            "\"12345678901234567890.1234\""
+        );
+    }
+
     #[test]
     fn test_sql_value_is_null() {
         assert!(SqlValue::Null.is_null());
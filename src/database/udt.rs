@@ -0,0 +1,643 @@
+//! Decoding of SQL Server CLR user-defined type values.
+//!
+//! `geometry`, `geography` and `hierarchyid` columns reach the client as the
+//! raw bytes of their CLR serialization. This module decodes them: spatial
+//! values into shapes that render as WKT or GeoJSON, and hierarchyid values
+//! into their `/1/2.5/` path. Values that cannot be decoded (other CLR
+//! types, unsupported curve shapes) are shown as hex.
+//!
+//! The driver does not report which UDT a column has, so spatial values are
+//! classified by SRID: SRID 0 and SRIDs outside the geography range are
+//! `geometry`. Geography points are stored latitude first and are written
+//! longitude first, as WKT and GeoJSON expect.
+
+use crate::database::types::hex;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Value};
+
+// =============================================================================
+// Representation
+// =============================================================================
+
+/// How spatial values are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpatialFormat {
+    /// Well-known text, e.g. `POINT (-122.349 47.651)` (default).
+    #[default]
+    Wkt,
+    /// GeoJSON geometry objects.
+    GeoJson,
+    /// The serialized bytes as hex, like `SELECT` in SSMS.
+    Binary,
+}
+
+impl SpatialFormat {
+    /// Get the format name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpatialFormat::Wkt => "wkt",
+            SpatialFormat::GeoJson => "geojson",
+            SpatialFormat::Binary => "binary",
+        }
+    }
+
+    /// Generate JSON Schema for this type.
+    pub fn tool_input_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "string",
+            "enum": ["wkt", "geojson", "binary"],
+            "default": "wkt",
+            "description": "Representation of geometry/geography values: 'wkt', 'geojson', or 'binary' (hex); hierarchyid values are returned as their path unless 'binary'"
+        })
+    }
+}
+
+/// A CLR user-defined type value.
+#[derive(Debug, Clone)]
+pub struct UdtValue {
+    bytes: Vec<u8>,
+    decoded: Decoded,
+    format: SpatialFormat,
+}
+
+#[derive(Debug, Clone)]
+enum Decoded {
+    Spatial(Spatial),
+    HierarchyId(String),
+    Unknown,
+}
+
+impl UdtValue {
+    /// Decode the serialized bytes of a UDT column value.
+    pub fn decode(bytes: Vec<u8>) -> Self {
+        let decoded = if let Some(spatial) = Spatial::parse(&bytes) {
+            Decoded::Spatial(spatial)
+        } else if let Some(path) = decode_hierarchyid(&bytes) {
+            Decoded::HierarchyId(path)
+        } else {
+            Decoded::Unknown
+        };
+        Self {
+            bytes,
+            decoded,
+            format: SpatialFormat::default(),
+        }
+    }
+
+    /// Choose how the value is rendered.
+    pub fn set_format(&mut self, format: SpatialFormat) {
+        self.format = format;
+    }
+
+    /// SQL type the value was decoded as.
+    pub fn type_name(&self) -> &'static str {
+        match &self.decoded {
+            Decoded::Spatial(s) if s.geography => "GEOGRAPHY",
+            Decoded::Spatial(_) => "GEOMETRY",
+            Decoded::HierarchyId(_) => "HIERARCHYID",
+            Decoded::Unknown => "UDT",
+        }
+    }
+
+    /// The raw serialized bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Text of the value in the chosen format.
+    pub fn to_display_string(&self) -> String {
+        match (&self.decoded, self.format) {
+            (_, SpatialFormat::Binary) | (Decoded::Unknown, _) => {
+                format!("0x{}", hex::encode(&self.bytes))
+            }
+            (Decoded::HierarchyId(path), _) => path.clone(),
+            (Decoded::Spatial(s), SpatialFormat::GeoJson) => match s.to_geojson() {
+                Some(geojson) => geojson.to_string(),
+                None => s.to_wkt(),
+            },
+            (Decoded::Spatial(s), SpatialFormat::Wkt) => s.to_wkt(),
+        }
+    }
+}
+
+impl Serialize for UdtValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let (Decoded::Spatial(s), SpatialFormat::GeoJson) = (&self.decoded, self.format) {
+            if let Some(geojson) = s.to_geojson() {
+                return geojson.serialize(serializer);
+            }
+        }
+        serializer.serialize_str(&self.to_display_string())
+    }
+}
+
+// =============================================================================
+// Spatial
+// =============================================================================
+
+const HAS_Z: u8 = 0x01;
+const HAS_M: u8 = 0x02;
+const SINGLE_POINT: u8 = 0x08;
+const SINGLE_LINE_SEGMENT: u8 = 0x10;
+
+/// OpenGIS shape types as serialized.
+const POINT: u8 = 1;
+const LINE_STRING: u8 = 2;
+const POLYGON: u8 = 3;
+const MULTI_POINT: u8 = 4;
+const MULTI_LINE_STRING: u8 = 5;
+const MULTI_POLYGON: u8 = 6;
+const GEOMETRY_COLLECTION: u8 = 7;
+const CIRCULAR_STRING: u8 = 8;
+const FULL_GLOBE: u8 = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Coord {
+    x: f64,
+    y: f64,
+    z: Option<f64>,
+    m: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+struct Shape {
+    kind: u8,
+    /// Point lists of the shape's own figures (points, line, rings).
+    figures: Vec<Vec<Coord>>,
+    children: Vec<Shape>,
+}
+
+/// A decoded geometry or geography value.
+#[derive(Debug, Clone)]
+struct Spatial {
+    geography: bool,
+    shape: Shape,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let chunk = self.bytes.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        chunk.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|b| b[0])
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.take::<4>().map(i32::from_le_bytes)
+    }
+
+    fn count(&mut self) -> Option<usize> {
+        // Counts are bounded by the remaining bytes, which rejects garbage early
+        let n = usize::try_from(self.i32()?).ok()?;
+        (n <= self.bytes.len() - self.pos).then_some(n)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.take::<8>().map(f64::from_le_bytes)
+    }
+}
+
+/// Whether an SRID can belong to a geography value.
+fn is_geography_srid(srid: i32) -> bool {
+    (4120..=4999).contains(&srid) || srid == 104001
+}
+
+impl Spatial {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader { bytes, pos: 0 };
+        let srid = r.i32()?;
+        let version = r.u8()?;
+        if !(1..=2).contains(&version) || srid < 0 {
+            return None;
+        }
+        let properties = r.u8()?;
+
+        let point_count = if properties & SINGLE_POINT != 0 {
+            1
+        } else if properties & SINGLE_LINE_SEGMENT != 0 {
+            2
+        } else {
+            r.count()?
+        };
+        let mut raw: Vec<(f64, f64)> = Vec::with_capacity(point_count);
+        for _ in 0..point_count {
+            raw.push((r.f64()?, r.f64()?));
+        }
+        let mut read_ordinates = |present: bool| -> Option<Vec<Option<f64>>> {
+            (0..point_count)
+                .map(|_| {
+                    if present {
+                        r.f64().map(Some)
+                    } else {
+                        Some(None)
+                    }
+                })
+                .collect()
+        };
+        let z = read_ordinates(properties & HAS_Z != 0)?;
+        let m = read_ordinates(properties & HAS_M != 0)?;
+
+        // Geography stores latitude first; a first ordinate outside +/-90 rules it out
+        let geography =
+            is_geography_srid(srid) && raw.iter().all(|(lat, _)| (-90.0..=90.0).contains(lat));
+        let points: Vec<Coord> = raw
+            .iter()
+            .zip(z.into_iter().zip(m))
+            .map(|(&(a, b), (z, m))| {
+                let (x, y) = if geography { (b, a) } else { (a, b) };
+                Coord { x, y, z, m }
+            })
+            .collect();
+
+        if properties & (SINGLE_POINT | SINGLE_LINE_SEGMENT) != 0 {
+            let kind = if point_count == 1 { POINT } else { LINE_STRING };
+            return (r.pos == bytes.len()).then(|| Self {
+                geography,
+                shape: Shape {
+                    kind,
+                    figures: vec![points],
+                    children: Vec::new(),
+                },
+            });
+        }
+
+        let figure_count = r.count()?;
+        let mut figure_offsets = Vec::with_capacity(figure_count);
+        for _ in 0..figure_count {
+            let _attribute = r.u8()?;
+            figure_offsets.push(usize::try_from(r.i32()?).ok()?);
+        }
+        let shape_count = r.count()?;
+        let mut shapes = Vec::with_capacity(shape_count);
+        for _ in 0..shape_count {
+            let parent = r.i32()?;
+            let figure = r.i32()?;
+            let kind = r.u8()?;
+            shapes.push((parent, figure, kind));
+        }
+        if version == 2 {
+            // Segments describe circular arcs, which are not decoded
+            let segment_count = r.count()?;
+            if segment_count > 0 {
+                return None;
+            }
+        }
+        if r.pos != bytes.len() || shapes.is_empty() {
+            return None;
+        }
+
+        let figures: Vec<Vec<Coord>> = (0..figure_count)
+            .map(|i| {
+                let start = figure_offsets[i];
+                let end = figure_offsets.get(i + 1).copied().unwrap_or(points.len());
+                points.get(start..end).map(<[Coord]>::to_vec)
+            })
+            .collect::<Option<_>>()?;
+        let shape = build_shape(0, &shapes, &figures)?;
+        Some(Self { geography, shape })
+    }
+
+    fn to_wkt(&self) -> String {
+        shape_wkt(&self.shape, true)
+    }
+
+    /// GeoJSON geometry; `None` for shapes GeoJSON cannot express.
+    fn to_geojson(&self) -> Option<Value> {
+        shape_geojson(&self.shape)
+    }
+}
+
+/// Build shape `index` with its own figures and child shapes.
+fn build_shape(index: usize, shapes: &[(i32, i32, u8)], figures: &[Vec<Coord>]) -> Option<Shape> {
+    let (_, figure_offset, kind) = shapes[index];
+    let children: Vec<Shape> = shapes
+        .iter()
+        .enumerate()
+        .filter(|(_, (parent, _, _))| usize::try_from(*parent).ok() == Some(index))
+        .map(|(child, _)| (child > index).then(|| build_shape(child, shapes, figures))?)
+        .collect::<Option<_>>()?;
+
+    let own_figures =
+        if figure_offset < 0 || !matches!(kind, POINT | LINE_STRING | POLYGON | CIRCULAR_STRING) {
+            Vec::new()
+        } else {
+            let start = usize::try_from(figure_offset).ok()?;
+            let end = shapes[index + 1..]
+                .iter()
+                .find(|(_, offset, _)| *offset >= 0)
+                .map(|(_, offset, _)| *offset as usize)
+                .unwrap_or(figures.len());
+            figures.get(start..end)?.to_vec()
+        };
+    if !matches!(kind, 1..=8 | FULL_GLOBE) {
+        return None;
+    }
+    Some(Shape {
+        kind,
+        figures: own_figures,
+        children,
+    })
+}
+
+fn number(value: f64) -> String {
+    format!("{}", value)
+}
+
+fn coord_wkt(c: &Coord) -> String {
+    let mut text = format!("{} {}", number(c.x), number(c.y));
+    match (c.z, c.m) {
+        (Some(z), Some(m)) => text.push_str(&format!(" {} {}", number(z), number(m))),
+        (Some(z), None) => text.push_str(&format!(" {}", number(z))),
+        (None, Some(m)) => text.push_str(&format!(" NULL {}", number(m))),
+        (None, None) => {}
+    }
+    text
+}
+
+fn coords_wkt(coords: &[Coord]) -> String {
+    format!(
+        "({})",
+        coords.iter().map(coord_wkt).collect::<Vec<_>>().join(", ")
+    )
+}
+
+fn shape_wkt(shape: &Shape, tagged: bool) -> String {
+    let name = match shape.kind {
+        POINT => "POINT",
+        LINE_STRING => "LINESTRING",
+        POLYGON => "POLYGON",
+        MULTI_POINT => "MULTIPOINT",
+        MULTI_LINE_STRING => "MULTILINESTRING",
+        MULTI_POLYGON => "MULTIPOLYGON",
+        GEOMETRY_COLLECTION => "GEOMETRYCOLLECTION",
+        CIRCULAR_STRING => "CIRCULARSTRING",
+        _ => return "FULLGLOBE".to_string(),
+    };
+    let body = match shape.kind {
+        POINT | LINE_STRING | CIRCULAR_STRING => shape.figures.first().map(|f| coords_wkt(f)),
+        POLYGON if !shape.figures.is_empty() => Some(format!(
+            "({})",
+            shape
+                .figures
+                .iter()
+                .map(|f| coords_wkt(f))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        MULTI_POINT | MULTI_LINE_STRING | MULTI_POLYGON if !shape.children.is_empty() => {
+            Some(format!(
+                "({})",
+                shape
+                    .children
+                    .iter()
+                    .map(|c| shape_wkt(c, false))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+        GEOMETRY_COLLECTION if !shape.children.is_empty() => Some(format!(
+            "({})",
+            shape
+                .children
+                .iter()
+                .map(|c| shape_wkt(c, true))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        _ => None,
+    }
+    .filter(|b| b != "()")
+    .unwrap_or_else(|| "EMPTY".to_string());
+
+    if tagged {
+        format!("{} {}", name, body)
+    } else {
+        body
+    }
+}
+
+fn coord_json(c: &Coord) -> Value {
+    match c.z {
+        Some(z) => json!([c.x, c.y, z]),
+        None => json!([c.x, c.y]),
+    }
+}
+
+fn shape_geojson(shape: &Shape) -> Option<Value> {
+    let line = |coords: &Vec<Coord>| Value::Array(coords.iter().map(coord_json).collect());
+    let polygon = |s: &Shape| Value::Array(s.figures.iter().map(line).collect());
+    let children = |f: &dyn Fn(&Shape) -> Option<Value>| -> Option<Vec<Value>> {
+        shape.children.iter().map(f).collect()
+    };
+    Some(match shape.kind {
+        POINT => json!({
+            "type": "Point",
+            "coordinates": shape.figures.first().and_then(|f| f.first()).map(coord_json)
+                .unwrap_or_else(|| json!([])),
+        }),
+        LINE_STRING => json!({
+            "type": "LineString",
+            "coordinates": shape.figures.first().map(line).unwrap_or_else(|| json!([])),
+        }),
+        POLYGON => json!({"type": "Polygon", "coordinates": polygon(shape)}),
+        MULTI_POINT => json!({
+            "type": "MultiPoint",
+            "coordinates": children(&|c| c.figures.first().and_then(|f| f.first()).map(coord_json))?,
+        }),
+        MULTI_LINE_STRING => json!({
+            "type": "MultiLineString",
+            "coordinates": children(&|c| c.figures.first().map(line))?,
+        }),
+        MULTI_POLYGON => json!({
+            "type": "MultiPolygon",
+            "coordinates": children(&|c| Some(polygon(c)))?,
+        }),
+        GEOMETRY_COLLECTION => json!({
+            "type": "GeometryCollection",
+            "geometries": children(&shape_geojson)?,
+        }),
+        _ => return None,
+    })
+}
+
+// =============================================================================
+// HierarchyId
+// =============================================================================
+
+/// Bit patterns of hierarchyid label components: the range of values and the
+/// pattern after the prefix bits, where `x` are value bits (most significant
+/// first), `0`/`1` are fixed filler bits and `T` ends the component.
+const HIERARCHYID_PATTERNS: &[(i64, &str)] = &[
+    (0, "01xxT"),
+    (4, "100xxT"),
+    (8, "101xxxT"),
+    (16, "110xx0x1xxxT"),
+    (80, "1110xxx0xxx0x1xxxT"),
+    (1104, "11110xxxxx0xxx0x1xxxT"),
+    (5200, "111110xxxxxxxxxxxxxxxxxxx0xxxxxx0xxx0x1xxxT"),
+    (
+        4294972496,
+        "111111xxxxxxxxxxxxxx0xxxxxxxxxxxxxxxxxxxxx0xxxxxx0xxx0x1xxxT",
+    ),
+    (-8, "00111xxxT"),
+    (-72, "0010xx0x1xxxT"),
+    (-4168, "000110xxxxx0xxx0x1xxxT"),
+    (-4294971464, "000101xxxxxxxxxxxxxxxxxxx0xxxxxx0xxx0x1xxxT"),
+    (
+        -281479271682120,
+        "000100xxxxxxxxxxxxxx0xxxxxxxxxxxxxxxxxxxxx0xxxxxx0xxx0x1xxxT",
+    ),
+];
+
+/// Decode a hierarchyid into its path, e.g. `/1/3.2/`.
+///
+/// Each component ends with a bit that is 1 at the end of a level and 0
+/// before a `.`; components followed by a `.` are stored one higher.
+fn decode_hierarchyid(bytes: &[u8]) -> Option<String> {
+    let bit = |i: usize| (bytes[i / 8] >> (7 - i % 8)) & 1 == 1;
+    let total = bytes.len() * 8;
+    let mut pos = 0;
+    let mut path = String::from("/");
+    let mut at_level_start = true;
+
+    while (pos..total).any(bit) {
+        let (min, pattern) = HIERARCHYID_PATTERNS.iter().find(|(_, pattern)| {
+            let prefix = pattern.find(['x', 'T']).unwrap_or(pattern.len());
+            pos + pattern.len() <= total
+                && pattern[..prefix]
+                    .bytes()
+                    .enumerate()
+                    .all(|(i, b)| bit(pos + i) == (b == b'1'))
+        })?;
+        let mut value: i64 = 0;
+        let mut last = false;
+        for (i, b) in pattern.bytes().enumerate() {
+            let set = bit(pos + i);
+            match b {
+                b'x' => value = (value << 1) | i64::from(set),
+                b'T' => last = set,
+                fixed => {
+                    if set != (fixed == b'1') {
+                        return None;
+                    }
+                }
+            }
+        }
+        pos += pattern.len();
+        let value = min + value - i64::from(!last);
+        path.push_str(&value.to_string());
+        path.push(if last { '/' } else { '.' });
+        at_level_start = last;
+    }
+
+    at_level_start.then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn display(hex: &str, format: SpatialFormat) -> String {
+        let mut value = UdtValue::decode(from_hex(hex));
+        value.set_format(format);
+        value.to_display_string()
+    }
+
+    #[test]
+    fn test_geography_point() {
+        // geography::Point(47.651, -122.349, 4326)
+        let hex = "E6100000010C17D9CEF753D347407593180456965EC0";
+        assert_eq!(display(hex, SpatialFormat::Wkt), "POINT (-122.349 47.651)");
+        assert_eq!(
+            display(hex, SpatialFormat::GeoJson),
+            r#"{"coordinates":[-122.349,47.651],"type":"Point"}"#
+        );
+        assert_eq!(display(hex, SpatialFormat::Binary), format!("0x{}", hex));
+        assert_eq!(UdtValue::decode(from_hex(hex)).type_name(), "GEOGRAPHY");
+    }
+
+    fn point(x: f64, y: f64) -> Vec<u8> {
+        [x.to_le_bytes(), y.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn test_geometry_shapes() {
+        // geometry::Parse('LINESTRING (1 2, 3 4)'): single line segment
+        let line = [
+            &[0, 0, 0, 0, 1, 0x14][..],
+            &point(1.0, 2.0),
+            &point(3.0, 4.0),
+        ]
+        .concat();
+        let value = UdtValue::decode(line);
+        assert_eq!(value.type_name(), "GEOMETRY");
+        assert_eq!(value.to_display_string(), "LINESTRING (1 2, 3 4)");
+
+        // POLYGON ((0 0, 4 0, 4 4, 0 0)) with a POINT in a GEOMETRYCOLLECTION
+        let mut bytes = vec![0, 0, 0, 0, 1, 0x04];
+        bytes.extend(5i32.to_le_bytes());
+        for (x, y) in [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 0.0), (9.0, 9.0)] {
+            bytes.extend(point(x, y));
+        }
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend([2u8].iter().chain(&0i32.to_le_bytes()));
+        bytes.extend([1u8].iter().chain(&4i32.to_le_bytes()));
+        bytes.extend(3i32.to_le_bytes());
+        for (parent, figure, kind) in [
+            (-1i32, 0i32, GEOMETRY_COLLECTION),
+            (0, 0, POLYGON),
+            (0, 1, POINT),
+        ] {
+            bytes.extend(parent.to_le_bytes());
+            bytes.extend(figure.to_le_bytes());
+            bytes.push(kind);
+        }
+        let mut value = UdtValue::decode(bytes);
+        assert_eq!(
+            value.to_display_string(),
+            "GEOMETRYCOLLECTION (POLYGON ((0 0, 4 0, 4 4, 0 0)), POINT (9 9))"
+        );
+        value.set_format(SpatialFormat::GeoJson);
+        let geojson: Value = serde_json::to_value(&value).unwrap();
+        assert_eq!(geojson["type"], "GeometryCollection");
+        assert_eq!(
+            geojson["geometries"][0]["coordinates"][0][2],
+            json!([4.0, 4.0])
+        );
+        assert_eq!(geojson["geometries"][1]["coordinates"], json!([9.0, 9.0]));
+    }
+
+    #[test]
+    fn test_hierarchyid() {
+        assert_eq!(display("", SpatialFormat::Wkt), "/");
+        assert_eq!(display("58", SpatialFormat::Wkt), "/1/");
+        assert_eq!(display("5AC0", SpatialFormat::Wkt), "/1/1/");
+        assert_eq!(display("62C0", SpatialFormat::Wkt), "/1.1/");
+        assert_eq!(display("3F80", SpatialFormat::Wkt), "/-1/");
+        assert_eq!(display("58", SpatialFormat::Binary), "0x58");
+        assert_eq!(UdtValue::decode(from_hex("58")).type_name(), "HIERARCHYID");
+    }
+
+    #[test]
+    fn test_undecodable_values_are_hex() {
+        let value = UdtValue::decode(vec![0xFF, 0xFF]);
+        assert_eq!(value.type_name(), "UDT");
+        assert_eq!(value.to_display_string(), "0xFFFF");
+    }
+}
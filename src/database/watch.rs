@@ -0,0 +1,499 @@
+//! Table change watches using polling watermarks.
+//!
+//! A watch registers a table and a monotonically increasing watermark column
+//! (`rowversion`, a datetime type, or an identity-style integer). A background
+//! poller periodically fetches rows whose watermark exceeds the last one seen
+//! and queues them so clients can retrieve new or changed rows on demand.
+//!
+//! Each watch is throttled independently: it polls no more often than its
+//! configured interval, fetches a bounded number of rows per poll, and keeps
+//! a bounded queue (dropping the oldest rows when clients fall behind).
+
+use crate::constants::{MAX_TABLE_WATCHES, MAX_WATCH_PENDING_ROWS, MIN_WATCH_POLL_INTERVAL_SECS};
+use crate::database::query::{QueryExecutor, ResultRow};
+use crate::database::types::SqlValue;
+use crate::error::ServerError;
+use chrono::{DateTime, NaiveDateTime, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Column types accepted as watermarks.
+const WATERMARK_TYPES: &[&str] = &[
+    "timestamp",
+    "rowversion",
+    "datetime",
+    "datetime2",
+    "smalldatetime",
+    "datetimeoffset",
+    "date",
+    "tinyint",
+    "smallint",
+    "int",
+    "bigint",
+    "decimal",
+    "numeric",
+];
+
+/// Parameters for registering a table watch.
+#[derive(Debug, Clone)]
+pub struct WatchSpec {
+    /// Schema of the watched table.
+    pub schema: String,
+    /// Name of the watched table.
+    pub table: String,
+    /// Watermark column (rowversion, datetime or increasing integer).
+    pub watermark_column: String,
+    /// Database to poll in (`None` = connection default).
+    pub database: Option<String>,
+    /// Interval between polls.
+    pub poll_interval: Duration,
+    /// Maximum rows fetched per poll.
+    pub max_rows_per_poll: usize,
+    /// Report existing rows instead of only rows added after registration.
+    pub from_beginning: bool,
+}
+
+/// Public status of a table watch.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchInfo {
+    /// Watch ID.
+    pub id: String,
+    /// Schema of the watched table.
+    pub schema: String,
+    /// Name of the watched table.
+    pub table: String,
+    /// Watermark column.
+    pub watermark_column: String,
+    /// SQL type of the watermark column.
+    pub watermark_type: String,
+    /// Database being polled.
+    pub database: Option<String>,
+    /// Interval between polls in seconds.
+    pub poll_interval_secs: u64,
+    /// Maximum rows fetched per poll.
+    pub max_rows_per_poll: usize,
+    /// Last watermark seen, as displayed by SQL Server.
+    pub last_watermark: Option<String>,
+    /// When the watch was created.
+    pub created_at: DateTime<Utc>,
+    /// When the table was last polled.
+    pub last_polled_at: Option<DateTime<Utc>>,
+    /// Number of polls performed.
+    pub poll_count: u64,
+    /// Total rows detected since the watch was created.
+    pub rows_detected: u64,
+    /// Rows dropped because the queue was full.
+    pub rows_dropped: u64,
+    /// Rows waiting to be retrieved.
+    pub pending_rows: usize,
+    /// Error from the most recent poll, if it failed.
+    pub last_error: Option<String>,
+}
+
+/// Rows retrieved from a watch queue.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchChanges {
+    /// Watch ID.
+    pub watch_id: String,
+    /// New or changed rows, ordered by watermark.
+    pub rows: Vec<ResultRow>,
+    /// Rows still queued after this retrieval.
+    pub remaining: usize,
+    /// Rows dropped because the queue was full.
+    pub rows_dropped: u64,
+    /// Last watermark seen.
+    pub last_watermark: Option<String>,
+}
+
+/// Internal watch state.
+struct TableWatch {
+    info: WatchInfo,
+    watermark: Option<SqlValue>,
+    pending: VecDeque<ResultRow>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Manager for table watches and their background pollers.
+pub struct WatchManager {
+    /// Executor used for polling queries.
+    executor: Arc<QueryExecutor>,
+
+    /// Active watches keyed by watch ID.
+    watches: Arc<Mutex<HashMap<String, TableWatch>>>,
+}
+
+impl WatchManager {
+    /// Create a new watch manager.
+    pub fn new(executor: Arc<QueryExecutor>) -> Self {
+        Self {
+            executor,
+            watches: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Register a watch and start its background poller.
+    pub async fn start(&self, spec: WatchSpec) -> Result<WatchInfo, ServerError> {
+        if self.watches.lock().await.len() >= MAX_TABLE_WATCHES {
+            return Err(ServerError::invalid_input(format!(
+                "Maximum number of table watches ({}) reached",
+                MAX_TABLE_WATCHES
+            )));
+        }
+
+        let (column, watermark_type) = self.resolve_watermark_column(&spec).await?;
+
+        let watermark = if spec.from_beginning {
+            None
+        } else {
+            let query = format!(
+                "{}SELECT MAX([{}]) AS watermark FROM [{}].[{}]",
+                use_prefix(spec.database.as_deref()),
+                column,
+                spec.schema,
+                spec.table
+            );
+            let result = self.executor.execute(&query).await?;
+            result
+                .rows
+                .first()
+                .and_then(|row| row.get("watermark"))
+                .filter(|v| !v.is_null())
+                .cloned()
+        };
+
+        let poll_interval = spec
+            .poll_interval
+            .max(Duration::from_secs(MIN_WATCH_POLL_INTERVAL_SECS));
+        let id = uuid::Uuid::new_v4().to_string();
+        let info = WatchInfo {
+            id: id.clone(),
+            schema: spec.schema,
+            table: spec.table,
+            watermark_column: column,
+            watermark_type,
+            database: spec.database,
+            poll_interval_secs: poll_interval.as_secs(),
+            max_rows_per_poll: spec.max_rows_per_poll.max(1),
+            last_watermark: watermark.as_ref().map(|v| v.to_display_string()),
+            created_at: Utc::now(),
+            last_polled_at: None,
+            poll_count: 0,
+            rows_detected: 0,
+            rows_dropped: 0,
+            pending_rows: 0,
+            last_error: None,
+        };
+
+        let handle = tokio::spawn(poll_loop(
+            id.clone(),
+            poll_interval,
+            Arc::clone(&self.executor),
+            Arc::clone(&self.watches),
+        ));
+
+        self.watches.lock().await.insert(
+            id,
+            TableWatch {
+                info: info.clone(),
+                watermark,
+                pending: VecDeque::new(),
+                handle: Some(handle),
+            },
+        );
+
+        Ok(info)
+    }
+
+    /// Take up to `max_rows` queued rows from a watch.
+    pub async fn take_changes(
+        &self,
+        watch_id: &str,
+        max_rows: usize,
+    ) -> Result<WatchChanges, ServerError> {
+        let mut watches = self.watches.lock().await;
+        let watch = watches
+            .get_mut(watch_id)
+            .ok_or_else(|| ServerError::object_not_found("watch", watch_id))?;
+
+        let count = max_rows.min(watch.pending.len());
+        let rows: Vec<ResultRow> = watch.pending.drain(..count).collect();
+        watch.info.pending_rows = watch.pending.len();
+
+        Ok(WatchChanges {
+            watch_id: watch_id.to_string(),
+            rows,
+            remaining: watch.pending.len(),
+            rows_dropped: watch.info.rows_dropped,
+            last_watermark: watch.info.last_watermark.clone(),
+        })
+    }
+
+    /// List all active watches.
+    pub async fn list(&self) -> Vec<WatchInfo> {
+        let watches = self.watches.lock().await;
+        let mut list: Vec<WatchInfo> = watches.values().map(|w| w.info.clone()).collect();
+        list.sort_by_key(|w| w.created_at);
+        list
+    }
+
+    /// Stop a watch and discard its queued rows.
+    pub async fn stop(&self, watch_id: &str) -> Result<WatchInfo, ServerError> {
+        let watch = self
+            .watches
+            .lock()
+            .await
+            .remove(watch_id)
+            .ok_or_else(|| ServerError::object_not_found("watch", watch_id))?;
+
+        if let Some(handle) = watch.handle {
+            handle.abort();
+        }
+        Ok(watch.info)
+    }
+
+    /// Stop all watches (used during shutdown).
+    pub async fn stop_all(&self) -> usize {
+        let mut watches = self.watches.lock().await;
+        let count = watches.len();
+        for (_, watch) in watches.drain() {
+            if let Some(handle) = watch.handle {
+                handle.abort();
+            }
+        }
+        count
+    }
+
+    /// Look up the watermark column, returning its exact name and SQL type.
+    async fn resolve_watermark_column(
+        &self,
+        spec: &WatchSpec,
+    ) -> Result<(String, String), ServerError> {
+        let query = format!(
+            "{}SELECT COLUMN_NAME, DATA_TYPE FROM INFORMATION_SCHEMA.COLUMNS \
+             WHERE TABLE_SCHEMA = '{}' AND TABLE_NAME = '{}' AND COLUMN_NAME = '{}'",
+            use_prefix(spec.database.as_deref()),
+            spec.schema.replace('\'', "''"),
+            spec.table.replace('\'', "''"),
+            spec.watermark_column.replace('\'', "''")
+        );
+        let result = self.executor.execute(&query).await?;
+        let row = result.rows.first().ok_or_else(|| {
+            ServerError::object_not_found(
+                "column",
+                format!("{}.{}.{}", spec.schema, spec.table, spec.watermark_column),
+            )
+        })?;
+
+        let column = row
+            .get("COLUMN_NAME")
+            .map(|v| v.to_display_string())
+            .unwrap_or_else(|| spec.watermark_column.clone());
+        let data_type = row
+            .get("DATA_TYPE")
+            .map(|v| v.to_display_string().to_lowercase())
+            .unwrap_or_default();
+
+        if !WATERMARK_TYPES.contains(&data_type.as_str()) {
+            return Err(ServerError::invalid_input(format!(
+                "Column '{}' has type {}; watermark columns must be rowversion, a datetime type, or an increasing integer",
+                column, data_type
+            )));
+        }
+
+        Ok((column, data_type))
+    }
+}
+
+/// Background poller for a single watch. Exits when the watch is removed.
+async fn poll_loop(
+    id: String,
+    interval: Duration,
+    executor: Arc<QueryExecutor>,
+    watches: Arc<Mutex<HashMap<String, TableWatch>>>,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let query = {
+            let watches = watches.lock().await;
+            let Some(watch) = watches.get(&id) else {
+                break;
+            };
+            build_poll_query(
+                &watch.info,
+                watch
+                    .watermark
+                    .as_ref()
+                    .and_then(watermark_literal)
+                    .as_deref(),
+            )
+        };
+
+        let outcome = executor.execute(&query).await;
+
+        let mut watches = watches.lock().await;
+        let Some(watch) = watches.get_mut(&id) else {
+            break;
+        };
+        watch.info.poll_count += 1;
+        watch.info.last_polled_at = Some(Utc::now());
+
+        match outcome {
+            Ok(result) => {
+                watch.info.last_error = None;
+                if let Some(value) = result
+                    .rows
+                    .last()
+                    .and_then(|row| row.get(&watch.info.watermark_column))
+                    .filter(|v| !v.is_null())
+                {
+                    watch.info.last_watermark = Some(value.to_display_string());
+                    watch.watermark = Some(value.clone());
+                }
+
+                let detected = result.rows.len();
+                watch.info.rows_detected += detected as u64;
+                watch.pending.extend(result.rows);
+                while watch.pending.len() > MAX_WATCH_PENDING_ROWS {
+                    watch.pending.pop_front();
+                    watch.info.rows_dropped += 1;
+                }
+                watch.info.pending_rows = watch.pending.len();
+
+                if detected > 0 {
+                    debug!("Watch {} detected {} new rows", id, detected);
+                }
+            }
+            Err(e) => {
+                warn!("Watch {} poll failed: {}", id, e);
+                watch.info.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Build the `USE` prefix for a database context.
+pub(super) fn use_prefix(database: Option<&str>) -> String {
+    database
+        .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+        .unwrap_or_default()
+}
+
+/// Build the polling query for a watch.
+fn build_poll_query(info: &WatchInfo, watermark: Option<&str>) -> String {
+    let filter = match watermark {
+        Some(literal) => format!("[{}] > {}", info.watermark_column, literal),
+        None => format!("[{}] IS NOT NULL", info.watermark_column),
+    };
+    format!(
+        "{}SELECT TOP ({}) * FROM [{}].[{}] WHERE {} ORDER BY [{}]",
+        use_prefix(info.database.as_deref()),
+        info.max_rows_per_poll,
+        info.schema,
+        info.table,
+        filter,
+        info.watermark_column
+    )
+}
+
+/// Render a watermark value as a T-SQL literal.
+fn watermark_literal(value: &SqlValue) -> Option<String> {
+    let literal = match value {
+        SqlValue::Null | SqlValue::Time(_) => return None,
+        SqlValue::Bool(v) => (if *v { "1" } else { "0" }).to_string(),
+        SqlValue::I8(v) => v.to_string(),
+        SqlValue::I16(v) => v.to_string(),
+        SqlValue::I32(v) => v.to_string(),
+        SqlValue::I64(v) => v.to_string(),
+        SqlValue::F32(v) => v.to_string(),
+        SqlValue::F64(v) => v.to_string(),
+        SqlValue::Decimal(v) => v.to_string(),
+        SqlValue::Bytes(_) => value.to_display_string(),
+        SqlValue::Udt(v) => format!("0x{}", crate::database::types::hex::encode(v.bytes())),
+        SqlValue::String(v) => format!("N'{}'", v.replace('\'', "''")),
+        SqlValue::Uuid(v) => format!("'{}'", v),
+        SqlValue::Date(v) => format!("CONVERT(date, '{}')", v.format("%Y-%m-%d")),
+        SqlValue::DateTime(v) => format!("CONVERT(datetime2(7), '{}')", format_datetime7(v)),
+        SqlValue::DateTimeUtc(v) => format!(
+            "CONVERT(datetimeoffset(7), '{} +00:00')",
+            format_datetime7(&v.naive_utc())
+        ),
+    };
+    Some(literal)
+}
+
+/// Format a timestamp with the 100ns precision SQL Server accepts.
+fn format_datetime7(value: &NaiveDateTime) -> String {
+    format!(
+        "{}.{:07}",
+        value.format("%Y-%m-%d %H:%M:%S"),
+        value.nanosecond() % 1_000_000_000 / 100
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn info() -> WatchInfo {
+        WatchInfo {
+            id: "w1".to_string(),
+            schema: "sales".to_string(),
+            table: "Orders".to_string(),
+            watermark_column: "RowVer".to_string(),
+            watermark_type: "timestamp".to_string(),
+            database: Some("Shop".to_string()),
+            poll_interval_secs: 30,
+            max_rows_per_poll: 50,
+            last_watermark: None,
+            created_at: Utc::now(),
+            last_polled_at: None,
+            poll_count: 0,
+            rows_detected: 0,
+            rows_dropped: 0,
+            pending_rows: 0,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_build_poll_query() {
+        let query = build_poll_query(&info(), Some("0x00000000000007d1"));
+        assert_eq!(
+            query,
+            "USE [Shop];\nSELECT TOP (50) * FROM [sales].[Orders] \
+             WHERE [RowVer] > 0x00000000000007d1 ORDER BY [RowVer]"
+        );
+
+        let query = build_poll_query(&info(), None);
+        assert!(query.contains("WHERE [RowVer] IS NOT NULL"));
+    }
+
+    #[test]
+    fn test_watermark_literal() {
+        assert_eq!(
+            watermark_literal(&SqlValue::Bytes(vec![0, 0, 0, 0, 0, 0, 7, 209])),
+            Some("0x00000000000007D1".to_string())
+        );
+        assert_eq!(
+            watermark_literal(&SqlValue::I64(42)),
+            Some("42".to_string())
+        );
+        assert_eq!(watermark_literal(&SqlValue::Null), None);
+
+        let ts = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_nano_opt(12, 30, 15, 123_456_700)
+            .unwrap();
+        assert_eq!(
+            watermark_literal(&SqlValue::DateTime(ts)),
+            Some("CONVERT(datetime2(7), '2024-03-01 12:30:15.1234567')".to_string())
+        );
+    }
+}
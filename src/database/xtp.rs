@@ -0,0 +1,265 @@
+//! In-Memory OLTP (memory-optimized tables and natively compiled modules).
+//!
+//! Memory use per memory-optimized table and its indexes comes from
+//! `sys.dm_db_xtp_table_memory_stats`; rows with negative object IDs are the
+//! engine's own internal tables and are reported as one system total.
+//!
+//! Queries touching both memory-optimized and disk-based tables run as
+//! cross-container transactions, which have isolation level rules of their
+//! own; [`cross_container_warnings`] points these out before the query runs.
+
+use crate::database::query::ResultRow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+/// Memory used by one memory-optimized table and its indexes.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableMemoryUsage {
+    pub schema_name: Option<String>,
+    pub table_name: Option<String>,
+    /// `SCHEMA_AND_DATA` or `SCHEMA_ONLY`.
+    pub durability: Option<String>,
+    pub table_allocated_kb: i64,
+    pub table_used_kb: i64,
+    pub indexes_allocated_kb: i64,
+    pub indexes_used_kb: i64,
+}
+
+impl TableMemoryUsage {
+    /// Build from a row of [`TABLE_MEMORY_QUERY`].
+    pub fn from_row(row: &ResultRow) -> Self {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |column: &str| {
+            text(column)
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(0)
+        };
+
+        Self {
+            schema_name: text("schema_name"),
+            table_name: text("table_name"),
+            durability: text("durability"),
+            table_allocated_kb: number("memory_allocated_for_table_kb"),
+            table_used_kb: number("memory_used_by_table_kb"),
+            indexes_allocated_kb: number("memory_allocated_for_indexes_kb"),
+            indexes_used_kb: number("memory_used_by_indexes_kb"),
+        }
+    }
+
+    /// Whether the row is one of the engine's internal tables.
+    pub fn is_system(&self) -> bool {
+        self.table_name.is_none()
+    }
+
+    /// Memory allocated for the table and its indexes.
+    pub fn total_allocated_kb(&self) -> i64 {
+        self.table_allocated_kb + self.indexes_allocated_kb
+    }
+}
+
+/// Memory used by each memory-optimized table of the current database,
+/// largest first.
+pub const TABLE_MEMORY_QUERY: &str = r#"
+    SELECT
+        CASE WHEN ms.object_id > 0 THEN OBJECT_SCHEMA_NAME(ms.object_id) END AS schema_name,
+        CASE WHEN ms.object_id > 0 THEN OBJECT_NAME(ms.object_id) END AS table_name,
+        t.durability_desc AS durability,
+        ms.memory_allocated_for_table_kb,
+        ms.memory_used_by_table_kb,
+        ms.memory_allocated_for_indexes_kb,
+        ms.memory_used_by_indexes_kb
+    FROM sys.dm_db_xtp_table_memory_stats ms
+    LEFT JOIN sys.tables t ON t.object_id = ms.object_id
+    ORDER BY ms.memory_allocated_for_table_kb + ms.memory_allocated_for_indexes_kb DESC
+"#;
+
+/// In-Memory OLTP settings of the current database.
+pub const DATABASE_XTP_QUERY: &str = r#"
+    SELECT
+        (SELECT COUNT(*) FROM sys.tables WHERE is_memory_optimized = 1)
+            AS memory_optimized_tables,
+        (SELECT COUNT(*) FROM sys.sql_modules WHERE uses_native_compilation = 1)
+            AS natively_compiled_modules,
+        CASE WHEN EXISTS (SELECT 1 FROM sys.filegroups WHERE type = 'FX') THEN 1 ELSE 0 END
+            AS has_memory_optimized_filegroup,
+        CAST(d.is_memory_optimized_elevate_to_snapshot_on AS int) AS elevate_to_snapshot,
+        rp.name AS resource_pool,
+        rp.max_memory_percent
+    FROM sys.databases d
+    LEFT JOIN sys.resource_governor_resource_pools rp ON rp.pool_id = d.resource_pool_id
+    WHERE d.database_id = DB_ID()
+"#;
+
+/// Memory-optimized flag and durability of the named tables (lowercase,
+/// unqualified) in the current database.
+pub fn table_kinds_query(tables: &[String]) -> String {
+    let names: Vec<String> = tables
+        .iter()
+        .map(|t| format!("N'{}'", t.replace('\'', "''")))
+        .collect();
+    format!(
+        "SELECT LOWER(t.name) AS table_name, CAST(t.is_memory_optimized AS int) AS is_memory_optimized, \
+         t.durability_desc AS durability FROM sys.tables t WHERE LOWER(t.name) IN ({})",
+        names.join(", ")
+    )
+}
+
+/// A table referenced by a query, as found by [`table_kinds_query`].
+#[derive(Debug, Clone)]
+pub struct ReferencedTable {
+    pub name: String,
+    pub is_memory_optimized: bool,
+    pub durability: Option<String>,
+}
+
+impl ReferencedTable {
+    /// Build from a row of [`table_kinds_query`].
+    pub fn from_row(row: &ResultRow) -> Option<Self> {
+        let text = |column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+
+        Some(Self {
+            name: text("table_name")?,
+            is_memory_optimized: matches!(
+                text("is_memory_optimized").as_deref(),
+                Some("1" | "true")
+            ),
+            durability: text("durability"),
+        })
+    }
+}
+
+/// Locking hints that memory-optimized tables reject.
+static UNSUPPORTED_HINTS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(UPDLOCK|XLOCK|HOLDLOCK|PAGLOCK|TABLOCKX?|READPAST|READCOMMITTEDLOCK)\b")
+        .unwrap()
+});
+
+/// Warnings about how a query touching memory-optimized tables behaves.
+///
+/// `tables` are the tables the query references; nothing is reported unless
+/// at least one of them is memory-optimized.
+pub fn cross_container_warnings(query: &str, tables: &[ReferencedTable]) -> Vec<String> {
+    let (memory, disk): (Vec<&ReferencedTable>, Vec<&ReferencedTable>) =
+        tables.iter().partition(|t| t.is_memory_optimized);
+    if memory.is_empty() {
+        return Vec::new();
+    }
+    let names = |tables: &[&ReferencedTable]| {
+        tables
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut warnings = Vec::new();
+    if !disk.is_empty() {
+        warnings.push(format!(
+            "Cross-container query: memory-optimized tables ({}) are combined with disk-based tables ({}). \
+             In an explicit transaction the memory-optimized tables need a WITH (SNAPSHOT) hint or the \
+             MEMORY_OPTIMIZED_ELEVATE_TO_SNAPSHOT database option, SNAPSHOT isolation cannot be used for \
+             the transaction, and REPEATABLE READ or SERIALIZABLE access may fail validation at commit \
+             (errors 41305/41325) and need a retry",
+            names(&memory),
+            names(&disk)
+        ));
+    }
+    if let Some(hint) = UNSUPPORTED_HINTS.find(query) {
+        warnings.push(format!(
+            "Locking hint {} is not supported on memory-optimized tables ({}); they use optimistic \
+             concurrency without locks",
+            hint.as_str().to_uppercase(),
+            names(&memory)
+        ));
+    }
+    let non_durable: Vec<&ReferencedTable> = memory
+        .iter()
+        .copied()
+        .filter(|t| t.durability.as_deref() == Some("SCHEMA_ONLY"))
+        .collect();
+    if !non_durable.is_empty() {
+        warnings.push(format!(
+            "Non-durable (SCHEMA_ONLY) tables ({}) lose their rows when the server restarts or fails over",
+            names(&non_durable)
+        ));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+
+    fn table(name: &str, memory: bool, durability: Option<&str>) -> ReferencedTable {
+        ReferencedTable {
+            name: name.to_string(),
+            is_memory_optimized: memory,
+            durability: durability.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_disk_only_query_has_no_warnings() {
+        let tables = [table("orders", false, Some("SCHEMA_AND_DATA"))];
+        assert!(
+            cross_container_warnings("SELECT * FROM orders WITH (UPDLOCK)", &tables).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_cross_container_query() {
+        let tables = [
+            table("sessions", true, Some("SCHEMA_AND_DATA")),
+            table("orders", false, Some("SCHEMA_AND_DATA")),
+        ];
+        let warnings = cross_container_warnings(
+            "SELECT * FROM sessions s JOIN orders o ON o.id = s.order_id",
+            &tables,
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("memory-optimized tables (sessions)"));
+        assert!(warnings[0].contains("disk-based tables (orders)"));
+    }
+
+    #[test]
+    fn test_unsupported_hint_and_non_durable_table() {
+        let tables = [table("cart", true, Some("SCHEMA_ONLY"))];
+        let warnings = cross_container_warnings("UPDATE cart WITH (updlock) SET qty = 1", &tables);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("UPDLOCK"));
+        assert!(warnings[1].contains("SCHEMA_ONLY"));
+    }
+
+    #[test]
+    fn test_table_memory_usage_from_row() {
+        let mut row = ResultRow::new();
+        row.insert("schema_name".into(), SqlValue::String("dbo".into()));
+        row.insert("table_name".into(), SqlValue::String("cart".into()));
+        row.insert("memory_allocated_for_table_kb".into(), SqlValue::I64(1_024));
+        row.insert("memory_allocated_for_indexes_kb".into(), SqlValue::I64(256));
+        row.insert("memory_used_by_table_kb".into(), SqlValue::Null);
+        let usage = TableMemoryUsage::from_row(&row);
+        assert!(!usage.is_system());
+        assert_eq!(usage.total_allocated_kb(), 1_280);
+        assert_eq!(usage.table_used_kb, 0);
+
+        let system = TableMemoryUsage::from_row(&ResultRow::new());
+        assert!(system.is_system());
+    }
+
+    #[test]
+    fn test_table_kinds_query_escapes_names() {
+        let query = table_kinds_query(&["orders".to_string(), "o'brien".to_string()]);
+        assert!(query.contains("IN (N'orders', N'o''brien')"));
+    }
+}
@@ -3,6 +3,7 @@
 //! This module defines semantic error types with SQL Server error code mapping
 //! for user-friendly error messages.
 
+use crate::resilience::RetryInfo;
 pub use mcpkit::McpError;
 use thiserror::Error;
 
@@ -65,6 +66,23 @@ pub enum ServerError {
     )]
     CircuitOpen { retry_after_seconds: u64 },
 
+    /// Client exceeded a rate limit or quota
+    #[error("Rate limit exceeded for '{identity}': at most {max} {limit}")]
+    RateLimited {
+        identity: String,
+        limit: String,
+        max: u64,
+        retry_after_seconds: Option<u64>,
+    },
+
+    /// A call waited too long for a concurrency slot
+    #[error("Concurrency limit reached for {scope}: at most {max} concurrent calls, none free after {waited_seconds} seconds")]
+    ConcurrencyLimited {
+        scope: String,
+        max: usize,
+        waited_seconds: u64,
+    },
+
     /// Constraint violation
     #[error("Constraint violation: {0}")]
     ConstraintViolation(String),
@@ -73,6 +91,10 @@ pub enum ServerError {
     #[error("Data truncation: {0}")]
     DataTruncation(String),
 
+    /// Result set exceeded a memory budget
+    #[error("Memory budget exceeded: {0}")]
+    MemoryBudget(String),
+
     /// Session error
     #[error("Session error: {0}")]
     Session(String),
@@ -92,6 +114,14 @@ pub enum ServerError {
     /// Internal error
     #[error("Internal error: {0}")]
     Internal(String),
+
+    /// Operation failed after one or more retries
+    #[error("{source} (after {attempts} attempts, {total_wait_ms} ms waiting)")]
+    RetriesExhausted {
+        attempts: u32,
+        total_wait_ms: u64,
+        source: Box<ServerError>,
+    },
 }
 
 impl ServerError {
@@ -165,6 +195,27 @@ impl ServerError {
         }
     }
 
+    /// Create a query execution error from a driver error.
+    ///
+    /// Keeps the SQL Server error number so deadlocks and throttling can be
+    /// recognized as transient, and maps broken connections and timeouts to
+    /// their own variants.
+    pub fn query_failed(context: &str, error: mssql_client::Error) -> Self {
+        use mssql_client::Error;
+
+        match error {
+            Error::Server { number, .. } => {
+                Self::query_error_with_code(format!("{}: {}", context, error), number, None)
+            }
+            Error::Io(_)
+            | Error::ConnectionClosed
+            | Error::Connection(_)
+            | Error::CommandTimeout
+            | Error::ConnectionTimeout => Self::from(error),
+            _ => Self::query_error(format!("{}: {}", context, error)),
+        }
+    }
+
     /// Create a timeout error.
     pub fn timeout(seconds: u64) -> Self {
         Self::Timeout {
@@ -179,6 +230,35 @@ impl ServerError {
         }
     }
 
+    /// Create a rate limit error.
+    pub fn rate_limited(
+        identity: impl Into<String>,
+        limit: impl Into<String>,
+        max: u64,
+        retry_after_seconds: Option<u64>,
+    ) -> Self {
+        Self::RateLimited {
+            identity: identity.into(),
+            limit: limit.into(),
+            max,
+            retry_after_seconds,
+        }
+    }
+
+    /// Create a concurrency limit error.
+    pub fn concurrency_limited(scope: impl Into<String>, max: usize, waited_seconds: u64) -> Self {
+        Self::ConcurrencyLimited {
+            scope: scope.into(),
+            max,
+            waited_seconds,
+        }
+    }
+
+    /// Create a memory budget exceeded error.
+    pub fn memory_budget(msg: impl Into<String>) -> Self {
+        Self::MemoryBudget(msg.into())
+    }
+
     /// Create a session not found error.
     pub fn session_not_found(id: impl Into<String>) -> Self {
         Self::SessionNotFound(id.into())
@@ -199,6 +279,28 @@ impl ServerError {
         Self::Internal(msg.into())
     }
 
+    /// Attach retry metadata to an error if the operation was retried.
+    ///
+    /// Errors from a single attempt are returned unchanged.
+    pub fn with_retry_info(self, info: &RetryInfo) -> Self {
+        if info.attempts <= 1 {
+            return self;
+        }
+        Self::RetriesExhausted {
+            attempts: info.attempts,
+            total_wait_ms: info.total_wait_ms,
+            source: Box::new(self),
+        }
+    }
+
+    /// Get the underlying error, unwrapping retry metadata.
+    pub fn root(&self) -> &ServerError {
+        match self {
+            Self::RetriesExhausted { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
     /// Check if this error is transient and may succeed on retry.
     pub fn is_transient(&self) -> bool {
         match self {
@@ -213,6 +315,21 @@ impl ServerError {
         }
     }
 
+    /// Check if this error looks like the server failed over or restarted.
+    ///
+    /// Broken connections and the errors SQL Server returns while a database
+    /// moves to another replica mean the pooled connections are stale.
+    pub fn is_failover(&self) -> bool {
+        match self.root() {
+            Self::Connection { .. } => true,
+            Self::QueryExecution {
+                sql_error_code: Some(code),
+                ..
+            } => is_failover_sql_error(*code),
+            _ => false,
+        }
+    }
+
     /// Get a user-friendly suggestion for how to fix this error.
     pub fn suggestion(&self) -> Option<&'static str> {
         match self {
@@ -237,6 +354,14 @@ impl ServerError {
             Self::ConstraintViolation(_) => {
                 Some("Check the constraint definition and your data values")
             }
+            Self::RateLimited { .. } => {
+                Some("Wait before retrying, or reduce the number of calls, sessions, or rows")
+            }
+            Self::ConcurrencyLimited { .. } => Some("Retry once fewer of these calls are running"),
+            Self::MemoryBudget(_) => {
+                Some("Add a TOP or WHERE clause, select fewer columns, or use execute_paginated")
+            }
+            Self::RetriesExhausted { source, .. } => source.suggestion(),
             _ => None,
         }
     }
@@ -316,6 +441,23 @@ fn is_transient_sql_error(code: i32) -> bool {
     )
 }
 
+/// Check if a SQL Server error code is a failover or restart signature.
+fn is_failover_sql_error(code: i32) -> bool {
+    matches!(
+        code,
+        -1      // Connection broken
+        | 233   // No process is on the other end of the pipe
+        | 976   // AG database not accessible on this replica
+        | 978   // AG database only readable with read-only intent
+        | 983   // AG database not accessible (replica not primary)
+        | 3906  // Database is read-only (now a secondary)
+        | 4060  // Cannot open database (not yet online after failover)
+        | 10053 // Connection forcibly closed
+        | 10054 // Connection reset
+        | 40613 // Azure: database unavailable
+    )
+}
+
 /// Convert ServerError to mcpkit's McpError for protocol responses.
 ///
 /// Note: Tool errors should generally return `ToolOutput::error()` with a message
@@ -350,9 +492,12 @@ impl From<ServerError> for McpError {
                 "Service unavailable, circuit breaker open. Retry after {} seconds",
                 retry_after_seconds
             )),
-            ServerError::ConstraintViolation(msg) | ServerError::DataTruncation(msg) => {
-                McpError::internal(msg)
-            }
+            ServerError::ConstraintViolation(msg)
+            | ServerError::DataTruncation(msg)
+            | ServerError::MemoryBudget(msg) => McpError::internal(msg),
+            e @ ServerError::RateLimited { .. } => McpError::internal(e.to_string()),
+            e @ ServerError::ConcurrencyLimited { .. } => McpError::internal(e.to_string()),
+            e @ ServerError::RetriesExhausted { .. } => McpError::internal(e.to_string()),
         }
     }
 }
@@ -432,6 +577,64 @@ mod tests {
 
         let err = ServerError::auth("test");
         assert!(!err.is_transient());
+
+        let deadlock = mssql_client::Error::Server {
+            number: 1205,
+            class: 13,
+            state: 51,
+            message: "Transaction was deadlocked".to_string(),
+            server: None,
+            procedure: None,
+            line: 1,
+        };
+        let err = ServerError::query_failed("Query execution failed", deadlock);
+        assert!(err.is_transient());
+        assert!(err.to_string().contains("server error 1205"));
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let err = ServerError::query_failed("Query execution failed", reset.into());
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn test_failover_errors() {
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(ServerError::query_failed("Query execution failed", reset.into()).is_failover());
+        assert!(
+            ServerError::query_error_with_code("Cannot open database", 4060, None).is_failover()
+        );
+        assert!(ServerError::query_error_with_code("Pipe closed", 233, None).is_failover());
+
+        // Deadlocks are transient, but the connection is fine
+        assert!(!ServerError::query_error_with_code("Deadlock", 1205, None).is_failover());
+        assert!(!ServerError::timeout(30).is_failover());
+    }
+
+    #[test]
+    fn test_retry_info_wrapping() {
+        use crate::resilience::RetryOutcome;
+
+        let info = RetryInfo {
+            attempts: 1,
+            total_wait_ms: 0,
+            outcome: RetryOutcome::Failed,
+        };
+        let err = ServerError::timeout(30).with_retry_info(&info);
+        assert!(matches!(err, ServerError::Timeout { .. }));
+
+        let info = RetryInfo {
+            attempts: 3,
+            total_wait_ms: 300,
+            outcome: RetryOutcome::Failed,
+        };
+        let err = ServerError::timeout(30).with_retry_info(&info);
+        assert!(!err.is_transient());
+        assert!(matches!(err.root(), ServerError::Timeout { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Query timeout: operation exceeded 30 seconds (after 3 attempts, 300 ms waiting)"
+        );
+        assert!(err.suggestion().is_some());
     }
 
     #[test]
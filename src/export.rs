@@ -0,0 +1,785 @@
+//! Streaming export of query results to files.
+//!
+//! `export_data` can write results to a `destination_path` instead of returning
+//! them inline. Destinations must resolve to a location inside one of the
+//! directories configured with `MSSQL_EXPORT_DIRS`. Rows are written as they
+//! are read from the server, optionally gzip-compressed, and split into
+//! numbered chunk files when a per-file row or byte limit is set. Excel
+//! exports are built in memory as a workbook with one worksheet per result set.
+
+use crate::database::{csv_field, QueryColumnInfo, QueryResult, ResultRow, SqlValue};
+use crate::error::ServerError;
+use crate::tools::ExportFormat;
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook, Worksheet, XlsxError};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Resolve an export destination against the allowed export directories.
+///
+/// Relative paths are resolved against the first allowed directory. The
+/// destination's parent directory must exist and, after resolving symlinks,
+/// lie inside an allowed directory.
+pub fn resolve_destination(path: &str, allowed_dirs: &[PathBuf]) -> Result<PathBuf, ServerError> {
+    let Some(default_dir) = allowed_dirs.first() else {
+        return Err(ServerError::invalid_input(
+            "File export is disabled. Set MSSQL_EXPORT_DIRS to allow export destinations.",
+        ));
+    };
+
+    let requested = Path::new(path);
+    if requested
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(ServerError::invalid_input(
+            "Export destination must not contain '..'",
+        ));
+    }
+    let requested = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        default_dir.join(requested)
+    };
+
+    let file_name = requested
+        .file_name()
+        .ok_or_else(|| ServerError::invalid_input("Export destination must name a file"))?;
+    let parent = requested.parent().unwrap_or(Path::new("/"));
+    let parent = parent.canonicalize().map_err(|e| {
+        ServerError::invalid_input(format!(
+            "Export directory '{}' is not accessible: {}",
+            parent.display(),
+            e
+        ))
+    })?;
+
+    if !is_within_allowed_dirs(&parent, allowed_dirs) {
+        return Err(ServerError::invalid_input(format!(
+            "Export destination '{}' is outside the allowed export directories",
+            requested.display()
+        )));
+    }
+
+    Ok(parent.join(file_name))
+}
+
+/// Check whether a canonical path lies inside one of the allowed directories.
+pub(crate) fn is_within_allowed_dirs(path: &Path, allowed_dirs: &[PathBuf]) -> bool {
+    allowed_dirs
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| path.starts_with(&dir))
+}
+
+/// Options for writing an export to files.
+#[derive(Debug, Clone)]
+pub struct FileExportOptions {
+    /// Output format.
+    pub format: ExportFormat,
+
+    /// Write a header row to each CSV file.
+    pub include_headers: bool,
+
+    /// Gzip-compress each file.
+    pub compress: bool,
+
+    /// Start a new file after this many rows.
+    pub max_rows_per_file: Option<usize>,
+
+    /// Start a new file before exceeding this many (uncompressed) bytes.
+    pub max_bytes_per_file: Option<u64>,
+
+    /// Replace existing files instead of failing.
+    pub overwrite: bool,
+}
+
+impl FileExportOptions {
+    /// Check whether output is split into numbered chunk files.
+    fn is_chunked(&self) -> bool {
+        self.max_rows_per_file.is_some() || self.max_bytes_per_file.is_some()
+    }
+}
+
+/// A file written by an export.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedFile {
+    /// Path of the file.
+    pub path: String,
+
+    /// Number of rows in the file.
+    pub rows: usize,
+
+    /// Uncompressed size in bytes.
+    pub bytes: u64,
+
+    /// Size on disk in bytes (differs from `bytes` when compressed).
+    pub size_on_disk: u64,
+}
+
+/// Output sink for a single chunk file.
+enum ChunkSink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl ChunkSink {
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            ChunkSink::Plain(w) => w.write_all(data),
+            ChunkSink::Gzip(w) => w.write_all(data),
+        }
+    }
+
+    /// Flush buffered data and write the gzip trailer.
+    fn close(self) -> io::Result<()> {
+        match self {
+            ChunkSink::Plain(mut w) => w.flush(),
+            ChunkSink::Gzip(w) => w.finish()?.flush(),
+        }
+    }
+}
+
+/// The chunk file currently being written.
+struct OpenChunk {
+    sink: ChunkSink,
+    path: PathBuf,
+    rows: usize,
+    bytes: u64,
+}
+
+/// Writes streamed rows to one or more export files.
+pub struct ChunkedFileWriter {
+    destination: PathBuf,
+    options: FileExportOptions,
+    current: Option<OpenChunk>,
+    files: Vec<ExportedFile>,
+}
+
+impl ChunkedFileWriter {
+    /// Create a writer for a resolved destination path.
+    pub fn new(destination: PathBuf, options: FileExportOptions) -> Self {
+        Self {
+            destination,
+            options,
+            current: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Write a row, starting a new chunk file if a limit would be exceeded.
+    pub fn write_row(
+        &mut self,
+        columns: &[QueryColumnInfo],
+        row: &ResultRow,
+    ) -> Result<(), ServerError> {
+        let line = self.format_row(columns, row)?;
+
+        let needs_rotation = match &self.current {
+            None => true,
+            Some(chunk) => {
+                let rows_full = self
+                    .options
+                    .max_rows_per_file
+                    .is_some_and(|max| chunk.rows >= max);
+                let bytes_full = self
+                    .options
+                    .max_bytes_per_file
+                    .is_some_and(|max| chunk.bytes + line.len() as u64 > max);
+                chunk.rows > 0 && (rows_full || bytes_full)
+            }
+        };
+        if needs_rotation {
+            self.close_current()?;
+            self.open_chunk(columns)?;
+        }
+
+        let chunk = self
+            .current
+            .as_mut()
+            .ok_or_else(|| ServerError::internal("Export chunk not open"))?;
+        let separator: &[u8] = match self.options.format {
+            ExportFormat::Json if chunk.rows > 0 => b",\n",
+            ExportFormat::Json => b"\n",
+            _ => b"",
+        };
+        write_chunk(chunk, separator)?;
+        write_chunk(chunk, line.as_bytes())?;
+        chunk.rows += 1;
+        Ok(())
+    }
+
+    /// Close the last file and return all files written.
+    ///
+    /// An empty result still produces a single (empty) file.
+    pub fn finish(mut self, columns: &[QueryColumnInfo]) -> Result<Vec<ExportedFile>, ServerError> {
+        if self.current.is_none() && self.files.is_empty() {
+            self.open_chunk(columns)?;
+        }
+        self.close_current()?;
+        Ok(self.files)
+    }
+
+    /// Remove all files written so far (used when an export fails).
+    pub fn abort(mut self) {
+        if let Some(chunk) = self.current.take() {
+            drop(chunk.sink);
+            let _ = fs::remove_file(&chunk.path);
+        }
+        for file in &self.files {
+            let _ = fs::remove_file(&file.path);
+        }
+    }
+
+    /// Format a row (without separators) in the configured format.
+    fn format_row(
+        &self,
+        columns: &[QueryColumnInfo],
+        row: &ResultRow,
+    ) -> Result<String, ServerError> {
+        match self.options.format {
+            ExportFormat::Csv => {
+                let values: Vec<String> = columns
+                    .iter()
+                    .map(|col| {
+                        csv_field(
+                            row.get(&col.name)
+                                .map(|v| v.to_display_string())
+                                .unwrap_or_default(),
+                        )
+                    })
+                    .collect();
+                Ok(format!("{}\n", values.join(",")))
+            }
+            ExportFormat::Json => serde_json::to_string(&row.columns)
+                .map_err(|e| ServerError::internal(format!("Failed to serialize row: {}", e))),
+            ExportFormat::JsonLines => serde_json::to_string(&row.columns)
+                .map(|json| format!("{}\n", json))
+                .map_err(|e| ServerError::internal(format!("Failed to serialize row: {}", e))),
+            ExportFormat::Xlsx => Err(ServerError::invalid_input(
+                "xlsx exports are written with write_xlsx, not streamed",
+            )),
+        }
+    }
+
+    /// Open the next chunk file and write its header.
+    fn open_chunk(&mut self, columns: &[QueryColumnInfo]) -> Result<(), ServerError> {
+        let path = chunk_path(
+            &self.destination,
+            self.files.len() + 1,
+            self.options.is_chunked(),
+            self.options.compress,
+        );
+
+        let mut open_options = OpenOptions::new();
+        open_options.write(true);
+        if self.options.overwrite {
+            open_options.create(true).truncate(true);
+        } else {
+            open_options.create_new(true);
+        }
+        let file = open_options.open(&path).map_err(|e| {
+            ServerError::internal(format!(
+                "Failed to create export file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let writer = BufWriter::new(file);
+        let sink = if self.options.compress {
+            ChunkSink::Gzip(GzEncoder::new(writer, Compression::default()))
+        } else {
+            ChunkSink::Plain(writer)
+        };
+        let mut chunk = OpenChunk {
+            sink,
+            path,
+            rows: 0,
+            bytes: 0,
+        };
+
+        match self.options.format {
+            ExportFormat::Csv if self.options.include_headers && !columns.is_empty() => {
+                let headers: Vec<String> =
+                    columns.iter().map(|c| csv_field(c.name.clone())).collect();
+                write_chunk(&mut chunk, format!("{}\n", headers.join(",")).as_bytes())?;
+            }
+            ExportFormat::Json => write_chunk(&mut chunk, b"[")?,
+            _ => {}
+        }
+
+        self.current = Some(chunk);
+        Ok(())
+    }
+
+    /// Close the current chunk file, if any, and record it.
+    fn close_current(&mut self) -> Result<(), ServerError> {
+        let Some(mut chunk) = self.current.take() else {
+            return Ok(());
+        };
+        if self.options.format == ExportFormat::Json {
+            write_chunk(&mut chunk, b"\n]\n")?;
+        }
+
+        let path_display = chunk.path.display().to_string();
+        chunk.sink.close().map_err(|e| {
+            ServerError::internal(format!(
+                "Failed to write export file '{}': {}",
+                path_display, e
+            ))
+        })?;
+        let size_on_disk = fs::metadata(&chunk.path).map(|m| m.len()).unwrap_or(0);
+
+        self.files.push(ExportedFile {
+            path: path_display,
+            rows: chunk.rows,
+            bytes: chunk.bytes,
+            size_on_disk,
+        });
+        Ok(())
+    }
+}
+
+/// Write data to a chunk and track its uncompressed size.
+fn write_chunk(chunk: &mut OpenChunk, data: &[u8]) -> Result<(), ServerError> {
+    chunk.sink.write_all(data).map_err(|e| {
+        ServerError::internal(format!(
+            "Failed to write export file '{}': {}",
+            chunk.path.display(),
+            e
+        ))
+    })?;
+    chunk.bytes += data.len() as u64;
+    Ok(())
+}
+
+/// Build the path of a chunk file.
+///
+/// Chunked exports insert a four-digit sequence number before the extension
+/// (`orders.csv` becomes `orders.0001.csv`). Compressed files get a `.gz`
+/// suffix unless the destination already has one.
+fn chunk_path(destination: &Path, index: usize, chunked: bool, compress: bool) -> PathBuf {
+    let file_name = destination
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let base = file_name.strip_suffix(".gz").unwrap_or(&file_name);
+
+    let mut name = if chunked {
+        match base.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => format!("{}.{:04}.{}", stem, index, ext),
+            _ => format!("{}.{:04}", base, index),
+        }
+    } else {
+        base.to_string()
+    };
+    if compress {
+        name.push_str(".gz");
+    }
+    destination.with_file_name(name)
+}
+
+// =============================================================================
+// Excel Workbooks
+// =============================================================================
+
+/// Maximum data rows on a worksheet (Excel's row limit, less the header row).
+pub const XLSX_MAX_ROWS_PER_SHEET: usize = 1_048_575;
+
+/// Maximum columns on a worksheet.
+const XLSX_MAX_COLUMNS: usize = 16_384;
+
+/// Maximum characters in a cell; longer strings are truncated.
+const XLSX_MAX_STRING_CHARS: usize = 32_767;
+
+/// Largest integer magnitude Excel stores exactly (2^53).
+const XLSX_MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+/// A worksheet written to an Excel export.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedSheet {
+    /// Worksheet name.
+    pub name: String,
+
+    /// Number of data rows.
+    pub rows: usize,
+
+    /// Number of columns.
+    pub columns: usize,
+
+    /// Whether rows were dropped to fit the worksheet row limit.
+    pub truncated: bool,
+}
+
+/// Cell formats shared by all worksheets of a workbook.
+struct XlsxFormats {
+    header: Format,
+    date: Format,
+    datetime: Format,
+    time: Format,
+}
+
+impl XlsxFormats {
+    fn new() -> Self {
+        Self {
+            header: Format::new()
+                .set_bold()
+                .set_font_color(Color::White)
+                .set_background_color(Color::RGB(0x1F4E78))
+                .set_border_bottom(FormatBorder::Thin),
+            date: Format::new().set_num_format("yyyy-mm-dd"),
+            datetime: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+            time: Format::new().set_num_format("hh:mm:ss"),
+        }
+    }
+}
+
+/// Write result sets to an Excel workbook, one worksheet per result set.
+///
+/// Result sets without columns (row counts from DML statements) are skipped.
+/// Values keep their types: numbers, booleans and dates become native Excel
+/// cells, while integers and decimals that Excel cannot represent exactly are
+/// written as text.
+pub fn write_xlsx(
+    path: &Path,
+    result_sets: &[QueryResult],
+    include_headers: bool,
+    overwrite: bool,
+) -> Result<(ExportedFile, Vec<ExportedSheet>), ServerError> {
+    let (mut workbook, sheets) = build_workbook(result_sets, include_headers)
+        .map_err(|e| ServerError::internal(format!("Failed to build workbook: {}", e)))?;
+    let buffer = workbook
+        .save_to_buffer()
+        .map_err(|e| ServerError::internal(format!("Failed to build workbook: {}", e)))?;
+
+    let mut open_options = OpenOptions::new();
+    open_options.write(true);
+    if overwrite {
+        open_options.create(true).truncate(true);
+    } else {
+        open_options.create_new(true);
+    }
+    open_options
+        .open(path)
+        .and_then(|mut file| file.write_all(&buffer))
+        .map_err(|e| {
+            ServerError::internal(format!(
+                "Failed to write export file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+    let file = ExportedFile {
+        path: path.display().to_string(),
+        rows: sheets.iter().map(|s| s.rows).sum(),
+        bytes: buffer.len() as u64,
+        size_on_disk: buffer.len() as u64,
+    };
+    Ok((file, sheets))
+}
+
+/// Build a workbook with one styled worksheet per result set.
+fn build_workbook(
+    result_sets: &[QueryResult],
+    include_headers: bool,
+) -> Result<(Workbook, Vec<ExportedSheet>), XlsxError> {
+    let formats = XlsxFormats::new();
+    let mut workbook = Workbook::new();
+    let mut sheets = Vec::new();
+
+    let sets: Vec<&QueryResult> = result_sets
+        .iter()
+        .filter(|r| !r.columns.is_empty())
+        .collect();
+    if sets.is_empty() {
+        workbook.add_worksheet().set_name("Results")?;
+    }
+
+    for (index, result) in sets.iter().enumerate() {
+        let name = if sets.len() == 1 {
+            "Results".to_string()
+        } else {
+            format!("Result {}", index + 1)
+        };
+        let columns = &result.columns[..result.columns.len().min(XLSX_MAX_COLUMNS)];
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(&name)?;
+
+        let mut row_num: u32 = 0;
+        if include_headers {
+            for (col, column) in columns.iter().enumerate() {
+                sheet.write_string_with_format(0, col as u16, &column.name, &formats.header)?;
+            }
+            sheet.set_freeze_panes(1, 0)?;
+            row_num = 1;
+        }
+
+        let rows = result.rows.len().min(XLSX_MAX_ROWS_PER_SHEET);
+        for row in &result.rows[..rows] {
+            for (col, column) in columns.iter().enumerate() {
+                if let Some(value) = row.get(&column.name) {
+                    write_cell(sheet, row_num, col as u16, value, &formats)?;
+                }
+            }
+            row_num += 1;
+        }
+
+        if include_headers && rows > 0 {
+            sheet.autofilter(0, 0, row_num - 1, (columns.len() - 1) as u16)?;
+        }
+        sheet.autofit();
+
+        sheets.push(ExportedSheet {
+            name,
+            rows,
+            columns: columns.len(),
+            truncated: result.truncated || rows < result.rows.len(),
+        });
+    }
+
+    Ok((workbook, sheets))
+}
+
+/// Write a value to a cell using the closest native Excel type.
+fn write_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &SqlValue,
+    formats: &XlsxFormats,
+) -> Result<(), XlsxError> {
+    // Excel dates start at 1900-01-01; earlier values are written as text
+    let excel_epoch = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap_or_default();
+
+    match value {
+        SqlValue::Null => {}
+        SqlValue::Bool(v) => {
+            sheet.write_boolean(row, col, *v)?;
+        }
+        SqlValue::I8(v) => {
+            sheet.write_number(row, col, *v as f64)?;
+        }
+        SqlValue::I16(v) => {
+            sheet.write_number(row, col, *v as f64)?;
+        }
+        SqlValue::I32(v) => {
+            sheet.write_number(row, col, *v as f64)?;
+        }
+        SqlValue::I64(v) if v.unsigned_abs() <= XLSX_MAX_EXACT_INTEGER => {
+            sheet.write_number(row, col, *v as f64)?;
+        }
+        SqlValue::F32(v) if v.is_finite() => {
+            sheet.write_number(row, col, *v as f64)?;
+        }
+        SqlValue::F64(v) if v.is_finite() => {
+            sheet.write_number(row, col, *v)?;
+        }
+        SqlValue::Decimal(d) if d.mantissa().unsigned_abs() < 10u128.pow(15) => {
+            match d.to_f64() {
+                Some(v) => sheet.write_number(row, col, v)?,
+                None => sheet.write_string(row, col, d.to_string())?,
+            };
+        }
+        SqlValue::Date(d) if *d >= excel_epoch => {
+            sheet.write_datetime_with_format(row, col, d, &formats.date)?;
+        }
+        SqlValue::Time(t) => {
+            sheet.write_datetime_with_format(row, col, t, &formats.time)?;
+        }
+        SqlValue::DateTime(dt) if dt.date() >= excel_epoch => {
+            sheet.write_datetime_with_format(row, col, dt, &formats.datetime)?;
+        }
+        SqlValue::DateTimeUtc(dt) if dt.date_naive() >= excel_epoch => {
+            let naive: NaiveDateTime = dt.naive_utc();
+            sheet.write_datetime_with_format(row, col, naive, &formats.datetime)?;
+        }
+        other => {
+            let text = other.to_display_string();
+            let text = match text.char_indices().nth(XLSX_MAX_STRING_CHARS) {
+                Some((end, _)) => &text[..end],
+                None => &text,
+            };
+            sheet.write_string(row, col, text)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::SqlValue;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mssql-mcp-export-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn columns() -> Vec<QueryColumnInfo> {
+        vec![
+            QueryColumnInfo {
+                name: "id".to_string(),
+                sql_type: "int".to_string(),
+                nullable: false,
+            },
+            QueryColumnInfo {
+                name: "name".to_string(),
+                sql_type: "nvarchar".to_string(),
+                nullable: true,
+            },
+        ]
+    }
+
+    fn row(id: i64, name: &str) -> ResultRow {
+        let mut row = ResultRow::new();
+        row.insert("id".to_string(), SqlValue::I64(id));
+        row.insert("name".to_string(), SqlValue::String(name.to_string()));
+        row
+    }
+
+    fn options(format: ExportFormat) -> FileExportOptions {
+        FileExportOptions {
+            format,
+            include_headers: true,
+            compress: false,
+            max_rows_per_file: None,
+            max_bytes_per_file: None,
+            overwrite: false,
+        }
+    }
+
+    #[test]
+    fn test_chunk_path() {
+        let dest = Path::new("/exports/orders.csv");
+        assert_eq!(chunk_path(dest, 1, false, false), dest);
+        assert_eq!(
+            chunk_path(dest, 2, true, false),
+            Path::new("/exports/orders.0002.csv")
+        );
+        assert_eq!(
+            chunk_path(Path::new("/exports/orders.csv.gz"), 3, true, true),
+            Path::new("/exports/orders.0003.csv.gz")
+        );
+    }
+
+    #[test]
+    fn test_resolve_destination() {
+        let dir = temp_dir();
+        let allowed = vec![dir.clone()];
+
+        let resolved = resolve_destination("out.csv", &allowed).unwrap();
+        assert_eq!(resolved, dir.canonicalize().unwrap().join("out.csv"));
+
+        assert!(resolve_destination("../out.csv", &allowed).is_err());
+        assert!(resolve_destination("/etc/out.csv", &allowed).is_err());
+        assert!(resolve_destination("out.csv", &[]).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunked_csv_export() {
+        let dir = temp_dir();
+        let mut opts = options(ExportFormat::Csv);
+        opts.max_rows_per_file = Some(2);
+        let mut writer = ChunkedFileWriter::new(dir.join("people.csv"), opts);
+
+        let cols = columns();
+        for i in 0..5 {
+            writer.write_row(&cols, &row(i, "a,b")).unwrap();
+        }
+        let files = writer.finish(&cols).unwrap();
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            files.iter().map(|f| f.rows).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        let first = fs::read_to_string(dir.join("people.0001.csv")).unwrap();
+        assert_eq!(first, "id,name\n0,\"a,b\"\n1,\"a,b\"\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_gzip_json_export() {
+        let dir = temp_dir();
+        let mut opts = options(ExportFormat::Json);
+        opts.compress = true;
+        let mut writer = ChunkedFileWriter::new(dir.join("people.json"), opts);
+
+        let cols = columns();
+        writer.write_row(&cols, &row(1, "x")).unwrap();
+        writer.write_row(&cols, &row(2, "y")).unwrap();
+        let files = writer.finish(&cols).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].path.ends_with("people.json.gz"));
+
+        let mut json = String::new();
+        GzDecoder::new(File::open(&files[0].path).unwrap())
+            .read_to_string(&mut json)
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(files[0].bytes, json.len() as u64);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_existing_file_not_overwritten() {
+        let dir = temp_dir();
+        fs::write(dir.join("out.csv"), "keep").unwrap();
+
+        let writer = ChunkedFileWriter::new(dir.join("out.csv"), options(ExportFormat::Csv));
+        assert!(writer.finish(&columns()).is_err());
+        assert_eq!(fs::read_to_string(dir.join("out.csv")).unwrap(), "keep");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_xlsx_workbook() {
+        let dir = temp_dir();
+        let result = |rows: Vec<ResultRow>| QueryResult {
+            columns: columns(),
+            rows,
+            rows_affected: 0,
+            execution_time_ms: 0,
+            truncated: false,
+            retry: None,
+            messages: Vec::new(),
+        };
+        let mut dml = result(Vec::new());
+        dml.columns.clear();
+
+        let mut typed = row(2, "y");
+        typed.insert("id".to_string(), SqlValue::I64(i64::MAX));
+        let sets = vec![result(vec![row(1, "x")]), dml, result(vec![typed])];
+
+        let path = dir.join("report.xlsx");
+        let (file, sheets) = write_xlsx(&path, &sets, true, false).unwrap();
+        assert_eq!(file.rows, 2);
+        assert_eq!(
+            sheets.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["Result 1", "Result 2"]
+        );
+        assert_eq!(&fs::read(&path).unwrap()[..2], b"PK");
+
+        // Existing workbooks are only replaced with overwrite
+        assert!(write_xlsx(&path, &sets, true, false).is_err());
+        assert!(write_xlsx(&path, &sets[..1], true, true).is_ok());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
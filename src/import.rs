@@ -0,0 +1,855 @@
+//! Import of CSV and JSON Lines files into tables.
+//!
+//! `import_data` reads a file from one of the directories configured with
+//! `MSSQL_IMPORT_DIRS`, maps its columns onto the target table, and validates
+//! every value against the target column types before anything is written.
+//! Rows that fail validation are reported with their line number. The load
+//! itself reads the file a second time and inserts the valid rows in batches,
+//! so files larger than memory can be imported.
+
+use crate::database::ResultRow;
+use crate::error::ServerError;
+use crate::export::is_within_allowed_dirs;
+use crate::security::safe_identifier;
+use crate::tools::ImportFormat;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Resolve an import source against the allowed import directories.
+///
+/// Relative paths are resolved against the first allowed directory. The file
+/// must exist and, after resolving symlinks, lie inside an allowed directory.
+pub fn resolve_source(path: &str, allowed_dirs: &[PathBuf]) -> Result<PathBuf, ServerError> {
+    let Some(default_dir) = allowed_dirs.first() else {
+        return Err(ServerError::invalid_input(
+            "File import is disabled. Set MSSQL_IMPORT_DIRS to allow import sources.",
+        ));
+    };
+
+    let requested = Path::new(path);
+    let requested = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        default_dir.join(requested)
+    };
+    let source = requested.canonicalize().map_err(|e| {
+        ServerError::invalid_input(format!(
+            "Import file '{}' is not accessible: {}",
+            requested.display(),
+            e
+        ))
+    })?;
+
+    if !source.is_file() {
+        return Err(ServerError::invalid_input(format!(
+            "Import source '{}' is not a file",
+            source.display()
+        )));
+    }
+    if !is_within_allowed_dirs(&source, allowed_dirs) {
+        return Err(ServerError::invalid_input(format!(
+            "Import source '{}' is outside the allowed import directories",
+            requested.display()
+        )));
+    }
+
+    Ok(source)
+}
+
+/// Determine the file format, inferring it from the extension if needed.
+pub fn detect_format(path: &Path, format: ImportFormat) -> Result<ImportFormat, ServerError> {
+    if format != ImportFormat::Auto {
+        return Ok(format);
+    }
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "csv" | "tsv" | "txt" => Ok(ImportFormat::Csv),
+        "jsonl" | "ndjson" | "json" => Ok(ImportFormat::JsonLines),
+        _ => Err(ServerError::invalid_input(format!(
+            "Cannot infer the format of '{}'; set format to 'csv' or 'json_lines'",
+            path.display()
+        ))),
+    }
+}
+
+// =============================================================================
+// Source Reading
+// =============================================================================
+
+/// A record read from the source file.
+#[derive(Debug, Clone)]
+pub struct SourceRecord {
+    /// Line number where the record starts (1-based).
+    pub line: usize,
+
+    /// Field values aligned with the source headers (`None` is an explicit null),
+    /// or the reason the record could not be parsed.
+    pub fields: Result<Vec<Option<String>>, String>,
+}
+
+/// A raw CSV record: its starting line and its fields or parse error.
+type CsvRecord = (usize, Result<Vec<String>, String>);
+
+/// Reader over the records of a CSV or JSON Lines file.
+pub struct SourceReader {
+    reader: BufReader<File>,
+    format: ImportFormat,
+    delimiter: char,
+    headers: Vec<String>,
+    line: usize,
+    pending: Option<SourceRecord>,
+}
+
+impl SourceReader {
+    /// Open a source file and read its headers.
+    ///
+    /// CSV files without a header row get positional headers (`1`, `2`, ...).
+    /// JSON Lines headers are the keys of the first object.
+    pub fn open(
+        path: &Path,
+        format: ImportFormat,
+        has_header: bool,
+        delimiter: char,
+    ) -> Result<Self, ServerError> {
+        let file = File::open(path).map_err(|e| {
+            ServerError::invalid_input(format!("Failed to open '{}': {}", path.display(), e))
+        })?;
+        let mut reader = Self {
+            reader: BufReader::new(file),
+            format,
+            delimiter,
+            headers: Vec::new(),
+            line: 0,
+            pending: None,
+        };
+
+        match format {
+            ImportFormat::JsonLines => {
+                let Some((line, text)) = reader.read_json_line()? else {
+                    return Ok(reader);
+                };
+                match serde_json::from_str::<serde_json::Value>(&text) {
+                    Ok(serde_json::Value::Object(obj)) => {
+                        reader.headers = obj.keys().cloned().collect();
+                    }
+                    _ => {
+                        return Err(ServerError::invalid_input(format!(
+                            "Line {}: expected a JSON object",
+                            line
+                        )))
+                    }
+                }
+                reader.pending = Some(reader.parse_json_record(line, &text));
+            }
+            _ => {
+                let Some((line, fields)) = reader.read_csv_record()? else {
+                    return Ok(reader);
+                };
+                let fields = fields
+                    .map_err(|e| ServerError::invalid_input(format!("Line {}: {}", line, e)))?;
+                if has_header {
+                    reader.headers = fields
+                        .into_iter()
+                        .map(|h| h.trim().trim_start_matches('\u{feff}').to_string())
+                        .collect();
+                } else {
+                    reader.headers = (1..=fields.len()).map(|i| i.to_string()).collect();
+                    reader.pending = Some(SourceRecord {
+                        line,
+                        fields: Ok(fields.into_iter().map(Some).collect()),
+                    });
+                }
+            }
+        }
+
+        Ok(reader)
+    }
+
+    /// Get the source column names.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Read the next record, or `None` at the end of the file.
+    pub fn next_record(&mut self) -> Result<Option<SourceRecord>, ServerError> {
+        if let Some(record) = self.pending.take() {
+            return Ok(Some(record));
+        }
+
+        match self.format {
+            ImportFormat::JsonLines => Ok(self
+                .read_json_line()?
+                .map(|(line, text)| self.parse_json_record(line, &text))),
+            _ => {
+                let Some((line, fields)) = self.read_csv_record()? else {
+                    return Ok(None);
+                };
+                let expected = self.headers.len();
+                let fields = fields.and_then(|f| {
+                    if f.len() == expected {
+                        Ok(f.into_iter().map(Some).collect())
+                    } else {
+                        Err(format!("expected {} fields, found {}", expected, f.len()))
+                    }
+                });
+                Ok(Some(SourceRecord { line, fields }))
+            }
+        }
+    }
+
+    /// Read a physical line, returning `None` at the end of the file.
+    fn read_line(&mut self) -> Result<Option<String>, ServerError> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|e| ServerError::internal(format!("Failed to read import file: {}", e)))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some(line))
+    }
+
+    /// Read the next non-empty JSON line with its line number.
+    fn read_json_line(&mut self) -> Result<Option<(usize, String)>, ServerError> {
+        while let Some(text) = self.read_line()? {
+            let text = text.trim();
+            if !text.is_empty() {
+                return Ok(Some((self.line, text.to_string())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Convert a JSON line into a record aligned with the headers.
+    fn parse_json_record(&self, line: usize, text: &str) -> SourceRecord {
+        let fields = match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(serde_json::Value::Object(obj)) => Ok(self
+                .headers
+                .iter()
+                .map(|h| match obj.get(h) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    Some(other) => Some(other.to_string()),
+                })
+                .collect()),
+            Ok(_) => Err("expected a JSON object".to_string()),
+            Err(e) => Err(format!("invalid JSON: {}", e)),
+        };
+        SourceRecord { line, fields }
+    }
+
+    /// Read the next CSV record, which may span several lines when quoted
+    /// fields contain line breaks. Blank lines are skipped.
+    fn read_csv_record(&mut self) -> Result<Option<CsvRecord>, ServerError> {
+        loop {
+            let Some(first) = self.read_line()? else {
+                return Ok(None);
+            };
+            let start_line = self.line;
+            let mut record = first;
+            while record.matches('"').count() % 2 == 1 {
+                match self.read_line()? {
+                    Some(next) => record.push_str(&next),
+                    None => break,
+                }
+            }
+
+            let record = record.trim_end_matches(['\r', '\n']);
+            if record.is_empty() {
+                continue;
+            }
+            return Ok(Some((start_line, split_csv_record(record, self.delimiter))));
+        }
+    }
+}
+
+/// Split a CSV record into fields, handling quoted fields and `""` escapes.
+fn split_csv_record(record: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = record.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else if c == '"' && field.is_empty() {
+            in_quotes = true;
+        } else {
+            field.push(c);
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+// =============================================================================
+// Target Columns and Mapping
+// =============================================================================
+
+/// A column of the import target table.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetColumn {
+    /// Column name.
+    pub name: String,
+
+    /// SQL data type (lowercase).
+    pub data_type: String,
+
+    /// Maximum length in characters (bytes for binary types); -1 for MAX.
+    pub max_length: Option<i64>,
+
+    /// Numeric precision.
+    pub precision: Option<u32>,
+
+    /// Numeric scale.
+    pub scale: Option<u32>,
+
+    /// Whether the column accepts NULL.
+    pub nullable: bool,
+
+    /// Whether the column has a default constraint.
+    pub has_default: bool,
+
+    /// Whether the column is an identity, computed or rowversion column.
+    pub generated: bool,
+}
+
+impl TargetColumn {
+    /// Build a target column from a row of [`target_columns_query`].
+    pub fn from_row(row: &ResultRow) -> Self {
+        let text = |name: &str| {
+            row.get(name)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+        let number = |name: &str| text(name).and_then(|v| v.parse::<i64>().ok());
+        let flag = |name: &str| number(name).is_some_and(|v| v != 0);
+
+        let data_type = text("DATA_TYPE").unwrap_or_default().to_lowercase();
+        Self {
+            name: text("COLUMN_NAME").unwrap_or_default(),
+            max_length: number("CHARACTER_MAXIMUM_LENGTH"),
+            precision: number("NUMERIC_PRECISION").map(|v| v as u32),
+            scale: number("NUMERIC_SCALE").map(|v| v as u32),
+            nullable: flag("IS_NULLABLE"),
+            has_default: flag("HAS_DEFAULT"),
+            generated: flag("IS_IDENTITY")
+                || flag("IS_COMPUTED")
+                || matches!(data_type.as_str(), "timestamp" | "rowversion"),
+            data_type,
+        }
+    }
+
+    /// Whether an INSERT must supply a value for this column.
+    fn is_required(&self) -> bool {
+        !self.nullable && !self.has_default && !self.generated
+    }
+}
+
+/// Build the query that lists a table's columns for import.
+///
+/// `schema` and `table` are unescaped names; they are embedded as string literals.
+pub fn target_columns_query(schema: &str, table: &str) -> String {
+    let schema = schema.replace('\'', "''");
+    let table = table.replace('\'', "''");
+    format!(
+        "SELECT COLUMN_NAME, DATA_TYPE, CHARACTER_MAXIMUM_LENGTH, \
+         NUMERIC_PRECISION, NUMERIC_SCALE, \
+         CASE WHEN IS_NULLABLE = 'YES' THEN 1 ELSE 0 END AS IS_NULLABLE, \
+         CASE WHEN COLUMN_DEFAULT IS NULL THEN 0 ELSE 1 END AS HAS_DEFAULT, \
+         COLUMNPROPERTY(OBJECT_ID(QUOTENAME(TABLE_SCHEMA) + '.' + QUOTENAME(TABLE_NAME)), COLUMN_NAME, 'IsIdentity') AS IS_IDENTITY, \
+         COLUMNPROPERTY(OBJECT_ID(QUOTENAME(TABLE_SCHEMA) + '.' + QUOTENAME(TABLE_NAME)), COLUMN_NAME, 'IsComputed') AS IS_COMPUTED \
+         FROM INFORMATION_SCHEMA.COLUMNS \
+         WHERE TABLE_SCHEMA = N'{}' AND TABLE_NAME = N'{}' \
+         ORDER BY ORDINAL_POSITION",
+        schema, table
+    )
+}
+
+/// A source column mapped to a target column.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnMapping {
+    /// Source column name (header, JSON key, or 1-based position).
+    pub source: String,
+
+    /// Index of the source column in each record.
+    #[serde(skip)]
+    pub source_index: usize,
+
+    /// Target column.
+    pub target: TargetColumn,
+}
+
+/// How source columns are loaded into the target table.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPlan {
+    /// Mapped columns in insert order.
+    pub mappings: Vec<ColumnMapping>,
+
+    /// Source columns that are not loaded.
+    pub ignored_columns: Vec<String>,
+}
+
+impl ImportPlan {
+    /// Build the mapping from source headers to target columns.
+    ///
+    /// An explicit mapping (source name to target name) is used as given.
+    /// Otherwise source columns are matched to target columns by name
+    /// (case-insensitive) or, for positional headers, by column order.
+    pub fn build(
+        headers: &[String],
+        targets: &[TargetColumn],
+        explicit: &HashMap<String, String>,
+        positional: bool,
+    ) -> Result<Self, ServerError> {
+        let find_target = |name: &str| {
+            targets
+                .iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| ServerError::object_not_found("target column", name))
+        };
+
+        let mut mappings = Vec::new();
+        let mut ignored_columns = Vec::new();
+
+        if !explicit.is_empty() {
+            let mut sources: Vec<(&String, &String)> = explicit.iter().collect();
+            sources.sort();
+            for (source, target) in sources {
+                let source_index = headers
+                    .iter()
+                    .position(|h| h == source)
+                    .or_else(|| headers.iter().position(|h| h.eq_ignore_ascii_case(source)))
+                    .ok_or_else(|| ServerError::object_not_found("source column", source))?;
+                mappings.push(ColumnMapping {
+                    source: headers[source_index].clone(),
+                    source_index,
+                    target: find_target(target)?.clone(),
+                });
+            }
+            ignored_columns = headers
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !mappings.iter().any(|m| m.source_index == *i))
+                .map(|(_, h)| h.clone())
+                .collect();
+        } else if positional {
+            let insertable: Vec<&TargetColumn> = targets.iter().filter(|t| !t.generated).collect();
+            if headers.len() > insertable.len() {
+                return Err(ServerError::invalid_input(format!(
+                    "The file has {} columns but the table has only {} insertable columns",
+                    headers.len(),
+                    insertable.len()
+                )));
+            }
+            for (source_index, (header, target)) in headers.iter().zip(insertable).enumerate() {
+                mappings.push(ColumnMapping {
+                    source: header.clone(),
+                    source_index,
+                    target: target.clone(),
+                });
+            }
+        } else {
+            for (source_index, header) in headers.iter().enumerate() {
+                match targets.iter().find(|t| t.name.eq_ignore_ascii_case(header)) {
+                    Some(target) => mappings.push(ColumnMapping {
+                        source: header.clone(),
+                        source_index,
+                        target: target.clone(),
+                    }),
+                    None => ignored_columns.push(header.clone()),
+                }
+            }
+        }
+
+        if mappings.is_empty() {
+            return Err(ServerError::invalid_input(
+                "No source columns match the target table; provide column_mapping",
+            ));
+        }
+        if let Some(m) = mappings.iter().find(|m| m.target.generated) {
+            return Err(ServerError::invalid_input(format!(
+                "Column '{}' is an identity, computed or rowversion column and cannot be loaded",
+                m.target.name
+            )));
+        }
+        let missing: Vec<&str> = targets
+            .iter()
+            .filter(|t| t.is_required() && !mappings.iter().any(|m| m.target.name == t.name))
+            .map(|t| t.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(ServerError::invalid_input(format!(
+                "Required columns are not mapped: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(Self {
+            mappings,
+            ignored_columns,
+        })
+    }
+
+    /// Get the escaped target column list for INSERT statements.
+    pub fn column_list(&self) -> Result<String, ServerError> {
+        let columns: Result<Vec<String>, _> = self
+            .mappings
+            .iter()
+            .map(|m| safe_identifier(&m.target.name))
+            .collect();
+        Ok(columns?.join(", "))
+    }
+
+    /// Convert a source record into a VALUES row constructor.
+    pub fn convert(&self, record: &SourceRecord, empty_as_null: bool) -> Result<String, BadRow> {
+        let fields = record.fields.as_ref().map_err(|e| BadRow {
+            line: record.line,
+            column: None,
+            error: e.clone(),
+        })?;
+        self.row_values(fields, empty_as_null)
+            .map_err(|(column, error)| BadRow {
+                line: record.line,
+                column: Some(column),
+                error,
+            })
+    }
+
+    /// Convert field values into a VALUES row constructor.
+    ///
+    /// Returns the failing column and reason if any value is invalid.
+    pub fn row_values(
+        &self,
+        fields: &[Option<String>],
+        empty_as_null: bool,
+    ) -> Result<String, (String, String)> {
+        let mut values = Vec::with_capacity(self.mappings.len());
+        for mapping in &self.mappings {
+            let value = fields.get(mapping.source_index).cloned().flatten();
+            let value = value.filter(|v| !(empty_as_null && v.is_empty()));
+            let literal = sql_literal(value.as_deref(), &mapping.target)
+                .map_err(|e| (mapping.target.name.clone(), e))?;
+            values.push(literal);
+        }
+        Ok(format!("({})", values.join(", ")))
+    }
+}
+
+/// A source record that failed validation.
+#[derive(Debug, Clone, Serialize)]
+pub struct BadRow {
+    /// Line number where the record starts (1-based).
+    pub line: usize,
+
+    /// Target column whose value was rejected, if the record itself parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+
+    /// Reason the record was rejected.
+    pub error: String,
+}
+
+// =============================================================================
+// Value Conversion
+// =============================================================================
+
+/// Convert a source value into a SQL literal for a target column.
+fn sql_literal(value: Option<&str>, column: &TargetColumn) -> Result<String, String> {
+    let Some(value) = value else {
+        return if column.nullable {
+            Ok("NULL".to_string())
+        } else {
+            Err("NULL is not allowed".to_string())
+        };
+    };
+    let trimmed = value.trim();
+
+    match column.data_type.as_str() {
+        "tinyint" | "smallint" | "int" | "bigint" => {
+            let n: i64 = trimmed
+                .parse()
+                .map_err(|_| format!("'{}' is not an integer", value))?;
+            let (min, max) = match column.data_type.as_str() {
+                "tinyint" => (0, u8::MAX as i64),
+                "smallint" => (i16::MIN as i64, i16::MAX as i64),
+                "int" => (i32::MIN as i64, i32::MAX as i64),
+                _ => (i64::MIN, i64::MAX),
+            };
+            if n < min || n > max {
+                return Err(format!("{} is out of range for {}", n, column.data_type));
+            }
+            Ok(n.to_string())
+        }
+        "bit" => match trimmed.to_lowercase().as_str() {
+            "1" | "true" => Ok("1".to_string()),
+            "0" | "false" => Ok("0".to_string()),
+            _ => Err(format!("'{}' is not a boolean", value)),
+        },
+        "decimal" | "numeric" | "money" | "smallmoney" => {
+            let d = Decimal::from_str(trimmed)
+                .or_else(|_| Decimal::from_scientific(trimmed))
+                .map_err(|_| format!("'{}' is not a number", value))?;
+            if let (Some(precision), Some(scale)) = (column.precision, column.scale) {
+                let integer_digits = d.trunc().abs().to_string().trim_start_matches('0').len();
+                if integer_digits as u32 > precision.saturating_sub(scale) {
+                    return Err(format!(
+                        "{} does not fit {}({}, {})",
+                        value, column.data_type, precision, scale
+                    ));
+                }
+            }
+            Ok(d.to_string())
+        }
+        "float" | "real" => {
+            let f: f64 = trimmed
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", value))?;
+            if !f.is_finite() {
+                return Err(format!("'{}' is not a finite number", value));
+            }
+            Ok(format!("{:?}", f))
+        }
+        "date" => parse_date(trimmed)
+            .map(|d| format!("'{}'", d.format("%Y-%m-%d")))
+            .ok_or_else(|| format!("'{}' is not a date", value)),
+        "datetime" | "datetime2" | "smalldatetime" => parse_datetime(trimmed)
+            .map(|dt| format!("'{}'", dt.format("%Y-%m-%dT%H:%M:%S%.f")))
+            .ok_or_else(|| format!("'{}' is not a date/time", value)),
+        "time" => parse_time(trimmed)
+            .map(|t| format!("'{}'", t.format("%H:%M:%S%.f")))
+            .ok_or_else(|| format!("'{}' is not a time", value)),
+        "datetimeoffset" => DateTime::parse_from_rfc3339(trimmed)
+            .or_else(|_| DateTime::parse_from_str(trimmed, "%Y-%m-%d %H:%M:%S%.f %:z"))
+            .map(|dt| format!("'{}'", dt.format("%Y-%m-%dT%H:%M:%S%.f%:z")))
+            .map_err(|_| format!("'{}' is not a date/time with offset", value)),
+        "uniqueidentifier" => uuid::Uuid::parse_str(trimmed)
+            .map(|u| format!("'{}'", u))
+            .map_err(|_| format!("'{}' is not a GUID", value)),
+        "binary" | "varbinary" | "image" => {
+            let hex = trimmed
+                .strip_prefix("0x")
+                .or_else(|| trimmed.strip_prefix("0X"))
+                .unwrap_or(trimmed);
+            if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("'{}' is not a hex binary value", value));
+            }
+            check_length(hex.len() / 2, column)?;
+            Ok(format!("0x{}", hex))
+        }
+        _ => {
+            check_length(value.chars().count(), column)?;
+            Ok(format!("N'{}'", value.replace('\'', "''")))
+        }
+    }
+}
+
+/// Check a value length against the column's maximum length.
+fn check_length(length: usize, column: &TargetColumn) -> Result<(), String> {
+    match column.max_length {
+        Some(max) if max > 0 && length as i64 > max => Err(format!(
+            "value length {} exceeds {}({})",
+            length, column.data_type, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parse a date in ISO format.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d"))
+        .ok()
+}
+
+/// Parse a date/time in ISO format (a bare date means midnight).
+fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+    .or_else(|| {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|dt| dt.naive_utc())
+    })
+    .or_else(|| parse_date(value).and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+/// Parse a time of day.
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> TargetColumn {
+        TargetColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            max_length: None,
+            precision: None,
+            scale: None,
+            nullable,
+            has_default: false,
+            generated: false,
+        }
+    }
+
+    #[test]
+    fn test_split_csv_record() {
+        assert_eq!(
+            split_csv_record("1,\"Smith, J\",\"say \"\"hi\"\"\",", ',').unwrap(),
+            vec!["1", "Smith, J", "say \"hi\"", ""]
+        );
+        assert_eq!(split_csv_record("a\tb", '\t').unwrap(), vec!["a", "b"]);
+        assert!(split_csv_record("\"open", ',').is_err());
+    }
+
+    #[test]
+    fn test_sql_literals() {
+        let int = column("id", "int", false);
+        assert_eq!(sql_literal(Some(" 42 "), &int).unwrap(), "42");
+        assert!(sql_literal(Some("3000000000"), &int).is_err());
+        assert!(sql_literal(None, &int).is_err());
+
+        let mut name = column("name", "nvarchar", true);
+        name.max_length = Some(5);
+        assert_eq!(
+            sql_literal(Some("O'Hara"), &name).unwrap_err(),
+            "value length 6 exceeds nvarchar(5)"
+        );
+        assert_eq!(sql_literal(Some("Ann's"), &name).unwrap(), "N'Ann''s'");
+        assert_eq!(sql_literal(None, &name).unwrap(), "NULL");
+
+        let mut price = column("price", "decimal", false);
+        price.precision = Some(5);
+        price.scale = Some(2);
+        assert_eq!(sql_literal(Some("123.45"), &price).unwrap(), "123.45");
+        assert!(sql_literal(Some("1234.5"), &price).is_err());
+
+        let created = column("created", "datetime", false);
+        assert_eq!(
+            sql_literal(Some("2024-03-01 08:30:00"), &created).unwrap(),
+            "'2024-03-01T08:30:00'"
+        );
+        assert!(sql_literal(Some("yesterday"), &created).is_err());
+    }
+
+    #[test]
+    fn test_import_plan_mapping() {
+        let mut id = column("Id", "int", false);
+        id.generated = true;
+        let targets = vec![
+            id,
+            column("Name", "nvarchar", false),
+            column("Email", "nvarchar", true),
+        ];
+        let headers = vec!["name".to_string(), "email".to_string(), "extra".to_string()];
+
+        let plan = ImportPlan::build(&headers, &targets, &HashMap::new(), false).unwrap();
+        assert_eq!(plan.mappings.len(), 2);
+        assert_eq!(plan.mappings[0].target.name, "Name");
+        assert_eq!(plan.ignored_columns, vec!["extra"]);
+
+        let values = plan
+            .row_values(&[Some("Ann".to_string()), Some(String::new()), None], true)
+            .unwrap();
+        assert_eq!(values, "(N'Ann', NULL)");
+        assert_eq!(
+            plan.row_values(&[None, None, None], true).unwrap_err().0,
+            "Name"
+        );
+
+        // Positional mapping skips generated columns
+        let positional = vec!["1".to_string(), "2".to_string()];
+        let plan = ImportPlan::build(&positional, &targets, &HashMap::new(), true).unwrap();
+        assert_eq!(plan.mappings[1].target.name, "Email");
+
+        // Required columns must be mapped
+        let explicit = HashMap::from([("email".to_string(), "Email".to_string())]);
+        assert!(ImportPlan::build(&headers, &targets, &explicit, false).is_err());
+    }
+
+    #[test]
+    fn test_source_reader() {
+        let dir = std::env::temp_dir().join(format!("mssql-mcp-import-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let allowed = vec![dir.clone()];
+
+        std::fs::write(
+            dir.join("people.csv"),
+            "\u{feff}id,note\r\n1,\"line one\nline two\"\r\n\r\n2\r\n3,plain\r\n",
+        )
+        .unwrap();
+        let path = resolve_source("people.csv", &allowed).unwrap();
+        assert_eq!(
+            detect_format(&path, ImportFormat::Auto).unwrap(),
+            ImportFormat::Csv
+        );
+        let mut reader = SourceReader::open(&path, ImportFormat::Csv, true, ',').unwrap();
+        assert_eq!(reader.headers(), ["id", "note"]);
+
+        let first = reader.next_record().unwrap().unwrap();
+        assert_eq!(first.line, 2);
+        assert_eq!(
+            first.fields.unwrap()[1].as_deref(),
+            Some("line one\nline two")
+        );
+        let short = reader.next_record().unwrap().unwrap();
+        assert_eq!(short.line, 5);
+        assert!(short.fields.is_err());
+        assert_eq!(reader.next_record().unwrap().unwrap().line, 6);
+        assert!(reader.next_record().unwrap().is_none());
+
+        std::fs::write(
+            dir.join("people.jsonl"),
+            "{\"id\": 1, \"note\": null}\n{\"id\": 2}\n",
+        )
+        .unwrap();
+        let path = resolve_source("people.jsonl", &allowed).unwrap();
+        let mut reader = SourceReader::open(&path, ImportFormat::JsonLines, true, ',').unwrap();
+        assert_eq!(reader.headers(), ["id", "note"]);
+        let first = reader.next_record().unwrap().unwrap().fields.unwrap();
+        assert_eq!(first, vec![Some("1".to_string()), None]);
+        assert_eq!(reader.next_record().unwrap().unwrap().line, 2);
+
+        assert!(resolve_source("../etc/passwd", &allowed).is_err());
+        assert!(resolve_source("people.csv", &[]).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
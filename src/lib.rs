@@ -15,14 +15,20 @@
 //! - Tools for active operations (query execution)
 //! - Prompts for templated AI interactions
 
+pub mod audit;
 pub mod cache;
 pub mod config;
 pub mod constants;
 pub mod database;
 pub mod error;
+pub mod export;
+pub mod import;
+pub mod localization;
 pub mod resilience;
+pub mod results;
 pub mod security;
 pub mod server;
+pub mod session_store;
 pub mod shutdown;
 pub mod state;
 pub mod telemetry;
@@ -0,0 +1,306 @@
+//! Localization of SQL Server error messages.
+//!
+//! When an error language is configured, error messages are translated using:
+//! - `sys.messages`, when the matching SQL Server language pack is installed
+//! - A bundled catalog of common errors as a fallback
+//!
+//! The original English message is always preserved alongside the translation
+//! so that nothing is lost when relaying errors to users.
+
+use crate::database::QueryExecutor;
+use crate::error::ServerError;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Languages with a bundled error catalog.
+pub const BUNDLED_LANGUAGES: &[&str] = &["de", "es", "fr", "it", "pt"];
+
+/// Translates server errors into a configured language.
+pub struct ErrorLocalizer {
+    /// Normalized two-letter language code, or `None` for English.
+    language: Option<String>,
+
+    /// Cached `sys.messages` lookups keyed by error number.
+    sys_messages: RwLock<HashMap<i32, Option<String>>>,
+}
+
+impl ErrorLocalizer {
+    /// Create a localizer for the given language (e.g. `de`, `fr-FR`, `German`).
+    ///
+    /// English or an unrecognized language disables localization.
+    pub fn new(language: Option<&str>) -> Self {
+        Self {
+            language: language.and_then(normalize_language),
+            sys_messages: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get the configured language code, if localization is enabled.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Localize an error message.
+    ///
+    /// Returns the plain English message when localization is disabled or no
+    /// translation is available.
+    pub async fn localize(&self, error: &ServerError, executor: &QueryExecutor) -> String {
+        let original = error.to_string();
+        let Some(language) = self.language.as_deref() else {
+            return original;
+        };
+
+        if let ServerError::QueryExecution {
+            sql_error_code: Some(code),
+            ..
+        } = error.root()
+        {
+            if let Some(text) = self.lookup_sys_message(*code, language, executor).await {
+                return format!("{}\n(original: {})", text, original);
+            }
+        }
+
+        match error_key(error).and_then(|key| bundled_message(language, key)) {
+            Some(text) => format!("{}\n(original: {})", text, original),
+            None => original,
+        }
+    }
+
+    /// Look up a translated message template in `sys.messages`.
+    async fn lookup_sys_message(
+        &self,
+        code: i32,
+        language: &str,
+        executor: &QueryExecutor,
+    ) -> Option<String> {
+        if code <= 0 {
+            return None;
+        }
+        if let Some(cached) = self.sys_messages.read().await.get(&code) {
+            return cached.clone();
+        }
+
+        let lcid = language_lcid(language)?;
+        let query = format!(
+            "SELECT text FROM sys.messages WHERE message_id = {} AND language_id = {}",
+            code, lcid
+        );
+        let text = match executor.execute(&query).await {
+            Ok(result) => result
+                .rows
+                .first()
+                .and_then(|row| row.get("text"))
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string()),
+            Err(e) => {
+                debug!("sys.messages lookup for error {} failed: {}", code, e);
+                None
+            }
+        };
+
+        self.sys_messages.write().await.insert(code, text.clone());
+        text
+    }
+}
+
+/// Normalize a configured language to a two-letter code.
+fn normalize_language(language: &str) -> Option<String> {
+    let lower = language.trim().to_lowercase();
+    let code = match lower.as_str() {
+        "german" | "deutsch" => "de",
+        "spanish" | "español" | "espanol" => "es",
+        "french" | "français" | "francais" => "fr",
+        "italian" | "italiano" => "it",
+        "portuguese" | "português" | "portugues" => "pt",
+        "japanese" => "ja",
+        "english" | "us_english" => "en",
+        other => other.split(['-', '_']).next().unwrap_or(other),
+    };
+    if code.is_empty() || code == "en" {
+        None
+    } else {
+        Some(code.to_string())
+    }
+}
+
+/// Map a language code to the LCID used by `sys.messages.language_id`.
+fn language_lcid(language: &str) -> Option<i32> {
+    match language {
+        "de" => Some(1031),
+        "fr" => Some(1036),
+        "ja" => Some(1041),
+        "es" => Some(3082),
+        "it" => Some(1040),
+        "nl" => Some(1043),
+        "pt" => Some(1046),
+        "ru" => Some(1049),
+        "sv" => Some(1053),
+        "ko" => Some(1042),
+        "zh" => Some(2052),
+        "pl" => Some(1045),
+        "tr" => Some(1055),
+        _ => None,
+    }
+}
+
+/// Classify an error into a catalog key.
+fn error_key(error: &ServerError) -> Option<&'static str> {
+    match error.root() {
+        ServerError::Authentication(_) => Some("authentication"),
+        ServerError::DatabaseNotFound(_) => Some("database_not_found"),
+        ServerError::ObjectNotFound { .. } => Some("object_not_found"),
+        ServerError::PermissionDenied(_) => Some("permission_denied"),
+        ServerError::ConstraintViolation(_) => Some("constraint_violation"),
+        ServerError::DataTruncation(_) => Some("data_truncation"),
+        ServerError::Timeout { .. } => Some("timeout"),
+        ServerError::Connection { .. } => Some("connection"),
+        ServerError::QueryExecution {
+            sql_error_code: Some(code),
+            ..
+        } => match code {
+            102 => Some("syntax"),
+            207 => Some("invalid_column"),
+            1205 => Some("deadlock"),
+            8115 => Some("arithmetic_overflow"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Bundled translations for common errors.
+fn bundled_message(language: &str, key: &str) -> Option<&'static str> {
+    let text = match (language, key) {
+        ("de", "authentication") => "Anmeldung fehlgeschlagen",
+        ("de", "database_not_found") => "Datenbank nicht gefunden",
+        ("de", "object_not_found") => "Objekt nicht gefunden",
+        ("de", "permission_denied") => "Berechtigung verweigert",
+        ("de", "constraint_violation") => "Einschränkungsverletzung",
+        ("de", "data_truncation") => "Daten würden abgeschnitten",
+        ("de", "timeout") => "Zeitüberschreitung bei der Abfrage",
+        ("de", "connection") => "Verbindungsfehler",
+        ("de", "syntax") => "Syntaxfehler",
+        ("de", "invalid_column") => "Ungültiger Spaltenname",
+        ("de", "deadlock") => {
+            "Die Transaktion wurde als Deadlockopfer ausgewählt und zurückgesetzt"
+        }
+        ("de", "arithmetic_overflow") => "Arithmetischer Überlauf",
+
+        ("es", "authentication") => "Error de inicio de sesión",
+        ("es", "database_not_found") => "Base de datos no encontrada",
+        ("es", "object_not_found") => "Objeto no encontrado",
+        ("es", "permission_denied") => "Permiso denegado",
+        ("es", "constraint_violation") => "Infracción de restricción",
+        ("es", "data_truncation") => "Los datos se truncarían",
+        ("es", "timeout") => "Tiempo de espera de la consulta agotado",
+        ("es", "connection") => "Error de conexión",
+        ("es", "syntax") => "Error de sintaxis",
+        ("es", "invalid_column") => "Nombre de columna no válido",
+        ("es", "deadlock") => {
+            "La transacción fue elegida como víctima de interbloqueo y se revirtió"
+        }
+        ("es", "arithmetic_overflow") => "Desbordamiento aritmético",
+
+        ("fr", "authentication") => "Échec de la connexion",
+        ("fr", "database_not_found") => "Base de données introuvable",
+        ("fr", "object_not_found") => "Objet introuvable",
+        ("fr", "permission_denied") => "Autorisation refusée",
+        ("fr", "constraint_violation") => "Violation de contrainte",
+        ("fr", "data_truncation") => "Les données seraient tronquées",
+        ("fr", "timeout") => "Délai d'exécution de la requête dépassé",
+        ("fr", "connection") => "Erreur de connexion",
+        ("fr", "syntax") => "Erreur de syntaxe",
+        ("fr", "invalid_column") => "Nom de colonne non valide",
+        ("fr", "deadlock") => {
+            "La transaction a été choisie comme victime d'un interblocage et annulée"
+        }
+        ("fr", "arithmetic_overflow") => "Dépassement arithmétique",
+
+        ("it", "authentication") => "Accesso non riuscito",
+        ("it", "database_not_found") => "Database non trovato",
+        ("it", "object_not_found") => "Oggetto non trovato",
+        ("it", "permission_denied") => "Autorizzazione negata",
+        ("it", "constraint_violation") => "Violazione di vincolo",
+        ("it", "data_truncation") => "I dati verrebbero troncati",
+        ("it", "timeout") => "Timeout della query",
+        ("it", "connection") => "Errore di connessione",
+        ("it", "syntax") => "Errore di sintassi",
+        ("it", "invalid_column") => "Nome di colonna non valido",
+        ("it", "deadlock") => {
+            "La transazione è stata scelta come vittima di deadlock ed è stata annullata"
+        }
+        ("it", "arithmetic_overflow") => "Overflow aritmetico",
+
+        ("pt", "authentication") => "Falha no logon",
+        ("pt", "database_not_found") => "Banco de dados não encontrado",
+        ("pt", "object_not_found") => "Objeto não encontrado",
+        ("pt", "permission_denied") => "Permissão negada",
+        ("pt", "constraint_violation") => "Violação de restrição",
+        ("pt", "data_truncation") => "Os dados seriam truncados",
+        ("pt", "timeout") => "Tempo limite da consulta esgotado",
+        ("pt", "connection") => "Erro de conexão",
+        ("pt", "syntax") => "Erro de sintaxe",
+        ("pt", "invalid_column") => "Nome de coluna inválido",
+        ("pt", "deadlock") => "A transação foi escolhida como vítima de deadlock e revertida",
+        ("pt", "arithmetic_overflow") => "Estouro aritmético",
+
+        _ => return None,
+    };
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language("de-DE"), Some("de".to_string()));
+        assert_eq!(normalize_language("French"), Some("fr".to_string()));
+        assert_eq!(normalize_language("pt_BR"), Some("pt".to_string()));
+        assert_eq!(normalize_language("en-US"), None);
+        assert_eq!(normalize_language(""), None);
+    }
+
+    #[test]
+    fn test_bundled_catalog_complete() {
+        let keys = [
+            "authentication",
+            "database_not_found",
+            "object_not_found",
+            "permission_denied",
+            "constraint_violation",
+            "data_truncation",
+            "timeout",
+            "connection",
+            "syntax",
+            "invalid_column",
+            "deadlock",
+            "arithmetic_overflow",
+        ];
+        for language in BUNDLED_LANGUAGES {
+            assert!(language_lcid(language).is_some());
+            for key in keys {
+                assert!(
+                    bundled_message(language, key).is_some(),
+                    "missing {} translation for {}",
+                    language,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_key() {
+        let err = crate::error::from_sql_error(208, "Invalid object name 'foo'");
+        assert_eq!(error_key(&err), Some("object_not_found"));
+
+        let err = crate::error::from_sql_error(1205, "deadlock");
+        assert_eq!(error_key(&err), Some("deadlock"));
+
+        let err = ServerError::internal("boom");
+        assert_eq!(error_key(&err), None);
+    }
+}
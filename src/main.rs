@@ -6,15 +6,18 @@
 //! Features:
 //! - Graceful shutdown with connection draining
 //! - Signal handling (SIGTERM, SIGINT)
+//! - Configuration reload and credential rotation on SIGHUP
 //! - Transaction rollback on shutdown
+//! - Cancellation and persistence of unfinished async sessions on shutdown
 //! - Cache cleanup
 
 use anyhow::Result;
 use mcpkit::transport::stdio::StdioTransport;
-use mssql_mcp_server::shutdown::{
-    install_signal_handlers, new_shutdown_controller_with_timeouts, ShutdownConfig,
-};
+use mssql_mcp_server::database::ServerMessageLayer;
+use mssql_mcp_server::shutdown::{install_signal_handlers, ShutdownConfig, ShutdownController};
 use mssql_mcp_server::{Config, MssqlMcpServer};
+use std::sync::Arc;
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
@@ -33,17 +36,21 @@ async fn main() -> Result<()> {
         eprintln!("[PANIC] {}", info);
     }));
 
-    // Load configuration from environment
-    let config = Config::from_env()?;
+    // Load configuration from the environment and optional config file; on a
+    // blocking thread, since secrets providers wait on network requests
+    let config = tokio::task::spawn_blocking(Config::load).await??;
     eprintln!("Configuration loaded successfully");
 
     // Load shutdown configuration
     let shutdown_config = ShutdownConfig::from_env();
 
     // Create the shutdown controller
-    let shutdown_controller = new_shutdown_controller_with_timeouts(
-        shutdown_config.drain_timeout,
-        shutdown_config.force_timeout,
+    let shutdown_controller = Arc::new(
+        ShutdownController::with_timeouts(
+            shutdown_config.drain_timeout,
+            shutdown_config.force_timeout,
+        )
+        .with_session_wait(shutdown_config.wait_for_sessions),
     );
 
     // Install signal handlers for graceful shutdown
@@ -52,14 +59,19 @@ async fn main() -> Result<()> {
     // Create the MCP server
     let server = MssqlMcpServer::new(config).await?;
     let state = server.state().clone();
+    let session_store = Arc::clone(server.session_store());
+    #[cfg(unix)]
+    server.reload_on_hangup();
+    server.watch_credentials();
     eprintln!("Server initialized. Ready to accept requests...");
 
     // Start serving on stdio transport
-    let mcp_server = server.into_server();
+    let transport = server.subscription_transport(StdioTransport::new());
+    let mcp_server = server.into_traced_server();
     let mut shutdown_signal = shutdown_controller.signal();
 
     tokio::select! {
-        result = mcp_server.serve(StdioTransport::new()) => {
+        result = mcp_server.serve(transport) => {
             match result {
                 Ok(()) => eprintln!("Service stopped normally"),
                 Err(e) => eprintln!("Service error: {e}"),
@@ -72,7 +84,9 @@ async fn main() -> Result<()> {
 
     // Perform graceful shutdown
     eprintln!("Initiating graceful shutdown...");
-    shutdown_controller.graceful_shutdown(&state).await;
+    shutdown_controller
+        .graceful_shutdown_with_store(&state, &session_store)
+        .await;
     eprintln!("Server shutdown complete");
 
     Ok(())
@@ -86,9 +100,15 @@ fn init_logging() {
         .map(EnvFilter::new)
         .unwrap_or_else(|_| EnvFilter::new("warn,mssql_mcp_server=info"));
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_writer(std::io::stderr)
-        .with_ansi(false)
+    // The log filter applies only to the fmt layer so that server info
+    // messages (PRINT output) are captured even when driver logging is off.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(false)
+                .with_filter(filter),
+        )
+        .with(ServerMessageLayer::new())
         .init();
 }
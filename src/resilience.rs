@@ -7,6 +7,7 @@
 
 use crate::error::ServerError;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
@@ -26,6 +27,8 @@ pub struct RetryConfig {
     pub multiplier: f64,
     /// Whether to add jitter to delays.
     pub jitter: bool,
+    /// Maximum total time to spend waiting between attempts (`None` = unlimited).
+    pub budget: Option<Duration>,
 }
 
 impl Default for RetryConfig {
@@ -36,6 +39,7 @@ impl Default for RetryConfig {
             max_delay: Duration::from_secs(10),
             multiplier: 2.0,
             jitter: true,
+            budget: None,
         }
     }
 }
@@ -57,6 +61,7 @@ impl RetryConfig {
             max_delay: Duration::from_secs(30),
             multiplier: 2.0,
             jitter: true,
+            budget: None,
         }
     }
 
@@ -68,6 +73,7 @@ impl RetryConfig {
             max_delay: Duration::from_secs(60),
             multiplier: 2.0,
             jitter: true,
+            budget: None,
         }
     }
 
@@ -99,6 +105,12 @@ impl RetryConfig {
             }
         }
 
+        if let Ok(budget) = std::env::var("MSSQL_RETRY_BUDGET_MS") {
+            if let Ok(ms) = budget.parse() {
+                config.budget = Some(Duration::from_millis(ms));
+            }
+        }
+
         config
     }
 
@@ -148,6 +160,10 @@ pub struct RetryResult<T> {
     pub attempts: u32,
     /// Total time spent (including delays).
     pub total_duration: Duration,
+    /// Total time spent waiting between attempts.
+    pub total_wait: Duration,
+    /// Whether retrying stopped early because the wait budget ran out.
+    pub budget_exhausted: bool,
     /// The last error, if the operation failed.
     pub last_error: Option<ServerError>,
 }
@@ -158,6 +174,21 @@ impl<T> RetryResult<T> {
         self.value.is_some()
     }
 
+    /// Summarize the retry behavior for reporting.
+    pub fn info(&self) -> RetryInfo {
+        RetryInfo {
+            attempts: self.attempts,
+            total_wait_ms: self.total_wait.as_millis() as u64,
+            outcome: if self.is_success() {
+                RetryOutcome::Succeeded
+            } else if self.budget_exhausted {
+                RetryOutcome::BudgetExhausted
+            } else {
+                RetryOutcome::Failed
+            },
+        }
+    }
+
     /// Convert to a standard Result.
     pub fn into_result(self) -> Result<T, ServerError> {
         match self.value {
@@ -169,6 +200,29 @@ impl<T> RetryResult<T> {
     }
 }
 
+/// Final outcome of a retried operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryOutcome {
+    /// The operation eventually succeeded.
+    Succeeded,
+    /// All attempts failed (or the error was not transient).
+    Failed,
+    /// Retrying stopped because the next delay would exceed the wait budget.
+    BudgetExhausted,
+}
+
+/// Retry metadata surfaced in query results and errors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryInfo {
+    /// Number of attempts made.
+    pub attempts: u32,
+    /// Total time spent waiting between attempts, in milliseconds.
+    pub total_wait_ms: u64,
+    /// Final outcome.
+    pub outcome: RetryOutcome,
+}
+
 /// Execute an async operation with retry logic.
 ///
 /// The operation will be retried if it returns an error and the error
@@ -189,21 +243,43 @@ where
 {
     let start = std::time::Instant::now();
     let mut last_error = None;
+    let mut total_wait = Duration::ZERO;
+    let mut attempts = 0;
 
     for attempt in 0..config.max_attempts {
         // Apply delay before retry (not on first attempt)
         if attempt > 0 {
             let delay = config.delay_for_attempt(attempt);
+            if let Some(budget) = config.budget {
+                if total_wait + delay > budget {
+                    warn!(
+                        "Retry budget of {:?} exhausted after {} attempts",
+                        budget, attempts
+                    );
+                    return RetryResult {
+                        value: None,
+                        attempts,
+                        total_duration: start.elapsed(),
+                        total_wait,
+                        budget_exhausted: true,
+                        last_error,
+                    };
+                }
+            }
             debug!("Retry attempt {} after {:?} delay", attempt + 1, delay);
             sleep(delay).await;
+            total_wait += delay;
         }
+        attempts = attempt + 1;
 
         match operation().await {
             Ok(value) => {
                 return RetryResult {
                     value: Some(value),
-                    attempts: attempt + 1,
+                    attempts,
                     total_duration: start.elapsed(),
+                    total_wait,
+                    budget_exhausted: false,
                     last_error: None,
                 };
             }
@@ -213,8 +289,10 @@ where
                     debug!("Non-transient error, not retrying: {}", e);
                     return RetryResult {
                         value: None,
-                        attempts: attempt + 1,
+                        attempts,
                         total_duration: start.elapsed(),
+                        total_wait,
+                        budget_exhausted: false,
                         last_error: Some(e),
                     };
                 }
@@ -232,8 +310,10 @@ where
 
     RetryResult {
         value: None,
-        attempts: config.max_attempts,
+        attempts,
         total_duration: start.elapsed(),
+        total_wait,
+        budget_exhausted: false,
         last_error,
     }
 }
@@ -668,6 +748,7 @@ mod tests {
             max_delay: Duration::from_secs(10),
             multiplier: 2.0,
             jitter: false,
+            budget: None,
         };
 
         // First attempt has no delay
@@ -687,6 +768,7 @@ mod tests {
             max_delay: Duration::from_secs(5),
             multiplier: 10.0,
             jitter: false,
+            budget: None,
         };
 
         // After a few attempts, delay should be capped
@@ -718,6 +800,7 @@ mod tests {
             max_delay: Duration::from_millis(10),
             multiplier: 2.0,
             jitter: false,
+            budget: None,
         };
         let counter = AtomicU32::new(0);
 
@@ -762,6 +845,7 @@ mod tests {
             max_delay: Duration::from_millis(10),
             multiplier: 2.0,
             jitter: false,
+            budget: None,
         };
         let counter = AtomicU32::new(0);
 
@@ -776,6 +860,62 @@ mod tests {
         assert_eq!(counter.load(Ordering::SeqCst), 3);
     }
 
+    #[tokio::test]
+    async fn test_retry_budget_exhausted() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            jitter: false,
+            budget: Some(Duration::from_millis(25)),
+        };
+        let counter = AtomicU32::new(0);
+
+        let result = retry_async(&config, || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(ServerError::timeout(30)) }
+        })
+        .await;
+
+        // Delays of 10ms and 20ms would exceed the 25ms budget, so only two attempts run
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+        let info = result.info();
+        assert_eq!(info.attempts, 2);
+        assert_eq!(info.total_wait_ms, 10);
+        assert_eq!(info.outcome, RetryOutcome::BudgetExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_retry_info_after_success() {
+        let config = RetryConfig {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            multiplier: 2.0,
+            jitter: false,
+            budget: None,
+        };
+        let counter = AtomicU32::new(0);
+
+        let result = retry_async(&config, || {
+            let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if count < 2 {
+                    Err(ServerError::timeout(30))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+
+        let info = result.info();
+        assert_eq!(info.attempts, 2);
+        assert_eq!(info.total_wait_ms, 1);
+        assert_eq!(info.outcome, RetryOutcome::Succeeded);
+    }
+
     // =========================================================================
     // Circuit Breaker Tests
     // =========================================================================
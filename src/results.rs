@@ -0,0 +1,385 @@
+//! Content-addressable storage for large query results.
+//!
+//! When a result exceeds the configured size threshold, tools return a compact
+//! reference (content hash, schema, and a short preview) instead of the full
+//! table. Clients retrieve slices of the stored result with `fetch_result_chunk`,
+//! so large results can be passed around by handle rather than re-embedded in
+//! every message.
+//!
+//! References are derived from the result content, so identical results share
+//! a single stored entry.
+//!
+//! Completed async sessions keep their result in the session itself; it is
+//! read page by page through the `mssql://sessions/{id}/results` resource
+//! ([`SessionResultsPage`]).
+
+use crate::cache::estimate_result_size;
+use crate::constants::{
+    DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, MIN_PAGE_SIZE, RESULT_REF_MAX_ENTRIES,
+    RESULT_REF_PREVIEW_ROWS,
+};
+use crate::database::{QueryColumnInfo, QueryResult, ResultRow};
+use crate::error::ServerError;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Prefix used for result references.
+const RESULT_REF_PREFIX: &str = "res_";
+
+/// Prefix of async session result resources.
+pub const SESSION_RESULTS_URI_PREFIX: &str = "mssql://sessions/";
+
+/// Compact reference returned in place of a large result.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultReference {
+    /// Content-derived reference for `fetch_result_chunk`.
+    pub result_ref: String,
+
+    /// Number of rows in the stored result.
+    pub row_count: usize,
+
+    /// Estimated size of the full result in bytes.
+    pub size_bytes: usize,
+
+    /// Whether the stored result was truncated by the row limit.
+    pub truncated: bool,
+
+    /// Result schema.
+    pub columns: Vec<QueryColumnInfo>,
+
+    /// First few rows of the result.
+    pub preview: Vec<ResultRow>,
+
+    /// Human-readable summary.
+    pub summary: String,
+}
+
+/// A stored result and its bookkeeping.
+struct StoredResult {
+    result: QueryResult,
+    size_bytes: usize,
+    created_at: Instant,
+    last_accessed: Instant,
+}
+
+/// Store of large query results keyed by content hash.
+pub struct ResultStore {
+    /// Stored results keyed by reference.
+    entries: RwLock<HashMap<String, StoredResult>>,
+
+    /// Results larger than this are stored (0 disables references).
+    threshold_bytes: usize,
+
+    /// How long stored results are kept.
+    ttl: Duration,
+}
+
+impl ResultStore {
+    /// Create a new result store.
+    pub fn new(threshold_bytes: usize, ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            threshold_bytes,
+            ttl,
+        }
+    }
+
+    /// Check if result references are enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.threshold_bytes > 0
+    }
+
+    /// Store a result if it exceeds the size threshold.
+    ///
+    /// Returns `None` when the result is small enough to return inline.
+    pub async fn store_if_large(&self, result: &QueryResult) -> Option<ResultReference> {
+        if !self.is_enabled() || result.rows.is_empty() {
+            return None;
+        }
+        let size_bytes = estimate_result_size(result);
+        if size_bytes <= self.threshold_bytes {
+            return None;
+        }
+
+        let result_ref = content_ref(result);
+        let now = Instant::now();
+
+        let mut entries = self.entries.write().await;
+        entries.retain(|_, e| e.created_at.elapsed() <= self.ttl);
+        if !entries.contains_key(&result_ref) && entries.len() >= RESULT_REF_MAX_ENTRIES {
+            // Evict the least recently accessed entry
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_accessed)
+                .map(|(k, _)| k.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        let entry = entries
+            .entry(result_ref.clone())
+            .or_insert_with(|| StoredResult {
+                result: result.clone(),
+                size_bytes,
+                created_at: now,
+                last_accessed: now,
+            });
+        entry.last_accessed = now;
+
+        Some(ResultReference {
+            result_ref,
+            row_count: result.rows.len(),
+            size_bytes,
+            truncated: result.truncated,
+            columns: result.columns.clone(),
+            preview: result
+                .rows
+                .iter()
+                .take(RESULT_REF_PREVIEW_ROWS)
+                .cloned()
+                .collect(),
+            summary: format!(
+                "{} rows x {} columns (~{} KB), stored by reference",
+                result.rows.len(),
+                result.columns.len(),
+                size_bytes.div_ceil(1024)
+            ),
+        })
+    }
+
+    /// Fetch a slice of rows from a stored result.
+    ///
+    /// Returns the chunk and the total number of rows in the stored result.
+    pub async fn fetch_chunk(
+        &self,
+        result_ref: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(QueryResult, usize), ServerError> {
+        let mut entries = self.entries.write().await;
+        let entry = entries
+            .get_mut(result_ref)
+            .filter(|e| e.created_at.elapsed() <= self.ttl)
+            .ok_or_else(|| ServerError::object_not_found("result reference", result_ref))?;
+        entry.last_accessed = Instant::now();
+
+        Ok((
+            slice_rows(&entry.result, offset, limit),
+            entry.result.rows.len(),
+        ))
+    }
+
+    /// Get the number of stored results and their total estimated size.
+    pub async fn usage(&self) -> (usize, usize) {
+        let entries = self.entries.read().await;
+        (entries.len(), entries.values().map(|e| e.size_bytes).sum())
+    }
+}
+
+/// Rows `offset..offset + limit` of a result.
+///
+/// `truncated` is set when rows follow the slice.
+pub fn slice_rows(result: &QueryResult, offset: usize, limit: usize) -> QueryResult {
+    let total = result.rows.len();
+    let start = offset.min(total);
+    let end = start.saturating_add(limit).min(total);
+
+    let mut slice = QueryResult::empty();
+    slice.columns = result.columns.clone();
+    slice.rows = result.rows[start..end].to_vec();
+    slice.execution_time_ms = result.execution_time_ms;
+    slice.truncated = end < total;
+    slice
+}
+
+/// A page of a completed async session's result, addressed as
+/// `mssql://sessions/{id}/results?page=N&page_size=M`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionResultsPage {
+    pub session_id: String,
+    /// One-based page number.
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl SessionResultsPage {
+    /// Parse a session results resource URI (default: first page of
+    /// `DEFAULT_PAGE_SIZE` rows).
+    pub fn from_uri(uri: &str) -> Result<Self, ServerError> {
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let session_id = path
+            .strip_prefix(SESSION_RESULTS_URI_PREFIX)
+            .and_then(|rest| rest.strip_suffix("/results"))
+            .filter(|id| !id.is_empty() && !id.contains('/'))
+            .ok_or_else(|| {
+                ServerError::invalid_input(format!("Invalid session results URI: {}", uri))
+            })?;
+
+        let param = |name: &str| -> Result<Option<usize>, ServerError> {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| {
+                    value.parse::<usize>().map_err(|_| {
+                        ServerError::invalid_input(format!(
+                            "Invalid {} '{}' (expected a positive integer)",
+                            name, value
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let page = param("page")?.unwrap_or(1);
+        if page == 0 {
+            return Err(ServerError::invalid_input("Pages are numbered from 1"));
+        }
+        let page_size = param("page_size")?
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(MIN_PAGE_SIZE, MAX_PAGE_SIZE);
+
+        Ok(Self {
+            session_id: session_id.to_string(),
+            page,
+            page_size,
+        })
+    }
+
+    /// Resource URI of a page.
+    pub fn uri(session_id: &str, page: usize, page_size: usize) -> String {
+        format!(
+            "{}{}/results?page={}&page_size={}",
+            SESSION_RESULTS_URI_PREFIX, session_id, page, page_size
+        )
+    }
+
+    /// Row offset of the first row on the page.
+    pub fn offset(&self) -> usize {
+        (self.page - 1).saturating_mul(self.page_size)
+    }
+
+    /// Number of pages holding `total_rows` rows.
+    pub fn page_count(&self, total_rows: usize) -> usize {
+        total_rows.div_ceil(self.page_size)
+    }
+}
+
+/// Derive a content reference from a result's columns and rows.
+fn content_ref(result: &QueryResult) -> String {
+    let mut hasher = DefaultHasher::new();
+    for column in &result.columns {
+        column.name.hash(&mut hasher);
+        column.sql_type.hash(&mut hasher);
+    }
+    for row in &result.rows {
+        for column in &result.columns {
+            row.get(&column.name)
+                .map(|v| v.to_display_string())
+                .hash(&mut hasher);
+        }
+    }
+    format!("{}{:016x}", RESULT_REF_PREFIX, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::SqlValue;
+
+    fn sample_result(rows: usize) -> QueryResult {
+        let mut result = QueryResult::empty();
+        result.columns = vec![QueryColumnInfo {
+            name: "id".to_string(),
+            sql_type: "int".to_string(),
+            nullable: false,
+        }];
+        result.rows = (0..rows)
+            .map(|i| {
+                let mut row = ResultRow::new();
+                row.columns
+                    .insert("id".to_string(), SqlValue::I64(i as i64));
+                row
+            })
+            .collect();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_small_results_inline() {
+        let store = ResultStore::new(1024 * 1024, Duration::from_secs(60));
+        assert!(store.store_if_large(&sample_result(10)).await.is_none());
+
+        let disabled = ResultStore::new(0, Duration::from_secs(60));
+        assert!(disabled
+            .store_if_large(&sample_result(1000))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_store_and_fetch_chunk() {
+        let store = ResultStore::new(64, Duration::from_secs(60));
+        let result = sample_result(250);
+
+        let reference = store.store_if_large(&result).await.unwrap();
+        assert!(reference.result_ref.starts_with(RESULT_REF_PREFIX));
+        assert_eq!(reference.row_count, 250);
+        assert_eq!(reference.preview.len(), RESULT_REF_PREVIEW_ROWS);
+
+        // Identical content yields the same reference
+        let again = store.store_if_large(&result).await.unwrap();
+        assert_eq!(again.result_ref, reference.result_ref);
+        assert_eq!(store.usage().await.0, 1);
+
+        let (chunk, total) = store
+            .fetch_chunk(&reference.result_ref, 200, 100)
+            .await
+            .unwrap();
+        assert_eq!(total, 250);
+        assert_eq!(chunk.rows.len(), 50);
+        assert!(!chunk.truncated);
+        assert_eq!(chunk.rows[0].get("id").unwrap().to_display_string(), "200");
+
+        assert!(store.fetch_chunk("res_missing", 0, 10).await.is_err());
+    }
+
+    #[test]
+    fn test_session_results_page_from_uri() {
+        let page = SessionResultsPage::from_uri("mssql://sessions/sess_1/results").unwrap();
+        assert_eq!(page.session_id, "sess_1");
+        assert_eq!((page.page, page.page_size), (1, DEFAULT_PAGE_SIZE));
+
+        let page =
+            SessionResultsPage::from_uri("mssql://sessions/sess_1/results?page=3&page_size=50")
+                .unwrap();
+        assert_eq!(page.offset(), 100);
+        assert_eq!(page.page_count(101), 3);
+        assert_eq!(
+            SessionResultsPage::uri("sess_1", 4, 50),
+            "mssql://sessions/sess_1/results?page=4&page_size=50"
+        );
+
+        for uri in [
+            "mssql://sessions/sess_1/results?page=0",
+            "mssql://sessions/sess_1/results?page=x",
+            "mssql://sessions//results",
+            "mssql://sessions/sess_1",
+        ] {
+            assert!(SessionResultsPage::from_uri(uri).is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_slice_rows() {
+        let result = sample_result(10);
+        let slice = slice_rows(&result, 8, 5);
+        assert_eq!(slice.rows.len(), 2);
+        assert!(!slice.truncated);
+        assert!(slice_rows(&result, 0, 5).truncated);
+        assert!(slice_rows(&result, 20, 5).rows.is_empty());
+    }
+}
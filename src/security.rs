@@ -1,12 +1,26 @@
 //! Security module for query validation and SQL injection prevention.
 
+pub mod compatibility;
+pub mod concurrency;
+pub mod confirmation;
+pub mod governor;
 mod identifiers;
 mod injection;
+pub mod lint;
+pub mod masking;
+pub mod procedures;
+pub mod rate_limit;
+pub mod registry;
 mod validation;
 
+pub use compatibility::{check_compatibility, CompatibilityIssue};
+pub use concurrency::ConcurrencyLimiter;
+pub use confirmation::ConfirmationStore;
 pub use identifiers::{
     escape_identifier, is_reserved_keyword, parse_qualified_name, safe_identifier,
     validate_identifier, validate_not_reserved, warn_if_reserved,
 };
 pub use injection::InjectionDetector;
-pub use validation::{QueryValidator, ValidationMode, ValidationResult};
+pub use masking::DataMasker;
+pub use rate_limit::RateLimiter;
+pub use validation::{is_read_only, QueryValidator, ValidationMode, ValidationResult};
@@ -0,0 +1,357 @@
+//! Version-aware lint rules for newer T-SQL syntax.
+//!
+//! Queries are checked against the connected server's major version so that
+//! syntax introduced in newer releases (e.g. `GREATEST`, `JSON_OBJECT`,
+//! `IS DISTINCT FROM`) is flagged before execution instead of surfacing as a
+//! runtime syntax error.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+/// SQL Server 2016 (13.x).
+const SQL_2016: u32 = 13;
+/// SQL Server 2017 (14.x).
+const SQL_2017: u32 = 14;
+/// SQL Server 2022 (16.x).
+const SQL_2022: u32 = 16;
+
+/// Version reported for evergreen cloud engines (Azure SQL Database / Managed Instance).
+pub const LATEST_VERSION: u32 = u32::MAX;
+
+/// A feature that requires a minimum server version.
+struct FeatureRule {
+    /// Feature name as shown to users.
+    feature: &'static str,
+    /// Pattern matching the feature in query text (literals and comments removed).
+    pattern: Regex,
+    /// Minimum major version that supports the feature.
+    min_version: u32,
+    /// Suggested alternative for older servers.
+    suggestion: &'static str,
+}
+
+/// Version-gated feature rules.
+///
+/// All patterns are hardcoded constants that have been verified to be valid regex.
+static FEATURE_RULES: Lazy<Vec<FeatureRule>> = Lazy::new(|| {
+    // Helper to compile regex with better error message on failure.
+    fn rule(
+        feature: &'static str,
+        pattern: &str,
+        min_version: u32,
+        suggestion: &'static str,
+    ) -> FeatureRule {
+        FeatureRule {
+            feature,
+            pattern: Regex::new(pattern).unwrap_or_else(|e| {
+                panic!("Internal error: invalid regex pattern '{}': {}", pattern, e)
+            }),
+            min_version,
+            suggestion,
+        }
+    }
+
+    vec![
+        // SQL Server 2016
+        rule(
+            "JSON functions (JSON_VALUE, JSON_QUERY, OPENJSON, ISJSON)",
+            r"(?i)\b(JSON_VALUE|JSON_QUERY|JSON_MODIFY|OPENJSON|ISJSON)\s*\(",
+            SQL_2016,
+            "Parse JSON in the client, or store the values in relational columns",
+        ),
+        rule(
+            "FOR JSON",
+            r"(?i)\bFOR\s+JSON\b",
+            SQL_2016,
+            "Return rows and serialize to JSON in the client",
+        ),
+        rule(
+            "STRING_SPLIT",
+            r"(?i)\bSTRING_SPLIT\s*\(",
+            SQL_2016,
+            "Use an XML-based or numbers-table split",
+        ),
+        rule(
+            "DROP ... IF EXISTS",
+            r"(?i)\bDROP\s+\w+\s+IF\s+EXISTS\b",
+            SQL_2016,
+            "Check OBJECT_ID(...) IS NOT NULL before dropping",
+        ),
+        // SQL Server 2017
+        rule(
+            "STRING_AGG",
+            r"(?i)\bSTRING_AGG\s*\(",
+            SQL_2017,
+            "Use FOR XML PATH('') with STUFF to concatenate",
+        ),
+        rule(
+            "CONCAT_WS",
+            r"(?i)\bCONCAT_WS\s*\(",
+            SQL_2017,
+            "Use CONCAT with explicit separators",
+        ),
+        rule(
+            "TRIM",
+            r"(?i)\bTRIM\s*\(",
+            SQL_2017,
+            "Use LTRIM(RTRIM(...))",
+        ),
+        rule(
+            "TRANSLATE",
+            r"(?i)\bTRANSLATE\s*\(",
+            SQL_2017,
+            "Use nested REPLACE calls",
+        ),
+        // SQL Server 2022
+        rule(
+            "JSON_OBJECT / JSON_ARRAY",
+            r"(?i)\b(JSON_OBJECT|JSON_ARRAY)\s*\(",
+            SQL_2022,
+            "Use FOR JSON PATH to build JSON",
+        ),
+        rule(
+            "JSON_PATH_EXISTS",
+            r"(?i)\bJSON_PATH_EXISTS\s*\(",
+            SQL_2022,
+            "Use JSON_VALUE(...) IS NOT NULL or OPENJSON",
+        ),
+        rule(
+            "GREATEST / LEAST",
+            r"(?i)\b(GREATEST|LEAST)\s*\(",
+            SQL_2022,
+            "Use a CASE expression or SELECT MAX/MIN over a VALUES list",
+        ),
+        rule(
+            "DATE_BUCKET",
+            r"(?i)\bDATE_BUCKET\s*\(",
+            SQL_2022,
+            "Use DATEADD/DATEDIFF arithmetic to bucket dates",
+        ),
+        rule(
+            "DATETRUNC",
+            r"(?i)\bDATETRUNC\s*\(",
+            SQL_2022,
+            "Use DATEADD(unit, DATEDIFF(unit, 0, value), 0)",
+        ),
+        rule(
+            "GENERATE_SERIES",
+            r"(?i)\bGENERATE_SERIES\s*\(",
+            SQL_2022,
+            "Use a numbers table or recursive CTE",
+        ),
+        rule(
+            "IS [NOT] DISTINCT FROM",
+            r"(?i)\bIS\s+(NOT\s+)?DISTINCT\s+FROM\b",
+            SQL_2022,
+            "Compare with explicit NULL handling, e.g. (a = b OR (a IS NULL AND b IS NULL))",
+        ),
+        rule(
+            "APPROX_PERCENTILE_CONT / APPROX_PERCENTILE_DISC",
+            r"(?i)\bAPPROX_PERCENTILE_(CONT|DISC)\s*\(",
+            SQL_2022,
+            "Use PERCENTILE_CONT / PERCENTILE_DISC with OVER ()",
+        ),
+        rule(
+            "Ledger tables",
+            r"(?i)\bLEDGER\s*=\s*ON\b",
+            SQL_2022,
+            "Ledger tables are not available; use temporal tables or triggers for auditing",
+        ),
+    ]
+});
+
+/// A feature used by a query that the server does not support.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatibilityIssue {
+    /// Feature name.
+    pub feature: &'static str,
+    /// Minimum SQL Server release that supports the feature.
+    pub requires: String,
+    /// Suggested alternative.
+    pub suggestion: &'static str,
+}
+
+/// Determine the effective major version for compatibility checks.
+///
+/// Azure SQL Database (engine edition 5) and Managed Instance (engine edition 8)
+/// are evergreen and support all features, so they report [`LATEST_VERSION`].
+pub fn effective_major_version(product_version: &str, engine_edition: i32) -> Option<u32> {
+    if matches!(engine_edition, 5 | 8) {
+        return Some(LATEST_VERSION);
+    }
+    product_version.split('.').next()?.trim().parse().ok()
+}
+
+/// Get the release name for a major version.
+pub fn release_name(major_version: u32) -> String {
+    match major_version {
+        10 => "SQL Server 2008".to_string(),
+        11 => "SQL Server 2012".to_string(),
+        12 => "SQL Server 2014".to_string(),
+        13 => "SQL Server 2016".to_string(),
+        14 => "SQL Server 2017".to_string(),
+        15 => "SQL Server 2019".to_string(),
+        16 => "SQL Server 2022".to_string(),
+        LATEST_VERSION => "Azure SQL".to_string(),
+        v => format!("SQL Server version {}", v),
+    }
+}
+
+/// Check a query for features the server version does not support.
+pub fn check_compatibility(query: &str, major_version: u32) -> Vec<CompatibilityIssue> {
+    let code = strip_literals_and_comments(query);
+
+    FEATURE_RULES
+        .iter()
+        .filter(|rule| major_version < rule.min_version && rule.pattern.is_match(&code))
+        .map(|rule| CompatibilityIssue {
+            feature: rule.feature,
+            requires: format!(
+                "{} ({}.x)",
+                release_name(rule.min_version),
+                rule.min_version
+            ),
+            suggestion: rule.suggestion,
+        })
+        .collect()
+}
+
+/// Format compatibility issues as a validation message.
+pub fn format_issues(issues: &[CompatibilityIssue], major_version: u32) -> String {
+    let mut message = format!(
+        "Query uses features not supported by this server ({}):",
+        release_name(major_version)
+    );
+    for issue in issues {
+        message.push_str(&format!(
+            "\n- {} requires {}. {}",
+            issue.feature, issue.requires, issue.suggestion
+        ));
+    }
+    message
+}
+
+/// Blank out string literals, quoted identifiers and comments so rules only
+/// match actual code.
+///
+/// Line breaks inside them are kept so matches can be mapped back to lines.
+/// Literals become `''`, except numeric ones (`'0'`) and patterns with a
+/// leading wildcard (`'%'`), which some lint rules look for.
+pub(super) fn strip_literals_and_comments(query: &str) -> String {
+    let mut output = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    let newlines = |text: &str| "\n".repeat(text.matches('\n').count());
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // String literal ('' is an escaped quote)
+                let mut literal = String::new();
+                while let Some(n) = chars.next() {
+                    if n == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    literal.push(n);
+                }
+                let trimmed = literal.trim();
+                if !trimmed.is_empty() && trimmed.parse::<f64>().is_ok() {
+                    output.push_str("'0'");
+                } else if literal.starts_with(['%', '_']) {
+                    output.push_str("'%'");
+                } else {
+                    output.push_str("''");
+                }
+                output.push_str(&newlines(&literal));
+            }
+            '[' => {
+                // Quoted identifier (]] is an escaped bracket)
+                let mut identifier = String::new();
+                while let Some(n) = chars.next() {
+                    if n == ']' {
+                        if chars.peek() == Some(&']') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    identifier.push(n);
+                }
+                output.push_str("[x]");
+                output.push_str(&newlines(&identifier));
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        break;
+                    }
+                }
+                output.push('\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                output.push(' ');
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    if n == '\n' {
+                        output.push('\n');
+                    }
+                    prev = n;
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sql_2022_features_flagged_on_2019() {
+        let issues = check_compatibility("SELECT GREATEST(a, b) FROM t", 15);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].feature, "GREATEST / LEAST");
+        assert_eq!(issues[0].requires, "SQL Server 2022 (16.x)");
+
+        let issues = check_compatibility(
+            "SELECT JSON_OBJECT('id': id), DATE_BUCKET(DAY, 1, d) FROM t \
+             WHERE a IS NOT DISTINCT FROM b",
+            15,
+        );
+        assert_eq!(issues.len(), 3);
+    }
+
+    #[test]
+    fn test_supported_features_not_flagged() {
+        assert!(check_compatibility("SELECT GREATEST(a, b) FROM t", 16).is_empty());
+        assert!(check_compatibility("SELECT STRING_AGG(name, ',') FROM t", 14).is_empty());
+        assert!(check_compatibility("SELECT LEAST(a, b)", LATEST_VERSION).is_empty());
+    }
+
+    #[test]
+    fn test_literals_and_comments_ignored() {
+        let query = "SELECT 'GREATEST(1, 2)', [LEAST] -- DATE_BUCKET(\n/* JSON_OBJECT( */ FROM t";
+        assert!(check_compatibility(query, 13).is_empty());
+    }
+
+    #[test]
+    fn test_effective_major_version() {
+        assert_eq!(effective_major_version("15.0.4312.2", 3), Some(15));
+        assert_eq!(
+            effective_major_version("12.0.2000.8", 5),
+            Some(LATEST_VERSION)
+        );
+        assert_eq!(effective_major_version("", 3), None);
+    }
+}
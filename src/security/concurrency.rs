@@ -0,0 +1,284 @@
+//! Concurrency limits for tool calls.
+//!
+//! Operators can cap how many calls of an expensive tool run at once
+//! (`MSSQL_TOOL_CONCURRENCY=bulk_insert=2,execute_query=4`) and how many calls
+//! run against one database (`MSSQL_DATABASE_CONCURRENCY=Sales=4`). A call over
+//! a limit queues for a free slot; a call still waiting after the queue
+//! timeout is rejected instead of piling more work onto the server.
+//!
+//! A call counts against the database named by its `database` argument, or
+//! else the client's current database.
+
+use crate::config::ConcurrencyConfig;
+use crate::error::ServerError;
+use crate::state::SharedState;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Slots of one tool or database.
+struct Slots {
+    /// Name as configured.
+    name: String,
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    /// Calls currently waiting for a slot.
+    waiting: AtomicUsize,
+    /// Calls that had to wait for a slot.
+    queued: AtomicU64,
+    /// Calls rejected after waiting for the queue timeout.
+    timed_out: AtomicU64,
+    /// Total time calls waited for a slot, in milliseconds.
+    wait_ms: AtomicU64,
+}
+
+impl Slots {
+    fn new(name: &str, limit: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            waiting: AtomicUsize::new(0),
+            queued: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+            wait_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a slot, waiting until `deadline` if none is free.
+    async fn acquire(
+        &self,
+        scope: &str,
+        deadline: Instant,
+    ) -> Result<OwnedSemaphorePermit, ServerError> {
+        if let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        let start = Instant::now();
+        let permit =
+            tokio::time::timeout_at(deadline.into(), Arc::clone(&self.semaphore).acquire_owned())
+                .await;
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        let waited = start.elapsed();
+        self.wait_ms
+            .fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+
+        match permit {
+            Ok(Ok(permit)) => Ok(permit),
+            // The semaphore is never closed; treat it like a timeout all the same
+            Ok(Err(_)) | Err(_) => {
+                self.timed_out.fetch_add(1, Ordering::Relaxed);
+                Err(ServerError::concurrency_limited(
+                    format!("{} '{}'", scope, self.name),
+                    self.limit,
+                    waited.as_secs(),
+                ))
+            }
+        }
+    }
+
+    fn stats(&self) -> SlotStats {
+        SlotStats {
+            limit: self.limit,
+            running: self.limit - self.semaphore.available_permits(),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+            wait_ms_total: self.wait_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Usage of the slots of one tool or database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlotStats {
+    pub limit: usize,
+    /// Calls holding a slot.
+    pub running: usize,
+    /// Calls waiting for a slot.
+    pub waiting: usize,
+    /// Calls that had to wait for a slot.
+    pub queued: u64,
+    /// Calls rejected after the queue timeout.
+    pub timed_out: u64,
+    /// Total time calls waited for a slot, in milliseconds.
+    pub wait_ms_total: u64,
+}
+
+/// Usage of all concurrency limits, for `get_internal_metrics`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConcurrencyStats {
+    pub queue_timeout_seconds: u64,
+    pub tools: BTreeMap<String, SlotStats>,
+    pub databases: BTreeMap<String, SlotStats>,
+}
+
+/// Slots held by a call; released when dropped.
+#[derive(Debug, Default)]
+pub struct ConcurrencyPermit {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+/// Enforces the per-tool and per-database concurrency limits.
+pub struct ConcurrencyLimiter {
+    tools: HashMap<String, Slots>,
+    /// Keyed by lowercase name, since database names are case-insensitive.
+    databases: HashMap<String, Slots>,
+    queue_timeout: Duration,
+    /// Database of calls whose client has not switched databases.
+    default_database: Option<String>,
+    state: SharedState,
+}
+
+impl ConcurrencyLimiter {
+    /// Create a limiter; `state` provides the current database of each client.
+    pub fn new(
+        config: &ConcurrencyConfig,
+        default_database: Option<String>,
+        state: SharedState,
+    ) -> Self {
+        Self {
+            tools: config
+                .tools
+                .iter()
+                .map(|(name, limit)| (name.clone(), Slots::new(name, *limit)))
+                .collect(),
+            databases: config
+                .databases
+                .iter()
+                .map(|(name, limit)| (name.to_lowercase(), Slots::new(name, *limit)))
+                .collect(),
+            queue_timeout: config.queue_timeout,
+            default_database,
+            state,
+        }
+    }
+
+    /// Whether any limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.tools.is_empty() || !self.databases.is_empty()
+    }
+
+    /// Take the slots a call of `tool` needs, waiting up to the queue timeout.
+    ///
+    /// `database` is the call's `database` argument; without one, the call
+    /// counts against the current client's database.
+    pub async fn acquire(
+        &self,
+        tool: &str,
+        database: Option<&str>,
+    ) -> Result<ConcurrencyPermit, ServerError> {
+        let deadline = Instant::now() + self.queue_timeout;
+        let mut permits = Vec::new();
+        if let Some(slots) = self.tools.get(tool) {
+            permits.push(slots.acquire("tool", deadline).await?);
+        }
+
+        if !self.databases.is_empty() {
+            let database = match database {
+                Some(database) => Some(database.to_string()),
+                None => self
+                    .state
+                    .read()
+                    .await
+                    .current_database()
+                    .map(str::to_string)
+                    .or_else(|| self.default_database.clone()),
+            };
+            let slots = database.and_then(|db| self.databases.get(&db.to_lowercase()));
+            if let Some(slots) = slots {
+                permits.push(slots.acquire("database", deadline).await?);
+            }
+        }
+        Ok(ConcurrencyPermit { _permits: permits })
+    }
+
+    /// Current usage of every limit.
+    pub fn stats(&self) -> ConcurrencyStats {
+        let stats = |slots: &HashMap<String, Slots>| {
+            slots
+                .values()
+                .map(|s| (s.name.clone(), s.stats()))
+                .collect()
+        };
+        ConcurrencyStats {
+            queue_timeout_seconds: self.queue_timeout.as_secs(),
+            tools: stats(&self.tools),
+            databases: stats(&self.databases),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::new_shared_state;
+
+    fn limiter(queue_timeout: Duration) -> ConcurrencyLimiter {
+        let config = ConcurrencyConfig {
+            tools: HashMap::from([("bulk_insert".to_string(), 1)]),
+            databases: HashMap::from([("Sales".to_string(), 2)]),
+            queue_timeout,
+        };
+        ConcurrencyLimiter::new(&config, Some("sales".to_string()), new_shared_state())
+    }
+
+    #[tokio::test]
+    async fn test_tool_limit_queues_and_times_out() {
+        let limiter = limiter(Duration::from_millis(50));
+        let first = limiter.acquire("bulk_insert", Some("Other")).await.unwrap();
+
+        let error = limiter
+            .acquire("bulk_insert", Some("Other"))
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("tool 'bulk_insert': at most 1 concurrent calls"));
+
+        // A queued call gets the slot once it is released
+        let limiter = Arc::new(limiter);
+        let waiter = tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            async move { limiter.acquire("bulk_insert", Some("Other")).await.is_ok() }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(first);
+        assert!(waiter.await.unwrap());
+
+        let stats = limiter.stats().tools["bulk_insert"].clone();
+        assert_eq!((stats.running, stats.waiting), (0, 0));
+        assert_eq!((stats.queued, stats.timed_out), (2, 1));
+        // Unlimited tools take no slot
+        assert!(limiter
+            .acquire("execute_query", Some("Other"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_database_limit() {
+        let limiter = limiter(Duration::from_millis(10));
+        // Without a database argument, the configured database counts
+        let _first = limiter.acquire("execute_query", None).await.unwrap();
+        let _second = limiter.acquire("list_tables", Some("SALES")).await.unwrap();
+        assert!(limiter
+            .acquire("execute_query", Some("sales"))
+            .await
+            .is_err());
+        assert!(limiter
+            .acquire("execute_query", Some("Other"))
+            .await
+            .is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.databases["Sales"].running, 2);
+        assert_eq!(stats.tools["bulk_insert"].running, 0);
+    }
+}
@@ -0,0 +1,855 @@
+//! Two-step confirmation for destructive statements.
+//!
+//! When `MSSQL_REQUIRE_CONFIRMATION` is enabled, queries containing an
+//! `UPDATE` or `DELETE` without a `WHERE` clause, a `TRUNCATE TABLE`, or a
+//! `DROP` are not executed directly. The calling tool returns a preview with
+//! estimated affected rows and a confirmation token instead; passing the token
+//! to `confirm_execution` runs the original call.
+//!
+//! Detection works on the query text with literals and comments blanked out,
+//! so keywords inside strings never trigger a confirmation. Statements inside
+//! procedure, function, trigger and view definitions are not flagged, since
+//! creating a module does not run its body.
+//!
+//! The same parser backs `estimate_impact`, which rewrites a single `UPDATE`
+//! or `DELETE` into `SELECT` queries counting and previewing the rows it
+//! would change.
+
+use crate::error::ServerError;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Kind of destructive statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DestructiveKind {
+    UpdateWithoutWhere,
+    DeleteWithoutWhere,
+    Truncate,
+    Drop,
+}
+
+/// A destructive statement found in a query.
+#[derive(Debug, Clone, Serialize)]
+pub struct DestructiveStatement {
+    /// Kind of statement.
+    pub kind: DestructiveKind,
+    /// Statement text.
+    pub statement: String,
+    /// Affected object (table, or dropped object), if it could be determined.
+    pub target: Option<String>,
+    /// Dropped object type (`TABLE`, `VIEW`, `COLUMN`, ...), for drops.
+    pub object_type: Option<String>,
+    /// Query estimating the affected rows, if rows are affected.
+    #[serde(skip)]
+    pub estimate_query: Option<String>,
+}
+
+/// Keywords that start a new statement.
+const STATEMENT_STARTS: &[&str] = &[
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "MERGE",
+    "TRUNCATE",
+    "DROP",
+    "CREATE",
+    "ALTER",
+    "EXEC",
+    "EXECUTE",
+    "DECLARE",
+    "WITH",
+    "GO",
+    "PRINT",
+    "RETURN",
+    "BEGIN",
+    "END",
+    "COMMIT",
+    "ROLLBACK",
+    "IF",
+    "ELSE",
+    "WHILE",
+    "USE",
+    "GRANT",
+    "DENY",
+    "REVOKE",
+    "THROW",
+    "RAISERROR",
+];
+
+/// Words after which `UPDATE`/`DELETE` are not statements (cascades,
+/// triggers, permissions, `MERGE` actions).
+const NON_STATEMENT_PREFIXES: &[&str] = &[
+    "ON", "FOR", "AFTER", "OF", "GRANT", "DENY", "REVOKE", "THEN", ",",
+];
+
+/// Module types whose bodies are not executed by `CREATE`/`ALTER`.
+const MODULE_TYPES: &[&str] = &["PROC", "PROCEDURE", "FUNCTION", "TRIGGER", "VIEW"];
+
+/// A word or punctuation token of the blanked query.
+#[derive(Debug)]
+struct Token {
+    /// Upper-cased text.
+    word: String,
+    /// Char range in the original query.
+    start: usize,
+    end: usize,
+    /// Parenthesis depth.
+    depth: usize,
+}
+
+/// Find destructive statements in a query.
+pub fn find_destructive_statements(query: &str) -> Vec<DestructiveStatement> {
+    let original: Vec<char> = query.chars().collect();
+    let tokens = tokenize(&blank_literals_and_comments(&original));
+
+    let mut found = Vec::new();
+    let mut in_module = false;
+    for (start, end) in statement_ranges(&tokens) {
+        let statement = &tokens[start..end];
+        // Statement text runs up to the next statement (or `;`)
+        let original = &original[..tokens.get(end).map_or(original.len(), |t| t.start)];
+        let first = statement[0].word.as_str();
+        let second = statement.get(1).map(|t| t.word.as_str());
+
+        match first {
+            "GO" => in_module = false,
+            "CREATE" | "ALTER" => {
+                let module = statement[1..]
+                    .iter()
+                    .take(3)
+                    .any(|t| MODULE_TYPES.contains(&t.word.as_str()));
+                if module {
+                    in_module = true;
+                } else if first == "ALTER" {
+                    found.extend(alter_drop(original, statement));
+                }
+            }
+            "UPDATE" | "DELETE" if !in_module => {
+                let has_where = statement.iter().any(|t| t.depth == 0 && t.word == "WHERE");
+                if !has_where {
+                    found.push(unfiltered_dml(original, statement));
+                }
+            }
+            "TRUNCATE" if second == Some("TABLE") => {
+                let target = statement.get(2).map(|t| text(original, t.start, t.end));
+                found.push(DestructiveStatement {
+                    kind: DestructiveKind::Truncate,
+                    statement: statement_text(original, statement),
+                    estimate_query: target.as_deref().map(table_rows_query),
+                    target,
+                    object_type: Some("TABLE".to_string()),
+                });
+            }
+            "DROP" if !in_module => found.push(drop_statement(original, statement)),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Split tokens into statements, returning token index ranges.
+fn statement_ranges(tokens: &[Token]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut case_depth = 0;
+    let mut current_first = String::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.depth > 0 {
+            continue;
+        }
+        let word = token.word.as_str();
+        if word == "CASE" {
+            case_depth += 1;
+            continue;
+        }
+        if case_depth > 0 && (word == "END" || word == "ELSE") {
+            if word == "END" {
+                case_depth -= 1;
+            }
+            continue;
+        }
+
+        let boundary = match word {
+            ";" => true,
+            "UPDATE" | "DELETE" => {
+                let prev = i.checked_sub(1).map(|p| tokens[p].word.as_str());
+                let is_call = tokens.get(i + 1).is_some_and(|t| t.word == "(");
+                !is_call && !prev.is_some_and(|p| NON_STATEMENT_PREFIXES.contains(&p))
+            }
+            // ALTER TABLE ... DROP COLUMN belongs to the ALTER statement
+            "DROP" => current_first != "ALTER",
+            // DROP TABLE IF EXISTS t, ALTER TABLE t DROP COLUMN IF EXISTS c
+            "IF" => {
+                let after_drop = i.checked_sub(2).is_some_and(|p| tokens[p].word == "DROP");
+                !(after_drop && tokens.get(i + 1).is_some_and(|t| t.word == "EXISTS"))
+            }
+            // Table hints: DELETE FROM t WITH (TABLOCK)
+            "WITH" => tokens.get(i + 1).is_none_or(|t| t.word != "("),
+            _ => STATEMENT_STARTS.contains(&word),
+        };
+        if !boundary {
+            continue;
+        }
+
+        if let Some(s) = start.take() {
+            ranges.push((s, i));
+        }
+        if word != ";" {
+            start = Some(i);
+            current_first = word.to_string();
+        } else {
+            current_first.clear();
+        }
+        case_depth = 0;
+    }
+    if let Some(s) = start {
+        ranges.push((s, tokens.len()));
+    }
+    ranges
+}
+
+/// Build the finding for an `UPDATE` or `DELETE` without `WHERE`.
+fn unfiltered_dml(original: &[char], statement: &[Token]) -> DestructiveStatement {
+    let is_update = statement[0].word == "UPDATE";
+
+    // Skip TOP (n) and, for DELETE, the optional FROM before the target
+    let mut i = 1;
+    if statement.get(i).is_some_and(|t| t.word == "TOP") {
+        i += 1;
+        while statement
+            .get(i)
+            .is_some_and(|t| t.depth > 0 || t.word == "(")
+        {
+            i += 1;
+        }
+    }
+    if !is_update && statement.get(i).is_some_and(|t| t.word == "FROM") {
+        i += 1;
+    }
+    let target = statement.get(i).map(|t| text(original, t.start, t.end));
+
+    // A FROM clause (joins, or the alias form) defines the affected rows
+    let from = statement
+        .iter()
+        .skip(i + 1)
+        .find(|t| t.depth == 0 && t.word == "FROM");
+    let estimate_query = match from {
+        Some(from) => Some(format!(
+            "SELECT COUNT_BIG(*) AS estimated_rows FROM {}",
+            text(original, from.end, original.len()).trim()
+        )),
+        None => target.as_deref().map(table_rows_query),
+    };
+
+    DestructiveStatement {
+        kind: if is_update {
+            DestructiveKind::UpdateWithoutWhere
+        } else {
+            DestructiveKind::DeleteWithoutWhere
+        },
+        statement: statement_text(original, statement),
+        target,
+        object_type: None,
+        estimate_query,
+    }
+}
+
+/// Build the finding for a `DROP` statement.
+fn drop_statement(original: &[char], statement: &[Token]) -> DestructiveStatement {
+    let object_type = statement.get(1).map(|t| t.word.clone());
+    let mut i = 2;
+    if statement.get(i).is_some_and(|t| t.word == "IF")
+        && statement.get(i + 1).is_some_and(|t| t.word == "EXISTS")
+    {
+        i += 2;
+    }
+    let target = statement.get(i).map(|t| text(original, t.start, t.end));
+    let estimate_query = match object_type.as_deref() {
+        Some("TABLE") => target.as_deref().map(table_rows_query),
+        _ => None,
+    };
+    DestructiveStatement {
+        kind: DestructiveKind::Drop,
+        statement: statement_text(original, statement),
+        target,
+        object_type,
+        estimate_query,
+    }
+}
+
+/// Find `ALTER TABLE t DROP COLUMN ...` in an `ALTER` statement.
+fn alter_drop(original: &[char], statement: &[Token]) -> Option<DestructiveStatement> {
+    if statement.get(1).map(|t| t.word.as_str()) != Some("TABLE") {
+        return None;
+    }
+    let drop = statement
+        .iter()
+        .position(|t| t.depth == 0 && t.word == "DROP")?;
+    let column = statement.get(drop + 1).is_some_and(|t| t.word == "COLUMN");
+    if !column {
+        return None;
+    }
+    let table = statement.get(2).map(|t| text(original, t.start, t.end));
+    Some(DestructiveStatement {
+        kind: DestructiveKind::Drop,
+        statement: statement_text(original, statement),
+        estimate_query: table.as_deref().map(table_rows_query),
+        target: table,
+        object_type: Some("COLUMN".to_string()),
+    })
+}
+
+/// Row count of a table from partition metadata (no scan, NULL if missing).
+fn table_rows_query(table: &str) -> String {
+    let name = if table.starts_with('#') {
+        format!("tempdb..{}", table)
+    } else {
+        table.to_string()
+    };
+    format!(
+        "SELECT SUM(p.rows) AS estimated_rows FROM sys.partitions p \
+         WHERE p.object_id = OBJECT_ID(N'{}') AND p.index_id IN (0, 1)",
+        name.replace('\'', "''")
+    )
+}
+
+/// Text of a statement, given the query truncated at the statement's end.
+fn statement_text(original: &[char], statement: &[Token]) -> String {
+    text(original, statement[0].start, original.len())
+        .trim()
+        .to_string()
+}
+
+fn text(original: &[char], start: usize, end: usize) -> String {
+    original[start..end].iter().collect()
+}
+
+/// Split blanked query text into words, dotted names and punctuation.
+fn tokenize(blanked: &[char]) -> Vec<Token> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || "_@#$.[]\"".contains(c);
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < blanked.len() {
+        let c = blanked[i];
+        if is_word(c) {
+            let start = i;
+            while i < blanked.len() && is_word(blanked[i]) {
+                i += 1;
+            }
+            tokens.push(Token {
+                word: blanked[start..i].iter().collect::<String>().to_uppercase(),
+                start,
+                end: i,
+                depth,
+            });
+            continue;
+        }
+        match c {
+            '(' => {
+                tokens.push(Token {
+                    word: "(".to_string(),
+                    start: i,
+                    end: i + 1,
+                    depth,
+                });
+                depth += 1;
+            }
+            ')' => depth = depth.saturating_sub(1),
+            ';' | ',' => tokens.push(Token {
+                word: c.to_string(),
+                start: i,
+                end: i + 1,
+                depth,
+            }),
+            _ => {}
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// Blank out string literal contents and comments, and mask quoted
+/// identifiers, keeping one output char per input char.
+fn blank_literals_and_comments(query: &[char]) -> Vec<char> {
+    let mut output = Vec::with_capacity(query.len());
+    let mut i = 0;
+    let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+
+    while i < query.len() {
+        let c = query[i];
+        match c {
+            '\'' | '[' | '"' => {
+                let close = if c == '[' { ']' } else { c };
+                let fill = if c == '\'' { ' ' } else { 'x' };
+                output.push(c);
+                i += 1;
+                while i < query.len() {
+                    if query[i] == close {
+                        // Doubled closing char is an escape
+                        if query.get(i + 1) == Some(&close) {
+                            output.extend([fill, fill]);
+                            i += 2;
+                            continue;
+                        }
+                        output.push(close);
+                        i += 1;
+                        break;
+                    }
+                    output.push(fill);
+                    i += 1;
+                }
+            }
+            '-' if query.get(i + 1) == Some(&'-') => {
+                while i < query.len() && query[i] != '\n' {
+                    output.push(' ');
+                    i += 1;
+                }
+            }
+            '/' if query.get(i + 1) == Some(&'*') => {
+                output.extend([' ', ' ']);
+                i += 2;
+                while i < query.len() && !(query[i] == '*' && query.get(i + 1) == Some(&'/')) {
+                    output.push(blank(query[i]));
+                    i += 1;
+                }
+                let rest = (query.len() - i).min(2);
+                output.extend(std::iter::repeat_n(' ', rest));
+                i += rest;
+            }
+            c if !c.is_ascii() => {
+                output.push('x');
+                i += 1;
+            }
+            c => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Read-only queries describing the impact of an `UPDATE` or `DELETE`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactPlan {
+    /// Statement kind (`update` or `delete`).
+    pub kind: &'static str,
+    /// Target table or alias.
+    pub target: String,
+    /// Row limit of `UPDATE TOP (n)` / `DELETE TOP (n)`, if given.
+    pub top: Option<String>,
+    /// Whether the statement has a `WHERE` clause.
+    pub filtered: bool,
+    /// Query counting the affected rows.
+    pub count_query: String,
+    /// Query returning a sample of affected rows (with new values for updates).
+    pub sample_query: String,
+}
+
+/// Rewrite a single `UPDATE` or `DELETE` into read-only impact queries.
+pub fn plan_impact(query: &str, sample_size: usize) -> Result<ImpactPlan, String> {
+    let original: Vec<char> = query.chars().collect();
+    let blanked = blank_literals_and_comments(&original);
+    let tokens = tokenize(&blanked);
+
+    let ranges = statement_ranges(&tokens);
+    let [(start, end)] = ranges.as_slice() else {
+        return Err("Expected exactly one UPDATE or DELETE statement".to_string());
+    };
+    let statement = &tokens[*start..*end];
+    let is_update = match statement[0].word.as_str() {
+        "UPDATE" => true,
+        "DELETE" => false,
+        "WITH" => return Err("Statements with common table expressions are not supported".into()),
+        other => {
+            return Err(format!(
+                "Expected an UPDATE or DELETE statement, found {}",
+                other
+            ))
+        }
+    };
+
+    // DELETE [TOP (n)] [FROM] target / UPDATE [TOP (n)] target
+    let mut i = 1;
+    let mut top = None;
+    if statement.get(i).is_some_and(|t| t.word == "TOP") {
+        let open = i + 1;
+        i += 1;
+        while statement
+            .get(i)
+            .is_some_and(|t| t.depth > 0 || t.word == "(")
+        {
+            i += 1;
+        }
+        top = statement
+            .get(open + 1)
+            .map(|t| text(&original, t.start, t.end));
+    }
+    if !is_update && statement.get(i).is_some_and(|t| t.word == "FROM") {
+        i += 1;
+    }
+    let target_token = statement
+        .get(i)
+        .ok_or_else(|| "Statement has no target table".to_string())?;
+    let target = text(&original, target_token.start, target_token.end);
+
+    // Depth-0 clause keywords after the target
+    let clause = |word: &str| {
+        statement[i + 1..]
+            .iter()
+            .find(|t| t.depth == 0 && t.word == word)
+    };
+    let set = if is_update {
+        Some(clause("SET").ok_or_else(|| "UPDATE statement has no SET clause".to_string())?)
+    } else {
+        None
+    };
+    let output = clause("OUTPUT");
+    let from = clause("FROM");
+    let filter = clause("WHERE");
+    let option = clause("OPTION");
+    if filter.is_some_and(|w| {
+        statement
+            .iter()
+            .any(|t| t.start > w.start && t.depth == 0 && t.word == "CURRENT")
+    }) {
+        return Err("WHERE CURRENT OF cursor statements are not supported".to_string());
+    }
+
+    let statement_end = tokens.get(*end).map_or(original.len(), |t| t.start);
+    // Each clause runs until the next clause keyword that follows it
+    let clause_end = |after: usize| {
+        [output, from, filter, option]
+            .into_iter()
+            .flatten()
+            .map(|t| t.start)
+            .filter(|&s| s > after)
+            .min()
+            .unwrap_or(statement_end)
+    };
+
+    let source = match from {
+        Some(from) => text(&original, from.end, clause_end(from.end))
+            .trim()
+            .to_string(),
+        None => target.clone(),
+    };
+    let where_clause = filter.map(|w| {
+        format!(
+            " WHERE {}",
+            text(&original, w.end, clause_end(w.end)).trim()
+        )
+    });
+    let where_clause = where_clause.unwrap_or_default();
+
+    let mut columns = if from.is_some() {
+        format!("{}.*", target)
+    } else {
+        "*".to_string()
+    };
+    if let Some(set) = set {
+        for (column, value) in assignments(&original, &blanked, set.end, clause_end(set.end)) {
+            columns.push_str(&format!(
+                ", {} AS [new_{}]",
+                value,
+                column.replace(']', "]]")
+            ));
+        }
+    }
+
+    Ok(ImpactPlan {
+        kind: if is_update { "update" } else { "delete" },
+        target,
+        top,
+        filtered: filter.is_some(),
+        count_query: format!(
+            "SELECT COUNT_BIG(*) AS affected_rows FROM {}{}",
+            source, where_clause
+        ),
+        sample_query: format!(
+            "SELECT TOP ({}) {} FROM {}{}",
+            sample_size, columns, source, where_clause
+        ),
+    })
+}
+
+/// Split a `SET` clause into (column, new value expression) pairs.
+///
+/// Compound assignments (`+=`, `-=`, ...) are expanded; variable
+/// assignments are skipped.
+fn assignments(
+    original: &[char],
+    blanked: &[char],
+    start: usize,
+    end: usize,
+) -> Vec<(String, String)> {
+    // Split on top-level commas
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut part_start = start;
+    for (i, &c) in blanked.iter().enumerate().take(end).skip(start) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push((part_start, i));
+                part_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push((part_start, end));
+
+    parts
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let eq = (a..b).find(|&i| blanked[i] == '=')?;
+            let mut left = text(original, a, eq).trim().to_string();
+            let value = text(original, eq + 1, b).trim().to_string();
+            if left.starts_with('@') || value.is_empty() {
+                return None;
+            }
+            let value = match left.pop() {
+                Some(op) if "+-*/%&|^".contains(op) => {
+                    let column = left.trim_end().to_string();
+                    let expr = format!("({} {} ({}))", column, op, value);
+                    left = column;
+                    expr
+                }
+                Some(c) => {
+                    left.push(c);
+                    format!("({})", value)
+                }
+                None => return None,
+            };
+            // Name the new value after the column, without qualifiers
+            let column = left
+                .rsplit('.')
+                .next()
+                .unwrap_or(&left)
+                .trim_matches(|c| c == '[' || c == ']')
+                .to_string();
+            Some((column, value))
+        })
+        .collect()
+}
+
+/// A call held back until confirmed.
+#[derive(Debug, Clone)]
+pub struct PendingExecution {
+    /// Tool that was called.
+    pub tool: String,
+    /// Original tool input.
+    pub input: serde_json::Value,
+    /// Query text that required confirmation.
+    pub query: String,
+    /// Client context (transport session or client identity) that made the call.
+    pub client: String,
+    created_at: Instant,
+}
+
+/// Pending confirmations and approved queries.
+pub struct ConfirmationStore {
+    ttl: Duration,
+    pending: Mutex<HashMap<String, PendingExecution>>,
+    /// Confirmed (client, query) pairs, consumed by the next matching call
+    /// within the token lifetime.
+    approved: Mutex<Vec<(String, String, Instant)>>,
+}
+
+impl ConfirmationStore {
+    /// Create a store whose tokens expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: Mutex::new(HashMap::new()),
+            approved: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Token lifetime.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Hold back a call and return its confirmation token.
+    pub fn register(
+        &self,
+        tool: &str,
+        input: serde_json::Value,
+        query: &str,
+        client: &str,
+    ) -> String {
+        let token = format!("confirm_{}", uuid::Uuid::new_v4().simple());
+        let mut pending = self.pending.lock();
+        pending.retain(|_, p| p.created_at.elapsed() < self.ttl);
+        pending.insert(
+            token.clone(),
+            PendingExecution {
+                tool: tool.to_string(),
+                input,
+                query: query.to_string(),
+                client: client.to_string(),
+                created_at: Instant::now(),
+            },
+        );
+        token
+    }
+
+    /// Redeem a token, approving its query for one execution by the client.
+    ///
+    /// The caller re-runs the held back call and then calls
+    /// [`Self::take_approval`] to revoke the approval if that call did not
+    /// consume it.
+    pub fn confirm(&self, token: &str, client: &str) -> Result<PendingExecution, ServerError> {
+        let mut pending = self.pending.lock();
+        let valid = pending
+            .get(token)
+            .is_some_and(|p| p.created_at.elapsed() < self.ttl && p.client == client);
+        if !valid {
+            return Err(ServerError::invalid_input(format!(
+                "Confirmation token '{}' is unknown, expired, or belongs to another client",
+                token
+            )));
+        }
+        let execution = pending
+            .remove(token)
+            .ok_or_else(|| ServerError::internal("Confirmation token disappeared"))?;
+        let mut approved = self.approved.lock();
+        approved.retain(|(_, _, at)| at.elapsed() < self.ttl);
+        approved.push((
+            execution.client.clone(),
+            execution.query.clone(),
+            Instant::now(),
+        ));
+        Ok(execution)
+    }
+
+    /// Consume an approval for a query, if an unexpired one exists.
+    pub fn take_approval(&self, client: &str, query: &str) -> bool {
+        let mut approved = self.approved.lock();
+        approved.retain(|(_, _, at)| at.elapsed() < self.ttl);
+        match approved
+            .iter()
+            .position(|(c, q, _)| c == client && q == query)
+        {
+            Some(index) => {
+                approved.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(query: &str) -> Vec<DestructiveKind> {
+        find_destructive_statements(query)
+            .into_iter()
+            .map(|s| s.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_destructive_statements() {
+        assert!(kinds("UPDATE dbo.Orders SET Status = 1 WHERE Id = 5").is_empty());
+        assert!(kinds("DELETE FROM Orders WHERE Id IN (SELECT Id FROM Old)").is_empty());
+        assert!(kinds("SELECT 'DELETE FROM Orders' AS txt -- DROP TABLE x").is_empty());
+        assert!(kinds(
+            "CREATE TABLE t (Id INT REFERENCES p(Id) ON DELETE CASCADE ON UPDATE NO ACTION)"
+        )
+        .is_empty());
+        assert!(kinds("CREATE PROCEDURE p AS DELETE FROM Orders").is_empty());
+
+        let found = find_destructive_statements(
+            "UPDATE [dbo].[Orders] SET Flag = CASE WHEN x = 1 THEN 0 END; DELETE Orders",
+        );
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].kind, DestructiveKind::UpdateWithoutWhere);
+        assert_eq!(found[0].target.as_deref(), Some("[dbo].[Orders]"));
+        assert_eq!(found[1].kind, DestructiveKind::DeleteWithoutWhere);
+
+        let found = find_destructive_statements("DELETE o FROM Orders o JOIN Old x ON x.Id = o.Id");
+        assert_eq!(
+            found[0].estimate_query.as_deref(),
+            Some("SELECT COUNT_BIG(*) AS estimated_rows FROM Orders o JOIN Old x ON x.Id = o.Id")
+        );
+
+        assert_eq!(
+            kinds("TRUNCATE TABLE dbo.Log\nGO\nDROP TABLE IF EXISTS #tmp"),
+            vec![DestructiveKind::Truncate, DestructiveKind::Drop]
+        );
+        let found = find_destructive_statements("DROP TABLE IF EXISTS dbo.Old");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].target.as_deref(), Some("dbo.Old"));
+        assert!(found[0]
+            .estimate_query
+            .as_deref()
+            .is_some_and(|q| q.contains("OBJECT_ID(N'dbo.Old')")));
+        let found = find_destructive_statements("DROP TABLE t\nIF EXISTS (SELECT 1) PRINT 'x'");
+        assert_eq!(found[0].target.as_deref(), Some("t"));
+
+        let found = find_destructive_statements("ALTER TABLE dbo.T DROP COLUMN Notes");
+        assert_eq!(found[0].object_type.as_deref(), Some("COLUMN"));
+        assert_eq!(found[0].target.as_deref(), Some("dbo.T"));
+    }
+
+    #[test]
+    fn test_plan_impact() {
+        let plan = plan_impact(
+            "UPDATE TOP (50) o SET o.Total += 1, Status = 'x,y' \
+             FROM dbo.Orders o JOIN dbo.Customers c ON c.Id = o.CustomerId \
+             WHERE c.Region = 'EU';",
+            5,
+        )
+        .unwrap();
+        assert_eq!(plan.kind, "update");
+        assert_eq!(plan.top.as_deref(), Some("50"));
+        assert_eq!(
+            plan.count_query,
+            "SELECT COUNT_BIG(*) AS affected_rows FROM dbo.Orders o \
+             JOIN dbo.Customers c ON c.Id = o.CustomerId WHERE c.Region = 'EU'"
+        );
+        assert!(plan.sample_query.starts_with(
+            "SELECT TOP (5) o.*, (o.Total + (1)) AS [new_Total], ('x,y') AS [new_Status] FROM"
+        ));
+
+        let plan = plan_impact("DELETE FROM Log WHERE Created < '2020-01-01'", 10).unwrap();
+        assert_eq!(
+            plan.sample_query,
+            "SELECT TOP (10) * FROM Log WHERE Created < '2020-01-01'"
+        );
+
+        assert!(plan_impact("DELETE FROM a WHERE x = 1; DROP TABLE b", 10).is_err());
+        assert!(plan_impact("SELECT 1", 10).is_err());
+    }
+
+    #[test]
+    fn test_confirmation_tokens() {
+        let store = ConfirmationStore::new(Duration::from_secs(60));
+        let token = store.register("execute_query", serde_json::json!({}), "DELETE t", "a/1");
+
+        assert!(store.confirm(&token, "b/1").is_err());
+        assert!(!store.take_approval("a/1", "DELETE t"));
+        assert!(store.confirm(&token, "a/1").is_ok());
+        assert!(store.confirm(&token, "a/1").is_err());
+        assert!(store.take_approval("a/1", "DELETE t"));
+        assert!(!store.take_approval("a/1", "DELETE t"));
+
+        let store = ConfirmationStore::new(Duration::ZERO);
+        store
+            .approved
+            .lock()
+            .push(("a/1".to_string(), "DELETE t".to_string(), Instant::now()));
+        assert!(!store.take_approval("a/1", "DELETE t"));
+    }
+}
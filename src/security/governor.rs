@@ -0,0 +1,504 @@
+//! Per-query resource guardrails.
+//!
+//! The governor runs before `execute_query` and `execute_async` send a
+//! statement to the server. With `MSSQL_MAX_QUERY_COST` set, the estimated
+//! plan is fetched first and statements whose total estimated subtree cost
+//! exceeds the limit are rejected. `MSSQL_MAX_DOP` adds or lowers an
+//! `OPTION (MAXDOP n)` hint, and `MSSQL_ENFORCE_ROW_LIMIT` (constrained mode)
+//! injects `TOP` into SELECTs that have no row limit of their own, so the
+//! server stops producing rows instead of the client discarding them.
+//! `MSSQL_MAX_RESULT_ESTIMATE_MB` sizes the result of a SELECT from its
+//! described columns and the plan's row estimate, and rejects (or only warns
+//! about) results too large to return inline.
+//!
+//! Rewrites only apply to a single statement whose shape is known; anything
+//! else (multiple statements, `UNION`, `SELECT ... INTO`, `OFFSET ... FETCH`)
+//! is left as written and still subject to the cost limit.
+
+use crate::config::GovernorConfig;
+use crate::database::describe::DescribedColumn;
+
+/// A statement rewritten by the governor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GovernedQuery {
+    /// Query to execute.
+    pub query: String,
+    /// Descriptions of the rewrites applied, e.g. `added TOP (1001)`.
+    pub changes: Vec<String>,
+    /// Warnings to return with the result, e.g. about its estimated size.
+    pub warnings: Vec<String>,
+}
+
+/// Apply the MAXDOP and row limit rewrites of the governor.
+///
+/// `max_rows` is the row limit of the call; `TOP (max_rows + 1)` is injected
+/// so that truncated results are still reported as such.
+pub fn rewrite_query(query: &str, config: &GovernorConfig, max_rows: usize) -> GovernedQuery {
+    let mut governed = GovernedQuery {
+        query: query.to_string(),
+        changes: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if config.enforce_row_limit {
+        let limit = max_rows.saturating_add(1);
+        if let Some(rewritten) = inject_top(&governed.query, limit) {
+            governed.query = rewritten;
+            governed.changes.push(format!("added TOP ({})", limit));
+        }
+    }
+    if let Some(dop) = config.max_dop {
+        if let Some(rewritten) = limit_maxdop(&governed.query, dop) {
+            governed.query = rewritten;
+            governed.changes.push(format!("limited MAXDOP to {}", dop));
+        }
+    }
+    governed
+}
+
+/// Whether a query reads or modifies data, and so has a plan to cost.
+pub fn has_plan(query: &str) -> bool {
+    tokens(query)
+        .iter()
+        .any(|t| DML_KEYWORDS.iter().any(|k| t.is_word(k)))
+}
+
+/// Check the total estimated cost of a query against the limit.
+pub fn check_cost(estimated_cost: f64, max_cost: f64) -> Result<(), String> {
+    if estimated_cost > max_cost {
+        return Err(format!(
+            "Estimated query cost {:.2} exceeds the limit of {:.2} (MSSQL_MAX_QUERY_COST). \
+             Narrow the query with filters or a row limit, or use explain_query to see the expensive operators.",
+            estimated_cost, max_cost
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a query is a single SELECT returning rows, and so has a result to size.
+pub fn is_select(query: &str) -> bool {
+    let tokens = tokens(query);
+    if !is_single_statement(&tokens) {
+        return false;
+    }
+    let Some(first) = tokens.first() else {
+        return false;
+    };
+    (first.is_word("SELECT") || first.is_word("WITH"))
+        && !tokens.iter().any(|t| t.depth == 0 && t.is_word("INTO"))
+}
+
+/// Estimate the size in bytes of a result with the given columns and row count.
+///
+/// Values are counted at their declared size, MAX types at
+/// [`MAX_TYPE_ESTIMATE_BYTES`], plus the column name repeated in every row.
+pub fn estimate_result_bytes(columns: &[DescribedColumn], rows: f64) -> f64 {
+    let width: usize = columns
+        .iter()
+        .map(|c| {
+            let value = usize::try_from(c.max_length).unwrap_or(MAX_TYPE_ESTIMATE_BYTES);
+            value + c.name.as_deref().map_or(0, str::len) + FIELD_OVERHEAD_BYTES
+        })
+        .sum();
+    width as f64 * rows
+}
+
+/// Check the estimated size of a result against the limit.
+pub fn check_result_size(
+    estimated_bytes: f64,
+    estimated_rows: f64,
+    max_mb: f64,
+) -> Result<(), String> {
+    let estimated_mb = estimated_bytes / (1024.0 * 1024.0);
+    if estimated_mb > max_mb {
+        return Err(format!(
+            "Estimated result of {:.0} rows ({:.1} MB) exceeds the limit of {:.1} MB (MSSQL_MAX_RESULT_ESTIMATE_MB). \
+             Page through it with execute_paginated, or write it to a file with export_data.",
+            estimated_rows, estimated_mb, max_mb
+        ));
+    }
+    Ok(())
+}
+
+/// Bytes assumed for a value of a MAX type, whose declared size is unbounded.
+const MAX_TYPE_ESTIMATE_BYTES: usize = 1024;
+
+/// Bytes of quoting and separators around each value in the serialized result.
+const FIELD_OVERHEAD_BYTES: usize = 6;
+
+const DML_KEYWORDS: &[&str] = &["SELECT", "INSERT", "UPDATE", "DELETE", "MERGE"];
+
+/// Statements a CTE can feed instead of a SELECT.
+const WRITE_KEYWORDS: &[&str] = &["INSERT", "UPDATE", "DELETE", "MERGE"];
+
+/// Keywords after which a SELECT returns no rows or is already limited.
+const UNLIMITABLE: &[&str] = &["INTO", "UNION", "EXCEPT", "INTERSECT", "OFFSET"];
+
+// =============================================================================
+// Rewrites
+// =============================================================================
+
+/// Insert `TOP (limit)` into a single SELECT without a row limit.
+fn inject_top(query: &str, limit: usize) -> Option<String> {
+    let tokens = tokens(query);
+    if !is_single_statement(&tokens) {
+        return None;
+    }
+    let first = tokens.first()?;
+    if !first.is_word("SELECT") && !first.is_word("WITH") {
+        return None;
+    }
+    let top_level: Vec<&Token> = tokens.iter().filter(|t| t.depth == 0).collect();
+    if top_level.iter().any(|t| {
+        UNLIMITABLE
+            .iter()
+            .chain(WRITE_KEYWORDS)
+            .any(|k| t.is_word(k))
+    }) {
+        return None;
+    }
+
+    // With a CTE, the outer SELECT is the first one outside parentheses
+    let select = top_level.iter().position(|t| t.is_word("SELECT"))?;
+    let mut anchor = top_level[select];
+    if let Some(next) = top_level.get(select + 1) {
+        if next.is_word("TOP") {
+            return None;
+        }
+        if next.is_word("DISTINCT") || next.is_word("ALL") {
+            if top_level.get(select + 2).is_some_and(|t| t.is_word("TOP")) {
+                return None;
+            }
+            anchor = next;
+        }
+    }
+    let at = anchor.end();
+    Some(format!("{} TOP ({}){}", &query[..at], limit, &query[at..]))
+}
+
+/// Add `OPTION (MAXDOP dop)` to a single statement, or lower a higher hint.
+fn limit_maxdop(query: &str, dop: u32) -> Option<String> {
+    let tokens = tokens(query);
+    if !is_single_statement(&tokens) {
+        return None;
+    }
+    let first = tokens.first()?;
+    let top_level: Vec<&Token> = tokens.iter().filter(|t| t.depth == 0).collect();
+    let applies = match first.text.to_uppercase().as_str() {
+        "SELECT" | "WITH" | "UPDATE" | "DELETE" | "MERGE" => true,
+        // INSERT ... VALUES has no plan worth parallelizing
+        "INSERT" => top_level.iter().any(|t| t.is_word("SELECT")),
+        _ => false,
+    };
+    if !applies {
+        return None;
+    }
+
+    let option = top_level
+        .windows(2)
+        .find(|pair| pair[0].is_word("OPTION") && pair[1].text == "(")
+        .map(|pair| pair[1]);
+    match option {
+        Some(open) => {
+            let hints: Vec<&Token> = tokens
+                .iter()
+                .skip_while(|t| t.start <= open.start)
+                .take_while(|t| t.depth > 0)
+                .filter(|t| t.depth == 1)
+                .collect();
+            match hints.windows(2).find(|pair| pair[0].is_word("MAXDOP")) {
+                Some(pair) => {
+                    let current: u32 = pair[1].text.parse().ok()?;
+                    if current != 0 && current <= dop {
+                        return None;
+                    }
+                    let value = pair[1];
+                    Some(format!(
+                        "{}{}{}",
+                        &query[..value.start],
+                        dop,
+                        &query[value.end()..]
+                    ))
+                }
+                None => {
+                    let at = open.end();
+                    Some(format!("{}MAXDOP {}, {}", &query[..at], dop, &query[at..]))
+                }
+            }
+        }
+        None => {
+            // Before a trailing semicolon, which MERGE requires
+            let last = tokens.iter().rev().find(|t| t.text != ";")?;
+            let at = last.end();
+            Some(format!(
+                "{}\nOPTION (MAXDOP {}){}",
+                &query[..at],
+                dop,
+                &query[at..]
+            ))
+        }
+    }
+}
+
+/// Whether a query is one statement, allowing a trailing semicolon.
+fn is_single_statement(tokens: &[Token]) -> bool {
+    let mut statements = tokens.split(|t| t.depth == 0 && t.text == ";");
+    statements.next().is_some_and(|s| !s.is_empty()) && statements.all(|s| s.is_empty())
+}
+
+// =============================================================================
+// Tokens
+// =============================================================================
+
+/// A word, identifier or symbol outside literals and comments.
+#[derive(Debug)]
+pub(crate) struct Token<'a> {
+    pub start: usize,
+    pub text: &'a str,
+    /// Parenthesis depth; an opening and its closing parenthesis share the
+    /// depth of their surroundings.
+    pub depth: usize,
+}
+
+impl Token<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    pub fn is_word(&self, keyword: &str) -> bool {
+        self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+pub(crate) fn tokens(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let mut nesting = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        nesting += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        nesting -= 1;
+                        i += 2;
+                        if nesting == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                continue;
+            }
+            quote @ (b'\'' | b'"' | b'[') => {
+                let close = if quote == b'[' { b']' } else { quote };
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == close {
+                        if bytes.get(i + 1) == Some(&close) {
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            b if is_word_byte(b) => {
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'(' => {
+                tokens.push(Token {
+                    start,
+                    text: &sql[start..start + 1],
+                    depth,
+                });
+                depth += 1;
+                i += 1;
+                continue;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            _ => {
+                // One character, keeping multi-byte characters whole
+                i += sql[i..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        tokens.push(Token {
+            start,
+            text: &sql[start..i],
+            depth,
+        });
+    }
+    tokens
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'@' | b'#' | b'$') || b >= 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_dop: Option<u32>, enforce_row_limit: bool) -> GovernorConfig {
+        GovernorConfig {
+            max_estimated_cost: None,
+            max_dop,
+            enforce_row_limit,
+            ..Default::default()
+        }
+    }
+
+    fn rewrite(query: &str, max_dop: Option<u32>, enforce_row_limit: bool) -> String {
+        rewrite_query(query, &config(max_dop, enforce_row_limit), 100).query
+    }
+
+    #[test]
+    fn test_inject_top() {
+        assert_eq!(
+            rewrite("SELECT * FROM Orders", None, true),
+            "SELECT TOP (101) * FROM Orders"
+        );
+        assert_eq!(
+            rewrite("select distinct CustomerId from Orders;", None, true),
+            "select distinct TOP (101) CustomerId from Orders;"
+        );
+        assert_eq!(
+            rewrite(
+                "WITH recent AS (SELECT TOP 5 * FROM Orders) SELECT * FROM recent",
+                None,
+                true
+            ),
+            "WITH recent AS (SELECT TOP 5 * FROM Orders) SELECT TOP (101) * FROM recent"
+        );
+        // Subqueries do not count as a row limit of the outer query
+        assert_eq!(
+            rewrite("SELECT (SELECT TOP 1 1) AS x", None, true),
+            "SELECT TOP (101) (SELECT TOP 1 1) AS x"
+        );
+
+        for unchanged in [
+            "SELECT TOP 10 * FROM Orders",
+            "SELECT DISTINCT TOP 10 a FROM t",
+            "SELECT ALL TOP (3) a FROM t",
+            "SELECT * INTO #copy FROM Orders",
+            "SELECT Id FROM a UNION SELECT Id FROM b",
+            "SELECT * FROM Orders ORDER BY Id OFFSET 0 ROWS FETCH NEXT 5 ROWS ONLY",
+            "SELECT 1; SELECT 2",
+            "UPDATE Orders SET Total = 0",
+            "WITH c AS (SELECT * FROM Staging) INSERT Orders SELECT * FROM c",
+            "WITH c AS (SELECT Id FROM Old) DELETE o FROM Orders o JOIN c ON c.Id = o.Id",
+        ] {
+            assert_eq!(rewrite(unchanged, None, true), unchanged);
+        }
+        // Keywords in literals, identifiers and comments are ignored
+        assert_eq!(
+            rewrite("SELECT 'union' AS [into] -- OFFSET", None, true),
+            "SELECT TOP (101) 'union' AS [into] -- OFFSET"
+        );
+    }
+
+    #[test]
+    fn test_limit_maxdop() {
+        assert_eq!(
+            rewrite("SELECT * FROM Orders -- all", Some(2), false),
+            "SELECT * FROM Orders\nOPTION (MAXDOP 2) -- all"
+        );
+        assert_eq!(
+            rewrite(
+                "MERGE t USING s ON t.id = s.id WHEN MATCHED THEN DELETE;",
+                Some(1),
+                false
+            ),
+            "MERGE t USING s ON t.id = s.id WHEN MATCHED THEN DELETE\nOPTION (MAXDOP 1);"
+        );
+        assert_eq!(
+            rewrite("SELECT * FROM t OPTION (RECOMPILE)", Some(4), false),
+            "SELECT * FROM t OPTION (MAXDOP 4, RECOMPILE)"
+        );
+        assert_eq!(
+            rewrite(
+                "SELECT * FROM t OPTION (MAXDOP 0, RECOMPILE)",
+                Some(4),
+                false
+            ),
+            "SELECT * FROM t OPTION (MAXDOP 4, RECOMPILE)"
+        );
+        for unchanged in [
+            "SELECT * FROM t OPTION (MAXDOP 2)",
+            "INSERT INTO t VALUES (1)",
+            "EXEC dbo.Report",
+            "SELECT 1; SELECT 2",
+        ] {
+            assert_eq!(rewrite(unchanged, Some(4), false), unchanged);
+        }
+    }
+
+    #[test]
+    fn test_combined_rewrite_and_cost() {
+        let governed = rewrite_query("SELECT * FROM t", &config(Some(1), true), 10);
+        assert_eq!(
+            governed.query,
+            "SELECT TOP (11) * FROM t\nOPTION (MAXDOP 1)"
+        );
+        assert_eq!(
+            governed.changes,
+            vec!["added TOP (11)", "limited MAXDOP to 1"]
+        );
+
+        assert!(has_plan("WITH x AS (SELECT 1 AS a) SELECT a FROM x"));
+        assert!(!has_plan("CREATE TABLE t (id INT) -- SELECT"));
+        assert!(check_cost(12.5, 50.0).is_ok());
+        assert!(check_cost(75.0, 50.0).unwrap_err().contains("75.00"));
+    }
+
+    #[test]
+    fn test_result_size_estimate() {
+        let column = |name: &str, max_length| DescribedColumn {
+            ordinal: 1,
+            name: Some(name.to_string()),
+            type_name: String::new(),
+            max_length,
+            nullable: true,
+            source_table: None,
+            source_column: None,
+            is_identity: false,
+            is_computed: false,
+        };
+        // (4 + 2 + 6) + (1024 + 4 + 6) bytes per row
+        let columns = [column("id", 4), column("body", -1)];
+        assert_eq!(estimate_result_bytes(&columns, 10.0), 10460.0);
+
+        let bytes = estimate_result_bytes(&columns, 100_000.0);
+        assert!(check_result_size(bytes, 100_000.0, 200.0).is_ok());
+        let error = check_result_size(bytes, 100_000.0, 50.0).unwrap_err();
+        assert!(error.contains("100000 rows (99.8 MB)"));
+        assert!(error.contains("export_data"));
+
+        assert!(is_select("WITH x AS (SELECT 1 AS a) SELECT a FROM x;"));
+        assert!(!is_select("SELECT * INTO #copy FROM Orders"));
+        assert!(!is_select("SELECT 1; SELECT 2"));
+        assert!(!is_select("UPDATE Orders SET Total = 0"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Static analysis of T-SQL without executing it.
+//!
+//! Rules are pattern-based and run on query text with literals and comments
+//! blanked out (see [`strip_literals_and_comments`]), so they work offline
+//! and never touch the server. Being textual, they are heuristics: findings
+//! point at likely problems for review rather than proving them.
+
+use super::compatibility::strip_literals_and_comments;
+use crate::database::csv_field;
+use once_cell::sync::Lazy;
+use regex::{Match, Regex};
+use serde::Serialize;
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// Style or maintainability concern.
+    Info,
+    /// Likely performance or correctness problem.
+    Warning,
+}
+
+impl LintSeverity {
+    /// Get the severity name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+/// A pattern-based lint rule.
+struct LintRule {
+    /// Rule name, shared by related patterns.
+    rule: &'static str,
+    severity: LintSeverity,
+    pattern: Regex,
+    message: &'static str,
+    suggestion: &'static str,
+    /// Extra check on a match; the match is reported only if it returns true.
+    applies: fn(&str, &Match) -> bool,
+}
+
+/// Names of all lint rules.
+pub const LINT_RULES: &[&str] = &[
+    "select-star",
+    "nolock",
+    "non-sargable",
+    "implicit-conversion",
+    "top-without-order-by",
+    "three-part-name",
+    "deprecated-syntax",
+];
+
+/// Identifier, possibly bracketed (brackets are blanked to `[x]`).
+const IDENT: &str = r"(?:\[x\]|[A-Za-z_@#][\w@#$]*)";
+
+/// Functions that hide a column from index seeks when wrapped around it.
+const WRAPPING_FUNCTIONS: &str = "YEAR|MONTH|DAY|DATEPART|DATEADD|DATEDIFF|CONVERT|CAST|\
+    UPPER|LOWER|LTRIM|RTRIM|TRIM|ISNULL|COALESCE|SUBSTRING|LEFT|RIGHT|LEN|ABS|FORMAT";
+
+/// Keywords that can precede a column named `text` or `image`.
+const KEYWORDS_BEFORE_COLUMN: &[&str] = &[
+    "SELECT", "DISTINCT", "BY", "WHERE", "AND", "OR", "ON", "SET", "THEN", "ELSE", "WHEN", "RETURN",
+];
+
+static RULES: Lazy<Vec<LintRule>> = Lazy::new(|| {
+    fn rule(
+        rule: &'static str,
+        severity: LintSeverity,
+        pattern: &str,
+        message: &'static str,
+        suggestion: &'static str,
+        applies: fn(&str, &Match) -> bool,
+    ) -> LintRule {
+        LintRule {
+            rule,
+            severity,
+            pattern: Regex::new(pattern).unwrap_or_else(|e| {
+                panic!("Internal error: invalid regex pattern '{}': {}", pattern, e)
+            }),
+            message,
+            suggestion,
+            applies,
+        }
+    }
+    let always = |_: &str, _: &Match| true;
+
+    vec![
+        rule(
+            "select-star",
+            LintSeverity::Info,
+            &format!(
+                r"(?i)\bSELECT\s+(?:DISTINCT\s+)?(?:TOP\s*\(?\s*\d+\s*\)?\s*(?:PERCENT\s+)?)?(?:{IDENT}\.)?\*"
+            ),
+            "SELECT * returns every column, including ones added later",
+            "List the columns the caller needs",
+            |code, m| !after_exists(code, m),
+        ),
+        rule(
+            "nolock",
+            LintSeverity::Warning,
+            r"(?i)\b(?:NOLOCK|READUNCOMMITTED)\b|\bREAD\s+UNCOMMITTED\b",
+            "NOLOCK / READ UNCOMMITTED can return uncommitted, duplicated or missing rows",
+            "Use READ COMMITTED SNAPSHOT or SNAPSHOT isolation for non-blocking reads",
+            always,
+        ),
+        rule(
+            "non-sargable",
+            LintSeverity::Warning,
+            &format!(r"(?i)\b(?:WHERE|AND|OR|ON|HAVING)\s+(?:{WRAPPING_FUNCTIONS})\s*\("),
+            "A function wrapped around a column in a predicate prevents index seeks",
+            "Apply the function to the other side, e.g. a date range instead of YEAR(col) = 2024",
+            always,
+        ),
+        rule(
+            "non-sargable",
+            LintSeverity::Warning,
+            &format!(
+                r"(?i)\b(?:WHERE|AND|OR|ON)\s+(?:{IDENT}\.)?{IDENT}\s*[-+*/]\s*[\w.@]+\s*(?:=|<|>)"
+            ),
+            "Arithmetic on a column in a predicate prevents index seeks",
+            "Move the arithmetic to the other side of the comparison",
+            always,
+        ),
+        rule(
+            "non-sargable",
+            LintSeverity::Warning,
+            r"(?i)\bLIKE\s+N?'%'",
+            "LIKE with a leading wildcard scans the whole index",
+            "Anchor the pattern at the start, or use full-text search",
+            always,
+        ),
+        rule(
+            "implicit-conversion",
+            LintSeverity::Warning,
+            r"(?:=|<>|!=|<=|>=|<|>)\s*N?'0'|N?'0'\s*(?:=|<>|!=|<=|>=|<|>)|(?i)\bIN\s*\(\s*N?'0'",
+            "A number in a string literal is compared with a value, forcing an implicit conversion",
+            "Compare with a numeric literal, or quote the literal only for character columns",
+            always,
+        ),
+        rule(
+            "top-without-order-by",
+            LintSeverity::Warning,
+            r"(?i)\bSELECT\s+(?:DISTINCT\s+)?TOP\b",
+            "TOP without ORDER BY returns an arbitrary set of rows",
+            "Add an ORDER BY that makes the selection deterministic",
+            |code, m| !after_exists(code, m) && !has_order_by(code, m),
+        ),
+        rule(
+            "three-part-name",
+            LintSeverity::Info,
+            &format!(
+                r"(?i)\b(?:FROM|JOIN|INTO|UPDATE|EXEC|EXECUTE|APPLY)\s+(?:{IDENT}\.)?{IDENT}\.{IDENT}?\.{IDENT}"
+            ),
+            "Three- or four-part names tie the query to a database or linked server name",
+            "Connect to the target database, or use a synonym",
+            always,
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Warning,
+            r"\*=|=\*",
+            "Old-style outer join operators (*= / =*) are not supported",
+            "Use LEFT/RIGHT OUTER JOIN",
+            always,
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Info,
+            &format!(r"(?i)\b{IDENT}\s+(?:NTEXT|TEXT|IMAGE)\b\s*(?:,|\)|NULL\b|NOT\b|$)"),
+            "text, ntext and image are deprecated",
+            "Use varchar(max), nvarchar(max) or varbinary(max)",
+            |code, m| {
+                let word = code[m.start()..].split_whitespace().next().unwrap_or("");
+                !KEYWORDS_BEFORE_COLUMN.contains(&word.to_uppercase().as_str())
+            },
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Info,
+            r"(?i)\bSET\s+ROWCOUNT\s+[@\d]",
+            "SET ROWCOUNT does not limit INSERT, UPDATE or DELETE in future versions",
+            "Use TOP",
+            always,
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Info,
+            r"(?i)\bGROUP\s+BY\s+ALL\b",
+            "GROUP BY ALL is deprecated",
+            "Use an outer join or a CASE expression",
+            always,
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Info,
+            r"(?i)\bRAISERROR\s+\d+",
+            "RAISERROR without parentheses is discontinued",
+            "Use THROW, or RAISERROR(msg, severity, state)",
+            always,
+        ),
+        rule(
+            "deprecated-syntax",
+            LintSeverity::Info,
+            r"\b(?:FROM|JOIN)\s+[\w.\[\]]+(?:\s+(?:AS\s+)?\w+)?\s*\(\s*(?i:NOLOCK|HOLDLOCK|UPDLOCK|TABLOCK|TABLOCKX|ROWLOCK|PAGLOCK|READPAST|XLOCK|INDEX\s*[=(])",
+            "Table hints without WITH are deprecated",
+            "Write table hints as WITH (hint)",
+            |code, m| {
+                let word = code[m.start()..m.end()].split_whitespace().nth(2);
+                !word.is_some_and(|w| w.eq_ignore_ascii_case("WITH"))
+            },
+        ),
+    ]
+});
+
+/// Whether a match sits directly inside `EXISTS (`, where `SELECT *` and
+/// `TOP` without ORDER BY are harmless.
+fn after_exists(code: &str, m: &Match) -> bool {
+    let before = code[..m.start()].trim_end();
+    let Some(before) = before.strip_suffix('(') else {
+        return false;
+    };
+    before.trim_end().to_uppercase().ends_with("EXISTS")
+}
+
+/// Whether the statement containing a match has an ORDER BY.
+///
+/// The statement ends at the next `;` or top-level `SELECT` after the
+/// select list, which is enough for single statements and simple batches.
+fn has_order_by(code: &str, m: &Match) -> bool {
+    static ORDER_BY: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bORDER\s+BY\b").unwrap());
+    static NEXT_STATEMENT: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?i);|\b(?:SELECT|INSERT|UPDATE|DELETE|MERGE)\b").unwrap());
+
+    let rest = &code[m.end()..];
+    let end = NEXT_STATEMENT
+        .find(rest)
+        .map(|next| next.start())
+        .unwrap_or(rest.len());
+    ORDER_BY.is_match(&rest[..end])
+}
+
+/// A lint finding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub severity: LintSeverity,
+    /// 1-based line of the query the finding is on.
+    pub line: usize,
+    pub message: &'static str,
+    pub suggestion: &'static str,
+    /// The line of the query, trimmed.
+    pub source: String,
+}
+
+/// Lint a query, optionally running only the named rules.
+///
+/// Findings are ordered by line; a rule is reported once per line.
+pub fn lint_sql(query: &str, only: &[String]) -> Result<Vec<LintFinding>, String> {
+    if let Some(unknown) = only.iter().find(|r| !LINT_RULES.contains(&r.as_str())) {
+        return Err(format!(
+            "Unknown lint rule '{}' (expected {})",
+            unknown,
+            LINT_RULES.join(", ")
+        ));
+    }
+
+    let code = strip_literals_and_comments(query);
+    let lines: Vec<&str> = query.lines().collect();
+    let mut findings: Vec<LintFinding> = Vec::new();
+    for rule in RULES
+        .iter()
+        .filter(|r| only.is_empty() || only.iter().any(|o| o == r.rule))
+    {
+        for m in rule.pattern.find_iter(&code) {
+            if !(rule.applies)(&code, &m) {
+                continue;
+            }
+            let line = code[..m.start()].matches('\n').count() + 1;
+            if findings
+                .iter()
+                .any(|f| f.rule == rule.rule && f.line == line && f.message == rule.message)
+            {
+                continue;
+            }
+            let source = lines.get(line - 1).map(|l| l.trim()).unwrap_or_default();
+            findings.push(LintFinding {
+                rule: rule.rule,
+                severity: rule.severity,
+                line,
+                message: rule.message,
+                suggestion: rule.suggestion,
+                source: truncate(source, 120),
+            });
+        }
+    }
+    findings.sort_by_key(|f| f.line);
+    Ok(findings)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Format findings as a markdown report.
+pub fn format_findings(findings: &[LintFinding]) -> String {
+    if findings.is_empty() {
+        return "No lint findings.".to_string();
+    }
+    let warnings = findings
+        .iter()
+        .filter(|f| f.severity == LintSeverity::Warning)
+        .count();
+    let mut output = format!(
+        "{} finding(s): {} warning(s), {} info\n",
+        findings.len(),
+        warnings,
+        findings.len() - warnings
+    );
+    for finding in findings {
+        output.push_str(&format!(
+            "\n- **Line {}** [{}] `{}`: {}. {}\n  `{}`",
+            finding.line,
+            finding.severity.as_str(),
+            finding.rule,
+            finding.message,
+            finding.suggestion,
+            finding.source
+        ));
+    }
+    output
+}
+
+/// Format findings as CSV.
+pub fn findings_to_csv(findings: &[LintFinding]) -> String {
+    let mut output = String::from("line,severity,rule,message,suggestion,source\n");
+    for finding in findings {
+        let fields = [
+            finding.line.to_string(),
+            finding.severity.as_str().to_string(),
+            finding.rule.to_string(),
+            finding.message.to_string(),
+            finding.suggestion.to_string(),
+            finding.source.clone(),
+        ];
+        let fields: Vec<String> = fields.into_iter().map(csv_field).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(query: &str) -> Vec<(&'static str, usize)> {
+        lint_sql(query, &[])
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.rule, f.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_star_and_top() {
+        assert_eq!(
+            rules("SELECT TOP 10 *\nFROM dbo.Orders o WITH (NOLOCK)"),
+            vec![
+                ("select-star", 1),
+                ("top-without-order-by", 1),
+                ("nolock", 2)
+            ]
+        );
+        assert!(rules("SELECT TOP (5) Id FROM t ORDER BY Id").is_empty());
+        assert!(rules("SELECT COUNT(*) FROM t WHERE EXISTS (SELECT * FROM u)").is_empty());
+        assert_eq!(rules("SELECT o.* FROM t o"), vec![("select-star", 1)]);
+    }
+
+    #[test]
+    fn test_non_sargable_and_conversion() {
+        let query = "SELECT Id FROM t\nWHERE YEAR(CreatedAt) = 2024\n  AND Name LIKE '%son'\n  AND Code = '42'";
+        assert_eq!(
+            rules(query),
+            vec![
+                ("non-sargable", 2),
+                ("non-sargable", 3),
+                ("implicit-conversion", 4)
+            ]
+        );
+        assert!(
+            rules("SELECT Id FROM t WHERE CreatedAt >= '2024-01-01' AND Name LIKE 'a%'").is_empty()
+        );
+        assert_eq!(
+            rules("SELECT a FROM t WHERE Price * 1.1 > 100"),
+            vec![("non-sargable", 1)]
+        );
+    }
+
+    #[test]
+    fn test_names_and_deprecated_syntax() {
+        assert_eq!(
+            rules("SELECT a FROM Sales.dbo.Orders JOIN [srv].[db]..[t] ON 1 = 1"),
+            vec![("three-part-name", 1)]
+        );
+        assert!(rules("SELECT a FROM dbo.Orders o JOIN Lines l ON l.OrderId = o.Id").is_empty());
+        assert_eq!(
+            rules("CREATE TABLE t (Notes ntext NULL, Body text)\nSELECT a FROM t (NOLOCK)"),
+            vec![
+                ("deprecated-syntax", 1),
+                ("nolock", 2),
+                ("deprecated-syntax", 2)
+            ]
+        );
+        assert_eq!(
+            rules("SELECT a.x FROM a, b WHERE a.id *= b.id"),
+            vec![("deprecated-syntax", 1)]
+        );
+        assert!(rules("SELECT text, image FROM t").is_empty());
+    }
+
+    #[test]
+    fn test_literals_comments_and_rule_filter() {
+        let query = "-- SELECT * FROM t WITH (NOLOCK)\nSELECT 'SELECT *' AS q /* NOLOCK\n */ FROM t\nWHERE x = 1";
+        assert!(rules(query).is_empty());
+
+        let only = vec!["nolock".to_string()];
+        let findings = lint_sql("SELECT * FROM t WITH (NOLOCK)", &only).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].source, "SELECT * FROM t WITH (NOLOCK)");
+        assert!(lint_sql("SELECT 1", &["bogus".to_string()]).is_err());
+    }
+}
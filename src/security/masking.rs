@@ -0,0 +1,595 @@
+//! Data masking for sensitive values in query results.
+//!
+//! Masking is applied to results before they are formatted and returned, so
+//! sensitive values never leave the server. Two mechanisms are supported:
+//!
+//! - **Column rules** (`MSSQL_MASK_COLUMNS`): `pattern[=strategy]` entries
+//!   where the pattern is `column`, `table.column` or `schema.table.column`
+//!   with `*` and `?` wildcards. When the source table of a result is unknown
+//!   (arbitrary queries), rules match on the column name alone.
+//! - **Content detectors** (`MSSQL_MASK_PATTERNS`): emails, SSNs, credit card
+//!   numbers and phone numbers found in any text value are partially masked.
+//!
+//! Strategies are `redact` (replace the value), `partial` (keep the last four
+//! characters, or the first letter and domain of an email) and `hash` (a
+//! stable SHA-256 prefix that still allows grouping and joining).
+
+use crate::config::MaskingConfig;
+use crate::database::{QueryColumnInfo, QueryResult, ResultRow, SqlValue};
+use crate::error::ServerError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Replacement for redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Share of sampled values a detector must match to classify a column.
+pub const CONTENT_MATCH_THRESHOLD: f64 = 0.5;
+
+/// How a sensitive value is masked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskStrategy {
+    Redact,
+    Partial,
+    Hash,
+}
+
+impl MaskStrategy {
+    fn parse(s: &str) -> Result<Self, ServerError> {
+        match s.trim().to_lowercase().as_str() {
+            "redact" | "full" => Ok(Self::Redact),
+            "partial" => Ok(Self::Partial),
+            "hash" => Ok(Self::Hash),
+            other => Err(ServerError::config(format!(
+                "Unknown masking strategy '{}' (expected redact, partial or hash)",
+                other
+            ))),
+        }
+    }
+
+    /// Name of the strategy as used in masking rules.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Redact => "redact",
+            Self::Partial => "partial",
+            Self::Hash => "hash",
+        }
+    }
+
+    /// Mask a text value.
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Self::Redact => REDACTED.to_string(),
+            Self::Partial => mask_partial(value),
+            Self::Hash => {
+                let digest = format!("{:x}", Sha256::digest(value.as_bytes()));
+                format!("sha256:{}", &digest[..16])
+            }
+        }
+    }
+}
+
+/// Kinds of sensitive data recognized by name or content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveCategory {
+    Email,
+    Ssn,
+    CreditCard,
+    Phone,
+    DateOfBirth,
+    Address,
+    PersonName,
+    NationalId,
+    Financial,
+    Credential,
+}
+
+impl SensitiveCategory {
+    /// Name of the category (also the detector name in `MSSQL_MASK_PATTERNS`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Ssn => "ssn",
+            Self::CreditCard => "credit_card",
+            Self::Phone => "phone",
+            Self::DateOfBirth => "date_of_birth",
+            Self::Address => "address",
+            Self::PersonName => "person_name",
+            Self::NationalId => "national_id",
+            Self::Financial => "financial",
+            Self::Credential => "credential",
+        }
+    }
+
+    /// Strategy suggested for columns of this category.
+    pub fn suggested_strategy(&self) -> MaskStrategy {
+        match self {
+            Self::Credential | Self::NationalId | Self::Ssn => MaskStrategy::Redact,
+            Self::PersonName => MaskStrategy::Hash,
+            _ => MaskStrategy::Partial,
+        }
+    }
+}
+
+/// A content detector for one kind of sensitive value.
+pub struct Detector {
+    pub category: SensitiveCategory,
+    regex: Regex,
+}
+
+impl Detector {
+    /// Check whether a value contains sensitive data of this kind.
+    pub fn matches(&self, value: &str) -> bool {
+        self.regex
+            .find_iter(value)
+            .any(|m| self.is_valid(m.as_str()))
+    }
+
+    /// Partially mask every occurrence in a value.
+    fn mask(&self, value: &str) -> String {
+        self.regex
+            .replace_all(value, |caps: &regex::Captures<'_>| {
+                let found = &caps[0];
+                if self.is_valid(found) {
+                    mask_partial(found)
+                } else {
+                    found.to_string()
+                }
+            })
+            .into_owned()
+    }
+
+    /// Reject regex matches that are not plausible values (e.g. failed Luhn check).
+    fn is_valid(&self, found: &str) -> bool {
+        match self.category {
+            SensitiveCategory::CreditCard => luhn_valid(found),
+            _ => true,
+        }
+    }
+}
+
+/// Built-in content detectors.
+static DETECTORS: Lazy<Vec<Detector>> = Lazy::new(|| {
+    fn detector(category: SensitiveCategory, pattern: &str) -> Detector {
+        Detector {
+            category,
+            regex: Regex::new(pattern).unwrap_or_else(|e| {
+                panic!("Internal error: invalid regex pattern '{}': {}", pattern, e)
+            }),
+        }
+    }
+
+    vec![
+        detector(
+            SensitiveCategory::Email,
+            r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+        ),
+        detector(SensitiveCategory::Ssn, r"\b\d{3}-\d{2}-\d{4}\b"),
+        detector(SensitiveCategory::CreditCard, r"\b(?:\d[ -]?){12,18}\d\b"),
+        detector(
+            SensitiveCategory::Phone,
+            r"(?:\+\d{1,3}[ .-]?)?\(?\b\d{3}\)?[ .-]\d{3}[ .-]\d{4}\b",
+        ),
+    ]
+});
+
+/// All built-in content detectors.
+pub fn detectors() -> &'static [Detector] {
+    &DETECTORS
+}
+
+/// Column name fragments that suggest sensitive data.
+const NAME_HINTS: &[(&str, SensitiveCategory)] = &[
+    ("email", SensitiveCategory::Email),
+    ("e_mail", SensitiveCategory::Email),
+    ("ssn", SensitiveCategory::Ssn),
+    ("socialsecurity", SensitiveCategory::Ssn),
+    ("social_security", SensitiveCategory::Ssn),
+    ("creditcard", SensitiveCategory::CreditCard),
+    ("credit_card", SensitiveCategory::CreditCard),
+    ("cardnumber", SensitiveCategory::CreditCard),
+    ("card_number", SensitiveCategory::CreditCard),
+    ("ccnumber", SensitiveCategory::CreditCard),
+    ("phone", SensitiveCategory::Phone),
+    ("mobile", SensitiveCategory::Phone),
+    ("fax", SensitiveCategory::Phone),
+    ("birth", SensitiveCategory::DateOfBirth),
+    ("dob", SensitiveCategory::DateOfBirth),
+    ("address", SensitiveCategory::Address),
+    ("street", SensitiveCategory::Address),
+    ("postal", SensitiveCategory::Address),
+    ("zip", SensitiveCategory::Address),
+    ("firstname", SensitiveCategory::PersonName),
+    ("first_name", SensitiveCategory::PersonName),
+    ("lastname", SensitiveCategory::PersonName),
+    ("last_name", SensitiveCategory::PersonName),
+    ("surname", SensitiveCategory::PersonName),
+    ("fullname", SensitiveCategory::PersonName),
+    ("full_name", SensitiveCategory::PersonName),
+    ("passport", SensitiveCategory::NationalId),
+    ("nationalid", SensitiveCategory::NationalId),
+    ("national_id", SensitiveCategory::NationalId),
+    ("taxid", SensitiveCategory::NationalId),
+    ("tax_id", SensitiveCategory::NationalId),
+    ("license", SensitiveCategory::NationalId),
+    ("iban", SensitiveCategory::Financial),
+    ("accountnumber", SensitiveCategory::Financial),
+    ("account_number", SensitiveCategory::Financial),
+    ("salary", SensitiveCategory::Financial),
+    ("password", SensitiveCategory::Credential),
+    ("passwd", SensitiveCategory::Credential),
+    ("secret", SensitiveCategory::Credential),
+    ("token", SensitiveCategory::Credential),
+    ("apikey", SensitiveCategory::Credential),
+    ("api_key", SensitiveCategory::Credential),
+];
+
+/// Categories suggested by a column name.
+pub fn classify_name(column: &str) -> Vec<SensitiveCategory> {
+    let name = column.to_lowercase();
+    let mut categories: Vec<SensitiveCategory> = NAME_HINTS
+        .iter()
+        .filter(|(hint, _)| name.contains(hint))
+        .map(|(_, category)| *category)
+        .collect();
+    categories.sort();
+    categories.dedup();
+    categories
+}
+
+/// A compiled column masking rule.
+#[derive(Debug, Clone)]
+struct ColumnRule {
+    schema: Option<Regex>,
+    table: Option<Regex>,
+    column: Regex,
+    strategy: MaskStrategy,
+}
+
+impl ColumnRule {
+    /// Parse `pattern[=strategy]` (strategy defaults to `redact`).
+    fn parse(rule: &str) -> Result<Self, ServerError> {
+        let (pattern, strategy) = match rule.split_once('=') {
+            Some((pattern, strategy)) => (pattern, MaskStrategy::parse(strategy)?),
+            None => (rule, MaskStrategy::Redact),
+        };
+        let parts: Vec<&str> = pattern.trim().split('.').collect();
+        let (schema, table, column) = match parts.as_slice() {
+            [column] => (None, None, *column),
+            [table, column] => (None, Some(*table), *column),
+            [schema, table, column] => (Some(*schema), Some(*table), *column),
+            _ => (None, None, ""),
+        };
+        if column.is_empty() {
+            return Err(ServerError::config(format!(
+                "Invalid masking rule '{}' (expected [schema.][table.]column[=strategy])",
+                rule
+            )));
+        }
+        Ok(Self {
+            schema: schema.map(glob_regex),
+            table: table.map(glob_regex),
+            column: glob_regex(column),
+            strategy,
+        })
+    }
+
+    /// Check whether the rule covers a column of a result.
+    fn matches(&self, source: Option<(&str, &str)>, column: &str) -> bool {
+        if !self.column.is_match(column) {
+            return false;
+        }
+        match source {
+            Some((schema, table)) => {
+                self.schema.as_ref().is_none_or(|s| s.is_match(schema))
+                    && self.table.as_ref().is_none_or(|t| t.is_match(table))
+            }
+            // Unknown source: mask conservatively by column name
+            None => true,
+        }
+    }
+}
+
+/// Applies masking rules and content detectors to query results.
+pub struct DataMasker {
+    rules: Vec<ColumnRule>,
+    detectors: Vec<&'static Detector>,
+}
+
+impl DataMasker {
+    /// Compile the masking configuration.
+    pub fn new(config: &MaskingConfig) -> Result<Self, ServerError> {
+        let rules = config
+            .column_rules
+            .iter()
+            .map(|rule| ColumnRule::parse(rule))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut enabled = Vec::new();
+        for name in &config.detectors {
+            let name = name.trim().to_lowercase();
+            if name == "all" {
+                enabled.extend(detectors().iter());
+                continue;
+            }
+            let detector = detectors()
+                .iter()
+                .find(|d| d.category.as_str() == name)
+                .ok_or_else(|| {
+                    ServerError::config(format!(
+                        "Unknown masking pattern '{}' (expected email, ssn, credit_card, phone or all)",
+                        name
+                    ))
+                })?;
+            enabled.push(detector);
+        }
+        enabled.dedup_by_key(|d| d.category);
+
+        Ok(Self {
+            rules,
+            detectors: enabled,
+        })
+    }
+
+    /// Whether any masking is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty() || !self.detectors.is_empty()
+    }
+
+    /// Strategy of the first rule covering a column, if any.
+    pub fn strategy_for(&self, source: Option<(&str, &str)>, column: &str) -> Option<MaskStrategy> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(source, column))
+            .map(|rule| rule.strategy)
+    }
+
+    /// Mask a result in place. `source` is the `(schema, table)` the rows
+    /// were read from, if known.
+    pub fn mask_result(&self, result: &mut QueryResult, source: Option<(&str, &str)>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let strategies = self.column_strategies(&result.columns, source);
+        for row in &mut result.rows {
+            self.mask_row_with(&strategies, row);
+        }
+    }
+
+    /// Mask every result set in place.
+    pub fn mask_results(&self, results: &mut [QueryResult]) {
+        for result in results {
+            self.mask_result(result, None);
+        }
+    }
+
+    /// Mask a single row in place (for streamed results).
+    pub fn mask_row(
+        &self,
+        columns: &[QueryColumnInfo],
+        row: &mut ResultRow,
+        source: Option<(&str, &str)>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let strategies = self.column_strategies(columns, source);
+        self.mask_row_with(&strategies, row);
+    }
+
+    /// Mask rows that carry no column metadata (e.g. watch change queues).
+    pub fn mask_rows(&self, rows: &mut [ResultRow], source: Option<(&str, &str)>) {
+        if !self.is_enabled() {
+            return;
+        }
+        for row in rows {
+            let strategies: Vec<_> = row
+                .columns
+                .keys()
+                .map(|name| (name.clone(), self.strategy_for(source, name)))
+                .collect();
+            self.mask_row_with(&strategies, row);
+        }
+    }
+
+    fn column_strategies(
+        &self,
+        columns: &[QueryColumnInfo],
+        source: Option<(&str, &str)>,
+    ) -> Vec<(String, Option<MaskStrategy>)> {
+        columns
+            .iter()
+            .map(|c| (c.name.clone(), self.strategy_for(source, &c.name)))
+            .collect()
+    }
+
+    fn mask_row_with(&self, strategies: &[(String, Option<MaskStrategy>)], row: &mut ResultRow) {
+        for (name, strategy) in strategies {
+            let Some(value) = row.columns.get_mut(name) else {
+                continue;
+            };
+            if value.is_null() {
+                continue;
+            }
+            match strategy {
+                Some(strategy) => {
+                    *value = SqlValue::String(strategy.apply(&value.to_display_string()));
+                }
+                None => {
+                    if let SqlValue::String(text) = value {
+                        for detector in &self.detectors {
+                            if detector.matches(text) {
+                                *text = detector.mask(text);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compile a `*`/`?` wildcard pattern into a case-insensitive regex.
+fn glob_regex(pattern: &str) -> Regex {
+    let pattern = pattern.trim().trim_matches(|c| c == '[' || c == ']');
+    let escaped = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+    Regex::new(&format!("(?i)^{}$", escaped)).unwrap_or_else(|e| {
+        panic!(
+            "Internal error: invalid masking pattern '{}': {}",
+            pattern, e
+        )
+    })
+}
+
+/// Keep the last four alphanumerics (or an email's first letter and domain).
+fn mask_partial(value: &str) -> String {
+    if let Some((local, domain)) = value.split_once('@') {
+        let first: String = local.chars().take(1).collect();
+        return format!("{}***@{}", first, domain);
+    }
+    let total = value.chars().filter(|c| c.is_alphanumeric()).count();
+    let keep = if total > 4 { 4 } else { 0 };
+    let mut seen = 0;
+    value
+        .chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                return c;
+            }
+            seen += 1;
+            if seen > total - keep {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+/// Luhn checksum used by payment card numbers.
+fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(result: &QueryResult, column: &str) -> String {
+        result.rows[0]
+            .get(column)
+            .map(|v| v.to_display_string())
+            .unwrap_or_default()
+    }
+
+    fn result() -> QueryResult {
+        let mut row = ResultRow::new();
+        row.insert(
+            "Email".to_string(),
+            SqlValue::String("jane@example.com".to_string()),
+        );
+        row.insert(
+            "SSN".to_string(),
+            SqlValue::String("123-45-6789".to_string()),
+        );
+        row.insert(
+            "Notes".to_string(),
+            SqlValue::String("Card 4111 1111 1111 1111, call 555-123-4567".to_string()),
+        );
+        row.insert("Id".to_string(), SqlValue::I32(7));
+        let column = |name: &str| QueryColumnInfo {
+            name: name.to_string(),
+            sql_type: "nvarchar".to_string(),
+            nullable: true,
+        };
+        QueryResult {
+            columns: vec![
+                column("Email"),
+                column("SSN"),
+                column("Notes"),
+                column("Id"),
+            ],
+            rows: vec![row],
+            ..QueryResult::empty()
+        }
+    }
+
+    #[test]
+    fn test_column_rules() {
+        let masker = DataMasker::new(&MaskingConfig {
+            column_rules: vec![
+                "dbo.Customers.SSN".to_string(),
+                "*.*.e?ail=partial".to_string(),
+                "Id=hash".to_string(),
+            ],
+            detectors: Vec::new(),
+        })
+        .unwrap();
+
+        // Explicit rules only apply to their table when the source is known
+        let mut other = result();
+        masker.mask_result(&mut other, Some(("dbo", "Orders")));
+        assert_eq!(text(&other, "SSN"), "123-45-6789");
+
+        let mut masked = result();
+        masker.mask_result(&mut masked, None);
+        assert_eq!(text(&masked, "SSN"), REDACTED);
+        assert_eq!(text(&masked, "Email"), "j***@example.com");
+        assert!(text(&masked, "Id").starts_with("sha256:"));
+
+        assert!(DataMasker::new(&MaskingConfig {
+            column_rules: vec!["a.b.c.d".to_string()],
+            detectors: Vec::new(),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_content_detectors() {
+        let masker = DataMasker::new(&MaskingConfig {
+            column_rules: Vec::new(),
+            detectors: vec!["all".to_string()],
+        })
+        .unwrap();
+
+        let mut masked = result();
+        masker.mask_result(&mut masked, None);
+        assert_eq!(text(&masked, "SSN"), "***-**-6789");
+        assert_eq!(
+            text(&masked, "Notes"),
+            "Card **** **** **** 1111, call ***-***-4567"
+        );
+        assert!(!luhn_valid("4111 1111 1111 1112"));
+        assert_eq!(
+            classify_name("CustomerEmailAddress"),
+            vec![SensitiveCategory::Email, SensitiveCategory::Address]
+        );
+    }
+}
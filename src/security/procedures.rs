@@ -0,0 +1,86 @@
+//! Stored procedure allow-list and procedures-only mode.
+//!
+//! `MSSQL_ALLOWED_PROCEDURES` restricts `execute_procedure` to a list of
+//! procedures, written as `schema.procedure`, `schema.*` or a bare procedure
+//! name (any schema). With `MSSQL_PROCEDURES_ONLY` the tools that accept SQL
+//! text are disabled and hidden, so clients only see the curated procedures.
+
+/// Tools that run ad-hoc SQL, disabled in procedures-only mode.
+pub const AD_HOC_QUERY_TOOLS: &[&str] = &[
+    "execute_query",
+    "explain_query",
+    "compare_plans",
+    "estimate_impact",
+    "execute_with_tvp",
+    "execute_async",
+    "run_script",
+    "watch_query",
+    "execute_parameterized",
+    "register_query",
+    "execute_registered",
+    "execute_in_transaction",
+    "execute_in_pinned_session",
+    "execute_paginated",
+    "recommend_indexes",
+    "export_data",
+    "download_blob",
+    "analyze_query",
+    "validate_syntax_tool",
+    "lint_sql",
+    "describe_query",
+];
+
+/// Whether a tool runs ad-hoc SQL.
+pub fn is_ad_hoc_tool(name: &str) -> bool {
+    AD_HOC_QUERY_TOOLS.contains(&name)
+}
+
+/// Whether `schema.procedure` matches one of the allow-list entries.
+///
+/// Matching is case-insensitive and ignores square brackets. An empty
+/// allow-list allows every procedure.
+pub fn procedure_allowed(allowed: &[String], schema: &str, procedure: &str) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    let schema = unbracket(schema);
+    let procedure = unbracket(procedure);
+    allowed.iter().any(|entry| match entry.rsplit_once('.') {
+        Some((s, p)) => {
+            unbracket(s).eq_ignore_ascii_case(schema)
+                && (p == "*" || unbracket(p).eq_ignore_ascii_case(procedure))
+        }
+        None => unbracket(entry).eq_ignore_ascii_case(procedure),
+    })
+}
+
+fn unbracket(name: &str) -> &str {
+    name.trim().trim_start_matches('[').trim_end_matches(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_procedure_allowed() {
+        let allowed = vec![
+            "dbo.GetOrders".to_string(),
+            "[reporting].*".to_string(),
+            "usp_Health".to_string(),
+        ];
+        assert!(procedure_allowed(&allowed, "dbo", "getorders"));
+        assert!(procedure_allowed(&allowed, "Reporting", "MonthlySales"));
+        assert!(procedure_allowed(&allowed, "ops", "usp_Health"));
+        assert!(!procedure_allowed(&allowed, "dbo", "DeleteOrders"));
+        assert!(!procedure_allowed(&allowed, "sales", "GetOrders"));
+        assert!(procedure_allowed(&[], "dbo", "anything"));
+    }
+
+    #[test]
+    fn test_ad_hoc_tools() {
+        assert!(is_ad_hoc_tool("execute_query"));
+        assert!(!is_ad_hoc_tool("execute_procedure"));
+        assert!(!is_ad_hoc_tool("list_sessions"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Per-client rate limits and quotas.
+//!
+//! Limits are keyed by client identity (the name and version the MCP client
+//! reported at initialization) so that a runaway agent cannot swamp the
+//! server:
+//!
+//! - **Queries per minute**: tool calls within a sliding one-minute window
+//! - **Concurrent sessions**: open async sessions, pinned sessions and
+//!   transactions
+//! - **Rows per hour**: rows read or affected within a sliding one-hour
+//!   window; checked before each call, so the call that crosses the quota
+//!   completes and later calls are throttled
+
+use crate::config::RateLimitConfig;
+use crate::error::ServerError;
+use crate::telemetry::current_request;
+use mcpkit::types::ToolOutput;
+use parking_lot::Mutex;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Identity used for clients that did not report a name.
+pub const ANONYMOUS_CLIENT: &str = "anonymous";
+
+/// Window for the queries-per-minute limit.
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Window for the rows-per-hour quota.
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Recent activity of one client.
+#[derive(Default)]
+struct ClientUsage {
+    calls: VecDeque<Instant>,
+    rows: VecDeque<(Instant, u64)>,
+}
+
+impl ClientUsage {
+    /// Drop activity that has left the limit windows.
+    fn prune(&mut self, now: Instant) {
+        while self
+            .calls
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= MINUTE)
+        {
+            self.calls.pop_front();
+        }
+        while self
+            .rows
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) >= HOUR)
+        {
+            self.rows.pop_front();
+        }
+    }
+
+    fn rows_in_window(&self) -> u64 {
+        self.rows.iter().map(|(_, rows)| rows).sum()
+    }
+}
+
+/// Enforces per-client rate limits.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    clients: Mutex<HashMap<String, ClientUsage>>,
+    /// Open sessions and transactions by ID, with the owning client.
+    sessions: Mutex<HashMap<String, String>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter.
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            clients: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether any limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.config.queries_per_minute.is_some()
+            || self.config.concurrent_sessions.is_some()
+            || self.config.rows_per_hour.is_some()
+    }
+
+    /// Admit a tool call, counting it towards the queries-per-minute limit.
+    pub fn check_call(&self, identity: &str) -> Result<(), ServerError> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock();
+        let usage = clients.entry(identity.to_string()).or_default();
+        usage.prune(now);
+
+        if let Some(max) = self.config.rows_per_hour {
+            if usage.rows_in_window() >= max {
+                let retry_after = usage.rows.front().map(|(t, _)| retry_after(now, *t, HOUR));
+                return Err(ServerError::rate_limited(
+                    identity,
+                    "rows per hour",
+                    max,
+                    retry_after,
+                ));
+            }
+        }
+
+        if let Some(max) = self.config.queries_per_minute {
+            if usage.calls.len() >= max as usize {
+                let retry_after = usage.calls.front().map(|t| retry_after(now, *t, MINUTE));
+                return Err(ServerError::rate_limited(
+                    identity,
+                    "queries per minute",
+                    u64::from(max),
+                    retry_after,
+                ));
+            }
+            usage.calls.push_back(now);
+        }
+
+        Ok(())
+    }
+
+    /// Count rows read or affected by a finished call towards the hourly quota.
+    pub fn record_rows(&self, identity: &str, rows: u64) {
+        if self.config.rows_per_hour.is_none() || rows == 0 {
+            return;
+        }
+        let mut clients = self.clients.lock();
+        let usage = clients.entry(identity.to_string()).or_default();
+        usage.rows.push_back((Instant::now(), rows));
+    }
+
+    /// Register an opened session or transaction for the current client.
+    ///
+    /// Fails if the client already holds the maximum number of sessions.
+    pub fn begin_session(&self, session_id: &str) -> Result<(), ServerError> {
+        let identity = request_identity();
+        let mut sessions = self.sessions.lock();
+        if let Some(max) = self.config.concurrent_sessions {
+            let open = sessions
+                .values()
+                .filter(|owner| **owner == identity)
+                .count();
+            if open >= max {
+                return Err(ServerError::rate_limited(
+                    identity,
+                    "concurrent sessions",
+                    max as u64,
+                    None,
+                ));
+            }
+        }
+        sessions.insert(session_id.to_string(), identity);
+        Ok(())
+    }
+
+    /// Release a session or transaction slot (no-op for unknown IDs).
+    pub fn end_session(&self, session_id: &str) {
+        self.sessions.lock().remove(session_id);
+    }
+}
+
+/// Identity of the client making the current request.
+pub fn request_identity() -> String {
+    current_request()
+        .and_then(|request| request.client_id)
+        .unwrap_or_else(|| ANONYMOUS_CLIENT.to_string())
+}
+
+/// Build the tool response for a throttled call.
+///
+/// The message carries a JSON object with the exceeded limit so that clients
+/// can back off programmatically.
+pub fn throttle_output(error: &ServerError) -> ToolOutput {
+    let details = match error {
+        ServerError::RateLimited {
+            identity,
+            limit,
+            max,
+            retry_after_seconds,
+        } => json!({
+            "error": "rate_limited",
+            "identity": identity,
+            "limit": limit,
+            "max": max,
+            "retry_after_seconds": retry_after_seconds,
+        }),
+        ServerError::ConcurrencyLimited {
+            scope,
+            max,
+            waited_seconds,
+        } => json!({
+            "error": "concurrency_limited",
+            "scope": scope,
+            "max": max,
+            "waited_seconds": waited_seconds,
+        }),
+        other => json!({ "error": other.to_string() }),
+    };
+    ToolOutput::RecoverableError {
+        message: format!("{}\n{}", error, details),
+        suggestion: error.suggestion().map(str::to_string),
+    }
+}
+
+/// Seconds until an event at `since` leaves a window of length `window`.
+fn retry_after(now: Instant, since: Instant, window: Duration) -> u64 {
+    window
+        .saturating_sub(now.duration_since(since))
+        .as_secs()
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries_and_rows_limits() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            queries_per_minute: Some(2),
+            concurrent_sessions: None,
+            rows_per_hour: Some(100),
+        });
+
+        assert!(limiter.check_call("a/1.0").is_ok());
+        assert!(limiter.check_call("a/1.0").is_ok());
+        match limiter.check_call("a/1.0") {
+            Err(ServerError::RateLimited {
+                limit,
+                max,
+                retry_after_seconds,
+                ..
+            }) => {
+                assert_eq!(limit, "queries per minute");
+                assert_eq!(max, 2);
+                assert!(retry_after_seconds.is_some_and(|s| s <= 60));
+            }
+            other => panic!("Expected rate limit error, got {:?}", other),
+        }
+
+        // Other clients have their own budget
+        limiter.record_rows("b/1.0", 150);
+        let err = limiter.check_call("b/1.0").unwrap_err();
+        assert!(err.to_string().contains("at most 100 rows per hour"));
+
+        let output = throttle_output(&err);
+        match output {
+            ToolOutput::RecoverableError { message, .. } => {
+                assert!(message.contains("\"error\":\"rate_limited\""));
+            }
+            _ => panic!("Expected recoverable error"),
+        }
+    }
+
+    #[test]
+    fn test_concurrent_sessions() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            concurrent_sessions: Some(1),
+            ..Default::default()
+        });
+
+        assert!(limiter.begin_session("s1").is_ok());
+        assert!(limiter.begin_session("s2").is_err());
+        limiter.end_session("s1");
+        assert!(limiter.begin_session("s2").is_ok());
+    }
+}
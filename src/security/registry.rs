@@ -0,0 +1,451 @@
+//! Named, parameterized queries that clients run by name.
+//!
+//! Operators define queries in the file named by `MSSQL_QUERY_REGISTRY`
+//! (TOML or YAML, one table per query) or at runtime with `register_query`.
+//! `execute_registered` binds the declared parameters through
+//! `sp_executesql`, so clients never supply SQL text. With
+//! `MSSQL_REGISTERED_ONLY` enabled, tools that accept SQL text are refused
+//! and runtime registration is disabled, leaving the file as the only source
+//! of queries.
+//!
+//! ```toml
+//! [orders_by_customer]
+//! description = "Recent orders of a customer"
+//! sql = "SELECT * FROM dbo.Orders WHERE CustomerId = @customer_id AND OrderDate >= @since"
+//! parameters = { customer_id = "int", since = { type = "date", default = "2024-01-01" } }
+//! ```
+
+use crate::error::ServerError;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Parameter and query names.
+static NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+
+/// SQL types accepted for parameters, e.g. `int`, `nvarchar(50)`, `decimal(18, 2)`.
+static SQL_TYPE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^[a-z][a-z0-9_]*(\s*\(\s*(\d+|max)\s*(,\s*\d+\s*)?\))?$").unwrap()
+});
+
+/// Where a registered query was defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuerySource {
+    /// The `MSSQL_QUERY_REGISTRY` file.
+    Config,
+    /// The `register_query` tool.
+    Runtime,
+}
+
+/// A declared parameter of a registered query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryParameter {
+    /// Name without the leading `@`.
+    pub name: String,
+    /// SQL type the value is converted to.
+    pub sql_type: String,
+    /// Value used when the parameter is omitted; required if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A named query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegisteredQuery {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub sql: String,
+    pub parameters: Vec<QueryParameter>,
+    pub source: QuerySource,
+}
+
+impl RegisteredQuery {
+    /// Create a query, checking its name and parameter declarations.
+    pub fn new(
+        name: &str,
+        sql: &str,
+        description: Option<String>,
+        parameters: Vec<QueryParameter>,
+        source: QuerySource,
+    ) -> Result<Self, String> {
+        if !NAME.is_match(name) {
+            return Err(format!(
+                "Invalid query name '{}': use letters, digits and underscores",
+                name
+            ));
+        }
+        if sql.trim().is_empty() {
+            return Err(format!("Query '{}' has no SQL", name));
+        }
+        let mut parameters = parameters;
+        for parameter in &mut parameters {
+            parameter.name = parameter.name.trim_start_matches('@').to_string();
+            if !NAME.is_match(&parameter.name) {
+                return Err(format!(
+                    "Query '{}': invalid parameter name '{}'",
+                    name, parameter.name
+                ));
+            }
+            if !SQL_TYPE.is_match(parameter.sql_type.trim()) {
+                return Err(format!(
+                    "Query '{}': invalid type '{}' for parameter '{}'",
+                    name, parameter.sql_type, parameter.name
+                ));
+            }
+            parameter.sql_type = parameter.sql_type.trim().to_string();
+        }
+        Ok(Self {
+            name: name.to_string(),
+            description,
+            sql: sql.to_string(),
+            parameters,
+            source,
+        })
+    }
+
+    /// Build the `sp_executesql` call running the query with `values`.
+    ///
+    /// Parameter names are matched case-insensitively, with or without `@`.
+    pub fn bind(&self, values: &HashMap<String, Value>) -> Result<String, String> {
+        let mut values: HashMap<String, &Value> = values
+            .iter()
+            .map(|(k, v)| (k.trim_start_matches('@').to_lowercase(), v))
+            .collect();
+
+        let mut declarations = Vec::with_capacity(self.parameters.len());
+        let mut assignments = Vec::with_capacity(self.parameters.len());
+        for parameter in &self.parameters {
+            let value = match values.remove(&parameter.name.to_lowercase()) {
+                Some(value) => value,
+                None => parameter
+                    .default
+                    .as_ref()
+                    .ok_or_else(|| format!("Missing required parameter '{}'", parameter.name))?,
+            };
+            declarations.push(format!("@{} {}", parameter.name, parameter.sql_type));
+            assignments.push(format!("@{} = {}", parameter.name, literal(value)));
+        }
+        if let Some(unknown) = values.keys().next() {
+            let expected: Vec<&str> = self.parameters.iter().map(|p| p.name.as_str()).collect();
+            return Err(format!(
+                "Unknown parameter '{}' (expected: {})",
+                unknown,
+                if expected.is_empty() {
+                    "none".to_string()
+                } else {
+                    expected.join(", ")
+                }
+            ));
+        }
+
+        if declarations.is_empty() {
+            return Ok(self.sql.clone());
+        }
+        Ok(format!(
+            "EXEC sp_executesql N'{}', N'{}', {}",
+            self.sql.replace('\'', "''"),
+            declarations.join(", "),
+            assignments.join(", ")
+        ))
+    }
+}
+
+/// SQL literal of a parameter value; arrays and objects are passed as JSON text.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => (if *b { "1" } else { "0" }).to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("N'{}'", s.replace('\'', "''")),
+        Value::Array(_) | Value::Object(_) => {
+            format!("N'{}'", value.to_string().replace('\'', "''"))
+        }
+    }
+}
+
+// =============================================================================
+// Registry
+// =============================================================================
+
+/// Registered queries by name.
+#[derive(Debug, Default)]
+pub struct QueryRegistry {
+    queries: RwLock<BTreeMap<String, RegisteredQuery>>,
+}
+
+impl QueryRegistry {
+    /// Create a registry holding the queries of the registry file, if any.
+    pub fn load(path: Option<&Path>) -> Result<Self, ServerError> {
+        let registry = Self::default();
+        if let Some(path) = path {
+            registry.replace_config_queries(read_registry_file(path)?);
+        }
+        Ok(registry)
+    }
+
+    /// Replace the queries defined in the registry file, keeping runtime ones.
+    pub fn replace_config_queries(&self, queries: Vec<RegisteredQuery>) {
+        let mut registered = self.queries.write();
+        registered.retain(|_, q| q.source == QuerySource::Runtime);
+        for query in queries {
+            registered.insert(query.name.to_lowercase(), query);
+        }
+    }
+
+    /// Register a runtime query; `replace` allows overwriting a runtime query.
+    pub fn register(&self, query: RegisteredQuery, replace: bool) -> Result<(), String> {
+        let mut registered = self.queries.write();
+        match registered.get(&query.name.to_lowercase()) {
+            Some(existing) if existing.source == QuerySource::Config => {
+                return Err(format!(
+                    "Query '{}' is defined in the registry file and cannot be replaced",
+                    existing.name
+                ));
+            }
+            Some(existing) if !replace => {
+                return Err(format!(
+                    "Query '{}' is already registered (set replace=true to overwrite it)",
+                    existing.name
+                ));
+            }
+            _ => {}
+        }
+        registered.insert(query.name.to_lowercase(), query);
+        Ok(())
+    }
+
+    /// Look up a query by name (case-insensitive).
+    pub fn get(&self, name: &str) -> Option<RegisteredQuery> {
+        self.queries.read().get(&name.to_lowercase()).cloned()
+    }
+
+    /// All registered queries, sorted by name.
+    pub fn list(&self) -> Vec<RegisteredQuery> {
+        self.queries.read().values().cloned().collect()
+    }
+}
+
+/// A query as written in the registry file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryDefinition {
+    sql: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    parameters: BTreeMap<String, ParameterDefinition>,
+}
+
+/// A parameter as written in the registry file: a type, or a table.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParameterDefinition {
+    Type(String),
+    Full {
+        #[serde(rename = "type")]
+        sql_type: String,
+        #[serde(default)]
+        default: Option<Value>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+/// Read the queries of a TOML or YAML registry file.
+pub fn read_registry_file(path: &Path) -> Result<Vec<RegisteredQuery>, ServerError> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        ServerError::config(format!(
+            "Failed to read query registry {}: {e}",
+            path.display()
+        ))
+    })?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let definitions: BTreeMap<String, QueryDefinition> = match extension.as_str() {
+        "toml" => toml::from_str(&text).map_err(|e| {
+            ServerError::config(format!("Invalid query registry {}: {e}", path.display()))
+        })?,
+        "yaml" | "yml" => serde_yaml_ng::from_str(&text).map_err(|e| {
+            ServerError::config(format!("Invalid query registry {}: {e}", path.display()))
+        })?,
+        _ => {
+            return Err(ServerError::config(format!(
+                "Unsupported query registry format '{}': expected .toml, .yaml or .yml",
+                path.display()
+            )))
+        }
+    };
+
+    definitions
+        .into_iter()
+        .map(|(name, definition)| {
+            let parameters = definition
+                .parameters
+                .into_iter()
+                .map(|(name, parameter)| match parameter {
+                    ParameterDefinition::Type(sql_type) => QueryParameter {
+                        name,
+                        sql_type,
+                        default: None,
+                        description: None,
+                    },
+                    ParameterDefinition::Full {
+                        sql_type,
+                        default,
+                        description,
+                    } => QueryParameter {
+                        name,
+                        sql_type,
+                        default,
+                        description,
+                    },
+                })
+                .collect();
+            RegisteredQuery::new(
+                &name,
+                &definition.sql,
+                definition.description,
+                parameters,
+                QuerySource::Config,
+            )
+            .map_err(ServerError::config)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parameter(name: &str, sql_type: &str, default: Option<Value>) -> QueryParameter {
+        QueryParameter {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            default,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_bind_parameters() {
+        let query = RegisteredQuery::new(
+            "orders",
+            "SELECT * FROM Orders WHERE CustomerId = @customer AND Status = @status",
+            None,
+            vec![
+                parameter("@customer", "int", None),
+                parameter("status", "nvarchar(20)", Some(json!("open"))),
+            ],
+            QuerySource::Runtime,
+        )
+        .unwrap();
+
+        let values = HashMap::from([("Customer".to_string(), json!(42))]);
+        assert_eq!(
+            query.bind(&values).unwrap(),
+            "EXEC sp_executesql N'SELECT * FROM Orders WHERE CustomerId = @customer AND Status = @status', \
+             N'@customer int, @status nvarchar(20)', @customer = 42, @status = N'open'"
+        );
+
+        let values = HashMap::from([
+            ("customer".to_string(), json!(1)),
+            ("status".to_string(), json!("it's")),
+        ]);
+        assert!(query.bind(&values).unwrap().ends_with("@status = N'it''s'"));
+
+        assert_eq!(
+            query.bind(&HashMap::new()).unwrap_err(),
+            "Missing required parameter 'customer'"
+        );
+        let values = HashMap::from([
+            ("customer".to_string(), json!(1)),
+            ("region".to_string(), json!("EU")),
+        ]);
+        assert_eq!(
+            query.bind(&values).unwrap_err(),
+            "Unknown parameter 'region' (expected: customer, status)"
+        );
+    }
+
+    #[test]
+    fn test_declarations_are_checked() {
+        let declare = |name: &str, sql_type: &str| {
+            RegisteredQuery::new(
+                "q",
+                "SELECT 1",
+                None,
+                vec![parameter(name, sql_type, None)],
+                QuerySource::Runtime,
+            )
+        };
+        assert!(declare("id", "decimal(18, 2)").is_ok());
+        assert!(declare("id", "NVARCHAR(MAX)").is_ok());
+        assert!(declare("id", "int; DROP TABLE t").is_err());
+        assert!(declare("id)--", "int").is_err());
+        assert!(
+            RegisteredQuery::new("bad name", "SELECT 1", None, vec![], QuerySource::Runtime)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_registry_file_and_runtime_queries() {
+        let path = std::env::temp_dir().join(format!("queries_{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+[orders_by_customer]
+description = "Orders of a customer"
+sql = "SELECT * FROM Orders WHERE CustomerId = @customer_id AND OrderDate >= @since"
+parameters = { customer_id = "int", since = { type = "date", default = "2024-01-01" } }
+"#,
+        )
+        .unwrap();
+        let registry = QueryRegistry::load(Some(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let query = registry.get("Orders_By_Customer").unwrap();
+        assert_eq!(query.source, QuerySource::Config);
+        assert_eq!(query.parameters[1].default, Some(json!("2024-01-01")));
+
+        let runtime = |sql: &str| {
+            RegisteredQuery::new(
+                "orders_by_customer",
+                sql,
+                None,
+                vec![],
+                QuerySource::Runtime,
+            )
+            .unwrap()
+        };
+        assert!(registry.register(runtime("SELECT 1"), true).is_err());
+
+        let adhoc =
+            RegisteredQuery::new("adhoc", "SELECT 1", None, vec![], QuerySource::Runtime).unwrap();
+        registry.register(adhoc.clone(), false).unwrap();
+        assert!(registry.register(adhoc.clone(), false).is_err());
+        registry.register(adhoc, true).unwrap();
+
+        // Reloading the file keeps runtime queries
+        registry.replace_config_queries(Vec::new());
+        assert_eq!(
+            registry
+                .list()
+                .iter()
+                .map(|q| q.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["adhoc"]
+        );
+    }
+}
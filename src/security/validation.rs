@@ -1,12 +1,13 @@
 //! Query validation for different security modes.
 
 use crate::error::ServerError;
+use crate::security::compatibility::strip_literals_and_comments;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 /// Query validation mode.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum ValidationMode {
     /// Read-only mode: Only SELECT queries allowed.
     /// Blocks: INSERT, UPDATE, DELETE, DROP, CREATE, ALTER, TRUNCATE, EXEC, etc.
@@ -151,17 +152,87 @@ static SAFE_EXEC_PATTERN: Lazy<Regex> = Lazy::new(|| {
         .unwrap_or_else(|e| panic!("Internal error: invalid safe exec pattern: {}", e))
 });
 
+/// Keywords that make a batch more than a read: data or schema changes,
+/// procedure calls, `SELECT ... INTO` and server operations.
+static WRITE_KEYWORDS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(?i)\b(INSERT|UPDATE|DELETE|MERGE|INTO|EXEC|EXECUTE|CREATE|ALTER|DROP|TRUNCATE",
+        r"|GRANT|REVOKE|DENY|BACKUP|RESTORE|DBCC|KILL|SHUTDOWN|WRITETEXT|UPDATETEXT",
+        r"|BULK|OPENROWSET|OPENQUERY|OPENDATASOURCE)\b",
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid write keyword pattern: {}", e))
+});
+
+/// Four-part object names (`server.database.schema.object`, where the
+/// database and schema may be empty), matched against code without literals
+/// and comments. A following `(` marks a method call on a column instead.
+static FOUR_PART_NAME: Lazy<Regex> = Lazy::new(|| {
+    let ident = r"(?:\[x\]|[A-Za-z_#][\w@#$]*)";
+    Regex::new(&format!(
+        r"(?:^|[^\w@#$.\]]){i}\s*\.\s*(?:{i})?\s*\.\s*(?:{i})?\s*\.\s*{i}(\s*\()?",
+        i = ident
+    ))
+    .unwrap_or_else(|e| panic!("Internal error: invalid four-part name pattern: {}", e))
+});
+
+/// Pattern for `OPENQUERY`, which passes a query through to a linked server.
+static OPENQUERY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bOPENQUERY\b")
+        .unwrap_or_else(|e| panic!("Internal error: invalid OPENQUERY pattern: {}", e))
+});
+
+/// Pattern for the leading database switch added when a session or call sets a database.
+static LEADING_USE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*USE\s+(\[[^\]]+\]|\w+)\s*;?")
+        .unwrap_or_else(|e| panic!("Internal error: invalid USE pattern: {}", e))
+});
+
+/// Check whether a batch only reads data, so running it again is harmless.
+///
+/// Conservative: the batch must start with `SELECT` or `WITH` (after comments
+/// and a leading `USE`) and must not mention any write keyword anywhere, even
+/// inside string literals.
+pub fn is_read_only(query: &str) -> bool {
+    let trimmed = remove_leading_comments(query);
+    let body = LEADING_USE_PATTERN.replace(&trimmed, "");
+    detect_query_type(&body).is_read() && !WRITE_KEYWORDS.is_match(&body)
+}
+
+/// How a query reaches a linked server, if it does: `OPENQUERY` or a
+/// four-part name.
+fn linked_server_reference(query: &str) -> Option<&'static str> {
+    let code = strip_literals_and_comments(query);
+    if OPENQUERY_PATTERN.is_match(&code) {
+        return Some("OPENQUERY");
+    }
+    FOUR_PART_NAME
+        .captures_iter(&code)
+        .any(|c| c.get(1).is_none())
+        .then_some("four-part name")
+}
+
 /// Query validator.
 #[derive(Debug, Clone)]
 pub struct QueryValidator {
     mode: ValidationMode,
     max_length: usize,
+    allow_linked_servers: bool,
 }
 
 impl QueryValidator {
     /// Create a new query validator.
     pub fn new(mode: ValidationMode, max_length: usize) -> Self {
-        Self { mode, max_length }
+        Self {
+            mode,
+            max_length,
+            allow_linked_servers: false,
+        }
+    }
+
+    /// Allow four-part names and `OPENQUERY` in read-only and standard mode.
+    pub fn with_linked_servers(mut self, allow: bool) -> Self {
+        self.allow_linked_servers = allow;
+        self
     }
 
     /// Validate a query against the current mode.
@@ -177,6 +248,16 @@ impl QueryValidator {
         // Detect query type
         let query_type = detect_query_type(query);
 
+        // Linked server queries leave the server, so they are opt-in
+        if self.mode != ValidationMode::Unrestricted && !self.allow_linked_servers {
+            if let Some(reference) = linked_server_reference(query) {
+                return Err(ServerError::validation(format!(
+                    "Linked server queries ({}) are not allowed; enable them with MSSQL_ALLOW_LINKED_SERVERS",
+                    reference
+                )));
+            }
+        }
+
         // Validate based on mode
         match self.mode {
             ValidationMode::ReadOnly => self.validate_read_only(query, query_type),
@@ -242,6 +323,9 @@ impl QueryValidator {
 
         // Check for dangerous keywords
         for (pattern, keyword) in DANGEROUS_KEYWORDS.iter() {
+            if *keyword == "OPENQUERY" && self.allow_linked_servers {
+                continue;
+            }
             if pattern.is_match(query) {
                 return Err(ServerError::validation(format!(
                     "Dangerous keyword '{}' is not allowed in standard mode",
@@ -403,6 +487,59 @@ mod tests {
         assert!(v.validate("BACKUP DATABASE foo").is_err());
     }
 
+    #[test]
+    fn test_linked_server_reference() {
+        assert_eq!(
+            linked_server_reference("SELECT * FROM REMOTE01.Sales.dbo.Orders"),
+            Some("four-part name")
+        );
+        assert_eq!(
+            linked_server_reference("SELECT * FROM [REMOTE\\SQL2019]...Orders"),
+            Some("four-part name")
+        );
+        assert_eq!(
+            linked_server_reference("SELECT * FROM OPENQUERY(REMOTE01, 'SELECT 1')"),
+            Some("OPENQUERY")
+        );
+
+        assert_eq!(
+            linked_server_reference("SELECT * FROM Sales.dbo.Orders"),
+            None
+        );
+        assert_eq!(
+            linked_server_reference("SELECT o.Shape.STBuffer(1).STArea() FROM dbo.Orders o"),
+            None
+        );
+        assert_eq!(
+            linked_server_reference("SELECT 'a.b.c.d' -- REMOTE01.Sales.dbo.Orders"),
+            None
+        );
+        assert_eq!(
+            linked_server_reference("SELECT 1.5 + 2.25, '10.0.0.1'"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_linked_servers_opt_in() {
+        let four_part = "SELECT * FROM REMOTE01.Sales.dbo.Orders";
+        let openquery = "SELECT * FROM OPENQUERY(REMOTE01, 'SELECT 1')";
+
+        assert!(read_only_validator().validate(four_part).is_err());
+        assert!(standard_validator().validate(openquery).is_err());
+
+        let read_only = read_only_validator().with_linked_servers(true);
+        assert!(read_only.validate(four_part).is_ok());
+        assert!(read_only.validate(openquery).is_ok());
+        let standard = standard_validator().with_linked_servers(true);
+        assert!(standard.validate(four_part).is_ok());
+        assert!(standard.validate(openquery).is_ok());
+        assert!(standard.validate("SELECT * FROM OPENROWSET(...)").is_err());
+
+        let unrestricted = QueryValidator::new(ValidationMode::Unrestricted, 1_000_000);
+        assert!(unrestricted.validate(four_part).is_ok());
+    }
+
     #[test]
     fn test_safe_exec() {
         let v = standard_validator();
@@ -421,4 +558,21 @@ mod tests {
         let long_query = "SELECT ".to_string() + &"x".repeat(100);
         assert!(v.validate(&long_query).is_err());
     }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("SELECT * FROM Users"));
+        assert!(is_read_only(
+            "-- report\nWITH t AS (SELECT 1 AS x) SELECT x FROM t"
+        ));
+        assert!(is_read_only(
+            "USE [Sales];\nSELECT TOP 10 * FROM dbo.Orders"
+        ));
+
+        assert!(!is_read_only("UPDATE Users SET Name = 'x'"));
+        assert!(!is_read_only("SELECT * INTO #copy FROM Users"));
+        assert!(!is_read_only("SELECT 1; DELETE FROM Users"));
+        assert!(!is_read_only("EXEC sp_help 'Users'"));
+        assert!(!is_read_only("USE [Sales]; INSERT INTO t VALUES (1)"));
+    }
 }
@@ -1,15 +1,106 @@
 //! MCP server struct definition and initialization.
 
+use crate::audit::AuditLog;
+use crate::cache::{new_shared_cache, QueryCache, SharedCache};
 use crate::config::Config;
+use crate::database::describe::{describe_query_sql, DescribedColumn};
+use crate::database::plan::ExecutionPlan;
 use crate::database::{
-    create_pool, BulkInsertManager, ConnectionPool, MetadataQueries, QueryExecutor, SessionManager,
-    TransactionManager,
+    connect_pool, connect_read_pool, resolve_instance, set_exact_decimals,
+    set_result_memory_budgets, with_read_intent, BulkInsertManager, ConnectionPool,
+    CredentialRotation, FailoverRecovery, MetadataQueries, PoolHealth, QueryChange, QueryExecutor,
+    QueryWatchManager, RotationOutcome, SchemaWatcher, SessionLimits, SessionManager,
+    TableSizeHistory, TransactionManager, WatchManager,
 };
 use crate::error::ServerError;
-use crate::security::QueryValidator;
-use crate::state::{new_shared_state, SharedState};
-use crate::telemetry::{new_shared_metrics, SharedMetrics};
+use crate::localization::ErrorLocalizer;
+use crate::resilience::{CircuitBreaker, CircuitBreakerConfig, RetryConfig};
+use crate::results::ResultStore;
+use crate::security::compatibility::{self, CompatibilityIssue};
+use crate::security::governor;
+use crate::security::registry::{read_registry_file, QueryRegistry};
+use crate::security::{
+    ConcurrencyLimiter, ConfirmationStore, DataMasker, QueryValidator, RateLimiter,
+};
+use crate::session_store::SessionStore;
+use crate::state::{new_shared_state, spawn_session_cleanup, SessionRetention, SharedState};
+use crate::telemetry::{
+    new_shared_metrics, set_query_tagging, set_session_context_tagging, RequestTracing,
+    SharedMetrics,
+};
+use crate::transport::{ClientRequests, SubscriptionTransport};
+use mcpkit::capability::ResourceCapability;
+use mcpkit::server::{NotRegistered, Registered, Server};
+use mcpkit::types::{Resource, ResourceContents, ResourceTemplate};
+use mcpkit::{Context, McpError, ResourceHandler, ServerBuilder, Transport};
+use parking_lot::RwLock;
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// MCP server whose tool calls are wrapped with request tracing.
+pub type TracedServer = Server<
+    Arc<MssqlMcpServer>,
+    Registered<RequestTracing<Arc<MssqlMcpServer>>>,
+    Registered<ReadOnlyResources<Arc<MssqlMcpServer>>>,
+    Registered<Arc<MssqlMcpServer>>,
+    NotRegistered,
+>;
+
+/// Read-only tools that report on the server the primary pool is connected
+/// to, and so keep using it when a read-only pool is configured.
+const PRIMARY_POOL_TOOLS: &[&str] = &[
+    "health_check",
+    "get_replica_info",
+    "get_ag_health",
+    "get_replication_status",
+    "get_active_requests",
+    "get_tempdb_usage",
+];
+
+/// Resource handler whose reads run with read intent, so they use the
+/// read-only pool when one is configured.
+pub struct ReadOnlyResources<T>(T);
+
+impl<T: ResourceHandler> ResourceHandler for ReadOnlyResources<T> {
+    fn list_resources(
+        &self,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<Vec<Resource>, McpError>> + Send {
+        self.0.list_resources(ctx)
+    }
+
+    fn list_resource_templates(
+        &self,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<Vec<ResourceTemplate>, McpError>> + Send {
+        self.0.list_resource_templates(ctx)
+    }
+
+    fn read_resource(
+        &self,
+        uri: &str,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<Vec<ResourceContents>, McpError>> + Send {
+        with_read_intent(true, self.0.read_resource(uri, ctx))
+    }
+
+    fn subscribe(
+        &self,
+        uri: &str,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<bool, McpError>> + Send {
+        self.0.subscribe(uri, ctx)
+    }
+
+    fn unsubscribe(
+        &self,
+        uri: &str,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<bool, McpError>> + Send {
+        self.0.unsubscribe(uri, ctx)
+    }
+}
 
 /// The MSSQL MCP Server instance.
 ///
@@ -27,8 +118,23 @@ pub struct MssqlMcpServer {
     /// Database connection pool (wrapped in Arc for sharing).
     pub(crate) pool: Arc<ConnectionPool>,
 
+    /// Pool with read-only intent used by read-only tools and resources, if configured.
+    pub(crate) read_pool: Option<Arc<ConnectionPool>>,
+
+    /// `host:port` the read-only pool is connected to.
+    pub(crate) read_endpoint: Option<String>,
+
+    /// Background probing, recycling and replenishment of pool connections.
+    pub(crate) pool_health: Arc<PoolHealth>,
+
+    /// Pool rebuild and read retry after server failovers.
+    pub(crate) failover: Arc<FailoverRecovery>,
+
+    /// Re-establishes the pools with rotated credentials.
+    pub(crate) credential_rotation: Arc<CredentialRotation>,
+
     /// Configuration.
-    pub(crate) config: Arc<Config>,
+    pub(crate) config: Arc<RwLock<Arc<Config>>>,
 
     /// Query executor.
     pub(crate) executor: Arc<QueryExecutor>,
@@ -37,7 +143,7 @@ pub struct MssqlMcpServer {
     pub(crate) metadata: Arc<MetadataQueries>,
 
     /// Query validator for security.
-    pub(crate) validator: Arc<QueryValidator>,
+    pub(crate) validator: Arc<RwLock<Arc<QueryValidator>>>,
 
     /// Server metrics for telemetry.
     pub(crate) metrics: SharedMetrics,
@@ -50,6 +156,54 @@ pub struct MssqlMcpServer {
 
     /// Bulk insert manager for native BCP operations.
     pub(crate) bulk_insert_manager: Arc<BulkInsertManager>,
+
+    /// Error message localizer.
+    pub(crate) error_localizer: Arc<ErrorLocalizer>,
+
+    /// Table watch manager for polling-based change detection.
+    pub(crate) watch_manager: Arc<WatchManager>,
+
+    /// Query watch manager for re-executed queries.
+    pub(crate) query_watch_manager: Arc<QueryWatchManager>,
+
+    /// Store for large results returned by reference.
+    pub(crate) result_store: Arc<ResultStore>,
+
+    /// Cache of read-only query results.
+    pub(crate) query_cache: SharedCache,
+
+    /// Schema change detection for resource subscriptions.
+    pub(crate) schema_watcher: Arc<SchemaWatcher>,
+
+    /// Previous table size snapshots for growth reporting.
+    pub(crate) table_size_history: Arc<TableSizeHistory>,
+
+    /// Finished async sessions persisted across restarts.
+    pub(crate) session_store: Arc<SessionStore>,
+
+    /// Audit log of tool calls.
+    pub(crate) audit_log: Arc<AuditLog>,
+
+    /// Per-client rate limits and quotas.
+    pub(crate) rate_limiter: Arc<RateLimiter>,
+
+    /// Per-tool and per-database concurrency limits.
+    pub(crate) concurrency_limiter: Arc<ConcurrencyLimiter>,
+
+    /// Masking of sensitive values in results.
+    pub(crate) masker: Arc<RwLock<Arc<DataMasker>>>,
+
+    /// Destructive statements awaiting confirmation.
+    pub(crate) confirmations: Arc<ConfirmationStore>,
+
+    /// Named queries run by `execute_registered`.
+    pub(crate) query_registry: Arc<QueryRegistry>,
+
+    /// Effective server major version, detected on first use.
+    pub(crate) server_version: Arc<OnceCell<Option<u32>>>,
+
+    /// Requests sent to the client, such as elicitation.
+    pub(crate) client_requests: Arc<ClientRequests>,
 }
 
 impl MssqlMcpServer {
@@ -58,9 +212,40 @@ impl MssqlMcpServer {
     /// This performs async initialization including:
     /// - Creating the connection pool
     /// - Validating the database connection
-    pub async fn new(config: Config) -> Result<Self, ServerError> {
-        // Create connection pool (wrapped in Arc for sharing)
-        let pool = Arc::new(create_pool(&config.database).await?);
+    pub async fn new(mut config: Config) -> Result<Self, ServerError> {
+        // Find the port of a named instance before any connection is made
+        resolve_instance(&mut config.database).await;
+
+        // Create connection pool (wrapped in Arc for sharing), choosing among
+        // failover partners and Availability Group replicas
+        let pool = Arc::new(connect_pool(&mut config.database).await?);
+
+        // Keep idle connections validated and the pool at its minimum size
+        let pool_health = Arc::new(PoolHealth::new(Arc::clone(&pool), &config.database.pool));
+        pool_health.spawn();
+
+        // Optional second pool for read-only work, on a readable secondary or replica
+        let mut pool_healths = vec![Arc::clone(&pool_health)];
+        let mut rotated_pools = vec![(Arc::clone(&pool), config.database.clone())];
+        let (read_pool, read_endpoint) = match connect_read_pool(&config.database).await {
+            Some((read_pool, read_config)) => {
+                let read_pool = Arc::new(read_pool);
+                let read_health =
+                    Arc::new(PoolHealth::new(Arc::clone(&read_pool), &read_config.pool));
+                read_health.spawn();
+                pool_healths.push(read_health);
+                let endpoint = format!("{}:{}", read_config.host, read_config.port);
+                rotated_pools.push((Arc::clone(&read_pool), read_config));
+                (Some(read_pool), Some(endpoint))
+            }
+            None => (None, None),
+        };
+
+        // Drop stale connections of both pools when the server fails over
+        let failover = Arc::new(FailoverRecovery::new(pool_healths));
+
+        // Replace both pools when the credentials are rotated
+        let credential_rotation = Arc::new(CredentialRotation::new(rotated_pools));
 
         // Create shared state
         let state = new_shared_state();
@@ -69,29 +254,50 @@ impl MssqlMcpServer {
         {
             let mut s = state.write().await;
             s.mark_initialized();
-            s.set_default_timeout(config.query.default_timeout.as_secs());
+            s.set_server_default_timeout(config.query.default_timeout.as_secs());
         }
 
+        // Fail fast while the server is unreachable
+        let circuit_breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::from_env()));
+
+        // Create metrics collector
+        let metrics = new_shared_metrics();
+
         // Create query executor (uses Arc<Pool>)
-        let executor = Arc::new(QueryExecutor::new(
-            Arc::clone(&pool),
-            config.security.max_result_rows,
-        ));
+        let executor = if config.query.retry_read_only || config.query.retry_statements {
+            QueryExecutor::with_retry(
+                Arc::clone(&pool),
+                config.security.max_result_rows,
+                RetryConfig::from_env(),
+            )
+            .with_write_retry(config.query.retry_statements)
+        } else {
+            QueryExecutor::new(Arc::clone(&pool), config.security.max_result_rows)
+        };
+        let executor = executor
+            .with_circuit_breaker(Arc::clone(&circuit_breaker))
+            .with_read_pool(read_pool.clone())
+            .with_failover_recovery(Arc::clone(&failover))
+            .with_metrics(Arc::clone(&metrics));
+        executor.slow_queries().configure(&config.query);
+        let executor = Arc::new(executor);
 
         // Create metadata queries (uses Arc<Pool>)
-        let metadata = Arc::new(MetadataQueries::new(
-            Arc::clone(&pool),
-            config.security.max_result_rows,
-        ));
+        let metadata = Arc::new(
+            MetadataQueries::new(Arc::clone(&pool), config.security.max_result_rows)
+                .with_circuit_breaker(circuit_breaker)
+                .with_read_pool(read_pool.clone())
+                .with_failover_recovery(Arc::clone(&failover)),
+        );
 
         // Create query validator
-        let validator = Arc::new(QueryValidator::new(
-            config.security.validation_mode,
-            config.security.max_query_length,
-        ));
-
-        // Create metrics collector
-        let metrics = new_shared_metrics();
+        let validator = Arc::new(RwLock::new(Arc::new(
+            QueryValidator::new(
+                config.security.validation_mode,
+                config.security.max_query_length,
+            )
+            .with_linked_servers(config.security.allow_linked_servers),
+        )));
 
         // Create transaction manager with database config
         let db_config = Arc::new(config.database.clone());
@@ -99,21 +305,128 @@ impl MssqlMcpServer {
             db_config.clone(),
             config.security.max_result_rows,
         ));
+        transaction_manager.set_deadlock_replays(config.query.deadlock_replays);
 
         // Create session manager for pinned connections
         let session_manager = Arc::new(SessionManager::new(
             db_config.clone(),
             config.security.max_result_rows,
-            config.session.result_retention, // Use result retention as session timeout
+            SessionLimits::from_config(&config.session),
         ));
 
         // Create bulk insert manager for native BCP operations
         let bulk_insert_manager = Arc::new(BulkInsertManager::new(db_config));
 
+        // Create error localizer
+        let error_localizer = Arc::new(ErrorLocalizer::new(config.query.error_language.as_deref()));
+
+        // Create table watch manager
+        let watch_manager = Arc::new(WatchManager::new(Arc::clone(&executor)));
+
+        // Create query watch manager
+        let query_watch_manager = Arc::new(QueryWatchManager::new(Arc::clone(&executor)));
+
+        // Tag executed SQL with request IDs and session context if enabled
+        set_query_tagging(config.query.tag_queries);
+        set_session_context_tagging(config.query.session_context);
+        set_exact_decimals(config.query.exact_decimals);
+        set_result_memory_budgets(
+            config.query.query_memory_mb * 1024 * 1024,
+            config.query.result_memory_mb * 1024 * 1024,
+        );
+
+        // Create result store for large results
+        let result_store = Arc::new(ResultStore::new(
+            config.query.result_ref_threshold_kb * 1024,
+            config.session.result_retention,
+        ));
+
+        // Create schema watcher for resource subscriptions
+        let schema_watcher = Arc::new(SchemaWatcher::new(
+            Arc::clone(&executor),
+            config.session.schema_poll_interval,
+        ));
+
+        // Create table size history for growth tracking
+        let table_size_history = Arc::new(TableSizeHistory::new(
+            config.session.table_size_history_path.clone(),
+        ));
+
+        // Restore async sessions persisted before the last restart
+        let session_store = Arc::new(SessionStore::new(
+            config.session.session_store_dir.clone(),
+            config.session.session_store_max_mb as u64 * 1024 * 1024,
+            config.session.result_retention,
+        ));
+        let restored = session_store.load();
+        if !restored.is_empty() {
+            tracing::info!("Restored {} persisted async sessions", restored.len());
+            let mut state = state.write().await;
+            for session in restored {
+                state.restore_session(session);
+            }
+        }
+
+        // Drop finished async sessions beyond the retention limits
+        let store = Arc::clone(&session_store);
+        spawn_session_cleanup(
+            &state,
+            config.session.cleanup_interval,
+            SessionRetention::from_config(&config.session),
+            move |id| store.remove(id),
+        );
+
+        // Create cache of read-only query results
+        let query_cache = new_shared_cache(
+            config.query.cache_ttl,
+            config.query.cache_max_size_mb,
+            config.query.cache_max_entries,
+            config.query.enable_caching,
+        );
+
+        // Create audit log of tool calls
+        let audit_log = Arc::new(AuditLog::new(&config.audit, Arc::clone(&executor))?);
+
+        // Create per-client rate limiter
+        let rate_limiter = Arc::new(RateLimiter::new(config.security.rate_limit.clone()));
+
+        // Create per-tool and per-database concurrency limiter
+        let concurrency_limiter = Arc::new(ConcurrencyLimiter::new(
+            &config.security.concurrency,
+            config.database.database.clone(),
+            Arc::clone(&state),
+        ));
+
+        // End pinned sessions leaked by clients that went away
+        let limiter = Arc::clone(&rate_limiter);
+        session_manager.spawn_cleanup(config.session.cleanup_interval, move |id| {
+            limiter.end_session(id)
+        });
+
+        // Compile masking rules for sensitive values
+        let masker = Arc::new(RwLock::new(Arc::new(DataMasker::new(
+            &config.security.masking,
+        )?)));
+
+        // Create store for destructive statements awaiting confirmation
+        let confirmations = Arc::new(ConfirmationStore::new(std::time::Duration::from_secs(
+            crate::constants::CONFIRMATION_TOKEN_TTL_SECS,
+        )));
+
+        // Load the named queries of the registry file
+        let query_registry = Arc::new(QueryRegistry::load(
+            config.security.query_registry.as_deref(),
+        )?);
+
         Ok(Self {
             state,
             pool,
-            config: Arc::new(config),
+            read_pool,
+            read_endpoint,
+            pool_health,
+            failover,
+            credential_rotation,
+            config: Arc::new(RwLock::new(Arc::new(config))),
             executor,
             metadata,
             validator,
@@ -121,20 +434,94 @@ impl MssqlMcpServer {
             transaction_manager,
             session_manager,
             bulk_insert_manager,
+            error_localizer,
+            watch_manager,
+            query_watch_manager,
+            result_store,
+            query_cache,
+            schema_watcher,
+            table_size_history,
+            session_store,
+            audit_log,
+            rate_limiter,
+            concurrency_limiter,
+            masker,
+            confirmations,
+            query_registry,
+            server_version: Arc::new(OnceCell::new()),
+            client_requests: Arc::new(ClientRequests::new()),
         })
     }
 
-    /// Create a server from environment variables.
+    /// Create a server from environment variables and the optional config file.
     ///
     /// This is the standard way to create a server for production use.
     pub async fn from_env() -> Result<Self, ServerError> {
-        let config = Config::from_env()?;
+        let config = load_config().await?;
         Self::new(config).await
     }
 
-    /// Get a reference to the configuration.
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// Convert into an MCP server that assigns a request ID to every tool call.
+    ///
+    /// Like `into_server`, but tool calls run inside a `tool_call` span carrying
+    /// the request ID, every tool response includes it, every call is
+    /// recorded in the audit log, per-client rate limits are enforced, and
+    /// tools disabled by the configuration are hidden. Read-only tools and
+    /// resource reads use the read-only pool when one is configured.
+    ///
+    /// The server advertises resource subscriptions, so it must be served over a
+    /// transport from [`Self::subscription_transport`].
+    pub fn into_traced_server(self) -> TracedServer {
+        let handler = Arc::new(self);
+        let builder = ServerBuilder::new(Arc::clone(&handler));
+        let mut capabilities = builder.get_capabilities().clone();
+        capabilities.resources = Some(ResourceCapability {
+            subscribe: Some(true),
+            list_changed: None,
+        });
+
+        builder
+            .capabilities(capabilities)
+            .with_tools(
+                RequestTracing::new(Arc::clone(&handler))
+                    .with_audit(Arc::clone(&handler.audit_log))
+                    .with_rate_limiter(Arc::clone(&handler.rate_limiter))
+                    .with_concurrency_limiter(Arc::clone(&handler.concurrency_limiter))
+                    .with_progress_notifications(Arc::clone(&handler.client_requests))
+                    .with_metrics(Arc::clone(&handler.metrics))
+                    .with_read_routing(PRIMARY_POOL_TOOLS)
+                    .with_tool_filter({
+                        let handler = Arc::clone(&handler);
+                        Arc::new(move |name: &str| handler.tool_disabled_reason(name))
+                    }),
+            )
+            .with_resources(ReadOnlyResources(Arc::clone(&handler)))
+            .with_prompts(handler)
+            .build()
+    }
+
+    /// Wrap a transport so it serves schema resource subscriptions, sends
+    /// query watch notifications and carries requests to the client.
+    ///
+    /// Must be called within a Tokio runtime; the returned transport polls for
+    /// schema changes in the background until it is closed or dropped.
+    pub fn subscription_transport<T: Transport + 'static>(
+        &self,
+        inner: T,
+    ) -> SubscriptionTransport<T> {
+        SubscriptionTransport::new(
+            inner,
+            Arc::clone(&self.schema_watcher),
+            self.query_watch_manager.updates(),
+            Arc::clone(&self.client_requests),
+        )
+    }
+
+    /// Get the current configuration.
+    ///
+    /// Returns a snapshot; a later reload does not change it.
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read())
     }
 
     /// Get a reference to the connection pool.
@@ -142,6 +529,11 @@ impl MssqlMcpServer {
         &self.pool
     }
 
+    /// Get a reference to the pool maintenance task.
+    pub fn pool_health(&self) -> &PoolHealth {
+        &self.pool_health
+    }
+
     /// Get a reference to the query executor.
     pub fn executor(&self) -> &QueryExecutor {
         &self.executor
@@ -152,9 +544,9 @@ impl MssqlMcpServer {
         &self.metadata
     }
 
-    /// Get a reference to the query validator.
-    pub fn validator(&self) -> &QueryValidator {
-        &self.validator
+    /// Get the current query validator.
+    pub fn validator(&self) -> Arc<QueryValidator> {
+        Arc::clone(&self.validator.read())
     }
 
     /// Get a reference to the session state.
@@ -182,20 +574,114 @@ impl MssqlMcpServer {
         &self.bulk_insert_manager
     }
 
+    /// Get a reference to the table watch manager.
+    pub fn watch_manager(&self) -> &WatchManager {
+        &self.watch_manager
+    }
+
+    /// Get a reference to the query watch manager.
+    pub fn query_watch_manager(&self) -> &QueryWatchManager {
+        &self.query_watch_manager
+    }
+
+    /// Get a reference to the result store.
+    pub fn result_store(&self) -> &ResultStore {
+        &self.result_store
+    }
+
+    /// Get a reference to the query result cache.
+    pub fn query_cache(&self) -> &QueryCache {
+        &self.query_cache
+    }
+
+    /// Get a reference to the schema watcher.
+    pub fn schema_watcher(&self) -> &SchemaWatcher {
+        &self.schema_watcher
+    }
+
+    /// Get a reference to the table size history.
+    pub fn table_size_history(&self) -> &TableSizeHistory {
+        &self.table_size_history
+    }
+
+    /// Get a reference to the persisted session store.
+    pub fn session_store(&self) -> &Arc<SessionStore> {
+        &self.session_store
+    }
+
+    /// Get a reference to the audit log.
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    /// Get a reference to the rate limiter.
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Get a reference to the concurrency limiter.
+    pub fn concurrency_limiter(&self) -> &ConcurrencyLimiter {
+        &self.concurrency_limiter
+    }
+
+    /// Get the current data masker.
+    pub fn masker(&self) -> Arc<DataMasker> {
+        Arc::clone(&self.masker.read())
+    }
+
+    /// Get a reference to the confirmation store.
+    pub fn confirmations(&self) -> &ConfirmationStore {
+        &self.confirmations
+    }
+
+    /// Get a reference to the requests sent to the client.
+    pub fn client_requests(&self) -> &ClientRequests {
+        &self.client_requests
+    }
+
+    /// Mask the rows of query watch changes before they are returned.
+    pub(crate) fn mask_query_changes(&self, changes: &mut [QueryChange]) {
+        let masker = self.masker();
+        for change in changes {
+            masker.mask_rows(&mut change.added, None);
+            masker.mask_rows(&mut change.changed, None);
+            masker.mask_rows(&mut change.removed, None);
+        }
+    }
+
+    /// Format an error message in the configured error language.
+    pub async fn localize_error(&self, error: &ServerError) -> String {
+        self.error_localizer.localize(error, &self.executor).await
+    }
+
     /// Check if the server is in database mode (connected to specific database).
     pub fn is_database_mode(&self) -> bool {
-        self.config.is_database_mode()
+        self.config().is_database_mode()
     }
 
     /// Get the current database name if in database mode.
-    pub fn current_database(&self) -> Option<&str> {
-        self.config.current_database()
+    pub fn current_database(&self) -> Option<String> {
+        self.config().database.database.clone()
     }
 
     /// Validate a query using the configured security settings.
+    ///
+    /// With `MSSQL_REGISTERED_ONLY` or `MSSQL_PROCEDURES_ONLY` every query is
+    /// refused, since only registered queries or procedures may run.
     pub fn validate_query(&self, query: &str) -> Result<(), ServerError> {
+        if self.config().security.procedures_only {
+            return Err(ServerError::validation(
+                "SQL text is not accepted (MSSQL_PROCEDURES_ONLY); run an allowed procedure with execute_procedure",
+            ));
+        }
+        if self.config().security.registered_queries_only {
+            return Err(ServerError::validation(
+                "SQL text is not accepted (MSSQL_REGISTERED_ONLY); run a registered query with execute_registered",
+            ));
+        }
+
         // Validate using security module (it also checks query length)
-        let result = self.validator.validate(query)?;
+        let result = self.validator().validate(query)?;
 
         // ValidationResult.valid should be true if no error was returned
         if !result.valid {
@@ -208,14 +694,312 @@ impl MssqlMcpServer {
 
         Ok(())
     }
+
+    /// Why a tool is unavailable under the current configuration, if it is.
+    ///
+    /// Disabled tools are left out of the advertised tool list and calls to
+    /// them are refused.
+    pub fn tool_disabled_reason(&self, name: &str) -> Option<String> {
+        self.config().security.tool_disabled_reason(name)
+    }
+
+    /// Reload the configuration from the config file and environment.
+    ///
+    /// Only runtime settings are re-applied (see [`Config::with_runtime_settings`]);
+    /// with `apply` false the changes are reported without taking effect.
+    /// Returns the names of the settings that changed.
+    pub async fn apply_config_reload(&self, apply: bool) -> Result<Vec<String>, ServerError> {
+        let current = self.config();
+        let updated = current.with_runtime_settings(load_config().await?);
+        let changed = current.changed_settings(&updated);
+
+        // The registry file is re-read even when the settings are unchanged
+        let registry_queries = match &updated.security.query_registry {
+            Some(path) => read_registry_file(path)?,
+            None => Vec::new(),
+        };
+        if apply {
+            self.query_registry.replace_config_queries(registry_queries);
+        }
+        if !apply || changed.is_empty() {
+            return Ok(changed);
+        }
+
+        // Build everything that can fail before swapping anything in
+        let masker = DataMasker::new(&updated.security.masking)?;
+        let validator = QueryValidator::new(
+            updated.security.validation_mode,
+            updated.security.max_query_length,
+        )
+        .with_linked_servers(updated.security.allow_linked_servers);
+
+        *self.masker.write() = Arc::new(masker);
+        *self.validator.write() = Arc::new(validator);
+
+        let max_rows = updated.security.max_result_rows;
+        self.executor.set_max_rows(max_rows);
+        self.metadata.set_max_rows(max_rows);
+        self.transaction_manager.set_max_rows(max_rows);
+        self.transaction_manager
+            .set_deadlock_replays(updated.query.deadlock_replays);
+        self.session_manager.set_max_rows(max_rows);
+        self.session_manager
+            .set_limits(SessionLimits::from_config(&updated.session));
+        set_query_tagging(updated.query.tag_queries);
+        set_session_context_tagging(updated.query.session_context);
+        set_exact_decimals(updated.query.exact_decimals);
+        set_result_memory_budgets(
+            updated.query.query_memory_mb * 1024 * 1024,
+            updated.query.result_memory_mb * 1024 * 1024,
+        );
+        self.executor.slow_queries().configure(&updated.query);
+        self.query_cache.configure(&updated.query).await;
+
+        if updated.query.default_timeout != current.query.default_timeout {
+            let mut state = self.state.write().await;
+            state.set_server_default_timeout(updated.query.default_timeout.as_secs());
+        }
+
+        *self.config.write() = Arc::new(updated);
+        tracing::info!("Configuration reloaded: {}", changed.join(", "));
+        Ok(changed)
+    }
+
+    /// Reload the configuration whenever the process receives SIGHUP.
+    ///
+    /// Must be called within a Tokio runtime.
+    #[cfg(unix)]
+    pub fn reload_on_hangup(&self) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    tracing::error!("Failed to install SIGHUP handler: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                tracing::info!("Received SIGHUP, reloading configuration...");
+                match server.apply_config_reload(true).await {
+                    Ok(changed) if changed.is_empty() => {
+                        tracing::info!("Configuration unchanged")
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Configuration reload failed: {}", e),
+                }
+                if let Err(e) = server.apply_credential_rotation(false).await {
+                    tracing::warn!("Credential rotation failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Reload the database credentials and re-establish the pools with them.
+    ///
+    /// The credentials are read from the environment, config file,
+    /// `MSSQL_PASSWORD_FILE` and secrets provider again. Unless `force` is set, nothing happens
+    /// when they are unchanged, and `None` is returned. Requests running on
+    /// the old connections finish on them; new pinned sessions and
+    /// transactions connect with the new credentials.
+    pub async fn apply_credential_rotation(
+        &self,
+        force: bool,
+    ) -> Result<Option<RotationOutcome>, ServerError> {
+        let auth = load_config().await?.database.auth;
+        if !force && self.credential_rotation.is_current(&auth) {
+            return Ok(None);
+        }
+
+        // New transactions, pinned sessions and the config switch to the new
+        // credentials together with the pools
+        let outcome = self
+            .credential_rotation
+            .rotate(&auth, || {
+                self.transaction_manager.set_auth(auth.clone());
+                self.session_manager.set_auth(auth.clone());
+                let mut updated = Config::clone(&self.config());
+                updated.database.auth = auth.clone();
+                *self.config.write() = Arc::new(updated);
+            })
+            .await?;
+        Ok(Some(outcome))
+    }
+
+    /// Check for rotated credentials every `MSSQL_CREDENTIAL_CHECK_INTERVAL`.
+    ///
+    /// Does nothing when the interval is zero. Must be called within a Tokio
+    /// runtime.
+    pub fn watch_credentials(&self) {
+        let interval = self.config().database.pool.credential_check_interval;
+        if interval.is_zero() {
+            return;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if server.pool.is_closed() {
+                    break;
+                }
+                if let Err(e) = server.apply_credential_rotation(false).await {
+                    tracing::warn!("Credential rotation failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Get the effective server major version used for compatibility checks.
+    ///
+    /// Detected once and cached; `None` if the version could not be determined.
+    pub async fn server_major_version(&self) -> Option<u32> {
+        *self
+            .server_version
+            .get_or_init(|| async {
+                match self.metadata.get_server_info().await {
+                    Ok(info) => compatibility::effective_major_version(
+                        &info.product_version,
+                        info.engine_edition,
+                    ),
+                    Err(e) => {
+                        tracing::debug!("Could not detect server version: {}", e);
+                        None
+                    }
+                }
+            })
+            .await
+    }
+
+    /// List features used by a query that the connected server does not support.
+    pub async fn compatibility_issues(&self, query: &str) -> Vec<CompatibilityIssue> {
+        match self.server_major_version().await {
+            Some(version) => compatibility::check_compatibility(query, version),
+            None => Vec::new(),
+        }
+    }
+
+    /// Check a query against the connected server's version before execution.
+    pub async fn check_compatibility(&self, query: &str) -> Result<(), ServerError> {
+        let Some(version) = self.server_major_version().await else {
+            return Ok(());
+        };
+        let issues = compatibility::check_compatibility(query, version);
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ServerError::validation(compatibility::format_issues(
+                &issues, version,
+            )))
+        }
+    }
+
+    /// Apply the query governor (`security.governor`) before execution.
+    ///
+    /// Rejects statements whose estimated plan cost exceeds the limit, and
+    /// SELECTs whose estimated result is too large unless only warnings are
+    /// configured. Returns the query with the MAXDOP hint and row limit
+    /// applied. The plan is estimated in `database` when given.
+    pub async fn govern_query(
+        &self,
+        query: &str,
+        database: Option<&str>,
+        max_rows: usize,
+    ) -> Result<governor::GovernedQuery, ServerError> {
+        let config = self.config().security.governor.clone();
+        let max_cost = config
+            .max_estimated_cost
+            .filter(|_| governor::has_plan(query));
+        let max_result_mb = config
+            .max_result_estimate_mb
+            .filter(|_| governor::is_select(query));
+        let in_database = |sql: String| match database {
+            Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), sql),
+            None => sql,
+        };
+
+        let mut warnings = Vec::new();
+        if max_cost.is_some() || max_result_mb.is_some() {
+            let plan = self
+                .executor
+                .execute_with_showplan_xml(&in_database(query.to_string()), "estimated")
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|documents| ExecutionPlan::parse(&documents).map_err(|e| e.to_string()));
+
+            if let Some(max_cost) = max_cost {
+                let plan = plan.as_ref().map_err(|e| {
+                    ServerError::validation(format!("Could not estimate query cost: {}", e))
+                })?;
+                let cost: f64 = plan
+                    .statements
+                    .iter()
+                    .filter_map(|s| s.estimated_cost)
+                    .sum();
+                governor::check_cost(cost, max_cost).map_err(ServerError::validation)?;
+            }
+
+            // The size check is advisory: queries that cannot be estimated still run
+            if let (Some(max_mb), Ok(plan)) = (max_result_mb, &plan) {
+                let rows = plan
+                    .statements
+                    .iter()
+                    .rev()
+                    .find_map(|s| s.estimated_rows)
+                    .unwrap_or(0.0)
+                    .min(max_rows as f64);
+                let describe = in_database(describe_query_sql(query));
+                match self.executor.execute(&describe).await {
+                    Ok(result) => {
+                        let columns: Vec<DescribedColumn> = result
+                            .rows
+                            .iter()
+                            .filter_map(DescribedColumn::from_row)
+                            .collect();
+                        let bytes = governor::estimate_result_bytes(&columns, rows);
+                        if let Err(message) = governor::check_result_size(bytes, rows, max_mb) {
+                            if !config.result_estimate_warn_only {
+                                return Err(ServerError::validation(message));
+                            }
+                            tracing::warn!("Query governor: {}", message);
+                            warnings.push(message);
+                        }
+                    }
+                    Err(e) => tracing::debug!("Could not estimate result size: {}", e),
+                }
+            }
+        }
+
+        let mut governed = governor::rewrite_query(query, &config, max_rows);
+        if !governed.changes.is_empty() {
+            tracing::debug!("Query governor {}", governed.changes.join(", "));
+        }
+        governed.warnings = warnings;
+        Ok(governed)
+    }
+}
+
+/// Load the configuration on a blocking thread, since secrets providers wait
+/// on network requests.
+async fn load_config() -> Result<Config, ServerError> {
+    tokio::task::spawn_blocking(Config::load)
+        .await
+        .map_err(|e| ServerError::internal(format!("Configuration load failed: {e}")))?
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::{
-        AuthConfig, DatabaseConfig, PoolConfig, QueryConfig, RetryConfig, SecurityConfig,
-        SessionConfig, TdsVersionConfig, TimeoutsConfig,
+        AuditConfig, AuthConfig, ConcurrencyConfig, DatabaseConfig, GovernorConfig, MaskingConfig,
+        PoolConfig, QueryConfig, RateLimitConfig, RetryConfig, SecurityConfig, SessionConfig,
+        TdsVersionConfig, TimeoutsConfig,
     };
     use crate::security::ValidationMode;
     use std::time::Duration;
@@ -226,6 +1010,7 @@ mod tests {
                 host: "localhost".to_string(),
                 port: 1433,
                 instance: None,
+                resolve_instance_port: false,
                 database: Some("master".to_string()),
                 auth: AuthConfig::SqlServer {
                     username: "sa".to_string(),
@@ -239,12 +1024,33 @@ mod tests {
                 mars: false,
                 retry: RetryConfig::default(),
                 tds_version: TdsVersionConfig::default(),
+                application_intent: Default::default(),
+                multi_subnet_failover: false,
+                failover_partners: Vec::new(),
+                read_pool: false,
+                read_pool_host: None,
             },
             security: SecurityConfig {
                 validation_mode: ValidationMode::Standard,
                 injection_detection: true,
                 max_query_length: 100_000,
                 max_result_rows: 1000,
+                export_directories: Vec::new(),
+                import_directories: Vec::new(),
+                rate_limit: RateLimitConfig::default(),
+                concurrency: ConcurrencyConfig::default(),
+                masking: MaskingConfig::default(),
+                require_confirmation: false,
+                elicit_confirmation: true,
+                confirm_bulk_rows: 10_000,
+                governor: GovernorConfig::default(),
+                query_registry: None,
+                registered_queries_only: false,
+                allow_linked_servers: false,
+                allowed_procedures: Vec::new(),
+                procedures_only: false,
+                enabled_tools: Vec::new(),
+                disabled_tools: Vec::new(),
             },
             query: QueryConfig {
                 default_timeout: Duration::from_secs(30),
@@ -253,8 +1059,24 @@ mod tests {
                 cache_ttl: Duration::from_secs(60),
                 cache_max_size_mb: 100,
                 cache_max_entries: 1000,
+                error_language: None,
+                retry_read_only: true,
+                retry_statements: false,
+                deadlock_replays: 0,
+                result_ref_threshold_kb: 256,
+                tag_queries: false,
+                session_context: false,
+                slow_query_threshold_ms: 0,
+                slow_query_plans: false,
+                slow_query_log_size: 100,
+                exact_decimals: true,
+                binary_format: Default::default(),
+                binary_preview_bytes: 64,
+                query_memory_mb: 256,
+                result_memory_mb: 1024,
             },
             session: SessionConfig::default(),
+            audit: AuditConfig::default(),
         }
     }
 
@@ -0,0 +1,239 @@
+//! Persistence of finished async sessions.
+//!
+//! Completed, failed and cancelled sessions are written to a directory, one
+//! JSON file per session, so their results can still be fetched after the
+//! server restarts. The directory is capped in size by dropping the oldest
+//! sessions, and sessions past the result retention are not restored.
+
+use crate::state::QuerySession;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+/// Directory of persisted sessions.
+pub struct SessionStore {
+    /// Directory sessions are persisted to (`None` = memory only).
+    dir: Option<PathBuf>,
+    /// Maximum total size of the persisted sessions in bytes.
+    max_bytes: u64,
+    /// Sessions older than this are not restored.
+    retention: Duration,
+}
+
+impl SessionStore {
+    /// Create a store persisting to `dir` if given.
+    pub fn new(dir: Option<PathBuf>, max_bytes: u64, retention: Duration) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            retention,
+        }
+    }
+
+    /// Persist a finished session, logging failures (persistence is best effort).
+    ///
+    /// Running sessions are skipped: only finished sessions can be restored.
+    pub fn save(&self, session: &QuerySession) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        if session.is_running() {
+            return;
+        }
+        let json = match serde_json::to_vec(session) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize session {}: {}", session.id, e);
+                return;
+            }
+        };
+        if json.len() as u64 > self.max_bytes {
+            warn!(
+                "Not persisting session {}: {} bytes exceeds the {} byte store limit",
+                session.id,
+                json.len(),
+                self.max_bytes
+            );
+            return;
+        }
+
+        let path = session_path(dir, &session.id);
+        let temp = path.with_extension("json.tmp");
+        let result = std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(&temp, &json))
+            .and_then(|()| std::fs::rename(&temp, &path));
+        if let Err(e) = result {
+            warn!(
+                "Failed to persist session {} to {}: {}",
+                session.id,
+                path.display(),
+                e
+            );
+            let _ = std::fs::remove_file(&temp);
+            return;
+        }
+        self.enforce_limit(dir);
+    }
+
+    /// Remove a persisted session.
+    pub fn remove(&self, id: &str) {
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_file(session_path(dir, id));
+        }
+    }
+
+    /// Read persisted sessions, deleting those past the retention time and
+    /// skipping files that are invalid.
+    pub fn load(&self) -> Vec<QuerySession> {
+        let Some(dir) = &self.dir else {
+            return Vec::new();
+        };
+        let mut sessions = Vec::new();
+        for (path, _, _) in session_files(dir) {
+            let session = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    serde_json::from_slice::<QuerySession>(&bytes).map_err(|e| e.to_string())
+                });
+            match session {
+                Ok(session) if self.is_expired(&session) => {
+                    debug!("Dropping expired persisted session {}", session.id);
+                    let _ = std::fs::remove_file(&path);
+                }
+                Ok(session) if !session.is_running() => sessions.push(session),
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Ignoring invalid persisted session {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        sessions
+    }
+
+    /// Whether a session finished longer ago than the retention time.
+    fn is_expired(&self, session: &QuerySession) -> bool {
+        (Utc::now() - session.updated_at)
+            .to_std()
+            .is_ok_and(|age| age > self.retention)
+    }
+
+    /// Delete the oldest sessions until the directory fits the size limit.
+    fn enforce_limit(&self, dir: &Path) {
+        let mut files = session_files(dir);
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                debug!(
+                    "Dropped persisted session {} to fit the store limit",
+                    path.display()
+                );
+                total -= size;
+            }
+        }
+    }
+}
+
+/// File a session is persisted to.
+fn session_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+/// Persisted session files with their size and modification time.
+fn session_files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), metadata.len(), modified))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::QueryResult;
+    use crate::state::SessionStatus;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("sessions_{}", uuid::Uuid::new_v4()))
+    }
+
+    fn completed(query: &str) -> QuerySession {
+        let mut session = QuerySession::new(query.to_string());
+        session.complete(QueryResult::empty());
+        session
+    }
+
+    #[test]
+    fn test_finished_sessions_survive_restart() {
+        let dir = temp_dir();
+        let store = SessionStore::new(Some(dir.clone()), 1024 * 1024, Duration::from_secs(3600));
+
+        let session = completed("SELECT 1");
+        store.save(&session);
+        let mut failed = QuerySession::new("SELECT 2".to_string());
+        failed.fail("boom".to_string());
+        store.save(&failed);
+        // Running sessions cannot be restored
+        store.save(&QuerySession::new("SELECT 3".to_string()));
+
+        let restarted =
+            SessionStore::new(Some(dir.clone()), 1024 * 1024, Duration::from_secs(3600));
+        let mut restored = restarted.load();
+        restored.sort_by_key(|s| s.query.clone());
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].id, session.id);
+        assert_eq!(restored[0].status, SessionStatus::Completed);
+        assert!(restored[0].result.is_some());
+        assert_eq!(restored[1].error.as_deref(), Some("boom"));
+
+        restarted.remove(&session.id);
+        assert_eq!(restarted.load().len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_expired_and_oversized_sessions_dropped() {
+        let dir = temp_dir();
+        let store = SessionStore::new(Some(dir.clone()), 1024 * 1024, Duration::from_secs(60));
+        let mut old = completed("SELECT 1");
+        old.updated_at = Utc::now() - chrono::Duration::seconds(120);
+        store.save(&old);
+        assert!(store.load().is_empty());
+        assert!(session_files(&dir).is_empty());
+
+        // Only the newest session fits
+        let size = serde_json::to_vec(&completed("SELECT 1")).unwrap().len() as u64;
+        let store = SessionStore::new(Some(dir.clone()), size + 10, Duration::from_secs(60));
+        store.save(&completed("SELECT 1"));
+        std::thread::sleep(Duration::from_millis(20));
+        let newest = completed("SELECT 2");
+        store.save(&newest);
+        let restored = store.load();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, newest.id);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_memory_only_store() {
+        let store = SessionStore::new(None, 1024, Duration::from_secs(60));
+        store.save(&completed("SELECT 1"));
+        assert!(store.load().is_empty());
+    }
+}
@@ -3,11 +3,13 @@
 //! This module provides:
 //! - Signal handling (SIGTERM, SIGINT, Ctrl+C)
 //! - Connection draining with configurable timeout
+//! - Cancellation and persistence of async sessions still running after the drain
 //! - Active transaction cleanup
 //! - Cache persistence (if enabled)
 //! - Proper resource cleanup
 
-use crate::state::SharedState;
+use crate::session_store::SessionStore;
+use crate::state::{SessionStatus, SharedState};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -50,6 +52,9 @@ pub struct ShutdownController {
 
     /// Force shutdown timeout (after drain timeout).
     force_timeout: Duration,
+
+    /// Whether to wait for running async sessions before cancelling them.
+    wait_for_sessions: bool,
 }
 
 /// Shutdown phases for coordinated cleanup.
@@ -61,6 +66,9 @@ pub enum ShutdownPhase {
     /// No longer accepting new requests.
     DrainingRequests,
 
+    /// Cancelling async sessions still running.
+    CancellingSessions,
+
     /// Rolling back active transactions.
     CleaningTransactions,
 
@@ -79,6 +87,7 @@ impl std::fmt::Display for ShutdownPhase {
         match self {
             ShutdownPhase::Initiated => write!(f, "initiated"),
             ShutdownPhase::DrainingRequests => write!(f, "draining_requests"),
+            ShutdownPhase::CancellingSessions => write!(f, "cancelling_sessions"),
             ShutdownPhase::CleaningTransactions => write!(f, "cleaning_transactions"),
             ShutdownPhase::ClosingConnections => write!(f, "closing_connections"),
             ShutdownPhase::FlushingCaches => write!(f, "flushing_caches"),
@@ -104,9 +113,17 @@ impl ShutdownController {
             phase_sender,
             drain_timeout,
             force_timeout,
+            wait_for_sessions: true,
         }
     }
 
+    /// Set whether shutdown waits for running async sessions (up to the drain
+    /// timeout) before cancelling them.
+    pub fn with_session_wait(mut self, wait: bool) -> Self {
+        self.wait_for_sessions = wait;
+        self
+    }
+
     /// Get a shutdown signal receiver.
     pub fn signal(&self) -> ShutdownSignal {
         ShutdownSignal {
@@ -148,29 +165,45 @@ impl ShutdownController {
     /// This method:
     /// 1. Stops accepting new requests
     /// 2. Waits for in-flight requests to complete (up to drain_timeout)
-    /// 3. Rolls back any active transactions
-    /// 4. Closes database connections
-    /// 5. Flushes caches
+    /// 3. Cancels async sessions still running
+    /// 4. Rolls back any active transactions
+    /// 5. Closes database connections
+    /// 6. Flushes caches
     pub async fn graceful_shutdown(&self, state: &SharedState) {
+        self.run_shutdown(state, None).await;
+    }
+
+    /// Perform graceful shutdown, persisting the sessions cancelled by it to `store`.
+    pub async fn graceful_shutdown_with_store(&self, state: &SharedState, store: &SessionStore) {
+        self.run_shutdown(state, Some(store)).await;
+    }
+
+    async fn run_shutdown(&self, state: &SharedState, store: Option<&SessionStore>) {
         self.shutdown();
 
         // Phase 1: Draining requests
         self.notify_phase(ShutdownPhase::DrainingRequests);
-        self.drain_requests(state).await;
+        if self.wait_for_sessions {
+            self.drain_requests(state).await;
+        }
 
-        // Phase 2: Clean up transactions
+        // Phase 2: Cancel sessions that did not finish
+        self.notify_phase(ShutdownPhase::CancellingSessions);
+        self.cancel_sessions(state, store).await;
+
+        // Phase 3: Clean up transactions
         self.notify_phase(ShutdownPhase::CleaningTransactions);
         self.cleanup_transactions(state).await;
 
-        // Phase 3: Close connections (pool cleanup is handled by Drop)
+        // Phase 4: Close connections (pool cleanup is handled by Drop)
         self.notify_phase(ShutdownPhase::ClosingConnections);
         // Connection pool cleanup happens automatically via mssql-driver-pool's drop
 
-        // Phase 4: Flush caches
+        // Phase 5: Flush caches
         self.notify_phase(ShutdownPhase::FlushingCaches);
         self.flush_caches().await;
 
-        // Phase 5: Complete
+        // Phase 6: Complete
         self.notify_phase(ShutdownPhase::Complete);
         info!("Graceful shutdown complete");
     }
@@ -206,6 +239,60 @@ impl ShutdownController {
         }
     }
 
+    /// Cancel the async sessions still running and persist them.
+    ///
+    /// Queries get a native cancellation (bounded by the force timeout), and
+    /// each session is marked cancelled with the reason so clients reading it
+    /// after a restart know why it stopped.
+    async fn cancel_sessions(&self, state: &SharedState, store: Option<&SessionStore>) {
+        let handles: Vec<_> = {
+            let s = state.read().await;
+            s.list_sessions_by_status(SessionStatus::Running)
+                .iter()
+                .filter_map(|session| s.get_cancel_handle(&session.id).cloned())
+                .collect()
+        };
+        if !handles.is_empty() {
+            let cancels = futures_util::future::join_all(handles.iter().map(|h| h.cancel()));
+            if tokio::time::timeout(self.force_timeout, cancels)
+                .await
+                .is_err()
+            {
+                warn!("Timed out sending query cancellations during shutdown");
+            }
+        }
+
+        let cancelled: Vec<_> = {
+            let mut s = state.write().await;
+            let ids: Vec<String> = s
+                .list_sessions_by_status(SessionStatus::Running)
+                .iter()
+                .map(|session| session.id.clone())
+                .collect();
+            ids.iter()
+                .filter_map(|id| {
+                    s.remove_cancel_handle(id);
+                    let session = s.get_session_mut(id)?;
+                    session.cancel_with_reason("Cancelled by server shutdown".to_string());
+                    Some(session.clone())
+                })
+                .collect()
+        };
+        if cancelled.is_empty() {
+            return;
+        }
+
+        warn!(
+            "Cancelled {} running sessions during shutdown",
+            cancelled.len()
+        );
+        if let Some(store) = store {
+            for session in &cancelled {
+                store.save(session);
+            }
+        }
+    }
+
     /// Clean up active transactions by rolling them back.
     async fn cleanup_transactions(&self, state: &SharedState) {
         let mut s = state.write().await;
@@ -308,7 +395,7 @@ pub async fn install_signal_handlers(controller: SharedShutdownController) {
     // Handle SIGTERM (Unix only)
     #[cfg(unix)]
     {
-        let term_controller = controller.clone();
+        let term_controller = controller;
         tokio::spawn(async move {
             match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
                 Ok(mut sigterm) => {
@@ -322,24 +409,6 @@ pub async fn install_signal_handlers(controller: SharedShutdownController) {
             }
         });
     }
-
-    // Handle SIGHUP (Unix only) - often used to reload config, but we use it for shutdown
-    #[cfg(unix)]
-    {
-        let hup_controller = controller;
-        tokio::spawn(async move {
-            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
-                Ok(mut sighup) => {
-                    sighup.recv().await;
-                    info!("Received SIGHUP, initiating shutdown...");
-                    hup_controller.shutdown();
-                }
-                Err(e) => {
-                    error!("Failed to install SIGHUP handler: {}", e);
-                }
-            }
-        });
-    }
 }
 
 /// Shutdown configuration.
@@ -356,6 +425,9 @@ pub struct ShutdownConfig {
 
     /// Whether to flush caches before shutdown.
     pub flush_caches: bool,
+
+    /// Whether to wait for running async sessions before cancelling them.
+    pub wait_for_sessions: bool,
 }
 
 impl Default for ShutdownConfig {
@@ -365,6 +437,7 @@ impl Default for ShutdownConfig {
             force_timeout: Duration::from_secs(10),
             rollback_transactions: true,
             flush_caches: true,
+            wait_for_sessions: true,
         }
     }
 }
@@ -394,6 +467,10 @@ impl ShutdownConfig {
             config.flush_caches = flush.to_lowercase() == "true" || flush == "1";
         }
 
+        if let Ok(wait) = std::env::var("MSSQL_SHUTDOWN_WAIT_SESSIONS") {
+            config.wait_for_sessions = wait.to_lowercase() == "true" || wait == "1";
+        }
+
         config
     }
 }
@@ -485,6 +562,36 @@ mod tests {
         assert!(controller_clone.is_shutting_down());
     }
 
+    #[tokio::test]
+    async fn test_shutdown_cancels_running_sessions() {
+        let controller = ShutdownController::new().with_session_wait(false);
+        let state = new_shared_state();
+        let dir = std::env::temp_dir().join(format!("shutdown_{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(Some(dir.clone()), 1024 * 1024, Duration::from_secs(3600));
+
+        let id = state
+            .write()
+            .await
+            .create_session("SELECT 1".to_string(), 10)
+            .unwrap();
+        let start = std::time::Instant::now();
+        controller
+            .graceful_shutdown_with_store(&state, &store)
+            .await;
+        // Without waiting, shutdown does not sit out the drain timeout
+        assert!(start.elapsed() < controller.drain_timeout());
+
+        let restored = store.load();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, id);
+        assert_eq!(restored[0].status, SessionStatus::Cancelled);
+        assert_eq!(
+            restored[0].error.as_deref(),
+            Some("Cancelled by server shutdown")
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_shutdown_config_defaults() {
         let config = ShutdownConfig::default();
@@ -492,6 +599,7 @@ mod tests {
         assert_eq!(config.force_timeout, Duration::from_secs(10));
         assert!(config.rollback_transactions);
         assert!(config.flush_caches);
+        assert!(config.wait_for_sessions);
     }
 
     #[test]
@@ -501,6 +609,10 @@ mod tests {
             ShutdownPhase::DrainingRequests.to_string(),
             "draining_requests"
         );
+        assert_eq!(
+            ShutdownPhase::CancellingSessions.to_string(),
+            "cancelling_sessions"
+        );
         assert_eq!(
             ShutdownPhase::CleaningTransactions.to_string(),
             "cleaning_transactions"
@@ -1,13 +1,20 @@
 //! Session state management for async query sessions and transactions.
 
+use crate::cache::estimate_result_size;
+use crate::config::SessionConfig;
+use crate::constants::{MAX_CLIENT_CONTEXTS, MAX_PLAN_BASELINES};
+use crate::database::plan::ExecutionPlan;
 use crate::database::QueryResult;
 use crate::error::ServerError;
+use crate::telemetry::client_context_key;
 use chrono::{DateTime, Utc};
 use mssql_client::CancelHandle;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::debug;
 use uuid::Uuid;
 
 /// Shared state wrapper type.
@@ -18,6 +25,45 @@ pub fn new_shared_state() -> SharedState {
     Arc::new(RwLock::new(SessionState::new()))
 }
 
+/// Enforce the session retention limits every `interval` until the state is dropped.
+///
+/// `on_removed` is called with the ID of every session removed. Must be
+/// called within a Tokio runtime.
+pub fn spawn_session_cleanup<F>(
+    state: &SharedState,
+    interval: Duration,
+    retention: SessionRetention,
+    on_removed: F,
+) where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    if interval.is_zero() {
+        debug!("Async session cleanup disabled");
+        return;
+    }
+
+    let state: Weak<RwLock<SessionState>> = Arc::downgrade(state);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+            let removed = state.write().await.enforce_session_retention(&retention);
+            if !removed.is_empty() {
+                debug!("Removed {} finished async sessions", removed.len());
+            }
+            for id in &removed {
+                on_removed(id);
+            }
+        }
+    });
+}
+
 /// Session state for managing async queries, transactions, and server state.
 #[derive(Debug, Default)]
 pub struct SessionState {
@@ -34,11 +80,28 @@ pub struct SessionState {
     /// Server initialization status.
     initialized: bool,
 
-    /// Current default timeout in seconds.
+    /// Server-wide default timeout in seconds.
     default_timeout_seconds: u64,
 
+    /// Current database and timeout per client, keyed by
+    /// [`RequestContext::context_key`](crate::telemetry::RequestContext::context_key).
+    clients: HashMap<String, ClientContext>,
+}
+
+/// Settings a client changed for itself.
+#[derive(Debug)]
+struct ClientContext {
     /// Current database name (for multi-database support).
     current_database: Option<String>,
+
+    /// Default timeout override in seconds.
+    default_timeout_seconds: Option<u64>,
+
+    /// Plans saved by `compare_plans`, oldest first.
+    plan_baselines: Vec<(String, ExecutionPlan)>,
+
+    /// When the client last changed a setting.
+    updated_at: Instant,
 }
 
 /// Transaction isolation level.
@@ -134,13 +197,32 @@ pub struct TransactionState {
     /// Number of statements executed in this transaction.
     pub statement_count: u32,
 
-    /// List of savepoints.
-    pub savepoints: Vec<String>,
+    /// Savepoints in creation order.
+    pub savepoints: Vec<Savepoint>,
+
+    /// Database the transaction's connection is using, if one was chosen.
+    pub database: Option<String>,
+
+    /// Tables modified by the transaction, whose cached results are dropped on commit.
+    pub modified_tables: Vec<String>,
 
     /// Transaction status.
     pub status: TransactionStatus,
 }
 
+/// A savepoint created within a transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct Savepoint {
+    /// Savepoint name.
+    pub name: String,
+
+    /// When the savepoint was created.
+    pub created_at: DateTime<Utc>,
+
+    /// Statements executed in the transaction before the savepoint.
+    pub statement_count: u32,
+}
+
 /// Status of a transaction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionStatus {
@@ -171,6 +253,8 @@ impl TransactionState {
             last_activity: now,
             statement_count: 0,
             savepoints: Vec::new(),
+            database: None,
+            modified_tables: Vec::new(),
             status: TransactionStatus::Active,
         }
     }
@@ -182,9 +266,30 @@ impl TransactionState {
     }
 
     /// Add a savepoint.
+    ///
+    /// Reusing a name moves the savepoint to the current position, matching
+    /// SQL Server, where a rollback goes to the most recent savepoint of that name.
     pub fn add_savepoint(&mut self, name: String) {
-        if !self.savepoints.contains(&name) {
-            self.savepoints.push(name);
+        self.savepoints.retain(|sp| sp.name != name);
+        self.savepoints.push(Savepoint {
+            name,
+            created_at: Utc::now(),
+            statement_count: self.statement_count,
+        });
+        self.last_activity = Utc::now();
+    }
+
+    /// Check whether a savepoint with this name exists.
+    pub fn has_savepoint(&self, name: &str) -> bool {
+        self.savepoints.iter().any(|sp| sp.name == name)
+    }
+
+    /// Record a rollback to a savepoint.
+    ///
+    /// The savepoint stays usable; savepoints created after it are discarded.
+    pub fn rollback_to_savepoint(&mut self, name: &str) {
+        if let Some(pos) = self.savepoints.iter().rposition(|sp| sp.name == name) {
+            self.savepoints.truncate(pos + 1);
         }
         self.last_activity = Utc::now();
     }
@@ -222,6 +327,7 @@ pub struct TransactionSummary {
     pub started_at: String,
     pub statement_count: u32,
     pub age_seconds: i64,
+    pub database: Option<String>,
 }
 
 impl From<&TransactionState> for TransactionSummary {
@@ -234,6 +340,7 @@ impl From<&TransactionState> for TransactionSummary {
             started_at: tx.started_at.to_rfc3339(),
             statement_count: tx.statement_count,
             age_seconds: tx.age_seconds(),
+            database: tx.database.clone(),
         }
     }
 }
@@ -266,7 +373,7 @@ impl std::fmt::Display for SessionStatus {
 }
 
 /// An async query session.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuerySession {
     /// Unique session identifier.
     pub id: String,
@@ -330,6 +437,12 @@ impl QuerySession {
         self.updated_at = Utc::now();
     }
 
+    /// Mark the session as cancelled, recording why.
+    pub fn cancel_with_reason(&mut self, reason: String) {
+        self.cancel();
+        self.error = Some(reason);
+    }
+
     /// Update progress.
     pub fn set_progress(&mut self, progress: u8) {
         self.progress = progress.min(100);
@@ -345,6 +458,35 @@ impl QuerySession {
     pub fn age_seconds(&self) -> i64 {
         (Utc::now() - self.created_at).num_seconds()
     }
+
+    /// Approximate memory held by the session's query, result and error.
+    pub fn size_bytes(&self) -> usize {
+        self.query.len()
+            + self.result.as_ref().map_or(0, estimate_result_size)
+            + self.error.as_ref().map_or(0, String::len)
+    }
+}
+
+/// Limits on the finished async sessions kept in memory; zero disables a limit.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionRetention {
+    /// Remove finished sessions last updated longer ago than this.
+    pub max_age: Duration,
+    /// Keep at most this many finished sessions.
+    pub max_count: usize,
+    /// Keep at most this many bytes of finished session results.
+    pub max_bytes: usize,
+}
+
+impl SessionRetention {
+    /// Take the retention limits from the session configuration.
+    pub fn from_config(config: &SessionConfig) -> Self {
+        Self {
+            max_age: config.result_retention,
+            max_count: config.max_finished_sessions,
+            max_bytes: config.session_memory_mb * 1024 * 1024,
+        }
+    }
 }
 
 /// Summary of a session for listing.
@@ -378,7 +520,7 @@ impl SessionState {
             cancel_handles: HashMap::new(),
             initialized: false,
             default_timeout_seconds: 30,
-            current_database: None,
+            clients: HashMap::new(),
         }
     }
 
@@ -392,16 +534,51 @@ impl SessionState {
         self.initialized
     }
 
-    /// Get the default timeout.
+    /// Get the default timeout of the current client.
     pub fn default_timeout(&self) -> u64 {
-        self.default_timeout_seconds
+        self.client()
+            .and_then(|client| client.default_timeout_seconds)
+            .unwrap_or(self.default_timeout_seconds)
     }
 
-    /// Set the default timeout.
+    /// Set the default timeout of the current client.
     pub fn set_default_timeout(&mut self, seconds: u64) {
+        self.client_mut().default_timeout_seconds = Some(seconds);
+    }
+
+    /// Set the default timeout of clients that did not choose their own.
+    pub fn set_server_default_timeout(&mut self, seconds: u64) {
         self.default_timeout_seconds = seconds;
     }
 
+    /// Settings of the client making the current request.
+    fn client(&self) -> Option<&ClientContext> {
+        self.clients.get(&client_context_key())
+    }
+
+    /// Settings of the client making the current request, created on first use.
+    fn client_mut(&mut self) -> &mut ClientContext {
+        let key = client_context_key();
+        if !self.clients.contains_key(&key) && self.clients.len() >= MAX_CLIENT_CONTEXTS {
+            let oldest = self
+                .clients
+                .iter()
+                .min_by_key(|(_, client)| client.updated_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.clients.remove(&oldest);
+            }
+        }
+        let client = self.clients.entry(key).or_insert_with(|| ClientContext {
+            current_database: None,
+            default_timeout_seconds: None,
+            plan_baselines: Vec::new(),
+            updated_at: Instant::now(),
+        });
+        client.updated_at = Instant::now();
+        client
+    }
+
     /// Create a new session and return its ID.
     pub fn create_session(
         &mut self,
@@ -447,6 +624,11 @@ impl SessionState {
             .collect()
     }
 
+    /// Restore a session persisted before a restart, unless one with the same ID exists.
+    pub fn restore_session(&mut self, session: QuerySession) {
+        self.sessions.entry(session.id.clone()).or_insert(session);
+    }
+
     /// Remove a session.
     pub fn remove_session(&mut self, id: &str) -> Option<QuerySession> {
         self.sessions.remove(id)
@@ -464,6 +646,60 @@ impl SessionState {
         });
     }
 
+    /// Remove finished sessions beyond the retention limits and return their IDs.
+    ///
+    /// Sessions are kept newest first while they fit the count and memory
+    /// limits; running sessions are never removed.
+    pub fn enforce_session_retention(&mut self, retention: &SessionRetention) -> Vec<String> {
+        let now = Utc::now();
+        let mut finished: Vec<&QuerySession> =
+            self.sessions.values().filter(|s| !s.is_running()).collect();
+        finished.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+
+        let mut kept = 0;
+        let mut kept_bytes = 0;
+        let mut expired = Vec::new();
+        for session in finished {
+            let too_old = !retention.max_age.is_zero()
+                && (now - session.updated_at)
+                    .to_std()
+                    .is_ok_and(|age| age > retention.max_age);
+            let size = session.size_bytes();
+            let over_count = retention.max_count > 0 && kept >= retention.max_count;
+            let over_bytes = retention.max_bytes > 0 && kept_bytes + size > retention.max_bytes;
+            if too_old || over_count || over_bytes {
+                expired.push(session.id.clone());
+            } else {
+                kept += 1;
+                kept_bytes += size;
+            }
+        }
+
+        for id in &expired {
+            self.sessions.remove(id);
+        }
+        expired
+    }
+
+    /// Remove the finished sessions matching `filter` and return their IDs.
+    pub fn purge_sessions(&mut self, filter: impl Fn(&QuerySession) -> bool) -> Vec<String> {
+        let purged: Vec<String> = self
+            .sessions
+            .values()
+            .filter(|s| !s.is_running() && filter(s))
+            .map(|s| s.id.clone())
+            .collect();
+        for id in &purged {
+            self.sessions.remove(id);
+        }
+        purged
+    }
+
+    /// Approximate memory held by all sessions.
+    pub fn session_memory_bytes(&self) -> usize {
+        self.sessions.values().map(QuerySession::size_bytes).sum()
+    }
+
     /// Get count of running sessions.
     pub fn running_session_count(&self) -> usize {
         self.sessions.values().filter(|s| s.is_running()).count()
@@ -604,14 +840,33 @@ impl SessionState {
     // Database Management
     // =========================================================================
 
-    /// Set the current database.
+    /// Set the current database of the current client.
     pub fn set_current_database(&mut self, database: Option<String>) {
-        self.current_database = database;
+        self.client_mut().current_database = database;
     }
 
-    /// Get the current database.
+    /// Get the current database of the current client.
     pub fn current_database(&self) -> Option<&str> {
-        self.current_database.as_deref()
+        self.client()?.current_database.as_deref()
+    }
+
+    /// Save a plan baseline for the current client, replacing any with the same key.
+    pub fn set_plan_baseline(&mut self, key: String, plan: ExecutionPlan) {
+        let baselines = &mut self.client_mut().plan_baselines;
+        baselines.retain(|(k, _)| *k != key);
+        if baselines.len() >= MAX_PLAN_BASELINES {
+            baselines.remove(0);
+        }
+        baselines.push((key, plan));
+    }
+
+    /// Get a plan baseline saved by the current client.
+    pub fn plan_baseline(&self, key: &str) -> Option<&ExecutionPlan> {
+        self.client()?
+            .plan_baselines
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, plan)| plan)
     }
 }
 
@@ -684,6 +939,61 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_session_retention() {
+        let mut state = SessionState::new();
+        let running = state.create_session("SELECT 0".to_string(), 10).unwrap();
+        let mut finished = Vec::new();
+        for i in 1..=4 {
+            let id = state.create_session(format!("SELECT {}", i), 10).unwrap();
+            let session = state.get_session_mut(&id).unwrap();
+            session.complete(crate::database::QueryResult::empty());
+            session.updated_at = Utc::now() - chrono::Duration::seconds(10 * i);
+            finished.push(id);
+        }
+
+        let mut retention = SessionRetention {
+            max_age: Duration::from_secs(35),
+            max_count: 0,
+            max_bytes: 0,
+        };
+        assert_eq!(
+            state.enforce_session_retention(&retention),
+            vec![finished[3].clone()]
+        );
+
+        // The newest finished sessions are kept
+        retention.max_count = 1;
+        let mut removed = state.enforce_session_retention(&retention);
+        removed.sort();
+        let mut expected = vec![finished[1].clone(), finished[2].clone()];
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert!(state.get_session(&finished[0]).is_some());
+
+        retention.max_bytes = 1;
+        state.enforce_session_retention(&retention);
+        assert_eq!(state.total_session_count(), 1);
+        assert!(state.get_session(&running).is_some());
+    }
+
+    #[test]
+    fn test_purge_sessions() {
+        let mut state = SessionState::new();
+        let running = state.create_session("SELECT 1".to_string(), 10).unwrap();
+        let failed = state.create_session("SELECT 2".to_string(), 10).unwrap();
+        state
+            .get_session_mut(&failed)
+            .unwrap()
+            .fail("boom".to_string());
+
+        assert!(state
+            .purge_sessions(|s| s.status == SessionStatus::Completed)
+            .is_empty());
+        assert_eq!(state.purge_sessions(|_| true), vec![failed]);
+        assert!(state.get_session(&running).is_some());
+    }
+
     #[test]
     fn test_truncate() {
         assert_eq!(truncate("short", 10), "short");
@@ -702,6 +1012,18 @@ mod tests {
 
         tx.add_savepoint("sp1".to_string());
         assert_eq!(tx.savepoints.len(), 1);
+        assert_eq!(tx.savepoints[0].statement_count, 1);
+
+        tx.record_statement();
+        tx.add_savepoint("sp2".to_string());
+        tx.add_savepoint("sp3".to_string());
+        tx.rollback_to_savepoint("sp2");
+        assert!(tx.has_savepoint("sp2"));
+        assert!(!tx.has_savepoint("sp3"));
+
+        tx.add_savepoint("sp1".to_string());
+        let names: Vec<&str> = tx.savepoints.iter().map(|sp| sp.name.as_str()).collect();
+        assert_eq!(names, ["sp2", "sp1"]);
 
         tx.commit();
         assert!(!tx.is_active());
@@ -779,4 +1101,39 @@ mod tests {
         state.set_current_database(None);
         assert!(state.current_database().is_none());
     }
+
+    #[tokio::test]
+    async fn test_client_isolation() {
+        use crate::telemetry::{scope_request, RequestContext};
+
+        let state = new_shared_state();
+        state.write().await.set_server_default_timeout(30);
+        let first = RequestContext::new()
+            .with_client("client/1.0")
+            .with_session("a");
+        let second = RequestContext::new()
+            .with_client("client/1.0")
+            .with_session("b");
+
+        scope_request(first.clone(), async {
+            let mut state = state.write().await;
+            state.set_current_database(Some("Sales".to_string()));
+            state.set_default_timeout(120);
+        })
+        .await;
+
+        scope_request(second, async {
+            let state = state.read().await;
+            assert!(state.current_database().is_none());
+            assert_eq!(state.default_timeout(), 30);
+        })
+        .await;
+
+        scope_request(first, async {
+            let state = state.read().await;
+            assert_eq!(state.current_database(), Some("Sales"));
+            assert_eq!(state.default_timeout(), 120);
+        })
+        .await;
+    }
 }
@@ -4,7 +4,7 @@
 //! - Metrics export (query counts, latency, errors)
 //! - Distributed tracing integration
 //! - Connection pool statistics
-//! - Correlation ID tracking for request tracing
+//! - Correlation ID tracking for request tracing (per tool invocation)
 //!
 //! When the `telemetry` feature is enabled, this module integrates with
 //! `mssql-client`'s OpenTelemetry instrumentation to provide:
@@ -14,9 +14,26 @@
 //!
 //! Requires the `telemetry` feature flag.
 
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use crate::audit::{self, AuditLog};
+use crate::constants::{MAX_QUERY_SHAPES, PROMETHEUS_QUERY_SHAPES};
+use crate::database::types::hex;
+use crate::database::with_read_intent;
+use crate::security::rate_limit::{self, RateLimiter};
+use crate::security::ConcurrencyLimiter;
+use crate::transport::{ClientRequests, NOTIFICATIONS_PROGRESS};
+use mcpkit::types::{Content, Tool, ToolOutput};
+use mcpkit::{Context, McpError, ProgressToken, ToolHandler};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
+use tracing::Instrument;
 use uuid::Uuid;
 
 // Re-export mssql-client instrumentation types when telemetry is enabled.
@@ -49,8 +66,14 @@ pub struct RequestContext {
     pub start_time: Instant,
     /// Client identifier (if available).
     pub client_id: Option<String>,
+    /// Transport session the request arrived on (HTTP `Mcp-Session-Id`).
+    pub session_id: Option<String>,
     /// Tool or resource being accessed.
     pub operation: Option<String>,
+    /// Rows read or affected by statements executed for the request.
+    rows: Arc<AtomicU64>,
+    /// Progress notifications, when the client sent a progress token.
+    progress: Option<ProgressReporter>,
 }
 
 impl RequestContext {
@@ -60,7 +83,10 @@ impl RequestContext {
             correlation_id: generate_short_correlation_id(),
             start_time: Instant::now(),
             client_id: None,
+            session_id: None,
             operation: None,
+            rows: Arc::new(AtomicU64::new(0)),
+            progress: None,
         }
     }
 
@@ -70,7 +96,10 @@ impl RequestContext {
             correlation_id: correlation_id.into(),
             start_time: Instant::now(),
             client_id: None,
+            session_id: None,
             operation: None,
+            rows: Arc::new(AtomicU64::new(0)),
+            progress: None,
         }
     }
 
@@ -80,17 +109,50 @@ impl RequestContext {
         self
     }
 
+    /// Set the transport session.
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Key under which per-client state (current database, timeout) is kept.
+    ///
+    /// Concurrent HTTP clients are told apart by their transport session;
+    /// otherwise the reported client identity is used.
+    pub fn context_key(&self) -> String {
+        self.session_id
+            .clone()
+            .or_else(|| self.client_id.clone())
+            .unwrap_or_else(|| rate_limit::ANONYMOUS_CLIENT.to_string())
+    }
+
     /// Set the operation name.
     pub fn with_operation(mut self, operation: impl Into<String>) -> Self {
         self.operation = Some(operation.into());
         self
     }
 
+    /// Report progress of the request through the given reporter.
+    pub fn with_progress(mut self, reporter: ProgressReporter) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
     /// Get the elapsed time since the request started.
     pub fn elapsed(&self) -> Duration {
         self.start_time.elapsed()
     }
 
+    /// Add to the number of rows read or affected by the request.
+    pub fn add_rows(&self, rows: u64) {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// Get the number of rows read or affected by the request so far.
+    pub fn rows(&self) -> u64 {
+        self.rows.load(Ordering::Relaxed)
+    }
+
     /// Create a log prefix for consistent logging format.
     pub fn log_prefix(&self) -> String {
         match (&self.client_id, &self.operation) {
@@ -108,6 +170,513 @@ impl Default for RequestContext {
     }
 }
 
+/// Sends `notifications/progress` for the progress token of a tool call.
+///
+/// The token stays valid while the work it covers runs, including the
+/// background task of an async session. Reports that do not increase the
+/// progress are dropped, as the protocol requires.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    token: ProgressToken,
+    requests: Arc<ClientRequests>,
+    /// Last progress sent plus one (0 before the first report).
+    sent: Arc<AtomicU64>,
+}
+
+impl ProgressReporter {
+    /// Report progress for `token` to the client behind `requests`.
+    pub fn new(token: ProgressToken, requests: Arc<ClientRequests>) -> Self {
+        Self {
+            token,
+            requests,
+            sent: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Send a progress notification (`total` when known).
+    pub fn report(&self, progress: u64, total: Option<u64>, message: &str) {
+        let next = progress.saturating_add(1);
+        if self.sent.fetch_max(next, Ordering::Relaxed) >= next {
+            return;
+        }
+        let mut params = json!({
+            "progressToken": self.token,
+            "progress": progress,
+            "message": message,
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        self.requests.notify(NOTIFICATIONS_PROGRESS, params);
+    }
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+// =============================================================================
+// Request Tracing
+// =============================================================================
+
+tokio::task_local! {
+    /// Context of the tool invocation the current task is serving.
+    static CURRENT_REQUEST: RequestContext;
+
+    /// Transport session of the message the current task is handling.
+    static TRANSPORT_SESSION: String;
+}
+
+/// Whether executed SQL is tagged with the request ID.
+static TAG_QUERIES: AtomicBool = AtomicBool::new(false);
+
+/// Whether executed SQL sets `CONTEXT_INFO` and session context for the request.
+static TAG_SESSION_CONTEXT: AtomicBool = AtomicBool::new(false);
+
+/// Name and version the MCP client reported during initialization.
+static CLIENT_IDENTITY: RwLock<Option<String>> = RwLock::new(None);
+
+/// Run a future with the given request context as the current request.
+pub async fn scope_request<F: Future>(context: RequestContext, future: F) -> F::Output {
+    CURRENT_REQUEST.scope(context, future).await
+}
+
+/// Get the context of the current request, if any.
+pub fn current_request() -> Option<RequestContext> {
+    CURRENT_REQUEST.try_with(|ctx| ctx.clone()).ok()
+}
+
+/// Run a future as part of the given transport session.
+pub async fn scope_transport_session<F: Future>(session_id: String, future: F) -> F::Output {
+    TRANSPORT_SESSION.scope(session_id, future).await
+}
+
+/// Get the transport session of the current task, if any.
+pub fn transport_session() -> Option<String> {
+    TRANSPORT_SESSION.try_with(|id| id.clone()).ok()
+}
+
+/// Key of the client making the current request (see [`RequestContext::context_key`]).
+///
+/// Outside tool calls (e.g. completions) the key is derived from the transport.
+pub fn client_context_key() -> String {
+    if let Some(request) = current_request() {
+        return request.context_key();
+    }
+    transport_session()
+        .or_else(client_identity)
+        .unwrap_or_else(|| rate_limit::ANONYMOUS_CLIENT.to_string())
+}
+
+/// Get the correlation ID of the current request, if any.
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST
+        .try_with(|ctx| ctx.correlation_id.clone())
+        .ok()
+}
+
+/// Report progress of the current request, if its client asked for progress
+/// notifications.
+pub fn report_progress(progress: u64, total: Option<u64>, message: &str) {
+    let _ = CURRENT_REQUEST.try_with(|ctx| {
+        if let Some(reporter) = &ctx.progress {
+            reporter.report(progress, total, message);
+        }
+    });
+}
+
+/// Count rows read or affected by a statement towards the current request.
+pub fn record_request_rows(rows: u64) {
+    let _ = CURRENT_REQUEST.try_with(|ctx| ctx.add_rows(rows));
+}
+
+/// Record the identity of the connected MCP client (`name/version`).
+pub fn set_client_identity(identity: impl Into<String>) {
+    *CLIENT_IDENTITY.write().unwrap_or_else(|e| e.into_inner()) = Some(identity.into());
+}
+
+/// Get the identity of the connected MCP client, if it has initialized.
+pub fn client_identity() -> Option<String> {
+    CLIENT_IDENTITY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Enable or disable tagging of executed SQL with the request ID.
+pub fn set_query_tagging(enabled: bool) {
+    TAG_QUERIES.store(enabled, Ordering::Relaxed);
+}
+
+/// Enable or disable setting `CONTEXT_INFO` and session context on executed SQL.
+pub fn set_session_context_tagging(enabled: bool) {
+    TAG_SESSION_CONTEXT.store(enabled, Ordering::Relaxed);
+}
+
+/// Tag a query with the current request.
+///
+/// With query tagging, the query is prefixed with a comment carrying the
+/// request ID, making statements traceable in Query Store, Extended Events and
+/// `sys.dm_exec_requests`. With session context tagging, the batch first sets
+/// `CONTEXT_INFO` and `SESSION_CONTEXT` keys naming the MCP client, tool and
+/// request (see [`session_context_prefix`]). Tags are placed on the first line
+/// so that server error line numbers are unchanged. Returns the query
+/// unchanged when tagging is disabled or no request is active.
+pub fn tag_query(query: &str) -> Cow<'_, str> {
+    let tag_queries = TAG_QUERIES.load(Ordering::Relaxed);
+    let tag_context = TAG_SESSION_CONTEXT.load(Ordering::Relaxed);
+    if !tag_queries && !tag_context {
+        return Cow::Borrowed(query);
+    }
+    let Some(request) = current_request() else {
+        return Cow::Borrowed(query);
+    };
+
+    let mut tagged = String::new();
+    if tag_queries {
+        tagged.push_str(&format!("/* request_id={} */ ", request.correlation_id));
+    }
+    // CREATE VIEW/PROCEDURE/... must start the batch, so they only get the comment
+    if tag_context && !requires_own_batch(query) {
+        tagged.push_str(&session_context_prefix(&request));
+    }
+    if tagged.is_empty() {
+        return Cow::Borrowed(query);
+    }
+    tagged.push_str(query);
+    Cow::Owned(tagged)
+}
+
+/// Statements that set `CONTEXT_INFO` and session context for a request.
+///
+/// Sets the read-write `SESSION_CONTEXT` keys `mcp_client`, `mcp_tool` and
+/// `mcp_request_id`, and `CONTEXT_INFO` to `mcp:<tool>:<request_id>` (at most
+/// 128 bytes), so DBAs can attribute sessions in `sys.dm_exec_sessions` and
+/// `sys.dm_exec_requests`. Pooled connections are reset when returned, which
+/// clears both.
+pub fn session_context_prefix(request: &RequestContext) -> String {
+    let tool = request.operation.as_deref().unwrap_or("unknown");
+    let mut prefix = String::new();
+    let mut set = |key: &str, value: &str| {
+        prefix.push_str(&format!(
+            "EXEC sys.sp_set_session_context N'{}', N'{}'; ",
+            key,
+            value.replace('\'', "''")
+        ));
+    };
+    if let Some(client) = &request.client_id {
+        set("mcp_client", client);
+    }
+    set("mcp_tool", tool);
+    set("mcp_request_id", &request.correlation_id);
+
+    let info = format!("mcp:{}:{}", tool, request.correlation_id);
+    let bytes = &info.as_bytes()[..info.len().min(128)];
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    prefix.push_str(&format!("SET CONTEXT_INFO 0x{}; ", hex));
+    prefix
+}
+
+/// Whether a statement must be the first one in its batch.
+fn requires_own_batch(query: &str) -> bool {
+    let mut rest = query.trim_start();
+    // Skip leading comments
+    loop {
+        if let Some(after) = rest.strip_prefix("--") {
+            rest = after.split_once('\n').map_or("", |(_, r)| r).trim_start();
+        } else if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.split_once("*/").map_or("", |(_, r)| r).trim_start();
+        } else {
+            break;
+        }
+    }
+
+    let words: Vec<String> = rest
+        .split_whitespace()
+        .take(4)
+        .map(|w| w.to_ascii_uppercase())
+        .collect();
+    let object = match words.as_slice() {
+        [create, or, alter, object, ..] if create == "CREATE" && or == "OR" && alter == "ALTER" => {
+            object
+        }
+        [verb, object, ..] if verb == "CREATE" || verb == "ALTER" => object,
+        _ => return false,
+    };
+    matches!(
+        object.as_str(),
+        "PROCEDURE" | "PROC" | "VIEW" | "FUNCTION" | "TRIGGER" | "SCHEMA" | "DEFAULT" | "RULE"
+    )
+}
+
+/// Tool handler wrapper that assigns a request ID to every tool invocation.
+///
+/// The ID is recorded on a `tool_call` span (so every log line emitted while
+/// serving the call carries it), made available through [`current_request_id`],
+/// and appended to the tool response. With an [`AuditLog`] attached, every
+/// call is also recorded in the audit log; with a [`RateLimiter`] attached,
+/// calls exceeding the client's limits are rejected with a throttle error;
+/// with a [`ConcurrencyLimiter`] attached, calls wait for a free slot of their
+/// tool and database; with [`ServerMetrics`] attached, call durations are
+/// recorded per tool.
+/// Calls carrying a progress token get a [`ProgressReporter`] that
+/// [`report_progress`] sends through. With read routing, calls to tools
+/// annotated read-only run with read intent (see [`with_read_intent`]).
+pub struct RequestTracing<T> {
+    inner: T,
+    audit: Option<Arc<AuditLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    concurrency: Option<Arc<ConcurrencyLimiter>>,
+    tool_filter: Option<ToolFilter>,
+    progress: Option<Arc<ClientRequests>>,
+    read_routing: Option<ReadRouting>,
+    metrics: Option<SharedMetrics>,
+}
+
+/// Tools whose calls run with read intent.
+struct ReadRouting {
+    /// Read-only tools that keep running on the primary pool.
+    excluded: &'static [&'static str],
+    /// Names of the tools annotated read-only, collected on first use.
+    read_only_tools: tokio::sync::OnceCell<HashSet<String>>,
+}
+
+impl ReadRouting {
+    /// Whether a call to `tool` runs with read intent.
+    async fn applies<T: ToolHandler>(&self, handler: &T, tool: &str, ctx: &Context<'_>) -> bool {
+        if self.excluded.contains(&tool) {
+            return false;
+        }
+        let read_only_tools = self
+            .read_only_tools
+            .get_or_try_init(|| async {
+                let tools = handler.list_tools(ctx).await?;
+                Ok::<_, McpError>(
+                    tools
+                        .into_iter()
+                        .filter(|t| {
+                            t.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true)
+                        })
+                        .map(|t| t.name)
+                        .collect(),
+                )
+            })
+            .await;
+        match read_only_tools {
+            Ok(tools) => tools.contains(tool),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to list read-only tools");
+                false
+            }
+        }
+    }
+}
+
+/// Returns why a tool is disabled, or `None` when it is available.
+pub type ToolFilter = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+impl<T> RequestTracing<T> {
+    /// Wrap a tool handler.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            audit: None,
+            rate_limiter: None,
+            concurrency: None,
+            tool_filter: None,
+            progress: None,
+            read_routing: None,
+            metrics: None,
+        }
+    }
+
+    /// Enforce per-client rate limits on tool calls.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter).filter(|limiter| limiter.is_enabled());
+        self
+    }
+
+    /// Cap concurrent calls per tool and per database.
+    pub fn with_concurrency_limiter(mut self, limiter: Arc<ConcurrencyLimiter>) -> Self {
+        self.concurrency = Some(limiter).filter(|limiter| limiter.is_enabled());
+        self
+    }
+
+    /// Hide disabled tools from the tool list and refuse calls to them.
+    pub fn with_tool_filter(mut self, filter: ToolFilter) -> Self {
+        self.tool_filter = Some(filter);
+        self
+    }
+
+    /// Record every tool call in an audit log.
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit).filter(|audit| audit.is_enabled());
+        self
+    }
+
+    /// Send progress notifications for calls made with a progress token.
+    pub fn with_progress_notifications(mut self, requests: Arc<ClientRequests>) -> Self {
+        self.progress = Some(requests);
+        self
+    }
+
+    /// Record the duration of every tool call.
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Run calls to tools annotated read-only, other than `excluded`, with read intent.
+    pub fn with_read_routing(mut self, excluded: &'static [&'static str]) -> Self {
+        self.read_routing = Some(ReadRouting {
+            excluded,
+            read_only_tools: tokio::sync::OnceCell::new(),
+        });
+        self
+    }
+}
+
+impl<T: ToolHandler> ToolHandler for RequestTracing<T> {
+    fn list_tools(
+        &self,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<Vec<Tool>, McpError>> + Send {
+        let tools = self.inner.list_tools(ctx);
+        let filter = self.tool_filter.clone();
+
+        async move {
+            let mut tools = tools.await?;
+            if let Some(filter) = filter {
+                tools.retain(|tool| filter(&tool.name).is_none());
+            }
+            Ok(tools)
+        }
+    }
+
+    fn call_tool(
+        &self,
+        name: &str,
+        args: Value,
+        ctx: &Context<'_>,
+    ) -> impl Future<Output = Result<ToolOutput, McpError>> + Send {
+        let mut request = RequestContext::new().with_operation(name);
+        if let Some(client) = client_identity() {
+            request = request.with_client(client);
+        }
+        if let Some(session) = transport_session() {
+            request = request.with_session(session);
+        }
+        if let (Some(token), Some(requests)) = (ctx.progress_token, &self.progress) {
+            request =
+                request.with_progress(ProgressReporter::new(token.clone(), Arc::clone(requests)));
+        }
+        let request_id = request.correlation_id.clone();
+        let span = tracing::info_span!("tool_call", tool = name, request_id = %request_id);
+        let audit = self.audit.clone();
+        let query = audit.as_ref().and_then(|_| audit::query_text(&args));
+        let tool = name.to_string();
+        let limiter = self.rate_limiter.clone();
+        let identity = request
+            .client_id
+            .clone()
+            .unwrap_or_else(|| rate_limit::ANONYMOUS_CLIENT.to_string());
+        let throttled = limiter
+            .as_ref()
+            .and_then(|limiter| limiter.check_call(&identity).err());
+        let disabled = self.tool_filter.as_ref().and_then(|filter| filter(name));
+        let database = args
+            .get("database")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        // Boxed to keep the nesting of the server's future types shallow
+        let call = Box::pin(self.inner.call_tool(name, args, ctx));
+
+        async move {
+            let result = match (&throttled, disabled) {
+                (Some(e), _) => {
+                    tracing::warn!(error = %e, "Tool call throttled");
+                    Ok(rate_limit::throttle_output(e))
+                }
+                (None, Some(reason)) => {
+                    tracing::warn!(reason = %reason, "Call to disabled tool refused");
+                    Ok(ToolOutput::error(reason))
+                }
+                (None, None) => {
+                    let read_intent = match &self.read_routing {
+                        Some(routing) => routing.applies(&self.inner, &tool, ctx).await,
+                        None => false,
+                    };
+                    scope_request(request.clone(), async {
+                        // The current database is per client, so this runs in the request scope
+                        let _permit = match &self.concurrency {
+                            Some(limiter) => match limiter.acquire(&tool, database.as_deref()).await
+                            {
+                                Ok(permit) => Some(permit),
+                                Err(e) => {
+                                    tracing::warn!(error = %e, "Tool call over concurrency limit");
+                                    return Ok(rate_limit::throttle_output(&e));
+                                }
+                            },
+                            None => None,
+                        };
+                        with_read_intent(read_intent, call).await
+                    })
+                    .await
+                }
+            };
+            if let Some(limiter) = &limiter {
+                limiter.record_rows(&identity, request.rows());
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.record_tool_call(&tool, request.elapsed());
+            }
+            let elapsed_ms = request.elapsed().as_millis() as u64;
+            let error = match &result {
+                Ok(ToolOutput::Success(_)) => {
+                    tracing::debug!(elapsed_ms, "Tool call completed");
+                    None
+                }
+                Ok(ToolOutput::RecoverableError { message, .. }) => {
+                    tracing::debug!(elapsed_ms, error = %message, "Tool call returned an error");
+                    Some(message.clone())
+                }
+                Err(e) => {
+                    tracing::warn!(elapsed_ms, error = %e, "Tool call failed");
+                    Some(e.to_string())
+                }
+            };
+            if let Some(audit) = &audit {
+                audit.record(&request, &tool, query.as_deref(), error);
+            }
+            result.map(|output| attach_request_id(output, &request_id))
+        }
+        .instrument(span)
+    }
+}
+
+/// Append the request ID to a tool response.
+fn attach_request_id(output: ToolOutput, request_id: &str) -> ToolOutput {
+    match output {
+        ToolOutput::Success(mut result) => {
+            result
+                .content
+                .push(Content::text(format!("request_id: {}", request_id)));
+            ToolOutput::Success(result)
+        }
+        ToolOutput::RecoverableError {
+            message,
+            suggestion,
+        } => ToolOutput::RecoverableError {
+            message: format!("{} (request_id: {})", message, request_id),
+            suggestion,
+        },
+    }
+}
+
 /// Server metrics collection.
 ///
 /// This struct collects metrics that can be exported via OpenTelemetry
@@ -152,6 +721,15 @@ pub struct ServerMetrics {
 
     /// Total bytes transferred.
     pub bytes_transferred: AtomicU64,
+
+    /// Distribution of statement execution times.
+    query_latency: parking_lot::Mutex<LatencyHistogram>,
+
+    /// Distribution of tool call durations, keyed by tool name.
+    tool_latency: parking_lot::Mutex<HashMap<String, LatencyHistogram>>,
+
+    /// Per-shape statistics keyed by query fingerprint.
+    query_shapes: parking_lot::Mutex<HashMap<String, QueryShape>>,
 }
 
 impl ServerMetrics {
@@ -170,6 +748,168 @@ impl ServerMetrics {
         }
         self.query_time_ms_total
             .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+        self.query_latency.lock().record(duration);
+    }
+
+    /// Distribution of statement execution times.
+    pub fn query_latency(&self) -> LatencyHistogram {
+        self.query_latency.lock().clone()
+    }
+
+    /// Record the duration of a tool call.
+    pub fn record_tool_call(&self, tool: &str, duration: Duration) {
+        let mut tools = self.tool_latency.lock();
+        match tools.get_mut(tool) {
+            Some(histogram) => histogram.record(duration),
+            None => {
+                let mut histogram = LatencyHistogram::new();
+                histogram.record(duration);
+                tools.insert(tool.to_string(), histogram);
+            }
+        }
+    }
+
+    /// Distribution of call durations per tool, sorted by tool name.
+    pub fn tool_latencies(&self) -> Vec<(String, LatencyHistogram)> {
+        let mut tools: Vec<_> = self
+            .tool_latency
+            .lock()
+            .iter()
+            .map(|(tool, histogram)| (tool.clone(), histogram.clone()))
+            .collect();
+        tools.sort_by(|a, b| a.0.cmp(&b.0));
+        tools
+    }
+
+    /// Record a statement execution, aggregated by its query shape.
+    ///
+    /// Statements differing only in literals, comments, or whitespace share a
+    /// shape. When `MAX_QUERY_SHAPES` shapes are tracked, the least recently
+    /// seen one is dropped to make room for a new one.
+    pub fn record_statement(&self, sql: &str, success: bool, duration: Duration, rows: u64) {
+        self.record_query(success, duration);
+
+        let normalized = normalize_statement(sql);
+        let fingerprint = fingerprint_of(&normalized);
+        let elapsed_ms = duration.as_millis() as u64;
+        let mut shapes = self.query_shapes.lock();
+        if !shapes.contains_key(&fingerprint) && shapes.len() >= MAX_QUERY_SHAPES {
+            let oldest = shapes
+                .iter()
+                .min_by_key(|(_, shape)| shape.last_seen)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                shapes.remove(&oldest);
+            }
+        }
+        let shape = shapes
+            .entry(fingerprint.clone())
+            .or_insert_with(|| QueryShape::new(fingerprint, normalized));
+        shape.count += 1;
+        if !success {
+            shape.failed += 1;
+        }
+        shape.total_time_ms += elapsed_ms;
+        shape.max_time_ms = shape.max_time_ms.max(elapsed_ms);
+        shape.latency.record(duration);
+        shape.rows += rows;
+        shape.last_seen = Instant::now();
+    }
+
+    /// Number of distinct query shapes currently tracked.
+    pub fn query_shape_count(&self) -> usize {
+        self.query_shapes.lock().len()
+    }
+
+    /// The `limit` most significant query shapes under the given ordering.
+    pub fn top_query_shapes(&self, limit: usize, order: QueryShapeOrder) -> Vec<QueryShape> {
+        let mut shapes: Vec<QueryShape> = self.query_shapes.lock().values().cloned().collect();
+        match order {
+            QueryShapeOrder::Count => shapes.sort_by_key(|shape| Reverse(shape.count)),
+            QueryShapeOrder::TotalTime => shapes.sort_by_key(|shape| Reverse(shape.total_time_ms)),
+            QueryShapeOrder::AvgTime => {
+                shapes.sort_by(|a, b| b.avg_time_ms().total_cmp(&a.avg_time_ms()))
+            }
+            QueryShapeOrder::P95Time => {
+                shapes.sort_by_key(|shape| Reverse(shape.latency.percentile(0.95)))
+            }
+            QueryShapeOrder::MaxTime => shapes.sort_by_key(|shape| Reverse(shape.max_time_ms)),
+        }
+        shapes.truncate(limit);
+        shapes
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    ///
+    /// Latency distributions are exported as summaries with p50, p95 and p99
+    /// quantiles: overall, per tool, and for the most executed query shapes.
+    pub fn prometheus_text(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        let counters = [
+            (
+                "queries_total",
+                "Statements executed, by outcome.",
+                vec![
+                    ("outcome=\"success\"", snapshot.queries_success),
+                    ("outcome=\"failure\"", snapshot.queries_failed),
+                ],
+            ),
+            (
+                "transactions_total",
+                "Transactions, by outcome.",
+                vec![
+                    ("outcome=\"started\"", snapshot.transactions_total),
+                    ("outcome=\"committed\"", snapshot.transactions_committed),
+                    ("outcome=\"rolled_back\"", snapshot.transactions_rolled_back),
+                ],
+            ),
+            (
+                "cache_requests_total",
+                "Query cache lookups, by result.",
+                vec![
+                    ("result=\"hit\"", snapshot.cache_hits),
+                    ("result=\"miss\"", snapshot.cache_misses),
+                ],
+            ),
+        ];
+        for (name, help, samples) in counters {
+            let _ = writeln!(out, "# HELP mssql_mcp_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE mssql_mcp_{} counter", name);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "mssql_mcp_{}{{{}}} {}", name, labels, value);
+            }
+        }
+
+        write_prometheus_summary(
+            &mut out,
+            "query_duration_seconds",
+            "Statement execution time.",
+            [(String::new(), self.query_latency())],
+        );
+        write_prometheus_summary(
+            &mut out,
+            "tool_duration_seconds",
+            "Tool call duration.",
+            self.tool_latencies()
+                .into_iter()
+                .map(|(tool, histogram)| (format!("tool=\"{}\"", tool), histogram)),
+        );
+        write_prometheus_summary(
+            &mut out,
+            "query_shape_duration_seconds",
+            "Execution time of the most executed query shapes.",
+            self.top_query_shapes(PROMETHEUS_QUERY_SHAPES, QueryShapeOrder::Count)
+                .into_iter()
+                .map(|shape| {
+                    (
+                        format!("fingerprint=\"{}\"", shape.fingerprint),
+                        shape.latency,
+                    )
+                }),
+        );
+        out
     }
 
     /// Record a transaction start.
@@ -299,6 +1039,383 @@ impl MetricsSnapshot {
     }
 }
 
+/// Aggregated statistics for statements sharing a query shape.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct QueryShape {
+    /// Short hash of the normalized statement.
+    pub fingerprint: String,
+    /// Statement with literals replaced by `?` and comments removed.
+    pub query: String,
+    /// Number of executions.
+    pub count: u64,
+    /// Number of failed executions.
+    pub failed: u64,
+    /// Total execution time in milliseconds.
+    pub total_time_ms: u64,
+    /// Longest execution time in milliseconds.
+    pub max_time_ms: u64,
+    /// Rows read or affected across all executions.
+    pub rows: u64,
+    /// Distribution of execution times.
+    #[serde(skip)]
+    pub latency: LatencyHistogram,
+    #[serde(skip)]
+    last_seen: Instant,
+}
+
+impl QueryShape {
+    fn new(fingerprint: String, query: String) -> Self {
+        Self {
+            fingerprint,
+            query,
+            count: 0,
+            failed: 0,
+            total_time_ms: 0,
+            max_time_ms: 0,
+            rows: 0,
+            latency: LatencyHistogram::new(),
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Average execution time in milliseconds.
+    pub fn avg_time_ms(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.total_time_ms as f64 / self.count as f64
+    }
+}
+
+/// Ordering used when reporting the top query shapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryShapeOrder {
+    /// Most executed first (default).
+    #[default]
+    Count,
+    /// Highest total execution time first.
+    TotalTime,
+    /// Highest average execution time first.
+    AvgTime,
+    /// Highest 95th percentile execution time first.
+    #[serde(rename = "p95_time")]
+    P95Time,
+    /// Slowest single execution first.
+    MaxTime,
+}
+
+impl QueryShapeOrder {
+    /// Generate JSON Schema for this type.
+    pub fn tool_input_schema() -> serde_json::Value {
+        json!({
+            "type": "string",
+            "enum": ["count", "total_time", "avg_time", "p95_time", "max_time"],
+            "default": "count",
+            "description": "Order of the reported query shapes: 'count', 'total_time', 'avg_time', 'p95_time', or 'max_time'"
+        })
+    }
+}
+
+/// Latency histogram with logarithmic buckets, in the style of HDR histograms.
+///
+/// Durations are recorded in microseconds. Each power of two is split into 16
+/// linear sub-buckets, so reported percentiles are within about 6% of the
+/// recorded values while memory stays proportional to the largest value.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+/// Linear sub-buckets per power of two (as a bit count).
+const HISTOGRAM_SUB_BUCKET_BITS: u32 = 4;
+const HISTOGRAM_SUB_BUCKETS: u64 = 1 << HISTOGRAM_SUB_BUCKET_BITS;
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a duration.
+    pub fn record(&mut self, duration: Duration) {
+        let us = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        let index = Self::bucket_index(us);
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// Number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Sum of the recorded durations.
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum_us)
+    }
+
+    /// Longest recorded duration.
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_us)
+    }
+
+    /// Average recorded duration.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.sum_us / self.count)
+    }
+
+    /// Duration below which `quantile` (0.0 to 1.0) of the recorded durations fall.
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(Self::bucket_upper(index).min(self.max_us));
+            }
+        }
+        self.max()
+    }
+
+    /// Count, average, p50/p95/p99 and maximum in milliseconds.
+    pub fn summary(&self) -> LatencySummary {
+        let ms = |duration: Duration| duration.as_micros() as f64 / 1000.0;
+        LatencySummary {
+            count: self.count,
+            avg_ms: ms(self.mean()),
+            p50_ms: ms(self.percentile(0.50)),
+            p95_ms: ms(self.percentile(0.95)),
+            p99_ms: ms(self.percentile(0.99)),
+            max_ms: ms(self.max()),
+        }
+    }
+
+    fn bucket_index(us: u64) -> usize {
+        if us < HISTOGRAM_SUB_BUCKETS {
+            return us as usize;
+        }
+        let shift = u64::from(63 - us.leading_zeros() - HISTOGRAM_SUB_BUCKET_BITS);
+        let sub_bucket = (us >> shift) - HISTOGRAM_SUB_BUCKETS;
+        (HISTOGRAM_SUB_BUCKETS * (shift + 1) + sub_bucket) as usize
+    }
+
+    /// Largest value falling in a bucket.
+    fn bucket_upper(index: usize) -> u64 {
+        let index = index as u64;
+        if index < HISTOGRAM_SUB_BUCKETS {
+            return index;
+        }
+        let shift = index / HISTOGRAM_SUB_BUCKETS - 1;
+        let sub_bucket = index % HISTOGRAM_SUB_BUCKETS;
+        let upper = u128::from(HISTOGRAM_SUB_BUCKETS + sub_bucket + 1) << shift;
+        u64::try_from(upper - 1).unwrap_or(u64::MAX)
+    }
+}
+
+/// Write one Prometheus summary metric with a series per label set.
+fn write_prometheus_summary(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    series: impl IntoIterator<Item = (String, LatencyHistogram)>,
+) {
+    let _ = writeln!(out, "# HELP mssql_mcp_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE mssql_mcp_{} summary", name);
+    for (labels, histogram) in series {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for quantile in [0.5, 0.95, 0.99] {
+            let _ = writeln!(
+                out,
+                "mssql_mcp_{}{{{}{}quantile=\"{}\"}} {}",
+                name,
+                labels,
+                separator,
+                quantile,
+                histogram.percentile(quantile).as_secs_f64()
+            );
+        }
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(
+            out,
+            "mssql_mcp_{}_sum{} {}",
+            name,
+            labels,
+            histogram.sum().as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "mssql_mcp_{}_count{} {}",
+            name,
+            labels,
+            histogram.count()
+        );
+    }
+}
+
+/// Latency percentiles of a [`LatencyHistogram`], in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Normalize a statement to its query shape.
+///
+/// Comments are removed, string, numeric, and binary literals become `?`,
+/// lists of literals such as `IN (1, 2, 3)` collapse to `(?)`, whitespace is
+/// collapsed, and keywords are uppercased. Bracketed and quoted identifiers
+/// are kept verbatim.
+pub fn normalize_statement(sql: &str) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut pending_space = false;
+    let mut i = 0;
+
+    fn is_word(c: char) -> bool {
+        c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$')
+    }
+
+    fn push(out: &mut String, pending_space: &mut bool, text: &str) {
+        let first = text.chars().next().unwrap_or(' ');
+        if *pending_space && !out.is_empty() && !out.ends_with('(') && !matches!(first, ',' | ')') {
+            out.push(' ');
+        }
+        *pending_space = first == ',';
+        out.push_str(text);
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let after_word = out.chars().next_back().is_some_and(is_word) && !pending_space;
+
+        if c.is_whitespace() {
+            pending_space = true;
+            i += 1;
+        } else if c == '-' && next == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            pending_space = true;
+        } else if c == '/' && next == Some('*') {
+            // Block comments nest in T-SQL
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            pending_space = true;
+        } else if c == '\'' || (matches!(c, 'N' | 'n') && next == Some('\'') && !after_word) {
+            i += if c == '\'' { 1 } else { 2 };
+            while i < chars.len() {
+                if chars[i] == '\'' && chars.get(i + 1) == Some(&'\'') {
+                    i += 2;
+                } else if chars[i] == '\'' {
+                    i += 1;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+            push(&mut out, &mut pending_space, "?");
+        } else if c == '[' || c == '"' {
+            let close = if c == '[' { ']' } else { '"' };
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == close && chars.get(i + 1) == Some(&close) {
+                    i += 2;
+                } else if chars[i] == close {
+                    i += 1;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+            let identifier: String = chars[start..i].iter().collect();
+            push(&mut out, &mut pending_space, &identifier);
+        } else if c.is_ascii_digit() && !after_word {
+            if c == '0' && matches!(next, Some('x' | 'X')) {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+            } else {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    i += 1;
+                    if i < chars.len() && matches!(chars[i], '+' | '-') {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            push(&mut out, &mut pending_space, "?");
+        } else {
+            let upper: String = c.to_uppercase().collect();
+            push(&mut out, &mut pending_space, &upper);
+            i += 1;
+        }
+    }
+
+    // Collapse literal lists and multi-row VALUES clauses
+    for (list, single) in [("?, ?", "?"), ("(?), (?)", "(?)")] {
+        while out.contains(list) {
+            out = out.replace(list, single);
+        }
+    }
+    out
+}
+
+/// Fingerprint of a statement's query shape (16 hex digits).
+///
+/// Statements normalizing to the same shape (see [`normalize_statement`])
+/// share a fingerprint.
+pub fn fingerprint_query(sql: &str) -> String {
+    fingerprint_of(&normalize_statement(sql))
+}
+
+fn fingerprint_of(normalized: &str) -> String {
+    hex::encode(&Sha256::digest(normalized.as_bytes())[..8])
+}
+
 /// Shared metrics type for thread-safe access.
 pub type SharedMetrics = Arc<ServerMetrics>;
 
@@ -549,6 +1666,74 @@ pub mod otel {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_request_scope_and_query_tagging() {
+        assert!(current_request_id().is_none());
+        assert_eq!(tag_query("SELECT 1"), "SELECT 1");
+
+        set_query_tagging(true);
+        let request = RequestContext::with_correlation_id("abc12345");
+        let (id, tagged) = scope_request(request, async {
+            (current_request_id(), tag_query("SELECT 1").into_owned())
+        })
+        .await;
+        set_query_tagging(false);
+
+        assert_eq!(id.as_deref(), Some("abc12345"));
+        assert_eq!(tagged, "/* request_id=abc12345 */ SELECT 1");
+
+        set_session_context_tagging(true);
+        let request = RequestContext::with_correlation_id("abc12345").with_operation("run");
+        let prefix = session_context_prefix(&request);
+        let (select, view) = scope_request(request, async {
+            (
+                tag_query("SELECT 1").into_owned(),
+                tag_query("CREATE VIEW v AS SELECT 1").into_owned(),
+            )
+        })
+        .await;
+        set_session_context_tagging(false);
+
+        assert_eq!(select, format!("{}SELECT 1", prefix));
+        assert_eq!(view, "CREATE VIEW v AS SELECT 1");
+    }
+
+    #[test]
+    fn test_session_context_prefix() {
+        let request = RequestContext::with_correlation_id("abc12345")
+            .with_client("O'Brien/1.0")
+            .with_operation("execute_query");
+        let prefix = session_context_prefix(&request);
+        assert!(prefix.contains("N'mcp_client', N'O''Brien/1.0'"));
+        assert!(prefix.contains("N'mcp_tool', N'execute_query'"));
+        // CONTEXT_INFO holds "mcp:execute_query:abc12345"
+        assert!(prefix.ends_with(
+            "SET CONTEXT_INFO 0x6D63703A657865637574655F71756572793A6162633132333435; "
+        ));
+
+        assert!(!requires_own_batch("SELECT 1"));
+        assert!(!requires_own_batch("CREATE TABLE t (id int)"));
+        assert!(requires_own_batch(
+            "-- note\nCREATE OR ALTER VIEW v AS SELECT 1"
+        ));
+        assert!(requires_own_batch("/* x */ alter procedure p as select 1"));
+    }
+
+    #[test]
+    fn test_attach_request_id() {
+        let output = attach_request_id(ToolOutput::text("ok"), "abc12345");
+        let ToolOutput::Success(result) = output else {
+            panic!("expected success output");
+        };
+        assert_eq!(result.content.len(), 2);
+
+        let output = attach_request_id(ToolOutput::error("failed"), "abc12345");
+        let ToolOutput::RecoverableError { message, .. } = output else {
+            panic!("expected error output");
+        };
+        assert_eq!(message, "failed (request_id: abc12345)");
+    }
+
     #[test]
     fn test_metrics_recording() {
         let metrics = ServerMetrics::new();
@@ -563,6 +1748,116 @@ mod tests {
         assert_eq!(metrics.query_time_ms_total.load(Ordering::Relaxed), 350);
     }
 
+    #[test]
+    fn test_normalize_statement() {
+        assert_eq!(
+            normalize_statement("select *  from [Order Details]\n where id = 42 -- trailing"),
+            "SELECT * FROM [Order Details] WHERE ID = ?"
+        );
+        assert_eq!(
+            normalize_statement(
+                "/* request_id=abc /* nested */ */ SELECT name FROM t WHERE name = N'O''Brien'"
+            ),
+            "SELECT NAME FROM T WHERE NAME = ?"
+        );
+        assert_eq!(
+            normalize_statement(
+                "SELECT * FROM t WHERE id IN (1,2 , 3) AND flag = 0x1F AND v > -1.5e3"
+            ),
+            "SELECT * FROM T WHERE ID IN (?) AND FLAG = ? AND V > -?"
+        );
+        assert_eq!(
+            normalize_statement("INSERT INTO t2 (a, b) VALUES (1, 'x'), (2, 'y')"),
+            "INSERT INTO T2 (A, B) VALUES (?)"
+        );
+        assert_eq!(
+            normalize_statement("SELECT @p1, col1 FROM t"),
+            "SELECT @P1, COL1 FROM T"
+        );
+        assert_eq!(
+            fingerprint_query("SELECT 1 FROM t WHERE id = 7"),
+            fingerprint_query("select 1\nfrom t where id = 8")
+        );
+        assert_eq!(fingerprint_query("SELECT 1").len(), 16);
+    }
+
+    #[test]
+    fn test_query_shapes() {
+        let metrics = ServerMetrics::new();
+
+        let id = |n| format!("SELECT * FROM a WHERE id = {}", n);
+        metrics.record_statement(&id(1), true, Duration::from_millis(10), 1);
+        metrics.record_statement(&id(2), false, Duration::from_millis(30), 0);
+        metrics.record_statement("SELECT * FROM b", true, Duration::from_millis(100), 5);
+
+        assert_eq!(metrics.queries_total.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.query_shape_count(), 2);
+
+        let by_count = metrics.top_query_shapes(10, QueryShapeOrder::Count);
+        assert_eq!(by_count[0].query, "SELECT * FROM A WHERE ID = ?");
+        assert_eq!(by_count[0].count, 2);
+        assert_eq!(by_count[0].failed, 1);
+        assert_eq!(by_count[0].total_time_ms, 40);
+        assert_eq!(by_count[0].max_time_ms, 30);
+
+        let by_latency = metrics.top_query_shapes(1, QueryShapeOrder::AvgTime);
+        assert_eq!(by_latency.len(), 1);
+        assert_eq!(by_latency[0].query, "SELECT * FROM B");
+        assert_eq!(by_latency[0].rows, 5);
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.percentile(0.5), Duration::ZERO);
+
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+        assert_eq!(histogram.mean(), Duration::from_micros(50_500));
+
+        // Buckets keep percentiles within ~6% above the exact value
+        for (quantile, exact) in [(0.5, 50.0), (0.95, 95.0), (0.99, 99.0)] {
+            let ms = histogram.percentile(quantile).as_secs_f64() * 1000.0;
+            assert!(ms >= exact && ms <= exact * 1.07, "p{} = {}", quantile, ms);
+        }
+        assert_eq!(histogram.percentile(1.0), Duration::from_millis(100));
+
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.max_ms, 100.0);
+
+        for us in [0, 15, 16, 17, 1_000_000, u64::MAX] {
+            let index = LatencyHistogram::bucket_index(us);
+            assert!(LatencyHistogram::bucket_upper(index) >= us);
+            if index > 0 {
+                assert!(LatencyHistogram::bucket_upper(index - 1) < us);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prometheus_text() {
+        let metrics = ServerMetrics::new();
+        metrics.record_statement("SELECT 1", true, Duration::from_millis(20), 1);
+        metrics.record_tool_call("execute_query", Duration::from_millis(25));
+
+        let text = metrics.prometheus_text();
+        assert!(text.contains("mssql_mcp_queries_total{outcome=\"success\"} 1\n"));
+        assert!(text.contains("# TYPE mssql_mcp_query_duration_seconds summary\n"));
+        assert!(text.contains("mssql_mcp_query_duration_seconds{quantile=\"0.99\"} 0.02"));
+        assert!(text.contains("mssql_mcp_query_duration_seconds_count 1\n"));
+        assert!(text.contains(
+            "mssql_mcp_tool_duration_seconds{tool=\"execute_query\",quantile=\"0.5\"} 0.025"
+        ));
+        assert!(text.contains(&format!(
+            "mssql_mcp_query_shape_duration_seconds_count{{fingerprint=\"{}\"}} 1\n",
+            fingerprint_query("SELECT 1")
+        )));
+    }
+
     #[test]
     fn test_avg_query_time() {
         let metrics = ServerMetrics::new();
@@ -3,51 +3,135 @@
 //! Tools are action-oriented operations that execute queries and procedures:
 //!
 //! - `execute_query`: Execute arbitrary SQL queries
+//! - `fetch_result_chunk`: Retrieve rows from a large result by reference
+//! - `confirm_execution`: Run a destructive query held back for confirmation
 //! - `execute_parameterized`: Execute parameterized queries (SQL injection safe)
+//! - `register_query`: Register a named, parameterized query
+//! - `execute_registered`: Execute a registered query by name
+//! - `list_registered_queries`: List registered queries and their parameters
 //! - `execute_procedure`: Execute stored procedures
 //! - `execute_with_tvp`: Execute queries with Table-Valued Parameters
 //! - `execute_async`: Start async query execution
 //! - `get_session_status`: Check async query status
-//! - `get_session_results`: Retrieve async query results
+//! - `get_session_results`: Retrieve async query results (row ranges, paged resource for large results)
 //! - `cancel_session`: Cancel running async query
+//! - `run_script`: Run a multi-batch SQLCMD-style script as an async session
+//! - `watch_table`: Watch a table for new or changed rows
+//! - `get_watch_changes`: Retrieve rows detected by a table watch
+//! - `list_watches`: List active table watches
+//! - `unwatch_table`: Stop a table watch
+//! - `watch_query`: Re-execute a query periodically and track result changes
+//! - `get_watch_status`: Retrieve status and changes of query watches
+//! - `unwatch_query`: Stop a query watch
 //! - `explain_query`: Get query execution plan
+//! - `compare_plans`: Diff the execution plans of two queries or against a baseline
+//! - `estimate_impact`: Estimate and preview rows affected by an UPDATE or DELETE
 //! - `list_sessions`: List async query sessions
+//! - `purge_sessions`: Remove finished async sessions and their results
 //! - `health_check`: Test database connectivity
+//! - `reset_circuit_breaker`: Close the connection circuit breaker
 //! - `set_timeout`: Adjust default query timeout at runtime
 //! - `get_timeout`: Get current query timeout configuration
+//! - `reload_config`: Re-apply runtime settings from the config file
+//! - `rotate_credentials`: Reconnect the pools with rotated database credentials
 //! - `execute_paginated`: Execute paginated queries
 //! - `begin_transaction`: Start a database transaction
 //! - `commit_transaction`: Commit a transaction
 //! - `rollback_transaction`: Rollback a transaction
-//! - `execute_in_transaction`: Execute SQL in a transaction
+//! - `execute_in_transaction`: Execute SQL in a transaction (per-statement results)
+//! - `create_savepoint`: Create a savepoint in a transaction
+//! - `list_savepoints`: List the savepoints of a transaction
 //! - `begin_pinned_session`: Start a pinned session for temp tables
 //! - `execute_in_pinned_session`: Execute SQL in a pinned session
 //! - `end_pinned_session`: End a pinned session
+//! - `use_database`: Switch the database of a pinned session or transaction
 //! - `list_pinned_sessions`: List active pinned sessions
 //! - `switch_database`: Switch to a different database
 //! - `recommend_indexes`: Get index recommendations for a query
+//! - `get_index_fragmentation`: Report index fragmentation and page density
+//! - `maintain_indexes`: Generate or run index REORGANIZE/REBUILD statements
+//! - `compression_advisor`: Estimate data compression savings and generate statements
+//! - `get_stale_statistics`: Report statistics modified since their last update
+//! - `update_statistics`: Generate or run UPDATE STATISTICS for stale statistics
+//! - `check_integrity`: Find orphaned rows of foreign keys and script repairs
 //! - `compare_schemas`: Compare two database schemas
 //! - `compare_tables`: Compare two tables
+//! - `generate_migration`: Generate migration scripts from schema diffs
+//! - `generate_crud`: Generate Get/List/Insert/Update/Delete procedures for a table
+//! - `copy_table`: Copy a table's structure and rows to a new table
+//! - `clone_schema`: Replicate all objects of a schema into a new schema
+//! - `get_dependencies`: Get what an object uses and what uses it
+//! - `script_object`: Script the complete DDL of a table, view, procedure, or function
+//! - `script_database`: Script all objects' DDL to files for source control
+//! - `search_schema`: Search object names, columns, and definitions
+//! - `generate_data_dictionary`: Document a schema's tables as Markdown, HTML, or JSON
 //! - `sample_data`: Sample data from a table
+//! - `query_json_column`: Infer a JSON column's paths and query them as typed columns
+//! - `describe_graph`: Describe graph node and edge tables with an example MATCH query
+//! - `query_history_table`: Query a temporal table at a point in time or over a range
+//! - `classify_columns`: Suggest sensitive columns and masking rules
+//! - `profile_table`: Compute per-column statistics of a table
 //! - `bulk_insert`: Bulk insert data into a table
-//! - `export_data`: Export query results
+//! - `generate_test_data`: Insert generated rows into a table
+//! - `anonymize_table`: Scrub columns with hash, shuffle, fake or nullify rules
+//! - `export_data`: Export query results inline or to files
+//! - `download_blob`: Write a binary cell to a file
+//! - `import_data`: Import CSV or JSON Lines files into a table
 //! - `get_metrics`: Get server performance metrics
 //! - `analyze_query`: Analyze query performance
 //! - `get_pool_metrics`: Get connection pool statistics
-//! - `get_internal_metrics`: Get internal server metrics (queries, cache, etc.)
+//! - `get_replica_info`: Report the server and Availability Group replica in use
+//! - `test_linked_server`: Test connectivity to a linked server
+//! - `get_memory_optimized_usage`: Report memory used by memory-optimized tables
+//! - `get_ag_health`: Report Availability Group synchronization and secondary lag
+//! - `get_replication_status`: Report replication agent status and latency
+//! - `get_file_usage`: Report database file sizes, free space and autogrowth
+//! - `get_tempdb_usage`: Report tempdb space by category and consuming session
+//! - `get_active_requests`: Show what is running right now, with waits and blocking
+//! - `kill_session`: Kill a session after confirming its login and host
+//! - `get_internal_metrics`: Get internal server metrics (queries, query shapes, cache, etc.)
+//! - `clear_cache`: Clear cached query results, optionally by pattern
+//! - `get_cache_entries`: List cached query results with sizes, hits and TTLs
+//! - `evict_cache_entry`: Evict one cached query result
+//! - `get_query_history`: Get recorded tool calls from the audit log
+//! - `get_slow_queries`: Get queries that exceeded the slow query threshold
 //! - `validate_syntax`: Validate SQL syntax without executing (dry-run)
+//! - `describe_query`: Describe the result columns of a query without executing it
+//! - `lint_sql`: Static analysis of SQL for common performance and style problems
+//! - `generate_sql`: Turn a question into SQL with the client's model (sampling)
 
+mod formatting;
 mod inputs;
+pub mod names;
+pub mod structured;
 
 pub use inputs::*;
 
+use crate::audit::AuditFilter;
+use crate::cache::{is_cacheable, modified_tables, CacheKey};
+use crate::config::AuthConfig;
+use crate::database::plan::ExecutionPlan;
+use crate::database::relationships::{
+    join_condition, strip_query, to_dot, to_mermaid, RelationshipFormat,
+};
+use crate::database::{with_partial_results, ForeignKeyRelationship};
+use crate::error::ServerError;
+use crate::export::{self, ChunkedFileWriter, FileExportOptions};
+use crate::import::{self, ImportPlan, SourceReader, TargetColumn};
+use crate::resilience::CircuitBreakerStats;
+use crate::security::confirmation::find_destructive_statements;
+use crate::security::procedures;
+use crate::security::rate_limit::throttle_output;
 use crate::security::{parse_qualified_name, safe_identifier, validate_identifier};
 use crate::server::MssqlMcpServer;
 use crate::state::{IsolationLevel, SessionStatus, TransactionStatus};
+use crate::telemetry::{
+    client_context_key, current_request, report_progress, scope_request, tag_query,
+};
 use mcpkit::prelude::*;
 use mcpkit::types::ResourceContents;
 use serde_json::json;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, warn, Instrument};
 
 /// MCP server implementation containing all tools, resources, and prompts.
 ///
@@ -80,6 +164,15 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Some(preview) = self
+            .confirmation_preview("execute_query", &input.query, &input)
+            .await
+        {
+            return Ok(preview);
+        }
 
         // Get current database from state (for switch_database support)
         // Pool connections don't persist database context, so we need to prepend USE
@@ -91,7 +184,8 @@ impl MssqlMcpServer {
         // Determine row limit
         let max_rows = input
             .max_rows
-            .unwrap_or(self.config.security.max_result_rows);
+            .unwrap_or(self.config().security.max_result_rows);
+        let binary_options = self.config().query.binary_options(input.binary_format);
 
         // Check execution mode on the ORIGINAL query (before USE prefix)
         // This ensures pattern detection works correctly for batch-first DDL
@@ -99,27 +193,33 @@ impl MssqlMcpServer {
             // Multi-batch query with GO separators
             // Pass database context so each batch gets the USE prefix
             debug!("Using multi-batch execution for script with GO separators");
-            let result = match self
-                .executor
-                .execute_multi_batch_with_db(&input.query, current_db.as_deref())
-                .await
-            {
+            let outcome = with_partial_results(
+                input.allow_partial,
+                self.executor
+                    .execute_multi_batch_with_db(&input.query, current_db.as_deref()),
+            )
+            .await;
+            self.invalidate_cached(&input.query).await;
+            let mut result = match outcome {
                 Ok(r) => r,
                 Err(e) => {
                     warn!("Multi-batch execution failed: {}", e);
-                    return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                    return Ok(ToolOutput::error(format!(
+                        "Query execution failed: {}",
+                        self.localize_error(&e).await
+                    )));
                 }
             };
+            self.masker().mask_result(&mut result, None);
+            result.set_spatial_format(input.spatial_format);
+            result.set_binary_options(binary_options);
+
+            if let Some(reference) = self.result_store.store_if_large(&result).await {
+                return Ok(ToolOutput::text(format_result_reference(&reference)));
+            }
 
             // Format output based on requested format
-            let output = match input.format {
-                OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                    warn!("Failed to serialize query result to JSON: {}", e);
-                    format!("Failed to serialize result: {}", e)
-                }),
-                OutputFormat::Csv => result.to_csv(),
-                OutputFormat::Table => result.to_markdown_table(),
-            };
+            let output = formatting::render(&result, input.format);
 
             return Ok(ToolOutput::text(output));
         }
@@ -129,26 +229,35 @@ impl MssqlMcpServer {
             // must be executed using simple_query to avoid sp_executesql wrapper
             debug!("Using raw execution for batch-first DDL statement");
             let effective_query = match &current_db {
-                Some(db) => format!("USE [{}];\n{}", db, input.query),
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), input.query),
                 None => input.query.clone(),
             };
-            let result = match self.executor.execute_raw(&effective_query).await {
+            let outcome = with_partial_results(
+                input.allow_partial,
+                self.executor.execute_raw(&effective_query),
+            )
+            .await;
+            self.invalidate_cached(&input.query).await;
+            let mut result = match outcome {
                 Ok(r) => r,
                 Err(e) => {
                     warn!("Raw query execution failed: {}", e);
-                    return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                    return Ok(ToolOutput::error(format!(
+                        "Query execution failed: {}",
+                        self.localize_error(&e).await
+                    )));
                 }
             };
+            self.masker().mask_result(&mut result, None);
+            result.set_spatial_format(input.spatial_format);
+            result.set_binary_options(binary_options);
+
+            if let Some(reference) = self.result_store.store_if_large(&result).await {
+                return Ok(ToolOutput::text(format_result_reference(&reference)));
+            }
 
             // Format output based on requested format
-            let output = match input.format {
-                OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                    warn!("Failed to serialize query result to JSON: {}", e);
-                    format!("Failed to serialize result: {}", e)
-                }),
-                OutputFormat::Csv => result.to_csv(),
-                OutputFormat::Table => result.to_markdown_table(),
-            };
+            let output = formatting::render(&result, input.format);
 
             return Ok(ToolOutput::text(output));
         }
@@ -156,89 +265,473 @@ impl MssqlMcpServer {
         // Check for multiple result sets (multiple SELECT statements)
         if QueryExecutor::has_multiple_result_sets(&input.query) {
             debug!("Using multi-result execution for query with multiple SELECTs");
-            let effective_query = match &current_db {
-                Some(db) => format!("USE [{}];\n{}", db, input.query),
-                None => input.query.clone(),
-            };
-            let result = match self
-                .executor
-                .execute_multi_result(&effective_query, max_rows)
+            // Multiple statements get no result size warnings
+            let query = match self
+                .govern_query(&input.query, current_db.as_deref(), max_rows)
                 .await
             {
+                Ok(governed) => governed.query,
+                Err(e) => return Ok(ToolOutput::error(format!("Query rejected: {}", e))),
+            };
+            let effective_query = match &current_db {
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), query),
+                None => query,
+            };
+            let outcome = with_partial_results(
+                input.allow_partial,
+                self.executor
+                    .execute_multi_result(&effective_query, max_rows),
+            )
+            .await;
+            self.invalidate_cached(&input.query).await;
+            let mut result = match outcome {
                 Ok(r) => r,
                 Err(e) => {
                     warn!("Multi-result query execution failed: {}", e);
-                    return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                    return Ok(ToolOutput::error(format!(
+                        "Query execution failed: {}",
+                        self.localize_error(&e).await
+                    )));
                 }
             };
+            self.masker().mask_results(&mut result.result_sets);
+            for result_set in &mut result.result_sets {
+                result_set.set_spatial_format(input.spatial_format);
+                result_set.set_binary_options(binary_options);
+            }
 
             // Format output based on requested format
-            let output = match input.format {
-                OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                    warn!("Failed to serialize query result to JSON: {}", e);
-                    format!("Failed to serialize result: {}", e)
-                }),
-                OutputFormat::Csv => result.to_csv(),
-                OutputFormat::Table => result.to_markdown_table(),
-            };
+            let output = formatting::render(&result, input.format);
 
             return Ok(ToolOutput::text(output));
         }
 
-        // Standard execution with optional database context
-        let effective_query = match &current_db {
-            Some(db) => format!("USE [{}];\n{}", db, input.query),
-            None => input.query.clone(),
+        // Repeated read-only queries are served from the cache when enabled
+        let cache_key = (self.query_cache.is_enabled() && is_cacheable(&input.query)).then(|| {
+            let mode = self.config().security.validation_mode;
+            CacheKey::new(&input.query, max_rows, current_db.clone(), mode)
+        });
+        let cached = match &cache_key {
+            Some(key) => self.query_cache.get(key).await,
+            None => None,
         };
-        let result = match self
-            .executor
-            .execute_with_options(&effective_query, max_rows, input.timeout_seconds)
+
+        let mut result = match cached {
+            Some(result) => {
+                self.metrics.record_cache_hit();
+                result
+            }
+            None => {
+                if cache_key.is_some() {
+                    self.metrics.record_cache_miss();
+                }
+
+                // Standard execution with optional database context
+                let governed = match self
+                    .govern_query(&input.query, current_db.as_deref(), max_rows)
+                    .await
+                {
+                    Ok(governed) => governed,
+                    Err(e) => return Ok(ToolOutput::error(format!("Query rejected: {}", e))),
+                };
+                let effective_query = match &current_db {
+                    Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), governed.query),
+                    None => governed.query,
+                };
+                let outcome = with_partial_results(
+                    input.allow_partial,
+                    self.executor.execute_with_retry_policy(
+                        &effective_query,
+                        max_rows,
+                        input.timeout_seconds,
+                        !input.no_retry,
+                    ),
+                )
+                .await;
+                self.invalidate_cached(&input.query).await;
+                let mut result = match outcome {
+                    Ok(r) => r,
+                    Err(e) => {
+                        warn!("Query execution failed: {}", e);
+                        return Ok(ToolOutput::error(format!(
+                            "Query execution failed: {}",
+                            self.localize_error(&e).await
+                        )));
+                    }
+                };
+                result.messages.extend(governed.warnings);
+                // Cached before masking so that reloaded masking rules apply to hits;
+                // results cut short by the memory budget are not cached
+                let complete = !(input.allow_partial && result.truncated);
+                if let Some(key) = cache_key.filter(|_| complete) {
+                    self.query_cache.insert(key, result.clone()).await;
+                }
+                result
+            }
+        };
+        self.masker().mask_result(&mut result, None);
+        result.set_spatial_format(input.spatial_format);
+        result.set_binary_options(binary_options);
+
+        if let Some(reference) = self.result_store.store_if_large(&result).await {
+            return Ok(ToolOutput::text(format_result_reference(&reference)));
+        }
+
+        // Format output based on requested format
+        let output = formatting::render(&result, input.format);
+
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Retrieve rows from a large result returned by reference.
+    ///
+    /// Large results are stored server-side and identified by a content hash,
+    /// so clients can page through them without re-embedding the full table.
+    #[tool(
+        description = "Retrieve a slice of rows from a large query result that was returned as a reference (res_...).",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn fetch_result_chunk(
+        &self,
+        input: FetchResultChunkInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::{MAX_PAGE_SIZE, MIN_PAGE_SIZE};
+
+        let limit = input.limit.clamp(MIN_PAGE_SIZE, MAX_PAGE_SIZE);
+        let (chunk, total_rows) = match self
+            .result_store
+            .fetch_chunk(&input.result_ref, input.offset, limit)
             .await
         {
-            Ok(r) => r,
+            Ok(c) => c,
             Err(e) => {
-                warn!("Query execution failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "{} (references expire after {} seconds)",
+                    e,
+                    self.config().session.result_retention.as_secs()
+                )));
             }
         };
 
-        // Format output based on requested format
-        let output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!("Failed to serialize query result to JSON: {}", e);
-                format!("Failed to serialize result: {}", e)
+        let data_output = formatting::render(&chunk, input.format);
+
+        let offset = input.offset.min(total_rows);
+        let next_offset = offset + chunk.rows.len();
+        let response = json!({
+            "data": data_output,
+            "chunk": {
+                "result_ref": input.result_ref,
+                "offset": offset,
+                "row_count": chunk.rows.len(),
+                "total_rows": total_rows,
+                "has_more": next_offset < total_rows,
+                "next_offset": if next_offset < total_rows { Some(next_offset) } else { None },
+            },
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                warn!("Failed to serialize chunk response: {}", e);
+                format!("Chunk error: {}", e)
             }),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
+        ))
+    }
+
+    /// Run a call that was held back for confirmation.
+    ///
+    /// With `MSSQL_REQUIRE_CONFIRMATION` enabled, unfiltered UPDATE/DELETE,
+    /// TRUNCATE and DROP statements and large bulk inserts return a preview
+    /// and a token instead of running, unless the client could ask the user
+    /// directly; this tool redeems the token and runs the original call.
+    #[tool(
+        description = "Run a call held back for confirmation (UPDATE/DELETE without WHERE, TRUNCATE, DROP, large bulk_insert) using the confirmation_token from its preview.",
+        destructive = true
+    )]
+    pub async fn confirm_execution(
+        &self,
+        input: ConfirmExecutionInput,
+    ) -> Result<ToolOutput, McpError> {
+        let client = client_context_key();
+        let pending = match self
+            .confirmations
+            .confirm(&input.confirmation_token, &client)
+        {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
         };
+        info!(
+            "Confirmed {} call: {}",
+            pending.tool,
+            truncate_for_log(&pending.query, 100)
+        );
 
-        Ok(ToolOutput::text(output))
+        let invalid = |e: serde_json::Error| {
+            McpError::internal(format!("Invalid input of confirmed call: {}", e))
+        };
+        let result = match pending.tool.as_str() {
+            "execute_query" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.execute_query(input)).await
+            }
+            "execute_async" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.execute_async(input)).await
+            }
+            "execute_parameterized" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.execute_parameterized(input)).await
+            }
+            "execute_in_pinned_session" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.execute_in_pinned_session(input)).await
+            }
+            "run_script" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.run_script(input)).await
+            }
+            "execute_registered" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.execute_registered(input)).await
+            }
+            "bulk_insert" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.bulk_insert(input)).await
+            }
+            "generate_test_data" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.generate_test_data(input)).await
+            }
+            "anonymize_table" => {
+                let input = serde_json::from_value(pending.input).map_err(invalid)?;
+                Box::pin(self.anonymize_table(input)).await
+            }
+            other => Ok(ToolOutput::error(format!(
+                "Tool '{}' cannot be confirmed",
+                other
+            ))),
+        };
+        // The approval covers this one call only; revoke it if the call
+        // returned before reaching its confirmation check.
+        self.confirmations.take_approval(&client, &pending.query);
+        result
     }
 
     /// Explain a SQL query's execution plan.
     ///
-    /// Returns the estimated or actual execution plan for analysis.
-    #[tool(description = "Get the execution plan for a SQL query. Useful for query optimization.", read_only = true, idempotent = true)]
-    pub async fn explain_query(
+    /// Returns the estimated or actual execution plan as an operator tree with
+    /// row estimates, costs and warnings, plus a summary of likely problems.
+    /// `raw` returns the unparsed showplan rows instead.
+    #[tool(
+        description = "Get the execution plan for a SQL query as an operator tree (estimated/actual rows, cost, warnings such as implicit conversions and spills) with a summary of the top problems. Set raw=true for the unparsed showplan rows (format: table, json or csv). Note: 'actual' plans execute the query.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn explain_query(&self, input: ExplainQueryInput) -> Result<ToolOutput, McpError> {
+        debug!("Explaining query: {}", truncate_for_log(&input.query, 100));
+
+        if input.raw {
+            // Use the executor's showplan method which handles the batch separation correctly
+            let result = match self
+                .executor
+                .execute_with_showplan(&input.query, &input.plan_type)
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to get execution plan: {}",
+                        e
+                    )));
+                }
+            };
+
+            return Ok(ToolOutput::text(formatting::render(&result, input.format)));
+        }
+
+        let plan = match self.parsed_plan(&input.query, &input.plan_type).await {
+            Ok(plan) => plan,
+            Err(output) => return Ok(output),
+        };
+
+        let response = json!({
+            "plan_type": input.plan_type.to_lowercase(),
+            "problems": plan.problems,
+            "statements": plan.statements,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                warn!("Failed to serialize execution plan: {}", e);
+                format!("Failed to serialize execution plan: {}", e)
+            }),
+        ))
+    }
+
+    /// Compare the execution plans of two queries.
+    ///
+    /// With only `before`, the query's plan is saved as a per-client baseline
+    /// on the first call and compared with that baseline on later calls, which
+    /// covers checking a query before and after an index change.
+    #[tool(
+        description = "Compare execution plans to check whether a rewrite or index change helped: estimated cost delta, operator and join strategy changes, missing-index suggestions and plan problems resolved or introduced. Pass before and after queries, or only before to save a baseline and compare against it after a schema/index change. Note: 'actual' plans execute the queries.",
+        read_only = true
+    )]
+    pub async fn compare_plans(&self, input: ComparePlansInput) -> Result<ToolOutput, McpError> {
+        debug!(
+            "Comparing plans for: {}",
+            truncate_for_log(&input.before, 100)
+        );
+
+        let plan_type = input.plan_type.to_lowercase();
+        let current = match self.parsed_plan(&input.before, &plan_type).await {
+            Ok(plan) => plan,
+            Err(output) => return Ok(output),
+        };
+
+        let (before, after, baseline) = match &input.after {
+            Some(after) => match self.parsed_plan(after, &plan_type).await {
+                Ok(plan) => (current, plan, false),
+                Err(output) => return Ok(output),
+            },
+            None => {
+                let key = format!("{}:{}", plan_type, input.before.trim());
+                let mut state = self.state.write().await;
+                match state.plan_baseline(&key).cloned() {
+                    Some(saved) if !input.reset_baseline => (saved, current, true),
+                    _ => {
+                        state.set_plan_baseline(key, current.clone());
+                        let response = json!({
+                            "status": "baseline_saved",
+                            "message": "Baseline saved; call compare_plans again with the same query after the change to compare.",
+                            "estimated_cost": current.total_cost(),
+                            "problems": current.problems,
+                        });
+                        return Ok(ToolOutput::text(
+                            serde_json::to_string_pretty(&response).unwrap_or_default(),
+                        ));
+                    }
+                }
+            }
+        };
+
+        let comparison = before.compare(&after);
+        let response = json!({
+            "plan_type": plan_type,
+            "compared_with": if baseline { "saved baseline" } else { "before query" },
+            "comparison": comparison,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                warn!("Failed to serialize plan comparison: {}", e);
+                format!("Failed to serialize plan comparison: {}", e)
+            }),
+        ))
+    }
+
+    /// Estimate the impact of an UPDATE or DELETE without running it.
+    ///
+    /// The statement is rewritten into a `SELECT COUNT_BIG(*)` over the same
+    /// source and filter, plus a `SELECT TOP (n)` preview of the affected rows
+    /// (with the new values of updated columns).
+    #[tool(
+        description = "Estimate how many rows an UPDATE or DELETE would affect and preview them (with new values for updates) without executing it.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn estimate_impact(
         &self,
-        input: ExplainQueryInput,
+        input: EstimateImpactInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!("Explaining query: {}", truncate_for_log(&input.query, 100));
+        use crate::constants::MAX_SAMPLE_SIZE;
+        use crate::security::confirmation::plan_impact;
 
-        // Use the executor's showplan method which handles the batch separation correctly
-        let result = match self
+        debug!(
+            "Estimating impact of: {}",
+            truncate_for_log(&input.query, 100)
+        );
+
+        let plan = match plan_impact(&input.query, input.sample_size.clamp(1, MAX_SAMPLE_SIZE)) {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolOutput::error(format!("Cannot estimate impact: {}", e))),
+        };
+        for query in [&plan.count_query, &plan.sample_query] {
+            if let Err(e) = self.validate_query(query) {
+                return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+            }
+        }
+
+        let current_db = {
+            let state = self.state.read().await;
+            state.current_database().map(|s| s.to_string())
+        };
+        let in_database = |query: &str| match &current_db {
+            Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), query),
+            None => query.to_string(),
+        };
+
+        let affected_rows = match self.executor.execute(&in_database(&plan.count_query)).await {
+            Ok(r) => r
+                .rows
+                .first()
+                .and_then(|row| row.get("affected_rows"))
+                .and_then(|v| v.to_display_string().parse::<u64>().ok())
+                .unwrap_or(0),
+            Err(e) => {
+                warn!("Impact count query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Impact estimation failed: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let mut sample = match self
             .executor
-            .execute_with_showplan(&input.query, &input.plan_type)
+            .execute(&in_database(&plan.sample_query))
             .await
         {
             Ok(r) => r,
             Err(e) => {
-                return Ok(ToolOutput::error(format!("Failed to get execution plan: {}", e)));
+                warn!("Impact sample query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Impact estimation failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        self.masker().mask_result(&mut sample, None);
 
-        let output = result.to_markdown_table();
-        Ok(ToolOutput::text(output))
+        // TOP (n) caps the rows the statement changes
+        let estimated_rows = match plan.top.as_deref().and_then(|t| t.parse::<u64>().ok()) {
+            Some(top) => affected_rows.min(top),
+            None => affected_rows,
+        };
+        let mut warnings = Vec::new();
+        if !plan.filtered {
+            warnings.push("Statement has no WHERE clause and affects every source row");
+        }
+        if plan.top.is_some() {
+            warnings.push("TOP (n) changes an arbitrary subset of the matching rows");
+        }
+
+        let response = json!({
+            "kind": plan.kind,
+            "target": plan.target,
+            "filtered": plan.filtered,
+            "top": plan.top,
+            "matching_rows": affected_rows,
+            "estimated_affected_rows": estimated_rows,
+            "warnings": warnings,
+            "sample_row_count": sample.rows.len(),
+            "sample": sample.rows.iter().map(|r| &r.columns).collect::<Vec<_>>(),
+            "count_query": plan.count_query,
+            "sample_query": plan.sample_query,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                warn!("Failed to serialize impact estimate: {}", e);
+                format!("Impact estimation failed: {}", e)
+            }),
+        ))
     }
 
     // =========================================================================
@@ -255,6 +748,15 @@ impl MssqlMcpServer {
     ) -> Result<ToolOutput, McpError> {
         debug!("Executing procedure: {}.{}", input.schema, input.procedure);
 
+        let config = self.config();
+        let allowed = &config.security.allowed_procedures;
+        if !procedures::procedure_allowed(allowed, &input.schema, &input.procedure) {
+            return Ok(ToolOutput::error(format!(
+                "Procedure {}.{} is not in the allow-list (MSSQL_ALLOWED_PROCEDURES)",
+                input.schema, input.procedure
+            )));
+        }
+
         // Build the EXEC statement with validated and escaped identifiers
         let escaped_schema = match safe_identifier(&input.schema) {
             Ok(s) => s,
@@ -286,18 +788,30 @@ impl MssqlMcpServer {
             format!(" {}", param_strs.join(", "))
         };
 
-        let query = format!("EXEC {}{}", proc_name, params);
+        let exec_args = format!("{}{}", proc_name, params);
 
-        // Execute the procedure
-        let result = match self.executor.execute(&query).await {
+        // Execute the procedure, capturing all result sets
+        let result = self
+            .executor
+            .execute_procedure(&exec_args, self.config().security.max_result_rows)
+            .await;
+        // The tables a procedure modifies can't be told from the call
+        self.clear_cached().await;
+        let mut result = match result {
             Ok(r) => r,
             Err(e) => {
                 warn!("Procedure execution failed: {}", e);
-                return Ok(ToolOutput::error(format!("Procedure execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "Procedure execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        self.masker().mask_results(&mut result.result_sets);
+
+        // Format output based on requested format
+        let output = formatting::render(&result, input.format);
 
-        let output = result.to_markdown_table();
         Ok(ToolOutput::text(output))
     }
 
@@ -310,7 +824,13 @@ impl MssqlMcpServer {
     /// Prerequisites:
     /// - A table type must exist in the database (CREATE TYPE schema.TypeName AS TABLE...)
     /// - The query should reference the TVP parameter (e.g., @p1 or @tvp)
-    #[tool(description = "Execute a query or stored procedure with a Table-Valued Parameter (TVP). Enables efficient bulk data passing to stored procedures.", destructive = true)]
+    ///
+    /// The table type's columns are read from `sys.table_types`, and rows are
+    /// validated and converted against them before anything is sent.
+    #[tool(
+        description = "Execute a query or stored procedure with a Table-Valued Parameter (TVP). Enables efficient bulk data passing to stored procedures. The table type's columns are looked up automatically; rows can be arrays in column order or objects keyed by column name, and are validated and converted to the column types with errors naming the row and column.",
+        destructive = true
+    )]
     pub async fn execute_with_tvp(
         &self,
         input: ExecuteWithTvpInput,
@@ -323,56 +843,90 @@ impl MssqlMcpServer {
             input.rows.len()
         );
 
-        // Validate columns
-        if input.columns.is_empty() {
-            return Ok(ToolOutput::error("At least one column definition is required for TVP"));
-        }
+        // Look up the table type's definition so rows are checked server-side
+        let (schema, type_name) = match parse_qualified_name(&input.tvp_type_name) {
+            Ok((schema, name)) => (schema.unwrap_or_else(|| "dbo".to_string()), name),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!("Invalid table type name: {}", e)));
+            }
+        };
+        let definition = match self
+            .metadata
+            .get_table_type_columns(&schema, &type_name)
+            .await
+        {
+            Ok(columns) if columns.is_empty() => {
+                return Ok(ToolOutput::error(format!(
+                    "Table type '{}.{}' not found. Create it with CREATE TYPE {}.{} AS TABLE (...)",
+                    schema, type_name, schema, type_name
+                )));
+            }
+            Ok(columns) => columns,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read table type '{}.{}': {}",
+                    schema, type_name, e
+                )));
+            }
+        };
 
-        // Validate rows have correct column count
-        for (idx, row) in input.rows.iter().enumerate() {
-            if row.len() != input.columns.len() {
+        // Column definitions given by the client must agree with the table type
+        if !input.columns.is_empty() {
+            let given: Vec<&str> = input.columns.iter().map(|c| c.name.as_str()).collect();
+            let matches = given.len() == definition.len()
+                && given
+                    .iter()
+                    .zip(&definition)
+                    .all(|(name, column)| column.name.eq_ignore_ascii_case(name));
+            if !matches {
+                let expected: Vec<String> = definition
+                    .iter()
+                    .map(|c| format!("{} {}", c.name, c.sql_type()))
+                    .collect();
                 return Ok(ToolOutput::error(format!(
-                    "Row {} has {} values but {} columns defined",
-                    idx,
-                    row.len(),
-                    input.columns.len()
+                    "Columns ({}) do not match table type {}.{} ({})",
+                    given.join(", "),
+                    schema,
+                    type_name,
+                    expected.join(", ")
                 )));
             }
         }
 
-        // Build column definitions
-        let columns: Vec<(String, String)> = input
-            .columns
-            .iter()
-            .map(|c| (c.name.clone(), c.sql_type.clone()))
-            .collect();
-
-        // Build the TVP
-        let tvp = match QueryExecutor::build_tvp(&input.tvp_type_name, &columns, &input.rows) {
+        // Build the TVP, converting each value to its column's type
+        let tvp = match QueryExecutor::build_typed_tvp(
+            &format!("{}.{}", schema, type_name),
+            &definition,
+            &input.rows,
+        ) {
             Ok(t) => t,
             Err(e) => {
-                return Ok(ToolOutput::error(format!("Failed to build TVP: {}", e)));
+                return Ok(ToolOutput::error(format!("Invalid TVP rows: {}", e)));
             }
         };
 
         // Execute the query with the TVP parameter
-        let max_rows = self.config.security.max_result_rows;
-        let result = match self.executor.execute_with_tvp(&input.query, tvp, max_rows).await {
+        let max_rows = self.config().security.max_result_rows;
+        let result = self
+            .executor
+            .execute_with_tvp(&input.query, tvp, max_rows)
+            .await;
+        // The query typically calls a procedure, whose modified tables can't
+        // be told from its text
+        self.clear_cached().await;
+        let mut result = match result {
             Ok(r) => r,
             Err(e) => {
                 warn!("TVP query execution failed: {}", e);
-                return Ok(ToolOutput::error(format!("TVP query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "TVP query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        self.masker().mask_result(&mut result, None);
 
-        let output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!("Failed to serialize TVP query result to JSON: {}", e);
-                format!("Failed to serialize result: {}", e)
-            }),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
-        };
+        let output = formatting::render(&result, input.format);
 
         Ok(ToolOutput::text(output))
     }
@@ -396,31 +950,55 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Some(preview) = self
+            .confirmation_preview("execute_async", &input.query, &input)
+            .await
+        {
+            return Ok(preview);
+        }
+
+        let max_rows = input
+            .max_rows
+            .unwrap_or(self.config().security.max_result_rows);
+        let governed = match self.govern_query(&input.query, None, max_rows).await {
+            Ok(governed) => governed,
+            Err(e) => return Ok(ToolOutput::error(format!("Query rejected: {}", e))),
+        };
+        let query = governed.query;
+        let warnings = governed.warnings;
 
         // Create a new session
         let session_id = {
             let mut state = self.state.write().await;
-            match state.create_session(input.query.clone(), self.config.session.max_sessions) {
+            let id = match state
+                .create_session(input.query.clone(), self.config().session.max_sessions)
+            {
                 Ok(id) => id,
                 Err(e) => {
                     return Ok(ToolOutput::error(format!("Failed to create session: {}", e)));
                 }
+            };
+            if let Err(e) = self.rate_limiter.begin_session(&id) {
+                state.remove_session(&id);
+                return Ok(throttle_output(&e));
             }
+            id
         };
 
         // Get a connection from the pool to access the cancel handle
-        let mut conn = match self.pool.get().await {
+        let mut conn = match self.executor.acquire().await {
             Ok(c) => c,
             Err(e) => {
                 // Clean up the session we just created
+                self.rate_limiter.end_session(&session_id);
                 let mut state = self.state.write().await;
                 if let Some(session) = state.get_session_mut(&session_id) {
-                    session.fail(format!("Failed to get connection: {}", e));
+                    session.fail(e.to_string());
                 }
-                return Ok(ToolOutput::error(format!(
-                    "Failed to get connection from pool: {}",
-                    e
-                )));
+                return Ok(ToolOutput::error(e.to_string()));
             }
         };
 
@@ -433,110 +1011,393 @@ impl MssqlMcpServer {
 
         // Spawn the async execution task with the connection
         let state = self.state.clone();
-        let max_rows = input
-            .max_rows
-            .unwrap_or(self.config.security.max_result_rows);
+        let rate_limiter = self.rate_limiter.clone();
+        let cache = self.query_cache.clone();
+        let session_store = self.session_store.clone();
+        let masker = self.masker();
         let timeout_seconds = input.timeout_seconds;
-        let query = input.query;
+        let allow_partial = input.allow_partial;
         let sid = session_id.clone();
 
-        tokio::spawn(async move {
-            use crate::database::{QueryColumnInfo as ColumnInfo, QueryResult, ResultRow, TypeMapper};
-            use futures_util::TryStreamExt;
-            use std::time::{Duration, Instant};
+        // Keep the request ID in logs and SQL tags of the background task
+        let request = current_request().unwrap_or_default();
 
-            let start = Instant::now();
+        tokio::spawn(
+            scope_request(request, async move {
+                use crate::constants::ASYNC_PROGRESS_ROWS;
+                use crate::database::{
+                    QueryColumnInfo as ColumnInfo, QueryResult, ResultMemory, ResultRow, TypeMapper,
+                };
+                use futures_util::TryStreamExt;
+                use std::time::{Duration, Instant};
+
+                let start = Instant::now();
+
+                // Execute the query on the dedicated connection
+                let result = async {
+                    let stream = conn
+                        .query(&tag_query(&query), &[])
+                        .await
+                        .map_err(|e| format!("Query execution failed: {}", e))?;
+
+                    // Process the stream with row limit
+                    let mut columns: Vec<ColumnInfo> = Vec::new();
+                    let mut rows = Vec::new();
+                    let mut truncated = false;
+                    let mut row_count = 0;
+                    let mut memory = ResultMemory::new();
+
+                    futures_util::pin_mut!(stream);
+                    while let Some(row) = stream
+                        .try_next()
+                        .await
+                        .map_err(|e| format!("Failed to read row: {}", e))?
+                    {
+                        // Extract column info from first row
+                        if columns.is_empty() {
+                            let row_columns = row.columns();
+                            for (i, col) in row_columns.iter().enumerate() {
+                                let name = col.name.clone();
+                                let sql_type = if !col.type_name.is_empty() {
+                                    col.type_name.clone()
+                                } else {
+                                    let sample_value = TypeMapper::extract_column(&row, i);
+                                    TypeMapper::sql_type_name_from_value(&sample_value).to_string()
+                                };
+                                columns.push(ColumnInfo {
+                                    name,
+                                    sql_type,
+                                    nullable: col.nullable,
+                                });
+                            }
+                        }
 
-            // Execute the query on the dedicated connection
-            let result = async {
-                let stream = conn
-                    .query(&query, &[])
-                    .await
-                    .map_err(|e| format!("Query execution failed: {}", e))?;
-
-                // Process the stream with row limit
-                let mut columns: Vec<ColumnInfo> = Vec::new();
-                let mut rows = Vec::new();
-                let mut truncated = false;
-                let mut row_count = 0;
-
-                futures_util::pin_mut!(stream);
-                while let Some(row) = stream.try_next().await.map_err(|e| format!("Failed to read row: {}", e))? {
-                    // Extract column info from first row
-                    if columns.is_empty() {
-                        let row_columns = row.columns();
-                        for (i, col) in row_columns.iter().enumerate() {
-                            let name = col.name.clone();
-                            let sql_type = if !col.type_name.is_empty() {
-                                col.type_name.clone()
-                            } else {
-                                let sample_value = TypeMapper::extract_column(&row, i);
-                                TypeMapper::sql_type_name_from_value(&sample_value).to_string()
-                            };
-                            columns.push(ColumnInfo {
-                                name,
-                                sql_type,
-                                nullable: col.nullable,
-                            });
+                        if row_count >= max_rows {
+                            truncated = true;
+                            break;
                         }
-                    }
 
-                    if row_count >= max_rows {
-                        truncated = true;
-                        break;
+                        let mut result_row = ResultRow::new();
+                        for (col_idx, col) in columns.iter().enumerate() {
+                            let value = TypeMapper::extract_column(&row, col_idx);
+                            result_row.insert(col.name.clone(), value);
+                        }
+                        if !memory.admit(&result_row).map_err(|e| e.to_string())? {
+                            truncated = true;
+                            break;
+                        }
+                        rows.push(result_row);
+                        row_count += 1;
+
+                        if row_count % ASYNC_PROGRESS_ROWS == 0 {
+                            let message = format!("{} rows read", row_count);
+                            report_progress(row_count as u64, None, &message);
+                            let mut state = state.write().await;
+                            if let Some(session) = state.get_session_mut(&sid) {
+                                // The total is unknown, so progress is measured against the row limit
+                                session.set_progress((row_count * 100 / max_rows).min(99) as u8);
+                            }
+                        }
                     }
 
-                    let mut result_row = ResultRow::new();
-                    for (col_idx, col) in columns.iter().enumerate() {
-                        let value = TypeMapper::extract_column(&row, col_idx);
-                        result_row.insert(col.name.clone(), value);
+                    Ok::<_, String>(QueryResult {
+                        columns,
+                        rows,
+                        rows_affected: 0,
+                        execution_time_ms: start.elapsed().as_millis() as u64,
+                        truncated,
+                        retry: None,
+                        messages: warnings.into_iter().chain(memory.partial_note()).collect(),
+                    })
+                };
+                let result = with_partial_results(allow_partial, result);
+
+                // Apply timeout if specified
+                let result = if let Some(secs) = timeout_seconds {
+                    match tokio::time::timeout(Duration::from_secs(secs), result).await {
+                        Ok(r) => r,
+                        Err(_) => Err(format!("Query timed out after {} seconds", secs)),
+                    }
+                } else {
+                    result.await
+                };
+                cache.invalidate_modified(&query).await;
+
+                // Update session state and clean up cancel handle
+                rate_limiter.end_session(&sid);
+                let mut state = state.write().await;
+                // Remove the cancel handle now that the query is complete
+                state.remove_cancel_handle(&sid);
+
+                if let Some(session) = state.get_session_mut(&sid) {
+                    // Cancelled sessions keep their status
+                    if !session.is_running() {
+                        return;
+                    }
+                    match result {
+                        Ok(mut r) => {
+                            info!("Async query {} completed successfully", sid);
+                            masker.mask_result(&mut r, None);
+                            session.complete(r);
+                        }
+                        Err(e) => {
+                            warn!("Async query {} failed: {}", sid, e);
+                            session.fail(e);
+                        }
                     }
-                    rows.push(result_row);
-                    row_count += 1;
                 }
 
-                Ok::<_, String>(QueryResult {
-                    columns,
-                    rows,
-                    rows_affected: 0,
-                    execution_time_ms: start.elapsed().as_millis() as u64,
-                    truncated,
-                })
+                // Persist the finished session outside the state lock
+                let finished = state.get_session(&sid).cloned();
+                drop(state);
+                if let Some(session) = finished {
+                    session_store.save(&session);
+                }
+            })
+            .instrument(tracing::Span::current()),
+        );
+
+        let response = json!({
+            "session_id": session_id,
+            "status": "running",
+            "message": "Query execution started. Use get_session_status to check progress.",
+            "cancellable": cancel_handle.is_some()
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Session ID: {}", session_id)),
+        ))
+    }
+
+    /// Run a multi-batch SQLCMD-style script as an async session.
+    ///
+    /// Batches run sequentially on one connection, so session settings and
+    /// temp tables carry over between them. Progress is reported through the
+    /// session, and the completed session holds a per-batch summary.
+    #[tool(
+        description = "Run a multi-batch script (GO separators, :setvar and $(VAR) variables, :on error) as an async session. Batches run in order on one connection; progress and a per-batch summary (status, rows affected, duration, error) are available through get_session_status.",
+        destructive = true
+    )]
+    pub async fn run_script(&self, input: RunScriptInput) -> Result<ToolOutput, McpError> {
+        use crate::database::script::parse_script;
+
+        let script = match parse_script(&input.script, &input.variables) {
+            Ok(script) => script,
+            Err(e) => return Ok(ToolOutput::error(format!("Invalid script: {}", e))),
+        };
+        if script.batches.is_empty() {
+            return Ok(ToolOutput::error("Script contains no batches"));
+        }
+        // Repetitions of a `GO n` batch share its line and are checked once
+        let mut checked_line = None;
+        for batch in &script.batches {
+            if checked_line.replace(batch.line) == Some(batch.line) {
+                continue;
+            }
+            let checked = match self.validate_query(&batch.sql) {
+                Ok(()) => self.check_compatibility(&batch.sql).await,
+                Err(e) => Err(e),
             };
+            if let Err(e) = checked {
+                return Ok(ToolOutput::error(format!(
+                    "Batch {} (line {}) failed validation: {}",
+                    batch.number, batch.line, e
+                )));
+            }
+        }
+        let use_database = match input.database.as_deref().map(safe_identifier) {
+            Some(Ok(db)) => Some(format!("USE {}", db)),
+            Some(Err(e)) => return Ok(ToolOutput::error(format!("Invalid database name: {}", e))),
+            None => None,
+        };
 
-            // Apply timeout if specified
-            let result = if let Some(secs) = timeout_seconds {
-                match tokio::time::timeout(Duration::from_secs(secs), result).await {
-                    Ok(r) => r,
-                    Err(_) => Err(format!("Query timed out after {} seconds", secs)),
+        let joined = script
+            .batches
+            .iter()
+            .map(|b| b.sql.as_str())
+            .collect::<Vec<_>>()
+            .join("\nGO\n");
+        if let Some(preview) = self
+            .confirmation_preview("run_script", &joined, &input)
+            .await
+        {
+            return Ok(preview);
+        }
+
+        let session_id = {
+            let mut state = self.state.write().await;
+            let id = match state.create_session(joined.clone(), self.config().session.max_sessions)
+            {
+                Ok(id) => id,
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to create session: {}",
+                        e
+                    )));
                 }
-            } else {
-                result.await
             };
+            if let Err(e) = self.rate_limiter.begin_session(&id) {
+                state.remove_session(&id);
+                return Ok(throttle_output(&e));
+            }
+            id
+        };
+
+        let mut conn = match self.executor.acquire().await {
+            Ok(c) => c,
+            Err(e) => {
+                self.rate_limiter.end_session(&session_id);
+                let mut state = self.state.write().await;
+                if let Some(session) = state.get_session_mut(&session_id) {
+                    session.fail(e.to_string());
+                }
+                return Ok(ToolOutput::error(e.to_string()));
+            }
+        };
+
+        let cancel_handle = conn.client().map(|c| c.cancel_handle());
+        if let Some(ref handle) = cancel_handle {
+            let mut state = self.state.write().await;
+            state.store_cancel_handle(&session_id, handle.clone());
+        }
+
+        let state = self.state.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let cache = self.query_cache.clone();
+        let session_store = self.session_store.clone();
+        let stop_on_error = script.stop_on_error.unwrap_or(input.stop_on_error);
+        let batch_timeout = input.batch_timeout_seconds;
+        let batches = script.batches;
+        let batch_count = batches.len();
+        let sid = session_id.clone();
+        let request = current_request().unwrap_or_default();
 
-            // Update session state and clean up cancel handle
-            let mut state = state.write().await;
-            // Remove the cancel handle now that the query is complete
-            state.remove_cancel_handle(&sid);
+        tokio::spawn(
+            scope_request(request, async move {
+                use crate::database::script::{summary_result, BatchOutcome, BatchStatus};
+                use std::time::{Duration, Instant};
 
-            if let Some(session) = state.get_session_mut(&sid) {
-                match result {
-                    Ok(r) => {
-                        info!("Async query {} completed successfully", sid);
-                        session.complete(r);
+                let start = Instant::now();
+                let mut outcomes = Vec::with_capacity(batch_count);
+                let mut stopped: Option<String> = None;
+
+                if let Some(ref use_database) = use_database {
+                    if let Err(e) = conn.execute(&tag_query(use_database), &[]).await {
+                        stopped = Some(format!("Failed to switch database: {}", e));
                     }
-                    Err(e) => {
-                        warn!("Async query {} failed: {}", sid, e);
-                        session.fail(e);
+                }
+
+                for batch in batches {
+                    let running = {
+                        let state = state.read().await;
+                        state.get_session(&sid).is_some_and(|s| s.is_running())
+                    };
+                    if stopped.is_some() || !running {
+                        outcomes.push(BatchOutcome {
+                            batch,
+                            status: BatchStatus::Skipped,
+                            duration_ms: 0,
+                        });
+                        continue;
+                    }
+
+                    let batch_start = Instant::now();
+                    let sql = tag_query(&batch.sql);
+                    let execution = conn.execute(&sql, &[]);
+                    let result = match batch_timeout {
+                        Some(secs) => {
+                            match tokio::time::timeout(Duration::from_secs(secs), execution).await {
+                                Ok(r) => r.map_err(|e| e.to_string()),
+                                Err(_) => {
+                                    // The abandoned batch leaves the connection unusable
+                                    let error = format!("Batch timed out after {} seconds", secs);
+                                    stopped = Some(format!(
+                                        "Batch {} (line {}) failed: {}",
+                                        batch.number, batch.line, error
+                                    ));
+                                    Err(error)
+                                }
+                            }
+                        }
+                        None => execution.await.map_err(|e| e.to_string()),
+                    };
+                    cache.invalidate_modified(&batch.sql).await;
+
+                    let status = match result {
+                        Ok(rows_affected) => BatchStatus::Succeeded { rows_affected },
+                        Err(error) => {
+                            warn!("Script {} batch {} failed: {}", sid, batch.number, error);
+                            if stop_on_error && stopped.is_none() {
+                                stopped = Some(format!(
+                                    "Batch {} (line {}) failed: {}",
+                                    batch.number, batch.line, error
+                                ));
+                            }
+                            BatchStatus::Failed { error }
+                        }
+                    };
+                    outcomes.push(BatchOutcome {
+                        batch,
+                        status,
+                        duration_ms: batch_start.elapsed().as_millis() as u64,
+                    });
+
+                    report_progress(
+                        outcomes.len() as u64,
+                        Some(batch_count as u64),
+                        &format!("Batch {}/{}", outcomes.len(), batch_count),
+                    );
+                    let mut state = state.write().await;
+                    if let Some(session) = state.get_session_mut(&sid) {
+                        session.set_progress((outcomes.len() * 100 / batch_count) as u8);
                     }
                 }
-            }
-        });
+
+                let summary = summary_result(&outcomes, start.elapsed().as_millis() as u64);
+                let failed = outcomes
+                    .iter()
+                    .filter(|o| matches!(o.status, BatchStatus::Failed { .. }))
+                    .count();
+
+                rate_limiter.end_session(&sid);
+                let mut state = state.write().await;
+                state.remove_cancel_handle(&sid);
+
+                if let Some(session) = state.get_session_mut(&sid) {
+                    if !session.is_running() {
+                        return;
+                    }
+                    match stopped {
+                        Some(error) => {
+                            warn!("Script {} stopped: {}", sid, error);
+                            session.fail(format!("{}\n\n{}", error, summary.to_markdown_table()));
+                        }
+                        None => {
+                            info!(
+                                "Script {} completed: {} batches, {} failed",
+                                sid, batch_count, failed
+                            );
+                            session.complete(summary);
+                        }
+                    }
+                }
+
+                let finished = state.get_session(&sid).cloned();
+                drop(state);
+                if let Some(session) = finished {
+                    session_store.save(&session);
+                }
+            })
+            .instrument(tracing::Span::current()),
+        );
 
         let response = json!({
             "session_id": session_id,
             "status": "running",
-            "message": "Query execution started. Use get_session_status to check progress.",
+            "batch_count": batch_count,
+            "stop_on_error": stop_on_error,
+            "message": "Script execution started. Use get_session_status to check progress and the per-batch summary.",
             "cancellable": cancel_handle.is_some()
         });
 
@@ -586,7 +1447,10 @@ impl MssqlMcpServer {
                     "columns": result.columns.iter().map(|c| &c.name).collect::<Vec<_>>(),
                     "execution_time_ms": result.execution_time_ms,
                     "truncated": result.truncated,
-                    "data": result.to_markdown_table(),
+                    "data": match input.format {
+                        OutputFormat::Json => json!(result.rows),
+                        format => json!(formatting::render(result, format)),
+                    },
                 });
             }
         }
@@ -666,6 +1530,7 @@ impl MssqlMcpServer {
 
         if let Some(session) = state.get_session_mut(&input.session_id) {
             session.cancel();
+            self.session_store.save(session);
         }
 
         info!("Session {} cancelled", input.session_id);
@@ -717,14 +1582,78 @@ impl MssqlMcpServer {
         ))
     }
 
+    /// Remove finished async query sessions.
+    ///
+    /// Running sessions are never removed; persisted copies are deleted too.
+    #[tool(
+        description = "Remove finished async query sessions and free their results. Filter by session IDs, status ('completed', 'failed', 'cancelled') and age; running sessions are never removed.",
+        destructive = true,
+        idempotent = true
+    )]
+    pub async fn purge_sessions(&self, input: PurgeSessionsInput) -> Result<ToolOutput, McpError> {
+        let status = match input.status.to_lowercase().as_str() {
+            "all" => None,
+            "completed" => Some(SessionStatus::Completed),
+            "failed" => Some(SessionStatus::Failed),
+            "cancelled" => Some(SessionStatus::Cancelled),
+            other => {
+                return Ok(ToolOutput::error(format!(
+                    "Invalid status '{}'. Use 'completed', 'failed', 'cancelled' or 'all'.",
+                    other
+                )));
+            }
+        };
+
+        let mut state = self.state.write().await;
+        let before = state.session_memory_bytes();
+        let purged = state.purge_sessions(|session| {
+            (input.session_ids.is_empty() || input.session_ids.contains(&session.id))
+                && status.is_none_or(|status| session.status == status)
+                && input.older_than_seconds.is_none_or(|seconds| {
+                    (chrono::Utc::now() - session.updated_at).num_seconds() >= seconds as i64
+                })
+        });
+        let freed_bytes = before - state.session_memory_bytes();
+        let remaining = state.total_session_count();
+        drop(state);
+
+        for id in &purged {
+            self.session_store.remove(id);
+        }
+
+        info!("Purged {} async sessions", purged.len());
+
+        let response = json!({
+            "purged": purged.len(),
+            "session_ids": purged,
+            "freed_bytes": freed_bytes,
+            "remaining": remaining,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Purged {} sessions", purged.len())),
+        ))
+    }
+
     /// Get the results of an async query session.
     ///
     /// Retrieves the results from a completed async query session with formatting options.
-    #[tool(description = "Get the results of a completed async query session with formatting options.", read_only = true, idempotent = true)]
+    /// Results too large to return inline point to the paginated
+    /// `mssql://sessions/{id}/results` resource unless a row range is given.
+    #[tool(
+        description = "Get the results of a completed async query session with formatting options. Pass offset and max_rows for a row range; results too large to return inline are read page by page from the mssql://sessions/{id}/results?page=N resource.",
+        read_only = true,
+        idempotent = true
+    )]
     pub async fn get_session_results(
         &self,
         input: GetSessionResultsInput,
     ) -> Result<ToolOutput, McpError> {
+        use crate::cache::estimate_result_size;
+        use crate::constants::DEFAULT_PAGE_SIZE;
+        use crate::results::{slice_rows, SessionResultsPage};
+
         let state = self.state.read().await;
 
         let session = match state.get_session(&input.session_id) {
@@ -753,152 +1682,519 @@ impl MssqlMcpServer {
             }
         };
 
-        // Apply row limit if specified
-        let rows_to_show = input.max_rows.unwrap_or(result.rows.len());
-        let truncated_by_request = rows_to_show < result.rows.len();
-
-        // Format output based on requested format
-        let output = match input.format {
-            OutputFormat::Json => {
-                let limited_result = if truncated_by_request {
-                    let mut limited = result.clone();
-                    limited.rows.truncate(rows_to_show);
-                    limited.truncated = true;
-                    limited
-                } else {
-                    result.clone()
-                };
-                serde_json::to_string_pretty(&limited_result).unwrap_or_else(|e| {
-                    warn!("Failed to serialize session result to JSON: {}", e);
-                    format!("Failed to serialize result: {}", e)
-                })
-            }
-            OutputFormat::Csv => {
-                if truncated_by_request {
-                    let mut limited = result.clone();
-                    limited.rows.truncate(rows_to_show);
-                    limited.to_csv()
-                } else {
-                    result.to_csv()
-                }
-            }
-            OutputFormat::Table => {
-                if truncated_by_request {
-                    let mut limited = result.clone();
-                    limited.rows.truncate(rows_to_show);
-                    limited.to_markdown_table()
-                } else {
-                    result.to_markdown_table()
-                }
+        let total_rows = result.rows.len();
+
+        // Large results are read page by page from the session results resource
+        let threshold_bytes = self.config().query.result_ref_threshold_kb * 1024;
+        if input.offset == 0 && input.max_rows.is_none() && threshold_bytes > 0 {
+            let size_bytes = estimate_result_size(result);
+            if size_bytes > threshold_bytes {
+                let first_page = SessionResultsPage::uri(&input.session_id, 1, DEFAULT_PAGE_SIZE);
+                let response = json!({
+                    "session_id": input.session_id,
+                    "total_rows": total_rows,
+                    "size_bytes": size_bytes,
+                    "columns": result.columns,
+                    "page_size": DEFAULT_PAGE_SIZE,
+                    "page_count": total_rows.div_ceil(DEFAULT_PAGE_SIZE),
+                    "first_page": first_page,
+                    "message": format!(
+                        "Result is too large to return inline ({} rows, ~{} KB). Read resource {} page by page, or pass offset and max_rows for a row range.",
+                        total_rows,
+                        size_bytes.div_ceil(1024),
+                        first_page
+                    ),
+                });
+                return Ok(ToolOutput::text(
+                    serde_json::to_string_pretty(&response)
+                        .unwrap_or_else(|e| format!("Failed to serialize result: {}", e)),
+                ));
             }
+        }
+
+        // Apply the requested row range
+        let limit = input.max_rows.unwrap_or(total_rows);
+        let output = if input.offset == 0 && limit >= total_rows {
+            formatting::render(result, input.format)
+        } else {
+            formatting::render(&slice_rows(result, input.offset, limit), input.format)
         };
 
         Ok(ToolOutput::text(output))
     }
 
     // =========================================================================
-    // Diagnostics Tools
+    // Table Watch Tools
     // =========================================================================
 
-    /// Check database connectivity and health.
+    /// Watch a table for new or changed rows.
     ///
-    /// Returns connection status and optionally detailed diagnostics.
-    #[tool(description = "Test database connectivity and return health status with optional diagnostics.", read_only = true, idempotent = true)]
-    pub async fn health_check(
-        &self,
-        input: HealthCheckInput,
-    ) -> Result<ToolOutput, McpError> {
-        debug!("Performing health check (detailed: {})", input.detailed);
-
-        let start = std::time::Instant::now();
-
-        // Try to execute a simple query to test connectivity
-        let connectivity_result = self.executor.execute("SELECT 1 AS health_check").await;
-
-        let latency_ms = start.elapsed().as_millis() as u64;
-        let healthy = connectivity_result.is_ok();
-
-        let mut response = json!({
-            "healthy": healthy,
-            "latency_ms": latency_ms,
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-        });
-
-        if !healthy {
-            if let Err(ref e) = connectivity_result {
-                response["error"] = json!(e.to_string());
+    /// A background poller compares the watermark column against the last
+    /// value seen and queues new rows for retrieval with `get_watch_changes`.
+    #[tool(
+        description = "Watch a table for new or changed rows using a watermark column (rowversion, datetime, or identity). Detected rows are queued for retrieval with get_watch_changes."
+    )]
+    pub async fn watch_table(&self, input: WatchTableInput) -> Result<ToolOutput, McpError> {
+        use crate::constants::{DEFAULT_WATCH_POLL_INTERVAL_SECS, DEFAULT_WATCH_ROWS_PER_POLL};
+        use crate::database::WatchSpec;
+        use std::time::Duration;
+
+        for (name, value) in [
+            ("schema", &input.schema),
+            ("table", &input.table),
+            ("watermark column", &input.watermark_column),
+        ] {
+            if let Err(e) = validate_identifier(value) {
+                return Ok(ToolOutput::error(format!("Invalid {} name: {}", name, e)));
             }
         }
 
-        // Add detailed diagnostics if requested
-        if input.detailed && healthy {
-            // Get server info
-            match self.metadata.get_server_info().await {
-                Ok(info) => {
-                    response["server"] = json!({
-                        "version": info.product_version,
-                        "edition": info.edition,
-                        "server_name": info.server_name,
-                        "collation": info.collation,
-                    });
-                }
-                Err(e) => {
-                    response["server_info_error"] = json!(e.to_string());
-                }
-            }
+        let database = {
+            let state = self.state.read().await;
+            state.current_database().map(|s| s.to_string())
+        };
 
-            // Get pool statistics
-            let pool_status = self.pool.status();
-            response["pool"] = json!({
-                "total_connections": pool_status.total,
-                "available_connections": pool_status.available,
-                "in_use_connections": pool_status.in_use,
-                "max_connections": pool_status.max,
-            });
+        let spec = WatchSpec {
+            schema: input.schema,
+            table: input.table,
+            watermark_column: input.watermark_column,
+            database,
+            poll_interval: Duration::from_secs(
+                input
+                    .poll_interval_seconds
+                    .unwrap_or(DEFAULT_WATCH_POLL_INTERVAL_SECS),
+            ),
+            max_rows_per_poll: input
+                .max_rows_per_poll
+                .unwrap_or(DEFAULT_WATCH_ROWS_PER_POLL)
+                .min(self.config().security.max_result_rows),
+            from_beginning: input.from_beginning,
+        };
 
-            // Get session statistics
-            let state = self.state.read().await;
-            response["sessions"] = json!({
-                "total": state.total_session_count(),
-                "running": state.running_session_count(),
-            });
+        let info = match self.watch_manager.start(spec).await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("Failed to start table watch: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Failed to start watch: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
 
-            // Configuration summary (includes runtime-modifiable settings)
-            response["config"] = json!({
-                "validation_mode": format!("{:?}", self.config.security.validation_mode),
-                "max_result_rows": self.config.security.max_result_rows,
-                "query_timeout_seconds": state.default_timeout(),
-                "initial_timeout_seconds": self.config.query.default_timeout.as_secs(),
-            });
-        }
+        info!(
+            "Started watch {} on [{}].[{}] ({}s interval)",
+            info.id, info.schema, info.table, info.poll_interval_secs
+        );
 
-        let status_text = if healthy { "healthy" } else { "unhealthy" };
-        info!("Health check completed: {} ({}ms)", status_text, latency_ms);
+        let response = json!({
+            "watch_id": info.id,
+            "status": "watching",
+            "watch": info,
+            "message": "Use get_watch_changes with this watch_id to retrieve new or changed rows.",
+        });
 
         Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&response)
-                .unwrap_or_else(|_| format!("Health: {}", status_text)),
+            serde_json::to_string_pretty(&response).unwrap_or_else(|_| "Watch started".to_string()),
         ))
     }
 
-    /// Set the default query timeout.
+    /// Retrieve rows detected by a table watch.
     ///
-    /// Adjusts the default timeout for query execution at runtime.
-    /// This affects all subsequent queries that don't specify their own timeout.
-    #[tool(description = "Set the default query timeout in seconds. Affects subsequent query executions.", idempotent = true)]
-    pub async fn set_timeout(
+    /// Returned rows are removed from the watch queue.
+    #[tool(
+        description = "Retrieve new or changed rows detected by a table watch. Returned rows are removed from the queue."
+    )]
+    pub async fn get_watch_changes(
         &self,
-        input: SetTimeoutInput,
+        input: GetWatchChangesInput,
     ) -> Result<ToolOutput, McpError> {
-        // Validate timeout range (1 second to 1 hour)
-        if input.timeout_seconds < 1 || input.timeout_seconds > 3600 {
-            return Ok(ToolOutput::error(
-                "Timeout must be between 1 and 3600 seconds (1 hour)",
-            ));
+        let mut changes = match self
+            .watch_manager
+            .take_changes(&input.watch_id, input.max_rows.unwrap_or(usize::MAX))
+            .await
+        {
+            Ok(c) => c,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let masker = self.masker();
+        if masker.is_enabled() {
+            let watches = self.watch_manager.list().await;
+            let source = watches.iter().find(|w| w.id == changes.watch_id);
+            masker.mask_rows(
+                &mut changes.rows,
+                source.map(|w| (w.schema.as_str(), w.table.as_str())),
+            );
         }
 
-        // Get current timeout and update to new value
-        let old_timeout_secs = {
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&changes)
+                .unwrap_or_else(|e| format!("Failed to serialize changes: {}", e)),
+        ))
+    }
+
+    /// List active table watches.
+    #[tool(
+        description = "List active table watches with their watermark, poll statistics, and queued row counts.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn list_watches(&self, input: ListWatchesInput) -> Result<ToolOutput, McpError> {
+        let watches: Vec<_> = self
+            .watch_manager
+            .list()
+            .await
+            .into_iter()
+            .filter(|w| {
+                input
+                    .table
+                    .as_deref()
+                    .is_none_or(|t| w.table.eq_ignore_ascii_case(t))
+            })
+            .collect();
+
+        let response = json!({
+            "total_count": watches.len(),
+            "watches": watches,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Error listing watches".to_string()),
+        ))
+    }
+
+    /// Stop a table watch.
+    #[tool(
+        description = "Stop a table watch and discard any queued rows.",
+        destructive = true
+    )]
+    pub async fn unwatch_table(&self, input: UnwatchTableInput) -> Result<ToolOutput, McpError> {
+        let info = match self.watch_manager.stop(&input.watch_id).await {
+            Ok(info) => info,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+
+        info!("Stopped watch {}", info.id);
+
+        let response = json!({
+            "watch_id": info.id,
+            "status": "stopped",
+            "rows_detected": info.rows_detected,
+            "rows_discarded": info.pending_rows,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|_| "Watch stopped".to_string()),
+        ))
+    }
+
+    // =========================================================================
+    // Query Watch Tools
+    // =========================================================================
+
+    /// Watch the result of a query.
+    ///
+    /// A background poller re-executes the query and records the rows that
+    /// were added, changed or removed whenever the result hash changes.
+    #[tool(
+        description = "Re-execute a read-only query periodically and record changes to its result (added, changed, and removed rows plus a result hash). Retrieve changes with get_watch_status; when notify is true, a resources/updated notification is sent for the watch URI on each change."
+    )]
+    pub async fn watch_query(&self, input: WatchQueryInput) -> Result<ToolOutput, McpError> {
+        use crate::constants::DEFAULT_WATCH_POLL_INTERVAL_SECS;
+        use crate::database::QueryWatchSpec;
+        use crate::security::{QueryValidator, ValidationMode};
+        use std::time::Duration;
+
+        // Watched queries run unattended, so only SELECT queries are allowed
+        if let Err(e) = self.validate_query(&input.query).and_then(|_| {
+            QueryValidator::new(
+                ValidationMode::ReadOnly,
+                self.config().security.max_query_length,
+            )
+            .validate(&input.query)
+            .map(|_| ())
+        }) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+
+        for column in &input.key_columns {
+            if let Err(e) = validate_identifier(column) {
+                return Ok(ToolOutput::error(format!("Invalid key column name: {}", e)));
+            }
+        }
+
+        let database = {
+            let state = self.state.read().await;
+            state.current_database().map(|s| s.to_string())
+        };
+
+        let spec = QueryWatchSpec {
+            query: input.query,
+            key_columns: input.key_columns,
+            database,
+            poll_interval: Duration::from_secs(
+                input
+                    .poll_interval_seconds
+                    .unwrap_or(DEFAULT_WATCH_POLL_INTERVAL_SECS),
+            ),
+            notify: input.notify,
+        };
+
+        let info = match self.query_watch_manager.start(spec).await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("Failed to start query watch: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Failed to start query watch: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        info!(
+            "Started query watch {} ({}s interval, {} rows)",
+            info.id, info.poll_interval_secs, info.row_count
+        );
+
+        let response = json!({
+            "watch_id": info.id,
+            "status": "watching",
+            "watch": info,
+            "message": "Use get_watch_status with this watch_id to retrieve result changes.",
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Query watch started".to_string()),
+        ))
+    }
+
+    /// Get the status and detected changes of query watches.
+    ///
+    /// Returned changes are removed from the queue unless `peek` is set.
+    #[tool(
+        description = "Get the status of a query watch and the result changes detected since the last call (removed from the queue unless peek is true). Without watch_id, lists all query watches."
+    )]
+    pub async fn get_watch_status(
+        &self,
+        input: GetWatchStatusInput,
+    ) -> Result<ToolOutput, McpError> {
+        let Some(watch_id) = input.watch_id else {
+            let watches = self.query_watch_manager.list().await;
+            let response = json!({
+                "total_count": watches.len(),
+                "watches": watches,
+            });
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Error listing query watches".to_string()),
+            ));
+        };
+
+        let mut status = match self
+            .query_watch_manager
+            .status(&watch_id, !input.peek)
+            .await
+        {
+            Ok(status) => status,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        self.mask_query_changes(&mut status.changes);
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&status)
+                .unwrap_or_else(|e| format!("Failed to serialize watch status: {}", e)),
+        ))
+    }
+
+    /// Stop a query watch.
+    #[tool(
+        description = "Stop a query watch and discard any queued changes.",
+        destructive = true
+    )]
+    pub async fn unwatch_query(&self, input: UnwatchQueryInput) -> Result<ToolOutput, McpError> {
+        let info = match self.query_watch_manager.stop(&input.watch_id).await {
+            Ok(info) => info,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+
+        info!("Stopped query watch {}", info.id);
+
+        let response = json!({
+            "watch_id": info.id,
+            "status": "stopped",
+            "changes_detected": info.change_count,
+            "changes_discarded": info.pending_changes,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Query watch stopped".to_string()),
+        ))
+    }
+
+    // =========================================================================
+    // Diagnostics Tools
+    // =========================================================================
+
+    /// Check database connectivity and health.
+    ///
+    /// Returns connection status and optionally detailed diagnostics.
+    #[tool(description = "Test database connectivity and return health status with optional diagnostics.", read_only = true, idempotent = true)]
+    pub async fn health_check(
+        &self,
+        input: HealthCheckInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!("Performing health check (detailed: {})", input.detailed);
+
+        let start = std::time::Instant::now();
+
+        // Try to execute a simple query to test connectivity
+        let connectivity_result = self.executor.execute("SELECT 1 AS health_check").await;
+
+        let latency_ms = start.elapsed().as_millis() as u64;
+        let healthy = connectivity_result.is_ok();
+
+        let mut response = json!({
+            "healthy": healthy,
+            "latency_ms": latency_ms,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+
+        if !healthy {
+            if let Err(ref e) = connectivity_result {
+                response["error"] = json!(e.to_string());
+            }
+        }
+        response["circuit_breaker"] =
+            circuit_breaker_status(&self.executor.circuit_breaker().stats());
+
+        // Add detailed diagnostics if requested
+        if input.detailed && healthy {
+            // Get server info
+            match self.metadata.get_server_info().await {
+                Ok(info) => {
+                    response["server"] = json!({
+                        "version": info.product_version,
+                        "edition": info.edition,
+                        "server_name": info.server_name,
+                        "collation": info.collation,
+                    });
+                }
+                Err(e) => {
+                    response["server_info_error"] = json!(e.to_string());
+                }
+            }
+
+            // Negotiated encryption next to the requested settings
+            let config = self.config();
+            let db = &config.database;
+            let mut encryption = json!({
+                "requested": db.encrypt,
+                "trust_server_certificate": db.trust_server_certificate,
+            });
+            match self.metadata.get_connection_security().await {
+                Ok(security) => {
+                    encryption["encrypted"] = json!(security.encrypted);
+                    encryption["auth_scheme"] = json!(security.auth_scheme);
+                    encryption["net_transport"] = json!(security.net_transport);
+                }
+                Err(e) => {
+                    encryption["error"] = json!(e.to_string());
+                }
+            }
+            response["encryption"] = encryption;
+
+            // Get pool statistics
+            let pool_status = self.pool.status();
+            response["pool"] = json!({
+                "total_connections": pool_status.total,
+                "available_connections": pool_status.available,
+                "in_use_connections": pool_status.in_use,
+                "max_connections": pool_status.max,
+            });
+
+            // Get session statistics
+            let state = self.state.read().await;
+            response["sessions"] = json!({
+                "total": state.total_session_count(),
+                "running": state.running_session_count(),
+            });
+
+            // Configuration summary (includes runtime-modifiable settings)
+            response["config"] = json!({
+                "validation_mode": format!("{:?}", config.security.validation_mode),
+                "max_result_rows": config.security.max_result_rows,
+                "query_timeout_seconds": state.default_timeout(),
+                "initial_timeout_seconds": config.query.default_timeout.as_secs(),
+            });
+        }
+
+        let status_text = if healthy { "healthy" } else { "unhealthy" };
+        info!("Health check completed: {} ({}ms)", status_text, latency_ms);
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Health: {}", status_text)),
+        ))
+    }
+
+    /// Close the circuit breaker guarding connection acquisition.
+    ///
+    /// Use after the server is known to be reachable again, instead of waiting
+    /// for the breaker's reset timeout.
+    #[tool(
+        description = "Reset the connection circuit breaker to closed so queries are attempted again immediately after the database recovers, instead of failing fast until the reset timeout passes. Optionally verifies connectivity afterwards."
+    )]
+    pub async fn reset_circuit_breaker(
+        &self,
+        input: ResetCircuitBreakerInput,
+    ) -> Result<ToolOutput, McpError> {
+        let breaker = self.executor.circuit_breaker();
+        let previous = breaker.state();
+        breaker.reset();
+        info!("Circuit breaker reset (was {})", previous);
+
+        let mut response = json!({
+            "previous_state": previous.to_string(),
+            "circuit_breaker": circuit_breaker_status(&breaker.stats()),
+        });
+        if input.verify {
+            let start = std::time::Instant::now();
+            let result = self.executor.execute("SELECT 1 AS health_check").await;
+            response["verified"] = json!(result.is_ok());
+            response["latency_ms"] = json!(start.elapsed().as_millis() as u64);
+            if let Err(e) = result {
+                response["error"] = json!(e.to_string());
+            }
+            response["circuit_breaker"] = circuit_breaker_status(&breaker.stats());
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Circuit breaker reset".to_string()),
+        ))
+    }
+
+    /// Set the default query timeout.
+    ///
+    /// Adjusts the default timeout for query execution at runtime.
+    /// This affects all subsequent queries that don't specify their own timeout.
+    #[tool(
+        description = "Set the default query timeout in seconds. Affects subsequent query executions by this client.",
+        idempotent = true
+    )]
+    pub async fn set_timeout(&self, input: SetTimeoutInput) -> Result<ToolOutput, McpError> {
+        // Validate timeout range (1 second to 1 hour)
+        if input.timeout_seconds < 1 || input.timeout_seconds > 3600 {
+            return Ok(ToolOutput::error(
+                "Timeout must be between 1 and 3600 seconds (1 hour)",
+            ));
+        }
+
+        // Get current timeout and update to new value
+        let old_timeout_secs = {
             let state = self.state.read().await;
             state.default_timeout()
         };
@@ -918,7 +2214,7 @@ impl MssqlMcpServer {
             "previous_timeout_seconds": old_timeout_secs,
             "new_timeout_seconds": input.timeout_seconds,
             "status": "applied",
-            "note": "Default timeout updated. All subsequent queries will use this timeout unless overridden."
+            "note": "Default timeout updated. Subsequent queries from this client will use this timeout unless overridden."
         });
 
         Ok(ToolOutput::text(
@@ -937,8 +2233,9 @@ impl MssqlMcpServer {
     ) -> Result<ToolOutput, McpError> {
         let state = self.state.read().await;
         let current_timeout = state.default_timeout();
-        let initial_timeout = self.config.query.default_timeout.as_secs();
-        let max_timeout = self.config.query.max_timeout.as_secs();
+        let config = self.config();
+        let initial_timeout = config.query.default_timeout.as_secs();
+        let max_timeout = config.query.max_timeout.as_secs();
 
         let mut response = json!({
             "current_timeout_seconds": current_timeout,
@@ -948,9 +2245,9 @@ impl MssqlMcpServer {
 
         if input.detailed {
             response["is_modified"] = json!(current_timeout != initial_timeout);
-            response["caching_enabled"] = json!(self.config.query.enable_caching);
-            if self.config.query.enable_caching {
-                response["cache_ttl_seconds"] = json!(self.config.query.cache_ttl.as_secs());
+            response["caching_enabled"] = json!(config.query.enable_caching);
+            if config.query.enable_caching {
+                response["cache_ttl_seconds"] = json!(config.query.cache_ttl.as_secs());
             }
         }
 
@@ -960,6 +2257,89 @@ impl MssqlMcpServer {
         ))
     }
 
+    /// Reload runtime settings from the configuration file.
+    ///
+    /// Re-reads `MSSQL_MCP_CONFIG` and the environment and applies security,
+    /// row limit, timeout, caching and session limit changes without a restart.
+    #[tool(
+        description = "Reload the configuration file and apply runtime settings (security mode, row limits, timeouts, cache TTL, masking) without restarting. Connection settings and rate limits require a restart.",
+        idempotent = true
+    )]
+    pub async fn reload_config(&self, input: ReloadConfigInput) -> Result<ToolOutput, McpError> {
+        let changed = match self.apply_config_reload(!input.dry_run).await {
+            Ok(c) => c,
+            Err(e) => return Ok(ToolOutput::error(format!("Reload failed: {}", e))),
+        };
+
+        let status = if changed.is_empty() {
+            "unchanged"
+        } else if input.dry_run {
+            "dry_run"
+        } else {
+            "applied"
+        };
+        let response = json!({
+            "status": status,
+            "changed_settings": changed,
+            "config_file": std::env::var(crate::config::CONFIG_FILE_ENV).ok(),
+            "note": "Connection, rate limit, audit and retention settings require a restart.",
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Configuration {}", status)),
+        ))
+    }
+
+    /// Reconnect the pools with rotated database credentials.
+    ///
+    /// Re-reads the credentials and, when they changed, replaces every pool
+    /// with one logged in with them. Requests in flight finish on the old
+    /// connections, which are closed as they are returned.
+    #[tool(
+        description = "Re-read the database password or token (MSSQL_PASSWORD, MSSQL_PASSWORD_FILE, config file) and reconnect the connection pools with it without restarting. Running queries finish on their current connections; the old connections are then closed.",
+        idempotent = true
+    )]
+    pub async fn rotate_credentials(
+        &self,
+        input: RotateCredentialsInput,
+    ) -> Result<ToolOutput, McpError> {
+        let outcome = match self.apply_credential_rotation(input.force).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Credential rotation failed, the previous credentials stay in use: {}",
+                    e
+                )))
+            }
+        };
+
+        let auth_type = match &self.config().database.auth {
+            AuthConfig::SqlServer { .. } => "sql",
+            #[cfg(windows)]
+            AuthConfig::Windows => "windows",
+            AuthConfig::AzureAd { .. } => "azuread",
+        };
+        let response = match outcome {
+            Some(outcome) => json!({
+                "status": "rotated",
+                "auth_type": auth_type,
+                "pools_replaced": outcome.pools_replaced,
+                "connections_draining": outcome.connections_draining,
+            }),
+            None => json!({
+                "status": "unchanged",
+                "auth_type": auth_type,
+                "note": "Credentials are unchanged; use force to reconnect anyway.",
+            }),
+        };
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Credentials rotated".to_string()),
+        ))
+    }
+
     // =========================================================================
     // Parameterized Query Tools
     // =========================================================================
@@ -982,6 +2362,15 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Some(preview) = self
+            .confirmation_preview("execute_parameterized", &input.query, &input)
+            .await
+        {
+            return Ok(preview);
+        }
 
         // Build the query with parameterized values
         // For SQL Server, we use sp_executesql for true parameterization
@@ -1001,35 +2390,188 @@ impl MssqlMcpServer {
 
         let max_rows = input
             .max_rows
-            .unwrap_or(self.config.security.max_result_rows);
+            .unwrap_or(self.config().security.max_result_rows);
 
-        let result = match self
+        let outcome = self
             .executor
             .execute_with_limit(&full_query, max_rows)
-            .await
-        {
+            .await;
+        self.invalidate_cached(&input.query).await;
+        let mut result = match outcome {
             Ok(r) => r,
             Err(e) => {
                 warn!("Parameterized query execution failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        self.masker().mask_result(&mut result, None);
 
-        let output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!(
-                    "Failed to serialize parameterized query result to JSON: {}",
-                    e
-                );
-                format!("Failed to serialize result: {}", e)
-            }),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
+        let output = formatting::render(&result, input.format);
+
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Register a named, parameterized query.
+    ///
+    /// Runtime registrations last until the server restarts and cannot replace
+    /// queries of the registry file. Disabled with `MSSQL_REGISTERED_ONLY`.
+    #[tool(
+        description = "Register a named, parameterized query that can then be run with execute_registered. Parameters are declared with their SQL types. Not available when the server only allows operator-defined queries."
+    )]
+    pub async fn register_query(&self, input: RegisterQueryInput) -> Result<ToolOutput, McpError> {
+        use crate::security::registry::{QueryParameter, QuerySource, RegisteredQuery};
+
+        if self.config().security.registered_queries_only {
+            return Ok(ToolOutput::error(
+                "Runtime registration is disabled (MSSQL_REGISTERED_ONLY); queries are defined in MSSQL_QUERY_REGISTRY",
+            ));
+        }
+        if let Err(e) = self.validate_query(&input.sql) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Err(e) = self.check_compatibility(&input.sql).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+
+        let mut parameters: Vec<QueryParameter> = input
+            .parameters
+            .iter()
+            .map(|(name, sql_type)| QueryParameter {
+                name: name.clone(),
+                sql_type: sql_type.clone(),
+                default: None,
+                description: None,
+            })
+            .collect();
+        parameters.sort_by(|a, b| a.name.cmp(&b.name));
+        let query = match RegisteredQuery::new(
+            &input.name,
+            &input.sql,
+            input.description,
+            parameters,
+            QuerySource::Runtime,
+        ) {
+            Ok(q) => q,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        if let Err(e) = self.query_registry.register(query.clone(), input.replace) {
+            return Ok(ToolOutput::error(e));
+        }
+        info!("Registered query '{}'", query.name);
+
+        let response = json!({
+            "name": query.name,
+            "status": "registered",
+            "parameters": query.parameters,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Registered {}", query.name)),
+        ))
+    }
+
+    /// Execute a registered query by name.
+    ///
+    /// Parameter values are bound through `sp_executesql` with the declared
+    /// types. The validation mode still applies to the query.
+    #[tool(
+        description = "Execute a registered (operator-defined) query by name with parameter values. Use list_registered_queries to see the available queries and their parameters.",
+        destructive = true
+    )]
+    pub async fn execute_registered(
+        &self,
+        input: ExecuteRegisteredInput,
+    ) -> Result<ToolOutput, McpError> {
+        let Some(query) = self.query_registry.get(&input.name) else {
+            return Ok(ToolOutput::error(format!(
+                "No registered query named '{}' (see list_registered_queries)",
+                input.name
+            )));
+        };
+        debug!("Executing registered query '{}'", query.name);
+
+        // Registered SQL bypasses the MSSQL_REGISTERED_ONLY check but not the validation mode
+        if let Err(e) = self.validator().validate(&query.sql) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Some(preview) = self
+            .confirmation_preview("execute_registered", &query.sql, &input)
+            .await
+        {
+            return Ok(preview);
+        }
+        let full_query = match query.bind(&input.parameters) {
+            Ok(q) => q,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let max_rows = input
+            .max_rows
+            .unwrap_or(self.config().security.max_result_rows);
+        let outcome = self
+            .executor
+            .execute_with_limit(&full_query, max_rows)
+            .await;
+        self.invalidate_cached(&query.sql).await;
+        let mut result = match outcome {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Registered query '{}' failed: {}", query.name, e);
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
+            }
         };
+        self.masker().mask_result(&mut result, None);
+
+        let output = formatting::render(&result, input.format);
 
         Ok(ToolOutput::text(output))
     }
 
+    /// List the registered queries and their parameters.
+    #[tool(
+        description = "List the registered queries that can be run with execute_registered, with their descriptions and parameters (name, SQL type, default).",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn list_registered_queries(
+        &self,
+        input: ListRegisteredQueriesInput,
+    ) -> Result<ToolOutput, McpError> {
+        let queries: Vec<serde_json::Value> = self
+            .query_registry
+            .list()
+            .into_iter()
+            .map(|query| {
+                let mut entry = json!({
+                    "name": query.name,
+                    "description": query.description,
+                    "parameters": query.parameters,
+                    "source": query.source,
+                });
+                if input.include_sql {
+                    entry["sql"] = json!(query.sql);
+                }
+                entry
+            })
+            .collect();
+
+        let response = json!({
+            "count": queries.len(),
+            "registered_only": self.config().security.registered_queries_only,
+            "queries": queries,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("{} registered queries", queries.len())),
+        ))
+    }
+
     // =========================================================================
     // Transaction Control Tools
     // =========================================================================
@@ -1051,19 +2593,32 @@ impl MssqlMcpServer {
             .parse::<IsolationLevel>()
             .unwrap_or_default();
 
+        let database = match self.connection_database(input.database).await {
+            Ok(db) => db,
+            Err(output) => return Ok(output),
+        };
+
         // Create transaction state (this generates the transaction ID)
         let transaction_id = {
             let mut state = self.state.write().await;
-            match state.create_transaction(
+            let id = match state.create_transaction(
                 input.name.clone(),
                 isolation_level,
-                self.config.session.max_sessions, // Use same limit for transactions
+                self.config().session.max_sessions, // Use same limit for transactions
             ) {
                 Ok(id) => id,
                 Err(e) => {
                     return Ok(ToolOutput::error(format!("Failed to create transaction: {}", e)));
                 }
+            };
+            if let Err(e) = self.rate_limiter.begin_session(&id) {
+                state.remove_transaction(&id);
+                return Ok(throttle_output(&e));
             }
+            if let Some(tx) = state.get_transaction_mut(&id) {
+                tx.database = database.clone();
+            }
+            id
         };
 
         // Use TransactionManager to create dedicated connection and begin transaction
@@ -1073,10 +2628,12 @@ impl MssqlMcpServer {
                 &transaction_id,
                 isolation_level,
                 input.name.as_deref(),
+                database.as_deref(),
             )
             .await
         {
             // Clean up state on failure
+            self.rate_limiter.end_session(&transaction_id);
             let mut state = self.state.write().await;
             state.remove_transaction(&transaction_id);
             return Ok(ToolOutput::error(format!("Failed to begin transaction: {}", e)));
@@ -1091,6 +2648,7 @@ impl MssqlMcpServer {
             "transaction_id": transaction_id,
             "name": input.name,
             "isolation_level": isolation_level.to_string(),
+            "database": database,
             "status": "active",
             "message": "Transaction started. Use execute_in_transaction to run queries, then commit_transaction or rollback_transaction."
         });
@@ -1137,17 +2695,20 @@ impl MssqlMcpServer {
         {
             return Ok(ToolOutput::error(format!("Failed to commit transaction: {}", e)));
         }
+        self.rate_limiter.end_session(&input.transaction_id);
 
         // Update state
-        let statement_count = {
+        let (statement_count, modified_tables) = {
             let mut state = self.state.write().await;
             if let Some(tx) = state.get_transaction_mut(&input.transaction_id) {
                 tx.commit();
-                tx.statement_count
+                (tx.statement_count, std::mem::take(&mut tx.modified_tables))
             } else {
-                0
+                (0, Vec::new())
             }
         };
+        // Results cached while the transaction was open may predate the commit
+        self.query_cache.invalidate_tables(&modified_tables).await;
 
         info!("Transaction {} committed", input.transaction_id);
 
@@ -1208,13 +2769,20 @@ impl MssqlMcpServer {
             }
         };
 
-        // Update state (only if full rollback, not savepoint)
-        if transaction_ended {
+        // Update state: a savepoint rollback keeps the transaction active
+        {
             let mut state = self.state.write().await;
             if let Some(tx) = state.get_transaction_mut(&input.transaction_id) {
-                tx.rollback();
+                if transaction_ended {
+                    tx.rollback();
+                } else if let Some(savepoint) = &input.savepoint {
+                    tx.rollback_to_savepoint(savepoint);
+                }
             }
         }
+        if transaction_ended {
+            self.rate_limiter.end_session(&input.transaction_id);
+        }
 
         info!(
             "Transaction {} rolled back (savepoint: {:?})",
@@ -1238,8 +2806,128 @@ impl MssqlMcpServer {
         ))
     }
 
+    /// Create a savepoint within a transaction.
+    #[tool(
+        description = "Create a savepoint (SAVE TRANSACTION) in an active transaction. Use it to checkpoint multi-step changes; rollback_transaction with the savepoint name undoes only the work done after it."
+    )]
+    pub async fn create_savepoint(
+        &self,
+        input: CreateSavepointInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_SAVEPOINT_NAME_LENGTH;
+
+        debug!(
+            "Creating savepoint {} in transaction {}",
+            input.name, input.transaction_id
+        );
+
+        let name = input.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_SAVEPOINT_NAME_LENGTH {
+            return Ok(ToolOutput::error(format!(
+                "Savepoint name must be 1 to {} characters",
+                MAX_SAVEPOINT_NAME_LENGTH
+            )));
+        }
+
+        // Validate transaction is active
+        {
+            let state = self.state.read().await;
+            match state.get_transaction(&input.transaction_id) {
+                Some(tx) if tx.status != TransactionStatus::Active => {
+                    return Ok(ToolOutput::error(format!(
+                        "Transaction {} is not active (status: {})",
+                        input.transaction_id, tx.status
+                    )));
+                }
+                None => {
+                    return Ok(ToolOutput::error(format!(
+                        "Transaction not found: {}",
+                        input.transaction_id
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        if let Err(e) = self
+            .transaction_manager
+            .create_savepoint(&input.transaction_id, name)
+            .await
+        {
+            return Ok(ToolOutput::error(format!(
+                "Failed to create savepoint: {}",
+                e
+            )));
+        }
+
+        let savepoints = {
+            let mut state = self.state.write().await;
+            match state.get_transaction_mut(&input.transaction_id) {
+                Some(tx) => {
+                    tx.add_savepoint(name.to_string());
+                    tx.savepoints.clone()
+                }
+                None => Vec::new(),
+            }
+        };
+
+        info!(
+            "Savepoint {} created in transaction {}",
+            name, input.transaction_id
+        );
+
+        let response = json!({
+            "transaction_id": input.transaction_id,
+            "savepoint": name,
+            "savepoints": savepoints,
+            "message": format!(
+                "Savepoint created. Roll back to it with rollback_transaction and savepoint '{}'.",
+                name
+            )
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Savepoint {} created", name)),
+        ))
+    }
+
+    /// List the savepoints of a transaction.
+    #[tool(
+        description = "List the savepoints of a transaction in creation order, with the number of statements executed before each.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn list_savepoints(
+        &self,
+        input: ListSavepointsInput,
+    ) -> Result<ToolOutput, McpError> {
+        let state = self.state.read().await;
+        let Some(tx) = state.get_transaction(&input.transaction_id) else {
+            return Ok(ToolOutput::error(format!(
+                "Transaction not found: {}",
+                input.transaction_id
+            )));
+        };
+
+        let response = json!({
+            "transaction_id": tx.id,
+            "status": tx.status.to_string(),
+            "statement_count": tx.statement_count,
+            "savepoints": tx.savepoints,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("{} savepoint(s)", tx.savepoints.len())),
+        ))
+    }
+
     /// Execute SQL within a transaction.
-    #[tool(description = "Execute a SQL statement within an active transaction.", destructive = true)]
+    #[tool(
+        description = "Execute SQL within an active transaction, reporting the rows affected and result sets of each statement and any PRINT messages (format: table, json or csv). If the server rolls the transaction back as a deadlock victim, its statements can be replayed automatically (max_deadlock_replays).",
+        destructive = true
+    )]
     pub async fn execute_in_transaction(
         &self,
         input: ExecuteInTransactionInput,
@@ -1274,6 +2962,9 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
 
         // Build query with parameters if provided
         let query = if input.parameters.is_empty() {
@@ -1294,27 +2985,70 @@ impl MssqlMcpServer {
         };
 
         // Execute the query using TransactionManager on the dedicated connection
-        let result = match self
+        let outcome = self
             .transaction_manager
-            .execute_in_transaction(&input.transaction_id, &query)
-            .await
-        {
+            .execute_with_replay(&input.transaction_id, &query, input.max_deadlock_replays)
+            .await;
+        self.invalidate_cached(&input.query).await;
+        let execution = match outcome {
             Ok(r) => r,
             Err(e) => {
                 warn!("Transaction query failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        let mut result = execution.result;
+        let masker = self.masker();
+        for result_set in result.result_sets_mut() {
+            masker.mask_result(result_set, None);
+        }
 
         // Update transaction state
         {
             let mut state = self.state.write().await;
             if let Some(tx) = state.get_transaction_mut(&input.transaction_id) {
                 tx.record_statement();
+                for table in modified_tables(&input.query) {
+                    if !tx.modified_tables.contains(&table) {
+                        tx.modified_tables.push(table);
+                    }
+                }
             }
         }
 
-        let output = result.to_markdown_table();
+        if input.format == OutputFormat::Json {
+            let mut response = json!(result);
+            if !execution.replays.is_empty() {
+                response["deadlock_replays"] = json!(execution.replays);
+                response["replayed_statements"] = json!(execution.replayed_statements);
+            }
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|e| format!("Failed to serialize result: {}", e)),
+            ));
+        }
+        let mut output = formatting::render(&result, input.format);
+        if input.format == OutputFormat::Table && !execution.replays.is_empty() {
+            let waited_ms: u64 = execution.replays.iter().map(|r| r.waited_ms).sum();
+            output.push_str(&format!(
+                "\n\n**Deadlock replay:** the server rolled the transaction back as deadlock \
+                 victim; it was restarted and this statement succeeded after {} replay(s) \
+                 ({} ms waiting). Re-executed {} earlier statement(s):",
+                execution.replays.len(),
+                waited_ms,
+                execution.replayed_statements.len()
+            ));
+            for (i, statement) in execution.replayed_statements.iter().enumerate() {
+                output.push_str(&format!(
+                    "\n{}. `{}`",
+                    i + 1,
+                    truncate_for_log(statement, 100).replace('`', "'")
+                ));
+            }
+        }
         Ok(ToolOutput::text(output))
     }
 
@@ -1333,6 +3067,11 @@ impl MssqlMcpServer {
     ) -> Result<ToolOutput, McpError> {
         debug!("Beginning pinned session");
 
+        let database = match self.connection_database(input.database).await {
+            Ok(db) => db,
+            Err(output) => return Ok(output),
+        };
+
         // Generate session ID
         let session_id = format!(
             "session_{}",
@@ -1344,9 +3083,17 @@ impl MssqlMcpServer {
         );
 
         // Create the session
-        let session_info = match self.session_manager.begin_session(&session_id).await {
+        if let Err(e) = self.rate_limiter.begin_session(&session_id) {
+            return Ok(throttle_output(&e));
+        }
+        let session_info = match self
+            .session_manager
+            .begin_session(&session_id, database.as_deref())
+            .await
+        {
             Ok(info) => info,
             Err(e) => {
+                self.rate_limiter.end_session(&session_id);
                 return Ok(ToolOutput::error(format!("Failed to begin session: {}", e)));
             }
         };
@@ -1359,6 +3106,7 @@ impl MssqlMcpServer {
         let response = json!({
             "session_id": session_id,
             "name": input.name,
+            "database": session_info.database,
             "status": "active",
             "created_at": format!("{:?}", session_info.created_at.elapsed()),
             "message": "Pinned session started. Use execute_in_pinned_session for temp tables and session state. Remember to call end_pinned_session when done."
@@ -1386,31 +3134,113 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Some(preview) = self
+            .confirmation_preview("execute_in_pinned_session", &input.query, &input)
+            .await
+        {
+            return Ok(preview);
+        }
 
         // Execute using SessionManager
-        let result = match self
+        let outcome = self
             .session_manager
             .execute_in_session(&input.session_id, &input.query)
-            .await
-        {
+            .await;
+        self.invalidate_cached(&input.query).await;
+        let mut result = match outcome {
             Ok(r) => r,
             Err(e) => {
                 warn!("Session query failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        self.masker().mask_result(&mut result, None);
 
         // Format output based on requested format
-        let output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result)
-                .unwrap_or_else(|e| format!("Failed to serialize result: {}", e)),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
-        };
+        let output = formatting::render(&result, input.format);
 
         Ok(ToolOutput::text(output))
     }
 
+    /// Switch the database of a pinned session or transaction.
+    ///
+    /// Unlike `switch_database`, only the dedicated connection is affected.
+    #[tool(
+        description = "Switch the database used by one pinned session or transaction without affecting the server's current database or other sessions.",
+        destructive = true,
+        idempotent = true
+    )]
+    pub async fn use_database(&self, input: UseDatabaseInput) -> Result<ToolOutput, McpError> {
+        if let Err(e) = validate_identifier(&input.database) {
+            return Ok(ToolOutput::error(format!("Invalid database name: {}", e)));
+        }
+
+        let (kind, id) = match (&input.session_id, &input.transaction_id) {
+            (Some(session_id), None) => {
+                if let Err(e) = self
+                    .session_manager
+                    .use_database(session_id, &input.database)
+                    .await
+                {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to switch database: {}",
+                        e
+                    )));
+                }
+                ("session_id", session_id)
+            }
+            (None, Some(transaction_id)) => {
+                let escaped_db = match safe_identifier(&input.database) {
+                    Ok(db) => db,
+                    Err(e) => {
+                        return Ok(ToolOutput::error(format!("Invalid database name: {}", e)))
+                    }
+                };
+                // Run through the transaction so the switch is replayed after a deadlock
+                let query = format!("USE {}", escaped_db);
+                if let Err(e) = self
+                    .transaction_manager
+                    .execute_in_transaction(transaction_id, &query)
+                    .await
+                {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to switch database: {}",
+                        e
+                    )));
+                }
+                let mut state = self.state.write().await;
+                if let Some(tx) = state.get_transaction_mut(transaction_id) {
+                    tx.database = Some(input.database.clone());
+                }
+                ("transaction_id", transaction_id)
+            }
+            _ => {
+                return Ok(ToolOutput::error(
+                    "Provide exactly one of session_id or transaction_id",
+                ))
+            }
+        };
+
+        info!("{} {} now using database {}", kind, id, input.database);
+
+        let response = json!({
+            kind: id,
+            "database": input.database,
+            "status": "switched",
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Switched to {}", input.database)),
+        ))
+    }
+
     /// End a pinned session and release its connection.
     #[tool(description = "End a pinned session and release its dedicated connection. Any temp tables will be automatically dropped.", destructive = true, idempotent = true)]
     pub async fn end_pinned_session(
@@ -1425,6 +3255,7 @@ impl MssqlMcpServer {
                 return Ok(ToolOutput::error(format!("Failed to end session: {}", e)));
             }
         };
+        self.rate_limiter.end_session(&input.session_id);
 
         info!(
             "Pinned session {} ended after {} queries",
@@ -1459,6 +3290,7 @@ impl MssqlMcpServer {
             .map(|s| {
                 let mut info = json!({
                     "session_id": s.id,
+                    "database": s.database,
                     "query_count": s.query_count,
                     "age_ms": s.created_at.elapsed().as_millis(),
                     "idle_ms": s.last_activity.elapsed().as_millis(),
@@ -1473,9 +3305,15 @@ impl MssqlMcpServer {
             })
             .collect();
 
+        let limits = self.session_manager.limits();
         let response = json!({
             "count": sessions.len(),
-            "sessions": session_list
+            "sessions": session_list,
+            "limits": {
+                "max_sessions": limits.max_sessions,
+                "idle_timeout_secs": limits.idle_timeout.as_secs(),
+                "max_lifetime_secs": limits.max_lifetime.as_secs(),
+            }
         });
 
         Ok(ToolOutput::text(
@@ -1489,11 +3327,23 @@ impl MssqlMcpServer {
     // =========================================================================
 
     /// Execute a paginated query.
-    #[tool(description = "Execute a SQL query with pagination support. Query must include ORDER BY for consistent results.", read_only = true)]
+    ///
+    /// Offset mode pages with OFFSET/FETCH and accepts page numbers; keyset
+    /// mode seeks past the ORDER BY values of the previous page. Cursors are
+    /// signed and only valid for the query they were issued for.
+    #[tool(
+        description = "Execute a SQL query with pagination support. Query must include ORDER BY for consistent results. mode='keyset' seeks past the last row's ORDER BY values for stable, fast deep paging (ORDER BY plain columns in the select list that identify a row); include_total=true also returns the total row count. Pass next_cursor as page to get the next page.",
+        read_only = true
+    )]
     pub async fn execute_paginated(
         &self,
         input: ExecutePaginatedInput,
     ) -> Result<ToolOutput, McpError> {
+        use crate::database::pagination::{
+            count_query, decode_cursor, encode_cursor, key_columns, key_literal, keyset_query,
+            offset_query, split_order_by, tie_query, PageCursor,
+        };
+
         debug!(
             "Executing paginated query: {}",
             truncate_for_log(&input.query, 100)
@@ -1503,80 +3353,168 @@ impl MssqlMcpServer {
         if let Err(e) = self.validate_query(&input.query) {
             return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
         }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
 
         // Check for ORDER BY clause (required for consistent pagination)
-        let query_upper = input.query.to_uppercase();
-        if !query_upper.contains("ORDER BY") {
+        let Some((body, order_by)) = split_order_by(&input.query) else {
             return Ok(ToolOutput::error(
                 "Paginated queries must include an ORDER BY clause for consistent results",
             ));
-        }
+        };
 
         // Validate page size
         let page_size = input.page_size.clamp(1, 10000);
 
-        // Determine offset based on page position
-        let offset = match &input.page {
+        // Determine the page position
+        let cursor = match &input.page {
             Some(PaginationPosition::PageNumber(page)) => {
-                let page_num = (*page).max(1);
-                (page_num - 1) * page_size
-            }
-            Some(PaginationPosition::Cursor(cursor)) => {
-                // Decode cursor (base64 encoded offset)
-                match decode_cursor(cursor) {
-                    Ok(off) => off,
-                    Err(e) => {
-                        return Ok(ToolOutput::error(format!("Invalid cursor: {}", e)));
-                    }
+                let page = (*page).max(1);
+                if input.mode == PaginationMode::Keyset && page > 1 {
+                    return Ok(ToolOutput::error(
+                        "Keyset pagination moves forward with cursors; use next_cursor, or mode='offset' for page numbers",
+                    ));
+                }
+                PageCursor {
+                    page,
+                    offset: (page - 1) * page_size,
+                    keys: None,
                 }
             }
-            None => 0,
+            Some(PaginationPosition::Cursor(token)) => match decode_cursor(token, &input.query) {
+                Ok(cursor) => cursor,
+                Err(e) => return Ok(ToolOutput::error(format!("Invalid cursor: {}", e))),
+            },
+            None => PageCursor {
+                page: 1,
+                offset: 0,
+                keys: None,
+            },
+        };
+        let keyset = input.mode == PaginationMode::Keyset || cursor.keys.is_some();
+        let columns = if keyset {
+            match key_columns(order_by) {
+                Ok(columns) => Some(columns),
+                Err(e) => return Ok(ToolOutput::error(e)),
+            }
+        } else {
+            None
+        };
+
+        // Fetch one extra row to tell whether there is a next page
+        let fetch = page_size + 1;
+        let paginated_query = match (&columns, &cursor.keys) {
+            (Some(columns), Some(keys)) => keyset_query(body, columns, keys, fetch),
+            _ => offset_query(body, order_by, cursor.offset, fetch),
         };
 
-        // Build paginated query using OFFSET-FETCH
-        let paginated_query = format!(
-            "{} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
-            input.query, offset, page_size
-        );
-
-        let result = match self.executor.execute(&paginated_query).await {
+        let mut result = match self.executor.execute(&paginated_query).await {
             Ok(r) => r,
             Err(e) => {
                 warn!("Paginated query failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
             }
         };
+        let has_more = result.rows.len() > page_size;
+        result.rows.truncate(page_size);
 
-        // Calculate pagination info
-        let has_more = result.rows.len() == page_size;
+        // Key values are read before masking; without usable values (NULL,
+        // UDT) the next page falls back to an offset cursor
         let next_cursor = if has_more {
-            Some(encode_cursor(offset + page_size))
+            let mut keys = None;
+            if let (Some(columns), Some(row)) = (&columns, result.rows.last()) {
+                let mut values = Vec::with_capacity(columns.len());
+                for column in columns {
+                    let Some(name) = result
+                        .columns
+                        .iter()
+                        .find(|c| c.name.eq_ignore_ascii_case(&column.name))
+                    else {
+                        return Ok(ToolOutput::error(format!(
+                            "ORDER BY column '{}' must be in the select list for keyset pagination",
+                            column.name
+                        )));
+                    };
+                    values.push(row.get(&name.name).and_then(key_literal));
+                }
+                keys = values.into_iter().collect::<Option<Vec<_>>>();
+            }
+            // Seeking past values shared by other rows would skip those rows
+            if let (Some(columns), Some(keys)) = (&columns, &keys) {
+                let tied = match self.executor.execute(&tie_query(body, columns, keys)).await {
+                    Ok(count) => count
+                        .rows
+                        .first()
+                        .and_then(|row| row.get("tied_rows"))
+                        .and_then(|v| v.to_display_string().parse::<u64>().ok())
+                        .unwrap_or_default(),
+                    Err(e) => {
+                        return Ok(ToolOutput::error(format!(
+                            "Failed to check the keyset columns: {}",
+                            self.localize_error(&e).await
+                        )));
+                    }
+                };
+                if tied > 1 {
+                    return Ok(ToolOutput::error(
+                        "The ORDER BY columns do not identify a row (several rows share the values of the last row of this page), so keyset pagination would skip rows; add a unique column such as the primary key as the last ORDER BY column, or use mode='offset'",
+                    ));
+                }
+            }
+            Some(encode_cursor(
+                &input.query,
+                &PageCursor {
+                    page: cursor.page + 1,
+                    offset: cursor.offset + page_size,
+                    keys,
+                },
+            ))
         } else {
             None
         };
+        self.masker().mask_result(&mut result, None);
 
-        let current_page = (offset / page_size) + 1;
+        let total_rows = if input.include_total {
+            match self.executor.execute(&count_query(body)).await {
+                Ok(count) => count
+                    .rows
+                    .first()
+                    .and_then(|row| row.get("total_rows"))
+                    .and_then(|v| v.to_display_string().parse::<u64>().ok()),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to count rows: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            }
+        } else {
+            None
+        };
 
         // Format output
-        let data_output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!("Failed to serialize paginated result to JSON: {}", e);
-                format!("Failed to serialize result: {}", e)
-            }),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
-        };
+        let data_output = formatting::render(&result, input.format);
 
+        let mut pagination = json!({
+            "mode": if keyset { PaginationMode::Keyset } else { PaginationMode::Offset }.as_str(),
+            "page": cursor.page,
+            "page_size": page_size,
+            "row_count": result.rows.len(),
+            "has_more": has_more,
+            "next_cursor": next_cursor,
+            "offset": cursor.offset,
+        });
+        if let Some(total) = total_rows {
+            pagination["total_rows"] = json!(total);
+            pagination["total_pages"] = json!(total.div_ceil(page_size as u64));
+        }
         let response = json!({
             "data": data_output,
-            "pagination": {
-                "page": current_page,
-                "page_size": page_size,
-                "row_count": result.rows.len(),
-                "has_more": has_more,
-                "next_cursor": next_cursor,
-                "offset": offset,
-            },
+            "pagination": pagination,
             "execution_time_ms": result.execution_time_ms,
         });
 
@@ -1593,7 +3531,10 @@ impl MssqlMcpServer {
     // =========================================================================
 
     /// Switch to a different database.
-    #[tool(description = "Switch the connection to a different database on the same server.", idempotent = true)]
+    #[tool(
+        description = "Switch this client's connection to a different database on the same server. Other clients keep their own database.",
+        idempotent = true
+    )]
     pub async fn switch_database(
         &self,
         input: SwitchDatabaseInput,
@@ -1778,158 +3719,150 @@ impl MssqlMcpServer {
         ))
     }
 
-    // =========================================================================
-    // Schema Comparison Tools
-    // =========================================================================
-
-    /// Compare two database schemas.
-    #[tool(description = "Compare objects between two schemas in the same database.", read_only = true, idempotent = true)]
-    pub async fn compare_schemas(
+    /// Report index fragmentation and page density.
+    #[tool(
+        description = "Report fragmentation and page density per index (and partition) from sys.dm_db_index_physical_stats, ordered by fragmentation.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_index_fragmentation(
         &self,
-        input: CompareSchemaInput,
+        input: GetIndexFragmentationInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!(
-            "Comparing schemas: {} vs {}",
-            input.source_schema, input.target_schema
-        );
+        use crate::constants::{
+            DEFAULT_REBUILD_THRESHOLD_PERCENT, DEFAULT_REORGANIZE_THRESHOLD_PERCENT,
+        };
 
-        // Validate schema names
-        if let Err(e) = validate_identifier(&input.source_schema) {
-            return Ok(ToolOutput::error(format!("Invalid source schema name: {}", e)));
-        }
-        if let Err(e) = validate_identifier(&input.target_schema) {
-            return Ok(ToolOutput::error(format!("Invalid target schema name: {}", e)));
-        }
+        let indexes = match self
+            .load_index_fragmentation(
+                input.schema.as_deref(),
+                input.table.as_deref(),
+                &input.mode,
+                input.min_page_count,
+            )
+            .await
+        {
+            Ok(indexes) => indexes,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read index fragmentation: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
 
-        let include_tables = input.object_types == "all" || input.object_types == "tables";
-        let include_views = input.object_types == "all" || input.object_types == "views";
-        let include_procedures = input.object_types == "all" || input.object_types == "procedures";
+        let rebuild_count = indexes
+            .iter()
+            .filter(|i| i.fragmentation_percent >= DEFAULT_REBUILD_THRESHOLD_PERCENT)
+            .count();
+        let reorganize_count = indexes
+            .iter()
+            .filter(|i| {
+                i.fragmentation_percent >= DEFAULT_REORGANIZE_THRESHOLD_PERCENT
+                    && i.fragmentation_percent < DEFAULT_REBUILD_THRESHOLD_PERCENT
+            })
+            .count();
 
-        let mut differences: Vec<serde_json::Value> = Vec::new();
+        let response = json!({
+            "mode": input.mode.to_lowercase(),
+            "min_page_count": input.min_page_count,
+            "index_count": indexes.len(),
+            "above_rebuild_threshold": rebuild_count,
+            "above_reorganize_threshold": reorganize_count,
+            "indexes": indexes,
+        });
 
-        // Compare tables
-        if include_tables {
-            let tables_query = format!(
-                r#"
-                SELECT
-                    COALESCE(s.name, t.name) AS table_name,
-                    CASE
-                        WHEN s.name IS NULL THEN 'only_in_target'
-                        WHEN t.name IS NULL THEN 'only_in_source'
-                        ELSE 'in_both'
-                    END AS status
-                FROM (
-                    SELECT TABLE_NAME AS name
-                    FROM INFORMATION_SCHEMA.TABLES
-                    WHERE TABLE_SCHEMA = '{0}' AND TABLE_TYPE = 'BASE TABLE'
-                ) s
-                FULL OUTER JOIN (
-                    SELECT TABLE_NAME AS name
-                    FROM INFORMATION_SCHEMA.TABLES
-                    WHERE TABLE_SCHEMA = '{1}' AND TABLE_TYPE = 'BASE TABLE'
-                ) t ON s.name = t.name
-                "#,
-                input.source_schema.replace('\'', "''"),
-                input.target_schema.replace('\'', "''")
-            );
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Fragmentation report failed".to_string()),
+        ))
+    }
 
-            if let Ok(result) = self.executor.execute(&tables_query).await {
-                for row in &result.rows {
-                    let status = row
-                        .get("status")
-                        .map(|v| v.to_display_string())
-                        .unwrap_or_default();
-                    if status != "in_both" {
-                        differences.push(json!({
-                            "type": "table",
-                            "name": row.get("table_name").map(|v| v.to_display_string()),
-                            "status": status,
-                        }));
-                    }
-                }
-            }
-        }
+    /// Generate and optionally execute index maintenance statements.
+    ///
+    /// Indexes at or above `rebuild_threshold` fragmentation (or below
+    /// `min_page_density`) are rebuilt, those at or above
+    /// `reorganize_threshold` are reorganized. Statements are only returned
+    /// unless `execute` is set, which requires the unrestricted validation mode.
+    #[tool(
+        description = "Generate ALTER INDEX REORGANIZE/REBUILD statements for fragmented indexes based on thresholds, and optionally execute them (execute requires unrestricted validation mode).",
+        destructive = true
+    )]
+    pub async fn maintain_indexes(
+        &self,
+        input: MaintainIndexesInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::maintenance::{plan_index_maintenance, MaintenanceThresholds};
+        use crate::security::ValidationMode;
 
-        // Compare views
-        if include_views {
-            let views_query = format!(
-                r#"
-                SELECT
-                    COALESCE(s.name, t.name) AS view_name,
-                    CASE
-                        WHEN s.name IS NULL THEN 'only_in_target'
-                        WHEN t.name IS NULL THEN 'only_in_source'
-                        ELSE 'in_both'
-                    END AS status
-                FROM (
-                    SELECT TABLE_NAME AS name
-                    FROM INFORMATION_SCHEMA.VIEWS
-                    WHERE TABLE_SCHEMA = '{0}'
-                ) s
-                FULL OUTER JOIN (
-                    SELECT TABLE_NAME AS name
-                    FROM INFORMATION_SCHEMA.VIEWS
-                    WHERE TABLE_SCHEMA = '{1}'
-                ) t ON s.name = t.name
-                "#,
-                input.source_schema.replace('\'', "''"),
-                input.target_schema.replace('\'', "''")
-            );
+        if !(0.0..=100.0).contains(&input.reorganize_threshold)
+            || !(0.0..=100.0).contains(&input.rebuild_threshold)
+            || input.reorganize_threshold > input.rebuild_threshold
+        {
+            return Ok(ToolOutput::error(
+                "Thresholds must be between 0 and 100, with reorganize_threshold <= rebuild_threshold",
+            ));
+        }
 
-            if let Ok(result) = self.executor.execute(&views_query).await {
-                for row in &result.rows {
-                    let status = row
-                        .get("status")
-                        .map(|v| v.to_display_string())
-                        .unwrap_or_default();
-                    if status != "in_both" {
-                        differences.push(json!({
-                            "type": "view",
-                            "name": row.get("view_name").map(|v| v.to_display_string()),
-                            "status": status,
-                        }));
-                    }
-                }
-            }
+        let validation_mode = self.config().security.validation_mode;
+        if input.execute && validation_mode != ValidationMode::Unrestricted {
+            return Ok(ToolOutput::error(format!(
+                "Executing index maintenance requires unrestricted validation mode (current: {:?}). \
+                 Run with execute: false to generate the statements instead.",
+                validation_mode
+            )));
         }
 
-        // Compare procedures
-        if include_procedures {
-            let procs_query = format!(
-                r#"
-                SELECT
-                    COALESCE(s.name, t.name) AS procedure_name,
-                    CASE
-                        WHEN s.name IS NULL THEN 'only_in_target'
-                        WHEN t.name IS NULL THEN 'only_in_source'
-                        ELSE 'in_both'
-                    END AS status
-                FROM (
-                    SELECT ROUTINE_NAME AS name
-                    FROM INFORMATION_SCHEMA.ROUTINES
-                    WHERE ROUTINE_SCHEMA = '{0}' AND ROUTINE_TYPE = 'PROCEDURE'
-                ) s
-                FULL OUTER JOIN (
-                    SELECT ROUTINE_NAME AS name
-                    FROM INFORMATION_SCHEMA.ROUTINES
-                    WHERE ROUTINE_SCHEMA = '{1}' AND ROUTINE_TYPE = 'PROCEDURE'
-                ) t ON s.name = t.name
-                "#,
-                input.source_schema.replace('\'', "''"),
-                input.target_schema.replace('\'', "''")
-            );
+        // Page density is only reported by the SAMPLED and DETAILED scan modes
+        let mode = if input.min_page_density.is_some() {
+            "sampled"
+        } else {
+            "limited"
+        };
+        let indexes = match self
+            .load_index_fragmentation(
+                input.schema.as_deref(),
+                input.table.as_deref(),
+                mode,
+                input.min_page_count,
+            )
+            .await
+        {
+            Ok(indexes) => indexes,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read index fragmentation: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
 
-            if let Ok(result) = self.executor.execute(&procs_query).await {
-                for row in &result.rows {
-                    let status = row
-                        .get("status")
-                        .map(|v| v.to_display_string())
-                        .unwrap_or_default();
-                    if status != "in_both" {
-                        differences.push(json!({
-                            "type": "procedure",
-                            "name": row.get("procedure_name").map(|v| v.to_display_string()),
-                            "status": status,
+        let thresholds = MaintenanceThresholds {
+            reorganize_percent: input.reorganize_threshold,
+            rebuild_percent: input.rebuild_threshold,
+            min_page_density_percent: input.min_page_density,
+            online: input.online,
+        };
+        let steps = plan_index_maintenance(&indexes, &thresholds);
+
+        let mut results = Vec::new();
+        if input.execute {
+            for step in &steps {
+                let outcome = match self.validate_query(&step.sql) {
+                    Ok(()) => self.executor.execute_non_query(&step.sql).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                match outcome {
+                    Ok(()) => {
+                        info!("Index maintenance: {}", step.sql);
+                        results.push(json!({ "sql": step.sql, "success": true }));
+                    }
+                    Err(e) => {
+                        warn!("Index maintenance failed for {}: {}", step.index, e);
+                        results.push(json!({
+                            "sql": step.sql,
+                            "success": false,
+                            "error": self.localize_error(&e).await,
                         }));
                     }
                 }
@@ -1937,1498 +3870,7642 @@ impl MssqlMcpServer {
         }
 
         let response = json!({
-            "source_schema": input.source_schema,
-            "target_schema": input.target_schema,
-            "object_types": input.object_types,
-            "difference_count": differences.len(),
-            "differences": differences,
+            "executed": input.execute,
+            "indexes_checked": indexes.len(),
+            "step_count": steps.len(),
+            "steps": steps,
+            "script": steps.iter().map(|s| format!("{};", s.sql)).collect::<Vec<_>>().join("\n"),
+            "results": if input.execute { Some(results) } else { None },
         });
 
         Ok(ToolOutput::text(
             serde_json::to_string_pretty(&response)
-                .unwrap_or_else(|_| "Schema comparison failed".to_string()),
+                .unwrap_or_else(|_| "Index maintenance failed".to_string()),
         ))
     }
 
-    /// Compare two tables.
-    #[tool(description = "Compare the structure of two tables, including columns, indexes, and constraints.", read_only = true, idempotent = true)]
-    pub async fn compare_tables(
+    /// Estimate ROW, PAGE and COLUMNSTORE compression savings for tables.
+    ///
+    /// Runs `sp_estimate_data_compression_savings` once per table and option;
+    /// an option that fails (e.g. COLUMNSTORE before SQL Server 2019) is
+    /// reported without stopping the other estimates. Nothing is changed.
+    #[tool(
+        description = "Estimate space savings of ROW, PAGE and COLUMNSTORE compression for selected tables with sp_estimate_data_compression_savings, per index, and recommend the best option above a savings threshold with the ALTER INDEX/ALTER TABLE REBUILD or CREATE CLUSTERED COLUMNSTORE INDEX statement to apply it. Estimation samples data into tempdb and can take a while on large tables.",
+        read_only = true
+    )]
+    pub async fn compression_advisor(
         &self,
-        input: CompareTablesInput,
+        input: CompressionAdvisorInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!(
-            "Comparing tables: {} vs {}",
-            input.source_table, input.target_table
-        );
+        use crate::constants::{COMPRESSION_ESTIMATE_TIMEOUT_SECS, MAX_COMPRESSION_ADVISOR_TABLES};
+        use crate::database::compression::{
+            add_estimates, estimate_query, indexes_query, CompressionOption, IndexCompression,
+        };
 
-        // Parse table names
-        let (source_schema, source_table) = parse_table_name(&input.source_table)?;
-        let (target_schema, target_table) = parse_table_name(&input.target_table)?;
+        if input.tables.is_empty() || input.tables.len() > MAX_COMPRESSION_ADVISOR_TABLES {
+            return Ok(ToolOutput::error(format!(
+                "tables must list between 1 and {} tables",
+                MAX_COMPRESSION_ADVISOR_TABLES
+            )));
+        }
+        if !(0.0..=100.0).contains(&input.min_savings_percent) {
+            return Ok(ToolOutput::error(
+                "min_savings_percent must be between 0 and 100",
+            ));
+        }
+        let mut options = Vec::new();
+        for option in &input.options {
+            match CompressionOption::parse(option) {
+                Ok(o) if !options.contains(&o) => options.push(o),
+                Ok(_) => {}
+                Err(e) => return Ok(ToolOutput::error(e.to_string())),
+            }
+        }
+        if options.is_empty() {
+            return Ok(ToolOutput::error(
+                "options must name at least one compression option",
+            ));
+        }
 
-        // Compare columns
-        let columns_query = format!(
-            r#"
-            SELECT
-                COALESCE(s.column_name, t.column_name) AS column_name,
-                s.data_type AS source_type,
-                t.data_type AS target_type,
-                s.character_maximum_length AS source_max_length,
-                t.character_maximum_length AS target_max_length,
-                s.is_nullable AS source_nullable,
-                t.is_nullable AS target_nullable,
-                CASE
-                    WHEN s.column_name IS NULL THEN 'only_in_target'
-                    WHEN t.column_name IS NULL THEN 'only_in_source'
-                    WHEN s.data_type <> t.data_type THEN 'type_mismatch'
-                    WHEN ISNULL(s.character_maximum_length, 0) <> ISNULL(t.character_maximum_length, 0) THEN 'length_mismatch'
-                    WHEN s.is_nullable <> t.is_nullable THEN 'nullable_mismatch'
-                    ELSE 'identical'
-                END AS status
-            FROM (
-                SELECT COLUMN_NAME AS column_name, DATA_TYPE AS data_type,
-                       CHARACTER_MAXIMUM_LENGTH AS character_maximum_length, IS_NULLABLE AS is_nullable
-                FROM INFORMATION_SCHEMA.COLUMNS
-                WHERE TABLE_SCHEMA = '{0}' AND TABLE_NAME = '{1}'
-            ) s
-            FULL OUTER JOIN (
-                SELECT COLUMN_NAME AS column_name, DATA_TYPE AS data_type,
-                       CHARACTER_MAXIMUM_LENGTH AS character_maximum_length, IS_NULLABLE AS is_nullable
-                FROM INFORMATION_SCHEMA.COLUMNS
-                WHERE TABLE_SCHEMA = '{2}' AND TABLE_NAME = '{3}'
-            ) t ON s.column_name = t.column_name
-            ORDER BY COALESCE(s.column_name, t.column_name)
-            "#,
-            source_schema.replace('\'', "''"),
-            source_table.replace('\'', "''"),
-            target_schema.replace('\'', "''"),
-            target_table.replace('\'', "''")
-        );
+        let mut indexes = Vec::new();
+        let mut errors = Vec::new();
+        for name in &input.tables {
+            let (schema, table) = parse_table_name(name)?;
+            if let Err(e) = validate_identifier(&schema).and_then(|_| validate_identifier(&table)) {
+                return Ok(ToolOutput::error(format!("Invalid table name: {}", e)));
+            }
+            let mut table_indexes: Vec<IndexCompression> =
+                match self.executor.execute(&indexes_query(&schema, &table)).await {
+                    Ok(result) => result
+                        .rows
+                        .iter()
+                        .filter_map(|row| IndexCompression::from_row(&schema, &table, row))
+                        .collect(),
+                    Err(e) => {
+                        errors.push(json!({
+                            "table": format!("{}.{}", schema, table),
+                            "error": self.localize_error(&e).await,
+                        }));
+                        continue;
+                    }
+                };
+            if table_indexes.is_empty() {
+                errors.push(json!({
+                    "table": format!("{}.{}", schema, table),
+                    "error": "Table not found",
+                }));
+                continue;
+            }
 
-        let columns_result = match self.executor.execute(&columns_query).await {
-            Ok(r) => r,
-            Err(e) => {
-                return Ok(ToolOutput::error(format!("Failed to compare columns: {}", e)));
+            for &option in &options {
+                if !table_indexes
+                    .iter()
+                    .any(|i| option.applies_to(&i.index_type))
+                {
+                    continue;
+                }
+                let query = estimate_query(&schema, &table, option);
+                match self
+                    .executor
+                    .execute_with_timeout(&query, COMPRESSION_ESTIMATE_TIMEOUT_SECS)
+                    .await
+                {
+                    Ok(result) => add_estimates(&mut table_indexes, option, &result.rows),
+                    Err(e) => errors.push(json!({
+                        "table": format!("{}.{}", schema, table),
+                        "option": option,
+                        "error": self.localize_error(&e).await,
+                    })),
+                }
             }
-        };
+            for index in &mut table_indexes {
+                index.recommend(input.min_savings_percent, input.online);
+            }
+            indexes.extend(table_indexes);
+        }
 
-        let column_diffs: Vec<serde_json::Value> = columns_result
-            .rows
+        let recommended: Vec<&IndexCompression> = indexes
             .iter()
-            .filter(|row| {
-                row.get("status")
-                    .map(|v| v.to_display_string())
-                    .unwrap_or_default()
-                    != "identical"
-            })
-            .map(|row| {
-                json!({
-                    "column": row.get("column_name").map(|v| v.to_display_string()),
-                    "status": row.get("status").map(|v| v.to_display_string()),
-                    "source_type": row.get("source_type").map(|v| v.to_display_string()),
-                    "target_type": row.get("target_type").map(|v| v.to_display_string()),
-                })
-            })
+            .filter(|i| i.recommendation.is_some())
             .collect();
+        let (current_kb, estimated_kb) = recommended
+            .iter()
+            .filter_map(|i| {
+                i.estimates
+                    .iter()
+                    .find(|e| Some(e.option) == i.recommendation)
+            })
+            .fold((0, 0), |(current, estimated), e| {
+                (current + e.current_kb, estimated + e.estimated_kb)
+            });
+        let script = recommended
+            .iter()
+            .filter_map(|i| i.sql.as_ref().map(|sql| format!("{};", sql)))
+            .collect::<Vec<_>>()
+            .join("\n");
 
         let response = json!({
-            "source_table": input.source_table,
-            "target_table": input.target_table,
-            "column_differences": column_diffs,
-            "difference_count": column_diffs.len(),
+            "tables": input.tables,
+            "options": options,
+            "min_savings_percent": input.min_savings_percent,
+            "index_count": indexes.len(),
+            "recommendation_count": recommended.len(),
+            "recommended_current_kb": current_kb,
+            "recommended_estimated_kb": estimated_kb,
+            "indexes": indexes,
+            "script": script,
+            "errors": errors,
         });
 
         Ok(ToolOutput::text(
             serde_json::to_string_pretty(&response)
-                .unwrap_or_else(|_| "Table comparison failed".to_string()),
+                .unwrap_or_else(|_| "Compression estimate failed".to_string()),
         ))
     }
 
     // =========================================================================
-    // Data Sampling Tools
+    // Statistics Tools
     // =========================================================================
 
-    /// Sample data from a table.
-    #[tool(description = "Get a random or stratified sample of data from a table.", read_only = true)]
-    pub async fn sample_data(
+    /// Report statistics with modifications since their last update.
+    #[tool(
+        description = "Report statistics modified since their last update (rows, modification counter, last update, sampled rows) and flag stale ones, using SQL Server's automatic update threshold or a modification percentage.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_stale_statistics(
         &self,
-        input: SampleDataInput,
+        input: GetStaleStatisticsInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!(
-            "Sampling {} rows from {} using method {}",
-            input.sample_size, input.table, input.method
-        );
+        let statistics = match self
+            .load_statistics(
+                input.schema.as_deref(),
+                input.table.as_deref(),
+                input.modification_threshold_percent,
+            )
+            .await
+        {
+            Ok(statistics) => statistics,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read statistics: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
 
-        // Parse table name
-        let (schema, table) = parse_table_name(&input.table)?;
-        let escaped_table = format!(
-            "{}.{}",
-            safe_identifier(&schema).map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
-            safe_identifier(&table).map_err(|e| McpError::invalid_params("table", e.to_string()))?
-        );
+        let stale_count = statistics.iter().filter(|s| s.stale).count();
+        let listed: Vec<_> = statistics
+            .into_iter()
+            .filter(|s| input.include_current || s.stale)
+            .collect();
 
-        let sample_size = input.sample_size.clamp(1, 10000);
+        let response = json!({
+            "threshold": match input.modification_threshold_percent {
+                Some(percent) => format!("{}% of rows modified", percent),
+                None => "automatic update threshold".to_string(),
+            },
+            "stale_count": stale_count,
+            "statistics": listed,
+        });
 
-        // Build filter clause
-        let filter_clause = match &input.filter {
-            Some(f) if !f.is_empty() => format!("WHERE {}", f),
-            _ => String::new(),
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Statistics report failed".to_string()),
+        ))
+    }
+
+    /// Generate and optionally execute UPDATE STATISTICS statements.
+    ///
+    /// Targets stale statistics, or the named statistics regardless of
+    /// staleness. Statements are only returned unless `execute` is set, which
+    /// requires the unrestricted validation mode.
+    #[tool(
+        description = "Generate UPDATE STATISTICS statements for stale statistics (or named statistics) with FULLSCAN or SAMPLE n PERCENT, and optionally execute them (execute requires unrestricted validation mode).",
+        destructive = true
+    )]
+    pub async fn update_statistics(
+        &self,
+        input: UpdateStatisticsInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::maintenance::{update_statistics_sql, StatisticsScan};
+        use crate::security::ValidationMode;
+
+        let scan = match StatisticsScan::from_options(input.fullscan, input.sample_percent) {
+            Ok(scan) => scan,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
         };
 
-        // Build query based on method
-        let query = match input.method.to_lowercase().as_str() {
-            "top" => {
-                format!(
-                    "SELECT TOP {} * FROM {} {} ORDER BY (SELECT NULL)",
-                    sample_size, escaped_table, filter_clause
-                )
-            }
-            "bottom" => {
-                // Use a subquery to reverse order
-                format!(
-                    r#"
-                    SELECT * FROM (
-                        SELECT TOP {} *, ROW_NUMBER() OVER (ORDER BY (SELECT NULL) DESC) AS _rn
-                        FROM {} {}
-                    ) t ORDER BY _rn DESC
-                    "#,
-                    sample_size, escaped_table, filter_clause
-                )
+        let validation_mode = self.config().security.validation_mode;
+        if input.execute && validation_mode != ValidationMode::Unrestricted {
+            return Ok(ToolOutput::error(format!(
+                "Executing statistics maintenance requires unrestricted validation mode (current: {:?}). \
+                 Run with execute: false to generate the statements instead.",
+                validation_mode
+            )));
+        }
+
+        let statistics = match self
+            .load_statistics(
+                input.schema.as_deref(),
+                input.table.as_deref(),
+                input.modification_threshold_percent,
+            )
+            .await
+        {
+            Ok(statistics) => statistics,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read statistics: {}",
+                    self.localize_error(&e).await
+                )));
             }
-            "stratified" => {
-                match &input.stratify_column {
-                    Some(col) => {
-                        // Validate column name
-                        if let Err(e) = validate_identifier(col) {
-                            return Ok(ToolOutput::error(format!("Invalid stratify column name: {}", e)));
-                        }
-                        let escaped_col = safe_identifier(col)
-                            .map_err(|e| McpError::invalid_params("stratify_column", e.to_string()))?;
-                        format!(
-                            r#"
-                            SELECT * FROM (
-                                SELECT *, ROW_NUMBER() OVER (PARTITION BY {} ORDER BY NEWID()) AS _rn
-                                FROM {} {}
-                            ) t WHERE _rn <= {} / (SELECT COUNT(DISTINCT {}) FROM {} {})
-                            "#,
-                            escaped_col,
-                            escaped_table,
-                            filter_clause,
-                            sample_size,
-                            escaped_col,
-                            escaped_table,
-                            filter_clause
-                        )
+        };
+
+        let targets: Vec<_> = statistics
+            .iter()
+            .filter(|s| {
+                if input.statistics.is_empty() {
+                    s.stale
+                } else {
+                    input
+                        .statistics
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(&s.statistics))
+                }
+            })
+            .collect();
+        let statements: Vec<String> = targets
+            .iter()
+            .map(|s| update_statistics_sql(s, scan))
+            .collect();
+
+        let mut results = Vec::new();
+        if input.execute {
+            for sql in &statements {
+                let outcome = match self.validate_query(sql) {
+                    Ok(()) => self.executor.execute_non_query(sql).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                match outcome {
+                    Ok(()) => {
+                        info!("Statistics maintenance: {}", sql);
+                        results.push(json!({ "sql": sql, "success": true }));
                     }
-                    None => {
-                        return Ok(ToolOutput::error(
-                            "Stratified sampling requires stratify_column parameter",
-                        ));
+                    Err(e) => {
+                        warn!("Statistics update failed: {}", e);
+                        results.push(json!({
+                            "sql": sql,
+                            "success": false,
+                            "error": self.localize_error(&e).await,
+                        }));
                     }
                 }
             }
-            _ => {
-                // Default: random sampling using TABLESAMPLE or NEWID()
-                format!(
-                    "SELECT TOP {} * FROM {} {} ORDER BY NEWID()",
-                    sample_size, escaped_table, filter_clause
-                )
-            }
-        };
-
-        let result = match self.executor.execute(&query).await {
-            Ok(r) => r,
-            Err(e) => {
-                warn!("Sample query failed: {}", e);
-                return Ok(ToolOutput::error(format!("Failed to sample data: {}", e)));
-            }
-        };
+        }
 
-        let output = match input.format {
-            OutputFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!("Failed to serialize sample data to JSON: {}", e);
-                format!("Failed to serialize result: {}", e)
-            }),
-            OutputFormat::Csv => result.to_csv(),
-            OutputFormat::Table => result.to_markdown_table(),
-        };
+        let response = json!({
+            "executed": input.execute,
+            "scan": scan,
+            "statistics_checked": statistics.len(),
+            "statement_count": statements.len(),
+            "statistics": targets,
+            "script": statements.iter().map(|s| format!("{};", s)).collect::<Vec<_>>().join("\n"),
+            "results": if input.execute { Some(results) } else { None },
+        });
 
-        Ok(ToolOutput::text(output))
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Statistics update failed".to_string()),
+        ))
     }
 
     // =========================================================================
-    // Bulk Operations Tools
+    // Referential Integrity Tools
     // =========================================================================
 
-    /// Bulk insert data into a table.
-    #[tool(description = "Insert multiple rows into a table efficiently using batched INSERT statements. Automatically splits large datasets into batches for memory efficiency. Supports optional transaction wrapping for atomicity. Native BCP protocol support is planned for future releases.", destructive = true)]
-    pub async fn bulk_insert(
+    /// Find child rows whose parent row is missing.
+    ///
+    /// Every foreign key in scope is probed, including disabled and untrusted
+    /// ones. Repair scripts are generated but never executed.
+    #[tool(
+        description = "Check foreign keys (including disabled and untrusted ones) for orphaned child rows, reporting counts and the most common missing parent keys per relationship, and optionally generate repair scripts (delete or set_null) that re-validate the constraints. Nothing is modified.",
+        read_only = true
+    )]
+    pub async fn check_integrity(
         &self,
-        input: BulkInsertInput,
+        input: CheckIntegrityInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!(
-            "Bulk inserting {} rows into {} (native_bcp={}, transaction={}, continue_on_error={})",
-            input.rows.len(),
-            input.table,
-            input.use_native_bcp,
-            input.use_transaction,
-            input.continue_on_error
-        );
-
-        if input.rows.is_empty() {
-            return Ok(ToolOutput::error("No rows to insert"));
-        }
-
-        if input.columns.is_empty() {
-            return Ok(ToolOutput::error("No columns specified"));
-        }
+        use crate::database::integrity::{
+            missing_keys_query, orphan_count_query, repair_script, ConstraintStatus,
+            ForeignKeyCheck, RepairStrategy,
+        };
 
-        // Parse and validate table name
-        let (schema, table) = parse_table_name(&input.table)?;
-        let escaped_table = format!(
-            "{}.{}",
-            safe_identifier(&schema).map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
-            safe_identifier(&table).map_err(|e| McpError::invalid_params("table", e.to_string()))?
-        );
+        let strategy = match input.repair.as_deref().map(RepairStrategy::parse) {
+            Some(Ok(strategy)) => Some(strategy),
+            Some(Err(e)) => return Ok(ToolOutput::error(e.to_string())),
+            None => None,
+        };
+        let target = match &input.table {
+            Some(t) => Some(parse_table_name(t)?),
+            None => None,
+        };
 
-        // Validate and escape column names
-        let escaped_columns: Result<Vec<String>, _> =
-            input.columns.iter().map(|c| safe_identifier(c)).collect();
-        let escaped_columns =
-            escaped_columns.map_err(|e| McpError::invalid_params("columns", e.to_string()))?;
-
-        let batch_size = input.batch_size.clamp(1, 5000);
-
-        // Check if native BCP was requested
-        // NOTE: Native BCP is not yet available in mssql-client v0.5.2
-        // The library has BCP packet building infrastructure but doesn't expose
-        // Client.bulk_insert() yet. This will be enabled in a future release.
-        let bcp_available = self.bulk_insert_manager.is_native_bcp_available();
-        if input.use_native_bcp && !bcp_available {
-            debug!("Native BCP requested but not available, using INSERT statements");
-        }
-
-        // Use batched INSERT statements
-        debug!("Using batched INSERT statements (batch_size={})", batch_size);
-
-        // Build all INSERT statements
-        let statements: Vec<String> = input
-            .rows
-            .chunks(batch_size)
-            .map(|chunk| {
-                let values: Vec<String> = chunk
-                    .iter()
-                    .map(|row| {
-                        let formatted_values: Vec<String> =
-                            row.iter().map(format_parameter_value).collect();
-                        format!("({})", formatted_values.join(", "))
-                    })
-                    .collect();
-
-                format!(
-                    "INSERT INTO {} ({}) VALUES {}",
-                    escaped_table,
-                    escaped_columns.join(", "),
-                    values.join(", ")
-                )
+        let foreign_keys = match self
+            .metadata
+            .list_foreign_keys(target.as_ref().map(|(s, t)| (s.as_str(), t.as_str())))
+            .await
+        {
+            Ok(fks) => fks,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to list foreign keys: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let foreign_keys: Vec<_> = foreign_keys
+            .into_iter()
+            .filter(|fk| {
+                input
+                    .schema
+                    .as_ref()
+                    .is_none_or(|s| fk.parent_schema.eq_ignore_ascii_case(s))
             })
+            .filter(|fk| !input.untrusted_only || fk.is_disabled || fk.is_not_trusted)
             .collect();
-
-        let total_batches = statements.len();
-
-        // Execute based on transaction mode
-        if input.use_transaction {
-            // Use transactional execution for atomicity
-            match self
-                .executor
-                .execute_in_transaction(&statements, input.continue_on_error)
-                .await
-            {
-                Ok(result) => {
-                    let response = json!({
-                        "table": input.table,
-                        "rows_requested": input.rows.len(),
-                        "rows_inserted": result.total_rows_affected,
-                        "batch_size": batch_size,
-                        "batches": total_batches,
-                        "successful_batches": result.successful_statements,
-                        "errors": result.errors,
-                        "status": if result.errors.is_empty() { "success" } else { "partial" },
-                        "execution_time_ms": result.execution_time_ms,
-                        "method": "insert_statements",
-                        "transaction": true,
-                        "native_bcp_requested": input.use_native_bcp,
-                        "native_bcp_available": bcp_available,
-                    });
-
-                    Ok(ToolOutput::text(
-                        serde_json::to_string_pretty(&response)
-                            .unwrap_or_else(|_| format!("Inserted {} rows", result.total_rows_affected)),
-                    ))
+        let mut checks = Vec::with_capacity(foreign_keys.len());
+        for fk in &foreign_keys {
+            let mut check = ForeignKeyCheck::new(fk);
+            let query = match orphan_count_query(fk) {
+                Ok(q) => q,
+                Err(e) => {
+                    check.error = Some(e.to_string());
+                    checks.push(check);
+                    continue;
                 }
+            };
+            let orphans = match self.executor.execute(&query).await {
+                Ok(result) => result
+                    .rows
+                    .first()
+                    .and_then(|row| row.get("orphan_count"))
+                    .and_then(|v| v.to_display_string().parse::<i64>().ok())
+                    .unwrap_or(0),
                 Err(e) => {
-                    let response = json!({
-                        "table": input.table,
-                        "rows_requested": input.rows.len(),
-                        "rows_inserted": 0,
-                        "batch_size": batch_size,
-                        "batches": total_batches,
-                        "status": "failed",
-                        "error": e.to_string(),
-                        "method": "insert_statements",
-                        "transaction": true,
-                        "rolled_back": true,
-                        "native_bcp_requested": input.use_native_bcp,
-                        "native_bcp_available": bcp_available,
-                    });
-
-                    Ok(ToolOutput::text(
-                        serde_json::to_string_pretty(&response)
-                            .unwrap_or_else(|_| format!("Insert failed: {}", e)),
-                    ))
+                    warn!("Integrity check of {} failed: {}", fk.constraint_name, e);
+                    check.error = Some(self.localize_error(&e).await);
+                    checks.push(check);
+                    continue;
                 }
-            }
-        } else {
-            // Non-transactional: execute each batch independently
-            let mut total_inserted: u64 = 0;
-            let mut successful_batches = 0;
-            let mut errors: Vec<String> = Vec::new();
-
-            for (idx, stmt) in statements.iter().enumerate() {
-                match self.executor.execute(stmt).await {
-                    Ok(result) => {
-                        total_inserted += result.rows_affected;
-                        successful_batches += 1;
+            };
+            check.orphan_count = Some(orphans);
+
+            if orphans > 0 && input.sample_size > 0 {
+                let sample = match missing_keys_query(fk, input.sample_size.min(100)) {
+                    Ok(query) => self
+                        .executor
+                        .execute(&query)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match sample {
+                    Ok(mut result) => {
+                        self.masker().mask_rows(
+                            &mut result.rows,
+                            Some((&fk.parent_schema, &fk.parent_table)),
+                        );
+                        check.missing_keys = result
+                            .rows
+                            .iter()
+                            .filter_map(|row| serde_json::to_value(row).ok())
+                            .collect();
                     }
-                    Err(e) => {
-                        let error_msg = format!("Batch {}/{} failed: {}", idx + 1, total_batches, e);
-                        errors.push(error_msg);
+                    Err(e) => warn!("Missing key sample of {} failed: {}", fk.constraint_name, e),
+                }
+            }
 
-                        if !input.continue_on_error {
-                            // Stop on first error
-                            break;
-                        }
+            if let Some(strategy) = strategy {
+                if check.needs_attention() {
+                    match repair_script(fk, strategy, orphans) {
+                        Ok(script) => check.repair_sql = Some(script),
+                        Err(e) => check.error = Some(e.to_string()),
                     }
                 }
             }
-
-            let response = json!({
-                "table": input.table,
-                "rows_requested": input.rows.len(),
-                "rows_inserted": total_inserted,
-                "batch_size": batch_size,
-                "batches": total_batches,
-                "successful_batches": successful_batches,
-                "errors": errors,
-                "status": if errors.is_empty() { "success" } else if successful_batches > 0 { "partial" } else { "failed" },
-                "method": "insert_statements",
-                "transaction": false,
-                "native_bcp_requested": input.use_native_bcp,
-                "native_bcp_available": bcp_available,
-            });
-
-            Ok(ToolOutput::text(
-                serde_json::to_string_pretty(&response)
-                    .unwrap_or_else(|_| format!("Inserted {} rows", total_inserted)),
-            ))
-        }
-    }
-
-    /// Export query results to various formats.
-    #[tool(description = "Export query results in CSV, JSON, or JSON Lines format.", read_only = true)]
-    pub async fn export_data(
-        &self,
-        input: ExportDataInput,
-    ) -> Result<ToolOutput, McpError> {
-        debug!("Exporting data: {}", truncate_for_log(&input.query, 100));
-
-        // Validate query
-        if let Err(e) = self.validate_query(&input.query) {
-            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+            checks.push(check);
         }
 
-        let max_rows = input
-            .max_rows
-            .unwrap_or(self.config.security.max_result_rows);
-
-        let result = match self
-            .executor
-            .execute_with_limit(&input.query, max_rows)
-            .await
-        {
-            Ok(r) => r,
-            Err(e) => {
-                warn!("Export query failed: {}", e);
-                return Ok(ToolOutput::error(format!("Query execution failed: {}", e)));
-            }
-        };
-
-        let output = match input.format {
-            ExportFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
-                warn!("Failed to serialize export to JSON: {}", e);
-                format!("Failed to serialize result: {}", e)
-            }),
-            ExportFormat::JsonLines => {
-                // One JSON object per line
-                result
-                    .rows
-                    .iter()
-                    .filter_map(|row| {
-                        serde_json::to_string(&row.columns)
-                            .map_err(|e| {
-                                warn!("Failed to serialize row to JSON Lines: {}", e);
-                                e
-                            })
-                            .ok()
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            }
-            ExportFormat::Csv => {
-                // CSV with optional headers
-                let mut csv_output = String::new();
-                if input.include_headers && !result.columns.is_empty() {
-                    csv_output.push_str(
-                        &result
-                            .columns
-                            .iter()
-                            .map(|c| c.name.as_str())
-                            .collect::<Vec<_>>()
-                            .join(","),
-                    );
-                    csv_output.push('\n');
-                }
-                csv_output.push_str(&result.to_csv());
-                // Remove duplicate header if to_csv includes it
-                if input.include_headers {
-                    csv_output
-                } else {
-                    // Skip first line if to_csv added header
-                    let lines: Vec<&str> = csv_output.lines().skip(1).collect();
-                    lines.join("\n")
-                }
-            }
-        };
+        let with_orphans = checks
+            .iter()
+            .filter(|c| c.orphan_count.unwrap_or(0) > 0)
+            .count();
+        let total_orphans: i64 = checks.iter().filter_map(|c| c.orphan_count).sum();
+        let not_trusted = checks
+            .iter()
+            .filter(|c| c.status != ConstraintStatus::Enabled)
+            .count();
+        let failed = checks.iter().filter(|c| c.error.is_some()).count();
+        let script: Vec<&str> = checks
+            .iter()
+            .filter_map(|c| c.repair_sql.as_deref())
+            .collect();
 
-        let response = json!({
-            "format": input.format.as_str(),
-            "row_count": result.rows.len(),
-            "column_count": result.columns.len(),
-            "truncated": result.truncated,
-            "data": output,
+        let mut response = json!({
+            "foreign_keys_checked": checks.len(),
+            "with_orphans": with_orphans,
+            "total_orphaned_rows": total_orphans,
+            "disabled_or_untrusted": not_trusted,
+            "failed_checks": failed,
+            "foreign_keys": checks,
         });
+        if strategy.is_some() {
+            response["repair_script"] = json!(if script.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "-- Review before running; wrap in a transaction to verify counts first\n{}",
+                    script.join("\n\n")
+                )
+            });
+        }
 
         Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
-                warn!("Failed to serialize export response: {}", e);
-                format!("Export failed: {}", e)
-            }),
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|e| format!("Failed to serialize integrity report: {}", e)),
         ))
     }
 
     // =========================================================================
-    // Server Metrics Tools
+    // Schema Comparison Tools
     // =========================================================================
 
-    /// Get server performance metrics.
-    #[tool(description = "Get SQL Server performance metrics including connections, queries, and memory usage.", read_only = true, idempotent = true)]
-    pub async fn get_metrics(
+    /// Compare two database schemas.
+    #[tool(description = "Compare objects between two schemas in the same database.", read_only = true, idempotent = true)]
+    pub async fn compare_schemas(
         &self,
-        input: GetMetricsInput,
+        input: CompareSchemaInput,
     ) -> Result<ToolOutput, McpError> {
         debug!(
-            "Getting server metrics for categories: {}",
-            input.categories
+            "Comparing schemas: {} vs {}",
+            input.source_schema, input.target_schema
         );
 
-        let mut metrics = json!({
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-        });
+        // Validate schema names
+        if let Err(e) = validate_identifier(&input.source_schema) {
+            return Ok(ToolOutput::error(format!("Invalid source schema name: {}", e)));
+        }
+        if let Err(e) = validate_identifier(&input.target_schema) {
+            return Ok(ToolOutput::error(format!("Invalid target schema name: {}", e)));
+        }
 
-        let include_all = input.categories == "all";
+        let include_tables = input.object_types == "all" || input.object_types == "tables";
+        let include_views = input.object_types == "all" || input.object_types == "views";
+        let include_procedures = input.object_types == "all" || input.object_types == "procedures";
 
-        // Connection metrics
-        if include_all || input.categories.contains("connections") {
-            let conn_query = r#"
+        let mut differences: Vec<serde_json::Value> = Vec::new();
+
+        // Compare tables
+        if include_tables {
+            let tables_query = format!(
+                r#"
                 SELECT
-                    COUNT(*) AS total_connections,
-                    SUM(CASE WHEN status = 'running' THEN 1 ELSE 0 END) AS active_connections,
-                    SUM(CASE WHEN status = 'sleeping' THEN 1 ELSE 0 END) AS idle_connections
-                FROM sys.dm_exec_sessions
-                WHERE is_user_process = 1
-            "#;
+                    COALESCE(s.name, t.name) AS table_name,
+                    CASE
+                        WHEN s.name IS NULL THEN 'only_in_target'
+                        WHEN t.name IS NULL THEN 'only_in_source'
+                        ELSE 'in_both'
+                    END AS status
+                FROM (
+                    SELECT TABLE_NAME AS name
+                    FROM INFORMATION_SCHEMA.TABLES
+                    WHERE TABLE_SCHEMA = '{0}' AND TABLE_TYPE = 'BASE TABLE'
+                ) s
+                FULL OUTER JOIN (
+                    SELECT TABLE_NAME AS name
+                    FROM INFORMATION_SCHEMA.TABLES
+                    WHERE TABLE_SCHEMA = '{1}' AND TABLE_TYPE = 'BASE TABLE'
+                ) t ON s.name = t.name
+                "#,
+                input.source_schema.replace('\'', "''"),
+                input.target_schema.replace('\'', "''")
+            );
 
-            if let Ok(result) = self.executor.execute(conn_query).await {
-                if let Some(row) = result.rows.first() {
-                    metrics["connections"] = json!({
-                        "total": row.get("total_connections").map(|v| v.to_display_string()),
-                        "active": row.get("active_connections").map(|v| v.to_display_string()),
-                        "idle": row.get("idle_connections").map(|v| v.to_display_string()),
-                    });
+            if let Ok(result) = self.executor.execute(&tables_query).await {
+                for row in &result.rows {
+                    let status = row
+                        .get("status")
+                        .map(|v| v.to_display_string())
+                        .unwrap_or_default();
+                    if status != "in_both" {
+                        differences.push(json!({
+                            "type": "table",
+                            "name": row.get("table_name").map(|v| v.to_display_string()),
+                            "status": status,
+                        }));
+                    }
                 }
             }
         }
 
-        // Query metrics
-        if include_all || input.categories.contains("queries") {
-            let query_stats = format!(
+        // Compare views
+        if include_views {
+            let views_query = format!(
                 r#"
-                SELECT TOP 10
-                    SUBSTRING(qt.text, 1, 200) AS query_text,
-                    qs.execution_count,
-                    qs.total_worker_time / 1000 AS total_cpu_ms,
-                    qs.total_elapsed_time / 1000 AS total_duration_ms,
-                    qs.total_logical_reads,
-                    qs.total_physical_reads,
-                    qs.last_execution_time
-                FROM sys.dm_exec_query_stats qs
-                CROSS APPLY sys.dm_exec_sql_text(qs.sql_handle) qt
-                WHERE qs.last_execution_time > DATEADD(minute, -{}, GETDATE())
-                ORDER BY qs.total_elapsed_time DESC
-            "#,
-                input.time_range_minutes
+                SELECT
+                    COALESCE(s.name, t.name) AS view_name,
+                    CASE
+                        WHEN s.name IS NULL THEN 'only_in_target'
+                        WHEN t.name IS NULL THEN 'only_in_source'
+                        ELSE 'in_both'
+                    END AS status
+                FROM (
+                    SELECT TABLE_NAME AS name
+                    FROM INFORMATION_SCHEMA.VIEWS
+                    WHERE TABLE_SCHEMA = '{0}'
+                ) s
+                FULL OUTER JOIN (
+                    SELECT TABLE_NAME AS name
+                    FROM INFORMATION_SCHEMA.VIEWS
+                    WHERE TABLE_SCHEMA = '{1}'
+                ) t ON s.name = t.name
+                "#,
+                input.source_schema.replace('\'', "''"),
+                input.target_schema.replace('\'', "''")
             );
 
-            if let Ok(result) = self.executor.execute(&query_stats).await {
-                let top_queries: Vec<serde_json::Value> = result
-                    .rows
-                    .iter()
-                    .map(|row| {
-                        json!({
-                            "query": row.get("query_text").map(|v| v.to_display_string()),
-                            "executions": row.get("execution_count").map(|v| v.to_display_string()),
-                            "total_cpu_ms": row.get("total_cpu_ms").map(|v| v.to_display_string()),
-                            "total_duration_ms": row.get("total_duration_ms").map(|v| v.to_display_string()),
-                        })
-                    })
-                    .collect();
-                metrics["top_queries"] = json!(top_queries);
+            if let Ok(result) = self.executor.execute(&views_query).await {
+                for row in &result.rows {
+                    let status = row
+                        .get("status")
+                        .map(|v| v.to_display_string())
+                        .unwrap_or_default();
+                    if status != "in_both" {
+                        differences.push(json!({
+                            "type": "view",
+                            "name": row.get("view_name").map(|v| v.to_display_string()),
+                            "status": status,
+                        }));
+                    }
+                }
             }
         }
 
-        // Memory metrics
-        if include_all || input.categories.contains("memory") {
-            let memory_query = r#"
+        // Compare procedures
+        if include_procedures {
+            let procs_query = format!(
+                r#"
                 SELECT
-                    physical_memory_in_use_kb / 1024 AS memory_used_mb,
-                    locked_page_allocations_kb / 1024 AS locked_pages_mb,
-                    large_page_allocations_kb / 1024 AS large_pages_mb,
-                    total_virtual_address_space_kb / 1024 AS virtual_space_mb,
-                    available_commit_limit_kb / 1024 AS available_commit_mb
-                FROM sys.dm_os_process_memory
-            "#;
+                    COALESCE(s.name, t.name) AS procedure_name,
+                    CASE
+                        WHEN s.name IS NULL THEN 'only_in_target'
+                        WHEN t.name IS NULL THEN 'only_in_source'
+                        ELSE 'in_both'
+                    END AS status
+                FROM (
+                    SELECT ROUTINE_NAME AS name
+                    FROM INFORMATION_SCHEMA.ROUTINES
+                    WHERE ROUTINE_SCHEMA = '{0}' AND ROUTINE_TYPE = 'PROCEDURE'
+                ) s
+                FULL OUTER JOIN (
+                    SELECT ROUTINE_NAME AS name
+                    FROM INFORMATION_SCHEMA.ROUTINES
+                    WHERE ROUTINE_SCHEMA = '{1}' AND ROUTINE_TYPE = 'PROCEDURE'
+                ) t ON s.name = t.name
+                "#,
+                input.source_schema.replace('\'', "''"),
+                input.target_schema.replace('\'', "''")
+            );
 
-            if let Ok(result) = self.executor.execute(memory_query).await {
-                if let Some(row) = result.rows.first() {
-                    metrics["memory"] = json!({
-                        "used_mb": row.get("memory_used_mb").map(|v| v.to_display_string()),
-                        "locked_pages_mb": row.get("locked_pages_mb").map(|v| v.to_display_string()),
-                        "virtual_space_mb": row.get("virtual_space_mb").map(|v| v.to_display_string()),
-                    });
+            if let Ok(result) = self.executor.execute(&procs_query).await {
+                for row in &result.rows {
+                    let status = row
+                        .get("status")
+                        .map(|v| v.to_display_string())
+                        .unwrap_or_default();
+                    if status != "in_both" {
+                        differences.push(json!({
+                            "type": "procedure",
+                            "name": row.get("procedure_name").map(|v| v.to_display_string()),
+                            "status": status,
+                        }));
+                    }
                 }
             }
         }
 
-        // Performance counters
-        if include_all || input.categories.contains("performance") {
-            let perf_query = r#"
-                SELECT
-                    object_name,
-                    counter_name,
-                    cntr_value
-                FROM sys.dm_os_performance_counters
-                WHERE counter_name IN (
-                    'Page life expectancy',
-                    'Buffer cache hit ratio',
-                    'Batch Requests/sec',
-                    'SQL Compilations/sec',
-                    'SQL Re-Compilations/sec',
-                    'User Connections'
-                )
-            "#;
-
-            if let Ok(result) = self.executor.execute(perf_query).await {
-                let counters: Vec<serde_json::Value> = result
-                    .rows
-                    .iter()
-                    .map(|row| {
-                        json!({
-                            "counter": row.get("counter_name").map(|v| v.to_display_string()),
-                            "value": row.get("cntr_value").map(|v| v.to_display_string()),
-                        })
-                    })
-                    .collect();
-                metrics["performance_counters"] = json!(counters);
-            }
-        }
+        let response = json!({
+            "source_schema": input.source_schema,
+            "target_schema": input.target_schema,
+            "object_types": input.object_types,
+            "difference_count": differences.len(),
+            "differences": differences,
+        });
 
         Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&metrics)
-                .unwrap_or_else(|_| "Failed to get metrics".to_string()),
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Schema comparison failed".to_string()),
         ))
     }
 
-    /// Analyze a query for performance issues.
-    #[tool(description = "Analyze a SQL query for performance issues and optimization opportunities.", read_only = true, idempotent = true)]
-    pub async fn analyze_query(
+    /// Compare two tables.
+    #[tool(description = "Compare the structure of two tables, including columns, indexes, and constraints.", read_only = true, idempotent = true)]
+    pub async fn compare_tables(
         &self,
-        input: AnalyzeQueryInput,
+        input: CompareTablesInput,
     ) -> Result<ToolOutput, McpError> {
-        debug!("Analyzing query: {}", truncate_for_log(&input.query, 100));
-
-        let mut analysis = json!({
-            "query": truncate_for_log(&input.query, 500),
-            "analysis": {},
-        });
-
-        // Get estimated execution plan as XML
-        let plan_query = format!(
-            "SET SHOWPLAN_XML ON; EXEC sp_executesql N'{}'; SET SHOWPLAN_XML OFF;",
-            input.query.replace('\'', "''")
+        debug!(
+            "Comparing tables: {} vs {}",
+            input.source_table, input.target_table
         );
 
-        // Try to get plan info
-        if let Ok(plan_result) = self.executor.execute(&plan_query).await {
-            if !plan_result.rows.is_empty() {
-                analysis["execution_plan"] = json!({
-                    "status": "retrieved",
-                    "note": "Execution plan retrieved successfully"
-                });
-            }
-        }
+        // Parse table names
+        let (source_schema, source_table) = parse_table_name(&input.source_table)?;
+        let (target_schema, target_table) = parse_table_name(&input.target_table)?;
 
-        // Get table statistics if requested
-        if input.include_statistics {
-            // Extract table names from query (simple heuristic)
-            let stats_query = r#"
-                SELECT
-                    OBJECT_SCHEMA_NAME(s.object_id) AS schema_name,
-                    OBJECT_NAME(s.object_id) AS table_name,
-                    s.name AS stats_name,
-                    STATS_DATE(s.object_id, s.stats_id) AS last_updated,
-                    sp.rows,
-                    sp.modification_counter
-                FROM sys.stats s
-                CROSS APPLY sys.dm_db_stats_properties(s.object_id, s.stats_id) sp
-                WHERE OBJECT_SCHEMA_NAME(s.object_id) NOT IN ('sys', 'INFORMATION_SCHEMA')
-                ORDER BY sp.modification_counter DESC
-            "#;
+        // Compare columns
+        let columns_query = format!(
+            r#"
+            SELECT
+                COALESCE(s.column_name, t.column_name) AS column_name,
+                s.data_type AS source_type,
+                t.data_type AS target_type,
+                s.character_maximum_length AS source_max_length,
+                t.character_maximum_length AS target_max_length,
+                s.is_nullable AS source_nullable,
+                t.is_nullable AS target_nullable,
+                CASE
+                    WHEN s.column_name IS NULL THEN 'only_in_target'
+                    WHEN t.column_name IS NULL THEN 'only_in_source'
+                    WHEN s.data_type <> t.data_type THEN 'type_mismatch'
+                    WHEN ISNULL(s.character_maximum_length, 0) <> ISNULL(t.character_maximum_length, 0) THEN 'length_mismatch'
+                    WHEN s.is_nullable <> t.is_nullable THEN 'nullable_mismatch'
+                    ELSE 'identical'
+                END AS status
+            FROM (
+                SELECT COLUMN_NAME AS column_name, DATA_TYPE AS data_type,
+                       CHARACTER_MAXIMUM_LENGTH AS character_maximum_length, IS_NULLABLE AS is_nullable
+                FROM INFORMATION_SCHEMA.COLUMNS
+                WHERE TABLE_SCHEMA = '{0}' AND TABLE_NAME = '{1}'
+            ) s
+            FULL OUTER JOIN (
+                SELECT COLUMN_NAME AS column_name, DATA_TYPE AS data_type,
+                       CHARACTER_MAXIMUM_LENGTH AS character_maximum_length, IS_NULLABLE AS is_nullable
+                FROM INFORMATION_SCHEMA.COLUMNS
+                WHERE TABLE_SCHEMA = '{2}' AND TABLE_NAME = '{3}'
+            ) t ON s.column_name = t.column_name
+            ORDER BY COALESCE(s.column_name, t.column_name)
+            "#,
+            source_schema.replace('\'', "''"),
+            source_table.replace('\'', "''"),
+            target_schema.replace('\'', "''"),
+            target_table.replace('\'', "''")
+        );
 
-            if let Ok(result) = self.executor.execute(stats_query).await {
-                let stats: Vec<serde_json::Value> = result
-                    .rows
-                    .iter()
-                    .take(20)
-                    .map(|row| {
-                        json!({
-                            "schema": row.get("schema_name").map(|v| v.to_display_string()),
-                            "table": row.get("table_name").map(|v| v.to_display_string()),
-                            "stats_name": row.get("stats_name").map(|v| v.to_display_string()),
-                            "last_updated": row.get("last_updated").map(|v| v.to_display_string()),
-                            "rows": row.get("rows").map(|v| v.to_display_string()),
-                            "modifications": row.get("modification_counter").map(|v| v.to_display_string()),
-                        })
-                    })
-                    .collect();
-                analysis["statistics"] = json!(stats);
+        let columns_result = match self.executor.execute(&columns_query).await {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!("Failed to compare columns: {}", e)));
             }
+        };
+
+        let column_diffs: Vec<serde_json::Value> = columns_result
+            .rows
+            .iter()
+            .filter(|row| {
+                row.get("status")
+                    .map(|v| v.to_display_string())
+                    .unwrap_or_default()
+                    != "identical"
+            })
+            .map(|row| {
+                json!({
+                    "column": row.get("column_name").map(|v| v.to_display_string()),
+                    "status": row.get("status").map(|v| v.to_display_string()),
+                    "source_type": row.get("source_type").map(|v| v.to_display_string()),
+                    "target_type": row.get("target_type").map(|v| v.to_display_string()),
+                })
+            })
+            .collect();
+
+        let response = json!({
+            "source_table": input.source_table,
+            "target_table": input.target_table,
+            "column_differences": column_diffs,
+            "difference_count": column_diffs.len(),
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Table comparison failed".to_string()),
+        ))
+    }
+
+    /// Generate a migration script from a schema diff.
+    ///
+    /// Produces CREATE/ALTER/DROP statements that bring the target schema in
+    /// line with the source. Runs in preview mode by default; set `preview`
+    /// to false to apply the script in a single transaction.
+    #[tool(
+        description = "Generate CREATE/ALTER/DROP statements to bring a target schema in line with a source schema. Statements are ordered by foreign key dependencies. Preview only by default.",
+        destructive = true
+    )]
+    pub async fn generate_migration(
+        &self,
+        input: GenerateMigrationInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::migration::plan_migration;
+
+        debug!(
+            "Generating migration: {} -> {} (preview={})",
+            input.source_schema, input.target_schema, input.preview
+        );
+
+        if let Err(e) = validate_identifier(&input.source_schema) {
+            return Ok(ToolOutput::error(format!(
+                "Invalid source schema name: {}",
+                e
+            )));
+        }
+        if let Err(e) = validate_identifier(&input.target_schema) {
+            return Ok(ToolOutput::error(format!(
+                "Invalid target schema name: {}",
+                e
+            )));
         }
 
-        // Check for missing indexes
-        if input.include_index_analysis {
-            let missing_query = r#"
-                SELECT TOP 10
-                    mid.statement AS table_name,
-                    mid.equality_columns,
-                    mid.inequality_columns,
-                    mid.included_columns,
-                    CONVERT(DECIMAL(10,2), migs.avg_total_user_cost * migs.avg_user_impact * (migs.user_seeks + migs.user_scans)) AS impact
-                FROM sys.dm_db_missing_index_groups mig
-                INNER JOIN sys.dm_db_missing_index_group_stats migs ON migs.group_handle = mig.index_group_handle
-                INNER JOIN sys.dm_db_missing_index_details mid ON mig.index_handle = mid.index_handle
-                WHERE mid.database_id = DB_ID()
-                ORDER BY impact DESC
-            "#;
+        let source = match self.metadata.load_schema_tables(&input.source_schema).await {
+            Ok(t) => t,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to load source schema: {}",
+                    e
+                )));
+            }
+        };
+        let target = match self.metadata.load_schema_tables(&input.target_schema).await {
+            Ok(t) => t,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to load target schema: {}",
+                    e
+                )));
+            }
+        };
 
-            if let Ok(result) = self.executor.execute(missing_query).await {
-                let missing: Vec<serde_json::Value> = result
-                    .rows
-                    .iter()
-                    .map(|row| {
-                        json!({
-                            "table": row.get("table_name").map(|v| v.to_display_string()),
-                            "equality_columns": row.get("equality_columns").map(|v| v.to_display_string()),
-                            "inequality_columns": row.get("inequality_columns").map(|v| v.to_display_string()),
-                            "included_columns": row.get("included_columns").map(|v| v.to_display_string()),
-                            "impact": row.get("impact").map(|v| v.to_display_string()),
-                        })
-                    })
-                    .collect();
-                analysis["missing_indexes"] = json!(missing);
+        let plan = plan_migration(
+            &input.source_schema,
+            &source,
+            &input.target_schema,
+            &target,
+            input.include_drops,
+        );
+
+        let mut applied = None;
+        if !input.preview && !plan.steps.is_empty() {
+            for step in &plan.steps {
+                if let Err(e) = self.validate_query(&step.sql) {
+                    return Ok(ToolOutput::error(format!(
+                        "Migration step for {} rejected: {}",
+                        step.object, e
+                    )));
+                }
+            }
+
+            let statements: Vec<String> = plan.steps.iter().map(|s| s.sql.clone()).collect();
+            match self
+                .executor
+                .execute_in_transaction(&statements, false)
+                .await
+            {
+                Ok(r) => {
+                    info!(
+                        "Applied migration {} -> {}: {} statements",
+                        input.source_schema, input.target_schema, r.successful_statements
+                    );
+                    applied = Some(r);
+                }
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Migration failed and was rolled back: {}",
+                        e
+                    )));
+                }
             }
         }
 
-        // Check for query patterns that might cause issues
-        let query_upper = input.query.to_uppercase();
-        let mut warnings: Vec<String> = Vec::new();
+        let response = json!({
+            "source_schema": input.source_schema,
+            "target_schema": input.target_schema,
+            "preview": input.preview,
+            "step_count": plan.steps.len(),
+            "destructive_count": plan.destructive_count(),
+            "steps": plan.steps,
+            "warnings": plan.warnings,
+            "script": plan.to_script(),
+            "applied": applied,
+        });
 
-        if query_upper.contains("SELECT *") {
-            warnings.push("Query uses SELECT * - consider specifying explicit columns".to_string());
-        }
-        if !query_upper.contains("WHERE") && query_upper.contains("SELECT") {
-            warnings.push("Query has no WHERE clause - may scan entire table".to_string());
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Migration generation failed".to_string()),
+        ))
+    }
+
+    /// Generate CRUD stored procedures for a table.
+    ///
+    /// Builds Get/List/Insert/Update/Delete procedures from the table's
+    /// column metadata. Runs in preview mode by default; set `preview` to
+    /// false to create the procedures in a single transaction.
+    #[tool(
+        description = "Generate standard Get/List/Insert/Update/Delete stored procedures for a table from its column metadata (identity and computed columns are never written, rowversion is used for optimistic concurrency), following a naming pattern. Preview only by default; set preview=false to create them.",
+        destructive = true
+    )]
+    pub async fn generate_crud(&self, input: GenerateCrudInput) -> Result<ToolOutput, McpError> {
+        use crate::database::crud::{generate_crud, CrudAction};
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        if let Err(e) = validate_identifier(&schema).and_then(|_| validate_identifier(&table)) {
+            return Ok(ToolOutput::error(format!("Invalid table name: {}", e)));
         }
-        if query_upper.contains("LIKE '%") {
-            warnings.push(
-                "Query uses leading wildcard in LIKE - cannot use index efficiently".to_string(),
-            );
+        if !input.naming.contains("{action}") {
+            return Ok(ToolOutput::error(
+                "Naming pattern must contain {action} so procedure names are distinct",
+            ));
         }
-        if query_upper.contains("OR ") {
-            warnings.push("Query contains OR conditions - may prevent index usage".to_string());
+        let sample_name = input
+            .naming
+            .replace("{schema}", &schema)
+            .replace("{table}", &table)
+            .replace("{action}", "Get");
+        if let Err(e) = validate_identifier(&sample_name) {
+            return Ok(ToolOutput::error(format!(
+                "Naming pattern gives an invalid procedure name '{}': {}",
+                sample_name, e
+            )));
         }
-        if query_upper.contains("NOT IN") || query_upper.contains("NOT EXISTS") {
-            warnings.push("Query uses NOT IN/NOT EXISTS - consider alternatives".to_string());
+
+        let actions = match &input.operations {
+            None => CrudAction::ALL.to_vec(),
+            Some(names) => {
+                let mut actions = Vec::new();
+                for name in names {
+                    match CrudAction::parse(name) {
+                        Some(action) if !actions.contains(&action) => actions.push(action),
+                        Some(_) => {}
+                        None => {
+                            return Ok(ToolOutput::error(format!(
+                                "Unknown operation '{}'. Use Get, List, Insert, Update, or Delete",
+                                name
+                            )));
+                        }
+                    }
+                }
+                actions
+            }
+        };
+
+        let columns = match self.metadata.get_table_columns(&schema, &table).await {
+            Ok(c) if c.is_empty() => {
+                return Ok(ToolOutput::error(format!(
+                    "Table '{}.{}' not found",
+                    schema, table
+                )));
+            }
+            Ok(c) => c,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!("Failed to read columns: {}", e)));
+            }
+        };
+        let primary_key = match self.metadata.get_primary_key_columns(&schema, &table).await {
+            Ok(k) => k,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read primary key: {}",
+                    e
+                )));
+            }
+        };
+
+        let script = generate_crud(
+            &schema,
+            &table,
+            &columns,
+            &primary_key,
+            &input.naming,
+            &actions,
+        );
+
+        let mut applied = None;
+        if !input.preview && !script.procedures.is_empty() {
+            let statements: Vec<String> = script.procedures.iter().map(|p| p.sql.clone()).collect();
+            for statement in &statements {
+                if let Err(e) = self.validate_query(statement) {
+                    return Ok(ToolOutput::error(format!(
+                        "Procedure creation rejected: {}",
+                        e
+                    )));
+                }
+            }
+
+            match self
+                .executor
+                .execute_in_transaction(&statements, false)
+                .await
+            {
+                Ok(r) => {
+                    info!(
+                        "Created {} CRUD procedures for {}.{}",
+                        r.successful_statements, schema, table
+                    );
+                    applied = Some(r);
+                }
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Procedure creation failed and was rolled back: {}",
+                        e
+                    )));
+                }
+            }
+        }
+
+        let response = json!({
+            "table": format!("{}.{}", schema, table),
+            "primary_key": primary_key,
+            "preview": input.preview,
+            "procedures": script.procedures,
+            "warnings": script.warnings,
+            "script": script.to_script(),
+            "applied": applied,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "CRUD generation failed".to_string()),
+        ))
+    }
+
+    /// Copy a table's structure and, optionally, its rows.
+    ///
+    /// The copy is scripted from the catalog like `script_object`, with only
+    /// the constraints and indexes asked for. Rows are copied in batches over
+    /// the primary key, each batch in its own transaction, after the table is
+    /// created. Runs in preview mode by default.
+    #[tool(
+        description = "Create a copy of a table under a new name: columns (with identity and computed definitions), optionally its constraints (primary key, unique, check, default, foreign keys) and indexes, and optionally its rows filtered by a WHERE clause and copied in batches. Preview only by default; set preview=false to create the copy.",
+        destructive = true
+    )]
+    pub async fn copy_table(&self, input: CopyTableInput) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_COPY_BATCH_SIZE;
+        use crate::database::copy::{
+            copy_data_statement, copy_definition, key_columns, CopyOptions,
+        };
+        use crate::database::dependencies::object_type_query;
+        use crate::database::script::parse_script;
+        use crate::database::scripting::ScriptOptions;
+        use std::collections::HashMap;
+
+        let (source_schema, source_name) = parse_table_name(&input.source_table)?;
+        let (target_schema, target_name) = parse_table_name(&input.target_table)?;
+        for name in [&source_schema, &source_name, &target_schema, &target_name] {
+            if let Err(e) = validate_identifier(name) {
+                return Ok(ToolOutput::error(format!("Invalid table name: {}", e)));
+            }
+        }
+        if source_schema.eq_ignore_ascii_case(&target_schema)
+            && source_name.eq_ignore_ascii_case(&target_name)
+        {
+            return Ok(ToolOutput::error(
+                "The target table must differ from the source table",
+            ));
+        }
+        let escaped_source = format!(
+            "{}.{}",
+            safe_identifier(&source_schema)
+                .map_err(|e| McpError::invalid_params("source_table", e.to_string()))?,
+            safe_identifier(&source_name)
+                .map_err(|e| McpError::invalid_params("source_table", e.to_string()))?
+        );
+
+        for (schema, name, should_exist) in [
+            (&source_schema, &source_name, true),
+            (&target_schema, &target_name, false),
+        ] {
+            let object_type = match self.catalog_rows(&object_type_query(schema, name)).await {
+                Ok(rows) => rows
+                    .first()
+                    .and_then(|row| row.get("object_type"))
+                    .map(|v| v.to_display_string()),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to look up {}.{}: {}",
+                        schema,
+                        name,
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            match (object_type, should_exist) {
+                (Some(t), true) if t == "USER_TABLE" => {}
+                (_, true) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Table not found: {}.{}",
+                        schema, name
+                    )))
+                }
+                (Some(t), false) => {
+                    return Ok(ToolOutput::error(format!(
+                        "{}.{} already exists ({})",
+                        schema, name, t
+                    )))
+                }
+                (None, false) => {}
+            }
+        }
+
+        let definition = match self
+            .table_definition(&source_schema, &source_name, false)
+            .await
+        {
+            Ok(d) => d,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read the definition of {}.{}: {}",
+                    source_schema,
+                    source_name,
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let copy = copy_definition(
+            &definition,
+            &target_schema,
+            &target_name,
+            &CopyOptions {
+                include_constraints: input.include_constraints,
+                include_indexes: input.include_indexes,
+                include_triggers: false,
+            },
+        );
+        let script = copy.script(&ScriptOptions {
+            include_drop: false,
+            include_indexes: true,
+            include_triggers: false,
+            include_foreign_keys: true,
+        });
+        let structure: Vec<String> = match parse_script(&script, &HashMap::new()) {
+            Ok(parsed) => parsed.batches.into_iter().map(|b| b.sql).collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to split the script: {}",
+                    e
+                )))
+            }
+        };
+
+        // Count the rows to copy and split them into pages over a unique key
+        let filter = input.filter.as_deref().filter(|f| !f.trim().is_empty());
+        let mut rows_to_copy = None;
+        let mut data = Vec::new();
+        if input.copy_data {
+            let count_query = format!(
+                "SELECT COUNT_BIG(*) AS row_count FROM {}{}",
+                escaped_source,
+                filter.map(|f| format!(" WHERE {}", f)).unwrap_or_default()
+            );
+            let count = match self.executor.execute(&count_query).await {
+                Ok(result) => result
+                    .rows
+                    .first()
+                    .and_then(|row| row.get("row_count"))
+                    .and_then(|v| v.to_display_string().parse::<usize>().ok())
+                    .unwrap_or_default(),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to count rows: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            rows_to_copy = Some(count);
+
+            let batch_size = input.batch_size.clamp(1, MAX_COPY_BATCH_SIZE);
+            let order = key_columns(&definition).unwrap_or_default();
+            let pages: Vec<Option<(usize, usize)>> = if count > batch_size && !order.is_empty() {
+                (0..count)
+                    .step_by(batch_size)
+                    .map(|offset| Some((offset, batch_size)))
+                    .collect()
+            } else {
+                vec![None]
+            };
+            for page in pages {
+                match copy_data_statement(&escaped_source, &copy, filter, &order, page) {
+                    Ok(statement) => data.push(statement),
+                    Err(e) => return Ok(ToolOutput::error(e.to_string())),
+                }
+            }
+        }
+
+        for statement in structure.iter().chain(&data) {
+            if let Err(e) = self.validate_query(statement) {
+                return Ok(ToolOutput::error(format!("Copy rejected: {}", e)));
+            }
+        }
+
+        let mut response = json!({
+            "source_table": format!("{}.{}", source_schema, source_name),
+            "target_table": format!("{}.{}", target_schema, target_name),
+            "preview": input.preview,
+            "rows_to_copy": rows_to_copy,
+            "batches": data.len(),
+            "script": script,
+        });
+        if input.preview {
+            response["first_batch"] = json!(data.first());
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Table copy failed".to_string()),
+            ));
+        }
+
+        if let Err(e) = self
+            .executor
+            .execute_in_transaction(&structure, false)
+            .await
+        {
+            return Ok(ToolOutput::error(format!(
+                "Creating {}.{} failed and was rolled back: {}",
+                target_schema,
+                target_name,
+                self.localize_error(&e).await
+            )));
+        }
+        let mut rows_copied: u64 = 0;
+        let mut batches_copied = 0;
+        let mut error = None;
+        for statement in &data {
+            match self
+                .executor
+                .execute_in_transaction(std::slice::from_ref(statement), false)
+                .await
+            {
+                Ok(result) => {
+                    rows_copied += result.total_rows_affected;
+                    batches_copied += 1;
+                }
+                Err(e) => {
+                    error = Some(self.localize_error(&e).await);
+                    break;
+                }
+            }
+        }
+        info!(
+            "Copied {}.{} to {}.{}: {} row(s) in {} batch(es)",
+            source_schema, source_name, target_schema, target_name, rows_copied, batches_copied
+        );
+
+        response["status"] = json!(if error.is_none() {
+            "success"
+        } else {
+            "partial"
+        });
+        response["rows_copied"] = json!(rows_copied);
+        response["batches_copied"] = json!(batches_copied);
+        response["error"] = json!(error);
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Table copy failed".to_string()),
+        ))
+    }
+
+    /// Clone every table, view, function and procedure of a schema into a new schema.
+    ///
+    /// Tables are created first, then modules in dependency order with
+    /// references to the source schema rewritten, then (optionally) the rows,
+    /// foreign keys and triggers. The script runs in a single transaction.
+    /// Runs in preview mode by default.
+    #[tool(
+        description = "Replicate all tables (with constraints, indexes and triggers), views, functions and stored procedures of a schema into a new schema, optionally with table data. References qualified with the source schema are rewritten to the new one and modules are created in dependency order. Preview only by default; set preview=false to run the script in one transaction.",
+        destructive = true
+    )]
+    pub async fn clone_schema(&self, input: CloneSchemaInput) -> Result<ToolOutput, McpError> {
+        use crate::database::copy::{
+            copy_data_statement, copy_definition, creation_order, qualify_module,
+            retarget_foreign_keys, rewrite_schema, CopyOptions,
+        };
+        use crate::database::dependencies::{DependencyEdge, DEPENDENCY_EDGES_QUERY};
+        use crate::database::script::parse_script;
+        use crate::database::scripting::{
+            indexes_query, module_definition_query, module_kind, module_script, parse_definitions,
+            parse_indexes, triggers_query, ScriptOptions, SCRIPTABLE_OBJECTS_QUERY,
+        };
+        use std::collections::HashMap;
+
+        let source = input.source_schema.trim();
+        let target = input.target_schema.trim();
+        if let Err(e) = validate_identifier(source).and_then(|_| validate_identifier(target)) {
+            return Ok(ToolOutput::error(format!("Invalid schema name: {}", e)));
+        }
+        if source.eq_ignore_ascii_case(target) {
+            return Ok(ToolOutput::error(
+                "The target schema must differ from the source schema",
+            ));
+        }
+        let escaped_target = safe_identifier(target)
+            .map_err(|e| McpError::invalid_params("target_schema", e.to_string()))?;
+
+        let schema_query = format!(
+            "SELECT SCHEMA_ID(N'{}') AS source_id, SCHEMA_ID(N'{}') AS target_id",
+            source.replace('\'', "''"),
+            target.replace('\'', "''")
+        );
+        match self.catalog_rows(&schema_query).await {
+            Ok(rows) => {
+                let exists = |column: &str| {
+                    rows.first()
+                        .and_then(|row| row.get(column))
+                        .is_some_and(|v| !v.is_null())
+                };
+                if !exists("source_id") {
+                    return Ok(ToolOutput::error(format!("Schema not found: {}", source)));
+                }
+                if exists("target_id") {
+                    return Ok(ToolOutput::error(format!(
+                        "Schema {} already exists",
+                        target
+                    )));
+                }
+            }
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to look up schemas: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        }
+
+        let rows = match self.catalog_rows(SCRIPTABLE_OBJECTS_QUERY).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to list objects: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let objects: Vec<(String, String)> = rows
+            .iter()
+            .filter(|row| {
+                row.get("schema_name")
+                    .is_some_and(|v| v.to_display_string().eq_ignore_ascii_case(source))
+            })
+            .map(|row| {
+                let text = |column: &str| {
+                    row.get(column)
+                        .map(|v| v.to_display_string())
+                        .unwrap_or_default()
+                };
+                (text("object_name"), text("object_type"))
+            })
+            .collect();
+        if objects.is_empty() {
+            return Ok(ToolOutput::error(format!(
+                "Schema {} has no objects to clone",
+                source
+            )));
+        }
+
+        let copy_options = CopyOptions {
+            include_constraints: true,
+            include_indexes: input.include_indexes,
+            include_triggers: input.include_triggers,
+        };
+        let table_options = ScriptOptions {
+            include_drop: false,
+            include_indexes: true,
+            include_triggers: false,
+            include_foreign_keys: false,
+        };
+        let module_options = ScriptOptions {
+            include_drop: false,
+            include_indexes: input.include_indexes,
+            include_triggers: input.include_triggers,
+            include_foreign_keys: false,
+        };
+
+        let mut tables = Vec::new();
+        let mut modules = Vec::new();
+        let mut data = Vec::new();
+        let mut foreign_keys = Vec::new();
+        let mut triggers = Vec::new();
+        let mut failed = Vec::new();
+        for (name, object_type) in objects.iter().filter(|(_, t)| t == "USER_TABLE") {
+            let definition = match self
+                .table_definition(source, name, input.include_triggers)
+                .await
+            {
+                Ok(d) => d,
+                Err(e) => {
+                    failed.push(json!({
+                        "object": format!("{}.{}", source, name),
+                        "error": self.localize_error(&e).await,
+                    }));
+                    continue;
+                }
+            };
+            let mut copy = copy_definition(&definition, target, name, &copy_options);
+            retarget_foreign_keys(&mut copy, source, target);
+            tables.push(format!(
+                "-- {} {}.{}\n{}",
+                object_type,
+                target,
+                name,
+                copy.script(&table_options)
+            ));
+            foreign_keys.extend(copy.foreign_keys_script());
+            triggers.extend(copy.triggers_script());
+            if input.include_data {
+                let escaped_source = format!(
+                    "{}.{}",
+                    safe_identifier(source)
+                        .map_err(|e| McpError::invalid_params("source_schema", e.to_string()))?,
+                    safe_identifier(name)
+                        .map_err(|e| McpError::invalid_params("source_schema", e.to_string()))?
+                );
+                match copy_data_statement(&escaped_source, &copy, None, &[], None) {
+                    Ok(statement) => data.push(format!("{}\nGO\n", statement)),
+                    Err(e) => return Ok(ToolOutput::error(e.to_string())),
+                }
+            }
+        }
+
+        // Views, functions and procedures after the objects they reference
+        let module_objects: Vec<&(String, String)> = objects
+            .iter()
+            .filter(|(_, t)| t != "USER_TABLE" && module_kind(t).is_some())
+            .collect();
+        let edges: Vec<DependencyEdge> = self
+            .catalog_rows(DEPENDENCY_EDGES_QUERY)
+            .await
+            .map(|rows| rows.iter().filter_map(DependencyEdge::from_row).collect())
+            .unwrap_or_default();
+        let names: Vec<String> = module_objects.iter().map(|(n, _)| n.clone()).collect();
+        for index in creation_order(&names, &edges, source) {
+            let (name, object_type) = module_objects[index];
+            let kind = module_kind(object_type).unwrap_or("PROCEDURE");
+            let definition = match self
+                .catalog_rows(&module_definition_query(source, name))
+                .await
+            {
+                Ok(rows) => rows
+                    .first()
+                    .and_then(|row| row.get("definition"))
+                    .filter(|v| !v.is_null())
+                    .map(|v| v.to_display_string()),
+                Err(e) => {
+                    failed.push(json!({
+                        "object": format!("{}.{}", source, name),
+                        "error": self.localize_error(&e).await,
+                    }));
+                    continue;
+                }
+            };
+            let Some(definition) = definition else {
+                failed.push(json!({
+                    "object": format!("{}.{}", source, name),
+                    "error": "The definition is encrypted or not visible",
+                }));
+                continue;
+            };
+            let definition = qualify_module(
+                &rewrite_schema(&definition, source, target),
+                target,
+                name,
+                None,
+            );
+            let (indexes, view_triggers) = if kind == "VIEW" {
+                let indexes = match self.catalog_rows(&indexes_query(source, name)).await {
+                    Ok(rows) => parse_indexes(&rows),
+                    Err(_) => Vec::new(),
+                };
+                let view_triggers = match self.catalog_rows(&triggers_query(source, name)).await {
+                    Ok(rows) => parse_definitions(&rows, "trigger_name"),
+                    Err(_) => Vec::new(),
+                };
+                let view_triggers = view_triggers
+                    .into_iter()
+                    .map(|mut t| {
+                        let rewritten = rewrite_schema(&t.definition, source, target);
+                        t.definition = qualify_module(&rewritten, target, &t.name, Some(name));
+                        t
+                    })
+                    .collect::<Vec<_>>();
+                (indexes, view_triggers)
+            } else {
+                (Vec::new(), Vec::new())
+            };
+            modules.push(format!(
+                "-- {} {}.{}\n{}",
+                object_type,
+                target,
+                name,
+                module_script(
+                    target,
+                    name,
+                    kind,
+                    &definition,
+                    &indexes,
+                    &view_triggers,
+                    &module_options
+                )
+            ));
+        }
+
+        let mut sections = vec![format!("CREATE SCHEMA {};\nGO\n", escaped_target)];
+        sections.extend(tables.iter().cloned());
+        sections.extend(data.iter().cloned());
+        sections.extend(foreign_keys.iter().cloned());
+        sections.extend(triggers.iter().cloned());
+        sections.extend(modules.iter().cloned());
+        let script = sections.join("\n");
+        let statements: Vec<String> = match parse_script(&script, &HashMap::new()) {
+            Ok(parsed) => parsed.batches.into_iter().map(|b| b.sql).collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to split the script: {}",
+                    e
+                )))
+            }
+        };
+
+        let mut applied = None;
+        if !input.preview {
+            if !failed.is_empty() {
+                return Ok(ToolOutput::error(format!(
+                    "{} object(s) could not be scripted; run a preview to see which",
+                    failed.len()
+                )));
+            }
+            for statement in &statements {
+                if let Err(e) = self.validate_query(statement) {
+                    return Ok(ToolOutput::error(format!("Clone rejected: {}", e)));
+                }
+            }
+            match self
+                .executor
+                .execute_in_transaction(&statements, false)
+                .await
+            {
+                Ok(r) => {
+                    info!(
+                        "Cloned schema {} to {}: {} statements",
+                        source, target, r.successful_statements
+                    );
+                    applied = Some(r);
+                }
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Cloning failed and was rolled back: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            }
+        }
+
+        let response = json!({
+            "source_schema": source,
+            "target_schema": target,
+            "preview": input.preview,
+            "table_count": tables.len(),
+            "module_count": modules.len(),
+            "include_data": input.include_data,
+            "statement_count": statements.len(),
+            "failed": failed,
+            "script": script,
+            "applied": applied,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Schema clone failed".to_string()),
+        ))
+    }
+
+    // =========================================================================
+    // Dependency Analysis Tools
+    // =========================================================================
+
+    /// Analyze which objects an object depends on and which depend on it.
+    #[tool(
+        description = "Get the dependency tree of an object from sys.sql_expression_dependencies: what it uses (tables, views, functions it references) and what uses it (views, procedures, functions, triggers referencing it), to a configurable depth. Check this before dropping or altering an object.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_dependencies(
+        &self,
+        input: GetDependenciesInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_DEPENDENCY_DEPTH;
+        use crate::database::dependencies::{
+            dependency_trees, flatten, object_type_query, DependencyDirection, DependencyEdge,
+            DependencyGraph, DEPENDENCY_EDGES_QUERY,
+        };
+
+        let (schema, name) = parse_table_name(&input.object)?;
+        if let Err(e) = validate_identifier(&schema).and_then(|_| validate_identifier(&name)) {
+            return Ok(ToolOutput::error(format!("Invalid object name: {}", e)));
+        }
+        let direction = match DependencyDirection::parse(&input.direction) {
+            Ok(direction) => direction,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let max_depth = input.max_depth.clamp(1, MAX_DEPENDENCY_DEPTH);
+
+        let use_prefix = {
+            let state = self.state.read().await;
+            state
+                .current_database()
+                .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+                .unwrap_or_default()
+        };
+
+        let object_type = match self
+            .executor
+            .execute(&format!(
+                "{}{}",
+                use_prefix,
+                object_type_query(&schema, &name)
+            ))
+            .await
+        {
+            Ok(result) => match result.rows.first().and_then(|row| row.get("object_type")) {
+                Some(value) => value.to_display_string(),
+                None => {
+                    return Ok(ToolOutput::error(format!(
+                        "Object not found: {}.{}",
+                        schema, name
+                    )));
+                }
+            },
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to look up object: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let edges = match self
+            .executor
+            .execute_with_limit(
+                &format!("{}{}", use_prefix, DEPENDENCY_EDGES_QUERY),
+                usize::MAX,
+            )
+            .await
+        {
+            Ok(result) => result
+                .rows
+                .iter()
+                .filter_map(DependencyEdge::from_row)
+                .collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read dependencies: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let graph = DependencyGraph::new(edges);
+        let (uses, used_by) = dependency_trees(&graph, &schema, &name, direction, max_depth);
+
+        let response = json!({
+            "object": format!("{}.{}", schema, name),
+            "object_type": object_type,
+            "max_depth": max_depth,
+            "uses": uses,
+            "used_by": used_by,
+            "affected_objects": used_by.as_deref().map(flatten),
+            "note": "Dependencies come from sys.sql_expression_dependencies: dynamic SQL, foreign keys, and references from other databases are not included.",
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Dependency analysis failed".to_string()),
+        ))
+    }
+
+    /// Script the DDL of an object, like "Script As" in SSMS.
+    ///
+    /// Tables are rebuilt from the catalog views with their constraints,
+    /// defaults, foreign keys, indexes and triggers; views, procedures,
+    /// functions and triggers come from `sys.sql_modules`.
+    #[tool(
+        description = "Generate a complete, re-runnable CREATE script for an object (like SSMS \"Script As\"): tables with columns, identity, computed columns, defaults, primary/unique/check constraints, foreign keys, indexes and triggers; views, procedures, functions and triggers from their definitions (as CREATE OR ALTER, or with DROP IF EXISTS).",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn script_object(&self, input: ScriptObjectInput) -> Result<ToolOutput, McpError> {
+        use crate::database::dependencies::object_type_query;
+        use crate::database::scripting::{module_kind, ScriptOptions};
+
+        let (schema, name) = parse_table_name(&input.object)?;
+        if let Err(e) = validate_identifier(&schema).and_then(|_| validate_identifier(&name)) {
+            return Ok(ToolOutput::error(format!("Invalid object name: {}", e)));
+        }
+        let options = ScriptOptions {
+            include_drop: input.include_drop,
+            include_indexes: input.include_indexes,
+            include_triggers: input.include_triggers,
+            include_foreign_keys: true,
+        };
+
+        let object_type = match self.catalog_rows(&object_type_query(&schema, &name)).await {
+            Ok(rows) => match rows.first().and_then(|row| row.get("object_type")) {
+                Some(value) => value.to_display_string(),
+                None => {
+                    return Ok(ToolOutput::error(format!(
+                        "Object not found: {}.{}",
+                        schema, name
+                    )));
+                }
+            },
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to look up object: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let result = if object_type == "USER_TABLE" {
+            self.script_table(&schema, &name, &options).await
+        } else if let Some(kind) = module_kind(&object_type) {
+            self.script_module(&schema, &name, kind, &options).await
+        } else {
+            return Ok(ToolOutput::error(format!(
+                "Scripting {} objects is not supported; use a table, view, procedure, function, or trigger",
+                object_type
+            )));
+        };
+
+        match result {
+            Ok(script) => Ok(ToolOutput::text(format!(
+                "-- {} {}.{}\n{}",
+                object_type, schema, name, script
+            ))),
+            Err(e) => Ok(ToolOutput::error(format!(
+                "Failed to script {}.{}: {}",
+                schema,
+                name,
+                self.localize_error(&e).await
+            ))),
+        }
+    }
+
+    /// Script every object in the database to files for source control.
+    ///
+    /// Directory mode writes `Tables/`, `Views/`, `Functions/` and
+    /// `StoredProcedures/` with one `schema.name.sql` per object. Single-file
+    /// mode writes tables, views, functions and procedures in that order and
+    /// adds all foreign keys at the end so the script runs in one pass.
+    #[tool(
+        description = "Script the DDL of every table, view, function and stored procedure in the current database to files under MSSQL_EXPORT_DIRS for source control: one schema.name.sql per object in Tables/, Views/, Functions/ and StoredProcedures/ folders, or a single script file (single_file). Filter objects with include/exclude patterns on schema.name (* wildcard).",
+        read_only = true
+    )]
+    pub async fn script_database(
+        &self,
+        input: ScriptDatabaseInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::scripting::{
+            module_kind, object_folder, script_file_name, ObjectFilter, ScriptOptions,
+            SCRIPTABLE_OBJECTS_QUERY,
+        };
+        use std::collections::BTreeMap;
+        use std::path::PathBuf;
+
+        let filter = match ObjectFilter::new(&input.include, &input.exclude) {
+            Ok(f) => f,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        let destination = match export::resolve_destination(
+            &input.destination,
+            &self.config().security.export_directories,
+        ) {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        if input.single_file && destination.is_dir() {
+            return Ok(ToolOutput::error(format!(
+                "'{}' is a directory; single_file mode needs a file name",
+                destination.display()
+            )));
+        }
+        if !input.single_file && destination.is_file() {
+            return Ok(ToolOutput::error(format!(
+                "'{}' is a file; set single_file to write one script",
+                destination.display()
+            )));
+        }
+
+        let objects: Vec<(String, String, String)> =
+            match self.catalog_rows(SCRIPTABLE_OBJECTS_QUERY).await {
+                Ok(rows) => rows
+                    .iter()
+                    .map(|row| {
+                        let text = |column: &str| {
+                            row.get(column)
+                                .map(|v| v.to_display_string())
+                                .unwrap_or_default()
+                        };
+                        (
+                            text("schema_name"),
+                            text("object_name"),
+                            text("object_type"),
+                        )
+                    })
+                    .filter(|(schema, name, object_type)| {
+                        object_folder(object_type).is_some() && filter.matches(schema, name)
+                    })
+                    .collect(),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to list objects: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+        if objects.is_empty() {
+            return Ok(ToolOutput::error(
+                "No objects match the include/exclude filters",
+            ));
+        }
+
+        let target = |schema: &str, name: &str, object_type: &str| -> PathBuf {
+            if input.single_file {
+                destination.clone()
+            } else {
+                destination
+                    .join(object_folder(object_type).unwrap_or("Other"))
+                    .join(script_file_name(schema, name))
+            }
+        };
+        if !input.overwrite {
+            let existing = objects
+                .iter()
+                .map(|(schema, name, object_type)| target(schema, name, object_type))
+                .find(|path| path.exists());
+            if let Some(path) = existing {
+                return Ok(ToolOutput::error(format!(
+                    "'{}' already exists; set overwrite to replace existing scripts",
+                    path.display()
+                )));
+            }
+        }
+
+        let options = ScriptOptions {
+            include_drop: false,
+            include_indexes: input.include_indexes,
+            include_triggers: input.include_triggers,
+            include_foreign_keys: !input.single_file,
+        };
+        let mut scripts = Vec::new();
+        let mut foreign_keys = Vec::new();
+        let mut failed = Vec::new();
+        for (schema, name, object_type) in &objects {
+            let result = if object_type == "USER_TABLE" {
+                self.table_definition(schema, name, options.include_triggers)
+                    .await
+                    .map(|table| {
+                        if input.single_file {
+                            foreign_keys.extend(table.foreign_keys_script());
+                        }
+                        table.script(&options)
+                    })
+            } else {
+                let kind = module_kind(object_type).unwrap_or("PROCEDURE");
+                self.script_module(schema, name, kind, &options).await
+            };
+            match result {
+                Ok(script) => scripts.push((
+                    target(schema, name, object_type),
+                    object_type.as_str(),
+                    format!("-- {} {}.{}\n{}", object_type, schema, name, script),
+                )),
+                Err(e) => failed.push(json!({
+                    "object": format!("{}.{}", schema, name),
+                    "error": self.localize_error(&e).await,
+                })),
+            }
+        }
+
+        let write_result = if input.single_file {
+            let mut content: Vec<&str> = scripts.iter().map(|(_, _, s)| s.as_str()).collect();
+            let keys = foreign_keys.join("\n");
+            if !keys.is_empty() {
+                content.push("-- Foreign keys");
+                content.push(&keys);
+            }
+            std::fs::write(&destination, content.join("\n"))
+        } else {
+            scripts.iter().try_for_each(|(path, _, script)| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, script)
+            })
+        };
+        if let Err(e) = write_result {
+            return Ok(ToolOutput::error(format!(
+                "Failed to write scripts to {}: {}",
+                destination.display(),
+                e
+            )));
+        }
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, object_type, _) in &scripts {
+            *counts
+                .entry(object_folder(object_type).unwrap_or("Other"))
+                .or_default() += 1;
+        }
+        info!(
+            "Scripted {} object(s) to {}",
+            scripts.len(),
+            destination.display()
+        );
+
+        let mut response = json!({
+            "destination": destination.display().to_string(),
+            "single_file": input.single_file,
+            "object_count": scripts.len(),
+            "objects_by_folder": counts,
+            "failed": failed,
+        });
+        if !input.single_file {
+            response["files"] = json!(scripts
+                .iter()
+                .map(|(path, _, _)| {
+                    path.strip_prefix(&destination)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string()
+                })
+                .collect::<Vec<_>>());
+        }
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Scripted {} objects", scripts.len())),
+        ))
+    }
+
+    // =========================================================================
+    // Schema Search Tools
+    // =========================================================================
+
+    /// Search schema metadata for a keyword or regex.
+    #[tool(
+        description = "Search object names, column names, view/procedure/function/trigger definitions, and extended properties for a keyword or regex. Returns matching objects ranked by relevance (exact name matches first) with snippets, instead of listing the whole schema.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn search_schema(&self, input: SearchSchemaInput) -> Result<ToolOutput, McpError> {
+        use crate::database::schema_search::{
+            rank_matches, search_query, SearchPattern, SEARCH_SOURCES,
+        };
+
+        let pattern = match SearchPattern::new(&input.pattern, input.regex) {
+            Ok(pattern) => pattern,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+
+        let sources: Vec<String> = if input.search_in.is_empty() {
+            SEARCH_SOURCES.iter().map(|s| s.to_string()).collect()
+        } else {
+            input.search_in.iter().map(|s| s.to_lowercase()).collect()
+        };
+        if let Some(unknown) = sources
+            .iter()
+            .find(|s| !SEARCH_SOURCES.contains(&s.as_str()))
+        {
+            return Ok(ToolOutput::error(format!(
+                "Invalid search_in value '{}' (expected {})",
+                unknown,
+                SEARCH_SOURCES.join(", ")
+            )));
+        }
+        if let Some(schema) = &input.schema {
+            if let Err(e) = validate_identifier(schema) {
+                return Ok(ToolOutput::error(format!("Invalid schema: {}", e)));
+            }
+        }
+
+        let query = search_query(&sources, input.schema.as_deref(), pattern.keyword());
+        let query = {
+            let state = self.state.read().await;
+            match state.current_database() {
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), query),
+                None => query,
+            }
+        };
+
+        let rows = match self.executor.execute_with_limit(&query, usize::MAX).await {
+            Ok(result) => result.rows,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Schema search failed: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let mut matches = rank_matches(&rows, &pattern);
+        let total = matches.len();
+        matches.truncate(input.limit.max(1));
+
+        let response = json!({
+            "pattern": input.pattern,
+            "regex": input.regex,
+            "searched": sources,
+            "total_matches": total,
+            "returned": matches.len(),
+            "matches": matches,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Schema search failed".to_string()),
+        ))
+    }
+
+    // =========================================================================
+    // Data Dictionary Tools
+    // =========================================================================
+
+    /// Generate a data dictionary document for the tables of a schema.
+    #[tool(
+        description = "Generate a data dictionary for all tables in a schema (or the whole database): columns, types, nullability, defaults, primary and foreign keys, MS_Description comments, and row counts, as one Markdown, HTML, or JSON document.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn generate_data_dictionary(
+        &self,
+        input: GenerateDataDictionaryInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::data_dictionary::{
+            build_dictionary, columns_query, tables_query, to_html, to_markdown, DictionaryFormat,
+        };
+
+        let format = match DictionaryFormat::parse(&input.format) {
+            Ok(format) => format,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let schema = input.schema.as_deref();
+        if let Some(schema) = schema {
+            if let Err(e) = validate_identifier(schema) {
+                return Ok(ToolOutput::error(format!("Invalid schema: {}", e)));
+            }
+        }
+
+        let (use_prefix, database) = {
+            let state = self.state.read().await;
+            let database = state
+                .current_database()
+                .map(str::to_string)
+                .or_else(|| self.config().database.database.clone());
+            let prefix = state
+                .current_database()
+                .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+                .unwrap_or_default();
+            (prefix, database)
+        };
+
+        let tables_sql = format!(
+            "{}{}",
+            use_prefix,
+            tables_query(schema, input.include_row_counts)
+        );
+        let columns_sql = format!("{}{}", use_prefix, columns_query(schema));
+        let loaded = async {
+            let tables = self
+                .executor
+                .execute_with_limit(&tables_sql, usize::MAX)
+                .await?;
+            let columns = self
+                .executor
+                .execute_with_limit(&columns_sql, usize::MAX)
+                .await?;
+            let relationships = self.metadata.list_foreign_keys(None).await?;
+            Ok::<_, ServerError>((tables, columns, relationships))
+        }
+        .await;
+        let (tables, columns, relationships) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read schema metadata: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let dictionary = build_dictionary(&tables.rows, &columns.rows, &relationships);
+        let title = match (database.as_deref(), schema) {
+            (Some(db), Some(schema)) => format!("{}.{}", db, schema),
+            (Some(db), None) => db.to_string(),
+            (None, Some(schema)) => schema.to_string(),
+            (None, None) => "all schemas".to_string(),
+        };
+
+        Ok(ToolOutput::text(match format {
+            DictionaryFormat::Markdown => to_markdown(&title, &dictionary),
+            DictionaryFormat::Html => to_html(&title, &dictionary),
+            DictionaryFormat::Json => serde_json::to_string_pretty(&json!({
+                "title": title,
+                "table_count": dictionary.len(),
+                "tables": dictionary,
+            }))
+            .unwrap_or_else(|_| "Data dictionary generation failed".to_string()),
+        }))
+    }
+
+    // =========================================================================
+    // Data Sampling Tools
+    // =========================================================================
+
+    /// Sample data from a table.
+    #[tool(description = "Get a random or stratified sample of data from a table.", read_only = true)]
+    pub async fn sample_data(
+        &self,
+        input: SampleDataInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!(
+            "Sampling {} rows from {} using method {}",
+            input.sample_size, input.table, input.method
+        );
+
+        // Parse table name
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema).map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table).map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+
+        let sample_size = input.sample_size.clamp(1, 10000);
+
+        // Build filter clause
+        let filter_clause = match &input.filter {
+            Some(f) if !f.is_empty() => format!("WHERE {}", f),
+            _ => String::new(),
+        };
+
+        // Build query based on method
+        let query = match input.method.to_lowercase().as_str() {
+            "top" => {
+                format!(
+                    "SELECT TOP {} * FROM {} {} ORDER BY (SELECT NULL)",
+                    sample_size, escaped_table, filter_clause
+                )
+            }
+            "bottom" => {
+                // Use a subquery to reverse order
+                format!(
+                    r#"
+                    SELECT * FROM (
+                        SELECT TOP {} *, ROW_NUMBER() OVER (ORDER BY (SELECT NULL) DESC) AS _rn
+                        FROM {} {}
+                    ) t ORDER BY _rn DESC
+                    "#,
+                    sample_size, escaped_table, filter_clause
+                )
+            }
+            "stratified" => {
+                match &input.stratify_column {
+                    Some(col) => {
+                        // Validate column name
+                        if let Err(e) = validate_identifier(col) {
+                            return Ok(ToolOutput::error(format!("Invalid stratify column name: {}", e)));
+                        }
+                        let escaped_col = safe_identifier(col)
+                            .map_err(|e| McpError::invalid_params("stratify_column", e.to_string()))?;
+                        format!(
+                            r#"
+                            SELECT * FROM (
+                                SELECT *, ROW_NUMBER() OVER (PARTITION BY {} ORDER BY NEWID()) AS _rn
+                                FROM {} {}
+                            ) t WHERE _rn <= {} / (SELECT COUNT(DISTINCT {}) FROM {} {})
+                            "#,
+                            escaped_col,
+                            escaped_table,
+                            filter_clause,
+                            sample_size,
+                            escaped_col,
+                            escaped_table,
+                            filter_clause
+                        )
+                    }
+                    None => {
+                        return Ok(ToolOutput::error(
+                            "Stratified sampling requires stratify_column parameter",
+                        ));
+                    }
+                }
+            }
+            _ => {
+                // Default: random sampling using TABLESAMPLE or NEWID()
+                format!(
+                    "SELECT TOP {} * FROM {} {} ORDER BY NEWID()",
+                    sample_size, escaped_table, filter_clause
+                )
+            }
+        };
+
+        let mut result = match self.executor.execute(&query).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Sample query failed: {}", e);
+                return Ok(ToolOutput::error(format!("Failed to sample data: {}", e)));
+            }
+        };
+        self.masker()
+            .mask_result(&mut result, Some((&schema, &table)));
+
+        let output = formatting::render(&result, input.format);
+
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Inspect a JSON text column and query it with typed projections.
+    ///
+    /// The paths are inferred from a sample of the column; the query is then
+    /// generated from them so JSON path quoting and typing are always valid.
+    #[tool(
+        description = "Inspect a column holding JSON text: infer its paths and types from sampled rows, then generate (and by default run) a query projecting the chosen paths as typed columns with JSON_VALUE/JSON_QUERY, expanding an array into rows with OPENJSON ... WITH. Use this instead of hand-writing SQL Server JSON path syntax.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn query_json_column(
+        &self,
+        input: QueryJsonColumnInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::json_column::{projection_query, JsonProjection};
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        let json_schema = match self
+            .json_column_schema(&schema, &table, &input.column, input.sample_size)
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let mut include_columns = Vec::with_capacity(input.include_columns.len());
+        for column in &input.include_columns {
+            match safe_identifier(column) {
+                Ok(c) => include_columns.push(c),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Invalid column name '{}': {}",
+                        column, e
+                    )));
+                }
+            }
+        }
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+        let escaped_column = safe_identifier(&input.column)
+            .map_err(|e| McpError::invalid_params("column", e.to_string()))?;
+        let query = match projection_query(
+            &json_schema,
+            &JsonProjection {
+                table: &escaped_table,
+                column: &escaped_column,
+                include_columns: &include_columns,
+                paths: &input.paths,
+                expand_array: input.expand_array.as_deref(),
+                filter: input.filter.as_deref(),
+                max_rows: input
+                    .max_rows
+                    .unwrap_or(self.config().security.max_result_rows)
+                    .max(1),
+            },
+        ) {
+            Ok(q) => q,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "{}\n\nInferred schema:\n{}",
+                    e,
+                    serde_json::to_string_pretty(&json_schema).unwrap_or_default()
+                )));
+            }
+        };
+
+        let mut response = json!({
+            "table": format!("{}.{}", schema, table),
+            "column": input.column,
+            "schema": json_schema,
+            "query": query,
+        });
+        if !input.execute {
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response).unwrap_or_else(|_| query.clone()),
+            ));
+        }
+
+        if let Err(e) = self.validate_query(&query) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        let mut result = match self.executor.execute(&query).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("JSON column query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Query failed: {}\n\nGenerated query:\n{}",
+                    self.localize_error(&e).await,
+                    query
+                )));
+            }
+        };
+        self.masker()
+            .mask_result(&mut result, Some((&schema, &table)));
+
+        let output = match input.format {
+            OutputFormat::Json => {
+                response["result"] = json!(result);
+                serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                    warn!("Failed to serialize JSON column query to JSON: {}", e);
+                    format!("Failed to serialize result: {}", e)
+                })
+            }
+            OutputFormat::Csv => result.to_csv(),
+            OutputFormat::Table => format!(
+                "## Query\n\n```sql\n{}\n```\n\n## Results\n\n{}",
+                query,
+                result.to_markdown_table()
+            ),
+        };
+
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Describe the graph (node and edge) tables of a database.
+    #[tool(
+        description = "Describe SQL Server graph tables: node and edge tables with their user columns, the $node_id/$edge_id/$from_id/$to_id pseudo-columns, the node tables each edge connects (edge constraints), and an example MATCH query. Use before querying graph tables: they are traversed with MATCH, not joins on their internal columns.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn describe_graph(&self, input: DescribeGraphInput) -> Result<ToolOutput, McpError> {
+        use crate::database::graph::{match_example, GraphKind};
+
+        if let Some(db) = &input.database {
+            if let Err(e) = validate_identifier(db) {
+                return Ok(ToolOutput::error(format!("Invalid database name: {}", e)));
+            }
+        }
+        let tables = match self.graph_tables(input.database.as_deref()).await {
+            Ok(tables) => tables,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        let (nodes, edges): (Vec<_>, Vec<_>) =
+            tables.iter().partition(|t| t.kind == GraphKind::Node);
+        let mut response = json!({
+            "node_tables": nodes,
+            "edge_tables": edges,
+            "pseudo_columns": {
+                "node": GraphKind::Node.pseudo_columns(),
+                "edge": GraphKind::Edge.pseudo_columns(),
+            },
+            "example_match": match_example(&tables),
+        });
+        if tables.is_empty() {
+            response["note"] =
+                json!("No graph tables found (graph tables require SQL Server 2017 or later).");
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to describe graph tables".to_string()),
+        ))
+    }
+
+    /// Query a system-versioned temporal table with `FOR SYSTEM_TIME`.
+    ///
+    /// The period columns are always returned (even when declared HIDDEN) so
+    /// each row shows the interval during which it was current.
+    #[tool(
+        description = "Query a system-versioned temporal table as of a point in time (as_of), over a time range (start/end with range_type between, from_to, or contained_in), or across all row versions, using FOR SYSTEM_TIME. Timestamps are UTC. Period columns are included so each row shows when it was valid.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn query_history_table(
+        &self,
+        input: QueryHistoryTableInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::temporal::{history_query, SystemTime};
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+
+        let system_time = match SystemTime::from_parts(
+            input.as_of.as_deref(),
+            input.start.as_deref(),
+            input.end.as_deref(),
+            input.range_type.as_deref(),
+            input.all_versions,
+        ) {
+            Ok(t) => t,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let versioning = match self.metadata.get_table_versioning(&schema, &table).await {
+            Ok(Some(v)) => v,
+            Ok(None) => {
+                return Ok(ToolOutput::error(format!(
+                    "Table not found: {}.{}",
+                    schema, table
+                )));
+            }
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read table metadata: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        if !versioning.is_system_versioned() {
+            return Ok(ToolOutput::error(match &versioning.temporal_table {
+                Some(parent) => format!(
+                    "{}.{} is the history table of {}; query {} instead",
+                    schema, table, parent, parent
+                ),
+                None => format!(
+                    "{}.{} is not a system-versioned temporal table",
+                    schema, table
+                ),
+            }));
+        }
+        let (Some(period_start), Some(period_end)) = (
+            &versioning.period_start_column,
+            &versioning.period_end_column,
+        ) else {
+            return Ok(ToolOutput::error(format!(
+                "Could not find the period columns of {}.{}",
+                schema, table
+            )));
+        };
+
+        let mut select_list = Vec::new();
+        match &input.columns {
+            Some(columns) if !columns.is_empty() => {
+                for column in columns {
+                    match safe_identifier(column) {
+                        Ok(c) => select_list.push(c),
+                        Err(e) => {
+                            return Ok(ToolOutput::error(format!(
+                                "Invalid column name '{}': {}",
+                                column, e
+                            )));
+                        }
+                    }
+                }
+            }
+            _ => select_list.push("*".to_string()),
+        }
+        let explicit = select_list[0] != "*";
+        for period_column in [period_start, period_end] {
+            let listed = input
+                .columns
+                .iter()
+                .flatten()
+                .any(|c| c.eq_ignore_ascii_case(period_column));
+            if (explicit && !listed) || (!explicit && versioning.period_columns_hidden) {
+                select_list.push(
+                    safe_identifier(period_column)
+                        .map_err(|e| McpError::internal(e.to_string()))?,
+                );
+            }
+        }
+
+        let query = history_query(
+            &escaped_table,
+            &select_list.join(", "),
+            &system_time,
+            input.filter.as_deref(),
+            &safe_identifier(period_start).map_err(|e| McpError::internal(e.to_string()))?,
+            input
+                .max_rows
+                .unwrap_or(self.config().security.max_result_rows)
+                .max(1),
+        );
+        if let Err(e) = self.validate_query(&query) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+
+        let mut result = match self.executor.execute(&query).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Temporal query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Failed to query history: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        self.masker()
+            .mask_result(&mut result, Some((&schema, &table)));
+
+        let output = formatting::render(&result, input.format);
+
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Suggest columns that hold sensitive data.
+    ///
+    /// Columns are classified by name across a schema; when a table is given,
+    /// a sample of its rows is also scanned for emails, SSNs, card numbers and
+    /// phone numbers. Sampled values are only counted, never returned.
+    #[tool(
+        description = "Suggest sensitive (PII) columns by name and sampled content, with masking rules for MSSQL_MASK_COLUMNS.",
+        read_only = true
+    )]
+    pub async fn classify_columns(
+        &self,
+        input: ClassifyColumnsInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::{ResultRow, SqlValue};
+        use crate::security::masking::{
+            classify_name, detectors, SensitiveCategory, CONTENT_MATCH_THRESHOLD,
+        };
+        use std::collections::BTreeMap;
+
+        let target = match &input.table {
+            Some(t) => Some(parse_table_name(t)?),
+            None => None,
+        };
+        let schema_filter = target
+            .as_ref()
+            .map(|(schema, _)| schema.clone())
+            .or_else(|| input.schema.clone());
+
+        let mut conditions = Vec::new();
+        if let Some(schema) = &schema_filter {
+            conditions.push(format!("TABLE_SCHEMA = '{}'", schema.replace('\'', "''")));
+        }
+        if let Some((_, table)) = &target {
+            conditions.push(format!("TABLE_NAME = '{}'", table.replace('\'', "''")));
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let columns_query = format!(
+            r#"
+            SELECT TABLE_SCHEMA AS schema_name, TABLE_NAME AS table_name,
+                   COLUMN_NAME AS column_name, DATA_TYPE AS data_type
+            FROM INFORMATION_SCHEMA.COLUMNS
+            {}
+            ORDER BY TABLE_SCHEMA, TABLE_NAME, ORDINAL_POSITION
+            "#,
+            where_clause
+        );
+        let columns = match self.executor.execute(&columns_query).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Column classification query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Failed to list columns: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        if columns.rows.is_empty() {
+            return Ok(ToolOutput::error(
+                "No columns found for the given schema or table",
+            ));
+        }
+
+        // Count detector matches in the sampled text values of each column
+        let mut content: BTreeMap<String, Vec<(SensitiveCategory, f64)>> = BTreeMap::new();
+        let mut sampled_rows = 0;
+        if let Some((schema, table)) = &target {
+            let escaped_table = format!(
+                "{}.{}",
+                safe_identifier(schema)
+                    .map_err(|e| McpError::invalid_params("table", e.to_string()))?,
+                safe_identifier(table)
+                    .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+            );
+            let sample_query = format!(
+                "SELECT TOP {} * FROM {}",
+                input.sample_size.clamp(1, 10000),
+                escaped_table
+            );
+            let sample = match self.executor.execute(&sample_query).await {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("Classification sample query failed: {}", e);
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to sample data: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            sampled_rows = sample.rows.len();
+            for column in &sample.columns {
+                let values: Vec<&str> = sample
+                    .rows
+                    .iter()
+                    .filter_map(|row| match row.get(&column.name) {
+                        Some(SqlValue::String(s)) if !s.is_empty() => Some(s.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if values.is_empty() {
+                    continue;
+                }
+                for detector in detectors() {
+                    let matched = values.iter().filter(|v| detector.matches(v)).count();
+                    let rate = matched as f64 / values.len() as f64;
+                    if rate >= CONTENT_MATCH_THRESHOLD {
+                        content
+                            .entry(column.name.clone())
+                            .or_default()
+                            .push((detector.category, rate));
+                    }
+                }
+            }
+        }
+
+        let field = |row: &ResultRow, name: &str| {
+            row.get(name)
+                .map(|v| v.to_display_string())
+                .unwrap_or_default()
+        };
+        let mut sensitive = Vec::new();
+        for row in &columns.rows {
+            let (schema, table, column) = (
+                field(row, "schema_name"),
+                field(row, "table_name"),
+                field(row, "column_name"),
+            );
+            let mut categories = classify_name(&column);
+            let mut reasons: Vec<String> = categories
+                .iter()
+                .map(|c| format!("column name suggests {}", c.as_str()))
+                .collect();
+            let mut match_rate = None;
+            for (category, rate) in content.get(&column).into_iter().flatten() {
+                if !categories.contains(category) {
+                    categories.push(*category);
+                }
+                reasons.push(format!(
+                    "{:.0}% of sampled values look like {}",
+                    rate * 100.0,
+                    category.as_str()
+                ));
+                match_rate = Some(match_rate.map_or(*rate, |r: f64| r.max(*rate)));
+            }
+            let Some(primary) = categories.first() else {
+                continue;
+            };
+
+            let masked_by = self
+                .masker()
+                .strategy_for(Some((&schema, &table)), &column)
+                .map(|s| s.as_str());
+            sensitive.push(json!({
+                "schema": schema,
+                "table": table,
+                "column": column,
+                "data_type": field(row, "data_type"),
+                "categories": categories,
+                "reasons": reasons,
+                "sample_match_rate": match_rate,
+                "suggested_rule": format!(
+                    "{}.{}.{}={}",
+                    schema,
+                    table,
+                    column,
+                    primary.suggested_strategy().as_str()
+                ),
+                "masked_by": masked_by,
+            }));
+        }
+
+        let response = json!({
+            "schema": schema_filter,
+            "table": input.table,
+            "columns_scanned": columns.rows.len(),
+            "sampled_rows": sampled_rows,
+            "sensitive_column_count": sensitive.len(),
+            "sensitive_columns": sensitive,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|e| format!("Failed to serialize classification: {}", e)),
+        ))
+    }
+
+    /// Compute per-column statistics of a table on the server.
+    ///
+    /// A single aggregate pass gives null and distinct counts, min/max and
+    /// string lengths; per-column queries then add the most frequent values and
+    /// numeric histograms until the time budget runs out.
+    #[tool(
+        description = "Profile a table's columns server-side: null %, distinct count, min/max, string lengths, value patterns, top-k values and numeric histograms over a row sample, within a time budget. Returns JSON.",
+        read_only = true
+    )]
+    pub async fn profile_table(&self, input: ProfileTableInput) -> Result<ToolOutput, McpError> {
+        use crate::constants::{MAX_PROFILE_BUCKETS, MAX_PROFILE_SAMPLE_ROWS};
+        use crate::database::profile::{
+            histogram, histogram_query, top_values, top_values_query, ColumnKind,
+        };
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        let sample_rows = input.sample_rows.clamp(1, MAX_PROFILE_SAMPLE_ROWS);
+        let top_k = input.top_k.min(MAX_PROFILE_BUCKETS);
+        let buckets = input.histogram_buckets.min(MAX_PROFILE_BUCKETS);
+        let budget = std::time::Duration::from_secs(input.time_budget_secs.max(1));
+        let start = std::time::Instant::now();
+
+        let mut profile = match self
+            .table_profile(
+                &schema,
+                &table,
+                input.columns.as_deref(),
+                sample_rows,
+                Some(budget.as_secs()),
+            )
+            .await
+        {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let rows = profile.rows_profiled;
+        let mut skipped = Vec::new();
+        let mut failed = Vec::new();
+        for column in &mut profile.columns {
+            let (masked, redacted) = self.profile_redaction(&schema, &table, column);
+            let wants_top = top_k > 0 && column.kind.has_top_values() && !redacted;
+            let histogram_range = match (column.kind, &column.min, &column.max) {
+                (ColumnKind::Numeric, Some(min), Some(max)) if buckets > 0 && !masked => {
+                    min.parse::<f64>().ok().zip(max.parse::<f64>().ok())
+                }
+                _ => None,
+            };
+            if !wants_top && histogram_range.is_none() {
+                continue;
+            }
+            let Some(remaining) = budget.checked_sub(start.elapsed()) else {
+                skipped.push(column.name.clone());
+                continue;
+            };
+            let remaining = remaining.as_secs().max(1);
+
+            if wants_top {
+                let query = top_values_query(&schema, &table, &column.name, sample_rows, top_k)
+                    .map_err(|e| McpError::invalid_params("columns", e.to_string()))?;
+                match self.executor.execute_with_timeout(&query, remaining).await {
+                    Ok(result) => {
+                        column.top_values = top_values(&result.rows, column.non_null(rows))
+                    }
+                    Err(e) => {
+                        warn!("Top values query for {} failed: {}", column.name, e);
+                        failed.push(json!({"column": column.name, "error": e.to_string()}));
+                        continue;
+                    }
+                }
+            }
+            if let Some((min, max)) = histogram_range {
+                let query = histogram_query(
+                    &schema,
+                    &table,
+                    &column.name,
+                    sample_rows,
+                    min,
+                    max,
+                    buckets,
+                )
+                .map_err(|e| McpError::invalid_params("columns", e.to_string()))?;
+                let Some(query) = query else {
+                    continue;
+                };
+                match self.executor.execute_with_timeout(&query, remaining).await {
+                    Ok(result) => column.histogram = histogram(&result.rows, min, max, buckets),
+                    Err(e) => {
+                        warn!("Histogram query for {} failed: {}", column.name, e);
+                        failed.push(json!({"column": column.name, "error": e.to_string()}));
+                    }
+                }
+            }
+        }
+
+        let response = json!({
+            "table": profile.table,
+            "rows_profiled": profile.rows_profiled,
+            "sample_limit": profile.sample_limit,
+            "complete_table": profile.rows_profiled < profile.sample_limit as i64,
+            "elapsed_ms": start.elapsed().as_millis() as u64,
+            "budget_exhausted": !skipped.is_empty(),
+            "skipped_columns": skipped,
+            "failed_columns": failed,
+            "masked_values_withheld": self.masker().is_enabled(),
+            "columns": profile.columns,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|e| format!("Failed to serialize profile: {}", e)),
+        ))
+    }
+
+    // =========================================================================
+    // Bulk Operations Tools
+    // =========================================================================
+
+    /// Bulk insert data into a table.
+    #[tool(description = "Insert multiple rows into a table efficiently using batched INSERT statements. Automatically splits large datasets into batches for memory efficiency. Supports optional transaction wrapping for atomicity. Native BCP protocol support is planned for future releases.", destructive = true)]
+    pub async fn bulk_insert(
+        &self,
+        input: BulkInsertInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!(
+            "Bulk inserting {} rows into {} (native_bcp={}, transaction={}, continue_on_error={})",
+            input.rows.len(),
+            input.table,
+            input.use_native_bcp,
+            input.use_transaction,
+            input.continue_on_error
+        );
+
+        if input.rows.is_empty() {
+            return Ok(ToolOutput::error("No rows to insert"));
+        }
+
+        if input.columns.is_empty() {
+            return Ok(ToolOutput::error("No columns specified"));
+        }
+
+        // Parse and validate table name
+        let (schema, table) = parse_table_name(&input.table)?;
+        if let Some(preview) = self
+            .bulk_confirmation("bulk_insert", &input.table, input.rows.len(), &input)
+            .await
+        {
+            return Ok(preview);
+        }
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema).map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table).map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+
+        // Validate and escape column names
+        let escaped_columns: Result<Vec<String>, _> =
+            input.columns.iter().map(|c| safe_identifier(c)).collect();
+        let escaped_columns =
+            escaped_columns.map_err(|e| McpError::invalid_params("columns", e.to_string()))?;
+
+        let batch_size = input.batch_size.clamp(1, 5000);
+
+        // Check if native BCP was requested
+        // NOTE: Native BCP is not yet available in mssql-client v0.5.2
+        // The library has BCP packet building infrastructure but doesn't expose
+        // Client.bulk_insert() yet. This will be enabled in a future release.
+        let bcp_available = self.bulk_insert_manager.is_native_bcp_available();
+        if input.use_native_bcp && !bcp_available {
+            debug!("Native BCP requested but not available, using INSERT statements");
+        }
+
+        // Use batched INSERT statements
+        debug!("Using batched INSERT statements (batch_size={})", batch_size);
+
+        // Build all INSERT statements
+        let statements: Vec<String> = input
+            .rows
+            .chunks(batch_size)
+            .map(|chunk| {
+                let values: Vec<String> = chunk
+                    .iter()
+                    .map(|row| {
+                        let formatted_values: Vec<String> =
+                            row.iter().map(format_parameter_value).collect();
+                        format!("({})", formatted_values.join(", "))
+                    })
+                    .collect();
+
+                format!(
+                    "INSERT INTO {} ({}) VALUES {}",
+                    escaped_table,
+                    escaped_columns.join(", "),
+                    values.join(", ")
+                )
+            })
+            .collect();
+
+        let total_batches = statements.len();
+
+        // Execute based on transaction mode
+        if input.use_transaction {
+            // Use transactional execution for atomicity
+            let outcome = self
+                .executor
+                .execute_in_transaction(&statements, input.continue_on_error)
+                .await;
+            self.query_cache
+                .invalidate_tables(std::slice::from_ref(&table))
+                .await;
+            match outcome {
+                Ok(result) => {
+                    let response = json!({
+                        "table": input.table,
+                        "rows_requested": input.rows.len(),
+                        "rows_inserted": result.total_rows_affected,
+                        "batch_size": batch_size,
+                        "batches": total_batches,
+                        "successful_batches": result.successful_statements,
+                        "errors": result.errors,
+                        "status": if result.errors.is_empty() { "success" } else { "partial" },
+                        "execution_time_ms": result.execution_time_ms,
+                        "method": "insert_statements",
+                        "transaction": true,
+                        "native_bcp_requested": input.use_native_bcp,
+                        "native_bcp_available": bcp_available,
+                    });
+
+                    Ok(ToolOutput::text(
+                        serde_json::to_string_pretty(&response)
+                            .unwrap_or_else(|_| format!("Inserted {} rows", result.total_rows_affected)),
+                    ))
+                }
+                Err(e) => {
+                    let response = json!({
+                        "table": input.table,
+                        "rows_requested": input.rows.len(),
+                        "rows_inserted": 0,
+                        "batch_size": batch_size,
+                        "batches": total_batches,
+                        "status": "failed",
+                        "error": e.to_string(),
+                        "method": "insert_statements",
+                        "transaction": true,
+                        "rolled_back": true,
+                        "native_bcp_requested": input.use_native_bcp,
+                        "native_bcp_available": bcp_available,
+                    });
+
+                    Ok(ToolOutput::text(
+                        serde_json::to_string_pretty(&response)
+                            .unwrap_or_else(|_| format!("Insert failed: {}", e)),
+                    ))
+                }
+            }
+        } else {
+            // Non-transactional: execute each batch independently
+            let mut total_inserted: u64 = 0;
+            let mut successful_batches = 0;
+            let mut errors: Vec<String> = Vec::new();
+
+            for (idx, stmt) in statements.iter().enumerate() {
+                match self.executor.execute(stmt).await {
+                    Ok(result) => {
+                        total_inserted += result.rows_affected;
+                        successful_batches += 1;
+                    }
+                    Err(e) => {
+                        let error_msg = format!("Batch {}/{} failed: {}", idx + 1, total_batches, e);
+                        errors.push(error_msg);
+
+                        if !input.continue_on_error {
+                            // Stop on first error
+                            break;
+                        }
+                    }
+                }
+                report_progress(
+                    (idx + 1) as u64,
+                    Some(total_batches as u64),
+                    &format!("Batch {}/{}", idx + 1, total_batches),
+                );
+            }
+            self.query_cache
+                .invalidate_tables(std::slice::from_ref(&table))
+                .await;
+
+            let response = json!({
+                "table": input.table,
+                "rows_requested": input.rows.len(),
+                "rows_inserted": total_inserted,
+                "batch_size": batch_size,
+                "batches": total_batches,
+                "successful_batches": successful_batches,
+                "errors": errors,
+                "status": if errors.is_empty() { "success" } else if successful_batches > 0 { "partial" } else { "failed" },
+                "method": "insert_statements",
+                "transaction": false,
+                "native_bcp_requested": input.use_native_bcp,
+                "native_bcp_available": bcp_available,
+            });
+
+            Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| format!("Inserted {} rows", total_inserted)),
+            ))
+        }
+    }
+
+    /// Insert generated rows into a table.
+    ///
+    /// Values are chosen per column from its type and name, foreign keys draw
+    /// from sampled parent keys, and identity and computed columns are left to
+    /// the server. All batches run in one transaction.
+    #[tool(
+        description = "Insert N rows of type-aware fake data into a table (names, emails, phone numbers, amounts, dates within a range, foreign key values sampled from parent tables). Identity, computed and rowversion columns are skipped; a seed makes runs reproducible. Use dry_run to preview the column plan and first rows.",
+        destructive = true
+    )]
+    pub async fn generate_test_data(
+        &self,
+        input: GenerateTestDataInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::{
+            MAX_IMPORT_BATCH_SIZE, MAX_TEST_DATA_ROWS, TEST_DATA_PARENT_SAMPLE,
+        };
+        use crate::database::test_data::{
+            generate_row, key_literal, plan_columns, GenerationOptions, ParentKeys, SeededRng,
+        };
+        use chrono::NaiveDate;
+
+        if input.rows == 0 || input.rows > MAX_TEST_DATA_ROWS {
+            return Ok(ToolOutput::error(format!(
+                "rows must be between 1 and {}",
+                MAX_TEST_DATA_ROWS
+            )));
+        }
+        if !(0.0..=1.0).contains(&input.null_rate) {
+            return Ok(ToolOutput::error("null_rate must be between 0 and 1"));
+        }
+        let today = chrono::Utc::now().date_naive();
+        let parse_date = |value: &Option<String>, default: NaiveDate| match value {
+            Some(v) => NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("'{}' is not a YYYY-MM-DD date", v)),
+            None => Ok(default),
+        };
+        let options = match (
+            parse_date(&input.date_from, today - chrono::Duration::days(3 * 365)),
+            parse_date(&input.date_to, today),
+        ) {
+            (Ok(date_from), Ok(date_to)) if date_from <= date_to => GenerationOptions {
+                null_rate: input.null_rate,
+                date_from,
+                date_to,
+            },
+            (Ok(_), Ok(_)) => return Ok(ToolOutput::error("date_from must not be after date_to")),
+            (Err(e), _) | (_, Err(e)) => return Ok(ToolOutput::error(e)),
+        };
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+        if !input.dry_run {
+            let probe = format!("INSERT INTO {} DEFAULT VALUES", escaped_table);
+            if let Err(e) = self.validate_query(&probe) {
+                return Ok(ToolOutput::error(format!(
+                    "Inserting test data not allowed: {}",
+                    e
+                )));
+            }
+            if let Some(preview) = self
+                .bulk_confirmation("generate_test_data", &input.table, input.rows, &input)
+                .await
+            {
+                return Ok(preview);
+            }
+        }
+
+        let columns = match self.metadata.get_table_columns(&schema, &table).await {
+            Ok(c) if !c.is_empty() => c,
+            Ok(_) => {
+                return Ok(ToolOutput::error(format!(
+                    "Table not found: {}.{}",
+                    schema, table
+                )))
+            }
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to get table columns: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let primary_key = self
+            .metadata
+            .get_primary_key_columns(&schema, &table)
+            .await
+            .unwrap_or_default();
+        let foreign_keys = match self
+            .metadata
+            .list_foreign_keys(Some((&schema, &table)))
+            .await
+        {
+            Ok(fks) => fks,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to list foreign keys: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        // Sample existing parent keys for each foreign key of the table
+        let mut parents = Vec::new();
+        for fk in foreign_keys
+            .iter()
+            .filter(|fk| fk.parent_schema == schema && fk.parent_table == table)
+        {
+            let referenced: Result<Vec<String>, _> = fk
+                .referenced_columns
+                .iter()
+                .map(|c| safe_identifier(c))
+                .collect();
+            let referenced =
+                referenced.map_err(|e| McpError::invalid_params("table", e.to_string()))?;
+            let query = format!(
+                "SELECT DISTINCT TOP ({}) {} FROM {}.{} WHERE {}",
+                TEST_DATA_PARENT_SAMPLE,
+                referenced.join(", "),
+                safe_identifier(&fk.referenced_schema)
+                    .map_err(|e| McpError::invalid_params("table", e.to_string()))?,
+                safe_identifier(&fk.referenced_table)
+                    .map_err(|e| McpError::invalid_params("table", e.to_string()))?,
+                referenced
+                    .iter()
+                    .map(|c| format!("{} IS NOT NULL", c))
+                    .collect::<Vec<_>>()
+                    .join(" AND ")
+            );
+            let sample = match self.executor.execute(&query).await {
+                Ok(r) => r,
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to sample keys of {}.{}: {}",
+                        fk.referenced_schema,
+                        fk.referenced_table,
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            let keys = sample
+                .rows
+                .iter()
+                .filter_map(|row| {
+                    fk.referenced_columns
+                        .iter()
+                        .map(|c| row.get(c).and_then(key_literal))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .collect();
+            parents.push(ParentKeys {
+                columns: fk.parent_columns.clone(),
+                parent: format!("{}.{}", fk.referenced_schema, fk.referenced_table),
+                keys,
+            });
+        }
+
+        // Continue a primary key without an identity after its current maximum
+        let mut sequence_start = 1;
+        if let [key] = primary_key.as_slice() {
+            let escaped_key = safe_identifier(key)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?;
+            let query = format!(
+                "SELECT CAST(ISNULL(MAX({}), 0) AS bigint) + 1 AS next_key FROM {}",
+                escaped_key, escaped_table
+            );
+            if let Ok(result) = self.executor.execute(&query).await {
+                sequence_start = result
+                    .rows
+                    .first()
+                    .and_then(|row| row.get("next_key"))
+                    .and_then(|v| v.to_display_string().parse().ok())
+                    .unwrap_or(1);
+            }
+        }
+
+        let (generated, skipped) =
+            match plan_columns(&columns, &primary_key, &parents, sequence_start) {
+                Ok(plan) => plan,
+                Err(e) => return Ok(ToolOutput::error(e.to_string())),
+            };
+        let escaped_columns: Result<Vec<String>, _> =
+            generated.iter().map(|c| safe_identifier(&c.name)).collect();
+        let escaped_columns =
+            escaped_columns.map_err(|e| McpError::invalid_params("table", e.to_string()))?;
+
+        let seed = input.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let mut rng = SeededRng::new(seed);
+        let plan: Vec<_> = generated
+            .iter()
+            .map(|c| json!({"column": c.name, "data_type": c.data_type, "generator": format!("{:?}", c.generator)}))
+            .collect();
+
+        if input.dry_run {
+            let preview: Vec<String> = (0..input.rows.min(5))
+                .map(|row| {
+                    format!(
+                        "({})",
+                        generate_row(&mut rng, &generated, &parents, &options, row).join(", ")
+                    )
+                })
+                .collect();
+            let response = json!({
+                "table": input.table,
+                "dry_run": true,
+                "seed": seed,
+                "rows_requested": input.rows,
+                "columns": plan,
+                "skipped_columns": skipped,
+                "preview": format!(
+                    "INSERT INTO {} ({}) VALUES\n{}",
+                    escaped_table,
+                    escaped_columns.join(", "),
+                    preview.join(",\n")
+                ),
+            });
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|e| format!("Failed to serialize preview: {}", e)),
+            ));
+        }
+
+        let batch_size = input.batch_size.clamp(1, MAX_IMPORT_BATCH_SIZE);
+        let mut statements = Vec::new();
+        let mut row = 0;
+        while row < input.rows {
+            let end = (row + batch_size).min(input.rows);
+            let values: Vec<String> = (row..end)
+                .map(|r| {
+                    format!(
+                        "({})",
+                        generate_row(&mut rng, &generated, &parents, &options, r).join(", ")
+                    )
+                })
+                .collect();
+            statements.push(format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                escaped_table,
+                escaped_columns.join(", "),
+                values.join(", ")
+            ));
+            row = end;
+        }
+
+        let outcome = self
+            .executor
+            .execute_in_transaction(&statements, false)
+            .await;
+        self.query_cache
+            .invalidate_tables(std::slice::from_ref(&table))
+            .await;
+        let response = match outcome {
+            Ok(result) => json!({
+                "table": input.table,
+                "status": if result.errors.is_empty() { "success" } else { "failed" },
+                "seed": seed,
+                "rows_requested": input.rows,
+                "rows_inserted": result.total_rows_affected,
+                "batches": statements.len(),
+                "errors": result.errors,
+                "execution_time_ms": result.execution_time_ms,
+                "columns": plan,
+                "skipped_columns": skipped,
+            }),
+            Err(e) => json!({
+                "table": input.table,
+                "status": "failed",
+                "seed": seed,
+                "rows_requested": input.rows,
+                "rows_inserted": 0,
+                "rolled_back": true,
+                "error": self.localize_error(&e).await,
+            }),
+        };
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|e| format!("Failed to serialize result: {}", e)),
+        ))
+    }
+
+    /// Scrub columns of a table in place.
+    ///
+    /// Shuffles run first as one statement per column; hash, fake and nullify
+    /// rules are applied in key order, one transaction per batch, so an
+    /// interrupted run leaves earlier batches scrubbed.
+    #[tool(
+        description = "Scrub selected columns of a table in place for sanitized copies of production data. Rules per column: hash (salted SHA-256, computed on the server), shuffle (permute values between rows), fake (type-aware generated values) or nullify. Rows are updated in batches, each in its own transaction. Runs as a dry run by default, returning the affected row count and before/after samples.",
+        destructive = true
+    )]
+    pub async fn anonymize_table(
+        &self,
+        input: AnonymizeTableInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_IMPORT_BATCH_SIZE;
+        use crate::database::anonymize::{
+            hash_expression, key_batch_query, plan_rules, shuffle_statement, update_statement,
+            ScrubStrategy,
+        };
+        use crate::database::test_data::{
+            generate_value, key_literal, GenerationOptions, SeededRng,
+        };
+        use crate::database::ResultRow;
+
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+        let filter = input.filter.as_deref().filter(|f| !f.trim().is_empty());
+
+        let columns = match self.metadata.get_table_columns(&schema, &table).await {
+            Ok(c) if !c.is_empty() => c,
+            Ok(_) => {
+                return Ok(ToolOutput::error(format!(
+                    "Table not found: {}.{}",
+                    schema, table
+                )))
+            }
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to get table columns: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let key_columns = match &input.key_columns {
+            Some(keys) if !keys.is_empty() => {
+                let mut resolved = Vec::with_capacity(keys.len());
+                for key in keys {
+                    match columns
+                        .iter()
+                        .find(|c| c.column_name.eq_ignore_ascii_case(key))
+                    {
+                        Some(c) => resolved.push(c.column_name.clone()),
+                        None => {
+                            return Ok(ToolOutput::error(format!("Key column not found: {}", key)))
+                        }
+                    }
+                }
+                resolved
+            }
+            _ => self
+                .metadata
+                .get_primary_key_columns(&schema, &table)
+                .await
+                .unwrap_or_default(),
+        };
+        if key_columns.is_empty() {
+            return Ok(ToolOutput::error(format!(
+                "{}.{} has no primary key; pass key_columns identifying each row",
+                schema, table
+            )));
+        }
+        let rules = match plan_rules(&columns, &key_columns, &input.rules) {
+            Ok(rules) => rules,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+
+        // The dry run's salt must be passed back, or the real run's hashes
+        // wouldn't match the ones previewed
+        let hashed = rules.iter().any(|r| r.strategy == ScrubStrategy::Hash);
+        if hashed && !input.dry_run && input.salt.is_none() {
+            return Ok(ToolOutput::error(
+                "Hash rules need a salt: pass the generated_salt of the dry run (or your own salt)",
+            ));
+        }
+
+        // Check the statements against the safety settings before reading anything
+        let first = safe_identifier(&rules[0].column)
+            .map_err(|e| McpError::invalid_params("rules", e.to_string()))?;
+        let probe = format!(
+            "UPDATE {} SET {} = {}{}",
+            escaped_table,
+            first,
+            first,
+            filter.map(|f| format!(" WHERE {}", f)).unwrap_or_default()
+        );
+        if let Err(e) = self.validate_query(&probe) {
+            return Ok(ToolOutput::error(format!(
+                "Anonymizing {} not allowed: {}",
+                input.table, e
+            )));
+        }
+        if !input.dry_run {
+            if let Some(preview) = self
+                .confirmation_preview("anonymize_table", &probe, &input)
+                .await
+            {
+                return Ok(preview);
+            }
+        }
+
+        // Unsalted hashes of low-cardinality values are reversible by lookup,
+        // so a dry run without a salt gets a random one, reported in the result.
+        // The salt is bound as a parameter to keep it out of the query logs.
+        let generated_salt =
+            (hashed && input.salt.is_none()).then(|| uuid::Uuid::new_v4().simple().to_string());
+        let salt = input
+            .salt
+            .clone()
+            .or_else(|| generated_salt.clone())
+            .unwrap_or_default();
+        let seed = input.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let mut rng = SeededRng::new(seed);
+        let today = chrono::Utc::now().date_naive();
+        let options = GenerationOptions {
+            null_rate: 0.0,
+            date_from: today - chrono::Duration::days(3 * 365),
+            date_to: today,
+        };
+        let fake_row = |rng: &mut SeededRng, row: usize| -> Vec<String> {
+            rules
+                .iter()
+                .filter_map(|r| r.generator.as_ref())
+                .map(|c| generate_value(rng, c, &options, row))
+                .collect()
+        };
+        let shuffles = rules
+            .iter()
+            .filter(|r| r.strategy == ScrubStrategy::Shuffle)
+            .map(|r| shuffle_statement(&escaped_table, &key_columns, &r.column, filter))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| McpError::invalid_params("rules", e.to_string()))?;
+        let batched = rules.iter().any(|r| r.strategy != ScrubStrategy::Shuffle);
+        let plan: Vec<_> = rules
+            .iter()
+            .map(|r| json!({"column": r.column, "strategy": r.strategy}))
+            .collect();
+        let row_key = |row: &ResultRow| -> Option<Vec<String>> {
+            key_columns
+                .iter()
+                .map(|k| row.get(k).and_then(key_literal))
+                .collect()
+        };
+
+        if input.dry_run {
+            let count_query = format!(
+                "SELECT COUNT_BIG(*) AS row_count FROM {}{}",
+                escaped_table,
+                filter.map(|f| format!(" WHERE {}", f)).unwrap_or_default()
+            );
+            let rows_matched = match self.executor.execute(&count_query).await {
+                Ok(result) => result
+                    .rows
+                    .first()
+                    .and_then(|row| row.get("row_count"))
+                    .and_then(|v| v.to_display_string().parse::<i64>().ok()),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to count rows: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+
+            // Before values, with hashes computed by the server for the after values
+            let mut select = Vec::new();
+            for column in key_columns.iter().chain(rules.iter().map(|r| &r.column)) {
+                select.push(format!(
+                    "t.{}",
+                    safe_identifier(column)
+                        .map_err(|e| McpError::invalid_params("rules", e.to_string()))?
+                ));
+            }
+            for (i, rule) in rules.iter().enumerate() {
+                if rule.strategy == ScrubStrategy::Hash {
+                    let expression = hash_expression(rule)
+                        .map_err(|e| McpError::invalid_params("rules", e.to_string()))?;
+                    select.push(format!("{} AS [__hash_{}]", expression, i));
+                }
+            }
+            let sample_query = format!(
+                "SELECT TOP (5) {} FROM {} AS t{} ORDER BY {}",
+                select.join(", "),
+                escaped_table,
+                filter.map(|f| format!(" WHERE {}", f)).unwrap_or_default(),
+                select[..key_columns.len()].join(", ")
+            );
+            let mut sample = match self
+                .executor
+                .execute_with_params(&sample_query, &[&salt], 5)
+                .await
+            {
+                Ok(result) => result.rows,
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to read sample rows: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            let hashes: Vec<Vec<Option<String>>> = sample
+                .iter()
+                .map(|row| {
+                    (0..rules.len())
+                        .map(|i| {
+                            row.get(&format!("__hash_{}", i))
+                                .map(|v| v.to_display_string())
+                        })
+                        .collect()
+                })
+                .collect();
+            self.masker()
+                .mask_rows(&mut sample, Some((&schema, &table)));
+
+            let mut samples = Vec::with_capacity(sample.len());
+            let mut sample_keys = Vec::new();
+            let mut sample_fakes = Vec::new();
+            for (n, row) in sample.iter().enumerate() {
+                let fakes = fake_row(&mut rng, n);
+                let mut fake = fakes.iter();
+                let mut key = serde_json::Map::new();
+                let mut before = serde_json::Map::new();
+                let mut after = serde_json::Map::new();
+                for column in &key_columns {
+                    key.insert(column.clone(), json!(row.get(column)));
+                }
+                for (i, rule) in rules.iter().enumerate() {
+                    before.insert(rule.column.clone(), json!(row.get(&rule.column)));
+                    let value = match rule.strategy {
+                        ScrubStrategy::Hash => json!(hashes[n][i]),
+                        ScrubStrategy::Shuffle => json!("(value from another row)"),
+                        ScrubStrategy::Fake => json!(fake.next()),
+                        ScrubStrategy::Nullify => serde_json::Value::Null,
+                    };
+                    after.insert(rule.column.clone(), value);
+                }
+                samples.push(json!({"key": key, "before": before, "after": after}));
+                if let Some(literals) = row_key(row) {
+                    sample_keys.push(literals);
+                    sample_fakes.push(fakes);
+                }
+            }
+
+            let mut statements = shuffles.clone();
+            if batched && !sample_keys.is_empty() {
+                statements.push(
+                    update_statement(
+                        &escaped_table,
+                        &key_columns,
+                        &rules,
+                        &sample_keys,
+                        &sample_fakes,
+                    )
+                    .map_err(|e| McpError::invalid_params("rules", e.to_string()))?,
+                );
+            }
+            let response = json!({
+                "table": input.table,
+                "dry_run": true,
+                "seed": seed,
+                "generated_salt": generated_salt,
+                "key_columns": key_columns,
+                "rules": plan,
+                "rows_matched": rows_matched,
+                "samples": samples,
+                "statements": statements,
+            });
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|e| format!("Failed to serialize preview: {}", e)),
+            ));
+        }
+
+        let batch_size = input.batch_size.clamp(1, MAX_IMPORT_BATCH_SIZE);
+        let start = std::time::Instant::now();
+        let mut rows_updated: u64 = 0;
+        let mut batches = 0;
+        let mut error = None;
+        for statement in &shuffles {
+            match self
+                .executor
+                .execute_in_transaction(std::slice::from_ref(statement), false)
+                .await
+            {
+                Ok(result) => rows_updated = rows_updated.max(result.total_rows_affected),
+                Err(e) => {
+                    error = Some(self.localize_error(&e).await);
+                    break;
+                }
+            }
+        }
+
+        let mut after: Option<Vec<String>> = None;
+        let mut row = 0;
+        while batched && error.is_none() {
+            let query = key_batch_query(
+                &escaped_table,
+                &key_columns,
+                filter,
+                after.as_deref(),
+                batch_size,
+            )
+            .map_err(|e| McpError::invalid_params("table", e.to_string()))?;
+            let keys = match self.executor.execute(&query).await {
+                Ok(result) => result.rows.iter().map(&row_key).collect::<Option<Vec<_>>>(),
+                Err(e) => {
+                    error = Some(self.localize_error(&e).await);
+                    break;
+                }
+            };
+            let Some(keys) = keys else {
+                error = Some("Key columns must not contain NULL or unsupported types".to_string());
+                break;
+            };
+            let Some(last) = keys.last().cloned() else {
+                break;
+            };
+            let fakes: Vec<Vec<String>> = (0..keys.len())
+                .map(|i| fake_row(&mut rng, row + i))
+                .collect();
+            let statement = update_statement(&escaped_table, &key_columns, &rules, &keys, &fakes)
+                .map_err(|e| McpError::invalid_params("rules", e.to_string()))?;
+            match self
+                .executor
+                .execute_in_transaction_with_params(&[statement], &[&salt], false)
+                .await
+            {
+                Ok(result) => {
+                    batches += 1;
+                    rows_updated = rows_updated.max(row as u64 + result.total_rows_affected);
+                }
+                Err(e) => {
+                    error = Some(self.localize_error(&e).await);
+                    break;
+                }
+            }
+            row += keys.len();
+            after = Some(last);
+        }
+
+        self.query_cache
+            .invalidate_tables(std::slice::from_ref(&table))
+            .await;
+        let response = json!({
+            "table": input.table,
+            "status": if error.is_none() { "success" } else { "failed" },
+            "seed": seed,
+            "key_columns": key_columns,
+            "rules": plan,
+            "rows_updated": rows_updated,
+            "shuffled_columns": shuffles.len(),
+            "batches": batches,
+            "error": error,
+            "execution_time_ms": start.elapsed().as_millis() as u64,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|e| format!("Failed to serialize result: {}", e)),
+        ))
+    }
+
+    /// Import a CSV or JSON Lines file into a table.
+    #[tool(
+        description = "Import a CSV or JSON Lines file from an allowed import directory (MSSQL_IMPORT_DIRS) into a table. Columns are matched by name or an explicit column_mapping, every value is validated against the target column types before loading, and invalid rows are reported by line number. Supports dry-run validation, batched transactional loads, and an optional staging table.",
+        destructive = true
+    )]
+    pub async fn import_data(&self, input: ImportDataInput) -> Result<ToolOutput, McpError> {
+        debug!(
+            "Importing {} into {} (dry_run={}, transaction={}, staging={})",
+            input.source_path,
+            input.table,
+            input.dry_run,
+            input.use_transaction,
+            input.use_staging_table
+        );
+        use crate::constants::{MAX_IMPORT_BAD_ROWS_REPORTED, MAX_IMPORT_BATCH_SIZE};
+        let start = std::time::Instant::now();
+
+        let source = match import::resolve_source(
+            &input.source_path,
+            &self.config().security.import_directories,
+        ) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let format = match import::detect_format(&source, input.format) {
+            Ok(format) => format,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let mut delimiter = input.delimiter.chars();
+        let delimiter = match (delimiter.next(), delimiter.next()) {
+            (Some(c), None) if !matches!(c, '"' | '\r' | '\n') => c,
+            _ => {
+                return Ok(ToolOutput::error(
+                    "delimiter must be a single character other than a quote or line break",
+                ))
+            }
+        };
+
+        // Parse and validate table name
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+
+        // Loading writes to the table, so it must pass the configured validation mode
+        if !input.dry_run {
+            let probe = format!("INSERT INTO {} DEFAULT VALUES", escaped_table);
+            if let Err(e) = self.validate_query(&probe) {
+                return Ok(ToolOutput::error(format!("Import not allowed: {}", e)));
+            }
+        }
+
+        let current_db = {
+            let state = self.state.read().await;
+            state.current_database().map(|s| s.to_string())
+        };
+
+        let columns_query = import::target_columns_query(&schema, &table);
+        let columns_query = match &current_db {
+            Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), columns_query),
+            None => columns_query,
+        };
+        let targets: Vec<TargetColumn> = match self.executor.execute(&columns_query).await {
+            Ok(result) => result.rows.iter().map(TargetColumn::from_row).collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read columns of {}: {}",
+                    input.table, e
+                )));
+            }
+        };
+        if targets.is_empty() {
+            return Ok(ToolOutput::error(format!(
+                "Table '{}' not found",
+                input.table
+            )));
+        }
+
+        let open_source = || SourceReader::open(&source, format, input.has_header, delimiter);
+        let mut reader = match open_source() {
+            Ok(reader) => reader,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let positional = format == ImportFormat::Csv && !input.has_header;
+        let plan = match ImportPlan::build(
+            reader.headers(),
+            &targets,
+            &input.column_mapping,
+            positional,
+        ) {
+            Ok(plan) => plan,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+
+        // Validate every record before anything is loaded
+        let mut rows_read = 0usize;
+        let mut bad_row_count = 0usize;
+        let mut bad_rows = Vec::new();
+        loop {
+            let record = match reader.next_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => return Ok(ToolOutput::error(e.to_string())),
+            };
+            rows_read += 1;
+            if let Err(bad_row) = plan.convert(&record, input.empty_as_null) {
+                bad_row_count += 1;
+                if bad_rows.len() < MAX_IMPORT_BAD_ROWS_REPORTED {
+                    bad_rows.push(bad_row);
+                }
+            }
+        }
+
+        let columns: Vec<_> = plan
+            .mappings
+            .iter()
+            .map(|m| json!({"source": m.source, "target": m.target.name, "type": m.target.data_type}))
+            .collect();
+        let mut response = json!({
+            "source": source.display().to_string(),
+            "format": format.as_str(),
+            "table": input.table,
+            "columns": columns,
+            "ignored_columns": plan.ignored_columns,
+            "rows_read": rows_read,
+            "valid_rows": rows_read - bad_row_count,
+            "bad_row_count": bad_row_count,
+            "bad_rows": bad_rows,
+            "dry_run": input.dry_run,
+        });
+
+        if input.dry_run || bad_row_count > input.max_bad_rows {
+            response["rows_loaded"] = json!(0);
+            if input.dry_run {
+                response["status"] = json!("validated");
+            } else {
+                response["status"] = json!("rejected");
+                response["error"] = json!(format!(
+                    "{} invalid rows exceed max_bad_rows ({}); nothing was loaded",
+                    bad_row_count, input.max_bad_rows
+                ));
+            }
+            response["execution_time_ms"] = json!(start.elapsed().as_millis() as u64);
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| format!("Validated {} rows", rows_read)),
+            ));
+        }
+
+        // Read the file again and load the valid records
+        let mut reader = match open_source() {
+            Ok(reader) => reader,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        let batch_size = input.batch_size.clamp(1, MAX_IMPORT_BATCH_SIZE);
+        let outcome = self
+            .load_import(
+                &mut reader,
+                &plan,
+                &input,
+                &schema,
+                &table,
+                current_db.as_deref(),
+            )
+            .await;
+        self.query_cache
+            .invalidate_tables(std::slice::from_ref(&table))
+            .await;
+
+        response["batch_size"] = json!(batch_size);
+        response["method"] = json!("insert_statements");
+        response["transaction"] = json!(input.use_transaction);
+        response["staging_table"] = json!(input.use_staging_table);
+        match outcome {
+            Ok((rows_loaded, batches)) => {
+                response["status"] = json!("success");
+                response["rows_loaded"] = json!(rows_loaded);
+                response["batches"] = json!(batches);
+            }
+            Err((rows_loaded, e)) => {
+                response["status"] = json!(if rows_loaded > 0 { "partial" } else { "failed" });
+                response["rows_loaded"] = json!(rows_loaded);
+                response["error"] = json!(e.to_string());
+                response["rolled_back"] = json!(input.use_transaction);
+            }
+        }
+        response["execution_time_ms"] = json!(start.elapsed().as_millis() as u64);
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Imported {} rows", rows_read - bad_row_count)),
+        ))
+    }
+
+    /// Export query results to various formats.
+    #[tool(
+        description = "Export query results in CSV, JSON, or JSON Lines format, inline or streamed to files (destination_path) with optional gzip compression and chunking, or as an Excel (xlsx) workbook file with one worksheet per result set.",
+        read_only = true
+    )]
+    pub async fn export_data(&self, input: ExportDataInput) -> Result<ToolOutput, McpError> {
+        debug!("Exporting data: {}", truncate_for_log(&input.query, 100));
+
+        // Validate query
+        if let Err(e) = self.validate_query(&input.query) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+
+        if let Some(destination) = &input.destination_path {
+            let path = match export::resolve_destination(
+                destination,
+                &self.config().security.export_directories,
+            ) {
+                Ok(p) => p,
+                Err(e) => return Ok(ToolOutput::error(e.to_string())),
+            };
+            if input.max_rows_per_file == Some(0) || input.max_bytes_per_file == Some(0) {
+                return Ok(ToolOutput::error(
+                    "max_rows_per_file and max_bytes_per_file must be greater than 0",
+                ));
+            }
+
+            if input.format == ExportFormat::Xlsx {
+                return Ok(self.export_xlsx(&input, &path).await);
+            }
+
+            let mut writer = ChunkedFileWriter::new(
+                path.clone(),
+                FileExportOptions {
+                    format: input.format,
+                    include_headers: input.include_headers,
+                    compress: input.compress,
+                    max_rows_per_file: input.max_rows_per_file,
+                    max_bytes_per_file: input.max_bytes_per_file,
+                    overwrite: input.overwrite,
+                },
+            );
+
+            // Files are not returned inline, so the result row limit does not apply
+            let max_rows = input.max_rows.unwrap_or(usize::MAX);
+            let masker = self.masker();
+            let summary = match self
+                .executor
+                .stream_rows(&input.query, max_rows, |columns, row| {
+                    if masker.is_enabled() {
+                        let mut row = row.clone();
+                        masker.mask_row(columns, &mut row, None);
+                        writer.write_row(columns, &row)
+                    } else {
+                        writer.write_row(columns, row)
+                    }
+                })
+                .await
+            {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("File export failed: {}", e);
+                    writer.abort();
+                    return Ok(ToolOutput::error(format!(
+                        "Export failed: {}",
+                        self.localize_error(&e).await
+                    )));
+                }
+            };
+            let files = match writer.finish(&summary.columns) {
+                Ok(f) => f,
+                Err(e) => return Ok(ToolOutput::error(format!("Export failed: {}", e))),
+            };
+
+            info!(
+                "Exported {} rows to {} file(s) at {}",
+                summary.row_count,
+                files.len(),
+                path.display()
+            );
+
+            let response = json!({
+                "format": input.format.as_str(),
+                "destination": path.display().to_string(),
+                "compressed": input.compress,
+                "row_count": summary.row_count,
+                "column_count": summary.columns.len(),
+                "truncated": summary.truncated,
+                "execution_time_ms": summary.execution_time_ms,
+                "files": files,
+            });
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                    warn!("Failed to serialize export response: {}", e);
+                    format!("Export failed: {}", e)
+                }),
+            ));
+        }
+
+        const XLSX_NEEDS_FILE: &str =
+            "xlsx export writes a workbook file; set destination_path to a file inside MSSQL_EXPORT_DIRS";
+        if input.format == ExportFormat::Xlsx {
+            return Ok(ToolOutput::error(XLSX_NEEDS_FILE));
+        }
+
+        let max_rows = input
+            .max_rows
+            .unwrap_or(self.config().security.max_result_rows);
+
+        let mut result = match self
+            .executor
+            .execute_with_limit(&input.query, max_rows)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Export query failed: {}", e);
+                return Ok(ToolOutput::error(format!(
+                    "Query execution failed: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        self.masker().mask_result(&mut result, None);
+
+        let output = match input.format {
+            ExportFormat::Json => serde_json::to_string_pretty(&result).unwrap_or_else(|e| {
+                warn!("Failed to serialize export to JSON: {}", e);
+                format!("Failed to serialize result: {}", e)
+            }),
+            ExportFormat::JsonLines => {
+                // One JSON object per line
+                result
+                    .rows
+                    .iter()
+                    .filter_map(|row| {
+                        serde_json::to_string(&row.columns)
+                            .map_err(|e| {
+                                warn!("Failed to serialize row to JSON Lines: {}", e);
+                                e
+                            })
+                            .ok()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            ExportFormat::Xlsx => return Ok(ToolOutput::error(XLSX_NEEDS_FILE)),
+            ExportFormat::Csv => {
+                // CSV with optional headers
+                let mut csv_output = String::new();
+                if input.include_headers && !result.columns.is_empty() {
+                    csv_output.push_str(
+                        &result
+                            .columns
+                            .iter()
+                            .map(|c| c.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    );
+                    csv_output.push('\n');
+                }
+                csv_output.push_str(&result.to_csv());
+                // Remove duplicate header if to_csv includes it
+                if input.include_headers {
+                    csv_output
+                } else {
+                    // Skip first line if to_csv added header
+                    let lines: Vec<&str> = csv_output.lines().skip(1).collect();
+                    lines.join("\n")
+                }
+            }
+        };
+
+        let response = json!({
+            "format": input.format.as_str(),
+            "row_count": result.rows.len(),
+            "column_count": result.columns.len(),
+            "truncated": result.truncated,
+            "data": output,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+                warn!("Failed to serialize export response: {}", e);
+                format!("Export failed: {}", e)
+            }),
+        ))
+    }
+
+    /// Download a single binary cell to a file.
+    ///
+    /// The cell is read in `BLOB_CHUNK_SIZE` pieces with `SUBSTRING`, so large
+    /// documents and images are never held in memory whole. Columns covered
+    /// by a masking rule are refused.
+    #[tool(
+        description = "Download one binary (varbinary/image) cell to a file under MSSQL_EXPORT_DIRS, reading it in chunks so large documents and images are not loaded into memory at once. The filter must match exactly one row.",
+        read_only = true
+    )]
+    pub async fn download_blob(&self, input: DownloadBlobInput) -> Result<ToolOutput, McpError> {
+        use crate::database::blob::blob_info_query;
+
+        let start = std::time::Instant::now();
+        let (schema, table) = parse_table_name(&input.table)?;
+        let escaped_table = format!(
+            "{}.{}",
+            safe_identifier(&schema)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?,
+            safe_identifier(&table)
+                .map_err(|e| McpError::invalid_params("table", e.to_string()))?
+        );
+        let column = safe_identifier(&input.column)
+            .map_err(|e| McpError::invalid_params("column", e.to_string()))?;
+        let filter = input.filter.trim();
+        if filter.is_empty() {
+            return Ok(ToolOutput::error(
+                "filter must identify the row to download",
+            ));
+        }
+        if self
+            .masker()
+            .strategy_for(Some((&schema, &table)), &input.column)
+            .is_some()
+        {
+            return Ok(ToolOutput::error(format!(
+                "{}.{}.{} is covered by a masking rule and cannot be downloaded",
+                schema, table, input.column
+            )));
+        }
+
+        let destination = match export::resolve_destination(
+            &input.destination,
+            &self.config().security.export_directories,
+        ) {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolOutput::error(e.to_string())),
+        };
+        if destination.is_dir() {
+            return Ok(ToolOutput::error(format!(
+                "'{}' is a directory; destination needs a file name",
+                destination.display()
+            )));
+        }
+        if destination.exists() && !input.overwrite {
+            return Ok(ToolOutput::error(format!(
+                "'{}' already exists; set overwrite to replace it",
+                destination.display()
+            )));
+        }
+
+        let info_query = blob_info_query(&escaped_table, &column, filter);
+        if let Err(e) = self.validate_query(&info_query) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+        let info = match self.executor.execute(&info_query).await {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read the cell: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let number = |name: &str| {
+            info.rows
+                .first()
+                .and_then(|row| row.get(name))
+                .and_then(|v| v.to_display_string().parse::<usize>().ok())
+        };
+        match number("row_count").unwrap_or(0) {
+            1 => {}
+            n => {
+                return Ok(ToolOutput::error(format!(
+                    "filter matched {} rows; it must identify exactly one row",
+                    n
+                )));
+            }
+        }
+        let Some(byte_count) = number("byte_count") else {
+            return Ok(ToolOutput::error(format!(
+                "{} is NULL in the matched row",
+                column
+            )));
+        };
+
+        let chunks = match self
+            .write_blob(&escaped_table, &column, filter, byte_count, &destination)
+            .await
+        {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                let _ = std::fs::remove_file(&destination);
+                return Ok(ToolOutput::error(format!("Download failed: {}", e)));
+            }
+        };
+
+        info!(
+            "Downloaded {} bytes from {}.{} to {}",
+            byte_count,
+            escaped_table,
+            column,
+            destination.display()
+        );
+
+        let response = json!({
+            "table": format!("{}.{}", schema, table),
+            "column": input.column,
+            "destination": destination.display().to_string(),
+            "bytes": byte_count,
+            "chunks": chunks,
+            "execution_time_ms": start.elapsed().as_millis() as u64,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Downloaded {} bytes", byte_count)),
+        ))
+    }
+
+    // =========================================================================
+    // Server Metrics Tools
+    // =========================================================================
+
+    /// Get server performance metrics.
+    #[tool(description = "Get SQL Server performance metrics including connections, queries, and memory usage.", read_only = true, idempotent = true)]
+    pub async fn get_metrics(
+        &self,
+        input: GetMetricsInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!(
+            "Getting server metrics for categories: {}",
+            input.categories
+        );
+
+        let mut metrics = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+
+        let include_all = input.categories == "all";
+
+        // Connection metrics
+        if include_all || input.categories.contains("connections") {
+            let conn_query = r#"
+                SELECT
+                    COUNT(*) AS total_connections,
+                    SUM(CASE WHEN status = 'running' THEN 1 ELSE 0 END) AS active_connections,
+                    SUM(CASE WHEN status = 'sleeping' THEN 1 ELSE 0 END) AS idle_connections
+                FROM sys.dm_exec_sessions
+                WHERE is_user_process = 1
+            "#;
+
+            if let Ok(result) = self.executor.execute(conn_query).await {
+                if let Some(row) = result.rows.first() {
+                    metrics["connections"] = json!({
+                        "total": row.get("total_connections").map(|v| v.to_display_string()),
+                        "active": row.get("active_connections").map(|v| v.to_display_string()),
+                        "idle": row.get("idle_connections").map(|v| v.to_display_string()),
+                    });
+                }
+            }
+        }
+
+        // Query metrics
+        if include_all || input.categories.contains("queries") {
+            let query_stats = format!(
+                r#"
+                SELECT TOP 10
+                    SUBSTRING(qt.text, 1, 200) AS query_text,
+                    qs.execution_count,
+                    qs.total_worker_time / 1000 AS total_cpu_ms,
+                    qs.total_elapsed_time / 1000 AS total_duration_ms,
+                    qs.total_logical_reads,
+                    qs.total_physical_reads,
+                    qs.last_execution_time
+                FROM sys.dm_exec_query_stats qs
+                CROSS APPLY sys.dm_exec_sql_text(qs.sql_handle) qt
+                WHERE qs.last_execution_time > DATEADD(minute, -{}, GETDATE())
+                ORDER BY qs.total_elapsed_time DESC
+            "#,
+                input.time_range_minutes
+            );
+
+            if let Ok(result) = self.executor.execute(&query_stats).await {
+                let top_queries: Vec<serde_json::Value> = result
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        json!({
+                            "query": row.get("query_text").map(|v| v.to_display_string()),
+                            "executions": row.get("execution_count").map(|v| v.to_display_string()),
+                            "total_cpu_ms": row.get("total_cpu_ms").map(|v| v.to_display_string()),
+                            "total_duration_ms": row.get("total_duration_ms").map(|v| v.to_display_string()),
+                        })
+                    })
+                    .collect();
+                metrics["top_queries"] = json!(top_queries);
+            }
+        }
+
+        // Memory metrics
+        if include_all || input.categories.contains("memory") {
+            let memory_query = r#"
+                SELECT
+                    physical_memory_in_use_kb / 1024 AS memory_used_mb,
+                    locked_page_allocations_kb / 1024 AS locked_pages_mb,
+                    large_page_allocations_kb / 1024 AS large_pages_mb,
+                    total_virtual_address_space_kb / 1024 AS virtual_space_mb,
+                    available_commit_limit_kb / 1024 AS available_commit_mb
+                FROM sys.dm_os_process_memory
+            "#;
+
+            if let Ok(result) = self.executor.execute(memory_query).await {
+                if let Some(row) = result.rows.first() {
+                    metrics["memory"] = json!({
+                        "used_mb": row.get("memory_used_mb").map(|v| v.to_display_string()),
+                        "locked_pages_mb": row.get("locked_pages_mb").map(|v| v.to_display_string()),
+                        "virtual_space_mb": row.get("virtual_space_mb").map(|v| v.to_display_string()),
+                    });
+                }
+            }
+        }
+
+        // Performance counters
+        if include_all || input.categories.contains("performance") {
+            let perf_query = r#"
+                SELECT
+                    object_name,
+                    counter_name,
+                    cntr_value
+                FROM sys.dm_os_performance_counters
+                WHERE counter_name IN (
+                    'Page life expectancy',
+                    'Buffer cache hit ratio',
+                    'Batch Requests/sec',
+                    'SQL Compilations/sec',
+                    'SQL Re-Compilations/sec',
+                    'User Connections'
+                )
+            "#;
+
+            if let Ok(result) = self.executor.execute(perf_query).await {
+                let counters: Vec<serde_json::Value> = result
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        json!({
+                            "counter": row.get("counter_name").map(|v| v.to_display_string()),
+                            "value": row.get("cntr_value").map(|v| v.to_display_string()),
+                        })
+                    })
+                    .collect();
+                metrics["performance_counters"] = json!(counters);
+            }
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&metrics)
+                .unwrap_or_else(|_| "Failed to get metrics".to_string()),
+        ))
+    }
+
+    /// Analyze a query for performance issues.
+    #[tool(description = "Analyze a SQL query for performance issues and optimization opportunities.", read_only = true, idempotent = true)]
+    pub async fn analyze_query(
+        &self,
+        input: AnalyzeQueryInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!("Analyzing query: {}", truncate_for_log(&input.query, 100));
+
+        let mut analysis = json!({
+            "query": truncate_for_log(&input.query, 500),
+            "analysis": {},
+        });
+
+        // Get estimated execution plan as XML
+        let plan_query = format!(
+            "SET SHOWPLAN_XML ON; EXEC sp_executesql N'{}'; SET SHOWPLAN_XML OFF;",
+            input.query.replace('\'', "''")
+        );
+
+        // Try to get plan info
+        if let Ok(plan_result) = self.executor.execute(&plan_query).await {
+            if !plan_result.rows.is_empty() {
+                analysis["execution_plan"] = json!({
+                    "status": "retrieved",
+                    "note": "Execution plan retrieved successfully"
+                });
+            }
+        }
+
+        // Get table statistics if requested
+        if input.include_statistics {
+            // Extract table names from query (simple heuristic)
+            let stats_query = r#"
+                SELECT
+                    OBJECT_SCHEMA_NAME(s.object_id) AS schema_name,
+                    OBJECT_NAME(s.object_id) AS table_name,
+                    s.name AS stats_name,
+                    STATS_DATE(s.object_id, s.stats_id) AS last_updated,
+                    sp.rows,
+                    sp.modification_counter
+                FROM sys.stats s
+                CROSS APPLY sys.dm_db_stats_properties(s.object_id, s.stats_id) sp
+                WHERE OBJECT_SCHEMA_NAME(s.object_id) NOT IN ('sys', 'INFORMATION_SCHEMA')
+                ORDER BY sp.modification_counter DESC
+            "#;
+
+            if let Ok(result) = self.executor.execute(stats_query).await {
+                let stats: Vec<serde_json::Value> = result
+                    .rows
+                    .iter()
+                    .take(20)
+                    .map(|row| {
+                        json!({
+                            "schema": row.get("schema_name").map(|v| v.to_display_string()),
+                            "table": row.get("table_name").map(|v| v.to_display_string()),
+                            "stats_name": row.get("stats_name").map(|v| v.to_display_string()),
+                            "last_updated": row.get("last_updated").map(|v| v.to_display_string()),
+                            "rows": row.get("rows").map(|v| v.to_display_string()),
+                            "modifications": row.get("modification_counter").map(|v| v.to_display_string()),
+                        })
+                    })
+                    .collect();
+                analysis["statistics"] = json!(stats);
+            }
+        }
+
+        // Check for missing indexes
+        if input.include_index_analysis {
+            let missing_query = r#"
+                SELECT TOP 10
+                    mid.statement AS table_name,
+                    mid.equality_columns,
+                    mid.inequality_columns,
+                    mid.included_columns,
+                    CONVERT(DECIMAL(10,2), migs.avg_total_user_cost * migs.avg_user_impact * (migs.user_seeks + migs.user_scans)) AS impact
+                FROM sys.dm_db_missing_index_groups mig
+                INNER JOIN sys.dm_db_missing_index_group_stats migs ON migs.group_handle = mig.index_group_handle
+                INNER JOIN sys.dm_db_missing_index_details mid ON mig.index_handle = mid.index_handle
+                WHERE mid.database_id = DB_ID()
+                ORDER BY impact DESC
+            "#;
+
+            if let Ok(result) = self.executor.execute(missing_query).await {
+                let missing: Vec<serde_json::Value> = result
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        json!({
+                            "table": row.get("table_name").map(|v| v.to_display_string()),
+                            "equality_columns": row.get("equality_columns").map(|v| v.to_display_string()),
+                            "inequality_columns": row.get("inequality_columns").map(|v| v.to_display_string()),
+                            "included_columns": row.get("included_columns").map(|v| v.to_display_string()),
+                            "impact": row.get("impact").map(|v| v.to_display_string()),
+                        })
+                    })
+                    .collect();
+                analysis["missing_indexes"] = json!(missing);
+            }
+        }
+
+        // Check for query patterns that might cause issues
+        let query_upper = input.query.to_uppercase();
+        let mut warnings: Vec<String> = Vec::new();
+
+        if query_upper.contains("SELECT *") {
+            warnings.push("Query uses SELECT * - consider specifying explicit columns".to_string());
+        }
+        if !query_upper.contains("WHERE") && query_upper.contains("SELECT") {
+            warnings.push("Query has no WHERE clause - may scan entire table".to_string());
+        }
+        if query_upper.contains("LIKE '%") {
+            warnings.push(
+                "Query uses leading wildcard in LIKE - cannot use index efficiently".to_string(),
+            );
+        }
+        if query_upper.contains("OR ") {
+            warnings.push("Query contains OR conditions - may prevent index usage".to_string());
+        }
+        if query_upper.contains("NOT IN") || query_upper.contains("NOT EXISTS") {
+            warnings.push("Query uses NOT IN/NOT EXISTS - consider alternatives".to_string());
+        }
+        warnings.extend(self.memory_optimized_warnings(&input.query, None).await);
+
+        analysis["warnings"] = json!(warnings);
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&analysis)
+                .unwrap_or_else(|_| "Analysis failed".to_string()),
+        ))
+    }
+
+    // =========================================================================
+    // Connection Pool Metrics Tools
+    // =========================================================================
+
+    /// Get connection pool metrics and statistics.
+    ///
+    /// Returns information about the connection pool including
+    /// active connections, idle connections, pool configuration, and the
+    /// age and usage of idle connections seen by the last health probe, plus
+    /// the read-only pool when one is configured.
+    #[tool(
+        description = "Get connection pool metrics including active connections, idle connections, pool health, background probe results, per-connection age and usage, and the read-only pool if configured.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_pool_metrics(
+        &self,
+        input: GetPoolMetricsInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!("Getting connection pool metrics");
+
+        let pool_status = self.pool.status();
+        let pool_config = self.config().database.pool.clone();
+
+        let mut response = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "pool": {
+                "total_connections": pool_status.total,
+                "available_connections": pool_status.available,
+                "in_use_connections": pool_status.in_use,
+                "max_connections": pool_status.max,
+                "utilization_percent": pool_status.utilization()
+            },
+            "config": {
+                "max_connections": pool_config.max_connections,
+                "min_connections": pool_config.min_connections,
+                "connection_timeout_seconds": pool_config.connection_timeout.as_secs(),
+                "idle_timeout_seconds": pool_config.idle_timeout.as_secs(),
+                "max_lifetime_seconds": pool_config.max_lifetime.as_secs(),
+                "health_check_interval_seconds": pool_config.health_check_interval.as_secs(),
+            }
+        });
+
+        // Lifetime counters kept by the pool
+        let pool_metrics = self.pool.metrics();
+        response["activity"] = json!({
+            "connections_created": pool_metrics.connections_created,
+            "connections_closed": pool_metrics.connections_closed,
+            "idle_expired": pool_metrics.connections_idle_expired,
+            "lifetime_expired": pool_metrics.connections_lifetime_expired,
+            "checkouts_successful": pool_metrics.checkouts_successful,
+            "checkouts_failed": pool_metrics.checkouts_failed,
+            "avg_acquisition_ms": pool_metrics.avg_acquisition_time_us as f64 / 1000.0,
+            "peak_wait_queue_depth": pool_metrics.peak_wait_queue_depth,
+            "uptime_seconds": pool_metrics.uptime.as_secs(),
+        });
+
+        // Background probing and per-connection age/usage
+        let maintenance = self.pool_health.snapshot();
+        response["maintenance"] = json!({
+            "enabled": !self.pool_health.interval().is_zero(),
+            "runs": maintenance.runs,
+            "last_run": maintenance.last_run.map(|t| t.to_rfc3339()),
+            "probes": maintenance.probes,
+            "probe_failures": maintenance.probe_failures,
+            "recycled": maintenance.recycled,
+            "replenished": maintenance.replenished,
+        });
+        response["connections"] = json!(maintenance.connections);
+
+        // Stale connections dropped after server failovers
+        response["failover"] = json!(self.failover.stats());
+
+        // Pools re-established with rotated credentials
+        response["credential_rotation"] = json!(self.credential_rotation.stats());
+
+        // Add health assessment
+        let healthy = pool_status.available > 0 || !pool_status.is_at_capacity();
+        response["health"] = json!({
+            "status": if healthy { "healthy" } else { "degraded" },
+            "has_available_connections": pool_status.available > 0,
+            "at_capacity": pool_status.is_at_capacity(),
+        });
+
+        // Pool with read-only intent serving read-only tools and resources
+        if let Some(read_pool) = &self.read_pool {
+            let read_status = read_pool.status();
+            response["read_pool"] = json!({
+                "endpoint": self.read_endpoint,
+                "total_connections": read_status.total,
+                "available_connections": read_status.available,
+                "in_use_connections": read_status.in_use,
+                "max_connections": read_status.max,
+                "utilization_percent": read_status.utilization()
+            });
+        }
+
+        if input.include_history {
+            // Get session state for historical context
+            let state = self.state.read().await;
+            response["sessions"] = json!({
+                "total": state.total_session_count(),
+                "running": state.running_session_count(),
+            });
+            response["transactions"] = json!({
+                "total": state.total_transaction_count(),
+                "active": state.active_transaction_count(),
+            });
+        }
+
+        info!(
+            "Pool metrics: {}/{} connections in use",
+            pool_status.in_use, pool_status.total
+        );
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get pool metrics".to_string()),
+        ))
+    }
+
+    /// Report which server and Availability Group replica the pool is connected to.
+    ///
+    /// Shows the endpoint chosen at startup (after failover partners,
+    /// multi-subnet failover and read-only routing) and the replica's role.
+    #[tool(
+        description = "Report which server and Availability Group replica the connection pool is using: endpoint, application intent, replica role, synchronization health and whether the database is writable.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_replica_info(
+        &self,
+        input: GetReplicaInfoInput,
+    ) -> Result<ToolOutput, McpError> {
+        let config = self.config();
+        let db = &config.database;
+
+        let local_query = r#"
+            SELECT
+                @@SERVERNAME AS server_name,
+                DB_NAME() AS database_name,
+                CAST(SERVERPROPERTY('IsHadrEnabled') AS int) AS hadr_enabled,
+                CAST(DATABASEPROPERTYEX(DB_NAME(), 'Updateability') AS nvarchar(20))
+                    AS updateability,
+                ag.name AS availability_group,
+                rs.role_desc AS role,
+                rs.synchronization_health_desc AS synchronization_health
+            FROM (SELECT 1 AS placeholder) AS d
+            LEFT JOIN sys.dm_hadr_availability_replica_states AS rs
+                ON rs.is_local = 1
+                AND rs.group_id = (
+                    SELECT TOP (1) group_id FROM sys.dm_hadr_database_replica_states
+                    WHERE is_local = 1 AND database_id = DB_ID()
+                )
+            LEFT JOIN sys.availability_groups AS ag ON ag.group_id = rs.group_id
+        "#;
+
+        let result = match self.executor.execute(local_query).await {
+            Ok(r) => r,
+            Err(e) => return Ok(ToolOutput::error(format!("Failed to query replica: {}", e))),
+        };
+        let text = |row: &crate::database::ResultRow, column: &str| {
+            row.get(column)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_display_string())
+        };
+
+        let mut response = json!({
+            "endpoint": {
+                "host": db.host,
+                "port": db.port,
+                "instance": db.instance,
+                "application_intent": db.application_intent.as_str(),
+                "multi_subnet_failover": db.multi_subnet_failover,
+                "failover_partners": db.failover_partners,
+            },
+        });
+        if let Some(row) = result.rows.first() {
+            response["server_name"] = json!(text(row, "server_name"));
+            response["database"] = json!(text(row, "database_name"));
+            response["hadr_enabled"] = json!(text(row, "hadr_enabled").as_deref() == Some("1"));
+            let updateability = text(row, "updateability");
+            response["writable"] = json!(updateability.as_deref() != Some("READ_ONLY"));
+            response["availability_group"] = json!(text(row, "availability_group"));
+            response["replica_role"] = json!(text(row, "role"));
+            response["synchronization_health"] = json!(text(row, "synchronization_health"));
+        }
+
+        if input.include_replicas {
+            let replicas_query = r#"
+                SELECT
+                    ag.name AS availability_group,
+                    ar.replica_server_name,
+                    rs.role_desc AS role,
+                    rs.connected_state_desc AS connected_state,
+                    rs.synchronization_health_desc AS synchronization_health,
+                    ar.availability_mode_desc AS availability_mode,
+                    ar.secondary_role_allow_connections_desc AS readable_secondary,
+                    ar.read_only_routing_url,
+                    CAST(ISNULL(rs.is_local, 0) AS int) AS is_local
+                FROM sys.availability_replicas AS ar
+                JOIN sys.availability_groups AS ag ON ag.group_id = ar.group_id
+                LEFT JOIN sys.dm_hadr_availability_replica_states AS rs
+                    ON rs.replica_id = ar.replica_id
+                ORDER BY ag.name, ar.replica_server_name
+            "#;
+            match self.executor.execute(replicas_query).await {
+                Ok(result) => {
+                    let replicas: Vec<serde_json::Value> = result
+                        .rows
+                        .iter()
+                        .map(|row| {
+                            json!({
+                                "availability_group": text(row, "availability_group"),
+                                "server": text(row, "replica_server_name"),
+                                "role": text(row, "role"),
+                                "connected_state": text(row, "connected_state"),
+                                "synchronization_health": text(row, "synchronization_health"),
+                                "availability_mode": text(row, "availability_mode"),
+                                "readable_secondary": text(row, "readable_secondary"),
+                                "read_only_routing_url": text(row, "read_only_routing_url"),
+                                "is_local": text(row, "is_local").as_deref() == Some("1"),
+                            })
+                        })
+                        .collect();
+                    response["replicas"] = json!(replicas);
+                }
+                Err(e) => {
+                    response["replicas_error"] = json!(e.to_string());
+                }
+            }
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get replica info".to_string()),
+        ))
+    }
+
+    /// Report Availability Group synchronization health and secondary lag.
+    #[tool(
+        description = "Report Availability Group health per database and replica: synchronization state and health, log send and redo queues with rates, last commit/hardened/redone times, each secondary's commit lag behind the primary and estimated redo time, and whether every secondary is caught up.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_ag_health(&self, input: GetAgHealthInput) -> Result<ToolOutput, McpError> {
+        match self
+            .ag_health_report(
+                input.availability_group.as_deref(),
+                input.database.as_deref(),
+            )
+            .await
+        {
+            Ok(response) => Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Failed to get Availability Group health".to_string()),
+            )),
+            Err(e) => Ok(ToolOutput::error(e)),
+        }
+    }
+
+    /// Report replication agent status and latency.
+    #[tool(
+        description = "Report transactional and snapshot replication agent status from the distribution database: the last run status, message and delivery latency of each log reader and distribution agent, with failed or retrying agents and the highest latency summarized.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_replication_status(
+        &self,
+        input: GetReplicationStatusInput,
+    ) -> Result<ToolOutput, McpError> {
+        if let Some(db) = &input.distribution_database {
+            if let Err(e) = validate_identifier(db) {
+                return Ok(ToolOutput::error(format!("Invalid database name: {}", e)));
+            }
+        }
+        match self
+            .replication_status_report(input.distribution_database.as_deref())
+            .await
+        {
+            Ok(response) => Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Failed to get replication status".to_string()),
+            )),
+            Err(e) => Ok(ToolOutput::error(e)),
+        }
+    }
+
+    /// Report memory used by memory-optimized tables.
+    #[tool(
+        description = "Report In-Memory OLTP usage of a database: memory allocated and used by each memory-optimized table and its indexes (with durability), the engine's internal tables as a system total, counts of memory-optimized tables and natively compiled modules, the memory-optimized filegroup, MEMORY_OPTIMIZED_ELEVATE_TO_SNAPSHOT and the bound resource pool.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_memory_optimized_usage(
+        &self,
+        input: GetMemoryOptimizedUsageInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::database::xtp::{TableMemoryUsage, DATABASE_XTP_QUERY, TABLE_MEMORY_QUERY};
+
+        let database = match &input.database {
+            Some(db) => {
+                if let Err(e) = validate_identifier(db) {
+                    return Ok(ToolOutput::error(format!("Invalid database name: {}", e)));
+                }
+                Some(db.clone())
+            }
+            None => self
+                .state
+                .read()
+                .await
+                .current_database()
+                .map(str::to_string),
+        };
+        let prefix = database
+            .as_ref()
+            .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+            .unwrap_or_default();
+
+        let settings = match self
+            .executor
+            .execute(&format!("{}{}", prefix, DATABASE_XTP_QUERY))
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to query In-Memory OLTP settings: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+        let usage: Vec<TableMemoryUsage> = match self
+            .executor
+            .execute(&format!("{}{}", prefix, TABLE_MEMORY_QUERY))
+            .await
+        {
+            Ok(r) => r.rows.iter().map(TableMemoryUsage::from_row).collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to query memory-optimized table usage: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+
+        let (system, tables): (Vec<TableMemoryUsage>, Vec<TableMemoryUsage>) =
+            usage.into_iter().partition(TableMemoryUsage::is_system);
+        let system_kb: i64 = system
+            .iter()
+            .map(TableMemoryUsage::total_allocated_kb)
+            .sum();
+        let tables_kb: i64 = tables
+            .iter()
+            .map(TableMemoryUsage::total_allocated_kb)
+            .sum();
+
+        let mut response = json!({
+            "database": database,
+            "total_allocated_kb": tables_kb + system_kb,
+            "tables_allocated_kb": tables_kb,
+            "system_allocated_kb": system_kb,
+            "tables": tables,
+        });
+        if let Some(row) = settings.rows.first() {
+            let text = |column: &str| {
+                row.get(column)
+                    .filter(|v| !v.is_null())
+                    .map(|v| v.to_display_string())
+            };
+            response["memory_optimized_tables"] = json!(text("memory_optimized_tables"));
+            response["natively_compiled_modules"] = json!(text("natively_compiled_modules"));
+            response["has_memory_optimized_filegroup"] =
+                json!(text("has_memory_optimized_filegroup").as_deref() == Some("1"));
+            response["elevate_to_snapshot"] =
+                json!(text("elevate_to_snapshot").as_deref() == Some("1"));
+            response["resource_pool"] = json!(text("resource_pool"));
+            response["resource_pool_max_memory_percent"] = json!(text("max_memory_percent"));
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get memory-optimized usage".to_string()),
+        ))
+    }
+
+    /// Test that a linked server can be reached.
+    #[tool(
+        description = "Test connectivity to a linked server with sp_testlinkedserver and report the time taken; for SQL Server linked servers with data access, also report the remote server name, version and edition.",
+        read_only = true
+    )]
+    pub async fn test_linked_server(
+        &self,
+        input: TestLinkedServerInput,
+    ) -> Result<ToolOutput, McpError> {
+        let servers = match self.metadata.list_linked_servers().await {
+            Ok(servers) => servers,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to list linked servers: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+        let Some(server) = servers
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(input.server.trim()))
+        else {
+            let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+            return Ok(ToolOutput::error(format!(
+                "Linked server '{}' not found (available: {})",
+                input.server,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )));
+        };
+
+        let start = std::time::Instant::now();
+        let test = format!(
+            "EXEC sys.sp_testlinkedserver @servername = N'{}'",
+            server.name.replace('\'', "''")
+        );
+        let connected = self.executor.execute_non_query(&test).await;
+        let mut response = json!({
+            "server": server.name,
+            "product": server.product,
+            "provider": server.provider,
+            "data_source": server.data_source,
+            "connected": connected.is_ok(),
+            "elapsed_ms": start.elapsed().as_millis() as u64,
+        });
+        if let Err(e) = connected {
+            response["error"] = json!(self.localize_error(&e).await);
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| format!("Linked server {} is unreachable", server.name)),
+            ));
+        }
+
+        let provider = server.provider.to_uppercase();
+        let is_sql_server = server.product.eq_ignore_ascii_case("SQL Server")
+            || provider.starts_with("SQLNCLI")
+            || provider.starts_with("MSOLEDBSQL");
+        if !server.data_access {
+            response["note"] =
+                json!("Data access is disabled, so four-part names and OPENQUERY will fail");
+        } else if is_sql_server {
+            let remote = format!(
+                "SELECT * FROM OPENQUERY([{}], 'SELECT @@SERVERNAME AS server_name, \
+                 CAST(SERVERPROPERTY(''ProductVersion'') AS nvarchar(128)) AS product_version, \
+                 CAST(SERVERPROPERTY(''Edition'') AS nvarchar(128)) AS edition')",
+                server.name.replace(']', "]]")
+            );
+            match self.executor.execute(&remote).await {
+                Ok(result) => {
+                    if let Some(row) = result.rows.first() {
+                        let text = |column: &str| {
+                            row.get(column)
+                                .filter(|v| !v.is_null())
+                                .map(|v| v.to_display_string())
+                        };
+                        response["remote"] = json!({
+                            "server_name": text("server_name"),
+                            "product_version": text("product_version"),
+                            "edition": text("edition"),
+                        });
+                    }
+                }
+                Err(e) => {
+                    response["remote_error"] = json!(self.localize_error(&e).await);
+                }
+            }
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Linked server {} is reachable", server.name)),
+        ))
+    }
+
+    /// Report size, free space and autogrowth of a database's files.
+    #[tool(
+        description = "Report a database's data and log files: size, used and free space, maximum size, autogrowth settings with warnings, log space used, and the log reuse wait reason with a hint on how to clear it.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_file_usage(&self, input: GetFileUsageInput) -> Result<ToolOutput, McpError> {
+        use crate::database::storage::{log_reuse_wait_hint, DatabaseFile, FILES_QUERY, LOG_QUERY};
+
+        let database = match &input.database {
+            Some(db) => {
+                if let Err(e) = validate_identifier(db) {
+                    return Ok(ToolOutput::error(format!("Invalid database name: {}", e)));
+                }
+                Some(db.clone())
+            }
+            None => self
+                .state
+                .read()
+                .await
+                .current_database()
+                .map(str::to_string),
+        };
+        let use_prefix = database
+            .as_ref()
+            .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+            .unwrap_or_default();
+
+        let files: Vec<DatabaseFile> = match self
+            .executor
+            .execute(&format!("{}{}", use_prefix, FILES_QUERY))
+            .await
+        {
+            Ok(result) => result
+                .rows
+                .iter()
+                .filter_map(DatabaseFile::from_row)
+                .collect(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read file usage: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+
+        let total = |file_type: &str, size: fn(&DatabaseFile) -> Option<f64>| {
+            let sum: f64 = files
+                .iter()
+                .filter(|f| f.file_type == file_type)
+                .filter_map(size)
+                .sum();
+            (sum * 100.0).round() / 100.0
+        };
+        let mut response = json!({
+            "database": database,
+            "data_size_mb": total("ROWS", |f| Some(f.size_mb)),
+            "data_free_mb": total("ROWS", |f| f.free_mb),
+            "log_size_mb": total("LOG", |f| Some(f.size_mb)),
+            "log_free_mb": total("LOG", |f| f.free_mb),
+            "files": files,
+        });
+
+        match self
+            .executor
+            .execute(&format!("{}{}", use_prefix, LOG_QUERY))
+            .await
+        {
+            Ok(result) => {
+                if let Some(row) = result.rows.first() {
+                    let text = |column: &str| {
+                        row.get(column)
+                            .filter(|v| !v.is_null())
+                            .map(|v| v.to_display_string())
+                    };
+                    let wait = text("log_reuse_wait_desc");
+                    response["database"] = json!(text("database_name"));
+                    response["recovery_model"] = json!(text("recovery_model_desc"));
+                    response["log_used_percent"] =
+                        json!(text("log_used_percent").and_then(|v| v.parse::<f64>().ok()));
+                    response["log_reuse_wait"] = json!(wait);
+                    if let Some(hint) = wait.as_deref().and_then(log_reuse_wait_hint) {
+                        response["log_reuse_wait_hint"] = json!(hint);
+                    }
+                }
+            }
+            Err(e) => {
+                response["log_error"] = json!(self.localize_error(&e).await);
+            }
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get file usage".to_string()),
+        ))
+    }
+
+    /// Report tempdb space by allocation category and consuming session.
+    #[tool(
+        description = "Report tempdb usage: size, free space, user objects, internal objects and version store, plus the sessions holding the most tempdb space with their login, host, program and running statement.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_tempdb_usage(
+        &self,
+        input: GetTempdbUsageInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_TEMPDB_SESSION_LIMIT;
+        use crate::database::storage::{
+            tempdb_consumers_query, TempdbConsumer, TempdbSummary, TEMPDB_SUMMARY_QUERY,
+        };
+
+        let limit = input.limit.clamp(1, MAX_TEMPDB_SESSION_LIMIT);
+
+        let summary = match self.executor.execute(TEMPDB_SUMMARY_QUERY).await {
+            Ok(result) => result
+                .rows
+                .first()
+                .map(TempdbSummary::from_row)
+                .unwrap_or_default(),
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to read tempdb usage: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+
+        let mut response = json!({
+            "summary": summary,
+        });
+        let query = tempdb_consumers_query(limit, input.include_system_sessions);
+        match self.executor.execute_with_limit(&query, limit).await {
+            Ok(result) => {
+                let sessions: Vec<TempdbConsumer> = result
+                    .rows
+                    .iter()
+                    .filter_map(TempdbConsumer::from_row)
+                    .collect();
+                response["session_count"] = json!(sessions.len());
+                response["sessions"] = json!(sessions);
+            }
+            Err(e) => {
+                response["sessions_error"] = json!(self.localize_error(&e).await);
+            }
+        }
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get tempdb usage".to_string()),
+        ))
+    }
+
+    /// Show what is running on the server right now.
+    #[tool(
+        description = "Show what is running right now: one row per active request with elapsed and CPU time, reads/writes, current wait, blocking session, tempdb and memory grant usage, and the running statement (optionally its plan), plus idle sessions blocking others. Filter by database or login.",
+        read_only = true
+    )]
+    pub async fn get_active_requests(
+        &self,
+        input: GetActiveRequestsInput,
+    ) -> Result<ToolOutput, McpError> {
+        use crate::constants::MAX_ACTIVE_REQUESTS_LIMIT;
+        use crate::database::activity::{active_requests_query, ActiveRequest, ActivityFilter};
+
+        let limit = input.limit.clamp(1, MAX_ACTIVE_REQUESTS_LIMIT);
+        let filter = ActivityFilter {
+            database: input.database.as_deref(),
+            login: input.login.as_deref(),
+            include_system_sessions: input.include_system_sessions,
+            include_plans: input.include_plans,
+        };
+        let query = active_requests_query(&filter, limit);
+
+        let requests: Vec<ActiveRequest> =
+            match self.executor.execute_with_limit(&query, limit).await {
+                Ok(result) => result
+                    .rows
+                    .iter()
+                    .filter_map(ActiveRequest::from_row)
+                    .collect(),
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Failed to read active requests: {}",
+                        self.localize_error(&e).await
+                    )))
+                }
+            };
+
+        let blocked = requests.iter().filter(|r| r.blocked_by.is_some()).count();
+        let head_blockers: Vec<i64> = requests
+            .iter()
+            .filter(|r| r.is_head_blocker)
+            .map(|r| r.session_id)
+            .collect();
+        let response = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "database": input.database,
+            "login": input.login,
+            "request_count": requests.len(),
+            "blocked_count": blocked,
+            "head_blockers": head_blockers,
+            "requests": requests,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get active requests".to_string()),
+        ))
+    }
+
+    /// Kill a session after confirming its login and host.
+    ///
+    /// The first call describes the session; the kill only happens when the
+    /// call is repeated with the session's login and host echoed back.
+    #[tool(
+        description = "Kill a SQL Server session (KILL spid). Requires unrestricted validation mode. Call first with just session_id to see the session, then repeat with its login and host echoed back to kill it. System sessions and this server's own connections are refused; its open transaction is rolled back.",
+        destructive = true
+    )]
+    pub async fn kill_session(&self, input: KillSessionInput) -> Result<ToolOutput, McpError> {
+        use crate::database::activity::{kill_target_query, KillTarget};
+        use crate::security::ValidationMode;
+
+        let validation_mode = self.config().security.validation_mode;
+        if validation_mode != ValidationMode::Unrestricted {
+            return Ok(ToolOutput::error(format!(
+                "Killing sessions requires unrestricted validation mode (current: {:?})",
+                validation_mode
+            )));
+        }
+        if !(1..=i16::MAX as i64).contains(&input.session_id) {
+            return Ok(ToolOutput::error(format!(
+                "Invalid session ID: {}",
+                input.session_id
+            )));
+        }
+
+        let target = match self
+            .executor
+            .execute(&kill_target_query(input.session_id))
+            .await
+        {
+            Ok(result) => match result.rows.first().and_then(KillTarget::from_row) {
+                Some(target) => target,
+                None => {
+                    return Ok(ToolOutput::error(format!(
+                        "Session {} not found",
+                        input.session_id
+                    )))
+                }
+            },
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to look up session: {}",
+                    self.localize_error(&e).await
+                )))
+            }
+        };
+        if let Some(reason) = target.refusal() {
+            return Ok(ToolOutput::error(reason));
+        }
+
+        if !target.confirmed_by(input.login.as_deref(), input.host.as_deref()) {
+            if input.login.is_some() || input.host.is_some() {
+                return Ok(ToolOutput::error(format!(
+                    "The login or host does not match session {} (login: {}, host: {})",
+                    target.session_id,
+                    target.login.as_deref().unwrap_or("none"),
+                    target.host.as_deref().unwrap_or("none")
+                )));
+            }
+            let response = json!({
+                "status": "confirmation_required",
+                "session": target,
+                "message": format!(
+                    "Call kill_session again with session_id {} and the login and host of the \
+                     session to kill it.",
+                    target.session_id
+                ),
+            });
+            return Ok(ToolOutput::text(
+                serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Confirmation required".to_string()),
+            ));
+        }
+
+        if let Err(e) = self
+            .executor
+            .execute_non_query(&format!("KILL {}", target.session_id))
+            .await
+        {
+            return Ok(ToolOutput::error(format!(
+                "Failed to kill session {}: {}",
+                target.session_id,
+                self.localize_error(&e).await
+            )));
+        }
+        warn!(
+            session_id = target.session_id,
+            login = target.login.as_deref().unwrap_or(""),
+            host = target.host.as_deref().unwrap_or(""),
+            "Killed session"
+        );
+
+        let response = json!({
+            "status": "killed",
+            "session": target,
+            "message": if target.open_transactions > 0 {
+                format!(
+                    "Session killed; its open transaction is being rolled back. \
+                     Check progress with KILL {} WITH STATUSONLY.",
+                    target.session_id
+                )
+            } else {
+                "Session killed.".to_string()
+            },
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Killed session {}", target.session_id)),
+        ))
+    }
+
+    /// Get internal server metrics.
+    ///
+    /// Returns metrics collected by the server including query counts,
+    /// latency percentiles overall and per tool, cache performance, and
+    /// transaction counts, plus the top query shapes (statements normalized
+    /// by fingerprint) with their own percentiles.
+    #[tool(
+        description = "Get internal server metrics including query counts, p50/p95/p99 latency overall and per tool, cache stats, transaction counts, and the top query shapes by count or latency.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_internal_metrics(
+        &self,
+        input: GetInternalMetricsInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!("Getting internal server metrics");
+
+        let snapshot = self.metrics.snapshot();
+
+        let mut response = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "queries": {
+                "total": snapshot.queries_total,
+                "success": snapshot.queries_success,
+                "failed": snapshot.queries_failed,
+                "total_time_ms": snapshot.query_time_ms_total,
+                "latency_ms": self.metrics.query_latency().summary(),
+            },
+            "transactions": {
+                "total": snapshot.transactions_total,
+                "committed": snapshot.transactions_committed,
+                "rolled_back": snapshot.transactions_rolled_back,
+            },
+            "cache": {
+                "hits": snapshot.cache_hits,
+                "misses": snapshot.cache_misses,
+            },
+            "network": {
+                "bytes_transferred": snapshot.bytes_transferred,
+            }
+        });
+
+        let breaker = self.executor.circuit_breaker().stats();
+        response["circuit_breaker"] = circuit_breaker_status(&breaker);
+
+        let config = self.config();
+        response["result_memory"] = json!({
+            "bytes_in_use": crate::database::result_bytes_in_use(),
+            "query_budget_mb": config.query.query_memory_mb,
+            "server_budget_mb": config.query.result_memory_mb,
+        });
+
+        if self.concurrency_limiter.is_enabled() {
+            response["concurrency"] = json!(self.concurrency_limiter.stats());
+        }
+
+        let tools: serde_json::Map<String, serde_json::Value> = self
+            .metrics
+            .tool_latencies()
+            .into_iter()
+            .map(|(tool, histogram)| (tool, json!(histogram.summary())))
+            .collect();
+        if !tools.is_empty() {
+            response["tools"] = json!(tools);
+        }
+
+        if input.top_queries > 0 {
+            let shapes: Vec<serde_json::Value> = self
+                .metrics
+                .top_query_shapes(input.top_queries, input.sort_by)
+                .into_iter()
+                .map(|shape| {
+                    let latency = shape.latency.summary();
+                    let mut entry = json!(shape);
+                    entry["latency_ms"] = json!(latency);
+                    entry
+                })
+                .collect();
+            response["query_shapes"] = json!({
+                "distinct": self.metrics.query_shape_count(),
+                "sort_by": input.sort_by,
+                "top": shapes,
+            });
+        }
+
+        if input.include_rates {
+            response["rates"] = json!({
+                "circuit_breaker_success_rate_percent": breaker.success_rate() * 100.0,
+                "circuit_breaker_rejection_rate_percent": breaker.rejection_rate() * 100.0,
+                "query_success_rate_percent": snapshot.success_rate(),
+                "avg_query_time_ms": snapshot.avg_query_time_ms(),
+                "cache_hit_rate_percent": snapshot.cache_hit_rate(),
+            });
+        }
+
+        info!(
+            "Internal metrics: {} queries, {:.1}% success rate",
+            snapshot.queries_total,
+            snapshot.success_rate()
+        );
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to get internal metrics".to_string()),
+        ))
+    }
+
+    /// Clear cached query results.
+    ///
+    /// Without a pattern the whole cache is cleared. A pattern removes entries
+    /// whose query text contains it (case-insensitive) or that read a table of
+    /// that name.
+    #[tool(
+        description = "Clear cached query results. Optionally pass a pattern to clear only entries whose query contains it or that read a table of that name.",
+        destructive = true,
+        idempotent = true
+    )]
+    pub async fn clear_cache(&self, input: ClearCacheInput) -> Result<ToolOutput, McpError> {
+        let before = self.query_cache.stats().await.entry_count;
+        let cleared = match input.pattern.as_deref().map(str::trim) {
+            Some(pattern) if !pattern.is_empty() => self.query_cache.invalidate(pattern).await,
+            _ => {
+                self.query_cache.clear().await;
+                before
+            }
+        };
+        let remaining = self.query_cache.stats().await.entry_count;
+
+        info!("Cleared {} cached query results", cleared);
+
+        let response = json!({
+            "enabled": self.query_cache.is_enabled(),
+            "pattern": input.pattern,
+            "cleared": cleared,
+            "remaining": remaining,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Cleared {} entries", cleared)),
+        ))
+    }
+
+    /// List cached query results.
+    ///
+    /// Keys are shown hashed so that literals in cached queries are not exposed.
+    #[tool(
+        description = "List cached query results with hashed key, database, tables read, size, hit count, age and remaining TTL, plus aggregate cache statistics.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn get_cache_entries(
+        &self,
+        input: GetCacheEntriesInput,
+    ) -> Result<ToolOutput, McpError> {
+        let stats = self.query_cache.stats().await;
+        let entries = self.query_cache.entries().await;
+        let listed: Vec<_> = entries.iter().take(input.limit).collect();
+
+        let response = json!({
+            "enabled": self.query_cache.is_enabled(),
+            "stats": {
+                "entries": stats.entry_count,
+                "total_size_bytes": stats.total_size_bytes,
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "hit_rate_percent": stats.hit_rate(),
+                "evictions": stats.evictions,
+            },
+            "count": listed.len(),
+            "truncated": entries.len() > listed.len(),
+            "entries": listed,
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("{} cache entries", entries.len())),
+        ))
+    }
+
+    /// Evict a single cached query result.
+    #[tool(
+        description = "Evict one cached query result by the ID shown in get_cache_entries.",
+        destructive = true,
+        idempotent = true
+    )]
+    pub async fn evict_cache_entry(
+        &self,
+        input: EvictCacheEntryInput,
+    ) -> Result<ToolOutput, McpError> {
+        if !self.query_cache.evict(input.id.trim()).await {
+            return Ok(ToolOutput::error(format!(
+                "Cache entry not found: {}",
+                input.id
+            )));
+        }
+
+        info!("Evicted cache entry {}", input.id);
+
+        let response = json!({
+            "id": input.id,
+            "status": "evicted",
+        });
+
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Evicted {}", input.id)),
+        ))
+    }
+
+    /// Get recorded tool calls from the audit log.
+    #[tool(
+        description = "Get the history of tool calls recorded in the audit log (tool, query text or hash, duration, rows, success, client), newest first. Filter by tool, client, outcome, time window, query text, or minimum duration.",
+        read_only = true
+    )]
+    pub async fn get_query_history(
+        &self,
+        input: GetQueryHistoryInput,
+    ) -> Result<ToolOutput, McpError> {
+        if !self.audit_log.is_enabled() {
+            return Ok(ToolOutput::error(
+                "Audit log is disabled; set MSSQL_AUDIT_ENABLED=true to record tool calls",
+            ));
+        }
+
+        let filter = AuditFilter {
+            tool: input.tool,
+            client: input.client,
+            success: input.success,
+            since: input
+                .since_minutes
+                .map(|minutes| chrono::Utc::now() - chrono::Duration::minutes(minutes as i64)),
+            contains: input.contains,
+            min_duration_ms: input.min_duration_ms,
+        };
+        let entries = self.audit_log.history(&filter, input.limit);
+
+        debug!("Query history: {} matching entries", entries.len());
+
+        let response = json!({
+            "count": entries.len(),
+            "recorded": self.audit_log.len(),
+            "entries": entries,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to serialize query history".to_string()),
+        ))
+    }
+
+    /// Get statements that exceeded the slow query threshold.
+    #[tool(
+        description = "Get queries that exceeded the slow query threshold (MSSQL_SLOW_QUERY_MS), newest first, with duration, rows, top session waits, the tool and request that ran them, and optionally the captured execution plan XML.",
+        read_only = true
+    )]
+    pub async fn get_slow_queries(
+        &self,
+        input: GetSlowQueriesInput,
+    ) -> Result<ToolOutput, McpError> {
+        let log = self.executor.slow_queries();
+        if !log.is_enabled() {
+            return Ok(ToolOutput::error(
+                "Slow query log is disabled; set MSSQL_SLOW_QUERY_MS to a threshold to enable it",
+            ));
+        }
+
+        let mut entries = log.entries(input.limit);
+        let recorded = log.len();
+        if input.clear {
+            log.clear();
+        }
+        let plans_captured = entries.iter().filter(|e| e.plan_xml.is_some()).count();
+        if !input.include_plans {
+            for entry in &mut entries {
+                entry.plan_xml = None;
+            }
+        }
+
+        debug!("Slow queries: {} entries", entries.len());
+
+        let response = json!({
+            "threshold_ms": log.threshold().as_millis() as u64,
+            "capture_plans": log.captures_plans(),
+            "count": entries.len(),
+            "recorded": recorded,
+            "plans_captured": plans_captured,
+            "cleared": input.clear,
+            "entries": entries,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| "Failed to serialize slow queries".to_string()),
+        ))
+    }
+
+    // -------------------------------------------------------------------------
+    // Validation Tools
+    // -------------------------------------------------------------------------
+
+    #[tool(description = "Validate SQL query syntax without executing. Use for dry-run validation before running DDL or complex queries.", read_only = true, idempotent = true)]
+    pub async fn validate_syntax_tool(
+        &self,
+        input: ValidateSyntaxInput,
+    ) -> Result<ToolOutput, McpError> {
+        debug!("Validating query syntax");
+
+        // If a database is specified, switch to it first
+        let effective_query = if let Some(ref db) = input.database {
+            format!("USE [{}];\n{}", db.replace(']', "]]"), input.query)
+        } else {
+            input.query.clone()
+        };
+
+        let result = self
+            .executor
+            .validate_syntax(&effective_query)
+            .await
+            .map_err(|e| McpError::internal(format!("Validation failed: {}", e)))?;
+
+        if result.valid {
+            info!("Query syntax is valid");
+        } else {
+            info!(
+                error = result.error_message.as_deref().unwrap_or("Unknown error"),
+                line = result.error_line,
+                "Query syntax validation failed"
+            );
+        }
+
+        // Flag syntax the connected server version does not support
+        let mut message = result.to_message();
+        if let Err(e) = self.check_compatibility(&input.query).await {
+            message.push_str(&format!("\n\nCompatibility: {}", e));
+        }
+        for warning in self
+            .memory_optimized_warnings(&input.query, input.database.as_deref())
+            .await
+        {
+            message.push_str(&format!("\n\nIn-Memory OLTP: {}", warning));
+        }
+
+        Ok(ToolOutput::text(message))
+    }
+
+    /// Lint SQL locally without executing it.
+    #[tool(
+        description = "Static analysis of SQL without executing it or contacting the server: flags SELECT *, NOLOCK hints, non-SARGable predicates (functions or arithmetic on columns, leading-wildcard LIKE), numbers in string literals that force implicit conversions, TOP without ORDER BY, three-part names, and deprecated syntax, with line numbers and suggested fixes.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn lint_sql(&self, input: LintSqlInput) -> Result<ToolOutput, McpError> {
+        use crate::security::lint::{findings_to_csv, format_findings, lint_sql};
+
+        let findings = match lint_sql(&input.query, &input.rules) {
+            Ok(f) => f,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        let output = match input.format {
+            OutputFormat::Json => serde_json::to_string_pretty(&json!({
+                "finding_count": findings.len(),
+                "findings": findings,
+            }))
+            .unwrap_or_else(|e| format!("Failed to serialize findings: {}", e)),
+            OutputFormat::Csv => findings_to_csv(&findings),
+            OutputFormat::Table => format_findings(&findings),
+        };
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Describe the result set of a query without executing it.
+    ///
+    /// The query is checked against the validation mode first, so describing
+    /// is never a way around it.
+    #[tool(
+        description = "Describe the columns a query would return (name, type, nullability, source table and column) without executing it, using sp_describe_first_result_set. Use to check the shape of a projection before running an expensive query.",
+        read_only = true,
+        idempotent = true
+    )]
+    pub async fn describe_query(&self, input: DescribeQueryInput) -> Result<ToolOutput, McpError> {
+        use crate::database::describe::{
+            columns_to_csv, columns_to_markdown, describe_query_sql, DescribedColumn,
+        };
+
+        if let Err(e) = self.validate_query(&input.query) {
+            return Ok(ToolOutput::error(format!("Query validation failed: {}", e)));
+        }
+
+        let describe = describe_query_sql(&input.query);
+        let effective_query = match &input.database {
+            Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), describe),
+            None => describe,
+        };
+        let result = match self.executor.execute(&effective_query).await {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to describe query: {}",
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+
+        let mut columns: Vec<DescribedColumn> = result
+            .rows
+            .iter()
+            .filter_map(DescribedColumn::from_row)
+            .collect();
+        if columns.is_empty() {
+            return Ok(ToolOutput::text("The query does not return a result set."));
+        }
+        columns.sort_by_key(|c| c.ordinal);
+
+        let output = match input.format {
+            OutputFormat::Json => serde_json::to_string_pretty(&columns).unwrap_or_else(|e| {
+                warn!("Failed to serialize query description to JSON: {}", e);
+                format!("Failed to serialize result: {}", e)
+            }),
+            OutputFormat::Csv => columns_to_csv(&columns),
+            OutputFormat::Table => columns_to_markdown(&columns),
+        };
+        Ok(ToolOutput::text(output))
+    }
+
+    /// Generate SQL for a natural-language question with the client's model.
+    ///
+    /// Sends the question with definitions of the relevant tables to the
+    /// client through MCP sampling and returns the proposed query together
+    /// with the excerpts it was grounded in. The query is never executed.
+    #[tool(
+        description = "Turn a natural-language question into a T-SQL query using the client's LLM (MCP sampling), grounded in the live definitions of the most relevant tables. Returns the SQL and the schema excerpts used, without executing it. Requires a client that supports sampling.",
+        read_only = true
+    )]
+    pub async fn generate_sql(&self, input: GenerateSqlInput) -> Result<ToolOutput, McpError> {
+        use crate::constants::SAMPLING_TIMEOUT_SECS;
+        use crate::database::sql_generation::{
+            extract_sql, sampling_prompt, select_tables, SYSTEM_PROMPT,
+        };
+        use crate::transport::SAMPLING_CREATE_MESSAGE;
+        use mcpkit::types::sampling::{CreateMessageRequest, IncludeContext};
+
+        debug!(
+            "Generating SQL for: {}",
+            truncate_for_log(&input.question, 100)
+        );
+
+        if input.question.trim().is_empty() {
+            return Ok(ToolOutput::error("A question is required"));
+        }
+        if let Err(e) = validate_identifier(&input.schema) {
+            return Ok(ToolOutput::error(format!("Invalid schema name: {}", e)));
+        }
+        if !self.client_requests.supports("sampling") {
+            return Ok(ToolOutput::error(
+                "The client does not support sampling, so SQL cannot be generated with its model",
+            ));
+        }
+
+        let tables = match self.metadata.load_schema_tables(&input.schema).await {
+            Ok(t) => t,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to load schema {}: {}",
+                    input.schema,
+                    self.localize_error(&e).await
+                )));
+            }
+        };
+        let excerpts = select_tables(
+            &input.schema,
+            &tables,
+            &input.question,
+            &input.tables,
+            input.max_tables,
+        );
+        if excerpts.is_empty() {
+            return Ok(ToolOutput::error(format!(
+                "No tables in schema {} match the question; name them in 'tables'",
+                input.schema
+            )));
+        }
+
+        let prompt = sampling_prompt(
+            &input.question,
+            &excerpts,
+            self.server_major_version().await,
+        );
+        let request = CreateMessageRequest::simple(prompt, input.max_tokens.max(1))
+            .system_prompt(SYSTEM_PROMPT)
+            .include_context(IncludeContext::None)
+            .temperature(0.0);
+        let params = serde_json::to_value(&request)
+            .map_err(|e| McpError::internal(format!("Failed to build sampling request: {}", e)))?;
+        let result = match self
+            .client_requests
+            .request(
+                SAMPLING_CREATE_MESSAGE,
+                params,
+                std::time::Duration::from_secs(SAMPLING_TIMEOUT_SECS),
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => return Ok(ToolOutput::error(format!("Sampling failed: {}", e))),
+        };
+        let Some(reply) = result["content"]["text"].as_str() else {
+            return Ok(ToolOutput::error("The client's model did not return text"));
+        };
+
+        let sql = extract_sql(reply);
+        let validation = match self.validate_query(&sql) {
+            Ok(()) => json!("passed"),
+            Err(e) => json!(e.to_string()),
+        };
+        info!(
+            "Generated SQL from {} schema excerpt(s) for: {}",
+            excerpts.len(),
+            truncate_for_log(&input.question, 100)
+        );
+
+        let response = json!({
+            "question": input.question,
+            "sql": sql,
+            "model": result["model"],
+            "validation": validation,
+            "executed": false,
+            "schema_excerpts": excerpts,
+        });
+        Ok(ToolOutput::text(
+            serde_json::to_string_pretty(&response).unwrap_or_else(|_| sql.clone()),
+        ))
+    }
+
+    // =========================================================================
+    // Resources (read-only metadata access)
+    // =========================================================================
+
+    /// Get SQL Server information including version, edition, and configuration.
+    #[resource(
+        uri_pattern = "mssql://server/info",
+        name = "Server Information",
+        description = "SQL Server version, edition, and configuration details",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_server_info(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        let info = self
+            .metadata
+            .get_server_info()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get server info: {}", e)))?;
+
+        ResourceContents::json(uri, &info)
+            .map_err(|e| McpError::internal(format!("Failed to serialize server info: {}", e)))
+    }
+
+    /// List all databases on the server.
+    #[resource(
+        uri_pattern = "mssql://databases",
+        name = "Databases",
+        description = "List of all databases on the server",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_databases(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        let databases = self
+            .metadata
+            .list_databases()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list databases: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": databases.len(),
+            "databases": databases,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize databases: {}", e)))
+    }
+
+    /// List all schemas in the current database.
+    #[resource(
+        uri_pattern = "mssql://schemas",
+        name = "Schemas",
+        description = "List of schemas in the current database",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_schemas(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Schemas resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let schemas = self
+            .metadata
+            .list_schemas()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list schemas: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": schemas.len(),
+            "schemas": schemas,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize schemas: {}", e)))
+    }
+
+    /// List all tables in the current database.
+    #[resource(
+        uri_pattern = "mssql://tables",
+        name = "Tables",
+        description = "List of all tables with row counts, sizes, and temporal/CDC/change tracking/memory-optimized/graph flags",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_tables(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Tables resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let tables = self
+            .metadata
+            .list_tables(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list tables: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": tables.len(),
+            "tables": tables,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize tables: {}", e)))
+    }
+
+    /// Get detailed information about a specific table.
+    #[resource(
+        uri_pattern = "mssql://tables/{schema}/{table}",
+        name = "Table Details",
+        description = "Get detailed information about a specific table including columns, system versioning (history table, period columns), and CDC/change tracking settings",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_table_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        // Sub-resources share the mssql://tables/ prefix and are routed here first
+        if strip_query(uri).ends_with("/relationships") {
+            return self.resource_table_relationships(uri).await;
+        }
+
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Table details resource requires database mode".to_string()),
+            });
+        }
+
+        // Parse schema and table from URI: mssql://tables/{schema}/{table}
+        let (schema, table) = parse_resource_path(uri, "tables")?;
+
+        // Validate identifiers
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params("table_details", format!("Invalid schema '{}': {}", schema, e))
+        })?;
+        validate_identifier(&table).map_err(|e| {
+            McpError::invalid_params("table_details", format!("Invalid table '{}': {}", table, e))
+        })?;
+
+        let columns = self
+            .metadata
+            .get_table_columns(&schema, &table)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
+
+        if columns.is_empty() {
+            return Err(McpError::resource_not_found(uri));
+        }
+
+        let versioning = self
+            .metadata
+            .get_table_versioning(&schema, &table)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get table versioning: {}", e)))?;
+
+        let mut response = serde_json::json!({
+            "schema": schema,
+            "table": table,
+            "column_count": columns.len(),
+            "columns": columns,
+            "versioning": versioning,
+        });
+        // Servers before SQL Server 2016 lack the Always Encrypted catalog views
+        let encrypted = self
+            .metadata
+            .get_encrypted_columns(&schema, &table)
+            .await
+            .unwrap_or_default();
+        if !encrypted.is_empty() {
+            let columns: Vec<_> = encrypted
+                .iter()
+                .map(|c| {
+                    let mut column = serde_json::json!(c);
+                    column["equality_lookups"] = serde_json::json!(c.is_deterministic());
+                    column
+                })
+                .collect();
+            response["always_encrypted"] = serde_json::json!({
+                "columns": columns,
+                "note": "Encrypted columns are returned as varbinary ciphertext and cannot be compared with literals; DETERMINISTIC columns support equality on encrypted parameters only",
+            });
+        }
+        let graph_kind = crate::database::graph::GraphKind::from_column_names(
+            columns.iter().map(|c| c.column_name.as_str()),
+        );
+        if let Some(kind) = graph_kind {
+            response["graph"] = serde_json::json!({
+                "type": kind,
+                "pseudo_columns": kind.pseudo_columns(),
+                "note": "Columns with a hex suffix ($node_id_..., graph_id_...) are internal; use the pseudo-columns, and MATCH to traverse edges",
+            });
+        }
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize table details: {}", e)))
+    }
+
+    /// Get the foreign keys of a table and the foreign keys referencing it.
+    #[resource(
+        uri_pattern = "mssql://tables/{schema}/{table}/relationships{?format}",
+        name = "Table Relationships",
+        description = "Foreign keys of a table and of tables referencing it, with join conditions and cascade rules (format: json, dot, or mermaid)",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_table_relationships(
+        &self,
+        uri: &str,
+    ) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Table relationships resource requires database mode".to_string()),
+            });
+        }
+
+        let format = RelationshipFormat::from_uri(uri)
+            .map_err(|e| McpError::invalid_params("table_relationships", e.to_string()))?;
+        let path = strip_query(uri).trim_end_matches("/relationships");
+        let (schema, table) = parse_resource_path(path, "tables")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params(
+                "table_relationships",
+                format!("Invalid schema '{}': {}", schema, e),
+            )
+        })?;
+        validate_identifier(&table).map_err(|e| {
+            McpError::invalid_params(
+                "table_relationships",
+                format!("Invalid table '{}': {}", table, e),
+            )
+        })?;
+
+        let relationships = self
+            .metadata
+            .list_foreign_keys(Some((&schema, &table)))
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list relationships: {}", e)))?;
+
+        if format != RelationshipFormat::Json {
+            return Ok(relationship_graph_contents(uri, &relationships, format));
+        }
+
+        let is_table =
+            |s: &str, t: &str| s.eq_ignore_ascii_case(&schema) && t.eq_ignore_ascii_case(&table);
+        let references: Vec<_> = relationships
+            .iter()
+            .filter(|fk| is_table(&fk.parent_schema, &fk.parent_table))
+            .collect();
+        let referenced_by: Vec<_> = relationships
+            .iter()
+            .filter(|fk| is_table(&fk.referenced_schema, &fk.referenced_table))
+            .collect();
+        let joins: Vec<String> = relationships.iter().map(join_condition).collect();
+
+        let response = serde_json::json!({
+            "schema": schema,
+            "table": table,
+            "references": references,
+            "referenced_by": referenced_by,
+            "join_conditions": joins,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize relationships: {}", e)))
+    }
+
+    /// Get the foreign key graph of the current database.
+    #[resource(
+        uri_pattern = "mssql://relationships{?format}",
+        name = "Relationships",
+        description = "Foreign key graph of the database: parent and child tables, columns, and cascade rules (format: json, dot, or mermaid)",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_relationships(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Relationships resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let format = RelationshipFormat::from_uri(uri)
+            .map_err(|e| McpError::invalid_params("relationships", e.to_string()))?;
+
+        let relationships = self
+            .metadata
+            .list_foreign_keys(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list relationships: {}", e)))?;
+
+        if format != RelationshipFormat::Json {
+            return Ok(relationship_graph_contents(uri, &relationships, format));
+        }
+
+        let response = serde_json::json!({
+            "count": relationships.len(),
+            "relationships": relationships,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize relationships: {}", e)))
+    }
+
+    /// List all views in the current database.
+    #[resource(
+        uri_pattern = "mssql://views",
+        name = "Views",
+        description = "List of all views in the database",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_views(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Views resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let views = self
+            .metadata
+            .list_views(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list views: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": views.len(),
+            "views": views,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize views: {}", e)))
+    }
+
+    /// Get detailed information about a specific view.
+    #[resource(
+        uri_pattern = "mssql://views/{schema}/{view}",
+        name = "View Details",
+        description = "Get detailed information about a specific view including definition",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_view_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("View details resource requires database mode".to_string()),
+            });
+        }
+
+        let (schema, view) = parse_resource_path(uri, "views")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params("view_details", format!("Invalid schema '{}': {}", schema, e))
+        })?;
+        validate_identifier(&view).map_err(|e| {
+            McpError::invalid_params("view_details", format!("Invalid view '{}': {}", view, e))
+        })?;
+
+        let views = self
+            .metadata
+            .list_views(Some(&schema))
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list views: {}", e)))?;
+
+        let view_info = views
+            .iter()
+            .find(|v| v.view_name.eq_ignore_ascii_case(&view))
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
+
+        let response = serde_json::json!({
+            "schema": view_info.schema_name,
+            "view": view_info.view_name,
+            "definition": view_info.definition,
+            "is_updatable": view_info.is_updatable,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize view details: {}", e)))
+    }
+
+    /// List all stored procedures in the current database.
+    #[resource(
+        uri_pattern = "mssql://procedures",
+        name = "Stored Procedures",
+        description = "List of stored procedures, flagging natively compiled ones",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_procedures(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Procedures resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let procedures = self
+            .metadata
+            .list_procedures(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list procedures: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": procedures.len(),
+            "procedures": procedures,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize procedures: {}", e)))
+    }
+
+    /// Get detailed information about a specific stored procedure.
+    #[resource(
+        uri_pattern = "mssql://procedures/{schema}/{procedure}",
+        name = "Procedure Details",
+        description = "Get detailed information about a stored procedure including parameters",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_procedure_details(
+        &self,
+        uri: &str,
+    ) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Procedure details resource requires database mode".to_string()),
+            });
+        }
+
+        let (schema, procedure) = parse_resource_path(uri, "procedures")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params(
+                "procedure_details",
+                format!("Invalid schema '{}': {}", schema, e),
+            )
+        })?;
+        validate_identifier(&procedure).map_err(|e| {
+            McpError::invalid_params(
+                "procedure_details",
+                format!("Invalid procedure '{}': {}", procedure, e),
+            )
+        })?;
+
+        let definition = self
+            .metadata
+            .get_procedure_definition(&schema, &procedure)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get procedure definition: {}", e)))?;
+
+        let parameters = self
+            .metadata
+            .get_procedure_parameters(&schema, &procedure)
+            .await
+            .map_err(|e| {
+                McpError::internal(format!("Failed to get procedure parameters: {}", e))
+            })?;
+
+        let response = serde_json::json!({
+            "schema": schema,
+            "procedure": procedure,
+            "definition": definition,
+            "parameter_count": parameters.len(),
+            "parameters": parameters,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize procedure details: {}", e)))
+    }
+
+    /// List all user-defined functions in the current database.
+    #[resource(
+        uri_pattern = "mssql://functions",
+        name = "Functions",
+        description = "List of user-defined functions (scalar and table-valued)",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_functions(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Functions resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let functions = self
+            .metadata
+            .list_functions(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list functions: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": functions.len(),
+            "functions": functions,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize functions: {}", e)))
+    }
+
+    /// Get detailed information about a specific user-defined function.
+    #[resource(
+        uri_pattern = "mssql://functions/{schema}/{function}",
+        name = "Function Details",
+        description = "Get detailed information about a user-defined function including parameters",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_function_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Function details resource requires database mode".to_string()),
+            });
+        }
+
+        let (schema, function) = parse_resource_path(uri, "functions")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params(
+                "function_details",
+                format!("Invalid schema '{}': {}", schema, e),
+            )
+        })?;
+        validate_identifier(&function).map_err(|e| {
+            McpError::invalid_params(
+                "function_details",
+                format!("Invalid function '{}': {}", function, e),
+            )
+        })?;
+
+        let functions = self
+            .metadata
+            .list_functions(Some(&schema))
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list functions: {}", e)))?;
+
+        let func_info = functions
+            .iter()
+            .find(|f| f.function_name.eq_ignore_ascii_case(&function))
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
+
+        let parameters = self
+            .metadata
+            .get_function_parameters(&schema, &function)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get function parameters: {}", e)))?;
+
+        let response = serde_json::json!({
+            "schema": func_info.schema_name,
+            "function": func_info.function_name,
+            "type": func_info.function_type,
+            "return_type": func_info.return_type,
+            "created": func_info.create_date,
+            "modified": func_info.modify_date,
+            "parameter_count": parameters.len(),
+            "parameters": parameters,
+            "definition": func_info.definition,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize function details: {}", e)))
+    }
+
+    /// List all triggers in the current database.
+    #[resource(
+        uri_pattern = "mssql://triggers",
+        name = "Triggers",
+        description = "List of database triggers",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_triggers(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Triggers resource requires database mode (connect to a specific database)".to_string()),
+            });
+        }
+
+        let triggers = self
+            .metadata
+            .list_triggers(None)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list triggers: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": triggers.len(),
+            "triggers": triggers,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize triggers: {}", e)))
+    }
+
+    /// Get detailed information about a specific trigger.
+    #[resource(
+        uri_pattern = "mssql://triggers/{schema}/{trigger}",
+        name = "Trigger Details",
+        description = "Get detailed information about a trigger including definition",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_trigger_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Trigger details resource requires database mode".to_string()),
+            });
+        }
+
+        let (schema, trigger) = parse_resource_path(uri, "triggers")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params(
+                "trigger_details",
+                format!("Invalid schema '{}': {}", schema, e),
+            )
+        })?;
+        validate_identifier(&trigger).map_err(|e| {
+            McpError::invalid_params(
+                "trigger_details",
+                format!("Invalid trigger '{}': {}", trigger, e),
+            )
+        })?;
+
+        let triggers = self
+            .metadata
+            .list_triggers(Some(&schema))
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list triggers: {}", e)))?;
+
+        let trigger_info = triggers
+            .iter()
+            .find(|t| t.trigger_name.eq_ignore_ascii_case(&trigger))
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
+
+        let response = serde_json::json!({
+            "schema": trigger_info.schema_name,
+            "trigger": trigger_info.trigger_name,
+            "parent_object": trigger_info.parent_object,
+            "type": trigger_info.trigger_type,
+            "status": if trigger_info.is_disabled { "Disabled" } else { "Enabled" },
+            "events": trigger_info.trigger_events,
+            "created": trigger_info.create_date,
+            "modified": trigger_info.modify_date,
+            "definition": trigger_info.definition,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize trigger details: {}", e)))
+    }
+
+    /// List server logins and their server role memberships.
+    #[resource(
+        uri_pattern = "mssql://security/logins",
+        name = "Logins",
+        description = "Server logins with type, status, default database, and server role memberships",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_security_logins(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        let logins = self
+            .metadata
+            .list_logins()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list logins: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": logins.len(),
+            "logins": logins,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize logins: {}", e)))
+    }
+
+    /// List linked servers and their login mappings.
+    #[resource(
+        uri_pattern = "mssql://linkedservers",
+        name = "Linked Servers",
+        description = "Linked servers with product, provider, data source, data access and RPC settings, timeouts, and login mappings",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_linked_servers(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        use crate::security::ValidationMode;
+
+        let servers = self
+            .metadata
+            .list_linked_servers()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list linked servers: {}", e)))?;
+
+        let security = &self.config().security;
+        let response = serde_json::json!({
+            "count": servers.len(),
+            "queries_allowed": security.allow_linked_servers
+                || security.validation_mode == ValidationMode::Unrestricted,
+            "linked_servers": servers,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize linked servers: {}", e)))
+    }
+
+    /// Availability Group health.
+    #[resource(
+        uri_pattern = "mssql://hadr/health",
+        name = "Availability Group Health",
+        description = "Synchronization state, queues and secondary lag of every availability database",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_ag_health(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        let response = self
+            .ag_health_report(None, None)
+            .await
+            .map_err(McpError::internal)?;
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize AG health: {}", e)))
+    }
+
+    /// Replication agent status.
+    #[resource(
+        uri_pattern = "mssql://replication/status",
+        name = "Replication Status",
+        description = "Run status and delivery latency of log reader and distribution agents",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_replication_status(
+        &self,
+        uri: &str,
+    ) -> Result<ResourceContents, McpError> {
+        let response = self
+            .replication_status_report(None)
+            .await
+            .map_err(McpError::internal)?;
+
+        ResourceContents::json(uri, &response).map_err(|e| {
+            McpError::internal(format!("Failed to serialize replication status: {}", e))
+        })
+    }
+
+    /// List database roles and their members.
+    #[resource(
+        uri_pattern = "mssql://security/roles",
+        name = "Database Roles",
+        description = "Database and application roles with their owners and members",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_security_roles(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some(
+                    "Roles resource requires database mode (connect to a specific database)"
+                        .to_string(),
+                ),
+            });
+        }
+
+        let roles = self
+            .metadata
+            .list_database_roles()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list roles: {}", e)))?;
+
+        let response = serde_json::json!({
+            "count": roles.len(),
+            "roles": roles,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize roles: {}", e)))
+    }
+
+    /// Get the effective permissions on a database object.
+    #[resource(
+        uri_pattern = "mssql://security/permissions/{schema}/{object}",
+        name = "Object Permissions",
+        description = "Effective permissions on an object: grants and denies on the object, its schema, and the database, plus fixed role permissions, expanded through role memberships",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_security_permissions(
+        &self,
+        uri: &str,
+    ) -> Result<ResourceContents, McpError> {
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Permissions resource requires database mode".to_string()),
+            });
+        }
+
+        let (schema, object) = parse_resource_path(uri, "security/permissions")?;
+
+        validate_identifier(&schema).map_err(|e| {
+            McpError::invalid_params(
+                "object_permissions",
+                format!("Invalid schema '{}': {}", schema, e),
+            )
+        })?;
+        validate_identifier(&object).map_err(|e| {
+            McpError::invalid_params(
+                "object_permissions",
+                format!("Invalid object '{}': {}", object, e),
+            )
+        })?;
+
+        let permissions = self
+            .metadata
+            .get_object_permissions(&schema, &object)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get permissions: {}", e)))?;
+
+        let principals_with = |names: &[&str]| -> Vec<String> {
+            let mut principals: Vec<String> = permissions
+                .iter()
+                .filter(|p| {
+                    p.state.starts_with("GRANT")
+                        && (p.permission_name == "CONTROL"
+                            || names.contains(&p.permission_name.as_str()))
+                })
+                .map(|p| p.principal_name.clone())
+                .collect();
+            principals.dedup();
+            principals
+        };
+
+        let response = serde_json::json!({
+            "schema": schema,
+            "object": object,
+            "summary": {
+                "can_read": principals_with(&["SELECT"]),
+                "can_write": principals_with(&["INSERT", "UPDATE", "DELETE"]),
+                "can_execute": principals_with(&["EXECUTE"]),
+                "can_alter": principals_with(&["ALTER"]),
+                "denied": permissions.iter()
+                    .filter(|p| p.state == "DENY")
+                    .map(|p| format!("{} ({})", p.principal_name, p.permission_name))
+                    .collect::<Vec<_>>(),
+            },
+            "note": "Members of sysadmin and the database owner (dbo) have all permissions; DENY overrides GRANT except for them.",
+            "count": permissions.len(),
+            "permissions": permissions,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize permissions: {}", e)))
+    }
+
+    /// Report table row counts and sizes with growth since the last read.
+    #[resource(
+        uri_pattern = "mssql://stats/table-sizes",
+        name = "Table Sizes",
+        description = "Per-table row counts and reserved, data, index, and unused space (KB), largest first, with growth since the previous read of this resource",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_table_sizes(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        use crate::database::TableSizeSnapshot;
+
+        if !self.is_database_mode() {
+            return Err(McpError::ResourceAccessDenied {
+                uri: uri.to_string(),
+                reason: Some("Table sizes resource requires database mode".to_string()),
+            });
+        }
+
+        let tables = self
+            .metadata
+            .list_table_sizes()
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get table sizes: {}", e)))?;
+
+        // Each read becomes the baseline for the next one
+        let snapshot = TableSizeSnapshot {
+            taken_at: chrono::Utc::now(),
+            tables,
+        };
+        let database = self.config().database.database.clone().unwrap_or_default();
+        let previous = self
+            .table_size_history
+            .record(&database, snapshot.clone())
+            .await;
+        let growth = previous.as_ref().map(|p| snapshot.growth_since(p));
+
+        let tables: Vec<serde_json::Value> = snapshot
+            .tables
+            .iter()
+            .map(|table| {
+                let mut entry = serde_json::json!(table);
+                if let Some(growth) = &growth {
+                    let key = crate::database::table_sizes::table_key(table);
+                    entry["growth"] = serde_json::json!(growth.get(&key));
+                }
+                entry
+            })
+            .collect();
+
+        let sum = |f: fn(&crate::database::TableSize) -> i64| -> i64 {
+            snapshot.tables.iter().map(f).sum()
+        };
+
+        let response = serde_json::json!({
+            "database": database,
+            "taken_at": snapshot.taken_at.to_rfc3339(),
+            "previous_snapshot_at": previous.as_ref().map(|p| p.taken_at.to_rfc3339()),
+            "totals": {
+                "table_count": snapshot.tables.len(),
+                "row_count": sum(|t| t.row_count),
+                "reserved_kb": sum(|t| t.reserved_kb),
+                "used_kb": sum(|t| t.used_kb),
+                "data_kb": sum(|t| t.data_kb),
+                "index_kb": sum(|t| t.index_kb),
+                "unused_kb": sum(|t| t.unused_kb),
+                "reserved_kb_growth": previous.as_ref().map(|p| {
+                    sum(|t| t.reserved_kb) - p.tables.iter().map(|t| t.reserved_kb).sum::<i64>()
+                }),
+            },
+            "dropped_tables": previous.as_ref().map(|p| snapshot.dropped_since(p)),
+            "tables": tables,
+        });
+
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize table sizes: {}", e)))
+    }
+
+    /// Get the status and queued changes of a query watch.
+    #[resource(
+        uri_pattern = "mssql://query-watches/{watch_id}",
+        name = "Query Watch",
+        description = "Status and pending result changes of a query watch (reading does not dequeue changes)",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_query_watch(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        use crate::database::QUERY_WATCH_URI_PREFIX;
+
+        let watch_id = uri
+            .strip_prefix(QUERY_WATCH_URI_PREFIX)
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
+
+        let mut status = self
+            .query_watch_manager
+            .status(watch_id, false)
+            .await
+            .map_err(|_| McpError::resource_not_found(uri))?;
+        self.mask_query_changes(&mut status.changes);
+
+        ResourceContents::json(uri, &status)
+            .map_err(|e| McpError::internal(format!("Failed to serialize query watch: {}", e)))
+    }
+
+    /// Get a page of the result of a completed async session.
+    #[resource(
+        uri_pattern = "mssql://sessions/{session_id}/results{?page,page_size}",
+        name = "Session Results",
+        description = "One page of the result of a completed async query session (page from 1, page_size default 100, max 10000), with the total row and page counts and the URI of the next page",
+        mime_type = "application/json"
+    )]
+    pub async fn resource_session_results(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        use crate::results::{slice_rows, SessionResultsPage};
+
+        let page = SessionResultsPage::from_uri(uri)
+            .map_err(|e| McpError::invalid_params("session_results", e.to_string()))?;
+
+        let state = self.state.read().await;
+        let session = state
+            .get_session(&page.session_id)
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
+        if session.status != SessionStatus::Completed {
+            return Err(McpError::invalid_params(
+                "session_results",
+                format!(
+                    "Session {} is not completed (status: {})",
+                    page.session_id, session.status
+                ),
+            ));
         }
+        let result = session
+            .result
+            .as_ref()
+            .ok_or_else(|| McpError::resource_not_found(uri))?;
 
-        analysis["warnings"] = json!(warnings);
+        let total_rows = result.rows.len();
+        let page_count = page.page_count(total_rows);
+        let rows = slice_rows(result, page.offset(), page.page_size);
+        let response = json!({
+            "session_id": page.session_id,
+            "page": page.page,
+            "page_size": page.page_size,
+            "page_count": page_count,
+            "total_rows": total_rows,
+            "offset": page.offset().min(total_rows),
+            "row_count": rows.rows.len(),
+            "truncated": result.truncated,
+            "columns": rows.columns,
+            "rows": rows.rows,
+            "next_page": (page.page < page_count)
+                .then(|| SessionResultsPage::uri(&page.session_id, page.page + 1, page.page_size)),
+        });
 
-        Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&analysis)
-                .unwrap_or_else(|_| "Analysis failed".to_string()),
-        ))
+        ResourceContents::json(uri, &response)
+            .map_err(|e| McpError::internal(format!("Failed to serialize session results: {}", e)))
     }
 
     // =========================================================================
-    // Connection Pool Metrics Tools
+    // Prompts - AI-assisted SQL generation and analysis
     // =========================================================================
 
-    /// Get connection pool metrics and statistics.
-    ///
-    /// Returns information about the connection pool including
-    /// active connections, idle connections, and pool configuration.
-    #[tool(description = "Get connection pool metrics including active connections, idle connections, and pool health.", read_only = true, idempotent = true)]
-    pub async fn get_pool_metrics(
+    /// Generate a SELECT query for a table with its schema information.
+    #[prompt(description = "Generate a SELECT query for a table with its schema information")]
+    pub async fn query_table(
         &self,
-        input: GetPoolMetricsInput,
-    ) -> Result<ToolOutput, McpError> {
-        debug!("Getting connection pool metrics");
+        schema: Option<String>,
+        table: String,
+        columns: Option<String>,
+        filter: Option<String>,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
 
-        let pool_status = self.pool.status();
+        // Get table columns from metadata
+        let column_info = self
+            .metadata
+            .get_table_columns(schema, &table)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
 
-        let mut response = json!({
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "pool": {
-                "total_connections": pool_status.total,
-                "available_connections": pool_status.available,
-                "in_use_connections": pool_status.in_use,
-                "max_connections": pool_status.max,
-                "utilization_percent": pool_status.utilization()
-            },
-            "config": {
-                "max_connections": self.config.database.pool.max_connections,
-                "min_connections": self.config.database.pool.min_connections,
-                "connection_timeout_seconds": self.config.database.pool.connection_timeout.as_secs(),
-                "idle_timeout_seconds": self.config.database.pool.idle_timeout.as_secs(),
+        if column_info.is_empty() {
+            return Err(McpError::invalid_params(
+                "query_table",
+                format!("Table not found: {}.{}", schema, table),
+            ));
+        }
+
+        // Build schema description
+        let schema_desc = column_info
+            .iter()
+            .map(|c| {
+                format!(
+                    "  - {} ({}{}){}",
+                    c.column_name,
+                    c.data_type,
+                    if c.is_nullable { ", nullable" } else { "" },
+                    if c.is_identity { " [IDENTITY]" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Suggest joins along foreign keys (best-effort)
+        let related = match self
+            .metadata
+            .list_foreign_keys(Some((schema, &table)))
+            .await
+        {
+            Ok(relationships) if !relationships.is_empty() => {
+                let joins = relationships
+                    .iter()
+                    .map(|fk| {
+                        let is_parent = fk.parent_schema.eq_ignore_ascii_case(schema)
+                            && fk.parent_table.eq_ignore_ascii_case(&table);
+                        let (other_schema, other_table) = if is_parent {
+                            (&fk.referenced_schema, &fk.referenced_table)
+                        } else {
+                            (&fk.parent_schema, &fk.parent_table)
+                        };
+                        format!(
+                            "  - JOIN [{}].[{}] ON {}",
+                            other_schema,
+                            other_table,
+                            join_condition(fk)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("## Related Tables (foreign keys)\n\n{}\n\n", joins)
             }
-        });
+            Ok(_) => String::new(),
+            Err(e) => {
+                warn!("Failed to load relationships for query_table prompt: {}", e);
+                String::new()
+            }
+        };
 
-        // Add health assessment
-        let healthy = pool_status.available > 0 || !pool_status.is_at_capacity();
-        response["health"] = json!({
-            "status": if healthy { "healthy" } else { "degraded" },
-            "has_available_connections": pool_status.available > 0,
-            "at_capacity": pool_status.is_at_capacity(),
-        });
+        let mut prompt_text = format!(
+            r#"Generate a SELECT query for the table [{schema}].[{table}].
 
-        if input.include_history {
-            // Get session state for historical context
-            let state = self.state.read().await;
-            response["sessions"] = json!({
-                "total": state.total_session_count(),
-                "running": state.running_session_count(),
-            });
-            response["transactions"] = json!({
-                "total": state.total_transaction_count(),
-                "active": state.active_transaction_count(),
-            });
+## Table Schema
+
+{schema_desc}
+
+{related}## Requirements
+"#
+        );
+
+        if let Some(cols) = &columns {
+            prompt_text.push_str(&format!("- Select only these columns: {}\n", cols));
+        } else {
+            prompt_text.push_str("- Select all relevant columns\n");
         }
 
-        info!(
-            "Pool metrics: {}/{} connections in use",
-            pool_status.in_use, pool_status.total
+        if let Some(f) = &filter {
+            prompt_text.push_str(&format!("- Filter condition: {}\n", f));
+        }
+
+        prompt_text.push_str(
+            r#"
+## Guidelines
+- Use proper bracket notation for identifiers: [schema].[table].[column]
+- Add appropriate TOP or OFFSET/FETCH for large tables
+- Consider using aliases for readability
+- Include ORDER BY for deterministic results
+"#,
         );
 
-        Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&response)
-                .unwrap_or_else(|_| "Failed to get pool metrics".to_string()),
-        ))
+        Ok(GetPromptResult {
+            description: Some(format!("Query builder for {}.{}", schema, table)),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
     }
 
-    /// Get internal server metrics.
+    /// Write a query against a JSON text column.
     ///
-    /// Returns metrics collected by the server including query counts,
-    /// latency statistics, cache performance, and transaction counts.
-    #[tool(description = "Get internal server metrics including query counts, latency, cache stats, and transaction counts.", read_only = true, idempotent = true)]
-    pub async fn get_internal_metrics(
+    /// Registered as `query_json_column`, alongside the tool of the same name.
+    #[prompt(
+        name = "query_json_column",
+        description = "Write a query against a column holding JSON, with the paths and types inferred from sampled rows and SQL Server JSON syntax rules"
+    )]
+    pub async fn query_json_column_prompt(
         &self,
-        input: GetInternalMetricsInput,
-    ) -> Result<ToolOutput, McpError> {
-        debug!("Getting internal server metrics");
+        schema: Option<String>,
+        table: String,
+        column: String,
+        question: Option<String>,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
+        validate_identifier(schema)
+            .and_then(|_| validate_identifier(&table))
+            .and_then(|_| validate_identifier(&column))
+            .map_err(|e| McpError::invalid_params("query_json_column", e.to_string()))?;
 
-        let snapshot = self.metrics.snapshot();
+        let json_schema = self
+            .json_column_schema(schema, &table, &column, 100)
+            .await
+            .map_err(|e| McpError::invalid_params("query_json_column", e))?;
 
-        let mut response = json!({
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "queries": {
-                "total": snapshot.queries_total,
-                "success": snapshot.queries_success,
-                "failed": snapshot.queries_failed,
-                "total_time_ms": snapshot.query_time_ms_total,
-            },
-            "transactions": {
-                "total": snapshot.transactions_total,
-                "committed": snapshot.transactions_committed,
-                "rolled_back": snapshot.transactions_rolled_back,
-            },
-            "cache": {
-                "hits": snapshot.cache_hits,
-                "misses": snapshot.cache_misses,
-            },
-            "network": {
-                "bytes_transferred": snapshot.bytes_transferred,
-            }
-        });
+        let paths = json_schema
+            .paths
+            .iter()
+            .map(|p| {
+                let types: Vec<&str> = p.types.iter().map(|t| t.as_str()).collect();
+                format!(
+                    "| `{}` | {} | {} | {}/{} | {} |",
+                    p.path,
+                    types.join(", "),
+                    p.sql_type,
+                    p.occurrences,
+                    json_schema.sampled_rows - json_schema.invalid_rows,
+                    p.example.as_deref().unwrap_or("").replace('|', "\\|")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
 
-        if input.include_rates {
-            response["rates"] = json!({
-                "query_success_rate_percent": snapshot.success_rate(),
-                "avg_query_time_ms": snapshot.avg_query_time_ms(),
-                "cache_hit_rate_percent": snapshot.cache_hit_rate(),
+        let task = question
+            .as_deref()
+            .map(|q| format!("Write a query that answers: {}", q))
+            .unwrap_or_else(|| {
+                "Write a query that returns the most useful fields as typed columns.".to_string()
             });
-        }
 
-        info!(
-            "Internal metrics: {} queries, {:.1}% success rate",
-            snapshot.queries_total,
-            snapshot.success_rate()
+        let prompt_text = format!(
+            r#"{task}
+
+The column [{schema}].[{table}].[{column}] holds JSON text. Paths found in {sampled} sampled rows ({invalid} not valid JSON){truncated}:
+
+| Path | JSON types | SQL type | Rows | Example |
+|------|------------|----------|------|---------|
+{paths}
+
+## SQL Server JSON rules
+- Paths start with `$`; `[*]` above marks array elements and is NOT valid in a query path
+- Quote keys that are not plain identifiers: `$."first order"`, never `$.first order` or `$['first order']`
+- `JSON_VALUE(col, path)` returns a scalar as nvarchar(4000) (NULL for objects/arrays or longer values); cast it, e.g. `TRY_CAST(JSON_VALUE(col, N'$.id') AS bigint)`
+- `JSON_QUERY(col, path)` returns an object or array as JSON text (NULL for scalars)
+- Expand arrays with `CROSS APPLY OPENJSON(col, N'$.items') WITH ([sku] nvarchar(100) N'$.sku', [meta] nvarchar(max) N'$.meta' AS JSON) AS j`; paths in WITH are relative to each element, and `N'$'` is the element itself
+- Paths are lax by default (missing keys give NULL); `strict $.key` raises an error instead
+- Filter out invalid documents with `ISJSON(col) = 1` before applying JSON functions
+- The `query_json_column` tool generates and runs these projections from the paths above
+"#,
+            task = task,
+            schema = schema,
+            table = table,
+            column = column,
+            sampled = json_schema.sampled_rows,
+            invalid = json_schema.invalid_rows,
+            truncated = if json_schema.truncated {
+                ", list truncated"
+            } else {
+                ""
+            },
+            paths = paths,
         );
 
-        Ok(ToolOutput::text(
-            serde_json::to_string_pretty(&response)
-                .unwrap_or_else(|_| "Failed to get internal metrics".to_string()),
-        ))
+        Ok(GetPromptResult {
+            description: Some(format!(
+                "JSON query builder for {}.{}.{}",
+                schema, table, column
+            )),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
     }
 
-    // -------------------------------------------------------------------------
-    // Validation Tools
-    // -------------------------------------------------------------------------
-
-    #[tool(description = "Validate SQL query syntax without executing. Use for dry-run validation before running DDL or complex queries.", read_only = true, idempotent = true)]
-    pub async fn validate_syntax_tool(
+    /// Write a MATCH query over the graph tables of the current database.
+    #[prompt(
+        name = "query_graph",
+        description = "Write a query over SQL Server graph (node/edge) tables using MATCH, with the graph schema, pseudo-columns and MATCH syntax rules"
+    )]
+    pub async fn query_graph_prompt(
         &self,
-        input: ValidateSyntaxInput,
-    ) -> Result<ToolOutput, McpError> {
-        debug!("Validating query syntax");
+        question: Option<String>,
+    ) -> Result<GetPromptResult, McpError> {
+        use crate::database::graph::{match_example, GraphKind};
 
-        // If a database is specified, switch to it first
-        let effective_query = if let Some(ref db) = input.database {
-            format!("USE [{}];\n{}", db.replace(']', "]]"), input.query)
-        } else {
-            input.query.clone()
-        };
+        let tables = self.graph_tables(None).await.map_err(McpError::internal)?;
+        if tables.is_empty() {
+            return Err(McpError::invalid_params(
+                "query_graph",
+                "No graph tables found in the current database",
+            ));
+        }
+
+        let table_rows = tables
+            .iter()
+            .map(|t| {
+                let connections: Vec<String> = t
+                    .connections
+                    .iter()
+                    .map(|(from, to)| format!("{} -> {}", from, to))
+                    .collect();
+                format!(
+                    "| {}.{} | {} | {} | {} |",
+                    t.schema_name,
+                    t.table_name,
+                    t.kind.as_str(),
+                    t.columns.join(", "),
+                    connections.join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let task = question
+            .as_deref()
+            .map(|q| format!("Write a query that answers: {}", q))
+            .unwrap_or_else(|| {
+                "Write a query that explores how the nodes are connected.".to_string()
+            });
+        let example = match_example(&tables).unwrap_or_default();
+
+        let prompt_text = format!(
+            r#"{task}
+
+The current database has these SQL Server graph tables:
+
+| Table | Type | Columns | Edge connects |
+|-------|------|---------|---------------|
+{table_rows}
+
+Pseudo-columns: node tables have `{node_pseudo}`; edge tables have `{edge_pseudo}`.
+
+## SQL Server graph rules
+- Traverse edges with `MATCH` in the WHERE clause, listing the tables comma-separated in FROM: `FROM Person AS p, FriendOf AS f, Person AS p2 WHERE MATCH(p-(f)->p2)`
+- Do not JOIN on `$from_id`/`$to_id`/`$node_id` or on columns named like `$node_id_<hex>` or `graph_id_<hex>`; these are internal
+- Arrows may point either way: `MATCH(p<-(f)-p2)`; chain hops `MATCH(a-(e1)->b-(e2)->c)` or combine patterns with AND inside MATCH
+- A node alias may repeat in a pattern, an edge alias may not; use a second alias of the same edge table for a second hop
+- MATCH cannot be combined with OR or NOT, and the matched tables cannot be joined with ANSI JOIN syntax; add other conditions with AND
+- Variable-length paths (SQL Server 2019+): declare the tables `FOR PATH` and use `MATCH(SHORTEST_PATH(a(-(e)->b)+))`, projecting path columns with `STRING_AGG(b.name, '->') WITHIN GROUP (GRAPH PATH)` or `LAST_VALUE(b.name) WITHIN GROUP (GRAPH PATH)`
+- Insert an edge with the node IDs: `INSERT INTO FriendOf ($from_id, $to_id) VALUES ((SELECT $node_id FROM Person WHERE id = 1), (SELECT $node_id FROM Person WHERE id = 2))`
+
+Example for this schema:
+```sql
+{example}
+```
+"#,
+            task = task,
+            table_rows = table_rows,
+            node_pseudo = GraphKind::Node
+                .pseudo_columns()
+                .iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>()
+                .join("`, `"),
+            edge_pseudo = GraphKind::Edge
+                .pseudo_columns()
+                .iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>()
+                .join("`, `"),
+            example = example,
+        );
+
+        Ok(GetPromptResult {
+            description: Some("Graph MATCH query builder".to_string()),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
+    }
 
-        let result = self
-            .executor
-            .validate_syntax(&effective_query)
+    /// Analyze a table's schema and suggest optimizations or improvements.
+    #[prompt(description = "Analyze a table's schema and suggest optimizations or improvements")]
+    pub async fn analyze_schema(
+        &self,
+        schema: Option<String>,
+        table: String,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
+
+        // Get table columns
+        let columns = self
+            .metadata
+            .get_table_columns(schema, &table)
             .await
-            .map_err(|e| McpError::internal(format!("Validation failed: {}", e)))?;
+            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
 
-        if result.valid {
-            info!("Query syntax is valid");
-        } else {
-            info!(
-                error = result.error_message.as_deref().unwrap_or("Unknown error"),
-                line = result.error_line,
-                "Query syntax validation failed"
-            );
+        if columns.is_empty() {
+            return Err(McpError::invalid_params(
+                "analyze_schema",
+                format!("Table not found: {}.{}", schema, table),
+            ));
         }
 
-        Ok(ToolOutput::text(result.to_message()))
-    }
+        let column_desc = columns
+            .iter()
+            .map(|c| {
+                format!(
+                    "| {} | {} | {} | {} | {} | {} |",
+                    c.column_name,
+                    c.data_type,
+                    c.max_length.map(|l| l.to_string()).unwrap_or("-".to_string()),
+                    if c.is_nullable { "Yes" } else { "No" },
+                    if c.is_identity { "Yes" } else { "No" },
+                    c.default_value.as_deref().unwrap_or("-")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
 
-    // =========================================================================
-    // Resources (read-only metadata access)
-    // =========================================================================
+        let prompt_text = format!(
+            r#"Analyze the schema of table [{schema}].[{table}] and provide recommendations.
 
-    /// Get SQL Server information including version, edition, and configuration.
-    #[resource(
-        uri_pattern = "mssql://server/info",
-        name = "Server Information",
-        description = "SQL Server version, edition, and configuration details",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_server_info(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        let info = self
-            .metadata
-            .get_server_info()
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get server info: {}", e)))?;
+## Current Schema
 
-        ResourceContents::json(uri, &info)
-            .map_err(|e| McpError::internal(format!("Failed to serialize server info: {}", e)))
-    }
+| Column | Type | Max Length | Nullable | Identity | Default |
+|--------|------|------------|----------|----------|---------|
+{column_desc}
 
-    /// List all databases on the server.
-    #[resource(
-        uri_pattern = "mssql://databases",
-        name = "Databases",
-        description = "List of all databases on the server",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_databases(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        let databases = self
-            .metadata
-            .list_databases()
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list databases: {}", e)))?;
+## Analysis Requested
 
-        let response = serde_json::json!({
-            "count": databases.len(),
-            "databases": databases,
-        });
+Please analyze this schema and provide:
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize databases: {}", e)))
-    }
+1. **Data Type Review**
+   - Are the data types appropriate for the column names/purposes?
+   - Could any types be optimized (e.g., varchar(max) -> varchar(n))?
 
-    /// List all schemas in the current database.
-    #[resource(
-        uri_pattern = "mssql://schemas",
-        name = "Schemas",
-        description = "List of schemas in the current database",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_schemas(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Schemas resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+2. **Nullability Assessment**
+   - Which nullable columns might benefit from NOT NULL constraints?
+   - Are there potential data integrity issues?
 
-        let schemas = self
-            .metadata
-            .list_schemas()
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list schemas: {}", e)))?;
+3. **Indexing Suggestions**
+   - Based on likely query patterns, which columns should be indexed?
+   - Any composite index recommendations?
 
-        let response = serde_json::json!({
-            "count": schemas.len(),
-            "schemas": schemas,
-        });
+4. **Best Practices**
+   - Naming convention compliance
+   - Potential normalization issues
+   - Missing audit columns (CreatedAt, UpdatedAt, etc.)
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize schemas: {}", e)))
+5. **Performance Considerations**
+   - Data type sizes and storage efficiency
+   - Potential query performance impacts
+"#
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Schema analysis for {}.{}", schema, table)),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
     }
 
-    /// List all tables in the current database.
-    #[resource(
-        uri_pattern = "mssql://tables",
-        name = "Tables",
-        description = "List of all tables with row counts and sizes",
-        mime_type = "application/json"
+    /// Profile a table server-side and ask for data quality findings.
+    #[prompt(
+        description = "Profile a table's columns (null rates, distinct counts, ranges, value patterns) and flag data quality issues with suggested constraints"
     )]
-    pub async fn resource_tables(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Tables resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+    pub async fn data_quality(
+        &self,
+        schema: Option<String>,
+        table: String,
+    ) -> Result<GetPromptResult, McpError> {
+        use crate::constants::DEFAULT_PROFILE_SAMPLE_ROWS;
 
-        let tables = self
-            .metadata
-            .list_tables(None)
+        let schema = schema.as_deref().unwrap_or("dbo");
+        validate_identifier(schema)
+            .map_err(|e| McpError::invalid_params("schema", e.to_string()))?;
+        validate_identifier(&table)
+            .map_err(|e| McpError::invalid_params("table", e.to_string()))?;
+
+        let profile = self
+            .table_profile(schema, &table, None, DEFAULT_PROFILE_SAMPLE_ROWS, None)
             .await
-            .map_err(|e| McpError::internal(format!("Failed to list tables: {}", e)))?;
+            .map_err(|e| McpError::invalid_params("data_quality", e))?;
 
-        let response = serde_json::json!({
-            "count": tables.len(),
-            "tables": tables,
-        });
+        let column_rows = profile
+            .columns
+            .iter()
+            .map(|c| {
+                let patterns = c
+                    .patterns
+                    .iter()
+                    .map(|p| format!("`{}` {}%", p.pattern.replace('|', "\\|"), p.percent))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "| {} | {} | {} ({}%) | {} | {} | {} | {} |",
+                    c.name,
+                    c.data_type,
+                    c.null_count,
+                    c.null_percent,
+                    c.distinct_count
+                        .map(|d| d.to_string())
+                        .unwrap_or("-".to_string()),
+                    c.min.as_deref().unwrap_or("-").replace('|', "\\|"),
+                    c.max.as_deref().unwrap_or("-").replace('|', "\\|"),
+                    if patterns.is_empty() {
+                        "-".to_string()
+                    } else {
+                        patterns
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize tables: {}", e)))
-    }
+        let coverage = if profile.rows_profiled < profile.sample_limit as i64 {
+            format!("all {} rows", profile.rows_profiled)
+        } else {
+            format!("a sample of the first {} rows", profile.rows_profiled)
+        };
 
-    /// Get detailed information about a specific table.
-    #[resource(
-        uri_pattern = "mssql://tables/{schema}/{table}",
-        name = "Table Details",
-        description = "Get detailed information about a specific table including columns",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_table_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Table details resource requires database mode".to_string()),
-            });
-        }
+        let prompt_text = format!(
+            r#"Review the data quality of table [{schema}].[{table}].
 
-        // Parse schema and table from URI: mssql://tables/{schema}/{table}
-        let (schema, table) = parse_resource_path(uri, "tables")?;
+The profile below was computed on the server over {coverage}. Patterns are value shapes of character columns: `A`/`a` are upper/lowercase letters (`A+`/`a+` a run of them) and `9` a digit.
 
-        // Validate identifiers
-        validate_identifier(&schema).map_err(|e| {
-            McpError::invalid_params("table_details", format!("Invalid schema '{}': {}", schema, e))
-        })?;
-        validate_identifier(&table).map_err(|e| {
-            McpError::invalid_params("table_details", format!("Invalid table '{}': {}", table, e))
-        })?;
+## Column Profile
 
-        let columns = self
-            .metadata
-            .get_table_columns(&schema, &table)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
+| Column | Type | Nulls | Distinct | Min | Max | Top Patterns |
+|--------|------|-------|----------|-----|-----|--------------|
+{column_rows}
 
-        if columns.is_empty() {
-            return Err(McpError::resource_not_found(uri));
-        }
+## Requested Output
 
-        let response = serde_json::json!({
-            "schema": schema,
-            "table": table,
-            "column_count": columns.len(),
-            "columns": columns,
-        });
+1. **Anomalies**: Flag suspicious findings, such as columns that are almost never or always null, unexpected ranges (negative quantities, future or sentinel dates like 1900-01-01), low-cardinality columns that may hide typos, and minority patterns that break a column's dominant format.
+2. **Proposed Constraints**: Suggest `NOT NULL`, `CHECK`, `UNIQUE`, `DEFAULT` and foreign key constraints, and data type changes the profile supports, as T-SQL DDL. Note which rows would violate each constraint and how to find them.
+3. **Follow-up Queries**: Give SELECT statements that list the offending rows so the findings can be verified before constraints are added.
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize table details: {}", e)))
+Keep in mind that a sample may miss rare values; say when a finding needs a full-table check.
+"#
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Data quality review for {}.{}", schema, table)),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
     }
 
-    /// List all views in the current database.
-    #[resource(
-        uri_pattern = "mssql://views",
-        name = "Views",
-        description = "List of all views in the database",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_views(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Views resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+    /// Describe the foreign key relationships of a schema as an ER model.
+    #[prompt(
+        description = "Describe the relationships between a schema's tables and draw them as a Mermaid ER diagram"
+    )]
+    pub async fn describe_relationships(
+        &self,
+        schema: Option<String>,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
+        validate_identifier(schema)
+            .map_err(|e| McpError::invalid_params("schema", e.to_string()))?;
 
-        let views = self
+        let relationships: Vec<ForeignKeyRelationship> = self
             .metadata
-            .list_views(None)
+            .list_foreign_keys(None)
             .await
-            .map_err(|e| McpError::internal(format!("Failed to list views: {}", e)))?;
+            .map_err(|e| McpError::internal(format!("Failed to list foreign keys: {}", e)))?
+            .into_iter()
+            .filter(|fk| {
+                fk.parent_schema.eq_ignore_ascii_case(schema)
+                    || fk.referenced_schema.eq_ignore_ascii_case(schema)
+            })
+            .collect();
+        let tables = self
+            .metadata
+            .list_tables(Some(schema))
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to list tables: {}", e)))?;
 
-        let response = serde_json::json!({
-            "count": views.len(),
-            "views": views,
-        });
+        if tables.is_empty() && relationships.is_empty() {
+            return Err(McpError::invalid_params(
+                "describe_relationships",
+                format!("No tables found in schema {}", schema),
+            ));
+        }
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize views: {}", e)))
-    }
+        let relationship_rows = relationships
+            .iter()
+            .map(|fk| {
+                let status = match (fk.is_disabled, fk.is_not_trusted) {
+                    (true, _) => "Disabled",
+                    (false, true) => "Not trusted",
+                    (false, false) => "Enabled",
+                };
+                format!(
+                    "| {}.{} ({}) | {}.{} ({}) | {} | {} | {} |",
+                    fk.parent_schema,
+                    fk.parent_table,
+                    fk.parent_columns.join(", "),
+                    fk.referenced_schema,
+                    fk.referenced_table,
+                    fk.referenced_columns.join(", "),
+                    fk.constraint_name,
+                    fk.on_delete,
+                    status
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
 
-    /// Get detailed information about a specific view.
-    #[resource(
-        uri_pattern = "mssql://views/{schema}/{view}",
-        name = "View Details",
-        description = "Get detailed information about a specific view including definition",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_view_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("View details resource requires database mode".to_string()),
-            });
-        }
+        let unrelated = tables
+            .iter()
+            .filter(|t| {
+                !relationships.iter().any(|fk| {
+                    (fk.parent_schema == t.schema_name && fk.parent_table == t.table_name)
+                        || (fk.referenced_schema == t.schema_name
+                            && fk.referenced_table == t.table_name)
+                })
+            })
+            .map(|t| format!("- {}.{}", t.schema_name, t.table_name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let unrelated = if unrelated.is_empty() {
+            "None".to_string()
+        } else {
+            unrelated
+        };
 
-        let (schema, view) = parse_resource_path(uri, "views")?;
+        let draft = to_mermaid(&relationships);
 
-        validate_identifier(&schema).map_err(|e| {
-            McpError::invalid_params("view_details", format!("Invalid schema '{}': {}", schema, e))
-        })?;
-        validate_identifier(&view).map_err(|e| {
-            McpError::invalid_params("view_details", format!("Invalid view '{}': {}", view, e))
-        })?;
+        let prompt_text = format!(
+            r#"Describe the data model of schema [{schema}] from its foreign key relationships.
 
-        let views = self
-            .metadata
-            .list_views(Some(&schema))
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list views: {}", e)))?;
+## Foreign Keys ({count})
 
-        let view_info = views
-            .iter()
-            .find(|v| v.view_name.eq_ignore_ascii_case(&view))
-            .ok_or_else(|| McpError::resource_not_found(uri))?;
+| Child (columns) | Parent (columns) | Constraint | On Delete | Status |
+|-----------------|------------------|------------|-----------|--------|
+{relationship_rows}
 
-        let response = serde_json::json!({
-            "schema": view_info.schema_name,
-            "view": view_info.view_name,
-            "definition": view_info.definition,
-            "is_updatable": view_info.is_updatable,
-        });
+## Tables Without Relationships
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize view details: {}", e)))
-    }
+{unrelated}
 
-    /// List all stored procedures in the current database.
-    #[resource(
-        uri_pattern = "mssql://procedures",
-        name = "Stored Procedures",
-        description = "List of stored procedures",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_procedures(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Procedures resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+## Draft Diagram
 
-        let procedures = self
-            .metadata
-            .list_procedures(None)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list procedures: {}", e)))?;
+```mermaid
+{draft}
+```
 
-        let response = serde_json::json!({
-            "count": procedures.len(),
-            "procedures": procedures,
-        });
+## Requested Output
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize procedures: {}", e)))
+1. **Entity Overview**: Group the tables into subject areas and explain what each central entity represents.
+2. **Relationship Description**: For each relationship, state the cardinality in plain language (e.g. "a customer places many orders"), and call out self-references, many-to-many junction tables and cascading deletes.
+3. **Mermaid ER Diagram**: Produce a complete `erDiagram`, refining the draft with correct cardinalities (optional vs. mandatory sides) and key attributes (PK/FK) for each entity.
+4. **Observations**: Flag disabled or untrusted keys, tables without relationships that look like they should have them (e.g. columns named like `CustomerId`), and likely missing foreign keys.
+"#,
+            count = relationships.len()
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!(
+                "Relationships and ER diagram for schema {}",
+                schema
+            )),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
     }
 
-    /// Get detailed information about a specific stored procedure.
-    #[resource(
-        uri_pattern = "mssql://procedures/{schema}/{procedure}",
-        name = "Procedure Details",
-        description = "Get detailed information about a stored procedure including parameters",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_procedure_details(
+    /// Generate an INSERT statement template for a table.
+    #[prompt(description = "Generate an INSERT statement template for a table")]
+    pub async fn generate_insert(
         &self,
-        uri: &str,
-    ) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Procedure details resource requires database mode".to_string()),
-            });
+        schema: Option<String>,
+        table: String,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
+
+        // Get table columns
+        let columns = self
+            .metadata
+            .get_table_columns(schema, &table)
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
+
+        if columns.is_empty() {
+            return Err(McpError::invalid_params(
+                "generate_insert",
+                format!("Table not found: {}.{}", schema, table),
+            ));
         }
 
-        let (schema, procedure) = parse_resource_path(uri, "procedures")?;
+        // Filter out identity and computed columns
+        let insertable_columns: Vec<_> = columns
+            .iter()
+            .filter(|c| !c.is_identity && !c.is_computed)
+            .collect();
 
-        validate_identifier(&schema).map_err(|e| {
-            McpError::invalid_params(
-                "procedure_details",
-                format!("Invalid schema '{}': {}", schema, e),
-            )
-        })?;
-        validate_identifier(&procedure).map_err(|e| {
-            McpError::invalid_params(
-                "procedure_details",
-                format!("Invalid procedure '{}': {}", procedure, e),
-            )
-        })?;
+        let column_list = insertable_columns
+            .iter()
+            .map(|c| format!("[{}]", c.column_name))
+            .collect::<Vec<_>>()
+            .join(", ");
 
-        let definition = self
-            .metadata
-            .get_procedure_definition(&schema, &procedure)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get procedure definition: {}", e)))?;
+        let value_placeholders = insertable_columns
+            .iter()
+            .map(|c| {
+                let placeholder = match c.data_type.to_uppercase().as_str() {
+                    "INT" | "BIGINT" | "SMALLINT" | "TINYINT" => "0".to_string(),
+                    "BIT" => "0".to_string(),
+                    "DECIMAL" | "NUMERIC" | "MONEY" | "SMALLMONEY" => "0.00".to_string(),
+                    "FLOAT" | "REAL" => "0.0".to_string(),
+                    "DATE" => "'YYYY-MM-DD'".to_string(),
+                    "TIME" => "'HH:MM:SS'".to_string(),
+                    "DATETIME" | "DATETIME2" | "SMALLDATETIME" => "'YYYY-MM-DD HH:MM:SS'".to_string(),
+                    "UNIQUEIDENTIFIER" => "NEWID()".to_string(),
+                    _ => format!("N'<{}>'", c.column_name),
+                };
+                format!("{} /* {} {} */", placeholder, c.column_name, c.data_type)
+            })
+            .collect::<Vec<_>>()
+            .join(",\n    ");
 
-        let parameters = self
-            .metadata
-            .get_procedure_parameters(&schema, &procedure)
-            .await
-            .map_err(|e| {
-                McpError::internal(format!("Failed to get procedure parameters: {}", e))
-            })?;
+        let column_desc = insertable_columns
+            .iter()
+            .map(|c| {
+                format!(
+                    "- {} ({}){}",
+                    c.column_name,
+                    c.data_type,
+                    if !c.is_nullable { " - REQUIRED" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
 
-        let response = serde_json::json!({
-            "schema": schema,
-            "procedure": procedure,
-            "definition": definition,
-            "parameter_count": parameters.len(),
-            "parameters": parameters,
-        });
+        let prompt_text = format!(
+            r#"Generate an INSERT statement for [{schema}].[{table}].
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize procedure details: {}", e)))
-    }
+## Insertable Columns
 
-    /// List all user-defined functions in the current database.
-    #[resource(
-        uri_pattern = "mssql://functions",
-        name = "Functions",
-        description = "List of user-defined functions (scalar and table-valued)",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_functions(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Functions resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+{column_desc}
 
-        let functions = self
-            .metadata
-            .list_functions(None)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list functions: {}", e)))?;
+## Template
 
-        let response = serde_json::json!({
-            "count": functions.len(),
-            "functions": functions,
-        });
+```sql
+INSERT INTO [{schema}].[{table}] (
+    {column_list}
+)
+VALUES (
+    {value_placeholders}
+);
+```
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize functions: {}", e)))
-    }
+## Instructions
 
-    /// Get detailed information about a specific user-defined function.
-    #[resource(
-        uri_pattern = "mssql://functions/{schema}/{function}",
-        name = "Function Details",
-        description = "Get detailed information about a user-defined function including parameters",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_function_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Function details resource requires database mode".to_string()),
-            });
-        }
+Replace the placeholder values with actual data. Note:
+- Columns marked REQUIRED cannot be NULL
+- String values should use N'...' for Unicode support
+- Date/time values should use ISO format
+- UNIQUEIDENTIFIER can use NEWID() for auto-generation
+"#
+        );
 
-        let (schema, function) = parse_resource_path(uri, "functions")?;
+        Ok(GetPromptResult {
+            description: Some(format!("INSERT template for {}.{}", schema, table)),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
+    }
 
-        validate_identifier(&schema).map_err(|e| {
-            McpError::invalid_params(
-                "function_details",
-                format!("Invalid schema '{}': {}", schema, e),
-            )
-        })?;
-        validate_identifier(&function).map_err(|e| {
-            McpError::invalid_params(
-                "function_details",
-                format!("Invalid function '{}': {}", function, e),
-            )
-        })?;
+    /// Explain what a stored procedure does and how to call it.
+    #[prompt(description = "Explain what a stored procedure does and how to call it")]
+    pub async fn explain_procedure(
+        &self,
+        schema: Option<String>,
+        procedure: String,
+    ) -> Result<GetPromptResult, McpError> {
+        let schema = schema.as_deref().unwrap_or("dbo");
 
-        let functions = self
+        // Get procedure definition
+        let definition = self
             .metadata
-            .list_functions(Some(&schema))
+            .get_procedure_definition(schema, &procedure)
             .await
-            .map_err(|e| McpError::internal(format!("Failed to list functions: {}", e)))?;
-
-        let func_info = functions
-            .iter()
-            .find(|f| f.function_name.eq_ignore_ascii_case(&function))
-            .ok_or_else(|| McpError::resource_not_found(uri))?;
+            .map_err(|e| McpError::internal(format!("Failed to get procedure definition: {}", e)))?;
 
+        // Get procedure parameters
         let parameters = self
             .metadata
-            .get_function_parameters(&schema, &function)
+            .get_procedure_parameters(schema, &procedure)
             .await
-            .map_err(|e| McpError::internal(format!("Failed to get function parameters: {}", e)))?;
+            .map_err(|e| McpError::internal(format!("Failed to get procedure parameters: {}", e)))?;
 
-        let response = serde_json::json!({
-            "schema": func_info.schema_name,
-            "function": func_info.function_name,
-            "type": func_info.function_type,
-            "return_type": func_info.return_type,
-            "created": func_info.create_date,
-            "modified": func_info.modify_date,
-            "parameter_count": parameters.len(),
-            "parameters": parameters,
-            "definition": func_info.definition,
-        });
+        let param_desc = if parameters.is_empty() {
+            "This procedure has no parameters.".to_string()
+        } else {
+            parameters
+                .iter()
+                .map(|p| {
+                    format!(
+                        "- {} ({}){}{}",
+                        p.parameter_name,
+                        p.data_type,
+                        if p.is_output { " OUTPUT" } else { "" },
+                        if p.has_default { " [has default]" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize function details: {}", e)))
-    }
+        let definition_text = definition.unwrap_or_else(|| "(Definition not available)".to_string());
 
-    /// List all triggers in the current database.
-    #[resource(
-        uri_pattern = "mssql://triggers",
-        name = "Triggers",
-        description = "List of database triggers",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_triggers(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Triggers resource requires database mode (connect to a specific database)".to_string()),
-            });
-        }
+        let prompt_text = format!(
+            r#"Explain the stored procedure [{schema}].[{procedure}].
 
-        let triggers = self
-            .metadata
-            .list_triggers(None)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list triggers: {}", e)))?;
+## Parameters
 
-        let response = serde_json::json!({
-            "count": triggers.len(),
-            "triggers": triggers,
-        });
+{param_desc}
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize triggers: {}", e)))
-    }
+## Definition
 
-    /// Get detailed information about a specific trigger.
-    #[resource(
-        uri_pattern = "mssql://triggers/{schema}/{trigger}",
-        name = "Trigger Details",
-        description = "Get detailed information about a trigger including definition",
-        mime_type = "application/json"
-    )]
-    pub async fn resource_trigger_details(&self, uri: &str) -> Result<ResourceContents, McpError> {
-        if !self.is_database_mode() {
-            return Err(McpError::ResourceAccessDenied {
-                uri: uri.to_string(),
-                reason: Some("Trigger details resource requires database mode".to_string()),
-            });
-        }
+```sql
+{definition_text}
+```
 
-        let (schema, trigger) = parse_resource_path(uri, "triggers")?;
+## Please Explain
 
-        validate_identifier(&schema).map_err(|e| {
-            McpError::invalid_params(
-                "trigger_details",
-                format!("Invalid schema '{}': {}", schema, e),
-            )
-        })?;
-        validate_identifier(&trigger).map_err(|e| {
-            McpError::invalid_params(
-                "trigger_details",
-                format!("Invalid trigger '{}': {}", trigger, e),
-            )
-        })?;
+1. **Purpose**: What does this procedure do?
+2. **Parameters**: Explain each parameter and its purpose
+3. **Logic Flow**: Step-by-step explanation of what happens
+4. **Return Values**: What does it return? (result sets, output parameters)
+5. **Example Usage**: Show how to call this procedure
+6. **Potential Issues**: Any edge cases or error conditions to watch for?
+"#
+        );
 
-        let triggers = self
-            .metadata
-            .list_triggers(Some(&schema))
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to list triggers: {}", e)))?;
+        Ok(GetPromptResult {
+            description: Some(format!("Explanation of {}.{}", schema, procedure)),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
+    }
 
-        let trigger_info = triggers
-            .iter()
-            .find(|t| t.trigger_name.eq_ignore_ascii_case(&trigger))
-            .ok_or_else(|| McpError::resource_not_found(uri))?;
+    /// Analyze a SQL query and suggest optimizations.
+    #[prompt(description = "Analyze a SQL query and suggest optimizations")]
+    pub fn optimize_query(
+        &self,
+        query: String,
+    ) -> Result<GetPromptResult, McpError> {
+        let prompt_text = format!(
+            r#"Analyze and optimize the following SQL query.
 
-        let response = serde_json::json!({
-            "schema": trigger_info.schema_name,
-            "trigger": trigger_info.trigger_name,
-            "parent_object": trigger_info.parent_object,
-            "type": trigger_info.trigger_type,
-            "status": if trigger_info.is_disabled { "Disabled" } else { "Enabled" },
-            "events": trigger_info.trigger_events,
-            "created": trigger_info.create_date,
-            "modified": trigger_info.modify_date,
-            "definition": trigger_info.definition,
-        });
+## Original Query
 
-        ResourceContents::json(uri, &response)
-            .map_err(|e| McpError::internal(format!("Failed to serialize trigger details: {}", e)))
-    }
+```sql
+{query}
+```
 
-    // =========================================================================
-    // Prompts - AI-assisted SQL generation and analysis
-    // =========================================================================
+## Analysis Requested
 
-    /// Generate a SELECT query for a table with its schema information.
-    #[prompt(description = "Generate a SELECT query for a table with its schema information")]
-    pub async fn query_table(
-        &self,
-        schema: Option<String>,
-        table: String,
-        columns: Option<String>,
-        filter: Option<String>,
-    ) -> Result<GetPromptResult, McpError> {
-        let schema = schema.as_deref().unwrap_or("dbo");
+1. **Query Structure**
+   - Is the query logically correct?
+   - Any syntax issues or anti-patterns?
 
-        // Get table columns from metadata
-        let column_info = self
-            .metadata
-            .get_table_columns(schema, &table)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
+2. **Performance Issues**
+   - Potential table scans
+   - Missing indexes
+   - Inefficient joins
+   - Subquery vs JOIN considerations
+
+3. **Optimizations**
+   - Rewrite suggestions
+   - Index recommendations
+   - Query hints if appropriate
+
+4. **Best Practices**
+   - SET NOCOUNT ON for procedures
+   - Avoiding SELECT *
+   - Proper use of CTEs vs temp tables
 
-        if column_info.is_empty() {
-            return Err(McpError::invalid_params(
-                "query_table",
-                format!("Table not found: {}.{}", schema, table),
-            ));
-        }
+5. **Optimized Version**
+   - Provide the optimized query with comments
+"#
+        );
 
-        // Build schema description
-        let schema_desc = column_info
-            .iter()
-            .map(|c| {
-                format!(
-                    "  - {} ({}{}){}",
-                    c.column_name,
-                    c.data_type,
-                    if c.is_nullable { ", nullable" } else { "" },
-                    if c.is_identity { " [IDENTITY]" } else { "" }
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        Ok(GetPromptResult {
+            description: Some("Query optimization analysis".to_string()),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: Content::text(prompt_text),
+            }],
+        })
+    }
 
+    /// Help debug a SQL Server error with context and suggestions.
+    #[prompt(description = "Help debug a SQL Server error with context and suggestions")]
+    pub fn debug_error(
+        &self,
+        error: String,
+        query: Option<String>,
+    ) -> Result<GetPromptResult, McpError> {
         let mut prompt_text = format!(
-            r#"Generate a SELECT query for the table [{schema}].[{table}].
-
-## Table Schema
+            r#"Help debug this SQL Server error.
 
-{schema_desc}
+## Error Message
 
-## Requirements
+```
+{error}
+```
 "#
         );
 
-        if let Some(cols) = &columns {
-            prompt_text.push_str(&format!("- Select only these columns: {}\n", cols));
-        } else {
-            prompt_text.push_str("- Select all relevant columns\n");
-        }
+        if let Some(q) = &query {
+            prompt_text.push_str(&format!(
+                r#"
+## Query That Caused the Error
 
-        if let Some(f) = &filter {
-            prompt_text.push_str(&format!("- Filter condition: {}\n", f));
+```sql
+{q}
+```
+"#
+            ));
         }
 
         prompt_text.push_str(
             r#"
-## Guidelines
-- Use proper bracket notation for identifiers: [schema].[table].[column]
-- Add appropriate TOP or OFFSET/FETCH for large tables
-- Consider using aliases for readability
-- Include ORDER BY for deterministic results
+## Please Provide
+
+1. **Error Explanation**: What does this error mean?
+2. **Common Causes**: Why does this error typically occur?
+3. **Diagnosis Steps**: How to investigate further
+4. **Solutions**: How to fix the issue
+5. **Prevention**: How to avoid this error in the future
 "#,
         );
 
         Ok(GetPromptResult {
-            description: Some(format!("Query builder for {}.{}", schema, table)),
+            description: Some("SQL Server error debugging".to_string()),
             messages: vec![PromptMessage {
                 role: Role::User,
                 content: Content::text(prompt_text),
@@ -3436,640 +11513,1602 @@ impl MssqlMcpServer {
         })
     }
 
-    /// Analyze a table's schema and suggest optimizations or improvements.
-    #[prompt(description = "Analyze a table's schema and suggest optimizations or improvements")]
-    pub async fn analyze_schema(
+    /// Guided onboarding for an unfamiliar database.
+    #[prompt(
+        description = "Get a high-level overview of the database and an exploration plan for getting started"
+    )]
+    pub async fn explore_database(
         &self,
         schema: Option<String>,
-        table: String,
     ) -> Result<GetPromptResult, McpError> {
-        let schema = schema.as_deref().unwrap_or("dbo");
+        if let Some(s) = &schema {
+            validate_identifier(s)
+                .map_err(|e| McpError::invalid_params("schema", e.to_string()))?;
+        }
+        let schema_filter = schema
+            .as_deref()
+            .map(|s| format!("AND s.name = '{}'", s.replace('\'', "''")))
+            .unwrap_or_default();
 
-        // Get table columns
-        let columns = self
+        let mut tables = self
             .metadata
-            .get_table_columns(schema, &table)
+            .list_tables(schema.as_deref())
             .await
-            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
-
-        if columns.is_empty() {
-            return Err(McpError::invalid_params(
-                "analyze_schema",
-                format!("Table not found: {}.{}", schema, table),
-            ));
-        }
+            .map_err(|e| McpError::internal(format!("Failed to list tables: {}", e)))?;
+        let table_count = tables.len();
+        tables.sort_by_key(|t| std::cmp::Reverse(t.row_count.unwrap_or(0)));
 
-        let column_desc = columns
+        let largest = tables
             .iter()
-            .map(|c| {
+            .take(10)
+            .map(|t| {
                 format!(
-                    "| {} | {} | {} | {} | {} | {} |",
-                    c.column_name,
-                    c.data_type,
-                    c.max_length.map(|l| l.to_string()).unwrap_or("-".to_string()),
-                    if c.is_nullable { "Yes" } else { "No" },
-                    if c.is_identity { "Yes" } else { "No" },
-                    c.default_value.as_deref().unwrap_or("-")
+                    "| {}.{} | {} | {} |",
+                    t.schema_name,
+                    t.table_name,
+                    t.row_count
+                        .map(|r| r.to_string())
+                        .unwrap_or("-".to_string()),
+                    t.data_size_kb
+                        .map(|k| k.to_string())
+                        .unwrap_or("-".to_string())
                 )
             })
             .collect::<Vec<_>>()
             .join("\n");
 
+        // Secondary sections are best-effort: a failure leaves the section empty
+        let referenced_query = format!(
+            r#"
+            SELECT TOP 10
+                s.name AS schema_name,
+                t.name AS table_name,
+                COUNT(*) AS reference_count
+            FROM sys.foreign_keys fk
+            INNER JOIN sys.tables t ON fk.referenced_object_id = t.object_id
+            INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+            WHERE 1 = 1 {schema_filter}
+            GROUP BY s.name, t.name
+            ORDER BY COUNT(*) DESC
+            "#
+        );
+        let most_referenced = match self.executor.execute(&referenced_query).await {
+            Ok(r) => r
+                .rows
+                .iter()
+                .map(|row| {
+                    format!(
+                        "| {}.{} | {} |",
+                        row.get("schema_name")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                        row.get("table_name")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                        row.get("reference_count")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => {
+                warn!("Failed to query most-referenced tables: {}", e);
+                String::new()
+            }
+        };
+
+        let recent_query = format!(
+            r#"
+            SELECT TOP 10
+                s.name AS schema_name,
+                o.name AS object_name,
+                o.type_desc AS object_type,
+                CONVERT(VARCHAR(19), o.modify_date, 120) AS modify_date
+            FROM sys.objects o
+            INNER JOIN sys.schemas s ON o.schema_id = s.schema_id
+            WHERE o.is_ms_shipped = 0
+              AND o.type IN ('U', 'V', 'P', 'FN', 'IF', 'TF', 'TR')
+              {schema_filter}
+            ORDER BY o.modify_date DESC
+            "#
+        );
+        let recently_modified = match self.executor.execute(&recent_query).await {
+            Ok(r) => r
+                .rows
+                .iter()
+                .map(|row| {
+                    format!(
+                        "| {}.{} | {} | {} |",
+                        row.get("schema_name")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                        row.get("object_name")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                        row.get("object_type")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default(),
+                        row.get("modify_date")
+                            .map(|v| v.to_display_string())
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => {
+                warn!("Failed to query recently modified objects: {}", e);
+                String::new()
+            }
+        };
+
+        let columns_query = format!(
+            r#"
+            SELECT TOP 2000 c.name AS column_name
+            FROM sys.columns c
+            INNER JOIN sys.tables t ON c.object_id = t.object_id
+            INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+            WHERE t.is_ms_shipped = 0 {schema_filter}
+            "#
+        );
+        let column_names: Vec<String> = match self.executor.execute(&columns_query).await {
+            Ok(r) => r
+                .rows
+                .iter()
+                .filter_map(|row| row.get("column_name").map(|v| v.to_display_string()))
+                .collect(),
+            Err(e) => {
+                warn!("Failed to query column names: {}", e);
+                Vec::new()
+            }
+        };
+        let table_names: Vec<String> = tables.iter().map(|t| t.table_name.clone()).collect();
+        let conventions = detect_naming_conventions(&table_names, &column_names)
+            .iter()
+            .map(|c| format!("- {}", c))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let scope = schema
+            .as_deref()
+            .map(|s| format!("schema [{}]", s))
+            .unwrap_or_else(|| "this database".to_string());
+        let or_none = |section: String| {
+            if section.is_empty() {
+                "_No data available._".to_string()
+            } else {
+                section
+            }
+        };
+
         let prompt_text = format!(
-            r#"Analyze the schema of table [{schema}].[{table}] and provide recommendations.
+            r#"I just connected to {scope} and I am not familiar with it yet. Here is a high-level overview ({table_count} tables).
 
-## Current Schema
+## Largest Tables
 
-| Column | Type | Max Length | Nullable | Identity | Default |
-|--------|------|------------|----------|----------|---------|
-{column_desc}
+| Table | Rows | Data Size (KB) |
+|-------|------|----------------|
+{largest}
 
-## Analysis Requested
+## Most Referenced Tables (by incoming foreign keys)
 
-Please analyze this schema and provide:
+| Table | References |
+|-------|------------|
+{most_referenced}
 
-1. **Data Type Review**
-   - Are the data types appropriate for the column names/purposes?
-   - Could any types be optimized (e.g., varchar(max) -> varchar(n))?
+## Recently Modified Objects
 
-2. **Nullability Assessment**
-   - Which nullable columns might benefit from NOT NULL constraints?
-   - Are there potential data integrity issues?
+| Object | Type | Modified |
+|--------|------|----------|
+{recently_modified}
 
-3. **Indexing Suggestions**
-   - Based on likely query patterns, which columns should be indexed?
-   - Any composite index recommendations?
+## Detected Naming Conventions
 
-4. **Best Practices**
-   - Naming convention compliance
-   - Potential normalization issues
-   - Missing audit columns (CreatedAt, UpdatedAt, etc.)
+{conventions}
 
-5. **Performance Considerations**
-   - Data type sizes and storage efficiency
-   - Potential query performance impacts
-"#
+## Please Provide
+
+1. **Domain Summary**: What does this database appear to be for, based on the table names?
+2. **Core Entities**: Which tables look like the central entities, and how do they relate?
+3. **Exploration Plan**: A short, ordered list of next steps (specific resources to read and read-only queries to run) to understand the data model
+4. **Caveats**: Anything that looks unusual (very large tables, inconsistent naming, recent schema churn) worth keeping in mind before querying
+"#,
+            largest = or_none(largest),
+            most_referenced = or_none(most_referenced),
+            recently_modified = or_none(recently_modified),
+            conventions = or_none(conventions),
         );
 
         Ok(GetPromptResult {
-            description: Some(format!("Schema analysis for {}.{}", schema, table)),
+            description: Some(format!("Exploration overview for {}", scope)),
             messages: vec![PromptMessage {
                 role: Role::User,
                 content: Content::text(prompt_text),
             }],
         })
     }
+}
 
-    /// Generate an INSERT statement template for a table.
-    #[prompt(description = "Generate an INSERT statement template for a table")]
-    pub async fn generate_insert(
-        &self,
-        schema: Option<String>,
-        table: String,
-    ) -> Result<GetPromptResult, McpError> {
-        let schema = schema.as_deref().unwrap_or("dbo");
+// =========================================================================
+// Helper Functions
+// =========================================================================
 
-        // Get table columns
-        let columns = self
-            .metadata
-            .get_table_columns(schema, &table)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get table columns: {}", e)))?;
+/// Format a large-result reference for tool output.
+fn format_result_reference(reference: &crate::results::ResultReference) -> String {
+    let response = json!({
+        "result_reference": reference,
+        "message": format!(
+            "{}. Use fetch_result_chunk with result_ref '{}' to retrieve rows.",
+            reference.summary, reference.result_ref
+        ),
+    });
+    serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+        warn!("Failed to serialize result reference: {}", e);
+        format!("Result stored as {}", reference.result_ref)
+    })
+}
 
-        if columns.is_empty() {
-            return Err(McpError::invalid_params(
-                "generate_insert",
-                format!("Table not found: {}.{}", schema, table),
-            ));
+/// Parse a resource path to extract schema and object name.
+///
+/// Expected format: `mssql://{type}/{schema}/{name}` or `mssql://{type}/{qualified_name}`
+/// Describe circuit breaker state and counters for tool responses.
+fn circuit_breaker_status(stats: &CircuitBreakerStats) -> serde_json::Value {
+    json!({
+        "state": stats.state.to_string(),
+        "total_calls": stats.total_calls,
+        "successes": stats.total_successes,
+        "failures": stats.total_failures,
+        "rejections": stats.total_rejections,
+        "consecutive_failures": stats.consecutive_failures,
+        "consecutive_successes": stats.consecutive_successes,
+    })
+}
+
+fn parse_resource_path(uri: &str, resource_type: &str) -> Result<(String, String), McpError> {
+    let prefix = format!("mssql://{}/", resource_type);
+    let path = uri
+        .strip_prefix(&prefix)
+        .ok_or_else(|| McpError::invalid_params(resource_type, format!("Invalid URI: {}", uri)))?;
+
+    // Try path format first: schema/name
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [schema, name] => Ok(((*schema).to_string(), (*name).to_string())),
+        [qualified] => {
+            // Try qualified format: schema.name
+            match parse_qualified_name(qualified) {
+                Ok((Some(schema), name)) => Ok((schema, name)),
+                Ok((None, name)) => Ok(("dbo".to_string(), name)),
+                Err(e) => Err(McpError::invalid_params(
+                    resource_type,
+                    format!("Invalid qualified name '{}': {}", qualified, e),
+                )),
+            }
+        }
+        _ => Err(McpError::invalid_params(
+            resource_type,
+            format!("Invalid resource path: {}", path),
+        )),
+    }
+}
+
+/// Render a relationship graph as DOT or Mermaid text contents.
+fn relationship_graph_contents(
+    uri: &str,
+    relationships: &[ForeignKeyRelationship],
+    format: RelationshipFormat,
+) -> ResourceContents {
+    let text = match format {
+        RelationshipFormat::Mermaid => to_mermaid(relationships),
+        _ => to_dot(relationships),
+    };
+    let mut contents = ResourceContents::text(uri, text);
+    contents.mime_type = Some(format.mime_type().to_string());
+    contents
+}
+
+/// Truncate a string for logging.
+fn truncate_for_log(s: &str, max_len: usize) -> String {
+    if s.len() <= max_len {
+        s.to_string()
+    } else {
+        format!("{}...", &s[..max_len])
+    }
+}
+
+/// Format a parameter value for SQL execution.
+fn format_parameter_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => {
+            // Escape single quotes for SQL
+            format!("N'{}'", s.replace('\'', "''"))
+        }
+        serde_json::Value::Array(arr) => {
+            // Convert array to comma-separated string for table-valued parameters
+            let elements: Vec<String> = arr.iter().map(format_parameter_value).collect();
+            elements.join(", ")
         }
+        serde_json::Value::Object(_) => {
+            // For objects, serialize as JSON string
+            format!(
+                "N'{}'",
+                serde_json::to_string(value)
+                    .unwrap_or_default()
+                    .replace('\'', "''")
+            )
+        }
+    }
+}
 
-        // Filter out identity and computed columns
-        let insertable_columns: Vec<_> = columns
-            .iter()
-            .filter(|c| !c.is_identity && !c.is_computed)
-            .collect();
+/// Build a parameterized query for sp_executesql.
+///
+/// Returns (query, param_declarations, param_values) tuple.
+fn build_parameterized_query(
+    query: &str,
+    parameters: &std::collections::HashMap<String, serde_json::Value>,
+) -> Result<(String, String, String), McpError> {
+    if parameters.is_empty() {
+        return Ok((query.to_string(), String::new(), String::new()));
+    }
 
-        let column_list = insertable_columns
-            .iter()
-            .map(|c| format!("[{}]", c.column_name))
-            .collect::<Vec<_>>()
-            .join(", ");
+    let mut declarations: Vec<String> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
 
-        let value_placeholders = insertable_columns
-            .iter()
-            .map(|c| {
-                let placeholder = match c.data_type.to_uppercase().as_str() {
-                    "INT" | "BIGINT" | "SMALLINT" | "TINYINT" => "0".to_string(),
-                    "BIT" => "0".to_string(),
-                    "DECIMAL" | "NUMERIC" | "MONEY" | "SMALLMONEY" => "0.00".to_string(),
-                    "FLOAT" | "REAL" => "0.0".to_string(),
-                    "DATE" => "'YYYY-MM-DD'".to_string(),
-                    "TIME" => "'HH:MM:SS'".to_string(),
-                    "DATETIME" | "DATETIME2" | "SMALLDATETIME" => "'YYYY-MM-DD HH:MM:SS'".to_string(),
-                    "UNIQUEIDENTIFIER" => "NEWID()".to_string(),
-                    _ => format!("N'<{}>'", c.column_name),
-                };
-                format!("{} /* {} {} */", placeholder, c.column_name, c.data_type)
-            })
-            .collect::<Vec<_>>()
-            .join(",\n    ");
+    for (name, value) in parameters {
+        // Normalize parameter name (ensure it starts with @)
+        let param_name = if name.starts_with('@') {
+            name.clone()
+        } else {
+            format!("@{}", name)
+        };
 
-        let column_desc = insertable_columns
-            .iter()
-            .map(|c| {
-                format!(
-                    "- {} ({}){}",
-                    c.column_name,
-                    c.data_type,
-                    if !c.is_nullable { " - REQUIRED" } else { "" }
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        // Determine SQL type based on JSON type
+        let sql_type = match value {
+            serde_json::Value::Null => "NVARCHAR(MAX)",
+            serde_json::Value::Bool(_) => "BIT",
+            serde_json::Value::Number(n) => {
+                if n.is_i64() {
+                    "BIGINT"
+                } else if n.is_f64() {
+                    "FLOAT"
+                } else {
+                    "DECIMAL(38, 10)"
+                }
+            }
+            serde_json::Value::String(_) => "NVARCHAR(MAX)",
+            serde_json::Value::Array(_) => "NVARCHAR(MAX)",
+            serde_json::Value::Object(_) => "NVARCHAR(MAX)",
+        };
 
-        let prompt_text = format!(
-            r#"Generate an INSERT statement for [{schema}].[{table}].
+        declarations.push(format!("{} {}", param_name, sql_type));
+        values.push(format!(
+            "{} = {}",
+            param_name,
+            format_parameter_value(value)
+        ));
+    }
 
-## Insertable Columns
+    Ok((
+        query.to_string(),
+        declarations.join(", "),
+        values.join(", "),
+    ))
+}
 
-{column_desc}
+/// Parse a table name in schema.table format.
+fn parse_table_name(table_ref: &str) -> Result<(String, String), McpError> {
+    match parse_qualified_name(table_ref) {
+        Ok((Some(schema), table)) => Ok((schema, table)),
+        Ok((None, table)) => Ok(("dbo".to_string(), table)),
+        Err(e) => Err(McpError::invalid_params(
+            "table",
+            format!("Invalid table name '{}': {}", table_ref, e),
+        )),
+    }
+}
 
-## Template
+/// Detect common naming conventions from table and column names.
+///
+/// Returns human-readable observations, e.g. "Tables use PascalCase".
+fn detect_naming_conventions(tables: &[String], columns: &[String]) -> Vec<String> {
+    fn case_style(name: &str) -> &'static str {
+        if name.contains('_') {
+            if name.chars().any(|c| c.is_ascii_uppercase()) {
+                "mixed_Snake_Case"
+            } else {
+                "snake_case"
+            }
+        } else if name.chars().next().is_some_and(|c| c.is_ascii_uppercase()) {
+            "PascalCase"
+        } else if name.chars().any(|c| c.is_ascii_uppercase()) {
+            "camelCase"
+        } else {
+            "lowercase"
+        }
+    }
 
-```sql
-INSERT INTO [{schema}].[{table}] (
-    {column_list}
-)
-VALUES (
-    {value_placeholders}
-);
-```
+    fn dominant<'a>(
+        names: &[String],
+        classify: impl Fn(&str) -> &'a str,
+    ) -> Option<(&'a str, usize)> {
+        let mut counts: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+        for name in names {
+            *counts.entry(classify(name)).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(style, count)| (*count, *style))
+    }
 
-## Instructions
+    let mut findings = Vec::new();
 
-Replace the placeholder values with actual data. Note:
-- Columns marked REQUIRED cannot be NULL
-- String values should use N'...' for Unicode support
-- Date/time values should use ISO format
-- UNIQUEIDENTIFIER can use NEWID() for auto-generation
-"#
-        );
+    if let Some((style, count)) = dominant(tables, case_style) {
+        findings.push(format!(
+            "Tables mostly use {} ({} of {})",
+            style,
+            count,
+            tables.len()
+        ));
+    }
+    if let Some((style, count)) = dominant(columns, case_style) {
+        findings.push(format!(
+            "Columns mostly use {} ({} of {})",
+            style,
+            count,
+            columns.len()
+        ));
+    }
 
-        Ok(GetPromptResult {
-            description: Some(format!("INSERT template for {}.{}", schema, table)),
-            messages: vec![PromptMessage {
-                role: Role::User,
-                content: Content::text(prompt_text),
-            }],
+    let prefixed = tables
+        .iter()
+        .filter(|t| {
+            let lower = t.to_lowercase();
+            lower.starts_with("tbl") || lower.starts_with("t_")
         })
+        .count();
+    if prefixed > 0 {
+        findings.push(format!("{} table(s) use a 'tbl'/'t_' prefix", prefixed));
     }
 
-    /// Explain what a stored procedure does and how to call it.
-    #[prompt(description = "Explain what a stored procedure does and how to call it")]
-    pub async fn explain_procedure(
-        &self,
-        schema: Option<String>,
-        procedure: String,
-    ) -> Result<GetPromptResult, McpError> {
-        let schema = schema.as_deref().unwrap_or("dbo");
+    let plural = tables
+        .iter()
+        .filter(|t| t.ends_with('s') && !t.ends_with("ss"))
+        .count();
+    if !tables.is_empty() {
+        let label = if plural * 2 >= tables.len() {
+            "plural"
+        } else {
+            "singular"
+        };
+        findings.push(format!(
+            "Table names are mostly {} ({} of {} end in 's')",
+            label,
+            plural,
+            tables.len()
+        ));
+    }
 
-        // Get procedure definition
-        let definition = self
-            .metadata
-            .get_procedure_definition(schema, &procedure)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get procedure definition: {}", e)))?;
+    let id_columns = columns
+        .iter()
+        .filter(|c| c.eq_ignore_ascii_case("id"))
+        .count();
+    let suffixed_ids = columns
+        .iter()
+        .filter(|c| c.len() > 2 && (c.ends_with("Id") || c.ends_with("ID") || c.ends_with("_id")))
+        .count();
+    if id_columns > 0 || suffixed_ids > 0 {
+        findings.push(format!(
+            "Key columns: {} named 'Id', {} with an Id/_id suffix",
+            id_columns, suffixed_ids
+        ));
+    }
 
-        // Get procedure parameters
-        let parameters = self
-            .metadata
-            .get_procedure_parameters(schema, &procedure)
-            .await
-            .map_err(|e| McpError::internal(format!("Failed to get procedure parameters: {}", e)))?;
+    findings
+}
 
-        let param_desc = if parameters.is_empty() {
-            "This procedure has no parameters.".to_string()
+// =========================================================================
+// CompletionHandler Implementation
+// =========================================================================
+
+use mcpkit::{CompletionHandler, Context};
+
+/// Completion suggestions for SQL Server objects.
+///
+/// Provides autocompletion for:
+/// - Resource URIs (tables, views, procedures, functions, triggers)
+/// - Prompt arguments (schema names, table names, procedure names)
+impl CompletionHandler for MssqlMcpServer {
+    /// Complete a partial resource URI.
+    ///
+    /// Supports completion for resource patterns like:
+    /// - `mssql://tables/{schema}/{table}`
+    /// - `mssql://views/{schema}/{view}`
+    /// - `mssql://procedures/{schema}/{procedure}`
+    /// - `mssql://functions/{schema}/{function}`
+    /// - `mssql://triggers/{schema}/{trigger}`
+    async fn complete_resource(
+        &self,
+        partial_uri: &str,
+        _ctx: &Context<'_>,
+    ) -> Result<Vec<String>, McpError> {
+        debug!("Completing resource URI: {}", partial_uri);
+
+        // Parse the partial URI to determine what we're completing
+        let completions = if partial_uri.starts_with("mssql://tables/") {
+            self.complete_table_resource(partial_uri).await?
+        } else if partial_uri.starts_with("mssql://views/") {
+            self.complete_view_resource(partial_uri).await?
+        } else if partial_uri.starts_with("mssql://procedures/") {
+            self.complete_procedure_resource(partial_uri).await?
+        } else if partial_uri.starts_with("mssql://functions/") {
+            self.complete_function_resource(partial_uri).await?
+        } else if partial_uri.starts_with("mssql://triggers/") {
+            self.complete_trigger_resource(partial_uri).await?
+        } else if partial_uri.starts_with("mssql://") {
+            // Complete top-level resource types
+            vec![
+                "mssql://server/info".to_string(),
+                "mssql://databases".to_string(),
+                "mssql://schemas".to_string(),
+                "mssql://tables".to_string(),
+                "mssql://views".to_string(),
+                "mssql://procedures".to_string(),
+                "mssql://functions".to_string(),
+                "mssql://triggers".to_string(),
+            ]
+            .into_iter()
+            .filter(|uri| uri.starts_with(partial_uri))
+            .collect()
         } else {
-            parameters
-                .iter()
-                .map(|p| {
-                    format!(
-                        "- {} ({}){}{}",
-                        p.parameter_name,
-                        p.data_type,
-                        if p.is_output { " OUTPUT" } else { "" },
-                        if p.has_default { " [has default]" } else { "" }
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
+            Vec::new()
         };
 
-        let definition_text = definition.unwrap_or_else(|| "(Definition not available)".to_string());
+        Ok(completions)
+    }
 
-        let prompt_text = format!(
-            r#"Explain the stored procedure [{schema}].[{procedure}].
+    /// Complete a partial prompt argument.
+    ///
+    /// Supports completion for prompts:
+    /// - `query_table`, `analyze_schema`, `data_quality`, `generate_insert`, `query_json_column`:
+    ///   schema, table
+    /// - `explain_procedure`: schema, procedure
+    async fn complete_prompt_arg(
+        &self,
+        prompt_name: &str,
+        arg_name: &str,
+        partial_value: &str,
+        _ctx: &Context<'_>,
+    ) -> Result<Vec<String>, McpError> {
+        debug!(
+            "Completing prompt '{}' arg '{}' with prefix '{}'",
+            prompt_name, arg_name, partial_value
+        );
 
-## Parameters
+        let completions = match (prompt_name, arg_name) {
+            // Schema completion for all prompts that have schema arguments
+            (_, "schema") => self.complete_schemas(partial_value).await?,
 
-{param_desc}
+            // Table completion for table-related prompts
+            (
+                "query_table" | "analyze_schema" | "data_quality" | "generate_insert"
+                | "query_json_column",
+                "table",
+            ) => self.complete_tables(partial_value).await?,
 
-## Definition
+            // Procedure completion for procedure-related prompts
+            ("explain_procedure", "procedure") => self.complete_procedures(partial_value).await?,
 
-```sql
-{definition_text}
-```
+            // Column completion would be context-dependent (needs table name)
+            // For now, return empty
+            (_, "columns") => Vec::new(),
 
-## Please Explain
+            // Query and error completion - no suggestions
+            (_, "query" | "error") => Vec::new(),
 
-1. **Purpose**: What does this procedure do?
-2. **Parameters**: Explain each parameter and its purpose
-3. **Logic Flow**: Step-by-step explanation of what happens
-4. **Return Values**: What does it return? (result sets, output parameters)
-5. **Example Usage**: Show how to call this procedure
-6. **Potential Issues**: Any edge cases or error conditions to watch for?
-"#
-        );
+            _ => Vec::new(),
+        };
 
-        Ok(GetPromptResult {
-            description: Some(format!("Explanation of {}.{}", schema, procedure)),
-            messages: vec![PromptMessage {
-                role: Role::User,
-                content: Content::text(prompt_text),
-            }],
-        })
+        Ok(completions)
     }
+}
 
-    /// Analyze a SQL query and suggest optimizations.
-    #[prompt(description = "Analyze a SQL query and suggest optimizations")]
-    pub fn optimize_query(
+/// Helper methods for destructive statement confirmation.
+impl MssqlMcpServer {
+    /// Hold back a call containing destructive statements until confirmed.
+    ///
+    /// Returns the preview to send instead of running the call, or `None` if
+    /// the call may run (confirmation disabled, nothing destructive, or the
+    /// client already confirmed this query).
+    async fn confirmation_preview(
         &self,
-        query: String,
-    ) -> Result<GetPromptResult, McpError> {
-        let prompt_text = format!(
-            r#"Analyze and optimize the following SQL query.
-
-## Original Query
-
-```sql
-{query}
-```
+        tool: &str,
+        query: &str,
+        input: &impl serde::Serialize,
+    ) -> Option<ToolOutput> {
+        if !self.config().security.require_confirmation {
+            return None;
+        }
+        let statements = find_destructive_statements(query);
+        if statements.is_empty() {
+            return None;
+        }
+        let client = client_context_key();
+        if self.confirmations.take_approval(&client, query) {
+            return None;
+        }
 
-## Analysis Requested
+        let current_db = {
+            let state = self.state.read().await;
+            state.current_database().map(|s| s.to_string())
+        };
+        let mut previews = Vec::with_capacity(statements.len());
+        for statement in &statements {
+            let mut preview = json!({
+                "kind": statement.kind,
+                "statement": truncate_for_log(&statement.statement, 500),
+                "target": statement.target,
+                "object_type": statement.object_type,
+                "estimated_rows": null,
+            });
+            if let Some(estimate) = &statement.estimate_query {
+                let estimate = match &current_db {
+                    Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), estimate),
+                    None => estimate.clone(),
+                };
+                match self.executor.execute(&estimate).await {
+                    Ok(result) => {
+                        preview["estimated_rows"] = json!(result
+                            .rows
+                            .first()
+                            .and_then(|row| row.get("estimated_rows"))
+                            .and_then(|v| v.to_display_string().parse::<i64>().ok()));
+                    }
+                    Err(e) => preview["estimate_error"] = json!(e.to_string()),
+                }
+            }
+            previews.push(preview);
+        }
 
-1. **Query Structure**
-   - Is the query logically correct?
-   - Any syntax issues or anti-patterns?
+        let summary = statements
+            .iter()
+            .zip(&previews)
+            .map(|(statement, preview)| {
+                let rows = preview["estimated_rows"]
+                    .as_i64()
+                    .map(|n| format!(" (about {} rows)", n))
+                    .unwrap_or_default();
+                format!("- {}{}", truncate_for_log(&statement.statement, 200), rows)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let message = format!(
+            "{} wants to run {} destructive statement(s):\n{}\nRun them?",
+            tool,
+            statements.len(),
+            summary
+        );
+        self.hold_for_confirmation(tool, query, input, &client, &message, previews)
+            .await
+    }
 
-2. **Performance Issues**
-   - Potential table scans
-   - Missing indexes
-   - Inefficient joins
-   - Subquery vs JOIN considerations
+    /// Hold back a `bulk_insert` or `generate_test_data` call when it inserts
+    /// more rows than `MSSQL_CONFIRM_BULK_ROWS`.
+    async fn bulk_confirmation(
+        &self,
+        tool: &str,
+        table: &str,
+        rows: usize,
+        input: &impl serde::Serialize,
+    ) -> Option<ToolOutput> {
+        let security = &self.config().security;
+        if !security.require_confirmation
+            || security.confirm_bulk_rows == 0
+            || rows <= security.confirm_bulk_rows
+        {
+            return None;
+        }
+        let client = client_context_key();
+        let operation = format!(
+            "{} {} ({} rows)",
+            tool.replace('_', " ").to_uppercase(),
+            table,
+            rows
+        );
+        if self.confirmations.take_approval(&client, &operation) {
+            return None;
+        }
 
-3. **Optimizations**
-   - Rewrite suggestions
-   - Index recommendations
-   - Query hints if appropriate
+        let message = format!(
+            "{} wants to insert {} rows into {}.\nRun it?",
+            tool, rows, table
+        );
+        let preview = json!({
+            "kind": tool,
+            "statement": operation,
+            "target": table,
+            "object_type": "TABLE",
+            "estimated_rows": rows,
+        });
+        self.hold_for_confirmation(tool, &operation, input, &client, &message, vec![preview])
+            .await
+    }
 
-4. **Best Practices**
-   - SET NOCOUNT ON for procedures
-   - Avoiding SELECT *
-   - Proper use of CTEs vs temp tables
+    /// Ask the user to confirm a destructive call, or hold it back with a token.
+    ///
+    /// When the client supports elicitation the user is asked directly and the
+    /// call runs (`None`) or is cancelled. Otherwise, or if the elicitation
+    /// fails, a preview with a token for `confirm_execution` is returned.
+    async fn hold_for_confirmation(
+        &self,
+        tool: &str,
+        query: &str,
+        input: &impl serde::Serialize,
+        client: &str,
+        message: &str,
+        previews: Vec<serde_json::Value>,
+    ) -> Option<ToolOutput> {
+        match self.elicit_confirmation(message).await {
+            Some(true) => {
+                info!("User confirmed {} call through elicitation", tool);
+                return None;
+            }
+            Some(false) => {
+                info!("User declined {} call through elicitation", tool);
+                let response = json!({
+                    "status": "cancelled",
+                    "message": "The user declined to run the destructive statements; nothing was executed.",
+                    "tool": tool,
+                    "statements": previews,
+                });
+                return Some(ToolOutput::text(
+                    serde_json::to_string_pretty(&response)
+                        .unwrap_or_else(|_| "Cancelled by the user".to_string()),
+                ));
+            }
+            None => {}
+        }
 
-5. **Optimized Version**
-   - Provide the optimized query with comments
-"#
+        let input = match serde_json::to_value(input) {
+            Ok(v) => v,
+            Err(e) => {
+                return Some(ToolOutput::error(format!(
+                    "Failed to hold back call for confirmation: {}",
+                    e
+                )))
+            }
+        };
+        let token = self.confirmations.register(tool, input, query, client);
+        info!(
+            "Held back {} call with {} destructive operation(s) for confirmation",
+            tool,
+            previews.len()
         );
 
-        Ok(GetPromptResult {
-            description: Some("Query optimization analysis".to_string()),
-            messages: vec![PromptMessage {
-                role: Role::User,
-                content: Content::text(prompt_text),
-            }],
-        })
+        let response = json!({
+            "status": "confirmation_required",
+            "message": "The call contains destructive operations and was not executed. \
+                Review the preview, then call confirm_execution with the confirmation_token to run it.",
+            "tool": tool,
+            "confirmation_token": token,
+            "expires_in_seconds": self.confirmations.ttl().as_secs(),
+            "statements": previews,
+        });
+        Some(ToolOutput::text(
+            serde_json::to_string_pretty(&response)
+                .unwrap_or_else(|_| format!("Confirmation token: {}", token)),
+        ))
     }
 
-    /// Help debug a SQL Server error with context and suggestions.
-    #[prompt(description = "Help debug a SQL Server error with context and suggestions")]
-    pub fn debug_error(
-        &self,
-        error: String,
-        query: Option<String>,
-    ) -> Result<GetPromptResult, McpError> {
-        let mut prompt_text = format!(
-            r#"Help debug this SQL Server error.
-
-## Error Message
+    /// Ask the user to confirm through an MCP elicitation prompt.
+    ///
+    /// Returns whether the user confirmed, or `None` when elicitation is
+    /// disabled, not supported by the client, or failed.
+    async fn elicit_confirmation(&self, message: &str) -> Option<bool> {
+        use crate::constants::ELICITATION_TIMEOUT_SECS;
+        use crate::transport::ELICITATION_CREATE;
+        use mcpkit::types::elicitation::{
+            ElicitAction, ElicitRequest, ElicitResult, ElicitationSchema, PropertySchema,
+        };
 
-```
-{error}
-```
-"#
+        if !self.config().security.elicit_confirmation
+            || !self.client_requests.supports("elicitation")
+        {
+            return None;
+        }
+        let request = ElicitRequest::new(
+            message,
+            ElicitationSchema::object().required_property(
+                "confirm",
+                PropertySchema::boolean().description("Run the destructive operation"),
+            ),
         );
+        let params = serde_json::to_value(&request).ok()?;
+        let result = match self
+            .client_requests
+            .request(
+                ELICITATION_CREATE,
+                params,
+                std::time::Duration::from_secs(ELICITATION_TIMEOUT_SECS),
+            )
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(
+                    "Elicited confirmation failed, using a confirmation token: {}",
+                    e
+                );
+                return None;
+            }
+        };
+        match serde_json::from_value::<ElicitResult>(result) {
+            Ok(result) => Some(
+                result.action == ElicitAction::Accept && result.get_bool("confirm") == Some(true),
+            ),
+            Err(e) => {
+                warn!(
+                    "Invalid elicitation result, using a confirmation token: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+}
 
-        if let Some(q) = &query {
-            prompt_text.push_str(&format!(
-                r#"
-## Query That Caused the Error
+/// Helper methods for the query result cache.
+impl MssqlMcpServer {
+    /// Drop cached results of tables that a statement may have modified.
+    async fn invalidate_cached(&self, query: &str) {
+        if !self.query_cache.is_enabled() {
+            return;
+        }
+        let removed = self.query_cache.invalidate_modified(query).await;
+        if removed > 0 {
+            debug!("Invalidated {} cached results", removed);
+        }
+    }
 
-```sql
-{q}
-```
-"#
-            ));
+    /// Drop all cached results after a call that may have modified any table.
+    async fn clear_cached(&self) {
+        if self.query_cache.is_enabled() {
+            self.query_cache.clear().await;
         }
+    }
+}
 
-        prompt_text.push_str(
-            r#"
-## Please Provide
+/// Helper methods for dedicated connections.
+impl MssqlMcpServer {
+    /// Database a new session or transaction connection should use.
+    ///
+    /// Falls back to the database selected with `switch_database`, if any.
+    async fn connection_database(
+        &self,
+        requested: Option<String>,
+    ) -> Result<Option<String>, ToolOutput> {
+        match requested {
+            Some(db) => match validate_identifier(&db) {
+                Ok(()) => Ok(Some(db)),
+                Err(e) => Err(ToolOutput::error(format!("Invalid database name: {}", e))),
+            },
+            None => {
+                let state = self.state.read().await;
+                Ok(state.current_database().map(|s| s.to_string()))
+            }
+        }
+    }
+}
 
-1. **Error Explanation**: What does this error mean?
-2. **Common Causes**: Why does this error typically occur?
-3. **Diagnosis Steps**: How to investigate further
-4. **Solutions**: How to fix the issue
-5. **Prevention**: How to avoid this error in the future
-"#,
-        );
+/// Helper methods for execution plans.
+impl MssqlMcpServer {
+    /// Get and parse the showplan XML of a query.
+    async fn parsed_plan(&self, query: &str, plan_type: &str) -> Result<ExecutionPlan, ToolOutput> {
+        let documents = self
+            .executor
+            .execute_with_showplan_xml(query, plan_type)
+            .await
+            .map_err(|e| ToolOutput::error(format!("Failed to get execution plan: {}", e)))?;
 
-        Ok(GetPromptResult {
-            description: Some("SQL Server error debugging".to_string()),
-            messages: vec![PromptMessage {
-                role: Role::User,
-                content: Content::text(prompt_text),
-            }],
+        ExecutionPlan::parse(&documents).map_err(|e| {
+            ToolOutput::error(format!(
+                "Failed to parse execution plan: {} (use explain_query with raw=true for the unparsed plan)",
+                e
+            ))
         })
     }
 }
 
-// =========================================================================
-// Helper Functions
-// =========================================================================
-
-/// Parse a resource path to extract schema and object name.
-///
-/// Expected format: `mssql://{type}/{schema}/{name}` or `mssql://{type}/{qualified_name}`
-fn parse_resource_path(uri: &str, resource_type: &str) -> Result<(String, String), McpError> {
-    let prefix = format!("mssql://{}/", resource_type);
-    let path = uri
-        .strip_prefix(&prefix)
-        .ok_or_else(|| McpError::invalid_params(resource_type, format!("Invalid URI: {}", uri)))?;
+/// Helper methods for object scripting.
+impl MssqlMcpServer {
+    /// Run a catalog query in the client's current database without a row limit.
+    async fn catalog_rows(
+        &self,
+        query: &str,
+    ) -> Result<Vec<crate::database::ResultRow>, ServerError> {
+        let use_prefix = {
+            let state = self.state.read().await;
+            state
+                .current_database()
+                .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+                .unwrap_or_default()
+        };
+        Ok(self
+            .executor
+            .execute_with_limit(&format!("{}{}", use_prefix, query), usize::MAX)
+            .await?
+            .rows)
+    }
 
-    // Try path format first: schema/name
-    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    match segments.as_slice() {
-        [schema, name] => Ok(((*schema).to_string(), (*name).to_string())),
-        [qualified] => {
-            // Try qualified format: schema.name
-            match parse_qualified_name(qualified) {
-                Ok((Some(schema), name)) => Ok((schema, name)),
-                Ok((None, name)) => Ok(("dbo".to_string(), name)),
-                Err(e) => Err(McpError::invalid_params(
-                    resource_type,
-                    format!("Invalid qualified name '{}': {}", qualified, e),
-                )),
-            }
-        }
-        _ => Err(McpError::invalid_params(
-            resource_type,
-            format!("Invalid resource path: {}", path),
-        )),
+    /// Read a table's definition from the catalog and script it.
+    async fn script_table(
+        &self,
+        schema: &str,
+        name: &str,
+        options: &crate::database::scripting::ScriptOptions,
+    ) -> Result<String, ServerError> {
+        Ok(self
+            .table_definition(schema, name, options.include_triggers)
+            .await?
+            .script(options))
     }
-}
 
-/// Truncate a string for logging.
-fn truncate_for_log(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len])
+    /// Read a table's columns, constraints, indexes and (optionally) triggers.
+    async fn table_definition(
+        &self,
+        schema: &str,
+        name: &str,
+        include_triggers: bool,
+    ) -> Result<crate::database::scripting::TableDefinition, ServerError> {
+        use crate::database::scripting::{
+            check_constraints_query, columns_query, foreign_keys_query, indexes_query,
+            parse_columns, parse_definitions, parse_foreign_keys, parse_indexes, triggers_query,
+            TableDefinition,
+        };
+
+        Ok(TableDefinition {
+            schema: schema.to_string(),
+            name: name.to_string(),
+            columns: parse_columns(&self.catalog_rows(&columns_query(schema, name)).await?),
+            indexes: parse_indexes(&self.catalog_rows(&indexes_query(schema, name)).await?),
+            foreign_keys: parse_foreign_keys(
+                &self.catalog_rows(&foreign_keys_query(schema, name)).await?,
+            ),
+            checks: parse_definitions(
+                &self
+                    .catalog_rows(&check_constraints_query(schema, name))
+                    .await?,
+                "constraint_name",
+            ),
+            triggers: if include_triggers {
+                parse_definitions(
+                    &self.catalog_rows(&triggers_query(schema, name)).await?,
+                    "trigger_name",
+                )
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /// Script a view, procedure, function or trigger from its definition.
+    ///
+    /// `kind` is the keyword from `scripting::module_kind`; views also get
+    /// their indexes and triggers.
+    async fn script_module(
+        &self,
+        schema: &str,
+        name: &str,
+        kind: &str,
+        options: &crate::database::scripting::ScriptOptions,
+    ) -> Result<String, ServerError> {
+        use crate::database::scripting::{
+            indexes_query, module_definition_query, module_script, parse_definitions,
+            parse_indexes, triggers_query,
+        };
+
+        let definition = self
+            .catalog_rows(&module_definition_query(schema, name))
+            .await?
+            .first()
+            .and_then(|row| row.get("definition"))
+            .filter(|v| !v.is_null())
+            .map(|v| v.to_display_string());
+        let Some(definition) = definition else {
+            return Err(ServerError::query_error(format!(
+                "The definition of {}.{} is encrypted or not visible",
+                schema, name
+            )));
+        };
+        let (indexes, triggers) = if kind == "VIEW" {
+            (
+                parse_indexes(&self.catalog_rows(&indexes_query(schema, name)).await?),
+                parse_definitions(
+                    &self.catalog_rows(&triggers_query(schema, name)).await?,
+                    "trigger_name",
+                ),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        Ok(module_script(
+            schema,
+            name,
+            kind,
+            &definition,
+            &indexes,
+            &triggers,
+            options,
+        ))
     }
 }
 
-/// Format a parameter value for SQL execution.
-fn format_parameter_value(value: &serde_json::Value) -> String {
-    match value {
-        serde_json::Value::Null => "NULL".to_string(),
-        serde_json::Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
-        serde_json::Value::Number(n) => n.to_string(),
-        serde_json::Value::String(s) => {
-            // Escape single quotes for SQL
-            format!("N'{}'", s.replace('\'', "''"))
+/// Helper methods for JSON columns.
+impl MssqlMcpServer {
+    /// Sample a JSON text column and infer its paths and types.
+    ///
+    /// Example values are dropped when masking is enabled, since they are
+    /// taken from the raw sample.
+    async fn json_column_schema(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        sample_size: usize,
+    ) -> Result<crate::database::json_column::JsonColumnSchema, String> {
+        use crate::database::json_column::JsonColumnSchema;
+
+        const TEXT_TYPES: &[&str] = &[
+            "nvarchar", "varchar", "nchar", "char", "ntext", "text", "json",
+        ];
+
+        let columns = self
+            .metadata
+            .get_table_columns(schema, table)
+            .await
+            .map_err(|e| format!("Failed to get table columns: {}", e))?;
+        if columns.is_empty() {
+            return Err(format!("Table not found: {}.{}", schema, table));
         }
-        serde_json::Value::Array(arr) => {
-            // Convert array to comma-separated string for table-valued parameters
-            let elements: Vec<String> = arr.iter().map(format_parameter_value).collect();
-            elements.join(", ")
+        let Some(info) = columns
+            .iter()
+            .find(|c| c.column_name.eq_ignore_ascii_case(column))
+        else {
+            return Err(format!("Column not found: {}.{}.{}", schema, table, column));
+        };
+        if !TEXT_TYPES.contains(&info.data_type.to_lowercase().as_str()) {
+            return Err(format!(
+                "Column {} is {}; JSON columns must be a character type such as nvarchar(max)",
+                info.column_name, info.data_type
+            ));
         }
-        serde_json::Value::Object(_) => {
-            // For objects, serialize as JSON string
-            format!(
-                "N'{}'",
-                serde_json::to_string(value)
-                    .unwrap_or_default()
-                    .replace('\'', "''")
-            )
+
+        let query = format!(
+            "SELECT TOP ({}) CAST(t.{} AS nvarchar(max)) AS json_text FROM {}.{} AS t WHERE t.{} IS NOT NULL",
+            sample_size.clamp(1, 1000),
+            safe_identifier(&info.column_name).map_err(|e| e.to_string())?,
+            safe_identifier(schema).map_err(|e| e.to_string())?,
+            safe_identifier(table).map_err(|e| e.to_string())?,
+            safe_identifier(&info.column_name).map_err(|e| e.to_string())?,
+        );
+        let result = self
+            .executor
+            .execute(&query)
+            .await
+            .map_err(|e| format!("Failed to sample {}: {}", info.column_name, e))?;
+        let documents: Vec<String> = result
+            .rows
+            .iter()
+            .filter_map(|row| row.get("json_text"))
+            .filter(|v| !v.is_null())
+            .map(|v| v.to_display_string())
+            .collect();
+        if documents.is_empty() {
+            return Err(format!(
+                "Column {} has no non-null values to sample",
+                info.column_name
+            ));
         }
-    }
-}
 
-/// Build a parameterized query for sp_executesql.
-///
-/// Returns (query, param_declarations, param_values) tuple.
-fn build_parameterized_query(
-    query: &str,
-    parameters: &std::collections::HashMap<String, serde_json::Value>,
-) -> Result<(String, String, String), McpError> {
-    if parameters.is_empty() {
-        return Ok((query.to_string(), String::new(), String::new()));
+        let mut inferred = JsonColumnSchema::infer(documents.iter().map(String::as_str));
+        if inferred.invalid_rows == inferred.sampled_rows {
+            return Err(format!(
+                "None of the {} sampled values of {} is valid JSON",
+                inferred.sampled_rows, info.column_name
+            ));
+        }
+        if self.masker().is_enabled() {
+            for path in &mut inferred.paths {
+                path.example = None;
+            }
+        }
+        Ok(inferred)
     }
+}
 
-    let mut declarations: Vec<String> = Vec::new();
-    let mut values: Vec<String> = Vec::new();
+/// Helper methods for data profiling.
+impl MssqlMcpServer {
+    /// Profile the first `sample_rows` rows of a table, optionally limited to
+    /// some of its columns.
+    ///
+    /// Min/max of character columns and all values of masked columns are
+    /// dropped when masking is enabled, since they are raw data.
+    async fn table_profile(
+        &self,
+        schema: &str,
+        table: &str,
+        only: Option<&[String]>,
+        sample_rows: usize,
+        timeout_secs: Option<u64>,
+    ) -> Result<crate::database::profile::TableProfile, String> {
+        use crate::constants::PROFILE_PATTERN_SAMPLE_ROWS;
+        use crate::database::profile::{pattern_query, profile_query, ProfileColumn, TableProfile};
+
+        let infos = self
+            .metadata
+            .get_table_columns(schema, table)
+            .await
+            .map_err(|e| format!("Failed to get table columns: {}", e))?;
+        if infos.is_empty() {
+            return Err(format!("Table not found: {}.{}", schema, table));
+        }
+        let columns: Vec<ProfileColumn> = match only {
+            Some(names) => {
+                let mut columns = Vec::with_capacity(names.len());
+                for name in names {
+                    let Some(info) = infos
+                        .iter()
+                        .find(|c| c.column_name.eq_ignore_ascii_case(name))
+                    else {
+                        return Err(format!("Column not found: {}.{}.{}", schema, table, name));
+                    };
+                    columns.push(ProfileColumn::from(info));
+                }
+                columns
+            }
+            None => infos.iter().map(ProfileColumn::from).collect(),
+        };
+        let execute = |query: String| async move {
+            match timeout_secs {
+                Some(secs) => {
+                    self.executor
+                        .execute_with_timeout(&query, secs.max(1))
+                        .await
+                }
+                None => self.executor.execute(&query).await,
+            }
+        };
 
-    for (name, value) in parameters {
-        // Normalize parameter name (ensure it starts with @)
-        let param_name = if name.starts_with('@') {
-            name.clone()
-        } else {
-            format!("@{}", name)
+        let query =
+            profile_query(schema, table, &columns, sample_rows).map_err(|e| e.to_string())?;
+        let result = execute(query)
+            .await
+            .map_err(|e| format!("Failed to profile {}.{}: {}", schema, table, e))?;
+        let Some(aggregate) = result.rows.first() else {
+            return Err(format!(
+                "Profile query for {}.{} returned no row",
+                schema, table
+            ));
         };
 
-        // Determine SQL type based on JSON type
-        let sql_type = match value {
-            serde_json::Value::Null => "NVARCHAR(MAX)",
-            serde_json::Value::Bool(_) => "BIT",
-            serde_json::Value::Number(n) => {
-                if n.is_i64() {
-                    "BIGINT"
-                } else if n.is_f64() {
-                    "FLOAT"
-                } else {
-                    "DECIMAL(38, 10)"
-                }
+        let pattern_rows = match pattern_query(
+            schema,
+            table,
+            &columns,
+            sample_rows.min(PROFILE_PATTERN_SAMPLE_ROWS),
+        )
+        .map_err(|e| e.to_string())?
+        {
+            Some(query) => {
+                execute(query)
+                    .await
+                    .map_err(|e| format!("Failed to sample {}.{}: {}", schema, table, e))?
+                    .rows
             }
-            serde_json::Value::String(_) => "NVARCHAR(MAX)",
-            serde_json::Value::Array(_) => "NVARCHAR(MAX)",
-            serde_json::Value::Object(_) => "NVARCHAR(MAX)",
+            None => Vec::new(),
         };
 
-        declarations.push(format!("{} {}", param_name, sql_type));
-        values.push(format!(
-            "{} = {}",
-            param_name,
-            format_parameter_value(value)
-        ));
+        let mut profile = TableProfile::from_rows(
+            format!("{}.{}", schema, table),
+            &columns,
+            aggregate,
+            &pattern_rows,
+            sample_rows,
+        );
+
+        for column in &mut profile.columns {
+            let (masked, redacted) = self.profile_redaction(schema, table, column);
+            if redacted {
+                column.min = None;
+                column.max = None;
+            }
+            if masked {
+                column.patterns.clear();
+            }
+        }
+        Ok(profile)
     }
 
-    Ok((
-        query.to_string(),
-        declarations.join(", "),
-        values.join(", "),
-    ))
+    /// Whether a profiled column is masked, and whether its raw values
+    /// (min/max, frequent values) must be withheld.
+    fn profile_redaction(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &crate::database::profile::ColumnProfile,
+    ) -> (bool, bool) {
+        use crate::database::profile::ColumnKind;
+
+        let masker = self.masker();
+        if !masker.is_enabled() {
+            return (false, false);
+        }
+        let masked = masker
+            .strategy_for(Some((schema, table)), &column.name)
+            .is_some();
+        (masked, masked || column.kind == ColumnKind::Text)
+    }
 }
 
-/// Encode an offset as a cursor string.
-fn encode_cursor(offset: usize) -> String {
-    use std::io::Write;
-    let mut bytes = Vec::new();
-    write!(&mut bytes, "cursor:{}", offset).unwrap();
-    // Simple base64-like encoding using hex
-    bytes.iter().map(|b| format!("{:02x}", b)).collect()
-}
+/// Helper methods for file exports.
+impl MssqlMcpServer {
+    /// Export all result sets of a query to an Excel workbook.
+    async fn export_xlsx(&self, input: &ExportDataInput, path: &std::path::Path) -> ToolOutput {
+        if input.compress || input.max_rows_per_file.is_some() || input.max_bytes_per_file.is_some()
+        {
+            return ToolOutput::error(
+                "compress, max_rows_per_file and max_bytes_per_file are not supported for xlsx exports",
+            );
+        }
 
-/// Decode a cursor string to an offset.
-fn decode_cursor(cursor: &str) -> Result<usize, String> {
-    // Decode hex string back to bytes
-    let bytes: Result<Vec<u8>, _> = (0..cursor.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
-        .collect();
+        // Workbooks are built in memory, so rows are capped at the worksheet limit
+        let max_rows = input
+            .max_rows
+            .unwrap_or(export::XLSX_MAX_ROWS_PER_SHEET)
+            .min(export::XLSX_MAX_ROWS_PER_SHEET);
+        let mut result = match self
+            .executor
+            .execute_multi_result(&input.query, max_rows)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Excel export query failed: {}", e);
+                return ToolOutput::error(format!(
+                    "Export failed: {}",
+                    self.localize_error(&e).await
+                ));
+            }
+        };
+        self.masker().mask_results(&mut result.result_sets);
+
+        let (file, sheets) = match export::write_xlsx(
+            path,
+            &result.result_sets,
+            input.include_headers,
+            input.overwrite,
+        ) {
+            Ok(written) => written,
+            Err(e) => return ToolOutput::error(format!("Export failed: {}", e)),
+        };
 
-    let bytes = bytes.map_err(|_| "Invalid cursor format")?;
-    let s = String::from_utf8(bytes).map_err(|_| "Invalid cursor encoding")?;
+        info!(
+            "Exported {} rows in {} worksheet(s) to {}",
+            file.rows,
+            sheets.len(),
+            path.display()
+        );
 
-    if !s.starts_with("cursor:") {
-        return Err("Invalid cursor prefix".to_string());
+        let response = json!({
+            "format": input.format.as_str(),
+            "destination": path.display().to_string(),
+            "row_count": file.rows,
+            "truncated": sheets.iter().any(|s| s.truncated),
+            "execution_time_ms": result.execution_time_ms,
+            "files": [file],
+            "sheets": sheets,
+        });
+        ToolOutput::text(serde_json::to_string_pretty(&response).unwrap_or_else(|e| {
+            warn!("Failed to serialize export response: {}", e);
+            format!("Export failed: {}", e)
+        }))
     }
-
-    s[7..]
-        .parse::<usize>()
-        .map_err(|_| "Invalid cursor offset".to_string())
 }
 
-/// Parse a table name in schema.table format.
-fn parse_table_name(table_ref: &str) -> Result<(String, String), McpError> {
-    match parse_qualified_name(table_ref) {
-        Ok((Some(schema), table)) => Ok((schema, table)),
-        Ok((None, table)) => Ok(("dbo".to_string(), table)),
-        Err(e) => Err(McpError::invalid_params(
-            "table",
-            format!("Invalid table name '{}': {}", table_ref, e),
-        )),
+/// Helper methods for binary downloads.
+impl MssqlMcpServer {
+    /// Copy `byte_count` bytes of a cell to `path` chunk by chunk, returning
+    /// the number of chunks read.
+    async fn write_blob(
+        &self,
+        table: &str,
+        column: &str,
+        filter: &str,
+        byte_count: usize,
+        path: &std::path::Path,
+    ) -> Result<usize, String> {
+        use crate::constants::BLOB_CHUNK_SIZE;
+        use crate::database::blob::blob_chunk_query;
+        use crate::database::SqlValue;
+        use std::io::Write;
+
+        let mut file = std::fs::File::create(path)
+            .map_err(|e| format!("Cannot create '{}': {}", path.display(), e))?;
+        let (mut written, mut chunks) = (0, 0);
+        while written < byte_count {
+            let length = BLOB_CHUNK_SIZE.min(byte_count - written);
+            let query = blob_chunk_query(table, column, filter, written, length);
+            let result = self
+                .executor
+                .execute(&query)
+                .await
+                .map_err(|e| e.to_string())?;
+            let chunk = match result.rows.first().and_then(|row| row.get("chunk")) {
+                Some(SqlValue::Bytes(bytes)) if !bytes.is_empty() => bytes,
+                _ => {
+                    return Err(format!(
+                        "the cell changed while downloading ({} of {} bytes read)",
+                        written, byte_count
+                    ));
+                }
+            };
+            file.write_all(chunk)
+                .map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+            written += chunk.len();
+            chunks += 1;
+        }
+        file.flush()
+            .map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+        Ok(chunks)
     }
 }
 
-// =========================================================================
-// CompletionHandler Implementation
-// =========================================================================
-
-use mcpkit::{CompletionHandler, Context};
-
-/// Completion suggestions for SQL Server objects.
-///
-/// Provides autocompletion for:
-/// - Resource URIs (tables, views, procedures, functions, triggers)
-/// - Prompt arguments (schema names, table names, procedure names)
-impl CompletionHandler for MssqlMcpServer {
-    /// Complete a partial resource URI.
+/// Helper methods for file imports.
+impl MssqlMcpServer {
+    /// Load the valid records of an import source into the target table.
     ///
-    /// Supports completion for resource patterns like:
-    /// - `mssql://tables/{schema}/{table}`
-    /// - `mssql://views/{schema}/{view}`
-    /// - `mssql://procedures/{schema}/{procedure}`
-    /// - `mssql://functions/{schema}/{function}`
-    /// - `mssql://triggers/{schema}/{trigger}`
-    async fn complete_resource(
+    /// Returns the number of rows loaded and INSERT batches executed. On
+    /// failure, returns the rows that remain in the target table (zero when
+    /// the load was transactional or staged) alongside the error.
+    async fn load_import(
         &self,
-        partial_uri: &str,
-        _ctx: &Context<'_>,
-    ) -> Result<Vec<String>, McpError> {
-        debug!("Completing resource URI: {}", partial_uri);
-
-        // Parse the partial URI to determine what we're completing
-        let completions = if partial_uri.starts_with("mssql://tables/") {
-            self.complete_table_resource(partial_uri).await?
-        } else if partial_uri.starts_with("mssql://views/") {
-            self.complete_view_resource(partial_uri).await?
-        } else if partial_uri.starts_with("mssql://procedures/") {
-            self.complete_procedure_resource(partial_uri).await?
-        } else if partial_uri.starts_with("mssql://functions/") {
-            self.complete_function_resource(partial_uri).await?
-        } else if partial_uri.starts_with("mssql://triggers/") {
-            self.complete_trigger_resource(partial_uri).await?
-        } else if partial_uri.starts_with("mssql://") {
-            // Complete top-level resource types
-            vec![
-                "mssql://server/info".to_string(),
-                "mssql://databases".to_string(),
-                "mssql://schemas".to_string(),
-                "mssql://tables".to_string(),
-                "mssql://views".to_string(),
-                "mssql://procedures".to_string(),
-                "mssql://functions".to_string(),
-                "mssql://triggers".to_string(),
-            ]
-            .into_iter()
-            .filter(|uri| uri.starts_with(partial_uri))
-            .collect()
-        } else {
-            Vec::new()
+        reader: &mut SourceReader,
+        plan: &ImportPlan,
+        input: &ImportDataInput,
+        schema: &str,
+        table: &str,
+        current_db: Option<&str>,
+    ) -> Result<(usize, usize), (usize, ServerError)> {
+        let names = || -> Result<_, ServerError> {
+            let escaped_schema = safe_identifier(schema)?;
+            let target = format!("{}.{}", escaped_schema, safe_identifier(table)?);
+            let staging = if input.use_staging_table {
+                let suffix = uuid::Uuid::new_v4().simple().to_string();
+                let name = format!("__import_{}_{}", table, &suffix[..8]);
+                Some(format!("{}.{}", escaped_schema, safe_identifier(&name)?))
+            } else {
+                None
+            };
+            Ok((target, staging, plan.column_list()?))
         };
+        use crate::constants::MAX_IMPORT_BATCH_SIZE;
+        let (target, staging, column_list) = names().map_err(|e| (0, e))?;
+        let batch_size = input.batch_size.clamp(1, MAX_IMPORT_BATCH_SIZE);
+
+        // The transaction connection keeps its database context, so USE is issued once
+        let transaction_id = input
+            .use_transaction
+            .then(|| format!("import_{}", uuid::Uuid::new_v4().simple()));
+        let tx = transaction_id.as_deref();
+        let statement_db = if tx.is_some() { None } else { current_db };
+        if let Some(id) = tx {
+            self.transaction_manager
+                .begin_transaction(id, IsolationLevel::ReadCommitted, None, None)
+                .await
+                .map_err(|e| (0, e))?;
+        }
+
+        let mut inserted = 0usize;
+        let mut batches = 0usize;
+        let mut staging_created = false;
+        let load: Result<(), ServerError> = async {
+            if let (Some(_), Some(db)) = (tx, current_db) {
+                let use_db = format!("USE [{}]", db.replace(']', "]]"));
+                self.run_import_statement(tx, None, &use_db).await?;
+            }
+            if let Some(staging) = &staging {
+                let create = format!(
+                    "SELECT TOP 0 {} INTO {} FROM {}",
+                    column_list, staging, target
+                );
+                self.run_import_statement(tx, statement_db, &create).await?;
+                staging_created = true;
+            }
+
+            let insert_into = staging.as_deref().unwrap_or(&target);
+            let mut values = Vec::with_capacity(batch_size);
+            loop {
+                let record = reader.next_record()?;
+                if let Some(Ok(row)) = record
+                    .as_ref()
+                    .map(|r| plan.convert(r, input.empty_as_null))
+                {
+                    values.push(row);
+                }
+                if values.len() >= batch_size || (record.is_none() && !values.is_empty()) {
+                    let insert = format!(
+                        "INSERT INTO {} ({}) VALUES {}",
+                        insert_into,
+                        column_list,
+                        values.join(", ")
+                    );
+                    self.run_import_statement(tx, statement_db, &insert).await?;
+                    inserted += values.len();
+                    batches += 1;
+                    values.clear();
+                    report_progress(
+                        inserted as u64,
+                        None,
+                        &format!("{} rows loaded in {} batches", inserted, batches),
+                    );
+                }
+                if record.is_none() {
+                    break;
+                }
+            }
+
+            if let Some(staging) = &staging {
+                let copy = format!(
+                    "INSERT INTO {} ({}) SELECT {} FROM {}",
+                    target, column_list, column_list, staging
+                );
+                self.run_import_statement(tx, statement_db, &copy).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        // Drop the staging table unless a rollback is about to discard it
+        if let Some(staging) = staging.as_deref().filter(|_| staging_created) {
+            if tx.is_none() || load.is_ok() {
+                let drop = format!(
+                    "IF OBJECT_ID(N'{}', N'U') IS NOT NULL DROP TABLE {}",
+                    staging.replace('\'', "''"),
+                    staging
+                );
+                if let Err(e) = self.run_import_statement(tx, statement_db, &drop).await {
+                    warn!("Failed to drop import staging table {}: {}", staging, e);
+                }
+            }
+        }
 
-        Ok(completions)
+        match (tx, load) {
+            (Some(id), Ok(())) => match self.transaction_manager.commit_transaction(id, None).await
+            {
+                Ok(()) => Ok((inserted, batches)),
+                Err(e) => Err((0, e)),
+            },
+            (Some(id), Err(e)) => {
+                if let Err(rollback_err) = self
+                    .transaction_manager
+                    .rollback_transaction(id, None, None)
+                    .await
+                {
+                    warn!(
+                        "Failed to roll back import transaction {}: {}",
+                        id, rollback_err
+                    );
+                }
+                Err((0, e))
+            }
+            (None, Ok(())) => Ok((inserted, batches)),
+            // A failed staged load never reaches the target table
+            (None, Err(e)) if staging.is_some() => Err((0, e)),
+            (None, Err(e)) => Err((inserted, e)),
+        }
     }
 
-    /// Complete a partial prompt argument.
-    ///
-    /// Supports completion for prompts:
-    /// - `query_table`, `analyze_schema`, `generate_insert`: schema, table
-    /// - `explain_procedure`: schema, procedure
-    async fn complete_prompt_arg(
+    /// Execute one import statement, in the import transaction if there is one.
+    async fn run_import_statement(
         &self,
-        prompt_name: &str,
-        arg_name: &str,
-        partial_value: &str,
-        _ctx: &Context<'_>,
-    ) -> Result<Vec<String>, McpError> {
-        debug!(
-            "Completing prompt '{}' arg '{}' with prefix '{}'",
-            prompt_name, arg_name, partial_value
-        );
+        transaction_id: Option<&str>,
+        current_db: Option<&str>,
+        sql: &str,
+    ) -> Result<(), ServerError> {
+        match (transaction_id, current_db) {
+            (Some(id), _) => {
+                self.transaction_manager
+                    .execute_in_transaction(id, sql)
+                    .await?;
+            }
+            (None, Some(db)) => {
+                self.executor
+                    .execute_non_query(&format!("USE [{}];\n{}", db.replace(']', "]]"), sql))
+                    .await?;
+            }
+            (None, None) => {
+                self.executor.execute_non_query(sql).await?;
+            }
+        }
+        Ok(())
+    }
+}
 
-        let completions = match (prompt_name, arg_name) {
-            // Schema completion for all prompts that have schema arguments
-            (_, "schema") => self.complete_schemas(partial_value).await?,
+/// Helper methods for index and statistics maintenance.
+impl MssqlMcpServer {
+    /// Read index fragmentation for the current database.
+    async fn load_index_fragmentation(
+        &self,
+        schema: Option<&str>,
+        table: Option<&str>,
+        mode: &str,
+        min_page_count: u64,
+    ) -> Result<Vec<crate::database::maintenance::IndexFragmentation>, ServerError> {
+        use crate::database::maintenance::{fragmentation_query, IndexFragmentation, SCAN_MODES};
+
+        for name in [schema, table].into_iter().flatten() {
+            validate_identifier(name)?;
+        }
+        let mode = mode.to_uppercase();
+        if !SCAN_MODES.contains(&mode.as_str()) {
+            return Err(ServerError::invalid_input(format!(
+                "Invalid scan mode '{}': expected limited, sampled, or detailed",
+                mode.to_lowercase()
+            )));
+        }
 
-            // Table completion for table-related prompts
-            ("query_table" | "analyze_schema" | "generate_insert", "table") => {
-                self.complete_tables(partial_value).await?
+        let query = fragmentation_query(schema, table, &mode, min_page_count);
+        let query = {
+            let state = self.state.read().await;
+            match state.current_database() {
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), query),
+                None => query,
             }
+        };
 
-            // Procedure completion for procedure-related prompts
-            ("explain_procedure", "procedure") => self.complete_procedures(partial_value).await?,
-
-            // Column completion would be context-dependent (needs table name)
-            // For now, return empty
-            (_, "columns") => Vec::new(),
+        let result = self.executor.execute(&query).await?;
+        Ok(result
+            .rows
+            .iter()
+            .filter_map(IndexFragmentation::from_row)
+            .collect())
+    }
 
-            // Query and error completion - no suggestions
-            (_, "query" | "error") => Vec::new(),
+    /// Read statistics freshness for the current database.
+    async fn load_statistics(
+        &self,
+        schema: Option<&str>,
+        table: Option<&str>,
+        threshold_percent: Option<f64>,
+    ) -> Result<Vec<crate::database::maintenance::StatisticsInfo>, ServerError> {
+        use crate::database::maintenance::{statistics_query, StatisticsInfo};
+
+        for name in [schema, table].into_iter().flatten() {
+            validate_identifier(name)?;
+        }
+        if threshold_percent.is_some_and(|p| !(0.0..=100.0).contains(&p)) {
+            return Err(ServerError::invalid_input(
+                "modification_threshold_percent must be between 0 and 100",
+            ));
+        }
 
-            _ => Vec::new(),
+        let query = statistics_query(schema, table);
+        let query = {
+            let state = self.state.read().await;
+            match state.current_database() {
+                Some(db) => format!("USE [{}];\n{}", db.replace(']', "]]"), query),
+                None => query,
+            }
         };
 
-        Ok(completions)
+        let result = self.executor.execute(&query).await?;
+        Ok(result
+            .rows
+            .iter()
+            .filter_map(|row| StatisticsInfo::from_row(row, threshold_percent))
+            .collect())
     }
 }
 
@@ -4423,61 +13462,6 @@ impl MssqlMcpServer {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_cursor_encoding() {
-        let offset = 100;
-        let cursor = encode_cursor(offset);
-        let decoded = decode_cursor(&cursor).unwrap();
-        assert_eq!(offset, decoded);
-    }
-
-    #[test]
-    fn test_cursor_encoding_large() {
-        let offset = 10000;
-        let cursor = encode_cursor(offset);
-        let decoded = decode_cursor(&cursor).unwrap();
-        assert_eq!(offset, decoded);
-    }
-
-    #[test]
-    fn test_build_parameterized_query() {
-        let mut params = std::collections::HashMap::new();
-        params.insert("name".to_string(), serde_json::json!("test"));
-        params.insert("id".to_string(), serde_json::json!(42));
-
-        let (_query, decls, vals) = build_parameterized_query(
-            "SELECT * FROM Users WHERE name = @name AND id = @id",
-            &params,
-        )
-        .unwrap();
-
-        assert!(!decls.is_empty());
-        assert!(!vals.is_empty());
-        assert!(decls.contains("NVARCHAR(MAX)"));
-        assert!(decls.contains("BIGINT"));
-    }
-
-    #[test]
-    fn test_format_parameter_value() {
-        assert_eq!(format_parameter_value(&serde_json::json!(null)), "NULL");
-        assert_eq!(format_parameter_value(&serde_json::json!(true)), "1");
-        assert_eq!(format_parameter_value(&serde_json::json!(false)), "0");
-        assert_eq!(format_parameter_value(&serde_json::json!(42)), "42");
-        assert_eq!(
-            format_parameter_value(&serde_json::json!("test")),
-            "N'test'"
-        );
-        assert_eq!(
-            format_parameter_value(&serde_json::json!("it's a test")),
-            "N'it''s a test'"
-        );
-    }
-}
-
 // =========================================================================
 // TaskHandler Implementation
 // =========================================================================
@@ -4660,3 +13644,308 @@ impl TaskHandler for MssqlMcpServer {
         }
     }
 }
+
+/// Helper methods for graph tables.
+impl MssqlMcpServer {
+    /// Node and edge tables of a database with their edge constraints.
+    ///
+    /// Edge constraints need SQL Server 2019; on older servers the tables
+    /// are returned without connections.
+    async fn graph_tables(
+        &self,
+        database: Option<&str>,
+    ) -> Result<Vec<crate::database::graph::GraphTable>, String> {
+        use crate::database::graph::{GraphTable, EDGE_CONSTRAINTS_QUERY, GRAPH_TABLES_QUERY};
+
+        let prefix = database
+            .map(|db| format!("USE [{}];\n", db.replace(']', "]]")))
+            .unwrap_or_default();
+        let tables = self
+            .executor
+            .execute(&format!("{}{}", prefix, GRAPH_TABLES_QUERY))
+            .await
+            .map_err(|e| format!("Failed to list graph tables: {}", e))?;
+        if tables.rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let constraints = match self
+            .executor
+            .execute(&format!("{}{}", prefix, EDGE_CONSTRAINTS_QUERY))
+            .await
+        {
+            Ok(result) => result.rows,
+            Err(e) => {
+                debug!("Could not read edge constraints: {}", e);
+                Vec::new()
+            }
+        };
+        Ok(GraphTable::from_rows(&tables.rows, &constraints))
+    }
+}
+
+/// Helper methods for In-Memory OLTP.
+impl MssqlMcpServer {
+    /// Cross-container and locking hint warnings for a query that touches
+    /// memory-optimized tables.
+    ///
+    /// Best-effort: lookup failures produce no warnings.
+    async fn memory_optimized_warnings(&self, query: &str, database: Option<&str>) -> Vec<String> {
+        use crate::cache::{modified_tables, referenced_tables};
+        use crate::database::xtp::{cross_container_warnings, table_kinds_query, ReferencedTable};
+
+        let mut tables = referenced_tables(query);
+        tables.extend(modified_tables(query));
+        tables.sort();
+        tables.dedup();
+        if tables.is_empty() {
+            return Vec::new();
+        }
+
+        let lookup = match database {
+            Some(db) => format!(
+                "USE [{}];\n{}",
+                db.replace(']', "]]"),
+                table_kinds_query(&tables)
+            ),
+            None => table_kinds_query(&tables),
+        };
+        match self.executor.execute(&lookup).await {
+            Ok(result) => {
+                let tables: Vec<ReferencedTable> = result
+                    .rows
+                    .iter()
+                    .filter_map(ReferencedTable::from_row)
+                    .collect();
+                cross_container_warnings(query, &tables)
+            }
+            Err(e) => {
+                debug!("Could not look up memory-optimized tables: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Helper methods for Availability Group and replication health.
+impl MssqlMcpServer {
+    /// Health report of the Availability Group databases visible from this server.
+    async fn ag_health_report(
+        &self,
+        availability_group: Option<&str>,
+        database: Option<&str>,
+    ) -> Result<serde_json::Value, String> {
+        use crate::database::hadr::{ReplicaDatabaseState, AG_DATABASE_STATES_QUERY};
+
+        let result = self
+            .executor
+            .execute(AG_DATABASE_STATES_QUERY)
+            .await
+            .map_err(|e| format!("Failed to query Availability Group state: {}", e))?;
+        let states: Vec<ReplicaDatabaseState> = result
+            .rows
+            .iter()
+            .filter_map(ReplicaDatabaseState::from_row)
+            .filter(|s| {
+                availability_group.is_none_or(|g| s.availability_group.eq_ignore_ascii_case(g))
+            })
+            .filter(|s| database.is_none_or(|d| s.database.eq_ignore_ascii_case(d)))
+            .collect();
+
+        if states.is_empty() {
+            return Ok(json!({
+                "availability_groups": [],
+                "note": "No availability databases found; the server may not have Always On enabled or is not a replica of a matching group",
+            }));
+        }
+
+        let connected_to_primary = states.iter().any(|s| s.is_local && s.is_primary());
+        let lagging: Vec<serde_json::Value> = states
+            .iter()
+            .filter(|s| !s.caught_up)
+            .map(|s| {
+                json!({
+                    "availability_group": s.availability_group,
+                    "replica": s.replica,
+                    "database": s.database,
+                    "synchronization_state": s.synchronization_state,
+                    "log_send_queue_kb": s.log_send_queue_kb,
+                    "redo_queue_kb": s.redo_queue_kb,
+                    "commit_lag_seconds": s.commit_lag_seconds,
+                    "estimated_redo_seconds": s.estimated_redo_seconds,
+                })
+            })
+            .collect();
+
+        let mut groups: Vec<String> = states
+            .iter()
+            .map(|s| s.availability_group.clone())
+            .collect();
+        groups.dedup();
+        let availability_groups: Vec<serde_json::Value> = groups
+            .iter()
+            .map(|group| {
+                let members: Vec<&ReplicaDatabaseState> = states
+                    .iter()
+                    .filter(|s| &s.availability_group == group)
+                    .collect();
+                json!({
+                    "name": group,
+                    "primary_replica": members.iter().find(|s| s.is_primary()).map(|s| &s.replica),
+                    "all_secondaries_caught_up": members.iter().all(|s| s.caught_up),
+                    "databases": members,
+                })
+            })
+            .collect();
+
+        let mut response = json!({
+            "connected_to_primary": connected_to_primary,
+            "all_caught_up": lagging.is_empty(),
+            "lagging": lagging,
+            "availability_groups": availability_groups,
+        });
+        if !connected_to_primary {
+            response["note"] = json!(
+                "Connected to a secondary replica, which only reports its own databases; connect to the primary to see every replica's lag"
+            );
+        }
+        Ok(response)
+    }
+
+    /// Status of the replication agents of this server's distribution databases.
+    async fn replication_status_report(
+        &self,
+        distribution_database: Option<&str>,
+    ) -> Result<serde_json::Value, String> {
+        use crate::database::hadr::{
+            replication_agents_query, ReplicationAgent, DISTRIBUTION_DATABASES_QUERY,
+        };
+
+        let databases: Vec<String> = match distribution_database {
+            Some(db) => vec![db.to_string()],
+            None => self
+                .executor
+                .execute(DISTRIBUTION_DATABASES_QUERY)
+                .await
+                .map_err(|e| format!("Failed to find distribution databases: {}", e))?
+                .rows
+                .iter()
+                .filter_map(|row| row.get("name").map(|v| v.to_display_string()))
+                .collect(),
+        };
+        if databases.is_empty() {
+            return Ok(json!({
+                "distribution_databases": [],
+                "agents": [],
+                "note": "This server is not a distributor; check replication on the server hosting the distribution database",
+            }));
+        }
+
+        let mut agents = Vec::new();
+        let mut errors = serde_json::Map::new();
+        for db in &databases {
+            match self.executor.execute(&replication_agents_query(db)).await {
+                Ok(result) => agents.extend(
+                    result
+                        .rows
+                        .iter()
+                        .filter_map(ReplicationAgent::from_row)
+                        .map(|agent| (db.clone(), agent)),
+                ),
+                Err(e) => {
+                    errors.insert(db.clone(), json!(e.to_string()));
+                }
+            }
+        }
+
+        let unhealthy: Vec<&str> = agents
+            .iter()
+            .filter(|(_, a)| matches!(a.status, "failed" | "retrying" | "never_run"))
+            .map(|(_, a)| a.name.as_str())
+            .collect();
+        let max_latency_ms = agents.iter().filter_map(|(_, a)| a.latency_ms).max();
+        let agents: Vec<serde_json::Value> = agents
+            .iter()
+            .map(|(db, agent)| {
+                let mut value = json!(agent);
+                value["distribution_database"] = json!(db);
+                value
+            })
+            .collect();
+
+        let mut response = json!({
+            "distribution_databases": databases,
+            "agent_count": agents.len(),
+            "unhealthy_agents": unhealthy,
+            "max_latency_ms": max_latency_ms,
+            "agents": agents,
+        });
+        if !errors.is_empty() {
+            response["errors"] = json!(errors);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_parameterized_query() {
+        let mut params = std::collections::HashMap::new();
+        params.insert("name".to_string(), serde_json::json!("test"));
+        params.insert("id".to_string(), serde_json::json!(42));
+
+        let (_query, decls, vals) = build_parameterized_query(
+            "SELECT * FROM Users WHERE name = @name AND id = @id",
+            &params,
+        )
+        .unwrap();
+
+        assert!(!decls.is_empty());
+        assert!(!vals.is_empty());
+        assert!(decls.contains("NVARCHAR(MAX)"));
+        assert!(decls.contains("BIGINT"));
+    }
+
+    #[test]
+    fn test_detect_naming_conventions() {
+        let tables = vec![
+            "Customers".to_string(),
+            "Orders".to_string(),
+            "tblLegacy".to_string(),
+        ];
+        let columns = vec![
+            "Id".to_string(),
+            "CustomerId".to_string(),
+            "order_date".to_string(),
+        ];
+        let findings = detect_naming_conventions(&tables, &columns);
+        assert!(findings
+            .iter()
+            .any(|f| f == "Tables mostly use PascalCase (2 of 3)"));
+        assert!(findings.iter().any(|f| f.contains("'tbl'/'t_' prefix")));
+        assert!(findings.iter().any(|f| f.contains("mostly plural")));
+        assert!(findings
+            .iter()
+            .any(|f| f == "Key columns: 1 named 'Id', 1 with an Id/_id suffix"));
+
+        assert!(detect_naming_conventions(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_format_parameter_value() {
+        assert_eq!(format_parameter_value(&serde_json::json!(null)), "NULL");
+        assert_eq!(format_parameter_value(&serde_json::json!(true)), "1");
+        assert_eq!(format_parameter_value(&serde_json::json!(false)), "0");
+        assert_eq!(format_parameter_value(&serde_json::json!(42)), "42");
+        assert_eq!(
+            format_parameter_value(&serde_json::json!("test")),
+            "N'test'"
+        );
+        assert_eq!(
+            format_parameter_value(&serde_json::json!("it's a test")),
+            "N'it''s a test'"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Rendering of tool results in the requested output format.
+//!
+//! Every tool that returns rows takes a `format` parameter; they all go
+//! through [`render`] so markdown, JSON and CSV output look the same whichever
+//! tool produced them.
+
+use super::inputs::OutputFormat;
+use crate::database::statement_results::BatchResult;
+use crate::database::{MultiQueryResult, ProcedureResult, QueryResult};
+use serde::Serialize;
+use tracing::warn;
+
+/// A result that can be rendered as a markdown table, JSON or CSV.
+pub trait FormattedResult: Serialize {
+    /// Render as markdown tables with any messages and status lines.
+    fn markdown(&self) -> String;
+
+    /// Render as CSV, result sets separated by blank lines.
+    fn csv(&self) -> String;
+}
+
+macro_rules! formatted_result {
+    ($($ty:ty),*) => {
+        $(
+            impl FormattedResult for $ty {
+                fn markdown(&self) -> String {
+                    self.to_markdown_table()
+                }
+
+                fn csv(&self) -> String {
+                    self.to_csv()
+                }
+            }
+        )*
+    };
+}
+
+formatted_result!(QueryResult, MultiQueryResult, ProcedureResult, BatchResult);
+
+/// Render a result in the given format.
+pub fn render<T: FormattedResult>(result: &T, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(result).unwrap_or_else(|e| {
+            warn!("Failed to serialize result to JSON: {}", e);
+            format!("Failed to serialize result: {}", e)
+        }),
+        OutputFormat::Csv => result.csv(),
+        OutputFormat::Table => result.markdown(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::SqlValue;
+    use crate::database::{QueryColumnInfo as ColumnInfo, ResultRow};
+
+    fn result() -> QueryResult {
+        let mut row = ResultRow::new();
+        row.insert("id".into(), SqlValue::I64(1));
+        QueryResult {
+            columns: vec![ColumnInfo {
+                name: "id".into(),
+                sql_type: "int".into(),
+                nullable: false,
+            }],
+            rows: vec![row],
+            ..QueryResult::empty()
+        }
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let result = result();
+        assert_eq!(render(&result, OutputFormat::Csv), result.to_csv());
+        assert_eq!(
+            render(&result, OutputFormat::Table),
+            result.to_markdown_table()
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&result, OutputFormat::Json)).unwrap();
+        assert_eq!(json["columns"][0]["name"], "id");
+    }
+
+    #[test]
+    fn test_render_multiple_result_sets() {
+        let multi = MultiQueryResult {
+            result_sets: vec![result(), result()],
+            execution_time_ms: 0,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&multi, OutputFormat::Json)).unwrap();
+        assert_eq!(json["result_sets"].as_array().map(Vec::len), Some(2));
+    }
+}
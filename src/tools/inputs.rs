@@ -1,5 +1,9 @@
 //! Tool input types with JSON Schema generation.
 
+pub use crate::database::blob::BinaryFormat;
+pub use crate::database::pagination::PaginationMode;
+pub use crate::database::udt::SpatialFormat;
+pub use crate::telemetry::QueryShapeOrder;
 use mcpkit::ToolInput;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -91,6 +95,8 @@ pub enum ExportFormat {
     Json,
     /// JSON Lines format (one JSON object per line).
     JsonLines,
+    /// Excel workbook (one worksheet per result set); requires a destination path.
+    Xlsx,
 }
 
 impl ExportFormat {
@@ -100,6 +106,7 @@ impl ExportFormat {
             ExportFormat::Csv => "csv",
             ExportFormat::Json => "json",
             ExportFormat::JsonLines => "json_lines",
+            ExportFormat::Xlsx => "xlsx",
         }
     }
 
@@ -107,9 +114,9 @@ impl ExportFormat {
     pub fn tool_input_schema() -> serde_json::Value {
         serde_json::json!({
             "type": "string",
-            "enum": ["csv", "json", "json_lines"],
+            "enum": ["csv", "json", "json_lines", "xlsx"],
             "default": "csv",
-            "description": "Export format: 'csv', 'json', or 'json_lines'"
+            "description": "Export format: 'csv', 'json', 'json_lines', or 'xlsx' (file exports only)"
         })
     }
 }
@@ -128,6 +135,7 @@ impl FromStr for ExportFormat {
             "csv" => Ok(ExportFormat::Csv),
             "json" => Ok(ExportFormat::Json),
             "json_lines" | "jsonlines" | "jsonl" => Ok(ExportFormat::JsonLines),
+            "xlsx" | "excel" => Ok(ExportFormat::Xlsx),
             _ => Err(InvalidExportFormatError(s.to_string())),
         }
     }
@@ -141,7 +149,7 @@ impl fmt::Display for InvalidExportFormatError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Invalid export format '{}'. Valid formats: csv, json, json_lines",
+            "Invalid export format '{}'. Valid formats: csv, json, json_lines, xlsx",
             self.0
         )
     }
@@ -149,6 +157,46 @@ impl fmt::Display for InvalidExportFormatError {
 
 impl std::error::Error for InvalidExportFormatError {}
 
+/// Source file format for `import_data`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// Infer the format from the file extension (default).
+    #[default]
+    Auto,
+    /// CSV format (RFC 4180 quoting).
+    Csv,
+    /// JSON Lines format (one JSON object per line).
+    JsonLines,
+}
+
+impl ImportFormat {
+    /// Get the format name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Auto => "auto",
+            ImportFormat::Csv => "csv",
+            ImportFormat::JsonLines => "json_lines",
+        }
+    }
+
+    /// Generate JSON Schema for this type.
+    pub fn tool_input_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "string",
+            "enum": ["auto", "csv", "json_lines"],
+            "default": "auto",
+            "description": "Source format: 'auto' (from file extension), 'csv', or 'json_lines'"
+        })
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Input for the `execute_query` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ExecuteQueryInput {
@@ -166,6 +214,52 @@ pub struct ExecuteQueryInput {
     /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
     #[serde(default)]
     pub format: OutputFormat,
+
+    /// Disable automatic retry for this request, e.g. when duplicate side effects
+    /// would be dangerous (default: false).
+    #[serde(default)]
+    pub no_retry: bool,
+
+    /// Representation of geometry/geography values: 'wkt', 'geojson', or 'binary'
+    /// (default: wkt). hierarchyid values are returned as their path, e.g. /1/3/.
+    #[serde(default)]
+    pub spatial_format: SpatialFormat,
+
+    /// Representation of binary values: 'hex' (truncated preview), 'base64' (full content),
+    /// or 'omit' (size only). Default: server setting (MSSQL_BINARY_FORMAT, hex).
+    #[serde(default)]
+    pub binary_format: Option<BinaryFormat>,
+
+    /// Return the rows read so far, marked as truncated, instead of failing when the
+    /// result exceeds the memory budget (default: false).
+    #[serde(default)]
+    pub allow_partial: bool,
+}
+
+/// Input for the `fetch_result_chunk` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct FetchResultChunkInput {
+    /// Result reference (res_...) returned in place of a large result.
+    pub result_ref: String,
+
+    /// Zero-based row offset to start from (default: 0).
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Number of rows to return (default: 100, max: 10000).
+    #[serde(default = "default_page_size")]
+    pub limit: usize,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// Input for the `confirm_execution` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ConfirmExecutionInput {
+    /// Confirmation token (confirm_...) returned in place of running a destructive statement.
+    pub confirmation_token: String,
 }
 
 /// Input for the `execute_procedure` tool.
@@ -185,6 +279,11 @@ pub struct ExecuteProcedureInput {
     /// Execution timeout in seconds.
     #[serde(default)]
     pub timeout_seconds: Option<u64>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    /// JSON output lists every result set in order.
+    #[serde(default)]
+    pub format: OutputFormat,
 }
 
 fn default_schema() -> String {
@@ -204,6 +303,34 @@ pub struct ExecuteAsyncInput {
     /// Per-query timeout in seconds. Overrides the global timeout for this query.
     #[serde(default)]
     pub timeout_seconds: Option<u64>,
+
+    /// Return the rows read so far, marked as truncated, instead of failing when the
+    /// result exceeds the memory budget (default: false).
+    #[serde(default)]
+    pub allow_partial: bool,
+}
+
+/// Input for the `run_script` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct RunScriptInput {
+    /// Multi-batch script; batches are separated by `GO` lines.
+    pub script: String,
+
+    /// Values for `$(NAME)` references; `:setvar` in the script takes precedence.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+
+    /// Database to run the script in (default: the connection's database).
+    #[serde(default)]
+    pub database: Option<String>,
+
+    /// Stop at the first failing batch (default: true). `:on error` in the script overrides this.
+    #[serde(default = "default_true")]
+    pub stop_on_error: bool,
+
+    /// Timeout in seconds for each batch.
+    #[serde(default)]
+    pub batch_timeout_seconds: Option<u64>,
 }
 
 /// Input for the `get_session_status` tool.
@@ -215,6 +342,11 @@ pub struct GetSessionStatusInput {
     /// Include query results if completed (default: true).
     #[serde(default = "default_true")]
     pub include_results: bool,
+
+    /// Format of the included results: 'table' (markdown), 'json' (rows), or
+    /// 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
 }
 
 fn default_true() -> bool {
@@ -237,12 +369,58 @@ pub struct ExplainQueryInput {
     /// Plan type: 'estimated' or 'actual' (default: estimated).
     #[serde(default = "default_plan_type")]
     pub plan_type: String,
+
+    /// Return the raw showplan rows as a table instead of the parsed
+    /// operator tree (default: false).
+    #[serde(default)]
+    pub raw: bool,
+
+    /// Output format of the raw showplan rows: 'table' (markdown), 'json', or
+    /// 'csv' (default: table). The parsed operator tree is always JSON.
+    #[serde(default)]
+    pub format: OutputFormat,
 }
 
 fn default_plan_type() -> String {
     "estimated".to_string()
 }
 
+/// Input for the `compare_plans` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ComparePlansInput {
+    /// Baseline query (e.g. the original version).
+    pub before: String,
+
+    /// Candidate query (e.g. the rewrite). When omitted, the current plan of
+    /// `before` is compared with the baseline saved by an earlier call, or
+    /// saved as the baseline if there is none (e.g. before an index change).
+    #[serde(default)]
+    pub after: Option<String>,
+
+    /// Plan type: 'estimated' or 'actual' (default: estimated).
+    #[serde(default = "default_plan_type")]
+    pub plan_type: String,
+
+    /// Replace the saved baseline with the current plan (default: false).
+    #[serde(default)]
+    pub reset_baseline: bool,
+}
+
+/// Input for the `estimate_impact` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct EstimateImpactInput {
+    /// UPDATE or DELETE statement to analyze (it is not executed).
+    pub query: String,
+
+    /// Number of affected rows to preview (default: 10).
+    #[serde(default = "default_impact_sample_size")]
+    pub sample_size: usize,
+}
+
+fn default_impact_sample_size() -> usize {
+    crate::constants::DEFAULT_IMPACT_SAMPLE_SIZE
+}
+
 /// Input for the `list_sessions` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ListSessionsInput {
@@ -255,6 +433,22 @@ fn default_status_filter() -> String {
     "all".to_string()
 }
 
+/// Input for the `purge_sessions` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct PurgeSessionsInput {
+    /// Session IDs to remove (default: every finished session matching the other filters).
+    #[serde(default)]
+    pub session_ids: Vec<String>,
+
+    /// Only remove sessions with this status: 'completed', 'failed', 'cancelled', or 'all' (default: all).
+    #[serde(default = "default_status_filter")]
+    pub status: String,
+
+    /// Only remove sessions that finished at least this many seconds ago.
+    #[serde(default)]
+    pub older_than_seconds: Option<u64>,
+}
+
 /// Input for the `health_check` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct HealthCheckInput {
@@ -263,6 +457,14 @@ pub struct HealthCheckInput {
     pub detailed: bool,
 }
 
+/// Input for the `reset_circuit_breaker` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ResetCircuitBreakerInput {
+    /// Run a connectivity check after the reset (default: true).
+    #[serde(default = "default_true")]
+    pub verify: bool,
+}
+
 /// Input for the `set_timeout` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct SetTimeoutInput {
@@ -278,6 +480,22 @@ pub struct GetTimeoutInput {
     pub detailed: bool,
 }
 
+/// Input for the `reload_config` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ReloadConfigInput {
+    /// Report the settings that would change without applying them (default: false).
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Input for the `rotate_credentials` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct RotateCredentialsInput {
+    /// Re-establish the pools even when the credentials are unchanged (default: false).
+    #[serde(default)]
+    pub force: bool,
+}
+
 /// Input for the `get_session_results` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct GetSessionResultsInput {
@@ -291,6 +509,108 @@ pub struct GetSessionResultsInput {
     /// Maximum rows to return (default: all available).
     #[serde(default)]
     pub max_rows: Option<usize>,
+
+    /// Zero-based row offset to start from (default: 0).
+    #[serde(default)]
+    pub offset: usize,
+}
+
+// =========================================================================
+// Table Watch Inputs
+// =========================================================================
+
+/// Input for the `watch_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct WatchTableInput {
+    /// Schema name (default: dbo).
+    #[serde(default = "default_schema")]
+    pub schema: String,
+
+    /// Table to watch for new or changed rows.
+    pub table: String,
+
+    /// Watermark column that increases on insert/update (rowversion, datetime, or identity).
+    pub watermark_column: String,
+
+    /// Seconds between polls (default: 30, minimum: 5).
+    #[serde(default)]
+    pub poll_interval_seconds: Option<u64>,
+
+    /// Maximum rows fetched per poll (default: 100).
+    #[serde(default)]
+    pub max_rows_per_poll: Option<usize>,
+
+    /// Report existing rows too, instead of only rows added after the watch starts (default: false).
+    #[serde(default)]
+    pub from_beginning: bool,
+}
+
+/// Input for the `get_watch_changes` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetWatchChangesInput {
+    /// Watch ID from watch_table.
+    pub watch_id: String,
+
+    /// Maximum rows to return (default: all queued rows).
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+}
+
+/// Input for the `list_watches` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ListWatchesInput {
+    /// Only list watches on this table (default: all watches).
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+/// Input for the `unwatch_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct UnwatchTableInput {
+    /// Watch ID from watch_table.
+    pub watch_id: String,
+}
+
+// =========================================================================
+// Query Watch Inputs
+// =========================================================================
+
+/// Input for the `watch_query` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct WatchQueryInput {
+    /// Read-only SELECT query to re-execute periodically.
+    pub query: String,
+
+    /// Columns identifying a row, used to report changed rows (default: compare whole rows).
+    #[serde(default)]
+    pub key_columns: Vec<String>,
+
+    /// Seconds between polls (default: 30, minimum: 5).
+    #[serde(default)]
+    pub poll_interval_seconds: Option<u64>,
+
+    /// Send a resources/updated notification for the watch URI when the result changes (default: true).
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+/// Input for the `get_watch_status` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetWatchStatusInput {
+    /// Watch ID from watch_query (omit to list all query watches).
+    #[serde(default)]
+    pub watch_id: Option<String>,
+
+    /// Leave returned changes queued instead of removing them (default: false).
+    #[serde(default)]
+    pub peek: bool,
+}
+
+/// Input for the `unwatch_query` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct UnwatchQueryInput {
+    /// Watch ID from watch_query.
+    pub watch_id: String,
 }
 
 // =========================================================================
@@ -316,6 +636,55 @@ pub struct ExecuteParameterizedInput {
     pub format: OutputFormat,
 }
 
+/// Input for the `register_query` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct RegisterQueryInput {
+    /// Name clients use to run the query (letters, digits and underscores).
+    pub name: String,
+
+    /// SQL of the query, referring to parameters as @name.
+    pub sql: String,
+
+    /// Parameter names (without @) and their SQL types, e.g. {"customer_id": "int"}.
+    #[serde(default)]
+    pub parameters: HashMap<String, String>,
+
+    /// What the query returns, shown by list_registered_queries.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Overwrite a query registered at runtime with the same name (default: false).
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Input for the `execute_registered` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ExecuteRegisteredInput {
+    /// Name of the registered query.
+    pub name: String,
+
+    /// Parameter values by name (with or without @); omitted parameters use their defaults.
+    #[serde(default)]
+    pub parameters: HashMap<String, Value>,
+
+    /// Maximum number of rows to return (default: server configured limit).
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// Input for the `list_registered_queries` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ListRegisteredQueriesInput {
+    /// Include the SQL text of each query (default: false).
+    #[serde(default)]
+    pub include_sql: bool,
+}
+
 // =========================================================================
 // Transaction Control Inputs
 // =========================================================================
@@ -330,6 +699,10 @@ pub struct BeginTransactionInput {
     /// Transaction isolation level: 'read_uncommitted', 'read_committed', 'repeatable_read', 'serializable', 'snapshot' (default: read_committed).
     #[serde(default = "default_isolation_level")]
     pub isolation_level: String,
+
+    /// Database for the transaction's connection (default: the current database).
+    #[serde(default)]
+    pub database: Option<String>,
 }
 
 fn default_isolation_level() -> String {
@@ -354,6 +727,23 @@ pub struct RollbackTransactionInput {
     pub savepoint: Option<String>,
 }
 
+/// Input for the `create_savepoint` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CreateSavepointInput {
+    /// Transaction ID from begin_transaction.
+    pub transaction_id: String,
+
+    /// Savepoint name (at most 32 characters).
+    pub name: String,
+}
+
+/// Input for the `list_savepoints` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ListSavepointsInput {
+    /// Transaction ID from begin_transaction.
+    pub transaction_id: String,
+}
+
 /// Input for the `execute_in_transaction` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ExecuteInTransactionInput {
@@ -366,6 +756,16 @@ pub struct ExecuteInTransactionInput {
     /// Parameters as key-value pairs for parameterized execution.
     #[serde(default)]
     pub parameters: HashMap<String, Value>,
+
+    /// Times to replay the transaction's statements if the server rolls it
+    /// back as a deadlock victim (default: MSSQL_DEADLOCK_REPLAYS; 0 disables).
+    #[serde(default)]
+    pub max_deadlock_replays: Option<u32>,
+
+    /// Output format: 'table' (markdown), 'json' (rows affected and result sets per
+    /// statement), or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
 }
 
 // =========================================================================
@@ -386,6 +786,14 @@ pub struct ExecutePaginatedInput {
     #[serde(default)]
     pub page: Option<PaginationPosition>,
 
+    /// Pagination strategy: 'offset' or 'keyset' (default: offset).
+    #[serde(default)]
+    pub mode: PaginationMode,
+
+    /// Also count the rows of the whole query (default: false).
+    #[serde(default)]
+    pub include_total: bool,
+
     /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
     #[serde(default)]
     pub format: OutputFormat,
@@ -444,44 +852,484 @@ pub struct RecommendIndexesInput {
     pub include_existing: bool,
 }
 
-// =========================================================================
-// Schema Diff Input
-// =========================================================================
-
-/// Input for the `compare_schemas` tool.
+/// Input for the `get_index_fragmentation` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
-pub struct CompareSchemaInput {
-    /// Source schema name to compare from.
-    pub source_schema: String,
+pub struct GetIndexFragmentationInput {
+    /// Only report indexes in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
 
-    /// Target schema name to compare to.
-    pub target_schema: String,
+    /// Only report indexes on this table (default: all tables).
+    #[serde(default)]
+    pub table: Option<String>,
 
-    /// Object types to compare: 'tables', 'views', 'procedures', 'all' (default: all).
-    #[serde(default = "default_object_types")]
-    pub object_types: String,
-}
+    /// Scan mode: 'limited' (fastest, no page density), 'sampled', or 'detailed' (default: sampled).
+    #[serde(default = "default_scan_mode")]
+    pub mode: String,
 
-fn default_object_types() -> String {
-    "all".to_string()
+    /// Skip indexes smaller than this many pages (default: 1000).
+    #[serde(default = "default_index_min_page_count")]
+    pub min_page_count: u64,
 }
 
-/// Input for the `compare_tables` tool.
+/// Input for the `maintain_indexes` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
-pub struct CompareTablesInput {
-    /// Source table in schema.table format.
-    pub source_table: String,
+pub struct MaintainIndexesInput {
+    /// Only maintain indexes in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
 
-    /// Target table in schema.table format.
-    pub target_table: String,
+    /// Only maintain indexes on this table (default: all tables).
+    #[serde(default)]
+    pub table: Option<String>,
 
-    /// Compare indexes between tables (default: true).
-    #[serde(default = "default_true")]
-    pub compare_indexes: bool,
+    /// Reorganize indexes at or above this fragmentation percentage (default: 5).
+    #[serde(default = "default_reorganize_threshold")]
+    pub reorganize_threshold: f64,
 
-    /// Compare constraints between tables (default: true).
-    #[serde(default = "default_true")]
-    pub compare_constraints: bool,
+    /// Rebuild indexes at or above this fragmentation percentage (default: 30).
+    #[serde(default = "default_rebuild_threshold")]
+    pub rebuild_threshold: f64,
+
+    /// Also rebuild indexes whose page density is below this percentage (default: ignore density).
+    #[serde(default)]
+    pub min_page_density: Option<f64>,
+
+    /// Skip indexes smaller than this many pages (default: 1000).
+    #[serde(default = "default_index_min_page_count")]
+    pub min_page_count: u64,
+
+    /// Rebuild with ONLINE = ON (requires Enterprise edition or Azure SQL, default: false).
+    #[serde(default)]
+    pub online: bool,
+
+    /// Execute the generated statements instead of only returning them (default: false).
+    /// Requires the unrestricted validation mode.
+    #[serde(default)]
+    pub execute: bool,
+}
+
+/// Input for the `compression_advisor` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CompressionAdvisorInput {
+    /// Tables to estimate in schema.table format (max: 25).
+    pub tables: Vec<String>,
+
+    /// Compression options to estimate: 'row', 'page', 'columnstore' (default: all three).
+    /// COLUMNSTORE estimates need SQL Server 2019 or later.
+    #[serde(default = "default_compression_options")]
+    pub options: Vec<String>,
+
+    /// Recommend an option when it saves at least this percentage of space (default: 20).
+    #[serde(default = "default_compression_min_savings")]
+    pub min_savings_percent: f64,
+
+    /// Generate statements with ONLINE = ON (requires Enterprise edition or Azure SQL, default: false).
+    #[serde(default)]
+    pub online: bool,
+}
+
+fn default_compression_options() -> Vec<String> {
+    vec![
+        "row".to_string(),
+        "page".to_string(),
+        "columnstore".to_string(),
+    ]
+}
+
+fn default_compression_min_savings() -> f64 {
+    crate::constants::DEFAULT_COMPRESSION_MIN_SAVINGS_PERCENT
+}
+
+/// Input for the `get_stale_statistics` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetStaleStatisticsInput {
+    /// Only report statistics in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Only report statistics on this table (default: all tables).
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Treat statistics as stale once modifications reach this percentage of rows
+    /// (default: SQL Server's automatic update threshold).
+    #[serde(default)]
+    pub modification_threshold_percent: Option<f64>,
+
+    /// Also list statistics below the threshold (default: false).
+    #[serde(default)]
+    pub include_current: bool,
+}
+
+/// Input for the `update_statistics` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct UpdateStatisticsInput {
+    /// Only update statistics in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Only update statistics on this table (default: all tables).
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Only update these statistics objects, by name (default: all stale statistics).
+    #[serde(default)]
+    pub statistics: Vec<String>,
+
+    /// Treat statistics as stale once modifications reach this percentage of rows
+    /// (default: SQL Server's automatic update threshold).
+    #[serde(default)]
+    pub modification_threshold_percent: Option<f64>,
+
+    /// Read every row (WITH FULLSCAN, default: false).
+    #[serde(default)]
+    pub fullscan: bool,
+
+    /// Sample this percentage of rows (WITH SAMPLE n PERCENT).
+    #[serde(default)]
+    pub sample_percent: Option<f64>,
+
+    /// Execute the generated statements instead of only returning them (default: false).
+    /// Requires the unrestricted validation mode.
+    #[serde(default)]
+    pub execute: bool,
+}
+
+fn default_scan_mode() -> String {
+    "sampled".to_string()
+}
+
+fn default_index_min_page_count() -> u64 {
+    crate::constants::DEFAULT_INDEX_MIN_PAGE_COUNT
+}
+
+fn default_reorganize_threshold() -> f64 {
+    crate::constants::DEFAULT_REORGANIZE_THRESHOLD_PERCENT
+}
+
+fn default_rebuild_threshold() -> f64 {
+    crate::constants::DEFAULT_REBUILD_THRESHOLD_PERCENT
+}
+
+// =========================================================================
+// Referential Integrity Input
+// =========================================================================
+
+/// Input for the `check_integrity` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CheckIntegrityInput {
+    /// Only check foreign keys of child tables in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Only check foreign keys from or to this table, in schema.table format.
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Only check disabled and untrusted foreign keys (default: false).
+    #[serde(default)]
+    pub untrusted_only: bool,
+
+    /// Number of missing parent keys listed per foreign key (default: 5; 0 to skip).
+    #[serde(default = "default_integrity_sample_size")]
+    pub sample_size: usize,
+
+    /// Generate a repair script: 'delete' removes orphaned rows, 'set_null' clears
+    /// their foreign key columns. Each script re-validates the constraint. The
+    /// script is returned, never executed.
+    #[serde(default)]
+    pub repair: Option<String>,
+}
+
+fn default_integrity_sample_size() -> usize {
+    5
+}
+
+// =========================================================================
+// Schema Diff Input
+// =========================================================================
+
+/// Input for the `compare_schemas` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CompareSchemaInput {
+    /// Source schema name to compare from.
+    pub source_schema: String,
+
+    /// Target schema name to compare to.
+    pub target_schema: String,
+
+    /// Object types to compare: 'tables', 'views', 'procedures', 'all' (default: all).
+    #[serde(default = "default_object_types")]
+    pub object_types: String,
+}
+
+fn default_object_types() -> String {
+    "all".to_string()
+}
+
+/// Input for the `compare_tables` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CompareTablesInput {
+    /// Source table in schema.table format.
+    pub source_table: String,
+
+    /// Target table in schema.table format.
+    pub target_table: String,
+
+    /// Compare indexes between tables (default: true).
+    #[serde(default = "default_true")]
+    pub compare_indexes: bool,
+
+    /// Compare constraints between tables (default: true).
+    #[serde(default = "default_true")]
+    pub compare_constraints: bool,
+}
+
+/// Input for the `generate_migration` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GenerateMigrationInput {
+    /// Source schema whose structure should be replicated.
+    pub source_schema: String,
+
+    /// Target schema to bring in line with the source.
+    pub target_schema: String,
+
+    /// Emit DROP statements for tables and columns that only exist in the target (default: false).
+    #[serde(default)]
+    pub include_drops: bool,
+
+    /// Only return the generated script without executing it (default: true).
+    #[serde(default = "default_true")]
+    pub preview: bool,
+}
+
+/// Input for the `generate_crud` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GenerateCrudInput {
+    /// Table in schema.table format (schema defaults to dbo).
+    pub table: String,
+
+    /// Procedure name pattern with {schema}, {table} and {action} placeholders
+    /// (default: usp_{table}_{action}).
+    #[serde(default = "default_crud_naming")]
+    pub naming: String,
+
+    /// Operations to generate: Get, List, Insert, Update, Delete (default: all).
+    #[serde(default)]
+    pub operations: Option<Vec<String>>,
+
+    /// Only return the generated procedures without creating them (default: true).
+    #[serde(default = "default_true")]
+    pub preview: bool,
+}
+
+fn default_crud_naming() -> String {
+    crate::database::crud::DEFAULT_CRUD_NAMING.to_string()
+}
+
+/// Input for the `copy_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CopyTableInput {
+    /// Table to copy in schema.table format (schema defaults to dbo).
+    pub source_table: String,
+
+    /// New table in schema.table format; it must not exist yet.
+    pub target_table: String,
+
+    /// Copy primary key, unique, check, default and foreign key constraints (default: false).
+    #[serde(default)]
+    pub include_constraints: bool,
+
+    /// Copy indexes that don't back a constraint (default: false).
+    #[serde(default)]
+    pub include_indexes: bool,
+
+    /// Copy the rows as well as the structure (default: true).
+    #[serde(default = "default_true")]
+    pub copy_data: bool,
+
+    /// Optional WHERE clause limiting the copied rows (without the WHERE keyword).
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Rows per INSERT ... SELECT batch, each in its own transaction
+    /// (default: 10000, max: 100000). Tables without a primary key or
+    /// unique index are copied in one statement.
+    #[serde(default = "default_copy_batch_size")]
+    pub batch_size: usize,
+
+    /// Only return the generated statements without executing them (default: true).
+    #[serde(default = "default_true")]
+    pub preview: bool,
+}
+
+fn default_copy_batch_size() -> usize {
+    crate::constants::DEFAULT_COPY_BATCH_SIZE
+}
+
+/// Input for the `clone_schema` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct CloneSchemaInput {
+    /// Schema to clone.
+    pub source_schema: String,
+
+    /// New schema name; it must not exist yet.
+    pub target_schema: String,
+
+    /// Copy table rows as well as object definitions (default: false).
+    #[serde(default)]
+    pub include_data: bool,
+
+    /// Clone indexes, including indexes on views (default: true).
+    #[serde(default = "default_true")]
+    pub include_indexes: bool,
+
+    /// Clone table and view triggers (default: true).
+    #[serde(default = "default_true")]
+    pub include_triggers: bool,
+
+    /// Only return the generated script without executing it (default: true).
+    #[serde(default = "default_true")]
+    pub preview: bool,
+}
+
+// =========================================================================
+// Dependency Analysis Input
+// =========================================================================
+
+/// Input for the `get_dependencies` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetDependenciesInput {
+    /// Object to analyze in schema.name format (table, view, procedure, function, ...).
+    pub object: String,
+
+    /// Direction: 'uses' (objects it references), 'used_by' (objects referencing it),
+    /// or 'both' (default: both).
+    #[serde(default = "default_dependency_direction")]
+    pub direction: String,
+
+    /// Number of levels to follow (default: 3, max: 10).
+    #[serde(default = "default_dependency_depth")]
+    pub max_depth: usize,
+}
+
+fn default_dependency_direction() -> String {
+    "both".to_string()
+}
+
+fn default_dependency_depth() -> usize {
+    crate::constants::DEFAULT_DEPENDENCY_DEPTH
+}
+
+/// Input for the `script_object` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ScriptObjectInput {
+    /// Object to script in schema.name format (table, view, procedure, function, or trigger).
+    pub object: String,
+
+    /// Start with DROP ... IF EXISTS; otherwise views, procedures, functions and
+    /// triggers are scripted as CREATE OR ALTER (default: false).
+    #[serde(default)]
+    pub include_drop: bool,
+
+    /// Script nonclustered indexes of tables and indexed views (default: true).
+    #[serde(default = "default_true")]
+    pub include_indexes: bool,
+
+    /// Script triggers defined on the table or view (default: true).
+    #[serde(default = "default_true")]
+    pub include_triggers: bool,
+}
+
+/// Input for the `script_database` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ScriptDatabaseInput {
+    /// Directory to write into (or the .sql file in single-file mode), inside
+    /// MSSQL_EXPORT_DIRS. Relative paths resolve against the first export directory.
+    pub destination: String,
+
+    /// Only script objects whose schema.name matches one of these patterns
+    /// (`*` wildcard, case-insensitive, e.g. "dbo.*"). Default: all objects.
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Skip objects whose schema.name matches one of these patterns.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Write one script with every object instead of a folder per object type
+    /// (default: false).
+    #[serde(default)]
+    pub single_file: bool,
+
+    /// Script nonclustered indexes of tables and indexed views (default: true).
+    #[serde(default = "default_true")]
+    pub include_indexes: bool,
+
+    /// Script triggers defined on tables and views (default: true).
+    #[serde(default = "default_true")]
+    pub include_triggers: bool,
+
+    /// Replace existing script files at the destination (default: false).
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+// =========================================================================
+// Schema Search Input
+// =========================================================================
+
+/// Input for the `search_schema` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct SearchSchemaInput {
+    /// Keyword (case-insensitive) or regular expression to search for.
+    pub pattern: String,
+
+    /// Treat the pattern as a regular expression (default: false).
+    #[serde(default)]
+    pub regex: bool,
+
+    /// Where to search: 'names', 'columns', 'definitions', 'extended_properties'
+    /// (default: all).
+    #[serde(default)]
+    pub search_in: Vec<String>,
+
+    /// Only search objects in this schema (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Maximum number of matching objects to return (default: 50).
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    50
+}
+
+// =========================================================================
+// Data Dictionary Input
+// =========================================================================
+
+/// Input for the `generate_data_dictionary` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GenerateDataDictionaryInput {
+    /// Schema to document (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Output format: 'markdown', 'html', or 'json' (default: markdown).
+    #[serde(default = "default_dictionary_format")]
+    pub format: String,
+
+    /// Include row counts (default: true).
+    #[serde(default = "default_true")]
+    pub include_row_counts: bool,
+}
+
+fn default_dictionary_format() -> String {
+    "markdown".to_string()
 }
 
 // =========================================================================
@@ -498,29 +1346,194 @@ pub struct SampleDataInput {
     #[serde(default = "default_sample_size")]
     pub sample_size: usize,
 
-    /// Sampling method: 'random', 'top', 'bottom', 'stratified' (default: random).
-    #[serde(default = "default_sampling_method")]
-    pub method: String,
+    /// Sampling method: 'random', 'top', 'bottom', 'stratified' (default: random).
+    #[serde(default = "default_sampling_method")]
+    pub method: String,
+
+    /// Column to stratify by (required for stratified sampling).
+    #[serde(default)]
+    pub stratify_column: Option<String>,
+
+    /// Optional WHERE clause to filter rows before sampling (without 'WHERE' keyword).
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+fn default_sample_size() -> usize {
+    100
+}
+
+fn default_sampling_method() -> String {
+    "random".to_string()
+}
+
+/// Input for the `query_json_column` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct QueryJsonColumnInput {
+    /// Table in schema.table format.
+    pub table: String,
+
+    /// Text column holding JSON documents.
+    pub column: String,
+
+    /// Number of non-null values sampled to infer the JSON paths (default: 100, max: 1000).
+    #[serde(default = "default_json_sample_size")]
+    pub sample_size: usize,
+
+    /// JSON paths to project, as listed in the inferred schema (e.g. "$.customer.name",
+    /// "$.items[*].sku"). Default: every scalar path outside arrays, or every field of
+    /// the expanded array.
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Array path (e.g. "$.items") whose elements become rows via OPENJSON.
+    #[serde(default)]
+    pub expand_array: Option<String>,
+
+    /// Regular columns of the table to return alongside the JSON values (e.g. the key).
+    #[serde(default)]
+    pub include_columns: Vec<String>,
+
+    /// Optional condition added to the WHERE clause (without 'WHERE'); refer to the
+    /// table as `t` and expanded array fields as `j`.
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Run the generated query; when false only the inferred schema and query are
+    /// returned (default: true).
+    #[serde(default = "default_true")]
+    pub execute: bool,
+
+    /// Maximum rows to return (default: server max_result_rows).
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Output format for the rows: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+fn default_json_sample_size() -> usize {
+    100
+}
+
+/// Input for the `describe_graph` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct DescribeGraphInput {
+    /// Database whose graph tables are described (default: the current database).
+    #[serde(default)]
+    pub database: Option<String>,
+}
+
+/// Input for the `query_history_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct QueryHistoryTableInput {
+    /// System-versioned temporal table in schema.table format.
+    pub table: String,
+
+    /// Return rows as they were at this UTC time (ISO 8601, e.g. 2024-01-31T12:00:00).
+    #[serde(default)]
+    pub as_of: Option<String>,
+
+    /// Start of a UTC time range (ISO 8601); requires end.
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End of a UTC time range (ISO 8601); requires start.
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// Range form: 'between' (versions active in [start, end]), 'from_to' (active in
+    /// [start, end)), or 'contained_in' (opened and closed within the range). Default: between.
+    #[serde(default)]
+    pub range_type: Option<String>,
+
+    /// Return every current and historical row version (default: false).
+    #[serde(default)]
+    pub all_versions: bool,
+
+    /// Columns to return (default: all). The period columns are always included.
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
+
+    /// Optional WHERE clause to filter rows (without 'WHERE' keyword).
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Maximum rows to return (default: server max_result_rows).
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// Input for the `classify_columns` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ClassifyColumnsInput {
+    /// Schema whose columns are classified by name (default: all schemas).
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    /// Table in schema.table format; when set, its text values are also sampled.
+    #[serde(default)]
+    pub table: Option<String>,
+
+    /// Number of rows to sample when a table is given (default: 100).
+    #[serde(default = "default_sample_size")]
+    pub sample_size: usize,
+}
+
+/// Input for the `profile_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ProfileTableInput {
+    /// Table in schema.table format.
+    pub table: String,
 
-    /// Column to stratify by (required for stratified sampling).
+    /// Columns to profile (default: all).
     #[serde(default)]
-    pub stratify_column: Option<String>,
+    pub columns: Option<Vec<String>>,
+
+    /// Number of rows profiled, read from the start of the table (default: 10000,
+    /// max: 1000000).
+    #[serde(default = "default_profile_sample_rows")]
+    pub sample_rows: usize,
+
+    /// Number of most frequent values reported per column (default: 5, max: 50;
+    /// 0 to skip).
+    #[serde(default = "default_profile_top_k")]
+    pub top_k: usize,
+
+    /// Number of histogram buckets for numeric columns (default: 10, max: 50;
+    /// 0 to skip).
+    #[serde(default = "default_profile_histogram_buckets")]
+    pub histogram_buckets: usize,
+
+    /// Seconds to spend in total; columns not reached are listed as skipped
+    /// (default: 30).
+    #[serde(default = "default_profile_time_budget")]
+    pub time_budget_secs: u64,
+}
 
-    /// Optional WHERE clause to filter rows before sampling (without 'WHERE' keyword).
-    #[serde(default)]
-    pub filter: Option<String>,
+fn default_profile_sample_rows() -> usize {
+    crate::constants::DEFAULT_PROFILE_SAMPLE_ROWS
+}
 
-    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
-    #[serde(default)]
-    pub format: OutputFormat,
+fn default_profile_top_k() -> usize {
+    5
 }
 
-fn default_sample_size() -> usize {
-    100
+fn default_profile_histogram_buckets() -> usize {
+    10
 }
 
-fn default_sampling_method() -> String {
-    "random".to_string()
+fn default_profile_time_budget() -> u64 {
+    30
 }
 
 // =========================================================================
@@ -595,23 +1608,208 @@ fn default_batch_size() -> usize {
     1000
 }
 
+/// Input for the `generate_test_data` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GenerateTestDataInput {
+    /// Target table in schema.table format.
+    pub table: String,
+
+    /// Number of rows to insert (max: 100000).
+    pub rows: usize,
+
+    /// Seed for the value generator; the same seed and table produce the same
+    /// rows (default: random, reported in the result).
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Share of nullable columns set to NULL, from 0 to 1 (default: 0.1).
+    #[serde(default = "default_test_data_null_rate")]
+    pub null_rate: f64,
+
+    /// Earliest generated date, YYYY-MM-DD (default: three years ago).
+    #[serde(default)]
+    pub date_from: Option<String>,
+
+    /// Latest generated date, YYYY-MM-DD (default: today).
+    #[serde(default)]
+    pub date_to: Option<String>,
+
+    /// Rows per INSERT statement (default: 500, max: 1000).
+    #[serde(default = "default_import_batch_size")]
+    pub batch_size: usize,
+
+    /// Only return the column plan and a preview of the first rows (default: false).
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn default_test_data_null_rate() -> f64 {
+    0.1
+}
+
+/// Input for the `anonymize_table` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct AnonymizeTableInput {
+    /// Table to scrub in schema.table format.
+    pub table: String,
+
+    /// Scrubbing rule per column: 'hash' (salted SHA-256, equal values stay
+    /// equal), 'shuffle' (permute values between rows), 'fake' (generated
+    /// values of the same kind) or 'nullify'.
+    pub rules: HashMap<String, String>,
+
+    /// Optional WHERE clause limiting the scrubbed rows (without the WHERE keyword).
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Columns identifying rows for batching (default: the primary key).
+    #[serde(default)]
+    pub key_columns: Option<Vec<String>>,
+
+    /// Salt mixed into hashed values; keep it secret and reuse it across
+    /// tables to keep hashed join columns consistent. Required to apply hash
+    /// rules; a dry run without one uses a random salt, reported in the result
+    /// as `generated_salt`, to pass to the real run.
+    #[serde(default)]
+    pub salt: Option<String>,
+
+    /// Seed for fake values (default: random, reported in the result).
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Rows per UPDATE statement and transaction (default: 500, max: 1000).
+    #[serde(default = "default_import_batch_size")]
+    pub batch_size: usize,
+
+    /// Only return the plan, affected row count and before/after samples
+    /// without changing data (default: true).
+    #[serde(default = "default_true")]
+    pub dry_run: bool,
+}
+
 /// Input for the `export_data` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ExportDataInput {
     /// SQL SELECT query to export results from.
     pub query: String,
 
-    /// Export format: 'csv', 'json', 'json_lines' (default: csv).
+    /// Export format: 'csv', 'json', 'json_lines', 'xlsx' (default: csv).
+    /// 'xlsx' writes a workbook with one worksheet per result set and
+    /// requires destination_path.
     #[serde(default)]
     pub format: ExportFormat,
 
-    /// Include column headers in CSV output (default: true).
+    /// Include column headers in CSV and xlsx output (default: true).
     #[serde(default = "default_true")]
     pub include_headers: bool,
 
     /// Maximum rows to export (default: no limit).
     #[serde(default)]
     pub max_rows: Option<usize>,
+
+    /// Write results to this file instead of returning them inline. Must be
+    /// inside a directory listed in MSSQL_EXPORT_DIRS; relative paths are
+    /// resolved against the first one.
+    #[serde(default)]
+    pub destination_path: Option<String>,
+
+    /// Gzip-compress written files (adds a .gz suffix) (default: false).
+    #[serde(default)]
+    pub compress: bool,
+
+    /// Split file output into numbered chunks of at most this many rows.
+    #[serde(default)]
+    pub max_rows_per_file: Option<usize>,
+
+    /// Split file output into numbered chunks of at most this many uncompressed bytes.
+    #[serde(default)]
+    pub max_bytes_per_file: Option<u64>,
+
+    /// Replace existing files at the destination (default: false).
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Input for the `download_blob` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct DownloadBlobInput {
+    /// Table in schema.table format.
+    pub table: String,
+
+    /// Binary column to download (varbinary, binary or image).
+    pub column: String,
+
+    /// WHERE clause (without 'WHERE' keyword) matching exactly one row, e.g. "[Id] = 42".
+    pub filter: String,
+
+    /// File path to write; must be inside a directory listed in MSSQL_EXPORT_DIRS.
+    pub destination: String,
+
+    /// Replace the file if it already exists (default: false).
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Input for the `import_data` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct ImportDataInput {
+    /// File to import. Must be inside a directory listed in MSSQL_IMPORT_DIRS;
+    /// relative paths are resolved against the first one.
+    pub source_path: String,
+
+    /// Target table in schema.table format.
+    pub table: String,
+
+    /// Source format: 'auto', 'csv', 'json_lines' (default: auto).
+    #[serde(default)]
+    pub format: ImportFormat,
+
+    /// Whether the first CSV line is a header row (default: true).
+    /// Without a header, CSV columns map to table columns by position.
+    #[serde(default = "default_true")]
+    pub has_header: bool,
+
+    /// CSV field delimiter, a single character (default: ",").
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+
+    /// Map of source column names to target column names. When omitted,
+    /// source columns are matched to table columns by name (case-insensitive).
+    #[serde(default)]
+    pub column_mapping: HashMap<String, String>,
+
+    /// Treat empty values as NULL (default: true).
+    #[serde(default = "default_true")]
+    pub empty_as_null: bool,
+
+    /// Validate the file against the table without loading it (default: false).
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Number of invalid rows to skip before the import is aborted (default: 0).
+    #[serde(default)]
+    pub max_bad_rows: usize,
+
+    /// Number of rows per INSERT statement (default: 500, max: 1000).
+    #[serde(default = "default_import_batch_size")]
+    pub batch_size: usize,
+
+    /// Load all rows in a single transaction (default: true).
+    #[serde(default = "default_true")]
+    pub use_transaction: bool,
+
+    /// Load rows into a temporary staging table first and copy them into the
+    /// target with a single INSERT ... SELECT (default: false).
+    #[serde(default)]
+    pub use_staging_table: bool,
+}
+
+fn default_delimiter() -> String {
+    ",".to_string()
+}
+
+fn default_import_batch_size() -> usize {
+    crate::constants::DEFAULT_IMPORT_BATCH_SIZE
 }
 
 // =========================================================================
@@ -677,6 +1875,25 @@ pub struct ClearCacheInput {
     pub pattern: Option<String>,
 }
 
+/// Input for the `get_cache_entries` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetCacheEntriesInput {
+    /// Maximum number of entries to list, most frequently hit first (default: 50).
+    #[serde(default = "default_cache_entries_limit")]
+    pub limit: usize,
+}
+
+fn default_cache_entries_limit() -> usize {
+    50
+}
+
+/// Input for the `evict_cache_entry` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct EvictCacheEntryInput {
+    /// Entry ID from get_cache_entries.
+    pub id: String,
+}
+
 /// Input for the `execute_cached` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ExecuteCachedInput {
@@ -712,6 +1929,116 @@ pub struct GetPoolMetricsInput {
     pub include_history: bool,
 }
 
+/// Input for the `get_replica_info` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetReplicaInfoInput {
+    /// List every replica of the server's Availability Groups (default: false).
+    #[serde(default)]
+    pub include_replicas: bool,
+}
+
+/// Input for the `get_file_usage` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetFileUsageInput {
+    /// Database whose files are reported (default: the current database).
+    #[serde(default)]
+    pub database: Option<String>,
+}
+
+/// Input for the `get_tempdb_usage` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetTempdbUsageInput {
+    /// Maximum number of sessions listed, largest consumers first (default: 20).
+    #[serde(default = "default_tempdb_session_limit")]
+    pub limit: usize,
+
+    /// Include system sessions such as background tasks (default: false).
+    #[serde(default)]
+    pub include_system_sessions: bool,
+}
+
+fn default_tempdb_session_limit() -> usize {
+    crate::constants::DEFAULT_TEMPDB_SESSION_LIMIT
+}
+
+/// Input for the `get_active_requests` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetActiveRequestsInput {
+    /// Only list requests running in this database.
+    #[serde(default)]
+    pub database: Option<String>,
+
+    /// Only list requests of this login.
+    #[serde(default)]
+    pub login: Option<String>,
+
+    /// Include each statement's execution plan XML (default: false).
+    #[serde(default)]
+    pub include_plans: bool,
+
+    /// Include system sessions such as background tasks (default: false).
+    #[serde(default)]
+    pub include_system_sessions: bool,
+
+    /// Maximum number of requests listed, longest running first (default: 100).
+    #[serde(default = "default_active_requests_limit")]
+    pub limit: usize,
+}
+
+fn default_active_requests_limit() -> usize {
+    crate::constants::DEFAULT_ACTIVE_REQUESTS_LIMIT
+}
+
+/// Input for the `get_memory_optimized_usage` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetMemoryOptimizedUsageInput {
+    /// Database to report (default: the current database).
+    #[serde(default)]
+    pub database: Option<String>,
+}
+
+/// Input for the `get_ag_health` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetAgHealthInput {
+    /// Only report this Availability Group.
+    #[serde(default)]
+    pub availability_group: Option<String>,
+
+    /// Only report this availability database.
+    #[serde(default)]
+    pub database: Option<String>,
+}
+
+/// Input for the `get_replication_status` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetReplicationStatusInput {
+    /// Distribution database to read (default: every distribution database on the server).
+    #[serde(default)]
+    pub distribution_database: Option<String>,
+}
+
+/// Input for the `test_linked_server` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct TestLinkedServerInput {
+    /// Name of the linked server, as listed by mssql://linkedservers.
+    pub server: String,
+}
+
+/// Input for the `kill_session` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct KillSessionInput {
+    /// SQL Server session ID (SPID) to kill.
+    pub session_id: i64,
+
+    /// Login of the session, echoed back from the first call to confirm the target.
+    #[serde(default)]
+    pub login: Option<String>,
+
+    /// Host of the session, echoed back from the first call to confirm the target.
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
 // =========================================================================
 // Internal Server Metrics Input
 // =========================================================================
@@ -722,6 +2049,83 @@ pub struct GetInternalMetricsInput {
     /// Include calculated rates and averages (default: true).
     #[serde(default = "default_true")]
     pub include_rates: bool,
+
+    /// Number of query shapes to report; 0 omits them (default: 10).
+    #[serde(default = "default_top_query_shapes")]
+    pub top_queries: usize,
+
+    /// Order of the reported query shapes: 'count', 'total_time',
+    /// 'avg_time', 'p95_time', or 'max_time' (default: count).
+    #[serde(default)]
+    pub sort_by: QueryShapeOrder,
+}
+
+fn default_top_query_shapes() -> usize {
+    crate::constants::DEFAULT_TOP_QUERY_SHAPES
+}
+
+// =========================================================================
+// Query History Input
+// =========================================================================
+
+/// Input for the `get_query_history` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetQueryHistoryInput {
+    /// Only calls of this tool (e.g. 'execute_query').
+    #[serde(default)]
+    pub tool: Option<String>,
+
+    /// Only calls from clients whose name contains this text.
+    #[serde(default)]
+    pub client: Option<String>,
+
+    /// Only successful (true) or failed (false) calls.
+    #[serde(default)]
+    pub success: Option<bool>,
+
+    /// Only calls from the last N minutes.
+    #[serde(default)]
+    pub since_minutes: Option<u64>,
+
+    /// Only calls whose query text (or query hash prefix) contains this text.
+    #[serde(default)]
+    pub contains: Option<String>,
+
+    /// Only calls that took at least this many milliseconds.
+    #[serde(default)]
+    pub min_duration_ms: Option<u64>,
+
+    /// Maximum number of entries to return, newest first (default: 50).
+    #[serde(default = "default_query_history_limit")]
+    pub limit: usize,
+}
+
+fn default_query_history_limit() -> usize {
+    crate::constants::DEFAULT_QUERY_HISTORY_LIMIT
+}
+
+// =========================================================================
+// Slow Query Log Input
+// =========================================================================
+
+/// Input for the `get_slow_queries` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GetSlowQueriesInput {
+    /// Maximum number of entries to return, newest first (default: 20).
+    #[serde(default = "default_slow_query_limit")]
+    pub limit: usize,
+
+    /// Include captured execution plan XML (default: false).
+    #[serde(default)]
+    pub include_plans: bool,
+
+    /// Clear the log after reading it (default: false).
+    #[serde(default)]
+    pub clear: bool,
+}
+
+fn default_slow_query_limit() -> usize {
+    crate::constants::DEFAULT_SLOW_QUERY_LIMIT
 }
 
 // =========================================================================
@@ -734,6 +2138,10 @@ pub struct BeginPinnedSessionInput {
     /// Optional name for the pinned session (for identification).
     #[serde(default)]
     pub name: Option<String>,
+
+    /// Database for the session's connection (default: the current database).
+    #[serde(default)]
+    pub database: Option<String>,
 }
 
 /// Input for the `execute_in_pinned_session` tool.
@@ -750,6 +2158,21 @@ pub struct ExecuteInPinnedSessionInput {
     pub format: OutputFormat,
 }
 
+/// Input for the `use_database` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct UseDatabaseInput {
+    /// Database to switch to.
+    pub database: String,
+
+    /// Pinned session to switch (from begin_pinned_session).
+    #[serde(default)]
+    pub session_id: Option<String>,
+
+    /// Transaction to switch (from begin_transaction).
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+}
+
 /// Input for the `end_pinned_session` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct EndPinnedSessionInput {
@@ -780,6 +2203,68 @@ pub struct ValidateSyntaxInput {
     pub database: Option<String>,
 }
 
+/// Input for the `lint_sql` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct LintSqlInput {
+    /// SQL to check (analyzed locally, never executed).
+    pub query: String,
+
+    /// Rules to run (default: all): select-star, nolock, non-sargable, implicit-conversion,
+    /// top-without-order-by, three-part-name, deprecated-syntax.
+    #[serde(default)]
+    pub rules: Vec<String>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// Input for the `describe_query` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct DescribeQueryInput {
+    /// SQL query whose first result set is described (it is compiled, not executed).
+    pub query: String,
+
+    /// Optional database context for the query.
+    #[serde(default)]
+    pub database: Option<String>,
+
+    /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// Input for the `generate_sql` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
+pub struct GenerateSqlInput {
+    /// Natural-language question to answer with a query.
+    pub question: String,
+
+    /// Schema whose tables ground the query (default: dbo).
+    #[serde(default = "default_schema")]
+    pub schema: String,
+
+    /// Tables to always include in the schema excerpts.
+    #[serde(default)]
+    pub tables: Vec<String>,
+
+    /// Maximum number of tables sent to the model (default: 8).
+    #[serde(default = "default_generate_sql_max_tables")]
+    pub max_tables: usize,
+
+    /// Maximum tokens the model may generate (default: 1000).
+    #[serde(default = "default_generate_sql_max_tokens")]
+    pub max_tokens: u32,
+}
+
+fn default_generate_sql_max_tables() -> usize {
+    8
+}
+
+fn default_generate_sql_max_tokens() -> u32 {
+    1000
+}
+
 // =========================================================================
 // Table-Valued Parameters (TVP) Input
 // =========================================================================
@@ -800,8 +2285,10 @@ pub struct TvpColumnDefinition {
 /// as a single parameter. This is more efficient than multiple INSERT statements or
 /// temporary tables for bulk operations.
 ///
-/// Prerequisites: A table type must exist in the database that matches the TVP definition.
-/// Create one with: `CREATE TYPE dbo.MyTableType AS TABLE (Column1 INT, Column2 NVARCHAR(100));`
+/// Prerequisites: A table type must exist in the database. Create one with:
+/// `CREATE TYPE dbo.MyTableType AS TABLE (Column1 INT, Column2 NVARCHAR(100));`
+/// Its column definitions are read from the database and rows are validated
+/// and converted against them.
 #[derive(Debug, Clone, Serialize, Deserialize, ToolInput)]
 pub struct ExecuteWithTvpInput {
     /// SQL query or stored procedure call containing the TVP parameter.
@@ -816,12 +2303,16 @@ pub struct ExecuteWithTvpInput {
     /// Example: "dbo.IntIdList" or "dbo.UserDataType"
     pub tvp_type_name: String,
 
-    /// Column definitions for the TVP. Must match the table type definition.
+    /// Optional column definitions. The table type's definition is looked up
+    /// in the database; if given, these names must match it in order.
+    #[serde(default)]
     pub columns: Vec<TvpColumnDefinition>,
 
-    /// Row data as arrays of values. Each row must have values matching the columns.
-    /// Supports: integers, floats, strings, booleans, null, dates (ISO 8601 strings).
-    pub rows: Vec<Vec<Value>>,
+    /// Row data, either as arrays of values in column order or as objects
+    /// keyed by column name (missing columns are NULL). Values are converted
+    /// to the column types: numbers or numeric strings, booleans, ISO 8601
+    /// strings for dates/times, GUID strings, and 0x-prefixed hex for binary.
+    pub rows: Vec<Value>,
 
     /// Output format: 'table' (markdown), 'json', or 'csv' (default: table).
     #[serde(default)]
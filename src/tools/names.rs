@@ -0,0 +1,146 @@
+//! Names of the registered tools.
+//!
+//! The tool router only lists tools on a server instance, so configuration
+//! checks tool names against this list instead. A test keeps it in step with
+//! the `#[tool]` methods in `tools.rs`.
+
+/// Every tool the server registers, in declaration order.
+pub const TOOL_NAMES: &[&str] = &[
+    "execute_query",
+    "fetch_result_chunk",
+    "confirm_execution",
+    "explain_query",
+    "compare_plans",
+    "estimate_impact",
+    "execute_procedure",
+    "execute_with_tvp",
+    "execute_async",
+    "run_script",
+    "get_session_status",
+    "cancel_session",
+    "list_sessions",
+    "purge_sessions",
+    "get_session_results",
+    "watch_table",
+    "get_watch_changes",
+    "list_watches",
+    "unwatch_table",
+    "watch_query",
+    "get_watch_status",
+    "unwatch_query",
+    "health_check",
+    "reset_circuit_breaker",
+    "set_timeout",
+    "get_timeout",
+    "reload_config",
+    "rotate_credentials",
+    "execute_parameterized",
+    "register_query",
+    "execute_registered",
+    "list_registered_queries",
+    "begin_transaction",
+    "commit_transaction",
+    "rollback_transaction",
+    "create_savepoint",
+    "list_savepoints",
+    "execute_in_transaction",
+    "begin_pinned_session",
+    "execute_in_pinned_session",
+    "use_database",
+    "end_pinned_session",
+    "list_pinned_sessions",
+    "execute_paginated",
+    "switch_database",
+    "recommend_indexes",
+    "get_index_fragmentation",
+    "maintain_indexes",
+    "compression_advisor",
+    "get_stale_statistics",
+    "update_statistics",
+    "check_integrity",
+    "compare_schemas",
+    "compare_tables",
+    "generate_migration",
+    "generate_crud",
+    "copy_table",
+    "clone_schema",
+    "get_dependencies",
+    "script_object",
+    "script_database",
+    "search_schema",
+    "generate_data_dictionary",
+    "sample_data",
+    "query_json_column",
+    "describe_graph",
+    "query_history_table",
+    "classify_columns",
+    "profile_table",
+    "bulk_insert",
+    "generate_test_data",
+    "anonymize_table",
+    "import_data",
+    "export_data",
+    "download_blob",
+    "get_metrics",
+    "analyze_query",
+    "get_pool_metrics",
+    "get_replica_info",
+    "get_ag_health",
+    "get_replication_status",
+    "get_memory_optimized_usage",
+    "test_linked_server",
+    "get_file_usage",
+    "get_tempdb_usage",
+    "get_active_requests",
+    "kill_session",
+    "get_internal_metrics",
+    "clear_cache",
+    "get_cache_entries",
+    "evict_cache_entry",
+    "get_query_history",
+    "get_slow_queries",
+    "validate_syntax_tool",
+    "lint_sql",
+    "describe_query",
+    "generate_sql",
+];
+
+/// Whether `name` is a registered tool, ignoring ASCII case.
+pub fn is_known_tool(name: &str) -> bool {
+    TOOL_NAMES.iter().any(|t| t.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_names_match_tool_methods() {
+        let source = include_str!("../tools.rs");
+        let mut declared = Vec::new();
+        let mut in_tool = false;
+        for line in source.lines() {
+            let line = line.trim();
+            if line.starts_with("#[tool(") {
+                in_tool = true;
+            } else if in_tool {
+                if let Some(rest) = line.strip_prefix("pub async fn ") {
+                    let name: String = rest
+                        .chars()
+                        .take_while(|c| c.is_alphanumeric() || *c == '_')
+                        .collect();
+                    declared.push(name);
+                    in_tool = false;
+                }
+            }
+        }
+        assert_eq!(declared, TOOL_NAMES);
+    }
+
+    #[test]
+    fn test_is_known_tool() {
+        assert!(is_known_tool("execute_query"));
+        assert!(is_known_tool("Switch_Database"));
+        assert!(!is_known_tool("execute_querry"));
+    }
+}
@@ -0,0 +1,852 @@
+//! Structured tool results.
+//!
+//! Tools answer with text, so a JSON response reaches the client as a string
+//! it has to parse again. [`add_structured_content`] attaches the parsed value
+//! of a `tools/call` result as `structuredContent`, and [`add_output_schemas`]
+//! declares an `outputSchema` in `tools/list` for the tools whose result
+//! fields are described below.
+//!
+//! A schema binds every successful result, so tools without one, including
+//! those with a `format` parameter that return markdown or CSV unless called
+//! with `format: json`, get structured content for JSON output only.
+
+use serde_json::{json, Map, Value};
+
+/// Schema of an object with the given properties.
+///
+/// No property is required: previews held back for confirmation are returned
+/// in place of the usual response and must still validate.
+fn object_schema(properties: Value) -> Value {
+    json!({ "type": "object", "properties": properties })
+}
+
+/// The `outputSchema` of a tool, if one is declared for it.
+///
+/// Every tool whose successful results are always JSON has one; tools that
+/// return result sets in the requested `format`, scripts or plain text do not.
+pub fn output_schema(tool: &str) -> Option<Value> {
+    let string = json!({ "type": "string" });
+    let integer = json!({ "type": "integer" });
+    let number = json!({ "type": "number" });
+    let boolean = json!({ "type": "boolean" });
+    let array = json!({ "type": "array" });
+    let object = json!({ "type": "object" });
+    let string_or_null = json!({ "type": ["string", "null"] });
+    let integer_or_null = json!({ "type": ["integer", "null"] });
+    let number_or_null = json!({ "type": ["number", "null"] });
+    let array_or_null = json!({ "type": ["array", "null"] });
+    let object_or_null = json!({ "type": ["object", "null"] });
+    let schema = match tool {
+        "fetch_result_chunk" => object_schema(json!({
+            "data": string,
+            "chunk": object,
+        })),
+        "compare_plans" => object_schema(json!({
+            "plan_type": string,
+            "compared_with": string,
+            "comparison": object,
+            "status": string,
+            "message": string,
+            "estimated_cost": number,
+            "problems": array,
+        })),
+        "estimate_impact" => object_schema(json!({
+            "kind": string,
+            "target": string,
+            "filtered": boolean,
+            "top": string_or_null,
+            "matching_rows": integer,
+            "estimated_affected_rows": integer,
+            "warnings": array,
+            "sample_row_count": integer,
+            "sample": array,
+            "count_query": string,
+            "sample_query": string,
+        })),
+        "execute_async" => object_schema(json!({
+            "session_id": string,
+            "status": string,
+            "message": string,
+            "cancellable": boolean,
+        })),
+        "run_script" => object_schema(json!({
+            "session_id": string,
+            "status": string,
+            "batch_count": integer,
+            "stop_on_error": boolean,
+            "message": string,
+            "cancellable": boolean,
+        })),
+        "get_session_status" => object_schema(json!({
+            "session_id": string,
+            "status": string,
+            "progress": integer,
+            "created_at": string,
+            "updated_at": string,
+            "age_seconds": integer,
+            "error": string_or_null,
+            "result": object,
+        })),
+        "cancel_session" => object_schema(json!({
+            "session_id": string,
+            "status": string,
+            "native_cancellation": boolean,
+            "message": string,
+        })),
+        "list_sessions" => object_schema(json!({
+            "total_count": integer,
+            "running_count": integer,
+            "sessions": array,
+        })),
+        "purge_sessions" => object_schema(json!({
+            "purged": integer,
+            "session_ids": array,
+            "freed_bytes": integer,
+            "remaining": integer,
+        })),
+        "watch_table" | "watch_query" => object_schema(json!({
+            "watch_id": string,
+            "status": string,
+            "watch": object,
+            "message": string,
+        })),
+        "get_watch_changes" => object_schema(json!({
+            "watch_id": string,
+            "rows": array,
+            "remaining": integer,
+            "rows_dropped": integer,
+            "last_watermark": string_or_null,
+        })),
+        "list_watches" => object_schema(json!({
+            "total_count": integer,
+            "watches": array,
+        })),
+        "unwatch_table" => object_schema(json!({
+            "watch_id": string,
+            "status": string,
+            "rows_detected": integer,
+            "rows_discarded": integer,
+        })),
+        "get_watch_status" => object_schema(json!({
+            "watch": object,
+            "changes": array,
+            "total_count": integer,
+            "watches": array,
+        })),
+        "unwatch_query" => object_schema(json!({
+            "watch_id": string,
+            "status": string,
+            "changes_detected": integer,
+            "changes_discarded": integer,
+        })),
+        "health_check" => object_schema(json!({
+            "healthy": boolean,
+            "latency_ms": integer,
+            "timestamp": string,
+            "error": string,
+            "circuit_breaker": object,
+            "server": object,
+            "server_info_error": string,
+            "encryption": object,
+            "pool": object,
+            "sessions": object,
+            "config": object,
+        })),
+        "reset_circuit_breaker" => object_schema(json!({
+            "previous_state": string,
+            "circuit_breaker": object,
+            "verified": boolean,
+            "latency_ms": integer,
+            "error": string,
+        })),
+        "set_timeout" => object_schema(json!({
+            "previous_timeout_seconds": integer,
+            "new_timeout_seconds": integer,
+            "status": string,
+            "note": string,
+        })),
+        "get_timeout" => object_schema(json!({
+            "current_timeout_seconds": integer,
+            "initial_timeout_seconds": integer,
+            "max_timeout_seconds": integer,
+            "is_modified": boolean,
+            "caching_enabled": boolean,
+            "cache_ttl_seconds": integer,
+        })),
+        "reload_config" => object_schema(json!({
+            "status": string,
+            "changed_settings": array,
+            "config_file": string_or_null,
+            "note": string,
+        })),
+        "rotate_credentials" => object_schema(json!({
+            "status": string,
+            "auth_type": string,
+            "pools_replaced": integer,
+            "connections_draining": integer,
+            "note": string,
+        })),
+        "register_query" => object_schema(json!({
+            "name": string,
+            "status": string,
+            "parameters": array,
+        })),
+        "list_registered_queries" => object_schema(json!({
+            "count": integer,
+            "registered_only": boolean,
+            "queries": array,
+        })),
+        "begin_transaction" => object_schema(json!({
+            "transaction_id": string,
+            "name": string_or_null,
+            "isolation_level": string,
+            "database": string_or_null,
+            "status": string,
+            "message": string,
+        })),
+        "commit_transaction" => object_schema(json!({
+            "transaction_id": string,
+            "status": string,
+            "statements_executed": integer,
+            "message": string,
+        })),
+        "rollback_transaction" => object_schema(json!({
+            "transaction_id": string,
+            "status": string,
+            "savepoint": string_or_null,
+            "message": string,
+        })),
+        "create_savepoint" => object_schema(json!({
+            "transaction_id": string,
+            "savepoint": string,
+            "savepoints": array,
+            "message": string,
+        })),
+        "list_savepoints" => object_schema(json!({
+            "transaction_id": string,
+            "status": string,
+            "statement_count": integer,
+            "savepoints": array,
+        })),
+        "begin_pinned_session" => object_schema(json!({
+            "session_id": string,
+            "name": string_or_null,
+            "database": string_or_null,
+            "status": string,
+            "created_at": string,
+            "message": string,
+        })),
+        "use_database" => object_schema(json!({
+            "session_id": string,
+            "transaction_id": string,
+            "database": string,
+            "status": string,
+        })),
+        "end_pinned_session" => object_schema(json!({
+            "session_id": string,
+            "status": string,
+            "queries_executed": integer,
+            "duration_ms": integer,
+            "message": string,
+        })),
+        "list_pinned_sessions" => object_schema(json!({
+            "count": integer,
+            "sessions": array,
+            "limits": object,
+        })),
+        "execute_paginated" => object_schema(json!({
+            "data": string,
+            "pagination": object,
+            "execution_time_ms": integer,
+        })),
+        "switch_database" => object_schema(json!({
+            "database": string,
+            "status": string,
+            "message": string,
+        })),
+        "recommend_indexes" => object_schema(json!({
+            "query": string,
+            "missing_indexes": array,
+            "recommendations": array,
+            "existing_indexes": array,
+        })),
+        "get_index_fragmentation" => object_schema(json!({
+            "mode": string,
+            "min_page_count": integer,
+            "index_count": integer,
+            "above_rebuild_threshold": integer,
+            "above_reorganize_threshold": integer,
+            "indexes": array,
+        })),
+        "maintain_indexes" => object_schema(json!({
+            "executed": boolean,
+            "indexes_checked": integer,
+            "step_count": integer,
+            "steps": array,
+            "script": string,
+            "results": array_or_null,
+        })),
+        "compression_advisor" => object_schema(json!({
+            "tables": array,
+            "options": array,
+            "min_savings_percent": number,
+            "index_count": integer,
+            "recommendation_count": integer,
+            "recommended_current_kb": number,
+            "recommended_estimated_kb": number,
+            "indexes": array,
+            "script": string,
+            "errors": array,
+        })),
+        "get_stale_statistics" => object_schema(json!({
+            "threshold": string,
+            "stale_count": integer,
+            "statistics": array,
+        })),
+        "update_statistics" => object_schema(json!({
+            "executed": boolean,
+            "scan": { "type": ["string", "object"] },
+            "statistics_checked": integer,
+            "statement_count": integer,
+            "statistics": array,
+            "script": string,
+            "results": array_or_null,
+        })),
+        "check_integrity" => object_schema(json!({
+            "foreign_keys_checked": integer,
+            "with_orphans": integer,
+            "total_orphaned_rows": integer,
+            "disabled_or_untrusted": integer,
+            "failed_checks": integer,
+            "foreign_keys": array,
+            "repair_script": string,
+        })),
+        "compare_schemas" => object_schema(json!({
+            "source_schema": string,
+            "target_schema": string,
+            "object_types": string,
+            "difference_count": integer,
+            "differences": array,
+        })),
+        "compare_tables" => object_schema(json!({
+            "source_table": string,
+            "target_table": string,
+            "column_differences": array,
+            "difference_count": integer,
+        })),
+        "generate_migration" => object_schema(json!({
+            "source_schema": string,
+            "target_schema": string,
+            "preview": boolean,
+            "step_count": integer,
+            "destructive_count": integer,
+            "steps": array,
+            "warnings": array,
+            "script": string,
+            "applied": object_or_null,
+        })),
+        "generate_crud" => object_schema(json!({
+            "table": string,
+            "primary_key": array,
+            "preview": boolean,
+            "procedures": array,
+            "warnings": array,
+            "script": string,
+            "applied": object_or_null,
+        })),
+        "copy_table" => object_schema(json!({
+            "source_table": string,
+            "target_table": string,
+            "preview": boolean,
+            "rows_to_copy": integer_or_null,
+            "batches": integer,
+            "script": string,
+            "first_batch": string_or_null,
+            "status": string,
+            "rows_copied": integer,
+            "batches_copied": integer,
+            "error": string_or_null,
+        })),
+        "clone_schema" => object_schema(json!({
+            "source_schema": string,
+            "target_schema": string,
+            "preview": boolean,
+            "table_count": integer,
+            "module_count": integer,
+            "include_data": boolean,
+            "statement_count": integer,
+            "failed": array,
+            "script": string,
+            "applied": object_or_null,
+        })),
+        "get_dependencies" => object_schema(json!({
+            "object": string,
+            "object_type": string,
+            "max_depth": integer,
+            "uses": array_or_null,
+            "used_by": array_or_null,
+            "affected_objects": array_or_null,
+            "note": string,
+        })),
+        "script_database" => object_schema(json!({
+            "destination": string,
+            "single_file": boolean,
+            "object_count": integer,
+            "objects_by_folder": object,
+            "failed": array,
+            "files": array,
+        })),
+        "search_schema" => object_schema(json!({
+            "pattern": string,
+            "regex": boolean,
+            "searched": array,
+            "total_matches": integer,
+            "returned": integer,
+            "matches": array,
+        })),
+        "describe_graph" => object_schema(json!({
+            "node_tables": array,
+            "edge_tables": array,
+            "pseudo_columns": object,
+            "example_match": string_or_null,
+            "note": string,
+        })),
+        "classify_columns" => object_schema(json!({
+            "schema": string_or_null,
+            "table": string_or_null,
+            "columns_scanned": integer,
+            "sampled_rows": integer,
+            "sensitive_column_count": integer,
+            "sensitive_columns": array,
+        })),
+        "profile_table" => object_schema(json!({
+            "table": string,
+            "rows_profiled": integer,
+            "sample_limit": integer,
+            "complete_table": boolean,
+            "elapsed_ms": integer,
+            "budget_exhausted": boolean,
+            "skipped_columns": array,
+            "failed_columns": array,
+            "masked_values_withheld": boolean,
+            "columns": array,
+        })),
+        "bulk_insert" => object_schema(json!({
+            "table": string,
+            "rows_requested": integer,
+            "rows_inserted": integer,
+            "batch_size": integer,
+            "batches": integer,
+            "successful_batches": integer,
+            "errors": array,
+            "status": string,
+            "error": string,
+            "execution_time_ms": integer,
+            "method": string,
+            "transaction": boolean,
+            "rolled_back": boolean,
+            "native_bcp_requested": boolean,
+            "native_bcp_available": boolean,
+        })),
+        "generate_test_data" => object_schema(json!({
+            "table": string,
+            "dry_run": boolean,
+            "status": string,
+            "seed": integer,
+            "rows_requested": integer,
+            "rows_inserted": integer,
+            "batches": integer,
+            "errors": array,
+            "error": string,
+            "rolled_back": boolean,
+            "execution_time_ms": integer,
+            "columns": array,
+            "skipped_columns": array,
+            "preview": string,
+        })),
+        "anonymize_table" => object_schema(json!({
+            "table": string,
+            "dry_run": boolean,
+            "status": string,
+            "seed": integer,
+            "generated_salt": string_or_null,
+            "key_columns": array,
+            "rules": array,
+            "rows_matched": integer_or_null,
+            "samples": array,
+            "statements": array,
+            "rows_updated": integer,
+            "shuffled_columns": integer,
+            "batches": integer,
+            "error": string_or_null,
+            "execution_time_ms": integer,
+        })),
+        "import_data" => object_schema(json!({
+            "source": string,
+            "format": string,
+            "table": string,
+            "columns": array,
+            "ignored_columns": array,
+            "rows_read": integer,
+            "valid_rows": integer,
+            "bad_row_count": integer,
+            "bad_rows": array,
+            "dry_run": boolean,
+            "status": string,
+            "rows_loaded": integer,
+            "batch_size": integer,
+            "batches": integer,
+            "method": string,
+            "transaction": boolean,
+            "staging_table": boolean,
+            "rolled_back": boolean,
+            "error": string,
+            "execution_time_ms": integer,
+        })),
+        "export_data" => object_schema(json!({
+            "format": string,
+            "destination": string,
+            "compressed": boolean,
+            "row_count": integer,
+            "column_count": integer,
+            "truncated": boolean,
+            "execution_time_ms": integer,
+            "files": array,
+            "data": string,
+        })),
+        "download_blob" => object_schema(json!({
+            "table": string,
+            "column": string,
+            "destination": string,
+            "bytes": integer,
+            "chunks": integer,
+            "execution_time_ms": integer,
+        })),
+        "get_metrics" => object_schema(json!({
+            "timestamp": string,
+            "connections": object,
+            "top_queries": array,
+            "memory": object,
+            "performance_counters": array,
+        })),
+        "analyze_query" => object_schema(json!({
+            "query": string,
+            "analysis": object,
+            "execution_plan": object,
+            "statistics": array,
+            "missing_indexes": array,
+            "warnings": array,
+        })),
+        "get_pool_metrics" => object_schema(json!({
+            "timestamp": string,
+            "pool": object,
+            "config": object,
+            "activity": object,
+            "maintenance": object,
+            "connections": array,
+            "failover": object,
+            "credential_rotation": object,
+            "health": object,
+            "read_pool": object,
+            "sessions": object,
+            "transactions": object,
+        })),
+        "get_replica_info" => object_schema(json!({
+            "endpoint": object,
+            "server_name": string_or_null,
+            "database": string_or_null,
+            "hadr_enabled": boolean,
+            "writable": boolean,
+            "availability_group": string_or_null,
+            "replica_role": string_or_null,
+            "synchronization_health": string_or_null,
+            "replicas": array,
+            "replicas_error": string,
+        })),
+        "get_ag_health" => object_schema(json!({
+            "connected_to_primary": boolean,
+            "all_caught_up": boolean,
+            "lagging": array,
+            "availability_groups": array,
+            "note": string,
+        })),
+        "get_replication_status" => object_schema(json!({
+            "distribution_databases": array,
+            "agent_count": integer,
+            "unhealthy_agents": array,
+            "max_latency_ms": integer_or_null,
+            "agents": array,
+            "errors": object,
+            "note": string,
+        })),
+        "get_memory_optimized_usage" => object_schema(json!({
+            "database": string_or_null,
+            "total_allocated_kb": integer,
+            "tables_allocated_kb": integer,
+            "system_allocated_kb": integer,
+            "tables": array,
+            "memory_optimized_tables": string_or_null,
+            "natively_compiled_modules": string_or_null,
+            "has_memory_optimized_filegroup": boolean,
+            "elevate_to_snapshot": boolean,
+            "resource_pool": string_or_null,
+            "resource_pool_max_memory_percent": string_or_null,
+        })),
+        "test_linked_server" => object_schema(json!({
+            "server": string,
+            "product": string,
+            "provider": string,
+            "data_source": string_or_null,
+            "connected": boolean,
+            "elapsed_ms": integer,
+            "error": string,
+            "note": string,
+            "remote": object,
+            "remote_error": string,
+        })),
+        "get_file_usage" => object_schema(json!({
+            "database": string_or_null,
+            "data_size_mb": number,
+            "data_free_mb": number,
+            "log_size_mb": number,
+            "log_free_mb": number,
+            "files": array,
+            "recovery_model": string_or_null,
+            "log_used_percent": number_or_null,
+            "log_reuse_wait": string_or_null,
+            "log_reuse_wait_hint": string,
+            "log_error": string,
+        })),
+        "get_tempdb_usage" => object_schema(json!({
+            "summary": object,
+            "session_count": integer,
+            "sessions": array,
+            "sessions_error": string,
+        })),
+        "get_active_requests" => object_schema(json!({
+            "timestamp": string,
+            "database": string_or_null,
+            "login": string_or_null,
+            "request_count": integer,
+            "blocked_count": integer,
+            "head_blockers": array,
+            "requests": array,
+        })),
+        "kill_session" => object_schema(json!({
+            "status": string,
+            "session": object,
+            "message": string,
+        })),
+        "get_internal_metrics" => object_schema(json!({
+            "timestamp": string,
+            "queries": object,
+            "transactions": object,
+            "cache": object,
+            "network": object,
+            "circuit_breaker": object,
+            "result_memory": object,
+            "concurrency": object,
+            "tools": object,
+            "query_shapes": object,
+            "rates": object,
+        })),
+        "clear_cache" => object_schema(json!({
+            "enabled": boolean,
+            "pattern": string_or_null,
+            "cleared": integer,
+            "remaining": integer,
+        })),
+        "get_cache_entries" => object_schema(json!({
+            "enabled": boolean,
+            "stats": object,
+            "count": integer,
+            "truncated": boolean,
+            "entries": array,
+        })),
+        "evict_cache_entry" => object_schema(json!({
+            "id": string,
+            "status": string,
+        })),
+        "get_query_history" => object_schema(json!({
+            "count": integer,
+            "recorded": integer,
+            "entries": array,
+        })),
+        "get_slow_queries" => object_schema(json!({
+            "threshold_ms": integer,
+            "capture_plans": boolean,
+            "count": integer,
+            "recorded": integer,
+            "plans_captured": integer,
+            "cleared": boolean,
+            "entries": array,
+        })),
+        "generate_sql" => object_schema(json!({
+            "question": string,
+            "sql": string,
+            "model": string_or_null,
+            "validation": string,
+            "executed": boolean,
+            "schema_excerpts": array,
+        })),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+/// Add `outputSchema` to the tools of a `tools/list` result.
+pub fn add_output_schemas(result: &mut Value) {
+    let Some(tools) = result.get_mut("tools").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for tool in tools {
+        let schema = tool
+            .get("name")
+            .and_then(Value::as_str)
+            .and_then(output_schema);
+        if let (Some(schema), Some(tool)) = (schema, tool.as_object_mut()) {
+            tool.insert("outputSchema".to_string(), schema);
+        }
+    }
+}
+
+/// The structured content of a successful `tools/call` result: its first
+/// text content parsed as JSON.
+///
+/// Later content items (such as the appended `request_id`) are ignored.
+/// Arrays are wrapped as `{"items": [...]}`, since structured content must
+/// be an object.
+pub fn structured_content(result: &Value) -> Option<Value> {
+    if result.get("isError").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    let content = result.get("content")?.as_array()?.first()?;
+    if content.get("type").and_then(Value::as_str) != Some("text") {
+        return None;
+    }
+    let text = content.get("text")?.as_str()?.trim_start();
+    if !text.starts_with(['{', '[']) {
+        return None;
+    }
+    match serde_json::from_str(text).ok()? {
+        Value::Object(object) => Some(Value::Object(object)),
+        Value::Array(items) => {
+            let mut object = Map::new();
+            object.insert("items".to_string(), Value::Array(items));
+            Some(Value::Object(object))
+        }
+        _ => None,
+    }
+}
+
+/// Add `structuredContent` to a `tools/call` result whose text is JSON.
+pub fn add_structured_content(result: &mut Value) {
+    if let Some(structured) = structured_content(result) {
+        if let Some(result) = result.as_object_mut() {
+            result.insert("structuredContent".to_string(), structured);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_result(text: &str) -> Value {
+        json!({ "content": [{ "type": "text", "text": text }] })
+    }
+
+    #[test]
+    fn test_structured_content_from_json_text() {
+        let mut result = text_result("{\n  \"transaction_id\": \"txn_1\"\n}");
+        result["content"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "type": "text", "text": "request_id: abc123" }));
+        add_structured_content(&mut result);
+        assert_eq!(result["structuredContent"]["transaction_id"], "txn_1");
+
+        let result = text_result("[1, 2]");
+        assert_eq!(
+            structured_content(&result),
+            Some(json!({ "items": [1, 2] }))
+        );
+    }
+
+    #[test]
+    fn test_no_structured_content_for_text_or_errors() {
+        assert!(structured_content(&text_result("| id |\n|----|\n| 1 |")).is_none());
+        assert!(structured_content(&text_result("[not json")).is_none());
+
+        let mut error = text_result("{\"error\": \"boom\"}");
+        error["isError"] = json!(true);
+        assert!(structured_content(&error).is_none());
+    }
+
+    #[test]
+    fn test_output_schemas() {
+        let mut result = json!({
+            "tools": [
+                { "name": "begin_transaction" },
+                { "name": "get_ag_health" },
+                { "name": "execute_query" },
+            ]
+        });
+        add_output_schemas(&mut result);
+        let tools = result["tools"].as_array().unwrap();
+        assert_eq!(
+            tools[0]["outputSchema"]["properties"]["transaction_id"]["type"],
+            "string"
+        );
+        assert_eq!(
+            tools[1]["outputSchema"]["properties"]["lagging"]["type"],
+            "array"
+        );
+        // Output depends on `format`
+        assert!(tools[2].get("outputSchema").is_none());
+    }
+
+    #[test]
+    fn test_every_json_tool_has_output_schema() {
+        // Tools answering in the requested `format`, with scripts or text
+        const UNSCHEMATIZED: &[&str] = &[
+            "execute_query",
+            "explain_query",
+            "execute_procedure",
+            "execute_with_tvp",
+            "get_session_results",
+            "execute_parameterized",
+            "execute_registered",
+            "execute_in_transaction",
+            "execute_in_pinned_session",
+            "confirm_execution",
+            "script_object",
+            "generate_data_dictionary",
+            "sample_data",
+            "query_json_column",
+            "query_history_table",
+            "validate_syntax_tool",
+            "lint_sql",
+            "describe_query",
+        ];
+
+        for tool in crate::tools::names::TOOL_NAMES {
+            let schema = output_schema(tool);
+            if UNSCHEMATIZED.contains(tool) {
+                assert!(schema.is_none(), "{tool} should not declare a schema");
+            } else {
+                assert!(schema.is_some(), "{tool} has no output schema");
+            }
+        }
+        assert!(output_schema("no_such_tool").is_none());
+    }
+
+    #[test]
+    fn test_session_status_error_is_nullable() {
+        let schema = output_schema("get_session_status").unwrap();
+        assert_eq!(
+            schema["properties"]["error"]["type"],
+            json!(["string", "null"])
+        );
+    }
+}
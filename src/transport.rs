@@ -5,6 +5,27 @@
 //! - http: HTTP with Server-Sent Events (SSE) for web integrations
 //!
 //! The HTTP transport is optional and requires the `http` feature flag.
+//!
+//! [`SubscriptionTransport`] wraps any transport to add schema resource
+//! subscriptions, server-to-client requests ([`ClientRequests`]) and
+//! structured tool results, which the MCP runtime does not provide on its own.
+
+use crate::database::SchemaWatcher;
+use crate::error::ServerError;
+use crate::telemetry::set_client_identity;
+use crate::tools::structured::{add_output_schemas, add_structured_content};
+use mcpkit::{
+    McpError, Message, Notification, Request, RequestId, Response, Transport, TransportMetadata,
+};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
 
 /// Transport configuration.
 #[derive(Debug, Clone)]
@@ -168,6 +189,409 @@ impl HttpConfig {
     }
 }
 
+// =============================================================================
+// Resource Subscriptions
+// =============================================================================
+
+/// Method used by clients to subscribe to resource updates.
+const RESOURCES_SUBSCRIBE: &str = "resources/subscribe";
+
+/// Method used by clients to cancel a resource subscription.
+const RESOURCES_UNSUBSCRIBE: &str = "resources/unsubscribe";
+
+/// Method a client starts the session with, carrying its `clientInfo`.
+const INITIALIZE: &str = "initialize";
+
+/// Method used by clients to list the server's tools.
+const TOOLS_LIST: &str = "tools/list";
+
+/// Method used by clients to call a tool.
+const TOOLS_CALL: &str = "tools/call";
+
+/// Notification sent when a subscribed resource changed.
+const RESOURCES_UPDATED: &str = "notifications/resources/updated";
+
+/// Method a server uses to ask the user for input through the client.
+pub const ELICITATION_CREATE: &str = "elicitation/create";
+
+/// Method a server uses to request a completion from the client's model.
+pub const SAMPLING_CREATE_MESSAGE: &str = "sampling/createMessage";
+
+/// Notification reporting the progress of a client request.
+pub const NOTIFICATIONS_PROGRESS: &str = "notifications/progress";
+
+/// Transport wrapper that serves schema resource subscriptions.
+///
+/// Requests for `resources/subscribe` and `resources/unsubscribe` are answered
+/// directly by the wrapper and never reach the server runtime. A background
+/// task polls the [`SchemaWatcher`] and sends `notifications/resources/updated`
+/// for each subscribed resource whose schema changed, along with the URIs
+/// received on an update channel (used by query watches).
+///
+/// Messages are read by a background task so that responses to
+/// [`ClientRequests`] arrive while a tool call is still running; they are
+/// handed to the waiting caller instead of the runtime.
+///
+/// Responses to `tools/list` gain an `outputSchema` for tools that declare
+/// one, and responses to `tools/call` a `structuredContent` with
+/// the parsed JSON of the result. All other messages pass through unchanged.
+pub struct SubscriptionTransport<T: Transport + 'static> {
+    inner: Arc<T>,
+    watcher: Arc<SchemaWatcher>,
+    requests: Arc<ClientRequests>,
+    tool_requests: Mutex<HashMap<RequestId, &'static str>>,
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<Result<Option<Message>, T::Error>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<T: Transport + 'static> SubscriptionTransport<T> {
+    /// Wrap a transport, start sending resource update notifications and
+    /// route server-to-client requests through it.
+    pub fn new(
+        inner: T,
+        watcher: Arc<SchemaWatcher>,
+        updates: broadcast::Receiver<String>,
+        requests: Arc<ClientRequests>,
+    ) -> Self {
+        let inner = Arc::new(inner);
+        let (incoming_tx, incoming) = mpsc::unbounded_channel();
+        let tasks = vec![
+            tokio::spawn(notify_loop(
+                Arc::clone(&inner),
+                Arc::clone(&watcher),
+                updates,
+            )),
+            tokio::spawn(read_loop(
+                Arc::clone(&inner),
+                Arc::clone(&requests),
+                incoming_tx,
+            )),
+            tokio::spawn(send_loop(Arc::clone(&inner), requests.attach())),
+        ];
+        Self {
+            inner,
+            watcher,
+            requests,
+            tool_requests: Mutex::new(HashMap::new()),
+            incoming: tokio::sync::Mutex::new(incoming),
+            tasks,
+        }
+    }
+
+    /// Stop the background tasks and fail pending client requests.
+    fn stop(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        self.requests.detach();
+    }
+
+    /// Handle a subscribe or unsubscribe request.
+    async fn handle_subscription(&self, request: &Request) -> Result<serde_json::Value, McpError> {
+        let uri = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("uri"))
+            .and_then(|uri| uri.as_str())
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    request.method.as_ref(),
+                    "Missing required 'uri' parameter",
+                )
+            })?;
+
+        if request.method == RESOURCES_SUBSCRIBE {
+            self.watcher
+                .subscribe(uri)
+                .await
+                .map_err(|e| McpError::invalid_params(request.method.as_ref(), e.to_string()))?;
+        } else {
+            self.watcher.unsubscribe(uri).await;
+        }
+        Ok(serde_json::json!({}))
+    }
+}
+
+impl<T: Transport + 'static> Drop for SubscriptionTransport<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<T: Transport + 'static> Transport for SubscriptionTransport<T> {
+    type Error = T::Error;
+
+    async fn send(&self, mut msg: Message) -> Result<(), Self::Error> {
+        if let Message::Response(response) = &mut msg {
+            let method = self.tool_requests.lock().remove(&response.id);
+            match (method, response.result.as_mut()) {
+                (Some(TOOLS_LIST), Some(result)) => add_output_schemas(result),
+                (Some(TOOLS_CALL), Some(result)) => add_structured_content(result),
+                _ => {}
+            }
+        }
+        self.inner.send(msg).await
+    }
+
+    async fn recv(&self) -> Result<Option<Message>, Self::Error> {
+        loop {
+            let Some(message) = self.incoming.lock().await.recv().await else {
+                return Ok(None);
+            };
+            let message = message?;
+            if let Some(Message::Request(request)) = &message {
+                if request.method == INITIALIZE {
+                    record_client_identity(request);
+                    self.requests.record_capabilities(request);
+                }
+                for method in [TOOLS_LIST, TOOLS_CALL] {
+                    if request.method == method {
+                        self.tool_requests.lock().insert(request.id.clone(), method);
+                    }
+                }
+                if request.method == RESOURCES_SUBSCRIBE || request.method == RESOURCES_UNSUBSCRIBE
+                {
+                    let response = match self.handle_subscription(request).await {
+                        Ok(result) => Response::success(request.id.clone(), result),
+                        Err(e) => Response::error(request.id.clone(), e.into()),
+                    };
+                    self.inner.send(Message::Response(response)).await?;
+                    continue;
+                }
+            }
+            return Ok(message);
+        }
+    }
+
+    fn close(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.stop();
+        self.inner.close()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn metadata(&self) -> TransportMetadata {
+        self.inner.metadata()
+    }
+}
+
+/// Remember the client name and version from an `initialize` request.
+fn record_client_identity(request: &Request) {
+    let Some(info) = request.params.as_ref().and_then(|p| p.get("clientInfo")) else {
+        return;
+    };
+    let Some(name) = info.get("name").and_then(|n| n.as_str()) else {
+        return;
+    };
+    let identity = match info.get("version").and_then(|v| v.as_str()) {
+        Some(version) => format!("{}/{}", name, version),
+        None => name.to_string(),
+    };
+    debug!("Client identified as {}", identity);
+    set_client_identity(identity);
+}
+
+/// Read messages from the transport, handing responses to client requests
+/// to their callers and queueing everything else for the runtime.
+///
+/// Stops after the connection closes, fails, or the wrapper is dropped.
+async fn read_loop<T: Transport>(
+    transport: Arc<T>,
+    requests: Arc<ClientRequests>,
+    incoming: mpsc::UnboundedSender<Result<Option<Message>, T::Error>>,
+) {
+    loop {
+        let message = match transport.recv().await {
+            Ok(Some(Message::Response(response))) => match requests.complete(response) {
+                Some(response) => Ok(Some(Message::Response(response))),
+                None => continue,
+            },
+            other => other,
+        };
+        let done = !matches!(message, Ok(Some(_)));
+        if incoming.send(message).is_err() || done {
+            return;
+        }
+    }
+}
+
+/// Send server-to-client requests over the transport.
+async fn send_loop<T: Transport>(
+    transport: Arc<T>,
+    mut outbound: mpsc::UnboundedReceiver<Message>,
+) {
+    while let Some(message) = outbound.recv().await {
+        if let Err(e) = transport.send(message).await {
+            warn!("Stopping client requests, send failed: {}", e);
+            return;
+        }
+    }
+}
+
+/// Notify the client about changed schema resources and published updates.
+///
+/// Stops when a notification cannot be sent or the update channel closes.
+async fn notify_loop<T: Transport>(
+    transport: Arc<T>,
+    watcher: Arc<SchemaWatcher>,
+    mut updates: broadcast::Receiver<String>,
+) {
+    let mut schema_poll = tokio::time::interval(watcher.poll_interval());
+    schema_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    schema_poll.tick().await;
+
+    loop {
+        let uris = tokio::select! {
+            _ = schema_poll.tick() => match watcher.poll().await {
+                Ok(uris) => uris,
+                Err(e) => {
+                    warn!("Schema change poll failed: {}", e);
+                    continue;
+                }
+            },
+            update = updates.recv() => match update {
+                Ok(uri) => vec![uri],
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Dropped {} resource update notifications", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+
+        for uri in uris {
+            debug!("Resource changed: {}", uri);
+            let notification =
+                Notification::with_params(RESOURCES_UPDATED, serde_json::json!({ "uri": uri }));
+            if let Err(e) = transport.send(Message::Notification(notification)).await {
+                warn!("Stopping resource notifications, send failed: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Server-to-Client Requests
+// =============================================================================
+
+/// Requests the server sends to the client, such as elicitation.
+///
+/// The MCP runtime only answers client requests, so a [`SubscriptionTransport`]
+/// sends these on the server's behalf and routes the client's responses back
+/// to the waiting caller. Without an attached transport (e.g. over HTTP) no
+/// client capability is reported, so callers fall back to other flows.
+#[derive(Default)]
+pub struct ClientRequests {
+    outbound: Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    pending: Mutex<HashMap<RequestId, oneshot::Sender<Response>>>,
+    capabilities: Mutex<serde_json::Value>,
+    next_id: AtomicU64,
+}
+
+impl ClientRequests {
+    /// Create a detached request broker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the connected client declared a capability (e.g. `elicitation`
+    /// or `sampling`) when it initialized the session.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.outbound.lock().is_some() && self.capabilities.lock().get(capability).is_some()
+    }
+
+    /// Send a request to the client and wait for its result.
+    pub async fn request(
+        &self,
+        method: &'static str,
+        params: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, ServerError> {
+        let closed = || ServerError::connection("Client connection is closed");
+        let outbound = self.outbound.lock().clone().ok_or_else(closed)?;
+        let id = RequestId::string(format!(
+            "mssql-mcp-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id.clone(), tx);
+        let request = Request::with_params(method, id.clone(), params);
+        if outbound.send(Message::Request(request)).is_err() {
+            self.pending.lock().remove(&id);
+            return Err(closed());
+        }
+
+        let response = match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(closed()),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                return Err(ServerError::internal(format!(
+                    "Client did not answer {} within {} seconds",
+                    method,
+                    timeout.as_secs()
+                )));
+            }
+        };
+        response.into_result().map_err(|e| {
+            ServerError::internal(format!("Client rejected {}: {}", method, e.message))
+        })
+    }
+
+    /// Send a notification to the client.
+    ///
+    /// Returns `false` when no transport is attached.
+    pub fn notify(&self, method: &'static str, params: serde_json::Value) -> bool {
+        let Some(outbound) = self.outbound.lock().clone() else {
+            return false;
+        };
+        let notification = Notification::with_params(method, params);
+        outbound.send(Message::Notification(notification)).is_ok()
+    }
+
+    /// Connect to a transport, returning the queue of messages to send.
+    fn attach(&self) -> mpsc::UnboundedReceiver<Message> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.outbound.lock() = Some(tx);
+        rx
+    }
+
+    /// Disconnect from the transport, failing pending requests.
+    fn detach(&self) {
+        *self.outbound.lock() = None;
+        self.pending.lock().clear();
+    }
+
+    /// Remember the capabilities from an `initialize` request.
+    fn record_capabilities(&self, request: &Request) {
+        let capabilities = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("capabilities"))
+            .cloned()
+            .unwrap_or_default();
+        *self.capabilities.lock() = capabilities;
+    }
+
+    /// Hand a response to the request waiting for it.
+    ///
+    /// Returns the response if no request is waiting for it.
+    fn complete(&self, response: Response) -> Option<Response> {
+        match self.pending.lock().remove(&response.id) {
+            Some(waiter) => {
+                // The caller may have timed out in the meantime
+                let _ = waiter.send(response);
+                None
+            }
+            None => Some(response),
+        }
+    }
+}
+
 /// HTTP server implementation using mcpkit-axum (only available with `http` feature).
 ///
 /// This provides full MCP functionality over HTTP, including:
@@ -178,7 +602,11 @@ impl HttpConfig {
 pub mod http_server {
     use super::*;
     use crate::shutdown::SharedShutdownController;
+    use crate::telemetry::{scope_transport_session, SharedMetrics};
     use crate::MssqlMcpServer;
+    use axum::extract::Request as HttpRequest;
+    use axum::middleware::{self, Next};
+    use axum::response::Response as HttpResponse;
     use axum::{response::IntoResponse, routing::get, Json, Router};
     use mcpkit_axum::McpRouter;
     use tracing::info;
@@ -201,11 +629,14 @@ pub mod http_server {
     /// Custom endpoints:
     /// - `/health` - Health check endpoint
     /// - `/` - Also serves health check
+    /// - `/metrics` - Server metrics in the Prometheus text format
     pub async fn start_http_server_with_shutdown(
         mcp_server: MssqlMcpServer,
         config: HttpConfig,
         shutdown_controller: Option<SharedShutdownController>,
     ) -> Result<(), anyhow::Error> {
+        let metrics = Arc::clone(mcp_server.metrics());
+
         // Build MCP router with mcpkit-axum for full protocol support
         let mut mcp_router = McpRouter::new(mcp_server)
             .post_path("/mcp")
@@ -226,7 +657,12 @@ pub mod http_server {
         let app = Router::new()
             .route("/health", get(health_handler))
             .route("/", get(health_handler))
-            .merge(mcp_router.into_router());
+            .route(
+                "/metrics",
+                get(move || metrics_handler(Arc::clone(&metrics))),
+            )
+            .merge(mcp_router.into_router())
+            .layer(middleware::from_fn(scope_mcp_session));
 
         let addr = format!("{}:{}", config.host, config.port);
         let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -235,6 +671,7 @@ pub mod http_server {
         info!("MCP endpoint: http://{}/mcp", addr);
         info!("SSE endpoint: http://{}/mcp/sse", addr);
         info!("Health endpoint: http://{}/health", addr);
+        info!("Metrics endpoint: http://{}/metrics", addr);
         if config.enable_tracing {
             info!("Request tracing enabled");
         }
@@ -255,6 +692,20 @@ pub mod http_server {
         Ok(())
     }
 
+    /// Handle a request as part of its MCP session, so that concurrent
+    /// clients keep separate database and timeout settings.
+    async fn scope_mcp_session(request: HttpRequest, next: Next) -> HttpResponse {
+        let session = request
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        match session {
+            Some(session) => scope_transport_session(session, next.run(request)).await,
+            None => next.run(request).await,
+        }
+    }
+
     /// Health check handler.
     async fn health_handler() -> impl IntoResponse {
         Json(serde_json::json!({
@@ -265,8 +716,97 @@ pub mod http_server {
             "endpoints": {
                 "mcp": "/mcp",
                 "sse": "/mcp/sse",
-                "health": "/health"
+                "health": "/health",
+                "metrics": "/metrics"
             }
         }))
     }
+
+    /// Prometheus scrape handler.
+    async fn metrics_handler(metrics: SharedMetrics) -> impl IntoResponse {
+        (
+            [(
+                axum::http::header::CONTENT_TYPE,
+                "text/plain; version=0.0.4; charset=utf-8",
+            )],
+            metrics.prometheus_text(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_request_round_trip() {
+        let requests = Arc::new(ClientRequests::new());
+        assert!(!requests.supports("elicitation"));
+
+        let mut outbound = requests.attach();
+        requests.record_capabilities(&Request::with_params(
+            INITIALIZE,
+            1u64,
+            serde_json::json!({ "capabilities": { "elicitation": {} } }),
+        ));
+        assert!(requests.supports("elicitation"));
+        assert!(!requests.supports("sampling"));
+
+        let caller = Arc::clone(&requests);
+        let pending = tokio::spawn(async move {
+            caller
+                .request(
+                    ELICITATION_CREATE,
+                    serde_json::json!({}),
+                    Duration::from_secs(5),
+                )
+                .await
+        });
+        let Some(Message::Request(request)) = outbound.recv().await else {
+            panic!("expected a request");
+        };
+        assert_eq!(request.method, ELICITATION_CREATE);
+
+        let unrelated = Response::success(RequestId::string("other"), serde_json::json!({}));
+        assert!(requests.complete(unrelated).is_some());
+        let answer = Response::success(request.id, serde_json::json!({ "action": "accept" }));
+        assert!(requests.complete(answer).is_none());
+        let result = pending.await.unwrap().unwrap();
+        assert_eq!(result["action"], "accept");
+
+        requests.detach();
+        assert!(!requests.supports("elicitation"));
+    }
+
+    #[tokio::test]
+    async fn test_progress_notifications() {
+        use crate::telemetry::ProgressReporter;
+        use mcpkit::ProgressToken;
+
+        let requests = Arc::new(ClientRequests::new());
+        let reporter =
+            ProgressReporter::new(ProgressToken::String("p1".into()), Arc::clone(&requests));
+        // Dropped without a transport
+        reporter.report(1, None, "detached");
+
+        let mut outbound = requests.attach();
+        reporter.report(2, Some(10), "Batch 2/10");
+        // Progress must increase, so repeated or lower values are dropped
+        reporter.report(2, Some(10), "again");
+        reporter.report(1, Some(10), "lower");
+        reporter.report(3, None, "Batch 3");
+        requests.detach();
+
+        let mut sent = Vec::new();
+        while let Some(Message::Notification(notification)) = outbound.recv().await {
+            assert_eq!(notification.method, NOTIFICATIONS_PROGRESS);
+            sent.push(notification.params.unwrap());
+        }
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["progressToken"], "p1");
+        assert_eq!(sent[0]["progress"], 2);
+        assert_eq!(sent[0]["total"], 10);
+        assert_eq!(sent[1]["progress"], 3);
+        assert!(sent[1].get("total").is_none());
+    }
 }